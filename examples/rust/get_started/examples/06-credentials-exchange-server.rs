@@ -58,6 +58,7 @@ async fn main(ctx: Context) -> Result<()> {
         issuer.identifier(),
         &MultiAddr::try_from("/dnsaddr/localhost/tcp/5000/secure/api").unwrap(),
         server.identifier(),
+        None,
     )
     .await?;
     let credential = authority_node.issue_credential(node.context()).await.unwrap();
@@ -65,6 +65,7 @@ async fn start_node(ctx: Context, project_information_path: &str, token: OneTime
         &edge_plane,
         &MultiAddr::try_from("/dnsaddr/localhost/tcp/5000")?,
         &node.create_identity().await?,
+        None,
     )
     .await?;
     authority_node.present_token(node.context(), token).await.unwrap();
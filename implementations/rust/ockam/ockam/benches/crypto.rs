@@ -0,0 +1,16 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ockam::bench::{aead, run, AEAD_PAYLOAD_SIZES};
+
+fn bench_aead(c: &mut Criterion) {
+    let mut group = c.benchmark_group("aead");
+    for size in AEAD_PAYLOAD_SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter_custom(|iterations| run(aead(size, iterations)).unwrap().duration)
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_aead);
+criterion_main!(benches);
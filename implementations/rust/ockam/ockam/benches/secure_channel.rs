@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ockam::bench::{handshake, portal, portal_small_writes, run_on_node, PORTAL_TRANSFER_SIZE};
+
+fn bench_handshake(c: &mut Criterion) {
+    c.bench_function("secure channel handshake", |b| {
+        b.iter_custom(|iterations| {
+            run_on_node(move |ctx| handshake(ctx, iterations))
+                .unwrap()
+                .duration
+        })
+    });
+}
+
+fn bench_portal(c: &mut Criterion) {
+    let mut group = c.benchmark_group("portal");
+    group.throughput(Throughput::Bytes(PORTAL_TRANSFER_SIZE as u64));
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(10));
+    group.bench_function("transfer", |b| {
+        b.iter_custom(|iterations| {
+            run_on_node(move |ctx| portal(ctx, iterations))
+                .unwrap()
+                .duration
+        })
+    });
+    group.bench_function("small writes", |b| {
+        b.iter_custom(|iterations| {
+            run_on_node(move |ctx| portal_small_writes(ctx, iterations))
+                .unwrap()
+                .duration
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_handshake, bench_portal);
+criterion_main!(benches);
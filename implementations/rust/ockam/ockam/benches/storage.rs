@@ -0,0 +1,17 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use ockam::bench::{run, storage_reads, storage_writes, STORAGE_VALUE_SIZE};
+
+fn bench_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("storage");
+    group.throughput(Throughput::Bytes(STORAGE_VALUE_SIZE as u64));
+    group.bench_function("write", |b| {
+        b.iter_custom(|iterations| run(storage_writes(iterations)).unwrap().duration)
+    });
+    group.bench_function("read", |b| {
+        b.iter_custom(|iterations| run(storage_reads(iterations)).unwrap().duration)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_storage);
+criterion_main!(benches);
@@ -0,0 +1,400 @@
+//! Benchmarks of the secure channels, the cryptographic primitives, the storage and the portals.
+
+use core::future::Future;
+use core::time::Duration;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use ockam_core::compat::rand::random;
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::compat::vec::Vec;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{route, Error, Result};
+use ockam_identity::storage::{LmdbStorage, Storage};
+use ockam_identity::{SecureChannelListenerOptions, SecureChannelOptions, Vault};
+use ockam_node::{Context, Executor, NodeBuilder};
+use ockam_transport_tcp::{
+    TcpConnectionOptions, TcpInletOptions, TcpListenerOptions, TcpOutletOptions,
+    TcpTransportExtension,
+};
+use ockam_vault::VaultForSecureChannels;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use crate::node::node;
+
+/// AEAD key used by the cryptographic benchmarks
+pub const AEAD_KEY: [u8; 32] = [0x42; 32];
+/// Sizes of the messages encrypted by the cryptographic benchmarks, from a small portal
+/// packet to a full portal chunk
+pub const AEAD_PAYLOAD_SIZES: [usize; 4] = [64, 1024, 16 * 1024, 64 * 1024];
+/// Size of the values written by the storage benchmarks
+pub const STORAGE_VALUE_SIZE: usize = 256;
+/// Number of records read by the storage benchmarks
+pub const STORAGE_RECORDS: u64 = 1_000;
+/// Number of bytes sent through a portal by each iteration of the portal benchmark
+pub const PORTAL_TRANSFER_SIZE: usize = 1024 * 1024;
+/// Size of the writes of the small writes portal scenario, typical of interactive protocols
+pub const PORTAL_SMALL_WRITE_SIZE: usize = 512;
+
+const STORAGE_NAMESPACE: &str = "bench";
+
+/// Return a payload of the given length, which is the same for every run
+pub fn payload(length: usize) -> Vec<u8> {
+    (0..length).map(|i| (i % 251) as u8).collect()
+}
+
+/// Result of a benchmark scenario
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    /// Name of the scenario
+    pub name: String,
+    /// Number of measured operations
+    pub iterations: u64,
+    /// Number of payload bytes processed by all the operations
+    pub bytes: u64,
+    /// Total duration of the measured operations, without their setup
+    pub duration: Duration,
+}
+
+impl Measurement {
+    /// Constructor
+    pub fn new(name: impl Into<String>, iterations: u64, bytes: u64, duration: Duration) -> Self {
+        Self {
+            name: name.into(),
+            iterations,
+            bytes,
+            duration,
+        }
+    }
+
+    /// Mean duration of an operation
+    pub fn latency(&self) -> Duration {
+        Duration::from_secs_f64(self.duration.as_secs_f64() / self.iterations.max(1) as f64)
+    }
+
+    /// Number of bytes processed per second, if the scenario processes a payload
+    pub fn throughput(&self) -> Option<f64> {
+        if self.bytes == 0 || self.duration.is_zero() {
+            None
+        } else {
+            Some(self.bytes as f64 / self.duration.as_secs_f64())
+        }
+    }
+}
+
+/// Description of the host running the benchmarks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Host {
+    /// CPU architecture
+    pub arch: String,
+    /// Operating system
+    pub os: String,
+    /// Number of available CPUs
+    pub cpus: usize,
+    /// True if the CPU has AES instructions
+    pub aes: bool,
+}
+
+impl Host {
+    /// Describe the current host
+    pub fn current() -> Self {
+        Self {
+            arch: std::env::consts::ARCH.to_string(),
+            os: std::env::consts::OS.to_string(),
+            cpus: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            aes: ockam_vault::has_aes_instructions(),
+        }
+    }
+}
+
+/// Run a scenario on a new node, which is stopped at the end of the scenario
+pub fn run_on_node<F, Fut>(scenario: F) -> Result<Measurement>
+where
+    F: FnOnce(Context) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<Measurement>> + Send + 'static,
+{
+    let (ctx, mut executor) = NodeBuilder::new().no_logging().build();
+    executor.execute(async move {
+        let result = match ctx.async_try_clone().await {
+            Ok(scenario_ctx) => scenario(scenario_ctx).await,
+            Err(e) => Err(e),
+        };
+        let _ = ctx.stop().await;
+        result
+    })?
+}
+
+/// Run a scenario which doesn't need a node
+pub fn run<Fut>(scenario: Fut) -> Result<Measurement>
+where
+    Fut: Future<Output = Result<Measurement>> + Send + 'static,
+{
+    Executor::execute_future(scenario)?
+}
+
+/// Create secure channels between two identities of the same node
+pub async fn handshake(ctx: Context, iterations: u64) -> Result<Measurement> {
+    let node = node(ctx);
+    let responder = node.create_identity().await?;
+    let initiator = node.create_identity().await?;
+    node.create_secure_channel_listener(
+        &responder,
+        "bench_listener",
+        SecureChannelListenerOptions::new(),
+    )
+    .await?;
+
+    let mut duration = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let channel = node
+            .create_secure_channel(
+                &initiator,
+                route!["bench_listener"],
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        duration += start.elapsed();
+        node.secure_channels()
+            .stop_secure_channel(node.context(), channel.encryptor_address())
+            .await?;
+    }
+    Ok(Measurement::new(
+        "secure channel handshake",
+        iterations,
+        0,
+        duration,
+    ))
+}
+
+/// Encrypt and decrypt messages of the given size with the AEAD of the secure channels
+pub async fn aead(size: usize, iterations: u64) -> Result<Measurement> {
+    let vault = Vault::create_secure_channel_vault();
+    let buffer = vault.import_secret_buffer(AEAD_KEY.to_vec()).await?;
+    let key = vault.convert_secret_buffer_to_aead_key(buffer).await?;
+    let plain_text = payload(size);
+
+    let mut duration = Duration::ZERO;
+    for counter in 0..iterations {
+        let mut nonce = [0u8; 12];
+        nonce[4..].copy_from_slice(&counter.to_be_bytes());
+        let start = Instant::now();
+        let cipher_text = vault.aead_encrypt(&key, &plain_text, &nonce, &[]).await?;
+        let decrypted = vault.aead_decrypt(&key, &cipher_text, &nonce, &[]).await?;
+        duration += start.elapsed();
+        if decrypted != plain_text {
+            return Err(bench_error("the decrypted message is different"));
+        }
+    }
+    Ok(Measurement::new(
+        format!("AEAD {size} B"),
+        iterations,
+        (size as u64) * iterations,
+        duration,
+    ))
+}
+
+/// Write values to a new LMDB storage
+pub async fn storage_writes(iterations: u64) -> Result<Measurement> {
+    let database = BenchDatabase::create().await?;
+    let value = payload(STORAGE_VALUE_SIZE);
+
+    let start = Instant::now();
+    for i in 0..iterations {
+        database
+            .storage
+            .set(
+                &format!("record-{i}"),
+                STORAGE_NAMESPACE.to_string(),
+                value.clone(),
+            )
+            .await?;
+    }
+    Ok(Measurement::new(
+        "storage write",
+        iterations,
+        (STORAGE_VALUE_SIZE as u64) * iterations,
+        start.elapsed(),
+    ))
+}
+
+/// Read values from an LMDB storage containing [`STORAGE_RECORDS`] records
+pub async fn storage_reads(iterations: u64) -> Result<Measurement> {
+    let database = BenchDatabase::create().await?;
+    let value = payload(STORAGE_VALUE_SIZE);
+    for i in 0..STORAGE_RECORDS {
+        database
+            .storage
+            .set(
+                &format!("record-{i}"),
+                STORAGE_NAMESPACE.to_string(),
+                value.clone(),
+            )
+            .await?;
+    }
+
+    let start = Instant::now();
+    for i in 0..iterations {
+        let key = format!("record-{}", i % STORAGE_RECORDS);
+        if database
+            .storage
+            .get(&key, STORAGE_NAMESPACE)
+            .await?
+            .is_none()
+        {
+            return Err(bench_error("a record is missing"));
+        }
+    }
+    Ok(Measurement::new(
+        "storage read",
+        iterations,
+        (STORAGE_VALUE_SIZE as u64) * iterations,
+        start.elapsed(),
+    ))
+}
+
+/// Send [`PORTAL_TRANSFER_SIZE`] bytes through a TCP inlet and outlet connected by a secure
+/// channel over TCP, on the loopback interface
+pub async fn portal(ctx: Context, iterations: u64) -> Result<Measurement> {
+    portal_transfer(ctx, iterations, PORTAL_TRANSFER_SIZE, "portal transfer").await
+}
+
+/// Send [`PORTAL_TRANSFER_SIZE`] bytes through a portal like [`portal`], but with writes of
+/// [`PORTAL_SMALL_WRITE_SIZE`] bytes, each one sent immediately by the client
+pub async fn portal_small_writes(ctx: Context, iterations: u64) -> Result<Measurement> {
+    portal_transfer(
+        ctx,
+        iterations,
+        PORTAL_SMALL_WRITE_SIZE,
+        "portal small writes",
+    )
+    .await
+}
+
+async fn portal_transfer(
+    ctx: Context,
+    iterations: u64,
+    write_size: usize,
+    name: &'static str,
+) -> Result<Measurement> {
+    let node = node(ctx);
+    let tcp = node.create_tcp_transport().await?;
+    let responder = node.create_identity().await?;
+    let initiator = node.create_identity().await?;
+
+    // the server behind the outlet reports the number of bytes received on each connection
+    let server = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+    let server_address = server.local_addr().map_err(io_error)?.to_string();
+    let (received_sender, mut received) = mpsc::channel(1);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = server.accept().await {
+            let received_sender = received_sender.clone();
+            tokio::spawn(async move {
+                let mut buffer = vec![0u8; 64 * 1024];
+                let mut total = 0;
+                while total < PORTAL_TRANSFER_SIZE {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => total += n,
+                    }
+                }
+                let _ = received_sender.send(total).await;
+            });
+        }
+    });
+
+    let tcp_listener_options = TcpListenerOptions::new();
+    let secure_channel_listener_options = SecureChannelListenerOptions::new()
+        .as_consumer(&tcp_listener_options.spawner_flow_control_id());
+    let secure_channel_flow_control_id = secure_channel_listener_options.spawner_flow_control_id();
+    node.create_secure_channel_listener(
+        &responder,
+        "bench_listener",
+        secure_channel_listener_options,
+    )
+    .await?;
+    tcp.create_outlet(
+        "bench_outlet",
+        server_address,
+        TcpOutletOptions::new().as_consumer(&secure_channel_flow_control_id),
+    )
+    .await?;
+    let listener = tcp.listen("127.0.0.1:0", tcp_listener_options).await?;
+
+    let connection = tcp
+        .connect(listener.socket_string(), TcpConnectionOptions::new())
+        .await?;
+    let channel = node
+        .create_secure_channel(
+            &initiator,
+            route![connection, "bench_listener"],
+            SecureChannelOptions::new(),
+        )
+        .await?;
+    let (inlet_address, _) = tcp
+        .create_inlet(
+            "127.0.0.1:0",
+            route![channel, "bench_outlet"],
+            TcpInletOptions::new(),
+        )
+        .await?;
+
+    let data = payload(PORTAL_TRANSFER_SIZE);
+    let mut duration = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let mut stream = TcpStream::connect(inlet_address).await.map_err(io_error)?;
+        stream.set_nodelay(true).map_err(io_error)?;
+        for chunk in data.chunks(write_size) {
+            stream.write_all(chunk).await.map_err(io_error)?;
+        }
+        let total = received.recv().await;
+        duration += start.elapsed();
+        if total != Some(PORTAL_TRANSFER_SIZE) {
+            return Err(bench_error(
+                "the data sent through the portal was truncated",
+            ));
+        }
+    }
+    Ok(Measurement::new(
+        name,
+        iterations,
+        (PORTAL_TRANSFER_SIZE as u64) * iterations,
+        duration,
+    ))
+}
+
+/// LMDB storage in a temporary file, deleted when dropped
+struct BenchDatabase {
+    path: PathBuf,
+    storage: LmdbStorage,
+}
+
+impl BenchDatabase {
+    async fn create() -> Result<Self> {
+        let path = std::env::temp_dir().join(format!("ockam-bench-{:x}.lmdb", random::<u64>()));
+        let storage = LmdbStorage::new(&path).await?;
+        Ok(Self { path, storage })
+    }
+}
+
+impl Drop for BenchDatabase {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+        let mut lock = self.path.clone().into_os_string();
+        lock.push("-lock");
+        let _ = std::fs::remove_file(lock);
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(Origin::Application, Kind::Io, e)
+}
+
+fn bench_error(message: &str) -> Error {
+    Error::new(Origin::Application, Kind::Invalid, message.to_string())
+}
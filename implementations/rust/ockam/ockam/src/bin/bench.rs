@@ -0,0 +1,81 @@
+//! Run the benchmarks of [`ockam::bench`] and print a report which can be compared between hosts.
+
+use ockam::bench::{
+    aead, handshake, portal, portal_small_writes, run, run_on_node, storage_reads, storage_writes,
+    Host, Measurement, AEAD_PAYLOAD_SIZES,
+};
+use ockam::Result;
+
+const HANDSHAKES: u64 = 100;
+const AEAD_OPERATIONS: u64 = 10_000;
+const STORAGE_OPERATIONS: u64 = 10_000;
+const PORTAL_TRANSFERS: u64 = 20;
+
+fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let quick = args.iter().any(|a| a == "--quick");
+    let csv = args.iter().any(|a| a == "--csv");
+    if let Some(unknown) = args.iter().find(|a| *a != "--quick" && *a != "--csv") {
+        eprintln!("unknown argument {unknown}\nusage: bench [--quick] [--csv]");
+        std::process::exit(2);
+    }
+    let iterations = |n: u64| if quick { (n / 10).max(1) } else { n };
+
+    let (handshakes, transfers) = (iterations(HANDSHAKES), iterations(PORTAL_TRANSFERS));
+
+    let mut measurements = vec![run_on_node(move |ctx| handshake(ctx, handshakes))?];
+    for size in AEAD_PAYLOAD_SIZES {
+        measurements.push(run(aead(size, iterations(AEAD_OPERATIONS)))?);
+    }
+    measurements.push(run(storage_writes(iterations(STORAGE_OPERATIONS)))?);
+    measurements.push(run(storage_reads(iterations(STORAGE_OPERATIONS)))?);
+    measurements.push(run_on_node(move |ctx| portal(ctx, transfers))?);
+    measurements.push(run_on_node(move |ctx| portal_small_writes(ctx, transfers))?);
+
+    let host = Host::current();
+    if csv {
+        println!("arch,os,cpus,aes,scenario,iterations,latency_us,throughput_mib_s");
+        for m in &measurements {
+            println!(
+                "{},{},{},{},{},{},{:.1},{}",
+                host.arch,
+                host.os,
+                host.cpus,
+                host.aes,
+                m.name,
+                m.iterations,
+                m.latency().as_secs_f64() * 1e6,
+                throughput(m)
+            );
+        }
+    } else {
+        println!(
+            "host: {} {}, {} CPUs, AES instructions: {}\n",
+            host.arch,
+            host.os,
+            host.cpus,
+            if host.aes { "yes" } else { "no" }
+        );
+        println!(
+            "{:<26} {:>10} {:>14} {:>12}",
+            "scenario", "iterations", "latency (µs)", "MiB/s"
+        );
+        for m in &measurements {
+            println!(
+                "{:<26} {:>10} {:>14.1} {:>12}",
+                m.name,
+                m.iterations,
+                m.latency().as_secs_f64() * 1e6,
+                throughput(m)
+            );
+        }
+    }
+    Ok(())
+}
+
+fn throughput(measurement: &Measurement) -> String {
+    measurement
+        .throughput()
+        .map(|t| format!("{:.1}", t / (1024.0 * 1024.0)))
+        .unwrap_or_default()
+}
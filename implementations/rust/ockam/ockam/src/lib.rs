@@ -63,6 +63,9 @@ pub use ockam_node::{
     debugger, Context, DelayedEvent, Executor, MessageReceiveOptions, MessageSendReceiveOptions,
     NodeBuilder, WorkerBuilder,
 };
+
+#[cfg(feature = "fault_injection")]
+pub use ockam_node::fault_injection;
 // ---
 
 mod delay;
@@ -154,4 +157,8 @@ pub use ockam_transport_tcp::{
 /// List of all top-level services
 pub mod node;
 
+/// Benchmarks of the secure channels, the cryptographic primitives, the storage and the portals
+#[cfg(feature = "bench")]
+pub mod bench;
+
 pub use node::*;
@@ -72,8 +72,17 @@ impl AbacAccessControl {
     pub async fn is_identity_authorized(&self, id: Identifier) -> Result<bool> {
         let mut environment = self.environment.clone();
 
-        // Get identity attributes and populate the environment:
-        if let Some(attrs) = self.repository.get_attributes(&id).await? {
+        // Get identity attributes and populate the environment.
+        // Attributes restricted to an audience are only used for the resources of that audience:
+        let resource = match environment.get("resource.id") {
+            Ok(Expr::Str(resource)) => Some(resource.clone()),
+            _ => None,
+        };
+        let attributes = self
+            .repository
+            .get_attributes_for(&id, resource.as_deref())
+            .await?;
+        if let Some(attrs) = attributes {
             for (key, value) in attrs.attrs() {
                 let key = match from_utf8(key) {
                     Ok(key) => key,
@@ -67,6 +67,13 @@ impl Inner {
             Vec::new()
         }
     }
+
+    fn all_policies(&self) -> Vec<(Resource, Action, Expr)> {
+        self.policies
+            .iter()
+            .flat_map(|(r, p)| p.iter().map(|(a, e)| (r.clone(), a.clone(), e.clone())))
+            .collect::<Vec<_>>()
+    }
 }
 
 #[async_trait]
@@ -88,6 +95,10 @@ impl PolicyStorage for Memory {
     async fn policies(&self, r: &Resource) -> Result<Vec<(Action, Expr)>> {
         Ok(self.inner.write().unwrap().policies(r))
     }
+
+    async fn all_policies(&self) -> Result<Vec<(Resource, Action, Expr)>> {
+        Ok(self.inner.read().unwrap().all_policies())
+    }
 }
 
 #[cfg(test)]
@@ -1,3 +1,4 @@
+use crate::expr::str;
 use crate::traits::PolicyStorage;
 use crate::types::{Action, Resource};
 use crate::AbacAccessControl;
@@ -85,7 +86,13 @@ impl IncomingAccessControl for PolicyAccessControl {
             return Ok(false);
         };
 
-        AbacAccessControl::new(self.repository.clone(), expr, self.environment.clone())
+        // Make sure that the resource is known to check the audience of subject attributes
+        let mut environment = self.environment.clone();
+        if !environment.contains("resource.id") {
+            environment.put("resource.id", str(self.resource.as_str()));
+        }
+
+        AbacAccessControl::new(self.repository.clone(), expr, environment)
             .is_authorized(msg)
             .await
     }
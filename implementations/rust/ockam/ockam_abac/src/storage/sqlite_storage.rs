@@ -115,6 +115,34 @@ impl PolicyStorage for SqliteStorage {
         };
         spawn_blocking(t).await.map_err(map_join_err)?
     }
+
+    async fn all_policies(&self) -> Result<Vec<(Resource, Action, Expr)>> {
+        let conn = self.conn();
+        let t = move || {
+            let conn = conn.lock().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT resource, action, value FROM policy;")
+                .map_err(map_sqlite_err)?;
+            let result = stmt
+                .query_map::<(Resource, Action, Vec<u8>), _, _>([], |row| {
+                    let resource = Resource::from(row.get::<_, String>(0)?);
+                    let action = Action::from(row.get::<_, String>(1)?);
+                    let value: Vec<u8> = row.get(2)?;
+                    Ok((resource, action, value))
+                })
+                .map_err(map_sqlite_err)?
+                .map(|value| value.map_err(map_sqlite_err))
+                .collect::<Result<Vec<(Resource, Action, Vec<u8>)>, Error>>()?;
+            result
+                .into_iter()
+                .map(|(resource, action, value)| {
+                    let e: PolicyEntry = minicbor::decode(&value).map_err(map_decode_err)?;
+                    Ok((resource, action, e.expr.into_owned()))
+                })
+                .collect()
+        };
+        spawn_blocking(t).await.map_err(map_join_err)?
+    }
 }
 
 fn map_join_err(err: JoinError) -> Error {
@@ -0,0 +1,676 @@
+//! Export of the changes made to the members attributes of an authority to an external system.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use indexmap::IndexMap;
+use kafka_protocol::messages::produce_request::{PartitionProduceData, TopicProduceData};
+use kafka_protocol::messages::{
+    ApiKey, ProduceRequest, ProduceResponse, RequestHeader, ResponseHeader, TopicName,
+};
+use kafka_protocol::protocol::{Builder, Decodable, Encodable, StrBytes};
+use kafka_protocol::records::{
+    Compression, Record, RecordBatchEncoder, RecordEncodeOptions, TimestampType,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_retry::strategy::{jitter, ExponentialBackoff};
+use tokio_retry::Retry;
+use url::Url;
+
+use ockam::identity::{AttributesEntry, IdentityAttributesReader};
+use ockam_core::compat::sync::Arc;
+use ockam_core::Result;
+
+use crate::error::ApiError;
+use crate::members_replication::{MemberChange, MembersChangeLog};
+
+/// Maximum time to wait for the response of a webhook or a Kafka broker
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Version of the Kafka produce requests
+const KAFKA_PRODUCE_VERSION: i16 = 7;
+
+/// Client identifier sent with the Kafka requests
+const KAFKA_CLIENT_ID: &str = "ockam-attributes-export";
+
+fn default_interval_secs() -> u64 {
+    10
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_retries() -> usize {
+    3
+}
+
+/// Destination of the attributes changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AttributesExportDestination {
+    /// A file where the changes are appended as JSON lines
+    File { path: PathBuf },
+    /// A webhook receiving the changes as a JSON array
+    Webhook {
+        url: Url,
+        /// Headers added to the requests, for example an `Authorization` header
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+    },
+    /// A Kafka topic. The broker must be the leader of the partition
+    Kafka {
+        /// Address of the broker, for example `localhost:9092`
+        broker: String,
+        topic: String,
+        #[serde(default)]
+        partition: i32,
+    },
+}
+
+/// Attributes export configuration of an authority, read from a JSON file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributesExportConfig {
+    pub destination: AttributesExportDestination,
+    /// File storing the version of the last change delivered to the destination
+    pub checkpoint_path: PathBuf,
+    /// Time between two exports of the changes
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Maximum number of changes sent in one request
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Number of retries when a batch can't be sent
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+}
+
+impl AttributesExportConfig {
+    /// Read a configuration from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the attributes export configuration {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        let config: Self = serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the attributes export configuration {} is invalid: {e}",
+                path.display()
+            ))
+        })?;
+        if config.interval_secs == 0 || config.batch_size == 0 {
+            return Err(ApiError::core(format!(
+                "the attributes export configuration {} is invalid: the interval and the batch size must be positive",
+                path.display()
+            )));
+        }
+        Ok(config)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Position of the last change delivered to the destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportCheckpoint {
+    pub epoch: u64,
+    pub version: u64,
+}
+
+impl ExportCheckpoint {
+    /// Read a checkpoint, None if the file doesn't exist yet
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ApiError::core(format!("the export checkpoint can't be read: {e}")))?;
+        let checkpoint = serde_json::from_str(&contents)
+            .map_err(|e| ApiError::core(format!("the export checkpoint is invalid: {e}")))?;
+        Ok(Some(checkpoint))
+    }
+
+    /// Write a checkpoint to a temporary file first, so that a crash can't leave
+    /// a partially written checkpoint
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let tmp = path.with_extension("tmp");
+        let contents = serde_json::to_vec(self).map_err(|e| ApiError::core(e.to_string()))?;
+        std::fs::write(&tmp, contents)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| ApiError::core(format!("the export checkpoint can't be written: {e}")))
+    }
+}
+
+/// A change made to the attributes of a member, serialized as a JSON object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributesChangeRecord {
+    pub epoch: u64,
+    pub version: u64,
+    /// True if this change is part of an export of all the members
+    pub full: bool,
+    pub identifier: String,
+    /// Attributes of the member, missing if the member has been removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<BTreeMap<String, String>>,
+    /// Seconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attested_by: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub exported_at_ms: u64,
+}
+
+impl AttributesChangeRecord {
+    fn new(epoch: u64, version: u64, full: bool, change: &MemberChange) -> Self {
+        let entry = change.entry.as_ref();
+        Self {
+            epoch,
+            version,
+            full,
+            identifier: change.identifier.to_string(),
+            attributes: entry.map(attributes_as_strings),
+            expires_at: entry.and_then(|e| e.expires()).map(|t| t.0),
+            attested_by: entry.and_then(|e| e.attested_by()).map(|i| i.to_string()),
+            exported_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Attributes are stored as bytes but are expected to be valid UTF-8 strings
+fn attributes_as_strings(entry: &AttributesEntry) -> BTreeMap<String, String> {
+    entry
+        .attrs()
+        .iter()
+        .map(|(k, v)| {
+            (
+                String::from_utf8_lossy(k).to_string(),
+                String::from_utf8_lossy(v).to_string(),
+            )
+        })
+        .collect()
+}
+
+/// Exporter of the changes made to the members attributes of an authority
+pub struct AttributesExporter {
+    config: AttributesExportConfig,
+    reader: Arc<dyn IdentityAttributesReader>,
+    change_log: Arc<MembersChangeLog>,
+    checkpoint: Option<ExportCheckpoint>,
+}
+
+impl Debug for AttributesExporter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AttributesExporter")
+            .field("config", &self.config)
+            .field("checkpoint", &self.checkpoint)
+            .finish()
+    }
+}
+
+impl AttributesExporter {
+    pub fn new(
+        config: AttributesExportConfig,
+        reader: Arc<dyn IdentityAttributesReader>,
+        change_log: Arc<MembersChangeLog>,
+    ) -> Result<Self> {
+        let checkpoint = ExportCheckpoint::read(&config.checkpoint_path)?;
+        Ok(Self {
+            config,
+            reader,
+            change_log,
+            checkpoint,
+        })
+    }
+
+    /// Start exporting the changes on an interval
+    pub fn start(self) -> JoinHandle<()> {
+        info!(
+            destination = ?self.config.destination,
+            interval_secs = self.config.interval_secs,
+            "Exporting the members attributes changes"
+        );
+        tokio::spawn(self.run())
+    }
+
+    async fn run(mut self) {
+        let sink = match Sink::create(&self.config.destination) {
+            Ok(sink) => sink,
+            Err(e) => {
+                warn!(%e, "The attributes exporter can't be started");
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(self.config.interval());
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.export(&sink).await {
+                warn!(%e, checkpoint = ?self.checkpoint, "The attributes changes can't be exported");
+            }
+        }
+    }
+
+    /// Send the changes made since the checkpoint, then move the checkpoint.
+    /// If a batch can't be sent the checkpoint is not moved, and all the changes are sent
+    /// again at the next interval
+    async fn export(&mut self, sink: &Sink) -> Result<usize> {
+        let records = self.pending_records().await?;
+        let (records, checkpoint) = match records {
+            Some(pending) => pending,
+            None => return Ok(0),
+        };
+
+        for batch in records.chunks(self.config.batch_size) {
+            let retry_strategy = ExponentialBackoff::from_millis(500)
+                .map(jitter)
+                .take(self.config.retries);
+            Retry::spawn(retry_strategy, || sink.send(batch)).await?;
+            debug!(
+                changes = batch.len(),
+                "Exported a batch of attributes changes"
+            );
+        }
+
+        checkpoint.write(&self.config.checkpoint_path)?;
+        self.checkpoint = Some(checkpoint);
+        Ok(records.len())
+    }
+
+    /// Return the records to send and the checkpoint to store once they are delivered,
+    /// or None if there is nothing to export
+    async fn pending_records(
+        &self,
+    ) -> Result<Option<(Vec<AttributesChangeRecord>, ExportCheckpoint)>> {
+        let epoch = self.change_log.epoch();
+        let (since_epoch, since_version) = match self.checkpoint {
+            Some(c) => (Some(c.epoch), c.version),
+            None => (None, 0),
+        };
+        let (version, changes) = self.change_log.changes_since(since_epoch, since_version);
+        let checkpoint = ExportCheckpoint { epoch, version };
+
+        let (full, changes) = match changes {
+            Some(changes) if changes.is_empty() => return Ok(None),
+            Some(changes) => (false, changes),
+            // the changes are not available anymore, export all the members
+            None => {
+                let members = self.reader.list().await?;
+                let changes = members
+                    .into_iter()
+                    .map(|(identifier, entry)| MemberChange {
+                        identifier,
+                        entry: Some(entry),
+                    })
+                    .collect::<Vec<_>>();
+                (true, changes)
+            }
+        };
+
+        let records = changes
+            .iter()
+            .map(|c| AttributesChangeRecord::new(epoch, version, full, c))
+            .collect();
+        Ok(Some((records, checkpoint)))
+    }
+}
+
+/// Client of an export destination
+enum Sink {
+    File {
+        path: PathBuf,
+    },
+    Webhook {
+        client: reqwest::Client,
+        url: Url,
+        headers: BTreeMap<String, String>,
+    },
+    Kafka {
+        broker: String,
+        topic: String,
+        partition: i32,
+    },
+}
+
+impl Sink {
+    fn create(config: &AttributesExportDestination) -> Result<Self> {
+        match config {
+            AttributesExportDestination::File { path } => Ok(Sink::File { path: path.clone() }),
+            AttributesExportDestination::Webhook { url, headers } => {
+                let client = reqwest::ClientBuilder::new()
+                    .timeout(EXPORT_TIMEOUT)
+                    .build()
+                    .map_err(|e| ApiError::core(e.to_string()))?;
+                Ok(Sink::Webhook {
+                    client,
+                    url: url.clone(),
+                    headers: headers.clone(),
+                })
+            }
+            AttributesExportDestination::Kafka {
+                broker,
+                topic,
+                partition,
+            } => Ok(Sink::Kafka {
+                broker: broker.clone(),
+                topic: topic.clone(),
+                partition: *partition,
+            }),
+        }
+    }
+
+    async fn send(&self, records: &[AttributesChangeRecord]) -> Result<()> {
+        match self {
+            Sink::File { path } => append_json_lines(path, records),
+            Sink::Webhook {
+                client,
+                url,
+                headers,
+            } => {
+                let mut request = client.post(url.clone()).json(records);
+                for (name, value) in headers.iter() {
+                    request = request.header(name, value);
+                }
+                let response = request
+                    .send()
+                    .await
+                    .map_err(|e| ApiError::core(e.to_string()))?;
+                if !response.status().is_success() {
+                    return Err(ApiError::core(format!(
+                        "the webhook responded with the status {}",
+                        response.status()
+                    )));
+                }
+                Ok(())
+            }
+            Sink::Kafka {
+                broker,
+                topic,
+                partition,
+            } => tokio::time::timeout(EXPORT_TIMEOUT, produce(broker, topic, *partition, records))
+                .await
+                .map_err(|_| ApiError::core("the Kafka broker didn't respond in time"))?,
+        }
+    }
+}
+
+/// Append records to a file as JSON lines, and make sure that they are on disk
+fn append_json_lines(path: &Path, records: &[AttributesChangeRecord]) -> Result<()> {
+    let mut lines = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut lines, record).map_err(|e| ApiError::core(e.to_string()))?;
+        lines.push(b'\n');
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| ApiError::core(format!("{}: {e}", path.display())))?;
+    file.write_all(&lines)
+        .and_then(|_| file.sync_data())
+        .map_err(|e| ApiError::core(format!("{}: {e}", path.display())))
+}
+
+/// Send the records to a Kafka partition and wait for their acknowledgement by all the replicas
+async fn produce(
+    broker: &str,
+    topic: &str,
+    partition: i32,
+    records: &[AttributesChangeRecord],
+) -> Result<()> {
+    let request = encode_produce_request(topic, partition, records)?;
+    let mut stream = TcpStream::connect(broker)
+        .await
+        .map_err(|e| ApiError::core(format!("the Kafka broker {broker} can't be reached: {e}")))?;
+
+    let mut buffer = BytesMut::new();
+    buffer.put_u32(request.len() as u32);
+    buffer.put_slice(&request);
+    stream
+        .write_all(&buffer)
+        .await
+        .map_err(|e| ApiError::core(e.to_string()))?;
+
+    let size = stream
+        .read_u32()
+        .await
+        .map_err(|e| ApiError::core(e.to_string()))?;
+    let mut response = vec![0; size as usize];
+    stream
+        .read_exact(&mut response)
+        .await
+        .map_err(|e| ApiError::core(e.to_string()))?;
+    check_produce_response(Bytes::from(response))
+}
+
+fn encode_produce_request(
+    topic: &str,
+    partition: i32,
+    records: &[AttributesChangeRecord],
+) -> Result<BytesMut> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default();
+    let mut kafka_records = Vec::with_capacity(records.len());
+    for (offset, record) in records.iter().enumerate() {
+        let value = serde_json::to_vec(record).map_err(|e| ApiError::core(e.to_string()))?;
+        kafka_records.push(Record {
+            transactional: false,
+            control: false,
+            partition_leader_epoch: 0,
+            producer_id: -1,
+            producer_epoch: -1,
+            timestamp_type: TimestampType::Creation,
+            offset: offset as i64,
+            sequence: -1,
+            timestamp,
+            key: Some(Bytes::from(record.identifier.clone())),
+            value: Some(Bytes::from(value)),
+            headers: Default::default(),
+        });
+    }
+
+    let mut encoded_records = BytesMut::new();
+    RecordBatchEncoder::encode(
+        &mut encoded_records,
+        kafka_records.iter(),
+        &RecordEncodeOptions {
+            version: 2,
+            compression: Compression::None,
+        },
+    )
+    .map_err(|_| ApiError::core("the Kafka records can't be encoded"))?;
+
+    let mut topic_data = IndexMap::new();
+    topic_data.insert(
+        TopicName::from(str_bytes(topic)),
+        TopicProduceData::builder()
+            .partition_data(vec![PartitionProduceData::builder()
+                .index(partition)
+                .records(Some(encoded_records.freeze()))
+                .unknown_tagged_fields(Default::default())
+                .build()
+                .map_err(|e| ApiError::core(e.to_string()))?])
+            .unknown_tagged_fields(Default::default())
+            .build()
+            .map_err(|e| ApiError::core(e.to_string()))?,
+    );
+    let body = ProduceRequest::builder()
+        .transactional_id(None)
+        // wait for all the in-sync replicas
+        .acks(-1)
+        .timeout_ms(EXPORT_TIMEOUT.as_millis() as i32)
+        .topic_data(topic_data)
+        .unknown_tagged_fields(Default::default())
+        .build()
+        .map_err(|e| ApiError::core(e.to_string()))?;
+    let header = RequestHeader::builder()
+        .request_api_key(ApiKey::ProduceKey as i16)
+        .request_api_version(KAFKA_PRODUCE_VERSION)
+        .correlation_id(1)
+        .client_id(Some(str_bytes(KAFKA_CLIENT_ID)))
+        .unknown_tagged_fields(Default::default())
+        .build()
+        .map_err(|e| ApiError::core(e.to_string()))?;
+
+    let mut buffer = BytesMut::new();
+    header
+        .encode(
+            &mut buffer,
+            ApiKey::ProduceKey.request_header_version(KAFKA_PRODUCE_VERSION),
+        )
+        .map_err(|_| ApiError::core("the Kafka request header can't be encoded"))?;
+    body.encode(&mut buffer, KAFKA_PRODUCE_VERSION)
+        .map_err(|_| ApiError::core("the Kafka produce request can't be encoded"))?;
+    Ok(buffer)
+}
+
+fn check_produce_response(mut response: Bytes) -> Result<()> {
+    ResponseHeader::decode(
+        &mut response,
+        ApiKey::ProduceKey.response_header_version(KAFKA_PRODUCE_VERSION),
+    )
+    .map_err(|_| ApiError::core("the Kafka response header can't be decoded"))?;
+    let body = ProduceResponse::decode(&mut response, KAFKA_PRODUCE_VERSION)
+        .map_err(|_| ApiError::core("the Kafka produce response can't be decoded"))?;
+    for (topic, topic_response) in body.responses.iter() {
+        for partition in topic_response.partition_responses.iter() {
+            if partition.error_code != 0 {
+                return Err(ApiError::core(format!(
+                    "the Kafka broker rejected the records for {}/{} with the error code {}",
+                    &*topic.0, partition.index, partition.error_code
+                )));
+            }
+        }
+    }
+    if response.has_remaining() {
+        debug!(
+            remaining = response.remaining(),
+            "Unexpected bytes after the Kafka produce response"
+        );
+    }
+    Ok(())
+}
+
+fn str_bytes(s: &str) -> StrBytes {
+    // the bytes of a str are valid UTF-8
+    unsafe { StrBytes::from_utf8_unchecked(Bytes::from(s.to_string())) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::models::TimestampInSeconds;
+    use ockam::identity::{identities, Identifier};
+    use std::str::FromStr;
+
+    fn member() -> (Identifier, AttributesEntry) {
+        let identifier = Identifier::from_str("Ie92f183eb4c324804ef4d62962dea94cf095a265").unwrap();
+        let mut attributes = BTreeMap::new();
+        attributes.insert(b"role".to_vec(), b"member".to_vec());
+        let entry = AttributesEntry::new(attributes, TimestampInSeconds(1), None, None);
+        (identifier, entry)
+    }
+
+    #[test]
+    fn test_read_config() {
+        let config: AttributesExportConfig = serde_json::from_str(
+            r#"{"destination": {"type": "kafka", "broker": "localhost:9092", "topic": "members"},
+                "checkpoint_path": "/tmp/checkpoint.json"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.interval_secs, 10);
+        assert_eq!(config.batch_size, 500);
+        assert_eq!(
+            config.destination,
+            AttributesExportDestination::Kafka {
+                broker: "localhost:9092".into(),
+                topic: "members".into(),
+                partition: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_produce_request_encoding() -> Result<()> {
+        let (identifier, entry) = member();
+        let change = MemberChange {
+            identifier,
+            entry: Some(entry),
+        };
+        let record = AttributesChangeRecord::new(1, 2, false, &change);
+        let request = encode_produce_request("members", 0, &[record])?;
+        assert!(!request.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_to_file_with_checkpoint() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("changes.jsonl");
+        let config = AttributesExportConfig {
+            destination: AttributesExportDestination::File {
+                path: output.clone(),
+            },
+            checkpoint_path: dir.path().join("checkpoint.json"),
+            interval_secs: 1,
+            batch_size: 1,
+            retries: 0,
+        };
+
+        let (identifier, entry) = member();
+        let repository = identities().repository();
+        repository
+            .put_attributes(&identifier, entry.clone())
+            .await?;
+        let change_log = Arc::new(MembersChangeLog::new());
+        let sink = Sink::create(&config.destination)?;
+
+        // the first export contains all the members
+        let mut exporter = AttributesExporter::new(
+            config.clone(),
+            repository.as_attributes_reader(),
+            change_log.clone(),
+        )?;
+        assert_eq!(exporter.export(&sink).await?, 1);
+
+        // then only the changes
+        change_log.record(&identifier, None);
+        assert_eq!(exporter.export(&sink).await?, 1);
+        assert_eq!(exporter.export(&sink).await?, 0);
+
+        let lines = std::fs::read_to_string(&output).unwrap();
+        let records: Vec<AttributesChangeRecord> = lines
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].full);
+        assert_eq!(
+            records[0].attributes.as_ref().unwrap().get("role"),
+            Some(&"member".to_string())
+        );
+        assert!(!records[1].full);
+        assert!(records[1].attributes.is_none());
+
+        // a new exporter resumes from the stored checkpoint
+        let exporter =
+            AttributesExporter::new(config, repository.as_attributes_reader(), change_log)?;
+        assert_eq!(exporter.checkpoint.map(|c| c.version), Some(1));
+        Ok(())
+    }
+}
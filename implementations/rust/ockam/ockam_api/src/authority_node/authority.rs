@@ -1,28 +1,38 @@
 use std::path::Path;
+use std::time::Duration;
 
-use tracing::info;
+use tracing::{info, warn};
 
-use ockam::identity::storage::LmdbStorage;
+use ockam::identity::storage::{EncryptedStorage, LmdbStorage, StorageKey};
 use ockam::identity::Vault;
 use ockam::identity::{
-    CredentialsIssuer, Identifier, Identities, IdentitiesRepository, IdentitiesStorage,
-    IdentityAttributesReader, IdentityAttributesWriter, SecureChannelListenerOptions,
-    SecureChannels, TrustEveryonePolicy,
+    AuthorityTransition, CredentialsIssuer, CredentialsServer, CredentialsServerModule, Identifier,
+    Identities, IdentitiesRepository, IdentitiesStorage, IdentityAttributesReader,
+    IdentityAttributesWriter, SecureChannelListenerOptions, SecureChannelOptions, SecureChannels,
+    TrustEveryonePolicy, TrustIdentifierPolicy,
 };
-use ockam_abac::expr::{and, eq, ident, str};
-use ockam_abac::{AbacAccessControl, Env};
+use ockam_abac::expr::{and, eq, ident, or, str};
+use ockam_abac::{AbacAccessControl, Env, Expr};
 use ockam_core::compat::sync::Arc;
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::flow_control::FlowControlId;
-use ockam_core::{Error, Result, Worker};
+use ockam_core::{route, Error, Result, Route, Worker};
 use ockam_node::{Context, WorkerBuilder};
 use ockam_transport_tcp::{TcpListenerOptions, TcpTransport};
 
+use crate::attributes_export::AttributesExporter;
 use crate::authenticator::enrollment_tokens::EnrollmentTokenAuthenticator;
-use crate::authority_node::authority::EnrollerCheck::{AnyMember, EnrollerOnly};
+use crate::authority_node::authority::EnrollerCheck::{AnyMember, EnrollerOnly, ReplicaOnly};
 use crate::authority_node::Configuration;
 use crate::bootstrapped_identities_store::BootstrapedIdentityStore;
 use crate::echoer::Echoer;
+use crate::identity::credentials_clock;
+use crate::members_replication::{
+    MembersChangeLog, MembersReplicationServer, ReplicatedAttributesWriter,
+    MEMBERS_REPLICA_ATTRIBUTE,
+};
+use crate::service_registry::ServiceRegistry;
+use crate::storage_maintenance::StorageMaintenance;
 use crate::{actions, DefaultAddress};
 
 /// This struct represents an Authority, which is an
@@ -32,9 +42,12 @@ use crate::{actions, DefaultAddress};
 //   - a credential issuer
 //   - an enrollment token issuer
 //   - an enrollment token acceptor
+//   - a members replication service
 pub struct Authority {
     identifier: Identifier,
     secure_channels: Arc<SecureChannels>,
+    members_changes: Arc<MembersChangeLog>,
+    storage: LmdbStorage,
 }
 
 /// Public functions to:
@@ -57,10 +70,13 @@ impl Authority {
     pub async fn create(configuration: &Configuration) -> Result<Authority> {
         debug!(?configuration, "creating the authority");
         let vault = Self::create_secure_channels_vault(configuration).await?;
-        let repository = Self::create_identities_repository(configuration).await?;
+        let storage = Self::create_storage(configuration).await?;
+        let repository =
+            Self::create_identities_repository(storage.clone(), &vault, configuration).await?;
         let secure_channels = SecureChannels::builder()
             .with_vault(vault)
             .with_identities_repository(repository)
+            .with_credentials_clock(credentials_clock()?)
             .build();
 
         let identifier = configuration.identifier();
@@ -69,6 +85,8 @@ impl Authority {
         Ok(Authority {
             identifier,
             secure_channels,
+            members_changes: Arc::new(MembersChangeLog::new()),
+            storage,
         })
     }
 
@@ -233,6 +251,78 @@ impl Authority {
         Ok(())
     }
 
+    /// Start the service replicating the members attributes to the edge nodes of the project.
+    /// Only the members having the replica attribute, and the authority itself, can call it
+    pub async fn start_members_replication(
+        &self,
+        ctx: &Context,
+        secure_channel_flow_control_id: &FlowControlId,
+        configuration: &Configuration,
+    ) -> Result<()> {
+        let server = MembersReplicationServer::new(
+            self.identifier(),
+            self.identities(),
+            self.members_changes.clone(),
+        );
+
+        let address = DefaultAddress::MEMBERS_REPLICATION.to_string();
+        ctx.flow_controls()
+            .add_consumer(address.clone(), secure_channel_flow_control_id);
+
+        self.start(ctx, configuration, address.clone(), ReplicaOnly, server)
+            .await?;
+
+        info!("started a members replication service at '{address}'");
+        Ok(())
+    }
+
+    /// Start the export of the members attributes changes, if it has been configured
+    pub fn start_attributes_export(&self, configuration: &Configuration) -> Result<()> {
+        if let Some(config) = &configuration.attributes_export {
+            AttributesExporter::new(
+                config.clone(),
+                self.identities().repository().as_attributes_reader(),
+                self.members_changes.clone(),
+            )?
+            .start();
+        }
+        Ok(())
+    }
+
+    /// Start the maintenance of the attributes storage, if it has been configured
+    pub fn start_storage_maintenance(&self, configuration: &Configuration) {
+        if let Some(interval) = configuration.storage_maintenance_interval {
+            StorageMaintenance::new(interval)
+                .with_database(self.storage.clone())
+                .start();
+        }
+    }
+
+    /// Start the registry where the project members publish the services exposed by their outlets.
+    /// Each service can only be published by the members having the attribute of that service
+    pub async fn start_service_registry(
+        &self,
+        ctx: &Context,
+        secure_channel_flow_control_id: &FlowControlId,
+        configuration: &Configuration,
+    ) -> Result<()> {
+        let address = DefaultAddress::SERVICE_REGISTRY.to_string();
+        ctx.flow_controls()
+            .add_consumer(address.clone(), secure_channel_flow_control_id);
+
+        self.start(
+            ctx,
+            configuration,
+            address.clone(),
+            AnyMember,
+            ServiceRegistry::new(self.attributes_reader()),
+        )
+        .await?;
+
+        info!("started a service registry at '{address}'");
+        Ok(())
+    }
+
     /// Start an echo service
     pub async fn start_echo_service(
         &self,
@@ -246,6 +336,80 @@ impl Authority {
 
         ctx.start_worker(address, Echoer).await
     }
+
+    /// Issue a statement, signed by this authority, declaring that the `next` identity becomes
+    /// the authority of the project. The keys of the `next` identity must be stored in the
+    /// authority vault so that the authority node can be restarted with that identity.
+    ///
+    /// Credentials issued by the current authority identity are still accepted by project
+    /// members until the end of the grace period.
+    pub async fn issue_authority_transition(
+        &self,
+        next: &Identifier,
+        grace_period: Duration,
+    ) -> Result<AuthorityTransition> {
+        let transition = AuthorityTransition::issue(
+            &self.identities().credentials(),
+            &self.identifier,
+            next,
+            grace_period,
+        )
+        .await?;
+        info!(
+            "issued an authority transition from {} to {}",
+            self.identifier, next
+        );
+        Ok(transition)
+    }
+
+    /// Push an authority transition to the credentials service of some project members.
+    /// Each route must lead to the secure channel listener of a member node.
+    ///
+    /// Return the routes of the members which could not accept the transition so that the
+    /// push can be retried later.
+    pub async fn push_authority_transition(
+        &self,
+        ctx: &Context,
+        transition: &AuthorityTransition,
+        members: Vec<(Identifier, Route)>,
+    ) -> Result<Vec<(Identifier, Route)>> {
+        let credentials_server =
+            CredentialsServerModule::new(self.secure_channels.identities().credentials());
+        let mut failed = vec![];
+        for (member, member_route) in members {
+            let options = SecureChannelOptions::new()
+                .with_trust_policy(TrustIdentifierPolicy::new(member.clone()));
+            let result = match self
+                .secure_channels
+                .create_secure_channel(ctx, &self.identifier, member_route.clone(), options)
+                .await
+            {
+                Ok(channel) => {
+                    let result = credentials_server
+                        .push_authority_transition(
+                            ctx,
+                            route![
+                                channel.encryptor_address().clone(),
+                                DefaultAddress::CREDENTIALS_SERVICE
+                            ],
+                            transition,
+                        )
+                        .await;
+                    let _ = self
+                        .secure_channels
+                        .stop_secure_channel(ctx, channel.encryptor_address())
+                        .await;
+                    result
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!("the authority transition could not be pushed to {member}: {e}");
+                failed.push((member, member_route));
+            }
+        }
+        Ok(failed)
+    }
 }
 
 /// Private Authority functions
@@ -260,9 +424,14 @@ impl Authority {
         self.identities().repository().clone()
     }
 
-    /// Return the identities repository as writer used by the authority
+    /// Return the identities repository as writer used by the authority.
+    /// All the changes are recorded so that they can be replicated to edge nodes
     fn attributes_writer(&self) -> Arc<dyn IdentityAttributesWriter> {
-        self.identities_repository().as_attributes_writer().clone()
+        Arc::new(ReplicatedAttributesWriter::new(
+            self.identities_repository().as_attributes_writer(),
+            self.attributes_reader(),
+            self.members_changes.clone(),
+        ))
     }
 
     /// Return the identities repository as reader used by the authority
@@ -278,13 +447,33 @@ impl Authority {
         Ok(vault)
     }
 
-    /// Create an authenticated storage backed by a Lmdb database
+    /// Create the Lmdb database storing the identities attributes
+    async fn create_storage(configuration: &Configuration) -> Result<LmdbStorage> {
+        let storage_path = &configuration.storage_path;
+        Self::create_ockam_directory_if_necessary(storage_path)?;
+        LmdbStorage::new(&storage_path).await
+    }
+
+    /// Create an authenticated storage backed by a Lmdb database.
+    /// The storage is encrypted with keys derived from a key wrapped by the authority vault.
+    /// A key kept next to the database by a previous version is wrapped, then deleted
     async fn create_identities_repository(
+        storage: LmdbStorage,
+        vault: &Vault,
         configuration: &Configuration,
     ) -> Result<Arc<dyn IdentitiesRepository>> {
-        let storage_path = &configuration.storage_path;
-        Self::create_ockam_directory_if_necessary(storage_path)?;
-        let storage = Arc::new(LmdbStorage::new(&storage_path).await?);
+        let vault = vault.secure_channel_vault.as_ref();
+        let key_path = configuration.storage_path.with_extension("key");
+        let key = if key_path.exists() {
+            let key = StorageKey::read_or_create(&key_path)?
+                .store_in_vault(&storage, vault)
+                .await?;
+            std::fs::remove_file(&key_path).map_err(|e| Error::new(Origin::Node, Kind::Io, e))?;
+            key
+        } else {
+            StorageKey::from_vault(&storage, vault).await?
+        };
+        let storage = EncryptedStorage::create(Arc::new(storage), &key).await?;
         let repository = Arc::new(IdentitiesStorage::new(storage));
         Ok(Self::bootstrap_repository(repository, configuration))
     }
@@ -339,6 +528,8 @@ impl Authority {
     ///   - the service is accessed via a secure channel
     ///   - the sender has the correct project identifier (the same as the authority)
     ///   - if enroller_check == EnrollerOnly, the sender is an identity with 'enroller' as its 'ockam-role'
+    ///   - if enroller_check == ReplicaOnly, the sender is the authority itself, or an identity
+    ///     with the replica attribute set to 'true'
     fn create_abac_policy(
         &self,
         configuration: &Configuration,
@@ -347,19 +538,7 @@ impl Authority {
     ) -> Arc<AbacAccessControl> {
         // create an ABAC policy to only allow messages having
         // the same project id as the authority
-        let rule = match enroller_check {
-            EnrollerOnly => and([
-                eq([
-                    ident("resource.trust_context_id"),
-                    ident("subject.trust_context_id"),
-                ]),
-                eq([ident("subject.ockam-role"), str("enroller")]),
-            ]),
-            AnyMember => eq([
-                ident("resource.trust_context_id"),
-                ident("subject.trust_context_id"),
-            ]),
-        };
+        let rule = access_rule(&self.identifier, enroller_check);
 
         let mut env = Env::new();
         env.put("resource.id", str(address.as_str()));
@@ -381,4 +560,83 @@ impl Authority {
 enum EnrollerCheck {
     EnrollerOnly,
     AnyMember,
+    ReplicaOnly,
+}
+
+/// Rule checked by the access control of an authority service
+fn access_rule(authority: &Identifier, enroller_check: EnrollerCheck) -> Expr {
+    match enroller_check {
+        EnrollerOnly => and([
+            eq([
+                ident("resource.trust_context_id"),
+                ident("subject.trust_context_id"),
+            ]),
+            eq([ident("subject.ockam-role"), str("enroller")]),
+        ]),
+        AnyMember => eq([
+            ident("resource.trust_context_id"),
+            ident("subject.trust_context_id"),
+        ]),
+        ReplicaOnly => or([
+            eq([ident("subject.identifier"), str(authority.to_string())]),
+            and([
+                eq([
+                    ident("resource.trust_context_id"),
+                    ident("subject.trust_context_id"),
+                ]),
+                eq([
+                    ident(format!("subject.{MEMBERS_REPLICA_ATTRIBUTE}")),
+                    str("true"),
+                ]),
+            ]),
+        ]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_abac::eval;
+
+    #[test]
+    fn test_replica_only_rule() {
+        let authority: Identifier = "I0123456789abcdef0123456789abcdef01234567"
+            .try_into()
+            .unwrap();
+        let rule = access_rule(&authority, ReplicaOnly);
+        let subject = |identifier: &str, attributes: &[(&str, &str)]| {
+            let mut env = Env::new();
+            env.put("resource.trust_context_id", str("project"));
+            env.put("subject.identifier", str(identifier));
+            for (key, value) in attributes {
+                env.put(format!("subject.{key}"), str(*value));
+            }
+            env
+        };
+
+        // the authority itself can replicate the members
+        let env = subject(&authority.to_string(), &[]);
+        assert!(matches!(eval(&rule, &env), Ok(Expr::Bool(true))));
+
+        // a member needs the replica attribute
+        let member = "I1123456789abcdef0123456789abcdef01234567";
+        let env = subject(member, &[("trust_context_id", "project")]);
+        assert!(!matches!(eval(&rule, &env), Ok(Expr::Bool(true))));
+        let env = subject(
+            member,
+            &[
+                ("trust_context_id", "project"),
+                (MEMBERS_REPLICA_ATTRIBUTE, "true"),
+            ],
+        );
+        assert!(matches!(eval(&rule, &env), Ok(Expr::Bool(true))));
+        let env = subject(
+            member,
+            &[
+                ("trust_context_id", "other"),
+                (MEMBERS_REPLICA_ATTRIBUTE, "true"),
+            ],
+        );
+        assert!(!matches!(eval(&rule, &env), Ok(Expr::Bool(true))));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::attributes_export::AttributesExportConfig;
 use crate::bootstrapped_identities_store::PreTrustedIdentities;
 use crate::DefaultAddress;
 
@@ -9,6 +10,7 @@ use ockam_core::compat::fmt::{Display, Formatter};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 /// Configuration for the Authority node
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +49,14 @@ pub struct Configuration {
 
     /// optional configuration for the okta service
     pub okta: Option<OktaConfiguration>,
+
+    /// optional configuration for the export of the members attributes changes
+    #[serde(default)]
+    pub attributes_export: Option<AttributesExportConfig>,
+
+    /// optional interval between two maintenances of the attributes storage
+    #[serde(default)]
+    pub storage_maintenance_interval: Option<Duration>,
 }
 
 /// Local and private functions for the authority configuration
@@ -41,6 +41,26 @@ pub async fn start_node(ctx: &Context, configuration: &Configuration) -> Result<
         .await?;
     debug!("okta service started");
 
+    // start the replication of the members attributes to edge nodes
+    authority
+        .start_members_replication(ctx, &secure_channel_flow_control_id, configuration)
+        .await?;
+    debug!("members replication started");
+
+    // export the changes of the members attributes (if the optional configuration has been provided)
+    authority.start_attributes_export(configuration)?;
+    debug!("attributes export started");
+
+    // maintain the attributes storage (if the optional interval has been provided)
+    authority.start_storage_maintenance(configuration);
+    debug!("storage maintenance started");
+
+    // start the registry of the services published by the project members
+    authority
+        .start_service_registry(ctx, &secure_channel_flow_control_id, configuration)
+        .await?;
+    debug!("service registry started");
+
     // start an echo service so that the node can be queried as healthy
     authority
         .start_echo_service(ctx, &secure_channel_flow_control_id)
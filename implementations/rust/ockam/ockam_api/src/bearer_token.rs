@@ -0,0 +1,260 @@
+//! Bearer tokens for the clients which can't establish a secure channel.
+
+use std::collections::BTreeMap;
+use std::str::from_utf8;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{Identifier, Identities, IdentityError};
+use ockam_abac::expr::str;
+use ockam_abac::{eval, Env, Expr};
+use ockam_core::Result;
+
+use crate::error::ApiError;
+
+/// Identifier for the schema of the attributes of a bearer token
+pub const BEARER_TOKEN_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(8);
+
+/// Audience of the bearer tokens
+pub const BEARER_TOKEN_AUDIENCE: &str = "ockam-bearer-token";
+
+/// Validity of a bearer token when no time-to-live is requested
+pub const DEFAULT_BEARER_TOKEN_VALIDITY: Duration = Duration::from_secs(15 * 60);
+
+/// Maximum validity of a bearer token
+pub const MAX_BEARER_TOKEN_VALIDITY: Duration = Duration::from_secs(60 * 60);
+
+/// Short-lived credential sent by a client in the `Authorization` header of its HTTP requests
+#[derive(Clone, Debug)]
+pub struct BearerToken(CredentialAndPurposeKey);
+
+impl BearerToken {
+    pub fn hex_encoded(&self) -> Result<String> {
+        Ok(hex::encode(minicbor::to_vec(&self.0)?))
+    }
+
+    pub fn hex_decoded(encoded: &str) -> Result<Self> {
+        let decoded = hex::decode(encoded.trim())
+            .map_err(|e| ApiError::core(format!("the bearer token can't be decoded: {e}")))?;
+        Ok(Self(minicbor::decode(&decoded)?))
+    }
+
+    /// Decode the token of an `Authorization` header value, `Bearer <token>`
+    pub fn from_authorization_header(value: &str) -> Result<Self> {
+        let value = value.trim();
+        match value.split_once(' ') {
+            Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => {
+                Self::hex_decoded(token)
+            }
+            _ => Err(ApiError::core(
+                "the authorization header doesn't contain a bearer token",
+            )),
+        }
+    }
+
+    /// Value of the `Authorization` header sending this token
+    pub fn authorization_header(&self) -> Result<String> {
+        Ok(format!("Bearer {}", self.hex_encoded()?))
+    }
+}
+
+/// Identity and attributes attested by a valid bearer token
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BearerTokenClaims {
+    pub subject: Identifier,
+    pub attributes: BTreeMap<String, String>,
+    /// Seconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+/// Issuer of the bearer tokens, signed with the identity of a node
+#[derive(Clone)]
+pub struct BearerTokenIssuer {
+    identities: Arc<Identities>,
+    issuer: Identifier,
+}
+
+impl BearerTokenIssuer {
+    pub fn new(identities: Arc<Identities>, issuer: &Identifier) -> Self {
+        Self {
+            identities,
+            issuer: issuer.clone(),
+        }
+    }
+
+    /// Issue a token for an identity known by the issuer.
+    /// The validity of the token is capped to [`MAX_BEARER_TOKEN_VALIDITY`]
+    pub async fn issue(
+        &self,
+        subject: &Identifier,
+        attributes: &BTreeMap<String, String>,
+        ttl: Duration,
+    ) -> Result<BearerToken> {
+        let mut builder = AttributesBuilder::with_schema(BEARER_TOKEN_SCHEMA);
+        for (key, value) in attributes {
+            builder = builder.with_attribute(key.as_bytes().to_vec(), value.as_bytes().to_vec());
+        }
+        let credential = self
+            .identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential_with_audience(
+                &self.issuer,
+                subject,
+                builder.build(),
+                ttl.min(MAX_BEARER_TOKEN_VALIDITY),
+                Some(vec![BEARER_TOKEN_AUDIENCE.to_string()]),
+            )
+            .await?;
+        Ok(BearerToken(credential))
+    }
+}
+
+/// Verification of the bearer tokens received by a service
+#[derive(Clone)]
+pub struct BearerTokenVerifier {
+    identities: Arc<Identities>,
+    issuers: Vec<Identifier>,
+}
+
+impl BearerTokenVerifier {
+    /// Accept the tokens issued by the given identities, which must be known by the verifier
+    pub fn new(identities: Arc<Identities>, issuers: Vec<Identifier>) -> Self {
+        Self {
+            identities,
+            issuers,
+        }
+    }
+
+    /// Check that a token was issued by an accepted issuer and has not expired
+    pub async fn verify(&self, token: &BearerToken) -> Result<BearerTokenClaims> {
+        let data = self
+            .identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(None, &self.issuers, &token.0)
+            .await?;
+        let credential_data = data.credential_data;
+        let is_bearer_token = credential_data.subject_attributes.schema == BEARER_TOKEN_SCHEMA
+            && credential_data.audience.map_or(false, |audience| {
+                audience.iter().any(|a| a == BEARER_TOKEN_AUDIENCE)
+            });
+        let subject = match credential_data.subject {
+            Some(subject) if is_bearer_token => subject,
+            _ => return Err(IdentityError::CredentialVerificationFailed.into()),
+        };
+        let mut attributes = BTreeMap::new();
+        for (key, value) in credential_data.subject_attributes.map {
+            let key = Vec::<u8>::from(key);
+            let value = Vec::<u8>::from(value);
+            match (from_utf8(&key), from_utf8(&value)) {
+                (Ok(key), Ok(value)) => {
+                    attributes.insert(key.to_string(), value.to_string());
+                }
+                _ => warn!(%subject, "Skipping a bearer token attribute which is not utf-8"),
+            }
+        }
+        Ok(BearerTokenClaims {
+            subject,
+            attributes,
+            expires_at: credential_data.expires_at.0,
+        })
+    }
+
+    /// Verify the bearer token of an `Authorization` header value
+    pub async fn verify_authorization_header(&self, value: &str) -> Result<BearerTokenClaims> {
+        self.verify(&BearerToken::from_authorization_header(value)?)
+            .await
+    }
+
+    /// Return true if the `Authorization` header contains a valid token whose identity and
+    /// attributes satisfy a policy. A missing or invalid token is never authorized
+    pub async fn is_authorized(&self, authorization_header: Option<&str>, policy: &Expr) -> bool {
+        let Some(value) = authorization_header else {
+            debug!(%policy, "No bearer token, access denied");
+            return false;
+        };
+        let claims = match self.verify_authorization_header(value).await {
+            Ok(claims) => claims,
+            Err(e) => {
+                debug!(%policy, %e, "Invalid bearer token, access denied");
+                return false;
+            }
+        };
+        let mut environment = Env::new();
+        for (key, value) in &claims.attributes {
+            environment.put(format!("subject.{key}"), str(value.clone()));
+        }
+        environment.put("subject.identifier", str(claims.subject.to_string()));
+        match eval(policy, &environment) {
+            Ok(Expr::Bool(b)) => b,
+            Ok(_) | Err(_) => {
+                warn!(%policy, subject = %claims.subject, "The policy evaluation failed");
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::identities;
+    use ockam_abac::parse;
+
+    #[tokio::test]
+    async fn test_issue_and_verify() -> Result<()> {
+        let identities = identities();
+        let node = identities.identities_creation().create_identity().await?;
+        let client = identities.identities_creation().create_identity().await?;
+        let issuer = BearerTokenIssuer::new(identities.clone(), node.identifier());
+        let token = issuer
+            .issue(
+                client.identifier(),
+                &BTreeMap::from([("role".to_string(), "reader".to_string())]),
+                Duration::from_secs(60),
+            )
+            .await?;
+
+        let header = token.authorization_header()?;
+        let verifier =
+            BearerTokenVerifier::new(identities.clone(), vec![node.identifier().clone()]);
+        let claims = verifier.verify_authorization_header(&header).await?;
+        assert_eq!(&claims.subject, client.identifier());
+        assert_eq!(claims.attributes["role"], "reader");
+
+        let reader = parse(r#"(= subject.role "reader")"#).unwrap().unwrap();
+        let writer = parse(r#"(= subject.role "writer")"#).unwrap().unwrap();
+        assert!(verifier.is_authorized(Some(&header), &reader).await);
+        assert!(!verifier.is_authorized(Some(&header), &writer).await);
+        assert!(!verifier.is_authorized(None, &reader).await);
+        assert!(!verifier.is_authorized(Some("Basic abc"), &reader).await);
+
+        // the tokens of other issuers are rejected
+        let other = BearerTokenVerifier::new(identities.clone(), vec![client.identifier().clone()]);
+        assert!(other.verify(&token).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_regular_credential_is_not_a_token() -> Result<()> {
+        let identities = identities();
+        let node = identities.identities_creation().create_identity().await?;
+        let client = identities.identities_creation().create_identity().await?;
+        let credential = identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(
+                node.identifier(),
+                client.identifier(),
+                AttributesBuilder::with_schema(BEARER_TOKEN_SCHEMA).build(),
+                Duration::from_secs(60),
+            )
+            .await?;
+        let verifier = BearerTokenVerifier::new(identities, vec![node.identifier().clone()]);
+        assert!(verifier.verify(&BearerToken(credential)).await.is_err());
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use ockam::identity::models::ChangeHistory;
+use ockam::identity::models::{ChangeHistory, HardwareAttestationKind};
 use ockam::identity::utils::now;
 use ockam::identity::{
     AttributesEntry, Identifier, IdentitiesReader, IdentitiesRepository, IdentitiesWriter,
@@ -14,10 +14,13 @@ use serde_json as json;
 use std::path::PathBuf;
 use tracing::trace;
 
+use crate::notifier::{NotificationEvent, Notifier};
+
 #[derive(Clone)]
 pub struct BootstrapedIdentityStore {
     bootstrapped: Arc<dyn IdentityAttributesReader>,
     repository: Arc<dyn IdentitiesRepository>,
+    notifier: Option<Notifier>,
 }
 
 impl BootstrapedIdentityStore {
@@ -28,8 +31,15 @@ impl BootstrapedIdentityStore {
         Self {
             bootstrapped,
             repository,
+            notifier: None,
         }
     }
+
+    /// Notify the changes of the known identities
+    pub fn with_notifier(mut self, notifier: Option<Notifier>) -> Self {
+        self.notifier = notifier;
+        self
+    }
 }
 
 #[async_trait]
@@ -97,6 +107,12 @@ impl IdentitiesReader for BootstrapedIdentityStore {
     async fn get_identity(&self, identifier: &Identifier) -> Result<ChangeHistory> {
         self.repository.get_identity(identifier).await
     }
+    async fn get_hardware_key(
+        &self,
+        identifier: &Identifier,
+    ) -> Result<Option<HardwareAttestationKind>> {
+        self.repository.get_hardware_key(identifier).await
+    }
 }
 
 #[async_trait]
@@ -106,10 +122,30 @@ impl IdentitiesWriter for BootstrapedIdentityStore {
         identifier: &Identifier,
         change_history: &ChangeHistory,
     ) -> Result<()> {
+        if let Some(notifier) = &self.notifier {
+            match self.repository.retrieve_identity(identifier).await? {
+                Some(known) if &known != change_history => {
+                    notifier.notify(NotificationEvent::IdentityChanged {
+                        identifier: identifier.to_string(),
+                    })
+                }
+                _ => (),
+            }
+        }
         self.repository
             .update_identity(identifier, change_history)
             .await
     }
+
+    async fn set_hardware_key(
+        &self,
+        identifier: &Identifier,
+        hardware_key: Option<HardwareAttestationKind>,
+    ) -> Result<()> {
+        self.repository
+            .set_hardware_key(identifier, hardware_key)
+            .await
+    }
 }
 
 impl IdentitiesRepository for BootstrapedIdentityStore {
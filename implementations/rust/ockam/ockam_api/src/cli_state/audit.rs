@@ -0,0 +1,305 @@
+//! Audit log of the changes made to the CLI state.
+
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::cli_state::cached::now;
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{CliState, IdentitiesState, StateDirTrait};
+
+use super::Result;
+
+/// Name of the file logging the changes made to the state
+pub const AUDIT_LOG_FILE_NAME: &str = "audit_log.jsonl";
+
+/// Kind of change made to the state
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    Create,
+    Delete,
+    SetDefault,
+    RemoveDefault,
+}
+
+/// Change made to the state
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Identifier of the default identity when the change was made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<String>,
+    /// System user who made the change
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub operation: AuditOperation,
+    /// Kind of item, named after the `DEFAULT_FILENAME` of its state directory
+    pub kind: String,
+    /// Name of the item. It is missing when a default item is removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Configuration of a deleted item, or name of the previous default item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub old_value: Option<Value>,
+    /// Configuration of a created item, or name of the new default item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<Value>,
+}
+
+/// Selection of the audit records, all the records are selected by default
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AuditLogFilter {
+    kind: Option<String>,
+    name: Option<String>,
+    operation: Option<AuditOperation>,
+    actor: Option<String>,
+    since: Option<u64>,
+}
+
+impl AuditLogFilter {
+    pub fn with_kind(mut self, kind: impl Into<String>) -> Self {
+        self.kind = Some(kind.into());
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_operation(mut self, operation: AuditOperation) -> Self {
+        self.operation = Some(operation);
+        self
+    }
+
+    pub fn with_actor(mut self, actor: impl Into<String>) -> Self {
+        self.actor = Some(actor.into());
+        self
+    }
+
+    /// Only select the changes made at this time, in seconds since the Unix epoch, or later
+    pub fn with_since(mut self, since: u64) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.kind.as_ref().map_or(true, |kind| kind == &record.kind)
+            && self
+                .name
+                .as_ref()
+                .map_or(true, |name| record.name.as_ref() == Some(name))
+            && self
+                .operation
+                .map_or(true, |operation| operation == record.operation)
+            && self
+                .actor
+                .as_ref()
+                .map_or(true, |actor| record.actor.as_ref() == Some(actor))
+            && self.since.map_or(true, |since| record.timestamp >= since)
+    }
+}
+
+/// Audit log of the state stored in a root directory
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AuditLog {
+    root_path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+        }
+    }
+
+    /// Path of the file logging the changes
+    pub fn path(&self) -> PathBuf {
+        self.root_path.join(AUDIT_LOG_FILE_NAME)
+    }
+
+    /// Record the creation of an item with its configuration
+    pub fn created(&self, kind: &str, name: &str, config: &impl Serialize) {
+        self.record(
+            AuditOperation::Create,
+            kind,
+            Some(name),
+            None,
+            serde_json::to_value(config).ok(),
+        )
+    }
+
+    /// Record the deletion of an item with its last configuration
+    pub fn deleted(&self, kind: &str, name: &str, config: &impl Serialize) {
+        self.record(
+            AuditOperation::Delete,
+            kind,
+            Some(name),
+            serde_json::to_value(config).ok(),
+            None,
+        )
+    }
+
+    /// Record a change of the default item of a given kind
+    pub fn default_changed(&self, kind: &str, old_name: Option<&str>, new_name: Option<&str>) {
+        let operation = match new_name {
+            Some(_) => AuditOperation::SetDefault,
+            None => AuditOperation::RemoveDefault,
+        };
+        self.record(
+            operation,
+            kind,
+            new_name,
+            old_name.map(Value::from),
+            new_name.map(Value::from),
+        )
+    }
+
+    /// Append a record to the log.
+    /// The change was already made when it is recorded, so a failure is only logged
+    fn record(
+        &self,
+        operation: AuditOperation,
+        kind: &str,
+        name: Option<&str>,
+        old_value: Option<Value>,
+        new_value: Option<Value>,
+    ) {
+        let record = AuditRecord {
+            timestamp: now(),
+            actor: self.actor(),
+            user: current_user(),
+            operation,
+            kind: kind.to_string(),
+            name: name.map(|name| name.to_string()),
+            old_value,
+            new_value,
+        };
+        if let Err(e) = self.append(&record) {
+            warn!(%e, ?record, "The change of the state can't be audited");
+        }
+    }
+
+    /// Return the records selected by a filter, from the oldest one
+    pub fn list(&self, filter: &AuditLogFilter) -> Result<Vec<AuditRecord>> {
+        let file = match std::fs::File::open(self.path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut records = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditRecord>(&line) {
+                Ok(record) if filter.matches(&record) => records.push(record),
+                Ok(_) => {}
+                Err(e) => warn!(%e, %line, "Skipping an invalid audit record"),
+            }
+        }
+        Ok(records)
+    }
+
+    fn append(&self, record: &AuditRecord) -> Result<()> {
+        let path = self.path();
+        check_writable(&path)?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        // a single write, so that the lines of concurrent processes are not interleaved
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Identifier of the default identity, if there is one
+    fn actor(&self) -> Option<String> {
+        IdentitiesState::new(&self.root_path)
+            .default()
+            .ok()
+            .map(|identity| identity.identifier().to_string())
+    }
+}
+
+fn current_user() -> Option<String> {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .ok()
+        .filter(|user| !user.is_empty())
+}
+
+impl CliState {
+    /// Return the changes made to this state and selected by a filter, from the oldest one
+    pub fn audit_log(&self, filter: &AuditLogFilter) -> Result<Vec<AuditRecord>> {
+        AuditLog::new(&self.dir).list(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{SpaceConfig, VaultConfig};
+
+    #[tokio::test]
+    async fn test_audit_log() -> Result<()> {
+        let state = CliState::test()?;
+        state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+        state
+            .vaults
+            .create_async("v2", VaultConfig::default())
+            .await?;
+        state.vaults.set_default("v2")?;
+        state.vaults.delete("v1")?;
+        let space = SpaceConfig {
+            name: "s1".to_string(),
+            id: "id".to_string(),
+        };
+        state.spaces.create("s1", space)?;
+
+        let records = state.audit_log(&AuditLogFilter::default().with_kind("vault"))?;
+        let operations: Vec<_> = records
+            .iter()
+            .map(|r| (r.operation, r.name.as_deref(), r.old_value.clone()))
+            .collect();
+        assert_eq!(
+            operations[..4],
+            [
+                (AuditOperation::Create, Some("v1"), None),
+                (AuditOperation::SetDefault, Some("v1"), None),
+                (AuditOperation::Create, Some("v2"), None),
+                (
+                    AuditOperation::SetDefault,
+                    Some("v2"),
+                    Some(Value::from("v1"))
+                ),
+            ]
+        );
+        assert_eq!(records[4].operation, AuditOperation::Delete);
+        assert!(records[4].old_value.is_some());
+
+        let records = state.audit_log(
+            &AuditLogFilter::default()
+                .with_kind("space")
+                .with_operation(AuditOperation::Create),
+        )?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].name.as_deref(), Some("s1"));
+        assert!(records[0].new_value.is_some());
+
+        // the audit log is kept when the state is reset
+        let state = state.reset().await?;
+        assert!(!state
+            .audit_log(&AuditLogFilter::default().with_since(0))?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,952 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use aws_sdk_s3::primitives::ByteStream;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use ockam::identity::storage::{LmdbStorage, Storage};
+use ockam_core::env::get_env;
+
+use super::read_only::check_writable;
+use super::{CliState, CliStateError, Result, StateDirTrait, AUDIT_LOG_FILE_NAME};
+
+/// Name of the file, in the state directory, containing the backups configuration
+const BACKUP_CONFIG_FILE: &str = "backups.json";
+
+/// Name of the file, in the state directory, listing the snapshots uploaded to a remote endpoint
+const BACKUP_INDEX_FILE: &str = "backups_index.json";
+
+const SNAPSHOT_PREFIX: &str = "snapshot-";
+
+/// Name of the file describing a snapshot, at its root
+const SNAPSHOT_MANIFEST_FILE: &str = "snapshot.json";
+
+/// Files of the state directory which are kept when a snapshot is restored, since they
+/// describe the history of the state rather than its contents
+const PRESERVED_FILES: [&str; 3] = [AUDIT_LOG_FILE_NAME, BACKUP_CONFIG_FILE, BACKUP_INDEX_FILE];
+
+/// Version of the layout of the state directory. It is incremented when the snapshots taken
+/// by a previous version can't be restored as they are
+pub const STATE_SCHEMA_VERSION: u32 = 1;
+
+/// Number of snapshots kept by [`CliState::backup`] when the backups are not configured
+pub const DEFAULT_BACKUP_RETENTION: usize = 5;
+
+/// Environment variable set to the passphrase from which the keys encrypting the snapshots
+/// uploaded to S3 are derived
+pub const OCKAM_BACKUP_PASSPHRASE: &str = "OCKAM_BACKUP_PASSPHRASE";
+
+/// Name of the object describing how the other objects of an uploaded snapshot are encrypted
+const SNAPSHOT_ENCRYPTION_OBJECT: &str = "encryption.json";
+
+/// Version of the format of the encrypted objects
+const ENCRYPTED_OBJECT_VERSION: u8 = 1;
+
+/// Number of PBKDF2-HMAC-SHA256 iterations used to derive the key of a snapshot
+const PASSPHRASE_ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+
+/// Configuration of the automatic backups of the local state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupConfig {
+    /// Number of seconds between two backups
+    pub interval_secs: u64,
+    /// Number of snapshots to keep. The oldest snapshots are deleted first
+    pub retention: usize,
+    /// Where the snapshots are stored
+    pub destination: BackupDestination,
+}
+
+/// Location of the backup snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackupDestination {
+    /// A local directory, for example a mounted network drive
+    Directory { path: PathBuf },
+    /// An S3 bucket. The requests are signed with the AWS credentials of the environment, and
+    /// the snapshots are encrypted with a key derived from [`OCKAM_BACKUP_PASSPHRASE`].
+    /// The endpoint of an S3-compatible service can be set, its buckets are then addressed
+    /// as `<endpoint>/<bucket>`
+    S3 {
+        bucket: String,
+        #[serde(default)]
+        region: Option<String>,
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
+}
+
+impl BackupConfig {
+    pub fn new(interval: Duration, retention: usize, destination: BackupDestination) -> Self {
+        Self {
+            interval_secs: interval.as_secs(),
+            retention,
+            destination,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Description of a snapshot, stored with its files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub schema_version: u32,
+    /// Profile of the state
+    pub profile: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+}
+
+impl SnapshotManifest {
+    /// Read the manifest of a snapshot
+    pub fn read(snapshot: &Path) -> Result<Self> {
+        let path = snapshot.join(SNAPSHOT_MANIFEST_FILE);
+        if !path.exists() {
+            return Err(CliStateError::InvalidData(format!(
+                "{} is not a snapshot of the local state",
+                snapshot.display()
+            )));
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Snapshots uploaded to a remote endpoint, with the list of their objects keys
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BackupIndex {
+    snapshots: BTreeMap<String, Vec<String>>,
+}
+
+impl CliState {
+    /// Return the backups configuration if backups have been configured
+    pub fn backup_config(&self) -> Result<Option<BackupConfig>> {
+        let path = self.dir.join(BACKUP_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    /// Configure the automatic backups
+    pub fn set_backup_config(&self, config: &BackupConfig) -> Result<()> {
+        if config.retention == 0 || config.interval_secs == 0 {
+            return Err(CliStateError::InvalidData(
+                "The backups interval and retention must be greater than 0".to_string(),
+            ));
+        }
+        let contents = serde_json::to_string_pretty(config)?;
+        std::fs::write(self.dir.join(BACKUP_CONFIG_FILE), contents)?;
+        Ok(())
+    }
+
+    /// Disable the automatic backups
+    pub fn remove_backup_config(&self) -> Result<()> {
+        let path = self.dir.join(BACKUP_CONFIG_FILE);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Start a background task taking a snapshot of the state at each configured interval.
+    /// Return None if backups are not configured. The scheduler is not started if the snapshots
+    /// are uploaded to S3 while [`OCKAM_BACKUP_PASSPHRASE`] is not set, since they could not
+    /// be encrypted
+    pub fn start_backup_scheduler(&self) -> Result<Option<JoinHandle<()>>> {
+        let config = match self.backup_config()? {
+            Some(config) => config,
+            None => return Ok(None),
+        };
+        if let BackupDestination::S3 { .. } = config.destination {
+            backup_passphrase()?;
+        }
+        info!(
+            "scheduling a backup of the local state every {}s",
+            config.interval_secs
+        );
+        let state = self.clone();
+        Ok(Some(tokio::spawn(async move {
+            let start = tokio::time::Instant::now() + config.interval();
+            let mut interval = tokio::time::interval_at(start, config.interval());
+            // a backup which takes longer than the interval delays the next ones
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                match state.run_backup(&config).await {
+                    Ok(name) => info!("created the backup snapshot {name}"),
+                    Err(e) => warn!("the backup of the local state failed: {e}"),
+                }
+            }
+        })))
+    }
+
+    /// Take a verified, timestamped, snapshot of the state and delete the oldest snapshots
+    /// exceeding the retention count. Return the path of the new snapshot.
+    ///
+    /// The snapshot is stored in the directory of the backups configuration, if any, otherwise
+    /// in [`CliState::snapshots_dir`], where [`DEFAULT_BACKUP_RETENTION`] snapshots are kept
+    /// unless a retention is configured
+    pub async fn backup(&self) -> Result<PathBuf> {
+        let config = self.backup_config()?;
+        let retention = config
+            .as_ref()
+            .map_or(DEFAULT_BACKUP_RETENTION, |c| c.retention);
+        let dir = match config.map(|c| c.destination) {
+            Some(BackupDestination::Directory { path }) => path,
+            _ => Self::snapshots_dir(&self.profile)?,
+        };
+        let snapshot = dir.join(snapshot_name()?);
+        self.take_snapshot(&snapshot).await?;
+        prune_directory(&dir, retention)?;
+        Ok(snapshot)
+    }
+
+    /// List the snapshots taken by [`CliState::backup`], from the oldest to the newest
+    pub fn list_snapshots(&self) -> Result<Vec<PathBuf>> {
+        let dir = match self.backup_config()?.map(|c| c.destination) {
+            Some(BackupDestination::Directory { path }) => path,
+            _ => Self::snapshots_dir(&self.profile)?,
+        };
+        list_snapshots(&dir)
+    }
+
+    /// Replace the contents of the state with a snapshot, and return the restored state.
+    ///
+    /// The snapshot must have been taken with the current [`STATE_SCHEMA_VERSION`], and the
+    /// nodes of the state must be stopped. The audit log and the backups configuration are
+    /// not replaced
+    pub async fn restore(&self, snapshot: &Path) -> Result<CliState> {
+        check_writable(&self.dir)?;
+        let manifest = SnapshotManifest::read(snapshot)?;
+        if manifest.schema_version != STATE_SCHEMA_VERSION {
+            return Err(CliStateError::InvalidData(format!(
+                "The snapshot {} has the schema version {}, only the version {STATE_SCHEMA_VERSION} can be restored",
+                snapshot.display(),
+                manifest.schema_version
+            )));
+        }
+        verify_snapshot(snapshot).await?;
+        if let Some(node) = self.nodes.list()?.into_iter().find(|n| n.is_running()) {
+            return Err(CliStateError::InvalidOperation(format!(
+                "The node {} must be stopped before restoring the state",
+                node.name()
+            )));
+        }
+
+        let preserved: Vec<(&str, Option<Vec<u8>>)> = PRESERVED_FILES
+            .iter()
+            .map(|name| (*name, std::fs::read(self.dir.join(name)).ok()))
+            .collect();
+        Self::delete_at(&self.dir)?;
+        Self::backup_dir(snapshot.to_path_buf(), self.dir.clone()).await?;
+        std::fs::remove_file(self.dir.join(SNAPSHOT_MANIFEST_FILE))?;
+        for (name, contents) in preserved {
+            let path = self.dir.join(name);
+            match contents {
+                Some(contents) => std::fs::write(path, contents)?,
+                None if path.exists() => std::fs::remove_file(path)?,
+                None => (),
+            }
+        }
+        info!(
+            "restored the snapshot {} taken at {}",
+            snapshot.display(),
+            manifest.created_at
+        );
+        Self::initialize_profile_at(self.profiles.root_path(), &self.profile).await
+    }
+
+    /// Take a verified snapshot of the state, store it at the configured destination and
+    /// delete the snapshots exceeding the retention count.
+    /// Return the name of the new snapshot
+    pub async fn run_backup(&self, config: &BackupConfig) -> Result<String> {
+        let name = snapshot_name()?;
+        match &config.destination {
+            BackupDestination::Directory { path } => {
+                self.take_snapshot(&path.join(&name)).await?;
+                prune_directory(path, config.retention)?;
+            }
+            BackupDestination::S3 {
+                bucket,
+                region,
+                endpoint,
+            } => {
+                let passphrase = backup_passphrase()?;
+                let tmp = tempfile::tempdir()?;
+                let snapshot = tmp.path().join(&name);
+                self.take_snapshot(&snapshot).await?;
+                let bucket = S3Bucket::new(bucket, region, endpoint).await;
+                let keys = bucket.upload_snapshot(&name, &snapshot, passphrase).await?;
+                self.prune_remote(&bucket, name.clone(), keys, config.retention)
+                    .await?;
+            }
+        }
+        Ok(name)
+    }
+
+    /// Copy the state to a snapshot directory, with its manifest, and check that it can be read.
+    /// The snapshot is deleted if it is invalid
+    async fn take_snapshot(&self, snapshot: &Path) -> Result<()> {
+        self.backup_to(snapshot).await?;
+        write_manifest(snapshot, &self.profile)?;
+        if let Err(e) = verify_snapshot(snapshot).await {
+            let _ = std::fs::remove_dir_all(snapshot);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Download a snapshot uploaded to S3 by [`CliState::run_backup`] to a local directory,
+    /// from which it can be restored with [`CliState::restore`]. Return the path of the snapshot
+    pub async fn download_snapshot(
+        &self,
+        config: &BackupConfig,
+        name: &str,
+        dir: &Path,
+    ) -> Result<PathBuf> {
+        let BackupDestination::S3 {
+            bucket,
+            region,
+            endpoint,
+        } = &config.destination
+        else {
+            return Err(CliStateError::InvalidOperation(
+                "Only the snapshots uploaded to S3 can be downloaded".to_string(),
+            ));
+        };
+        let passphrase = backup_passphrase()?;
+        let snapshot = dir.join(name);
+        S3Bucket::new(bucket, region, endpoint)
+            .await
+            .download_snapshot(name, &snapshot, passphrase)
+            .await?;
+        verify_snapshot(&snapshot).await?;
+        Ok(snapshot)
+    }
+
+    async fn prune_remote(
+        &self,
+        bucket: &S3Bucket,
+        name: String,
+        keys: Vec<String>,
+        retention: usize,
+    ) -> Result<()> {
+        let index_path = self.dir.join(BACKUP_INDEX_FILE);
+        let mut index: BackupIndex = if index_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&index_path)?)?
+        } else {
+            BackupIndex::default()
+        };
+        index.snapshots.insert(name, keys);
+
+        // snapshot names contain a timestamp, so they are sorted from the oldest to the newest
+        while index.snapshots.len() > retention {
+            let (oldest, keys) = match index.snapshots.pop_first() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+            for key in keys {
+                bucket.delete(&key).await?;
+            }
+            debug!("deleted the backup snapshot {oldest}");
+        }
+
+        std::fs::write(index_path, serde_json::to_string_pretty(&index)?)?;
+        Ok(())
+    }
+}
+
+/// Name of a snapshot taken now. The names are sorted from the oldest to the newest snapshot.
+/// The timestamp is in microseconds, so that two snapshots taken in the same second have
+/// distinct names
+fn snapshot_name() -> Result<String> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| CliStateError::InvalidOperation(e.to_string()))?
+        .as_micros();
+    Ok(format!("{SNAPSHOT_PREFIX}{timestamp}"))
+}
+
+/// Describe the snapshot of the state of a profile
+pub(super) fn write_manifest(snapshot: &Path, profile: &str) -> Result<()> {
+    let manifest = SnapshotManifest {
+        schema_version: STATE_SCHEMA_VERSION,
+        profile: profile.to_string(),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| CliStateError::InvalidOperation(e.to_string()))?
+            .as_secs(),
+    };
+    std::fs::write(
+        snapshot.join(SNAPSHOT_MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(())
+}
+
+/// Check that all the databases of a snapshot can be read
+async fn verify_snapshot(snapshot: &Path) -> Result<()> {
+    for file in list_files(snapshot)? {
+        if file.extension() == Some("lmdb".as_ref()) {
+            LmdbStorage::new(&file).await?.keys("").await.map_err(|e| {
+                CliStateError::InvalidData(format!(
+                    "The backup of {} is invalid: {e}",
+                    file.display()
+                ))
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Delete the oldest snapshots of a directory
+fn prune_directory(dir: &Path, retention: usize) -> Result<()> {
+    let snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(retention);
+    for snapshot in snapshots.into_iter().take(excess) {
+        std::fs::remove_dir_all(&snapshot)?;
+        debug!("deleted the backup snapshot {}", snapshot.display());
+    }
+    Ok(())
+}
+
+/// Return the snapshots of a directory, from the oldest to the newest
+fn list_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut snapshots: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .map(|n| n.starts_with(SNAPSHOT_PREFIX))
+                    .unwrap_or(false)
+        })
+        .collect();
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Return the passphrase set with [`OCKAM_BACKUP_PASSPHRASE`]
+fn backup_passphrase() -> Result<String> {
+    get_env::<String>(OCKAM_BACKUP_PASSPHRASE)?
+        .filter(|passphrase| !passphrase.is_empty())
+        .ok_or_else(|| {
+            CliStateError::InvalidOperation(format!(
+                "The snapshots uploaded to S3 are encrypted, {OCKAM_BACKUP_PASSPHRASE} must be set"
+            ))
+        })
+}
+
+/// Parameters of the derivation of the key encrypting the objects of an uploaded snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SnapshotEncryption {
+    /// Hex-encoded random salt, specific to the snapshot
+    salt: String,
+    iterations: u32,
+}
+
+/// Key encrypting the objects of an uploaded snapshot
+struct SnapshotKey(Aes256Gcm);
+
+impl SnapshotKey {
+    /// Derive the key of a snapshot from a passphrase.
+    /// The derivation is slow on purpose, so it is run on a blocking thread
+    async fn derive(passphrase: String, encryption: &SnapshotEncryption) -> Result<Self> {
+        let salt = hex::decode(&encryption.salt)
+            .map_err(|e| CliStateError::InvalidData(format!("invalid snapshot salt: {e}")))?;
+        let iterations = encryption.iterations;
+        let key = tokio::task::spawn_blocking(move || {
+            let mut key = [0u8; 32];
+            pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, iterations, &mut key);
+            key
+        })
+        .await
+        .map_err(|e| CliStateError::InvalidOperation(e.to_string()))?;
+        Ok(Self(Aes256Gcm::new((&key).into())))
+    }
+
+    /// Encrypt the contents of an object. The object key is authenticated with its contents,
+    /// so that the objects of a snapshot can't be swapped
+    fn encrypt(&self, key: &str, contents: &[u8]) -> Result<Vec<u8>> {
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let ciphertext = self
+            .0
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: contents,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| CliStateError::InvalidOperation(format!("{key} can't be encrypted")))?;
+        let mut encrypted = vec![ENCRYPTED_OBJECT_VERSION];
+        encrypted.extend(nonce);
+        encrypted.extend(ciphertext);
+        Ok(encrypted)
+    }
+
+    /// Decrypt the contents of an object
+    fn decrypt(&self, key: &str, encrypted: &[u8]) -> Result<Vec<u8>> {
+        let invalid = || {
+            CliStateError::InvalidData(format!(
+                "{key} can't be decrypted, check {OCKAM_BACKUP_PASSPHRASE}"
+            ))
+        };
+        if encrypted.len() < 1 + NONCE_LENGTH || encrypted[0] != ENCRYPTED_OBJECT_VERSION {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = encrypted[1..].split_at(NONCE_LENGTH);
+        self.0
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_| invalid())
+    }
+}
+
+/// S3 bucket storing snapshots
+struct S3Bucket {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3Bucket {
+    async fn new(bucket: &str, region: &Option<String>, endpoint: &Option<String>) -> Self {
+        let mut loader = aws_config::from_env();
+        if let Some(region) = region {
+            loader = loader.region(aws_sdk_s3::config::Region::new(region.clone()));
+        }
+        let sdk_config = loader.load().await;
+        let mut config = aws_sdk_s3::config::Builder::from(&sdk_config);
+        if let Some(endpoint) = endpoint {
+            config = config.endpoint_url(endpoint).force_path_style(true);
+        }
+        Self {
+            client: aws_sdk_s3::Client::from_conf(config.build()),
+            bucket: bucket.to_string(),
+        }
+    }
+
+    /// Encrypt and upload all the files of a snapshot, and return their object keys
+    async fn upload_snapshot(
+        &self,
+        name: &str,
+        snapshot: &Path,
+        passphrase: String,
+    ) -> Result<Vec<String>> {
+        let encryption = SnapshotEncryption {
+            salt: hex::encode(rand::random::<[u8; SALT_LENGTH]>()),
+            iterations: PASSPHRASE_ITERATIONS,
+        };
+        let snapshot_key = SnapshotKey::derive(passphrase, &encryption).await?;
+        let encryption_key = format!("{name}/{SNAPSHOT_ENCRYPTION_OBJECT}");
+        self.put(&encryption_key, serde_json::to_vec(&encryption)?)
+            .await?;
+        let mut keys = vec![encryption_key];
+        for file in list_files(snapshot)? {
+            let relative = file
+                .strip_prefix(snapshot)
+                .map_err(|e| CliStateError::InvalidPath(e.to_string()))?;
+            let key = format!("{name}/{}", relative.to_string_lossy().replace('\\', "/"));
+            let encrypted = snapshot_key.encrypt(&key, &std::fs::read(&file)?)?;
+            self.put(&key, encrypted).await?;
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    /// Download and decrypt all the objects of a snapshot to a directory
+    async fn download_snapshot(
+        &self,
+        name: &str,
+        snapshot: &Path,
+        passphrase: String,
+    ) -> Result<()> {
+        let encryption_key = format!("{name}/{SNAPSHOT_ENCRYPTION_OBJECT}");
+        let encryption: SnapshotEncryption =
+            serde_json::from_slice(&self.get(&encryption_key).await?)?;
+        let snapshot_key = SnapshotKey::derive(passphrase, &encryption).await?;
+        for key in self.list(&format!("{name}/")).await? {
+            if key == encryption_key {
+                continue;
+            }
+            let relative = &key[name.len() + 1..];
+            if relative
+                .split('/')
+                .any(|part| part.is_empty() || part == "..")
+            {
+                return Err(CliStateError::InvalidPath(key));
+            }
+            let path = snapshot.join(relative);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, snapshot_key.decrypt(&key, &self.get(&key).await?)?)?;
+        }
+        Ok(())
+    }
+
+    async fn put(&self, key: &str, contents: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .body(ByteStream::from(contents))
+            .send()
+            .await
+            .map_err(|e| {
+                CliStateError::InvalidOperation(format!("{key} can't be uploaded: {e}"))
+            })?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| {
+                CliStateError::InvalidOperation(format!("{key} can't be downloaded: {e}"))
+            })?;
+        let contents = object.body.collect().await.map_err(|e| {
+            CliStateError::InvalidOperation(format!("{key} can't be downloaded: {e}"))
+        })?;
+        Ok(contents.into_bytes().to_vec())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|e| CliStateError::InvalidOperation(format!("{key} can't be deleted: {e}")))?;
+        Ok(())
+    }
+
+    /// Return the keys of the objects starting with a prefix
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = vec![];
+        let mut continuation_token = None;
+        loop {
+            let objects = self
+                .client
+                .list_objects_v2()
+                .bucket(&self.bucket)
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|e| {
+                    CliStateError::InvalidOperation(format!("{prefix} can't be listed: {e}"))
+                })?;
+            keys.extend(
+                objects
+                    .contents()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|o| o.key().map(|k| k.to_string())),
+            );
+            match objects.next_continuation_token() {
+                Some(token) if objects.is_truncated() => {
+                    continuation_token = Some(token.to_string())
+                }
+                _ => return Ok(keys),
+            }
+        }
+    }
+}
+
+pub(super) fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::InvitationConfig;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_scheduled_backups_with_retention() -> Result<()> {
+        let state = CliState::test()?;
+        let destination = tempfile::tempdir()?;
+        let config = BackupConfig::new(
+            Duration::from_secs(3600),
+            2,
+            BackupDestination::Directory {
+                path: destination.path().to_path_buf(),
+            },
+        );
+        state.set_backup_config(&config)?;
+        assert_eq!(state.backup_config()?, Some(config.clone()));
+
+        // the time is advanced as soon as the backups are done
+        let scheduler = state.start_backup_scheduler()?.unwrap();
+        tokio::time::sleep(Duration::from_secs(2 * 3600 + 1)).await;
+        let snapshots = state.list_snapshots()?;
+        assert_eq!(snapshots.len(), 2);
+
+        // the oldest snapshot is deleted by the next backup
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        let next_snapshots = state.list_snapshots()?;
+        assert_eq!(next_snapshots.len(), 2);
+        assert_eq!(next_snapshots[0], snapshots[1]);
+
+        scheduler.abort();
+        assert!(scheduler.await.unwrap_err().is_cancelled());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() -> Result<()> {
+        let state = CliState::test()?;
+        let destination = tempfile::tempdir()?;
+        let config = BackupConfig::new(
+            Duration::from_secs(3600),
+            2,
+            BackupDestination::Directory {
+                path: destination.path().to_path_buf(),
+            },
+        );
+        state.set_backup_config(&config)?;
+
+        let invitation = InvitationConfig {
+            id: "invitation".to_string(),
+            service: "db".to_string(),
+            attributes: BTreeMap::new(),
+            project: "default".to_string(),
+            created_at: 0,
+            expires_at: 3600,
+            revoked_at: None,
+        };
+        state.invitations.create("invitation", invitation)?;
+        let snapshot = state.backup().await?;
+        assert_eq!(state.list_snapshots()?, vec![snapshot.clone()]);
+        assert_eq!(
+            SnapshotManifest::read(&snapshot)?.schema_version,
+            STATE_SCHEMA_VERSION
+        );
+
+        state.invitations.delete("invitation")?;
+        let state = state.restore(&snapshot).await?;
+        assert!(state.invitations.exists("invitation"));
+        assert_eq!(state.backup_config()?, Some(config));
+        assert!(!state.dir.join(SNAPSHOT_MANIFEST_FILE).exists());
+
+        // the snapshots of another schema version are not restored
+        let manifest = SnapshotManifest {
+            schema_version: STATE_SCHEMA_VERSION + 1,
+            ..SnapshotManifest::read(&snapshot)?
+        };
+        std::fs::write(
+            snapshot.join(SNAPSHOT_MANIFEST_FILE),
+            serde_json::to_string(&manifest)?,
+        )?;
+        assert!(state.restore(&snapshot).await.is_err());
+        assert!(state.restore(destination.path()).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_backups_to_s3() -> Result<()> {
+        let mock = Arc::new(Mutex::new(MockS3::default()));
+        let endpoint = start_mock_s3(mock.clone()).await?;
+        std::env::set_var("AWS_ACCESS_KEY_ID", "test-access-key");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test-secret-key");
+        std::env::set_var(OCKAM_BACKUP_PASSPHRASE, "backup passphrase");
+
+        let state = CliState::test()?;
+        let invitation = InvitationConfig {
+            id: "invitation".to_string(),
+            service: "db".to_string(),
+            attributes: BTreeMap::new(),
+            project: "default".to_string(),
+            created_at: 0,
+            expires_at: 3600,
+            revoked_at: None,
+        };
+        state.invitations.create("invitation", invitation)?;
+        let config = BackupConfig::new(
+            Duration::from_secs(3600),
+            1,
+            BackupDestination::S3 {
+                bucket: "backups".to_string(),
+                region: Some("us-east-1".to_string()),
+                endpoint: Some(endpoint),
+            },
+        );
+        state.run_backup(&config).await?;
+        let name = state.run_backup(&config).await?;
+
+        {
+            let mock = mock.lock().unwrap();
+            // the requests are signed with SigV4
+            assert!(mock.authorizations.iter().all(|authorization| authorization
+                .starts_with("AWS4-HMAC-SHA256 Credential=test-access-key/")));
+            // only the last snapshot is kept
+            let prefix = format!("/backups/{name}/");
+            assert!(mock.objects.keys().all(|key| key.starts_with(&prefix)));
+            // the files of the snapshot are encrypted
+            let (_, invitation) = mock
+                .objects
+                .iter()
+                .find(|(key, _)| key.contains("invitations"))
+                .unwrap();
+            assert!(!invitation.windows(7).any(|w| w == b"service"));
+        }
+
+        let downloads = tempfile::tempdir()?;
+        let snapshot = state
+            .download_snapshot(&config, &name, downloads.path())
+            .await?;
+        state.invitations.delete("invitation")?;
+        let state = state.restore(&snapshot).await?;
+        assert!(state.invitations.exists("invitation"));
+
+        // the snapshot can't be decrypted with another passphrase
+        std::env::set_var(OCKAM_BACKUP_PASSPHRASE, "another passphrase");
+        let downloads = tempfile::tempdir()?;
+        assert!(state
+            .download_snapshot(&config, &name, downloads.path())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    /// Objects stored by a mock S3 endpoint, by path, and the authorizations of its requests
+    #[derive(Default)]
+    struct MockS3 {
+        objects: BTreeMap<String, Vec<u8>>,
+        authorizations: Vec<String>,
+    }
+
+    /// Start an S3 endpoint keeping the objects in memory, and return its URL
+    async fn start_mock_s3(mock: Arc<Mutex<MockS3>>) -> Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_mock_s3(stream, mock.clone()));
+            }
+        });
+        Ok(url)
+    }
+
+    async fn serve_mock_s3(stream: TcpStream, mock: Arc<Mutex<MockS3>>) -> std::io::Result<()> {
+        let mut stream = BufReader::new(stream);
+        loop {
+            let mut request_line = String::new();
+            if stream.read_line(&mut request_line).await? == 0 {
+                return Ok(());
+            }
+            let mut parts = request_line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_string();
+            let target = parts.next().unwrap_or_default().to_string();
+            let mut headers = BTreeMap::new();
+            loop {
+                let mut line = String::new();
+                stream.read_line(&mut line).await?;
+                match line.trim_end().split_once(':') {
+                    Some((name, value)) => {
+                        headers.insert(name.to_lowercase(), value.trim().to_string());
+                    }
+                    None => break,
+                }
+            }
+            let length = headers
+                .get("content-length")
+                .and_then(|length| length.parse().ok())
+                .unwrap_or(0);
+            let mut body = vec![0; length];
+            stream.read_exact(&mut body).await?;
+
+            let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+            // the objects are listed with a request on the path of the bucket, ending with '/'
+            let path = path.trim_end_matches('/');
+            let (status, response) = {
+                let mut mock = mock.lock().unwrap();
+                let authorization = headers.remove("authorization").unwrap_or_default();
+                mock.authorizations.push(authorization);
+                match method.as_str() {
+                    "PUT" => {
+                        mock.objects.insert(path.to_string(), body);
+                        ("200 OK", vec![])
+                    }
+                    "DELETE" => {
+                        mock.objects.remove(path);
+                        ("204 No Content", vec![])
+                    }
+                    "GET" if query.contains("list-type=2") => {
+                        let prefix = query
+                            .split('&')
+                            .find_map(|p| p.strip_prefix("prefix="))
+                            .unwrap_or_default()
+                            .replace("%2F", "/");
+                        let contents: String = mock
+                            .objects
+                            .keys()
+                            .filter_map(|key| key.strip_prefix(&format!("{path}/")))
+                            .filter(|key| key.starts_with(&prefix))
+                            .map(|key| format!("<Contents><Key>{key}</Key></Contents>"))
+                            .collect();
+                        let list = format!(
+                            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><ListBucketResult><IsTruncated>false</IsTruncated>{contents}</ListBucketResult>"
+                        );
+                        ("200 OK", list.into_bytes())
+                    }
+                    "GET" => match mock.objects.get(path) {
+                        Some(object) => ("200 OK", object.clone()),
+                        None => ("404 Not Found", vec![]),
+                    },
+                    _ => ("405 Method Not Allowed", vec![]),
+                }
+            };
+            let head = format!(
+                "HTTP/1.1 {status}\r\ncontent-length: {}\r\n\r\n",
+                response.len()
+            );
+            stream.get_mut().write_all(head.as_bytes()).await?;
+            stream.get_mut().write_all(&response).await?;
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Age after which a project or a space retrieved from the Orchestrator is considered stale
+pub const CACHE_MAX_AGE: Duration = Duration::from_secs(24 * 3600);
+
+/// Value served from the local state, with the time at which it was last fetched
+/// from the Orchestrator
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cached<T> {
+    #[serde(flatten)]
+    pub value: T,
+    /// Seconds since the Unix epoch. This time is not known for the values which were
+    /// stored before the fetch time was recorded, or which were not fetched from the Orchestrator
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<u64>,
+}
+
+impl<T> Cached<T> {
+    pub fn new(value: T, fetched_at: Option<u64>) -> Self {
+        Self { value, fetched_at }
+    }
+
+    /// Time elapsed since the value was fetched, if known
+    pub fn age(&self) -> Option<Duration> {
+        self.fetched_at
+            .map(|fetched_at| Duration::from_secs(now().saturating_sub(fetched_at)))
+    }
+
+    /// Return true if the value was fetched more than `max_age` ago, or at an unknown time
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.age().map_or(true, |age| age > max_age)
+    }
+
+    /// Warning to display when a stale value is used
+    pub fn staleness_warning(&self, max_age: Duration) -> Option<String> {
+        if !self.is_stale(max_age) {
+            return None;
+        }
+        Some(match self.age() {
+            Some(age) => format!(
+                "this data was fetched from the Orchestrator {} ago and might be outdated",
+                format_age(age)
+            ),
+            None => "this data was never refreshed from the Orchestrator and might be outdated"
+                .to_string(),
+        })
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        s if s >= 24 * 3600 => format!("{} days", s / (24 * 3600)),
+        s if s >= 3600 => format!("{} hours", s / 3600),
+        s if s >= 60 => format!("{} minutes", s / 60),
+        s => format!("{s} seconds"),
+    }
+}
+
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::SpaceConfig;
+
+    #[test]
+    fn test_staleness() {
+        let config = SpaceConfig {
+            name: "space".into(),
+            id: "id".into(),
+        };
+        let never_fetched = Cached::new(config.clone(), None);
+        assert!(never_fetched.is_stale(CACHE_MAX_AGE));
+        assert!(never_fetched
+            .staleness_warning(CACHE_MAX_AGE)
+            .unwrap()
+            .contains("never refreshed"));
+
+        let fresh = Cached::new(config.clone(), Some(now()));
+        assert!(!fresh.is_stale(CACHE_MAX_AGE));
+        assert_eq!(fresh.staleness_warning(CACHE_MAX_AGE), None);
+
+        let old = Cached::new(config, Some(now() - 3 * 24 * 3600));
+        assert!(old.is_stale(CACHE_MAX_AGE));
+        assert!(old
+            .staleness_warning(CACHE_MAX_AGE)
+            .unwrap()
+            .contains("3 days ago"));
+    }
+
+    #[test]
+    fn test_serialization_is_compatible() {
+        let config = SpaceConfig {
+            name: "space".into(),
+            id: "id".into(),
+        };
+        // values stored without a fetch time can still be read
+        let stored = serde_json::to_string(&config).unwrap();
+        let cached: Cached<SpaceConfig> = serde_json::from_str(&stored).unwrap();
+        assert_eq!(cached, Cached::new(config.clone(), None));
+
+        let cached = Cached::new(config, Some(10));
+        let stored = serde_json::to_string(&cached).unwrap();
+        assert_eq!(stored, r#"{"name":"space","id":"id","fetched_at":10}"#);
+        assert_eq!(
+            serde_json::from_str::<Cached<SpaceConfig>>(&stored).unwrap(),
+            cached
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Default items of the CLI state.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli_state::locks::write_atomically;
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{file_stem, AuditLog, StateEvent, StateEvents, StateLock};
+
+use super::Result;
+
+/// Name of the file storing the default items
+pub const DEFAULTS_FILE_NAME: &str = "defaults.json";
+
+/// Name of the legacy directory of symbolic links to the default items
+const LEGACY_DEFAULTS_DIR_NAME: &str = "defaults";
+
+/// Names of the default items, by kind of item.
+/// The kind of an item is the `DEFAULT_FILENAME` of its state directory, for example `vault`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DefaultsState {
+    path: PathBuf,
+}
+
+impl DefaultsState {
+    /// Defaults of the state stored in a root directory
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            path: root_path.join(DEFAULTS_FILE_NAME),
+        }
+    }
+
+    /// Load the defaults and migrate the legacy `defaults` directory if there is one
+    pub fn init(root_path: &Path) -> Result<Self> {
+        let defaults = Self::new(root_path);
+        defaults.migrate(&root_path.join(LEGACY_DEFAULTS_DIR_NAME))?;
+        Ok(defaults)
+    }
+
+    /// Name of the default item of a given kind, if any
+    pub fn get(&self, kind: &str) -> Result<Option<String>> {
+        Ok(self.read()?.remove(kind))
+    }
+
+    /// Set the name of the default item of a given kind
+    pub fn set(&self, kind: &str, name: &str) -> Result<()> {
+        let lock = self.lock()?;
+        self.set_locked(&lock, kind, name)
+    }
+
+    /// Same as [`DefaultsState::set`], while holding the lock of the state
+    pub fn set_locked(&self, _lock: &StateLock, kind: &str, name: &str) -> Result<()> {
+        let mut defaults = self.read()?;
+        let previous = defaults.insert(kind.to_string(), name.to_string());
+        if previous.as_deref() != Some(name) {
+            self.write(&defaults)?;
+            self.events()
+                .emit(StateEvent::default_changed(kind, Some(name)));
+            self.audit()
+                .default_changed(kind, previous.as_deref(), Some(name));
+        }
+        Ok(())
+    }
+
+    /// Remove the default item of a given kind
+    pub fn remove(&self, kind: &str) -> Result<()> {
+        let lock = self.lock()?;
+        self.remove_locked(&lock, kind)
+    }
+
+    /// Same as [`DefaultsState::remove`], while holding the lock of the state
+    pub fn remove_locked(&self, _lock: &StateLock, kind: &str) -> Result<()> {
+        let mut defaults = self.read()?;
+        if let Some(previous) = defaults.remove(kind) {
+            self.write(&defaults)?;
+            self.events().emit(StateEvent::default_changed(kind, None));
+            self.audit().default_changed(kind, Some(&previous), None);
+        }
+        Ok(())
+    }
+
+    /// Path of the file storing the defaults
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn lock(&self) -> Result<StateLock> {
+        StateLock::acquire(self.path.parent().expect("Should have parent"))
+    }
+
+    fn events(&self) -> StateEvents {
+        StateEvents::new(self.path.parent().expect("Should have parent"))
+    }
+
+    fn audit(&self) -> AuditLog {
+        AuditLog::new(self.path.parent().expect("Should have parent"))
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let contents = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    fn write(&self, defaults: &BTreeMap<String, String>) -> Result<()> {
+        check_writable(&self.path)?;
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        write_atomically(&self.path, serde_json::to_string(defaults)?)
+    }
+
+    /// Record the targets of the legacy links, then delete them.
+    /// The defaults which are already in the table are kept
+    fn migrate(&self, legacy_dir: &Path) -> Result<()> {
+        if !legacy_dir.is_dir() {
+            return Ok(());
+        }
+        let mut defaults = self.read()?;
+        for entry in std::fs::read_dir(legacy_dir)? {
+            let link = entry?.path();
+            let kind = file_stem(&link)?;
+            // a link to a deleted item doesn't set a default
+            let target = match std::fs::read_link(&link) {
+                Ok(target) if target.exists() => target,
+                _ => continue,
+            };
+            debug!(%kind, ?target, "migrating a default item");
+            defaults.entry(kind).or_insert(file_stem(&target)?);
+        }
+        self.write(&defaults)?;
+        std::fs::remove_dir_all(legacy_dir)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::CliState;
+
+    #[test]
+    fn test_set_and_remove_defaults() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let defaults = DefaultsState::new(&dir);
+        assert_eq!(defaults.get("vault")?, None);
+
+        defaults.set("vault", "v1")?;
+        defaults.set("node", "n1")?;
+        defaults.set("vault", "v2")?;
+        assert_eq!(defaults.get("vault")?, Some("v2".to_string()));
+        assert_eq!(
+            DefaultsState::new(&dir).get("node")?,
+            Some("n1".to_string())
+        );
+
+        defaults.remove("vault")?;
+        assert_eq!(defaults.get("vault")?, None);
+        assert_eq!(defaults.get("node")?, Some("n1".to_string()));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_migrate_legacy_links() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let legacy_dir = dir.join(LEGACY_DEFAULTS_DIR_NAME);
+        std::fs::create_dir_all(&legacy_dir)?;
+        std::fs::create_dir_all(dir.join("vaults"))?;
+        std::fs::create_dir_all(dir.join("nodes").join("n1"))?;
+        std::fs::write(dir.join("vaults").join("v1.json"), "{}")?;
+        std::os::unix::fs::symlink(dir.join("vaults/v1.json"), legacy_dir.join("vault"))?;
+        std::os::unix::fs::symlink(dir.join("nodes/n1"), legacy_dir.join("node"))?;
+        std::os::unix::fs::symlink(dir.join("spaces/s1.json"), legacy_dir.join("space"))?;
+
+        let defaults = DefaultsState::init(&dir)?;
+        assert_eq!(defaults.get("vault")?, Some("v1".to_string()));
+        assert_eq!(defaults.get("node")?, Some("n1".to_string()));
+        // the link to a deleted space is dropped
+        assert_eq!(defaults.get("space")?, None);
+        assert!(!legacy_dir.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::BTreeMap;
+
+use ockam::identity::Identifier;
+
+use crate::cli_state::traits::{StateDirTrait, StateItemTrait};
+use crate::cli_state::{CliState, Result};
+
+/// Number of characters of an identifier displayed next to its name
+const SHORT_IDENTIFIER_LENGTH: usize = 6;
+
+/// Names of the identifiers known locally, used to display identifiers to humans.
+///
+/// When several names are known for an identifier, the first one added is kept, so the most
+/// specific sources of names must be added first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DisplayNames {
+    names: BTreeMap<Identifier, String>,
+}
+
+impl DisplayNames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a name for an identifier, unless it already has one
+    pub fn with_name(mut self, identifier: Identifier, name: impl Into<String>) -> Self {
+        self.add_name(identifier, name);
+        self
+    }
+
+    /// Add a name for an identifier, unless it already has one
+    pub fn add_name(&mut self, identifier: Identifier, name: impl Into<String>) {
+        self.names.entry(identifier).or_insert_with(|| name.into());
+    }
+
+    /// Return the name of an identifier, if it is known
+    pub fn name(&self, identifier: &Identifier) -> Option<&str> {
+        self.names.get(identifier).map(|n| n.as_str())
+    }
+
+    /// Return the name of an identifier followed by the start of the identifier,
+    /// for example `alice (I2abc…)`, or the full identifier if it has no known name
+    pub fn display(&self, identifier: &Identifier) -> String {
+        match self.name(identifier) {
+            Some(name) => format!("{name} ({})", short_identifier(identifier)),
+            None => identifier.to_string(),
+        }
+    }
+}
+
+/// Return the first characters of an identifier
+pub fn short_identifier(identifier: &Identifier) -> String {
+    let identifier = identifier.to_string();
+    match identifier.get(..SHORT_IDENTIFIER_LENGTH) {
+        Some(start) => format!("{start}…"),
+        None => identifier,
+    }
+}
+
+impl CliState {
+    /// Return the names of the identifiers known to this state, from the names of
+    /// the identities, then the names of the nodes and finally the names of the projects
+    pub fn display_names(&self) -> Result<DisplayNames> {
+        let mut names = DisplayNames::new();
+        for identity in self.identities.list()? {
+            names.add_name(identity.identifier(), identity.name());
+        }
+        for node in self.nodes.list()? {
+            if let Ok(identifier) = node.config().identifier() {
+                names.add_name(identifier, format!("node {}", node.name()));
+            }
+        }
+        for project in self.projects.list()? {
+            if let Some(identifier) = project.config().identity.clone() {
+                names.add_name(identifier, format!("project {}", project.name()));
+            }
+        }
+        Ok(names)
+    }
+
+    /// Return an identifier prefixed with its name, if it is known to this state
+    pub fn display_name(&self, identifier: &Identifier) -> String {
+        match self.display_names() {
+            Ok(names) => names.display(identifier),
+            Err(e) => {
+                debug!(%e, "The names of the identifiers can't be read");
+                identifier.to_string()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_display_names() {
+        let alice = Identifier::from_str("I2abc000000000000000000000000000000000000").unwrap();
+        let bob = Identifier::from_str("I3def000000000000000000000000000000000000").unwrap();
+
+        let names = DisplayNames::new()
+            .with_name(alice.clone(), "alice")
+            .with_name(alice.clone(), "node n1");
+        assert_eq!(names.name(&alice), Some("alice"));
+        assert_eq!(names.display(&alice), "alice (I2abc0…)");
+        assert_eq!(names.display(&bob), bob.to_string());
+    }
+}
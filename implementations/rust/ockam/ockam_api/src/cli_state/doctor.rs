@@ -0,0 +1,304 @@
+//! Health check of the local state.
+
+use std::fmt::{Display, Formatter};
+
+use serde::Serialize;
+
+use ockam::identity::{Identifier, Identity, Vault};
+
+use crate::cli_state::read_only::is_read_only;
+use crate::cli_state::{
+    CliState, IdentitiesState, NodesState, StateDirTrait, VaultsState, STATE_SCHEMA_VERSION,
+};
+
+use super::Result;
+
+/// Name of the file, in the state directory, containing the schema version of the state
+pub const SCHEMA_VERSION_FILE_NAME: &str = "schema_version";
+
+/// Result of the health check of the local state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StateDoctorReport {
+    pub problems: Vec<StateProblem>,
+}
+
+impl StateDoctorReport {
+    pub fn is_healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Problem found in the local state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum StateProblem {
+    /// The state was written by a more recent version of `ockam`
+    UnsupportedSchemaVersion { version: String },
+    /// The change history of a named identity is not in the identities database
+    MissingChangeHistory {
+        identity: String,
+        identifier: Identifier,
+    },
+    /// The change history of a named identity can't be verified
+    InvalidChangeHistory {
+        identity: String,
+        identifier: Identifier,
+        error: String,
+    },
+    /// The identities database can't be opened
+    UnreadableIdentities { error: String },
+    /// The default item of a kind doesn't exist anymore
+    MissingDefault { kind: String, name: String },
+    /// The process recorded for a node is not running
+    StaleNodeProcess { node: String, pid: i32 },
+    /// The process file of a node can't be read
+    InvalidNodeProcess { node: String, error: String },
+}
+
+impl StateProblem {
+    /// Return the suggested repair of the problem
+    pub fn repair(&self) -> String {
+        match self {
+            StateProblem::UnsupportedSchemaVersion { .. } => {
+                "Upgrade ockam, or reset the state with `ockam reset`".to_string()
+            }
+            StateProblem::MissingChangeHistory { identity, .. }
+            | StateProblem::InvalidChangeHistory { identity, .. } => format!(
+                "Delete the identity with `ockam identity delete {identity}` and create it again"
+            ),
+            StateProblem::UnreadableIdentities { .. } => {
+                "Check that the default vault can be used with `ockam vault show`".to_string()
+            }
+            StateProblem::MissingDefault { kind, .. } => {
+                format!("Set another default {kind} with `ockam {kind} default <name>`")
+            }
+            StateProblem::StaleNodeProcess { node, .. }
+            | StateProblem::InvalidNodeProcess { node, .. } => {
+                format!("Start the node again with `ockam node start {node}`")
+            }
+        }
+    }
+}
+
+impl Display for StateProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateProblem::UnsupportedSchemaVersion { version } => write!(
+                f,
+                "The schema version of the state is {version}, only the version {STATE_SCHEMA_VERSION} is supported"
+            ),
+            StateProblem::MissingChangeHistory {
+                identity,
+                identifier,
+            } => write!(
+                f,
+                "The identity {identity} ({identifier}) has no change history"
+            ),
+            StateProblem::InvalidChangeHistory {
+                identity,
+                identifier,
+                error,
+            } => write!(
+                f,
+                "The change history of the identity {identity} ({identifier}) is invalid: {error}"
+            ),
+            StateProblem::UnreadableIdentities { error } => {
+                write!(f, "The identities database can't be read: {error}")
+            }
+            StateProblem::MissingDefault { kind, name } => {
+                write!(f, "The default {kind} {name} doesn't exist")
+            }
+            StateProblem::StaleNodeProcess { node, pid } => {
+                write!(f, "The process {pid} of the node {node} is not running")
+            }
+            StateProblem::InvalidNodeProcess { node, error } => {
+                write!(f, "The process of the node {node} can't be read: {error}")
+            }
+        }
+    }
+}
+
+impl CliState {
+    /// Check the consistency of the local state and return the problems found.
+    /// See the [module documentation](self) for the details
+    pub async fn doctor(&self) -> Result<StateDoctorReport> {
+        let mut problems = vec![];
+        problems.extend(self.check_schema_version()?);
+        problems.extend(self.check_identities().await?);
+        problems.extend(self.check_default::<VaultsState>(&self.vaults)?);
+        problems.extend(self.check_default::<IdentitiesState>(&self.identities)?);
+        problems.extend(self.check_default::<NodesState>(&self.nodes)?);
+        problems.extend(self.check_node_processes()?);
+        for problem in &problems {
+            warn!(%problem, "Found a problem in the state");
+        }
+        Ok(StateDoctorReport { problems })
+    }
+
+    /// Record the schema version of a state which doesn't have one yet
+    pub(crate) fn record_schema_version(&self) -> Result<()> {
+        let path = self.dir.join(SCHEMA_VERSION_FILE_NAME);
+        if !path.exists() && !is_read_only(&self.dir) {
+            std::fs::create_dir_all(&self.dir)?;
+            std::fs::write(path, STATE_SCHEMA_VERSION.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// A state without a recorded version was created before the versions were recorded,
+    /// and has the first version
+    fn check_schema_version(&self) -> Result<Option<StateProblem>> {
+        let path = self.dir.join(SCHEMA_VERSION_FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let version = std::fs::read_to_string(path)?.trim().to_string();
+        match version.parse::<u32>() {
+            Ok(v) if v <= STATE_SCHEMA_VERSION => Ok(None),
+            _ => Ok(Some(StateProblem::UnsupportedSchemaVersion { version })),
+        }
+    }
+
+    async fn check_identities(&self) -> Result<Vec<StateProblem>> {
+        let identities = self.identities.list()?;
+        if identities.is_empty() {
+            return Ok(vec![]);
+        }
+        let repository = match self.identities_repository().await {
+            Ok(repository) => repository,
+            Err(e) => {
+                return Ok(vec![StateProblem::UnreadableIdentities {
+                    error: e.to_string(),
+                }])
+            }
+        };
+        let mut problems = vec![];
+        for identity in identities {
+            let identifier = identity.identifier();
+            let change_history = match repository.retrieve_identity(&identifier).await {
+                Ok(Some(change_history)) => change_history,
+                Ok(None) => {
+                    problems.push(StateProblem::MissingChangeHistory {
+                        identity: identity.name().to_string(),
+                        identifier,
+                    });
+                    continue;
+                }
+                Err(e) => {
+                    problems.push(StateProblem::InvalidChangeHistory {
+                        identity: identity.name().to_string(),
+                        identifier,
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+            if let Err(e) = Identity::import_from_change_history(
+                Some(&identifier),
+                change_history,
+                Vault::create_verifying_vault(),
+            )
+            .await
+            {
+                problems.push(StateProblem::InvalidChangeHistory {
+                    identity: identity.name().to_string(),
+                    identifier,
+                    error: e.to_string(),
+                });
+            }
+        }
+        Ok(problems)
+    }
+
+    fn check_default<S: StateDirTrait>(&self, items: &S) -> Result<Option<StateProblem>> {
+        let kind = S::default_filename();
+        match self.defaults.get(kind)? {
+            Some(name) if !items.exists(&name) => Ok(Some(StateProblem::MissingDefault {
+                kind: kind.to_string(),
+                name,
+            })),
+            _ => Ok(None),
+        }
+    }
+
+    fn check_node_processes(&self) -> Result<Vec<StateProblem>> {
+        let mut problems = vec![];
+        for node in self.nodes.list()? {
+            match node.pid() {
+                Ok(Some(pid)) if !node.is_running() => {
+                    problems.push(StateProblem::StaleNodeProcess {
+                        node: node.name().to_string(),
+                        pid,
+                    })
+                }
+                Ok(_) => (),
+                Err(e) => problems.push(StateProblem::InvalidNodeProcess {
+                    node: node.name().to_string(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+        Ok(problems)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{IdentityConfig, NodeConfig, VaultConfig};
+
+    #[tokio::test]
+    async fn test_doctor() -> Result<()> {
+        let state = CliState::test()?;
+        state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+        let identities = state.default_identities().await?;
+        let identity = identities.identities_creation().create_identity().await?;
+        state
+            .identities
+            .create("i1", IdentityConfig::new(identity.identifier()).await)?;
+        let node = state.nodes.create("n1", NodeConfig::try_from(&state)?)?;
+        state.record_schema_version()?;
+        assert_eq!(state.doctor().await?, StateDoctorReport::default());
+
+        // an identity without a change history
+        let unknown: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        state
+            .identities
+            .create("i2", IdentityConfig::new(&unknown).await)?;
+        // a dangling default
+        state.defaults.set("node", "n2")?;
+        // a node process which is not running anymore
+        node.set_pid(i32::MAX)?;
+        std::fs::write(state.dir.join(SCHEMA_VERSION_FILE_NAME), "1000")?;
+
+        let problems = state.doctor().await?.problems;
+        assert_eq!(
+            problems,
+            vec![
+                StateProblem::UnsupportedSchemaVersion {
+                    version: "1000".to_string()
+                },
+                StateProblem::MissingChangeHistory {
+                    identity: "i2".to_string(),
+                    identifier: unknown,
+                },
+                StateProblem::MissingDefault {
+                    kind: "node".to_string(),
+                    name: "n2".to_string()
+                },
+                StateProblem::StaleNodeProcess {
+                    node: "n1".to_string(),
+                    pid: i32::MAX
+                },
+            ]
+        );
+        assert_eq!(
+            problems[2].repair(),
+            "Set another default node with `ockam node default <name>`"
+        );
+        Ok(())
+    }
+}
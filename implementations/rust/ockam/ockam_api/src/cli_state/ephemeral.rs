@@ -0,0 +1,130 @@
+//! Ephemeral CLI state, for tests and short-lived nodes.
+
+use std::fmt::{Debug, Formatter};
+use std::path::Path;
+use std::sync::Arc;
+
+use ockam::identity::storage::{InMemoryStorage, Storage};
+use tempfile::TempDir;
+
+use crate::cli_state::CliState;
+
+use super::Result;
+
+/// Temporary directory of an ephemeral state, shared by all the copies of that state
+#[derive(Clone)]
+pub struct EphemeralDir {
+    dir: Arc<TempDir>,
+}
+
+impl EphemeralDir {
+    fn new() -> Result<Self> {
+        let dir = tempfile::Builder::new().prefix("ockam-").tempdir()?;
+        Ok(Self { dir: Arc::new(dir) })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+impl Debug for EphemeralDir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("EphemeralDir").field(&self.path()).finish()
+    }
+}
+
+impl PartialEq for EphemeralDir {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.dir, &other.dir)
+    }
+}
+
+impl Eq for EphemeralDir {}
+
+/// Storage of the identities of an ephemeral state, shared by all the copies of that state
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    storage: Arc<InMemoryStorage>,
+}
+
+impl MemoryStorage {
+    pub fn storage(&self) -> Arc<dyn Storage> {
+        self.storage.clone()
+    }
+}
+
+impl Debug for MemoryStorage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MemoryStorage")
+    }
+}
+
+impl PartialEq for MemoryStorage {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.storage, &other.storage)
+    }
+}
+
+impl Eq for MemoryStorage {}
+
+impl CliState {
+    /// Return a new state which is only kept in memory and in a temporary directory.
+    /// Migrations are not necessary since the state is empty
+    pub fn ephemeral() -> Result<Self> {
+        let ephemeral = EphemeralDir::new()?;
+        let mut state = Self::new(ephemeral.path())?;
+        state.identities = state
+            .identities
+            .with_memory_storage(MemoryStorage::default());
+        state.ephemeral = Some(ephemeral);
+        Ok(state)
+    }
+
+    /// Return true if this state is deleted once it is dropped
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::VaultConfig;
+
+    #[tokio::test]
+    async fn test_ephemeral_state() -> Result<()> {
+        let state = CliState::ephemeral()?;
+        let dir = state.dir.clone();
+        assert!(state.is_ephemeral());
+        assert!(!dir.starts_with(CliState::default_dir()?));
+
+        let vault_state = state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+        let identities = state.get_identities(vault_state.get().await?).await?;
+        let identity = identities.identities_creation().create_identity().await?;
+
+        // the identities are shared by the copies of the state, and never written to disk
+        let copy = state.clone();
+        drop(state);
+        assert!(dir.exists());
+        let identities = copy.default_identities().await?;
+        identities.get_identity(identity.identifier()).await?;
+        assert!(!copy.identities.identities_repository_path()?.exists());
+
+        // the directory is deleted with the last copy of the state
+        drop(copy);
+        assert!(!dir.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_ephemeral_states_are_distinct() -> Result<()> {
+        let state = CliState::ephemeral()?;
+        assert_eq!(state, state.clone());
+        assert_ne!(state, CliState::ephemeral()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! Notifications of the changes made to the CLI state.
+
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::CliState;
+
+use super::Result;
+
+/// Name of the file logging the changes made to the state
+pub const EVENTS_FILE_NAME: &str = "events.jsonl";
+
+/// Size of the log at which it is truncated
+pub const MAX_EVENTS_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Interval between two checks of the log by a subscription waiting for an event
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Change made to the state.
+/// The vaults, identities and nodes have their own events, the other kinds of items, named
+/// after the `DEFAULT_FILENAME` of their state directory, share the generic events
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum StateEvent {
+    VaultCreated {
+        name: String,
+    },
+    VaultDeleted {
+        name: String,
+    },
+    IdentityCreated {
+        name: String,
+    },
+    IdentityDeleted {
+        name: String,
+    },
+    NodeCreated {
+        name: String,
+    },
+    NodeDeleted {
+        name: String,
+    },
+    ItemCreated {
+        kind: String,
+        name: String,
+    },
+    ItemDeleted {
+        kind: String,
+        name: String,
+    },
+    /// The name is missing when the default vault is removed
+    DefaultVaultChanged {
+        name: Option<String>,
+    },
+    DefaultIdentityChanged {
+        name: Option<String>,
+    },
+    DefaultNodeChanged {
+        name: Option<String>,
+    },
+    DefaultChanged {
+        kind: String,
+        name: Option<String>,
+    },
+}
+
+impl StateEvent {
+    pub fn created(kind: &str, name: &str) -> Self {
+        let name = name.to_string();
+        match kind {
+            "vault" => Self::VaultCreated { name },
+            "identity" => Self::IdentityCreated { name },
+            "node" => Self::NodeCreated { name },
+            _ => Self::ItemCreated {
+                kind: kind.to_string(),
+                name,
+            },
+        }
+    }
+
+    pub fn deleted(kind: &str, name: &str) -> Self {
+        let name = name.to_string();
+        match kind {
+            "vault" => Self::VaultDeleted { name },
+            "identity" => Self::IdentityDeleted { name },
+            "node" => Self::NodeDeleted { name },
+            _ => Self::ItemDeleted {
+                kind: kind.to_string(),
+                name,
+            },
+        }
+    }
+
+    pub fn default_changed(kind: &str, name: Option<&str>) -> Self {
+        let name = name.map(|name| name.to_string());
+        match kind {
+            "vault" => Self::DefaultVaultChanged { name },
+            "identity" => Self::DefaultIdentityChanged { name },
+            "node" => Self::DefaultNodeChanged { name },
+            _ => Self::DefaultChanged {
+                kind: kind.to_string(),
+                name,
+            },
+        }
+    }
+}
+
+/// Log of the changes made to the state stored in a root directory
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StateEvents {
+    path: PathBuf,
+}
+
+impl StateEvents {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            path: root_path.join(EVENTS_FILE_NAME),
+        }
+    }
+
+    /// Path of the file logging the events
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Append an event to the log.
+    /// The change was already made when it is notified, so a failure is only logged
+    pub fn emit(&self, event: StateEvent) {
+        if let Err(e) = self.append(&event) {
+            warn!(%e, ?event, "The change of the state can't be notified");
+        }
+    }
+
+    /// Return a subscription to the events emitted from now on
+    pub fn subscribe(&self) -> StateEventStream {
+        let offset = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        StateEventStream {
+            path: self.path.clone(),
+            offset,
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn append(&self, event: &StateEvent) -> Result<()> {
+        check_writable(&self.path)?;
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if file.metadata()?.len() >= MAX_EVENTS_FILE_SIZE {
+            file.set_len(0)?;
+        }
+        // a single write, so that the lines of concurrent processes are not interleaved
+        file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Events appended to the log of a state since a subscription was created
+#[derive(Debug)]
+pub struct StateEventStream {
+    path: PathBuf,
+    offset: u64,
+    pending: VecDeque<StateEvent>,
+}
+
+impl StateEventStream {
+    /// Wait for the next event
+    pub async fn next(&mut self) -> StateEvent {
+        loop {
+            match self.try_next() {
+                Ok(Some(event)) => return event,
+                Ok(None) => {}
+                Err(e) => warn!(%e, "The changes of the state can't be read"),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Return the next event if there is one, without waiting
+    pub fn try_next(&mut self) -> Result<Option<StateEvent>> {
+        if self.pending.is_empty() {
+            self.read_events()?;
+        }
+        Ok(self.pending.pop_front())
+    }
+
+    fn read_events(&mut self) -> Result<()> {
+        let mut file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.offset = 0;
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        // the log was truncated
+        if file.metadata()?.len() < self.offset {
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        // a line which is not terminated yet is read again later
+        let complete = match contents.rfind('\n') {
+            Some(index) => &contents[..=index],
+            None => return Ok(()),
+        };
+        self.offset += complete.len() as u64;
+        for line in complete.lines().filter(|line| !line.is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(event) => self.pending.push_back(event),
+                Err(e) => warn!(%e, %line, "Skipping an invalid state event"),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CliState {
+    /// Log of the changes made to this state
+    pub fn events(&self) -> StateEvents {
+        StateEvents::new(&self.dir)
+    }
+
+    /// Return a subscription to the changes made to this state from now on, by this process
+    /// or by any other process using the same state
+    pub fn subscribe(&self) -> StateEventStream {
+        self.events().subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{StateDirTrait, VaultConfig};
+
+    #[tokio::test]
+    async fn test_subscribe_to_changes() -> Result<()> {
+        let state = CliState::ephemeral()?;
+        state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+
+        // the previous changes are not notified
+        let mut events = state.subscribe();
+        assert_eq!(events.try_next()?, None);
+
+        // a change made with another copy of the state is notified
+        let other = state.clone();
+        other
+            .vaults
+            .create_async("v2", VaultConfig::default())
+            .await?;
+        other.vaults.set_default("v2")?;
+        other.vaults.delete("v1")?;
+        assert_eq!(
+            events.next().await,
+            StateEvent::VaultCreated {
+                name: "v2".to_string()
+            }
+        );
+        assert_eq!(
+            events.next().await,
+            StateEvent::DefaultVaultChanged {
+                name: Some("v2".to_string())
+            }
+        );
+        assert_eq!(
+            events.next().await,
+            StateEvent::VaultDeleted {
+                name: "v1".to_string()
+            }
+        );
+        assert_eq!(events.try_next()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_log() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let events = StateEvents::new(&dir);
+        std::fs::write(events.path(), vec![b' '; MAX_EVENTS_FILE_SIZE as usize])?;
+        let mut stream = events.subscribe();
+
+        events.emit(StateEvent::created("space", "s1"));
+        assert_eq!(
+            stream.try_next()?,
+            Some(StateEvent::ItemCreated {
+                kind: "space".to_string(),
+                name: "s1".to_string()
+            })
+        );
+        assert!(std::fs::metadata(events.path())?.len() < MAX_EVENTS_FILE_SIZE);
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::fmt::{Display, Formatter};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::models::{
+    ChangeData, CredentialAndPurposeKey, CredentialData, PurposeKeyAttestationData,
+};
+use ockam::identity::Identifier;
+
+use super::{CliState, Result, StateDirTrait, StateItemTrait};
+
+/// Kind of the items which can expire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiringItemKind {
+    /// The current key of an identity, which must be rotated before it expires
+    IdentityKey,
+    /// A stored credential
+    Credential,
+    /// The purpose key of the authority which issued a stored credential
+    CredentialIssuerKey,
+    /// The attributes of an identity, as stored locally
+    Attributes,
+    /// The trial period of the subscription of a space
+    SubscriptionTrial,
+}
+
+impl Display for ExpiringItemKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExpiringItemKind::IdentityKey => "identity key",
+            ExpiringItemKind::Credential => "credential",
+            ExpiringItemKind::CredentialIssuerKey => "credential issuer key",
+            ExpiringItemKind::Attributes => "attributes",
+            ExpiringItemKind::SubscriptionTrial => "subscription trial",
+        })
+    }
+}
+
+/// An item which expires at a given time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpiringItem {
+    pub kind: ExpiringItemKind,
+    /// Name of the item in the local state: the name of an identity, of a credential,
+    /// or the identifier of a space
+    pub name: String,
+    /// Identity the item is about, if any
+    pub subject: Option<Identifier>,
+    /// Expiration time, in seconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+impl ExpiringItem {
+    pub fn new(kind: ExpiringItemKind, name: impl Into<String>, expires_at: u64) -> Self {
+        Self {
+            kind,
+            name: name.into(),
+            subject: None,
+            expires_at,
+        }
+    }
+
+    pub fn with_subject(mut self, subject: Option<Identifier>) -> Self {
+        self.subject = subject;
+        self
+    }
+}
+
+/// Items of the local state which are already expired, or which expire within a time window,
+/// sorted by expiration time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExpirationsReport {
+    /// Time of the report, in seconds since the Unix epoch
+    pub now: u64,
+    /// Length of the time window, in seconds
+    pub window: u64,
+    /// Items which are already expired
+    pub expired: Vec<ExpiringItem>,
+    /// Items which expire within the time window
+    pub expiring: Vec<ExpiringItem>,
+}
+
+impl ExpirationsReport {
+    /// Keep the items expiring before `now + window`
+    pub fn new(now: u64, window: Duration, items: Vec<ExpiringItem>) -> Self {
+        let window = window.as_secs();
+        let (mut expired, mut expiring): (Vec<_>, Vec<_>) = items
+            .into_iter()
+            .filter(|item| item.expires_at <= now.saturating_add(window))
+            .partition(|item| item.expires_at <= now);
+        expired.sort_by_key(|item| (item.expires_at, item.kind));
+        expiring.sort_by_key(|item| (item.expires_at, item.kind));
+        Self {
+            now,
+            window,
+            expired,
+            expiring,
+        }
+    }
+
+    /// Return true if nothing is expired or expiring
+    pub fn is_empty(&self) -> bool {
+        self.expired.is_empty() && self.expiring.is_empty()
+    }
+}
+
+impl CliState {
+    /// Return the credentials, identity keys, attributes and subscription trials of the
+    /// local state which are expired or will expire within `window`.
+    ///
+    /// The enrollment tokens and the leases are not part of the report since they are only
+    /// stored by the authority and the Orchestrator
+    pub async fn expirations(&self, window: Duration) -> Result<ExpirationsReport> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let mut items = vec![];
+
+        let repository = self.identities_repository().await?;
+        for identity in self.identities.list()? {
+            let identifier = identity.config().identifier();
+            let change_history = match repository.retrieve_identity(&identifier).await? {
+                Some(change_history) => change_history,
+                None => continue,
+            };
+            if let Some(change) = change_history.0.last() {
+                let data = ChangeData::get_data(&change.get_versioned_data()?)?;
+                items.push(
+                    ExpiringItem::new(
+                        ExpiringItemKind::IdentityKey,
+                        identity.name(),
+                        data.expires_at.0,
+                    )
+                    .with_subject(Some(identifier)),
+                );
+            }
+        }
+
+        for credential in self.credentials.list()? {
+            let CredentialAndPurposeKey {
+                credential: stored_credential,
+                purpose_key_attestation,
+            } = credential.config().credential()?;
+            let data = CredentialData::get_data(&stored_credential.get_versioned_data()?)?;
+            items.push(
+                ExpiringItem::new(
+                    ExpiringItemKind::Credential,
+                    credential.name(),
+                    data.expires_at.0,
+                )
+                .with_subject(data.subject),
+            );
+            let data = PurposeKeyAttestationData::get_data(
+                &purpose_key_attestation.get_versioned_data()?,
+            )?;
+            items.push(
+                ExpiringItem::new(
+                    ExpiringItemKind::CredentialIssuerKey,
+                    credential.name(),
+                    data.expires_at.0,
+                )
+                .with_subject(Some(data.subject)),
+            );
+        }
+
+        for (identifier, entry) in repository.list().await? {
+            if let Some(expires) = entry.expires() {
+                items.push(
+                    ExpiringItem::new(
+                        ExpiringItemKind::Attributes,
+                        identifier.to_string(),
+                        expires.0,
+                    )
+                    .with_subject(Some(identifier)),
+                );
+            }
+        }
+
+        for subscription in self.subscriptions.list()? {
+            let usage = &subscription.config().usage;
+            if let Some(trial_ends_at) = usage.trial_ends_at {
+                items.push(ExpiringItem::new(
+                    ExpiringItemKind::SubscriptionTrial,
+                    usage.space_id.clone(),
+                    trial_ends_at,
+                ));
+            }
+        }
+
+        Ok(ExpirationsReport::new(now, window, items))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expirations_report() {
+        let day = 24 * 3600;
+        let items = vec![
+            ExpiringItem::new(ExpiringItemKind::Credential, "late", 100 + 40 * day),
+            ExpiringItem::new(ExpiringItemKind::SubscriptionTrial, "space", 100 + 2 * day),
+            ExpiringItem::new(ExpiringItemKind::IdentityKey, "alice", 100 + day),
+            ExpiringItem::new(ExpiringItemKind::Attributes, "expired", 50),
+            ExpiringItem::new(ExpiringItemKind::Credential, "now", 100),
+        ];
+
+        let report = ExpirationsReport::new(100, Duration::from_secs(30 * day), items);
+        let names = |items: &[ExpiringItem]| -> Vec<String> {
+            items.iter().map(|i| i.name.clone()).collect()
+        };
+        assert_eq!(names(&report.expired), vec!["expired", "now"]);
+        assert_eq!(names(&report.expiring), vec!["alice", "space"]);
+        assert!(!report.is_empty());
+
+        let report = ExpirationsReport::new(100, Duration::from_secs(day), vec![]);
+        assert!(report.is_empty());
+    }
+}
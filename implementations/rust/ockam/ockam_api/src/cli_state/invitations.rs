@@ -0,0 +1,204 @@
+use super::{CliStateError, Result};
+use crate::cli_state::cached::now;
+use crate::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_core::env::get_env_with_default;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Default maximum number of invitations which can be issued in an hour
+pub const DEFAULT_MAX_INVITATIONS_PER_HOUR: u32 = 20;
+
+/// Invitations to access a portal issued from this machine, stored by invitation id
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvitationsState {
+    dir: PathBuf,
+}
+
+impl InvitationsState {
+    /// Return an error if the maximum number of invitations issued in the last hour is reached.
+    /// The maximum can be set with the `OCKAM_MAX_INVITATIONS_PER_HOUR` environment variable
+    pub fn check_rate_limit(&self) -> Result<()> {
+        let max = get_env_with_default(
+            "OCKAM_MAX_INVITATIONS_PER_HOUR",
+            DEFAULT_MAX_INVITATIONS_PER_HOUR,
+        )?;
+        let since = now().saturating_sub(3600);
+        let issued = self
+            .list()?
+            .iter()
+            .filter(|i| i.config().created_at >= since)
+            .count();
+        if issued >= max as usize {
+            return Err(CliStateError::InvalidOperation(format!(
+                "{issued} invitations were issued in the last hour, which is the maximum. Please try again later"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Mark an invitation as revoked
+    pub fn revoke(&self, id: impl AsRef<str>) -> Result<InvitationState> {
+        let mut state = self.get(id)?;
+        if state.config.revoked_at.is_none() {
+            state.config.revoked_at = Some(now());
+            state.persist()?;
+        }
+        Ok(state)
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct InvitationState {
+    path: PathBuf,
+    config: InvitationConfig,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct InvitationConfig {
+    pub id: String,
+    /// Name of the service the invitation gives access to
+    pub service: String,
+    /// Attributes given to the identities enrolled with the invitation
+    pub attributes: BTreeMap<String, String>,
+    /// Name of the project the invitation enrolls to
+    pub project: String,
+    /// Seconds since the Unix epoch
+    pub created_at: u64,
+    /// Seconds since the Unix epoch
+    pub expires_at: u64,
+    /// Seconds since the Unix epoch
+    #[serde(default)]
+    pub revoked_at: Option<u64>,
+}
+
+impl InvitationConfig {
+    pub fn status(&self) -> InvitationStatus {
+        if self.revoked_at.is_some() {
+            InvitationStatus::Revoked
+        } else if self.expires_at <= now() {
+            InvitationStatus::Expired
+        } else {
+            InvitationStatus::Active
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvitationStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+impl Display for InvitationStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            InvitationStatus::Active => "active",
+            InvitationStatus::Expired => "expired",
+            InvitationStatus::Revoked => "revoked",
+        })
+    }
+}
+
+mod traits {
+    use super::*;
+    use crate::cli_state::traits::*;
+    use ockam_core::async_trait;
+    use std::path::Path;
+
+    #[async_trait]
+    impl StateDirTrait for InvitationsState {
+        type Item = InvitationState;
+        const DEFAULT_FILENAME: &'static str = "invitation";
+        const DIR_NAME: &'static str = "invitations";
+        const HAS_DATA_DIR: bool = false;
+
+        fn new(root_path: &Path) -> Self {
+            Self {
+                dir: Self::build_dir(root_path),
+            }
+        }
+
+        fn dir(&self) -> &PathBuf {
+            &self.dir
+        }
+    }
+
+    #[async_trait]
+    impl StateItemTrait for InvitationState {
+        type Config = InvitationConfig;
+
+        fn new(path: PathBuf, config: Self::Config) -> Result<Self> {
+            let contents = serde_json::to_string(&config)?;
+            std::fs::write(&path, contents)?;
+            Ok(Self { path, config })
+        }
+
+        fn load(path: PathBuf) -> Result<Self> {
+            let contents = std::fs::read_to_string(&path)?;
+            let config = serde_json::from_str(&contents)?;
+            Ok(Self { path, config })
+        }
+
+        fn path(&self) -> &PathBuf {
+            &self.path
+        }
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::CliState;
+
+    fn invitation(id: &str, created_at: u64) -> InvitationConfig {
+        InvitationConfig {
+            id: id.to_string(),
+            service: "db".to_string(),
+            attributes: BTreeMap::from([("role".to_string(), "reader".to_string())]),
+            project: "default".to_string(),
+            created_at,
+            expires_at: created_at + 3600,
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_rate_limit_and_revocation() {
+        let state = CliState::test().unwrap();
+        // old invitations are not counted
+        state
+            .invitations
+            .create("old", invitation("old", now() - 2 * 3600))
+            .unwrap();
+        for i in 0..DEFAULT_MAX_INVITATIONS_PER_HOUR {
+            state.invitations.check_rate_limit().unwrap();
+            let id = format!("i{i}");
+            state
+                .invitations
+                .create(&id, invitation(&id, now()))
+                .unwrap();
+        }
+        assert!(state.invitations.check_rate_limit().is_err());
+
+        let old = state.invitations.get("old").unwrap();
+        assert_eq!(old.config().status(), InvitationStatus::Expired);
+        let revoked = state.invitations.revoke("i0").unwrap();
+        assert_eq!(revoked.config().status(), InvitationStatus::Revoked);
+        assert_eq!(
+            state.invitations.get("i0").unwrap().config().status(),
+            InvitationStatus::Revoked
+        );
+        assert_eq!(
+            state.invitations.get("i1").unwrap().config().status(),
+            InvitationStatus::Active
+        );
+    }
+}
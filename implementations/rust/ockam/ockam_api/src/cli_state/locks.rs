@@ -0,0 +1,291 @@
+//! Advisory lock on the `state.lock` file of a state directory, taken by the commands
+//! modifying the state.
+
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+use ockam_core::env::get_env_with_default;
+
+use crate::cli_state::CliStateError;
+
+use super::Result;
+
+/// Name of the file locked by the commands modifying a state
+pub const STATE_LOCK_FILE_NAME: &str = "state.lock";
+
+/// Maximum number of seconds to wait for the lock of a state held by another command
+pub const OCKAM_STATE_LOCK_TIMEOUT: &str = "OCKAM_STATE_LOCK_TIMEOUT";
+
+const DEFAULT_LOCK_TIMEOUT_SECS: u64 = 10;
+
+/// Time between two attempts to take a lock held by another command
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Exclusive lock on a state directory, released when dropped.
+///
+/// The lock is not re-entrant: the operations made while holding it take a reference to it,
+/// for example [`StateDirTrait::create_locked`](crate::cli_state::StateDirTrait::create_locked)
+#[derive(Debug)]
+pub struct StateLock {
+    file: File,
+}
+
+impl StateLock {
+    /// Lock the state stored in a root directory, waiting for the other commands
+    /// modifying it for at most `OCKAM_STATE_LOCK_TIMEOUT` seconds
+    pub fn acquire(root_path: &Path) -> Result<Self> {
+        Self::acquire_with_timeout(root_path, lock_timeout()?)
+    }
+
+    pub fn acquire_with_timeout(root_path: &Path, timeout: Duration) -> Result<Self> {
+        let path = root_path.join(STATE_LOCK_FILE_NAME);
+        let file = lock_file(root_path, &path, timeout, LockMode::Exclusive)?;
+        trace!(path = %path.display(), "Locked the state");
+        Ok(Self { file })
+    }
+
+    /// Lock the state stored in a root directory, without blocking the thread
+    /// while waiting for the other commands
+    pub async fn acquire_async(root_path: &Path) -> Result<Self> {
+        Self::acquire_async_with_timeout(root_path, lock_timeout()?).await
+    }
+
+    pub async fn acquire_async_with_timeout(root_path: &Path, timeout: Duration) -> Result<Self> {
+        let path = root_path.join(STATE_LOCK_FILE_NAME);
+        let file = lock_file_async(root_path, &path, timeout, LockMode::Exclusive).await?;
+        trace!(path = %path.display(), "Locked the state");
+        Ok(Self { file })
+    }
+
+    /// Lock the state stored in a root directory, or return `None` if another command
+    /// holds the lock
+    pub fn try_acquire(root_path: &Path) -> Result<Option<Self>> {
+        let path = root_path.join(STATE_LOCK_FILE_NAME);
+        let file = open_lock_file(root_path, &path)?;
+        if try_lock(&file, LockMode::Exclusive)? {
+            trace!(path = %path.display(), "Locked the state");
+            Ok(Some(Self { file }))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+/// Shared lock on a state directory, released when dropped.
+///
+/// It is held by the commands reading several items which must be consistent with each other,
+/// and only waits for the commands modifying the state
+#[derive(Debug)]
+pub struct SharedStateLock {
+    file: File,
+}
+
+impl SharedStateLock {
+    /// Lock the state stored in a root directory for reading, waiting for the commands
+    /// modifying it for at most `OCKAM_STATE_LOCK_TIMEOUT` seconds
+    pub fn acquire(root_path: &Path) -> Result<Self> {
+        Self::acquire_with_timeout(root_path, lock_timeout()?)
+    }
+
+    pub fn acquire_with_timeout(root_path: &Path, timeout: Duration) -> Result<Self> {
+        let path = root_path.join(STATE_LOCK_FILE_NAME);
+        let file = lock_file(root_path, &path, timeout, LockMode::Shared)?;
+        trace!(path = %path.display(), "Locked the state for reading");
+        Ok(Self { file })
+    }
+
+    /// Lock the state stored in a root directory for reading, without blocking the thread
+    /// while waiting for the commands modifying it
+    pub async fn acquire_async(root_path: &Path) -> Result<Self> {
+        let timeout = lock_timeout()?;
+        let path = root_path.join(STATE_LOCK_FILE_NAME);
+        let file = lock_file_async(root_path, &path, timeout, LockMode::Shared).await?;
+        trace!(path = %path.display(), "Locked the state for reading");
+        Ok(Self { file })
+    }
+}
+
+impl Drop for SharedStateLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum LockMode {
+    Exclusive,
+    Shared,
+}
+
+/// Maximum time to wait for the lock of a state held by another command
+fn lock_timeout() -> Result<Duration> {
+    let timeout = get_env_with_default(OCKAM_STATE_LOCK_TIMEOUT, DEFAULT_LOCK_TIMEOUT_SECS)?;
+    Ok(Duration::from_secs(timeout))
+}
+
+/// Open and lock the lock file of a state, retrying while another command holds it
+fn lock_file(root_path: &Path, path: &Path, timeout: Duration, mode: LockMode) -> Result<File> {
+    let file = open_lock_file(root_path, path)?;
+    let start = Instant::now();
+    while !try_lock(&file, mode)? {
+        if start.elapsed() >= timeout {
+            return Err(lock_timeout_error(path, timeout));
+        }
+        std::thread::sleep(LOCK_RETRY_INTERVAL);
+    }
+    Ok(file)
+}
+
+/// Open and lock the lock file of a state, yielding to the other tasks between two attempts
+async fn lock_file_async(
+    root_path: &Path,
+    path: &Path,
+    timeout: Duration,
+    mode: LockMode,
+) -> Result<File> {
+    let file = open_lock_file(root_path, path)?;
+    let start = Instant::now();
+    while !try_lock(&file, mode)? {
+        if start.elapsed() >= timeout {
+            return Err(lock_timeout_error(path, timeout));
+        }
+        tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+    }
+    Ok(file)
+}
+
+fn open_lock_file(root_path: &Path, path: &Path) -> Result<File> {
+    std::fs::create_dir_all(root_path)?;
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(path)?)
+}
+
+/// Try to lock a lock file once, return false if another command holds the lock
+fn try_lock(file: &File, mode: LockMode) -> Result<bool> {
+    let locked = match mode {
+        LockMode::Exclusive => file.try_lock_exclusive(),
+        LockMode::Shared => file.try_lock_shared(),
+    };
+    match locked {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == fs2::lock_contended_error().kind() => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn lock_timeout_error(path: &Path, timeout: Duration) -> CliStateError {
+    CliStateError::LockTimeout {
+        path: path.to_string_lossy().to_string(),
+        timeout: timeout.as_secs(),
+    }
+}
+
+/// Replace the content of a file at once, so that a concurrent command never reads a
+/// partially written file
+pub(crate) fn write_atomically(path: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+    let mut tmp_path = path.as_os_str().to_os_string();
+    tmp_path.push(".tmp");
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::CliState;
+
+    #[test]
+    fn test_state_lock() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let lock = StateLock::acquire(&dir)?;
+        // the lock is not re-entrant, even on the same thread
+        let timeout = StateLock::acquire_with_timeout(&dir, Duration::from_millis(100));
+        assert!(matches!(timeout, Err(CliStateError::LockTimeout { .. })));
+
+        let other = dir.clone();
+        let timeout = std::thread::spawn(move || {
+            StateLock::acquire_with_timeout(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap();
+        assert!(matches!(timeout, Err(CliStateError::LockTimeout { .. })));
+
+        drop(lock);
+        let other = dir.clone();
+        std::thread::spawn(move || {
+            StateLock::acquire_with_timeout(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_state_lock() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let lock = SharedStateLock::acquire(&dir)?;
+
+        // several commands can read the state at once
+        let other = dir.clone();
+        std::thread::spawn(move || {
+            SharedStateLock::acquire_with_timeout(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap()?;
+
+        // but the state can't be modified while it is read
+        let other = dir.clone();
+        let timeout = std::thread::spawn(move || {
+            StateLock::acquire_with_timeout(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap();
+        assert!(matches!(timeout, Err(CliStateError::LockTimeout { .. })));
+
+        drop(lock);
+        drop(StateLock::acquire(&dir)?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_acquire_async() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let lock = StateLock::acquire_async(&dir).await?;
+
+        // the wait doesn't block the thread running the other tasks
+        let waiting = tokio::spawn({
+            let dir = dir.clone();
+            async move { StateLock::acquire_async(&dir).await.map(|_| ()) }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+
+        drop(lock);
+        waiting.await.unwrap()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_acquire() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let lock = StateLock::try_acquire(&dir)?;
+        assert!(lock.is_some());
+        assert!(StateLock::try_acquire(&dir)?.is_none());
+
+        drop(lock);
+        assert!(StateLock::try_acquire(&dir)?.is_some());
+        Ok(())
+    }
+}
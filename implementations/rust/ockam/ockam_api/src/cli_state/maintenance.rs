@@ -0,0 +1,246 @@
+//! Maintenance of the databases of the local state.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use ockam::identity::storage::LmdbStorage;
+use ockam::identity::{Identifier, IdentityConstants};
+
+use crate::cli_state::backups::list_files;
+use crate::cli_state::cached::now;
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{CliState, StateDirTrait, StateItemTrait};
+
+use super::Result;
+
+/// Time during which the expired or revoked invitations are kept, so that they are still listed
+pub const STALE_INVITATIONS_RETENTION: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Extension of the files storing the compacted copy of a database
+const COMPACTED_EXTENSION: &str = "compacted";
+
+/// Result of the maintenance of the local state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StateMaintenanceReport {
+    /// Identities whose expired attributes were deleted
+    pub pruned_attributes: Vec<Identifier>,
+    /// Ids of the deleted invitations
+    pub pruned_invitations: Vec<String>,
+    pub databases: Vec<DatabaseMaintenanceReport>,
+}
+
+impl StateMaintenanceReport {
+    /// Return true if the integrity check of all the databases succeeded
+    pub fn is_healthy(&self) -> bool {
+        self.databases.iter().all(|d| d.error.is_none())
+    }
+
+    /// Number of bytes given back to the file system
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.databases
+            .iter()
+            .map(|d| d.size_before.saturating_sub(d.size_after))
+            .sum()
+    }
+}
+
+/// Result of the maintenance of a database of the local state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseMaintenanceReport {
+    pub path: PathBuf,
+    /// Number of entries read during the integrity check
+    pub entries: u64,
+    /// Size of the database file before the maintenance, in bytes
+    pub size_before: u64,
+    /// Size of the database file after the maintenance, in bytes
+    pub size_after: u64,
+    pub compacted: bool,
+    /// Error returned by the integrity check or by the compaction, if any
+    pub error: Option<String>,
+}
+
+impl CliState {
+    /// Prune the expired data of the local state, then check and compact its databases.
+    /// See the [module documentation](self) for the details
+    pub async fn maintain(&self) -> Result<StateMaintenanceReport> {
+        check_writable(&self.dir)?;
+        let mut report = StateMaintenanceReport {
+            pruned_attributes: self.prune_expired_attributes().await?,
+            pruned_invitations: self.prune_stale_invitations()?,
+            ..Default::default()
+        };
+
+        let compact = !self.nodes.list()?.iter().any(|node| node.is_running());
+        if !compact {
+            info!("Nodes are running, the databases of the state are not compacted");
+        }
+        let mut databases: Vec<PathBuf> = list_files(&self.dir)?
+            .into_iter()
+            .filter(|path| path.extension() == Some("lmdb".as_ref()))
+            .collect();
+        databases.sort();
+        for path in databases {
+            let database = maintain_database(&path, compact).await;
+            match &database.error {
+                Some(e) => warn!(path = %path.display(), %e, "The database can't be maintained"),
+                None => info!(
+                    path = %path.display(),
+                    entries = database.entries,
+                    reclaimed_bytes = database.size_before.saturating_sub(database.size_after),
+                    "Maintained a database"
+                ),
+            }
+            report.databases.push(database);
+        }
+        Ok(report)
+    }
+
+    /// Delete the attributes which are expired and return the identities they belonged to
+    async fn prune_expired_attributes(&self) -> Result<Vec<Identifier>> {
+        let storage = self.identities.identities_storage().await?;
+        let repository = self.identities_repository().await?;
+        let mut pruned = vec![];
+        for id in storage.keys(IdentityConstants::ATTRIBUTES_KEY).await? {
+            let identifier = Identifier::try_from(id)?;
+            // an expired entry is deleted when it is read
+            if repository.get_attributes(&identifier).await?.is_none() {
+                pruned.push(identifier);
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Delete the invitations which expired, or were revoked, before the retention period
+    fn prune_stale_invitations(&self) -> Result<Vec<String>> {
+        let before = now().saturating_sub(STALE_INVITATIONS_RETENTION.as_secs());
+        let mut pruned = vec![];
+        for invitation in self.invitations.list()? {
+            let config = invitation.config();
+            let ended_at = config.revoked_at.map_or(config.expires_at, |revoked_at| {
+                revoked_at.min(config.expires_at)
+            });
+            if ended_at < before {
+                self.invitations.delete(&config.id)?;
+                pruned.push(config.id.clone());
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+/// Check the integrity of a database and compact it if requested
+async fn maintain_database(path: &Path, compact: bool) -> DatabaseMaintenanceReport {
+    let size_before = file_size(path);
+    let mut report = DatabaseMaintenanceReport {
+        path: path.to_path_buf(),
+        entries: 0,
+        size_before,
+        size_after: size_before,
+        compacted: false,
+        error: None,
+    };
+    let result = async {
+        let storage = LmdbStorage::new(path).await?;
+        report.entries = storage.check_integrity().await?;
+        if compact {
+            let compacted = path.with_extension(COMPACTED_EXTENSION);
+            storage.backup_to(&compacted).await?;
+            drop(storage);
+            std::fs::rename(&compacted, path)?;
+            let _ = std::fs::remove_file(lock_file(&compacted));
+            report.compacted = true;
+        }
+        Ok::<(), super::CliStateError>(())
+    }
+    .await;
+    if let Err(e) = result {
+        report.error = Some(e.to_string());
+    }
+    report.size_after = file_size(path);
+    report
+}
+
+/// Lock file of a database opened with the `NO_SUB_DIR` flag
+fn lock_file(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_os_string();
+    lock.push("-lock");
+    PathBuf::from(lock)
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::InvitationConfig;
+    use ockam::identity::storage::Storage;
+    use ockam::identity::{AttributesEntry, TimestampInSeconds};
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_maintain() -> Result<()> {
+        let state = CliState::test()?;
+        let identifier: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let repository = state.identities_repository().await?;
+        repository
+            .put_attributes(
+                &identifier,
+                AttributesEntry::new(
+                    BTreeMap::new(),
+                    TimestampInSeconds(0),
+                    Some(TimestampInSeconds(1)),
+                    None,
+                ),
+            )
+            .await?;
+        drop(repository);
+
+        let stale = InvitationConfig {
+            id: "stale".to_string(),
+            service: "db".to_string(),
+            attributes: BTreeMap::new(),
+            project: "default".to_string(),
+            created_at: 0,
+            expires_at: 3600,
+            revoked_at: None,
+        };
+        let active = InvitationConfig {
+            id: "active".to_string(),
+            expires_at: now() + 3600,
+            ..stale.clone()
+        };
+        state.invitations.create("stale", stale)?;
+        state.invitations.create("active", active)?;
+
+        // add some free pages to a database
+        let storage = LmdbStorage::new(state.identities.identities_repository_path()?).await?;
+        for i in 0..100 {
+            storage.set("id", format!("key{i}"), vec![0; 1024]).await?;
+        }
+        for i in 0..100 {
+            storage.del("id", &format!("key{i}")).await?;
+        }
+        drop(storage);
+
+        let report = state.maintain().await?;
+        assert_eq!(report.pruned_attributes, vec![identifier.clone()]);
+        assert_eq!(report.pruned_invitations, vec!["stale".to_string()]);
+        assert!(report.is_healthy());
+        assert!(report.databases.iter().all(|d| d.compacted));
+        assert!(report.reclaimed_bytes() > 0);
+
+        assert!(state
+            .identities_repository()
+            .await?
+            .get_attributes(&identifier)
+            .await?
+            .is_none());
+        assert!(state.invitations.exists("active"));
+        assert!(!state.invitations.exists("stale"));
+        Ok(())
+    }
+}
@@ -1,30 +1,83 @@
+pub mod audit;
+pub mod backups;
+pub mod cached;
 pub mod credentials;
+pub mod defaults;
+pub mod display_names;
+pub mod doctor;
+pub mod ephemeral;
+pub mod events;
+pub mod expirations;
 pub mod identities;
+pub mod invitations;
+pub mod locks;
+pub mod maintenance;
+pub mod node_process;
 pub mod nodes;
+pub mod operations;
+pub mod profiles;
 pub mod projects;
+pub mod proxy;
+pub mod read_only;
+pub mod resolver;
+pub mod snapshot;
 pub mod spaces;
+pub mod subscriptions;
+pub mod tls;
 pub mod traits;
+pub mod transactions;
+pub mod trash;
 pub mod trust_contexts;
+pub mod trusted_peers;
 pub mod user_info;
 pub mod vaults;
 
+pub use crate::cli_state::audit::*;
+pub use crate::cli_state::backups::*;
+pub use crate::cli_state::cached::*;
 pub use crate::cli_state::credentials::*;
+pub use crate::cli_state::defaults::*;
+pub use crate::cli_state::display_names::*;
+pub use crate::cli_state::doctor::*;
+pub use crate::cli_state::ephemeral::*;
+pub use crate::cli_state::events::*;
+pub use crate::cli_state::expirations::*;
 pub use crate::cli_state::identities::*;
+pub use crate::cli_state::invitations::*;
+pub use crate::cli_state::locks::*;
+pub use crate::cli_state::maintenance::*;
+pub use crate::cli_state::node_process::*;
 pub use crate::cli_state::nodes::*;
+pub use crate::cli_state::operations::*;
+pub use crate::cli_state::profiles::*;
 pub use crate::cli_state::projects::*;
+pub use crate::cli_state::proxy::*;
+pub use crate::cli_state::read_only::*;
+pub use crate::cli_state::resolver::*;
+pub use crate::cli_state::snapshot::*;
 pub use crate::cli_state::spaces::*;
+pub use crate::cli_state::subscriptions::*;
+pub use crate::cli_state::tls::*;
 pub use crate::cli_state::traits::*;
+pub use crate::cli_state::transactions::*;
+pub use crate::cli_state::trash::*;
 pub use crate::cli_state::trust_contexts::*;
+pub use crate::cli_state::trusted_peers::*;
 use crate::cli_state::user_info::UsersInfoState;
 pub use crate::cli_state::vaults::*;
 use crate::config::cli::LegacyCliConfig;
+use crate::identity::credentials_clock;
 use miette::Diagnostic;
+use ockam::identity::storage::{
+    EncryptedStorage, IntegrityStorage, LmdbStorage, Storage, StorageKey,
+};
 use ockam::identity::Identifier;
-use ockam::identity::Identities;
 use ockam::identity::Vault;
+use ockam::identity::{Identities, IdentitiesBuilder, IdentitiesRepository, IdentitiesStorage};
 use ockam_core::compat::sync::Arc;
 use ockam_core::env::get_env_with_default;
 use ockam_node::Executor;
+use ockam_vault::VaultForSecureChannels;
 use rand::random;
 use std::path::{Path, PathBuf};
 use thiserror::Error;
@@ -72,6 +125,20 @@ pub enum CliStateError {
     #[diagnostic(code("OCK500"))]
     InvalidOperation(String),
 
+    #[error("The state at {0} is opened in read-only mode")]
+    #[diagnostic(
+        code("OCK403"),
+        help("Please close the tools inspecting this state before modifying it")
+    )]
+    ReadOnly(String),
+
+    #[error("The state is being modified by another command, the lock {path} was not released after {timeout}s")]
+    #[diagnostic(
+        code("OCK503"),
+        help("Please wait for the other ockam commands to finish, or increase OCKAM_STATE_LOCK_TIMEOUT")
+    )]
+    LockTimeout { path: String, timeout: u64 },
+
     #[error("Invalid configuration version '{0}'")]
     #[diagnostic(
         code("OCK500"),
@@ -109,25 +176,41 @@ pub struct CliState {
     pub credentials: CredentialsState,
     pub trust_contexts: TrustContextsState,
     pub users_info: UsersInfoState,
+    pub subscriptions: SubscriptionsState,
+    pub invitations: InvitationsState,
+    pub defaults: DefaultsState,
+    pub profiles: ProfilesState,
+    /// Name of the profile of this state
+    pub profile: String,
     pub dir: PathBuf,
+    /// Temporary directory of an ephemeral state
+    ephemeral: Option<EphemeralDir>,
+    /// Directory of a read-only state
+    read_only: Option<ReadOnlyDir>,
 }
 
 impl CliState {
-    /// Return an initialized CliState
+    /// Return an initialized CliState for a given profile, or for the profile set with
+    /// `OCKAM_PROFILE`, or for the active profile.
     /// There should only be one call to this function since it also performs a migration
     /// of configuration files if necessary
-    pub fn initialize() -> Result<Self> {
-        let dir = Self::default_dir()?;
-        std::fs::create_dir_all(dir.join("defaults"))?;
-        Executor::execute_future(Self::initialize_cli_state())?
+    pub fn initialize(profile: Option<&str>) -> Result<Self> {
+        Executor::execute_future(Self::initialize_cli_state(profile.map(|p| p.to_string())))?
+    }
+
+    async fn initialize_cli_state(profile: Option<String>) -> Result<CliState> {
+        let root_path = Self::default_dir()?;
+        let profile = ProfilesState::new(&root_path).resolve(profile.as_deref())?;
+        Self::initialize_profile_at(&root_path, &profile).await
     }
 
     /// Create a new CliState by initializing all of its components
     /// The calls to 'init(dir)' are loading each piece of configuration and possibly doing some
     /// configuration migration if necessary
-    async fn initialize_cli_state() -> Result<CliState> {
-        let default = Self::default_dir()?;
-        let dir = default.as_path();
+    async fn initialize_profile_at(root_path: &Path, profile: &str) -> Result<CliState> {
+        let profiles = ProfilesState::new(root_path);
+        let profile_dir = profiles.dir(profile)?;
+        let dir = profile_dir.as_path();
         let state = Self {
             vaults: VaultsState::init(dir).await?,
             identities: IdentitiesState::init(dir).await?,
@@ -137,39 +220,108 @@ impl CliState {
             credentials: CredentialsState::init(dir).await?,
             trust_contexts: TrustContextsState::init(dir).await?,
             users_info: UsersInfoState::init(dir).await?,
+            subscriptions: SubscriptionsState::init(dir).await?,
+            invitations: InvitationsState::init(dir).await?,
+            defaults: DefaultsState::init(dir)?,
+            profiles,
+            profile: profile.to_string(),
             dir: dir.to_path_buf(),
+            ephemeral: None,
+            read_only: None,
         };
+        // an interrupted transaction must not prevent the commands from using the state
+        if let Err(e) = state.recover_transactions() {
+            warn!(%e, dir = %state.dir.display(), "Failed to roll back the interrupted transactions");
+        }
         state.migrate()?;
         Ok(state)
     }
 
-    /// Reset all directories and return a new CliState
+    /// Reset all directories of the profile and return a new CliState
     pub async fn reset(&self) -> Result<CliState> {
         Self::delete_at(&self.dir)?;
-        Self::initialize_cli_state().await
+        let mut state =
+            Self::initialize_profile_at(self.profiles.root_path(), &self.profile).await?;
+        // an ephemeral state stays ephemeral, with new identities
+        if let Some(ephemeral) = &self.ephemeral {
+            state.identities = state
+                .identities
+                .with_memory_storage(MemoryStorage::default());
+            state.ephemeral = Some(ephemeral.clone());
+        }
+        Ok(state)
     }
 
-    pub fn backup_and_reset() -> Result<CliState> {
-        let dir = Self::default_dir()?;
+    /// Back up the state of a profile, or of the profile used by default, then reset it
+    pub fn backup_and_reset(profile: Option<&str>) -> Result<CliState> {
+        let profiles = ProfilesState::new(&Self::default_dir()?);
+        let profile = profiles.resolve(profile)?;
+        let dir = profiles.dir(&profile)?;
 
         // Reset backup directory
-        let backup_dir = Self::backup_default_dir()?;
+        let backup_dir = Self::backup_profile_dir(&profile)?;
         if backup_dir.exists() {
             let _ = std::fs::remove_dir_all(&backup_dir);
         }
         std::fs::create_dir_all(&backup_dir)?;
 
-        // Move state to backup directory
-        for entry in std::fs::read_dir(&dir)? {
-            let entry = entry?;
-            let from = entry.path();
-            let to = backup_dir.join(entry.file_name());
-            std::fs::rename(from, to)?;
-        }
+        // Snapshot state to backup directory, so that it can be restored later
+        Executor::execute_future(Self::backup_dir(dir.clone(), backup_dir.clone()))??;
+        backups::write_manifest(&backup_dir, &profile)?;
 
         // Reset state
         Self::delete_at(&dir)?;
-        Self::initialize()
+        Self::initialize(Some(&profile))
+    }
+
+    /// Copy the state directory to a backup directory while nodes may still be running.
+    /// The databases are copied from a consistent snapshot instead of being moved
+    /// from under open connections. The key protecting the identities is not copied: it is
+    /// wrapped by the default vault, or derived from a passphrase
+    pub async fn backup_to(&self, backup_dir: &Path) -> Result<()> {
+        // a key kept in a file by a previous version is wrapped by the default vault first
+        self.storage_key().await?;
+        std::fs::create_dir_all(backup_dir)?;
+        Self::backup_dir(self.dir.clone(), backup_dir.to_path_buf()).await
+    }
+
+    async fn backup_dir(from: PathBuf, to: PathBuf) -> Result<()> {
+        let root = to.clone();
+        let mut dirs = vec![(from, to)];
+        while let Some((from, to)) = dirs.pop() {
+            std::fs::create_dir_all(&to)?;
+            for entry in std::fs::read_dir(&from)? {
+                let entry = entry?;
+                let source = entry.path();
+                let destination = to.join(entry.file_name());
+                if root.starts_with(&source) {
+                    // the backups can be stored in the state directory
+                    continue;
+                } else if entry.file_type()?.is_dir() {
+                    dirs.push((source, destination));
+                } else if source.extension() == Some("lmdb".as_ref()) {
+                    // a database which can't be opened, for example because it is corrupted,
+                    // is copied as it is
+                    if Self::backup_lmdb(&source, &destination).await.is_err() {
+                        std::fs::copy(&source, &destination)?;
+                    }
+                } else if !source.to_string_lossy().ends_with(".lmdb-lock")
+                    && entry.file_name() != STATE_LOCK_FILE_NAME
+                    && entry.file_name() != STORAGE_KEY_FILE_NAME
+                {
+                    std::fs::copy(&source, &destination)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn backup_lmdb(source: &Path, destination: &Path) -> Result<()> {
+        // a database which can't be opened is copied at once instead of being retried
+        Ok(LmdbStorage::open(source)
+            .await?
+            .backup_to(destination)
+            .await?)
     }
 
     fn migrate(&self) -> Result<()> {
@@ -193,10 +345,11 @@ impl CliState {
             }
             std::fs::remove_file(legacy_config_path)?;
         }
-        Ok(())
+        self.record_schema_version()
     }
 
     pub fn delete_at(root_path: &PathBuf) -> Result<()> {
+        read_only::check_writable(root_path)?;
         // Delete nodes' state and processes, if possible
         let nodes_state = NodesState::new(root_path);
         let _ = nodes_state.list().map(|nodes| {
@@ -215,7 +368,10 @@ impl CliState {
             CredentialsState::new(root_path).dir(),
             TrustContextsState::new(root_path).dir(),
             UsersInfoState::new(root_path).dir(),
+            SubscriptionsState::new(root_path).dir(),
+            InvitationsState::new(root_path).dir(),
             &root_path.join("defaults"),
+            &Trash::new(root_path).dir(),
         ] {
             let _ = std::fs::remove_dir_all(dir);
         }
@@ -223,6 +379,11 @@ impl CliState {
         // Delete config files located at the root of the state directory
         let config_file = root_path.join("config.json");
         let _ = std::fs::remove_file(config_file);
+        let _ = std::fs::remove_file(DefaultsState::new(root_path).path());
+        let _ = std::fs::remove_file(StateEvents::new(root_path).path());
+        let _ = std::fs::remove_file(root_path.join(STATE_LOCK_FILE_NAME));
+        let _ = std::fs::remove_file(root_path.join(SCHEMA_VERSION_FILE_NAME));
+        // the audit log is kept, see the `audit` module
 
         // If the state directory is now empty, delete it
         let is_empty = std::fs::read_dir(root_path)
@@ -265,6 +426,17 @@ impl CliState {
 
     /// Returns the default backup directory for the CLI state.
     pub fn backup_default_dir() -> Result<PathBuf> {
+        Self::sibling_dir("bak")
+    }
+
+    /// Returns the directory storing the snapshots taken by [`CliState::backup`] for a profile,
+    /// when the backups are not stored in another directory.
+    pub fn snapshots_dir(profile: &str) -> Result<PathBuf> {
+        Ok(Self::sibling_dir("snapshots")?.join(profile))
+    }
+
+    /// Returns a directory next to the $OCKAM_HOME directory, named with a suffix
+    fn sibling_dir(suffix: &str) -> Result<PathBuf> {
         let dir = Self::default_dir()?;
         let dir_name =
             dir.file_name()
@@ -275,30 +447,49 @@ impl CliState {
         let parent = dir.parent().ok_or(CliStateError::InvalidOperation(
             "The $OCKAM_HOME directory does not a valid parent directory".to_string(),
         ))?;
-        Ok(parent.join(format!("{dir_name}.bak")))
+        Ok(parent.join(format!("{dir_name}.{suffix}")))
+    }
+
+    /// Returns the backup directory for the state of a profile.
+    pub fn backup_profile_dir(profile: &str) -> Result<PathBuf> {
+        let dir = Self::backup_default_dir()?;
+        if profile == DEFAULT_PROFILE_NAME {
+            Ok(dir)
+        } else {
+            Ok(dir.join(PROFILES_DIR_NAME).join(profile))
+        }
     }
 
-    /// Returns the directory where the default objects are stored.
-    fn defaults_dir(dir: &Path) -> Result<PathBuf> {
-        Ok(dir.join("defaults"))
+    /// Return the name of the default item of a given kind, for example `vault` or `project`
+    pub fn get_default(&self, kind: &str) -> Result<Option<String>> {
+        self.defaults.get(kind)
     }
 
-    pub async fn create_vault_state(&self, vault_name: Option<&str>) -> Result<VaultState> {
-        // Try to get the vault with the given name
-        let vault_state = if let Some(v) = vault_name {
-            self.vaults.get(v)?
-        }
-        // Or get the default
-        else if let Ok(v) = self.vaults.default() {
-            v
+    /// Set the default item of a given kind, once it has been checked that the item exists
+    pub fn set_default(&self, kind: &str, name: &str) -> Result<()> {
+        match kind {
+            VaultsState::DEFAULT_FILENAME => self.vaults.set_default(name),
+            IdentitiesState::DEFAULT_FILENAME => self.identities.set_default(name),
+            NodesState::DEFAULT_FILENAME => self.nodes.set_default(name),
+            SpacesState::DEFAULT_FILENAME => self.spaces.set_default(name),
+            ProjectsState::DEFAULT_FILENAME => self.projects.set_default(name),
+            TrustContextsState::DEFAULT_FILENAME => self.trust_contexts.set_default(name),
+            UsersInfoState::DEFAULT_FILENAME => self.users_info.set_default(name),
+            CredentialsState::DEFAULT_FILENAME => self.credentials.set_default(name),
+            SubscriptionsState::DEFAULT_FILENAME => self.subscriptions.set_default(name),
+            InvitationsState::DEFAULT_FILENAME => self.invitations.set_default(name),
+            _ => Err(CliStateError::InvalidOperation(format!(
+                "There is no default item of kind '{kind}'"
+            ))),
         }
-        // Or create a new one with a random name
-        else {
-            let n = random_name();
-            let c = VaultConfig::default();
-            self.vaults.create_async(&n, c).await?
-        };
-        Ok(vault_state)
+    }
+
+    /// Return the vault with the given name, or the default vault, or create a new vault
+    /// with a random name
+    pub async fn create_vault_state(&self, vault_name: Option<&str>) -> Result<VaultState> {
+        let mut transaction = self.transaction().await?;
+        let vault_state = transaction.create_vault_state(vault_name).await;
+        transaction.finish(vault_state)
     }
 
     pub async fn create_identity_state(
@@ -306,35 +497,91 @@ impl CliState {
         identifier: &Identifier,
         identity_name: Option<&str>,
     ) -> Result<IdentityState> {
-        if let Ok(identity) = self.identities.get_or_default(identity_name) {
-            Ok(identity)
-        } else {
-            self.make_identity_state(identifier, identity_name).await
-        }
+        let mut transaction = self.transaction().await?;
+        let identity_state = transaction
+            .create_identity_state(identifier, identity_name)
+            .await;
+        transaction.finish(identity_state)
     }
 
     async fn make_identity_state(
         &self,
+        lock: &StateLock,
         identifier: &Identifier,
         name: Option<&str>,
     ) -> Result<IdentityState> {
         let identity_config = IdentityConfig::new(identifier).await;
         let identity_name = name.map(|x| x.to_string()).unwrap_or_else(random_name);
-        self.identities.create(identity_name, identity_config)
+        self.identities
+            .create_locked(lock, identity_name, identity_config)
     }
 
     pub async fn get_identities(&self, vault: Vault) -> Result<Arc<Identities>> {
-        Ok(Identities::builder()
-            .with_vault(vault)
-            .with_identities_repository(self.identities.identities_repository().await?)
-            .build())
+        Ok(self.identities_builder(vault).await?.build())
     }
 
     pub async fn default_identities(&self) -> Result<Arc<Identities>> {
-        Ok(Identities::builder()
-            .with_vault(self.vaults.default()?.vault().await?)
-            .with_identities_repository(self.identities.identities_repository().await?)
-            .build())
+        let vault = self.vaults.default()?.vault().await?;
+        Ok(self.identities_builder(vault).await?.build())
+    }
+
+    /// Return a builder for identities using the repository and the verification cache
+    /// shared by all identities
+    pub async fn identities_builder(&self, vault: Vault) -> Result<IdentitiesBuilder> {
+        let builder = Identities::builder()
+            .with_vault(vault)
+            .with_identities_repository(self.identities_repository().await?)
+            .with_credentials_clock(credentials_clock()?);
+        Ok(match self.verification_cache().await? {
+            Some(storage) => builder.with_verification_cache(storage),
+            None => builder,
+        })
+    }
+
+    /// Return the repository shared by all identities.
+    /// Its content is encrypted at rest, with a distinct key for each category of data, and
+    /// the change histories and purpose keys are integrity protected.
+    /// The keys are derived from the [storage key](IdentitiesState::storage_key) of the state
+    pub async fn identities_repository(&self) -> Result<Arc<dyn IdentitiesRepository>> {
+        let storage = self.identities.identities_storage().await?;
+        let key = self.storage_key().await?;
+        let storage = EncryptedStorage::create(storage, &key).await?;
+        Ok(Arc::new(IdentitiesStorage::new(storage)))
+    }
+
+    /// Return the storage of the signatures verified by the nodes of this host, so that the
+    /// change histories and credentials presented to several nodes are only verified once.
+    /// The entries are kept in the storage shared by all identities, with their integrity
+    /// protected by the [storage key](IdentitiesState::storage_key) of the state
+    pub async fn verification_cache(&self) -> Result<Option<Arc<dyn Storage>>> {
+        if IdentitiesState::database_url()?.is_some() {
+            return Ok(None);
+        }
+        let storage = self.identities.identities_storage().await?;
+        let key = self.storage_key().await?;
+        Ok(Some(IntegrityStorage::create(storage, &key).await?))
+    }
+
+    /// Return the key protecting the storage shared by all identities, wrapped by the default
+    /// vault unless the storage is protected by a passphrase
+    async fn storage_key(&self) -> Result<StorageKey> {
+        self.identities.storage_key(self.storage_key_vaults()).await
+    }
+
+    /// Return the vaults which may wrap the storage key, starting with the default vault, which
+    /// is created if there is none
+    async fn storage_key_vaults(&self) -> Result<Vec<Arc<dyn VaultForSecureChannels>>> {
+        let default_vault = match self.vaults.default() {
+            Ok(default_vault) => default_vault,
+            Err(_) => self.create_vault_state(None).await?,
+        };
+        let mut vaults = vec![default_vault.vault().await?.secure_channel_vault];
+        for vault_state in self.vaults.list()? {
+            if vault_state.name() != default_vault.name() {
+                vaults.push(vault_state.vault().await?.secure_channel_vault);
+            }
+        }
+        Ok(vaults)
     }
 
     /// Return true if the user is enrolled.
@@ -371,25 +618,11 @@ impl CliState {
     #[cfg(test)]
     /// Initialize CliState at the given directory
     async fn initialize_at(dir: &Path) -> Result<Self> {
-        std::fs::create_dir_all(dir.join("defaults"))?;
-        let state = Self {
-            vaults: VaultsState::init(dir).await?,
-            identities: IdentitiesState::init(dir).await?,
-            nodes: NodesState::init(dir).await?,
-            spaces: SpacesState::init(dir).await?,
-            projects: ProjectsState::init(dir).await?,
-            credentials: CredentialsState::init(dir).await?,
-            trust_contexts: TrustContextsState::init(dir).await?,
-            users_info: UsersInfoState::init(dir).await?,
-            dir: dir.to_path_buf(),
-        };
-        state.migrate()?;
-        Ok(state)
+        Self::initialize_profile_at(dir, DEFAULT_PROFILE_NAME).await
     }
 
     /// Create a new CliState (but do not run migrations)
     fn new(dir: &Path) -> Result<Self> {
-        std::fs::create_dir_all(dir.join("defaults"))?;
         Ok(Self {
             vaults: VaultsState::load(dir)?,
             identities: IdentitiesState::load(dir)?,
@@ -399,7 +632,14 @@ impl CliState {
             credentials: CredentialsState::load(dir)?,
             trust_contexts: TrustContextsState::load(dir)?,
             users_info: UsersInfoState::load(dir)?,
+            subscriptions: SubscriptionsState::load(dir)?,
+            invitations: InvitationsState::load(dir)?,
+            defaults: DefaultsState::new(dir),
+            profiles: ProfilesState::new(dir),
+            profile: DEFAULT_PROFILE_NAME.to_string(),
             dir: dir.to_path_buf(),
+            ephemeral: None,
+            read_only: None,
         })
     }
 
@@ -434,6 +674,7 @@ fn file_stem(path: &Path) -> Result<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cli_state::backups::list_files;
     use crate::cloud::enroll::auth0::UserInfo;
     use crate::config::cli::TrustContextConfig;
     use crate::config::lookup::{ConfigLookup, LookupValue, ProjectLookup, SpaceLookup};
@@ -491,6 +732,123 @@ mod tests {
         assert_eq!(identity1.path(), identity2.path());
     }
 
+    #[tokio::test]
+    async fn test_delete_waits_for_the_state_lock() -> Result<()> {
+        let state = CliState::test()?;
+        init_node_state(&state, "node", None, Some("alice"))
+            .await
+            .unwrap();
+        let vault = state.vaults.default()?.name().to_string();
+
+        // another command holds the lock
+        let lock = StateLock::acquire(&state.dir)?;
+        let deletes = vec![
+            std::thread::spawn({
+                let state = state.clone();
+                let vault = vault.clone();
+                move || state.vaults.delete(vault)
+            }),
+            std::thread::spawn({
+                let state = state.clone();
+                move || state.identities.delete("alice")
+            }),
+            std::thread::spawn({
+                let state = state.clone();
+                move || state.nodes.delete("node")
+            }),
+        ];
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(state.vaults.exists(&vault));
+        assert!(state.identities.exists("alice"));
+        assert!(state.nodes.exists("node"));
+        assert!(state.nodes.is_default("node")?);
+
+        drop(lock);
+        for delete in deletes {
+            delete.join().unwrap()?;
+        }
+        assert!(!state.vaults.exists(&vault));
+        assert!(!state.identities.exists("alice"));
+        assert!(!state.nodes.exists("node"));
+        assert!(state.nodes.default().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_to_and_restore() -> Result<()> {
+        let state = CliState::test()?;
+        init_node_state(&state, "node", None, Some("alice"))
+            .await
+            .map_err(|e| CliStateError::InvalidOperation(e.to_string()))?;
+        let vaults = state.vaults.list()?;
+        let identities = state.identities.list()?;
+        let nodes = state.nodes.list()?;
+        let identities_path = state.identities.identities_repository_path()?;
+        let entries = LmdbStorage::new(&identities_path)
+            .await?
+            .check_integrity()
+            .await?;
+        assert!(entries > 0);
+
+        let backup_dir = tempfile::tempdir()?;
+        state.backup_to(backup_dir.path()).await?;
+
+        // restore the backup in place of the deleted state
+        CliState::delete_at(&state.dir)?;
+        CliState::backup_dir(backup_dir.path().to_path_buf(), state.dir.clone()).await?;
+        let restored = CliState::new(&state.dir)?;
+
+        assert_eq!(restored.vaults.list()?, vaults);
+        assert_eq!(restored.identities.list()?, identities);
+        assert_eq!(restored.nodes.list()?, nodes);
+        // the identities are restored from the backup of their database
+        let restored_entries = LmdbStorage::new(&identities_path)
+            .await?
+            .check_integrity()
+            .await?;
+        assert_eq!(restored_entries, entries);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_corrupted_database() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let source = dir.path().join("corrupted.lmdb");
+        std::fs::write(&source, b"not a database")?;
+
+        // the database is copied as it is, without waiting for it to be openable
+        let destination = dir.path().join("backup").join("corrupted.lmdb");
+        let started_at = std::time::Instant::now();
+        CliState::backup_dir(dir.path().to_path_buf(), dir.path().join("backup")).await?;
+        assert!(started_at.elapsed() < std::time::Duration::from_secs(1));
+        assert_eq!(std::fs::read(destination)?, b"not a database");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_without_storage_key() -> Result<()> {
+        let state = CliState::test()?;
+        let key_path = state
+            .identities
+            .dir()
+            .join(DATA_DIR_NAME)
+            .join(STORAGE_KEY_FILE_NAME);
+        let key = StorageKey::read_or_create(&key_path)?;
+        let storage = state.identities.identities_storage().await?;
+        EncryptedStorage::create(storage, &key).await?;
+
+        // the key kept in a file by a previous version is wrapped by the default vault
+        // instead of being copied
+        let backup_dir = tempfile::tempdir()?;
+        state.backup_to(backup_dir.path()).await?;
+        assert!(!key_path.exists());
+        assert!(list_files(backup_dir.path())?
+            .iter()
+            .all(|path| path.file_name() != Some(STORAGE_KEY_FILE_NAME.as_ref())));
+        assert_eq!(state.storage_key().await?, key);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn migrate_legacy_cli_config() {
         // Before this migration, there was a `config.json` file in the root $OCKAM_HOME directory
@@ -572,7 +930,7 @@ mod tests {
             let vault: Vault = vault_state.get().await.unwrap();
             let identities = Identities::builder()
                 .with_vault(vault)
-                .with_identities_repository(sut.identities.identities_repository().await?)
+                .with_identities_repository(sut.identities_repository().await?)
                 .build();
             let identity = identities
                 .identities_creation()
@@ -615,9 +973,15 @@ mod tests {
                 id,
             };
 
-            let state = sut.spaces.create(&name, config).unwrap();
+            let state = sut.spaces.create(&name, config.clone()).unwrap();
             let got = sut.spaces.get(&name).unwrap();
             assert_eq!(got, state);
+            assert!(got.fetched_at().is_none());
+
+            let state = sut.spaces.refresh(&name, config).unwrap();
+            let got = sut.spaces.get_cached(&name).unwrap();
+            assert_eq!(got.fetched_at, state.fetched_at());
+            assert!(!got.is_stale(CACHE_MAX_AGE));
 
             name
         };
@@ -681,14 +1045,11 @@ mod tests {
             "users_info".to_string(),
             format!("users_info/{user_info_email}.json"),
             "credentials".to_string(),
-            "defaults".to_string(),
-            "defaults/vault".to_string(),
-            "defaults/identity".to_string(),
-            "defaults/node".to_string(),
-            "defaults/space".to_string(),
-            "defaults/project".to_string(),
-            "defaults/trust_context".to_string(),
-            "defaults/user_info".to_string(),
+            "subscriptions".to_string(),
+            "invitations".to_string(),
+            DEFAULTS_FILE_NAME.to_string(),
+            EVENTS_FILE_NAME.to_string(),
+            AUDIT_LOG_FILE_NAME.to_string(),
         ];
         expected_entries.sort();
         let mut found_entries = vec![];
@@ -733,7 +1094,7 @@ mod tests {
                                 if !file_name.ends_with("-lock") {
                                     found_entries
                                         .push(format!("{dir_name}/{entry_name}/{file_name}"));
-                                    assert_eq!(file_name, format!("authenticated_storage.lmdb"));
+                                    assert_eq!(file_name, "authenticated_storage.lmdb");
                                 }
                             })
                         } else {
@@ -753,8 +1114,13 @@ mod tests {
                         found_entries.push(format!("{dir_name}/{file_name}"));
                     });
                 }
-                "defaults" | "spaces" | "projects" | "credentials" | "trust_contexts"
-                | "users_info" => {
+                DEFAULTS_FILE_NAME | EVENTS_FILE_NAME | AUDIT_LOG_FILE_NAME => {
+                    assert!(entry.path().is_file());
+                    found_entries.push(dir_name.clone());
+                }
+                STATE_LOCK_FILE_NAME => assert!(entry.path().is_file()),
+                "spaces" | "projects" | "credentials" | "trust_contexts" | "users_info"
+                | "subscriptions" | "invitations" => {
                     assert!(entry.path().is_dir());
                     found_entries.push(dir_name.clone());
                     entry.path().read_dir().unwrap().for_each(|entry| {
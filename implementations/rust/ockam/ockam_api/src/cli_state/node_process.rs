@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use ockam_core::errcode::{Kind, Origin};
+
+/// Environment, working directory and resource limits of the process of a background node.
+///
+/// They are recorded with the setup of the node, so that they are applied again each time
+/// the node is restarted. The environment variables are stored in clear text in the node
+/// directory
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Eq, PartialEq)]
+pub struct NodeProcessConfig {
+    /// Environment variables added to the environment of the process
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Working directory of the process. The relative paths given to the node, for example
+    /// the path of a launch configuration, are resolved from this directory
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    /// Resource limits set on the process before it starts
+    #[serde(default)]
+    pub limits: Vec<ProcessLimit>,
+}
+
+impl NodeProcessConfig {
+    pub fn with_env(mut self, env: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.env.extend(env);
+        self
+    }
+
+    pub fn with_working_dir(mut self, working_dir: Option<PathBuf>) -> Self {
+        self.working_dir = working_dir;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Vec<ProcessLimit>) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Return true if the process is started with the defaults of the `ockam` command
+    pub fn is_empty(&self) -> bool {
+        self.env.is_empty() && self.working_dir.is_none() && self.limits.is_empty()
+    }
+}
+
+/// Resources which can be limited, named like the options of `ulimit`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessResource {
+    /// Maximum number of open file descriptors
+    Nofile,
+    /// Maximum number of processes of the user
+    Nproc,
+    /// Maximum size of a core dump, in bytes
+    Core,
+    /// Maximum size of a file written by the process, in bytes
+    Fsize,
+    /// Maximum size of the stack, in bytes
+    Stack,
+    /// Maximum CPU time, in seconds
+    Cpu,
+}
+
+impl Display for ProcessResource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ProcessResource::Nofile => "nofile",
+            ProcessResource::Nproc => "nproc",
+            ProcessResource::Core => "core",
+            ProcessResource::Fsize => "fsize",
+            ProcessResource::Stack => "stack",
+            ProcessResource::Cpu => "cpu",
+        })
+    }
+}
+
+impl FromStr for ProcessResource {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "nofile" => Ok(ProcessResource::Nofile),
+            "nproc" => Ok(ProcessResource::Nproc),
+            "core" => Ok(ProcessResource::Core),
+            "fsize" => Ok(ProcessResource::Fsize),
+            "stack" => Ok(ProcessResource::Stack),
+            "cpu" => Ok(ProcessResource::Cpu),
+            _ => Err(invalid_limit(format!(
+                "unknown resource {s}, expected nofile, nproc, core, fsize, stack or cpu"
+            ))),
+        }
+    }
+}
+
+/// Limit of a resource, parsed from `RESOURCE=SOFT` or `RESOURCE=SOFT:HARD`, for example
+/// `nofile=4096:8192`. A value can be `unlimited`, which is stored as `u64::MAX`.
+/// The current hard limit is kept when it is not specified
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ProcessLimit {
+    pub resource: ProcessResource,
+    pub soft: u64,
+    pub hard: Option<u64>,
+}
+
+impl Display for ProcessLimit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let value = |v: u64| {
+            if v == u64::MAX {
+                "unlimited".to_string()
+            } else {
+                v.to_string()
+            }
+        };
+        write!(f, "{}={}", self.resource, value(self.soft))?;
+        if let Some(hard) = self.hard {
+            write!(f, ":{}", value(hard))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ProcessLimit {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (resource, values) = s.split_once('=').ok_or_else(|| {
+            invalid_limit(format!(
+                "invalid resource limit {s}, expected RESOURCE=SOFT[:HARD]"
+            ))
+        })?;
+        let parse = |v: &str| -> Result<u64, Self::Err> {
+            match v {
+                "unlimited" => Ok(u64::MAX),
+                _ => v
+                    .parse()
+                    .map_err(|_| invalid_limit(format!("invalid resource limit value {v}"))),
+            }
+        };
+        let (soft, hard) = match values.split_once(':') {
+            Some((soft, hard)) => (parse(soft)?, Some(parse(hard)?)),
+            None => (parse(values)?, None),
+        };
+        if hard.map_or(false, |hard| soft > hard) {
+            return Err(invalid_limit(format!(
+                "invalid resource limit {s}, the soft limit is greater than the hard limit"
+            )));
+        }
+        Ok(ProcessLimit {
+            resource: resource.parse()?,
+            soft,
+            hard,
+        })
+    }
+}
+
+fn invalid_limit(message: String) -> ockam_core::Error {
+    ockam_core::Error::new(Origin::Node, Kind::Invalid, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_process_limit() {
+        let limit: ProcessLimit = "nofile=4096:8192".parse().unwrap();
+        assert_eq!(
+            limit,
+            ProcessLimit {
+                resource: ProcessResource::Nofile,
+                soft: 4096,
+                hard: Some(8192)
+            }
+        );
+        assert_eq!(limit.to_string(), "nofile=4096:8192");
+
+        let limit: ProcessLimit = "core=unlimited".parse().unwrap();
+        assert_eq!(limit.soft, u64::MAX);
+        assert_eq!(limit.hard, None);
+        assert_eq!(limit.to_string(), "core=unlimited");
+
+        assert!("nofile".parse::<ProcessLimit>().is_err());
+        assert!("memory=10".parse::<ProcessLimit>().is_err());
+        assert!("nofile=8192:4096".parse::<ProcessLimit>().is_err());
+        assert!("nofile=lots".parse::<ProcessLimit>().is_err());
+    }
+
+    #[test]
+    fn test_process_config_defaults() {
+        let config: NodeProcessConfig = serde_json::from_str("{}").unwrap();
+        assert!(config.is_empty());
+        let config = config.with_env([("HOOK".to_string(), "1".to_string())]);
+        assert!(!config.is_empty());
+    }
+}
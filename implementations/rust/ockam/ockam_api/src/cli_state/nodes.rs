@@ -1,22 +1,30 @@
 use super::Result;
+use crate::cli_state::read_only::check_writable;
 use crate::cli_state::{
-    CliState, CliStateError, IdentityConfig, IdentityState, ProjectConfig, ProjectConfigCompact,
-    StateDirTrait, StateItemTrait, VaultState,
+    CliState, CliStateError, IdentityConfig, IdentityState, NodeProcessConfig, ProjectConfig,
+    ProjectConfigCompact, Resolver, StateDirTrait, StateEvent, StateItemTrait, StateLock,
+    VaultState, OCKAM_PROJECT,
 };
 use crate::config::lookup::ProjectLookup;
+use crate::fleet::HeartbeatConfig;
 use crate::nodes::models::transport::CreateTransportJson;
+use crate::ping::HealthCheckConfig;
+use crate::resource_profile::ResourceProfile;
 use backwards_compatibility::*;
 use miette::{IntoDiagnostic, WrapErr};
-use nix::errno::Errno;
-use ockam::identity::Identifier;
+use ockam::identity::storage::StorageKey;
 use ockam::identity::Vault;
+use ockam::identity::{Identifier, QuotaLimits};
 use ockam::LmdbStorage;
 use ockam_core::compat::collections::HashSet;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use sysinfo::{Pid, ProcessExt, ProcessStatus, System, SystemExt};
+use std::time::Duration;
+
+mod platform;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct NodesState {
@@ -26,15 +34,35 @@ pub struct NodesState {
 impl NodesState {
     pub fn stdout_logs(&self, name: &str) -> Result<PathBuf> {
         let dir = self.path(name);
+        check_writable(&dir)?;
         std::fs::create_dir_all(&dir)?;
         Ok(NodePaths::new(&dir).stdout())
     }
 
     pub fn delete_sigkill(&self, name: &str, sigkill: bool) -> Result<()> {
-        self._delete(name, sigkill)
+        let lock = self.lock()?;
+        self._delete(&lock, name, sigkill, true)
+    }
+
+    /// Delete a node without keeping it in the trash, for example a node which was only
+    /// created to run a command
+    pub fn delete_for_good(&self, name: &str) -> Result<()> {
+        let lock = self.lock()?;
+        self.delete_for_good_locked(&lock, name)
+    }
+
+    /// Same as [`NodesState::delete_for_good`], while holding the lock of the state
+    pub fn delete_for_good_locked(&self, lock: &StateLock, name: &str) -> Result<()> {
+        self._delete(lock, name, false, false)
     }
 
-    fn _delete(&self, name: impl AsRef<str>, sigkill: bool) -> Result<()> {
+    fn _delete(
+        &self,
+        lock: &StateLock,
+        name: impl AsRef<str>,
+        sigkill: bool,
+        keep: bool,
+    ) -> Result<()> {
         // If doesn't exist do nothing
         if !self.exists(&name) {
             return Ok(());
@@ -42,17 +70,27 @@ impl NodesState {
         let node = self.get(&name)?;
         // Set default to another node if it's the default
         if self.is_default(&name)? {
-            // Remove link if it exists
-            let _ = std::fs::remove_file(self.default_path()?);
+            self.remove_default_locked(lock)?;
             for node in self.list()? {
-                if node.name() != name.as_ref() && self.set_default(node.name()).is_ok() {
+                if node.name() != name.as_ref()
+                    && self.set_default_locked(lock, node.name()).is_ok()
+                {
                     debug!(name=%node.name(), "set default node");
                     break;
                 }
             }
         }
-        // Remove node directory
-        node.delete_sigkill(sigkill)?;
+        // Stop the node and remove its directory
+        if keep {
+            node.kill_process(sigkill)?;
+            self.discard(&name, &node)?;
+        } else {
+            node.delete_sigkill(sigkill)?;
+        }
+        self.events()
+            .emit(StateEvent::deleted(Self::default_filename(), name.as_ref()));
+        self.audit()
+            .deleted(Self::default_filename(), name.as_ref(), node.config());
         Ok(())
     }
 }
@@ -67,6 +105,7 @@ pub struct NodeState {
 
 impl NodeState {
     fn _delete(&self, sikgill: bool) -> Result<()> {
+        check_writable(&self.path)?;
         self.kill_process(sikgill)?;
         std::fs::remove_dir_all(&self.path)?;
         let _ = std::fs::remove_dir(&self.path); // Make sure the dir is gone
@@ -79,29 +118,17 @@ impl NodeState {
     }
 
     pub fn kill_process(&self, sigkill: bool) -> Result<()> {
+        check_writable(&self.path)?;
         if let Some(pid) = self.pid()? {
-            nix::sys::signal::kill(
-                nix::unistd::Pid::from_raw(pid),
-                if sigkill {
-                    nix::sys::signal::Signal::SIGKILL
-                } else {
-                    nix::sys::signal::Signal::SIGTERM
-                },
-            )
-            .or_else(|e| {
-                if e == Errno::ESRCH {
-                    tracing::warn!(node = %self.name(), %pid, "No such process");
-                    Ok(())
-                } else {
-                    Err(e)
-                }
-            })
-            .map_err(|e| {
+            let stopped = platform::stop_process(pid, sigkill).map_err(|e| {
                 CliStateError::Io(std::io::Error::new(
                     std::io::ErrorKind::Other,
                     format!("failed to stop PID `{pid}` with error `{e}`"),
                 ))
             })?;
+            if !stopped {
+                tracing::warn!(node = %self.name(), %pid, "No such process");
+            }
             std::fs::remove_file(self.paths.pid())?;
         }
         info!(name = %self.name(), "node process killed");
@@ -109,6 +136,7 @@ impl NodeState {
     }
 
     pub fn set_setup(&self, setup: &NodeSetupConfig) -> Result<()> {
+        check_writable(&self.path)?;
         let contents = serde_json::to_string(setup)?;
         std::fs::write(self.paths.setup(), contents)?;
         info!(name = %self.name(), "setup config updated");
@@ -128,22 +156,54 @@ impl NodeState {
     }
 
     pub fn set_pid(&self, pid: i32) -> Result<()> {
+        check_writable(&self.path)?;
         std::fs::write(self.paths.pid(), pid.to_string())?;
         Ok(())
     }
 
+    /// Record the current process as the process of this node, when the node runs in it.
+    /// On Windows the process is also put in a job, which is terminated to stop the node
+    pub fn set_current_process(&self) -> Result<()> {
+        if let Err(e) = platform::track_current_process() {
+            warn!(name = %self.name(), "the node process can't be put in a job: {e}");
+        }
+        self.set_pid(std::process::id() as i32)
+    }
+
+    /// Create a new token for the clients of the node API, readable only by the current user.
+    /// A token is created each time the node starts, replacing the previous one
+    pub fn create_api_token(&self) -> Result<String> {
+        check_writable(&self.path)?;
+        let token = hex::encode(rand::random::<[u8; 32]>());
+        let path = self.paths.api_token();
+        // the file is created again to get its permissions
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&path)?.write_all(token.as_bytes())?;
+        Ok(token)
+    }
+
+    /// Return the token authenticating the clients of the node API, if the node has one
+    pub fn api_token(&self) -> Result<Option<String>> {
+        let path = self.paths.api_token();
+        if path.exists() {
+            Ok(Some(std::fs::read_to_string(path)?.trim().to_string()))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub fn is_running(&self) -> bool {
         if let Ok(Some(pid)) = self.pid() {
-            let mut sys = System::new();
-            sys.refresh_processes();
-            if let Some(p) = sys.process(Pid::from(pid as usize)) {
-                // Under certain circumstances the process can be in a state where it's not running
-                // and we are unable to kill it. For example, `kill -9` a process created by
-                // `node create` in a Docker environment will result in a zombie process.
-                !matches!(p.status(), ProcessStatus::Dead | ProcessStatus::Zombie)
-            } else {
-                false
-            }
+            platform::is_process_running(pid)
         } else {
             false
         }
@@ -157,8 +217,72 @@ impl NodeState {
         self.paths.stderr()
     }
 
+    /// Path of the last configuration update applied to this node
+    pub fn config_update(&self) -> PathBuf {
+        self.paths.config_update()
+    }
+
+    /// Directory of the files written by the portal taps of this node
+    pub fn taps_dir(&self) -> PathBuf {
+        self.paths.taps()
+    }
+
     pub async fn policies_storage(&self) -> Result<LmdbStorage> {
-        Ok(LmdbStorage::new(self.paths.policies_storage()).await?)
+        self.storage(self.paths.policies_storage()).await
+    }
+
+    /// Return true if the policies storage of this node has already been created
+    pub fn has_policies_storage(&self) -> bool {
+        self.paths.policies_storage().exists()
+    }
+
+    pub async fn quotas_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.quotas_storage()).await
+    }
+
+    /// Database of the authority transitions accepted by the node
+    pub async fn authority_transitions_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.authority_transitions_storage())
+            .await
+    }
+
+    pub async fn runtime_state_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.runtime_state_storage()).await
+    }
+
+    pub async fn idempotency_keys_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.idempotency_keys_storage()).await
+    }
+
+    pub async fn kv_store_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.kv_store_storage()).await
+    }
+
+    /// Return the key encrypting the key-value store, created the first time it is needed
+    pub fn kv_store_key(&self) -> Result<StorageKey> {
+        Ok(StorageKey::read_or_create(&self.paths.kv_store_key())?)
+    }
+
+    pub async fn fleet_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.fleet_storage()).await
+    }
+
+    pub async fn resource_usage_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.resource_usage_storage()).await
+    }
+
+    pub async fn health_checks_storage(&self) -> Result<LmdbStorage> {
+        self.storage(self.paths.health_checks_storage()).await
+    }
+
+    /// Open a database of this node, logging its slow operations with the threshold
+    /// configured for the node
+    async fn storage(&self, path: PathBuf) -> Result<LmdbStorage> {
+        let storage = LmdbStorage::new(path).await?;
+        Ok(match self.config.setup.slow_storage_threshold() {
+            Some(threshold) => storage.with_slow_operation_threshold(threshold),
+            None => storage,
+        })
     }
 
     pub fn name(&self) -> &str {
@@ -192,7 +316,7 @@ impl NodeConfig {
     }
 
     pub fn vault_path(&self) -> Result<PathBuf> {
-        Ok(std::fs::canonicalize(&self.default_vault)?)
+        Ok(platform::resolve_item(&self.default_vault)?)
     }
 
     pub async fn vault(&self) -> Result<Vault> {
@@ -201,12 +325,12 @@ impl NodeConfig {
     }
 
     pub fn identity_config(&self) -> Result<IdentityConfig> {
-        let path = std::fs::canonicalize(&self.default_identity)?;
+        let path = platform::resolve_item(&self.default_identity)?;
         Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
     }
 
     pub fn identifier(&self) -> Result<Identifier> {
-        let state_path = std::fs::canonicalize(&self.default_identity)?;
+        let state_path = platform::resolve_item(&self.default_identity)?;
         let state = IdentityState::load(state_path)?;
         Ok(state.identifier())
     }
@@ -216,14 +340,10 @@ impl TryFrom<&CliState> for NodeConfig {
     type Error = CliStateError;
 
     fn try_from(cli_state: &CliState) -> std::result::Result<Self, Self::Error> {
-        let default_vault = cli_state.vaults.default_path()?;
-        assert!(default_vault.exists(), "default vault does not exist");
-        let default_identity = cli_state.identities.default_path()?;
-        assert!(default_identity.exists(), "default identity does not exist");
         Ok(Self {
             version: ConfigVersion::latest(),
-            default_vault,
-            default_identity,
+            default_vault: cli_state.vaults.default()?.path().clone(),
+            default_identity: cli_state.identities.default()?.path().clone(),
             setup: NodeSetupConfig::default(),
         })
     }
@@ -249,11 +369,11 @@ impl NodeConfigBuilder {
     pub fn build(self, cli_state: &CliState) -> Result<NodeConfig> {
         let vault = match self.vault {
             Some(path) => path,
-            None => cli_state.vaults.default_path()?,
+            None => cli_state.vaults.default()?.path().clone(),
         };
         let identity = match self.identity {
             Some(path) => path,
-            None => cli_state.identities.default_path()?,
+            None => cli_state.identities.default()?.path().clone(),
         };
         Ok(NodeConfig {
             default_vault: vault,
@@ -309,6 +429,27 @@ pub struct NodeSetupConfig {
     pub authority_node: Option<bool>,
     pub project: Option<ProjectLookup>,
     pub api_transport: Option<CreateTransportJson>,
+    /// The field might be missing in previous configuration files, hence it is an Option
+    pub resource_profile: Option<ResourceProfile>,
+    /// The field might be missing in previous configuration files, hence it is an Option
+    pub warm_start: Option<bool>,
+    /// The field might be missing in previous configuration files, hence it is an Option
+    pub process: Option<NodeProcessConfig>,
+    /// Identity allowed to push configuration updates to the node
+    pub control_identity: Option<Identifier>,
+    /// The field might be missing in previous configuration files, hence it is an Option
+    pub fleet_inventory: Option<bool>,
+    /// Control node to which the node reports its heartbeats
+    pub heartbeats: Option<HeartbeatConfig>,
+    /// Nodes probed by the node on an interval
+    pub health_checks: Option<HealthCheckConfig>,
+    /// Maximum number of secure channels established by each identity
+    pub max_secure_channels_per_identity: Option<u32>,
+    /// Maximum number of connections opened on the portal outlets by each identity
+    pub max_portal_connections_per_identity: Option<u32>,
+    /// Operations on the databases of the node taking longer than this number of
+    /// milliseconds are logged as slow operations
+    pub slow_storage_threshold_ms: Option<u64>,
 }
 
 impl NodeSetupConfig {
@@ -332,6 +473,68 @@ impl NodeSetupConfig {
         self
     }
 
+    pub fn set_resource_profile(mut self, resource_profile: Option<ResourceProfile>) -> Self {
+        self.resource_profile = resource_profile;
+        self
+    }
+
+    pub fn set_warm_start(mut self, warm_start: bool) -> Self {
+        self.warm_start = Some(warm_start);
+        self
+    }
+
+    pub fn set_process(mut self, process: NodeProcessConfig) -> Self {
+        self.process = if process.is_empty() {
+            None
+        } else {
+            Some(process)
+        };
+        self
+    }
+
+    pub fn set_control_identity(mut self, control_identity: Option<Identifier>) -> Self {
+        self.control_identity = control_identity;
+        self
+    }
+
+    pub fn set_fleet_inventory(mut self, fleet_inventory: bool) -> Self {
+        self.fleet_inventory = Some(fleet_inventory);
+        self
+    }
+
+    pub fn set_heartbeats(mut self, heartbeats: Option<HeartbeatConfig>) -> Self {
+        self.heartbeats = heartbeats;
+        self
+    }
+
+    pub fn set_health_checks(mut self, health_checks: Option<HealthCheckConfig>) -> Self {
+        self.health_checks = health_checks;
+        self
+    }
+
+    pub fn set_quota_limits(mut self, quota_limits: QuotaLimits) -> Self {
+        self.max_secure_channels_per_identity = quota_limits.max_secure_channels;
+        self.max_portal_connections_per_identity = quota_limits.max_portal_connections;
+        self
+    }
+
+    /// Limits applied to each identity connecting to the node
+    pub fn quota_limits(&self) -> QuotaLimits {
+        QuotaLimits {
+            max_secure_channels: self.max_secure_channels_per_identity,
+            max_portal_connections: self.max_portal_connections_per_identity,
+        }
+    }
+
+    pub fn set_slow_storage_threshold(mut self, threshold: Option<Duration>) -> Self {
+        self.slow_storage_threshold_ms = threshold.map(|t| t.as_millis() as u64);
+        self
+    }
+
+    pub fn slow_storage_threshold(&self) -> Option<Duration> {
+        self.slow_storage_threshold_ms.map(Duration::from_millis)
+    }
+
     pub fn api_transport(&self) -> Result<&CreateTransportJson> {
         self.api_transport.as_ref().ok_or_else(|| {
             CliStateError::InvalidOperation(
@@ -373,6 +576,10 @@ impl NodePaths {
         self.path.join("version")
     }
 
+    fn api_token(&self) -> PathBuf {
+        self.path.join("api_token")
+    }
+
     fn stdout(&self) -> PathBuf {
         self.path.join("stdout.log")
     }
@@ -384,6 +591,50 @@ impl NodePaths {
     fn policies_storage(&self) -> PathBuf {
         self.path.join("policies_storage.lmdb")
     }
+
+    fn quotas_storage(&self) -> PathBuf {
+        self.path.join("quotas_storage.lmdb")
+    }
+
+    fn authority_transitions_storage(&self) -> PathBuf {
+        self.path.join("authority_transitions.lmdb")
+    }
+
+    fn runtime_state_storage(&self) -> PathBuf {
+        self.path.join("runtime_state.lmdb")
+    }
+
+    fn idempotency_keys_storage(&self) -> PathBuf {
+        self.path.join("idempotency_keys.lmdb")
+    }
+
+    fn kv_store_storage(&self) -> PathBuf {
+        self.path.join("kv_store.lmdb")
+    }
+
+    fn kv_store_key(&self) -> PathBuf {
+        self.path.join("kv_store.key")
+    }
+
+    fn config_update(&self) -> PathBuf {
+        self.path.join("config_update.json")
+    }
+
+    fn fleet_storage(&self) -> PathBuf {
+        self.path.join("fleet.lmdb")
+    }
+
+    fn resource_usage_storage(&self) -> PathBuf {
+        self.path.join("resource_usage.lmdb")
+    }
+
+    fn health_checks_storage(&self) -> PathBuf {
+        self.path.join("health_checks.lmdb")
+    }
+
+    fn taps(&self) -> PathBuf {
+        self.path.join("taps")
+    }
 }
 
 mod backwards_compatibility {
@@ -450,6 +701,7 @@ mod traits {
         const DEFAULT_FILENAME: &'static str = "node";
         const DIR_NAME: &'static str = "nodes";
         const HAS_DATA_DIR: bool = false;
+        const SOFT_DELETE: bool = true;
 
         fn new(root_path: &Path) -> Self {
             Self {
@@ -472,8 +724,8 @@ mod traits {
             paths.setup().exists()
         }
 
-        fn delete(&self, name: impl AsRef<str>) -> Result<()> {
-            self._delete(&name, false)
+        fn delete_locked(&self, lock: &StateLock, name: impl AsRef<str>) -> Result<()> {
+            self._delete(lock, &name, false, true)
         }
 
         async fn migrate(&self, node_path: &Path) -> Result<()> {
@@ -482,6 +734,20 @@ mod traits {
                 return Ok(());
             }
             let paths = NodePaths::new(node_path);
+            // the links to the legacy `defaults` directory are replaced by links to the items,
+            // before that directory is migrated
+            for link in [paths.vault(), paths.identity()] {
+                let target = match std::fs::read_link(&link) {
+                    Ok(target) => target,
+                    Err(_) => continue,
+                };
+                if target.parent().and_then(|p| p.file_name()) == Some("defaults".as_ref()) {
+                    if let Ok(item) = platform::resolve_item(&link) {
+                        std::fs::remove_file(&link)?;
+                        platform::link_item(&item, &link)?;
+                    }
+                }
+            }
             let contents = std::fs::read_to_string(paths.setup())?;
             match serde_json::from_str(&contents)? {
                 NodeSetupConfigs::V1(setup) => {
@@ -492,6 +758,16 @@ mod traits {
                         authority_node: setup.authority_node,
                         project: setup.project,
                         api_transport: None,
+                        resource_profile: None,
+                        warm_start: None,
+                        process: None,
+                        control_identity: None,
+                        fleet_inventory: None,
+                        heartbeats: None,
+                        health_checks: None,
+                        max_secure_channels_per_identity: None,
+                        max_portal_connections_per_identity: None,
+                        slow_storage_threshold_ms: None,
                     };
                     if let Some(t) = setup
                         .transports
@@ -519,10 +795,10 @@ mod traits {
             std::fs::write(paths.setup(), serde_json::to_string(config.setup())?)?;
             std::fs::write(paths.version(), config.version.to_string())?;
             let _ = std::fs::remove_file(paths.vault());
-            std::os::unix::fs::symlink(&config.default_vault, paths.vault())?;
+            platform::link_item(&config.default_vault, &paths.vault())?;
             config.default_vault = paths.vault();
             let _ = std::fs::remove_file(paths.identity());
-            std::os::unix::fs::symlink(&config.default_identity, paths.identity())?;
+            platform::link_item(&config.default_identity, &paths.identity())?;
             config.default_identity = paths.identity();
             Ok(Self {
                 name,
@@ -578,29 +854,36 @@ pub async fn init_node_state(
     identity_name: Option<&str>,
 ) -> miette::Result<()> {
     debug!(name=%node_name, "initializing node state");
-    // Get vault specified in the argument, or get the default
-    let vault_state = cli_state.create_vault_state(vault_name).await?;
-
-    // create an identity for the node
-    let identity = cli_state
-        .get_identities(vault_state.get().await?)
-        .await?
-        .identities_creation()
-        .create_identity()
-        .await
-        .into_diagnostic()
-        .wrap_err("Failed to create identity")?;
-
-    let identity_state = cli_state
-        .create_identity_state(identity.identifier(), identity_name)
-        .await?;
-
-    // Create the node with the given vault and identity
-    let node_config = NodeConfigBuilder::default()
-        .vault(vault_state.path().clone())
-        .identity(identity_state.path().clone())
-        .build(cli_state)?;
-    cli_state.nodes.overwrite(node_name, node_config)?;
+    // The vault, the identity and the node are created at once, or not at all
+    let mut transaction = cli_state.transaction().await?;
+    let created: miette::Result<()> = async {
+        // Get vault specified in the argument, or get the default
+        let vault_state = transaction.create_vault_state(vault_name).await?;
+
+        // create an identity for the node
+        let identity = cli_state
+            .get_identities(vault_state.get().await?)
+            .await?
+            .identities_creation()
+            .create_identity()
+            .await
+            .into_diagnostic()
+            .wrap_err("Failed to create identity")?;
+
+        let identity_state = transaction
+            .create_identity_state(identity.identifier(), identity_name)
+            .await?;
+
+        // Create the node with the given vault and identity
+        let node_config = NodeConfigBuilder::default()
+            .vault(vault_state.path().clone())
+            .identity(identity_state.path().clone())
+            .build(cli_state)?;
+        transaction.overwrite_node(node_name, node_config)?;
+        Ok(())
+    }
+    .await;
+    transaction.finish(created)?;
 
     info!(name=%node_name, "node state initialized");
     Ok(())
@@ -614,7 +897,7 @@ pub async fn add_project_info_to_node_state(
     debug!(name=%node_name, "Adding project info to state");
     let proj_path = if let Some(path) = project_path {
         Some(path.clone())
-    } else if let Ok(proj) = cli_state.projects.default() {
+    } else if let Ok(proj) = Resolver::new(&cli_state.projects, OCKAM_PROJECT).get(None) {
         Some(proj.path().clone())
     } else {
         None
@@ -666,6 +949,20 @@ mod tests {
     use crate::config::lookup::InternetAddress;
     use crate::nodes::models::transport::{TransportMode, TransportType};
 
+    #[test]
+    fn node_config_setup_keeps_the_quota_limits() {
+        let limits = QuotaLimits::default().with_max_portal_connections(3);
+        let setup = NodeSetupConfig::default().set_quota_limits(limits);
+
+        let json = serde_json::to_string(&setup).unwrap();
+        let setup: NodeSetupConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(setup.quota_limits(), limits);
+
+        // the nodes created before the quotas were persisted have no limits
+        let setup: NodeSetupConfig = serde_json::from_str(r#"{"verbose":0}"#).unwrap();
+        assert_eq!(setup.quota_limits(), QuotaLimits::default());
+    }
+
     #[test]
     fn node_config_setup_transports_no_duplicates() {
         let mut config = NodeSetupConfigV1 {
@@ -0,0 +1,255 @@
+//! Platform specific management of the node processes and of the node files.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[cfg(unix)]
+pub(crate) use unix::*;
+#[cfg(windows)]
+pub(crate) use windows::*;
+
+#[cfg(unix)]
+mod unix {
+    use super::*;
+    use nix::errno::Errno;
+    use nix::sys::signal::{kill, Signal};
+    use sysinfo::{Pid, ProcessExt, ProcessStatus, System, SystemExt};
+
+    /// Stop a process, right away if `force` is true.
+    /// Return false if there is no such process
+    pub(crate) fn stop_process(pid: i32, force: bool) -> io::Result<bool> {
+        let signal = if force {
+            Signal::SIGKILL
+        } else {
+            Signal::SIGTERM
+        };
+        match kill(nix::unistd::Pid::from_raw(pid), signal) {
+            Ok(()) => Ok(true),
+            Err(Errno::ESRCH) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub(crate) fn is_process_running(pid: i32) -> bool {
+        let mut sys = System::new();
+        sys.refresh_processes();
+        if let Some(p) = sys.process(Pid::from(pid as usize)) {
+            // Under certain circumstances the process can be in a state where it's not running
+            // and we are unable to kill it. For example, `kill -9` a process created by
+            // `node create` in a Docker environment will result in a zombie process.
+            !matches!(p.status(), ProcessStatus::Dead | ProcessStatus::Zombie)
+        } else {
+            false
+        }
+    }
+
+    /// The processes are stopped with signals, there is nothing to set up
+    pub(crate) fn track_current_process() -> io::Result<()> {
+        Ok(())
+    }
+
+    pub(crate) fn link_item(item: &Path, link: &Path) -> io::Result<()> {
+        std::os::unix::fs::symlink(item, link)
+    }
+
+    pub(crate) fn resolve_item(path: &Path) -> io::Result<PathBuf> {
+        std::fs::canonicalize(path)
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use super::*;
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, GetLastError, ERROR_INVALID_PARAMETER, HANDLE, STILL_ACTIVE,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, OpenJobObjectW, TerminateJobObject,
+        JOB_OBJECT_TERMINATE,
+    };
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, GetExitCodeProcess, OpenProcess, TerminateProcess,
+        PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_TERMINATE,
+    };
+
+    /// Exit code of a stopped node process
+    const STOPPED_EXIT_CODE: u32 = 1;
+
+    /// Stop a process and the processes it started.
+    /// There is no graceful stop on Windows, the process is always terminated.
+    /// Return false if there is no such process
+    pub(crate) fn stop_process(pid: i32, _force: bool) -> io::Result<bool> {
+        if !is_process_running(pid) {
+            return Ok(false);
+        }
+        let name = job_name(pid as u32);
+        // SAFETY: the handles are checked before being used and closed once used
+        unsafe {
+            let job = OpenJobObjectW(JOB_OBJECT_TERMINATE, 0, name.as_ptr());
+            if job != 0 {
+                let terminated = TerminateJobObject(job, STOPPED_EXIT_CODE);
+                CloseHandle(job);
+                if terminated != 0 {
+                    return Ok(true);
+                }
+            }
+            // the node was started without a job, by a previous version
+            let process = OpenProcess(PROCESS_TERMINATE, 0, pid as u32);
+            if process == 0 {
+                return no_such_process();
+            }
+            let terminated = TerminateProcess(process, STOPPED_EXIT_CODE);
+            CloseHandle(process);
+            if terminated == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(true)
+    }
+
+    /// A pid is only valid as long as a handle to the process is open, so the exit code of the
+    /// process is checked instead of only looking for a process with that pid
+    pub(crate) fn is_process_running(pid: i32) -> bool {
+        // SAFETY: the handle is checked before being used and closed once used
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid as u32);
+            if process == 0 {
+                return false;
+            }
+            let mut exit_code = 0u32;
+            let queried = GetExitCodeProcess(process, &mut exit_code);
+            CloseHandle(process);
+            queried != 0 && exit_code == STILL_ACTIVE as u32
+        }
+    }
+
+    /// Put the current process in a job named after its pid.
+    /// The handle of the job is kept open until the process exits, so that the job can be
+    /// found by name to stop the node
+    pub(crate) fn track_current_process() -> io::Result<()> {
+        let name = job_name(std::process::id());
+        // SAFETY: the job handle is checked before being used
+        unsafe {
+            let job: HANDLE = CreateJobObjectW(std::ptr::null(), name.as_ptr());
+            if job == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            if AssignProcessToJobObject(job, GetCurrentProcess()) == 0 {
+                let error = io::Error::last_os_error();
+                CloseHandle(job);
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn link_item(item: &Path, link: &Path) -> io::Result<()> {
+        std::fs::write(link, item.to_string_lossy().as_bytes())
+    }
+
+    /// Return the path of an item, or of the item targeted by a link of a node.
+    /// The items are `.json` files while the links have no extension
+    pub(crate) fn resolve_item(path: &Path) -> io::Result<PathBuf> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        let item = if !metadata.file_type().is_symlink() && path.extension().is_none() {
+            PathBuf::from(std::fs::read_to_string(path)?.trim())
+        } else {
+            path.to_path_buf()
+        };
+        Ok(strip_verbatim_prefix(std::fs::canonicalize(item)?))
+    }
+
+    /// `canonicalize` returns a `\\?\C:\...` path which is not comparable with the paths
+    /// built from `OCKAM_HOME`, and is not supported by some programs
+    pub(super) fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+        match path.to_str() {
+            Some(s) if s.starts_with(r"\\?\") && !s.starts_with(r"\\?\UNC\") => {
+                PathBuf::from(&s[4..])
+            }
+            _ => path,
+        }
+    }
+
+    fn job_name(pid: u32) -> Vec<u16> {
+        OsStr::new(&format!(r"Local\ockam-node-{pid}"))
+            .encode_wide()
+            .chain(Some(0))
+            .collect()
+    }
+
+    fn no_such_process() -> io::Result<bool> {
+        // SAFETY: reads the last error of the current thread
+        if unsafe { GetLastError() } == ERROR_INVALID_PARAMETER {
+            Ok(false)
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+
+    #[test]
+    fn test_stop_process() -> io::Result<()> {
+        let mut child = long_running_process()?;
+        let pid = child.id() as i32;
+        assert!(is_process_running(pid));
+
+        assert!(stop_process(pid, true)?);
+        child.wait()?;
+        assert!(!is_process_running(pid));
+        // the process is gone
+        assert!(!stop_process(pid, true)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_link_item() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let item = dir.path().join("vault.json");
+        std::fs::write(&item, "{}")?;
+        let link = dir.path().join("vault");
+        link_item(&item, &link)?;
+
+        let resolved = resolve_item(&link)?;
+        assert_eq!(resolved, resolve_item(&item)?);
+        assert_eq!(std::fs::read_to_string(resolved)?, "{}");
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        use super::windows::strip_verbatim_prefix;
+
+        assert_eq!(
+            strip_verbatim_prefix(PathBuf::from(r"\\?\C:\ockam\vaults\v.json")),
+            PathBuf::from(r"C:\ockam\vaults\v.json")
+        );
+        assert_eq!(
+            strip_verbatim_prefix(PathBuf::from(r"\\?\UNC\server\ockam")),
+            PathBuf::from(r"\\?\UNC\server\ockam")
+        );
+    }
+
+    #[cfg(unix)]
+    fn long_running_process() -> io::Result<Child> {
+        Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::null())
+            .spawn()
+    }
+
+    #[cfg(windows)]
+    fn long_running_process() -> io::Result<Child> {
+        Command::new("ping")
+            .args(["-n", "30", "127.0.0.1"])
+            .stdout(Stdio::null())
+            .spawn()
+    }
+}
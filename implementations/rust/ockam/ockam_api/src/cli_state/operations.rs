@@ -0,0 +1,156 @@
+//! Batches of changes to the CLI state.
+
+use crate::cli_state::{
+    CliState, CliStateError, NodeConfigBuilder, StateDirTrait, StateItemTrait, StateTransaction,
+    VaultConfig,
+};
+
+use super::Result;
+
+/// Change of the CLI state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// Create a vault
+    CreateVault {
+        name: String,
+        config: VaultConfig,
+    },
+    /// Create an identity, in the given vault or in the default vault
+    CreateIdentity {
+        name: String,
+        vault: Option<String>,
+    },
+    /// Create a node using the given vault and identity, or the default ones
+    CreateNode {
+        name: String,
+        vault: Option<String>,
+        identity: Option<String>,
+    },
+    SetDefaultVault(String),
+    SetDefaultIdentity(String),
+    SetDefaultNode(String),
+}
+
+impl CliState {
+    /// Apply a batch of changes: either all the changes are applied or none of them
+    pub async fn apply(&self, operations: Vec<Op>) -> Result<()> {
+        let mut transaction = self.transaction().await?;
+        for (index, operation) in operations.into_iter().enumerate() {
+            if let Err(e) = self.apply_operation(operation, &mut transaction).await {
+                warn!(%e, index, "the batch of changes failed, rolling back");
+                transaction.roll_back()?;
+                return Err(CliStateError::InvalidOperation(format!(
+                    "The change {} of the batch failed, no change was applied: {e}",
+                    index + 1
+                )));
+            }
+        }
+        transaction.commit()
+    }
+
+    async fn apply_operation(
+        &self,
+        operation: Op,
+        transaction: &mut StateTransaction<'_>,
+    ) -> Result<()> {
+        debug!(?operation, "applying a change to the state");
+        match operation {
+            Op::CreateVault { name, config } => {
+                transaction.create_vault(&name, config).await?;
+            }
+            Op::CreateIdentity { name, vault } => {
+                if self.identities.exists(&name) {
+                    return Err(CliStateError::AlreadyExists {
+                        resource: "identity".to_string(),
+                        name,
+                    });
+                }
+                let vault = match vault {
+                    Some(vault) => self.vaults.get(vault)?,
+                    None => self.vaults.default()?,
+                };
+                let identity = self
+                    .get_identities(vault.get().await?)
+                    .await?
+                    .identities_creation()
+                    .create_identity()
+                    .await?;
+                transaction
+                    .create_identity_state(identity.identifier(), Some(&name))
+                    .await?;
+            }
+            Op::CreateNode {
+                name,
+                vault,
+                identity,
+            } => {
+                // the node configuration requires a default vault and a default identity
+                self.vaults.default()?;
+                self.identities.default()?;
+                let mut builder = NodeConfigBuilder::default();
+                if let Some(vault) = vault {
+                    builder = builder.vault(self.vaults.get(vault)?.path().clone());
+                }
+                if let Some(identity) = identity {
+                    builder = builder.identity(self.identities.get(identity)?.path().clone());
+                }
+                transaction.create_node(&name, builder.build(self)?)?;
+            }
+            Op::SetDefaultVault(name) => transaction.set_default("vault", &name)?,
+            Op::SetDefaultIdentity(name) => transaction.set_default("identity", &name)?,
+            Op::SetDefaultNode(name) => transaction.set_default("node", &name)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_apply_all_or_nothing() -> Result<()> {
+        let state = CliState::test()?;
+
+        // the last change fails, so the vault and the identity are not created
+        let failed = state
+            .apply(vec![
+                Op::CreateVault {
+                    name: "v1".into(),
+                    config: VaultConfig::default(),
+                },
+                Op::CreateIdentity {
+                    name: "i1".into(),
+                    vault: Some("v1".into()),
+                },
+                Op::SetDefaultNode("unknown".into()),
+            ])
+            .await;
+        assert!(failed.is_err());
+        assert!(!state.vaults.exists("v1"));
+        assert!(!state.identities.exists("i1"));
+        assert!(state.vaults.default().is_err());
+
+        state
+            .apply(vec![
+                Op::CreateVault {
+                    name: "v1".into(),
+                    config: VaultConfig::default(),
+                },
+                Op::CreateIdentity {
+                    name: "i1".into(),
+                    vault: None,
+                },
+                Op::CreateNode {
+                    name: "n1".into(),
+                    vault: Some("v1".into()),
+                    identity: Some("i1".into()),
+                },
+                Op::SetDefaultNode("n1".into()),
+            ])
+            .await?;
+        assert!(state.nodes.is_default("n1")?);
+        assert!(state.identities.is_default("i1")?);
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+//! Profiles of the CLI state.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use ockam_core::env::get_env;
+
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{CliState, CliStateError};
+
+use super::Result;
+
+/// Environment variable set to the name of the profile used by a command, instead of the
+/// active profile. It is inherited by the nodes started in the background
+pub const OCKAM_PROFILE: &str = "OCKAM_PROFILE";
+
+/// Name of the profile stored at the root of the state directory
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+/// Name of the directory containing the profiles other than the default one
+pub const PROFILES_DIR_NAME: &str = "profiles";
+
+/// Name of the file storing the active profile
+const ACTIVE_PROFILE_FILE_NAME: &str = "active.json";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ProfilesConfig {
+    active: Option<String>,
+}
+
+/// Profiles stored in a `$OCKAM_HOME` directory, and the active one
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProfilesState {
+    root_path: PathBuf,
+}
+
+impl ProfilesState {
+    /// Profiles stored in a root directory
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+        }
+    }
+
+    /// Root directory of the profiles
+    pub fn root_path(&self) -> &PathBuf {
+        &self.root_path
+    }
+
+    /// Return the name of the profile to use: the given one, then the one set with
+    /// [`OCKAM_PROFILE`], then the active one
+    pub fn resolve(&self, profile: Option<&str>) -> Result<String> {
+        let name = match profile {
+            Some(name) => name.to_string(),
+            None => match get_env::<String>(OCKAM_PROFILE)?.filter(|name| !name.is_empty()) {
+                Some(name) => name,
+                None => self.active()?,
+            },
+        };
+        validate_name(&name)?;
+        Ok(name)
+    }
+
+    /// Directory storing the state of a profile
+    pub fn dir(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        if name == DEFAULT_PROFILE_NAME {
+            Ok(self.root_path.clone())
+        } else {
+            Ok(self.root_path.join(PROFILES_DIR_NAME).join(name))
+        }
+    }
+
+    /// Return true if a profile exists. The default profile always exists
+    pub fn exists(&self, name: &str) -> Result<bool> {
+        Ok(name == DEFAULT_PROFILE_NAME || self.dir(name)?.is_dir())
+    }
+
+    /// Create the directory of a new profile
+    pub fn create(&self, name: &str) -> Result<PathBuf> {
+        if self.exists(name)? {
+            return Err(CliStateError::AlreadyExists {
+                resource: "profile".to_string(),
+                name: name.to_string(),
+            });
+        }
+        let dir = self.dir(name)?;
+        check_writable(&dir)?;
+        std::fs::create_dir_all(&dir)?;
+        info!(%name, "Created new profile");
+        Ok(dir)
+    }
+
+    /// Names of all the profiles, starting with the default one
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        let profiles_dir = self.root_path.join(PROFILES_DIR_NAME);
+        if profiles_dir.is_dir() {
+            for entry in std::fs::read_dir(profiles_dir)? {
+                let entry = entry?;
+                if entry.path().is_dir() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        names.push(name.to_string());
+                    }
+                }
+            }
+        }
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE_NAME.to_string());
+        Ok(names)
+    }
+
+    /// Name of the active profile. It is the default profile until another one is activated
+    pub fn active(&self) -> Result<String> {
+        let path = self.active_profile_path();
+        if !path.exists() {
+            return Ok(DEFAULT_PROFILE_NAME.to_string());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let config: ProfilesConfig = serde_json::from_str(&contents)?;
+        match config.active {
+            Some(name) if validate_name(&name).is_ok() && self.exists(&name)? => Ok(name),
+            _ => Ok(DEFAULT_PROFILE_NAME.to_string()),
+        }
+    }
+
+    /// Activate an existing profile
+    pub fn set_active(&self, name: &str) -> Result<()> {
+        if !self.exists(name)? {
+            return Err(CliStateError::ResourceNotFound {
+                resource: "profile".to_string(),
+                name: name.to_string(),
+            });
+        }
+        let path = self.active_profile_path();
+        check_writable(&path)?;
+        std::fs::create_dir_all(self.root_path.join(PROFILES_DIR_NAME))?;
+        let config = ProfilesConfig {
+            active: Some(name.to_string()),
+        };
+        // replace the file at once, so that a concurrent command never reads a partial file
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_string(&config)?)?;
+        std::fs::rename(tmp_path, path)?;
+        info!(%name, "Set active profile");
+        Ok(())
+    }
+
+    /// Delete a profile, its nodes are stopped.
+    /// The default profile is activated if the deleted profile was the active one
+    pub fn delete(&self, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE_NAME {
+            return Err(CliStateError::InvalidOperation(
+                "The default profile can't be deleted".to_string(),
+            ));
+        }
+        if !self.exists(name)? {
+            return Err(CliStateError::ResourceNotFound {
+                resource: "profile".to_string(),
+                name: name.to_string(),
+            });
+        }
+        check_writable(&self.dir(name)?)?;
+        if self.active()? == name {
+            self.set_active(DEFAULT_PROFILE_NAME)?;
+        }
+        let dir = self.dir(name)?;
+        CliState::delete_at(&dir)?;
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    fn active_profile_path(&self) -> PathBuf {
+        self.root_path
+            .join(PROFILES_DIR_NAME)
+            .join(ACTIVE_PROFILE_FILE_NAME)
+    }
+}
+
+/// A profile name is used as a directory name
+fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(CliStateError::InvalidData(format!(
+            "The profile name '{name}' must only contain letters, digits, '-' or '_'"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let profiles = ProfilesState::new(&dir);
+        assert_eq!(profiles.active()?, DEFAULT_PROFILE_NAME);
+        assert_eq!(profiles.dir(DEFAULT_PROFILE_NAME)?, dir);
+        assert_eq!(profiles.list()?, vec![DEFAULT_PROFILE_NAME.to_string()]);
+
+        let work_dir = profiles.create("work")?;
+        assert_eq!(work_dir, dir.join(PROFILES_DIR_NAME).join("work"));
+        profiles.create("staging")?;
+        assert!(profiles.create("work").is_err());
+        assert!(profiles.create("../work").is_err());
+        assert_eq!(profiles.list()?, vec!["default", "staging", "work"]);
+
+        assert!(profiles.set_active("personal").is_err());
+        profiles.set_active("work")?;
+        assert_eq!(profiles.active()?, "work");
+        assert_eq!(profiles.resolve(Some("staging"))?, "staging");
+
+        // the deleted active profile is replaced by the default one
+        profiles.delete("work")?;
+        assert!(!work_dir.exists());
+        assert_eq!(profiles.active()?, DEFAULT_PROFILE_NAME);
+        assert!(profiles.delete(DEFAULT_PROFILE_NAME).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_profiles_are_independent() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        let profiles = ProfilesState::new(&dir);
+        profiles.create("work")?;
+
+        let default_state = CliState::initialize_profile_at(&dir, DEFAULT_PROFILE_NAME).await?;
+        let work_state = CliState::initialize_profile_at(&dir, "work").await?;
+        assert_eq!(work_state.profile, "work");
+        assert_eq!(work_state.dir, dir.join(PROFILES_DIR_NAME).join("work"));
+
+        default_state.defaults.set("vault", "v1")?;
+        assert_eq!(work_state.defaults.get("vault")?, None);
+        Ok(())
+    }
+}
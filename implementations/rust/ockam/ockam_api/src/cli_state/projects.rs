@@ -1,4 +1,6 @@
 use super::Result;
+use crate::cli_state::cached::{now, Cached};
+use crate::cli_state::{StateDirTrait, StateItemTrait};
 use crate::cloud::project::{OktaConfig, Project};
 use crate::config::lookup::ProjectLookup;
 use crate::error::ApiError;
@@ -16,6 +18,29 @@ pub struct ProjectState {
     name: String,
     path: PathBuf,
     config: ProjectConfig,
+    /// Seconds since the Unix epoch
+    fetched_at: Option<u64>,
+}
+
+impl ProjectsState {
+    /// Store a project which was just fetched from the Orchestrator
+    pub fn refresh(&self, name: impl AsRef<str>, config: ProjectConfig) -> Result<ProjectState> {
+        let mut state = self.overwrite(name, config)?;
+        state.fetched_at = Some(now());
+        state.persist()?;
+        Ok(state)
+    }
+
+    /// Return a project from the local state, without contacting the Orchestrator,
+    /// with the time at which it was last fetched
+    pub fn get_cached(&self, name: impl AsRef<str>) -> Result<Cached<ProjectConfig>> {
+        Ok(self.get(name)?.cached())
+    }
+
+    /// Return all the projects of the local state, with the time at which they were last fetched
+    pub fn list_cached(&self) -> Result<Vec<Cached<ProjectConfig>>> {
+        Ok(self.list()?.iter().map(|p| p.cached()).collect())
+    }
 }
 
 impl ProjectState {
@@ -25,6 +50,15 @@ impl ProjectState {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Seconds since the Unix epoch, if the project was fetched from the Orchestrator
+    pub fn fetched_at(&self) -> Option<u64> {
+        self.fetched_at
+    }
+
+    pub fn cached(&self) -> Cached<ProjectConfig> {
+        Cached::new(self.config.clone(), self.fetched_at)
+    }
 }
 
 pub type ProjectConfig = Project;
@@ -120,6 +154,7 @@ impl From<&ProjectConfigCompact> for Project {
 mod traits {
     use super::*;
     use crate::cli_state::file_stem;
+    use crate::cli_state::read_only::check_writable;
     use crate::cli_state::traits::*;
     use ockam_core::async_trait;
     use std::path::Path;
@@ -130,6 +165,7 @@ mod traits {
         const DEFAULT_FILENAME: &'static str = "project";
         const DIR_NAME: &'static str = "projects";
         const HAS_DATA_DIR: bool = false;
+        const SOFT_DELETE: bool = true;
 
         fn new(root_path: &Path) -> Self {
             Self {
@@ -150,14 +186,32 @@ mod traits {
             let contents = serde_json::to_string(&config)?;
             std::fs::write(&path, contents)?;
             let name = file_stem(&path)?;
-            Ok(Self { name, path, config })
+            Ok(Self {
+                name,
+                path,
+                config,
+                fetched_at: None,
+            })
         }
 
         fn load(path: PathBuf) -> Result<Self> {
             let name = file_stem(&path)?;
             let contents = std::fs::read_to_string(&path)?;
-            let config = serde_json::from_str(&contents)?;
-            Ok(Self { name, path, config })
+            let cached: Cached<Self::Config> = serde_json::from_str(&contents)?;
+            Ok(Self {
+                name,
+                path,
+                config: cached.value,
+                fetched_at: cached.fetched_at,
+            })
+        }
+
+        /// Keep the fetch time when the item is persisted
+        fn persist(&self) -> Result<()> {
+            check_writable(&self.path)?;
+            let contents = serde_json::to_string(&Cached::new(&self.config, self.fetched_at))?;
+            std::fs::write(&self.path, contents)?;
+            Ok(())
         }
 
         fn path(&self) -> &PathBuf {
@@ -0,0 +1,181 @@
+//! Read-only CLI state, for the tools inspecting a live state directory.
+
+use std::fmt::{Debug, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::cli_state::user_info::UsersInfoState;
+use crate::cli_state::{
+    CliState, CliStateError, CredentialsState, DefaultsState, IdentitiesState, InvitationsState,
+    NodesState, ProfilesState, ProjectsState, SpacesState, StateDirTrait, SubscriptionsState,
+    TrustContextsState, VaultsState,
+};
+
+use super::Result;
+
+/// Directories of the states opened in read-only mode by the current process
+static READ_ONLY_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Return an error if a path belongs to a state opened in read-only mode
+pub(crate) fn check_writable(path: &Path) -> Result<()> {
+    let dirs = READ_ONLY_DIRS.lock().unwrap();
+    match dirs.iter().find(|dir| path.starts_with(dir)) {
+        Some(dir) => Err(CliStateError::ReadOnly(dir.to_string_lossy().to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Return true if a path belongs to a state opened in read-only mode
+pub(crate) fn is_read_only(path: &Path) -> bool {
+    check_writable(path).is_err()
+}
+
+/// Directory of a read-only state, shared by all the copies of that state.
+/// The directory is writable again once the last copy is dropped
+#[derive(Clone)]
+pub struct ReadOnlyDir {
+    dir: Arc<ReadOnlyDirGuard>,
+}
+
+struct ReadOnlyDirGuard {
+    path: PathBuf,
+}
+
+impl ReadOnlyDir {
+    fn new(path: &Path) -> Self {
+        READ_ONLY_DIRS.lock().unwrap().push(path.to_path_buf());
+        Self {
+            dir: Arc::new(ReadOnlyDirGuard {
+                path: path.to_path_buf(),
+            }),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir.path
+    }
+}
+
+impl Drop for ReadOnlyDirGuard {
+    fn drop(&mut self) {
+        let mut dirs = READ_ONLY_DIRS.lock().unwrap();
+        // the same directory can be opened several times in read-only mode
+        if let Some(index) = dirs.iter().position(|dir| dir == &self.path) {
+            dirs.remove(index);
+        }
+    }
+}
+
+impl Debug for ReadOnlyDir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ReadOnlyDir").field(&self.path()).finish()
+    }
+}
+
+impl PartialEq for ReadOnlyDir {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.dir, &other.dir)
+    }
+}
+
+impl Eq for ReadOnlyDir {}
+
+impl CliState {
+    /// Open the state of a profile, or of the profile set with `OCKAM_PROFILE`, or of the
+    /// active profile, in read-only mode
+    pub fn read_only(profile: Option<&str>) -> Result<Self> {
+        let root_path = Self::default_dir()?;
+        let profiles = ProfilesState::new(&root_path);
+        let profile = profiles.resolve(profile)?;
+        let dir = profiles.dir(&profile)?;
+        let mut state = Self::read_only_at(&dir)?;
+        state.profiles = profiles;
+        state.profile = profile;
+        Ok(state)
+    }
+
+    /// Open the state stored in a directory in read-only mode.
+    /// The directory must exist, it is never created
+    pub fn read_only_at(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            return Err(CliStateError::InvalidPath(
+                dir.to_string_lossy().to_string(),
+            ));
+        }
+        let read_only = ReadOnlyDir::new(dir);
+        Ok(Self {
+            vaults: VaultsState::new(dir),
+            identities: IdentitiesState::new(dir),
+            nodes: NodesState::new(dir),
+            spaces: SpacesState::new(dir),
+            projects: ProjectsState::new(dir),
+            credentials: CredentialsState::new(dir),
+            trust_contexts: TrustContextsState::new(dir),
+            users_info: UsersInfoState::new(dir),
+            subscriptions: SubscriptionsState::new(dir),
+            invitations: InvitationsState::new(dir),
+            defaults: DefaultsState::new(dir),
+            profiles: ProfilesState::new(dir),
+            profile: super::DEFAULT_PROFILE_NAME.to_string(),
+            dir: dir.to_path_buf(),
+            ephemeral: None,
+            read_only: Some(read_only),
+        })
+    }
+
+    /// Return true if the calls modifying this state are rejected
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{StateItemTrait, VaultConfig};
+
+    #[tokio::test]
+    async fn test_read_only_state() -> Result<()> {
+        let state = CliState::test()?;
+        state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+
+        let read_only = CliState::read_only_at(&state.dir)?;
+        assert!(read_only.is_read_only());
+        assert_eq!(read_only.vaults.default()?.name(), "v1");
+
+        // the changes are rejected, whatever the copy of the state used to make them
+        assert!(matches!(
+            read_only
+                .vaults
+                .create_async("v2", VaultConfig::default())
+                .await,
+            Err(CliStateError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            state.vaults.delete("v1"),
+            Err(CliStateError::ReadOnly(_))
+        ));
+        assert!(matches!(
+            read_only.vaults.default()?.persist(),
+            Err(CliStateError::ReadOnly(_))
+        ));
+        assert!(state.vaults.exists("v1"));
+
+        // the state can be modified again once the read-only state is dropped
+        drop(read_only);
+        state.vaults.delete("v1")?;
+        assert!(!state.vaults.exists("v1"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_read_only_state() -> Result<()> {
+        let dir = CliState::test_dir()?;
+        assert!(CliState::read_only_at(&dir).is_err());
+        assert!(!dir.exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+//! Resolution of the name of the vault, identity, node or project used by a command.
+
+use std::fmt::{Display, Formatter};
+
+use ockam_core::env::get_env;
+
+use crate::cli_state::{file_stem, CliState, CliStateError, StateDirTrait, StateItemTrait};
+
+use super::Result;
+
+pub const OCKAM_VAULT: &str = "OCKAM_VAULT";
+pub const OCKAM_IDENTITY: &str = "OCKAM_IDENTITY";
+pub const OCKAM_NODE: &str = "OCKAM_NODE";
+pub const OCKAM_PROJECT: &str = "OCKAM_PROJECT";
+
+/// Name of the node used when no node is given and no default node is set
+pub const DEFAULT_NODE_NAME: &str = "default";
+
+/// Source of a resolved name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Explicit,
+    Environment(&'static str),
+    StateDefault,
+    BuiltIn,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Explicit => write!(f, "given explicitly"),
+            Source::Environment(variable) => write!(f, "set by the ${variable} variable"),
+            Source::StateDefault => write!(f, "set as default"),
+            Source::BuiltIn => write!(f, "used when no default is set"),
+        }
+    }
+}
+
+/// Name resolved by a [`Resolver`], with its source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub name: String,
+    pub source: Source,
+}
+
+impl Resolved {
+    fn new(name: impl Into<String>, source: Source) -> Self {
+        Self {
+            name: name.into(),
+            source,
+        }
+    }
+
+    /// Return a sentence explaining why this name is used
+    pub fn explain(&self) -> String {
+        format!("'{}' is {}", self.name, self.source)
+    }
+}
+
+/// Resolve the name of an item of a state directory
+pub struct Resolver<'a, S> {
+    state: &'a S,
+    variable: &'static str,
+    built_in: Option<&'static str>,
+}
+
+impl<'a, S: StateDirTrait> Resolver<'a, S> {
+    pub fn new(state: &'a S, variable: &'static str) -> Self {
+        Self {
+            state,
+            variable,
+            built_in: None,
+        }
+    }
+
+    /// Use a name when no other source provides one
+    pub fn with_built_in(mut self, name: &'static str) -> Self {
+        self.built_in = Some(name);
+        self
+    }
+
+    pub fn resolve(&self, explicit: Option<&str>) -> Result<Resolved> {
+        if let Some(name) = explicit {
+            return Ok(Resolved::new(name, Source::Explicit));
+        }
+        if let Some(name) = get_env::<String>(self.variable)?.filter(|n| !n.is_empty()) {
+            return Ok(Resolved::new(name, Source::Environment(self.variable)));
+        }
+        if let Ok(item) = self.state.default() {
+            return Ok(Resolved::new(file_stem(item.path())?, Source::StateDefault));
+        }
+        if let Some(name) = self.built_in {
+            return Ok(Resolved::new(name, Source::BuiltIn));
+        }
+        Err(CliStateError::ResourceNotFound {
+            resource: S::default_filename().to_string(),
+            name: "default".to_string(),
+        })
+    }
+
+    /// Resolve a name and return the corresponding item
+    pub fn get(&self, explicit: Option<&str>) -> Result<S::Item> {
+        self.state.get(self.resolve(explicit)?.name)
+    }
+}
+
+impl CliState {
+    pub fn resolve_vault(&self, name: Option<&str>) -> Result<Resolved> {
+        Resolver::new(&self.vaults, OCKAM_VAULT).resolve(name)
+    }
+
+    pub fn resolve_identity(&self, name: Option<&str>) -> Result<Resolved> {
+        Resolver::new(&self.identities, OCKAM_IDENTITY).resolve(name)
+    }
+
+    pub fn resolve_node(&self, name: Option<&str>) -> Result<Resolved> {
+        Resolver::new(&self.nodes, OCKAM_NODE)
+            .with_built_in(DEFAULT_NODE_NAME)
+            .resolve(name)
+    }
+
+    pub fn resolve_project(&self, name: Option<&str>) -> Result<Resolved> {
+        Resolver::new(&self.projects, OCKAM_PROJECT).resolve(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::VaultConfig;
+
+    #[tokio::test]
+    async fn test_resolution_order() -> Result<()> {
+        let state = CliState::test()?;
+
+        let node = state.resolve_node(None)?;
+        assert_eq!(node, Resolved::new(DEFAULT_NODE_NAME, Source::BuiltIn));
+        assert!(state.resolve_vault(None).is_err());
+
+        state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+        let vault = state.resolve_vault(None)?;
+        assert_eq!(vault, Resolved::new("v1", Source::StateDefault));
+        assert_eq!(vault.explain(), "'v1' is set as default");
+
+        let vault = state.resolve_vault(Some("v2"))?;
+        assert_eq!(vault, Resolved::new("v2", Source::Explicit));
+        Ok(())
+    }
+}
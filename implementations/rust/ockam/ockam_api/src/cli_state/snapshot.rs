@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+
+use ockam_abac::{Action, Expr, PolicyStorage, Resource};
+
+use super::{
+    CliState, IdentityState, NodeState, Result, SharedStateLock, StateDirTrait, VaultState,
+};
+
+/// Immutable view of the local state at a single point in time.
+///
+/// This is meant to be used by reporting tools which need to read several parts of the state
+/// without observing a change being applied concurrently, for example a node being created
+/// while its identity is being read
+#[derive(Debug, Clone)]
+pub struct CliStateSnapshot {
+    vaults: Vec<VaultState>,
+    identities: Vec<IdentityState>,
+    nodes: Vec<NodeState>,
+    policies: BTreeMap<String, Vec<(Resource, Action, Expr)>>,
+}
+
+impl CliStateSnapshot {
+    /// Vaults present in the snapshot
+    pub fn vaults(&self) -> &[VaultState] {
+        &self.vaults
+    }
+
+    /// Identities present in the snapshot
+    pub fn identities(&self) -> &[IdentityState] {
+        &self.identities
+    }
+
+    /// Nodes present in the snapshot
+    pub fn nodes(&self) -> &[NodeState] {
+        &self.nodes
+    }
+
+    /// Policies of each node, indexed by node name
+    pub fn policies(&self) -> &BTreeMap<String, Vec<(Resource, Action, Expr)>> {
+        &self.policies
+    }
+
+    /// Policies of a given node
+    pub fn node_policies(&self, node_name: &str) -> &[(Resource, Action, Expr)] {
+        self.policies
+            .get(node_name)
+            .map(|p| p.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+impl CliState {
+    /// Return a consistent snapshot of the vaults, identities, nodes and policies.
+    ///
+    /// The state is read while holding a shared lock, so that no other command modifies it
+    /// in the meantime. The policies of each node are read in a single read transaction
+    pub async fn read_snapshot(&self) -> Result<CliStateSnapshot> {
+        let _lock = SharedStateLock::acquire_async(&self.dir).await?;
+        self.read_state().await
+    }
+
+    async fn read_state(&self) -> Result<CliStateSnapshot> {
+        let vaults = self.vaults.list()?;
+        let identities = self.identities.list()?;
+        let nodes = self.nodes.list()?;
+        let mut policies = BTreeMap::new();
+        for node in nodes.iter() {
+            // don't create a storage for nodes which never stored a policy
+            if !node.has_policies_storage() {
+                continue;
+            }
+            let storage = node.policies_storage().await?;
+            policies.insert(node.name().to_string(), storage.all_policies().await?);
+        }
+        Ok(CliStateSnapshot {
+            vaults,
+            identities,
+            nodes,
+            policies,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{IdentityConfig, VaultConfig};
+    use ockam::identity::Identifier;
+    use ockam_abac::expr::str;
+    use ockam_abac::mem::Memory;
+
+    #[tokio::test]
+    async fn test_read_snapshot() -> Result<()> {
+        let state = CliState::test()?;
+        state
+            .vaults
+            .create_async("vault", VaultConfig::default())
+            .await?;
+        let identifier: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        state
+            .identities
+            .create("identity", IdentityConfig::new(&identifier).await)?;
+
+        let snapshot = state.read_snapshot().await?;
+        assert_eq!(snapshot.vaults().len(), 1);
+        assert_eq!(snapshot.identities().len(), 1);
+        assert!(snapshot.nodes().is_empty());
+        assert!(snapshot.node_policies("node").is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_all_policies() -> Result<()> {
+        let storage = Memory::new();
+        let (resource, action) = (Resource::new("outlet"), Action::new("handle_message"));
+        storage.set_policy(&resource, &action, &str("true")).await?;
+        storage
+            .set_policy(&Resource::new("inlet"), &action, &str("false"))
+            .await?;
+
+        let policies = storage.all_policies().await?;
+        assert_eq!(policies.len(), 2);
+        assert!(policies
+            .iter()
+            .any(|(r, a, _)| r == &resource && a == &action));
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use super::Result;
+use crate::cli_state::cached::{now, Cached};
+use crate::cli_state::{StateDirTrait, StateItemTrait};
 use crate::cloud::space::Space;
 use crate::config::lookup::SpaceLookup;
 use serde::{Deserialize, Serialize};
@@ -14,12 +16,44 @@ pub struct SpaceState {
     name: String,
     path: PathBuf,
     config: SpaceConfig,
+    /// Seconds since the Unix epoch
+    fetched_at: Option<u64>,
+}
+
+impl SpacesState {
+    /// Store a space which was just fetched from the Orchestrator
+    pub fn refresh(&self, name: impl AsRef<str>, config: SpaceConfig) -> Result<SpaceState> {
+        let mut state = self.overwrite(name, config)?;
+        state.fetched_at = Some(now());
+        state.persist()?;
+        Ok(state)
+    }
+
+    /// Return a space from the local state, without contacting the Orchestrator,
+    /// with the time at which it was last fetched
+    pub fn get_cached(&self, name: impl AsRef<str>) -> Result<Cached<SpaceConfig>> {
+        Ok(self.get(name)?.cached())
+    }
+
+    /// Return all the spaces of the local state, with the time at which they were last fetched
+    pub fn list_cached(&self) -> Result<Vec<Cached<SpaceConfig>>> {
+        Ok(self.list()?.iter().map(|s| s.cached()).collect())
+    }
 }
 
 impl SpaceState {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// Seconds since the Unix epoch, if the space was fetched from the Orchestrator
+    pub fn fetched_at(&self) -> Option<u64> {
+        self.fetched_at
+    }
+
+    pub fn cached(&self) -> Cached<SpaceConfig> {
+        Cached::new(self.config.clone(), self.fetched_at)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -37,6 +71,17 @@ impl SpaceConfig {
     }
 }
 
+// The users of a space are not stored in the local state
+impl From<&SpaceConfig> for Space {
+    fn from(s: &SpaceConfig) -> Self {
+        Self {
+            id: s.id.to_string(),
+            name: s.name.to_string(),
+            users: vec![],
+        }
+    }
+}
+
 impl From<&Space> for SpaceConfig {
     fn from(s: &Space) -> Self {
         Self {
@@ -49,6 +94,7 @@ impl From<&Space> for SpaceConfig {
 mod traits {
     use super::*;
     use crate::cli_state::file_stem;
+    use crate::cli_state::read_only::check_writable;
     use crate::cli_state::traits::*;
     use ockam_core::async_trait;
     use std::path::Path;
@@ -59,6 +105,7 @@ mod traits {
         const DEFAULT_FILENAME: &'static str = "space";
         const DIR_NAME: &'static str = "spaces";
         const HAS_DATA_DIR: bool = false;
+        const SOFT_DELETE: bool = true;
 
         fn new(root_path: &Path) -> Self {
             Self {
@@ -79,14 +126,32 @@ mod traits {
             let contents = serde_json::to_string(&config)?;
             std::fs::write(&path, contents)?;
             let name = file_stem(&path)?;
-            Ok(Self { name, path, config })
+            Ok(Self {
+                name,
+                path,
+                config,
+                fetched_at: None,
+            })
         }
 
         fn load(path: PathBuf) -> Result<Self> {
             let name = file_stem(&path)?;
             let contents = std::fs::read_to_string(&path)?;
-            let config = serde_json::from_str(&contents)?;
-            Ok(Self { name, path, config })
+            let cached: Cached<Self::Config> = serde_json::from_str(&contents)?;
+            Ok(Self {
+                name,
+                path,
+                config: cached.value,
+                fetched_at: cached.fetched_at,
+            })
+        }
+
+        /// Keep the fetch time when the item is persisted
+        fn persist(&self) -> Result<()> {
+            check_writable(&self.path)?;
+            let contents = serde_json::to_string(&Cached::new(&self.config, self.fetched_at))?;
+            std::fs::write(&self.path, contents)?;
+            Ok(())
         }
 
         fn path(&self) -> &PathBuf {
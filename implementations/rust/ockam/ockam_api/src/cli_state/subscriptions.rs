@@ -0,0 +1,132 @@
+use super::Result;
+use crate::cli_state::cached::now;
+use crate::cli_state::{StateDirTrait, StateItemTrait};
+use crate::cloud::subscription::SubscriptionUsage;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Usage of the subscriptions of the spaces, as last retrieved from the Orchestrator,
+/// stored by space id
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SubscriptionsState {
+    dir: PathBuf,
+}
+
+impl SubscriptionsState {
+    /// Store the usage of the subscription of a space
+    pub fn cache(&self, usage: SubscriptionUsage) -> Result<SubscriptionState> {
+        let space_id = usage.space_id.clone();
+        let config = SubscriptionConfig {
+            usage,
+            retrieved_at: now(),
+        };
+        self.overwrite(space_id, config)
+    }
+
+    /// Return the usage of the subscription of a space if it was retrieved less than `max_age` ago
+    pub fn get_fresh(&self, space_id: &str, max_age: Duration) -> Option<SubscriptionUsage> {
+        let state = self.get(space_id).ok()?;
+        let config = state.config();
+        if config.retrieved_at.saturating_add(max_age.as_secs()) < now() {
+            return None;
+        }
+        Some(config.usage.clone())
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SubscriptionState {
+    path: PathBuf,
+    config: SubscriptionConfig,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SubscriptionConfig {
+    pub usage: SubscriptionUsage,
+    /// Seconds since the Unix epoch
+    pub retrieved_at: u64,
+}
+
+mod traits {
+    use super::*;
+    use crate::cli_state::traits::*;
+    use ockam_core::async_trait;
+    use std::path::Path;
+
+    #[async_trait]
+    impl StateDirTrait for SubscriptionsState {
+        type Item = SubscriptionState;
+        const DEFAULT_FILENAME: &'static str = "subscription";
+        const DIR_NAME: &'static str = "subscriptions";
+        const HAS_DATA_DIR: bool = false;
+
+        fn new(root_path: &Path) -> Self {
+            Self {
+                dir: Self::build_dir(root_path),
+            }
+        }
+
+        fn dir(&self) -> &PathBuf {
+            &self.dir
+        }
+    }
+
+    #[async_trait]
+    impl StateItemTrait for SubscriptionState {
+        type Config = SubscriptionConfig;
+
+        fn new(path: PathBuf, config: Self::Config) -> Result<Self> {
+            let contents = serde_json::to_string(&config)?;
+            std::fs::write(&path, contents)?;
+            Ok(Self { path, config })
+        }
+
+        fn load(path: PathBuf) -> Result<Self> {
+            let contents = std::fs::read_to_string(&path)?;
+            let config = serde_json::from_str(&contents)?;
+            Ok(Self { path, config })
+        }
+
+        fn path(&self) -> &PathBuf {
+            &self.path
+        }
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::CliState;
+    use crate::cloud::subscription::ResourceUsage;
+
+    #[test]
+    fn test_cache_usage() {
+        let state = CliState::test().unwrap();
+        let usage = SubscriptionUsage {
+            space_id: "space-id".into(),
+            plan: "free".into(),
+            trial_ends_at: None,
+            resources: vec![ResourceUsage {
+                resource: "relays".into(),
+                used: 1,
+                limit: Some(5),
+            }],
+        };
+        state.subscriptions.cache(usage.clone()).unwrap();
+
+        let cached = state
+            .subscriptions
+            .get_fresh("space-id", Duration::from_secs(60));
+        assert_eq!(cached, Some(usage));
+        assert!(state.subscriptions.exists("space-id"));
+        assert!(state
+            .subscriptions
+            .get_fresh("other", Duration::from_secs(60))
+            .is_none());
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{CliState, CliStateError, Result};
+
+/// Name of the file, in the state directory, containing the TLS configuration
+const TLS_CONFIG_FILE: &str = "tls.json";
+
+/// TLS configuration of the HTTPS connections made to the Ockam cloud services,
+/// for example during the enrollment.
+///
+/// Enterprise networks often replace or inspect TLS connections. This configuration allows to:
+///  - trust additional certificate authorities, for example the CA of a corporate network
+///  - pin the public keys of the servers certificates
+///  - explicitly accept a TLS-intercepting proxy
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file containing additional trusted root certificates
+    #[serde(default)]
+    pub ca_bundle: Option<PathBuf>,
+    /// If true, the root certificates of the system are not trusted,
+    /// only the ones of the CA bundle
+    #[serde(default)]
+    pub disable_system_roots: bool,
+    /// Hex-encoded SHA-256 digests of accepted SubjectPublicKeyInfo.
+    /// When pins are configured, one of the certificates presented by the server must match one of them
+    #[serde(default)]
+    pub pinned_spki_sha256: Vec<String>,
+    /// If true, a proxy intercepting TLS connections is accepted: the pins are not enforced and
+    /// the proxy certificate authority must be part of the CA bundle
+    #[serde(default)]
+    pub allow_tls_interception: bool,
+}
+
+impl TlsConfig {
+    /// Trust the root certificates of a PEM file
+    pub fn with_ca_bundle(mut self, ca_bundle: PathBuf) -> Self {
+        self.ca_bundle = Some(ca_bundle);
+        self
+    }
+
+    /// Only trust the root certificates of the CA bundle
+    pub fn with_system_roots_disabled(mut self) -> Self {
+        self.disable_system_roots = true;
+        self
+    }
+
+    /// Pin the hex-encoded SHA-256 digest of a SubjectPublicKeyInfo
+    pub fn with_pinned_spki_sha256(mut self, pin: impl Into<String>) -> Self {
+        self.pinned_spki_sha256.push(pin.into());
+        self
+    }
+
+    /// Accept a TLS-intercepting proxy whose certificate authority is part of the CA bundle
+    pub fn with_tls_interception_allowed(mut self) -> Self {
+        self.allow_tls_interception = true;
+        self
+    }
+
+    /// Return the decoded pins
+    pub fn spki_pins(&self) -> Result<Vec<[u8; 32]>> {
+        self.pinned_spki_sha256
+            .iter()
+            .map(|pin| {
+                hex::decode(pin.trim())
+                    .ok()
+                    .and_then(|pin| <[u8; 32]>::try_from(pin).ok())
+                    .ok_or_else(|| {
+                        CliStateError::InvalidData(format!(
+                            "The pin {pin} is not a hex-encoded SHA-256 digest"
+                        ))
+                    })
+            })
+            .collect()
+    }
+
+    /// Check that the configuration is consistent
+    pub fn validate(&self) -> Result<()> {
+        self.spki_pins()?;
+        if self.ca_bundle.is_none() && (self.disable_system_roots || self.allow_tls_interception) {
+            return Err(CliStateError::InvalidData(
+                "A CA bundle is required when the system roots are disabled or when TLS interception is allowed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl CliState {
+    /// Return the TLS configuration of the connections to the Ockam cloud services.
+    /// The default configuration is returned if nothing has been configured
+    pub fn tls_config(&self) -> Result<TlsConfig> {
+        let path = self.dir.join(TLS_CONFIG_FILE);
+        if !path.exists() {
+            return Ok(TlsConfig::default());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Configure the TLS connections to the Ockam cloud services
+    pub fn set_tls_config(&self, config: &TlsConfig) -> Result<()> {
+        config.validate()?;
+        let contents = serde_json::to_string_pretty(config)?;
+        std::fs::write(self.dir.join(TLS_CONFIG_FILE), contents)?;
+        Ok(())
+    }
+
+    /// Restore the default TLS configuration
+    pub fn remove_tls_config(&self) -> Result<()> {
+        let path = self.dir.join(TLS_CONFIG_FILE);
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tls_config() -> Result<()> {
+        let state = CliState::test()?;
+        assert_eq!(state.tls_config()?, TlsConfig::default());
+
+        let pin = "a".repeat(64);
+        let config = TlsConfig::default()
+            .with_ca_bundle("corporate-ca.pem".into())
+            .with_pinned_spki_sha256(pin);
+        state.set_tls_config(&config)?;
+        assert_eq!(state.tls_config()?, config);
+        assert_eq!(config.spki_pins()?, vec![[0xaa; 32]]);
+
+        // pins must be SHA-256 digests
+        let invalid = TlsConfig::default().with_pinned_spki_sha256("abcd");
+        assert!(state.set_tls_config(&invalid).is_err());
+
+        // a CA bundle is required to intercept TLS connections
+        let invalid = TlsConfig::default().with_tls_interception_allowed();
+        assert!(state.set_tls_config(&invalid).is_err());
+
+        state.remove_tls_config()?;
+        assert_eq!(state.tls_config()?, TlsConfig::default());
+        Ok(())
+    }
+}
@@ -1,6 +1,9 @@
-use crate::cli_state::{file_stem, CliState, CliStateError};
-use ockam_core::errcode::{Kind, Origin};
-use ockam_core::{async_trait, Error};
+use crate::cli_state::locks::write_atomically;
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{
+    file_stem, AuditLog, CliStateError, DefaultsState, StateEvent, StateEvents, StateLock, Trash,
+};
+use ockam_core::async_trait;
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
@@ -18,6 +21,8 @@ pub trait StateDirTrait: Sized + Send + Sync {
     const DEFAULT_FILENAME: &'static str;
     const DIR_NAME: &'static str;
     const HAS_DATA_DIR: bool;
+    /// If true, the deleted items are moved to the trash, from which they can be restored
+    const SOFT_DELETE: bool = false;
 
     fn new(root_path: &Path) -> Self;
 
@@ -80,11 +85,24 @@ pub trait StateDirTrait: Sized + Send + Sync {
         &self,
         name: impl AsRef<str>,
         config: <<Self as StateDirTrait>::Item as StateItemTrait>::Config,
+    ) -> Result<Self::Item> {
+        check_writable(&self.path(&name))?;
+        let lock = self.lock()?;
+        self.overwrite_locked(&lock, name, config)
+    }
+
+    /// Same as [`StateDirTrait::overwrite`], while holding the lock of the state
+    fn overwrite_locked(
+        &self,
+        lock: &StateLock,
+        name: impl AsRef<str>,
+        config: <<Self as StateDirTrait>::Item as StateItemTrait>::Config,
     ) -> Result<Self::Item> {
         let path = self.path(&name);
+        check_writable(&path)?;
         let state = Self::Item::new(path, config)?;
-        if !self.default_path()?.exists() {
-            self.set_default(&name)?;
+        if self.default_name()?.is_none() {
+            self.set_default_locked(lock, &name)?;
         }
         Ok(state)
     }
@@ -93,8 +111,21 @@ pub trait StateDirTrait: Sized + Send + Sync {
         &self,
         name: impl AsRef<str>,
         config: <<Self as StateDirTrait>::Item as StateItemTrait>::Config,
+    ) -> Result<Self::Item> {
+        check_writable(self.dir())?;
+        let lock = self.lock()?;
+        self.create_locked(&lock, name, config)
+    }
+
+    /// Same as [`StateDirTrait::create`], while holding the lock of the state
+    fn create_locked(
+        &self,
+        lock: &StateLock,
+        name: impl AsRef<str>,
+        config: <<Self as StateDirTrait>::Item as StateItemTrait>::Config,
     ) -> Result<Self::Item> {
         debug!(name = %name.as_ref(), "Creating new config resource");
+        check_writable(self.dir())?;
         if self.exists(&name) {
             return Err(CliStateError::AlreadyExists {
                 resource: Self::default_filename().to_string(),
@@ -103,8 +134,12 @@ pub trait StateDirTrait: Sized + Send + Sync {
         }
         trace!(name = %name.as_ref(), "Creating config resource instance");
         let state = Self::Item::new(self.path(&name), config)?;
-        if !self.default_path()?.exists() {
-            self.set_default(&name)?;
+        self.events()
+            .emit(StateEvent::created(Self::default_filename(), name.as_ref()));
+        self.audit()
+            .created(Self::default_filename(), name.as_ref(), state.config());
+        if self.default_name()?.is_none() {
+            self.set_default_locked(lock, &name)?;
         }
         info!(name = %name.as_ref(), "Created new config resource");
         Ok(state)
@@ -164,33 +199,131 @@ pub trait StateDirTrait: Sized + Send + Sync {
 
     // TODO: move to StateItemTrait
     fn delete(&self, name: impl AsRef<str>) -> Result<()> {
+        check_writable(self.dir())?;
+        let lock = self.lock()?;
+        self.delete_locked(&lock, name)
+    }
+
+    /// Same as [`StateDirTrait::delete`], while holding the lock of the state
+    fn delete_locked(&self, lock: &StateLock, name: impl AsRef<str>) -> Result<()> {
+        check_writable(self.dir())?;
         // Retrieve state. If doesn't exist do nothing.
         let s = match self.get(&name) {
             Ok(project) => project,
             Err(CliStateError::ResourceNotFound { .. }) => return Ok(()),
             Err(e) => return Err(e),
         };
-        // If it's the default, remove link
-        if let Ok(default) = self.default() {
-            if default.path() == s.path() {
-                let _ = std::fs::remove_file(self.default_path()?);
-            }
+        // If it's the default, remove it from the defaults
+        if self.is_default(&name)? {
+            self.remove_default_locked(lock)?;
         }
         // Remove state data
-        s.delete()
+        self.discard(&name, &s)?;
+        self.events()
+            .emit(StateEvent::deleted(Self::default_filename(), name.as_ref()));
+        self.audit()
+            .deleted(Self::default_filename(), name.as_ref(), s.config());
+        Ok(())
+    }
+
+    /// Remove the files of a deleted item, or move them to the trash
+    fn discard(&self, name: impl AsRef<str>, item: &Self::Item) -> Result<()> {
+        if Self::SOFT_DELETE {
+            self.trash()
+                .put(Self::default_filename(), name.as_ref(), &item.files())?;
+            Ok(())
+        } else {
+            item.delete()
+        }
+    }
+
+    /// Restore the last deleted item with a given name from the trash
+    fn restore_deleted(&self, name: impl AsRef<str>) -> Result<Self::Item> {
+        check_writable(self.dir())?;
+        let lock = self.lock()?;
+        self.trash().take(Self::default_filename(), name.as_ref())?;
+        let item = self.get(&name)?;
+        self.events()
+            .emit(StateEvent::created(Self::default_filename(), name.as_ref()));
+        self.audit()
+            .created(Self::default_filename(), name.as_ref(), item.config());
+        if self.default_name()?.is_none() {
+            self.set_default_locked(&lock, &name)?;
+        }
+        info!(name = %name.as_ref(), "Restored a deleted config resource");
+        Ok(item)
+    }
+
+    /// Defaults of the state containing this directory
+    fn defaults(&self) -> DefaultsState {
+        let root_path = self.dir().parent().expect("Should have parent");
+        DefaultsState::new(root_path)
     }
 
-    fn default_path(&self) -> Result<PathBuf> {
+    /// Lock the state containing this directory, until the returned lock is dropped
+    fn lock(&self) -> Result<StateLock> {
+        StateLock::acquire(self.dir().parent().expect("Should have parent"))
+    }
+
+    /// Lock the state containing this directory, without blocking the thread while waiting
+    async fn lock_async(&self) -> Result<StateLock> {
+        StateLock::acquire_async(self.dir().parent().expect("Should have parent")).await
+    }
+
+    /// Log of the changes made to the state containing this directory
+    fn events(&self) -> StateEvents {
+        let root_path = self.dir().parent().expect("Should have parent");
+        StateEvents::new(root_path)
+    }
+
+    /// Trash of the state containing this directory
+    fn trash(&self) -> Trash {
         let root_path = self.dir().parent().expect("Should have parent");
-        Ok(CliState::defaults_dir(root_path)?.join(Self::default_filename()))
+        Trash::new(root_path)
+    }
+
+    /// Audit log of the state containing this directory
+    fn audit(&self) -> AuditLog {
+        let root_path = self.dir().parent().expect("Should have parent");
+        AuditLog::new(root_path)
+    }
+
+    /// Name of the default item, if it is set and the item still exists
+    fn default_name(&self) -> Result<Option<String>> {
+        Ok(self
+            .defaults()
+            .get(Self::default_filename())?
+            .filter(|name| self.exists(name)))
     }
 
     fn default(&self) -> Result<Self::Item> {
-        let path = std::fs::canonicalize(self.default_path()?)?;
-        Self::Item::load(path)
+        match self.defaults().get(Self::default_filename())? {
+            Some(name) => self.get(name),
+            None => Err(CliStateError::ResourceNotFound {
+                resource: Self::default_filename().to_string(),
+                name: "default".to_string(),
+            }),
+        }
+    }
+
+    fn remove_default(&self) -> Result<()> {
+        let lock = self.lock()?;
+        self.remove_default_locked(&lock)
+    }
+
+    /// Same as [`StateDirTrait::remove_default`], while holding the lock of the state
+    fn remove_default_locked(&self, lock: &StateLock) -> Result<()> {
+        self.defaults()
+            .remove_locked(lock, Self::default_filename())
     }
 
     fn set_default(&self, name: impl AsRef<str>) -> Result<()> {
+        let lock = self.lock()?;
+        self.set_default_locked(&lock, name)
+    }
+
+    /// Same as [`StateDirTrait::set_default`], while holding the lock of the state
+    fn set_default_locked(&self, lock: &StateLock, name: impl AsRef<str>) -> Result<()> {
         debug!(name = %name.as_ref(), "Setting default item");
         if !self.exists(&name) {
             return Err(CliStateError::ResourceNotFound {
@@ -198,29 +331,14 @@ pub trait StateDirTrait: Sized + Send + Sync {
                 name: name.as_ref().to_string(),
             });
         }
-        let original = self.path(&name);
-        let link = self.default_path()?;
-        info!("removing link {:?}", link);
-        // Remove link if it exists
-        let _ = std::fs::remove_file(&link);
-        info!("symlink to {:?}", original);
-        // Create link to the default item
-        std::fs::create_dir_all(link.parent().unwrap())
-            .map_err(|e| Error::new(Origin::Node, Kind::Io, e))?;
-        std::os::unix::fs::symlink(original, link)?;
+        self.defaults()
+            .set_locked(lock, Self::default_filename(), name.as_ref())?;
         info!(name = %name.as_ref(), "Set default item");
         Ok(())
     }
 
     fn is_default(&self, name: impl AsRef<str>) -> Result<bool> {
-        if !self.exists(&name) {
-            return Ok(false);
-        }
-        let default_name = {
-            let path = std::fs::canonicalize(self.default_path()?)?;
-            file_stem(&path)?
-        };
-        Ok(default_name.eq(name.as_ref()))
+        Ok(self.default_name()?.as_deref() == Some(name.as_ref()))
     }
 
     fn is_empty(&self) -> Result<bool> {
@@ -252,16 +370,22 @@ pub trait StateItemTrait: Sized + Send {
 
     /// Persist the item to disk after updating the config.
     fn persist(&self) -> Result<()> {
+        check_writable(self.path())?;
         let contents = serde_json::to_string(self.config())?;
-        std::fs::write(self.path(), contents)?;
-        Ok(())
+        write_atomically(self.path(), contents)
     }
     fn delete(&self) -> Result<()> {
+        check_writable(self.path())?;
         std::fs::remove_file(self.path())?;
         Ok(())
     }
     fn path(&self) -> &PathBuf;
     fn config(&self) -> &Self::Config;
+
+    /// Files storing the item, moved to the trash when the item is soft-deleted
+    fn files(&self) -> Vec<PathBuf> {
+        vec![self.path().clone()]
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,427 @@
+//! Transactions making the changes spanning several directories of the CLI state atomic.
+//! The journal of an interrupted transaction is rolled back when the state is opened again.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use ockam::identity::Identifier;
+use rand::random;
+use serde::{Deserialize, Serialize};
+
+use crate::cli_state::cached::now;
+use crate::cli_state::locks::write_atomically;
+use crate::cli_state::read_only::is_read_only;
+use crate::cli_state::{
+    random_name, CliState, CliStateError, IdentityState, NodeConfig, NodeState, StateDirTrait,
+    StateLock, Trash, VaultConfig, VaultState,
+};
+
+use super::Result;
+
+/// Name of the directory, in the state directory, containing the journals of the transactions
+pub const TRANSACTIONS_DIR_NAME: &str = "transactions";
+
+/// Extension of the journals which could not be rolled back
+const FAILED_JOURNAL_EXTENSION: &str = "failed";
+
+/// Changes made by a transaction, to undo if it is rolled back
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+struct Journal {
+    /// Process running the transaction
+    pid: u32,
+    /// Seconds since the Unix epoch
+    started_at: u64,
+    /// Kind and name of the items created by the transaction, in order of creation
+    created: Vec<(String, String)>,
+    /// Default items, by kind, before the transaction changed them
+    defaults: BTreeMap<String, Option<String>>,
+}
+
+impl Journal {
+    fn roll_back(&self, state: &CliState, lock: &StateLock) -> Result<()> {
+        let trash = Trash::new(&state.dir);
+        for (kind, name) in self.created.iter().rev() {
+            debug!(%kind, %name, "Removing an item created by a rolled back transaction");
+            // the items created by a rolled back transaction are not kept in the trash
+            match kind.as_str() {
+                "vault" if state.vaults.exists(name) => {
+                    state.vaults.delete_locked(lock, name)?;
+                    trash.remove(kind, name)?;
+                }
+                "identity" if state.identities.exists(name) => {
+                    state.identities.delete_locked(lock, name)?;
+                    trash.remove(kind, name)?;
+                }
+                "node" => state.nodes.delete_for_good_locked(lock, name)?,
+                _ => {}
+            }
+        }
+        for (kind, name) in &self.defaults {
+            match name {
+                Some(name) => state.defaults.set_locked(lock, kind, name)?,
+                None => state.defaults.remove_locked(lock, kind)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Changes of the state which are committed or rolled back as a whole.
+///
+/// A transaction must be finished explicitly, with [`StateTransaction::commit`],
+/// [`StateTransaction::roll_back`] or [`StateTransaction::finish`]. A transaction which is
+/// dropped before being finished is only rolled back the next time the state is opened.
+///
+/// The transaction holds the lock of the state until it is dropped, and its own operations
+/// are made with that lock
+#[derive(Debug)]
+pub struct StateTransaction<'a> {
+    state: &'a CliState,
+    path: PathBuf,
+    journal: Journal,
+    finished: bool,
+    lock: StateLock,
+}
+
+impl<'a> StateTransaction<'a> {
+    async fn new(state: &'a CliState) -> Result<Self> {
+        let lock = StateLock::acquire_async(&state.dir).await?;
+        let pid = std::process::id();
+        let path = state
+            .dir
+            .join(TRANSACTIONS_DIR_NAME)
+            .join(format!("{pid}-{}.json", hex::encode(random::<[u8; 8]>())));
+        Ok(Self {
+            state,
+            path,
+            journal: Journal {
+                pid,
+                started_at: now(),
+                ..Default::default()
+            },
+            finished: false,
+            lock,
+        })
+    }
+
+    /// Return the vault with the given name, or the default vault, or create a new vault
+    /// with a random name
+    pub async fn create_vault_state(&mut self, vault_name: Option<&str>) -> Result<VaultState> {
+        if let Ok(vault) = self.state.resolve_vault(vault_name) {
+            return self.state.vaults.get(vault.name);
+        }
+        self.create_vault(&random_name(), VaultConfig::default())
+            .await
+    }
+
+    /// Create a vault
+    pub async fn create_vault(&mut self, name: &str, config: VaultConfig) -> Result<VaultState> {
+        self.record_created("vault", name)?;
+        let created = self
+            .state
+            .vaults
+            .create_async_locked(&self.lock, name, config)
+            .await;
+        self.check_created(created)
+    }
+
+    /// Return the identity with the given name, or the default identity, or create the state
+    /// of an identity with the given name, or with a random name
+    pub async fn create_identity_state(
+        &mut self,
+        identifier: &Identifier,
+        identity_name: Option<&str>,
+    ) -> Result<IdentityState> {
+        if let Ok(identity) = self.state.identities.get_or_default(identity_name) {
+            return Ok(identity);
+        }
+        let name = identity_name
+            .map(|n| n.to_string())
+            .unwrap_or_else(random_name);
+        self.record_created("identity", &name)?;
+        let created = self
+            .state
+            .make_identity_state(&self.lock, identifier, Some(&name))
+            .await;
+        self.check_created(created)
+    }
+
+    /// Create a node
+    pub fn create_node(&mut self, name: &str, config: NodeConfig) -> Result<NodeState> {
+        self.record_created("node", name)?;
+        let created = self.state.nodes.create_locked(&self.lock, name, config);
+        self.check_created(created)
+    }
+
+    /// Create a node, or replace the configuration of an existing node.
+    /// Only the creation of a node is undone when the transaction is rolled back
+    pub fn overwrite_node(&mut self, name: &str, config: NodeConfig) -> Result<NodeState> {
+        if !self.state.nodes.exists(name) {
+            self.record_created("node", name)?;
+        }
+        self.state.nodes.overwrite_locked(&self.lock, name, config)
+    }
+
+    /// Set the default `vault`, `identity` or `node`
+    pub fn set_default(&mut self, kind: &str, name: &str) -> Result<()> {
+        self.record_default(kind)?;
+        match kind {
+            "vault" => self.state.vaults.set_default_locked(&self.lock, name),
+            "identity" => self.state.identities.set_default_locked(&self.lock, name),
+            "node" => self.state.nodes.set_default_locked(&self.lock, name),
+            _ => Err(CliStateError::InvalidOperation(format!(
+                "The default {kind} can't be set in a transaction"
+            ))),
+        }
+    }
+
+    /// Keep all the changes of the transaction
+    pub fn commit(mut self) -> Result<()> {
+        self.finished = true;
+        remove_journal(&self.path)
+    }
+
+    /// Undo all the changes of the transaction
+    pub fn roll_back(mut self) -> Result<()> {
+        self.finished = true;
+        self.journal.roll_back(self.state, &self.lock)?;
+        remove_journal(&self.path)
+    }
+
+    /// Commit the transaction if the result of its changes is a success, otherwise roll it back
+    /// and return the error of the changes
+    pub fn finish<T, E: From<CliStateError>>(
+        self,
+        result: std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        match result {
+            Ok(value) => {
+                self.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.roll_back()?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Record an item before creating it. Creating an item can also make it the default item
+    fn record_created(&mut self, kind: &str, name: &str) -> Result<()> {
+        self.record_default(kind)?;
+        self.journal
+            .created
+            .push((kind.to_string(), name.to_string()));
+        self.write()
+    }
+
+    /// Record the default item of a kind before changing it for the first time
+    fn record_default(&mut self, kind: &str) -> Result<()> {
+        if !self.journal.defaults.contains_key(kind) {
+            let name = self.state.defaults.get(kind)?;
+            self.journal.defaults.insert(kind.to_string(), name);
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// An item which already exists was not created by this transaction,
+    /// so it must not be removed when the transaction is rolled back
+    fn check_created<T>(&mut self, created: Result<T>) -> Result<T> {
+        if let Err(CliStateError::AlreadyExists { .. }) = &created {
+            self.journal.created.pop();
+            self.write()?;
+        }
+        created
+    }
+
+    /// The journal is only written once the transaction changes the state,
+    /// so that a transaction can read a read-only state
+    fn write(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        write_atomically(&self.path, serde_json::to_string(&self.journal)?)
+    }
+}
+
+impl Drop for StateTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.finished && self.path.exists() {
+            warn!(path = %self.path.display(), "A transaction was neither committed nor rolled back, it will be rolled back the next time the state is opened");
+        }
+    }
+}
+
+fn remove_journal(path: &Path) -> Result<()> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+impl CliState {
+    /// Start a transaction over this state, once the other commands modifying it
+    /// are finished
+    pub async fn transaction(&self) -> Result<StateTransaction<'_>> {
+        StateTransaction::new(self).await
+    }
+
+    /// Roll back the transactions which were not finished by the commands which started them,
+    /// and return the number of rolled back transactions.
+    ///
+    /// The journals which can't be read or rolled back are moved aside
+    pub(crate) fn recover_transactions(&self) -> Result<usize> {
+        let dir = self.dir.join(TRANSACTIONS_DIR_NAME);
+        if !dir.is_dir() || is_read_only(&self.dir) {
+            return Ok(0);
+        }
+        // a transaction may be running while another command holds the lock,
+        // the interrupted transactions are then recovered the next time the state is opened
+        let lock = match StateLock::try_acquire(&self.dir)? {
+            Some(lock) => lock,
+            None => return Ok(0),
+        };
+        let mut recovered = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension() != Some("json".as_ref()) {
+                continue;
+            }
+            let rolled_back = std::fs::read_to_string(&path)
+                .map_err(CliStateError::from)
+                .and_then(|journal| Ok(serde_json::from_str::<Journal>(&journal)?))
+                .and_then(|journal| {
+                    info!(path = %path.display(), pid = journal.pid, started_at = journal.started_at, "Rolling back an interrupted transaction");
+                    journal.roll_back(self, &lock)
+                });
+            match rolled_back {
+                Ok(()) => {
+                    remove_journal(&path)?;
+                    recovered += 1;
+                }
+                Err(e) => {
+                    let failed = path.with_extension(format!("json.{FAILED_JOURNAL_EXTENSION}"));
+                    warn!(%e, path = %path.display(), failed = %failed.display(), "Failed to roll back an interrupted transaction, moving its journal aside");
+                    std::fs::rename(&path, failed)?;
+                }
+            }
+        }
+        Ok(recovered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::VaultConfig;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_commit_and_roll_back() -> Result<()> {
+        let state = CliState::test()?;
+
+        let mut transaction = state.transaction().await?;
+        transaction
+            .create_vault("v1", VaultConfig::default())
+            .await?;
+        // an existing item is not removed by a rollback
+        assert!(transaction
+            .create_vault("v1", VaultConfig::default())
+            .await
+            .is_err());
+        transaction.commit()?;
+        let mut transaction = state.transaction().await?;
+        transaction
+            .create_vault("v2", VaultConfig::default())
+            .await?;
+        transaction.set_default("vault", "v2")?;
+        transaction.roll_back()?;
+        assert!(state.vaults.exists("v1"));
+        assert!(!state.vaults.exists("v2"));
+        assert!(state.vaults.is_default("v1")?);
+        assert!(state.list_deleted()?.is_empty());
+
+        // a failed change rolls back the transaction
+        let mut transaction = state.transaction().await?;
+        let created = transaction.create_vault("v3", VaultConfig::default()).await;
+        let result = created
+            .and_then(|_| Err::<(), _>(CliStateError::InvalidOperation("failed".to_string())));
+        assert!(transaction.finish(result).is_err());
+        assert!(!state.vaults.exists("v3"));
+        assert!(state
+            .dir
+            .join(TRANSACTIONS_DIR_NAME)
+            .read_dir()?
+            .next()
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transaction_holds_the_lock() -> Result<()> {
+        let state = CliState::test()?;
+        let mut transaction = state.transaction().await?;
+        transaction
+            .create_vault("v1", VaultConfig::default())
+            .await?;
+
+        // the other operations of the process wait for the transaction, on any thread
+        let timeout = StateLock::acquire_with_timeout(&state.dir, Duration::from_millis(100));
+        assert!(matches!(timeout, Err(CliStateError::LockTimeout { .. })));
+        let other = state.dir.clone();
+        let timeout = std::thread::spawn(move || {
+            StateLock::acquire_with_timeout(&other, Duration::from_millis(100)).map(|_| ())
+        })
+        .join()
+        .unwrap();
+        assert!(matches!(timeout, Err(CliStateError::LockTimeout { .. })));
+        // and the interrupted transactions are not recovered while it is running
+        assert_eq!(state.recover_transactions()?, 0);
+        assert!(state.vaults.exists("v1"));
+
+        transaction.commit()?;
+        drop(StateLock::acquire_with_timeout(
+            &state.dir,
+            Duration::from_millis(100),
+        )?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recover_interrupted_transaction() -> Result<()> {
+        let state = CliState::test()?;
+        let mut transaction = state.transaction().await?;
+        transaction.create_vault_state(None).await?;
+        transaction
+            .create_vault("v1", VaultConfig::default())
+            .await?;
+        // the journal of a command which stopped before finishing its transaction
+        let path = transaction.path.clone();
+        drop(transaction);
+        assert!(path.exists());
+        assert!(state.vaults.exists("v1"));
+
+        assert_eq!(state.recover_transactions()?, 1);
+        assert!(!path.exists());
+        assert!(!state.vaults.exists("v1"));
+        assert!(state.vaults.default().is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_move_aside_invalid_journal() -> Result<()> {
+        let state = CliState::test()?;
+        let dir = state.dir.join(TRANSACTIONS_DIR_NAME);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("1-0000000000000000.json");
+        std::fs::write(&path, "not a journal")?;
+
+        // the state can still be opened
+        assert_eq!(state.recover_transactions()?, 0);
+        assert!(!path.exists());
+        assert!(dir.join("1-0000000000000000.json.failed").exists());
+        assert_eq!(state.recover_transactions()?, 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! Trash of the deleted items of the CLI state.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cli_state::cached::now;
+use crate::cli_state::read_only::check_writable;
+use crate::cli_state::{CliState, CliStateError, StateDirTrait};
+
+use super::Result;
+
+/// Name of the directory, in the state directory, containing the deleted items
+pub const TRASH_DIR_NAME: &str = "trash";
+
+const TOMBSTONE_FILE_NAME: &str = "tombstone.json";
+
+/// Item moved to the trash
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct DeletedItem {
+    /// Kind of item, named after the `DEFAULT_FILENAME` of its state directory
+    pub kind: String,
+    pub name: String,
+    /// Seconds since the Unix epoch
+    pub deleted_at: u64,
+    /// Files of the item, relative to the state directory
+    pub files: Vec<PathBuf>,
+    /// Directory of the item in the trash
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+/// Trash of the state stored in a root directory
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Trash {
+    root_path: PathBuf,
+}
+
+impl Trash {
+    pub fn new(root_path: &Path) -> Self {
+        Self {
+            root_path: root_path.to_path_buf(),
+        }
+    }
+
+    /// Directory containing the deleted items
+    pub fn dir(&self) -> PathBuf {
+        self.root_path.join(TRASH_DIR_NAME)
+    }
+
+    /// Move the files of an item to the trash
+    pub fn put(&self, kind: &str, name: &str, files: &[PathBuf]) -> Result<DeletedItem> {
+        check_writable(&self.root_path)?;
+        let deleted_at = now();
+        let item_dir = self.dir().join(kind).join(name);
+        // an item can be deleted several times in the same second
+        let mut path = item_dir.join(deleted_at.to_string());
+        let mut index = 1;
+        while path.exists() {
+            path = item_dir.join(format!("{deleted_at}-{index}"));
+            index += 1;
+        }
+        std::fs::create_dir_all(&path)?;
+
+        let mut relative_files = vec![];
+        for file in files.iter().filter(|file| file.exists()) {
+            let relative = file
+                .strip_prefix(&self.root_path)
+                .map_err(|_| CliStateError::InvalidPath(file.to_string_lossy().to_string()))?;
+            let destination = path.join(relative);
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(file, destination)?;
+            relative_files.push(relative.to_path_buf());
+        }
+        let item = DeletedItem {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            deleted_at,
+            files: relative_files,
+            path,
+        };
+        std::fs::write(
+            item.path.join(TOMBSTONE_FILE_NAME),
+            serde_json::to_string(&item)?,
+        )?;
+        debug!(%kind, %name, "Moved a deleted item to the trash");
+        Ok(item)
+    }
+
+    /// Move the files of the last deleted item with a given kind and name back to the state.
+    /// Return an error if one of these files was created again since the item was deleted
+    pub fn take(&self, kind: &str, name: &str) -> Result<DeletedItem> {
+        check_writable(&self.root_path)?;
+        let item = self
+            .list()?
+            .into_iter()
+            .filter(|item| item.kind == kind && item.name == name)
+            .last()
+            .ok_or_else(|| CliStateError::ResourceNotFound {
+                resource: format!("deleted {kind}"),
+                name: name.to_string(),
+            })?;
+        if item
+            .files
+            .iter()
+            .any(|file| self.root_path.join(file).exists())
+        {
+            return Err(CliStateError::AlreadyExists {
+                resource: kind.to_string(),
+                name: name.to_string(),
+            });
+        }
+        for file in &item.files {
+            let destination = self.root_path.join(file);
+            if let Some(parent) = destination.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::rename(item.path.join(file), destination)?;
+        }
+        remove_item_dir(&item.path)?;
+        Ok(item)
+    }
+
+    /// Remove for good the last deleted item with a given kind and name, if there is one
+    pub fn remove(&self, kind: &str, name: &str) -> Result<Option<DeletedItem>> {
+        check_writable(&self.root_path)?;
+        let item = self
+            .list()?
+            .into_iter()
+            .filter(|item| item.kind == kind && item.name == name)
+            .last();
+        if let Some(item) = &item {
+            remove_item_dir(&item.path)?;
+        }
+        Ok(item)
+    }
+
+    /// Return the deleted items, from the oldest one
+    pub fn list(&self) -> Result<Vec<DeletedItem>> {
+        let mut items = vec![];
+        for kind_dir in read_dirs(&self.dir())? {
+            for name_dir in read_dirs(&kind_dir)? {
+                for path in read_dirs(&name_dir)? {
+                    let contents = match std::fs::read_to_string(path.join(TOMBSTONE_FILE_NAME)) {
+                        Ok(contents) => contents,
+                        Err(_) => continue,
+                    };
+                    match serde_json::from_str::<DeletedItem>(&contents) {
+                        Ok(item) => items.push(DeletedItem { path, ..item }),
+                        Err(e) => {
+                            warn!(%e, path = %path.display(), "Skipping an invalid tombstone")
+                        }
+                    }
+                }
+            }
+        }
+        items.sort_by(|a, b| (a.deleted_at, &a.path).cmp(&(b.deleted_at, &b.path)));
+        Ok(items)
+    }
+
+    /// Remove for good the items deleted at least `older_than` ago, and return them
+    pub fn purge(&self, older_than: Duration) -> Result<Vec<DeletedItem>> {
+        check_writable(&self.root_path)?;
+        let limit = now().saturating_sub(older_than.as_secs());
+        let mut purged = vec![];
+        for item in self.list()? {
+            if item.deleted_at <= limit {
+                remove_item_dir(&item.path)?;
+                info!(kind = %item.kind, name = %item.name, "Purged a deleted item");
+                purged.push(item);
+            }
+        }
+        Ok(purged)
+    }
+}
+
+/// Remove the directory of a deleted item, and the directories of its name and kind
+/// once they are empty
+fn remove_item_dir(path: &Path) -> Result<()> {
+    std::fs::remove_dir_all(path)?;
+    for dir in path.ancestors().skip(1).take(2) {
+        if std::fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn read_dirs(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut dirs = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+impl CliState {
+    /// Return the deleted items which can be restored, from the oldest one
+    pub fn list_deleted(&self) -> Result<Vec<DeletedItem>> {
+        Trash::new(&self.dir).list()
+    }
+
+    /// Restore the last deleted item of a given kind, `identity`, `vault`, `node`, `space`
+    /// or `project`, with a given name
+    pub fn restore_deleted(&self, resource: &str, name: &str) -> Result<()> {
+        match resource {
+            "identity" => self.identities.restore_deleted(name).map(|_| ()),
+            "vault" => self.vaults.restore_deleted(name).map(|_| ()),
+            "node" => self.nodes.restore_deleted(name).map(|_| ()),
+            "space" => self.spaces.restore_deleted(name).map(|_| ()),
+            "project" => self.projects.restore_deleted(name).map(|_| ()),
+            _ => Err(CliStateError::InvalidOperation(format!(
+                "The deleted items of kind {resource} can't be restored"
+            ))),
+        }
+    }
+
+    /// Remove for good the items deleted at least `older_than` ago, and return them.
+    /// All the deleted items are purged with a zero duration
+    pub fn purge_deleted(&self, older_than: Duration) -> Result<Vec<DeletedItem>> {
+        Trash::new(&self.dir).purge(older_than)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::{SpaceConfig, StateItemTrait, VaultConfig};
+
+    #[tokio::test]
+    async fn test_restore_deleted_items() -> Result<()> {
+        let state = CliState::test()?;
+        let vault = state
+            .vaults
+            .create_async("v1", VaultConfig::default())
+            .await?;
+        let vault_file = vault.vault_file_path().clone();
+        let space = SpaceConfig {
+            name: "s1".to_string(),
+            id: "id".to_string(),
+        };
+        state.spaces.create("s1", space)?;
+
+        state.vaults.delete("v1")?;
+        state.spaces.delete("s1")?;
+        assert!(!state.vaults.exists("v1"));
+        assert!(!vault_file.exists());
+        assert_eq!(state.list_deleted()?.len(), 2);
+
+        // the restored items get their files back, and become the default items again
+        state.restore_deleted("vault", "v1")?;
+        assert!(vault_file.exists());
+        assert_eq!(state.vaults.default()?.name(), "v1");
+        assert_eq!(state.list_deleted()?.len(), 1);
+        assert!(state.restore_deleted("vault", "v1").is_err());
+
+        // an item can't be restored over a new item with the same name
+        let space = SpaceConfig {
+            name: "s1".to_string(),
+            id: "id2".to_string(),
+        };
+        state.spaces.create("s1", space)?;
+        assert!(matches!(
+            state.restore_deleted("space", "s1"),
+            Err(CliStateError::AlreadyExists { .. })
+        ));
+        assert_eq!(state.spaces.get("s1")?.config().id, "id2");
+
+        assert_eq!(state.purge_deleted(Duration::from_secs(3600))?, vec![]);
+        assert_eq!(state.purge_deleted(Duration::ZERO)?.len(), 1);
+        assert!(state.list_deleted()?.is_empty());
+        assert!(!Trash::new(&state.dir).dir().join("space").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use ockam::identity::Identifier;
+
+use super::{CliState, Result};
+use crate::cli_state::cached::now;
+
+/// Name of the file, in the state directory, containing the identities verified by the user
+const TRUSTED_PEERS_FILE: &str = "trusted_peers.json";
+
+/// Attributes of the trusted peers, by identifier.
+///
+/// The file has the format of the trusted identities files, so that it can be given to
+/// `ockam node create --trusted-identities-file` to accept the verified peers in a node
+pub type TrustedPeers = BTreeMap<String, BTreeMap<String, String>>;
+
+impl CliState {
+    pub fn trusted_peers_path(&self) -> PathBuf {
+        self.dir.join(TRUSTED_PEERS_FILE)
+    }
+
+    /// Return the identities verified by the user
+    pub fn trusted_peers(&self) -> Result<TrustedPeers> {
+        let path = self.trusted_peers_path();
+        if !path.exists() {
+            return Ok(TrustedPeers::new());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Trust an identity verified by the user. The identity is replaced if it was already trusted
+    pub fn add_trusted_peer(
+        &self,
+        identifier: &Identifier,
+        name: Option<&str>,
+        verified_by: &str,
+    ) -> Result<()> {
+        let mut peers = self.trusted_peers()?;
+        let mut attributes = BTreeMap::from([
+            ("verified_by".to_string(), verified_by.to_string()),
+            ("verified_at".to_string(), now().to_string()),
+        ]);
+        if let Some(name) = name {
+            attributes.insert("name".to_string(), name.to_string());
+        }
+        peers.insert(identifier.to_string(), attributes);
+        let contents = serde_json::to_string_pretty(&peers)?;
+        std::fs::write(self.trusted_peers_path(), contents)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootstrapped_identities_store::PreTrustedIdentities;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_trusted_peers() {
+        let state = CliState::test().unwrap();
+        assert!(state.trusted_peers().unwrap().is_empty());
+
+        let identifier = Identifier::from_str("I0123456789abcdef0123456789abcdef01234567").unwrap();
+        state
+            .add_trusted_peer(&identifier, Some("bob"), "sas")
+            .unwrap();
+        let peers = state.trusted_peers().unwrap();
+        assert_eq!(peers[&identifier.to_string()]["name"], "bob");
+
+        // the file can be used as a trusted identities file
+        let trusted =
+            PreTrustedIdentities::new_from_disk(state.trusted_peers_path(), false).unwrap();
+        match trusted {
+            PreTrustedIdentities::Fixed(identities) => {
+                assert!(identities.contains_key(&identifier))
+            }
+            PreTrustedIdentities::ReloadFrom(_) => panic!("the identities should be loaded"),
+        }
+    }
+}
@@ -5,10 +5,15 @@ use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 use ockam::identity::Vault;
+use ockam_vault::{
+    SigningSecret, SigningSecretKeyHandle, SoftwareVaultForSigning, VaultForSigning,
+    VerifyingPublicKey,
+};
 use ockam_vault_aws::AwsSigningVault;
 
+use crate::cli_state::read_only::check_writable;
 use crate::cli_state::traits::StateItemTrait;
-use crate::cli_state::{CliStateError, StateDirTrait, DATA_DIR_NAME};
+use crate::cli_state::{CliStateError, StateDirTrait, StateEvent, StateLock, DATA_DIR_NAME};
 
 use super::Result;
 
@@ -19,6 +24,19 @@ pub struct VaultsState {
 
 impl VaultsState {
     pub async fn create_async(&self, name: &str, config: VaultConfig) -> Result<VaultState> {
+        check_writable(self.dir())?;
+        let lock = self.lock_async().await?;
+        self.create_async_locked(&lock, name, config).await
+    }
+
+    /// Same as [`VaultsState::create_async`], while holding the lock of the state
+    pub async fn create_async_locked(
+        &self,
+        lock: &StateLock,
+        name: &str,
+        config: VaultConfig,
+    ) -> Result<VaultState> {
+        check_writable(self.dir())?;
         if self.exists(name) {
             return Err(CliStateError::AlreadyExists {
                 resource: Self::default_filename().to_string(),
@@ -27,8 +45,12 @@ impl VaultsState {
         }
         let state = VaultState::new(self.path(name), config)?;
         state.get().await?;
-        if !self.default_path()?.exists() {
-            self.set_default(name)?;
+        self.events()
+            .emit(StateEvent::created(Self::default_filename(), name));
+        self.audit()
+            .created(Self::default_filename(), name, state.config());
+        if self.default_name()?.is_none() {
+            self.set_default_locked(lock, name)?;
         }
         Ok(state)
     }
@@ -52,6 +74,10 @@ impl VaultState {
             vault.credential_vault = aws_vault;
 
             Ok(vault)
+        } else if self.config.tpm {
+            self.tpm_vault().await
+        } else if self.config.platform {
+            self.platform_vault().await
         } else {
             let vault =
                 Vault::create_with_persistent_storage_path(self.vault_file_path().as_path())
@@ -60,6 +86,63 @@ impl VaultState {
         }
     }
 
+    /// The signing keys are kept in the TPM while the secure channel keys stay in the software vault
+    #[cfg(feature = "tpm")]
+    async fn tpm_vault(&self) -> Result<Vault> {
+        let mut vault =
+            Vault::create_with_persistent_storage_path(self.vault_file_path().as_path()).await?;
+        let tpm_vault = Arc::new(
+            ockam_vault_tpm::TpmSigningVault::create(self.tpm_keys_file_path().as_path()).await?,
+        );
+        vault.identity_vault = tpm_vault.clone();
+        vault.credential_vault = tpm_vault;
+        Ok(vault)
+    }
+
+    #[cfg(not(feature = "tpm"))]
+    async fn tpm_vault(&self) -> Result<Vault> {
+        Err(CliStateError::InvalidOperation(format!(
+            "The vault {} uses a TPM but this binary was built without TPM support",
+            self.name
+        )))
+    }
+
+    /// The signing keys are kept in the Secure Enclave or in the Android Keystore
+    #[cfg(any(feature = "secure-enclave", feature = "android-keystore"))]
+    async fn platform_vault(&self) -> Result<Vault> {
+        use ockam_vault_platform::{KeyOptions, PlatformSigningVault};
+
+        let mut vault =
+            Vault::create_with_persistent_storage_path(self.vault_file_path().as_path()).await?;
+        let options = KeyOptions::default().with_user_presence(self.config.user_presence.into());
+        let platform_vault = Arc::new(
+            PlatformSigningVault::create(options, self.platform_keys_file_path().as_path()).await?,
+        );
+        vault.identity_vault = platform_vault.clone();
+        vault.credential_vault = platform_vault;
+        Ok(vault)
+    }
+
+    #[cfg(not(any(feature = "secure-enclave", feature = "android-keystore")))]
+    async fn platform_vault(&self) -> Result<Vault> {
+        Err(CliStateError::InvalidOperation(format!(
+            "The vault {} uses the keys of the platform but this binary was built without support for them",
+            self.name
+        )))
+    }
+
+    /// The path to the file listing the keys created in the key store of the platform
+    pub fn platform_keys_file_path(&self) -> PathBuf {
+        self.data_path
+            .with_file_name(format!("{}-platform-keys.json", self.name))
+    }
+
+    /// The path to the file storing the keys wrapped by the TPM, contained in the data directory
+    pub fn tpm_keys_file_path(&self) -> PathBuf {
+        self.data_path
+            .with_file_name(format!("{}-tpm-keys.json", self.name))
+    }
+
     fn build_data_path(name: &str, path: &Path) -> PathBuf {
         path.parent()
             .expect("Should have parent")
@@ -77,6 +160,42 @@ impl VaultState {
         Ok(vault)
     }
 
+    /// Import an existing signing key, for example a key read from a PEM file,
+    /// so that it can be used as the root key of a new identity
+    pub async fn import_signing_key(
+        &self,
+        secret: SigningSecret,
+    ) -> Result<SigningSecretKeyHandle> {
+        Ok(self
+            .software_signing_vault()
+            .await?
+            .import_key(secret)
+            .await?)
+    }
+
+    /// Export the signing key corresponding to a public key
+    pub async fn export_signing_key(
+        &self,
+        public_key: &VerifyingPublicKey,
+    ) -> Result<SigningSecret> {
+        let vault = self.software_signing_vault().await?;
+        let handle = vault.get_secret_key_handle(public_key).await?;
+        Ok(vault.export_key(&handle).await?)
+    }
+
+    /// Signing keys can only be imported to and exported from a software vault
+    async fn software_signing_vault(&self) -> Result<SoftwareVaultForSigning> {
+        if self.is_aws() || self.is_tpm() || self.is_platform() {
+            return Err(CliStateError::InvalidOperation(format!(
+                "The keys of the vault {} can't be imported or exported",
+                self.name
+            )));
+        }
+        let storage =
+            ockam_vault::storage::PersistentStorage::create(self.vault_file_path()).await?;
+        Ok(SoftwareVaultForSigning::new(storage))
+    }
+
     pub fn name(&self) -> &str {
         &self.name
     }
@@ -84,19 +203,20 @@ impl VaultState {
     pub fn is_aws(&self) -> bool {
         self.config.is_aws()
     }
+
+    pub fn is_tpm(&self) -> bool {
+        self.config.is_tpm()
+    }
+
+    pub fn is_platform(&self) -> bool {
+        self.config.is_platform()
+    }
 }
 
 impl Display for VaultState {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Name: {}", self.name)?;
-        writeln!(
-            f,
-            "Type: {}",
-            match self.config.is_aws() {
-                true => "AWS KMS",
-                false => "OCKAM",
-            }
-        )?;
+        writeln!(f, "Type: {}", self.config.kind())?;
         Ok(())
     }
 }
@@ -105,16 +225,86 @@ impl Display for VaultState {
 pub struct VaultConfig {
     #[serde(default)]
     aws_kms: bool,
+    #[serde(default)]
+    tpm: bool,
+    #[serde(default)]
+    platform: bool,
+    /// Presence of the user required to sign with a key of the platform
+    #[serde(default)]
+    user_presence: UserPresence,
+}
+
+/// Presence of the user required to sign with a key of the Secure Enclave or of the Android Keystore
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Eq, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserPresence {
+    #[default]
+    None,
+    Biometry,
+    BiometryOrPasscode,
+}
+
+#[cfg(any(feature = "secure-enclave", feature = "android-keystore"))]
+impl From<UserPresence> for ockam_vault_platform::UserPresence {
+    fn from(user_presence: UserPresence) -> Self {
+        match user_presence {
+            UserPresence::None => Self::None,
+            UserPresence::Biometry => Self::Biometry,
+            UserPresence::BiometryOrPasscode => Self::BiometryOrPasscode,
+        }
+    }
 }
 
 impl VaultConfig {
-    pub fn new(aws_kms: bool) -> Result<Self> {
-        Ok(Self { aws_kms })
+    pub fn new(aws_kms: bool, tpm: bool) -> Result<Self> {
+        if aws_kms && tpm {
+            return Err(CliStateError::InvalidData(
+                "A vault can't use both AWS KMS and a TPM".to_string(),
+            ));
+        }
+        Ok(Self {
+            aws_kms,
+            tpm,
+            ..Default::default()
+        })
+    }
+
+    /// Configuration of a vault keeping its signing keys in the Apple Secure Enclave
+    /// or in the Android Keystore
+    pub fn platform(user_presence: UserPresence) -> Self {
+        Self {
+            platform: true,
+            user_presence,
+            ..Default::default()
+        }
     }
 
     pub fn is_aws(&self) -> bool {
         self.aws_kms
     }
+
+    pub fn is_tpm(&self) -> bool {
+        self.tpm
+    }
+
+    pub fn is_platform(&self) -> bool {
+        self.platform
+    }
+
+    /// Name of the kind of vault, as displayed to users
+    pub fn kind(&self) -> &'static str {
+        if self.aws_kms {
+            "AWS KMS"
+        } else if self.tpm {
+            "TPM"
+        } else if self.platform && cfg!(target_os = "android") {
+            "ANDROID KEYSTORE"
+        } else if self.platform {
+            "SECURE ENCLAVE"
+        } else {
+            "OCKAM"
+        }
+    }
 }
 
 mod traits {
@@ -131,6 +321,7 @@ mod traits {
         const DEFAULT_FILENAME: &'static str = "vault";
         const DIR_NAME: &'static str = "vaults";
         const HAS_DATA_DIR: bool = true;
+        const SOFT_DELETE: bool = true;
 
         fn new(root_path: &Path) -> Self {
             Self {
@@ -150,20 +341,23 @@ mod traits {
             unreachable!()
         }
 
-        fn delete(&self, name: impl AsRef<str>) -> Result<()> {
+        fn delete_locked(&self, lock: &StateLock, name: impl AsRef<str>) -> Result<()> {
+            check_writable(self.dir())?;
             // If doesn't exist do nothing.
             if !self.exists(&name) {
                 return Ok(());
             }
             let vault = self.get(&name)?;
-            // If it's the default, remove link
-            if let Ok(default) = self.default() {
-                if default.path == vault.path {
-                    let _ = std::fs::remove_file(self.default_path()?);
-                }
+            // If it's the default, remove it from the defaults
+            if self.is_default(&name)? {
+                self.remove_default_locked(lock)?;
             }
             // Remove vault files
-            vault.delete()?;
+            self.discard(&name, &vault)?;
+            self.events()
+                .emit(StateEvent::deleted(Self::default_filename(), name.as_ref()));
+            self.audit()
+                .deleted(Self::default_filename(), name.as_ref(), vault.config());
             Ok(())
         }
     }
@@ -200,12 +394,29 @@ mod traits {
         }
 
         fn delete(&self) -> Result<()> {
+            check_writable(&self.path)?;
             std::fs::remove_file(&self.path)?;
             std::fs::remove_file(&self.data_path)?;
             std::fs::remove_file(self.data_path.with_extension("json.lock"))?;
+            for keys_path in [self.tpm_keys_file_path(), self.platform_keys_file_path()] {
+                if keys_path.exists() {
+                    std::fs::remove_file(&keys_path)?;
+                    let _ = std::fs::remove_file(keys_path.with_extension("json.lock"));
+                }
+            }
             Ok(())
         }
 
+        fn files(&self) -> Vec<PathBuf> {
+            vec![
+                self.path.clone(),
+                self.data_path.clone(),
+                self.data_path.with_extension("json.lock"),
+                self.tpm_keys_file_path(),
+                self.platform_keys_file_path(),
+            ]
+        }
+
         fn path(&self) -> &PathBuf {
             &self.path
         }
@@ -134,11 +134,8 @@ impl Addons for Controller {
             "/v1/projects/{project_id}/configure_addon/confluent"
         ))
         .body(config);
-        self.0
-            .ask(ctx, API_SERVICE, req)
+        self.ask_api(ctx, 1, API_SERVICE, req)
             .await
-            .into_diagnostic()?
-            .success()
             .into_diagnostic()
     }
 
@@ -151,11 +148,8 @@ impl Addons for Controller {
         trace!(target: TARGET, project_id, "configuring okta addon");
         let req =
             Request::post(format!("/v1/projects/{project_id}/configure_addon/okta")).body(config);
-        self.0
-            .ask(ctx, API_SERVICE, req)
+        self.ask_api(ctx, 1, API_SERVICE, req)
             .await
-            .into_diagnostic()?
-            .success()
             .into_diagnostic()
     }
 
@@ -171,11 +165,8 @@ impl Addons for Controller {
             "/v1/projects/{project_id}/configure_addon/influxdb_token_lease_manager"
         ))
         .body(config);
-        self.0
-            .ask(ctx, API_SERVICE, req)
+        self.ask_api(ctx, 1, API_SERVICE, req)
             .await
-            .into_diagnostic()?
-            .success()
             .into_diagnostic()
     }
 
@@ -188,11 +179,8 @@ impl Addons for Controller {
         trace!(target: TARGET, project_id, "disabling addon");
         let req = Request::post(format!("/v1/projects/{project_id}/disable_addon"))
             .body(DisableAddon::new(addon_id));
-        self.0
-            .ask(ctx, API_SERVICE, req)
+        self.ask_api(ctx, 1, API_SERVICE, req)
             .await
-            .into_diagnostic()?
-            .success()
             .into_diagnostic()
     }
 }
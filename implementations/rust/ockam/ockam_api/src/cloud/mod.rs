@@ -9,3 +9,5 @@ pub mod secure_clients;
 pub mod share;
 pub mod space;
 pub mod subscription;
+pub mod tls;
+pub mod version;
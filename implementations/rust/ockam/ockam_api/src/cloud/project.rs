@@ -136,6 +136,12 @@ pub struct ProjectVersion {
     /// The version of the Projects
     #[cbor(n(2))]
     pub project_version: Option<String>,
+
+    /// The versions of the API supported by the Orchestrator Controller.
+    /// The controllers released before the API versions were advertised don't set this field
+    #[cbor(n(3))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_versions: Option<Vec<u16>>,
 }
 
 #[derive(Encode, Decode, Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -281,11 +287,8 @@ impl Projects for Controller {
         trace!(target: TARGET, %space_id, project_name = name, "creating project");
         let req = Request::post(format!("/v1/spaces/{space_id}/projects"))
             .body(CreateProject::new(name, users));
-        self.0
-            .ask(ctx, "projects", req)
+        self.ask_api(ctx, 1, "projects", req)
             .await
-            .into_diagnostic()?
-            .success()
             .into_diagnostic()
     }
 
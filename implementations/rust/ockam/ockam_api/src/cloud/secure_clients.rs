@@ -9,8 +9,10 @@ use ockam_multiaddr::MultiAddr;
 use ockam_node::Context;
 use ockam_transport_tcp::TcpTransport;
 
+use crate::cli_state::ProxyConfig;
+use crate::cloud::version::NegotiatedVersion;
 use crate::error::ApiError;
-use crate::multiaddr_to_route;
+use crate::multiaddr_to_route_through_proxy;
 use crate::nodes::NodeManager;
 
 pub const OCKAM_CONTROLLER_ADDR: &str = "OCKAM_CONTROLLER_ADDR";
@@ -30,12 +32,23 @@ pub const ORCHESTRATOR_AWAIT_TIMEOUT_MS: usize = 60 * 10 * 1000;
 
 impl NodeManager {
     pub(crate) async fn create_controller_client(&self) -> Result<Controller> {
-        NodeManager::controller_node(
+        let controller = NodeManager::controller_node(
             &self.tcp_transport,
             self.secure_channels.clone(),
             &self.get_identifier(None).await?,
+            self.cli_state.proxy_config()?.as_ref(),
         )
-        .await
+        .await?;
+        // the Controller requests can take as long as a restart of the Orchestrator
+        let timeouts = self.timeouts().with_management_request(
+            self.timeouts()
+                .management_request()
+                .max(Duration::from_secs(ORCHESTRATOR_RESTART_TIMEOUT)),
+        );
+        Ok(Controller(
+            controller.0.with_timeouts(&timeouts),
+            controller.1,
+        ))
     }
 
     pub(crate) async fn make_authority_node_client(
@@ -44,14 +57,18 @@ impl NodeManager {
         authority_multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
     ) -> Result<AuthorityNode> {
-        NodeManager::authority_node(
+        let authority_node = NodeManager::authority_node(
             &self.tcp_transport,
             self.secure_channels.clone(),
             authority_identifier,
             authority_multiaddr,
             caller_identifier,
+            self.cli_state.proxy_config()?.as_ref(),
         )
-        .await
+        .await?;
+        Ok(AuthorityNode(
+            authority_node.0.with_timeouts(self.timeouts()),
+        ))
     }
 
     pub(crate) async fn make_project_node_client(
@@ -60,14 +77,16 @@ impl NodeManager {
         project_multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
     ) -> Result<ProjectNode> {
-        NodeManager::project_node(
+        let project_node = NodeManager::project_node(
             &self.tcp_transport,
             self.secure_channels.clone(),
             project_identifier,
             project_multiaddr,
             caller_identifier,
+            self.cli_state.proxy_config()?.as_ref(),
         )
-        .await
+        .await?;
+        Ok(ProjectNode(project_node.0.with_timeouts(self.timeouts())))
     }
 
     pub async fn make_secure_client(
@@ -76,31 +95,37 @@ impl NodeManager {
         multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
     ) -> Result<SecureClient> {
-        NodeManager::generic(
+        Ok(NodeManager::generic(
             &self.tcp_transport,
             self.secure_channels.clone(),
             identifier,
             multiaddr,
             caller_identifier,
+            self.cli_state.proxy_config()?.as_ref(),
         )
-        .await
+        .await?
+        .with_timeouts(self.timeouts()))
     }
 
     pub async fn controller_node(
         tcp_transport: &TcpTransport,
         secure_channels: Arc<SecureChannels>,
         caller_identifier: &Identifier,
+        proxy_config: Option<&ProxyConfig>,
     ) -> Result<Controller> {
-        let controller_route = Self::controller_route(tcp_transport).await?;
+        let controller_route = Self::controller_route(tcp_transport, proxy_config).await?;
         let controller_identifier = Self::load_controller_identifier()?;
 
-        Ok(Controller(SecureClient::new(
-            secure_channels,
-            controller_route,
-            &controller_identifier,
-            caller_identifier,
-            Duration::from_secs(ORCHESTRATOR_RESTART_TIMEOUT),
-        )))
+        Ok(Controller(
+            SecureClient::new(
+                secure_channels,
+                controller_route,
+                &controller_identifier,
+                caller_identifier,
+                Duration::from_secs(ORCHESTRATOR_RESTART_TIMEOUT),
+            ),
+            NegotiatedVersion::default(),
+        ))
     }
 
     pub async fn authority_node(
@@ -109,9 +134,10 @@ impl NodeManager {
         authority_identifier: &Identifier,
         authority_multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
+        proxy_config: Option<&ProxyConfig>,
     ) -> Result<AuthorityNode> {
         let authority_route =
-            Self::resolve_secure_route(tcp_transport, authority_multiaddr).await?;
+            Self::resolve_secure_route(tcp_transport, authority_multiaddr, proxy_config).await?;
 
         Ok(AuthorityNode(SecureClient::new(
             secure_channels,
@@ -128,8 +154,10 @@ impl NodeManager {
         project_identifier: &Identifier,
         project_multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
+        proxy_config: Option<&ProxyConfig>,
     ) -> Result<ProjectNode> {
-        let project_route = Self::resolve_secure_route(tcp_transport, project_multiaddr).await?;
+        let project_route =
+            Self::resolve_secure_route(tcp_transport, project_multiaddr, proxy_config).await?;
 
         Ok(ProjectNode(SecureClient::new(
             secure_channels,
@@ -146,8 +174,9 @@ impl NodeManager {
         identifier: &Identifier,
         multiaddr: &MultiAddr,
         caller_identifier: &Identifier,
+        proxy_config: Option<&ProxyConfig>,
     ) -> Result<SecureClient> {
-        let route = Self::resolve_secure_route(tcp_transport, multiaddr).await?;
+        let route = Self::resolve_secure_route(tcp_transport, multiaddr, proxy_config).await?;
 
         Ok(SecureClient::new(
             secure_channels,
@@ -175,15 +204,21 @@ impl NodeManager {
         get_env_with_default::<MultiAddr>(OCKAM_CONTROLLER_ADDR, default_addr).unwrap()
     }
 
-    async fn controller_route(tcp_transport: &TcpTransport) -> Result<Route> {
-        Self::resolve_secure_route(tcp_transport, &Self::controller_multiaddr()).await
+    async fn controller_route(
+        tcp_transport: &TcpTransport,
+        proxy_config: Option<&ProxyConfig>,
+    ) -> Result<Route> {
+        Self::resolve_secure_route(tcp_transport, &Self::controller_multiaddr(), proxy_config).await
     }
 
+    /// Connect to a cloud service, through a proxy if one is configured
+    /// or defined by the environment variables
     async fn resolve_secure_route(
         tcp_transport: &TcpTransport,
         multiaddr: &MultiAddr,
+        proxy_config: Option<&ProxyConfig>,
     ) -> Result<Route> {
-        let secure_route = multiaddr_to_route(multiaddr, tcp_transport)
+        let secure_route = multiaddr_to_route_through_proxy(multiaddr, tcp_transport, proxy_config)
             .await
             .ok_or_else(|| {
                 ApiError::core(format!(
@@ -198,7 +233,8 @@ impl NodeManager {
 
 pub struct AuthorityNode(pub(crate) SecureClient);
 pub struct ProjectNode(pub(crate) SecureClient);
-pub struct Controller(pub(crate) SecureClient);
+/// Client of the Orchestrator Controller, with the version of its API once negotiated
+pub struct Controller(pub(crate) SecureClient, pub(crate) NegotiatedVersion);
 
 pub trait HasSecureClient {
     fn get_secure_client(&self) -> &SecureClient;
@@ -67,6 +67,60 @@ pub struct Subscription {
     pub space_id: Option<String>,
 }
 
+/// Usage of a resource limited by the plan of a space, for example the number of relays
+#[derive(Encode, Decode, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ResourceUsage {
+    #[n(1)] pub resource: String,
+    #[n(2)] pub used: u64,
+    /// `None` when the plan doesn't limit this resource
+    #[n(3)] pub limit: Option<u64>,
+}
+
+impl ResourceUsage {
+    /// Return the number of resources which can still be created
+    pub fn remaining(&self) -> Option<u64> {
+        self.limit.map(|limit| limit.saturating_sub(self.used))
+    }
+
+    /// Return true if `count` more resources can be created without exceeding the limit
+    pub fn allows(&self, count: u64) -> bool {
+        self.remaining().map(|r| count <= r).unwrap_or(true)
+    }
+}
+
+/// Plan of a space, with the limits and the current usage of its resources
+#[derive(Encode, Decode, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SubscriptionUsage {
+    #[n(1)] pub space_id: String,
+    #[n(2)] pub plan: String,
+    /// End of the trial period, as a number of seconds since the Unix epoch
+    #[n(3)] pub trial_ends_at: Option<u64>,
+    #[n(4)] pub resources: Vec<ResourceUsage>,
+}
+
+impl SubscriptionUsage {
+    pub fn resource(&self, resource: &str) -> Option<&ResourceUsage> {
+        self.resources.iter().find(|r| r.resource == resource)
+    }
+
+    /// Return true if `count` more resources can be created.
+    /// The resources which are not listed are not limited
+    pub fn allows(&self, resource: &str, count: u64) -> bool {
+        self.resource(resource)
+            .map(|r| r.allows(count))
+            .unwrap_or(true)
+    }
+
+    /// Return true if the trial period ended at the given time, in seconds since the Unix epoch
+    pub fn is_trial_expired(&self, now: u64) -> bool {
+        self.trial_ends_at.map(|end| end <= now).unwrap_or(false)
+    }
+}
+
 #[async_trait]
 pub trait Subscriptions {
     async fn activate_subscription(
@@ -109,6 +163,12 @@ pub trait Subscriptions {
         ctx: &Context,
         space_id: String,
     ) -> Result<Reply<Subscription>>;
+
+    async fn get_subscription_usage(
+        &self,
+        ctx: &Context,
+        space_id: String,
+    ) -> Result<Reply<SubscriptionUsage>>;
 }
 
 #[async_trait]
@@ -190,6 +250,16 @@ impl Subscriptions for Controller {
             )),
         }
     }
+
+    async fn get_subscription_usage(
+        &self,
+        ctx: &Context,
+        space_id: String,
+    ) -> Result<Reply<SubscriptionUsage>> {
+        trace!(target: TARGET, space = %space_id, "getting subscription usage");
+        let req = Request::get(format!("/v0/spaces/{space_id}/usage"));
+        self.0.ask(ctx, API_SERVICE, req).await
+    }
 }
 
 #[cfg(test)]
@@ -208,6 +278,50 @@ pub mod tests {
         fn activate_subcription(s: ActivateSubscription) -> TestResult {
             validate_with_schema("activate_subscription", s)
         }
+
+        fn subscription_usage(s: SubscriptionUsage) -> TestResult {
+            validate_with_schema("subscription_usage", s)
+        }
+    }
+
+    #[test]
+    fn test_subscription_usage() {
+        let usage = SubscriptionUsage {
+            space_id: "space".into(),
+            plan: "free".into(),
+            trial_ends_at: Some(100),
+            resources: vec![ResourceUsage {
+                resource: "relays".into(),
+                used: 4,
+                limit: Some(5),
+            }],
+        };
+        assert!(usage.allows("relays", 1));
+        assert!(!usage.allows("relays", 2));
+        assert!(usage.allows("projects", 10));
+        assert!(!usage.is_trial_expired(99));
+        assert!(usage.is_trial_expired(100));
+    }
+
+    impl Arbitrary for ResourceUsage {
+        fn arbitrary(g: &mut Gen) -> Self {
+            ResourceUsage {
+                resource: String::arbitrary(g),
+                used: u64::arbitrary(g),
+                limit: Option::arbitrary(g),
+            }
+        }
+    }
+
+    impl Arbitrary for SubscriptionUsage {
+        fn arbitrary(g: &mut Gen) -> Self {
+            SubscriptionUsage {
+                space_id: String::arbitrary(g),
+                plan: String::arbitrary(g),
+                trial_ends_at: Option::arbitrary(g),
+                resources: Vec::arbitrary(g),
+            }
+        }
     }
 
     impl Arbitrary for Subscription {
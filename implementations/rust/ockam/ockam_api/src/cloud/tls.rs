@@ -0,0 +1,204 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+use sha2::{Digest, Sha256};
+use tracing::{debug, warn};
+
+use ockam_core::Result;
+
+use crate::cli_state::{ProxyConfig, TlsConfig};
+use crate::error::ApiError;
+
+/// Create an HTTP client for the Ockam cloud services, using a specific TLS configuration.
+/// If a proxy is configured, HTTPS requests are tunneled through it, otherwise the proxy
+/// environment variables are used
+pub fn http_client(
+    config: &TlsConfig,
+    proxy_config: Option<&ProxyConfig>,
+) -> Result<reqwest::Client> {
+    let mut builder =
+        reqwest::ClientBuilder::new().use_preconfigured_tls(rustls_client_config(config)?);
+    if let Some(proxy_config) = proxy_config {
+        builder = builder.no_proxy().proxy(http_proxy(proxy_config)?);
+    }
+    builder.build().map_err(|e| ApiError::core(e.to_string()))
+}
+
+/// Create a reqwest proxy, skipping the hosts excluded by the proxy configuration
+fn http_proxy(proxy_config: &ProxyConfig) -> Result<reqwest::Proxy> {
+    proxy_config.validate()?;
+    let proxy_config = proxy_config.clone();
+    Ok(reqwest::Proxy::custom(move |url| {
+        let peer = format!("{}:{}", url.host_str()?, url.port_or_known_default()?);
+        let proxy = proxy_config.proxy_for(&peer).ok()??;
+        let mut proxy_url = reqwest::Url::parse(&format!("http://{}", proxy.address())).ok()?;
+        if let Some((username, password)) = proxy.credentials() {
+            proxy_url.set_username(username).ok()?;
+            proxy_url.set_password(Some(password)).ok()?;
+        }
+        Some(proxy_url)
+    }))
+}
+
+/// Create a rustls client configuration:
+///  - trusting the system root certificates (unless disabled) and the certificates of the CA bundle
+///  - checking the certificate pins, unless TLS interception has been allowed
+pub fn rustls_client_config(config: &TlsConfig) -> Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    if !config.disable_system_roots {
+        let certificates = rustls_native_certs::load_native_certs()
+            .map_err(|e| ApiError::core(format!("Error loading the system certificates: {e}")))?;
+        let certificates: Vec<Vec<u8>> = certificates.into_iter().map(|c| c.0).collect();
+        let (_, ignored) = roots.add_parsable_certificates(&certificates);
+        if ignored > 0 {
+            debug!("ignored {ignored} invalid system certificates");
+        }
+    }
+
+    if let Some(ca_bundle) = &config.ca_bundle {
+        let file = File::open(ca_bundle).map_err(|e| {
+            ApiError::core(format!(
+                "Error opening the CA bundle {}: {e}",
+                ca_bundle.display()
+            ))
+        })?;
+        let certificates = rustls_pemfile::certs(&mut BufReader::new(file))
+            .map_err(|e| ApiError::core(format!("Error parsing the CA bundle: {e}")))?;
+        let (added, _) = roots.add_parsable_certificates(&certificates);
+        if added == 0 {
+            return Err(ApiError::core(format!(
+                "The CA bundle {} doesn't contain any valid certificate",
+                ca_bundle.display()
+            )));
+        }
+    }
+
+    let pins = config
+        .spki_pins()
+        .map_err(|e| ApiError::core(e.to_string()))?;
+    let builder = ClientConfig::builder().with_safe_defaults();
+    if pins.is_empty() {
+        return Ok(builder.with_root_certificates(roots).with_no_client_auth());
+    }
+    if config.allow_tls_interception {
+        warn!("TLS interception is allowed, the certificate pins are not enforced");
+        return Ok(builder.with_root_certificates(roots).with_no_client_auth());
+    }
+
+    let verifier = PinnedCertVerifier {
+        inner: WebPkiVerifier::new(roots, None),
+        pins,
+    };
+    Ok(builder
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
+
+/// Certificate verifier checking, in addition to the usual verification, that the presented
+/// certificate chain contains a public key which has been pinned
+struct PinnedCertVerifier {
+    inner: WebPkiVerifier,
+    pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+
+        let pinned = std::iter::once(end_entity)
+            .chain(intermediates.iter())
+            .filter_map(|certificate| subject_public_key_info(&certificate.0))
+            .any(|spki| {
+                let digest: [u8; 32] = Sha256::digest(spki).into();
+                self.pins.contains(&digest)
+            });
+        if pinned {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(
+                "the server certificate doesn't match any pinned public key".to_string(),
+            ))
+        }
+    }
+}
+
+/// Return the DER-encoded SubjectPublicKeyInfo of a DER-encoded X.509 certificate
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let (certificate, _) = der_element(certificate)?;
+    let (mut tbs_certificate, _) = der_element(certificate)?;
+    // skip the optional version
+    if tbs_certificate.first() == Some(&0xa0) {
+        tbs_certificate = der_element(tbs_certificate)?.1;
+    }
+    // skip the serial number, signature algorithm, issuer, validity and subject
+    for _ in 0..5 {
+        tbs_certificate = der_element(tbs_certificate)?.1;
+    }
+    let (_, rest) = der_element(tbs_certificate)?;
+    Some(&tbs_certificate[..tbs_certificate.len() - rest.len()])
+}
+
+/// Split a DER element into its content and the bytes following it
+fn der_element(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let first_length_byte = *data.get(1)? as usize;
+    let (length, header_length) = if first_length_byte < 0x80 {
+        (first_length_byte, 2)
+    } else {
+        let length_bytes = first_length_byte & 0x7f;
+        if length_bytes == 0 || length_bytes > 4 {
+            return None;
+        }
+        let length = data
+            .get(2..2 + length_bytes)?
+            .iter()
+            .fold(0usize, |length, b| (length << 8) | *b as usize);
+        (length, 2 + length_bytes)
+    };
+    let content = data.get(header_length..header_length + length)?;
+    Some((content, &data[header_length + length..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subject_public_key_info() {
+        let spki = [0x30, 0x03, 0x03, 0x01, 0x00];
+        let mut tbs_certificate = vec![
+            0xa0, 0x03, 0x02, 0x01, 0x02, // version
+            0x02, 0x01, 0x01, // serial number
+            0x30, 0x00, // signature algorithm
+            0x30, 0x00, // issuer
+            0x30, 0x00, // validity
+            0x30, 0x00, // subject
+        ];
+        tbs_certificate.extend_from_slice(&spki);
+        let mut certificate = vec![0x30, tbs_certificate.len() as u8 + 2];
+        certificate.push(0x30);
+        certificate.push(tbs_certificate.len() as u8);
+        certificate.extend_from_slice(&tbs_certificate);
+
+        assert_eq!(subject_public_key_info(&certificate), Some(&spki[..]));
+        assert_eq!(subject_public_key_info(&certificate[..10]), None);
+    }
+}
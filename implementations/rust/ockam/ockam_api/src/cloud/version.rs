@@ -0,0 +1,178 @@
+//! Negotiation of the version of the Orchestrator Controller API.
+
+use minicbor::{Decode, Encode};
+
+use ockam_core::api::Request;
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+use ockam_node::Context;
+
+use crate::cloud::project::ProjectVersion;
+use crate::cloud::Controller;
+
+/// Versions of the controller API used by this library
+pub const SUPPORTED_API_VERSIONS: [u16; 2] = [0, 1];
+
+/// Version of the controller, retrieved once per [`Controller`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControllerVersion {
+    pub version: Option<String>,
+    /// `None` when the controller doesn't advertise its API versions
+    pub api_versions: Option<Vec<u16>>,
+}
+
+impl From<ProjectVersion> for ControllerVersion {
+    fn from(v: ProjectVersion) -> Self {
+        Self {
+            version: v.version,
+            api_versions: v.api_versions,
+        }
+    }
+}
+
+impl ControllerVersion {
+    /// Return true if the controller supports a version of its API
+    pub fn supports(&self, api_version: u16) -> bool {
+        self.api_versions
+            .as_ref()
+            .map(|versions| versions.contains(&api_version))
+            .unwrap_or(true)
+    }
+
+    /// Return an error if a version of the API is not supported by the controller
+    pub fn check(&self, api_version: u16) -> Result<()> {
+        if self.supports(api_version) {
+            return Ok(());
+        }
+        let supported = self
+            .api_versions
+            .iter()
+            .flatten()
+            .map(|v| format!("v{v}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let message = if self
+            .api_versions
+            .iter()
+            .flatten()
+            .any(|v| !SUPPORTED_API_VERSIONS.contains(v) && *v > api_version)
+        {
+            format!(
+                "The Orchestrator Controller{} doesn't support the v{api_version} API anymore, it supports {supported}. Please upgrade ockam, the current version is {}",
+                self.display_version(),
+                env!("CARGO_PKG_VERSION")
+            )
+        } else {
+            format!(
+                "The Orchestrator Controller{} doesn't support the v{api_version} API yet, it supports {supported}",
+                self.display_version()
+            )
+        };
+        Err(Error::new(Origin::Api, Kind::Unsupported, message))
+    }
+
+    /// Explain an error raised when a response of the controller can't be decoded
+    pub fn explain(&self, error: Error) -> Error {
+        if error.code().kind != Kind::Serialization {
+            return error;
+        }
+        Error::new(
+            Origin::Api,
+            Kind::Serialization,
+            format!(
+                "The response of the Orchestrator Controller{} is not understood by this version of ockam ({}). Please upgrade ockam. Cause: {error}",
+                self.display_version(),
+                env!("CARGO_PKG_VERSION")
+            ),
+        )
+    }
+
+    fn display_version(&self) -> String {
+        self.version
+            .as_ref()
+            .map(|v| format!(" (version {v})"))
+            .unwrap_or_default()
+    }
+}
+
+/// Version of a controller, once negotiated
+pub(crate) type NegotiatedVersion = Arc<Mutex<Option<ControllerVersion>>>;
+
+impl Controller {
+    /// Return the version of the controller.
+    /// If it can't be retrieved the controller is assumed to support all the versions of its API
+    pub async fn negotiate_version(&self, ctx: &Context) -> ControllerVersion {
+        if let Some(version) = self.1.lock().unwrap().clone() {
+            return version;
+        }
+        let version = match self
+            .0
+            .ask::<(), ProjectVersion>(ctx, "version_info", Request::get(""))
+            .await
+            .and_then(|reply| reply.success())
+        {
+            Ok(version) => ControllerVersion::from(version),
+            Err(e) => {
+                warn!(%e, "the version of the controller could not be retrieved");
+                return ControllerVersion::default();
+            }
+        };
+        debug!(?version, "negotiated the version of the controller");
+        *self.1.lock().unwrap() = Some(version.clone());
+        version
+    }
+
+    /// Send a request for a given version of the controller API and return its successful response
+    pub(crate) async fn ask_api<T, R>(
+        &self,
+        ctx: &Context,
+        api_version: u16,
+        api_service: &str,
+        req: Request<T>,
+    ) -> Result<R>
+    where
+        T: Encode<()>,
+        R: for<'a> Decode<'a, ()>,
+    {
+        let version = self.negotiate_version(ctx).await;
+        version.check(api_version)?;
+        self.0
+            .ask(ctx, api_service, req)
+            .await
+            .and_then(|reply| reply.success())
+            .map_err(|e| version.explain(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_api_version() {
+        let unknown = ControllerVersion::default();
+        assert!(unknown.check(1).is_ok());
+
+        let old = ControllerVersion {
+            version: Some("1.0".into()),
+            api_versions: Some(vec![0]),
+        };
+        let error = old.check(1).unwrap_err();
+        assert_eq!(error.code().kind, Kind::Unsupported);
+        assert!(error.to_string().contains("doesn't support the v1 API yet"));
+
+        let new = ControllerVersion {
+            version: Some("3.0".into()),
+            api_versions: Some(vec![2]),
+        };
+        let error = new.check(1).unwrap_err();
+        assert!(error.to_string().contains("Please upgrade ockam"));
+
+        let decoding = Error::new(Origin::Api, Kind::Serialization, "unexpected type");
+        assert!(new
+            .explain(decoding)
+            .to_string()
+            .contains("(version 3.0) is not understood"));
+    }
+}
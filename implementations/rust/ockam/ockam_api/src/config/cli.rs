@@ -5,10 +5,11 @@ use crate::cloud::project::Project;
 use crate::config::{lookup::ConfigLookup, ConfigValues};
 use crate::error::ApiError;
 use crate::{cli_state, multiaddr_to_transport_route, DefaultAddress, HexByteVec};
+use ockam::identity::storage::Storage;
 use ockam::identity::{
-    identities, AuthorityService, CredentialsMemoryRetriever, CredentialsRetriever, Identifier,
-    Identities, Identity, RemoteCredentialsRetriever, RemoteCredentialsRetrieverInfo,
-    SecureChannels, TrustContext,
+    identities, AuthorityService, CredentialsMemoryRetriever, CredentialsRetriever,
+    FederatedAuthority, Identifier, Identities, Identity, RemoteCredentialsRetriever,
+    RemoteCredentialsRetrieverInfo, SecureChannels, TrustContext,
 };
 use ockam_core::compat::sync::Arc;
 use ockam_core::{Result, Route};
@@ -80,6 +81,8 @@ pub struct TrustContextConfig {
     id: String,
     authority: Option<TrustAuthorityConfig>,
     path: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    federated_authorities: Vec<FederatedAuthorityConfig>,
 }
 
 impl TrustContextConfig {
@@ -88,9 +91,23 @@ impl TrustContextConfig {
             id,
             authority,
             path: None,
+            federated_authorities: vec![],
         }
     }
 
+    /// Accept credentials issued by the authority of another project
+    pub fn with_federated_authority(
+        mut self,
+        federated_authority: FederatedAuthorityConfig,
+    ) -> Self {
+        self.federated_authorities.push(federated_authority);
+        self
+    }
+
+    pub fn federated_authorities(&self) -> &[FederatedAuthorityConfig] {
+        &self.federated_authorities
+    }
+
     pub fn id(&self) -> &str {
         &self.id
     }
@@ -109,10 +126,13 @@ impl TrustContextConfig {
             .ok_or_else(|| ApiError::core("Missing authority on trust context config"))
     }
 
+    /// Create the trust context. The transitions of its authority are persisted in
+    /// `transitions_storage` when it is set
     pub async fn to_trust_context(
         &self,
         secure_channels: Arc<SecureChannels>,
         tcp_transport: Option<TcpTransport>,
+        transitions_storage: Option<Arc<dyn Storage>>,
     ) -> Result<TrustContext> {
         let authority = if let Some(authority_config) = self.authority.as_ref() {
             let identity = authority_config.identity().await?;
@@ -127,16 +147,28 @@ impl TrustContextConfig {
                     None
                 };
 
-            Some(AuthorityService::new(
+            let authority = AuthorityService::new(
                 secure_channels.identities().credentials(),
                 identity.identifier().clone(),
                 credential_retriever,
-            ))
+            );
+            Some(match transitions_storage {
+                Some(storage) => authority.with_transitions_storage(storage).await?,
+                None => authority,
+            })
         } else {
             None
         };
 
-        Ok(TrustContext::new(self.id.to_string(), authority))
+        let mut trust_context = TrustContext::new(self.id.to_string(), authority);
+        for federated_authority in &self.federated_authorities {
+            trust_context = trust_context.with_federated_authority(
+                federated_authority
+                    .to_federated_authority(secure_channels.identities())
+                    .await?,
+            );
+        }
+        Ok(trust_context)
     }
 
     pub fn from_authority_identity(
@@ -254,6 +286,52 @@ impl TrustAuthorityConfig {
     }
 }
 
+/// Configuration of the authority of another project whose credentials are accepted
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FederatedAuthorityConfig {
+    identity: String,
+    attributes_prefix: String,
+}
+
+impl FederatedAuthorityConfig {
+    /// Create a federated authority configuration from the hex-encoded change history of the
+    /// foreign authority and the prefix used to rename the attributes it attests
+    pub fn new(identity: String, attributes_prefix: String) -> Self {
+        Self {
+            identity,
+            attributes_prefix,
+        }
+    }
+
+    pub fn identity_str(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn attributes_prefix(&self) -> &str {
+        &self.attributes_prefix
+    }
+
+    /// Import the federated authority identity, so that its credentials can be verified,
+    /// and return the corresponding [`FederatedAuthority`]
+    pub async fn to_federated_authority(
+        &self,
+        identities: Arc<Identities>,
+    ) -> Result<FederatedAuthority> {
+        let identity = identities
+            .identities_creation()
+            .import(
+                None,
+                &hex::decode(&self.identity)
+                    .map_err(|_| ApiError::core("unable to decode federated authority identity"))?,
+            )
+            .await?;
+        Ok(FederatedAuthority::new(
+            identity.identifier().clone(),
+            self.attributes_prefix.clone(),
+        ))
+    }
+}
+
 /// Type of credential retriever
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub enum CredentialRetrieverConfig {
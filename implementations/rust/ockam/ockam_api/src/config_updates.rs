@@ -0,0 +1,415 @@
+//! Configuration updates pushed by a control identity.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{
+    secure_channel_required, Identifier, Identities, IdentityError, IdentitySecureChannelLocalInfo,
+    SecureClient,
+};
+use ockam_core::api::{Method, Request, RequestHeader, Response};
+use ockam_core::{Address, Result, Routed, Worker};
+use ockam_node::Context;
+
+use crate::cli_state::cached::now;
+use crate::error::ApiError;
+use crate::nodes::models::drift::Drift;
+use crate::nodes::service::drift::NodeDeclaration;
+use crate::nodes::NodeManager;
+use crate::DefaultAddress;
+
+/// Identifier for the schema of the statements signing a configuration update
+pub const CONFIG_UPDATE_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(6);
+
+/// Name of the attribute containing the SHA-256 digest of a configuration update
+pub const CONFIG_UPDATE_DIGEST: &[u8] = b"ockam.config_update.digest";
+
+/// Duration during which a signed update can be applied
+pub const CONFIG_UPDATE_VALIDITY: Duration = Duration::from_secs(24 * 3600);
+
+/// A versioned configuration of the resources of a node
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ConfigUpdate {
+    #[n(1)] pub version: u64,
+    /// Seconds since the Unix epoch
+    #[n(2)] pub created_at: u64,
+    /// YAML representation of a [`NodeDeclaration`]
+    #[n(3)] pub config: String,
+}
+
+impl ConfigUpdate {
+    /// Create an update, checking that the configuration can be parsed
+    pub fn new(version: u64, config: impl Into<String>) -> Result<Self> {
+        let update = Self {
+            version,
+            created_at: now(),
+            config: config.into(),
+        };
+        update.declaration()?;
+        Ok(update)
+    }
+
+    pub fn declaration(&self) -> Result<NodeDeclaration> {
+        NodeDeclaration::parse(&self.config)
+    }
+
+    /// Return true if this update must be applied after the last applied update
+    pub fn is_newer_than(&self, applied: Option<&AppliedConfigUpdate>) -> bool {
+        applied.map_or(true, |applied| self.version > applied.version)
+    }
+
+    /// SHA-256 digest of the JSON representation of the update
+    async fn digest(&self, identities: &Identities) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ApiError::core(format!("the update can't be encoded: {e}")))?;
+        Ok(identities
+            .vault()
+            .verifying_vault
+            .sha256(&json)
+            .await?
+            .0
+            .to_vec())
+    }
+
+    /// Sign the update with the control identity
+    pub async fn sign(
+        self,
+        identities: &Identities,
+        signer: &Identifier,
+    ) -> Result<SignedConfigUpdate> {
+        let attributes = AttributesBuilder::with_schema(CONFIG_UPDATE_SCHEMA)
+            .with_attribute(
+                CONFIG_UPDATE_DIGEST.to_vec(),
+                self.digest(identities).await?,
+            )
+            .build();
+        let statement = identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(signer, signer, attributes, CONFIG_UPDATE_VALIDITY)
+            .await?;
+        Ok(SignedConfigUpdate {
+            update: self,
+            signer: signer.clone(),
+            signer_identity: hex::encode(identities.export_identity(signer).await?),
+            signature: hex::encode(minicbor::to_vec(&statement)?),
+        })
+    }
+}
+
+/// An update with the identity of its signer and a statement attesting its digest
+#[derive(Clone, Debug, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SignedConfigUpdate {
+    #[n(1)] pub update: ConfigUpdate,
+    #[n(2)] pub signer: Identifier,
+    /// Change history of the signer, hex encoded
+    #[n(3)] pub signer_identity: String,
+    /// Statement of the signer, hex encoded
+    #[n(4)] pub signature: String,
+}
+
+impl SignedConfigUpdate {
+    /// Check that the update was signed by the control identity, and return its configuration
+    pub async fn verify(
+        &self,
+        identities: &Identities,
+        control: &Identifier,
+    ) -> Result<NodeDeclaration> {
+        if &self.signer != control {
+            return Err(ApiError::core(format!(
+                "the update was signed by {} instead of the control identity {control}",
+                self.signer
+            )));
+        }
+        let invalid = |e: hex::FromHexError| {
+            ApiError::core(format!("the signature of the update is invalid: {e}"))
+        };
+        let signer_identity = hex::decode(&self.signer_identity).map_err(invalid)?;
+        identities
+            .identities_creation()
+            .import(Some(&self.signer), &signer_identity)
+            .await?;
+        let statement: CredentialAndPurposeKey =
+            minicbor::decode(&hex::decode(&self.signature).map_err(invalid)?)?;
+        let data = identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(Some(&self.signer), &[self.signer.clone()], &statement)
+            .await?;
+        let attributes = data.credential_data.subject_attributes;
+        if attributes.schema != CONFIG_UPDATE_SCHEMA {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+        let attested_digest = attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == CONFIG_UPDATE_DIGEST)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if attested_digest != Some(self.update.digest(identities).await?) {
+            return Err(ApiError::core(
+                "the update was modified after it was signed",
+            ));
+        }
+        self.update.declaration()
+    }
+}
+
+/// Last update applied to a node, kept in the node directory
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct AppliedConfigUpdate {
+    #[n(1)] pub version: u64,
+    #[n(2)] pub signer: Identifier,
+    /// Seconds since the Unix epoch
+    #[n(3)] pub applied_at: u64,
+}
+
+impl AppliedConfigUpdate {
+    pub fn new(signed: &SignedConfigUpdate) -> Self {
+        Self {
+            version: signed.update.version,
+            signer: signed.signer.clone(),
+            applied_at: now(),
+        }
+    }
+
+    /// Read the last applied update, if any
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the applied update {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        serde_json::from_str(&contents).map(Some).map_err(|e| {
+            ApiError::core(format!(
+                "the applied update {} is invalid: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| ApiError::core(format!("the applied update can't be encoded: {e}")))?;
+        std::fs::write(path, contents).map_err(|e| {
+            ApiError::core(format!(
+                "the applied update {} can't be written: {e}",
+                path.display()
+            ))
+        })
+    }
+}
+
+/// Changes made to a node by an update
+#[derive(Clone, Debug, Encode, Decode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ConfigUpdateReport {
+    #[n(1)] pub version: u64,
+    #[n(2)] pub changes: Vec<Drift>,
+}
+
+/// Worker applying the updates sent by the control identity of a node
+pub struct ConfigUpdateService {
+    node: Arc<NodeManager>,
+    control: Identifier,
+}
+
+impl ConfigUpdateService {
+    /// Start the service, reachable through the secure channels created by the node listeners
+    pub async fn start(ctx: &Context, node: Arc<NodeManager>, control: Identifier) -> Result<()> {
+        let address = Address::from_string(DefaultAddress::CONFIG_UPDATES);
+        for listener in node.registry.secure_channel_listeners.values().await {
+            ctx.flow_controls()
+                .add_consumer(address.clone(), listener.listener().flow_control_id());
+        }
+        info!(%control, "Accepting configuration updates");
+        ctx.start_worker(address, ConfigUpdateService { node, control })
+            .await
+    }
+
+    async fn apply(
+        &self,
+        ctx: &Context,
+        req: &RequestHeader,
+        signed: SignedConfigUpdate,
+    ) -> Result<Vec<u8>> {
+        let identities = self.node.secure_channels.identities();
+        let declaration = match signed.verify(&identities, &self.control).await {
+            Ok(declaration) => declaration,
+            Err(e) => return Ok(Response::forbidden(req, &e.to_string()).to_vec()?),
+        };
+        let response = match self
+            .node
+            .apply_config_update(ctx, &signed, &declaration)
+            .await
+        {
+            Ok(Some(report)) => Response::ok(req).body(report).to_vec()?,
+            Ok(None) => {
+                let message = format!(
+                    "the version {} is not greater than the applied version {}",
+                    signed.update.version,
+                    self.node
+                        .applied_config_update()?
+                        .map(|a| a.version)
+                        .unwrap_or_default()
+                );
+                Response::bad_request(req, &message).to_vec()?
+            }
+            Err(e) => Response::internal_error(req, &format!("the update was rolled back: {e}"))
+                .to_vec()?,
+        };
+        Ok(response)
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for ConfigUpdateService {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::config_updates",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                body   = %req.has_body(),
+                "request"
+            }
+            let res = if from != self.control {
+                Response::unauthorized(req.id()).to_vec()?
+            } else {
+                let path_segments = req.path_segments::<5>();
+                match (req.method(), path_segments.as_slice()) {
+                    (Some(Method::Get), ["config"]) => match self.node.applied_config_update()? {
+                        Some(applied) => Response::ok(&req).body(applied).to_vec()?,
+                        None => Response::not_found(&req, "no update was applied").to_vec()?,
+                    },
+                    (Some(Method::Post), ["config"]) => {
+                        let signed: SignedConfigUpdate = dec.decode()?;
+                        self.apply(c, &req, signed).await?
+                    }
+                    _ => Response::unknown_path(&req).to_vec()?,
+                }
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Client of the configuration update service of a node
+pub struct ConfigUpdateClient {
+    client: SecureClient,
+}
+
+impl ConfigUpdateClient {
+    /// The client must connect to the node with the control identity
+    pub fn new(client: SecureClient) -> Self {
+        Self { client }
+    }
+
+    /// Send an update to the node, and return the changes made to the node
+    pub async fn push(
+        &self,
+        ctx: &Context,
+        update: &SignedConfigUpdate,
+    ) -> Result<ConfigUpdateReport> {
+        let request = Request::post("/config").body(update.clone());
+        self.client
+            .ask(ctx, DefaultAddress::CONFIG_UPDATES, request)
+            .await?
+            .success()
+    }
+
+    /// Return the last update applied to the node, if any
+    pub async fn applied(&self, ctx: &Context) -> Result<Option<AppliedConfigUpdate>> {
+        let request = Request::get("/config");
+        self.client
+            .ask(ctx, DefaultAddress::CONFIG_UPDATES, request)
+            .await?
+            .found()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::identities;
+
+    const CONFIG: &str = r#"
+outlets:
+  - alias: web
+    to: 127.0.0.1:3000
+"#;
+
+    #[tokio::test]
+    async fn test_sign_and_verify() -> Result<()> {
+        let control_identities = identities();
+        let control = control_identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        let signed = ConfigUpdate::new(2, CONFIG)?
+            .sign(&control_identities, &control)
+            .await?;
+
+        // the update is received by a node of the fleet
+        let declaration = signed.verify(&identities(), &control).await?;
+        assert_eq!(declaration.outlets[0].alias, "web");
+
+        let mut modified = signed.clone();
+        modified.update.config = modified.update.config.replace("3000", "22");
+        assert!(modified.verify(&identities(), &control).await.is_err());
+
+        let other = control_identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        assert!(signed.verify(&identities(), &other).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_versions() -> Result<()> {
+        let update = ConfigUpdate::new(2, CONFIG)?;
+        assert!(ConfigUpdate::new(1, "outlets: web").is_err());
+
+        let applied = |version| AppliedConfigUpdate {
+            version,
+            signer: "I0000000000000000000000000000000000000001".parse().unwrap(),
+            applied_at: now(),
+        };
+        assert!(update.is_newer_than(None));
+        assert!(update.is_newer_than(Some(&applied(1))));
+        assert!(!update.is_newer_than(Some(&applied(2))));
+        assert!(!update.is_newer_than(Some(&applied(3))));
+        Ok(())
+    }
+}
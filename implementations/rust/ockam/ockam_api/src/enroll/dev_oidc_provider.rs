@@ -0,0 +1,479 @@
+//! In-process OIDC provider for development and tests.
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rand::distributions::{Alphanumeric, DistString};
+use sha2::{Digest, Sha256};
+use tiny_http::{Header, Method, Request, Response, Server};
+use url::Url;
+
+use ockam_core::Result;
+
+use crate::cloud::enroll::auth0::UserInfo;
+use crate::enroll::oidc_provider::OidcProvider;
+use crate::error::ApiError;
+
+/// Client identifier expected by the development provider
+pub const DEV_OIDC_CLIENT_ID: &str = "ockam-dev";
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Configuration of the development OIDC server
+#[derive(Debug, Clone)]
+pub struct DevOidcConfig {
+    claims: UserInfo,
+    auto_approve: bool,
+}
+
+impl Default for DevOidcConfig {
+    fn default() -> Self {
+        Self {
+            claims: UserInfo {
+                sub: "dev|ockam".to_string(),
+                nickname: "dev".to_string(),
+                name: "Ockam Developer".to_string(),
+                picture: "".to_string(),
+                updated_at: "1970-01-01T00:00:00.000Z".to_string(),
+                email: "dev@ockam.local".to_string(),
+                email_verified: true,
+            },
+            auto_approve: true,
+        }
+    }
+}
+
+impl DevOidcConfig {
+    /// Claims returned by the user info endpoint
+    pub fn with_claims(mut self, claims: UserInfo) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    /// If false, a device code is only approved once its verification URI has been visited
+    pub fn with_auto_approve(mut self, auto_approve: bool) -> Self {
+        self.auto_approve = auto_approve;
+        self
+    }
+}
+
+#[derive(Default)]
+struct DevOidcState {
+    /// Device codes with their user code and approval status
+    device_codes: HashMap<String, (String, bool)>,
+    /// Authorization codes with their PKCE code challenge
+    authorization_codes: HashMap<String, String>,
+    access_tokens: HashSet<String>,
+}
+
+/// OIDC server running on a local port until it is dropped
+pub struct DevOidcServer {
+    server: Arc<Server>,
+    address: SocketAddr,
+}
+
+impl DevOidcServer {
+    /// Start a server on a random local port
+    pub fn start(config: DevOidcConfig) -> Result<Self> {
+        let server = Server::http("127.0.0.1:0")
+            .map_err(|e| ApiError::core(format!("the OIDC server can't be started: {e}")))?;
+        let address = server
+            .server_addr()
+            .to_ip()
+            .ok_or_else(|| ApiError::core("the OIDC server is not listening on a TCP port"))?;
+        let server = Arc::new(server);
+
+        let handler = DevOidcHandler {
+            config,
+            base_url: base_url(&address),
+            state: Default::default(),
+        };
+        let incoming = server.clone();
+        std::thread::spawn(move || {
+            for request in incoming.incoming_requests() {
+                handler.handle(request);
+            }
+        });
+        info!(%address, "started a development OIDC server");
+        Ok(Self { server, address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Return a provider sending its requests to this server
+    pub fn provider(&self) -> DevOidcProvider {
+        DevOidcProvider::new(base_url(&self.address))
+    }
+}
+
+impl Drop for DevOidcServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+fn base_url(address: &SocketAddr) -> Url {
+    Url::parse(&format!("http://{address}")).unwrap()
+}
+
+struct DevOidcHandler {
+    config: DevOidcConfig,
+    base_url: Url,
+    state: Mutex<DevOidcState>,
+}
+
+impl DevOidcHandler {
+    fn handle(&self, mut request: Request) {
+        let url = match self.base_url.join(request.url()) {
+            Ok(url) => url,
+            Err(_) => return respond(request, error_response(400, "invalid_request")),
+        };
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let method = request.method().clone();
+        let response = match (&method, url.path()) {
+            (Method::Post, "/oauth/device/code") => self.device_code(),
+            (Method::Get, "/activate") => self.activate(&query),
+            (Method::Get, "/authorize") => self.authorize(&query),
+            (Method::Post, "/oauth/token") => {
+                let mut body = String::new();
+                if request.as_reader().read_to_string(&mut body).is_err() {
+                    return respond(request, error_response(400, "invalid_request"));
+                }
+                let form: HashMap<String, String> = url::form_urlencoded::parse(body.as_bytes())
+                    .into_owned()
+                    .collect();
+                self.token(&form)
+            }
+            (Method::Get, "/userinfo") => {
+                let token = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Authorization"))
+                    .and_then(|h| h.value.as_str().strip_prefix("Bearer ").map(String::from));
+                self.user_info(token)
+            }
+            _ => error_response(404, "not_found"),
+        };
+        respond(request, response)
+    }
+
+    fn device_code(&self) -> Response<std::io::Cursor<Vec<u8>>> {
+        let device_code = random_string(32);
+        let user_code = random_string(8).to_uppercase();
+        self.state.lock().unwrap().device_codes.insert(
+            device_code.clone(),
+            (user_code.clone(), self.config.auto_approve),
+        );
+
+        let verification_uri = self.base_url.join("/activate").unwrap();
+        let mut verification_uri_complete = verification_uri.clone();
+        verification_uri_complete
+            .query_pairs_mut()
+            .append_pair("user_code", &user_code);
+        json_response(
+            200,
+            serde_json::json!({
+                "device_code": device_code,
+                "user_code": user_code,
+                "verification_uri": verification_uri.to_string(),
+                "verification_uri_complete": verification_uri_complete.to_string(),
+                "expires_in": Duration::from_secs(600).as_secs(),
+                "interval": 1,
+            }),
+        )
+    }
+
+    /// Approve the device code corresponding to a user code
+    fn activate(&self, query: &HashMap<String, String>) -> Response<std::io::Cursor<Vec<u8>>> {
+        let user_code = match query.get("user_code") {
+            Some(user_code) => user_code,
+            None => return error_response(400, "invalid_request"),
+        };
+        let mut state = self.state.lock().unwrap();
+        match state
+            .device_codes
+            .values_mut()
+            .find(|(code, _)| code == user_code)
+        {
+            Some((_, approved)) => {
+                *approved = true;
+                Response::from_string("Device activated, you can close this page")
+            }
+            None => error_response(404, "invalid_grant"),
+        }
+    }
+
+    /// Redirect to the client with a new authorization code
+    fn authorize(&self, query: &HashMap<String, String>) -> Response<std::io::Cursor<Vec<u8>>> {
+        let (redirect_uri, code_challenge) =
+            match (query.get("redirect_uri"), query.get("code_challenge")) {
+                (Some(redirect_uri), Some(code_challenge)) => (redirect_uri, code_challenge),
+                _ => return error_response(400, "invalid_request"),
+            };
+        let mut redirect_uri = match Url::parse(redirect_uri) {
+            Ok(redirect_uri) => redirect_uri,
+            Err(_) => return error_response(400, "invalid_request"),
+        };
+        let code = random_string(32);
+        self.state
+            .lock()
+            .unwrap()
+            .authorization_codes
+            .insert(code.clone(), code_challenge.clone());
+        redirect_uri.query_pairs_mut().append_pair("code", &code);
+        let location = Header::from_bytes("Location", redirect_uri.as_str()).unwrap();
+        Response::from_string("")
+            .with_status_code(302)
+            .with_header(location)
+    }
+
+    fn token(&self, form: &HashMap<String, String>) -> Response<std::io::Cursor<Vec<u8>>> {
+        if form.get("client_id").map(|c| c.as_str()) != Some(DEV_OIDC_CLIENT_ID) {
+            return error_response(401, "invalid_client");
+        }
+        let mut state = self.state.lock().unwrap();
+        let granted = match form.get("grant_type").map(|g| g.as_str()) {
+            Some(DEVICE_CODE_GRANT_TYPE) => {
+                let device_code = form.get("device_code").cloned().unwrap_or_default();
+                match state.device_codes.get(&device_code) {
+                    Some((_, true)) => {
+                        state.device_codes.remove(&device_code);
+                        true
+                    }
+                    Some((_, false)) => return error_response(403, "authorization_pending"),
+                    None => false,
+                }
+            }
+            Some("authorization_code") => {
+                let code = form.get("code").cloned().unwrap_or_default();
+                let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+                match state.authorization_codes.remove(&code) {
+                    Some(challenge) => {
+                        base64_url::encode(&Sha256::digest(verifier.as_bytes())) == challenge
+                    }
+                    None => false,
+                }
+            }
+            _ => return error_response(400, "unsupported_grant_type"),
+        };
+        if !granted {
+            return error_response(400, "invalid_grant");
+        }
+
+        let access_token = random_string(32);
+        state.access_tokens.insert(access_token.clone());
+        json_response(
+            200,
+            serde_json::json!({
+                "token_type": "Bearer",
+                "access_token": access_token,
+            }),
+        )
+    }
+
+    fn user_info(&self, token: Option<String>) -> Response<std::io::Cursor<Vec<u8>>> {
+        let is_valid = token
+            .map(|t| self.state.lock().unwrap().access_tokens.contains(&t))
+            .unwrap_or(false);
+        if !is_valid {
+            return error_response(401, "invalid_token");
+        }
+        match serde_json::to_value(&self.config.claims) {
+            Ok(claims) => json_response(200, claims),
+            Err(_) => error_response(500, "server_error"),
+        }
+    }
+}
+
+fn random_string(length: usize) -> String {
+    Alphanumeric.sample_string(&mut rand::thread_rng(), length)
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+fn error_response(status: u16, error: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(
+        status,
+        serde_json::json!({"error": error, "error_description": error}),
+    )
+}
+
+fn respond(request: Request, response: Response<std::io::Cursor<Vec<u8>>>) {
+    if let Err(e) = request.respond(response) {
+        warn!(%e, "the development OIDC server can't respond to a request");
+    }
+}
+
+/// OIDC provider using a [`DevOidcServer`]
+pub struct DevOidcProvider {
+    base_url: Url,
+    redirect_timeout: Duration,
+}
+
+impl DevOidcProvider {
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            base_url,
+            redirect_timeout: Duration::from_secs(10),
+        }
+    }
+
+    fn url(&self, path: &str) -> Url {
+        self.base_url.join(path).unwrap()
+    }
+}
+
+impl OidcProvider for DevOidcProvider {
+    fn client_id(&self) -> String {
+        DEV_OIDC_CLIENT_ID.to_string()
+    }
+
+    fn redirect_timeout(&self) -> Duration {
+        self.redirect_timeout
+    }
+
+    fn redirect_url(&self) -> Url {
+        Url::parse("http://localhost:8000/callback").unwrap()
+    }
+
+    fn device_code_url(&self) -> Url {
+        self.url("/oauth/device/code")
+    }
+
+    fn authorization_url(&self) -> Url {
+        self.url("/authorize")
+    }
+
+    fn token_request_url(&self) -> Url {
+        self.url("/oauth/token")
+    }
+
+    fn user_info_url(&self) -> Url {
+        self.url("/userinfo")
+    }
+
+    fn build_http_client(&self) -> Result<reqwest::Client> {
+        reqwest::ClientBuilder::new()
+            .build()
+            .map_err(|e| ApiError::core(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cloud::enroll::auth0::{AuthorizationCode, OidcToken};
+    use crate::enroll::oidc_service::OidcService;
+
+    #[tokio::test]
+    async fn test_device_code_flow() -> Result<()> {
+        let server = DevOidcServer::start(DevOidcConfig::default())?;
+        let provider = server.provider();
+        let client = provider.build_http_client()?;
+        let oidc_service = OidcService::new(Arc::new(server.provider()));
+
+        let device_code = oidc_service.device_code().await?;
+        let token: OidcToken = client
+            .post(provider.token_request_url())
+            .form(&[
+                ("client_id", DEV_OIDC_CLIENT_ID),
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", device_code.device_code.as_ref()),
+            ])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+
+        let user_info = oidc_service.get_user_info(&token).await?;
+        assert_eq!(user_info.email, "dev@ockam.local");
+        assert!(user_info.email_verified);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_device_code_pending_until_activated() -> Result<()> {
+        let server = DevOidcServer::start(DevOidcConfig::default().with_auto_approve(false))?;
+        let provider = server.provider();
+        let client = provider.build_http_client()?;
+        let oidc_service = OidcService::new(Arc::new(server.provider()));
+
+        let device_code = oidc_service.device_code().await?;
+        let request_token = || {
+            client.post(provider.token_request_url()).form(&[
+                ("client_id", DEV_OIDC_CLIENT_ID),
+                ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                ("device_code", device_code.device_code.as_ref()),
+            ])
+        };
+        let response = request_token().send().await.unwrap();
+        assert_eq!(response.status(), 403);
+
+        client
+            .get(device_code.verification_uri_complete.as_ref())
+            .send()
+            .await
+            .unwrap();
+        let response = request_token().send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authorization_code_flow() -> Result<()> {
+        let mut claims = DevOidcConfig::default().claims;
+        claims.email = "ci@example.com".to_string();
+        let server = DevOidcServer::start(DevOidcConfig::default().with_claims(claims))?;
+        let provider = server.provider();
+        let oidc_service = OidcService::new(Arc::new(server.provider()));
+
+        // get a code without following the redirection to the local callback server
+        let code_verifier = "a-code-verifier-which-is-long-enough";
+        let code_challenge = base64_url::encode(&Sha256::digest(code_verifier.as_bytes()));
+        let client = reqwest::ClientBuilder::new()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap();
+        let response = client
+            .get(provider.authorization_url())
+            .query(&[
+                ("redirect_uri", provider.redirect_url().as_str()),
+                ("code_challenge", code_challenge.as_str()),
+            ])
+            .send()
+            .await
+            .unwrap();
+        let location = Url::parse(
+            response
+                .headers()
+                .get("location")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+        )
+        .unwrap();
+        let code = location
+            .query_pairs()
+            .find(|(k, _)| k == "code")
+            .unwrap()
+            .1
+            .to_string();
+
+        let token = oidc_service
+            .retrieve_token_with_authorization_code(AuthorizationCode::new(code), code_verifier)
+            .await?;
+        let user_info = oidc_service.get_user_info(&token).await?;
+        assert_eq!(user_info.email, "ci@example.com");
+        Ok(())
+    }
+}
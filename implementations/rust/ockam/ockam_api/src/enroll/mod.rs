@@ -1,3 +1,4 @@
+pub mod dev_oidc_provider;
 pub mod enrollment;
 pub mod ockam_oidc_provider;
 pub mod oidc_provider;
@@ -2,10 +2,14 @@ use ockam_core::Result;
 use std::time::Duration;
 use url::Url;
 
+use crate::cli_state::{ProxyConfig, TlsConfig};
+use crate::cloud::tls::http_client;
 use crate::enroll::oidc_provider::OidcProvider;
 
 pub struct OckamOidcProvider {
     redirect_timeout: Duration,
+    tls_config: TlsConfig,
+    proxy_config: Option<ProxyConfig>,
 }
 
 impl Default for OckamOidcProvider {
@@ -16,7 +20,23 @@ impl Default for OckamOidcProvider {
 
 impl OckamOidcProvider {
     pub fn new(redirect_timeout: Duration) -> Self {
-        Self { redirect_timeout }
+        Self {
+            redirect_timeout,
+            tls_config: TlsConfig::default(),
+            proxy_config: None,
+        }
+    }
+
+    /// Use a specific TLS configuration to connect to the OIDC provider
+    pub fn with_tls_config(mut self, tls_config: TlsConfig) -> Self {
+        self.tls_config = tls_config;
+        self
+    }
+
+    /// Connect to the OIDC provider through a proxy
+    pub fn with_proxy_config(mut self, proxy_config: Option<ProxyConfig>) -> Self {
+        self.proxy_config = proxy_config;
+        self
     }
 }
 
@@ -45,7 +65,11 @@ impl OidcProvider for OckamOidcProvider {
         Url::parse("https://account.ockam.io/oauth/token").unwrap()
     }
 
+    fn user_info_url(&self) -> Url {
+        Url::parse("https://account.ockam.io/userinfo").unwrap()
+    }
+
     fn build_http_client(&self) -> Result<reqwest::Client> {
-        Ok(reqwest::Client::new())
+        http_client(&self.tls_config, self.proxy_config.as_ref())
     }
 }
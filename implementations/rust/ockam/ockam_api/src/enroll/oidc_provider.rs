@@ -10,5 +10,6 @@ pub trait OidcProvider {
     fn device_code_url(&self) -> Url;
     fn authorization_url(&self) -> Url;
     fn token_request_url(&self) -> Url;
+    fn user_info_url(&self) -> Url;
     fn build_http_client(&self) -> Result<reqwest::Client>;
 }
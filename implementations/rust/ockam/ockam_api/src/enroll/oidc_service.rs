@@ -9,6 +9,7 @@ use tokio::time::Duration;
 use tokio_retry::{strategy::ExponentialBackoff, Retry};
 use tracing::{error, info};
 
+use crate::cli_state::{ProxyConfig, TlsConfig};
 use crate::cloud::enroll::auth0::{AuthorizationCode, DeviceCode, OidcToken, UserInfo};
 use crate::enroll::ockam_oidc_provider::OckamOidcProvider;
 use crate::enroll::oidc_provider::OidcProvider;
@@ -47,6 +48,19 @@ impl OidcService {
         Self::new(Arc::new(OckamOidcProvider::new(timeout)))
     }
 
+    /// Create an OIDC service using the Ockam provider with a specific TLS configuration
+    /// and, optionally, a proxy
+    pub fn default_with_network_config(
+        tls_config: TlsConfig,
+        proxy_config: Option<ProxyConfig>,
+    ) -> Self {
+        Self::new(Arc::new(
+            OckamOidcProvider::default()
+                .with_tls_config(tls_config)
+                .with_proxy_config(proxy_config),
+        ))
+    }
+
     /// Request an authorization token with a PKCE flow
     /// See the full protocol here: https://datatracker.ietf.org/doc/html/rfc7636
     pub async fn get_token_with_pkce(&self) -> Result<OidcToken> {
@@ -138,7 +152,7 @@ impl OidcService {
             authorization_code.code
         );
         self.request_code(
-            Url::parse("https://account.ockam.io/oauth/token").unwrap(),
+            self.provider().token_request_url(),
             vec![
                 ("code", authorization_code.code),
                 ("code_verifier", code_verifier.to_string()),
@@ -294,9 +308,10 @@ impl OidcService {
     pub async fn get_user_info(&self, token: &OidcToken) -> Result<UserInfo> {
         let client = self.provider().build_http_client()?;
         let access_token = token.access_token.0.clone();
+        let user_info_url = self.provider().user_info_url();
         let req = || {
             client
-                .get("https://account.ockam.io/userinfo")
+                .get(user_info_url.clone())
                 .header("Authorization", format!("Bearer {}", access_token.clone()))
         };
         let retry_strategy = ExponentialBackoff::from_millis(10).take(3);
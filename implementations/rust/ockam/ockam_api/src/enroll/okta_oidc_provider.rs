@@ -46,6 +46,10 @@ impl OidcProvider for OktaOidcProvider {
         Url::parse(format!("{}/v1/token", &self.okta.tenant_base_url).as_str()).unwrap()
     }
 
+    fn user_info_url(&self) -> Url {
+        Url::parse(format!("{}/v1/userinfo", &self.okta.tenant_base_url).as_str()).unwrap()
+    }
+
     fn build_http_client(&self) -> Result<reqwest::Client> {
         let certificate = reqwest::Certificate::from_pem(self.okta.certificate.as_bytes())
             .map_err(|e| ApiError::core(format!("Error parsing certificate: {}", e)))?;
@@ -1,10 +1,12 @@
 use core::fmt;
 use miette::Diagnostic;
 
-use ockam_core::errcode::{Kind, Origin};
+use ockam_core::errcode::{CatalogueEntry, Kind, Origin};
 
-/// Potential API errors
-#[derive(Debug, thiserror::Error, Diagnostic)]
+/// Potential API errors.
+///
+/// Their diagnostic code and help come from the errors catalogue of `ockam_core`
+#[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error(transparent)]
     Core(#[from] ockam_core::Error),
@@ -48,4 +50,50 @@ impl ApiError {
     pub fn core<T: fmt::Display>(m: T) -> ockam_core::Error {
         ockam_core::Error::new(Origin::Application, Kind::Unknown, m.to_string())
     }
+
+    /// Return the entry of this error in the errors catalogue
+    pub fn catalogue_entry(&self) -> &'static CatalogueEntry {
+        match self {
+            ApiError::Core(e) => e.code().catalogue_entry(),
+            ApiError::MultiAddr(_) | ApiError::Parse(_) => CatalogueEntry::for_kind(Kind::Invalid),
+            ApiError::Io(_) => CatalogueEntry::for_kind(Kind::Io),
+            ApiError::Reqwest(e) if e.is_timeout() => CatalogueEntry::for_kind(Kind::Timeout),
+            ApiError::Reqwest(_) => CatalogueEntry::for_kind(Kind::Io),
+        }
+    }
+}
+
+impl Diagnostic for ApiError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.catalogue_entry().code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.catalogue_entry().help))
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(format!(
+            "https://docs.ockam.io/errors/{}",
+            self.catalogue_entry().code
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::errcode::Category;
+
+    #[test]
+    fn test_diagnostic_code() {
+        let error: ApiError =
+            ockam_core::Error::new(Origin::Node, Kind::NotFound, "no relay named r1").into();
+        assert_eq!(error.code().unwrap().to_string(), "OCK404");
+        assert_eq!(error.catalogue_entry().category, Category::NotFound);
+
+        let error = ApiError::message("unexpected");
+        assert_eq!(error.code().unwrap().to_string(), "OCK500");
+        assert!(error.help().is_some());
+    }
 }
@@ -0,0 +1,461 @@
+//! Inventory of a fleet of nodes.
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::storage::Storage;
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{
+    secure_channel_required, Identifier, Identities, IdentityError, IdentitySecureChannelLocalInfo,
+};
+use ockam_core::api::{Method, RequestHeader, Response};
+use ockam_core::{Address, AllowAll, DenyAll, Result, Routed, Worker};
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use crate::cli_state::cached::now;
+use crate::config_updates::SignedConfigUpdate;
+use crate::error::ApiError;
+use crate::nodes::NodeManager;
+use crate::rollout::Rollouts;
+use crate::DefaultAddress;
+
+/// Identifier for the schema of the statements signing a heartbeat
+pub const HEARTBEAT_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(7);
+
+/// Name of the attribute containing the SHA-256 digest of a heartbeat
+pub const HEARTBEAT_DIGEST: &[u8] = b"ockam.heartbeat.digest";
+
+/// Duration during which the signature of a heartbeat can be verified
+const HEARTBEAT_VALIDITY: Duration = Duration::from_secs(600);
+
+/// Storage namespace of the fleet members
+const FLEET_NAMESPACE: &str = "fleet";
+
+fn default_interval_secs() -> u64 {
+    30
+}
+
+/// Control node to which the heartbeats of a node are sent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    /// Identity of the control node
+    pub control: Identifier,
+    /// Route to the secure channel listener of the control node
+    pub route: MultiAddr,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Labels of the node, used to select the nodes of a rollout
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl HeartbeatConfig {
+    pub fn new(control: Identifier, route: MultiAddr) -> Self {
+        Self {
+            control,
+            route,
+            interval_secs: default_interval_secs(),
+            labels: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_labels(mut self, labels: BTreeMap<String, String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval_secs = interval.as_secs().max(1);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// State of a node, reported to its control node
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Heartbeat {
+    #[n(1)] pub node_name: String,
+    #[n(2)] pub version: String,
+    #[n(3)] pub uptime_secs: u64,
+    /// Seconds since the Unix epoch
+    #[n(4)] pub sent_at: u64,
+    /// Number of bytes used by each subsystem of the node
+    #[n(5)] pub memory: BTreeMap<String, u64>,
+    #[n(6)] pub secure_channels: u64,
+    #[n(7)] pub inlets: Vec<String>,
+    #[n(8)] pub outlets: Vec<String>,
+    #[n(9)] pub relays: Vec<String>,
+    /// Version of the last configuration update applied to the node
+    #[n(10)] pub config_version: Option<u64>,
+    #[n(11)] pub labels: BTreeMap<String, String>,
+}
+
+impl Heartbeat {
+    /// SHA-256 digest of the JSON representation of the heartbeat
+    async fn digest(&self, identities: &Identities) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ApiError::core(format!("the heartbeat can't be encoded: {e}")))?;
+        Ok(identities
+            .vault()
+            .verifying_vault
+            .sha256(&json)
+            .await?
+            .0
+            .to_vec())
+    }
+
+    /// Sign the heartbeat with the identity of the node
+    pub async fn sign(
+        self,
+        identities: &Identities,
+        signer: &Identifier,
+    ) -> Result<SignedHeartbeat> {
+        let attributes = AttributesBuilder::with_schema(HEARTBEAT_SCHEMA)
+            .with_attribute(HEARTBEAT_DIGEST.to_vec(), self.digest(identities).await?)
+            .build();
+        let statement = identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(signer, signer, attributes, HEARTBEAT_VALIDITY)
+            .await?;
+        Ok(SignedHeartbeat {
+            heartbeat: self,
+            signature: hex::encode(minicbor::to_vec(statement)?),
+        })
+    }
+}
+
+/// A heartbeat with a statement of the node attesting its digest
+#[derive(Clone, Debug, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SignedHeartbeat {
+    #[n(1)] pub heartbeat: Heartbeat,
+    /// Statement of the node, hex encoded
+    #[n(2)] pub signature: String,
+}
+
+impl SignedHeartbeat {
+    /// Check that the heartbeat was signed by a node.
+    /// The identity of the node must already be known, for example from a secure channel
+    pub async fn verify(&self, identities: &Identities, node: &Identifier) -> Result<()> {
+        let signature = hex::decode(&self.signature).map_err(|e| {
+            ApiError::core(format!("the signature of the heartbeat is invalid: {e}"))
+        })?;
+        let statement: CredentialAndPurposeKey = minicbor::decode(&signature)?;
+        let data = identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(Some(node), &[node.clone()], &statement)
+            .await?;
+        let attributes = data.credential_data.subject_attributes;
+        if attributes.schema != HEARTBEAT_SCHEMA {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+        let attested_digest = attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == HEARTBEAT_DIGEST)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if attested_digest != Some(self.heartbeat.digest(identities).await?) {
+            return Err(ApiError::core(
+                "the heartbeat was modified after it was signed",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Reply of the control node to a heartbeat
+#[derive(Clone, Debug, Default, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct HeartbeatReply {
+    /// Configuration update to apply, when the node takes part in a rollout
+    #[n(1)] pub update: Option<SignedConfigUpdate>,
+}
+
+/// Last heartbeat received from a node of the fleet
+#[derive(Clone, Debug, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct FleetMember {
+    #[n(1)] pub identifier: Identifier,
+    #[n(2)] pub heartbeat: SignedHeartbeat,
+    /// Seconds since the Unix epoch
+    #[n(3)] pub received_at: u64,
+}
+
+impl FleetMember {
+    /// Return true if no heartbeat was received for more than `max_age`
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        now().saturating_sub(self.received_at) > max_age.as_secs()
+    }
+
+    /// Return true if the node has all the labels of a selector
+    pub fn matches(&self, selector: &BTreeMap<String, String>) -> bool {
+        let labels = &self.heartbeat.heartbeat.labels;
+        selector
+            .iter()
+            .all(|(name, value)| labels.get(name) == Some(value))
+    }
+}
+
+/// Last heartbeats of the nodes of a fleet, by identifier
+#[derive(Clone)]
+pub struct FleetInventory {
+    storage: Arc<dyn Storage>,
+    rollouts: Rollouts,
+}
+
+impl FleetInventory {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            rollouts: Rollouts::new(storage.clone()),
+            storage,
+        }
+    }
+
+    /// Return the rollouts of configuration updates to the fleet, kept with the inventory
+    pub fn rollouts(&self) -> &Rollouts {
+        &self.rollouts
+    }
+
+    /// Keep a heartbeat received from a node, replacing its previous heartbeat
+    pub async fn record(&self, identifier: &Identifier, heartbeat: SignedHeartbeat) -> Result<()> {
+        let member = FleetMember {
+            identifier: identifier.clone(),
+            heartbeat,
+            received_at: now(),
+        };
+        self.storage
+            .set(
+                &identifier.to_string(),
+                FLEET_NAMESPACE.to_string(),
+                minicbor::to_vec(&member)?,
+            )
+            .await
+    }
+
+    pub async fn member(&self, identifier: &Identifier) -> Result<Option<FleetMember>> {
+        match self
+            .storage
+            .get(&identifier.to_string(), FLEET_NAMESPACE)
+            .await?
+        {
+            Some(bytes) => Ok(Some(minicbor::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return the members of the fleet, sorted by node name
+    pub async fn members(&self) -> Result<Vec<FleetMember>> {
+        let mut members = vec![];
+        for key in self.storage.keys(FLEET_NAMESPACE).await? {
+            if let Some(bytes) = self.storage.get(&key, FLEET_NAMESPACE).await? {
+                members.push(minicbor::decode::<FleetMember>(&bytes)?);
+            }
+        }
+        members.sort_by(|a, b| {
+            a.heartbeat
+                .heartbeat
+                .node_name
+                .cmp(&b.heartbeat.heartbeat.node_name)
+        });
+        Ok(members)
+    }
+}
+
+/// Worker keeping the heartbeats sent by the nodes of a fleet
+pub struct FleetService {
+    inventory: FleetInventory,
+    identities: Arc<Identities>,
+}
+
+impl FleetService {
+    /// Start the service, reachable through the secure channels created by the node listeners
+    pub(crate) async fn start(
+        ctx: &Context,
+        node: &NodeManager,
+        inventory: FleetInventory,
+    ) -> Result<()> {
+        let address = Address::from_string(DefaultAddress::FLEET);
+        for listener in node.registry.secure_channel_listeners.values().await {
+            ctx.flow_controls()
+                .add_consumer(address.clone(), listener.listener().flow_control_id());
+        }
+        info!("Keeping the heartbeats of the fleet nodes");
+        let service = FleetService {
+            inventory,
+            identities: node.secure_channels.identities(),
+        };
+        ctx.start_worker(address, service).await
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for FleetService {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::fleet",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                body   = %req.has_body(),
+                "request"
+            }
+            let path_segments = req.path_segments::<5>();
+            let res = match (req.method(), path_segments.as_slice()) {
+                (Some(Method::Post), ["heartbeats"]) => {
+                    let heartbeat: SignedHeartbeat = dec.decode()?;
+                    match heartbeat.verify(&self.identities, &from).await {
+                        Ok(()) => {
+                            debug!(node = %from, "Received a heartbeat");
+                            let config_version = heartbeat.heartbeat.config_version;
+                            self.inventory.record(&from, heartbeat).await?;
+                            let update = self
+                                .inventory
+                                .rollouts()
+                                .update_for(&from, config_version)
+                                .await?;
+                            Response::ok(&req)
+                                .body(HeartbeatReply { update })
+                                .to_vec()?
+                        }
+                        Err(e) => Response::forbidden(&req, &e.to_string()).to_vec()?,
+                    }
+                }
+                _ => Response::unknown_path(&req).to_vec()?,
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Reporter of the heartbeats of a node to its control node
+pub struct HeartbeatReporter;
+
+impl HeartbeatReporter {
+    /// Send a heartbeat on each interval, until the node is dropped.
+    /// A heartbeat which can't be sent is skipped
+    pub async fn start(
+        ctx: &Context,
+        node: Weak<NodeManager>,
+        config: HeartbeatConfig,
+    ) -> Result<JoinHandle<()>> {
+        info!(
+            control = %config.control,
+            route = %config.route,
+            interval_secs = config.interval_secs,
+            "Reporting the node heartbeats"
+        );
+        let ctx = ctx
+            .new_detached(
+                Address::random_tagged("HeartbeatReporter.ctx"),
+                DenyAll,
+                AllowAll,
+            )
+            .await?;
+        let started_at = Instant::now();
+        Ok(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval());
+            loop {
+                interval.tick().await;
+                let node = match node.upgrade() {
+                    Some(node) => node,
+                    None => return,
+                };
+                if let Err(e) = node
+                    .send_heartbeat(&ctx, &config, started_at.elapsed())
+                    .await
+                {
+                    warn!(%e, control = %config.control, "The heartbeat can't be sent");
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::identities;
+    use ockam::identity::storage::InMemoryStorage;
+
+    fn heartbeat(node_name: &str) -> Heartbeat {
+        Heartbeat {
+            node_name: node_name.to_string(),
+            version: "0.1.0".to_string(),
+            uptime_secs: 60,
+            sent_at: now(),
+            memory: BTreeMap::from([("mailboxes".to_string(), 1024)]),
+            secure_channels: 2,
+            inlets: vec![],
+            outlets: vec!["web".to_string()],
+            relays: vec![node_name.to_string()],
+            config_version: None,
+            labels: BTreeMap::from([("region".to_string(), "eu".to_string())]),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_signed_heartbeats() -> Result<()> {
+        let node_identities = identities();
+        let edge = node_identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        let signed = heartbeat("edge").sign(&node_identities, &edge).await?;
+
+        // the identity of the edge node is known by the control node after the secure channel
+        let control_identities = identities();
+        control_identities
+            .identities_creation()
+            .import(Some(&edge), &node_identities.export_identity(&edge).await?)
+            .await?;
+        signed.verify(&control_identities, &edge).await?;
+
+        let mut modified = signed.clone();
+        modified.heartbeat.outlets.push("ssh".to_string());
+        assert!(modified.verify(&control_identities, &edge).await.is_err());
+
+        let inventory = FleetInventory::new(InMemoryStorage::create());
+        inventory.record(&edge, signed.clone()).await?;
+        let member = inventory.member(&edge).await?.unwrap();
+        assert_eq!(member.heartbeat.heartbeat, signed.heartbeat);
+        assert!(!member.is_stale(Duration::from_secs(60)));
+
+        let mut restarted = heartbeat("edge");
+        restarted.uptime_secs = 1;
+        let restarted = restarted.sign(&node_identities, &edge).await?;
+        inventory.record(&edge, restarted).await?;
+        let members = inventory.members().await?;
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].heartbeat.heartbeat.uptime_secs, 1);
+        Ok(())
+    }
+}
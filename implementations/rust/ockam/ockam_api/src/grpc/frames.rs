@@ -0,0 +1,224 @@
+//! Framing of HTTP/2, for the frames inspected by the gRPC interceptors.
+
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+
+/// First bytes sent by an HTTP/2 client
+pub(crate) const CONNECTION_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+pub(crate) const HEADERS: u8 = 0x1;
+pub(crate) const SETTINGS: u8 = 0x4;
+pub(crate) const PING: u8 = 0x6;
+pub(crate) const CONTINUATION: u8 = 0x9;
+
+pub(crate) const END_STREAM: u8 = 0x1;
+pub(crate) const ACK: u8 = 0x1;
+pub(crate) const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+pub(crate) const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+
+const FRAME_HEADER_LENGTH: usize = 9;
+
+/// Maximum size of the frames which can be sent before knowing the settings of the peer
+const DEFAULT_MAX_FRAME_SIZE: usize = 16384;
+
+/// A frame, with its header fields and its payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub(crate) frame_type: u8,
+    pub(crate) flags: u8,
+    pub(crate) stream_id: u32,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Frame {
+    pub(crate) fn new(frame_type: u8, flags: u8, stream_id: u32, payload: Vec<u8>) -> Self {
+        Self {
+            frame_type,
+            flags,
+            stream_id,
+            payload,
+        }
+    }
+
+    /// Remove the first frame of a buffer, once it is complete
+    pub(crate) fn take(buffer: &mut Vec<u8>) -> Option<Frame> {
+        if buffer.len() < FRAME_HEADER_LENGTH {
+            return None;
+        }
+        let length = u32::from_be_bytes([0, buffer[0], buffer[1], buffer[2]]) as usize;
+        if buffer.len() < FRAME_HEADER_LENGTH + length {
+            return None;
+        }
+        let stream_id =
+            u32::from_be_bytes([buffer[5], buffer[6], buffer[7], buffer[8]]) & 0x7fff_ffff;
+        let frame = Frame::new(
+            buffer[3],
+            buffer[4],
+            stream_id,
+            buffer[FRAME_HEADER_LENGTH..FRAME_HEADER_LENGTH + length].to_vec(),
+        );
+        buffer.drain(..FRAME_HEADER_LENGTH + length);
+        Some(frame)
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = (self.payload.len() as u32).to_be_bytes()[1..].to_vec();
+        bytes.push(self.frame_type);
+        bytes.push(self.flags);
+        bytes.extend(self.stream_id.to_be_bytes());
+        bytes.extend(&self.payload);
+        bytes
+    }
+
+    pub(crate) fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Return the header block fragment of a `HEADERS` or `CONTINUATION` frame
+    pub(crate) fn header_block_fragment(&self) -> Result<&[u8]> {
+        if self.frame_type == CONTINUATION {
+            return Ok(&self.payload);
+        }
+        let mut start = 0;
+        let mut end = self.payload.len();
+        if self.has_flag(PADDED) {
+            let padding = *self
+                .payload
+                .first()
+                .ok_or_else(|| protocol_error("empty frame"))?;
+            start += 1;
+            end = end
+                .checked_sub(padding as usize)
+                .ok_or_else(|| protocol_error("invalid padding"))?;
+        }
+        if self.has_flag(PRIORITY) {
+            start += 5;
+        }
+        self.payload
+            .get(start..end)
+            .ok_or_else(|| protocol_error("invalid HEADERS frame"))
+    }
+
+    /// Return the value of a setting of a `SETTINGS` frame
+    pub(crate) fn setting(&self, id: u16) -> Option<u32> {
+        self.payload
+            .chunks_exact(6)
+            .filter(|setting| u16::from_be_bytes([setting[0], setting[1]]) == id)
+            .map(|setting| u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]))
+            .last()
+    }
+}
+
+/// Return the `HEADERS` frame, and the `CONTINUATION` frames if necessary,
+/// sending a header block on a stream
+pub(crate) fn headers_frames(stream_id: u32, end_stream: bool, block: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![];
+    let chunks: Vec<&[u8]> = if block.is_empty() {
+        vec![block]
+    } else {
+        block.chunks(DEFAULT_MAX_FRAME_SIZE).collect()
+    };
+    for (i, chunk) in chunks.iter().enumerate() {
+        let (frame_type, mut flags) = if i == 0 {
+            (HEADERS, if end_stream { END_STREAM } else { 0 })
+        } else {
+            (CONTINUATION, 0)
+        };
+        if i == chunks.len() - 1 {
+            flags |= END_HEADERS;
+        }
+        bytes.extend(Frame::new(frame_type, flags, stream_id, chunk.to_vec()).encode());
+    }
+    bytes
+}
+
+/// Encode header fields as literals which are not added to the dynamic table
+pub(crate) fn encode_header_block<'a>(
+    fields: impl IntoIterator<Item = (&'a [u8], &'a [u8])>,
+) -> Vec<u8> {
+    let mut block = vec![];
+    for (name, value) in fields {
+        // literal header field without indexing, with a new name
+        block.push(0);
+        encode_string(&mut block, name);
+        encode_string(&mut block, value);
+    }
+    block
+}
+
+fn encode_string(block: &mut Vec<u8>, value: &[u8]) {
+    // strings are not Huffman-encoded, the high bit of the length is 0
+    encode_integer(block, value.len(), 7);
+    block.extend(value);
+}
+
+fn encode_integer(block: &mut Vec<u8>, mut value: usize, prefix_bits: u8) {
+    let max_prefix = (1usize << prefix_bits) - 1;
+    if value < max_prefix {
+        block.push(value as u8);
+        return;
+    }
+    block.push(max_prefix as u8);
+    value -= max_prefix;
+    while value >= 128 {
+        block.push((value % 128 + 128) as u8);
+        value /= 128;
+    }
+    block.push(value as u8);
+}
+
+pub(crate) fn protocol_error(message: &str) -> Error {
+    Error::new(Origin::Api, Kind::Protocol, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_frames() {
+        let settings = Frame::new(SETTINGS, 0, 0, vec![0, 1, 0, 0, 0x10, 0]);
+        let mut buffer = settings.encode();
+        buffer.extend(Frame::new(PING, 0, 0, vec![1; 8]).encode());
+
+        let mut partial = buffer[..12].to_vec();
+        assert_eq!(Frame::take(&mut partial), None);
+        let frame = Frame::take(&mut buffer).unwrap();
+        assert_eq!(frame, settings);
+        assert_eq!(frame.setting(SETTINGS_HEADER_TABLE_SIZE), Some(4096));
+        assert_eq!(Frame::take(&mut buffer).unwrap().frame_type, PING);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_header_blocks() -> Result<()> {
+        let block = encode_header_block([
+            (b":path".as_slice(), b"/payments.Payments/Refund".as_slice()),
+            (b"x-long".as_slice(), [b'a'; 200].as_slice()),
+        ]);
+        let fields = hpack::Decoder::new().decode(&block).unwrap();
+        assert_eq!(fields[0].1, b"/payments.Payments/Refund");
+        assert_eq!(fields[1].1, vec![b'a'; 200]);
+
+        // a padded frame with a priority
+        let mut payload = vec![2, 0, 0, 0, 1, 16];
+        payload.extend(&block);
+        payload.extend([0, 0]);
+        let frame = Frame::new(HEADERS, PADDED | PRIORITY | END_HEADERS, 1, payload);
+        assert_eq!(frame.header_block_fragment()?, block.as_slice());
+
+        let mut bytes = headers_frames(3, true, &[0; DEFAULT_MAX_FRAME_SIZE + 1]);
+        let headers = Frame::take(&mut bytes).unwrap();
+        assert_eq!((headers.frame_type, headers.flags), (HEADERS, END_STREAM));
+        let continuation = Frame::take(&mut bytes).unwrap();
+        assert_eq!(
+            (continuation.frame_type, continuation.flags),
+            (CONTINUATION, END_HEADERS)
+        );
+        assert_eq!(continuation.payload.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,618 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use ockam::identity::{Identifier, IdentitiesRepository, IdentitySecureChannelLocalInfo};
+use ockam_abac::expr::str;
+use ockam_abac::{AbacAccessControl, Env, Expr};
+use ockam_core::{async_trait, LocalMessage, Result};
+use ockam_node::Context;
+use ockam_transport_tcp::{PortalDirection, PortalInterceptor, PortalInterceptorFactory};
+
+use super::frames::{
+    encode_header_block, headers_frames, protocol_error, Frame, ACK, CONNECTION_PREFACE,
+    CONTINUATION, END_HEADERS, END_STREAM, HEADERS, PING, SETTINGS, SETTINGS_HEADER_TABLE_SIZE,
+};
+use super::{method_matches, GrpcPortalConfig, IDENTITY_HEADERS_PREFIX};
+
+/// gRPC status of the denied calls
+const PERMISSION_DENIED: &[u8] = b"7";
+
+const DENIED_MESSAGE: &[u8] = b"The call is not allowed by the policies of the Ockam outlet";
+
+type HeaderFields = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// Create the interceptors of the connections of a gRPC outlet.
+/// See the [module documentation](super) for the details
+#[derive(Clone)]
+pub struct GrpcInterceptorFactory {
+    policies: Arc<MethodPolicies>,
+}
+
+impl GrpcInterceptorFactory {
+    /// Create the interceptors of an outlet. The `resource` is the ABAC resource of the outlet:
+    /// the attributes of an identity which are restricted to other resources are not used
+    pub fn new(
+        config: &GrpcPortalConfig,
+        resource: &str,
+        repository: Arc<dyn IdentitiesRepository>,
+    ) -> Result<Self> {
+        Ok(Self {
+            policies: Arc::new(MethodPolicies {
+                resource: resource.to_string(),
+                repository,
+                rules: config.rules()?,
+                default: config.default_expression()?,
+            }),
+        })
+    }
+
+    fn interceptor(&self, identity: Option<Identifier>) -> GrpcInterceptor {
+        GrpcInterceptor {
+            identity,
+            policies: self.policies.clone(),
+            state: Mutex::new(ConnectionState::default()),
+        }
+    }
+}
+
+impl Debug for GrpcInterceptorFactory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GrpcInterceptorFactory")
+            .field("rules", &self.policies.rules)
+            .field("default", &self.policies.default)
+            .finish()
+    }
+}
+
+impl PortalInterceptorFactory for GrpcInterceptorFactory {
+    fn create(&self) -> Arc<dyn PortalInterceptor> {
+        Arc::new(self.interceptor(None))
+    }
+
+    fn create_for_message(&self, message: &LocalMessage) -> Arc<dyn PortalInterceptor> {
+        let identity = IdentitySecureChannelLocalInfo::find_info(message)
+            .map(|info| info.their_identity_id())
+            .ok();
+        Arc::new(self.interceptor(identity))
+    }
+}
+
+struct MethodPolicies {
+    resource: String,
+    repository: Arc<dyn IdentitiesRepository>,
+    rules: Vec<(String, Expr)>,
+    default: Option<Expr>,
+}
+
+impl MethodPolicies {
+    /// Return true if an identity can call the method with a given `:path`
+    async fn is_allowed(&self, identity: &Identifier, path: &str) -> Result<bool> {
+        let expression = match self.rules.iter().find(|(m, _)| method_matches(m, path)) {
+            Some((_, expression)) => expression.clone(),
+            None => match &self.default {
+                Some(expression) => expression.clone(),
+                None => return Ok(false),
+            },
+        };
+        let mut environment = Env::new();
+        environment.put("resource.id", str(self.resource.clone()));
+        if let Some((service, method)) = path.trim_start_matches('/').split_once('/') {
+            environment.put("grpc.service", str(service.to_string()));
+            environment.put("grpc.method", str(method.to_string()));
+        }
+        AbacAccessControl::new(self.repository.clone(), expression, environment)
+            .is_identity_authorized(identity.clone())
+            .await
+    }
+
+    /// Return the headers describing an identity, added to the authorized requests
+    async fn identity_headers(&self, identity: &Identifier) -> Result<HeaderFields> {
+        let mut headers = vec![(
+            format!("{IDENTITY_HEADERS_PREFIX}identifier").into_bytes(),
+            identity.to_string().into_bytes(),
+        )];
+        if let Some(attributes) = self
+            .repository
+            .get_attributes_for(identity, Some(&self.resource))
+            .await?
+        {
+            for (key, value) in attributes.attrs() {
+                let key = String::from_utf8_lossy(key).to_lowercase();
+                let valid_key = key
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+                let valid_value = value.iter().all(|b| (0x20..0x7f).contains(b));
+                if valid_key && valid_value {
+                    headers.push((
+                        format!("{IDENTITY_HEADERS_PREFIX}attribute-{key}").into_bytes(),
+                        value.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(headers)
+    }
+}
+
+struct GrpcInterceptor {
+    /// Identity of the inlet
+    identity: Option<Identifier>,
+    policies: Arc<MethodPolicies>,
+    state: Mutex<ConnectionState>,
+}
+
+struct ConnectionState {
+    preface_received: bool,
+    to_server: Vec<u8>,
+    to_client: Vec<u8>,
+    /// HPACK context of the header blocks sent by the client
+    decoder: hpack::Decoder<'static>,
+    /// Header block split in several frames, until its last frame is received
+    pending_block: Option<HeaderBlock>,
+    /// Streams whose request headers were received
+    requests: HashSet<u32>,
+    /// Streams of the denied calls, whose frames are not sent to the server
+    denied: HashSet<u32>,
+    /// Streams of the denied calls, by payload of the `PING` sent to the server in place
+    /// of their request. The client is notified of the denial when the `PING` is acknowledged
+    denials: HashMap<[u8; 8], u32>,
+    identity_headers: Option<HeaderFields>,
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self {
+            preface_received: false,
+            to_server: vec![],
+            to_client: vec![],
+            decoder: hpack::Decoder::new(),
+            pending_block: None,
+            requests: HashSet::new(),
+            denied: HashSet::new(),
+            denials: HashMap::new(),
+            identity_headers: None,
+        }
+    }
+}
+
+struct HeaderBlock {
+    stream_id: u32,
+    end_stream: bool,
+    block: Vec<u8>,
+}
+
+/// Data received from the client
+enum Item {
+    /// Data which is not specific to a stream
+    Connection(Vec<u8>),
+    /// Frame of a stream
+    Stream(u32, Vec<u8>),
+    /// Decoded header block of a stream
+    Headers {
+        stream_id: u32,
+        end_stream: bool,
+        fields: HeaderFields,
+        is_request: bool,
+    },
+}
+
+impl GrpcInterceptor {
+    async fn process(&self, direction: PortalDirection, chunk: Vec<u8>) -> Result<Vec<u8>> {
+        match direction {
+            PortalDirection::ToPeer => self.to_server(chunk).await,
+            PortalDirection::FromPeer => self.to_client(chunk),
+        }
+    }
+
+    /// Return the frames to send to the server, once the calls are authorized
+    async fn to_server(&self, chunk: Vec<u8>) -> Result<Vec<u8>> {
+        let mut output = vec![];
+        for item in self.read_client_frames(chunk)? {
+            match item {
+                Item::Connection(bytes) => output.extend(bytes),
+                Item::Stream(stream_id, bytes) => {
+                    if !self.is_denied(stream_id) {
+                        output.extend(bytes)
+                    }
+                }
+                Item::Headers {
+                    stream_id,
+                    end_stream,
+                    mut fields,
+                    is_request,
+                } => {
+                    if is_request {
+                        match self.authorize(&fields).await? {
+                            Some(identity_headers) => fields.extend(identity_headers),
+                            None => {
+                                output.extend(self.deny(stream_id));
+                                continue;
+                            }
+                        }
+                    } else if self.is_denied(stream_id) {
+                        continue;
+                    }
+                    let block = encode_header_block(
+                        fields
+                            .iter()
+                            .map(|(name, value)| (name.as_slice(), value.as_slice())),
+                    );
+                    output.extend(headers_frames(stream_id, end_stream, &block));
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Return the complete frames sent by the client, with their header blocks decoded
+    fn read_client_frames(&self, chunk: Vec<u8>) -> Result<Vec<Item>> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+        state.to_server.extend(chunk);
+
+        let mut items = vec![];
+        if !state.preface_received {
+            let length = state.to_server.len().min(CONNECTION_PREFACE.len());
+            if state.to_server[..length] != CONNECTION_PREFACE[..length] {
+                return Err(protocol_error("the client is not using HTTP/2 without TLS"));
+            }
+            if length < CONNECTION_PREFACE.len() {
+                return Ok(items);
+            }
+            state.to_server.drain(..length);
+            state.preface_received = true;
+            items.push(Item::Connection(CONNECTION_PREFACE.to_vec()));
+        }
+
+        while let Some(frame) = Frame::take(&mut state.to_server) {
+            match frame.frame_type {
+                HEADERS if state.pending_block.is_none() => {
+                    state.pending_block = Some(HeaderBlock {
+                        stream_id: frame.stream_id,
+                        end_stream: frame.has_flag(END_STREAM),
+                        block: vec![],
+                    });
+                }
+                CONTINUATION
+                    if state.pending_block.as_ref().map(|b| b.stream_id)
+                        == Some(frame.stream_id) => {}
+                _ if state.pending_block.is_some() => {
+                    return Err(protocol_error("a header block was interrupted"));
+                }
+                _ if frame.stream_id == 0 => {
+                    items.push(Item::Connection(frame.encode()));
+                    continue;
+                }
+                _ => {
+                    items.push(Item::Stream(frame.stream_id, frame.encode()));
+                    continue;
+                }
+            }
+
+            let fragment = frame.header_block_fragment()?.to_vec();
+            if let Some(pending) = state.pending_block.as_mut() {
+                pending.block.extend(fragment);
+            }
+            if frame.has_flag(END_HEADERS) {
+                if let Some(pending) = state.pending_block.take() {
+                    let fields = state
+                        .decoder
+                        .decode(&pending.block)
+                        .map_err(|e| protocol_error(&format!("invalid header block: {e:?}")))?;
+                    items.push(Item::Headers {
+                        stream_id: pending.stream_id,
+                        end_stream: pending.end_stream,
+                        fields,
+                        is_request: state.requests.insert(pending.stream_id),
+                    });
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Return the headers to add to a request if it is authorized
+    async fn authorize(&self, fields: &HeaderFields) -> Result<Option<HeaderFields>> {
+        let path = fields
+            .iter()
+            .find(|(name, _)| name == b":path")
+            .map(|(_, value)| String::from_utf8_lossy(value).to_string())
+            .unwrap_or_default();
+        let Some(identity) = &self.identity else {
+            warn!(%path, "Denying a call from an unknown identity");
+            return Ok(None);
+        };
+        if fields
+            .iter()
+            .any(|(name, _)| name.starts_with(IDENTITY_HEADERS_PREFIX.as_bytes()))
+        {
+            warn!(%identity, %path, "Denying a call setting the headers of the outlet");
+            return Ok(None);
+        }
+        if !self.policies.is_allowed(identity, &path).await? {
+            info!(%identity, %path, "Denying a call not allowed by the policies");
+            return Ok(None);
+        }
+
+        let cached = self.state.lock().unwrap().identity_headers.clone();
+        let headers = match cached {
+            Some(headers) => headers,
+            None => {
+                let headers = self.policies.identity_headers(identity).await?;
+                self.state.lock().unwrap().identity_headers = Some(headers.clone());
+                headers
+            }
+        };
+        Ok(Some(headers))
+    }
+
+    /// Drop the frames of a denied call, and return a `PING` to send to the server.
+    /// Its acknowledgement is replaced by the response to the denied call
+    fn deny(&self, stream_id: u32) -> Vec<u8> {
+        let payload: [u8; 8] = rand::random();
+        let mut state = self.state.lock().unwrap();
+        state.denied.insert(stream_id);
+        state.denials.insert(payload, stream_id);
+        Frame::new(PING, 0, 0, payload.to_vec()).encode()
+    }
+
+    fn is_denied(&self, stream_id: u32) -> bool {
+        self.state.lock().unwrap().denied.contains(&stream_id)
+    }
+
+    /// Return the frames to send to the client
+    fn to_client(&self, chunk: Vec<u8>) -> Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        state.to_client.extend(chunk);
+        let mut output = vec![];
+        while let Some(frame) = Frame::take(&mut state.to_client) {
+            match frame.frame_type {
+                SETTINGS if !frame.has_flag(ACK) => {
+                    // the server limits the size of the HPACK table of the client
+                    if let Some(size) = frame.setting(SETTINGS_HEADER_TABLE_SIZE) {
+                        state.decoder.set_max_table_size(size as usize);
+                    }
+                }
+                PING if frame.has_flag(ACK) => {
+                    let denial = <[u8; 8]>::try_from(frame.payload.as_slice())
+                        .ok()
+                        .and_then(|payload| state.denials.remove(&payload));
+                    if let Some(stream_id) = denial {
+                        output.extend(denied_response(stream_id));
+                        continue;
+                    }
+                }
+                _ => (),
+            }
+            output.extend(frame.encode());
+        }
+        Ok(output)
+    }
+}
+
+/// Trailers-only response of a denied call. Its header fields are not indexed, so that the
+/// HPACK context of the client stays synchronized with the server
+fn denied_response(stream_id: u32) -> Vec<u8> {
+    let block = encode_header_block([
+        (b":status".as_slice(), b"200".as_slice()),
+        (b"content-type".as_slice(), b"application/grpc".as_slice()),
+        (b"grpc-status".as_slice(), PERMISSION_DENIED),
+        (b"grpc-message".as_slice(), DENIED_MESSAGE),
+    ]);
+    headers_frames(stream_id, true, &block)
+}
+
+#[async_trait]
+impl PortalInterceptor for GrpcInterceptor {
+    async fn intercept(
+        &self,
+        _context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.process(direction, chunk).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grpc::GrpcMethodPolicy;
+    use ockam::identity::utils::now;
+    use ockam::identity::{AttributesEntry, IdentitiesStorage};
+    use std::collections::BTreeMap;
+    use PortalDirection::*;
+
+    const DATA: u8 = 0x0;
+
+    async fn factory(identity: &Identifier) -> Result<GrpcInterceptorFactory> {
+        factory_with_audience(identity, None).await
+    }
+
+    async fn factory_with_audience(
+        identity: &Identifier,
+        audience: Option<Vec<String>>,
+    ) -> Result<GrpcInterceptorFactory> {
+        let repository: Arc<dyn IdentitiesRepository> = IdentitiesStorage::create();
+        let attributes = BTreeMap::from([(b"team".to_vec(), b"payments".to_vec())]);
+        repository
+            .put_attributes(
+                identity,
+                AttributesEntry::new(attributes, now()?, None, None).with_audience(audience),
+            )
+            .await?;
+        let config = GrpcPortalConfig {
+            methods: vec![
+                GrpcMethodPolicy {
+                    method: "/payments.Payments/Refund".to_string(),
+                    policy: r#"(= subject.role "admin")"#.to_string(),
+                },
+                GrpcMethodPolicy {
+                    method: "/payments.Payments/*".to_string(),
+                    policy: r#"(= subject.team "payments")"#.to_string(),
+                },
+            ],
+            default_policy: None,
+        };
+        GrpcInterceptorFactory::new(&config, "payments-outlet", repository)
+    }
+
+    fn request(encoder: &mut hpack::Encoder, stream_id: u32, path: &str) -> Vec<u8> {
+        let block = encoder.encode([
+            (b":method".as_slice(), b"POST".as_slice()),
+            (b":path".as_slice(), path.as_bytes()),
+            (b"content-type".as_slice(), b"application/grpc".as_slice()),
+        ]);
+        let mut bytes = Frame::new(HEADERS, END_HEADERS, stream_id, block).encode();
+        bytes.extend(Frame::new(DATA, END_STREAM, stream_id, vec![0; 5]).encode());
+        bytes
+    }
+
+    fn frames(mut bytes: Vec<u8>) -> Vec<Frame> {
+        let mut frames = vec![];
+        while let Some(frame) = Frame::take(&mut bytes) {
+            frames.push(frame);
+        }
+        assert!(bytes.is_empty());
+        frames
+    }
+
+    fn header(fields: &HeaderFields, name: &str) -> Option<String> {
+        fields
+            .iter()
+            .find(|(n, _)| n == name.as_bytes())
+            .map(|(_, v)| String::from_utf8_lossy(v).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_authorize_calls() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let outlet = factory(&identity)
+            .await?
+            .interceptor(Some(identity.clone()));
+        let mut encoder = hpack::Encoder::new();
+        let mut client = CONNECTION_PREFACE.to_vec();
+        client.extend(request(&mut encoder, 1, "/payments.Payments/Get"));
+        client.extend(request(&mut encoder, 3, "/payments.Payments/Refund"));
+        // the indexed fields of a denied request can be used by the next requests
+        client.extend(request(&mut encoder, 5, "/payments.Payments/Get"));
+
+        // the preface is sent once it is complete
+        assert_eq!(
+            outlet.process(ToPeer, client[..10].to_vec()).await?,
+            Vec::<u8>::new()
+        );
+        let sent = outlet.process(ToPeer, client[10..].to_vec()).await?;
+        assert!(sent.starts_with(CONNECTION_PREFACE));
+        let sent = frames(sent[CONNECTION_PREFACE.len()..].to_vec());
+        let types: Vec<(u8, u32)> = sent.iter().map(|f| (f.frame_type, f.stream_id)).collect();
+        assert_eq!(
+            types,
+            vec![(HEADERS, 1), (DATA, 1), (PING, 0), (HEADERS, 5), (DATA, 5)]
+        );
+
+        let mut server = hpack::Decoder::new();
+        let fields = server.decode(&sent[0].payload).unwrap();
+        assert_eq!(
+            header(&fields, ":path").as_deref(),
+            Some("/payments.Payments/Get")
+        );
+        assert_eq!(
+            header(&fields, "x-ockam-identifier"),
+            Some(identity.to_string())
+        );
+        assert_eq!(
+            header(&fields, "x-ockam-attribute-team").as_deref(),
+            Some("payments")
+        );
+        let fields = server.decode(&sent[3].payload).unwrap();
+        assert_eq!(
+            header(&fields, ":path").as_deref(),
+            Some("/payments.Payments/Get")
+        );
+
+        // the acknowledgement of the ping is replaced by the response to the denied call
+        let ping = Frame::new(PING, ACK, 0, sent[2].payload.clone()).encode();
+        let received = frames(outlet.process(FromPeer, ping).await?);
+        assert_eq!(received.len(), 1);
+        assert_eq!(
+            (received[0].frame_type, received[0].stream_id),
+            (HEADERS, 3)
+        );
+        let fields = hpack::Decoder::new().decode(&received[0].payload).unwrap();
+        assert_eq!(header(&fields, "grpc-status").as_deref(), Some("7"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_only_use_the_attributes_valid_for_the_outlet() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let path = "/payments.Payments/Get";
+
+        let policies = factory_with_audience(&identity, Some(vec!["payments-outlet".to_string()]))
+            .await?
+            .policies;
+        assert!(policies.is_allowed(&identity, path).await?);
+        let headers = policies.identity_headers(&identity).await?;
+        assert_eq!(
+            header(&headers, "x-ockam-attribute-team").as_deref(),
+            Some("payments")
+        );
+
+        // attributes restricted to another outlet are neither used by the policies
+        // nor sent to the server
+        let policies = factory_with_audience(&identity, Some(vec!["other-outlet".to_string()]))
+            .await?
+            .policies;
+        assert!(!policies.is_allowed(&identity, path).await?);
+        let headers = policies.identity_headers(&identity).await?;
+        assert_eq!(
+            header(&headers, "x-ockam-identifier"),
+            Some(identity.to_string())
+        );
+        assert_eq!(header(&headers, "x-ockam-attribute-team"), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deny_spoofed_and_unknown_identities() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let factory = factory(&identity).await?;
+
+        let outlet = factory.interceptor(Some(identity.clone()));
+        let mut encoder = hpack::Encoder::new();
+        let block = encoder.encode([
+            (b":path".as_slice(), b"/payments.Payments/Get".as_slice()),
+            (b"x-ockam-attribute-role".as_slice(), b"admin".as_slice()),
+        ]);
+        let mut client = CONNECTION_PREFACE.to_vec();
+        client.extend(Frame::new(HEADERS, END_HEADERS | END_STREAM, 1, block).encode());
+        let sent = outlet.process(ToPeer, client.clone()).await?;
+        assert_eq!(
+            frames(sent[CONNECTION_PREFACE.len()..].to_vec())[0].frame_type,
+            PING
+        );
+
+        let unknown = factory.interceptor(None);
+        let mut client = CONNECTION_PREFACE.to_vec();
+        client.extend(request(
+            &mut hpack::Encoder::new(),
+            1,
+            "/payments.Payments/Get",
+        ));
+        let sent = unknown.process(ToPeer, client).await?;
+        assert_eq!(
+            frames(sent[CONNECTION_PREFACE.len()..].to_vec())[0].frame_type,
+            PING
+        );
+
+        // a connection which is not using HTTP/2 is closed
+        let http1 = factory.interceptor(Some(identity));
+        assert!(http1
+            .process(ToPeer, b"GET / HTTP/1.1\r\n".to_vec())
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+//! gRPC outlets, authorizing each call with the ABAC policy of its method, and forwarding the
+//! identifier and the attributes of the caller in `x-ockam-` metadata headers.
+
+mod frames;
+mod interceptor;
+
+pub use interceptor::GrpcInterceptorFactory;
+
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam_abac::Expr;
+use ockam_core::Result;
+
+use crate::error::ApiError;
+
+/// Method pattern matching all the methods
+pub const ANY_METHOD: &str = "*";
+
+/// Prefix of the metadata set by the outlet
+pub const IDENTITY_HEADERS_PREFIX: &str = "x-ockam-";
+
+/// Policies of the gRPC methods called through an outlet
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct GrpcPortalConfig {
+    /// Policies of the methods, the first rule matching a method applies
+    #[serde(default)]
+    #[n(1)] pub methods: Vec<GrpcMethodPolicy>,
+    /// Policy of the methods which don't match any rule
+    #[serde(default)]
+    #[n(2)] pub default_policy: Option<String>,
+}
+
+/// Policy of the methods matching a pattern
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct GrpcMethodPolicy {
+    /// A method, `/<package>.<service>/<method>`, all the methods of a service,
+    /// `/<package>.<service>/*`, or [`ANY_METHOD`]
+    #[n(1)] pub method: String,
+    /// ABAC policy expression
+    #[n(2)] pub policy: String,
+}
+
+impl GrpcPortalConfig {
+    /// Parse and validate the YAML policies of a gRPC outlet
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let config: GrpcPortalConfig = serde_yaml::from_str(yaml)
+            .map_err(|e| ApiError::core(format!("the gRPC policies can't be parsed: {e}")))?;
+        config.rules()?;
+        config.default_expression()?;
+        Ok(config)
+    }
+
+    /// Return the method patterns with their parsed policies
+    pub(crate) fn rules(&self) -> Result<Vec<(String, Expr)>> {
+        self.methods
+            .iter()
+            .map(|rule| {
+                if rule.method != ANY_METHOD
+                    && !(rule.method.starts_with('/') && rule.method[1..].contains('/'))
+                {
+                    return Err(ApiError::core(format!(
+                        "invalid gRPC method: {}, expected /<package>.<service>/<method>",
+                        rule.method
+                    )));
+                }
+                Ok((rule.method.clone(), parse_policy(&rule.policy)?))
+            })
+            .collect()
+    }
+
+    pub(crate) fn default_expression(&self) -> Result<Option<Expr>> {
+        self.default_policy.as_deref().map(parse_policy).transpose()
+    }
+}
+
+/// Return true if a method pattern matches the `:path` of a request
+pub fn method_matches(pattern: &str, path: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        _ if pattern == ANY_METHOD => true,
+        Some(service) if service.ends_with('/') => path.starts_with(service),
+        _ => pattern == path,
+    }
+}
+
+fn parse_policy(policy: &str) -> Result<Expr> {
+    ockam_abac::parse(policy)
+        .map_err(|e| ApiError::core(format!("invalid policy {policy}: {e}")))?
+        .ok_or_else(|| ApiError::core("a gRPC method policy can't be empty"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        let config = GrpcPortalConfig::parse(
+            r#"
+methods:
+  - method: /payments.Payments/Refund
+    policy: (= subject.role "admin")
+  - method: /payments.Payments/*
+    policy: "true"
+"#,
+        )?;
+        assert_eq!(config.rules()?.len(), 2);
+        assert!(config.default_expression()?.is_none());
+
+        assert!(GrpcPortalConfig::parse("methods: [{method: Refund, policy: 'true'}]").is_err());
+        assert!(GrpcPortalConfig::parse("default_policy: (= subject.role").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_method_matches() {
+        assert!(method_matches("*", "/payments.Payments/Refund"));
+        assert!(method_matches(
+            "/payments.Payments/*",
+            "/payments.Payments/Refund"
+        ));
+        assert!(!method_matches(
+            "/payments.Payments/*",
+            "/payments.PaymentsAdmin/Refund"
+        ));
+        assert!(method_matches(
+            "/payments.Payments/Refund",
+            "/payments.Payments/Refund"
+        ));
+        assert!(!method_matches(
+            "/payments.Payments/Refund",
+            "/payments.Payments/RefundAll"
+        ));
+    }
+}
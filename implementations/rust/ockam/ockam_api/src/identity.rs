@@ -1,3 +1,5 @@
+mod credentials_clock;
 mod enrollment_ticket;
 
+pub use credentials_clock::*;
 pub use enrollment_ticket::*;
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use ockam::identity::CredentialsClock;
+use ockam_core::env::get_env;
+use ockam_core::Result;
+
+/// Offset in seconds of the local clock compared to a reference clock, for example a NTP server.
+/// It is embedded in the credentials issued by this machine
+pub const OCKAM_CREDENTIALS_CLOCK_OFFSET: &str = "OCKAM_CREDENTIALS_CLOCK_OFFSET";
+
+/// Maximum difference in seconds accepted between the creation time of a credential
+/// and the local time, when verifying credentials
+pub const OCKAM_CREDENTIALS_ACCEPTANCE_WINDOW: &str = "OCKAM_CREDENTIALS_ACCEPTANCE_WINDOW";
+
+/// Return the clock settings used to issue and verify credentials, as configured
+/// with environment variables
+pub fn credentials_clock() -> Result<CredentialsClock> {
+    let mut clock = CredentialsClock::default();
+    if let Some(offset) = get_env::<i64>(OCKAM_CREDENTIALS_CLOCK_OFFSET)? {
+        clock = clock.with_clock_offset(offset);
+    }
+    if let Some(window) = get_env::<u64>(OCKAM_CREDENTIALS_ACCEPTANCE_WINDOW)? {
+        clock = clock.with_acceptance_window(Duration::from_secs(window));
+    }
+    Ok(clock)
+}
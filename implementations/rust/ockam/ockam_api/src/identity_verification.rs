@@ -0,0 +1,306 @@
+//! Verification of the identity of a peer with a short authentication string.
+
+use core::fmt::{Display, Formatter};
+use std::time::Duration;
+
+use minicbor::{Decode, Encode};
+use rand::random;
+use sha2::{Digest, Sha256};
+
+use ockam::identity::{Identifier, Identities, Identity};
+use ockam_core::flow_control::FlowControlId;
+use ockam_core::{Address, AllowAll, Result, Route};
+use ockam_node::{Context, MessageReceiveOptions, MessageSendReceiveOptions};
+
+use crate::error::ApiError;
+use crate::DefaultAddress;
+
+/// Message sent by the initiator with its identity and a commitment to its nonce
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct VerificationCommit {
+    /// Change history of the initiator
+    #[n(1)] pub identity: Vec<u8>,
+    #[n(2)] pub commitment: Vec<u8>,
+}
+
+/// Message sent by the responder with its identity and its nonce
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct VerificationReply {
+    /// Change history of the responder
+    #[n(1)] pub identity: Vec<u8>,
+    #[n(2)] pub nonce: Vec<u8>,
+}
+
+/// Message sent by the initiator to reveal its nonce
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct VerificationReveal {
+    #[n(1)] pub nonce: Vec<u8>,
+}
+
+/// Code compared by the users, displayed as two groups of 3 digits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VerificationCode(u32);
+
+impl VerificationCode {
+    fn new(
+        initiator_identity: &[u8],
+        responder_identity: &[u8],
+        initiator_nonce: &[u8],
+        responder_nonce: &[u8],
+    ) -> Self {
+        let mut hasher = Sha256::new();
+        // each part is prefixed with its length so that the parts can't be shifted
+        for part in [
+            initiator_identity,
+            responder_identity,
+            initiator_nonce,
+            responder_nonce,
+        ] {
+            hasher.update((part.len() as u64).to_be_bytes());
+            hasher.update(part);
+        }
+        let digest = hasher.finalize();
+        let value = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        Self(value % 1_000_000)
+    }
+}
+
+impl Display for VerificationCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:03} {:03}", self.0 / 1000, self.0 % 1000)
+    }
+}
+
+/// Identity of the peer, to be trusted once the users confirmed that their codes match
+#[derive(Debug, Clone)]
+pub struct VerifiedPeer {
+    pub identifier: Identifier,
+    pub code: VerificationCode,
+}
+
+/// Initiator side of the verification
+pub struct VerificationInitiator {
+    identity: Vec<u8>,
+    nonce: [u8; 32],
+}
+
+impl VerificationInitiator {
+    /// Start a verification with the change history of the local identity
+    pub fn new(identity: Vec<u8>) -> Self {
+        Self {
+            identity,
+            nonce: random(),
+        }
+    }
+
+    pub fn commit(&self) -> VerificationCommit {
+        VerificationCommit {
+            identity: self.identity.clone(),
+            commitment: Sha256::digest(self.nonce).to_vec(),
+        }
+    }
+
+    /// Check the identity of the responder and return the message revealing the nonce
+    pub async fn finish(
+        self,
+        identities: &Identities,
+        reply: VerificationReply,
+    ) -> Result<(VerificationReveal, VerifiedPeer)> {
+        let peer = import_identity(identities, &reply.identity).await?;
+        let code =
+            VerificationCode::new(&self.identity, &reply.identity, &self.nonce, &reply.nonce);
+        Ok((
+            VerificationReveal {
+                nonce: self.nonce.to_vec(),
+            },
+            VerifiedPeer {
+                identifier: peer.identifier().clone(),
+                code,
+            },
+        ))
+    }
+}
+
+/// Responder side of the verification
+pub struct VerificationResponder {
+    identity: Vec<u8>,
+    nonce: [u8; 32],
+    commit: VerificationCommit,
+}
+
+impl VerificationResponder {
+    /// Answer a verification with the change history of the local identity
+    pub fn new(identity: Vec<u8>, commit: VerificationCommit) -> Self {
+        Self {
+            identity,
+            nonce: random(),
+            commit,
+        }
+    }
+
+    pub fn reply(&self) -> VerificationReply {
+        VerificationReply {
+            identity: self.identity.clone(),
+            nonce: self.nonce.to_vec(),
+        }
+    }
+
+    /// Check the revealed nonce and the identity of the initiator
+    pub async fn finish(
+        self,
+        identities: &Identities,
+        reveal: VerificationReveal,
+    ) -> Result<VerifiedPeer> {
+        if Sha256::digest(&reveal.nonce).as_slice() != self.commit.commitment.as_slice() {
+            return Err(ApiError::core(
+                "the nonce of the initiator doesn't match its commitment",
+            ));
+        }
+        let peer = import_identity(identities, &self.commit.identity).await?;
+        let code = VerificationCode::new(
+            &self.commit.identity,
+            &self.identity,
+            &reveal.nonce,
+            &self.nonce,
+        );
+        Ok(VerifiedPeer {
+            identifier: peer.identifier().clone(),
+            code,
+        })
+    }
+}
+
+/// Check that the change history of the peer is valid
+async fn import_identity(identities: &Identities, change_history: &[u8]) -> Result<Identity> {
+    Identity::import(None, change_history, identities.vault().verifying_vault).await
+}
+
+/// Run the verification with a responder reachable with `route`
+pub async fn initiate_verification(
+    ctx: &Context,
+    identities: &Identities,
+    identity: Vec<u8>,
+    route: Route,
+    timeout: Duration,
+) -> Result<VerifiedPeer> {
+    let initiator = VerificationInitiator::new(identity);
+    let options = || MessageSendReceiveOptions::new().with_timeout(timeout);
+    let reply = ctx
+        .send_and_receive_extended::<Vec<u8>>(
+            route.clone(),
+            minicbor::to_vec(initiator.commit())?,
+            options(),
+        )
+        .await?
+        .body();
+    let (reveal, peer) = initiator
+        .finish(identities, minicbor::decode(&reply)?)
+        .await?;
+    // the responder acknowledges the reveal once it computed its code
+    ctx.send_and_receive_extended::<Vec<u8>>(route, minicbor::to_vec(reveal)?, options())
+        .await?;
+    Ok(peer)
+}
+
+/// Wait for an initiator connecting through the TCP listener with `flow_control_id`,
+/// and run the verification with it
+pub async fn respond_to_verification(
+    ctx: &Context,
+    identities: &Identities,
+    identity: Vec<u8>,
+    flow_control_id: &FlowControlId,
+    timeout: Duration,
+) -> Result<VerifiedPeer> {
+    let address = Address::from_string(DefaultAddress::IDENTITY_VERIFICATION);
+    ctx.flow_controls()
+        .add_consumer(address.clone(), flow_control_id);
+    let mut child_ctx = ctx.new_detached(address, AllowAll, AllowAll).await?;
+    let options = || MessageReceiveOptions::new().with_timeout(timeout);
+
+    let commit = child_ctx.receive_extended::<Vec<u8>>(options()).await?;
+    let return_route = commit.return_route();
+    let responder = VerificationResponder::new(identity, minicbor::decode(&commit.body())?);
+    child_ctx
+        .send(return_route, minicbor::to_vec(responder.reply())?)
+        .await?;
+
+    let reveal = child_ctx.receive_extended::<Vec<u8>>(options()).await?;
+    let return_route = reveal.return_route();
+    let peer = responder
+        .finish(identities, minicbor::decode(&reveal.body())?)
+        .await?;
+    child_ctx.send(return_route, Vec::<u8>::new()).await?;
+    Ok(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::identities;
+
+    async fn identity(identities: &Identities) -> Result<Vec<u8>> {
+        let identity = identities.identities_creation().create_identity().await?;
+        identity.export()
+    }
+
+    #[tokio::test]
+    async fn test_codes_match() -> Result<()> {
+        let alice_identities = identities();
+        let bob_identities = identities();
+        let alice = identity(&alice_identities).await?;
+        let bob = identity(&bob_identities).await?;
+
+        let initiator = VerificationInitiator::new(alice.clone());
+        let responder = VerificationResponder::new(bob.clone(), initiator.commit());
+        let (reveal, bob_peer) = initiator
+            .finish(&alice_identities, responder.reply())
+            .await?;
+        let alice_peer = responder.finish(&bob_identities, reveal).await?;
+
+        assert_eq!(alice_peer.code, bob_peer.code);
+        assert_eq!(
+            alice_peer.identifier,
+            Identity::import(None, &alice, alice_identities.vault().verifying_vault)
+                .await?
+                .identifier()
+                .clone()
+        );
+        assert_eq!(alice_peer.code.to_string().len(), 7);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_substituted_identity_changes_the_codes() -> Result<()> {
+        let alice_identities = identities();
+        let bob_identities = identities();
+        let mallory_identities = identities();
+        let alice = identity(&alice_identities).await?;
+        let bob = identity(&bob_identities).await?;
+        let mallory = identity(&mallory_identities).await?;
+
+        // mallory replaces the identity of alice in the commit received by bob
+        let initiator = VerificationInitiator::new(alice);
+        let mut commit = initiator.commit();
+        commit.identity = mallory;
+        let responder = VerificationResponder::new(bob, commit);
+        let (reveal, bob_peer) = initiator
+            .finish(&alice_identities, responder.reply())
+            .await?;
+        let alice_peer = responder.finish(&bob_identities, reveal).await?;
+        assert_ne!(alice_peer.code, bob_peer.code);
+
+        // a nonce which doesn't match the commitment is rejected
+        let initiator = VerificationInitiator::new(identity(&alice_identities).await?);
+        let responder =
+            VerificationResponder::new(identity(&bob_identities).await?, initiator.commit());
+        let forged = VerificationReveal { nonce: vec![0; 32] };
+        assert!(responder.finish(&bob_identities, forged).await.is_err());
+        Ok(())
+    }
+}
@@ -128,6 +128,11 @@ impl Worker for KafkaPortalWorker {
                 }
             }
             PortalMessage::Ping => self.forward(context, routed_message).await?,
+            // the sessions of the kafka portals are not resumed since the payloads are
+            // transformed, but the messages are forwarded so that the portals can close them
+            PortalMessage::Resume { .. } | PortalMessage::Resumed { .. } => {
+                self.forward(context, routed_message).await?
+            }
 
             PortalMessage::Pong => {
                 match self.receiving {
@@ -0,0 +1,406 @@
+//! A key-value store for the applications connected to a node.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::storage::Storage;
+use ockam::identity::{
+    secure_channel_required, Identifier, IdentityAttributesReader, IdentitySecureChannelLocalInfo,
+    SecureClient,
+};
+use ockam_core::api::{Method, Request, RequestHeader, Response};
+use ockam_core::{Result, Routed, Worker};
+use ockam_node::Context;
+
+use crate::DefaultAddress;
+
+/// Storage namespace of the key-value store entries
+const KV_STORE_NAMESPACE: &str = "kv_store";
+
+/// Maximum size of a value
+const MAX_VALUE_SIZE: usize = 64 * 1024;
+
+/// Attributes required to read or write an entry, in addition to its owner.
+/// An identity is granted an access if it has all the attributes of at least one of the rules
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct KvAcl {
+    #[n(1)] pub read: Vec<BTreeMap<String, String>>,
+    #[n(2)] pub write: Vec<BTreeMap<String, String>>,
+}
+
+impl KvAcl {
+    /// Grant a read access to the identities having some attributes
+    pub fn with_reader(mut self, attributes: BTreeMap<String, String>) -> Self {
+        self.read.push(attributes);
+        self
+    }
+
+    /// Grant a read and write access to the identities having some attributes
+    pub fn with_writer(mut self, attributes: BTreeMap<String, String>) -> Self {
+        self.read.push(attributes.clone());
+        self.write.push(attributes);
+        self
+    }
+
+    fn allows(rules: &[BTreeMap<String, String>], attributes: &BTreeMap<Vec<u8>, Vec<u8>>) -> bool {
+        rules.iter().any(|rule| {
+            rule.iter().all(|(name, value)| {
+                attributes.get(name.as_bytes()).map(|v| v.as_slice()) == Some(value.as_bytes())
+            })
+        })
+    }
+}
+
+/// An entry of the store
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct KvEntry {
+    #[n(1)] pub value: Vec<u8>,
+    #[n(2)] pub owner: Identifier,
+    #[n(3)] pub acl: KvAcl,
+}
+
+/// Request body to create or update an entry.
+/// The access control list is only changed if it is present and the request is made by the owner
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PutKvEntry {
+    #[n(1)] pub value: Vec<u8>,
+    #[n(2)] pub acl: Option<KvAcl>,
+}
+
+enum Access {
+    Read,
+    Write,
+}
+
+/// Key-value store worker
+pub struct KvStore {
+    storage: Arc<dyn Storage>,
+    attributes: Arc<dyn IdentityAttributesReader>,
+}
+
+impl KvStore {
+    pub fn new(storage: Arc<dyn Storage>, attributes: Arc<dyn IdentityAttributesReader>) -> Self {
+        Self {
+            storage,
+            attributes,
+        }
+    }
+
+    async fn entry(&self, key: &str) -> Result<Option<KvEntry>> {
+        match self.storage.get(key, KV_STORE_NAMESPACE).await? {
+            Some(bytes) => Ok(Some(minicbor::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn is_allowed(&self, identifier: &Identifier, entry: &KvEntry, access: Access) -> bool {
+        if &entry.owner == identifier {
+            return true;
+        }
+        let rules = match access {
+            Access::Read => &entry.acl.read,
+            Access::Write => &entry.acl.write,
+        };
+        if rules.is_empty() {
+            return false;
+        }
+        match self
+            .attributes
+            .get_attributes_for(identifier, Some(DefaultAddress::KV_STORE))
+            .await
+        {
+            Ok(Some(attributes)) => KvAcl::allows(rules, attributes.attrs()),
+            _ => false,
+        }
+    }
+
+    /// Return the keys of the entries which can be read by an identity
+    pub async fn keys(&self, identifier: &Identifier) -> Result<Vec<String>> {
+        let mut keys = vec![];
+        for key in self.storage.keys(KV_STORE_NAMESPACE).await? {
+            if let Some(entry) = self.entry(&key).await? {
+                if self.is_allowed(identifier, &entry, Access::Read).await {
+                    keys.push(key);
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Return the value of an entry, if it exists and can be read by an identity
+    pub async fn get(&self, identifier: &Identifier, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.entry(key).await? {
+            Some(entry) if self.is_allowed(identifier, &entry, Access::Read).await => {
+                Ok(Some(entry.value))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Create or update an entry. Return false if the identity is not allowed to update it
+    pub async fn put(&self, identifier: &Identifier, key: &str, put: PutKvEntry) -> Result<bool> {
+        let entry = match self.entry(key).await? {
+            Some(entry) => {
+                if !self.is_allowed(identifier, &entry, Access::Write).await {
+                    return Ok(false);
+                }
+                let acl = match put.acl {
+                    Some(acl) if &entry.owner == identifier => acl,
+                    Some(_) => return Ok(false),
+                    None => entry.acl,
+                };
+                KvEntry {
+                    value: put.value,
+                    owner: entry.owner,
+                    acl,
+                }
+            }
+            None => KvEntry {
+                value: put.value,
+                owner: identifier.clone(),
+                acl: put.acl.unwrap_or_default(),
+            },
+        };
+        self.storage
+            .set(
+                key,
+                KV_STORE_NAMESPACE.to_string(),
+                minicbor::to_vec(&entry)?,
+            )
+            .await?;
+        Ok(true)
+    }
+
+    /// Delete an entry. Return false if the identity is not allowed to delete it
+    pub async fn delete(&self, identifier: &Identifier, key: &str) -> Result<bool> {
+        match self.entry(key).await? {
+            Some(entry) => {
+                if !self.is_allowed(identifier, &entry, Access::Write).await {
+                    return Ok(false);
+                }
+                self.storage.del(key, KV_STORE_NAMESPACE).await?;
+                Ok(true)
+            }
+            None => Ok(true),
+        }
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for KvStore {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::kv_store",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                body   = %req.has_body(),
+                "request"
+            }
+            let path_segments = req.path_segments::<5>();
+            let res = match (req.method(), path_segments.as_slice()) {
+                (Some(Method::Get), ["entries"]) => {
+                    Response::ok(&req).body(self.keys(&from).await?).to_vec()?
+                }
+                (Some(Method::Get), ["entries", key]) => match self.get(&from, key).await? {
+                    Some(value) => Response::ok(&req).body(value).to_vec()?,
+                    None => Response::not_found(&req, &format!("no entry {key}")).to_vec()?,
+                },
+                (Some(Method::Put), ["entries", key]) => {
+                    let put: PutKvEntry = dec.decode()?;
+                    if put.value.len() > MAX_VALUE_SIZE {
+                        Response::bad_request(
+                            &req,
+                            &format!("the value is larger than {MAX_VALUE_SIZE} bytes"),
+                        )
+                        .to_vec()?
+                    } else if self.put(&from, key, put).await? {
+                        debug!("{from} updated the entry {key}");
+                        Response::ok(&req).to_vec()?
+                    } else {
+                        Response::unauthorized(req.id()).to_vec()?
+                    }
+                }
+                (Some(Method::Delete), ["entries", key]) => {
+                    if self.delete(&from, key).await? {
+                        Response::ok(&req).to_vec()?
+                    } else {
+                        Response::unauthorized(req.id()).to_vec()?
+                    }
+                }
+                _ => Response::unknown_path(&req).to_vec()?,
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Client of a key-value store
+pub struct KvStoreClient {
+    client: SecureClient,
+    address: String,
+}
+
+impl KvStoreClient {
+    /// The client must connect to the node running the store at `address`
+    pub fn new(client: SecureClient, address: impl Into<String>) -> Self {
+        Self {
+            client,
+            address: address.into(),
+        }
+    }
+
+    /// Return the keys of the entries which can be read
+    pub async fn keys(&self, ctx: &Context) -> Result<Vec<String>> {
+        let request = Request::get("/entries");
+        self.client
+            .ask(ctx, &self.address, request)
+            .await?
+            .success()
+    }
+
+    /// Return the value of an entry, if it exists and can be read
+    pub async fn get(&self, ctx: &Context, key: &str) -> Result<Option<Vec<u8>>> {
+        let request = Request::get(format!("/entries/{key}"));
+        self.client.ask(ctx, &self.address, request).await?.found()
+    }
+
+    /// Create or update an entry
+    pub async fn put(
+        &self,
+        ctx: &Context,
+        key: &str,
+        value: Vec<u8>,
+        acl: Option<KvAcl>,
+    ) -> Result<()> {
+        let request = Request::put(format!("/entries/{key}")).body(PutKvEntry { value, acl });
+        self.client
+            .tell(ctx, &self.address, request)
+            .await?
+            .success()
+    }
+
+    /// Delete an entry
+    pub async fn delete(&self, ctx: &Context, key: &str) -> Result<()> {
+        let request = Request::delete(format!("/entries/{key}"));
+        self.client
+            .tell(ctx, &self.address, request)
+            .await?
+            .success()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::storage::InMemoryStorage;
+    use ockam::identity::{identities, AttributesEntry};
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_kv_store_access_control() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000002")?;
+        let carol = Identifier::from_str("I0000000000000000000000000000000000000003")?;
+
+        let repository = identities().repository();
+        repository
+            .put_attributes(
+                &bob,
+                AttributesEntry::new(
+                    BTreeMap::from([(b"role".to_vec(), b"admin".to_vec())]),
+                    ockam::identity::utils::now()?,
+                    None,
+                    None,
+                ),
+            )
+            .await?;
+        let store = KvStore::new(InMemoryStorage::create(), repository.as_attributes_reader());
+
+        let acl = KvAcl::default()
+            .with_writer(BTreeMap::from([("role".to_string(), "admin".to_string())]));
+        let put = |value: &str, acl: Option<KvAcl>| PutKvEntry {
+            value: value.as_bytes().to_vec(),
+            acl,
+        };
+        assert!(store.put(&alice, "config", put("1", Some(acl))).await?);
+
+        // an admin can read and update the entry, but not change its access control list
+        assert_eq!(store.get(&bob, "config").await?, Some(b"1".to_vec()));
+        assert!(store.put(&bob, "config", put("2", None)).await?);
+        assert!(
+            !store
+                .put(&bob, "config", put("3", Some(KvAcl::default())))
+                .await?
+        );
+        assert_eq!(store.get(&alice, "config").await?, Some(b"2".to_vec()));
+
+        // other identities can't see the entry
+        assert_eq!(store.get(&carol, "config").await?, None);
+        assert!(store.keys(&carol).await?.is_empty());
+        assert!(!store.put(&carol, "config", put("4", None)).await?);
+        assert!(!store.delete(&carol, "config").await?);
+
+        assert_eq!(store.keys(&bob).await?, vec!["config".to_string()]);
+        assert!(store.delete(&bob, "config").await?);
+        assert_eq!(store.get(&alice, "config").await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_kv_store_access_control_with_audience() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000002")?;
+        let carol = Identifier::from_str("I0000000000000000000000000000000000000003")?;
+
+        // bob's attributes can only be used for an outlet, carol's for the key-value store
+        let repository = identities().repository();
+        let entry = |audience: &str| {
+            AttributesEntry::new(
+                BTreeMap::from([(b"role".to_vec(), b"admin".to_vec())]),
+                ockam::identity::utils::now().unwrap(),
+                None,
+                None,
+            )
+            .with_audience(Some(vec![audience.to_string()]))
+        };
+        repository.put_attributes(&bob, entry("outlet-1")).await?;
+        repository
+            .put_attributes(&carol, entry(DefaultAddress::KV_STORE))
+            .await?;
+        let store = KvStore::new(InMemoryStorage::create(), repository.as_attributes_reader());
+
+        let acl = KvAcl::default()
+            .with_reader(BTreeMap::from([("role".to_string(), "admin".to_string())]));
+        let put = PutKvEntry {
+            value: b"1".to_vec(),
+            acl: Some(acl),
+        };
+        assert!(store.put(&alice, "config", put).await?);
+
+        assert_eq!(store.get(&bob, "config").await?, None);
+        assert!(store.keys(&bob).await?.is_empty());
+        assert_eq!(store.get(&carol, "config").await?, Some(b"1".to_vec()));
+        Ok(())
+    }
+}
@@ -11,11 +11,9 @@
 //! │  ├─ c1.json
 //! │  ├─ c2.json
 //! │  └─ ...
-//! ├─ defaults
-//! │  ├── credential -> ...
-//! │  ├── identity -> ...
-//! │  ├── node -> ...
-//! │  └── vault -> ...
+//! ├─ audit_log.jsonl
+//! ├─ defaults.json
+//! ├─ events.jsonl
 //! ├─ identities
 //! │  ├─ data
 //! │  │  ├─ authenticated-storage.lmdb
@@ -35,6 +33,10 @@
 //! │  │  └─ version.log
 //! │  ├─ node2
 //! │  └─ ...
+//! ├─ profiles
+//! │  ├─ active.json
+//! │  ├─ profile1
+//! │  └─ ...
 //! ├─ projects
 //! │  └─ default.json
 //! ├─ trust_contexts
@@ -56,17 +58,28 @@
 //! Those files are created with the `ockam credential store` command. They are then read during the creation of
 //! a secure channel to send the credentials to the other party
 //!
-//! # `defaults`
+//! # `defaults.json`
 //!
-//! This directory contains symlinks to other files or directories in order to specify which node,
-//! identity, credential or vault must be considered as a default when running a command expecting those
+//! This file contains the name of the default item for each kind of item: node, identity, credential, vault,...
+//! It specifies which item must be considered as a default when running a command expecting those
 //! inputs
 //!
+//! # `events.jsonl`
+//!
+//! This file logs the changes made to the items and to the defaults, one JSON event per line. It is
+//! read by the applications subscribed to the changes of the state with `CliState::subscribe`
+//!
+//! # `audit_log.jsonl`
+//!
+//! This file records the creations and deletions of items and the changes of the defaults, with
+//! the time, the actor and the previous and new values of each change. It is kept when the state is
+//! reset and it is queried with `CliState::audit_log`
+//!
 //! # `identities`
 //!
 //! This directory contains one file per identity and a data directory. An identity file is created
-//! with the `ockam identity create` command or created by default for some commands (in that case the
-//! `defaults/identity` symlink points to that identity). The identity file contains:
+//! with the `ockam identity create` command or created by default for some commands (in that case it is
+//! recorded as the default `identity` in `defaults.json`). The identity file contains:
 //!
 //! - the identity identifier
 //! - the enrollment status for that identity
@@ -98,6 +111,12 @@
 //!  - log files: for system errors and system outputs. The stdout.log file is where almost all the node logs are written
 //!  - a version number for the configuration
 //!
+//! # `profiles`
+//!
+//! This directory contains one directory per named profile, with the same layout as the root directory,
+//! which stores the `default` profile. The `active.json` file contains the name of the profile used
+//! when the `OCKAM_PROFILE` environment variable is not set
+//!
 //! # `projects`
 //!
 //! This directory contains a list of files, one per project that was created, either the default project
@@ -124,22 +143,48 @@
 //! channels to sign or encrypt data involved in the handshake.
 //!
 pub mod address;
+pub mod attributes_export;
 pub mod auth;
 pub mod authenticator;
+pub mod bearer_token;
 pub mod bootstrapped_identities_store;
 pub mod cli_state;
 pub mod cloud;
 pub mod config;
+pub mod config_updates;
 pub mod echoer;
 pub mod enroll;
 pub mod error;
+pub mod fleet;
+pub mod grpc;
 pub mod hop;
 pub mod identity;
+pub mod identity_verification;
 pub mod kafka;
+pub mod kv_store;
+pub mod members_replication;
+pub mod metrics_exporter;
 pub mod minicbor_url;
+pub mod mqtt;
+pub mod node_hooks;
 pub mod nodes;
+pub mod notifier;
 pub mod okta;
+pub mod outlet_resolver;
+pub mod ping;
+pub mod policy_bundle;
 pub mod port_range;
+pub mod portal_dns;
+pub mod portal_events;
+pub mod portal_invitation;
+pub mod portal_tap;
+pub mod postgres_outlet;
+pub mod resource_profile;
+pub mod rollout;
+pub mod service_registry;
+pub mod stdio_bridge;
+pub mod storage_maintenance;
+pub mod test_harness;
 pub mod trust_context;
 pub mod uppercase;
 
@@ -163,6 +208,7 @@ impl DefaultAddress {
     pub const RELAY_SERVICE: &'static str = "forwarding_service";
     pub const UPPERCASE_SERVICE: &'static str = "uppercase";
     pub const ECHO_SERVICE: &'static str = "echo";
+    pub const PING_SERVICE: &'static str = "ping";
     pub const HOP_SERVICE: &'static str = "hop";
     pub const CREDENTIALS_SERVICE: &'static str = "credentials";
     pub const SECURE_CHANNEL_LISTENER: &'static str = "api";
@@ -175,6 +221,12 @@ impl DefaultAddress {
     pub const KAFKA_CONSUMER: &'static str = "kafka_consumer";
     pub const KAFKA_PRODUCER: &'static str = "kafka_producer";
     pub const KAFKA_DIRECT: &'static str = "kafka_direct";
+    pub const MEMBERS_REPLICATION: &'static str = "members_replication";
+    pub const SERVICE_REGISTRY: &'static str = "service_registry";
+    pub const KV_STORE: &'static str = "kv_store";
+    pub const IDENTITY_VERIFICATION: &'static str = "identity_verification";
+    pub const CONFIG_UPDATES: &'static str = "config_updates";
+    pub const FLEET: &'static str = "fleet";
 
     pub fn is_valid(name: &str) -> bool {
         matches!(
@@ -183,6 +235,7 @@ impl DefaultAddress {
                 | Self::RELAY_SERVICE
                 | Self::UPPERCASE_SERVICE
                 | Self::ECHO_SERVICE
+                | Self::PING_SERVICE
                 | Self::HOP_SERVICE
                 | Self::CREDENTIALS_SERVICE
                 | Self::SECURE_CHANNEL_LISTENER
@@ -195,6 +248,12 @@ impl DefaultAddress {
                 | Self::KAFKA_PRODUCER
                 | Self::KAFKA_OUTLET
                 | Self::KAFKA_DIRECT
+                | Self::MEMBERS_REPLICATION
+                | Self::SERVICE_REGISTRY
+                | Self::KV_STORE
+                | Self::IDENTITY_VERIFICATION
+                | Self::CONFIG_UPDATES
+                | Self::FLEET
         )
     }
 
@@ -204,6 +263,7 @@ impl DefaultAddress {
             Self::RELAY_SERVICE,
             Self::UPPERCASE_SERVICE,
             Self::ECHO_SERVICE,
+            Self::PING_SERVICE,
             Self::HOP_SERVICE,
             Self::CREDENTIALS_SERVICE,
             Self::SECURE_CHANNEL_LISTENER,
@@ -216,6 +276,12 @@ impl DefaultAddress {
             Self::KAFKA_PRODUCER,
             Self::KAFKA_OUTLET,
             Self::KAFKA_DIRECT,
+            Self::MEMBERS_REPLICATION,
+            Self::SERVICE_REGISTRY,
+            Self::KV_STORE,
+            Self::IDENTITY_VERIFICATION,
+            Self::CONFIG_UPDATES,
+            Self::FLEET,
         ]
         .iter()
         .copied()
@@ -226,6 +292,7 @@ pub mod actions {
     use ockam_abac::Action;
 
     pub const HANDLE_MESSAGE: Action = Action::assert_inline("handle_message");
+    pub const TAP: Action = Action::assert_inline("tap");
 }
 
 pub mod resources {
@@ -303,6 +370,7 @@ mod test {
         assert!(DefaultAddress::is_valid(DefaultAddress::RELAY_SERVICE));
         assert!(DefaultAddress::is_valid(DefaultAddress::UPPERCASE_SERVICE));
         assert!(DefaultAddress::is_valid(DefaultAddress::ECHO_SERVICE));
+        assert!(DefaultAddress::is_valid(DefaultAddress::PING_SERVICE));
         assert!(DefaultAddress::is_valid(DefaultAddress::HOP_SERVICE));
         assert!(DefaultAddress::is_valid(
             DefaultAddress::CREDENTIALS_SERVICE
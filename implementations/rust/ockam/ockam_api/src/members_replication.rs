@@ -0,0 +1,506 @@
+//! Replication of the members attributes of an authority to edge nodes.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::time::Duration;
+
+use minicbor::{Decode, Decoder, Encode};
+use tokio::task::JoinHandle;
+use tracing::{debug, trace, warn};
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{
+    secure_channel_required, AttributesEntry, Identifier, Identities, IdentitiesRepository,
+    IdentityAttributesReader, IdentityAttributesWriter, IdentityError,
+    IdentitySecureChannelLocalInfo, SecureClient,
+};
+use ockam_core::api::{Method, Request, RequestHeader, Response};
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::{async_trait, Address, DenyAll, Result, Routed, Worker};
+use ockam_node::Context;
+
+use crate::DefaultAddress;
+
+/// Identifier for the schema of the statements signing a members delta
+pub const MEMBERS_REPLICATION_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(3);
+
+/// Name of the attribute containing the SHA-256 digest of a members delta
+pub const MEMBERS_REPLICATION_DIGEST: &[u8] = b"ockam.replication.digest";
+
+/// Attribute, set to "true", of the members allowed to replicate the members of an authority
+pub const MEMBERS_REPLICA_ATTRIBUTE: &str = "ockam-replica";
+
+/// Maximum number of changes kept by the authority.
+/// Replicas lagging behind receive all the members instead
+const MAX_CHANGES: usize = 10_000;
+
+/// Validity of the statement signing a members delta
+const STATEMENT_VALIDITY: Duration = Duration::from_secs(10 * 60);
+
+/// Change made to the attributes of a member
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct MemberChange {
+    #[n(1)] pub identifier: Identifier,
+    /// New attributes of the member, or None if the member has been removed
+    #[n(2)] pub entry: Option<AttributesEntry>,
+}
+
+/// Changes made to the members of an authority up to a given version
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct MembersDelta {
+    #[n(1)] pub epoch: u64,
+    #[n(2)] pub version: u64,
+    /// If true the delta contains all the members and the members
+    /// missing from the delta must be removed from the replica
+    #[n(3)] pub full: bool,
+    #[n(4)] pub changes: Vec<MemberChange>,
+}
+
+/// An encoded [`MembersDelta`] with a statement, signed by the authority, attesting its digest
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SignedMembersDelta {
+    #[cbor(with = "minicbor::bytes")]
+    #[n(1)] pub delta: Vec<u8>,
+    #[n(2)] pub statement: CredentialAndPurposeKey,
+}
+
+/// Request for the changes made since the last version known by a replica
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SyncMembers {
+    #[n(1)] pub epoch: Option<u64>,
+    #[n(2)] pub version: u64,
+}
+
+/// In-memory log of the changes made to the members of an authority.
+///
+/// The log is not persisted: each authority process starts a new epoch, so that the replicas
+/// synchronized with a previous process fetch all the members again.
+pub struct MembersChangeLog {
+    epoch: u64,
+    state: Mutex<ChangeLogState>,
+}
+
+#[derive(Default)]
+struct ChangeLogState {
+    version: u64,
+    changes: VecDeque<(u64, MemberChange)>,
+}
+
+impl Default for MembersChangeLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MembersChangeLog {
+    pub fn new() -> Self {
+        Self {
+            epoch: rand::random(),
+            state: Mutex::new(ChangeLogState::default()),
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Record the new attributes of a member, or its removal
+    pub fn record(&self, identifier: &Identifier, entry: Option<AttributesEntry>) {
+        let mut state = self.state.lock().unwrap();
+        state.version += 1;
+        let version = state.version;
+        state.changes.push_back((
+            version,
+            MemberChange {
+                identifier: identifier.clone(),
+                entry,
+            },
+        ));
+        if state.changes.len() > MAX_CHANGES {
+            state.changes.pop_front();
+        }
+    }
+
+    /// Return the current version and the changes made after a given version.
+    /// The changes are None if they are not available anymore
+    pub fn changes_since(
+        &self,
+        epoch: Option<u64>,
+        version: u64,
+    ) -> (u64, Option<Vec<MemberChange>>) {
+        let state = self.state.lock().unwrap();
+        if epoch != Some(self.epoch) || version > state.version {
+            return (state.version, None);
+        }
+        let oldest = state
+            .changes
+            .front()
+            .map(|(v, _)| *v)
+            .unwrap_or(state.version + 1);
+        if version + 1 < oldest {
+            return (state.version, None);
+        }
+
+        // only keep the last change for each member
+        let mut latest = BTreeMap::new();
+        for (_, change) in state.changes.iter().filter(|(v, _)| *v > version) {
+            latest.insert(change.identifier.clone(), change.clone());
+        }
+        (state.version, Some(latest.into_values().collect()))
+    }
+}
+
+/// Attributes writer recording each change in a [`MembersChangeLog`]
+pub struct ReplicatedAttributesWriter {
+    writer: Arc<dyn IdentityAttributesWriter>,
+    reader: Arc<dyn IdentityAttributesReader>,
+    change_log: Arc<MembersChangeLog>,
+}
+
+impl ReplicatedAttributesWriter {
+    pub fn new(
+        writer: Arc<dyn IdentityAttributesWriter>,
+        reader: Arc<dyn IdentityAttributesReader>,
+        change_log: Arc<MembersChangeLog>,
+    ) -> Self {
+        Self {
+            writer,
+            reader,
+            change_log,
+        }
+    }
+}
+
+#[async_trait]
+impl IdentityAttributesWriter for ReplicatedAttributesWriter {
+    async fn put_attributes(&self, identity: &Identifier, entry: AttributesEntry) -> Result<()> {
+        self.writer.put_attributes(identity, entry.clone()).await?;
+        self.change_log.record(identity, Some(entry));
+        Ok(())
+    }
+
+    async fn put_attribute_value(
+        &self,
+        subject: &Identifier,
+        attribute_name: Vec<u8>,
+        attribute_value: Vec<u8>,
+    ) -> Result<()> {
+        self.writer
+            .put_attribute_value(subject, attribute_name, attribute_value)
+            .await?;
+        let entry = self.reader.get_attributes(subject).await?;
+        self.change_log.record(subject, entry);
+        Ok(())
+    }
+
+    async fn delete(&self, identity: &Identifier) -> Result<()> {
+        self.writer.delete(identity).await?;
+        self.change_log.record(identity, None);
+        Ok(())
+    }
+}
+
+/// Service returning the signed changes made to the members of an authority
+pub struct MembersReplicationServer {
+    authority: Identifier,
+    identities: Arc<Identities>,
+    change_log: Arc<MembersChangeLog>,
+}
+
+impl MembersReplicationServer {
+    pub fn new(
+        authority: Identifier,
+        identities: Arc<Identities>,
+        change_log: Arc<MembersChangeLog>,
+    ) -> Self {
+        Self {
+            authority,
+            identities,
+            change_log,
+        }
+    }
+
+    async fn members_delta(&self, request: &SyncMembers) -> Result<MembersDelta> {
+        let epoch = self.change_log.epoch();
+        let (version, changes) = self
+            .change_log
+            .changes_since(request.epoch, request.version);
+        match changes {
+            Some(changes) => Ok(MembersDelta {
+                epoch,
+                version,
+                full: false,
+                changes,
+            }),
+            None => {
+                let members = self.identities.repository().list().await?;
+                debug!(
+                    "sending all the {} members to a replica at version {}",
+                    members.len(),
+                    request.version
+                );
+                Ok(MembersDelta {
+                    epoch,
+                    version,
+                    full: true,
+                    changes: members
+                        .into_iter()
+                        .map(|(identifier, entry)| MemberChange {
+                            identifier,
+                            entry: Some(entry),
+                        })
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    async fn sign(&self, replica: &Identifier, delta: &MembersDelta) -> Result<SignedMembersDelta> {
+        let delta = minicbor::to_vec(delta)?;
+        let digest = self
+            .identities
+            .vault()
+            .verifying_vault
+            .sha256(&delta)
+            .await?;
+        let attributes = AttributesBuilder::with_schema(MEMBERS_REPLICATION_SCHEMA)
+            .with_attribute(MEMBERS_REPLICATION_DIGEST.to_vec(), digest.0.to_vec())
+            .build();
+        let statement = self
+            .identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(&self.authority, replica, attributes, STATEMENT_VALIDITY)
+            .await?;
+        Ok(SignedMembersDelta { delta, statement })
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for MembersReplicationServer {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::members_replication",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                body   = %req.has_body(),
+                "request"
+            }
+            let path_segments = req.path_segments::<5>();
+            let res = match (req.method(), path_segments.as_slice()) {
+                (Some(Method::Get), ["changes"]) => {
+                    let request: SyncMembers = dec.decode()?;
+                    let delta = self.members_delta(&request).await?;
+                    let signed = self.sign(&from, &delta).await?;
+                    Response::ok(&req).body(signed).to_vec()?
+                }
+                _ => Response::unknown_path(&req).to_vec()?,
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Local copy of the members attributes of an authority
+pub struct MembersReplica {
+    client: SecureClient,
+    authority: Identifier,
+    replica: Identifier,
+    identities: Arc<Identities>,
+    repository: Arc<dyn IdentitiesRepository>,
+    cursor: SyncMembers,
+}
+
+impl MembersReplica {
+    /// Create a replica storing the members attributes in the repository of `identities`.
+    /// The client must connect the `replica` identity to the authority node
+    pub fn new(
+        client: SecureClient,
+        authority: Identifier,
+        replica: Identifier,
+        identities: Arc<Identities>,
+    ) -> Self {
+        let repository = identities.repository();
+        Self {
+            client,
+            authority,
+            replica,
+            identities,
+            repository,
+            cursor: SyncMembers::default(),
+        }
+    }
+
+    /// Fetch and apply the changes made since the last synchronization.
+    /// Return the number of applied changes
+    pub async fn sync(&mut self, ctx: &Context) -> Result<usize> {
+        let request = Request::get("/changes").body(self.cursor.clone());
+        let signed: SignedMembersDelta = self
+            .client
+            .ask(ctx, DefaultAddress::MEMBERS_REPLICATION, request)
+            .await?
+            .success()?;
+        let delta = self.verify(&signed).await?;
+
+        if delta.full {
+            self.remove_missing_members(&delta.changes).await?;
+        }
+        let count = delta.changes.len();
+        for change in delta.changes {
+            self.apply(change).await?;
+        }
+
+        self.cursor = SyncMembers {
+            epoch: Some(delta.epoch),
+            version: delta.version,
+        };
+        Ok(count)
+    }
+
+    /// Synchronize the replica at a regular interval.
+    /// Synchronization errors are logged and the local copy is kept until the authority can be reached again
+    pub async fn start(mut self, ctx: &Context, interval: Duration) -> Result<JoinHandle<()>> {
+        let ctx = ctx
+            .new_detached(
+                Address::random_tagged("MembersReplica.ctx"),
+                DenyAll,
+                DenyAll,
+            )
+            .await?;
+        Ok(tokio::spawn(async move {
+            loop {
+                match self.sync(&ctx).await {
+                    Ok(count) => debug!("replicated {count} member changes from the authority"),
+                    Err(e) => warn!("the members of the authority could not be replicated: {e}"),
+                }
+                tokio::time::sleep(interval).await;
+            }
+        }))
+    }
+
+    /// Check that the delta has been signed by the authority for this replica and decode it
+    async fn verify(&self, signed: &SignedMembersDelta) -> Result<MembersDelta> {
+        let data = self
+            .identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(
+                Some(&self.replica),
+                &[self.authority.clone()],
+                &signed.statement,
+            )
+            .await?;
+        let attributes = data.credential_data.subject_attributes;
+        if attributes.schema != MEMBERS_REPLICATION_SCHEMA {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+
+        let digest = self
+            .identities
+            .vault()
+            .verifying_vault
+            .sha256(&signed.delta)
+            .await?;
+        let attested_digest = attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == MEMBERS_REPLICATION_DIGEST)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if attested_digest.as_deref() != Some(digest.0.as_slice()) {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+
+        Ok(minicbor::decode(&signed.delta)?)
+    }
+
+    async fn apply(&self, change: MemberChange) -> Result<()> {
+        let result = match change.entry {
+            Some(entry) => {
+                // the replicated attributes are attested by the authority
+                let entry = AttributesEntry::new(
+                    entry.attrs().clone(),
+                    entry.added(),
+                    entry.expires(),
+                    Some(self.authority.clone()),
+                );
+                self.repository
+                    .put_attributes(&change.identifier, entry)
+                    .await
+            }
+            None => self.repository.delete(&change.identifier).await,
+        };
+        if let Err(e) = result {
+            warn!(
+                "the attributes of {} could not be replicated: {e}",
+                change.identifier
+            );
+        }
+        Ok(())
+    }
+
+    /// Remove the members attested by the authority which are not part of a full delta
+    async fn remove_missing_members(&self, changes: &[MemberChange]) -> Result<()> {
+        for (identifier, entry) in self.repository.list().await? {
+            if entry.attested_by().as_ref() == Some(&self.authority)
+                && !changes.iter().any(|c| c.identifier == identifier)
+            {
+                self.repository.delete(&identifier).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_change_log() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000002")?;
+        let log = MembersChangeLog::new();
+        let epoch = Some(log.epoch());
+        let entry = AttributesEntry::new(BTreeMap::new(), 0.into(), None, None);
+
+        log.record(&alice, Some(entry.clone()));
+        log.record(&bob, Some(entry));
+        log.record(&alice, None);
+
+        let (version, changes) = log.changes_since(epoch, 0);
+        assert_eq!(version, 3);
+        let changes = changes.unwrap();
+        assert_eq!(changes.len(), 2);
+        assert!(changes
+            .iter()
+            .any(|c| c.identifier == alice && c.entry.is_none()));
+
+        let (_, changes) = log.changes_since(epoch, 2);
+        assert_eq!(changes.unwrap().len(), 1);
+
+        // a replica synchronized with another epoch gets all the members
+        let (_, changes) = log.changes_since(None, 2);
+        assert!(changes.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,599 @@
+//! Export of the metrics of a node to InfluxDB or AWS Timestream.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Debug, Formatter};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tokio_retry::strategy::{jitter, ExponentialBackoff};
+use tokio_retry::Retry;
+use url::Url;
+
+use ockam::identity::storage::MaintenanceStatistics;
+use ockam::LmdbStorage;
+use ockam_core::{LocalMessage, Result};
+use ockam_transport_tcp::{
+    OutletConnectionObserver, OutletConnectionPermit, PortalConnectionStats,
+};
+
+use crate::cli_state::cached::now_millis;
+use crate::error::ApiError;
+use crate::nodes::NodeManager;
+
+/// Maximum number of points kept while the sink can't be reached.
+/// The oldest points are dropped first
+const MAX_PENDING_POINTS: usize = 10_000;
+
+/// Maximum time to wait for the response of an InfluxDB endpoint
+const INFLUXDB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Maximum number of records accepted by a Timestream write
+const TIMESTREAM_MAX_RECORDS: usize = 100;
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_retries() -> usize {
+    3
+}
+
+/// Destination of the metrics
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MetricsSink {
+    /// Write endpoint of InfluxDB, for example
+    /// `http://localhost:8086/api/v2/write?org=my-org&bucket=ockam` for InfluxDB 2
+    /// or `http://localhost:8086/write?db=ockam` for InfluxDB 1
+    Influxdb {
+        url: Url,
+        /// Token sent in the `Authorization` header
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// Table of AWS Timestream. The credentials are taken from the environment
+    Timestream {
+        database: String,
+        table: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        region: Option<String>,
+    },
+}
+
+/// Metrics exporter configuration of a node, read from a JSON file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MetricsExporterConfig {
+    pub sink: MetricsSink,
+    /// Time between two collections of the metrics
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Maximum number of points sent in one request
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Number of retries when a batch can't be sent
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+    /// Tags added to all the points, for example the environment of the node
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl MetricsExporterConfig {
+    /// Read a configuration from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the metrics exporter configuration {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        let config: Self = serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the metrics exporter configuration {} is invalid: {e}",
+                path.display()
+            ))
+        })?;
+        if config.interval_secs == 0 || config.batch_size == 0 {
+            return Err(ApiError::core(format!(
+                "the metrics exporter configuration {} is invalid: the interval and the batch size must be positive",
+                path.display()
+            )));
+        }
+        Ok(config)
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// A measurement of the node at a given time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Point {
+    pub measurement: String,
+    pub tags: BTreeMap<String, String>,
+    pub fields: BTreeMap<String, u64>,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+}
+
+impl Point {
+    fn new(measurement: &str, timestamp_ms: u64) -> Self {
+        Self {
+            measurement: measurement.to_string(),
+            tags: BTreeMap::new(),
+            fields: BTreeMap::new(),
+            timestamp_ms,
+        }
+    }
+
+    fn with_tag(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.tags.insert(name.to_string(), value.into());
+        self
+    }
+
+    fn with_field(mut self, name: &str, value: u64) -> Self {
+        self.fields.insert(name.to_string(), value);
+        self
+    }
+
+    /// Return the InfluxDB line protocol representation of the point, with a timestamp
+    /// in nanoseconds
+    pub fn to_line_protocol(&self) -> String {
+        let mut line = escape(&self.measurement, &[',', ' ']);
+        for (name, value) in self.tags.iter() {
+            line.push(',');
+            line.push_str(&escape(name, &[',', '=', ' ']));
+            line.push('=');
+            line.push_str(&escape(value, &[',', '=', ' ']));
+        }
+        let fields = self
+            .fields
+            .iter()
+            .map(|(name, value)| format!("{}={value}u", escape(name, &[',', '=', ' '])))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("{line} {fields} {}", self.timestamp_ms as u128 * 1_000_000)
+    }
+}
+
+/// Escape the special characters of a line protocol element
+fn escape(s: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if c == '\\' || special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Traffic of the outlets of a node
+#[derive(Clone, Default)]
+pub struct PortalTraffic {
+    outlets: Arc<Mutex<BTreeMap<String, OutletTraffic>>>,
+}
+
+impl Debug for PortalTraffic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PortalTraffic")
+    }
+}
+
+#[derive(Default)]
+struct OutletTraffic {
+    opened: u64,
+    next_connection: u64,
+    open: BTreeMap<u64, PortalConnectionStats>,
+    closed_bytes_to_target: u64,
+    closed_bytes_from_target: u64,
+}
+
+impl PortalTraffic {
+    /// Return an observer counting the connections and bytes of an outlet,
+    /// then notifying another observer if there is one
+    pub(crate) fn outlet_observer(
+        &self,
+        outlet: &str,
+        inner: Option<Arc<dyn OutletConnectionObserver>>,
+    ) -> Arc<dyn OutletConnectionObserver> {
+        Arc::new(TrafficObserver {
+            traffic: self.clone(),
+            outlet: outlet.to_string(),
+            inner,
+        })
+    }
+
+    /// Return one point per outlet
+    fn points(&self, timestamp_ms: u64) -> Vec<Point> {
+        let outlets = self.outlets.lock().unwrap();
+        outlets
+            .iter()
+            .map(|(alias, traffic)| {
+                let open = traffic.open.values();
+                let bytes_to_target = traffic.closed_bytes_to_target
+                    + open.clone().map(|s| s.bytes_to_peer()).sum::<u64>();
+                let bytes_from_target = traffic.closed_bytes_from_target
+                    + open.map(|s| s.bytes_from_peer()).sum::<u64>();
+                Point::new("ockam_outlet", timestamp_ms)
+                    .with_tag("outlet", alias.clone())
+                    .with_field("connections_opened", traffic.opened)
+                    .with_field("connections_open", traffic.open.len() as u64)
+                    .with_field("bytes_to_target", bytes_to_target)
+                    .with_field("bytes_from_target", bytes_from_target)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+struct TrafficObserver {
+    traffic: PortalTraffic,
+    outlet: String,
+    inner: Option<Arc<dyn OutletConnectionObserver>>,
+}
+
+impl OutletConnectionObserver for TrafficObserver {
+    fn connection_opened(
+        &self,
+        message: &LocalMessage,
+        peer: SocketAddr,
+        stats: PortalConnectionStats,
+    ) -> OutletConnectionPermit {
+        let inner = self
+            .inner
+            .as_ref()
+            .map(|inner| inner.connection_opened(message, peer, stats.clone()));
+        let id = {
+            let mut outlets = self.traffic.outlets.lock().unwrap();
+            let traffic = outlets.entry(self.outlet.clone()).or_default();
+            let id = traffic.next_connection;
+            traffic.next_connection += 1;
+            traffic.opened += 1;
+            traffic.open.insert(id, stats);
+            id
+        };
+        Box::new(TrafficGuard {
+            traffic: self.traffic.clone(),
+            outlet: self.outlet.clone(),
+            id,
+            _inner: inner,
+        })
+    }
+}
+
+/// Add the bytes of a connection to the totals of its outlet when it is closed
+struct TrafficGuard {
+    traffic: PortalTraffic,
+    outlet: String,
+    id: u64,
+    _inner: Option<OutletConnectionPermit>,
+}
+
+impl Drop for TrafficGuard {
+    fn drop(&mut self) {
+        let mut outlets = self.traffic.outlets.lock().unwrap();
+        if let Some(traffic) = outlets.get_mut(&self.outlet) {
+            if let Some(stats) = traffic.open.remove(&self.id) {
+                traffic.closed_bytes_to_target += stats.bytes_to_peer();
+                traffic.closed_bytes_from_target += stats.bytes_from_peer();
+            }
+        }
+    }
+}
+
+impl NodeManager {
+    /// Return the current metrics of the node
+    pub(crate) async fn metrics_points(&self) -> Vec<Point> {
+        let timestamp_ms = now_millis();
+        let node = Point::new("ockam_node", timestamp_ms)
+            .with_field(
+                "secure_channels",
+                self.registry.secure_channels.list().await.len() as u64,
+            )
+            .with_field("inlets", self.registry.inlets.keys().await.len() as u64)
+            .with_field("outlets", self.registry.outlets.keys().await.len() as u64)
+            .with_field("relays", self.registry.relays.keys().await.len() as u64);
+        let statistics = LmdbStorage::statistics();
+        let maintenance = MaintenanceStatistics::current();
+        let storage = Point::new("ockam_storage", timestamp_ms)
+            .with_field("operations", statistics.operations)
+            .with_field("slow_operations", statistics.slow_operations)
+            .with_field("total_duration_us", statistics.total_duration_us)
+            .with_field("max_duration_us", statistics.max_duration_us)
+            .with_field("maintenance_runs", maintenance.runs)
+            .with_field("reclaimed_bytes", maintenance.reclaimed_bytes)
+            .with_field("free_bytes", maintenance.free_bytes);
+        let mut points = vec![node, storage];
+        points.extend(self.portal_traffic().points(timestamp_ms));
+        let node_name = self.node_name();
+        points
+            .into_iter()
+            .map(|p| p.with_tag("node", node_name.clone()))
+            .collect()
+    }
+}
+
+/// Exporter of the metrics of a node
+pub struct MetricsExporter {
+    config: MetricsExporterConfig,
+    pending: VecDeque<Point>,
+    dropped: u64,
+}
+
+impl MetricsExporter {
+    /// Start exporting the metrics of a node, until the node is dropped
+    pub fn start(node: Weak<NodeManager>, config: MetricsExporterConfig) -> JoinHandle<()> {
+        info!(
+            sink = ?config.sink,
+            interval_secs = config.interval_secs,
+            "Exporting the node metrics"
+        );
+        let exporter = Self {
+            config,
+            pending: VecDeque::new(),
+            dropped: 0,
+        };
+        tokio::spawn(exporter.run(node))
+    }
+
+    async fn run(mut self, node: Weak<NodeManager>) {
+        let sink = match Sink::create(&self.config.sink).await {
+            Ok(sink) => sink,
+            Err(e) => {
+                warn!(%e, "The metrics exporter can't be started");
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(self.config.interval());
+        loop {
+            interval.tick().await;
+            let points = match node.upgrade() {
+                Some(node) => node.metrics_points().await,
+                None => return,
+            };
+            self.push(points);
+            self.flush(&sink).await;
+        }
+    }
+
+    /// Add points to the pending points, dropping the oldest ones if there are too many
+    fn push(&mut self, points: Vec<Point>) {
+        for mut point in points {
+            for (name, value) in self.config.tags.iter() {
+                point.tags.entry(name.clone()).or_insert(value.clone());
+            }
+            self.pending.push_back(point);
+        }
+        while self.pending.len() > MAX_PENDING_POINTS {
+            self.pending.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// Send the pending points in batches. Stop at the first batch which can't be sent,
+    /// it is sent again at the next interval
+    async fn flush(&mut self, sink: &Sink) {
+        while !self.pending.is_empty() {
+            let size = self.config.batch_size.min(self.pending.len());
+            let batch = self.pending.range(..size).cloned().collect::<Vec<_>>();
+            let retry_strategy = ExponentialBackoff::from_millis(500)
+                .map(jitter)
+                .take(self.config.retries);
+            let batch = &batch;
+            match Retry::spawn(retry_strategy, || sink.send(batch)).await {
+                Ok(()) => {
+                    debug!(points = size, "Exported a batch of metrics");
+                    self.pending.drain(..size);
+                }
+                Err(e) => {
+                    warn!(
+                        %e,
+                        pending = self.pending.len(),
+                        dropped = self.dropped,
+                        "The metrics can't be exported"
+                    );
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Client of a metrics sink
+enum Sink {
+    Influxdb {
+        client: reqwest::Client,
+        url: Url,
+        token: Option<String>,
+    },
+    Timestream {
+        client: aws_sdk_timestreamwrite::Client,
+        database: String,
+        table: String,
+    },
+}
+
+impl Sink {
+    async fn create(config: &MetricsSink) -> Result<Self> {
+        match config {
+            MetricsSink::Influxdb { url, token } => {
+                let client = reqwest::ClientBuilder::new()
+                    .timeout(INFLUXDB_TIMEOUT)
+                    .build()
+                    .map_err(|e| ApiError::core(e.to_string()))?;
+                Ok(Sink::Influxdb {
+                    client,
+                    url: url.clone(),
+                    token: token.clone(),
+                })
+            }
+            MetricsSink::Timestream {
+                database,
+                table,
+                region,
+            } => {
+                let mut loader = aws_config::from_env();
+                if let Some(region) = region {
+                    loader =
+                        loader.region(aws_sdk_timestreamwrite::config::Region::new(region.clone()));
+                }
+                let sdk_config = loader.load().await;
+                // Timestream requires the discovery of its endpoint, which must be refreshed
+                let (client, reload) = aws_sdk_timestreamwrite::Client::new(&sdk_config)
+                    .with_endpoint_discovery_enabled()
+                    .await
+                    .map_err(|e| ApiError::core(e.to_string()))?;
+                tokio::spawn(reload.reload_task());
+                Ok(Sink::Timestream {
+                    client,
+                    database: database.clone(),
+                    table: table.clone(),
+                })
+            }
+        }
+    }
+
+    async fn send(&self, points: &[Point]) -> Result<()> {
+        match self {
+            Sink::Influxdb { client, url, token } => {
+                let body = points
+                    .iter()
+                    .map(|p| p.to_line_protocol())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let mut request = client
+                    .post(url.clone())
+                    .header("content-type", "text/plain; charset=utf-8")
+                    .body(body);
+                if let Some(token) = token {
+                    request = request.header("authorization", format!("Token {token}"));
+                }
+                request
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .map_err(|e| ApiError::core(e.to_string()))?;
+                Ok(())
+            }
+            Sink::Timestream {
+                client,
+                database,
+                table,
+            } => {
+                for chunk in points.chunks(TIMESTREAM_MAX_RECORDS) {
+                    client
+                        .write_records()
+                        .database_name(database)
+                        .table_name(table)
+                        .set_records(Some(timestream_records(chunk)))
+                        .send()
+                        .await
+                        .map_err(|e| ApiError::core(e.to_string()))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Convert points to Timestream records, one record with multiple measures per point
+fn timestream_records(points: &[Point]) -> Vec<aws_sdk_timestreamwrite::types::Record> {
+    use aws_sdk_timestreamwrite::types::{
+        Dimension, MeasureValue, MeasureValueType, Record, TimeUnit,
+    };
+    points
+        .iter()
+        .map(|point| {
+            let dimensions = point
+                .tags
+                .iter()
+                .map(|(name, value)| Dimension::builder().name(name).value(value).build())
+                .collect();
+            let measures = point
+                .fields
+                .iter()
+                .map(|(name, value)| {
+                    MeasureValue::builder()
+                        .name(name)
+                        .value(value.to_string())
+                        .r#type(MeasureValueType::Bigint)
+                        .build()
+                })
+                .collect();
+            Record::builder()
+                .set_dimensions(Some(dimensions))
+                .measure_name(&point.measurement)
+                .measure_value_type(MeasureValueType::Multi)
+                .set_measure_values(Some(measures))
+                .time(point.timestamp_ms.to_string())
+                .time_unit(TimeUnit::Milliseconds)
+                .build()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_protocol() {
+        let point = Point::new("ockam_outlet", 1_700_000_000_000)
+            .with_tag("node", "n1")
+            .with_tag("outlet", "my db,eu=1")
+            .with_field("bytes_to_target", 42)
+            .with_field("connections_open", 2);
+        assert_eq!(
+            point.to_line_protocol(),
+            r"ockam_outlet,node=n1,outlet=my\ db\,eu\=1 bytes_to_target=42u,connections_open=2u 1700000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_read_config() {
+        let config: MetricsExporterConfig = serde_json::from_str(
+            r#"{"sink": {"type": "timestream", "database": "ockam", "table": "metrics"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.interval_secs, 60);
+        assert_eq!(config.batch_size, 100);
+        assert_eq!(
+            config.sink,
+            MetricsSink::Timestream {
+                database: "ockam".into(),
+                table: "metrics".into(),
+                region: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_pending_points_are_bounded() {
+        let config: MetricsExporterConfig = serde_json::from_str(
+            r#"{"sink": {"type": "influxdb", "url": "http://localhost:8086/write?db=ockam"}, "tags": {"env": "prod"}}"#,
+        )
+        .unwrap();
+        let mut exporter = MetricsExporter {
+            config,
+            pending: VecDeque::new(),
+            dropped: 0,
+        };
+        exporter.push(vec![Point::new("ockam_node", 1); MAX_PENDING_POINTS + 5]);
+        assert_eq!(exporter.pending.len(), MAX_PENDING_POINTS);
+        assert_eq!(exporter.dropped, 5);
+        assert_eq!(
+            exporter.pending[0].tags.get("env"),
+            Some(&"prod".to_string())
+        );
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+use ockam::identity::{Identifier, IdentitySecureChannelLocalInfo};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{async_trait, Error, LocalMessage, Result};
+use ockam_node::Context;
+use ockam_transport_tcp::{PortalDirection, PortalInterceptor, PortalInterceptorFactory};
+
+use super::packet::{protocol_error, Packet, Publish, CONNECT, MQTT_V3_1_1, PUBLISH, SUBSCRIBE};
+use super::topics::{TopicAcl, TopicKeys};
+use super::MqttPortalConfig;
+
+/// Side of the portal an interceptor is created for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Side {
+    /// The peer of the portal is an MQTT client
+    Inlet,
+    /// The peer of the portal is an MQTT broker
+    Outlet,
+}
+
+/// Create the interceptors of the connections of an MQTT inlet or outlet.
+/// See the [module documentation](super) for the details
+#[derive(Clone)]
+pub struct MqttInterceptorFactory {
+    side: Side,
+    keys: Arc<TopicKeys>,
+    acl: Arc<TopicAcl>,
+}
+
+impl MqttInterceptorFactory {
+    /// Interceptors encrypting and decrypting the payloads of the encrypted topics
+    pub fn inlet(config: &MqttPortalConfig) -> Result<Self> {
+        Self::new(Side::Inlet, config)
+    }
+
+    /// Interceptors checking the topics used by the identity of each connection
+    pub fn outlet(config: &MqttPortalConfig) -> Result<Self> {
+        Self::new(Side::Outlet, config)
+    }
+
+    fn new(side: Side, config: &MqttPortalConfig) -> Result<Self> {
+        Ok(Self {
+            side,
+            keys: Arc::new(TopicKeys::new(&config.encrypted_topics)?),
+            acl: Arc::new(TopicAcl::new(&config.acl)?),
+        })
+    }
+
+    fn interceptor(&self, identity: Option<Identifier>) -> MqttInterceptor {
+        MqttInterceptor {
+            side: self.side,
+            identity,
+            keys: self.keys.clone(),
+            acl: self.acl.clone(),
+            state: Mutex::new(ConnectionState::default()),
+        }
+    }
+}
+
+impl Debug for MqttInterceptorFactory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MqttInterceptorFactory")
+            .field("side", &self.side)
+            .finish()
+    }
+}
+
+impl PortalInterceptorFactory for MqttInterceptorFactory {
+    fn create(&self) -> Arc<dyn PortalInterceptor> {
+        Arc::new(self.interceptor(None))
+    }
+
+    fn create_for_message(&self, message: &LocalMessage) -> Arc<dyn PortalInterceptor> {
+        let identity = IdentitySecureChannelLocalInfo::find_info(message)
+            .map(|info| info.their_identity_id())
+            .ok();
+        Arc::new(self.interceptor(identity))
+    }
+}
+
+struct MqttInterceptor {
+    side: Side,
+    /// Identity of the inlet, for the connections of an outlet
+    identity: Option<Identifier>,
+    keys: Arc<TopicKeys>,
+    acl: Arc<TopicAcl>,
+    state: Mutex<ConnectionState>,
+}
+
+struct ConnectionState {
+    /// Protocol version sent by the client in its `CONNECT` packet
+    version: u8,
+    to_broker: Flow,
+    to_client: Flow,
+}
+
+impl Default for ConnectionState {
+    fn default() -> Self {
+        Self {
+            version: MQTT_V3_1_1,
+            to_broker: Flow::default(),
+            to_client: Flow::default(),
+        }
+    }
+}
+
+/// Packets sent in one direction
+#[derive(Default)]
+struct Flow {
+    /// Data received before a packet is complete
+    buffer: Vec<u8>,
+    /// Topics of the MQTT 5 topic aliases
+    aliases: HashMap<u16, String>,
+}
+
+impl MqttInterceptor {
+    /// Return the packets to forward once they are complete
+    fn process(&self, direction: PortalDirection, chunk: Vec<u8>) -> Result<Vec<u8>> {
+        let to_broker = matches!(
+            (self.side, direction),
+            (Side::Inlet, PortalDirection::FromPeer) | (Side::Outlet, PortalDirection::ToPeer)
+        );
+        // nothing is checked on the packets sent by the broker to an outlet
+        if self.side == Side::Outlet && !to_broker {
+            return Ok(chunk);
+        }
+
+        let mut guard = self.state.lock().unwrap();
+        let ConnectionState {
+            version,
+            to_broker: broker_flow,
+            to_client: client_flow,
+        } = &mut *guard;
+        let flow = if to_broker { broker_flow } else { client_flow };
+        flow.buffer.extend(chunk);
+
+        let mut output = vec![];
+        while let Some(packet) = Packet::take(&mut flow.buffer)? {
+            match packet.packet_type() {
+                CONNECT if to_broker => {
+                    *version = packet.protocol_version()?;
+                    output.extend(packet.encode());
+                }
+                PUBLISH => {
+                    let publish = Publish::decode(&packet, *version)?;
+                    if let Some(publish) = self.publish(to_broker, &mut flow.aliases, publish)? {
+                        output.extend(publish.encode().encode());
+                    }
+                }
+                SUBSCRIBE if to_broker && self.side == Side::Outlet => {
+                    for filter in packet.subscribe_filters(*version)? {
+                        if !self.acl.can_subscribe(self.identity.as_ref(), &filter) {
+                            return Err(self.denied("subscribe to", &filter));
+                        }
+                    }
+                    output.extend(packet.encode());
+                }
+                _ => output.extend(packet.encode()),
+            }
+        }
+        Ok(output)
+    }
+
+    /// Return the packet to forward in place of a `PUBLISH` packet, if any
+    fn publish(
+        &self,
+        to_broker: bool,
+        aliases: &mut HashMap<u16, String>,
+        mut publish: Publish,
+    ) -> Result<Option<Publish>> {
+        let topic = match publish.topic_alias()? {
+            Some(alias) if publish.topic.is_empty() => aliases
+                .get(&alias)
+                .cloned()
+                .ok_or_else(|| protocol_error("unknown topic alias"))?,
+            Some(alias) => {
+                aliases.insert(alias, publish.topic.clone());
+                publish.topic.clone()
+            }
+            None => publish.topic.clone(),
+        };
+
+        match (self.side, to_broker) {
+            (Side::Outlet, _) => {
+                if !self.acl.can_publish(self.identity.as_ref(), &topic) {
+                    return Err(self.denied("publish to", &topic));
+                }
+            }
+            (Side::Inlet, true) => {
+                if let Some(payload) = self.keys.encrypt(&topic, &publish.payload)? {
+                    publish.payload = payload;
+                    publish.set_binary_payload()?;
+                }
+            }
+            (Side::Inlet, false) => match self.keys.decrypt(&topic, &publish.payload) {
+                Ok(Some(payload)) => publish.payload = payload,
+                Ok(None) => (),
+                Err(e) => {
+                    warn!(%topic, %e, "Dropping an MQTT message which can't be decrypted");
+                    return Ok(None);
+                }
+            },
+        }
+        Ok(Some(publish))
+    }
+
+    fn denied(&self, action: &str, topic: &str) -> Error {
+        let identity = self
+            .identity
+            .as_ref()
+            .map_or("an unknown identity".to_string(), |i| i.to_string());
+        warn!(%identity, %topic, "Closing an MQTT connection not allowed to {action} a topic");
+        Error::new(
+            Origin::Api,
+            Kind::Invalid,
+            format!("{identity} is not allowed to {action} {topic}"),
+        )
+    }
+}
+
+#[async_trait]
+impl PortalInterceptor for MqttInterceptor {
+    async fn intercept(
+        &self,
+        _context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self.process(direction, chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mqtt::{EncryptedTopic, TopicAclRule};
+    use PortalDirection::*;
+
+    fn publish(topic: &str, payload: &[u8]) -> Vec<u8> {
+        Publish {
+            flags: 0,
+            topic: topic.to_string(),
+            packet_id: None,
+            properties: None,
+            payload: payload.to_vec(),
+        }
+        .encode()
+        .encode()
+    }
+
+    fn payload(data: &[u8]) -> Result<Vec<u8>> {
+        let mut data = data.to_vec();
+        let packet = Packet::take(&mut data)?.unwrap();
+        Ok(Publish::decode(&packet, MQTT_V3_1_1)?.payload)
+    }
+
+    #[test]
+    fn test_end_to_end_encryption() -> Result<()> {
+        let config = MqttPortalConfig {
+            encrypted_topics: vec![EncryptedTopic {
+                filter: "sensors/#".to_string(),
+                key: hex::encode([7u8; 32]),
+            }],
+            acl: vec![],
+        };
+        let device = MqttInterceptorFactory::inlet(&config)?.interceptor(None);
+        let dashboard = MqttInterceptorFactory::inlet(&config)?.interceptor(None);
+
+        // a packet split in two chunks is sent once it is complete
+        let message = publish("sensors/1", b"21.5");
+        assert_eq!(
+            device.process(FromPeer, message[..4].to_vec())?,
+            Vec::<u8>::new()
+        );
+        let sent = device.process(FromPeer, message[4..].to_vec())?;
+        assert_ne!(payload(&sent)?, b"21.5");
+        assert_eq!(payload(&dashboard.process(ToPeer, sent)?)?, b"21.5");
+
+        // the other topics are not encrypted, and the clear payloads of encrypted topics are dropped
+        let message = publish("announcements", b"hello");
+        assert_eq!(device.process(FromPeer, message.clone())?, message);
+        assert_eq!(
+            dashboard.process(ToPeer, publish("sensors/1", b"forged"))?,
+            Vec::<u8>::new()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_topic_acl() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let config = MqttPortalConfig {
+            encrypted_topics: vec![],
+            acl: vec![TopicAclRule {
+                identity: identity.to_string(),
+                publish: vec!["sensors/+".to_string()],
+                subscribe: vec!["commands/#".to_string()],
+            }],
+        };
+        let factory = MqttInterceptorFactory::outlet(&config)?;
+        let outlet = factory.interceptor(Some(identity));
+
+        let message = publish("sensors/1", b"21.5");
+        assert_eq!(outlet.process(ToPeer, message.clone())?, message);
+        assert!(outlet
+            .process(ToPeer, publish("commands/1", b"on"))
+            .is_err());
+
+        let unknown = factory.interceptor(None);
+        assert!(unknown
+            .process(ToPeer, publish("sensors/1", b"21.5"))
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! MQTT portals: the inlets encrypt the payloads of the configured topics, and the outlets check
+//! the published and subscribed topics against an access control list.
+
+mod interceptor;
+mod packet;
+mod topics;
+
+pub use interceptor::MqttInterceptorFactory;
+pub use topics::{filter_covers, topic_matches, validate_filter};
+
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam_core::Result;
+
+use crate::error::ApiError;
+
+/// Identity of the access control rules applying to all the identities
+pub const ANY_IDENTITY: &str = "*";
+
+/// Configuration of the interceptors of an MQTT inlet or outlet
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct MqttPortalConfig {
+    /// Topics whose payloads are encrypted by the inlets
+    #[serde(default)]
+    #[n(1)] pub encrypted_topics: Vec<EncryptedTopic>,
+    /// Topics which can be used by each identity, checked by the outlets
+    #[serde(default)]
+    #[n(2)] pub acl: Vec<TopicAclRule>,
+}
+
+impl MqttPortalConfig {
+    /// Parse and validate the YAML configuration of an MQTT portal
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let config: MqttPortalConfig = serde_yaml::from_str(yaml)
+            .map_err(|e| ApiError::core(format!("the MQTT configuration can't be parsed: {e}")))?;
+        MqttInterceptorFactory::inlet(&config)?;
+        MqttInterceptorFactory::outlet(&config)?;
+        Ok(config)
+    }
+}
+
+/// Key encrypting the payloads of the topics matching a filter
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct EncryptedTopic {
+    #[n(1)] pub filter: String,
+    /// Hex-encoded AES-256-GCM key
+    #[n(2)] pub key: String,
+}
+
+impl std::fmt::Debug for EncryptedTopic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptedTopic")
+            .field("filter", &self.filter)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Topic filters an identity can publish or subscribe to
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct TopicAclRule {
+    /// Identifier of the identity, or [`ANY_IDENTITY`]
+    #[n(1)] pub identity: String,
+    #[serde(default)]
+    #[n(2)] pub publish: Vec<String>,
+    #[serde(default)]
+    #[n(3)] pub subscribe: Vec<String>,
+}
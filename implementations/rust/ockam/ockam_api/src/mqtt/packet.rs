@@ -0,0 +1,346 @@
+//! Framing of the MQTT control packets, for the versions 3.1, 3.1.1 and 5 of the protocol.
+
+use std::ops::Range;
+
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+
+pub(crate) const CONNECT: u8 = 1;
+pub(crate) const PUBLISH: u8 = 3;
+pub(crate) const SUBSCRIBE: u8 = 8;
+
+/// Protocol version of MQTT 3.1.1, used until the `CONNECT` packet is received
+pub(crate) const MQTT_V3_1_1: u8 = 4;
+pub(crate) const MQTT_V5: u8 = 5;
+
+const PAYLOAD_FORMAT_INDICATOR: u8 = 0x01;
+const TOPIC_ALIAS: u8 = 0x23;
+
+/// A control packet: its first byte, containing the packet type and flags,
+/// and the rest of the packet after the remaining length
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Packet {
+    pub(crate) header: u8,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Packet {
+    pub(crate) fn packet_type(&self) -> u8 {
+        self.header >> 4
+    }
+
+    /// Remove the first packet of a buffer, once it is complete
+    pub(crate) fn take(buffer: &mut Vec<u8>) -> Result<Option<Packet>> {
+        let mut length = 0usize;
+        let mut position = 1;
+        loop {
+            let Some(byte) = buffer.get(position) else {
+                return Ok(None);
+            };
+            // the remaining length is encoded on 4 bytes at most
+            if position == 5 {
+                return Err(protocol_error("invalid remaining length"));
+            }
+            length |= ((byte & 0x7f) as usize) << (7 * (position - 1));
+            position += 1;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        if buffer.len() < position + length {
+            return Ok(None);
+        }
+        let header = buffer[0];
+        let body = buffer[position..position + length].to_vec();
+        buffer.drain(..position + length);
+        Ok(Some(Packet { header, body }))
+    }
+
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![self.header];
+        bytes.extend(encode_variable_integer(self.body.len()));
+        bytes.extend(&self.body);
+        bytes
+    }
+
+    /// Return the protocol version of a `CONNECT` packet
+    pub(crate) fn protocol_version(&self) -> Result<u8> {
+        let mut reader = Reader::new(&self.body);
+        reader.string()?;
+        reader.byte()
+    }
+
+    /// Return the topic filters of a `SUBSCRIBE` packet
+    pub(crate) fn subscribe_filters(&self, version: u8) -> Result<Vec<String>> {
+        let mut reader = Reader::new(&self.body);
+        // packet identifier
+        reader.bytes(2)?;
+        if version >= MQTT_V5 {
+            let length = reader.variable_integer()?;
+            reader.bytes(length)?;
+        }
+        let mut filters = vec![];
+        while !reader.is_empty() {
+            filters.push(reader.string()?.to_string());
+            // subscription options
+            reader.byte()?;
+        }
+        Ok(filters)
+    }
+}
+
+/// A `PUBLISH` packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Publish {
+    pub(crate) flags: u8,
+    /// Name of the topic, which is empty when a topic alias is used instead
+    pub(crate) topic: String,
+    pub(crate) packet_id: Option<u16>,
+    /// Encoded properties, for MQTT 5 only
+    pub(crate) properties: Option<Vec<u8>>,
+    pub(crate) payload: Vec<u8>,
+}
+
+impl Publish {
+    pub(crate) fn decode(packet: &Packet, version: u8) -> Result<Self> {
+        let flags = packet.header & 0x0f;
+        let mut reader = Reader::new(&packet.body);
+        let topic = reader.string()?.to_string();
+        let packet_id = if (flags >> 1) & 0x03 > 0 {
+            Some(reader.u16()?)
+        } else {
+            None
+        };
+        let properties = if version >= MQTT_V5 {
+            let length = reader.variable_integer()?;
+            Some(reader.bytes(length)?.to_vec())
+        } else {
+            None
+        };
+        Ok(Self {
+            flags,
+            topic,
+            packet_id,
+            properties,
+            payload: reader.rest().to_vec(),
+        })
+    }
+
+    pub(crate) fn encode(&self) -> Packet {
+        let mut body = (self.topic.len() as u16).to_be_bytes().to_vec();
+        body.extend(self.topic.as_bytes());
+        if let Some(packet_id) = self.packet_id {
+            body.extend(packet_id.to_be_bytes());
+        }
+        if let Some(properties) = &self.properties {
+            body.extend(encode_variable_integer(properties.len()));
+            body.extend(properties);
+        }
+        body.extend(&self.payload);
+        Packet {
+            header: (PUBLISH << 4) | self.flags,
+            body,
+        }
+    }
+
+    /// Return the topic alias property of an MQTT 5 packet
+    pub(crate) fn topic_alias(&self) -> Result<Option<u16>> {
+        let Some(properties) = &self.properties else {
+            return Ok(None);
+        };
+        Ok(find_property(properties, TOPIC_ALIAS)?.map(|range| {
+            u16::from_be_bytes([properties[range.start], properties[range.start + 1]])
+        }))
+    }
+
+    /// Mark the payload as unspecified bytes, for example once it is encrypted,
+    /// since brokers can reject the payloads declared as utf-8 which are not valid utf-8
+    pub(crate) fn set_binary_payload(&mut self) -> Result<()> {
+        if let Some(properties) = &mut self.properties {
+            if let Some(range) = find_property(properties, PAYLOAD_FORMAT_INDICATOR)? {
+                properties[range.start] = 0;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Return the position of the value of a property
+fn find_property(properties: &[u8], id: u8) -> Result<Option<Range<usize>>> {
+    let mut reader = Reader::new(properties);
+    while !reader.is_empty() {
+        let property = reader.variable_integer()?;
+        let start = reader.position;
+        match property {
+            0x01 | 0x17 | 0x19 | 0x24 | 0x25 | 0x28 | 0x29 | 0x2a => {
+                reader.bytes(1)?;
+            }
+            0x13 | 0x21 | 0x22 | 0x23 => {
+                reader.bytes(2)?;
+            }
+            0x02 | 0x11 | 0x18 | 0x27 => {
+                reader.bytes(4)?;
+            }
+            0x0b => {
+                reader.variable_integer()?;
+            }
+            0x03 | 0x08 | 0x09 | 0x12 | 0x15 | 0x16 | 0x1a | 0x1c | 0x1f => {
+                reader.binary()?;
+            }
+            0x26 => {
+                reader.binary()?;
+                reader.binary()?;
+            }
+            _ => return Err(protocol_error("unknown property")),
+        }
+        if property == id as usize {
+            return Ok(Some(start..reader.position));
+        }
+    }
+    Ok(None)
+}
+
+fn encode_variable_integer(mut value: usize) -> Vec<u8> {
+    let mut bytes = vec![];
+    loop {
+        let mut byte = (value % 128) as u8;
+        value /= 128;
+        if value > 0 {
+            byte |= 0x80;
+        }
+        bytes.push(byte);
+        if value == 0 {
+            return bytes;
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position + length;
+        if end > self.data.len() {
+            return Err(protocol_error("truncated packet"));
+        }
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn binary(&mut self) -> Result<&'a [u8]> {
+        let length = self.u16()? as usize;
+        self.bytes(length)
+    }
+
+    fn string(&mut self) -> Result<&'a str> {
+        std::str::from_utf8(self.binary()?).map_err(|_| protocol_error("invalid utf-8 string"))
+    }
+
+    fn variable_integer(&mut self) -> Result<usize> {
+        let mut value = 0usize;
+        for i in 0..4 {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as usize) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(protocol_error("invalid variable byte integer"))
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.data[self.position.min(self.data.len())..];
+        self.position = self.data.len();
+        rest
+    }
+}
+
+pub(crate) fn protocol_error(message: &str) -> Error {
+    Error::new(Origin::Api, Kind::Protocol, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_packets() -> Result<()> {
+        let publish = Publish {
+            flags: 0b0010,
+            topic: "sensors/1".to_string(),
+            packet_id: Some(7),
+            properties: None,
+            payload: vec![1; 200],
+        };
+        let mut buffer = publish.encode().encode();
+        // the remaining length of 213 bytes is encoded on 2 bytes
+        assert_eq!(&buffer[1..3], &[0xd5, 0x01]);
+        buffer.extend([0xc0, 0x00]);
+
+        let mut partial = buffer[..2].to_vec();
+        assert_eq!(Packet::take(&mut partial)?, None);
+        let packet = Packet::take(&mut buffer)?.unwrap();
+        assert_eq!(Publish::decode(&packet, MQTT_V3_1_1)?, publish);
+        let ping = Packet::take(&mut buffer)?.unwrap();
+        assert_eq!(ping.packet_type(), 12);
+        assert!(buffer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_publish_properties() -> Result<()> {
+        // payload format indicator: utf-8, then topic alias 3
+        let properties = vec![0x01, 0x01, 0x23, 0x00, 0x03];
+        let mut publish = Publish {
+            flags: 0,
+            topic: "".to_string(),
+            packet_id: None,
+            properties: Some(properties),
+            payload: b"hello".to_vec(),
+        };
+        let decoded = Publish::decode(&publish.encode(), MQTT_V5)?;
+        assert_eq!(decoded, publish);
+        assert_eq!(decoded.topic_alias()?, Some(3));
+
+        publish.set_binary_payload()?;
+        assert_eq!(publish.properties, Some(vec![0x01, 0x00, 0x23, 0x00, 0x03]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_subscribe_filters() -> Result<()> {
+        let mut body = vec![0x00, 0x01, 0x00];
+        for filter in ["a/+", "b/#"] {
+            body.extend((filter.len() as u16).to_be_bytes());
+            body.extend(filter.as_bytes());
+            body.push(0x01);
+        }
+        let packet = Packet {
+            header: (SUBSCRIBE << 4) | 0b0010,
+            body,
+        };
+        assert_eq!(packet.subscribe_filters(MQTT_V5)?, vec!["a/+", "b/#"]);
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+//! Topics matching, payloads encryption and access control lists of the MQTT portals.
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+
+use ockam::identity::Identifier;
+use ockam_core::Result;
+
+use super::packet::protocol_error;
+use super::{EncryptedTopic, TopicAclRule, ANY_IDENTITY};
+use crate::error::ApiError;
+
+/// Version of the format of the encrypted payloads
+const ENCRYPTED_PAYLOAD_VERSION: u8 = 1;
+
+const NONCE_LENGTH: usize = 12;
+
+/// Return true if a topic name matches a topic filter, which can contain the `+` and `#`
+/// wildcards. As specified by MQTT, the topics starting with `$` are not matched by the
+/// filters starting with a wildcard
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut topic_levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        match (filter_level, topic_levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => (),
+            (filter_level, Some(topic_level)) if filter_level == topic_level => (),
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+/// Return true if all the topics matched by the `requested` filter are matched by the
+/// `allowed` filter
+pub fn filter_covers(allowed: &str, requested: &str) -> bool {
+    let mut requested_levels = requested.split('/');
+    for allowed_level in allowed.split('/') {
+        match (allowed_level, requested_levels.next()) {
+            ("#", _) => return true,
+            (_, Some("#")) => return false,
+            ("+", Some(_)) => (),
+            (allowed_level, Some(requested_level)) if allowed_level == requested_level => (),
+            _ => return false,
+        }
+    }
+    requested_levels.next().is_none()
+}
+
+/// Check that the wildcards of a topic filter occupy entire levels, `#` being the last one
+pub fn validate_filter(filter: &str) -> Result<()> {
+    let levels: Vec<&str> = filter.split('/').collect();
+    let valid = !filter.is_empty()
+        && levels.iter().enumerate().all(|(i, level)| {
+            (*level == "#" && i == levels.len() - 1)
+                || *level == "+"
+                || !(level.contains('#') || level.contains('+'))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(ApiError::core(format!("invalid topic filter: {filter}")))
+    }
+}
+
+/// Keys encrypting the payloads of the messages published on some topics
+pub(crate) struct TopicKeys {
+    keys: Vec<(String, Aes256Gcm)>,
+}
+
+impl TopicKeys {
+    pub(crate) fn new(topics: &[EncryptedTopic]) -> Result<Self> {
+        let mut keys = vec![];
+        for topic in topics {
+            validate_filter(&topic.filter)?;
+            let key: [u8; 32] = hex::decode(&topic.key)
+                .ok()
+                .and_then(|key| key.try_into().ok())
+                .ok_or_else(|| {
+                    ApiError::core(format!(
+                        "the key of the topics {} must be 32 hex-encoded bytes",
+                        topic.filter
+                    ))
+                })?;
+            keys.push((topic.filter.clone(), Aes256Gcm::new((&key).into())));
+        }
+        Ok(Self { keys })
+    }
+
+    /// Return the key of the first filter matching a topic
+    fn key(&self, topic: &str) -> Option<&Aes256Gcm> {
+        self.keys
+            .iter()
+            .find(|(filter, _)| topic_matches(filter, topic))
+            .map(|(_, key)| key)
+    }
+
+    /// Encrypt a payload if its topic is encrypted. The topic is authenticated with the
+    /// payload, so that a payload can't be replayed on another topic using the same key
+    pub(crate) fn encrypt(&self, topic: &str, payload: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(key) = self.key(topic) else {
+            return Ok(None);
+        };
+        let nonce: [u8; NONCE_LENGTH] = rand::random();
+        let ciphertext = key
+            .encrypt(
+                GenericArray::from_slice(&nonce),
+                Payload {
+                    msg: payload,
+                    aad: topic.as_bytes(),
+                },
+            )
+            .map_err(|_| protocol_error("the payload can't be encrypted"))?;
+        let mut encrypted = vec![ENCRYPTED_PAYLOAD_VERSION];
+        encrypted.extend(nonce);
+        encrypted.extend(ciphertext);
+        Ok(Some(encrypted))
+    }
+
+    /// Decrypt a payload if its topic is encrypted
+    pub(crate) fn decrypt(&self, topic: &str, payload: &[u8]) -> Result<Option<Vec<u8>>> {
+        let Some(key) = self.key(topic) else {
+            return Ok(None);
+        };
+        if payload.len() < 1 + NONCE_LENGTH || payload[0] != ENCRYPTED_PAYLOAD_VERSION {
+            return Err(protocol_error("the payload is not encrypted"));
+        }
+        let (nonce, ciphertext) = payload[1..].split_at(NONCE_LENGTH);
+        let plaintext = key
+            .decrypt(
+                GenericArray::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: topic.as_bytes(),
+                },
+            )
+            .map_err(|_| protocol_error("the payload can't be decrypted"))?;
+        Ok(Some(plaintext))
+    }
+}
+
+/// Topics on which each identity can publish or subscribe.
+/// An identity which doesn't match any rule can't publish or subscribe to any topic
+pub(crate) struct TopicAcl {
+    rules: Vec<TopicAclRule>,
+}
+
+impl TopicAcl {
+    pub(crate) fn new(rules: &[TopicAclRule]) -> Result<Self> {
+        for rule in rules {
+            if rule.identity != ANY_IDENTITY {
+                Identifier::try_from(rule.identity.as_str())?;
+            }
+            for filter in rule.publish.iter().chain(rule.subscribe.iter()) {
+                validate_filter(filter)?;
+            }
+        }
+        Ok(Self {
+            rules: rules.to_vec(),
+        })
+    }
+
+    /// Return the rules applying to an identity. No rule applies to an unknown identity
+    fn rules(&self, identity: Option<&Identifier>) -> impl Iterator<Item = &TopicAclRule> {
+        let identity = identity.map(|i| i.to_string());
+        self.rules.iter().filter(move |rule| match &identity {
+            Some(identity) => rule.identity == ANY_IDENTITY || &rule.identity == identity,
+            None => false,
+        })
+    }
+
+    pub(crate) fn can_publish(&self, identity: Option<&Identifier>, topic: &str) -> bool {
+        self.rules(identity)
+            .any(|rule| rule.publish.iter().any(|f| topic_matches(f, topic)))
+    }
+
+    pub(crate) fn can_subscribe(&self, identity: Option<&Identifier>, filter: &str) -> bool {
+        self.rules(identity)
+            .any(|rule| rule.subscribe.iter().any(|f| filter_covers(f, filter)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topic_matches() {
+        assert!(topic_matches(
+            "sensors/+/temperature",
+            "sensors/1/temperature"
+        ));
+        assert!(!topic_matches(
+            "sensors/+/temperature",
+            "sensors/1/humidity"
+        ));
+        assert!(topic_matches("sensors/#", "sensors"));
+        assert!(topic_matches("sensors/#", "sensors/1/temperature"));
+        assert!(!topic_matches("sensors/+", "sensors/1/temperature"));
+        assert!(!topic_matches("#", "$SYS/uptime"));
+        assert!(topic_matches("$SYS/#", "$SYS/uptime"));
+    }
+
+    #[test]
+    fn test_filter_covers() {
+        assert!(filter_covers("sensors/#", "sensors/+/temperature"));
+        assert!(filter_covers(
+            "sensors/+/temperature",
+            "sensors/1/temperature"
+        ));
+        assert!(!filter_covers("sensors/+/temperature", "sensors/#"));
+        assert!(!filter_covers("sensors/1", "sensors/+"));
+        assert!(!filter_covers("sensors/+", "sensors/1/temperature"));
+        assert!(validate_filter("sensors/#/temperature").is_err());
+        assert!(validate_filter("sensors/a+").is_err());
+    }
+
+    #[test]
+    fn test_encryption() -> Result<()> {
+        let keys = TopicKeys::new(&[EncryptedTopic {
+            filter: "sensors/#".to_string(),
+            key: hex::encode([1u8; 32]),
+        }])?;
+        assert_eq!(keys.encrypt("commands/1", b"on")?, None);
+        let encrypted = keys.encrypt("sensors/1", b"21.5")?.unwrap();
+        assert_eq!(
+            keys.decrypt("sensors/1", &encrypted)?,
+            Some(b"21.5".to_vec())
+        );
+        // the payload is bound to its topic
+        assert!(keys.decrypt("sensors/2", &encrypted).is_err());
+        assert!(keys.decrypt("sensors/1", b"21.5").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_acl() -> Result<()> {
+        let device: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let other: Identifier = "I6c20e814b56579306f55c64e8747e6c1b4a53d9a".try_into()?;
+        let acl = TopicAcl::new(&[
+            TopicAclRule {
+                identity: device.to_string(),
+                publish: vec!["sensors/#".to_string()],
+                subscribe: vec![],
+            },
+            TopicAclRule {
+                identity: ANY_IDENTITY.to_string(),
+                publish: vec![],
+                subscribe: vec!["announcements".to_string()],
+            },
+        ])?;
+        assert!(acl.can_publish(Some(&device), "sensors/1"));
+        assert!(!acl.can_publish(Some(&other), "sensors/1"));
+        assert!(acl.can_subscribe(Some(&other), "announcements"));
+        assert!(!acl.can_subscribe(Some(&other), "#"));
+        assert!(!acl.can_subscribe(None, "announcements"));
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+//! Hooks running commands, or in-process [`NodeHook`]s, before a node starts, once it is started
+//! and before it stops.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use ockam_core::{async_trait, Result};
+
+use crate::error::ApiError;
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+/// Stages of the life of a node where hooks are run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeHookStage {
+    /// Before the node manager is started. The API listener of the node is already bound
+    PreStart,
+    /// Once the node manager and the services of the launch configuration are started
+    PostStart,
+    /// When the node is asked to stop, before its workers are stopped
+    PreStop,
+}
+
+impl Display for NodeHookStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            NodeHookStage::PreStart => "pre_start",
+            NodeHookStage::PostStart => "post_start",
+            NodeHookStage::PreStop => "pre_stop",
+        })
+    }
+}
+
+/// Node for which a hook is run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeHookContext {
+    pub node_name: String,
+    /// Address of the API listener of the node
+    pub node_address: String,
+}
+
+/// Hook run at a stage of the life of a node
+#[async_trait]
+pub trait NodeHook: Send + Sync + 'static {
+    /// Name of the hook, used in the logs
+    fn name(&self) -> String;
+
+    /// Run the hook. The hook is cancelled if it doesn't complete before the timeout
+    /// it is registered with
+    async fn run(&self, stage: NodeHookStage, context: &NodeHookContext) -> Result<()>;
+}
+
+/// Command run by a hook
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HookCommand {
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl HookCommand {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            args: vec![],
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> Self {
+        self.args = args;
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_secs = timeout.as_secs();
+        self
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+}
+
+#[async_trait]
+impl NodeHook for HookCommand {
+    fn name(&self) -> String {
+        self.command.clone()
+    }
+
+    async fn run(&self, stage: NodeHookStage, context: &NodeHookContext) -> Result<()> {
+        let output = Command::new(&self.command)
+            .args(&self.args)
+            .env("OCKAM_NODE_NAME", &context.node_name)
+            .env("OCKAM_NODE_ADDRESS", &context.node_address)
+            .env("OCKAM_HOOK_STAGE", stage.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // the command is killed when the hook is cancelled by its timeout
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| ApiError::core(format!("the command can't be run: {e}")))?;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            info!(hook = %self.command, %stage, "{line}");
+        }
+        for line in String::from_utf8_lossy(&output.stderr).lines() {
+            warn!(hook = %self.command, %stage, "{line}");
+        }
+        if !output.status.success() {
+            return Err(ApiError::core(format!(
+                "the command failed with {}",
+                output.status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Commands run at each stage, read from a JSON file. The commands get the name of the node, the
+/// address of its API listener and the stage in the `OCKAM_NODE_NAME`, `OCKAM_NODE_ADDRESS` and
+/// `OCKAM_HOOK_STAGE` environment variables
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeHooksConfig {
+    #[serde(default)]
+    pub pre_start: Vec<HookCommand>,
+    #[serde(default)]
+    pub post_start: Vec<HookCommand>,
+    #[serde(default)]
+    pub pre_stop: Vec<HookCommand>,
+}
+
+impl NodeHooksConfig {
+    /// Read a configuration from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the hooks configuration {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the hooks configuration {} is invalid: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Create the hooks described by this configuration
+    pub fn hooks(&self) -> NodeHooks {
+        let mut hooks = NodeHooks::default();
+        for (stage, commands) in [
+            (NodeHookStage::PreStart, &self.pre_start),
+            (NodeHookStage::PostStart, &self.post_start),
+            (NodeHookStage::PreStop, &self.pre_stop),
+        ] {
+            for command in commands {
+                let timeout = command.timeout();
+                hooks = hooks.with_hook(stage, Arc::new(command.clone()), timeout);
+            }
+        }
+        hooks
+    }
+}
+
+/// Hook registered for a stage, with its timeout
+#[derive(Clone)]
+struct RegisteredHook {
+    hook: Arc<dyn NodeHook>,
+    timeout: Duration,
+}
+
+/// Hooks of a node, run in their registration order at each stage
+#[derive(Clone, Default)]
+pub struct NodeHooks {
+    hooks: BTreeMap<NodeHookStage, Vec<RegisteredHook>>,
+}
+
+impl NodeHooks {
+    /// Run a hook at a stage, and cancel it if it doesn't complete before `timeout`
+    pub fn with_hook(
+        mut self,
+        stage: NodeHookStage,
+        hook: Arc<dyn NodeHook>,
+        timeout: Duration,
+    ) -> Self {
+        self.hooks
+            .entry(stage)
+            .or_default()
+            .push(RegisteredHook { hook, timeout });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.values().all(|hooks| hooks.is_empty())
+    }
+
+    /// Run the hooks of a stage.
+    ///
+    /// The pre-start hooks are stopped at the first failure, which is returned.
+    /// The failures of the other hooks are logged and the next hooks are still run
+    pub async fn run(&self, stage: NodeHookStage, context: &NodeHookContext) -> Result<()> {
+        for registered in self.hooks.get(&stage).into_iter().flatten() {
+            let name = registered.hook.name();
+            info!(hook = %name, %stage, node = %context.node_name, "running a node hook");
+            let result =
+                match tokio::time::timeout(registered.timeout, registered.hook.run(stage, context))
+                    .await
+                {
+                    Ok(result) => result,
+                    Err(_) => Err(ApiError::core(format!(
+                        "the hook didn't complete in {}s",
+                        registered.timeout.as_secs()
+                    ))),
+                };
+            if let Err(e) = result {
+                let e = ApiError::core(format!("the {stage} hook {name} failed: {e}"));
+                if stage == NodeHookStage::PreStart {
+                    return Err(e);
+                }
+                warn!(%e, "a node hook failed");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct RecordingHook {
+        name: String,
+        fail: bool,
+        runs: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl NodeHook for RecordingHook {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        async fn run(&self, stage: NodeHookStage, context: &NodeHookContext) -> Result<()> {
+            self.runs
+                .lock()
+                .unwrap()
+                .push(format!("{stage}:{}:{}", self.name, context.node_name));
+            if self.fail {
+                return Err(ApiError::core("failed"));
+            }
+            Ok(())
+        }
+    }
+
+    fn context() -> NodeHookContext {
+        NodeHookContext {
+            node_name: "n1".to_string(),
+            node_address: "127.0.0.1:4000".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_hooks() {
+        let runs = Arc::new(Mutex::new(vec![]));
+        let hook = |name: &str, fail: bool| -> Arc<dyn NodeHook> {
+            Arc::new(RecordingHook {
+                name: name.to_string(),
+                fail,
+                runs: runs.clone(),
+            })
+        };
+        let timeout = Duration::from_secs(1);
+        let hooks = NodeHooks::default()
+            .with_hook(NodeHookStage::PreStart, hook("check", true), timeout)
+            .with_hook(NodeHookStage::PreStart, hook("never", false), timeout)
+            .with_hook(NodeHookStage::PreStop, hook("failing", true), timeout)
+            .with_hook(NodeHookStage::PreStop, hook("deregister", false), timeout);
+
+        // a failing pre-start hook stops the startup
+        assert!(hooks
+            .run(NodeHookStage::PreStart, &context())
+            .await
+            .is_err());
+        // the failures of the other hooks are only logged
+        assert!(hooks
+            .run(NodeHookStage::PostStart, &context())
+            .await
+            .is_ok());
+        assert!(hooks.run(NodeHookStage::PreStop, &context()).await.is_ok());
+
+        assert_eq!(
+            *runs.lock().unwrap(),
+            vec![
+                "pre_start:check:n1",
+                "pre_stop:failing:n1",
+                "pre_stop:deregister:n1"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_command_hooks() {
+        let config: NodeHooksConfig = serde_json::from_str(
+            r#"{
+              "pre_start": [{ "command": "sh", "args": ["-c", "test \"$OCKAM_NODE_NAME:$OCKAM_HOOK_STAGE\" = n1:pre_start"] }],
+              "post_start": [{ "command": "sleep", "args": ["10"], "timeout_secs": 1 }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.pre_start[0].timeout_secs, 30);
+        assert_eq!(config.post_start[0].timeout_secs, 1);
+        let hooks = config.hooks();
+        assert!(hooks.run(NodeHookStage::PreStart, &context()).await.is_ok());
+
+        // a command which doesn't complete in time fails
+        let hooks = NodeHooks::default().with_hook(
+            NodeHookStage::PreStart,
+            Arc::new(HookCommand::new("sleep").with_args(vec!["10".to_string()])),
+            Duration::from_millis(100),
+        );
+        assert!(hooks
+            .run(NodeHookStage::PreStart, &context())
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! Recording and replay of the requests sent to a node manager.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use minicbor::decode::{Token, Tokenizer};
+use minicbor::encode::{self, Encoder};
+use minicbor::{Decoder, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam_core::api::{Method, Request, RequestHeader, Response, ResponseHeader, Status};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::Result;
+use ockam_multiaddr::MultiAddr;
+use ockam_node::api::Client;
+use ockam_node::Context;
+
+/// A request handled by a node manager, with its response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub method: String,
+    pub path: String,
+    /// Status of the response, for example `200 Ok`, if it could be decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Hex-encoded request, with its header
+    pub request: String,
+    /// Hex-encoded response, with its header
+    pub response: String,
+}
+
+impl RecordedRequest {
+    fn new(req: &RequestHeader, request: &[u8], response: &[u8]) -> Self {
+        let status = Decoder::new(response)
+            .decode::<ResponseHeader>()
+            .ok()
+            .and_then(|h| h.status())
+            .map(|s| s.to_string());
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            method: req.method().map(|m| m.to_string()).unwrap_or_default(),
+            path: req.path().to_string(),
+            status,
+            request: hex::encode(request),
+            response: hex::encode(response),
+        }
+    }
+
+    /// Return true if the request changes the state of the node and was successful,
+    /// so that it must be replayed to get the same state
+    pub fn is_successful_change(&self) -> bool {
+        self.method != Method::Get.to_string() && self.status == Some(Status::Ok.to_string())
+    }
+
+    /// Send the request again to a node manager, after remapping its addresses.
+    /// Return the status of the response
+    pub async fn replay(
+        &self,
+        ctx: &Context,
+        client: &Client,
+        mapping: &AddressMapping,
+    ) -> Result<Option<Status>> {
+        let recorded = hex::decode(&self.request)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Serialization, e.to_string()))?;
+        let remapped = mapping.remap_cbor(&recorded)?;
+
+        // the request is sent with a new identifier
+        let mut dec = Decoder::new(&remapped);
+        let header: RequestHeader = dec.decode()?;
+        let method = header.method().ok_or_else(|| {
+            ockam_core::Error::new(Origin::Api, Kind::Invalid, "the request has no method")
+        })?;
+        let request = Request::get(header.path()).method(method);
+        let response = if header.has_body() {
+            let body = RawCbor(remapped[dec.position()..].to_vec());
+            client.request(ctx, request.body(body)).await?
+        } else {
+            client.request(ctx, request).await?
+        };
+        let (response, _) = Response::parse_response_header(response.as_slice())?;
+        Ok(response.status())
+    }
+}
+
+/// CBOR data which is already encoded
+struct RawCbor(Vec<u8>);
+
+impl<C> Encode<C> for RawCbor {
+    fn encode<W: encode::Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _: &mut C,
+    ) -> Result<(), encode::Error<W::Error>> {
+        e.writer_mut()
+            .write_all(&self.0)
+            .map_err(encode::Error::write)
+    }
+}
+
+/// Appends the requests handled by a node manager to a file
+#[derive(Debug)]
+pub struct ApiRecorder {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl ApiRecorder {
+    /// Record the requests at the end of a file, which is created if it doesn't exist
+    pub fn create(path: &Path) -> Result<Self> {
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options
+            .open(path)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Io, e))?;
+        info!("Recording the node manager requests to {}", path.display());
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append a request and its response to the recording
+    pub fn record(&self, req: &RequestHeader, request: &[u8], response: &[u8]) -> Result<()> {
+        let recorded = RecordedRequest::new(req, &without_api_token(request)?, response);
+        let mut line = serde_json::to_vec(&recorded)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Serialization, e))?;
+        line.push(b'\n');
+        self.file
+            .lock()
+            .unwrap()
+            .write_all(&line)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Io, e))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Return a request without the API token of its header, so that the token is neither
+/// recorded nor kept for a warm start
+pub(crate) fn without_api_token(request: &[u8]) -> Result<Vec<u8>> {
+    let mut dec = Decoder::new(request);
+    let mut header: RequestHeader = dec.decode()?;
+    if header.api_token().is_none() {
+        return Ok(request.to_vec());
+    }
+    header.clear_api_token();
+    let mut stripped = minicbor::to_vec(&header)?;
+    stripped.extend_from_slice(&request[dec.position()..]);
+    Ok(stripped)
+}
+
+/// Read the requests of a recording, in the order they were handled
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedRequest>> {
+    let file = File::open(path).map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Io, e))?;
+    let mut requests = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Io, e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        requests.push(
+            serde_json::from_str(&line)
+                .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Serialization, e))?,
+        );
+    }
+    Ok(requests)
+}
+
+/// Names and addresses to replace in the replayed requests.
+///
+/// A text is replaced when it is equal to a mapped value. A path or a multiaddr, like
+/// `/node/n1/service/api`, has each of its segments replaced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressMapping {
+    replacements: BTreeMap<String, String>,
+}
+
+impl AddressMapping {
+    pub fn new(replacements: impl IntoIterator<Item = (String, String)>) -> Self {
+        Self {
+            replacements: replacements.into_iter().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replacements.is_empty()
+    }
+
+    /// Return a text with its mapped values replaced
+    pub fn apply(&self, text: &str) -> String {
+        if let Some(replacement) = self.replacements.get(text) {
+            return replacement.clone();
+        }
+        if text.starts_with('/') {
+            return text
+                .split('/')
+                .map(|segment| {
+                    self.replacements
+                        .get(segment)
+                        .map(|s| s.as_str())
+                        .unwrap_or(segment)
+                })
+                .collect::<Vec<_>>()
+                .join("/");
+        }
+        text.to_string()
+    }
+
+    /// Replace the mapped values in the text strings and the multiaddrs of CBOR data
+    pub fn remap_cbor(&self, bytes: &[u8]) -> Result<Vec<u8>> {
+        if self.is_empty() {
+            return Ok(bytes.to_vec());
+        }
+        let mut encoder = Encoder::new(Vec::new());
+        for token in Tokenizer::new(bytes) {
+            match token? {
+                Token::String(text) => {
+                    encoder.str(&self.apply(text))?;
+                }
+                Token::Bytes(bytes) => match self.remap_multiaddr(bytes) {
+                    Some(multiaddr) => {
+                        encoder.bytes(multiaddr.as_ref())?;
+                    }
+                    None => {
+                        encoder.bytes(bytes)?;
+                    }
+                },
+                token => encode_token(&mut encoder, token)?,
+            }
+        }
+        Ok(encoder.into_writer())
+    }
+
+    fn remap_multiaddr(&self, bytes: &[u8]) -> Option<MultiAddr> {
+        let multiaddr = MultiAddr::try_from(bytes).ok()?;
+        let remapped = self.apply(&multiaddr.to_string());
+        MultiAddr::from_str(&remapped).ok()
+    }
+}
+
+/// Encode a CBOR token which is not remapped as it is
+fn encode_token(encoder: &mut Encoder<Vec<u8>>, token: Token) -> Result<()> {
+    match token {
+        Token::Bool(b) => encoder.bool(b)?,
+        Token::U8(n) => encoder.u8(n)?,
+        Token::U16(n) => encoder.u16(n)?,
+        Token::U32(n) => encoder.u32(n)?,
+        Token::U64(n) => encoder.u64(n)?,
+        Token::I8(n) => encoder.i8(n)?,
+        Token::I16(n) => encoder.i16(n)?,
+        Token::I32(n) => encoder.i32(n)?,
+        Token::I64(n) => encoder.i64(n)?,
+        Token::Int(n) => encoder.int(n)?,
+        Token::F16(n) => encoder.f16(n)?,
+        Token::F32(n) => encoder.f32(n)?,
+        Token::F64(n) => encoder.f64(n)?,
+        Token::Bytes(bytes) => encoder.bytes(bytes)?,
+        Token::String(text) => encoder.str(text)?,
+        Token::Array(len) => encoder.array(len)?,
+        Token::Map(len) => encoder.map(len)?,
+        Token::Tag(tag) => encoder.tag(tag)?,
+        Token::Simple(n) => encoder.simple(n)?,
+        Token::Break => encoder.end()?,
+        Token::Null => encoder.null()?,
+        Token::Undefined => encoder.undefined()?,
+        Token::BeginBytes => encoder.begin_bytes()?,
+        Token::BeginString => encoder.begin_str()?,
+        Token::BeginArray => encoder.begin_array()?,
+        Token::BeginMap => encoder.begin_map()?,
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::models::relay::CreateRelay;
+
+    #[test]
+    fn test_remap_request() -> Result<()> {
+        let mapping = AddressMapping::new([
+            ("n1".to_string(), "n2".to_string()),
+            ("alias1".to_string(), "alias2".to_string()),
+        ]);
+        assert_eq!(
+            mapping.apply("/node/n1/service/api"),
+            "/node/n2/service/api"
+        );
+        assert_eq!(mapping.apply("n1"), "n2");
+        assert_eq!(mapping.apply("n10"), "n10");
+
+        let request = Request::post("/node/forwarder/alias1")
+            .body(CreateRelay::new(
+                MultiAddr::from_str("/node/n1")?,
+                Some("alias1".to_string()),
+                true,
+                None,
+            ))
+            .to_vec()?;
+        let expected = Request::post("/node/forwarder/alias2")
+            .body(CreateRelay::new(
+                MultiAddr::from_str("/node/n2")?,
+                Some("alias2".to_string()),
+                true,
+                None,
+            ))
+            .to_vec()?;
+        let remapped = mapping.remap_cbor(&request)?;
+
+        // the requests only differ by their identifiers
+        let mut dec = Decoder::new(&remapped);
+        let header: RequestHeader = dec.decode()?;
+        assert_eq!(header.path(), "/node/forwarder/alias2");
+        let mut expected_dec = Decoder::new(&expected);
+        let _: RequestHeader = expected_dec.decode()?;
+        assert_eq!(
+            &remapped[dec.position()..],
+            &expected[expected_dec.position()..]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_without_api_token() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.jsonl");
+        let recorder = ApiRecorder::create(&path)?;
+
+        let request = Request::get("/node").api_token("token");
+        let response = Response::ok(request.header()).to_vec()?;
+        recorder.record(request.header(), &request.to_vec()?, &response)?;
+
+        let recorded = read_recording(&path)?;
+        let request = hex::decode(&recorded[0].request).unwrap();
+        let header: RequestHeader = Decoder::new(&request).decode()?;
+        assert_eq!(header.api_token(), None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        Ok(())
+    }
+}
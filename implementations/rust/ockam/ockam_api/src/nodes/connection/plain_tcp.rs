@@ -5,7 +5,7 @@ use std::sync::Arc;
 
 use crate::nodes::NodeManager;
 use ockam_core::{async_trait, Error, Route};
-use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Tcp};
+use ockam_multiaddr::proto::{Dns4, Dns6, DnsAddr, Ip4, Ip6, Tcp};
 use ockam_multiaddr::{Match, MultiAddr, Protocol};
 use ockam_node::Context;
 
@@ -23,7 +23,7 @@ impl Instantiator for PlainTcpInstantiator {
     fn matches(&self) -> Vec<Match> {
         vec![
             // matches any tcp address followed by a tcp protocol
-            Match::any([DnsAddr::CODE, Ip4::CODE, Ip6::CODE]),
+            Match::any([DnsAddr::CODE, Dns4::CODE, Dns6::CODE, Ip4::CODE, Ip6::CODE]),
             Tcp::CODE.into(),
         ]
     }
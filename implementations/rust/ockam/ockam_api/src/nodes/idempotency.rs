@@ -0,0 +1,312 @@
+//! Idempotency keys of the node management requests.
+
+use std::time::Duration;
+
+use minicbor::{Decode, Decoder, Encode};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::Storage;
+use ockam::identity::Identifier;
+use ockam_core::api::{Method, RequestHeader, Response, ResponseHeader, Status};
+use ockam_core::compat::sync::Arc;
+use ockam_core::Result;
+
+use crate::cli_state::cached::now;
+
+/// Default time during which the responses of the requests having an idempotency key are kept
+pub const DEFAULT_IDEMPOTENCY_KEY_RETENTION: Duration = Duration::from_secs(600);
+
+const NAMESPACE: &str = "idempotency_keys";
+
+/// Response kept for an idempotency key
+#[derive(Debug, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+struct KeptResponse {
+    /// Seconds since the Unix epoch
+    #[n(1)] created_at: u64,
+    #[n(2)] method: String,
+    #[n(3)] path: String,
+    #[cbor(n(4), with = "minicbor::bytes")] response: Vec<u8>,
+    /// Hex encoded SHA-256 of the request body
+    #[n(5)] body_hash: String,
+    /// Identifier of the remote caller, if the request was sent over a secure channel
+    #[n(6)] caller: Option<String>,
+}
+
+impl KeptResponse {
+    /// Return true if the kept response was sent to the same request, from the same caller
+    fn is_for(&self, req: &RequestHeader, body: &[u8], caller: Option<&Identifier>) -> bool {
+        self.path == req.path()
+            && Some(&self.method) == req.method().map(|m| m.to_string()).as_ref()
+            && self.body_hash == body_hash(body)
+            && self.caller == caller.map(|c| c.to_string())
+    }
+}
+
+/// Responses of the requests having an idempotency key, by key
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    storage: Arc<dyn Storage>,
+    retention: Duration,
+}
+
+impl IdempotencyKeys {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            retention: DEFAULT_IDEMPOTENCY_KEY_RETENTION,
+        }
+    }
+
+    /// Set the time during which the responses are kept
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Return the response to send again for a request having an already used key.
+    ///
+    /// The kept response is addressed to the new request. A key used for a request with another
+    /// method, path or body, or by another caller, gets a conflict response
+    pub async fn response(
+        &self,
+        req: &RequestHeader,
+        body: &[u8],
+        caller: Option<&Identifier>,
+    ) -> Result<Option<Vec<u8>>> {
+        let Some(key) = Self::key(req) else {
+            return Ok(None);
+        };
+        let Some(kept) = self.get(key).await? else {
+            return Ok(None);
+        };
+        if !kept.is_for(req, body, caller) {
+            let response = Response::error(
+                req,
+                &format!("the idempotency key {key} was already used for another request"),
+                Status::Conflict,
+            );
+            return Ok(Some(response.to_vec()?));
+        }
+        debug!(%key, path = %req.path(), "returning the response of a retried request");
+
+        let mut dec = Decoder::new(&kept.response);
+        let header: ResponseHeader = dec.decode()?;
+        let body = &kept.response[dec.position()..];
+        let header = ResponseHeader::new(
+            req.id(),
+            header.status().unwrap_or(Status::Ok),
+            header.has_body(),
+        );
+        let mut response = minicbor::to_vec(header)?;
+        response.extend_from_slice(body);
+        Ok(Some(response))
+    }
+
+    /// Keep the response of a successful request having a key
+    pub async fn keep(
+        &self,
+        req: &RequestHeader,
+        body: &[u8],
+        caller: Option<&Identifier>,
+        response: &[u8],
+    ) -> Result<()> {
+        let Some(key) = Self::key(req) else {
+            return Ok(());
+        };
+        let header: ResponseHeader = Decoder::new(response).decode()?;
+        if header.status() != Some(Status::Ok) {
+            return Ok(());
+        }
+        let kept = KeptResponse {
+            created_at: now(),
+            method: req.method().map(|m| m.to_string()).unwrap_or_default(),
+            path: req.path().to_string(),
+            response: response.to_vec(),
+            body_hash: body_hash(body),
+            caller: caller.map(|c| c.to_string()),
+        };
+        self.storage
+            .set(key, NAMESPACE.to_string(), minicbor::to_vec(kept)?)
+            .await
+    }
+
+    /// Only the requests changing the node use their keys
+    fn key(req: &RequestHeader) -> Option<&str> {
+        match req.method() {
+            Some(Method::Get) | None => None,
+            Some(_) => req.idempotency_key(),
+        }
+    }
+
+    /// Return the response kept for a key, unless it is expired.
+    /// The responses kept by a previous version, without the body hash and the caller,
+    /// are removed
+    async fn get(&self, key: &str) -> Result<Option<KeptResponse>> {
+        let Some(bytes) = self.storage.get(key, NAMESPACE).await? else {
+            return Ok(None);
+        };
+        match minicbor::decode::<KeptResponse>(&bytes) {
+            Ok(kept) if !self.is_expired(&kept) => Ok(Some(kept)),
+            _ => {
+                self.storage.del(key, NAMESPACE).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Remove the expired responses and return their number
+    pub async fn prune(&self) -> Result<usize> {
+        let mut removed = 0;
+        for key in self.storage.keys(NAMESPACE).await? {
+            if self.get(&key).await?.is_none() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Remove the expired responses when the node starts, then on an interval of the
+    /// retention time
+    pub fn start_pruning(self) -> JoinHandle<()> {
+        let period = self.retention.max(Duration::from_secs(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                match self.prune().await {
+                    Ok(removed) => debug!(removed, "Pruned the expired idempotency keys"),
+                    Err(e) => warn!(%e, "The expired idempotency keys can't be pruned"),
+                }
+            }
+        })
+    }
+
+    fn is_expired(&self, kept: &KeptResponse) -> bool {
+        kept.created_at.saturating_add(self.retention.as_secs()) < now()
+    }
+}
+
+fn body_hash(body: &[u8]) -> String {
+    hex::encode(Sha256::digest(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::models::portal::OutletStatus;
+    use ockam::identity::storage::InMemoryStorage;
+    use ockam_core::api::Request;
+    use std::str::FromStr;
+
+    #[tokio::test]
+    async fn test_retried_request() -> Result<()> {
+        let keys = IdempotencyKeys::new(InMemoryStorage::create());
+
+        let request = Request::post("/node/outlet").idempotency_key("k1");
+        assert!(keys
+            .response(request.header(), b"outlet", None)
+            .await?
+            .is_none());
+        let response = Response::ok(request.header())
+            .body(OutletStatus::new(
+                "127.0.0.1:5000".parse().unwrap(),
+                "outlet".into(),
+                "db",
+                None,
+            ))
+            .to_vec()?;
+        keys.keep(request.header(), b"outlet", None, &response)
+            .await?;
+
+        // the retried request gets the original response, addressed to the new request
+        let retried = Request::post("/node/outlet").idempotency_key("k1");
+        let kept = keys
+            .response(retried.header(), b"outlet", None)
+            .await?
+            .unwrap();
+        let mut dec = Decoder::new(&kept);
+        let header: ResponseHeader = dec.decode()?;
+        assert_eq!(header.re(), retried.header().id());
+        assert_eq!(header.status(), Some(Status::Ok));
+        let outlet: OutletStatus = dec.decode()?;
+        assert_eq!(outlet.alias, "db");
+
+        // the key can't be used for another request
+        let other = Request::post("/node/inlet").idempotency_key("k1");
+        let conflict = keys.response(other.header(), b"outlet", None).await?;
+        assert_eq!(status(conflict), Some(Status::Conflict));
+
+        // failed requests are not kept
+        let failed = Request::post("/node/inlet").idempotency_key("k2");
+        let response = Response::bad_request(failed.header(), "no").to_vec()?;
+        keys.keep(failed.header(), b"inlet", None, &response)
+            .await?;
+        assert!(keys
+            .response(failed.header(), b"inlet", None)
+            .await?
+            .is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_with_another_body_or_caller() -> Result<()> {
+        let keys = IdempotencyKeys::new(InMemoryStorage::create());
+        let alice = Identifier::from_str("Ie92f183eb4c324804ef4d62962dea94cf095a265")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000000")?;
+
+        let request = Request::delete("/node/outlet").idempotency_key("k1");
+        let response = Response::ok(request.header()).to_vec()?;
+        keys.keep(request.header(), b"db", Some(&alice), &response)
+            .await?;
+
+        let retried = Request::delete("/node/outlet").idempotency_key("k1");
+        let kept = keys.response(retried.header(), b"db", Some(&alice)).await?;
+        assert_eq!(status(kept), Some(Status::Ok));
+
+        // a request with the same key but another body, or from another caller, is rejected
+        let conflict = keys
+            .response(retried.header(), b"web", Some(&alice))
+            .await?;
+        assert_eq!(status(conflict), Some(Status::Conflict));
+        let conflict = keys.response(retried.header(), b"db", Some(&bob)).await?;
+        assert_eq!(status(conflict), Some(Status::Conflict));
+        let conflict = keys.response(retried.header(), b"db", None).await?;
+        assert_eq!(status(conflict), Some(Status::Conflict));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prune_expired_responses() -> Result<()> {
+        let storage = InMemoryStorage::create();
+        let keys = IdempotencyKeys::new(storage.clone()).with_retention(Duration::from_secs(60));
+        let request = Request::post("/node/outlet").idempotency_key("k1");
+        let response = Response::ok(request.header()).to_vec()?;
+        keys.keep(request.header(), b"", None, &response).await?;
+
+        // keeping a response doesn't scan the other keys
+        let expired = KeptResponse {
+            created_at: now() - 120,
+            method: "POST".into(),
+            path: "/node/inlet".into(),
+            response: response.clone(),
+            body_hash: body_hash(b""),
+            caller: None,
+        };
+        storage
+            .set("k2", NAMESPACE.into(), minicbor::to_vec(expired)?)
+            .await?;
+        assert_eq!(storage.keys(NAMESPACE).await?.len(), 2);
+
+        assert_eq!(keys.prune().await?, 1);
+        assert_eq!(storage.keys(NAMESPACE).await?, vec!["k1".to_string()]);
+        Ok(())
+    }
+
+    fn status(response: Option<Vec<u8>>) -> Option<Status> {
+        let header: ResponseHeader = Decoder::new(&response.unwrap()).decode().unwrap();
+        header.status()
+    }
+}
@@ -1,7 +1,11 @@
+pub mod api_recording;
 pub mod config;
 pub(crate) mod connection;
+pub mod idempotency;
 pub mod models;
 pub mod registry;
+pub mod resource_usage;
+pub mod runtime_state;
 pub mod service;
 pub use service::background_node::*;
 pub use service::in_memory_node::*;
@@ -1,6 +1,8 @@
 //! Nodemanager API types
 
 use minicbor::{Decode, Encode};
+use ockam_node::memory::NodeMemory;
+use serde::Serialize;
 
 ///////////////////-!  RESPONSE BODIES
 
@@ -30,3 +32,43 @@ impl NodeStatus {
         }
     }
 }
+
+/// Response body for the memory used by the subsystems of a node.
+///
+/// The node storage is not part of it since LMDB maps its files in memory,
+/// leaving the caching of their pages to the operating system
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct NodeMemoryUsage {
+    #[n(1)] pub subsystems: Vec<SubsystemMemoryUsage>,
+}
+
+impl NodeMemoryUsage {
+    pub fn new(memory: &NodeMemory) -> Self {
+        Self {
+            subsystems: memory
+                .trackers()
+                .into_iter()
+                .map(|(name, tracker)| SubsystemMemoryUsage {
+                    name,
+                    used: tracker.used() as u64,
+                    peak: tracker.peak() as u64,
+                    limit: tracker.limit().map(|l| l as u64),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Number of bytes used by a subsystem of a node
+#[derive(Debug, Clone, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SubsystemMemoryUsage {
+    #[n(1)] pub name: String,
+    #[n(2)] pub used: u64,
+    #[n(3)] pub peak: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[n(4)] pub limit: Option<u64>,
+}
@@ -1,8 +1,12 @@
 //! Credential request/response types
 
+use std::collections::BTreeMap;
+
 use minicbor::{Decode, Encode};
+use ockam::identity::Identifier;
 use ockam_core::compat::borrow::Cow;
 use ockam_multiaddr::MultiAddr;
+use serde::Serialize;
 
 #[derive(Clone, Debug, Decode, Encode)]
 #[rustfmt::skip]
@@ -41,3 +45,48 @@ impl<'a> PresentCredentialRequest<'a> {
         }
     }
 }
+
+/// Request to issue a bearer token for a client which can't establish a secure channel
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct IssueBearerTokenRequest {
+    #[n(1)] pub subject: Identifier,
+    /// Change history of the subject, hex encoded, if it is not known by the node yet
+    #[n(2)] pub subject_identity: Option<String>,
+    #[n(3)] pub attributes: BTreeMap<String, String>,
+    #[n(4)] pub ttl_secs: Option<u64>,
+}
+
+impl IssueBearerTokenRequest {
+    pub fn new(subject: Identifier, attributes: BTreeMap<String, String>) -> Self {
+        Self {
+            subject,
+            subject_identity: None,
+            attributes,
+            ttl_secs: None,
+        }
+    }
+
+    pub fn with_subject_identity(mut self, subject_identity: Option<String>) -> Self {
+        self.subject_identity = subject_identity;
+        self
+    }
+
+    pub fn with_ttl_secs(mut self, ttl_secs: Option<u64>) -> Self {
+        self.ttl_secs = ttl_secs;
+        self
+    }
+}
+
+/// Bearer token issued by a node
+#[derive(Clone, Debug, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct BearerTokenResponse {
+    /// Token, hex encoded
+    #[n(1)] pub token: String,
+    #[n(2)] pub subject: Identifier,
+    /// Seconds since the Unix epoch
+    #[n(3)] pub expires_at: u64,
+}
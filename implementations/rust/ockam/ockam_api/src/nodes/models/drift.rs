@@ -0,0 +1,83 @@
+use minicbor::{Decode, Encode};
+use serde::Serialize;
+use std::fmt::{self, Display};
+
+/// Kind of a resource compared with a declarative configuration
+#[derive(Copy, Clone, Debug, Decode, Encode, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[rustfmt::skip]
+#[cbor(index_only)]
+#[serde(rename_all = "snake_case")]
+pub enum DriftResourceKind {
+    #[n(0)] Inlet,
+    #[n(1)] Outlet,
+    #[n(2)] Relay,
+    #[n(3)] Policy,
+}
+
+impl Display for DriftResourceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Inlet => "inlet",
+            Self::Outlet => "outlet",
+            Self::Relay => "relay",
+            Self::Policy => "policy",
+        })
+    }
+}
+
+/// Difference between a declared resource and the live resource of a node.
+///
+/// A resource is missing when it is only declared, unexpected when it only exists on the
+/// node, and changed when both exist with different values
+#[derive(Clone, Debug, Decode, Encode, Serialize, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Drift {
+    #[n(1)] pub kind: DriftResourceKind,
+    #[n(2)] pub name: String,
+    #[n(3)] pub declared: Option<String>,
+    #[n(4)] pub live: Option<String>,
+}
+
+impl Drift {
+    pub fn is_missing(&self) -> bool {
+        self.declared.is_some() && self.live.is_none()
+    }
+
+    pub fn is_unexpected(&self) -> bool {
+        self.declared.is_none() && self.live.is_some()
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.declared.is_some() && self.live.is_some()
+    }
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, name) = (self.kind, &self.name);
+        match (&self.declared, &self.live) {
+            (Some(declared), None) => write!(f, "missing {kind} {name}: {declared}"),
+            (None, Some(live)) => write!(f, "unexpected {kind} {name}: {live}"),
+            (Some(declared), Some(live)) => {
+                write!(f, "changed {kind} {name}: {live} instead of {declared}")
+            }
+            (None, None) => write!(f, "{kind} {name}"),
+        }
+    }
+}
+
+/// Response body listing the differences between a node and its declarative configuration
+#[derive(Clone, Debug, Default, Decode, Encode, Serialize, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct DriftReport {
+    #[n(1)] pub drifts: Vec<Drift>,
+}
+
+impl DriftReport {
+    /// Return true if the node matches its configuration
+    pub fn is_empty(&self) -> bool {
+        self.drifts.is_empty()
+    }
+}
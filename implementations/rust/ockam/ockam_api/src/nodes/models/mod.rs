@@ -4,6 +4,7 @@
 /// its own
 pub mod base;
 pub mod credentials;
+pub mod drift;
 pub mod flow_controls;
 pub mod policy;
 pub mod portal;
@@ -11,4 +12,5 @@ pub mod relay;
 pub mod secure_channel;
 pub mod services;
 pub mod transport;
+pub mod trust_context;
 pub mod workers;
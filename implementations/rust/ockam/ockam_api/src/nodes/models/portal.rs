@@ -8,10 +8,17 @@ use ockam::identity::Identifier;
 use ockam::route;
 use ockam_core::{Address, Route};
 use ockam_multiaddr::MultiAddr;
+use ockam_transport_tcp::AddressFamily;
 use serde::{Deserialize, Serialize};
 
 use crate::error::ApiError;
+use crate::grpc::GrpcPortalConfig;
+use crate::mqtt::MqttPortalConfig;
+use crate::port_range::PortRange;
+use crate::portal_tap::TapFormat;
+use crate::postgres_outlet::PostgresUsers;
 use crate::route_to_multiaddr;
+use crate::service_registry::ServicePublication;
 
 /// Request body to create an inlet
 #[derive(Clone, Debug, Decode, Encode)]
@@ -38,6 +45,56 @@ pub struct CreateInlet {
     #[n(6)] pub(crate) suffix_route: Route,
     /// The maximum duration to wait for an outlet to be available
     #[n(7)] pub(crate) wait_for_outlet_duration: Option<Duration>,
+    /// Name of a service published in the service registry of the project of `outlet_addr`.
+    /// The inlet then connects to one of the outlets publishing that service
+    #[n(8)] pub(crate) service: Option<String>,
+    /// If set, the inlet accepts the connections redirected by the firewall and sends each
+    /// of them to an outlet depending on its original destination. `outlet_addr` is not used
+    #[n(9)] pub(crate) transparent: Option<TransparentInlet>,
+    /// Name resolving to the address of the inlet, if the node has a DNS responder
+    #[n(10)] pub(crate) dns_name: Option<String>,
+    /// Range of the ports the inlet can listen on. If the port of `listen_addr` is 0, the inlet
+    /// listens on a free port of the range, returned in the bind address of the inlet status
+    #[n(11)] pub(crate) port_range: Option<(u16, u16)>,
+    /// Encrypt the payloads of the MQTT topics sent through the inlet
+    #[n(12)] pub(crate) mqtt: Option<MqttPortalConfig>,
+}
+
+/// Configuration of a transparent inlet
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct TransparentInlet {
+    /// How the connections are redirected: `redirect` or `tproxy`
+    #[n(1)] pub(crate) mode: String,
+    #[n(2)] pub(crate) mappings: Vec<TransparentInletMapping>,
+}
+
+/// Outlet receiving the connections to some original destinations
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct TransparentInletMapping {
+    /// IP address or subnet, with an optional port, for example `10.0.0.0/24:5432`
+    #[n(1)] pub(crate) destination: String,
+    #[n(2)] pub(crate) outlet_addr: MultiAddr,
+}
+
+impl TransparentInletMapping {
+    pub fn new(destination: impl Into<String>, outlet_addr: MultiAddr) -> Self {
+        Self {
+            destination: destination.into(),
+            outlet_addr,
+        }
+    }
+
+    pub fn destination(&self) -> &str {
+        &self.destination
+    }
+
+    pub fn outlet_addr(&self) -> &MultiAddr {
+        &self.outlet_addr
+    }
 }
 
 impl CreateInlet {
@@ -55,6 +112,11 @@ impl CreateInlet {
             prefix_route,
             suffix_route,
             wait_for_outlet_duration: None,
+            service: None,
+            transparent: None,
+            dns_name: None,
+            port_range: None,
+            mqtt: None,
         }
     }
 
@@ -73,6 +135,11 @@ impl CreateInlet {
             prefix_route,
             suffix_route,
             wait_for_outlet_duration: None,
+            service: None,
+            transparent: None,
+            dns_name: None,
+            port_range: None,
+            mqtt: None,
         }
     }
 
@@ -80,6 +147,52 @@ impl CreateInlet {
         self.alias = Some(a.into())
     }
 
+    /// Connect to one of the outlets publishing a service in the project registry
+    pub fn via_service(
+        listen: String,
+        project: MultiAddr,
+        service: impl Into<String>,
+        prefix_route: Route,
+        suffix_route: Route,
+    ) -> Self {
+        Self {
+            service: Some(service.into()),
+            ..Self::via_project(listen, project, prefix_route, suffix_route)
+        }
+    }
+
+    /// Accept the connections redirected by the firewall, and send them to the outlets
+    /// configured for their original destinations
+    pub fn transparent(
+        listen: String,
+        mode: impl Into<String>,
+        mappings: Vec<TransparentInletMapping>,
+        auth: Option<Identifier>,
+    ) -> Self {
+        Self {
+            transparent: Some(TransparentInlet {
+                mode: mode.into(),
+                mappings,
+            }),
+            ..Self::to_node(listen, MultiAddr::default(), route![], route![], auth)
+        }
+    }
+
+    /// Resolve a name to the address of the inlet, with the DNS responder of the node
+    pub fn set_dns_name(&mut self, name: impl Into<String>) {
+        self.dns_name = Some(name.into())
+    }
+
+    /// Restrict the ports the inlet can listen on
+    pub fn set_port_range(&mut self, port_range: PortRange) {
+        self.port_range = Some(port_range.into())
+    }
+
+    /// Encrypt and decrypt the payloads of the MQTT topics of a configuration
+    pub fn set_mqtt(&mut self, config: MqttPortalConfig) {
+        self.mqtt = Some(config)
+    }
+
     pub fn set_wait_ms(&mut self, ms: u64) {
         self.wait_for_outlet_duration = Some(Duration::from_millis(ms))
     }
@@ -111,6 +224,18 @@ impl CreateInlet {
     pub fn wait_for_outlet_duration(&self) -> Option<Duration> {
         self.wait_for_outlet_duration
     }
+
+    pub fn service(&self) -> Option<&str> {
+        self.service.as_deref()
+    }
+
+    pub fn transparent_inlet(&self) -> Option<&TransparentInlet> {
+        self.transparent.as_ref()
+    }
+
+    pub fn port_range(&self) -> Option<(u16, u16)> {
+        self.port_range
+    }
 }
 
 /// Request body to create an outlet
@@ -127,6 +252,20 @@ pub struct CreateOutlet {
     /// Allow the outlet to be reachable from the default secure channel, useful when we want to
     /// tighten the flow control
     #[n(4)] pub reachable_from_default_secure_channel: bool,
+    /// Publish the outlet as a service in the service registry of its project
+    #[n(5)] pub publication: Option<ServicePublication>,
+    /// Hostname and port of the target, resolved by the node. The connections are then
+    /// attempted on all its addresses, IPv4 and IPv6 attempts being raced
+    #[n(6)] pub hostname: Option<String>,
+    /// Family of the addresses of the hostname: "any", "ipv4" or "ipv6"
+    #[n(7)] pub address_family: Option<String>,
+    /// Database users of the identities connecting to the outlet, when the target is a
+    /// Postgres server authenticated by the outlet node
+    #[n(8)] pub postgres_users: Option<PostgresUsers>,
+    /// Access control list of the MQTT topics, when the target is an MQTT broker
+    #[n(9)] pub mqtt: Option<MqttPortalConfig>,
+    /// Policies of the methods, when the target is a gRPC server
+    #[n(10)] pub grpc: Option<GrpcPortalConfig>,
 }
 
 impl CreateOutlet {
@@ -141,8 +280,113 @@ impl CreateOutlet {
             worker_addr,
             alias: alias.into(),
             reachable_from_default_secure_channel,
+            publication: None,
+            hostname: None,
+            address_family: None,
+            postgres_users: None,
+            mqtt: None,
+            grpc: None,
+        }
+    }
+
+    pub fn with_publication(mut self, publication: ServicePublication) -> Self {
+        self.publication = Some(publication);
+        self
+    }
+
+    /// Connect to a hostname instead of the socket address, only using the addresses
+    /// of the given family
+    pub fn with_hostname(
+        mut self,
+        hostname: impl Into<String>,
+        address_family: AddressFamily,
+    ) -> Self {
+        self.hostname = Some(hostname.into());
+        self.address_family = Some(address_family.to_string());
+        self
+    }
+
+    /// Authenticate the connections to a Postgres server with the credentials of these users
+    pub fn with_postgres_users(mut self, users: PostgresUsers) -> Self {
+        self.postgres_users = Some(users);
+        self
+    }
+
+    /// Check the MQTT topics used by the identities of the inlets
+    pub fn with_mqtt(mut self, config: MqttPortalConfig) -> Self {
+        self.mqtt = Some(config);
+        self
+    }
+
+    /// Authorize each gRPC call made by the identities of the inlets
+    pub fn with_grpc(mut self, config: GrpcPortalConfig) -> Self {
+        self.grpc = Some(config);
+        self
+    }
+}
+
+/// Request body to switch the target of the outlet publishing a service,
+/// for example to upgrade its backend without downtime
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct SwitchServiceTarget {
+    /// The new address the outlet should connect to
+    #[n(1)] pub to: SocketAddr,
+    /// Maximum time to wait for the new address to be reachable
+    #[n(2)] pub health_check_timeout_ms: u64,
+}
+
+impl SwitchServiceTarget {
+    pub fn new(to: SocketAddr, health_check_timeout: Duration) -> Self {
+        Self {
+            to,
+            health_check_timeout_ms: health_check_timeout.as_millis() as u64,
+        }
+    }
+
+    pub fn health_check_timeout(&self) -> Duration {
+        Duration::from_millis(self.health_check_timeout_ms)
+    }
+}
+
+/// Request body to record the connections of a portal to a local file
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct StartPortalTap {
+    /// Path of a new file, relative to the `taps` directory of the node
+    #[n(1)] pub path: String,
+    /// Format of the file: `pcap` or `jsonl`
+    #[n(2)] pub format: String,
+    /// Duration of the recording
+    #[n(3)] pub duration_secs: u64,
+}
+
+impl StartPortalTap {
+    pub fn new(path: impl Into<String>, format: TapFormat, duration: Duration) -> Self {
+        Self {
+            path: path.into(),
+            format: format.to_string(),
+            duration_secs: duration.as_secs(),
         }
     }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_secs)
+    }
+}
+
+/// Response body when starting the recording of a portal
+#[derive(Clone, Debug, Decode, Encode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PortalTapStatus {
+    #[n(1)] pub alias: String,
+    #[n(2)] pub path: String,
+    #[n(3)] pub format: String,
+    /// End of the recording, in seconds since the Unix epoch
+    #[n(4)] pub expires_at: u64,
 }
 
 /// Response body when interacting with a portal endpoint
@@ -191,6 +191,22 @@ impl DeleteSecureChannelResponse {
     }
 }
 
+/// Request body to exchange credentials again over an established secure channel
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ExchangeCredentialsRequest {
+    #[n(1)] pub channel: String,
+}
+
+impl ExchangeCredentialsRequest {
+    pub fn new(channel: &Address) -> Self {
+        Self {
+            channel: channel.to_string(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Decode, Encode)]
 #[rustfmt::skip]
 #[cbor(map)]
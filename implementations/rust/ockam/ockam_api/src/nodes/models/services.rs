@@ -241,6 +241,20 @@ impl StartHopServiceRequest {
     }
 }
 
+/// Request body when instructing a node to start a key-value store service
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct StartKvStoreServiceRequest {
+    #[n(1)] pub addr: String,
+}
+
+impl StartKvStoreServiceRequest {
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self { addr: addr.into() }
+    }
+}
+
 #[derive(Debug, Clone, Decode, Encode)]
 #[rustfmt::skip]
 #[cbor(map)]
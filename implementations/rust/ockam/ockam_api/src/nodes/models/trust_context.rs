@@ -0,0 +1,103 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use minicbor::{Decode, Encode};
+use serde::Serialize;
+
+use ockam_core::Result;
+
+use crate::config::cli::TrustContextConfig;
+use crate::error::ApiError;
+
+/// What to do with the secure channels of a node when its trust context is replaced
+#[derive(Copy, Clone, Debug, Default, Decode, Encode, Serialize, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(index_only)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelRevalidation {
+    /// Keep all the channels, only the new channels use the new trust context
+    #[n(0)] Keep,
+    /// Close the channels whose peer has no attributes attested by an authority of the
+    /// new trust context, unless the peer is itself one of these authorities
+    #[default]
+    #[n(1)] Revalidate,
+    /// Close all the channels
+    #[n(2)] Close,
+}
+
+impl Display for ChannelRevalidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Keep => "keep",
+            Self::Revalidate => "revalidate",
+            Self::Close => "close",
+        })
+    }
+}
+
+impl FromStr for ChannelRevalidation {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(Self::Keep),
+            "revalidate" => Ok(Self::Revalidate),
+            "close" => Ok(Self::Close),
+            _ => Err(ApiError::core(format!(
+                "unknown channel revalidation '{s}', expected keep, revalidate or close"
+            ))),
+        }
+    }
+}
+
+/// Request body to replace the trust context of a running node.
+/// The trust context configuration is sent as JSON, as it is stored in the CLI state
+#[derive(Clone, Debug, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct UpdateTrustContextRequest {
+    #[n(1)] trust_context: String,
+    #[n(2)] pub revalidation: ChannelRevalidation,
+}
+
+impl UpdateTrustContextRequest {
+    pub fn new(
+        trust_context: &TrustContextConfig,
+        revalidation: ChannelRevalidation,
+    ) -> Result<Self> {
+        Ok(Self {
+            trust_context: serde_json::to_string(trust_context).map_err(ApiError::core)?,
+            revalidation,
+        })
+    }
+
+    pub fn trust_context(&self) -> Result<TrustContextConfig> {
+        serde_json::from_str(&self.trust_context).map_err(ApiError::core)
+    }
+}
+
+/// Response body once the trust context of a node has been replaced
+#[derive(Clone, Debug, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct UpdateTrustContextResponse {
+    #[n(1)] pub trust_context_id: String,
+    #[n(2)] pub authorities: Vec<String>,
+    #[n(3)] pub closed_channels: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_trust_context_request() -> Result<()> {
+        let config = TrustContextConfig::new("tc".to_string(), None);
+        let request = UpdateTrustContextRequest::new(&config, "close".parse()?)?;
+        let decoded: UpdateTrustContextRequest = minicbor::decode(&minicbor::to_vec(request)?)?;
+        assert_eq!(decoded.trust_context()?, config);
+        assert_eq!(decoded.revalidation, ChannelRevalidation::Close);
+        assert!("reopen".parse::<ChannelRevalidation>().is_err());
+        Ok(())
+    }
+}
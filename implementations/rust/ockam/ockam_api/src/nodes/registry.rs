@@ -1,13 +1,21 @@
+use crate::grpc::GrpcPortalConfig;
+use crate::mqtt::MqttPortalConfig;
 use crate::nodes::service::Alias;
+use crate::portal_dns::PortalDnsRecord;
+use crate::postgres_outlet::PostgresAuthProxy;
+use crate::service_registry::ServicePublication;
 use ockam::identity::Identifier;
 use ockam::identity::{SecureChannel, SecureChannelListener};
 use ockam::remote::RemoteRelayInfo;
 use ockam_core::compat::collections::BTreeMap;
 use ockam_core::{Address, Route};
 use ockam_node::compat::asynchronous::RwLock;
+use ockam_transport_tcp::{PortalInterceptorFactory, PortalSessionResumption};
 use std::borrow::Borrow;
 use std::fmt::Display;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
 
 #[derive(Default)]
 pub(crate) struct SecureChannelRegistry {
@@ -111,6 +119,9 @@ pub(crate) struct EchoerServiceInfo {}
 #[derive(Default, Clone)]
 pub(crate) struct HopServiceInfo {}
 
+#[derive(Default, Clone)]
+pub(crate) struct KvStoreServiceInfo {}
+
 #[derive(Default, Clone)]
 pub(crate) struct VerifierServiceInfo {}
 
@@ -195,6 +206,23 @@ impl OutletInfo {
     }
 }
 
+/// Service published in the service registry of a project for an outlet
+#[derive(Clone)]
+pub(crate) struct PublishedServiceInfo {
+    pub(crate) publication: ServicePublication,
+    /// Task refreshing the publication
+    pub(crate) task: Arc<JoinHandle<()>>,
+}
+
+impl PublishedServiceInfo {
+    pub(crate) fn new(publication: ServicePublication, task: JoinHandle<()>) -> Self {
+        Self {
+            publication,
+            task: Arc::new(task),
+        }
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Registry {
     pub(crate) secure_channels: SecureChannelRegistry,
@@ -204,10 +232,18 @@ pub(crate) struct Registry {
     pub(crate) echoer_services: RegistryOf<Address, EchoerServiceInfo>,
     pub(crate) kafka_services: RegistryOf<Address, KafkaServiceInfo>,
     pub(crate) hop_services: RegistryOf<Address, HopServiceInfo>,
+    pub(crate) kv_store_services: RegistryOf<Address, KvStoreServiceInfo>,
     pub(crate) credentials_services: RegistryOf<Address, CredentialsServiceInfo>,
     pub(crate) relays: RegistryOf<String, RemoteRelayInfo>,
     pub(crate) inlets: RegistryOf<Alias, InletInfo>,
     pub(crate) outlets: RegistryOf<Alias, OutletInfo>,
+    pub(crate) published_services: RegistryOf<Alias, PublishedServiceInfo>,
+    pub(crate) portal_interceptors: RegistryOf<Alias, Arc<dyn PortalInterceptorFactory>>,
+    pub(crate) postgres_proxies: RegistryOf<Alias, PostgresAuthProxy>,
+    pub(crate) mqtt_portals: RegistryOf<Alias, MqttPortalConfig>,
+    pub(crate) grpc_portals: RegistryOf<Alias, GrpcPortalConfig>,
+    pub(crate) portal_dns_records: RegistryOf<String, PortalDnsRecord>,
+    pub(crate) portal_session_resumptions: RegistryOf<Alias, PortalSessionResumption>,
 }
 
 pub(crate) struct RegistryOf<K, V> {
@@ -0,0 +1,224 @@
+//! Resource usage of a node process.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use minicbor::{Decode, Encode};
+use serde::Serialize;
+use sysinfo::{Pid, ProcessExt, System, SystemExt};
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::Storage;
+use ockam_core::Result;
+
+use crate::cli_state::cached::now_millis;
+
+/// Interval between two samples of the resource usage of a node
+pub const DEFAULT_RESOURCE_USAGE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of samples kept in the history, one day with the default interval
+pub const DEFAULT_MAX_SAMPLES: usize = 1440;
+
+const RESOURCE_USAGE_NAMESPACE: &str = "resource_usage";
+
+/// Resource usage of a node process at a given time
+#[derive(Debug, Clone, PartialEq, Decode, Encode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ResourceUsageSample {
+    /// Milliseconds since the Unix epoch
+    #[n(1)] pub timestamp: u64,
+    /// Percentage of one CPU used since the previous sample
+    #[n(2)] pub cpu_usage: f32,
+    /// Resident memory, in bytes
+    #[n(3)] pub memory: u64,
+    /// Number of open file descriptors, only known on Linux
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[n(4)] pub open_files: Option<u64>,
+}
+
+/// Response body for the resource usage history of a node, from the oldest sample
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct NodeResourceUsage {
+    #[n(1)] pub samples: Vec<ResourceUsageSample>,
+}
+
+impl NodeResourceUsage {
+    pub fn new(samples: Vec<ResourceUsageSample>) -> Self {
+        Self { samples }
+    }
+
+    /// Most recent sample, if any
+    pub fn last(&self) -> Option<&ResourceUsageSample> {
+        self.samples.last()
+    }
+}
+
+/// Samples of the resource usage of a node, by time
+#[derive(Clone)]
+pub struct ResourceUsageHistory {
+    storage: Arc<dyn Storage>,
+    max_samples: usize,
+}
+
+impl ResourceUsageHistory {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            max_samples: DEFAULT_MAX_SAMPLES,
+        }
+    }
+
+    pub fn with_max_samples(mut self, max_samples: usize) -> Self {
+        self.max_samples = max_samples;
+        self
+    }
+
+    /// Keep a sample, and remove the oldest ones beyond the maximum number of samples
+    pub async fn save(&self, sample: &ResourceUsageSample) -> Result<()> {
+        self.storage
+            .set(
+                &sample_key(sample.timestamp),
+                RESOURCE_USAGE_NAMESPACE.to_string(),
+                minicbor::to_vec(sample)?,
+            )
+            .await?;
+        let keys = self.sorted_keys().await?;
+        if keys.len() > self.max_samples {
+            for key in &keys[..keys.len() - self.max_samples] {
+                self.storage.del(key, RESOURCE_USAGE_NAMESPACE).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the samples, from the oldest one
+    pub async fn list(&self) -> Result<Vec<ResourceUsageSample>> {
+        let mut samples = vec![];
+        for key in self.sorted_keys().await? {
+            if let Some(bytes) = self.storage.get(&key, RESOURCE_USAGE_NAMESPACE).await? {
+                samples.push(minicbor::decode(&bytes)?);
+            }
+        }
+        Ok(samples)
+    }
+
+    /// Sample the resource usage of the current process on an interval.
+    /// The first sample is taken after one interval, so that the CPU usage can be measured
+    pub fn start(self, interval: Duration) -> JoinHandle<()> {
+        info!(
+            interval_secs = interval.as_secs(),
+            "Scheduling the sampling of the node resource usage"
+        );
+        tokio::spawn(async move {
+            let mut sampler = ResourceUsageSampler::new();
+            let start = tokio::time::Instant::now() + interval;
+            let mut interval = tokio::time::interval_at(start, interval);
+            loop {
+                interval.tick().await;
+                let sample = match sampler.sample() {
+                    Some(sample) => sample,
+                    None => {
+                        warn!("The resource usage of the node can't be measured");
+                        continue;
+                    }
+                };
+                if let Err(e) = self.save(&sample).await {
+                    warn!(%e, "The resource usage of the node can't be stored")
+                }
+            }
+        })
+    }
+
+    /// The keys are zero-padded timestamps, sorted by time
+    async fn sorted_keys(&self) -> Result<Vec<String>> {
+        let mut keys = self.storage.keys(RESOURCE_USAGE_NAMESPACE).await?;
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// The CPU usage of a process is measured between two refreshes of the same [`System`]
+struct ResourceUsageSampler {
+    system: System,
+    pid: Option<Pid>,
+}
+
+impl ResourceUsageSampler {
+    fn new() -> Self {
+        let mut system = System::new();
+        let pid = sysinfo::get_current_pid().ok();
+        if let Some(pid) = pid {
+            system.refresh_process(pid);
+        }
+        Self { system, pid }
+    }
+
+    fn sample(&mut self) -> Option<ResourceUsageSample> {
+        let pid = self.pid?;
+        if !self.system.refresh_process(pid) {
+            return None;
+        }
+        let process = self.system.process(pid)?;
+        Some(ResourceUsageSample {
+            timestamp: now_millis(),
+            cpu_usage: process.cpu_usage(),
+            memory: process.memory(),
+            open_files: open_files(),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn open_files() -> Option<u64> {
+    std::fs::read_dir("/proc/self/fd")
+        .ok()
+        .map(|entries| entries.count() as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_files() -> Option<u64> {
+    None
+}
+
+fn sample_key(timestamp: u64) -> String {
+    format!("{timestamp:020}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::storage::InMemoryStorage;
+
+    fn sample(timestamp: u64) -> ResourceUsageSample {
+        ResourceUsageSample {
+            timestamp,
+            cpu_usage: 1.5,
+            memory: 1024,
+            open_files: Some(12),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_the_last_samples() -> Result<()> {
+        let history = ResourceUsageHistory::new(InMemoryStorage::create()).with_max_samples(2);
+        history.save(&sample(9)).await?;
+        history.save(&sample(1000)).await?;
+        history.save(&sample(100)).await?;
+
+        let samples = history.list().await?;
+        assert_eq!(samples, vec![sample(100), sample(1000)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sample_current_process() {
+        let mut sampler = ResourceUsageSampler::new();
+        let sample = sampler.sample().unwrap();
+        assert!(sample.memory > 0);
+        #[cfg(target_os = "linux")]
+        assert!(sample.open_files.unwrap() > 0);
+    }
+}
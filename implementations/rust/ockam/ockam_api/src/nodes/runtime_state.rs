@@ -0,0 +1,334 @@
+//! Warm start of a node.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use minicbor::{Decoder, Encoder};
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::Storage;
+use ockam_core::api::{Method, RequestHeader, ResponseHeader, Status};
+use ockam_core::compat::sync::Arc;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Address, DenyAll, Result};
+use ockam_node::{Context, MessageSendReceiveOptions};
+
+use crate::nodes::api_recording::without_api_token;
+use crate::nodes::models::portal::{CreateInlet, InletStatus, OutletStatus};
+use crate::nodes::models::relay::RelayInfo;
+use crate::nodes::models::secure_channel::{
+    CreateSecureChannelResponse, DeleteSecureChannelRequest,
+};
+use crate::nodes::NODEMANAGER_ADDR;
+
+/// Maximum time to wait for a replayed request, which can wait for a remote outlet
+const REPLAY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Kinds of resources restored by a warm start, in the order of their restoration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeStateKind {
+    SecureChannel,
+    Relay,
+    Outlet,
+    Inlet,
+}
+
+impl RuntimeStateKind {
+    const ALL: [RuntimeStateKind; 4] = [
+        RuntimeStateKind::SecureChannel,
+        RuntimeStateKind::Relay,
+        RuntimeStateKind::Outlet,
+        RuntimeStateKind::Inlet,
+    ];
+
+    fn namespace(&self) -> &'static str {
+        match self {
+            RuntimeStateKind::SecureChannel => "secure_channel",
+            RuntimeStateKind::Relay => "relay",
+            RuntimeStateKind::Outlet => "outlet",
+            RuntimeStateKind::Inlet => "inlet",
+        }
+    }
+}
+
+/// Requests to replay when a node restarts, by kind of resource and resource key
+#[derive(Clone)]
+pub struct RuntimeState {
+    storage: Arc<dyn Storage>,
+}
+
+impl RuntimeState {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self { storage }
+    }
+
+    /// Keep the request creating a resource, without its API token
+    pub async fn save(&self, kind: RuntimeStateKind, key: &str, request: Vec<u8>) -> Result<()> {
+        self.storage
+            .set(
+                key,
+                kind.namespace().to_string(),
+                without_api_token(&request)?,
+            )
+            .await
+    }
+
+    /// Forget the request creating a resource
+    pub async fn remove(&self, kind: RuntimeStateKind, key: &str) -> Result<()> {
+        self.storage.del(key, kind.namespace()).await
+    }
+
+    /// Return the requests kept for a kind of resource, by resource key
+    pub async fn entries(&self, kind: RuntimeStateKind) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut entries = vec![];
+        for key in self.storage.keys(kind.namespace()).await? {
+            if let Some(request) = self.storage.get(&key, kind.namespace()).await? {
+                entries.push((key, request));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Keep or forget a request handled by the node manager, depending on its response
+    pub async fn record(&self, req: &RequestHeader, request: &[u8], response: &[u8]) -> Result<()> {
+        let mut dec = Decoder::new(response);
+        let header: ResponseHeader = dec.decode()?;
+        if header.status() != Some(Status::Ok) {
+            return Ok(());
+        }
+
+        let path_segments = req.path_segments::<5>();
+        match (req.method(), path_segments.as_slice()) {
+            (Some(Method::Post), ["node", "secure_channel"]) => {
+                let created: CreateSecureChannelResponse = dec.decode()?;
+                self.save(
+                    RuntimeStateKind::SecureChannel,
+                    &created.addr.to_string(),
+                    request.to_vec(),
+                )
+                .await
+            }
+            (Some(Method::Delete), ["node", "secure_channel"]) => {
+                let mut dec = Decoder::new(request);
+                let _: RequestHeader = dec.decode()?;
+                let deleted: DeleteSecureChannelRequest = dec.decode()?;
+                self.remove(RuntimeStateKind::SecureChannel, &deleted.channel)
+                    .await
+            }
+            (Some(Method::Post), ["node", "forwarder"]) => {
+                let created: RelayInfo = dec.decode()?;
+                self.save(
+                    RuntimeStateKind::Relay,
+                    created.remote_address(),
+                    request.to_vec(),
+                )
+                .await
+            }
+            (Some(Method::Delete), ["node", "forwarder", remote_address]) => {
+                self.remove(RuntimeStateKind::Relay, remote_address).await
+            }
+            (Some(Method::Post), ["node", "outlet"]) => {
+                let created: OutletStatus = dec.decode()?;
+                self.save(RuntimeStateKind::Outlet, &created.alias, request.to_vec())
+                    .await
+            }
+            (Some(Method::Delete), ["node", "outlet", alias]) => {
+                self.remove(RuntimeStateKind::Outlet, alias).await
+            }
+            (Some(Method::Post), ["node", "inlet"]) => {
+                let created: InletStatus = dec.decode()?;
+                let request = pin_inlet_port(request, &created.bind_addr)?;
+                self.save(RuntimeStateKind::Inlet, &created.alias, request)
+                    .await
+            }
+            (Some(Method::Delete), ["node", "inlet", alias]) => {
+                self.remove(RuntimeStateKind::Inlet, alias).await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replay the kept requests to the node manager of this node, which must be started.
+    ///
+    /// The resources are created again in the background since some of them can wait for
+    /// a remote node. The replayed requests are recorded again with the keys of the new resources
+    pub async fn restore(&self, ctx: &Context) -> Result<JoinHandle<()>> {
+        let ctx = ctx
+            .new_detached(Address::random_tagged("RuntimeState.ctx"), DenyAll, DenyAll)
+            .await?;
+        let state = self.clone();
+        Ok(tokio::spawn(async move {
+            for kind in RuntimeStateKind::ALL {
+                let entries = match state.entries(kind).await {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        warn!("the {} entries could not be read: {e}", kind.namespace());
+                        continue;
+                    }
+                };
+                for (key, request) in entries {
+                    if let Err(e) = state.replay(&ctx, kind, &key, request).await {
+                        warn!("the {} {key} could not be restored: {e}", kind.namespace());
+                    }
+                }
+            }
+        }))
+    }
+
+    async fn replay(
+        &self,
+        ctx: &Context,
+        kind: RuntimeStateKind,
+        key: &str,
+        request: Vec<u8>,
+    ) -> Result<()> {
+        // the restored resource is recorded again with its new key
+        self.remove(kind, key).await?;
+        let response = ctx
+            .send_and_receive_extended::<Vec<u8>>(
+                NODEMANAGER_ADDR,
+                request.clone(),
+                MessageSendReceiveOptions::new().with_timeout(REPLAY_TIMEOUT),
+            )
+            .await
+            .map(|r| r.body());
+        let status = match &response {
+            Ok(response) => Decoder::new(response).decode::<ResponseHeader>()?.status(),
+            Err(_) => None,
+        };
+        if status == Some(Status::Ok) {
+            info!("restored the {} {key}", kind.namespace());
+            return Ok(());
+        }
+
+        // keep the request for the next restart
+        self.save(kind, key, request).await?;
+        match response {
+            Ok(_) => Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                format!("the request was rejected with the status {status:?}"),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Replace the port 0 of the address of an inlet with the port which was chosen for it
+fn pin_inlet_port(request: &[u8], bind_addr: &str) -> Result<Vec<u8>> {
+    let mut dec = Decoder::new(request);
+    let header: RequestHeader = dec.decode()?;
+    let mut create_inlet: CreateInlet = dec.decode()?;
+    let is_dynamic = create_inlet
+        .listen_addr
+        .parse::<SocketAddr>()
+        .map(|addr| addr.port() == 0)
+        .unwrap_or(false);
+    if !is_dynamic {
+        return Ok(request.to_vec());
+    }
+
+    create_inlet.listen_addr = bind_addr.to_string();
+    let mut pinned = vec![];
+    Encoder::new(&mut pinned)
+        .encode(&header)?
+        .encode(&create_inlet)?;
+    Ok(pinned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::storage::InMemoryStorage;
+    use ockam_core::api::{Request, Response};
+    use ockam_core::route;
+
+    #[tokio::test]
+    async fn test_record_requests() -> Result<()> {
+        let state = RuntimeState::new(InMemoryStorage::create());
+
+        let request = Request::post("/node/outlet");
+        let response = Response::ok(request.header())
+            .body(OutletStatus::new(
+                "127.0.0.1:5000".parse().unwrap(),
+                "outlet".into(),
+                "db",
+                None,
+            ))
+            .to_vec()?;
+        let request_bytes = request.to_vec()?;
+        state
+            .record(request.header(), &request_bytes, &response)
+            .await?;
+        assert_eq!(
+            state.entries(RuntimeStateKind::Outlet).await?,
+            vec![("db".to_string(), request_bytes.clone())]
+        );
+
+        // failed requests are not recorded
+        let request = Request::post("/node/inlet");
+        let response = Response::bad_request(request.header(), "no").to_vec()?;
+        state
+            .record(request.header(), &request.to_vec()?, &response)
+            .await?;
+        assert!(state.entries(RuntimeStateKind::Inlet).await?.is_empty());
+
+        let request = Request::delete("/node/outlet/db");
+        let response = Response::ok(request.header()).to_vec()?;
+        state
+            .record(request.header(), &request.to_vec()?, &response)
+            .await?;
+        assert!(state.entries(RuntimeStateKind::Outlet).await?.is_empty());
+
+        // the API token of a request is not kept
+        let request = Request::post("/node/outlet").api_token("token");
+        let response = Response::ok(request.header())
+            .body(OutletStatus::new(
+                "127.0.0.1:5000".parse().unwrap(),
+                "outlet".into(),
+                "db",
+                None,
+            ))
+            .to_vec()?;
+        state
+            .record(request.header(), &request.to_vec()?, &response)
+            .await?;
+        let entries = state.entries(RuntimeStateKind::Outlet).await?;
+        let header: RequestHeader = Decoder::new(&entries[0].1).decode()?;
+        assert_eq!(header.api_token(), None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pin_the_port_of_an_inlet() -> Result<()> {
+        let state = RuntimeState::new(InMemoryStorage::create());
+
+        let request = Request::post("/node/inlet").body(CreateInlet::to_node(
+            "127.0.0.1:0".to_string(),
+            "/service/outlet".parse().unwrap(),
+            route![],
+            route![],
+            None,
+        ));
+        let response = Response::ok(request.header())
+            .body(InletStatus::new(
+                "127.0.0.1:41005",
+                "inlet",
+                "db",
+                None,
+                "outlet",
+            ))
+            .to_vec()?;
+        state
+            .record(request.header(), &request.to_vec()?, &response)
+            .await?;
+
+        let entries = state.entries(RuntimeStateKind::Inlet).await?;
+        assert_eq!(entries.len(), 1);
+        let mut dec = Decoder::new(&entries[0].1);
+        let _: RequestHeader = dec.decode()?;
+        let recorded: CreateInlet = dec.decode()?;
+        assert_eq!(recorded.listen_addr(), "127.0.0.1:41005");
+        Ok(())
+    }
+}
@@ -11,61 +11,99 @@ use minicbor::{Decoder, Encode};
 
 pub use node_identities::*;
 use ockam::identity::models::CredentialAndPurposeKey;
+use ockam::identity::storage::{InMemoryStorage, Storage};
 use ockam::identity::CredentialsServerModule;
 use ockam::identity::TrustContext;
 use ockam::identity::Vault;
 use ockam::identity::{
     Credentials, CredentialsServer, Identities, IdentitiesRepository, IdentityAttributesReader,
 };
-use ockam::identity::{Identifier, SecureChannels};
+use ockam::identity::{Identifier, IdentitySecureChannelLocalInfo, SecureChannels};
+use ockam::identity::{IdentityQuotas, QuotaLimits, Timeouts, VerificationCache};
+use ockam::LmdbStorage;
 use ockam::{
     Address, Context, RelayService, RelayServiceOptions, Result, Routed, TcpTransport, Worker,
 };
-use ockam_abac::expr::{eq, ident, str};
+use ockam_abac::expr::str;
 use ockam_abac::{Action, Env, Expr, PolicyAccessControl, PolicyStorage, Resource};
 use ockam_core::api::{Method, RequestHeader, Response};
-use ockam_core::compat::{string::String, sync::Arc};
+use ockam_core::compat::{
+    string::String,
+    sync::{Arc, RwLock},
+};
 use ockam_core::flow_control::FlowControlId;
 use ockam_core::IncomingAccessControl;
-use ockam_core::{AllowAll, AsyncTryClone};
+use ockam_core::{AllowAll, AsyncTryClone, Route};
 use ockam_multiaddr::MultiAddr;
+use ockam_transport_tcp::OutletResolver;
+use subtle::ConstantTimeEq;
 
+use crate::bearer_token::BearerTokenVerifier;
 use crate::bootstrapped_identities_store::BootstrapedIdentityStore;
 use crate::bootstrapped_identities_store::PreTrustedIdentities;
-use crate::cli_state::{CliState, StateDirTrait, StateItemTrait};
+use crate::cli_state::{CliState, IdentitiesState, StateDirTrait, StateItemTrait};
 use crate::cloud::{AuthorityNode, ProjectNode};
-use crate::config::cli::TrustContextConfig;
+use crate::config::cli::{CredentialRetrieverConfig, TrustContextConfig};
 use crate::config::lookup::ProjectLookup;
 use crate::error::ApiError;
+use crate::fleet::FleetInventory;
+use crate::identity::credentials_clock;
+use crate::members_replication::MembersReplica;
+use crate::metrics_exporter::PortalTraffic;
+use crate::nodes::api_recording::ApiRecorder;
 use crate::nodes::connection::{
     Connection, ConnectionBuilder, PlainTcpInstantiator, ProjectInstantiator,
     SecureChannelInstantiator,
 };
-use crate::nodes::models::base::NodeStatus;
+use crate::nodes::idempotency::IdempotencyKeys;
+use crate::nodes::models::base::{NodeMemoryUsage, NodeStatus};
 use crate::nodes::models::portal::{OutletList, OutletStatus};
 use crate::nodes::models::transport::{TransportMode, TransportType};
 use crate::nodes::models::workers::{WorkerList, WorkerStatus};
 use crate::nodes::registry::KafkaServiceKind;
+use crate::nodes::resource_usage::{NodeResourceUsage, ResourceUsageHistory, ResourceUsageSample};
+use crate::nodes::runtime_state::RuntimeState;
 use crate::nodes::{InMemoryNode, NODEMANAGER_ADDR};
+use crate::notifier::{Notifier, NotifierConfig};
+use crate::ping::HealthCheckHistory;
+use crate::policy_bundle::default_policy;
+use crate::portal_events::{PortalEvents, PortalEventsSink};
+use crate::portal_tap::{PortalSide, PortalTaps};
+use crate::resource_profile::{NodeResources, ResourceProfile};
+use crate::storage_maintenance::StorageMaintenance;
 use crate::DefaultAddress;
 
 use super::registry::Registry;
 
 pub(crate) mod background_node;
+mod config_updates;
 pub(crate) mod credentials;
+pub mod drift;
+mod fleet;
 mod flow_controls;
 pub(crate) mod in_memory_node;
 pub mod message;
+pub mod nested_secure_channel;
 mod node_identities;
 mod node_services;
+mod ping;
 mod policy;
 mod portals;
 pub mod relay;
+pub mod roles;
+pub mod rollout;
 mod secure_channel;
 mod transport;
+mod trust_context;
 
 const TARGET: &str = "ockam_api::nodemanager::service";
 
+/// Duration after which a signature verified by a node of this host is verified again
+const VERIFICATION_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 3600);
+
+/// Interval between two synchronizations of the members replicated from the authority
+const MEMBERS_REPLICATION_INTERVAL: Duration = Duration::from_secs(30);
+
 pub(crate) type Alias = String;
 
 /// Generate a new alias for some user created extension
@@ -95,12 +133,28 @@ pub struct NodeManager {
     node_name: String,
     api_transport_flow_control_id: FlowControlId,
     pub(crate) tcp_transport: TcpTransport,
-    enable_credential_checks: bool,
     identifier: Identifier,
     pub(crate) secure_channels: Arc<SecureChannels>,
-    trust_context: Option<TrustContext>,
+    trust_context: RwLock<Option<TrustContext>>,
     pub(crate) registry: Registry,
     policies: Arc<dyn PolicyStorage>,
+    quotas: IdentityQuotas,
+    authority_transitions: Option<Arc<dyn Storage>>,
+    portal_events: Option<PortalEvents>,
+    portal_traffic: PortalTraffic,
+    portal_taps: PortalTaps,
+    resource_profile: ResourceProfile,
+    runtime_state: Option<RuntimeState>,
+    notifier: Option<Notifier>,
+    api_recorder: Option<ApiRecorder>,
+    idempotency_keys: IdempotencyKeys,
+    api_token: Option<String>,
+    timeouts: Timeouts,
+    portal_session_resumption: bool,
+    outlet_resolver: OutletResolver,
+    fleet_inventory: Option<FleetInventory>,
+    resource_usage: Option<ResourceUsageHistory>,
+    health_checks: HealthCheckHistory,
 }
 
 impl NodeManager {
@@ -108,6 +162,14 @@ impl NodeManager {
         &self.identifier
     }
 
+    /// Return the samples of the resource usage of the node, none if it is not sampled
+    pub async fn resource_usage_samples(&self) -> Result<Vec<ResourceUsageSample>> {
+        match &self.resource_usage {
+            Some(history) => history.list().await,
+            None => Ok(vec![]),
+        }
+    }
+
     pub fn node_name(&self) -> String {
         self.node_name.clone()
     }
@@ -132,6 +194,92 @@ impl NodeManager {
         Arc::new(CredentialsServerModule::new(self.credentials()))
     }
 
+    /// Verifier of the bearer tokens issued by this node, for the services receiving the
+    /// requests of HTTP clients
+    pub fn bearer_token_verifier(&self) -> BearerTokenVerifier {
+        BearerTokenVerifier::new(self.identities(), vec![self.identifier.clone()])
+    }
+
+    pub(super) fn quotas(&self) -> IdentityQuotas {
+        self.quotas.clone()
+    }
+
+    pub(super) fn portal_events(&self) -> Option<&PortalEvents> {
+        self.portal_events.as_ref()
+    }
+
+    pub(crate) fn portal_traffic(&self) -> &PortalTraffic {
+        &self.portal_traffic
+    }
+
+    pub(crate) fn portal_taps(&self) -> &PortalTaps {
+        &self.portal_taps
+    }
+
+    pub fn resource_profile(&self) -> ResourceProfile {
+        self.resource_profile
+    }
+
+    /// Return the timeouts used by default when communicating with other nodes
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Return true if the sessions of the portal connections are resumed after an outage
+    pub fn portal_session_resumption(&self) -> bool {
+        self.portal_session_resumption
+    }
+
+    /// Return the requests to replay when the node restarts, if the node has a warm start
+    pub fn runtime_state(&self) -> Option<&RuntimeState> {
+        self.runtime_state.as_ref()
+    }
+
+    pub(super) fn notifier(&self) -> Option<&Notifier> {
+        self.notifier.as_ref()
+    }
+
+    /// Return the recorder of the requests handled by the node manager, if they are recorded
+    pub fn api_recorder(&self) -> Option<&ApiRecorder> {
+        self.api_recorder.as_ref()
+    }
+
+    /// Return true if a request can be handled: the requests received by the API transport
+    /// listener, from local processes, must have the API token of the node
+    pub(super) fn is_authenticated(
+        &self,
+        ctx: &Context,
+        req: &RequestHeader,
+        return_route: &Route,
+    ) -> bool {
+        let Some(api_token) = self.api_token.as_ref() else {
+            return true;
+        };
+        let via_api_transport = return_route
+            .next()
+            .ok()
+            .and_then(|next| {
+                ctx.flow_controls()
+                    .find_flow_control_with_producer_address(next)
+            })
+            .map(|producer| {
+                producer.spawner_flow_control_id().as_ref()
+                    == Some(&self.api_transport_flow_control_id)
+            })
+            .unwrap_or(false);
+        // the token is compared in constant time so that it can't be guessed from the
+        // response times
+        !via_api_transport
+            || req.api_token().map_or(false, |token| {
+                token.as_bytes().ct_eq(api_token.as_bytes()).into()
+            })
+    }
+
+    /// Return the responses kept for the requests having an idempotency key
+    pub fn idempotency_keys(&self) -> &IdempotencyKeys {
+        &self.idempotency_keys
+    }
+
     pub(super) fn secure_channels_vault(&self) -> Vault {
         self.secure_channels.identities().vault()
     }
@@ -159,7 +307,7 @@ impl NodeManager {
         Ok(self
             .cli_state
             .nodes
-            .delete_sigkill(self.node_name().as_str(), false)?)
+            .delete_for_good(self.node_name().as_str())?)
     }
 }
 
@@ -243,10 +391,7 @@ impl NodeManager {
             if self.policies.get_policy(r, a).await?.is_none() {
                 let fallback = match custom_default {
                     Some(e) => e.clone(),
-                    None => eq([
-                        ident("resource.trust_context_id"),
-                        ident("subject.trust_context_id"),
-                    ]),
+                    None => default_policy(),
                 };
                 self.policies.set_policy(r, a, &fallback).await?
             }
@@ -263,11 +408,22 @@ impl NodeManager {
         }
     }
 
-    pub(crate) fn trust_context(&self) -> Result<&TrustContext> {
+    /// Return the current trust context of the node.
+    /// It can be replaced while the node is running, see [`NodeManager::update_trust_context`]
+    pub(crate) fn trust_context(&self) -> Result<TrustContext> {
         self.trust_context
-            .as_ref()
+            .read()
+            .unwrap()
+            .clone()
             .ok_or_else(|| ApiError::core("Trust context doesn't exist"))
     }
+
+    /// The credentials are checked when the trust context of the node has an authority
+    pub(crate) fn enable_credential_checks(&self) -> bool {
+        self.trust_context()
+            .map(|tc| tc.authority().is_ok())
+            .unwrap_or(false)
+    }
 }
 
 pub struct NodeManagerGeneralOptions {
@@ -276,6 +432,20 @@ pub struct NodeManagerGeneralOptions {
     pre_trusted_identities: Option<PreTrustedIdentities>,
     start_default_services: bool,
     persistent: bool,
+    quota_limits: QuotaLimits,
+    members_replication: bool,
+    portal_events_sink: Option<PortalEventsSink>,
+    resource_profile: ResourceProfile,
+    warm_start: bool,
+    notifier_config: Option<NotifierConfig>,
+    api_recording: Option<PathBuf>,
+    api_token: Option<String>,
+    timeouts: Timeouts,
+    portal_session_resumption: bool,
+    outlet_resolver: OutletResolver,
+    fleet_inventory: bool,
+    storage_maintenance_interval: Option<Duration>,
+    resource_usage_interval: Option<Duration>,
 }
 
 impl NodeManagerGeneralOptions {
@@ -292,8 +462,110 @@ impl NodeManagerGeneralOptions {
             pre_trusted_identities,
             start_default_services,
             persistent,
+            quota_limits: QuotaLimits::default(),
+            members_replication: false,
+            portal_events_sink: None,
+            resource_profile: ResourceProfile::default(),
+            warm_start: false,
+            notifier_config: None,
+            api_recording: None,
+            api_token: None,
+            timeouts: Timeouts::default(),
+            portal_session_resumption: false,
+            outlet_resolver: OutletResolver::new(),
+            fleet_inventory: false,
+            storage_maintenance_interval: None,
+            resource_usage_interval: None,
         }
     }
+
+    /// Limit the resources which can be used by each identity connecting to the node
+    pub fn with_quota_limits(mut self, quota_limits: QuotaLimits) -> Self {
+        self.quota_limits = quota_limits;
+        self
+    }
+
+    /// Keep a local copy of the members attributes of the project authority
+    pub fn with_members_replication(mut self, members_replication: bool) -> Self {
+        self.members_replication = members_replication;
+        self
+    }
+
+    /// Write the portal connection events to a file or a local socket
+    pub fn with_portal_events_sink(mut self, sink: Option<PortalEventsSink>) -> Self {
+        self.portal_events_sink = sink;
+        self
+    }
+
+    /// Use the portal buffer sizes of a resource profile
+    pub fn with_resource_profile(mut self, resource_profile: ResourceProfile) -> Self {
+        self.resource_profile = resource_profile;
+        self
+    }
+
+    /// Keep the channels, relays and portals created on a persistent node
+    /// in order to create them again when the node restarts
+    pub fn with_warm_start(mut self, warm_start: bool) -> Self {
+        self.warm_start = warm_start;
+        self
+    }
+
+    /// Post the critical events of the node to webhooks
+    pub fn with_notifier_config(mut self, notifier_config: Option<NotifierConfig>) -> Self {
+        self.notifier_config = notifier_config;
+        self
+    }
+
+    /// Append the requests handled by the node manager, and their responses, to a file
+    pub fn with_api_recording(mut self, api_recording: Option<PathBuf>) -> Self {
+        self.api_recording = api_recording;
+        self
+    }
+
+    /// Require this token in the requests received by the API transport listener
+    pub fn with_api_token(mut self, api_token: Option<String>) -> Self {
+        self.api_token = api_token;
+        self
+    }
+
+    /// Use different timeouts for the handshakes, the credential exchanges, the messages
+    /// and the management requests, unless a timeout is given for a specific call
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Keep the portal connections open during a brief outage of the route between
+    /// the inlets and the outlets, and resume their sessions once the route is re-established
+    pub fn with_portal_session_resumption(mut self, portal_session_resumption: bool) -> Self {
+        self.portal_session_resumption = portal_session_resumption;
+        self
+    }
+
+    /// Resolve the outlet targets which are hostnames with this resolver, caching their
+    /// addresses for the TTL of their records
+    pub fn with_outlet_resolver(mut self, outlet_resolver: OutletResolver) -> Self {
+        self.outlet_resolver = outlet_resolver;
+        self
+    }
+
+    /// Maintain the databases of a persistent node on an interval
+    pub fn with_storage_maintenance(mut self, interval: Option<Duration>) -> Self {
+        self.storage_maintenance_interval = interval;
+        self
+    }
+
+    /// Keep the heartbeats sent by the nodes of a fleet to this node
+    pub fn with_fleet_inventory(mut self, fleet_inventory: bool) -> Self {
+        self.fleet_inventory = fleet_inventory;
+        self
+    }
+
+    /// Sample the CPU, memory and open files used by the node process on an interval
+    pub fn with_resource_usage(mut self, interval: Option<Duration>) -> Self {
+        self.resource_usage_interval = interval;
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -359,74 +631,244 @@ impl NodeManager {
         let cli_state = general_options.cli_state;
         let node_state = cli_state.nodes.get(&general_options.node_name)?;
 
-        let repository: Arc<dyn IdentitiesRepository> =
-            cli_state.identities.identities_repository().await?;
+        let repository: Arc<dyn IdentitiesRepository> = cli_state.identities_repository().await?;
+
+        let notifier = general_options
+            .notifier_config
+            .map(|config| Notifier::start(general_options.node_name.clone(), config));
 
         //TODO: fix this.  Either don't require it to be a bootstrappedidentitystore (and use the
         //trait instead),  or pass it from the general_options always.
         let vault: Vault = node_state.config().vault().await?;
-        let identities_repository: Arc<dyn IdentitiesRepository> =
-            Arc::new(match general_options.pre_trusted_identities {
+        let identities_repository: Arc<dyn IdentitiesRepository> = Arc::new(
+            match general_options.pre_trusted_identities {
                 None => BootstrapedIdentityStore::new(
                     Arc::new(PreTrustedIdentities::new_from_string("{}")?),
                     repository.clone(),
                 ),
                 Some(f) => BootstrapedIdentityStore::new(Arc::new(f), repository.clone()),
-            });
+            }
+            .with_notifier(notifier.clone()),
+        );
 
         debug!("create the secure channels service");
-        let secure_channels = SecureChannels::builder()
+        let mut secure_channels = SecureChannels::builder()
             .with_vault(vault)
             .with_identities_repository(identities_repository.clone())
-            .build();
+            .with_credentials_clock(credentials_clock()?);
+        if let Some(storage) = cli_state.verification_cache().await? {
+            // the oldest entries are removed when a persistent node starts
+            if general_options.persistent {
+                let cache =
+                    VerificationCache::new(Vault::create_verifying_vault(), storage.clone());
+                tokio::spawn(async move {
+                    match cache.prune(VERIFICATION_CACHE_MAX_AGE.as_secs()).await {
+                        Ok(removed) => debug!(removed, "Pruned the verified signatures cache"),
+                        Err(e) => warn!(%e, "The verified signatures cache can't be pruned"),
+                    }
+                });
+            }
+            secure_channels = secure_channels.with_verification_cache(storage);
+        }
+        let secure_channels = secure_channels.build();
 
-        let policies: Arc<dyn PolicyStorage> = Arc::new(node_state.policies_storage().await?);
+        let policies_storage = node_state.policies_storage().await?;
+        let mut databases = vec![policies_storage.clone()];
+        let policies: Arc<dyn PolicyStorage> = Arc::new(policies_storage);
+
+        // the limits specific to some identities are only kept for persistent nodes
+        let quotas = if general_options.persistent {
+            let storage = node_state.quotas_storage().await?;
+            databases.push(storage.clone());
+            IdentityQuotas::create_with_storage(general_options.quota_limits, Arc::new(storage))
+                .await?
+        } else {
+            IdentityQuotas::new(general_options.quota_limits)
+        };
+
+        // a persistent node still accepts the credentials of a rotated authority after a restart
+        let authority_transitions: Option<Arc<dyn Storage>> = if general_options.persistent {
+            let storage = node_state.authority_transitions_storage().await?;
+            databases.push(storage.clone());
+            Some(Arc::new(storage))
+        } else {
+            None
+        };
+
+        let runtime_state = if general_options.persistent && general_options.warm_start {
+            let storage = node_state.runtime_state_storage().await?;
+            databases.push(storage.clone());
+            Some(RuntimeState::new(Arc::new(storage)))
+        } else {
+            None
+        };
+
+        let portal_events = general_options.portal_events_sink.map(|sink| {
+            PortalEvents::start(
+                general_options.node_name.clone(),
+                sink,
+                identities_repository.as_attributes_reader(),
+                cli_state.display_names().unwrap_or_default(),
+            )
+        });
+
+        // the keys of the requests must outlive a restart of a persistent node
+        let idempotency_keys = if general_options.persistent {
+            let storage = node_state.idempotency_keys_storage().await?;
+            databases.push(storage.clone());
+            IdempotencyKeys::new(Arc::new(storage))
+        } else {
+            IdempotencyKeys::new(InMemoryStorage::create())
+        };
+        idempotency_keys.clone().start_pruning();
+
+        // the inventory of a persistent node is kept until the next heartbeats after a restart
+        let fleet_inventory = match (general_options.fleet_inventory, general_options.persistent) {
+            (false, _) => None,
+            (true, true) => {
+                let storage = node_state.fleet_storage().await?;
+                databases.push(storage.clone());
+                Some(FleetInventory::new(Arc::new(storage)))
+            }
+            (true, false) => Some(FleetInventory::new(InMemoryStorage::create())),
+        };
+
+        // the resource usage history of a persistent node is kept across restarts
+        let resource_usage = match general_options.resource_usage_interval {
+            None => None,
+            Some(interval) => {
+                let history = if general_options.persistent {
+                    let storage = node_state.resource_usage_storage().await?;
+                    databases.push(storage.clone());
+                    ResourceUsageHistory::new(Arc::new(storage))
+                } else {
+                    ResourceUsageHistory::new(InMemoryStorage::create())
+                };
+                history.clone().start(interval);
+                Some(history)
+            }
+        };
+
+        // the results of the health checks of a persistent node are kept across restarts
+        let health_checks = if general_options.persistent {
+            let storage = node_state.health_checks_storage().await?;
+            databases.push(storage.clone());
+            HealthCheckHistory::new(Arc::new(storage))
+        } else {
+            HealthCheckHistory::new(InMemoryStorage::create())
+        };
+
+        // the identities database is shared by the nodes, and contains the members of a project
+        // when the node replicates them from the authority.
+        // A database set with OCKAM_DATABASE_URL is maintained by its own server
+        if let (Some(interval), true) = (
+            general_options.storage_maintenance_interval,
+            general_options.persistent,
+        ) {
+            if IdentitiesState::database_url()?.is_none() {
+                let identities_path = cli_state.identities.identities_repository_path()?;
+                databases.push(LmdbStorage::new(identities_path).await?);
+            }
+            let mut maintenance = StorageMaintenance::new(interval);
+            for database in databases {
+                maintenance = maintenance.with_database(database);
+            }
+            maintenance.start();
+        }
+
+        let api_recorder = general_options
+            .api_recording
+            .as_deref()
+            .map(ApiRecorder::create)
+            .transpose()?;
 
         let mut s = Self {
             cli_state,
             node_name: general_options.node_name,
             api_transport_flow_control_id: transport_options.api_transport_flow_control_id,
             tcp_transport: transport_options.tcp_transport,
-            enable_credential_checks: trust_options.trust_context_config.is_some()
-                && trust_options
-                    .trust_context_config
-                    .as_ref()
-                    .unwrap()
-                    .authority()
-                    .is_ok(),
             identifier: node_state.config().identifier()?,
             secure_channels,
-            trust_context: None,
+            trust_context: RwLock::new(None),
             registry: Default::default(),
             policies,
+            quotas,
+            authority_transitions,
+            portal_events,
+            portal_traffic: Default::default(),
+            portal_taps: PortalTaps::new(node_state.taps_dir()),
+            resource_profile: general_options.resource_profile,
+            runtime_state,
+            notifier,
+            api_recorder,
+            idempotency_keys,
+            api_token: general_options.api_token,
+            timeouts: general_options.timeouts,
+            portal_session_resumption: general_options.portal_session_resumption,
+            outlet_resolver: general_options.outlet_resolver,
+            fleet_inventory,
+            resource_usage,
+            health_checks,
         };
 
         if let Some(tc) = trust_options.trust_context_config {
             debug!("configuring trust context");
             s.configure_trust_context(&tc).await?;
+            if general_options.members_replication {
+                s.start_members_replica(ctx, &tc).await?;
+            }
         }
 
         s.initialize_services(ctx, general_options.start_default_services)
             .await?;
+        s.start_fleet_service(ctx).await?;
         info!("created a node manager for the node: {}", s.node_name);
 
         Ok(s)
     }
 
-    async fn configure_trust_context(&mut self, tc: &TrustContextConfig) -> Result<()> {
-        self.trust_context = Some(
-            tc.to_trust_context(
+    async fn configure_trust_context(&self, tc: &TrustContextConfig) -> Result<()> {
+        let trust_context = tc
+            .to_trust_context(
                 self.secure_channels.clone(),
                 Some(self.tcp_transport.async_try_clone().await?),
+                self.authority_transitions.clone(),
             )
-            .await?,
-        );
+            .await?;
+        *self.trust_context.write().unwrap() = Some(trust_context);
 
         info!("NodeManager::configure_trust_context: trust context configured");
 
         Ok(())
     }
 
+    /// Replicate the members attributes of the trust context authority.
+    /// This is only possible when the node retrieves its credential from the authority node
+    async fn start_members_replica(&self, ctx: &Context, tc: &TrustContextConfig) -> Result<()> {
+        let authority_multiaddr = match tc.authority()?.own_credential()? {
+            CredentialRetrieverConfig::FromCredentialIssuer(issuer) => issuer.multiaddr.clone(),
+            _ => {
+                warn!("the members can only be replicated from an authority node");
+                return Ok(());
+            }
+        };
+        let authority = self.trust_context()?.authority()?.current_identifier();
+        let client = self
+            .make_secure_client(&authority, &authority_multiaddr, &self.identifier)
+            .await?;
+        MembersReplica::new(
+            client,
+            authority.clone(),
+            self.identifier.clone(),
+            self.secure_channels.identities(),
+        )
+        .start(ctx, MEMBERS_REPLICATION_INTERVAL)
+        .await?;
+
+        info!("replicating the members of the authority {authority}");
+        Ok(())
+    }
+
     async fn initialize_default_services(
         &self,
         ctx: &Context,
@@ -460,7 +902,7 @@ impl NodeManager {
         if let Ok(tc) = self.trust_context() {
             self.start_credentials_service_impl(
                 ctx,
-                tc.clone(),
+                tc,
                 DefaultAddress::CREDENTIALS_SERVICE.into(),
                 false,
             )
@@ -488,6 +930,7 @@ impl NodeManager {
             .add_consumer(DefaultAddress::ECHO_SERVICE, &api_flow_control_id);
         self.start_echoer_service_impl(ctx, DefaultAddress::ECHO_SERVICE.into())
             .await?;
+        self.start_ping_service(ctx).await?;
 
         Ok(())
     }
@@ -606,6 +1049,37 @@ impl NodeManagerWorker {
                     ))
                     .to_vec()?
             }
+            (Get, ["node", "memory"]) => Response::ok(req)
+                .body(NodeMemoryUsage::new(ctx.memory()))
+                .to_vec()?,
+            (Get, ["node", "resources"]) => Response::ok(req)
+                .body(NodeResources::new(self.node_manager.resource_profile()))
+                .to_vec()?,
+            (Get, ["node", "usage"]) => Response::ok(req)
+                .body(NodeResourceUsage::new(
+                    self.node_manager.resource_usage_samples().await?,
+                ))
+                .to_vec()?,
+            (Post, ["node", "drift"]) => encode_response(self.diff_against_config(req, dec).await)?,
+            (Put, ["node", "trust_context"]) => {
+                encode_response(self.update_trust_context(ctx, req, dec).await)?
+            }
+            (Get, ["node", "health_checks"]) => {
+                encode_response(self.list_health_checks(req).await)?
+            }
+            (Post, ["node", "ping"]) => {
+                encode_response(self.ping_node(ctx, req, dec.decode()?).await)?
+            }
+            (Get, ["node", "fleet"]) => encode_response(self.list_fleet_members(req).await)?,
+            (Get, ["node", "fleet", identifier]) => {
+                encode_response(self.get_fleet_member(req, identifier).await)?
+            }
+            (Post, ["node", "rollouts"]) => encode_response(self.create_rollout(req, dec).await)?,
+            (Get, ["node", "rollouts"]) => encode_response(self.list_rollouts(req).await)?,
+            (Get, ["node", "rollouts", id]) => encode_response(self.get_rollout(req, id).await)?,
+            (Post, ["node", "rollouts", id, "abort"]) => {
+                encode_response(self.abort_rollout(req, id).await)?
+            }
 
             // ==*== Tcp Connection ==*==
             (Get, ["node", "tcp", "connection"]) => self.get_tcp_connections(req).await.to_vec()?,
@@ -636,6 +1110,9 @@ impl NodeManagerWorker {
                 .get_credential(req, dec, ctx)
                 .await?
                 .either(Response::to_vec, Response::to_vec)?,
+            (Post, ["node", "credentials", "actions", "issue_token"]) => {
+                encode_response(self.issue_bearer_token(req, dec).await)?
+            }
             (Post, ["node", "credentials", "actions", "present"]) => {
                 encode_response(self.present_credential(req, dec, ctx).await)?
             }
@@ -651,6 +1128,9 @@ impl NodeManagerWorker {
             (Delete, ["node", "secure_channel"]) => {
                 encode_response(self.delete_secure_channel(req, dec, ctx).await)?
             }
+            (Post, ["node", "secure_channel", "credentials"]) => {
+                encode_response(self.exchange_credentials(req, dec, ctx).await)?
+            }
             (Get, ["node", "show_secure_channel"]) => {
                 encode_response(self.show_secure_channel(req, dec).await)?
             }
@@ -678,6 +1158,9 @@ impl NodeManagerWorker {
             (Post, ["node", "services", DefaultAddress::HOP_SERVICE]) => {
                 encode_response(self.start_hop_service(ctx, req, dec).await)?
             }
+            (Post, ["node", "services", DefaultAddress::KV_STORE]) => {
+                encode_response(self.start_kv_store_service(ctx, req, dec).await)?
+            }
             (Post, ["node", "services", DefaultAddress::CREDENTIALS_SERVICE]) => {
                 encode_response(self.start_credentials_service(ctx, req, dec).await)?
             }
@@ -741,9 +1224,27 @@ impl NodeManagerWorker {
             (Delete, ["node", "outlet", alias]) => {
                 encode_response(self.delete_outlet(req, alias).await)?
             }
+            (Put, ["node", "services", service, "target"]) => encode_response(
+                self.switch_service_target(req, service, dec.decode()?)
+                    .await,
+            )?,
             (Delete, ["node", "inlet", alias]) => {
                 encode_response(self.delete_inlet(req, alias).await)?
             }
+            (Post, ["node", "inlet", alias, "tap"]) => encode_response(
+                self.start_portal_tap(req, PortalSide::Inlet, alias, dec.decode()?)
+                    .await,
+            )?,
+            (Post, ["node", "outlet", alias, "tap"]) => encode_response(
+                self.start_portal_tap(req, PortalSide::Outlet, alias, dec.decode()?)
+                    .await,
+            )?,
+            (Delete, ["node", "inlet", alias, "tap"]) => {
+                encode_response(self.stop_portal_tap(req, PortalSide::Inlet, alias).await)?
+            }
+            (Delete, ["node", "outlet", alias, "tap"]) => {
+                encode_response(self.stop_portal_tap(req, PortalSide::Outlet, alias).await)?
+            }
             (Delete, ["node", "portal"]) => todo!(),
 
             // ==*== Flow Controls ==*==
@@ -778,6 +1279,10 @@ impl NodeManagerWorker {
             (Delete, ["policy", resource, action]) => {
                 encode_response(self.node_manager.del_policy(req, resource, action).await)?
             }
+            (Get, ["policies"]) => encode_response(self.node_manager.export_policies(req).await)?,
+            (Post, ["policies"]) => {
+                encode_response(self.node_manager.import_policies(req, dec).await)?
+            }
 
             // ==*== Messages ==*==
             (Post, ["v0", "message"]) => self.send_message(ctx, req, dec).await?,
@@ -812,8 +1317,82 @@ impl Worker for NodeManagerWorker {
             }
         };
 
+        if !self
+            .node_manager
+            .is_authenticated(ctx, &req, &msg.return_route())
+        {
+            warn!(path = %req.path(), "rejecting a request without a valid API token");
+            let r = Response::unauthorized(req.id()).to_vec()?;
+            return ctx.send(msg.return_route(), r).await;
+        }
+
+        // the remote administrators can only send the requests allowed by their role
+        let caller = IdentitySecureChannelLocalInfo::find_info(msg.local_message())
+            .ok()
+            .map(|info| info.their_identity_id());
+        if let Some(identifier) = caller.clone() {
+            match self
+                .node_manager
+                .is_authorized_by_role(&identifier, &req)
+                .await
+            {
+                Ok(true) => (),
+                Ok(false) => {
+                    warn!(%identifier, path = %req.path(), "rejecting a request not allowed by the role");
+                    let r = Response::forbidden(&req, "the request is not allowed by your role")
+                        .to_vec()?;
+                    return ctx.send(msg.return_route(), r).await;
+                }
+                Err(e) => {
+                    let r = Response::internal_error(
+                        &req,
+                        &format!("the role of {identifier} could not be checked: {e}"),
+                    )
+                    .to_vec()?;
+                    return ctx.send(msg.return_route(), r).await;
+                }
+            }
+        }
+
+        // a retried request gets the response of the original request
+        let body = &msg.as_body()[dec.position()..];
+        match self
+            .node_manager
+            .idempotency_keys()
+            .response(&req, body, caller.as_ref())
+            .await
+        {
+            Ok(Some(r)) => return ctx.send(msg.return_route(), r).await,
+            Ok(None) => (),
+            Err(e) => warn!(
+                "the idempotency key of {} could not be read: {e}",
+                req.path()
+            ),
+        }
+
         let r = match self.handle_request(ctx, &req, &mut dec).await {
-            Ok(r) => r,
+            Ok(r) => {
+                if let Err(e) = self
+                    .node_manager
+                    .idempotency_keys()
+                    .keep(&req, body, caller.as_ref(), &r)
+                    .await
+                {
+                    warn!(
+                        "the response to {} could not be kept for its idempotency key: {e}",
+                        req.path()
+                    );
+                }
+                if let Some(runtime_state) = self.node_manager.runtime_state() {
+                    if let Err(e) = runtime_state.record(&req, msg.as_body(), &r).await {
+                        warn!(
+                            "the request {} could not be kept for a warm start: {e}",
+                            req.path()
+                        );
+                    }
+                }
+                r
+            }
             Err(err) => {
                 error! {
                     target: TARGET,
@@ -828,6 +1407,11 @@ impl Worker for NodeManagerWorker {
                     .to_vec()?
             }
         };
+        if let Some(recorder) = self.node_manager.api_recorder() {
+            if let Err(e) = recorder.record(&req, msg.as_body(), &r) {
+                warn!("the request {} could not be recorded: {e}", req.path());
+            }
+        }
         debug! {
             target: TARGET,
             re     = %req.id(),
@@ -838,3 +1422,75 @@ impl Worker for NodeManagerWorker {
         ctx.send(msg.return_route(), r).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::start_manager_with_api_token_for_tests;
+    use ockam::identity::{SecureChannelListenerOptions, SecureChannelOptions};
+    use ockam::{TcpConnectionOptions, TcpListenerOptions};
+    use ockam_core::api::{Request, Status};
+    use ockam_core::route;
+    use ockam_node::api::Client;
+
+    #[ockam_macros::test]
+    async fn test_api_token(context: &mut Context) -> Result<()> {
+        let options = TcpListenerOptions::new();
+        let handler = start_manager_with_api_token_for_tests(
+            context,
+            options.spawner_flow_control_id(),
+            Some("token".to_string()),
+        )
+        .await?;
+        let listener = handler.tcp.listen("127.0.0.1:0", options).await?;
+        context
+            .flow_controls()
+            .add_consumer(NODEMANAGER_ADDR, listener.flow_control_id());
+        let connection = handler
+            .tcp
+            .connect(listener.socket_string(), TcpConnectionOptions::new())
+            .await?;
+        let route = route![connection.sender_address().clone(), NODEMANAGER_ADDR];
+
+        // the requests received by the API transport must have the token of the node
+        let client = Client::new(&route, None);
+        assert_eq!(status(context, &client).await?, Some(Status::Unauthorized));
+        let client = Client::new(&route, None).with_api_token(Some("other".to_string()));
+        assert_eq!(status(context, &client).await?, Some(Status::Unauthorized));
+        let client = Client::new(&route, None).with_api_token(Some("token".to_string()));
+        assert_eq!(status(context, &client).await?, Some(Status::Ok));
+
+        // the requests received by a secure channel don't need it
+        let options = SecureChannelListenerOptions::new();
+        context
+            .flow_controls()
+            .add_consumer(NODEMANAGER_ADDR, &options.spawner_flow_control_id());
+        handler
+            .secure_channels
+            .create_secure_channel_listener(
+                context,
+                handler.node_manager.identifier(),
+                "local_api",
+                options,
+            )
+            .await?;
+        let channel = handler
+            .secure_channels
+            .create_secure_channel(
+                context,
+                handler.node_manager.identifier(),
+                "local_api",
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        let client = Client::new(&route![channel, NODEMANAGER_ADDR], None);
+        assert_eq!(status(context, &client).await?, Some(Status::Ok));
+
+        context.stop().await
+    }
+
+    async fn status(context: &Context, client: &Client) -> Result<Option<Status>> {
+        let bytes = client.request(context, Request::get("/node/inlet")).await?;
+        Ok(Response::parse_response_header(&bytes)?.0.status())
+    }
+}
@@ -155,6 +155,7 @@ impl BackgroundNode {
         timeout: Option<Duration>,
     ) -> miette::Result<Client> {
         let route = self.create_route().await?;
-        Ok(Client::new(&route, timeout))
+        let api_token = self.cli_state.nodes.get(&self.node_name)?.api_token()?;
+        Ok(Client::new(&route, timeout).with_api_token(api_token))
     }
 }
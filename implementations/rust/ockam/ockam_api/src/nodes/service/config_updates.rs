@@ -0,0 +1,253 @@
+//! Application of a declarative configuration to a node, see [`crate::config_updates`].
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use ockam::identity::Identifier;
+use ockam::{Address, Result};
+use ockam_abac::{Action, Expr, Resource};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use super::drift::NodeDeclaration;
+use super::NodeManager;
+use crate::cli_state::StateDirTrait;
+use crate::config_updates::{
+    AppliedConfigUpdate, ConfigUpdate, ConfigUpdateClient, ConfigUpdateReport, SignedConfigUpdate,
+};
+use crate::nodes::models::drift::{Drift, DriftResourceKind};
+use crate::nodes::registry::OutletInfo;
+
+/// Change made to a resource of the node, with the value needed to undo it
+enum AppliedChange {
+    Outlet {
+        alias: String,
+        created: bool,
+        previous: Option<OutletInfo>,
+    },
+    Policy {
+        resource: Resource,
+        action: Action,
+        previous: Option<Expr>,
+    },
+}
+
+impl NodeManager {
+    /// Sign a configuration update with the identity of this node
+    pub async fn sign_config_update(&self, update: ConfigUpdate) -> Result<SignedConfigUpdate> {
+        update.sign(&self.identities(), self.identifier()).await
+    }
+
+    /// Send a signed configuration update to the node having the given identity
+    pub async fn push_config_update(
+        &self,
+        ctx: &Context,
+        identifier: &Identifier,
+        multiaddr: &MultiAddr,
+        update: &SignedConfigUpdate,
+    ) -> Result<ConfigUpdateReport> {
+        let client = self
+            .make_secure_client(identifier, multiaddr, self.identifier())
+            .await?;
+        ConfigUpdateClient::new(client).push(ctx, update).await
+    }
+
+    /// Return the last configuration update applied to the node, if any
+    pub fn applied_config_update(&self) -> Result<Option<AppliedConfigUpdate>> {
+        AppliedConfigUpdate::read(&self.applied_config_update_path()?)
+    }
+
+    fn applied_config_update_path(&self) -> Result<PathBuf> {
+        Ok(self.cli_state.nodes.get(self.node_name())?.config_update())
+    }
+
+    /// Apply a verified configuration update and keep its version.
+    ///
+    /// Return `None` if the version of the update is not greater than the applied version
+    pub async fn apply_config_update(
+        &self,
+        ctx: &Context,
+        signed: &SignedConfigUpdate,
+        declaration: &NodeDeclaration,
+    ) -> Result<Option<ConfigUpdateReport>> {
+        let path = self.applied_config_update_path()?;
+        let applied = AppliedConfigUpdate::read(&path)?;
+        if !signed.update.is_newer_than(applied.as_ref()) {
+            return Ok(None);
+        }
+        let applied = AppliedConfigUpdate::new(signed);
+        let changes = self
+            .apply_declaration(ctx, declaration, || applied.write(&path))
+            .await?;
+        info!(version = applied.version, "Applied a configuration update");
+        Ok(Some(ConfigUpdateReport {
+            version: applied.version,
+            changes,
+        }))
+    }
+
+    /// Apply the outlets and policies of a configuration to the node, then call `commit`.
+    ///
+    /// The outlets are created at a worker address named after their alias. The inlets and
+    /// relays of the configuration are not applied since they need a route to another node.
+    /// If a change or the commit fails, the changes already made are undone in reverse order
+    pub async fn apply_declaration(
+        &self,
+        ctx: &Context,
+        declaration: &NodeDeclaration,
+        commit: impl FnOnce() -> Result<()>,
+    ) -> Result<Vec<Drift>> {
+        let report = self.diff_against(declaration).await?;
+        let mut changes = vec![];
+        let mut applied = vec![];
+        for drift in report.drifts {
+            let result = match drift.kind {
+                DriftResourceKind::Outlet => self.apply_outlet(ctx, &drift).await,
+                DriftResourceKind::Policy => self.apply_policy(&drift).await,
+                DriftResourceKind::Inlet | DriftResourceKind::Relay => continue,
+            };
+            match result {
+                Ok(change) => {
+                    applied.push(change);
+                    changes.push(drift);
+                }
+                Err(e) => {
+                    warn!(%e, name = %drift.name, "Failed to apply the configuration, rolling back");
+                    self.rollback(ctx, applied).await;
+                    return Err(e);
+                }
+            }
+        }
+        if let Err(e) = commit() {
+            warn!(%e, "Failed to commit the configuration, rolling back");
+            self.rollback(ctx, applied).await;
+            return Err(e);
+        }
+        Ok(changes)
+    }
+
+    async fn apply_outlet(&self, ctx: &Context, drift: &Drift) -> Result<AppliedChange> {
+        let socket_addr = drift
+            .declared
+            .as_deref()
+            .map(|to| {
+                SocketAddr::from_str(to).map_err(|e| {
+                    ockam_core::Error::new(
+                        Origin::Node,
+                        Kind::Invalid,
+                        format!("the outlet {} can't connect to {to}: {e}", drift.name),
+                    )
+                })
+            })
+            .transpose()?;
+        let previous = if drift.live.is_some() {
+            self.delete_outlet(&drift.name).await?
+        } else {
+            None
+        };
+        let mut change = AppliedChange::Outlet {
+            alias: drift.name.clone(),
+            created: false,
+            previous,
+        };
+        if let Some(socket_addr) = socket_addr {
+            if let Err(e) = self
+                .create_outlet(
+                    ctx,
+                    socket_addr,
+                    Address::from_string(&drift.name),
+                    Some(drift.name.clone()),
+                    true,
+                )
+                .await
+            {
+                // the deleted outlet is created again
+                self.undo(ctx, change).await;
+                return Err(e);
+            }
+            if let AppliedChange::Outlet { created, .. } = &mut change {
+                *created = true;
+            }
+        }
+        Ok(change)
+    }
+
+    async fn apply_policy(&self, drift: &Drift) -> Result<AppliedChange> {
+        let (resource, action) = drift.name.split_once('/').ok_or_else(|| {
+            ockam_core::Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                format!("invalid policy name {}", drift.name),
+            )
+        })?;
+        let resource = Resource::new(resource);
+        let action = Action::new(action);
+        let previous = self.policies.get_policy(&resource, &action).await?;
+        match drift.declared.as_deref() {
+            Some(expression) => {
+                let expression = Expr::try_from(expression)?;
+                self.policies
+                    .set_policy(&resource, &action, &expression)
+                    .await?
+            }
+            None => self.policies.del_policy(&resource, &action).await?,
+        }
+        Ok(AppliedChange::Policy {
+            resource,
+            action,
+            previous,
+        })
+    }
+
+    async fn rollback(&self, ctx: &Context, applied: Vec<AppliedChange>) {
+        for change in applied.into_iter().rev() {
+            self.undo(ctx, change).await
+        }
+    }
+
+    async fn undo(&self, ctx: &Context, change: AppliedChange) {
+        let result = match change {
+            AppliedChange::Outlet {
+                alias,
+                created,
+                previous,
+            } => {
+                if created {
+                    if let Err(e) = self.delete_outlet(&alias).await {
+                        warn!(%e, %alias, "Failed to delete an outlet during a rollback");
+                    }
+                }
+                match previous {
+                    Some(outlet) => self
+                        .create_outlet(
+                            ctx,
+                            outlet.socket_addr,
+                            outlet.worker_addr,
+                            Some(alias),
+                            true,
+                        )
+                        .await
+                        .map(|_| ()),
+                    None => Ok(()),
+                }
+            }
+            AppliedChange::Policy {
+                resource,
+                action,
+                previous,
+            } => match previous {
+                Some(expression) => {
+                    self.policies
+                        .set_policy(&resource, &action, &expression)
+                        .await
+                }
+                None => self.policies.del_policy(&resource, &action).await,
+            },
+        };
+        if let Err(e) = result {
+            warn!(%e, "Failed to undo a change during a rollback");
+        }
+    }
+}
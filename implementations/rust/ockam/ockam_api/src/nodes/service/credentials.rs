@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::Duration;
 
 use either::Either;
 use miette::IntoDiagnostic;
@@ -11,11 +12,14 @@ use ockam_core::async_trait;
 use ockam_multiaddr::MultiAddr;
 use ockam_node::Context;
 
+use crate::bearer_token::{BearerTokenIssuer, DEFAULT_BEARER_TOKEN_VALIDITY};
 use crate::cli_state::traits::StateDirTrait;
 use crate::cloud::AuthorityNode;
 use crate::error::ApiError;
 use crate::local_multiaddr_to_route;
-use crate::nodes::models::credentials::{GetCredentialRequest, PresentCredentialRequest};
+use crate::nodes::models::credentials::{
+    BearerTokenResponse, GetCredentialRequest, IssueBearerTokenRequest, PresentCredentialRequest,
+};
 use crate::nodes::BackgroundNode;
 
 use super::NodeManagerWorker;
@@ -197,4 +201,40 @@ impl NodeManagerWorker {
         let response = Response::ok(req);
         Ok(response)
     }
+
+    /// Issue a bearer token signed by the identity of the node
+    pub(super) async fn issue_bearer_token(
+        &self,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response<BearerTokenResponse>, Response<Error>> {
+        let request: IssueBearerTokenRequest = dec.decode()?;
+        let identities = self.node_manager.identities();
+        if let Some(subject_identity) = &request.subject_identity {
+            let change_history = hex::decode(subject_identity).map_err(|_| {
+                Response::bad_request(req, "The identity of the subject can't be decoded")
+            })?;
+            identities
+                .identities_creation()
+                .import(Some(&request.subject), &change_history)
+                .await?;
+        }
+        let ttl = request
+            .ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_BEARER_TOKEN_VALIDITY);
+        let token = BearerTokenIssuer::new(identities, self.node_manager.identifier())
+            .issue(&request.subject, &request.attributes, ttl)
+            .await?;
+        let claims = self
+            .node_manager
+            .bearer_token_verifier()
+            .verify(&token)
+            .await?;
+        Ok(Response::ok(req).body(BearerTokenResponse {
+            token: token.hex_encoded()?,
+            subject: claims.subject,
+            expires_at: claims.expires_at,
+        }))
+    }
 }
@@ -0,0 +1,261 @@
+//! Comparison of a node with a declarative configuration.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam_abac::{Expr, Resource};
+use ockam_core::api::{Error, RequestHeader, Response};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::Result;
+
+use super::{NodeManager, NodeManagerWorker};
+use crate::nodes::models::drift::{Drift, DriftReport, DriftResourceKind};
+
+/// Prefix of the remote address of the static relays
+const RELAY_PREFIX: &str = "forward_to_";
+
+/// Declarative configuration of the resources of a node
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NodeDeclaration {
+    pub inlets: Vec<DeclaredInlet>,
+    pub outlets: Vec<DeclaredOutlet>,
+    pub relays: Vec<DeclaredRelay>,
+    pub policies: Vec<DeclaredPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeclaredInlet {
+    pub alias: String,
+    /// Address the inlet listens to
+    pub from: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeclaredOutlet {
+    pub alias: String,
+    /// Address the outlet connects to
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeclaredRelay {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DeclaredPolicy {
+    pub resource: String,
+    pub action: String,
+    pub expression: String,
+}
+
+impl NodeDeclaration {
+    /// Read a configuration file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Io, e))?;
+        Self::parse(&contents)
+    }
+
+    /// Parse a YAML configuration
+    pub fn parse(contents: &str) -> Result<Self> {
+        serde_yaml::from_str(contents)
+            .map_err(|e| ockam_core::Error::new(Origin::Api, Kind::Invalid, e))
+    }
+
+    /// Return the declared resources, by kind and name
+    fn resources(&self) -> Result<Resources> {
+        let mut resources = Resources::default();
+        for inlet in &self.inlets {
+            resources.insert(DriftResourceKind::Inlet, &inlet.alias, inlet.from.clone());
+        }
+        for outlet in &self.outlets {
+            resources.insert(DriftResourceKind::Outlet, &outlet.alias, outlet.to.clone());
+        }
+        for relay in &self.relays {
+            resources.insert(DriftResourceKind::Relay, &relay.name, relay.name.clone());
+        }
+        for policy in &self.policies {
+            // the expression is parsed to be compared with the stored expressions
+            let expression = Expr::try_from(policy.expression.as_str())?;
+            resources.insert(
+                DriftResourceKind::Policy,
+                &policy_name(&policy.resource, &policy.action),
+                expression.to_string(),
+            );
+        }
+        Ok(resources)
+    }
+}
+
+/// Values of some resources, by kind and name
+#[derive(Debug, Default)]
+struct Resources(BTreeMap<(DriftResourceKind, String), String>);
+
+impl Resources {
+    fn insert(&mut self, kind: DriftResourceKind, name: &str, value: String) {
+        self.0.insert((kind, name.to_string()), value);
+    }
+
+    /// Return the differences between declared and live resources
+    fn diff(declared: &Resources, live: &Resources) -> DriftReport {
+        let mut drifts = vec![];
+        for ((kind, name), declared_value) in &declared.0 {
+            match live.0.get(&(*kind, name.clone())) {
+                Some(live_value) if live_value == declared_value => (),
+                live_value => drifts.push(Drift {
+                    kind: *kind,
+                    name: name.clone(),
+                    declared: Some(declared_value.clone()),
+                    live: live_value.cloned(),
+                }),
+            }
+        }
+        for ((kind, name), live_value) in &live.0 {
+            if !declared.0.contains_key(&(*kind, name.clone())) {
+                drifts.push(Drift {
+                    kind: *kind,
+                    name: name.clone(),
+                    declared: None,
+                    live: Some(live_value.clone()),
+                })
+            }
+        }
+        DriftReport { drifts }
+    }
+}
+
+fn policy_name(resource: &str, action: &str) -> String {
+    format!("{resource}/{action}")
+}
+
+/// Request body to compare a node with a declarative configuration
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct DriftRequest {
+    /// YAML configuration
+    #[n(1)] pub config: String,
+}
+
+impl NodeManager {
+    /// Compare the live resources of the node with a declarative configuration file
+    pub async fn diff_against_config(&self, path: &Path) -> Result<DriftReport> {
+        self.diff_against(&NodeDeclaration::read(path)?).await
+    }
+
+    /// Compare the live resources of the node with a declarative configuration
+    pub async fn diff_against(&self, declaration: &NodeDeclaration) -> Result<DriftReport> {
+        let declared = declaration.resources()?;
+        let live = self.live_resources(declaration).await?;
+        Ok(Resources::diff(&declared, &live))
+    }
+
+    async fn live_resources(&self, declaration: &NodeDeclaration) -> Result<Resources> {
+        let mut resources = Resources::default();
+        for (alias, inlet) in self.registry.inlets.entries().await {
+            resources.insert(DriftResourceKind::Inlet, &alias, inlet.bind_addr);
+        }
+        for (alias, outlet) in self.registry.outlets.entries().await {
+            resources.insert(
+                DriftResourceKind::Outlet,
+                &alias,
+                outlet.socket_addr.to_string(),
+            );
+        }
+        for remote_address in self.registry.relays.keys().await {
+            let name = remote_address
+                .strip_prefix(RELAY_PREFIX)
+                .unwrap_or(&remote_address);
+            resources.insert(DriftResourceKind::Relay, name, name.to_string());
+        }
+        let mut declared_resources: Vec<&str> = declaration
+            .policies
+            .iter()
+            .map(|p| p.resource.as_str())
+            .collect();
+        declared_resources.dedup();
+        for resource in declared_resources {
+            for (action, expression) in self.policies.policies(&Resource::new(resource)).await? {
+                resources.insert(
+                    DriftResourceKind::Policy,
+                    &policy_name(resource, action.as_str()),
+                    expression.to_string(),
+                );
+            }
+        }
+        Ok(resources)
+    }
+}
+
+impl NodeManagerWorker {
+    pub(super) async fn diff_against_config(
+        &self,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response<DriftReport>, Response<Error>> {
+        let request: DriftRequest = dec.decode()?;
+        let declaration = NodeDeclaration::parse(&request.config)
+            .map_err(|e| Response::bad_request(req, &e.to_string()))?;
+        let report = self.node_manager.diff_against(&declaration).await?;
+        Ok(Response::ok(req).body(report))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drift_report() -> Result<()> {
+        let declaration = NodeDeclaration::parse(
+            r#"
+inlets:
+  - alias: web
+    from: 127.0.0.1:8080
+outlets:
+  - alias: db
+    to: 127.0.0.1:5432
+relays:
+  - name: web
+policies:
+  - resource: tcp-outlet
+    action: handle_message
+    expression: (= subject.component "web")
+"#,
+        )?;
+        let declared = declaration.resources()?;
+
+        let mut live = Resources::default();
+        live.insert(DriftResourceKind::Inlet, "web", "127.0.0.1:9090".into());
+        live.insert(DriftResourceKind::Outlet, "db", "127.0.0.1:5432".into());
+        live.insert(DriftResourceKind::Outlet, "cache", "127.0.0.1:6379".into());
+        live.insert(
+            DriftResourceKind::Policy,
+            "tcp-outlet/handle_message",
+            Expr::try_from(r#"(= subject.component "web")"#)?.to_string(),
+        );
+
+        let report = Resources::diff(&declared, &live);
+        assert_eq!(report.drifts.len(), 3);
+        let changed = &report.drifts[0];
+        assert!(changed.is_changed());
+        assert_eq!(changed.name, "web");
+        assert_eq!(changed.live, Some("127.0.0.1:9090".to_string()));
+        assert!(report.drifts[1].is_missing());
+        assert_eq!(report.drifts[1].kind, DriftResourceKind::Relay);
+        assert!(report.drifts[2].is_unexpected());
+        assert_eq!(report.drifts[2].name, "cache");
+
+        assert!(NodeDeclaration::parse("unknown: []").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+//! Heartbeats of a node and fleet inventory of a control node, see [`crate::fleet`].
+
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ockam::identity::Identifier;
+use ockam_core::api::{Error, Request, RequestHeader, Response};
+use ockam_core::Result;
+use ockam_node::Context;
+
+use super::{NodeManager, NodeManagerWorker};
+use crate::fleet::{
+    FleetInventory, FleetMember, FleetService, Heartbeat, HeartbeatConfig, HeartbeatReply,
+};
+use crate::rollout::RolloutOrchestrator;
+use crate::DefaultAddress;
+
+impl NodeManager {
+    /// Return the inventory of the nodes reporting to this node, if it is a control node
+    pub fn fleet_inventory(&self) -> Option<&FleetInventory> {
+        self.fleet_inventory.as_ref()
+    }
+
+    pub(super) async fn start_fleet_service(&self, ctx: &Context) -> Result<()> {
+        if let Some(inventory) = &self.fleet_inventory {
+            FleetService::start(ctx, self, inventory.clone()).await?;
+            RolloutOrchestrator::start(inventory.clone());
+        }
+        Ok(())
+    }
+
+    /// Return the current state of the node
+    pub async fn heartbeat(
+        &self,
+        ctx: &Context,
+        config: &HeartbeatConfig,
+        uptime: Duration,
+    ) -> Heartbeat {
+        Heartbeat {
+            node_name: self.node_name(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: uptime.as_secs(),
+            sent_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            memory: ctx
+                .memory()
+                .trackers()
+                .into_iter()
+                .map(|(name, tracker)| (name, tracker.used() as u64))
+                .collect(),
+            secure_channels: self.registry.secure_channels.list().await.len() as u64,
+            inlets: self.registry.inlets.keys().await,
+            outlets: self.registry.outlets.keys().await,
+            relays: self.registry.relays.keys().await,
+            config_version: self
+                .applied_config_update()
+                .ok()
+                .flatten()
+                .map(|applied| applied.version),
+            labels: config.labels.clone(),
+        }
+    }
+
+    /// Sign the current state of the node and send it to a control node.
+    ///
+    /// The configuration update returned by the control node, if any, is then applied
+    pub(crate) async fn send_heartbeat(
+        &self,
+        ctx: &Context,
+        config: &HeartbeatConfig,
+        uptime: Duration,
+    ) -> Result<()> {
+        let heartbeat = self
+            .heartbeat(ctx, config, uptime)
+            .await
+            .sign(&self.identities(), self.identifier())
+            .await?;
+        let client = self
+            .make_secure_client(&config.control, &config.route, self.identifier())
+            .await?;
+        let reply: HeartbeatReply = client
+            .ask(
+                ctx,
+                DefaultAddress::FLEET,
+                Request::post("/heartbeats").body(heartbeat),
+            )
+            .await?
+            .success()?;
+        if let Some(signed) = reply.update {
+            let declaration = signed.verify(&self.identities(), &config.control).await?;
+            self.apply_config_update(ctx, &signed, &declaration).await?;
+        }
+        Ok(())
+    }
+}
+
+impl NodeManagerWorker {
+    pub(super) fn fleet_inventory(
+        &self,
+        req: &RequestHeader,
+    ) -> Result<&FleetInventory, Response<Error>> {
+        self.node_manager
+            .fleet_inventory()
+            .ok_or_else(|| Response::bad_request(req, "The node doesn't keep a fleet inventory"))
+    }
+
+    pub(super) async fn list_fleet_members(
+        &self,
+        req: &RequestHeader,
+    ) -> Result<Response<Vec<FleetMember>>, Response<Error>> {
+        let members = self.fleet_inventory(req)?.members().await?;
+        Ok(Response::ok(req).body(members))
+    }
+
+    pub(super) async fn get_fleet_member(
+        &self,
+        req: &RequestHeader,
+        identifier: &str,
+    ) -> Result<Response<FleetMember>, Response<Error>> {
+        let identifier = Identifier::from_str(identifier)
+            .map_err(|e| Response::bad_request(req, &e.to_string()))?;
+        match self.fleet_inventory(req)?.member(&identifier).await? {
+            Some(member) => Ok(Response::ok(req).body(member)),
+            None => Err(Response::not_found(
+                req,
+                &format!("No heartbeat was received from {identifier}"),
+            )),
+        }
+    }
+}
@@ -81,11 +81,8 @@ impl MessageSender for NodeManager {
         let route = connection.route(self.tcp_transport()).await?;
 
         trace!(target: TARGET, route = %route, msg_l = %msg_length, "sending message");
-        let options = if let Some(timeout) = timeout {
-            MessageSendReceiveOptions::new().with_timeout(timeout)
-        } else {
-            MessageSendReceiveOptions::new()
-        };
+        let options = MessageSendReceiveOptions::new()
+            .with_timeout(timeout.unwrap_or(self.timeouts().message_send()));
         Ok(ctx
             .send_and_receive_extended::<Vec<u8>>(route, message, options)
             .await?
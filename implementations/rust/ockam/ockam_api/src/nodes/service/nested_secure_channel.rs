@@ -0,0 +1,262 @@
+//! Creation of secure channels established inside other secure channels.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use ockam::identity::models::CredentialAndPurposeKey;
+use ockam::identity::{Identifier, SecureChannel};
+use ockam::{Address, Result, Route};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::AsyncTryClone;
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use crate::cli_state::traits::StateDirTrait;
+use crate::cli_state::StateItemTrait;
+use crate::local_multiaddr_to_route;
+use crate::nodes::NodeManager;
+
+/// Credential presented by the local identity when establishing one layer of a nested secure channel
+#[derive(Debug, Clone, Default)]
+pub enum HopCredential {
+    /// Present the credential issued by the trust context of the node, if there is one
+    #[default]
+    TrustContext,
+    /// Present the credential stored under this name
+    Named(String),
+    /// Present this credential
+    Explicit(CredentialAndPurposeKey),
+    /// Don't present any credential
+    None,
+}
+
+/// One layer of a nested secure channel
+#[derive(Debug, Clone)]
+pub struct SecureChannelHop {
+    address: MultiAddr,
+    authorized_identifiers: Option<Vec<Identifier>>,
+    credential: HopCredential,
+}
+
+impl SecureChannelHop {
+    /// Create a hop to the secure channel listener at `address`.
+    ///
+    /// The address of the first hop is resolved like any other node address, for example
+    /// `/dnsaddr/localhost/tcp/4000/service/api`. The address of the next hops is a
+    /// local route, relative to the previous channel, for example `/service/forward_to_n1/service/api`
+    pub fn new(address: MultiAddr) -> Self {
+        Self {
+            address,
+            authorized_identifiers: None,
+            credential: HopCredential::default(),
+        }
+    }
+
+    /// Only accept the given identifiers at the other end of this layer
+    pub fn with_authorized_identifiers(mut self, identifiers: Vec<Identifier>) -> Self {
+        self.authorized_identifiers = Some(identifiers);
+        self
+    }
+
+    /// Set the credential presented when establishing this layer
+    pub fn with_credential(mut self, credential: HopCredential) -> Self {
+        self.credential = credential;
+        self
+    }
+
+    /// Address of the secure channel listener for this layer
+    pub fn address(&self) -> &MultiAddr {
+        &self.address
+    }
+
+    /// Credential presented when establishing this layer
+    pub fn credential(&self) -> &HopCredential {
+        &self.credential
+    }
+}
+
+/// Result of the creation of a nested secure channel.
+/// The channels are ordered from the outermost to the innermost one
+#[derive(Debug, Clone)]
+pub struct NestedSecureChannel {
+    channels: Vec<SecureChannel>,
+}
+
+impl NestedSecureChannel {
+    /// The innermost channel, to be used to send messages to the final target
+    pub fn innermost(&self) -> &SecureChannel {
+        // there is at least one channel, see `create_nested_secure_channel`
+        &self.channels[self.channels.len() - 1]
+    }
+
+    /// All the channels, from the outermost to the innermost one
+    pub fn channels(&self) -> &[SecureChannel] {
+        &self.channels
+    }
+
+    /// Encryptor address of the innermost channel
+    pub fn encryptor_address(&self) -> &Address {
+        self.innermost().encryptor_address()
+    }
+}
+
+impl NodeManager {
+    /// Create a secure channel for each hop, each channel being created inside the previous one
+    pub async fn create_nested_secure_channel(
+        &self,
+        ctx: &Context,
+        hops: Vec<SecureChannelHop>,
+        identity_name: Option<String>,
+        timeout: Option<Duration>,
+    ) -> Result<NestedSecureChannel> {
+        if hops.is_empty() {
+            return Err(ockam_core::Error::new(
+                Origin::Api,
+                Kind::Invalid,
+                "a nested secure channel needs at least one hop",
+            ));
+        }
+        let identifier = self.get_identifier(identity_name).await?;
+
+        let mut channels: Vec<SecureChannel> = vec![];
+        let mut trust_context_credential = None;
+        for (index, hop) in hops.into_iter().enumerate() {
+            let result = self
+                .create_secure_channel_hop(
+                    ctx,
+                    channels.last(),
+                    &identifier,
+                    hop,
+                    &mut trust_context_credential,
+                    timeout,
+                )
+                .await;
+            match result {
+                Ok(channel) => channels.push(channel),
+                Err(e) => {
+                    warn!(%e, layer = index, "a nested secure channel could not be created");
+                    for channel in channels.iter().rev() {
+                        let _ = self
+                            .delete_secure_channel(ctx, channel.encryptor_address())
+                            .await;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(NestedSecureChannel { channels })
+    }
+
+    async fn create_secure_channel_hop(
+        &self,
+        ctx: &Context,
+        previous: Option<&SecureChannel>,
+        identifier: &Identifier,
+        hop: SecureChannelHop,
+        trust_context_credential: &mut Option<Option<CredentialAndPurposeKey>>,
+        timeout: Option<Duration>,
+    ) -> Result<SecureChannel> {
+        let credential = match hop.credential {
+            HopCredential::TrustContext => match trust_context_credential {
+                Some(credential) => credential.clone(),
+                None => {
+                    let credential = self.get_credential(ctx, identifier, None, timeout).await?;
+                    *trust_context_credential = Some(credential.clone());
+                    credential
+                }
+            },
+            HopCredential::Named(name) => Some(
+                self.cli_state
+                    .credentials
+                    .get(name)?
+                    .config()
+                    .credential()?,
+            ),
+            HopCredential::Explicit(credential) => Some(credential),
+            HopCredential::None => None,
+        };
+
+        let route = match previous {
+            // the first layer is established over a regular connection
+            None => {
+                let connection_ctx = Arc::new(ctx.async_try_clone().await?);
+                let connection = self
+                    .make_connection(
+                        connection_ctx,
+                        &hop.address,
+                        Some(identifier.clone()),
+                        None,
+                        credential.clone(),
+                        timeout,
+                    )
+                    .await?;
+                connection.route(self.tcp_transport()).await?
+            }
+            // the next layers are established inside the previous channel
+            Some(previous) => Route::new()
+                .append(previous.encryptor_address().clone())
+                .append_route(local_multiaddr_to_route(&hop.address)?)
+                .into(),
+        };
+
+        self.create_secure_channel_with_credential(
+            ctx,
+            route,
+            identifier,
+            hop.authorized_identifiers,
+            timeout,
+            credential,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::TestHarness;
+    use crate::DefaultAddress;
+    use ockam::route;
+
+    #[tokio::test]
+    async fn test_nested_secure_channel_with_one_hop() -> Result<()> {
+        let mut harness = TestHarness::start_with_authority(0).await?;
+        let client = harness.add_member(Default::default()).await?;
+        let server = harness.add_member(Default::default()).await?;
+
+        let hop = SecureChannelHop::new(harness.node(server).secure_channel_listener_multiaddr()?)
+            .with_authorized_identifiers(vec![harness.node(server).identifier().clone()]);
+        let node = harness.node(client);
+        let channel = node
+            .node()
+            .create_nested_secure_channel(node.context(), vec![hop], None, None)
+            .await?;
+        assert_eq!(channel.channels().len(), 1);
+
+        let response: String = node
+            .context()
+            .send_and_receive(
+                route![
+                    channel.encryptor_address().clone(),
+                    DefaultAddress::ECHO_SERVICE
+                ],
+                "hello".to_string(),
+            )
+            .await?;
+        assert_eq!(response, "hello");
+
+        harness.stop().await
+    }
+
+    #[tokio::test]
+    async fn test_nested_secure_channel_needs_a_hop() -> Result<()> {
+        let harness = TestHarness::start(1).await?;
+        let node = harness.node(0);
+        assert!(node
+            .node()
+            .create_nested_secure_channel(node.context(), vec![], None, None)
+            .await
+            .is_err());
+        harness.stop().await
+    }
+}
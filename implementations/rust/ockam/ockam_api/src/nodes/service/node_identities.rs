@@ -35,11 +35,7 @@ impl NodeIdentities {
         vault_name: Option<String>,
     ) -> Result<Arc<Identities>> {
         let vault = self.get_identities_vault(vault_name).await?;
-        let repository = self.cli_state.identities.identities_repository().await?;
-        Ok(Identities::builder()
-            .with_vault(vault)
-            .with_identities_repository(repository)
-            .build())
+        Ok(self.cli_state.identities_builder(vault).await?.build())
     }
 
     /// Return either the default vault or a specific one
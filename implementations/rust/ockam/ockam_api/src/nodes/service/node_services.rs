@@ -2,17 +2,20 @@ use std::net::IpAddr;
 
 use minicbor::Decoder;
 
+use ockam::identity::storage::EncryptedStorage;
 use ockam::identity::{identities, AuthorityService, TrustContext};
 use ockam::{Address, Context, Result};
 use ockam_abac::expr::{eq, ident, str};
 use ockam_abac::Resource;
 use ockam_core::api::{Error, RequestHeader, Response};
 use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::sync::Arc;
 use ockam_core::route;
 use ockam_multiaddr::MultiAddr;
 use ockam_node::WorkerBuilder;
 
 use crate::auth::Server;
+use crate::cli_state::StateDirTrait;
 use crate::echoer::Echoer;
 use crate::error::ApiError;
 use crate::hop::Hop;
@@ -21,11 +24,13 @@ use crate::kafka::{
     KAFKA_OUTLET_BOOTSTRAP_ADDRESS, KAFKA_OUTLET_INTERCEPTOR_ADDRESS,
 };
 use crate::kafka::{OutletManagerService, PrefixRelayService};
+use crate::kv_store::KvStore;
 use crate::nodes::models::services::{
     DeleteServiceRequest, ServiceList, ServiceStatus, StartAuthenticatedServiceRequest,
     StartCredentialsService, StartEchoerServiceRequest, StartHopServiceRequest,
     StartKafkaConsumerRequest, StartKafkaDirectRequest, StartKafkaOutletRequest,
-    StartKafkaProducerRequest, StartServiceRequest, StartUppercaseServiceRequest,
+    StartKafkaProducerRequest, StartKvStoreServiceRequest, StartServiceRequest,
+    StartUppercaseServiceRequest,
 };
 use crate::nodes::registry::{
     CredentialsServiceInfo, KafkaServiceInfo, KafkaServiceKind, Registry,
@@ -123,13 +128,13 @@ impl NodeManager {
             return Err(ApiError::core("Echoer service exists at this address"));
         }
 
-        let maybe_trust_context_id = self.trust_context.as_ref().map(|c| c.id());
+        let maybe_trust_context_id = self.trust_context().ok().map(|c| c.id().to_string());
         let resource = Resource::assert_inline(addr.address());
         let ac = self
             .access_control(
                 &resource,
                 &actions::HANDLE_MESSAGE,
-                maybe_trust_context_id,
+                maybe_trust_context_id.as_deref(),
                 None,
             )
             .await?;
@@ -165,6 +170,44 @@ impl NodeManager {
 
         Ok(())
     }
+
+    pub(super) async fn start_kv_store_service_impl(
+        &self,
+        ctx: &Context,
+        addr: Address,
+    ) -> Result<()> {
+        if self.registry.kv_store_services.contains_key(&addr).await {
+            return Err(ApiError::core(
+                "Key-value store service exists at this address",
+            ));
+        }
+
+        let node_state = self.cli_state.nodes.get(&self.node_name)?;
+        let storage = EncryptedStorage::create(
+            Arc::new(node_state.kv_store_storage().await?),
+            &node_state.kv_store_key()?,
+        )
+        .await?;
+
+        // the store is only reachable through the secure channels created by this node listeners
+        for listener in self.registry.secure_channel_listeners.values().await {
+            ctx.flow_controls()
+                .add_consumer(addr.clone(), listener.listener().flow_control_id());
+        }
+
+        ctx.start_worker(
+            addr.clone(),
+            KvStore::new(storage, self.attributes_reader()),
+        )
+        .await?;
+
+        self.registry
+            .kv_store_services
+            .insert(addr, Default::default())
+            .await;
+
+        Ok(())
+    }
 }
 
 impl NodeManagerWorker {
@@ -222,6 +265,20 @@ impl NodeManagerWorker {
         Ok(Response::ok(req))
     }
 
+    pub(super) async fn start_kv_store_service(
+        &self,
+        ctx: &Context,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response, Response<Error>> {
+        let req_body: StartKvStoreServiceRequest = dec.decode()?;
+        let addr = req_body.addr.to_string().into();
+        self.node_manager
+            .start_kv_store_service_impl(ctx, addr)
+            .await?;
+        Ok(Response::ok(req))
+    }
+
     pub(super) async fn start_credentials_service(
         &self,
         ctx: &Context,
@@ -416,6 +473,7 @@ impl NodeManagerWorker {
                 context,
                 SocketAddr::new(bind_ip, server_bootstrap_port).to_string(),
                 None,
+                None,
                 route![local_interceptor_address.clone()],
                 route![
                     KAFKA_OUTLET_INTERCEPTOR_ADDRESS,
@@ -570,6 +628,7 @@ impl NodeManagerWorker {
                 context,
                 SocketAddr::new(bind_ip, server_bootstrap_port).to_string(),
                 None,
+                None,
                 route![local_interceptor_address.clone()],
                 route![
                     KAFKA_OUTLET_INTERCEPTOR_ADDRESS,
@@ -718,6 +777,14 @@ impl NodeManagerWorker {
                 DefaultAddress::HOP_SERVICE,
             ))
         });
+        registry
+            .kv_store_services
+            .keys()
+            .await
+            .iter()
+            .for_each(|addr| {
+                list.push(ServiceStatus::new(addr.address(), DefaultAddress::KV_STORE))
+            });
         registry
             .credentials_services
             .keys()
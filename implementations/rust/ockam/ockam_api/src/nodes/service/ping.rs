@@ -0,0 +1,68 @@
+//! Probes of the other nodes and results of the health checks, see [`crate::ping`].
+
+use ockam_core::api::{Error, RequestHeader, Response};
+use ockam_core::Result;
+use ockam_node::Context;
+
+use super::{NodeManager, NodeManagerWorker};
+use crate::ping::{
+    ping, HealthCheck, HealthCheckHistory, HealthCheckTarget, PingNode, PingService,
+};
+use crate::DefaultAddress;
+
+impl NodeManager {
+    /// Return the results of the health checks of the node
+    pub fn health_checks(&self) -> &HealthCheckHistory {
+        &self.health_checks
+    }
+
+    /// Start the service answering the probes of the other nodes
+    pub(super) async fn start_ping_service(&self, ctx: &Context) -> Result<()> {
+        ctx.flow_controls().add_consumer(
+            DefaultAddress::PING_SERVICE,
+            &self.api_transport_flow_control_id,
+        );
+        let service = PingService::new(self.identifier().clone(), self.node_name());
+        ctx.start_worker(DefaultAddress::PING_SERVICE, service)
+            .await
+    }
+
+    /// Probe a node through a secure channel checking its identity
+    pub async fn ping(&self, ctx: &Context, target: &HealthCheckTarget) -> HealthCheck {
+        let result = match self
+            .make_secure_client(&target.identifier, &target.route, self.identifier())
+            .await
+        {
+            Ok(client) => ping(ctx, &client).await,
+            Err(e) => Err(e),
+        };
+        HealthCheck::new(target, result)
+    }
+}
+
+impl NodeManagerWorker {
+    pub(super) async fn ping_node(
+        &self,
+        ctx: &Context,
+        req: &RequestHeader,
+        ping_node: PingNode,
+    ) -> Result<Response<HealthCheck>, Response<Error>> {
+        let target = ping_node
+            .target()
+            .map_err(|e| Response::bad_request(req, &e.to_string()))?;
+        Ok(Response::ok(req).body(self.node_manager.ping(ctx, &target).await))
+    }
+
+    pub(super) async fn list_health_checks(
+        &self,
+        req: &RequestHeader,
+    ) -> Result<Response<Vec<HealthCheck>>, Response<Error>> {
+        let checks = self
+            .node_manager
+            .health_checks()
+            .list()
+            .await
+            .map_err(|e| Response::internal_error(req, &e.to_string()))?;
+        Ok(Response::ok(req).body(checks))
+    }
+}
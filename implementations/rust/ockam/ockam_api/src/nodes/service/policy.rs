@@ -6,6 +6,8 @@ use ockam_core::api::{Error, RequestHeader, Response};
 use ockam_core::Result;
 
 use crate::nodes::models::policy::{Expression, Policy, PolicyList};
+use crate::notifier::NotificationEvent;
+use crate::policy_bundle::{ImportPolicyBundle, Policies, PolicyBundle, PolicyImportReport};
 
 use super::NodeManager;
 
@@ -21,6 +23,13 @@ impl NodeManager {
         let r = Resource::new(resource);
         let a = Action::new(action);
         self.policies.set_policy(&r, &a, p.expression()).await?;
+        if let Some(notifier) = self.notifier() {
+            notifier.notify(NotificationEvent::PolicyChanged {
+                resource: resource.to_string(),
+                action: action.to_string(),
+                expression: Some(p.expression().to_string()),
+            })
+        }
         Ok(Response::ok(req))
     }
 
@@ -59,6 +68,58 @@ impl NodeManager {
         let r = Resource::new(res);
         let a = Action::new(act);
         self.policies.del_policy(&r, &a).await?;
+        if let Some(notifier) = self.notifier() {
+            notifier.notify(NotificationEvent::PolicyChanged {
+                resource: res.to_string(),
+                action: act.to_string(),
+                expression: None,
+            })
+        }
         Ok(Response::ok(req))
     }
+
+    pub(super) async fn export_policies(
+        &self,
+        req: &RequestHeader,
+    ) -> Result<Response<PolicyBundle>, Response<Error>> {
+        let bundle = Policies::new(self.policies.clone()).export_bundle().await?;
+        Ok(Response::ok(req).body(bundle))
+    }
+
+    pub(super) async fn import_policies(
+        &self,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response<PolicyImportReport>, Response<Error>> {
+        let import: ImportPolicyBundle = dec.decode()?;
+        let report = Policies::new(self.policies.clone())
+            .import_bundle(&import.bundle, import.strategy)
+            .await?;
+        if let Some(notifier) = self.notifier() {
+            for changed in report.added.iter().chain(report.updated.iter()) {
+                if let Some((resource, action)) = changed.split_once('/') {
+                    let expression = self
+                        .policies
+                        .get_policy(&Resource::new(resource), &Action::new(action))
+                        .await?
+                        .map(|e| e.to_string());
+                    notifier.notify(NotificationEvent::PolicyChanged {
+                        resource: resource.to_string(),
+                        action: action.to_string(),
+                        expression,
+                    })
+                }
+            }
+            for removed in &report.removed {
+                if let Some((resource, action)) = removed.split_once('/') {
+                    notifier.notify(NotificationEvent::PolicyChanged {
+                        resource: resource.to_string(),
+                        action: action.to_string(),
+                        expression: None,
+                    })
+                }
+            }
+        }
+        Ok(Response::ok(req).body(report))
+    }
 }
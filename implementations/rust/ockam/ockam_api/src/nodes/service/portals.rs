@@ -1,30 +1,44 @@
 use minicbor::Decoder;
 use std::net::SocketAddr;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::timeout;
 
-use ockam::identity::Identifier;
+use ockam::identity::{Identifier, IdentityQuotas, IdentitySecureChannelLocalInfo, QuotaKind};
 use ockam::{Address, Result};
 use ockam_abac::Resource;
 use ockam_core::api::{Error, RequestHeader, Response};
 use ockam_core::errcode::{Kind, Origin};
-use ockam_core::{route, AsyncTryClone, IncomingAccessControl, Route};
-use ockam_multiaddr::proto::Project;
-use ockam_multiaddr::MultiAddr;
+use ockam_core::{route, AsyncTryClone, IncomingAccessControl, LocalMessage, Route};
+use ockam_multiaddr::proto::{Project, Secure};
+use ockam_multiaddr::{MultiAddr, Protocol};
 use ockam_node::Context;
-use ockam_transport_tcp::{TcpInletOptions, TcpOutletOptions};
+use ockam_transport_tcp::{
+    AddressFamily, DestinationRule, OutletConnectionLimiter, OutletConnectionPermit,
+    PortalInterceptorFactory, PortalSessionResumption, TcpInletOptions, TcpOutletOptions,
+    TransparentProxy, TransparentProxyMode, DEFAULT_SESSION_BUFFER_SIZE,
+};
 
 use crate::cli_state::StateDirTrait;
 use crate::config::lookup::ProjectLookup;
 use crate::error::ApiError;
+use crate::grpc::{GrpcInterceptorFactory, GrpcPortalConfig};
+use crate::mqtt::{MqttInterceptorFactory, MqttPortalConfig};
 use crate::nodes::connection::Connection;
 use crate::nodes::models::portal::{
-    CreateInlet, CreateOutlet, InletList, InletStatus, OutletList, OutletStatus,
+    CreateInlet, CreateOutlet, InletList, InletStatus, OutletList, OutletStatus, PortalTapStatus,
+    StartPortalTap, SwitchServiceTarget, TransparentInlet,
 };
-use crate::nodes::registry::{InletInfo, OutletInfo};
+use crate::nodes::registry::{InletInfo, OutletInfo, PublishedServiceInfo};
 use crate::nodes::service::random_alias;
 use crate::nodes::InMemoryNode;
+use crate::port_range::PortRange;
+use crate::portal_dns::{normalize_name, PortalDnsRecord};
+use crate::portal_tap::{PortalSide, TapFormat};
+use crate::postgres_outlet::{PostgresAuthProxy, PostgresUsers};
+use crate::service_registry::{ServicePublication, ServiceRegistryClient};
 use crate::session::sessions::{Replacer, Session, MAX_CONNECT_TIME, MAX_RECOVERY_TIME};
 use crate::{actions, resources, DefaultAddress};
 
@@ -52,23 +66,91 @@ impl NodeManagerWorker {
             prefix_route,
             suffix_route,
             wait_for_outlet_duration,
+            service,
+            transparent,
+            dns_name,
+            port_range,
+            mqtt,
         } = create_inlet_req;
-        match self
-            .node_manager
-            .create_inlet(
-                ctx,
-                listen_addr,
-                alias,
-                prefix_route,
-                suffix_route,
-                outlet_addr,
-                wait_for_outlet_duration,
-                authorized,
-            )
-            .await
-        {
-            Ok(status) => Ok(Response::ok(req).body(status)),
-            Err(e) => Err(Response::bad_request(req, &format!("{e:?}"))),
+        let port_range = match port_range.map(PortRange::try_from).transpose() {
+            Ok(port_range) => port_range,
+            Err(e) => return Err(Response::bad_request(req, &format!("{e}"))),
+        };
+        let alias = match mqtt {
+            Some(config) => {
+                let alias = alias.unwrap_or_else(random_alias);
+                let factory = match MqttInterceptorFactory::inlet(&config) {
+                    Ok(factory) => factory,
+                    Err(e) => return Err(Response::bad_request(req, &format!("{e}"))),
+                };
+                self.node_manager
+                    .register_mqtt_portal(&alias, config, factory)
+                    .await;
+                Some(alias)
+            }
+            None => alias,
+        };
+        let mqtt_alias = alias.clone();
+        let result = match (transparent, service) {
+            (Some(transparent), _) => {
+                self.node_manager
+                    .create_transparent_inlet(
+                        ctx,
+                        listen_addr,
+                        port_range,
+                        alias,
+                        transparent,
+                        wait_for_outlet_duration,
+                        authorized,
+                    )
+                    .await
+            }
+            (None, Some(service)) => {
+                self.node_manager
+                    .create_service_inlet(
+                        ctx,
+                        listen_addr,
+                        port_range,
+                        alias,
+                        prefix_route,
+                        suffix_route,
+                        outlet_addr,
+                        service,
+                        wait_for_outlet_duration,
+                    )
+                    .await
+            }
+            (None, None) => {
+                self.node_manager
+                    .create_inlet(
+                        ctx,
+                        listen_addr,
+                        port_range,
+                        alias,
+                        prefix_route,
+                        suffix_route,
+                        outlet_addr,
+                        wait_for_outlet_duration,
+                        authorized,
+                    )
+                    .await
+            }
+        };
+        match result {
+            Ok(status) => {
+                if let Some(dns_name) = dns_name {
+                    self.node_manager
+                        .add_dns_record(&dns_name, PortalDnsRecord::Inlet(status.alias.clone()))
+                        .await;
+                }
+                Ok(Response::ok(req).body(status))
+            }
+            Err(e) => {
+                if let Some(alias) = mqtt_alias {
+                    self.node_manager.unregister_mqtt_portal(&alias).await;
+                }
+                Err(Response::bad_request(req, &format!("{e:?}")))
+            }
         }
     }
 
@@ -111,23 +193,127 @@ impl NodeManagerWorker {
             worker_addr,
             alias,
             reachable_from_default_secure_channel,
-            ..
+            publication,
+            hostname,
+            address_family,
+            postgres_users,
+            mqtt,
+            grpc,
         } = create_outlet;
 
-        match self
-            .node_manager
-            .create_outlet(
-                ctx,
-                socket_addr,
-                worker_addr,
-                alias,
-                reachable_from_default_secure_channel,
-            )
-            .await
-        {
-            Ok(outlet_status) => Ok(Response::ok(req).body(outlet_status)),
-            Err(e) => Err(Response::bad_request(req, &format!("{e:?}"))),
+        if grpc.is_some() && (postgres_users.is_some() || mqtt.is_some()) {
+            return Err(Response::bad_request(
+                req,
+                "A gRPC outlet can't also target a Postgres server or an MQTT broker",
+            ));
         }
+        let alias = match mqtt {
+            Some(_) if postgres_users.is_some() => {
+                return Err(Response::bad_request(
+                    req,
+                    "An outlet can't target both a Postgres server and an MQTT broker",
+                ));
+            }
+            Some(config) => {
+                let alias = alias.unwrap_or_else(random_alias);
+                let factory = match MqttInterceptorFactory::outlet(&config) {
+                    Ok(factory) => factory,
+                    Err(e) => return Err(Response::bad_request(req, &format!("{e}"))),
+                };
+                self.node_manager
+                    .register_mqtt_portal(&alias, config, factory)
+                    .await;
+                Some(alias)
+            }
+            None => alias,
+        };
+        let alias = match grpc {
+            Some(config) => {
+                let alias = alias.unwrap_or_else(random_alias);
+                let factory = match GrpcInterceptorFactory::new(
+                    &config,
+                    &alias,
+                    self.node_manager.identities_repository(),
+                ) {
+                    Ok(factory) => factory,
+                    Err(e) => return Err(Response::bad_request(req, &format!("{e}"))),
+                };
+                self.node_manager
+                    .register_grpc_portal(&alias, config, factory)
+                    .await;
+                Some(alias)
+            }
+            None => alias,
+        };
+        let interceptor_alias = alias.clone();
+        let result = match (postgres_users, hostname) {
+            (Some(users), hostname) => {
+                let upstream = hostname.unwrap_or_else(|| socket_addr.to_string());
+                self.node_manager
+                    .create_postgres_outlet(
+                        ctx,
+                        upstream,
+                        users,
+                        worker_addr,
+                        alias,
+                        reachable_from_default_secure_channel,
+                    )
+                    .await
+            }
+            (None, Some(hostname)) => {
+                let address_family = match address_family.as_deref().map(AddressFamily::from_str) {
+                    Some(Ok(address_family)) => address_family,
+                    Some(Err(_)) => {
+                        return Err(Response::bad_request(req, "Invalid address family"));
+                    }
+                    None => AddressFamily::Any,
+                };
+                self.node_manager
+                    .create_outlet_to_hostname(
+                        ctx,
+                        hostname,
+                        address_family,
+                        worker_addr,
+                        alias,
+                        reachable_from_default_secure_channel,
+                    )
+                    .await
+            }
+            (None, None) => {
+                self.node_manager
+                    .create_outlet(
+                        ctx,
+                        socket_addr,
+                        worker_addr,
+                        alias,
+                        reachable_from_default_secure_channel,
+                    )
+                    .await
+            }
+        };
+        let outlet_status = match result {
+            Ok(outlet_status) => outlet_status,
+            Err(e) => {
+                if let Some(alias) = interceptor_alias {
+                    self.node_manager.unregister_mqtt_portal(&alias).await;
+                    self.node_manager.unregister_grpc_portal(&alias).await;
+                }
+                return Err(Response::bad_request(req, &format!("{e:?}")));
+            }
+        };
+
+        if let Some(publication) = publication {
+            if let Err(e) = self
+                .node_manager
+                .publish_service(ctx, &outlet_status.alias, publication)
+                .await
+            {
+                // an outlet which can't be published is not kept
+                let _ = self.node_manager.delete_outlet(&outlet_status.alias).await;
+                return Err(Response::bad_request(req, &format!("{e:?}")));
+            }
+        }
+        Ok(Response::ok(req).body(outlet_status))
     }
 
     pub(super) async fn delete_outlet(
@@ -169,6 +355,57 @@ impl NodeManagerWorker {
     pub(super) async fn get_outlets(&self, req: &RequestHeader) -> Response<OutletList> {
         Response::ok(req).body(self.node_manager.list_outlets().await)
     }
+
+    pub(super) async fn switch_service_target(
+        &self,
+        req: &RequestHeader,
+        service: &str,
+        switch: SwitchServiceTarget,
+    ) -> Result<Response<OutletStatus>, Response<Error>> {
+        match self
+            .node_manager
+            .switch_service_target(service, switch.to, switch.health_check_timeout())
+            .await
+        {
+            Ok(outlet_status) => Ok(Response::ok(req).body(outlet_status)),
+            Err(e) if e.code().kind == Kind::NotFound => {
+                Err(Response::not_found(req, &e.to_string()))
+            }
+            Err(e) => Err(Response::bad_request(req, &e.to_string())),
+        }
+    }
+
+    pub(super) async fn start_portal_tap(
+        &self,
+        req: &RequestHeader,
+        side: PortalSide,
+        alias: &str,
+        tap: StartPortalTap,
+    ) -> Result<Response<PortalTapStatus>, Response<Error>> {
+        match self.node_manager.start_portal_tap(side, alias, tap).await {
+            Ok(status) => Ok(Response::ok(req).body(status)),
+            Err(e) if e.code().kind == Kind::NotFound => {
+                Err(Response::not_found(req, &e.to_string()))
+            }
+            Err(e) => Err(Response::bad_request(req, &e.to_string())),
+        }
+    }
+
+    pub(super) async fn stop_portal_tap(
+        &self,
+        req: &RequestHeader,
+        side: PortalSide,
+        alias: &str,
+    ) -> Result<Response<()>, Response<Error>> {
+        if self.node_manager.portal_taps().stop(side, alias) {
+            Ok(Response::ok(req))
+        } else {
+            Err(Response::not_found(
+                req,
+                &format!("The {side} {alias} is not tapped"),
+            ))
+        }
+    }
 }
 
 /// OUTLETS
@@ -180,6 +417,53 @@ impl NodeManager {
         worker_addr: Address,
         alias: Option<String>,
         reachable_from_default_secure_channel: bool,
+    ) -> Result<OutletStatus> {
+        self.create_outlet_impl(
+            ctx,
+            socket_addr,
+            None,
+            worker_addr,
+            alias,
+            reachable_from_default_secure_channel,
+        )
+        .await
+    }
+
+    /// Create an outlet connecting to a hostname. The hostname is resolved to the addresses
+    /// of the given family by the outlet resolver of the node, which caches them for the TTL
+    /// of their records, and each connection is raced over these addresses
+    pub async fn create_outlet_to_hostname(
+        &self,
+        ctx: &Context,
+        hostname: String,
+        address_family: AddressFamily,
+        worker_addr: Address,
+        alias: Option<String>,
+        reachable_from_default_secure_channel: bool,
+    ) -> Result<OutletStatus> {
+        let socket_addr = self
+            .outlet_resolver
+            .resolve(&hostname, address_family)
+            .await?[0];
+        self.create_outlet_impl(
+            ctx,
+            socket_addr,
+            Some((hostname, address_family)),
+            worker_addr,
+            alias,
+            reachable_from_default_secure_channel,
+        )
+        .await
+    }
+
+    async fn create_outlet_impl(
+        &self,
+        ctx: &Context,
+        socket_addr: SocketAddr,
+        hostname: Option<(String, AddressFamily)>,
+        worker_addr: Address,
+        alias: Option<String>,
+        reachable_from_default_secure_channel: bool,
     ) -> Result<OutletStatus> {
         info!(
             "Handling request to create outlet portal at {:?}",
@@ -202,18 +486,46 @@ impl NodeManager {
             ));
         }
 
-        let check_credential = self.enable_credential_checks;
+        let check_credential = self.enable_credential_checks();
         let trust_context_id = if check_credential {
-            Some(self.trust_context()?.id())
+            Some(self.trust_context()?.id().to_string())
         } else {
             None
         };
 
         let access_control = self
-            .access_control(&resource, &actions::HANDLE_MESSAGE, trust_context_id, None)
+            .access_control(
+                &resource,
+                &actions::HANDLE_MESSAGE,
+                trust_context_id.as_deref(),
+                None,
+            )
             .await?;
 
-        let options = TcpOutletOptions::new().with_incoming_access_control(access_control);
+        let options = TcpOutletOptions::new()
+            .with_connection_limiter(Arc::new(IdentityQuotasLimiter::new(self.quotas())));
+        let options = self.resource_profile().settings().outlet_options(options);
+        let options =
+            options.with_interceptor(self.portal_interceptor(&alias, PortalSide::Outlet).await);
+        let options = if self.portal_session_resumption {
+            options.with_session_resumption(DEFAULT_SESSION_BUFFER_SIZE)
+        } else {
+            options
+        };
+        let (options, observer) = match self.portal_events() {
+            Some(events) => (
+                options.with_incoming_access_control(events.outlet_access_control(
+                    &alias,
+                    resource.as_str(),
+                    worker_addr.clone(),
+                    access_control,
+                )),
+                Some(events.outlet_observer(&alias, resource.as_str())),
+            ),
+            None => (options.with_incoming_access_control(access_control), None),
+        };
+        let options = options
+            .with_connection_observer(self.portal_traffic().outlet_observer(&alias, observer));
         let options = if !check_credential {
             options.as_consumer(&self.api_transport_flow_control_id)
         } else {
@@ -234,10 +546,24 @@ impl NodeManager {
             options
         };
 
-        let res = self
-            .tcp_transport
-            .create_tcp_outlet(worker_addr.clone(), socket_addr, options)
-            .await;
+        let res = match hostname {
+            Some((hostname, address_family)) => {
+                self.tcp_transport
+                    .create_outlet(
+                        worker_addr.clone(),
+                        hostname,
+                        options
+                            .with_address_family(address_family)
+                            .with_resolver(self.outlet_resolver.clone()),
+                    )
+                    .await
+            }
+            None => {
+                self.tcp_transport
+                    .create_tcp_outlet(worker_addr.clone(), socket_addr, options)
+                    .await
+            }
+        };
 
         Ok(match res {
             Ok(_) => {
@@ -264,8 +590,65 @@ impl NodeManager {
         })
     }
 
+    /// Create an outlet to a Postgres server, which authenticates its connections with the
+    /// database users mapped to the identities of the inlets.
+    /// See the [`postgres_outlet`](crate::postgres_outlet) module for the details
+    pub async fn create_postgres_outlet(
+        &self,
+        ctx: &Context,
+        upstream: String,
+        users: PostgresUsers,
+        worker_addr: Address,
+        alias: Option<String>,
+        reachable_from_default_secure_channel: bool,
+    ) -> Result<OutletStatus> {
+        let alias = alias.unwrap_or_else(random_alias);
+        if self.registry.outlets.contains_key(&alias).await {
+            let message = format!("A TCP outlet with alias '{alias}' already exists");
+            return Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::AlreadyExists,
+                message,
+            ));
+        }
+        let proxy = PostgresAuthProxy::start(upstream.clone(), users).await?;
+        self.register_portal_interceptor(&alias, proxy.interceptor_factory())
+            .await;
+        let result = self
+            .create_outlet(
+                ctx,
+                proxy.address(),
+                worker_addr,
+                Some(alias.clone()),
+                reachable_from_default_secure_channel,
+            )
+            .await;
+        self.unregister_portal_interceptor(&alias).await;
+        match result {
+            Ok(outlet_status) => {
+                info!(%alias, %upstream, proxy = %proxy.address(), "Created a Postgres outlet");
+                self.registry.postgres_proxies.insert(alias, proxy).await;
+                Ok(outlet_status)
+            }
+            Err(e) => {
+                proxy.stop();
+                Err(e)
+            }
+        }
+    }
+
     pub async fn delete_outlet(&self, alias: &str) -> Result<Option<OutletInfo>> {
         info!(%alias, "Handling request to delete outlet portal");
+        if let Some(proxy) = self.registry.postgres_proxies.remove(alias).await {
+            proxy.stop();
+        }
+        self.unregister_mqtt_portal(alias).await;
+        self.unregister_grpc_portal(alias).await;
+        if let Some(published) = self.registry.published_services.remove(alias).await {
+            // the publication expires in the registry once it is not refreshed anymore
+            published.task.abort();
+            debug!(%alias, service = %published.publication.name, "Stopped publishing the outlet");
+        }
         if let Some(deleted_outlet) = self.registry.outlets.remove(alias).await {
             debug!(%alias, "Successfully removed outlet from node registry");
             if let Err(e) = self
@@ -300,12 +683,421 @@ impl NodeManager {
     }
 }
 
+/// PORTAL INTERCEPTORS
+impl NodeManager {
+    /// Intercept the data of the inlet or outlet created with a given alias, for example to
+    /// scan or validate it. The interceptor must be registered before the portal is created
+    pub async fn register_portal_interceptor(
+        &self,
+        alias: impl Into<String>,
+        interceptor: Arc<dyn PortalInterceptorFactory>,
+    ) {
+        self.registry
+            .portal_interceptors
+            .insert(alias.into(), interceptor)
+            .await;
+    }
+
+    /// Stop intercepting the data of the portals created from now on with a given alias
+    pub async fn unregister_portal_interceptor(&self, alias: &str) {
+        self.registry.portal_interceptors.remove(alias).await;
+    }
+
+    /// Return the interceptor of the connections of a portal: its registered interceptor,
+    /// if any, wrapped so that the portal can be tapped
+    pub(super) async fn portal_interceptor(
+        &self,
+        alias: &str,
+        side: PortalSide,
+    ) -> Arc<dyn PortalInterceptorFactory> {
+        let inner = self.registry.portal_interceptors.get(alias).await;
+        self.portal_taps().interceptor_factory(
+            side,
+            alias,
+            self.identifier().clone(),
+            self.identities_repository(),
+            inner,
+        )
+    }
+
+    /// Record the connections of an inlet or an outlet to a local file, for a bounded duration.
+    /// The tap is refused if no policy is set for the `tap` action on the alias of the portal.
+    /// See the [`portal_tap`](crate::portal_tap) module for the details
+    pub async fn start_portal_tap(
+        &self,
+        side: PortalSide,
+        alias: &str,
+        tap: StartPortalTap,
+    ) -> Result<PortalTapStatus> {
+        let server_port = match side {
+            PortalSide::Inlet => self.registry.inlets.get(alias).await.map(|inlet| {
+                inlet
+                    .bind_addr
+                    .parse::<SocketAddr>()
+                    .map(|a| a.port())
+                    .unwrap_or_default()
+            }),
+            PortalSide::Outlet => self
+                .registry
+                .outlets
+                .get(alias)
+                .await
+                .map(|outlet| outlet.socket_addr.port()),
+        };
+        let server_port = server_port.ok_or_else(|| {
+            ockam_core::Error::new(
+                Origin::Node,
+                Kind::NotFound,
+                format!("The {side} {alias} doesn't exist"),
+            )
+        })?;
+        let policy = self
+            .policies
+            .get_policy(&Resource::new(alias), &actions::TAP)
+            .await?
+            .ok_or_else(|| {
+                ApiError::core(format!(
+                    "The {side} {alias} can't be tapped without a policy, set one with `ockam policy create --resource {alias} --action tap --expression <EXPRESSION>`"
+                ))
+            })?;
+        let format = TapFormat::from_str(&tap.format).map_err(ApiError::core)?;
+        self.portal_taps().start(
+            side,
+            alias,
+            Path::new(&tap.path),
+            format,
+            tap.duration(),
+            policy,
+            server_port,
+        )
+    }
+
+    /// Intercept the MQTT packets of the inlet or outlet created with a given alias,
+    /// until it is deleted
+    pub async fn register_mqtt_portal(
+        &self,
+        alias: &str,
+        config: MqttPortalConfig,
+        factory: MqttInterceptorFactory,
+    ) {
+        self.register_portal_interceptor(alias, Arc::new(factory))
+            .await;
+        self.registry
+            .mqtt_portals
+            .insert(alias.into(), config)
+            .await;
+    }
+
+    pub(super) async fn unregister_mqtt_portal(&self, alias: &str) {
+        if self.registry.mqtt_portals.remove(alias).await.is_some() {
+            self.unregister_portal_interceptor(alias).await;
+        }
+    }
+
+    /// Authorize the gRPC calls going through the outlet created with a given alias,
+    /// until it is deleted
+    pub async fn register_grpc_portal(
+        &self,
+        alias: &str,
+        config: GrpcPortalConfig,
+        factory: GrpcInterceptorFactory,
+    ) {
+        self.register_portal_interceptor(alias, Arc::new(factory))
+            .await;
+        self.registry
+            .grpc_portals
+            .insert(alias.into(), config)
+            .await;
+    }
+
+    pub(super) async fn unregister_grpc_portal(&self, alias: &str) {
+        if self.registry.grpc_portals.remove(alias).await.is_some() {
+            self.unregister_portal_interceptor(alias).await;
+        }
+    }
+}
+
+/// PORTAL DNS
+impl NodeManager {
+    /// Answer the DNS queries for a name, if the node has been started with a DNS responder
+    pub async fn add_dns_record(&self, name: &str, record: PortalDnsRecord) {
+        let name = normalize_name(name);
+        debug!(%name, ?record, "Adding a DNS record");
+        self.registry.portal_dns_records.insert(name, record).await;
+    }
+
+    /// Stop answering the DNS queries for a name
+    pub async fn remove_dns_record(&self, name: &str) {
+        self.registry
+            .portal_dns_records
+            .remove(&normalize_name(name))
+            .await;
+    }
+
+    /// Remove the DNS names of a deleted inlet
+    async fn remove_inlet_dns_records(&self, alias: &str) {
+        for (name, record) in self.registry.portal_dns_records.entries().await {
+            if record == PortalDnsRecord::Inlet(alias.to_string()) {
+                self.remove_dns_record(&name).await;
+            }
+        }
+    }
+}
+
+/// SERVICE REGISTRY
+impl NodeManager {
+    /// Return a client to the service registry of a project, hosted by the project authority
+    pub(crate) async fn service_registry_client(
+        &self,
+        project: &str,
+    ) -> Result<ServiceRegistryClient> {
+        let projects = ProjectLookup::from_state(self.cli_state.projects.list()?)
+            .await
+            .map_err(|e| ockam_core::Error::new(Origin::Node, Kind::NotFound, e))?;
+        let authority = projects
+            .get(project)
+            .and_then(|p| p.authority.clone())
+            .ok_or_else(|| {
+                ockam_core::Error::new(
+                    Origin::Node,
+                    Kind::NotFound,
+                    format!("The authority of the project {project} is unknown"),
+                )
+            })?;
+        let client = self
+            .make_secure_client(
+                authority.identity_id(),
+                authority.address(),
+                &self.identifier,
+            )
+            .await?;
+        Ok(ServiceRegistryClient::new(client))
+    }
+
+    /// Publish an outlet in the service registry of the project starting its route.
+    /// The publication is refreshed until the outlet is deleted
+    pub async fn publish_service(
+        &self,
+        ctx: &Context,
+        alias: &str,
+        publication: ServicePublication,
+    ) -> Result<()> {
+        let route = MultiAddr::from_str(&publication.route)
+            .map_err(|e| ockam_core::Error::new(Origin::Node, Kind::Invalid, e))?;
+        let project = project_name(&route)?;
+        let task = self
+            .service_registry_client(&project)
+            .await?
+            .start_publishing(ctx, publication.clone())
+            .await?;
+        info!(
+            %alias,
+            service = %publication.name,
+            %project,
+            "Publishing the outlet in the service registry"
+        );
+        self.registry
+            .published_services
+            .insert(
+                alias.to_string(),
+                PublishedServiceInfo::new(publication, task),
+            )
+            .await;
+        Ok(())
+    }
+
+    /// Connect to one of the outlets publishing a service in the registry of a project.
+    /// The publishers other than `previous`, for example a publisher which can't be reached
+    /// anymore, are tried first.
+    ///
+    /// The route of each publisher must go through a secure channel authenticated by the
+    /// publisher itself, so that an outlet can't be reached in place of another one
+    pub(crate) async fn connect_to_service(
+        &self,
+        ctx: Arc<Context>,
+        project: &MultiAddr,
+        service: &str,
+        previous: Option<&MultiAddr>,
+        timeout: Duration,
+    ) -> Result<(MultiAddr, Connection)> {
+        let publishers = self
+            .service_registry_client(&project_name(project)?)
+            .await?
+            .publishers(&ctx, service)
+            .await?;
+        let mut routes: Vec<(MultiAddr, Identifier)> = publishers
+            .into_iter()
+            .filter_map(|p| match MultiAddr::from_str(&p.route) {
+                Ok(route) if route.iter().any(|p| p.code() == Secure::CODE) => {
+                    Some((route, p.publisher))
+                }
+                Ok(route) => {
+                    warn!(%service, %route, "Ignoring a service route without a secure channel");
+                    None
+                }
+                Err(e) => {
+                    warn!(%service, route = %p.route, %e, "Ignoring an invalid service route");
+                    None
+                }
+            })
+            .collect();
+        // `sort_by_key` is stable, so the order of the registry is kept otherwise
+        routes.sort_by_key(|(route, _)| Some(route) == previous);
+
+        for (route, publisher) in routes {
+            match self
+                .make_connection(
+                    ctx.clone(),
+                    &route,
+                    None,
+                    Some(publisher.clone()),
+                    None,
+                    Some(timeout),
+                )
+                .await
+            {
+                Ok(connection) => {
+                    debug!(%service, %route, %publisher, "Connected to a publisher of the service");
+                    return Ok((route, connection));
+                }
+                Err(e) => {
+                    warn!(%service, %route, %publisher, %e, "Couldn't connect to a publisher of the service")
+                }
+            }
+        }
+        Err(ockam_core::Error::new(
+            Origin::Node,
+            Kind::NotFound,
+            format!("No publisher of the service {service} can be reached"),
+        ))
+    }
+}
+
+/// SERVICE TARGET SWITCH
+impl NodeManager {
+    /// Switch the outlet publishing a service to a new target, without interrupting the service:
+    ///
+    ///  1. wait until the new target accepts TCP connections
+    ///  2. flip the outlet: the connections opened from now on are made to the new target
+    ///  3. drain the previous target: the established connections are kept until they are closed
+    ///
+    /// If the new target can't be reached, before or right after the switch, the outlet is
+    /// switched back to its previous target and an error is returned
+    pub async fn switch_service_target(
+        &self,
+        service: &str,
+        to: SocketAddr,
+        health_check_timeout: Duration,
+    ) -> Result<OutletStatus> {
+        let alias = self
+            .registry
+            .published_services
+            .entries()
+            .await
+            .into_iter()
+            .find(|(_, published)| published.publication.name == service)
+            .map(|(alias, _)| alias)
+            .ok_or_else(|| {
+                ockam_core::Error::new(
+                    Origin::Node,
+                    Kind::NotFound,
+                    format!("The service {service} is not published by this node"),
+                )
+            })?;
+        let outlet = self.registry.outlets.get(&alias).await.ok_or_else(|| {
+            ockam_core::Error::new(
+                Origin::Node,
+                Kind::NotFound,
+                format!("The outlet {alias} publishing the service {service} doesn't exist"),
+            )
+        })?;
+        info!(%service, %alias, from = %outlet.socket_addr, %to, "Switching the service target");
+
+        if !wait_until_reachable(to, health_check_timeout).await {
+            return Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::Timeout,
+                format!("The new target {to} of the service {service} is not reachable, the service was not switched"),
+            ));
+        }
+
+        let previous = self
+            .tcp_transport
+            .retarget_outlet(outlet.worker_addr.clone(), to)?;
+
+        // the new target must still be reachable once the traffic has been flipped
+        if !wait_until_reachable(to, SWITCH_VERIFICATION_TIMEOUT).await {
+            self.tcp_transport
+                .retarget_outlet(outlet.worker_addr.clone(), previous)?;
+            warn!(%service, %alias, %to, %previous, "Rolled back the service target");
+            return Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::Timeout,
+                format!("The new target {to} of the service {service} became unreachable, the service was switched back to {previous}"),
+            ));
+        }
+
+        self.registry
+            .outlets
+            .insert(
+                alias.clone(),
+                OutletInfo::new(&to, Some(&outlet.worker_addr)),
+            )
+            .await;
+        info!(%service, %alias, %to, %previous, "Switched the service target, the previous target is draining");
+        Ok(OutletStatus::new(
+            to,
+            outlet.worker_addr,
+            alias,
+            Some(format!("switched from {previous}")),
+        ))
+    }
+}
+
+/// Maximum time to check that the new target of a service is still reachable after a switch
+const SWITCH_VERIFICATION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Interval between two attempts to connect to the new target of a service
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Return true if a TCP connection can be established to an address before a timeout
+async fn wait_until_reachable(addr: SocketAddr, health_check_timeout: Duration) -> bool {
+    let check = async {
+        loop {
+            match tokio::net::TcpStream::connect(addr).await {
+                Ok(_) => return,
+                Err(e) => {
+                    debug!(%addr, %e, "The service target is not reachable yet");
+                    tokio::time::sleep(HEALTH_CHECK_INTERVAL).await
+                }
+            }
+        }
+    };
+    timeout(health_check_timeout, check).await.is_ok()
+}
+
+/// Return the name of the project starting a route
+fn project_name(route: &MultiAddr) -> Result<String> {
+    route
+        .first()
+        .and_then(|p| p.cast::<Project>().map(|p| p.to_string()))
+        .ok_or_else(|| {
+            ockam_core::Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                format!("The route {route} doesn't start with a project"),
+            )
+        })
+}
+
 /// INLETS
 impl NodeManager {
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_inlet(
         &self,
         connection: Connection,
         listen_addr: String,
+        port_range: Option<PortRange>,
         requested_alias: Option<String>,
         prefix_route: Route,
         suffix_route: Route,
@@ -323,44 +1115,181 @@ impl NodeManager {
             "Creating inlet portal"
         }
 
-        {
-            let registry = &self.registry.inlets;
+        self.check_inlet_is_available(&alias, &listen_addr).await?;
 
-            // Check that there is no entry in the registry with the same alias
-            if registry.contains_key(&alias).await {
-                let message = format!("A TCP inlet with alias '{alias}' already exists");
+        let outlet_route = connection.route(self.tcp_transport()).await?;
+        let outlet_route = route![prefix_route.clone(), outlet_route, suffix_route.clone()];
+
+        let access_control = self
+            .inlet_access_control(requested_alias, &outlet_addr)
+            .await?;
+
+        let options = TcpInletOptions::new().with_incoming_access_control(access_control.clone());
+        let options = match port_range {
+            Some(port_range) => options.with_port_range(port_range.start()..=port_range.end()),
+            None => options,
+        };
+        let options = self.resource_profile().settings().inlet_options(options);
+        let options =
+            options.with_interceptor(self.portal_interceptor(&alias, PortalSide::Inlet).await);
+        let options = if self.portal_session_resumption {
+            let session_resumption = PortalSessionResumption::new(outlet_route.clone());
+            self.registry
+                .portal_session_resumptions
+                .insert(alias.clone(), session_resumption.clone())
+                .await;
+            options.with_session_resumption(session_resumption)
+        } else {
+            options
+        };
+        let res = self
+            .tcp_transport
+            .create_inlet(listen_addr.clone(), outlet_route.clone(), options)
+            .await;
+
+        Ok(match res {
+            Ok((socket_address, worker_addr)) => {
+                //when using 0 port, the chosen port will be populated
+                //in the returned socket address
+                let listen_addr = socket_address.to_string();
+
+                // TODO: Use better way to store inlets?
+                self.registry
+                    .inlets
+                    .insert(
+                        alias.clone(),
+                        InletInfo::new(&listen_addr, Some(&worker_addr), &outlet_route),
+                    )
+                    .await;
+                (
+                    InletStatus::new(
+                        listen_addr,
+                        worker_addr.to_string(),
+                        alias,
+                        None,
+                        outlet_route.to_string(),
+                    ),
+                    access_control,
+                )
+            }
+            Err(e) => {
+                warn!(to = %outlet_addr, err = %e, "Failed to create TCP inlet");
+                self.registry
+                    .portal_session_resumptions
+                    .remove(&alias)
+                    .await;
+                let message = format!("Failed to create TCP inlet: {}", e);
                 return Err(ockam_core::Error::new(
                     Origin::Node,
-                    Kind::AlreadyExists,
+                    Kind::Internal,
                     message,
                 ));
             }
+        })
+    }
 
-            // Check that there is no entry in the registry with the same TCP bind address
-            if registry
-                .values()
-                .await
-                .iter()
-                .any(|inlet| inlet.bind_addr == listen_addr)
-            {
-                let message =
-                    format!("A TCP inlet with bind tcp address '{listen_addr}' already exists");
-                return Err(ockam_core::Error::new(
+    /// Create an inlet accepting the connections redirected by the firewall, and sending
+    /// each of them to an outlet depending on its original destination
+    pub async fn create_transparent_inlet(
+        &self,
+        listen_addr: String,
+        port_range: Option<PortRange>,
+        requested_alias: Option<String>,
+        transparent_proxy: TransparentProxy,
+        outlet_addr: &MultiAddr,
+    ) -> Result<(InletStatus, Arc<dyn IncomingAccessControl>)> {
+        info!("Handling request to create transparent inlet portal");
+
+        let alias = requested_alias.clone().unwrap_or_else(random_alias);
+        debug! {
+            listen_addr = %listen_addr,
+            mode = %transparent_proxy.mode(),
+            %alias,
+            "Creating transparent inlet portal"
+        }
+        self.check_inlet_is_available(&alias, &listen_addr).await?;
+        let access_control = self
+            .inlet_access_control(requested_alias, outlet_addr)
+            .await?;
+
+        let options = TcpInletOptions::new()
+            .with_incoming_access_control(access_control.clone())
+            .with_transparent_proxy(transparent_proxy);
+        let options = match port_range {
+            Some(port_range) => options.with_port_range(port_range.start()..=port_range.end()),
+            None => options,
+        };
+        let options = self.resource_profile().settings().inlet_options(options);
+        let options =
+            options.with_interceptor(self.portal_interceptor(&alias, PortalSide::Inlet).await);
+        let (socket_address, worker_addr) = self
+            .tcp_transport
+            .create_inlet(listen_addr, route![], options)
+            .await
+            .map_err(|e| {
+                warn!(err = %e, "Failed to create transparent TCP inlet");
+                ockam_core::Error::new(
                     Origin::Node,
-                    Kind::AlreadyExists,
-                    message,
-                ));
-            }
+                    Kind::Internal,
+                    format!("Failed to create transparent TCP inlet: {e}"),
+                )
+            })?;
+        let listen_addr = socket_address.to_string();
+        self.registry
+            .inlets
+            .insert(
+                alias.clone(),
+                InletInfo::new(&listen_addr, Some(&worker_addr), &route![]),
+            )
+            .await;
+        Ok((
+            InletStatus::new(listen_addr, worker_addr.to_string(), alias, None, ""),
+            access_control,
+        ))
+    }
+
+    async fn check_inlet_is_available(&self, alias: &str, listen_addr: &str) -> Result<()> {
+        let registry = &self.registry.inlets;
+
+        // Check that there is no entry in the registry with the same alias
+        if registry.contains_key(alias).await {
+            let message = format!("A TCP inlet with alias '{alias}' already exists");
+            return Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::AlreadyExists,
+                message,
+            ));
         }
 
-        let outlet_route = connection.route(self.tcp_transport()).await?;
-        let outlet_route = route![prefix_route.clone(), outlet_route, suffix_route.clone()];
+        // Check that there is no entry in the registry with the same TCP bind address
+        if registry
+            .values()
+            .await
+            .iter()
+            .any(|inlet| inlet.bind_addr == listen_addr)
+        {
+            let message =
+                format!("A TCP inlet with bind tcp address '{listen_addr}' already exists");
+            return Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::AlreadyExists,
+                message,
+            ));
+        }
+        Ok(())
+    }
 
+    async fn inlet_access_control(
+        &self,
+        requested_alias: Option<String>,
+        outlet_addr: &MultiAddr,
+    ) -> Result<Arc<dyn IncomingAccessControl>> {
         let projects = self.cli_state.projects.list()?;
         let projects = ProjectLookup::from_state(projects)
             .await
             .map_err(|e| ockam_core::Error::new(Origin::Node, Kind::NotFound, e))?;
-        let check_credential = self.enable_credential_checks;
+        let check_credential = self.enable_credential_checks();
+        let trust_context = self.trust_context().ok();
         let project_id = if check_credential {
             let pid = outlet_addr
                 .first()
@@ -371,7 +1300,7 @@ impl NodeManager {
                         None
                     }
                 })
-                .or_else(|| Some(self.trust_context().ok()?.id()));
+                .or_else(|| Some(trust_context.as_ref()?.id()));
             if pid.is_none() {
                 let message = "Credential check requires a project or trust context";
                 return Err(ockam_core::Error::new(Origin::Node, Kind::Invalid, message));
@@ -384,57 +1313,17 @@ impl NodeManager {
         let resource = requested_alias
             .map(|a| Resource::new(a.as_str()))
             .unwrap_or(resources::INLET);
-        let access_control = self
-            .access_control(&resource, &actions::HANDLE_MESSAGE, project_id, None)
-            .await?;
-
-        let options = TcpInletOptions::new().with_incoming_access_control(access_control.clone());
-        let res = self
-            .tcp_transport
-            .create_inlet(listen_addr.clone(), outlet_route.clone(), options)
-            .await;
-
-        Ok(match res {
-            Ok((socket_address, worker_addr)) => {
-                //when using 0 port, the chosen port will be populated
-                //in the returned socket address
-                let listen_addr = socket_address.to_string();
-
-                // TODO: Use better way to store inlets?
-                self.registry
-                    .inlets
-                    .insert(
-                        alias.clone(),
-                        InletInfo::new(&listen_addr, Some(&worker_addr), &outlet_route),
-                    )
-                    .await;
-                (
-                    InletStatus::new(
-                        listen_addr,
-                        worker_addr.to_string(),
-                        alias,
-                        None,
-                        outlet_route.to_string(),
-                    ),
-                    access_control,
-                )
-            }
-            Err(e) => {
-                warn!(to = %outlet_addr, err = %e, "Failed to create TCP inlet");
-                let message = format!("Failed to create TCP inlet: {}", e);
-                return Err(ockam_core::Error::new(
-                    Origin::Node,
-                    Kind::Internal,
-                    message,
-                ));
-            }
-        })
+        self.access_control(&resource, &actions::HANDLE_MESSAGE, project_id, None)
+            .await
     }
 
     pub async fn delete_inlet(&self, alias: &str) -> Result<InletStatus> {
         info!(%alias, "Handling request to delete inlet portal");
+        self.unregister_mqtt_portal(alias).await;
         if let Some(inlet_to_delete) = self.registry.inlets.remove(alias).await {
             debug!(%alias, "Successfully removed inlet from node registry");
+            self.remove_inlet_dns_records(alias).await;
+            self.registry.portal_session_resumptions.remove(alias).await;
             match self
                 .tcp_transport
                 .stop_inlet(inlet_to_delete.worker_addr.clone())
@@ -515,12 +1404,161 @@ impl InMemoryNode {
         &self,
         ctx: &Context,
         listen_addr: String,
+        port_range: Option<PortRange>,
         requested_alias: Option<String>,
         prefix_route: Route,
         suffix_route: Route,
         outlet_addr: MultiAddr,
         wait_for_outlet_duration: Option<Duration>,
         authorized: Option<Identifier>,
+    ) -> Result<InletStatus> {
+        self.create_inlet_impl(
+            ctx,
+            listen_addr,
+            port_range,
+            requested_alias,
+            prefix_route,
+            suffix_route,
+            outlet_addr,
+            None,
+            wait_for_outlet_duration,
+            authorized,
+        )
+        .await
+    }
+
+    /// Create an inlet connected to one of the outlets publishing a service in the
+    /// registry of a project. When the connection is lost the inlet is connected again
+    /// to one of the current publishers of the service
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_service_inlet(
+        &self,
+        ctx: &Context,
+        listen_addr: String,
+        port_range: Option<PortRange>,
+        requested_alias: Option<String>,
+        prefix_route: Route,
+        suffix_route: Route,
+        project_addr: MultiAddr,
+        service: String,
+        wait_for_outlet_duration: Option<Duration>,
+    ) -> Result<InletStatus> {
+        self.create_inlet_impl(
+            ctx,
+            listen_addr,
+            port_range,
+            requested_alias,
+            prefix_route,
+            suffix_route,
+            project_addr,
+            Some(service),
+            wait_for_outlet_duration,
+            None,
+        )
+        .await
+    }
+
+    /// Create a transparent inlet, connected to the outlet of each of its mappings.
+    /// When the connection to an outlet is lost, only the route of its mapping is replaced
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_transparent_inlet(
+        &self,
+        ctx: &Context,
+        listen_addr: String,
+        port_range: Option<PortRange>,
+        requested_alias: Option<String>,
+        transparent: TransparentInlet,
+        wait_for_outlet_duration: Option<Duration>,
+        authorized: Option<Identifier>,
+    ) -> Result<InletStatus> {
+        let mode = TransparentProxyMode::from_str(&transparent.mode)
+            .map_err(|e| ockam_core::Error::new(Origin::Node, Kind::Invalid, e))?;
+        let first_outlet_addr = match transparent.mappings.first() {
+            Some(mapping) => mapping.outlet_addr.clone(),
+            None => {
+                let message = "A transparent inlet requires at least one mapping";
+                return Err(ockam_core::Error::new(Origin::Node, Kind::Invalid, message));
+            }
+        };
+
+        let duration = wait_for_outlet_duration.unwrap_or(Duration::from_secs(5));
+        let connection_ctx = Arc::new(ctx.async_try_clone().await?);
+        let transparent_proxy = TransparentProxy::new(mode);
+        let mut connections = vec![];
+        for mapping in transparent.mappings {
+            let rule = DestinationRule::from_str(&mapping.destination).map_err(|_| {
+                ockam_core::Error::new(
+                    Origin::Node,
+                    Kind::Invalid,
+                    format!("Invalid destination {}", mapping.destination),
+                )
+            })?;
+            let connection = self
+                .make_connection(
+                    connection_ctx.clone(),
+                    &mapping.outlet_addr,
+                    None,
+                    authorized.clone(),
+                    None,
+                    Some(duration),
+                )
+                .await?;
+            transparent_proxy
+                .set_route(rule.clone(), connection.route(self.tcp_transport()).await?);
+            connections.push((rule, mapping.outlet_addr, connection));
+        }
+
+        let (inlet, _) = self
+            .node_manager
+            .create_transparent_inlet(
+                listen_addr,
+                port_range,
+                requested_alias,
+                transparent_proxy.clone(),
+                &first_outlet_addr,
+            )
+            .await?;
+
+        for (rule, outlet_addr, connection) in connections {
+            if connection.route(self.tcp_transport()).await?.is_empty() {
+                continue;
+            }
+            debug! {
+                %inlet.alias,
+                %rule,
+                ping_addr = %connection.transport_route(),
+                "Creating session for a transparent TCP inlet mapping"
+            };
+            let mut session = Session::new(connection.transport_route());
+            session.set_replacer(Self::transparent_route_replacer(
+                self.node_manager.clone(),
+                connection_ctx.clone(),
+                connection,
+                transparent_proxy.clone(),
+                rule,
+                outlet_addr,
+                authorized.clone(),
+            ));
+            self.add_session(session);
+        }
+        Ok(inlet)
+    }
+
+    /// Create an inlet to `addr` or, if a service is specified, to one of the
+    /// publishers of the service in the registry of the project `addr`
+    #[allow(clippy::too_many_arguments)]
+    async fn create_inlet_impl(
+        &self,
+        ctx: &Context,
+        listen_addr: String,
+        port_range: Option<PortRange>,
+        requested_alias: Option<String>,
+        prefix_route: Route,
+        suffix_route: Route,
+        addr: MultiAddr,
+        service: Option<String>,
+        wait_for_outlet_duration: Option<Duration>,
+        authorized: Option<Identifier>,
     ) -> Result<InletStatus> {
         // The addressing scheme is very flexible. Typically the node connects to
         // the cloud via secure channel and the with another secure channel via
@@ -529,22 +1567,32 @@ impl InMemoryNode {
         // to another node.
         let duration = wait_for_outlet_duration.unwrap_or(Duration::from_secs(5));
         let connection_ctx = Arc::new(ctx.async_try_clone().await?);
-        let connection = self
-            .make_connection(
-                connection_ctx.clone(),
-                &outlet_addr,
-                None,
-                authorized.clone(),
-                None,
-                Some(duration),
-            )
-            .await?;
+        let (outlet_addr, connection) = match &service {
+            Some(service) => {
+                self.connect_to_service(connection_ctx.clone(), &addr, service, None, duration)
+                    .await?
+            }
+            None => {
+                let connection = self
+                    .make_connection(
+                        connection_ctx.clone(),
+                        &addr,
+                        None,
+                        authorized.clone(),
+                        None,
+                        Some(duration),
+                    )
+                    .await?;
+                (addr.clone(), connection)
+            }
+        };
 
         let (inlet, access_control) = self
             .node_manager
             .create_inlet(
                 connection.clone(),
-                listen_addr.clone(),
+                listen_addr,
+                port_range,
                 requested_alias,
                 prefix_route.clone(),
                 suffix_route.clone(),
@@ -566,8 +1614,11 @@ impl InMemoryNode {
                 connection_ctx,
                 connection,
                 Address::from_string(inlet.worker_addr.clone()),
-                listen_addr,
+                inlet.alias.clone(),
+                // a replaced inlet keeps listening on the port chosen when it was created
+                inlet.bind_addr.clone(),
                 outlet_addr,
+                service.map(|service| (addr, service)),
                 prefix_route,
                 suffix_route,
                 authorized,
@@ -584,14 +1635,19 @@ impl InMemoryNode {
     /// This returns a function that accepts the previous ping address (e.g.
     /// the secure channel worker address) and constructs the whole route
     /// again.
+    ///
+    /// If the inlet has been created for a service, given as a project address and a
+    /// service name, the service is resolved again to connect to another publisher.
     #[allow(clippy::too_many_arguments)]
     fn portal_replacer(
         node_manager: Arc<NodeManager>,
         ctx: Arc<Context>,
         connection: Connection,
         inlet_address: Address,
+        alias: String,
         bind: String,
         addr: MultiAddr,
+        service: Option<(MultiAddr, String)>,
         prefix_route: Route,
         suffix_route: Route,
         authorized: Option<Identifier>,
@@ -599,11 +1655,15 @@ impl InMemoryNode {
     ) -> Replacer {
         let connection_arc = Arc::new(Mutex::new(connection.clone()));
         let inlet_address_arc = Arc::new(Mutex::new(inlet_address));
+        let addr_arc = Arc::new(Mutex::new(addr));
         let node_manager = node_manager.clone();
 
         Box::new(move |previous_addr| {
-            let addr = addr.clone();
+            let addr_arc = addr_arc.clone();
+            let addr = addr_arc.lock().unwrap().clone();
+            let service = service.clone();
             let authorized = authorized.clone();
+            let alias = alias.clone();
             let bind = bind.clone();
             let access = access.clone();
             let ctx = ctx.clone();
@@ -636,33 +1696,72 @@ impl InMemoryNode {
                         }
                     }
 
-                    // The previous inlet worker needs to be stopped:
-                    if let Err(error) = node_manager
-                        .tcp_transport
-                        .stop_inlet(inlet_address.clone())
-                        .await
-                    {
-                        debug!("cannot stop inlet `{inlet_address}`: {error}");
+                    // The previous inlet worker needs to be stopped, unless its connections
+                    // can resume their sessions over the new route
+                    let session_resumption = node_manager
+                        .registry
+                        .portal_session_resumptions
+                        .get(&alias)
+                        .await;
+                    if session_resumption.is_none() {
+                        if let Err(error) = node_manager
+                            .tcp_transport
+                            .stop_inlet(inlet_address.clone())
+                            .await
+                        {
+                            debug!("cannot stop inlet `{inlet_address}`: {error}");
+                        }
                     }
 
                     // Now a connection attempt is made
-                    let new_connection = node_manager
-                        .make_connection(
-                            ctx.clone(),
-                            &addr,
-                            None,
-                            authorized,
-                            None,
-                            Some(MAX_CONNECT_TIME),
-                        )
-                        .await?;
+                    let (new_addr, new_connection) = match &service {
+                        Some((project, service)) => {
+                            node_manager
+                                .connect_to_service(
+                                    ctx.clone(),
+                                    project,
+                                    service,
+                                    Some(&addr),
+                                    MAX_CONNECT_TIME,
+                                )
+                                .await?
+                        }
+                        None => {
+                            let connection = node_manager
+                                .make_connection(
+                                    ctx.clone(),
+                                    &addr,
+                                    None,
+                                    authorized,
+                                    None,
+                                    Some(MAX_CONNECT_TIME),
+                                )
+                                .await?;
+                            (addr.clone(), connection)
+                        }
+                    };
                     *connection_arc.lock().unwrap() = new_connection.clone();
+                    *addr_arc.lock().unwrap() = new_addr;
                     let connection_route =
                         new_connection.route(node_manager.tcp_transport()).await?;
 
                     //we expect a fully normalized MultiAddr
                     let normalized_route = route![prefix_route, connection_route, suffix_route];
+                    if let Some(session_resumption) = session_resumption {
+                        debug!(%alias, route = %normalized_route, "resuming the inlet sessions");
+                        session_resumption.set_route(normalized_route);
+                        return Ok(new_connection.transport_route());
+                    }
                     let options = TcpInletOptions::new().with_incoming_access_control(access);
+                    let options = node_manager
+                        .resource_profile()
+                        .settings()
+                        .inlet_options(options);
+                    let options = options.with_interceptor(
+                        node_manager
+                            .portal_interceptor(&alias, PortalSide::Inlet)
+                            .await,
+                    );
 
                     // Finally attempt to create a new inlet using the new route:
                     let new_inlet_address = node_manager
@@ -691,3 +1790,115 @@ impl InMemoryNode {
         })
     }
 }
+
+impl InMemoryNode {
+    /// Create a session replacer for a mapping of a transparent inlet.
+    ///
+    /// The inlet keeps listening: only the route used for the destinations of the mapping is
+    /// replaced, once the connection to its outlet has been re-established
+    fn transparent_route_replacer(
+        node_manager: Arc<NodeManager>,
+        ctx: Arc<Context>,
+        connection: Connection,
+        transparent_proxy: TransparentProxy,
+        rule: DestinationRule,
+        addr: MultiAddr,
+        authorized: Option<Identifier>,
+    ) -> Replacer {
+        let connection_arc = Arc::new(Mutex::new(connection));
+
+        Box::new(move |previous_addr| {
+            let node_manager = node_manager.clone();
+            let ctx = ctx.clone();
+            let transparent_proxy = transparent_proxy.clone();
+            let rule = rule.clone();
+            let addr = addr.clone();
+            let authorized = authorized.clone();
+            let connection_arc = connection_arc.clone();
+            let previous_connection = connection_arc.lock().unwrap().clone();
+            Box::pin(async move {
+                debug!(%previous_addr, %addr, %rule, "replacing the route of a transparent tcp inlet");
+                let f = async {
+                    for encryptor in &previous_connection.secure_channel_encryptors {
+                        if let Err(error) =
+                            node_manager.delete_secure_channel(&ctx, encryptor).await
+                        {
+                            debug!("cannot delete secure channel `{encryptor}`: {error}");
+                        }
+                    }
+                    if let Some(tcp_connection) = previous_connection.tcp_connection.as_ref() {
+                        if let Err(error) = node_manager
+                            .tcp_transport
+                            .disconnect(tcp_connection.sender_address().clone())
+                            .await
+                        {
+                            debug!("cannot stop tcp worker `{tcp_connection}`: {error}");
+                        }
+                    }
+
+                    let new_connection = node_manager
+                        .make_connection(
+                            ctx.clone(),
+                            &addr,
+                            None,
+                            authorized,
+                            None,
+                            Some(MAX_CONNECT_TIME),
+                        )
+                        .await?;
+                    *connection_arc.lock().unwrap() = new_connection.clone();
+                    transparent_proxy.set_route(
+                        rule,
+                        new_connection.route(node_manager.tcp_transport()).await?,
+                    );
+                    Ok(new_connection.transport_route())
+                };
+
+                match timeout(MAX_RECOVERY_TIME, f).await {
+                    Err(_) => {
+                        warn!(%addr, "timeout replacing the route of a transparent tcp inlet");
+                        Err(ApiError::core("timeout"))
+                    }
+                    Ok(Err(e)) => {
+                        warn!(%addr, err = %e, "error replacing the route of a transparent tcp inlet");
+                        Err(e)
+                    }
+                    Ok(Ok(route)) => Ok(route),
+                }
+            })
+        })
+    }
+}
+
+/// Limit the number of outlet connections opened by each identity.
+/// Connections which are not coming from a secure channel are not limited
+#[derive(Clone)]
+struct IdentityQuotasLimiter {
+    quotas: IdentityQuotas,
+}
+
+impl IdentityQuotasLimiter {
+    fn new(quotas: IdentityQuotas) -> Self {
+        Self { quotas }
+    }
+}
+
+impl std::fmt::Debug for IdentityQuotasLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("IdentityQuotasLimiter")
+    }
+}
+
+impl OutletConnectionLimiter for IdentityQuotasLimiter {
+    fn acquire(&self, message: &LocalMessage) -> Result<OutletConnectionPermit> {
+        match IdentitySecureChannelLocalInfo::find_info(message) {
+            Ok(info) => {
+                let permit = self
+                    .quotas
+                    .try_acquire(&info.their_identity_id(), QuotaKind::PortalConnections)?;
+                Ok(Box::new(permit))
+            }
+            Err(_) => Ok(Box::new(())),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use miette::IntoDiagnostic;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use ockam::compat::sync::Mutex;
 use ockam::identity::Identifier;
@@ -21,6 +21,7 @@ use crate::nodes::models::secure_channel::{
 };
 use crate::nodes::service::in_memory_node::InMemoryNode;
 use crate::nodes::BackgroundNode;
+use crate::notifier::NotificationEvent;
 use crate::session::sessions::{Replacer, Session};
 use crate::session::sessions::{MAX_CONNECT_TIME, MAX_RECOVERY_TIME};
 
@@ -274,6 +275,8 @@ impl InMemoryNode {
     ) -> Replacer {
         let connection_arc = Arc::new(Mutex::new(connection));
         let node_manager = node_manager.clone();
+        // time of the first failed attempt to recreate the relay since it was last up
+        let down_since: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
         Box::new(move |prev_route| {
             let ctx = ctx.clone();
             let addr = addr.clone();
@@ -282,6 +285,7 @@ impl InMemoryNode {
             let connection_arc = connection_arc.clone();
             let previous_connection = connection_arc.lock().unwrap().clone();
             let node_manager = node_manager.clone();
+            let down_since = down_since.clone();
 
             Box::pin(async move {
                 debug!(%prev_route, %addr, "creating new remote relay");
@@ -329,7 +333,7 @@ impl InMemoryNode {
                     }
                     Ok(connection.transport_route())
                 };
-                match timeout(MAX_RECOVERY_TIME, f).await {
+                let result = match timeout(MAX_RECOVERY_TIME, f).await {
                     Err(_) => {
                         warn!(%addr, "timeout creating new remote relay");
                         Err(ApiError::core("timeout"))
@@ -339,7 +343,23 @@ impl InMemoryNode {
                         Err(e)
                     }
                     Ok(Ok(a)) => Ok(a),
+                };
+                if result.is_ok() {
+                    *down_since.lock().unwrap() = None;
+                } else if let Some(notifier) = node_manager.notifier() {
+                    let down_for = down_since
+                        .lock()
+                        .unwrap()
+                        .get_or_insert_with(Instant::now)
+                        .elapsed();
+                    if down_for >= notifier.config().relay_down_after() {
+                        notifier.notify(NotificationEvent::RelayDown {
+                            relay: alias.clone().unwrap_or_else(|| addr.to_string()),
+                            down_for_secs: down_for.as_secs(),
+                        })
+                    }
                 }
+                result
             })
         })
     }
@@ -0,0 +1,270 @@
+//! Roles of the remote administrators of a node.
+
+use core::fmt;
+use core::str::FromStr;
+
+use ockam::identity::Identifier;
+use ockam_abac::{AbacAccessControl, Action, Env, Resource};
+use ockam_core::api::{Method, RequestHeader};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::Result;
+
+use super::NodeManager;
+use crate::DefaultAddress;
+
+/// Resource of the role policies
+pub const NODE_MANAGER_RESOURCE: &str = "node_manager";
+
+/// Role of an identity administrating a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Can only read the state of the node
+    Viewer,
+    /// Can also create and delete portals, relays and services
+    Operator,
+    /// Can send all the requests, including the changes of the policies
+    Admin,
+}
+
+impl Role {
+    /// All the roles, from the highest to the lowest
+    pub const ALL: [Role; 3] = [Role::Admin, Role::Operator, Role::Viewer];
+
+    /// Action of the policy granting this role
+    pub fn action(&self) -> Action {
+        Action::new(&self.to_string())
+    }
+
+    /// Return true if this role can send a request
+    pub fn allows(&self, req: &RequestHeader) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Operator => Self::is_operator_request(req),
+            Role::Viewer => matches!(req.method(), Some(Method::Get)),
+        }
+    }
+
+    /// Requests creating or deleting portals, relays and services.
+    /// The services giving access to the identities and credentials of the node, as well as
+    /// the taps of the portals, are left to the administrators
+    fn is_operator_request(req: &RequestHeader) -> bool {
+        use Method::*;
+
+        let Some(method) = req.method() else {
+            return false;
+        };
+        matches!(
+            (method, req.path_segments::<5>().as_slice()),
+            (Get, _)
+                | (Post, ["node", "inlet"])
+                | (Delete, ["node", "inlet", _])
+                | (Post, ["node", "outlet"])
+                | (Delete, ["node", "outlet", _])
+                | (Post, ["node", "forwarder"])
+                | (Delete, ["node", "forwarder", _])
+                | (Put, ["node", "services", _, "target"])
+                | (
+                    Post,
+                    [
+                        "node",
+                        "services",
+                        DefaultAddress::ECHO_SERVICE
+                            | DefaultAddress::HOP_SERVICE
+                            | DefaultAddress::UPPERCASE_SERVICE
+                            | DefaultAddress::KV_STORE
+                    ],
+                )
+                | (
+                    Post | Delete,
+                    [
+                        "node",
+                        "services",
+                        DefaultAddress::KAFKA_OUTLET
+                            | DefaultAddress::KAFKA_CONSUMER
+                            | DefaultAddress::KAFKA_PRODUCER
+                            | DefaultAddress::KAFKA_DIRECT
+                    ],
+                )
+        )
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Role::Admin => "admin",
+            Role::Operator => "operator",
+            Role::Viewer => "viewer",
+        })
+    }
+}
+
+impl FromStr for Role {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            "operator" => Ok(Role::Operator),
+            "viewer" => Ok(Role::Viewer),
+            _ => Err(ockam_core::Error::new(
+                Origin::Api,
+                Kind::Invalid,
+                format!("unknown role {s}, expected admin, operator or viewer"),
+            )),
+        }
+    }
+}
+
+impl NodeManager {
+    /// Return true if a remote identity has a role allowing a request.
+    /// The requests of the other identities are denied when no role policy is defined
+    pub(super) async fn is_authorized_by_role(
+        &self,
+        identifier: &Identifier,
+        req: &RequestHeader,
+    ) -> Result<bool> {
+        if identifier == self.identifier() {
+            return Ok(true);
+        }
+        let resource = Resource::new(NODE_MANAGER_RESOURCE);
+        let policies = self.policies.policies(&resource).await?;
+        for role in Role::ALL {
+            let Some((_, expression)) = policies.iter().find(|(a, _)| a == &role.action()) else {
+                continue;
+            };
+            let access_control = AbacAccessControl::new(
+                self.identities_repository(),
+                expression.clone(),
+                Env::new(),
+            );
+            if access_control
+                .is_identity_authorized(identifier.clone())
+                .await?
+            {
+                debug!(%identifier, %role, path = %req.path(), "role granted");
+                return Ok(role.allows(req));
+            }
+        }
+        debug!(%identifier, "no role granted");
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nodes::NODEMANAGER_ADDR;
+    use crate::test_utils::start_manager_for_tests;
+    use ockam::identity::{SecureChannelListenerOptions, SecureChannelOptions};
+    use ockam_abac::parse;
+    use ockam_core::api::{Request, Response, Status};
+    use ockam_core::route;
+    use ockam_node::api::Client;
+    use ockam_node::Context;
+
+    #[test]
+    fn test_roles() {
+        let status = Request::get("/node");
+        let create_outlet = Request::post("/node/outlet");
+        let delete_inlet = Request::delete("/node/inlet/db");
+        let create_echo = Request::post("/node/services/echo");
+        let create_credentials = Request::post("/node/services/credentials");
+        let create_tap = Request::post("/node/inlet/db/tap");
+        let create_policy = Request::post("/policy/node_manager/admin");
+
+        assert!(Role::Viewer.allows(status.header()));
+        assert!(!Role::Viewer.allows(create_outlet.header()));
+        assert!(Role::Operator.allows(create_outlet.header()));
+        assert!(Role::Operator.allows(delete_inlet.header()));
+        assert!(Role::Operator.allows(create_echo.header()));
+        assert!(!Role::Operator.allows(create_credentials.header()));
+        assert!(!Role::Operator.allows(create_tap.header()));
+        assert!(!Role::Operator.allows(create_policy.header()));
+        assert!(Role::Admin.allows(create_policy.header()));
+
+        assert_eq!(Role::from_str("operator").unwrap(), Role::Operator);
+        assert!(Role::from_str("root").is_err());
+    }
+
+    #[ockam_macros::test]
+    async fn test_requests_denied_by_role(context: &mut Context) -> Result<()> {
+        let handler = start_manager_for_tests(context).await?;
+        let node_manager = handler.node_manager.clone();
+
+        // a remote identity connects to the node manager with a secure channel
+        let options = SecureChannelListenerOptions::new();
+        context
+            .flow_controls()
+            .add_consumer(NODEMANAGER_ADDR, &options.spawner_flow_control_id());
+        handler
+            .secure_channels
+            .create_secure_channel_listener(
+                context,
+                node_manager.identifier(),
+                "remote_api",
+                options,
+            )
+            .await?;
+        let remote = handler
+            .secure_channels
+            .identities()
+            .identities_creation()
+            .create_identity()
+            .await?;
+        let channel = handler
+            .secure_channels
+            .create_secure_channel(
+                context,
+                remote.identifier(),
+                "remote_api",
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        let client = Client::new(&route![channel, NODEMANAGER_ADDR], None);
+
+        // without a role policy, the remote identity can't send any request
+        assert_eq!(
+            status(context, &client, Request::get("/node/inlet")).await?,
+            Some(Status::Forbidden)
+        );
+
+        // a viewer can only read the state of the node
+        let expression = parse(&format!(
+            r#"(= subject.identifier "{}")"#,
+            remote.identifier()
+        ))?
+        .unwrap();
+        node_manager
+            .policies
+            .set_policy(
+                &Resource::new(NODE_MANAGER_RESOURCE),
+                &Role::Viewer.action(),
+                &expression,
+            )
+            .await?;
+        assert_eq!(
+            status(context, &client, Request::get("/node/inlet")).await?,
+            Some(Status::Ok)
+        );
+        assert_eq!(
+            status(context, &client, Request::post("/node/outlet")).await?,
+            Some(Status::Forbidden)
+        );
+        assert_eq!(
+            status(context, &client, Request::delete("/node/inlet/db")).await?,
+            Some(Status::Forbidden)
+        );
+
+        context.stop().await
+    }
+
+    async fn status(
+        context: &Context,
+        client: &Client,
+        req: Request<()>,
+    ) -> Result<Option<Status>> {
+        let bytes = client.request(context, req).await?;
+        Ok(Response::parse_response_header(&bytes)?.0.status())
+    }
+}
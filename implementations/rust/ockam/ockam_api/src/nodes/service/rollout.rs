@@ -0,0 +1,100 @@
+//! Rollouts of configuration updates started on a control node, see [`crate::rollout`].
+
+use minicbor::{Decode, Decoder, Encode};
+
+use ockam_core::api::{Error, RequestHeader, Response};
+
+use super::NodeManagerWorker;
+use crate::config_updates::ConfigUpdate;
+use crate::rollout::{Rollout, RolloutSpec};
+
+/// Request body to start a rollout.
+///
+/// The rollback update is created with the version following `version`
+#[derive(Debug, Clone, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct CreateRollout {
+    #[n(1)] pub spec: RolloutSpec,
+    /// YAML configuration rolled out to the nodes
+    #[n(2)] pub config: String,
+    #[n(3)] pub version: u64,
+    /// YAML configuration restoring the previous configuration of the nodes
+    #[n(4)] pub rollback_config: String,
+}
+
+impl NodeManagerWorker {
+    pub(super) async fn create_rollout(
+        &self,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response<Rollout>, Response<Error>> {
+        let request: CreateRollout = dec.decode()?;
+        let rollouts = self.fleet_inventory(req)?.rollouts();
+        let bad_request = |e: ockam_core::Error| Response::bad_request(req, &e.to_string());
+        let update = ConfigUpdate::new(request.version, request.config).map_err(bad_request)?;
+        let rollback =
+            ConfigUpdate::new(request.version + 1, request.rollback_config).map_err(bad_request)?;
+
+        // the nodes accept the updates signed by the identity of this node
+        let update = self.node_manager.sign_config_update(update).await?;
+        let rollback = self.node_manager.sign_config_update(rollback).await?;
+        let rollout = Rollout::new(request.spec, update, rollback).map_err(bad_request)?;
+        rollouts
+            .create(rollout.clone())
+            .await
+            .map_err(bad_request)?;
+        info!(rollout = %rollout.spec.id, version = request.version, "Started a rollout");
+        Ok(Response::ok(req).body(rollout))
+    }
+
+    pub(super) async fn list_rollouts(
+        &self,
+        req: &RequestHeader,
+    ) -> Result<Response<Vec<Rollout>>, Response<Error>> {
+        let rollouts = self.fleet_inventory(req)?.rollouts().list().await?;
+        Ok(Response::ok(req).body(rollouts))
+    }
+
+    pub(super) async fn get_rollout(
+        &self,
+        req: &RequestHeader,
+        id: &str,
+    ) -> Result<Response<Rollout>, Response<Error>> {
+        match self.fleet_inventory(req)?.rollouts().get(id).await? {
+            Some(rollout) => Ok(Response::ok(req).body(rollout)),
+            None => Err(Response::not_found(
+                req,
+                &format!("The rollout {id} doesn't exist"),
+            )),
+        }
+    }
+
+    /// Stop a running rollout and roll back the nodes which received the update
+    pub(super) async fn abort_rollout(
+        &self,
+        req: &RequestHeader,
+        id: &str,
+    ) -> Result<Response<Rollout>, Response<Error>> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let rollout = self
+            .fleet_inventory(req)?
+            .rollouts()
+            .update(id, |rollout| {
+                rollout.abort("the rollout was aborted", now)?;
+                Ok(true)
+            })
+            .await
+            .map_err(|e| Response::bad_request(req, &e.to_string()))?;
+        match rollout {
+            Some(rollout) => Ok(Response::ok(req).body(rollout)),
+            None => Err(Response::not_found(
+                req,
+                &format!("The rollout {id} doesn't exist"),
+            )),
+        }
+    }
+}
@@ -3,7 +3,8 @@ use std::time::Duration;
 
 use minicbor::Decoder;
 
-use ockam::identity::models::CredentialAndPurposeKey;
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialData};
+use ockam::identity::utils::now;
 use ockam::identity::TrustEveryonePolicy;
 use ockam::identity::Vault;
 use ockam::identity::{
@@ -24,13 +25,14 @@ use crate::cli_state::StateItemTrait;
 use crate::nodes::models::secure_channel::{
     CreateSecureChannelListenerRequest, CreateSecureChannelRequest, CreateSecureChannelResponse,
     DeleteSecureChannelListenerRequest, DeleteSecureChannelListenerResponse,
-    DeleteSecureChannelRequest, DeleteSecureChannelResponse, SecureChannelListenersList,
-    ShowSecureChannelListenerRequest, ShowSecureChannelListenerResponse, ShowSecureChannelRequest,
-    ShowSecureChannelResponse,
+    DeleteSecureChannelRequest, DeleteSecureChannelResponse, ExchangeCredentialsRequest,
+    SecureChannelListenersList, ShowSecureChannelListenerRequest,
+    ShowSecureChannelListenerResponse, ShowSecureChannelRequest, ShowSecureChannelResponse,
 };
 use crate::nodes::registry::{SecureChannelInfo, SecureChannelListenerInfo};
 use crate::nodes::service::NodeIdentities;
 use crate::nodes::{NodeManager, NodeManagerWorker};
+use crate::notifier::NotificationEvent;
 use crate::DefaultAddress;
 
 /// SECURE CHANNELS
@@ -135,6 +137,24 @@ impl NodeManagerWorker {
         let info = self.node_manager.get_secure_channel(&sc_address).await;
         Ok(Response::ok(req).body(ShowSecureChannelResponse::new(info)))
     }
+
+    pub async fn exchange_credentials(
+        &self,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+        ctx: &Context,
+    ) -> Result<Response, Response<Error>> {
+        let body: ExchangeCredentialsRequest = dec.decode()?;
+        let addr = Address::from(body.channel);
+        if self.node_manager.get_secure_channel(&addr).await.is_none() {
+            return Err(Response::not_found(
+                req,
+                &format!("Secure Channel, {}, not found.", addr),
+            ));
+        }
+        self.node_manager.exchange_credentials(ctx, &addr).await?;
+        Ok(Response::ok(req))
+    }
 }
 
 /// SECURE CHANNEL LISTENERS
@@ -320,22 +340,54 @@ impl NodeManager {
         } else {
             match self.trust_context().ok() {
                 Some(tc) => {
-                    if let Some(t) = timeout {
-                        ockam_node::compat::timeout(t, tc.get_credential(ctx, identifier))
-                            .await
-                            .map_err(|e| {
-                                ockam_core::Error::new(Origin::Api, Kind::Timeout, e.to_string())
-                            })?
-                    } else {
-                        tc.get_credential(ctx, identifier).await
-                    }
+                    let timeout = timeout.unwrap_or(self.timeouts().credential_exchange());
+                    ockam_node::compat::timeout(timeout, tc.get_credential(ctx, identifier))
+                        .await
+                        .map_err(|e| {
+                            ockam_core::Error::new(Origin::Api, Kind::Timeout, e.to_string())
+                        })?
                 }
                 None => None,
             }
         };
+        if let Some(credential) = &credential {
+            self.notify_credential_expiry(identifier, credential);
+        }
         Ok(credential)
     }
 
+    /// Notify a credential which expires before the end of the warning period
+    fn notify_credential_expiry(
+        &self,
+        identifier: &Identifier,
+        credential: &CredentialAndPurposeKey,
+    ) {
+        let notifier = match self.notifier() {
+            Some(notifier) => notifier,
+            None => return,
+        };
+        let credential_data = match credential
+            .credential
+            .get_versioned_data()
+            .and_then(|versioned_data| CredentialData::get_data(&versioned_data))
+        {
+            Ok(credential_data) => credential_data,
+            Err(e) => {
+                warn!(%e, "The expiry of a credential can't be read");
+                return;
+            }
+        };
+        let warning = notifier.config().credential_expiry_warning().as_secs();
+        match now() {
+            Ok(now) if credential_data.expires_at.0 <= now.0.saturating_add(warning) => notifier
+                .notify(NotificationEvent::CredentialNearExpiry {
+                    identifier: identifier.to_string(),
+                    expires_at: credential_data.expires_at.0,
+                }),
+            _ => (),
+        }
+    }
+
     pub(crate) async fn create_secure_channel_internal(
         &self,
         ctx: &Context,
@@ -345,22 +397,43 @@ impl NodeManager {
         timeout: Option<Duration>,
         credential: Option<CredentialAndPurposeKey>,
     ) -> Result<SecureChannel> {
-        debug!(%sc_route, "Creating secure channel");
-        let options = SecureChannelOptions::new();
-
-        let options = if let Some(timeout) = timeout {
-            options.with_timeout(timeout)
-        } else {
-            options
+        let credential = match credential {
+            Some(credential) => Some(credential),
+            None => self.get_credential(ctx, identifier, None, timeout).await?,
         };
+        self.create_secure_channel_with_credential(
+            ctx,
+            sc_route,
+            identifier,
+            authorized_identifiers,
+            timeout,
+            credential,
+        )
+        .await
+    }
 
-        let options = if let Some(credential) = credential {
-            options.with_credential(credential)
-        } else if let Some(credential) = self.get_credential(ctx, identifier, None, timeout).await?
-        {
-            options.with_credential(credential)
-        } else {
-            options
+    /// Create a secure channel presenting exactly the given credential, if any.
+    /// Contrary to `create_secure_channel_internal` no credential is retrieved from the trust context
+    pub(crate) async fn create_secure_channel_with_credential(
+        &self,
+        ctx: &Context,
+        sc_route: Route,
+        identifier: &Identifier,
+        authorized_identifiers: Option<Vec<Identifier>>,
+        timeout: Option<Duration>,
+        credential: Option<CredentialAndPurposeKey>,
+    ) -> Result<SecureChannel> {
+        debug!(
+            %sc_route,
+            identity = %self.cli_state.display_name(identifier),
+            "Creating secure channel"
+        );
+        let options = SecureChannelOptions::new()
+            .with_timeout(timeout.unwrap_or(self.timeouts().handshake()));
+
+        let options = match credential {
+            Some(credential) => options.with_credential(credential),
+            None => options,
         };
 
         let options = match authorized_identifiers.clone() {
@@ -368,7 +441,7 @@ impl NodeManager {
             None => options.with_trust_policy(TrustEveryonePolicy),
         };
 
-        let options = match self.trust_context.clone() {
+        let options = match self.trust_context().ok() {
             Some(trust_context) => options.with_trust_context(trust_context),
             None => options,
         };
@@ -395,6 +468,26 @@ impl NodeManager {
         Ok(())
     }
 
+    /// Present the credential of the node again over an established secure channel, and store
+    /// the attributes of the credential presented back by the other party
+    pub async fn exchange_credentials(&self, ctx: &Context, addr: &Address) -> Result<()> {
+        debug!(%addr, "exchanging credentials over a secure channel");
+        let trust_context = self.trust_context()?;
+        let credential = trust_context
+            .authority()?
+            .credential(ctx, self.identifier())
+            .await?;
+        self.secure_channels
+            .exchange_credentials(
+                ctx,
+                addr,
+                DefaultAddress::CREDENTIALS_SERVICE,
+                &trust_context,
+                credential,
+            )
+            .await
+    }
+
     pub async fn get_secure_channel(&self, addr: &Address) -> Option<SecureChannelInfo> {
         debug!(%addr, "On show secure channel");
         self.registry.secure_channels.get_by_addr(addr).await
@@ -424,8 +517,9 @@ impl NodeManager {
         let secure_channels = self.build_secure_channels(vault_name.clone()).await?;
         let identifier = self.get_identifier(identity_name.clone()).await?;
 
-        let options =
-            SecureChannelListenerOptions::new().as_consumer(&self.api_transport_flow_control_id);
+        let options = SecureChannelListenerOptions::new()
+            .as_consumer(&self.api_transport_flow_control_id)
+            .with_quotas(self.quotas());
 
         let options = match authorized_identifiers {
             Some(ids) => options.with_trust_policy(TrustMultiIdentifiersPolicy::new(ids)),
@@ -433,7 +527,7 @@ impl NodeManager {
         };
 
         let options = if let Ok(trust_context) = self.trust_context() {
-            options.with_trust_context(trust_context.clone())
+            options.with_trust_context(trust_context)
         } else {
             options
         };
@@ -455,6 +549,9 @@ impl NodeManager {
         ctx.flow_controls()
             .add_consumer(DefaultAddress::ECHO_SERVICE, listener.flow_control_id());
 
+        ctx.flow_controls()
+            .add_consumer(DefaultAddress::PING_SERVICE, listener.flow_control_id());
+
         ctx.flow_controls().add_consumer(
             DefaultAddress::UPPERCASE_SERVICE,
             listener.flow_control_id(),
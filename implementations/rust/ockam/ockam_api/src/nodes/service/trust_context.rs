@@ -0,0 +1,151 @@
+//! Replacement of the trust context of a running node.
+
+use minicbor::Decoder;
+
+use ockam::identity::{Identifier, TrustContext};
+use ockam_core::api::{Error, RequestHeader, Response};
+use ockam_core::{Address, Result};
+use ockam_node::Context;
+
+use super::{NodeManager, NodeManagerWorker};
+use crate::config::cli::TrustContextConfig;
+use crate::nodes::models::trust_context::{
+    ChannelRevalidation, UpdateTrustContextRequest, UpdateTrustContextResponse,
+};
+use crate::DefaultAddress;
+
+impl NodeManager {
+    /// Replace the trust context of the node, then apply a revalidation policy to its
+    /// secure channels. Return the encryptor addresses of the closed channels
+    pub async fn update_trust_context(
+        &self,
+        ctx: &Context,
+        config: &TrustContextConfig,
+        revalidation: ChannelRevalidation,
+    ) -> Result<Vec<Address>> {
+        self.configure_trust_context(config).await?;
+        let trust_context = self.trust_context()?;
+        info!(
+            trust_context_id = trust_context.id(),
+            %revalidation,
+            "The trust context of the node has been replaced"
+        );
+
+        self.restart_credentials_service(ctx, &trust_context)
+            .await?;
+        self.revalidate_secure_channels(ctx, &trust_context, revalidation)
+            .await
+    }
+
+    /// The credentials service verifies the credentials with the authorities of a trust context
+    async fn restart_credentials_service(
+        &self,
+        ctx: &Context,
+        trust_context: &TrustContext,
+    ) -> Result<()> {
+        let addr = Address::from(DefaultAddress::CREDENTIALS_SERVICE);
+        if self
+            .registry
+            .credentials_services
+            .remove(&addr)
+            .await
+            .is_some()
+        {
+            ctx.stop_worker(addr.clone()).await?;
+        } else if !self.enable_credential_checks() {
+            return Ok(());
+        }
+        self.start_credentials_service_impl(ctx, trust_context.clone(), addr, false)
+            .await
+    }
+
+    async fn revalidate_secure_channels(
+        &self,
+        ctx: &Context,
+        trust_context: &TrustContext,
+        revalidation: ChannelRevalidation,
+    ) -> Result<Vec<Address>> {
+        if revalidation == ChannelRevalidation::Keep {
+            return Ok(vec![]);
+        }
+        let authorities = trusted_authorities(trust_context).await;
+        let attributes = self.identities_repository().as_attributes_reader();
+
+        let mut closed = vec![];
+        let channels = self
+            .secure_channels
+            .secure_channel_registry()
+            .get_channel_list();
+        for channel in channels {
+            let peer = channel.their_id();
+            let is_valid = match revalidation {
+                ChannelRevalidation::Keep => true,
+                ChannelRevalidation::Close => false,
+                ChannelRevalidation::Revalidate => {
+                    authorities.contains(peer)
+                        || attributes
+                            .get_attributes(peer)
+                            .await?
+                            .and_then(|entry| entry.attested_by())
+                            .map(|authority| authorities.contains(&authority))
+                            .unwrap_or(false)
+                }
+            };
+            if is_valid {
+                continue;
+            }
+            let addr = channel.encryptor_messaging_address().clone();
+            match self.delete_secure_channel(ctx, &addr).await {
+                Ok(()) => {
+                    debug!(%addr, %peer, "Closed a secure channel after a trust context update");
+                    closed.push(addr);
+                }
+                Err(e) => warn!(%addr, %e, "The secure channel can't be closed"),
+            }
+        }
+        Ok(closed)
+    }
+}
+
+/// Identifiers of the authorities, and federated authorities, of a trust context
+async fn trusted_authorities(trust_context: &TrustContext) -> Vec<Identifier> {
+    let mut authorities = trust_context.authorities().await.unwrap_or_default();
+    authorities.extend(
+        trust_context
+            .federated_authorities()
+            .iter()
+            .map(|authority| authority.identifier().clone()),
+    );
+    authorities
+}
+
+impl NodeManagerWorker {
+    pub(super) async fn update_trust_context(
+        &self,
+        ctx: &Context,
+        req: &RequestHeader,
+        dec: &mut Decoder<'_>,
+    ) -> Result<Response<UpdateTrustContextResponse>, Response<Error>> {
+        let request: UpdateTrustContextRequest = dec.decode()?;
+        let config = request
+            .trust_context()
+            .map_err(|e| Response::bad_request(req, &e.to_string()))?;
+        let closed_channels = self
+            .node_manager
+            .update_trust_context(ctx, &config, request.revalidation)
+            .await?;
+        let trust_context = self.node_manager.trust_context()?;
+        Ok(Response::ok(req).body(UpdateTrustContextResponse {
+            trust_context_id: trust_context.id().to_string(),
+            authorities: trusted_authorities(&trust_context)
+                .await
+                .iter()
+                .map(|authority| authority.to_string())
+                .collect(),
+            closed_channels: closed_channels
+                .iter()
+                .map(|addr| addr.address().to_string())
+                .collect(),
+        }))
+    }
+}
@@ -0,0 +1,458 @@
+//! Notifications of the critical events of a node, sent to webhooks.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_retry::strategy::{jitter, ExponentialBackoff};
+use tokio_retry::Retry;
+use url::Url;
+
+use ockam_core::Result;
+
+use crate::error::ApiError;
+
+/// Maximum number of notifications waiting to be sent
+const MAX_PENDING_NOTIFICATIONS: usize = 1_000;
+
+/// Maximum time to wait for the response of a webhook
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_retries() -> usize {
+    3
+}
+
+fn default_relay_down_after_secs() -> u64 {
+    300
+}
+
+fn default_credential_expiry_warning_secs() -> u64 {
+    86_400
+}
+
+fn default_repeat_after_secs() -> u64 {
+    3_600
+}
+
+/// Kinds of events which can be notified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    CredentialNearExpiry,
+    RelayDown,
+    PolicyChanged,
+    IdentityChanged,
+}
+
+/// A critical event of a node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum NotificationEvent {
+    /// A credential of the node expires soon
+    CredentialNearExpiry {
+        identifier: String,
+        /// Seconds since the Unix epoch
+        expires_at: u64,
+    },
+    /// A relay couldn't be recreated for some time
+    RelayDown { relay: String, down_for_secs: u64 },
+    /// A policy was set, or deleted if it doesn't have an expression
+    PolicyChanged {
+        resource: String,
+        action: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expression: Option<String>,
+    },
+    /// The change history of a known identity changed
+    IdentityChanged { identifier: String },
+}
+
+impl NotificationEvent {
+    pub fn kind(&self) -> NotificationKind {
+        match self {
+            NotificationEvent::CredentialNearExpiry { .. } => {
+                NotificationKind::CredentialNearExpiry
+            }
+            NotificationEvent::RelayDown { .. } => NotificationKind::RelayDown,
+            NotificationEvent::PolicyChanged { .. } => NotificationKind::PolicyChanged,
+            NotificationEvent::IdentityChanged { .. } => NotificationKind::IdentityChanged,
+        }
+    }
+
+    /// Key identifying the repetitions of the same event.
+    /// Policy and identity changes are always notified
+    fn repetition_key(&self) -> Option<String> {
+        match self {
+            NotificationEvent::CredentialNearExpiry {
+                identifier,
+                expires_at,
+            } => Some(format!("credential:{identifier}:{expires_at}")),
+            NotificationEvent::RelayDown { relay, .. } => Some(format!("relay:{relay}")),
+            NotificationEvent::PolicyChanged { .. } | NotificationEvent::IdentityChanged { .. } => {
+                None
+            }
+        }
+    }
+}
+
+impl Display for NotificationEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NotificationEvent::CredentialNearExpiry {
+                identifier,
+                expires_at,
+            } => write!(
+                f,
+                "the credential of {identifier} expires at {expires_at} (Unix time)"
+            ),
+            NotificationEvent::RelayDown {
+                relay,
+                down_for_secs,
+            } => write!(f, "the relay {relay} is down since {down_for_secs} seconds"),
+            NotificationEvent::PolicyChanged {
+                resource,
+                action,
+                expression: Some(expression),
+            } => write!(
+                f,
+                "the policy of {resource}/{action} was set to {expression}"
+            ),
+            NotificationEvent::PolicyChanged {
+                resource,
+                action,
+                expression: None,
+            } => write!(f, "the policy of {resource}/{action} was deleted"),
+            NotificationEvent::IdentityChanged { identifier } => {
+                write!(f, "the identity {identifier} changed")
+            }
+        }
+    }
+}
+
+/// A notification, serialized as a JSON object
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub node: String,
+    /// Description of the event, for humans
+    pub message: String,
+    #[serde(flatten)]
+    pub event: NotificationEvent,
+}
+
+impl Notification {
+    fn new(node: &str, event: NotificationEvent) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            node: node.to_string(),
+            message: format!("node {node}: {event}"),
+            event,
+        }
+    }
+
+    /// Return the fields of the notification which can be used in a template
+    fn fields(&self) -> BTreeMap<String, String> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(fields)) => fields
+                .into_iter()
+                .map(|(name, value)| match value {
+                    serde_json::Value::String(s) => (name, s),
+                    value => (name, value.to_string()),
+                })
+                .collect(),
+            _ => BTreeMap::new(),
+        }
+    }
+}
+
+/// Replace the `{{field}}` placeholders of a template with the fields of a notification.
+/// Unknown fields are replaced with an empty string
+fn render(template: &str, fields: &BTreeMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+        match rest[start + 2..].find("}}") {
+            Some(end) => {
+                let name = rest[start + 2..start + 2 + end].trim();
+                if let Some(value) = fields.get(name) {
+                    rendered.push_str(value);
+                }
+                rest = &rest[start + 2 + end + 2..];
+            }
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// A webhook receiving notifications
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: Url,
+    /// Kinds of events posted to this webhook, all of them if empty
+    #[serde(default)]
+    pub events: Vec<NotificationKind>,
+    /// Template of the request body, the JSON notification if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Headers added to the requests, for example an `Authorization` header
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Number of retries when a notification can't be posted
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+}
+
+impl WebhookConfig {
+    fn accepts(&self, kind: NotificationKind) -> bool {
+        self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    fn body(&self, notification: &Notification) -> Result<String> {
+        match &self.template {
+            Some(template) => Ok(render(template, &notification.fields())),
+            None => serde_json::to_string(notification).map_err(|e| ApiError::core(e.to_string())),
+        }
+    }
+}
+
+/// Notifications configuration of a node, read from a JSON file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotifierConfig {
+    pub webhooks: Vec<WebhookConfig>,
+    /// Time after which a relay which can't be recreated is notified
+    #[serde(default = "default_relay_down_after_secs")]
+    pub relay_down_after_secs: u64,
+    /// Time before the expiry of a credential when it is notified
+    #[serde(default = "default_credential_expiry_warning_secs")]
+    pub credential_expiry_warning_secs: u64,
+    /// Minimum time between two notifications of the same event
+    #[serde(default = "default_repeat_after_secs")]
+    pub repeat_after_secs: u64,
+}
+
+impl NotifierConfig {
+    /// Read a configuration from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the notifications configuration {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the notifications configuration {} is invalid: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    pub fn relay_down_after(&self) -> Duration {
+        Duration::from_secs(self.relay_down_after_secs)
+    }
+
+    pub fn credential_expiry_warning(&self) -> Duration {
+        Duration::from_secs(self.credential_expiry_warning_secs)
+    }
+
+    pub fn repeat_after(&self) -> Duration {
+        Duration::from_secs(self.repeat_after_secs)
+    }
+}
+
+/// Emitter of the notifications of a node
+#[derive(Clone)]
+pub struct Notifier {
+    node_name: String,
+    config: Arc<NotifierConfig>,
+    sender: mpsc::Sender<Notification>,
+    last_notified: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Notifier")
+    }
+}
+
+impl Notifier {
+    /// Start sending the notifications of a node to the configured webhooks
+    pub fn start(node_name: impl Into<String>, config: NotifierConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_NOTIFICATIONS);
+        let config = Arc::new(config);
+        info!(
+            "Sending the notifications to {} webhook(s)",
+            config.webhooks.len()
+        );
+        tokio::spawn(send_notifications(config.clone(), receiver));
+        Self {
+            node_name: node_name.into(),
+            config,
+            sender,
+            last_notified: Default::default(),
+        }
+    }
+
+    pub fn config(&self) -> &NotifierConfig {
+        &self.config
+    }
+
+    /// Notify an event, unless it was already notified during the repeat interval
+    pub fn notify(&self, event: NotificationEvent) {
+        if !self.should_notify(&event, Instant::now()) {
+            return;
+        }
+        if let Err(e) = self
+            .sender
+            .try_send(Notification::new(&self.node_name, event))
+        {
+            warn!("A notification was dropped: {e}");
+        }
+    }
+
+    fn should_notify(&self, event: &NotificationEvent, now: Instant) -> bool {
+        if !self.config.webhooks.iter().any(|w| w.accepts(event.kind())) {
+            return false;
+        }
+        let key = match event.repetition_key() {
+            Some(key) => key,
+            None => return true,
+        };
+        let mut last_notified = self.last_notified.lock().unwrap();
+        match last_notified.get(&key) {
+            Some(last) if now.duration_since(*last) < self.config.repeat_after() => false,
+            _ => {
+                last_notified.insert(key, now);
+                true
+            }
+        }
+    }
+}
+
+/// Send the notifications until all the emitters are dropped
+async fn send_notifications(
+    config: Arc<NotifierConfig>,
+    mut receiver: mpsc::Receiver<Notification>,
+) {
+    let client = match reqwest::ClientBuilder::new()
+        .timeout(WEBHOOK_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(%e, "The notifications HTTP client can't be created");
+            return;
+        }
+    };
+    while let Some(notification) = receiver.recv().await {
+        for webhook in config.webhooks.iter() {
+            if !webhook.accepts(notification.event.kind()) {
+                continue;
+            }
+            if let Err(e) = post(&client, webhook, &notification).await {
+                warn!(url = %webhook.url, %e, "A notification can't be posted");
+            }
+        }
+    }
+}
+
+async fn post(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    notification: &Notification,
+) -> Result<()> {
+    let body = webhook.body(notification)?;
+    let content_type = if webhook.template.is_some() {
+        "text/plain"
+    } else {
+        "application/json"
+    };
+    let body = &body;
+    let retry_strategy = ExponentialBackoff::from_millis(500)
+        .map(jitter)
+        .take(webhook.retries);
+    Retry::spawn(retry_strategy, move || async move {
+        let mut request = client
+            .post(webhook.url.clone())
+            .header("content-type", content_type)
+            .body(body.clone());
+        for (name, value) in webhook.headers.iter() {
+            request = request.header(name, value);
+        }
+        request.send().await?.error_for_status()
+    })
+    .await
+    .map_err(|e| ApiError::core(e.to_string()))?;
+    debug!(url = %webhook.url, "Posted a notification");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(events: Vec<NotificationKind>) -> NotifierConfig {
+        serde_json::from_value(serde_json::json!({
+            "webhooks": [{ "url": "http://localhost:8080/hook", "events": events }],
+            "repeat_after_secs": 60
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let notification = Notification::new(
+            "n1",
+            NotificationEvent::RelayDown {
+                relay: "forward_to_n1".to_string(),
+                down_for_secs: 300,
+            },
+        );
+        let rendered = render(
+            r#"{"text": "{{ message }}", "event": "{{event}}", "secs": {{down_for_secs}}{{unknown}}} {{"#,
+            &notification.fields(),
+        );
+        assert_eq!(
+            rendered,
+            r#"{"text": "node n1: the relay forward_to_n1 is down since 300 seconds", "event": "relay_down", "secs": 300} {{"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeated_events() {
+        let config = config(vec![NotificationKind::RelayDown]);
+        assert_eq!(config.relay_down_after(), Duration::from_secs(300));
+        let notifier = Notifier::start("n1", config);
+
+        let relay_down = NotificationEvent::RelayDown {
+            relay: "r1".to_string(),
+            down_for_secs: 300,
+        };
+        let now = Instant::now();
+        assert!(notifier.should_notify(&relay_down, now));
+        assert!(!notifier.should_notify(&relay_down, now + Duration::from_secs(30)));
+        assert!(notifier.should_notify(&relay_down, now + Duration::from_secs(61)));
+
+        // events which are not posted to any webhook are ignored
+        let policy_changed = NotificationEvent::PolicyChanged {
+            resource: "outlet".to_string(),
+            action: "handle_message".to_string(),
+            expression: None,
+        };
+        assert!(!notifier.should_notify(&policy_changed, now));
+    }
+}
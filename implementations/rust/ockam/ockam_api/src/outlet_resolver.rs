@@ -0,0 +1,249 @@
+//! Resolution of the outlet targets which are hostnames.
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use ockam_core::{async_trait, Result};
+use ockam_transport_tcp::{HostLookup, OutletResolver, ResolvedHost, DEFAULT_RESOLVER_TTL};
+
+use crate::error::ApiError;
+
+/// Maximum time to wait for the response of a DNS over HTTPS server
+const DOH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Type of the DNS records of IPv4 addresses
+const RECORD_A: u16 = 1;
+
+/// Type of the DNS records of IPv6 addresses
+const RECORD_AAAA: u16 = 28;
+
+fn default_min_ttl_secs() -> u64 {
+    5
+}
+
+fn default_max_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_ttl_secs() -> u64 {
+    DEFAULT_RESOLVER_TTL.as_secs()
+}
+
+/// Outlet resolver configuration of a node, read from a JSON file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutletResolverConfig {
+    /// Minimum time during which the addresses of a target are cached
+    #[serde(default = "default_min_ttl_secs")]
+    pub min_ttl_secs: u64,
+    /// Maximum time during which the addresses of a target are cached
+    #[serde(default = "default_max_ttl_secs")]
+    pub max_ttl_secs: u64,
+    /// Time during which the addresses of a target are cached when the TTL of their records
+    /// is not known
+    #[serde(default = "default_ttl_secs")]
+    pub default_ttl_secs: u64,
+    /// Static addresses of some hosts, which are never looked up
+    #[serde(default)]
+    pub overrides: BTreeMap<String, Vec<IpAddr>>,
+    /// URL of a DNS over HTTPS server supporting the JSON API
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dns_over_https: Option<Url>,
+}
+
+impl Default for OutletResolverConfig {
+    fn default() -> Self {
+        Self {
+            min_ttl_secs: default_min_ttl_secs(),
+            max_ttl_secs: default_max_ttl_secs(),
+            default_ttl_secs: default_ttl_secs(),
+            overrides: BTreeMap::new(),
+            dns_over_https: None,
+        }
+    }
+}
+
+impl OutletResolverConfig {
+    /// Read a configuration from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the outlet resolver configuration {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        let config: Self = serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the outlet resolver configuration {} is invalid: {e}",
+                path.display()
+            ))
+        })?;
+        if config.min_ttl_secs > config.max_ttl_secs {
+            return Err(ApiError::core(format!(
+                "the outlet resolver configuration {} is invalid: the minimum TTL is greater than the maximum TTL",
+                path.display()
+            )));
+        }
+        Ok(config)
+    }
+
+    /// Create the resolver described by this configuration
+    pub fn resolver(&self) -> Result<OutletResolver> {
+        let mut resolver = OutletResolver::new()
+            .with_default_ttl(Duration::from_secs(self.default_ttl_secs))
+            .with_ttl_bounds(
+                Duration::from_secs(self.min_ttl_secs),
+                Duration::from_secs(self.max_ttl_secs),
+            );
+        for (host, addresses) in &self.overrides {
+            resolver = resolver.with_override(host, addresses.clone());
+        }
+        if let Some(url) = &self.dns_over_https {
+            resolver = resolver.with_lookup(Arc::new(DohLookup::new(url.clone())?));
+        }
+        Ok(resolver)
+    }
+}
+
+/// Look up the addresses of a host with the JSON API of a DNS over HTTPS server,
+/// for example `https://cloudflare-dns.com/dns-query` or `https://dns.google/resolve`
+#[derive(Debug, Clone)]
+pub struct DohLookup {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl DohLookup {
+    pub fn new(url: Url) -> Result<Self> {
+        let client = reqwest::ClientBuilder::new()
+            .timeout(DOH_TIMEOUT)
+            .build()
+            .map_err(|e| {
+                ApiError::core(format!("the DNS over HTTPS client can't be created: {e}"))
+            })?;
+        Ok(Self { url, client })
+    }
+
+    async fn query(&self, host: &str, record_type: u16) -> Result<DohResponse> {
+        let response = self
+            .client
+            .get(self.url.clone())
+            .query(&[("name", host), ("type", &record_type.to_string())])
+            .header("accept", "application/dns-json")
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ApiError::core(format!("the DNS over HTTPS query failed: {e}")))?;
+        response
+            .json()
+            .await
+            .map_err(|e| ApiError::core(format!("the DNS over HTTPS response is invalid: {e}")))
+    }
+}
+
+#[async_trait]
+impl HostLookup for DohLookup {
+    async fn lookup(&self, host: &str) -> Result<ResolvedHost> {
+        let (a, aaaa) = tokio::join!(self.query(host, RECORD_A), self.query(host, RECORD_AAAA));
+        // the addresses of one family are still used when the query for the other family fails
+        match (a, aaaa) {
+            (Err(e), Err(_)) => Err(e),
+            (a, aaaa) => Ok(DohResponse::merge(
+                a.into_iter().chain(aaaa).collect::<Vec<_>>(),
+            )),
+        }
+    }
+}
+
+/// Response of the JSON API of a DNS over HTTPS server
+#[derive(Debug, Clone, Deserialize)]
+struct DohResponse {
+    #[serde(rename = "Status")]
+    status: u16,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DohAnswer>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct DohAnswer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL")]
+    ttl: u64,
+    data: String,
+}
+
+impl DohResponse {
+    /// Collect the addresses of successful responses, with the lowest TTL of their records.
+    /// The other records, for example CNAME records, are skipped
+    fn merge(responses: Vec<DohResponse>) -> ResolvedHost {
+        let mut addresses = vec![];
+        let mut ttl: Option<u64> = None;
+        for answer in responses
+            .into_iter()
+            .filter(|r| r.status == 0)
+            .flat_map(|r| r.answer)
+            .filter(|a| a.record_type == RECORD_A || a.record_type == RECORD_AAAA)
+        {
+            if let Ok(address) = answer.data.parse::<IpAddr>() {
+                addresses.push(address);
+                ttl = Some(ttl.map_or(answer.ttl, |ttl| ttl.min(answer.ttl)));
+            }
+        }
+        ResolvedHost {
+            addresses,
+            ttl: ttl.map(Duration::from_secs),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_doh_responses() {
+        let a: DohResponse = serde_json::from_str(
+            r#"{"Status":0,"Answer":[
+                {"name":"db.example.com","type":5,"TTL":3600,"data":"lb.example.com."},
+                {"name":"lb.example.com","type":1,"TTL":30,"data":"10.0.0.1"},
+                {"name":"lb.example.com","type":1,"TTL":60,"data":"10.0.0.2"}]}"#,
+        )
+        .unwrap();
+        let aaaa: DohResponse = serde_json::from_str(
+            r#"{"Status":3,"Answer":[{"name":"x","type":28,"TTL":1,"data":"::1"}]}"#,
+        )
+        .unwrap();
+
+        let resolved = DohResponse::merge(vec![a, aaaa]);
+        assert_eq!(
+            resolved.addresses,
+            vec![
+                "10.0.0.1".parse::<IpAddr>().unwrap(),
+                "10.0.0.2".parse().unwrap()
+            ]
+        );
+        assert_eq!(resolved.ttl, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_config() {
+        let config: OutletResolverConfig = serde_json::from_str(
+            r#"{"max_ttl_secs": 300, "overrides": {"db.internal": ["10.0.0.5"]}, "dns_over_https": "https://dns.google/resolve"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.min_ttl_secs, 5);
+        assert_eq!(config.max_ttl_secs, 300);
+        assert_eq!(config.default_ttl_secs, 60);
+        assert_eq!(
+            config.overrides.get("db.internal"),
+            Some(&vec!["10.0.0.5".parse::<IpAddr>().unwrap()])
+        );
+        assert!(config.resolver().is_ok());
+    }
+}
@@ -0,0 +1,402 @@
+//! Authenticated liveness probes of the nodes.
+
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::Storage;
+use ockam::identity::{
+    secure_channel_required, Identifier, IdentitySecureChannelLocalInfo, SecureClient,
+};
+use ockam_core::api::{Method, Request, RequestHeader, Response};
+use ockam_core::{Address, AllowAll, DenyAll, Result, Routed, Worker};
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use crate::cli_state::cached::now_millis;
+use crate::error::ApiError;
+use crate::nodes::NodeManager;
+use crate::DefaultAddress;
+
+/// Number of results kept for each target, one day with the default interval
+pub const DEFAULT_MAX_RESULTS: usize = 1440;
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+const HEALTH_CHECKS_NAMESPACE: &str = "health_checks";
+
+/// Probe sent to the ping service of a node
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Ping {
+    /// Random value returned in the reply
+    #[n(1)] pub nonce: u64,
+}
+
+/// Reply of the ping service of a node
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Pong {
+    #[n(1)] pub nonce: u64,
+    /// Identifier of the node
+    #[n(2)] pub identifier: Identifier,
+    #[n(3)] pub node_name: String,
+    #[n(4)] pub version: String,
+    #[n(5)] pub uptime_secs: u64,
+    /// Identifier of the caller, as authenticated by the node
+    #[n(6)] pub caller: Identifier,
+}
+
+/// Worker answering the authenticated probes of the other nodes
+pub struct PingService {
+    identifier: Identifier,
+    node_name: String,
+    started_at: Instant,
+}
+
+impl PingService {
+    pub fn new(identifier: Identifier, node_name: impl Into<String>) -> Self {
+        Self {
+            identifier,
+            node_name: node_name.into(),
+            started_at: Instant::now(),
+        }
+    }
+
+    fn pong(&self, ping: Ping, caller: Identifier) -> Pong {
+        Pong {
+            nonce: ping.nonce,
+            identifier: self.identifier.clone(),
+            node_name: self.node_name.clone(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            uptime_secs: self.started_at.elapsed().as_secs(),
+            caller,
+        }
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for PingService {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::ping",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                "request"
+            }
+            let path_segments = req.path_segments::<5>();
+            let res = match (req.method(), path_segments.as_slice()) {
+                (Some(Method::Post), ["ping"]) => {
+                    let ping: Ping = dec.decode()?;
+                    Response::ok(&req).body(self.pong(ping, from)).to_vec()?
+                }
+                _ => Response::unknown_path(&req).to_vec()?,
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Probe the node reached with a secure client and return its reply with the round trip
+pub async fn ping(ctx: &Context, client: &SecureClient) -> Result<(Pong, Duration)> {
+    let ping = Ping {
+        nonce: rand::random(),
+    };
+    let nonce = ping.nonce;
+    let started_at = Instant::now();
+    let pong: Pong = client
+        .ask(
+            ctx,
+            DefaultAddress::PING_SERVICE,
+            Request::post("/ping").body(ping),
+        )
+        .await?
+        .success()?;
+    if pong.nonce != nonce {
+        return Err(ApiError::core("the reply doesn't match the probe"));
+    }
+    Ok((pong, started_at.elapsed()))
+}
+
+/// Node probed by a health check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheckTarget {
+    /// Identity of the node
+    pub identifier: Identifier,
+    /// Route to the secure channel listener of the node
+    pub route: MultiAddr,
+}
+
+/// Nodes probed on an interval
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheckConfig {
+    pub targets: Vec<HealthCheckTarget>,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl HealthCheckConfig {
+    pub fn new(targets: Vec<HealthCheckTarget>) -> Self {
+        Self {
+            targets,
+            interval_secs: default_interval_secs(),
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval_secs = interval.as_secs().max(1);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.interval_secs)
+    }
+}
+
+/// Result of the probe of a node
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct HealthCheck {
+    /// Milliseconds since the Unix epoch
+    #[n(1)] pub timestamp: u64,
+    /// Identifier of the probed node
+    #[n(2)] pub target: Identifier,
+    #[n(3)] pub route: String,
+    /// Reply of the node, if it answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[n(4)] pub pong: Option<Pong>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[n(5)] pub round_trip_ms: Option<u64>,
+    /// Reason why the node didn't answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[n(6)] pub error: Option<String>,
+}
+
+impl HealthCheck {
+    /// Return the result of a probe of a target
+    pub fn new(target: &HealthCheckTarget, result: Result<(Pong, Duration)>) -> Self {
+        let (pong, round_trip_ms, error) = match result {
+            Ok((pong, round_trip)) => (Some(pong), Some(round_trip.as_millis() as u64), None),
+            Err(e) => (None, None, Some(e.to_string())),
+        };
+        Self {
+            timestamp: now_millis(),
+            target: target.identifier.clone(),
+            route: target.route.to_string(),
+            pong,
+            round_trip_ms,
+            error,
+        }
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.pong.is_some()
+    }
+}
+
+/// Request body to probe a node once
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PingNode {
+    #[n(1)] pub identifier: Identifier,
+    #[n(2)] pub route: String,
+}
+
+impl PingNode {
+    pub fn new(identifier: Identifier, route: &MultiAddr) -> Self {
+        Self {
+            identifier,
+            route: route.to_string(),
+        }
+    }
+
+    pub fn target(&self) -> Result<HealthCheckTarget> {
+        Ok(HealthCheckTarget {
+            identifier: self.identifier.clone(),
+            route: self
+                .route
+                .parse()
+                .map_err(|e| ApiError::core(format!("Invalid route {}: {e}", self.route)))?,
+        })
+    }
+}
+
+/// Results of the health checks of a node, by target and time
+#[derive(Clone)]
+pub struct HealthCheckHistory {
+    storage: Arc<dyn Storage>,
+    max_results: usize,
+}
+
+impl HealthCheckHistory {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            max_results: DEFAULT_MAX_RESULTS,
+        }
+    }
+
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    /// Keep a result, and remove the oldest results of its target beyond the maximum number
+    pub async fn save(&self, check: &HealthCheck) -> Result<()> {
+        self.storage
+            .set(
+                &result_key(&check.target, check.timestamp),
+                HEALTH_CHECKS_NAMESPACE.to_string(),
+                minicbor::to_vec(check)?,
+            )
+            .await?;
+        let prefix = format!("{}/", check.target);
+        let keys: Vec<String> = self
+            .sorted_keys()
+            .await?
+            .into_iter()
+            .filter(|key| key.starts_with(&prefix))
+            .collect();
+        if keys.len() > self.max_results {
+            for key in &keys[..keys.len() - self.max_results] {
+                self.storage.del(key, HEALTH_CHECKS_NAMESPACE).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the results, by target and from the oldest one
+    pub async fn list(&self) -> Result<Vec<HealthCheck>> {
+        let mut checks = vec![];
+        for key in self.sorted_keys().await? {
+            if let Some(bytes) = self.storage.get(&key, HEALTH_CHECKS_NAMESPACE).await? {
+                checks.push(minicbor::decode(&bytes)?);
+            }
+        }
+        Ok(checks)
+    }
+
+    /// The keys are the target identifiers followed by zero-padded timestamps
+    async fn sorted_keys(&self) -> Result<Vec<String>> {
+        let mut keys = self.storage.keys(HEALTH_CHECKS_NAMESPACE).await?;
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+/// Prober of the nodes configured with a [`HealthCheckConfig`]
+pub struct HealthChecker;
+
+impl HealthChecker {
+    /// Probe the targets on each interval and record the results, until the node is dropped
+    pub async fn start(
+        ctx: &Context,
+        node: Weak<NodeManager>,
+        config: HealthCheckConfig,
+    ) -> Result<JoinHandle<()>> {
+        info!(
+            targets = config.targets.len(),
+            interval_secs = config.interval_secs,
+            "Scheduling the health checks of the nodes"
+        );
+        let ctx = ctx
+            .new_detached(
+                Address::random_tagged("HealthChecker.ctx"),
+                DenyAll,
+                AllowAll,
+            )
+            .await?;
+        Ok(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval());
+            loop {
+                interval.tick().await;
+                let node = match node.upgrade() {
+                    Some(node) => node,
+                    None => return,
+                };
+                for target in &config.targets {
+                    let check = node.ping(&ctx, target).await;
+                    if let Some(error) = &check.error {
+                        warn!(node = %target.identifier, route = %target.route, %error, "The health check failed");
+                    }
+                    if let Err(e) = node.health_checks().save(&check).await {
+                        warn!(%e, "The result of a health check can't be stored")
+                    }
+                }
+            }
+        }))
+    }
+}
+
+fn result_key(target: &Identifier, timestamp: u64) -> String {
+    format!("{target}/{timestamp:020}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::storage::InMemoryStorage;
+    use std::str::FromStr;
+
+    fn check(target: &Identifier, timestamp: u64) -> HealthCheck {
+        HealthCheck {
+            timestamp,
+            target: target.clone(),
+            route: "/node/n1/secure/api".to_string(),
+            pong: None,
+            round_trip_ms: None,
+            error: Some("timeout".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keep_the_last_results_of_each_target() -> Result<()> {
+        let n1 = Identifier::from_str("Ie92f183eb4c324804ef4d62962dea94cf095a265")?;
+        let n2 = Identifier::from_str("Ibb37445cacb3ca7a20040a9b36469e321a57d2cd")?;
+        let history = HealthCheckHistory::new(InMemoryStorage::create()).with_max_results(2);
+        history.save(&check(&n1, 9)).await?;
+        history.save(&check(&n2, 5)).await?;
+        history.save(&check(&n1, 1000)).await?;
+        history.save(&check(&n1, 100)).await?;
+
+        // the results are sorted by target, then from the oldest one
+        let checks = history.list().await?;
+        assert_eq!(
+            checks,
+            vec![check(&n2, 5), check(&n1, 100), check(&n1, 1000)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pong() {
+        let node = Identifier::from_str("Ie92f183eb4c324804ef4d62962dea94cf095a265").unwrap();
+        let caller = Identifier::from_str("Ibb37445cacb3ca7a20040a9b36469e321a57d2cd").unwrap();
+        let service = PingService::new(node.clone(), "n1");
+        let pong = service.pong(Ping { nonce: 42 }, caller.clone());
+        assert_eq!(pong.nonce, 42);
+        assert_eq!(pong.identifier, node);
+        assert_eq!(pong.caller, caller);
+        assert_eq!(pong.version, env!("CARGO_PKG_VERSION"));
+    }
+}
@@ -0,0 +1,503 @@
+//! Export and import of all the policies of a node as a bundle.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{Identifier, Identities, IdentityError};
+use ockam_abac::expr::{eq, ident};
+use ockam_abac::{Action, Expr, PolicyStorage, Resource};
+use ockam_core::Result;
+
+use crate::error::ApiError;
+
+/// Version of the format of the bundles
+pub const POLICY_BUNDLE_VERSION: u8 = 1;
+
+/// Identifier for the schema of the statements signing a policy bundle
+pub const POLICY_BUNDLE_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(4);
+
+/// Name of the attribute containing the SHA-256 digest of a policy bundle
+pub const POLICY_BUNDLE_DIGEST: &[u8] = b"ockam.policy_bundle.digest";
+
+/// Default validity of the signature of a bundle
+pub const DEFAULT_BUNDLE_SIGNATURE_VALIDITY: Duration = Duration::from_secs(90 * 24 * 3600);
+
+/// Policy set by a node on a resource when no policy has been configured for it:
+/// only the members of the same trust context can access the resource
+pub fn default_policy() -> Expr {
+    eq([
+        ident("resource.trust_context_id"),
+        ident("subject.trust_context_id"),
+    ])
+}
+
+/// Policy of a resource and an action
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PolicyBundleEntry {
+    #[n(1)] pub resource: String,
+    #[n(2)] pub action: String,
+    #[n(3)] pub expression: String,
+    /// True if the policy is the default policy set by the node. A default policy doesn't
+    /// replace a policy configured on the importing node
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[n(4)] pub default: bool,
+}
+
+/// Policies of all the resources of a node
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PolicyBundle {
+    #[n(1)] pub version: u8,
+    /// Creation time, in seconds since the Unix epoch
+    #[n(2)] pub created_at: u64,
+    #[n(3)] pub policies: Vec<PolicyBundleEntry>,
+}
+
+impl PolicyBundle {
+    /// Create a bundle from a list of policies
+    pub fn new(policies: Vec<(Resource, Action, Expr)>) -> Self {
+        let default = default_policy().to_string();
+        let mut policies: Vec<PolicyBundleEntry> = policies
+            .into_iter()
+            .map(|(resource, action, expr)| {
+                let expression = expr.to_string();
+                PolicyBundleEntry {
+                    resource: resource.to_string(),
+                    action: action.to_string(),
+                    default: expression == default,
+                    expression,
+                }
+            })
+            .collect();
+        policies.sort_by(|a, b| (&a.resource, &a.action).cmp(&(&b.resource, &b.action)));
+        Self {
+            version: POLICY_BUNDLE_VERSION,
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            policies,
+        }
+    }
+
+    /// Check the version of the bundle and parse the expressions of its policies
+    fn parse(&self) -> Result<BTreeMap<(Resource, Action), (Expr, bool)>> {
+        if self.version != POLICY_BUNDLE_VERSION {
+            return Err(ApiError::core(format!(
+                "the version {} of the policy bundle is not supported, the supported version is {POLICY_BUNDLE_VERSION}",
+                self.version
+            )));
+        }
+        let mut policies = BTreeMap::new();
+        for entry in &self.policies {
+            let expr = Expr::from_str(&entry.expression).map_err(|e| {
+                ApiError::core(format!(
+                    "the policy of the action {} on the resource {} is invalid: {e}",
+                    entry.action, entry.resource
+                ))
+            })?;
+            let key = (Resource::new(&entry.resource), Action::new(&entry.action));
+            if policies.insert(key, (expr, entry.default)).is_some() {
+                return Err(ApiError::core(format!(
+                    "the policy bundle contains several policies for the action {} on the resource {}",
+                    entry.action, entry.resource
+                )));
+            }
+        }
+        Ok(policies)
+    }
+
+    /// SHA-256 digest of the JSON representation of the bundle
+    async fn digest(&self, identities: &Identities) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ApiError::core(format!("the policy bundle can't be encoded: {e}")))?;
+        Ok(identities
+            .vault()
+            .verifying_vault
+            .sha256(&json)
+            .await?
+            .0
+            .to_vec())
+    }
+
+    /// Sign the bundle with an identity
+    pub async fn sign(
+        self,
+        identities: &Identities,
+        signer: &Identifier,
+        validity: Duration,
+    ) -> Result<SignedPolicyBundle> {
+        let attributes = AttributesBuilder::with_schema(POLICY_BUNDLE_SCHEMA)
+            .with_attribute(
+                POLICY_BUNDLE_DIGEST.to_vec(),
+                self.digest(identities).await?,
+            )
+            .build();
+        let statement = identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(signer, signer, attributes, validity)
+            .await?;
+        Ok(SignedPolicyBundle {
+            bundle: self,
+            signer: signer.clone(),
+            signer_identity: hex::encode(identities.export_identity(signer).await?),
+            signature: hex::encode(minicbor::to_vec(&statement)?),
+        })
+    }
+}
+
+/// A bundle with the identity of its signer and a statement attesting its digest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPolicyBundle {
+    pub bundle: PolicyBundle,
+    pub signer: Identifier,
+    /// Change history of the signer, hex encoded
+    pub signer_identity: String,
+    /// Statement of the signer, hex encoded
+    pub signature: String,
+}
+
+impl SignedPolicyBundle {
+    /// Read a signed bundle from a JSON file
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).map_err(|e| {
+            ApiError::core(format!(
+                "the policy bundle {} can't be read: {e}",
+                path.display()
+            ))
+        })?;
+        serde_json::from_str(&contents).map_err(|e| {
+            ApiError::core(format!(
+                "the policy bundle {} is invalid: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Write the bundle to a JSON file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| ApiError::core(format!("the policy bundle can't be encoded: {e}")))?;
+        std::fs::write(path, contents).map_err(|e| {
+            ApiError::core(format!(
+                "the policy bundle {} can't be written: {e}",
+                path.display()
+            ))
+        })
+    }
+
+    /// Check that the bundle was signed by one of the trusted signers, or by its signer
+    /// if no signers are trusted, and return it
+    pub async fn verify(
+        &self,
+        identities: &Identities,
+        trusted_signers: &[Identifier],
+    ) -> Result<PolicyBundle> {
+        if !trusted_signers.is_empty() && !trusted_signers.contains(&self.signer) {
+            return Err(ApiError::core(format!(
+                "the policy bundle is signed by {}, which is not a trusted signer",
+                self.signer
+            )));
+        }
+        let invalid = |e: hex::FromHexError| {
+            ApiError::core(format!(
+                "the signature of the policy bundle is invalid: {e}"
+            ))
+        };
+        let signer_identity = hex::decode(&self.signer_identity).map_err(invalid)?;
+        identities
+            .identities_creation()
+            .import(Some(&self.signer), &signer_identity)
+            .await?;
+        let statement: CredentialAndPurposeKey =
+            minicbor::decode(&hex::decode(&self.signature).map_err(invalid)?)?;
+        let data = identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(Some(&self.signer), &[self.signer.clone()], &statement)
+            .await?;
+        let attributes = data.credential_data.subject_attributes;
+        if attributes.schema != POLICY_BUNDLE_SCHEMA {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+        let attested_digest = attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == POLICY_BUNDLE_DIGEST)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if attested_digest != Some(self.bundle.digest(identities).await?) {
+            return Err(ApiError::core(
+                "the policy bundle was modified after it was signed",
+            ));
+        }
+        Ok(self.bundle.clone())
+    }
+}
+
+/// How the policies of a bundle are combined with the policies of a node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[rustfmt::skip]
+pub enum MergeStrategy {
+    /// The policies of the bundle replace the policies of the node, and the other policies
+    /// of the node are kept
+    #[default]
+    #[n(0)] Merge,
+    /// The node ends up with the policies of the bundle only
+    #[n(1)] Replace,
+    /// Only the policies which don't exist on the node are added
+    #[n(2)] KeepExisting,
+}
+
+impl Display for MergeStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MergeStrategy::Merge => "merge",
+            MergeStrategy::Replace => "replace",
+            MergeStrategy::KeepExisting => "keep-existing",
+        })
+    }
+}
+
+impl FromStr for MergeStrategy {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "merge" => Ok(MergeStrategy::Merge),
+            "replace" => Ok(MergeStrategy::Replace),
+            "keep-existing" => Ok(MergeStrategy::KeepExisting),
+            _ => Err(ApiError::core(format!(
+                "unknown merge strategy {s}, expected merge, replace or keep-existing"
+            ))),
+        }
+    }
+}
+
+/// Policies changed by the import of a bundle, as `resource/action`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PolicyImportReport {
+    #[n(1)] pub added: Vec<String>,
+    #[n(2)] pub updated: Vec<String>,
+    #[n(3)] pub removed: Vec<String>,
+    #[n(4)] pub unchanged: usize,
+}
+
+/// Request to import a bundle in a node
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ImportPolicyBundle {
+    #[n(1)] pub bundle: PolicyBundle,
+    #[n(2)] pub strategy: MergeStrategy,
+}
+
+/// Policies of all the resources stored by a node
+#[derive(Clone)]
+pub struct Policies {
+    storage: Arc<dyn PolicyStorage>,
+}
+
+impl Policies {
+    pub fn new(storage: Arc<dyn PolicyStorage>) -> Self {
+        Self { storage }
+    }
+
+    /// Return all the policies as a bundle
+    pub async fn export_bundle(&self) -> Result<PolicyBundle> {
+        Ok(PolicyBundle::new(self.storage.all_policies().await?))
+    }
+
+    /// Import the policies of a bundle. No policy is changed if the bundle is invalid
+    pub async fn import_bundle(
+        &self,
+        bundle: &PolicyBundle,
+        strategy: MergeStrategy,
+    ) -> Result<PolicyImportReport> {
+        let imported = bundle.parse()?;
+        let existing: BTreeMap<(Resource, Action), Expr> = self
+            .storage
+            .all_policies()
+            .await?
+            .into_iter()
+            .map(|(r, a, e)| ((r, a), e))
+            .collect();
+
+        let mut report = PolicyImportReport::default();
+        for ((resource, action), (expr, default)) in &imported {
+            let name = format!("{resource}/{action}");
+            match existing.get(&(resource.clone(), action.clone())) {
+                None => {
+                    self.storage.set_policy(resource, action, expr).await?;
+                    report.added.push(name);
+                }
+                // expressions don't implement PartialEq, they are compared by their representation
+                Some(current) if current.to_string() == expr.to_string() => report.unchanged += 1,
+                // a default policy doesn't replace a configured policy
+                Some(_) if strategy == MergeStrategy::KeepExisting || *default => {
+                    report.unchanged += 1
+                }
+                Some(_) => {
+                    self.storage.set_policy(resource, action, expr).await?;
+                    report.updated.push(name);
+                }
+            }
+        }
+        if strategy == MergeStrategy::Replace {
+            for (resource, action) in existing.keys() {
+                if !imported.contains_key(&(resource.clone(), action.clone())) {
+                    self.storage.del_policy(resource, action).await?;
+                    report.removed.push(format!("{resource}/{action}"));
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::identities;
+    use ockam_abac::expr::str;
+    use ockam_abac::mem::Memory;
+
+    async fn policies(entries: &[(&str, &str, Expr)]) -> Result<Policies> {
+        let storage = Arc::new(Memory::new());
+        for (resource, action, expr) in entries {
+            storage
+                .set_policy(&Resource::new(resource), &Action::new(action), expr)
+                .await?;
+        }
+        Ok(Policies::new(storage))
+    }
+
+    fn member_of(project: &str) -> Expr {
+        eq([ident("subject.trust_context_id"), str(project)])
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import() -> Result<()> {
+        let staging = policies(&[
+            ("outlet", "handle_message", member_of("staging")),
+            ("inlet", "handle_message", default_policy()),
+        ])
+        .await?;
+        let bundle = staging.export_bundle().await?;
+        assert_eq!(bundle.policies.len(), 2);
+        assert_eq!(bundle.policies[0].resource, "inlet");
+        assert!(bundle.policies[0].default);
+
+        let merged = policies(&[
+            ("inlet", "handle_message", member_of("production")),
+            ("kafka", "handle_message", member_of("production")),
+        ])
+        .await?;
+        let report = merged.import_bundle(&bundle, MergeStrategy::Merge).await?;
+        assert_eq!(report.added, vec!["outlet/handle_message"]);
+        assert!(report.updated.is_empty());
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(merged.export_bundle().await?.policies.len(), 3);
+
+        let replaced = policies(&[
+            ("outlet", "handle_message", member_of("production")),
+            ("kafka", "handle_message", member_of("production")),
+        ])
+        .await?;
+        let report = replaced
+            .import_bundle(&bundle, MergeStrategy::Replace)
+            .await?;
+        assert_eq!(report.added, vec!["inlet/handle_message"]);
+        assert_eq!(report.updated, vec!["outlet/handle_message"]);
+        assert_eq!(report.removed, vec!["kafka/handle_message"]);
+        assert_eq!(replaced.export_bundle().await?.policies, bundle.policies);
+
+        let kept = policies(&[("outlet", "handle_message", member_of("production"))]).await?;
+        let report = kept
+            .import_bundle(&bundle, MergeStrategy::KeepExisting)
+            .await?;
+        assert_eq!(report.added, vec!["inlet/handle_message"]);
+        assert_eq!(report.unchanged, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_invalid_bundle_is_not_imported() -> Result<()> {
+        let mut bundle = PolicyBundle::new(vec![(
+            Resource::new("outlet"),
+            Action::new("handle_message"),
+            member_of("staging"),
+        )]);
+        bundle.policies.push(PolicyBundleEntry {
+            resource: "inlet".to_string(),
+            action: "handle_message".to_string(),
+            expression: "(= subject.".to_string(),
+            default: false,
+        });
+        let policies = policies(&[]).await?;
+        assert!(policies
+            .import_bundle(&bundle, MergeStrategy::Merge)
+            .await
+            .is_err());
+        assert!(policies.export_bundle().await?.policies.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_and_verify() -> Result<()> {
+        let signer_identities = identities();
+        let signer = signer_identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        let bundle = policies(&[("outlet", "handle_message", member_of("staging"))])
+            .await?
+            .export_bundle()
+            .await?;
+        let signed = bundle
+            .clone()
+            .sign(
+                &signer_identities,
+                &signer,
+                DEFAULT_BUNDLE_SIGNATURE_VALIDITY,
+            )
+            .await?;
+
+        // the signed bundle is verified by another node
+        let json = serde_json::to_string_pretty(&signed).unwrap();
+        let signed: SignedPolicyBundle = serde_json::from_str(&json).unwrap();
+        let other = identities();
+        assert_eq!(signed.verify(&other, &[signer.clone()]).await?, bundle);
+        assert_eq!(signed.verify(&other, &[]).await?, bundle);
+
+        let stranger = other
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        assert!(signed.verify(&other, &[stranger]).await.is_err());
+
+        let mut modified = signed.clone();
+        modified.bundle.policies[0].expression = "true".to_string();
+        assert!(modified.verify(&other, &[]).await.is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,443 @@
+//! Local DNS responder giving stable hostnames to the inlets of a node.
+
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tokio::net::UdpSocket;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{route, Address, AllowAll, DenyAll, Result};
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use crate::cli_state::{StateDirTrait, StateItemTrait};
+use crate::error::ApiError;
+use crate::nodes::InMemoryNode;
+
+/// Number of seconds during which the answers can be cached
+const DNS_TTL: u32 = 5;
+
+/// Maximum size of a DNS message sent over UDP
+const MAX_UDP_MESSAGE_SIZE: usize = 512;
+
+/// Loopback addresses allocated to the inlets created on demand
+const LOOPBACK_PREFIX: [u8; 2] = [127, 77];
+
+const TYPE_A: u16 = 1;
+const CLASS_IN: u16 = 1;
+
+const RCODE_NO_ERROR: u8 = 0;
+const RCODE_FORMAT_ERROR: u8 = 1;
+const RCODE_SERVER_FAILURE: u8 = 2;
+const RCODE_NAME_ERROR: u8 = 3;
+const RCODE_NOT_IMPLEMENTED: u8 = 4;
+
+/// Target of a name answered by the DNS responder
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalDnsRecord {
+    /// An existing inlet, given by its alias
+    Inlet(String),
+    /// A service published in the registry of the node project.
+    /// An inlet listening on `port` is created for it on demand
+    Service { service: String, port: u16 },
+}
+
+/// Name of a service published in the registry of the node project, for example
+/// `payments.internal.ockam=payments-db:5432`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsServiceName {
+    pub name: String,
+    pub service: String,
+    pub port: u16,
+}
+
+impl FromStr for DnsServiceName {
+    type Err = String;
+
+    /// Parse `<name>=<service>:<port>`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("{s} must have the form <name>=<service>:<port>");
+        let (name, target) = s.split_once('=').ok_or_else(invalid)?;
+        let (service, port) = target.rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        if name.is_empty() || service.is_empty() || port == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: normalize_name(name),
+            service: service.to_string(),
+            port,
+        })
+    }
+}
+
+impl Display for DnsServiceName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}:{}", self.name, self.service, self.port)
+    }
+}
+
+/// Names are case-insensitive and can be fully qualified
+pub fn normalize_name(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// DNS responder of a node
+pub struct PortalDns {
+    node: Arc<InMemoryNode>,
+    ctx: Context,
+    /// Serialize the creation of the inlets, so that concurrent queries for the same
+    /// name don't create several inlets
+    creating_inlets: Mutex<()>,
+}
+
+impl PortalDns {
+    /// Start answering the DNS queries received on a UDP address.
+    /// Return the bound address, and the task answering the queries
+    pub async fn start(
+        ctx: &Context,
+        node: Arc<InMemoryNode>,
+        bind_addr: SocketAddr,
+    ) -> Result<(SocketAddr, JoinHandle<()>)> {
+        let socket = UdpSocket::bind(bind_addr).await.map_err(|e| {
+            ApiError::core(format!(
+                "Couldn't bind the DNS responder to {bind_addr}: {e}"
+            ))
+        })?;
+        let bind_addr = socket
+            .local_addr()
+            .map_err(|e| ApiError::core(e.to_string()))?;
+        let ctx = ctx
+            .new_detached(Address::random_tagged("PortalDns.ctx"), DenyAll, AllowAll)
+            .await?;
+        let dns = Arc::new(PortalDns {
+            node,
+            ctx,
+            creating_inlets: Mutex::new(()),
+        });
+        let socket = Arc::new(socket);
+        info!(%bind_addr, "Answering the DNS queries for the portal names");
+
+        let task = tokio::spawn(async move {
+            let mut buffer = [0u8; MAX_UDP_MESSAGE_SIZE];
+            loop {
+                let (size, peer) = match socket.recv_from(&mut buffer).await {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!(%e, "Couldn't receive a DNS query");
+                        continue;
+                    }
+                };
+                let packet = buffer[..size].to_vec();
+                let dns = dns.clone();
+                let socket = socket.clone();
+                // Creating an inlet can take a while, the other queries must not wait for it
+                tokio::spawn(async move {
+                    if let Some(response) = dns.answer(&packet).await {
+                        if let Err(e) = socket.send_to(&response, peer).await {
+                            debug!(%peer, %e, "Couldn't send a DNS response");
+                        }
+                    }
+                });
+            }
+        });
+        Ok((bind_addr, task))
+    }
+
+    /// Return the response to a DNS message, if it is a query
+    async fn answer(&self, packet: &[u8]) -> Option<Vec<u8>> {
+        let query = match DnsQuery::parse(packet) {
+            Ok(query) => query,
+            Err(Some(id)) => return Some(DnsQuery::error(id, RCODE_FORMAT_ERROR)),
+            Err(None) => return None,
+        };
+        if query.opcode != 0 {
+            return Some(query.response(RCODE_NOT_IMPLEMENTED, None));
+        }
+        let name = normalize_name(&query.name);
+        let record = match self.node.registry.portal_dns_records.get(&name).await {
+            Some(record) => record,
+            None => {
+                trace!(%name, "Unknown DNS name");
+                return Some(query.response(RCODE_NAME_ERROR, None));
+            }
+        };
+        // The other query types, for example AAAA, get an empty answer
+        // so that the clients fall back to an A query
+        if query.qtype != TYPE_A || query.qclass != CLASS_IN {
+            return Some(query.response(RCODE_NO_ERROR, None));
+        }
+        match self.resolve(&name, record).await {
+            Ok(Some(address)) => Some(query.response(RCODE_NO_ERROR, Some(address))),
+            Ok(None) => Some(query.response(RCODE_NAME_ERROR, None)),
+            Err(e) => {
+                warn!(%name, %e, "Couldn't resolve a DNS name");
+                Some(query.response(RCODE_SERVER_FAILURE, None))
+            }
+        }
+    }
+
+    /// Return the address of the inlet of a record, creating it if necessary
+    async fn resolve(&self, name: &str, record: PortalDnsRecord) -> Result<Option<Ipv4Addr>> {
+        match record {
+            PortalDnsRecord::Inlet(alias) => Ok(self.inlet_address(&alias).await),
+            PortalDnsRecord::Service { service, port } => {
+                let alias = format!("dns-{name}");
+                if let Some(address) = self.inlet_address(&alias).await {
+                    return Ok(Some(address));
+                }
+                let _guard = self.creating_inlets.lock().await;
+                if let Some(address) = self.inlet_address(&alias).await {
+                    return Ok(Some(address));
+                }
+                let address = self.allocate_loopback_address().await?;
+                let project = self.node_project()?;
+                info!(%name, %service, %address, "Creating an inlet for a DNS name");
+                self.node
+                    .create_service_inlet(
+                        &self.ctx,
+                        SocketAddr::new(IpAddr::V4(address), port).to_string(),
+                        None,
+                        Some(alias),
+                        route![],
+                        route![],
+                        project,
+                        service,
+                        None,
+                    )
+                    .await?;
+                Ok(Some(address))
+            }
+        }
+    }
+
+    /// Return the IP address of an inlet. Inlets bound to all the interfaces are reachable
+    /// with the IPv4 loopback address
+    async fn inlet_address(&self, alias: &str) -> Option<Ipv4Addr> {
+        let inlet = self.node.registry.inlets.get(alias).await?;
+        match SocketAddr::from_str(&inlet.bind_addr).ok()?.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => Some(Ipv4Addr::LOCALHOST),
+            IpAddr::V4(ip) => Some(ip),
+            IpAddr::V6(ip) if ip.is_unspecified() || ip.is_loopback() => Some(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) => ip.to_ipv4_mapped(),
+        }
+    }
+
+    /// Return the first dedicated loopback address which is not used by an inlet
+    async fn allocate_loopback_address(&self) -> Result<Ipv4Addr> {
+        let used: Vec<IpAddr> = self
+            .node
+            .registry
+            .inlets
+            .values()
+            .await
+            .iter()
+            .filter_map(|inlet| SocketAddr::from_str(&inlet.bind_addr).ok())
+            .map(|addr| addr.ip())
+            .collect();
+        (1..=u16::MAX - 1)
+            .map(|n| {
+                let [high, low] = n.to_be_bytes();
+                Ipv4Addr::new(LOOPBACK_PREFIX[0], LOOPBACK_PREFIX[1], high, low)
+            })
+            .find(|address| !used.contains(&IpAddr::V4(*address)))
+            .ok_or_else(|| {
+                ockam_core::Error::new(
+                    Origin::Node,
+                    Kind::ResourceExhausted,
+                    "no loopback address is available for a new inlet",
+                )
+            })
+    }
+
+    /// Return the address of the project of the node
+    fn node_project(&self) -> Result<MultiAddr> {
+        let project = self
+            .node
+            .cli_state
+            .nodes
+            .get(self.node.node_name())?
+            .config()
+            .setup()
+            .project
+            .as_ref()
+            .map(|project| project.name.clone())
+            .ok_or_else(|| {
+                ockam_core::Error::new(
+                    Origin::Node,
+                    Kind::NotFound,
+                    "the node must belong to a project to connect to a service",
+                )
+            })?;
+        MultiAddr::from_str(&format!("/project/{project}"))
+            .map_err(|e| ockam_core::Error::new(Origin::Node, Kind::Invalid, e))
+    }
+}
+
+/// DNS query, with a single question
+#[derive(Debug, PartialEq, Eq)]
+struct DnsQuery {
+    id: u16,
+    opcode: u8,
+    recursion_desired: bool,
+    name: String,
+    qtype: u16,
+    qclass: u16,
+    /// Question section, copied in the response
+    question: Vec<u8>,
+}
+
+impl DnsQuery {
+    /// Parse a DNS query. If the message is not a valid query, return its identifier
+    /// when the header can be read
+    fn parse(packet: &[u8]) -> std::result::Result<Self, Option<u16>> {
+        if packet.len() < 12 {
+            return Err(None);
+        }
+        let id = u16::from_be_bytes([packet[0], packet[1]]);
+        let flags = u16::from_be_bytes([packet[2], packet[3]]);
+        // Responses are ignored
+        if flags & 0x8000 != 0 {
+            return Err(None);
+        }
+        let question_count = u16::from_be_bytes([packet[4], packet[5]]);
+        if question_count != 1 {
+            return Err(Some(id));
+        }
+
+        let mut labels = vec![];
+        let mut offset = 12;
+        loop {
+            let length = *packet.get(offset).ok_or(Some(id))? as usize;
+            offset += 1;
+            if length == 0 {
+                break;
+            }
+            // Compressed names are not expected in a question
+            if length > 63 {
+                return Err(Some(id));
+            }
+            let label = packet.get(offset..offset + length).ok_or(Some(id))?;
+            labels.push(String::from_utf8_lossy(label).to_string());
+            offset += length;
+        }
+        let name = labels.join(".");
+        if name.len() > 253 {
+            return Err(Some(id));
+        }
+        let fields = packet.get(offset..offset + 4).ok_or(Some(id))?;
+        Ok(Self {
+            id,
+            opcode: ((flags >> 11) & 0x0f) as u8,
+            recursion_desired: flags & 0x0100 != 0,
+            name,
+            qtype: u16::from_be_bytes([fields[0], fields[1]]),
+            qclass: u16::from_be_bytes([fields[2], fields[3]]),
+            question: packet[12..offset + 4].to_vec(),
+        })
+    }
+
+    /// Create an authoritative response, with an optional `A` record
+    fn response(&self, rcode: u8, address: Option<Ipv4Addr>) -> Vec<u8> {
+        let mut flags: u16 = 0x8000 | 0x0400 | ((self.opcode as u16) << 11) | rcode as u16;
+        if self.recursion_desired {
+            flags |= 0x0100;
+        }
+        let mut response = Vec::with_capacity(12 + self.question.len() + 16);
+        response.extend_from_slice(&self.id.to_be_bytes());
+        response.extend_from_slice(&flags.to_be_bytes());
+        response.extend_from_slice(&1u16.to_be_bytes());
+        response.extend_from_slice(&(address.is_some() as u16).to_be_bytes());
+        response.extend_from_slice(&[0, 0, 0, 0]);
+        response.extend_from_slice(&self.question);
+        if let Some(address) = address {
+            // Pointer to the name of the question
+            response.extend_from_slice(&[0xc0, 0x0c]);
+            response.extend_from_slice(&TYPE_A.to_be_bytes());
+            response.extend_from_slice(&CLASS_IN.to_be_bytes());
+            response.extend_from_slice(&DNS_TTL.to_be_bytes());
+            response.extend_from_slice(&4u16.to_be_bytes());
+            response.extend_from_slice(&address.octets());
+        }
+        response
+    }
+
+    /// Create an error response to a message which couldn't be parsed
+    fn error(id: u16, rcode: u8) -> Vec<u8> {
+        let mut response = Vec::with_capacity(12);
+        response.extend_from_slice(&id.to_be_bytes());
+        response.extend_from_slice(&(0x8000 | rcode as u16).to_be_bytes());
+        response.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+        let mut packet = vec![];
+        packet.extend_from_slice(&id.to_be_bytes());
+        packet.extend_from_slice(&0x0100u16.to_be_bytes());
+        packet.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&qtype.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet
+    }
+
+    #[test]
+    fn test_dns_query() {
+        let packet = query(0x1234, "Postgres.internal.ockam", TYPE_A);
+        let parsed = DnsQuery::parse(&packet).unwrap();
+        assert_eq!(parsed.id, 0x1234);
+        assert_eq!(parsed.name, "Postgres.internal.ockam");
+        assert_eq!(normalize_name(&parsed.name), "postgres.internal.ockam");
+        assert_eq!(parsed.qtype, TYPE_A);
+        assert!(parsed.recursion_desired);
+
+        let response = parsed.response(RCODE_NO_ERROR, Some(Ipv4Addr::new(127, 77, 0, 1)));
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        // QR, AA and RD are set, no error
+        assert_eq!(&response[2..4], &[0x85, 0x00]);
+        // one question and one answer
+        assert_eq!(&response[4..8], &[0, 1, 0, 1]);
+        assert_eq!(&response[12..packet.len()], &packet[12..]);
+        assert_eq!(&response[response.len() - 4..], &[127, 77, 0, 1]);
+
+        let response = parsed.response(RCODE_NAME_ERROR, None);
+        assert_eq!(response[3] & 0x0f, RCODE_NAME_ERROR);
+        assert_eq!(&response[6..8], &[0, 0]);
+        assert_eq!(response.len(), packet.len());
+
+        // truncated messages are rejected with their identifier
+        assert_eq!(
+            DnsQuery::parse(&packet[..packet.len() - 2]),
+            Err(Some(0x1234))
+        );
+        assert_eq!(DnsQuery::parse(&packet[..4]), Err(None));
+    }
+
+    #[test]
+    fn test_dns_service_name() {
+        let name = DnsServiceName::from_str("Payments.internal.ockam.=payments-db:5432").unwrap();
+        assert_eq!(name.name, "payments.internal.ockam");
+        assert_eq!(name.service, "payments-db");
+        assert_eq!(name.port, 5432);
+        assert_eq!(name.to_string(), "payments.internal.ockam=payments-db:5432");
+
+        assert!(DnsServiceName::from_str("payments.internal.ockam").is_err());
+        assert!(DnsServiceName::from_str("payments.internal.ockam=payments-db").is_err());
+        assert!(DnsServiceName::from_str("=payments-db:5432").is_err());
+        assert!(DnsServiceName::from_str("payments.internal.ockam=payments-db:0").is_err());
+    }
+}
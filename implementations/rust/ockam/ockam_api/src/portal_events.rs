@@ -0,0 +1,559 @@
+//! Stream of portal connection events, for the integration with a SIEM.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use ockam::identity::{Identifier, IdentityAttributesReader, IdentitySecureChannelLocalInfo};
+use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::rand::random;
+use ockam_core::{async_trait, Address, IncomingAccessControl, LocalMessage, RelayMessage, Result};
+use ockam_transport_tcp::{
+    OutletConnectionObserver, OutletConnectionPermit, PortalConnectionStats,
+};
+
+use crate::cli_state::DisplayNames;
+
+/// Maximum number of events waiting to be written
+const MAX_PENDING_EVENTS: usize = 10_000;
+
+/// Destination of the portal events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalEventsSink {
+    /// Append the events to a file
+    File(PathBuf),
+    /// Send the events to a local Unix socket, for example the one of a log forwarder
+    UnixSocket(PathBuf),
+}
+
+impl FromStr for PortalEventsSink {
+    type Err = String;
+
+    /// Parse `unix:<path>`, `file:<path>` or `<path>`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let sink = match s.split_once(':') {
+            Some(("unix", path)) => PortalEventsSink::UnixSocket(path.into()),
+            Some(("file", path)) => PortalEventsSink::File(path.into()),
+            _ => PortalEventsSink::File(s.into()),
+        };
+        match &sink {
+            PortalEventsSink::File(path) | PortalEventsSink::UnixSocket(path)
+                if path.as_os_str().is_empty() =>
+            {
+                Err(format!("the portal events sink {s} doesn't have a path"))
+            }
+            _ => Ok(sink),
+        }
+    }
+}
+
+impl Display for PortalEventsSink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortalEventsSink::File(path) => write!(f, "file:{}", path.display()),
+            PortalEventsSink::UnixSocket(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl PortalEventsSink {
+    async fn open(&self) -> std::io::Result<Box<dyn AsyncWrite + Unpin + Send>> {
+        match self {
+            PortalEventsSink::File(path) => Ok(Box::new(
+                tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?,
+            )),
+            #[cfg(unix)]
+            PortalEventsSink::UnixSocket(path) => {
+                Ok(Box::new(tokio::net::UnixStream::connect(path).await?))
+            }
+            #[cfg(not(unix))]
+            PortalEventsSink::UnixSocket(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortalEventKind {
+    Opened,
+    Closed,
+    Denied,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyDecision {
+    Allow,
+    Deny,
+}
+
+/// A portal connection event, serialized as one JSON line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortalEvent {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    pub event: PortalEventKind,
+    pub node: String,
+    /// Alias of the outlet
+    pub outlet: String,
+    /// Identifier shared by the `opened` and `closed` events of a connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connection_id: Option<String>,
+    /// Address of the TCP server the outlet connects to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Identifier of the peer, if the connection comes from a secure channel
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_identifier: Option<String>,
+    /// Name of the peer identifier, if it is known locally
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peer_name: Option<String>,
+    /// Attributes verified for the peer identifier
+    #[serde(default)]
+    pub attributes: BTreeMap<String, String>,
+    pub policy_decision: PolicyDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_to_target: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_from_target: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+impl PortalEvent {
+    fn new(
+        event: PortalEventKind,
+        node: &str,
+        outlet: &str,
+        peer: Option<&Identifier>,
+        policy_decision: PolicyDecision,
+    ) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            event,
+            node: node.to_string(),
+            outlet: outlet.to_string(),
+            connection_id: None,
+            target: None,
+            peer_identifier: peer.map(|p| p.to_string()),
+            peer_name: None,
+            attributes: BTreeMap::new(),
+            policy_decision,
+            bytes_to_target: None,
+            bytes_from_target: None,
+            duration_ms: None,
+        }
+    }
+}
+
+/// An event waiting for the attributes of its peer to be added before being written
+struct PendingEvent {
+    event: PortalEvent,
+    peer: Option<Identifier>,
+    /// ABAC resource of the outlet: only the peer attributes valid for this resource are added
+    resource: String,
+}
+
+/// Emitter of the portal events of a node
+#[derive(Clone)]
+pub struct PortalEvents {
+    node_name: String,
+    sender: mpsc::Sender<PendingEvent>,
+}
+
+impl std::fmt::Debug for PortalEvents {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PortalEvents")
+    }
+}
+
+impl PortalEvents {
+    /// Start writing the portal events of a node to a sink
+    pub fn start(
+        node_name: impl Into<String>,
+        sink: PortalEventsSink,
+        attributes_reader: Arc<dyn IdentityAttributesReader>,
+        display_names: DisplayNames,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel(MAX_PENDING_EVENTS);
+        info!("Writing the portal events to {sink}");
+        tokio::spawn(write_events(
+            sink,
+            receiver,
+            attributes_reader,
+            display_names,
+        ));
+        Self {
+            node_name: node_name.into(),
+            sender,
+        }
+    }
+
+    /// Return an observer emitting the `opened` and `closed` events of the connections to an outlet
+    pub(crate) fn outlet_observer(
+        &self,
+        outlet: &str,
+        resource: &str,
+    ) -> Arc<dyn OutletConnectionObserver> {
+        Arc::new(OutletEventsObserver {
+            events: self.clone(),
+            outlet: outlet.to_string(),
+            resource: resource.to_string(),
+        })
+    }
+
+    /// Wrap the access control of an outlet to emit a `denied` event when a new connection
+    /// to the outlet listener is refused
+    pub(crate) fn outlet_access_control(
+        &self,
+        outlet: &str,
+        resource: &str,
+        listener: Address,
+        access_control: Arc<dyn IncomingAccessControl>,
+    ) -> Arc<dyn IncomingAccessControl> {
+        Arc::new(AuditedAccessControl {
+            inner: access_control,
+            events: self.clone(),
+            outlet: outlet.to_string(),
+            resource: resource.to_string(),
+            listener,
+        })
+    }
+
+    fn emit(&self, event: PortalEvent, peer: Option<Identifier>, resource: &str) {
+        let pending = PendingEvent {
+            event,
+            peer,
+            resource: resource.to_string(),
+        };
+        if let Err(e) = self.sender.try_send(pending) {
+            warn!("A portal event was dropped: {e}");
+        }
+    }
+}
+
+/// Write the events until all the emitters are dropped
+async fn write_events(
+    sink: PortalEventsSink,
+    mut receiver: mpsc::Receiver<PendingEvent>,
+    attributes_reader: Arc<dyn IdentityAttributesReader>,
+    display_names: DisplayNames,
+) {
+    let mut writer: Option<Box<dyn AsyncWrite + Unpin + Send>> = None;
+    while let Some(PendingEvent {
+        mut event,
+        peer,
+        resource,
+    }) = receiver.recv().await
+    {
+        if let Some(peer) = &peer {
+            event.peer_name = display_names.name(peer).map(|n| n.to_string());
+            match attributes_reader
+                .get_attributes_for(peer, Some(&resource))
+                .await
+            {
+                Ok(Some(entry)) => {
+                    event.attributes = entry
+                        .attrs()
+                        .iter()
+                        .map(|(k, v)| {
+                            (
+                                String::from_utf8_lossy(k).to_string(),
+                                String::from_utf8_lossy(v).to_string(),
+                            )
+                        })
+                        .collect()
+                }
+                Ok(None) => (),
+                Err(e) => warn!(%peer, %e, "The attributes of a portal peer can't be read"),
+            }
+        }
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!(%e, "A portal event can't be serialized");
+                continue;
+            }
+        };
+        line.push(b'\n');
+
+        if writer.is_none() {
+            match sink.open().await {
+                Ok(w) => writer = Some(w),
+                Err(e) => {
+                    warn!(%sink, %e, "The portal events sink can't be opened, an event was dropped");
+                    continue;
+                }
+            }
+        }
+        if let Some(w) = writer.as_mut() {
+            let written = match w.write_all(&line).await {
+                Ok(()) => w.flush().await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                warn!(%sink, %e, "A portal event can't be written, the sink will be reopened");
+                writer = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OutletEventsObserver {
+    events: PortalEvents,
+    outlet: String,
+    resource: String,
+}
+
+impl OutletConnectionObserver for OutletEventsObserver {
+    fn connection_opened(
+        &self,
+        message: &LocalMessage,
+        peer: SocketAddr,
+        stats: PortalConnectionStats,
+    ) -> OutletConnectionPermit {
+        let identifier = IdentitySecureChannelLocalInfo::find_info(message)
+            .ok()
+            .map(|i| i.their_identity_id());
+        let connection_id = hex::encode(random::<[u8; 8]>());
+
+        let mut event = PortalEvent::new(
+            PortalEventKind::Opened,
+            &self.events.node_name,
+            &self.outlet,
+            identifier.as_ref(),
+            PolicyDecision::Allow,
+        );
+        event.connection_id = Some(connection_id.clone());
+        event.target = Some(peer.to_string());
+        self.events.emit(event, identifier.clone(), &self.resource);
+
+        Box::new(ConnectionGuard {
+            events: self.events.clone(),
+            outlet: self.outlet.clone(),
+            resource: self.resource.clone(),
+            connection_id,
+            target: peer,
+            peer: identifier,
+            stats,
+            opened_at: Instant::now(),
+        })
+    }
+}
+
+/// Emit the `closed` event of a connection when it is dropped
+struct ConnectionGuard {
+    events: PortalEvents,
+    outlet: String,
+    resource: String,
+    connection_id: String,
+    target: SocketAddr,
+    peer: Option<Identifier>,
+    stats: PortalConnectionStats,
+    opened_at: Instant,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        let mut event = PortalEvent::new(
+            PortalEventKind::Closed,
+            &self.events.node_name,
+            &self.outlet,
+            self.peer.as_ref(),
+            PolicyDecision::Allow,
+        );
+        event.connection_id = Some(self.connection_id.clone());
+        event.target = Some(self.target.to_string());
+        event.bytes_to_target = Some(self.stats.bytes_to_peer());
+        event.bytes_from_target = Some(self.stats.bytes_from_peer());
+        event.duration_ms = Some(self.opened_at.elapsed().as_millis() as u64);
+        self.events.emit(event, self.peer.take(), &self.resource);
+    }
+}
+
+#[derive(Debug)]
+struct AuditedAccessControl {
+    inner: Arc<dyn IncomingAccessControl>,
+    events: PortalEvents,
+    outlet: String,
+    resource: String,
+    listener: Address,
+}
+
+#[async_trait]
+impl IncomingAccessControl for AuditedAccessControl {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        let authorized = self.inner.is_authorized(relay_msg).await?;
+        // only the refused connections are reported, not each refused message of a connection
+        if !authorized && relay_msg.destination() == &self.listener {
+            let identifier = IdentitySecureChannelLocalInfo::find_info(relay_msg.local_message())
+                .ok()
+                .map(|i| i.their_identity_id());
+            let event = PortalEvent::new(
+                PortalEventKind::Denied,
+                &self.events.node_name,
+                &self.outlet,
+                identifier.as_ref(),
+                PolicyDecision::Deny,
+            );
+            self.events.emit(event, identifier, &self.resource);
+        }
+        Ok(authorized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::utils::now;
+    use ockam::identity::{identities, AttributesEntry};
+    use std::path::Path;
+    use std::time::Duration;
+
+    #[test]
+    fn test_parse_sink() {
+        assert_eq!(
+            PortalEventsSink::from_str("unix:/var/run/siem.sock"),
+            Ok(PortalEventsSink::UnixSocket("/var/run/siem.sock".into()))
+        );
+        assert_eq!(
+            PortalEventsSink::from_str("file:events.jsonl"),
+            Ok(PortalEventsSink::File("events.jsonl".into()))
+        );
+        assert_eq!(
+            PortalEventsSink::from_str("/var/log/ockam/events.jsonl"),
+            Ok(PortalEventsSink::File("/var/log/ockam/events.jsonl".into()))
+        );
+        assert!(PortalEventsSink::from_str("unix:").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_events_to_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let events = PortalEvents::start(
+            "n1",
+            PortalEventsSink::File(path.clone()),
+            identities().repository().as_attributes_reader(),
+            DisplayNames::new(),
+        );
+
+        let stats = PortalConnectionStats::default();
+        let guard = events.outlet_observer("db", "db").connection_opened(
+            &LocalMessage::new(
+                ockam_core::TransportMessage::v1(
+                    ockam_core::route![],
+                    ockam_core::route![],
+                    vec![],
+                ),
+                vec![],
+            ),
+            "127.0.0.1:5432".parse().unwrap(),
+            stats,
+        );
+        drop(guard);
+
+        let lines = read_events(&path, 2).await;
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].event, PortalEventKind::Opened);
+        assert_eq!(lines[1].event, PortalEventKind::Closed);
+        assert_eq!(lines[0].connection_id, lines[1].connection_id);
+        assert_eq!(lines[1].outlet, "db");
+        assert_eq!(lines[1].bytes_to_target, Some(0));
+        assert_eq!(lines[1].peer_identifier, None);
+        assert_eq!(lines[1].peer_name, None);
+    }
+
+    #[tokio::test]
+    async fn test_only_add_the_peer_attributes_valid_for_the_outlet() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let identities = identities();
+        let repository = identities.repository();
+        let peer = identities.identities_creation().create_identity().await?;
+        repository
+            .put_attributes(
+                peer.identifier(),
+                AttributesEntry::new(
+                    BTreeMap::from([(b"team".to_vec(), b"payments".to_vec())]),
+                    now()?,
+                    None,
+                    None,
+                )
+                .with_audience(Some(vec!["db".to_string()])),
+            )
+            .await?;
+        let events = PortalEvents::start(
+            "n1",
+            PortalEventsSink::File(path.clone()),
+            repository.as_attributes_reader(),
+            DisplayNames::new(),
+        );
+
+        let message = LocalMessage::new(
+            ockam_core::TransportMessage::v1(ockam_core::route![], ockam_core::route![], vec![]),
+            IdentitySecureChannelLocalInfo::mark(vec![], peer.identifier().clone())?,
+        );
+        let target = "127.0.0.1:5432".parse().unwrap();
+        let stats = PortalConnectionStats::default();
+        drop(
+            events
+                .outlet_observer("db", "db")
+                .connection_opened(&message, target, stats.clone()),
+        );
+        drop(
+            events
+                .outlet_observer("cache", "cache")
+                .connection_opened(&message, target, stats),
+        );
+
+        let lines = read_events(&path, 4).await;
+        assert_eq!(lines.len(), 4);
+        for event in lines {
+            assert_eq!(event.peer_identifier, Some(peer.identifier().to_string()));
+            if event.outlet == "db" {
+                assert_eq!(
+                    event.attributes.get("team").map(|v| v.as_str()),
+                    Some("payments")
+                );
+            } else {
+                assert!(event.attributes.is_empty());
+            }
+        }
+        Ok(())
+    }
+
+    async fn read_events(path: &Path, expected: usize) -> Vec<PortalEvent> {
+        let mut lines = vec![];
+        for _ in 0..50 {
+            lines = std::fs::read_to_string(path)
+                .unwrap_or_default()
+                .lines()
+                .map(|l| serde_json::from_str::<PortalEvent>(l).unwrap())
+                .collect::<Vec<_>>();
+            if lines.len() == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        lines
+    }
+}
@@ -0,0 +1,205 @@
+//! Invitations to access a portal.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier};
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{Identifier, Identities, IdentityError};
+use ockam_core::Result;
+
+use crate::cli_state::cached::now;
+use crate::error::ApiError;
+use crate::identity::EnrollmentTicket;
+
+/// Identifier for the schema of the statements signing an invitation
+pub const PORTAL_INVITATION_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(5);
+
+/// Name of the attribute containing the SHA-256 digest of an invitation
+pub const PORTAL_INVITATION_DIGEST: &[u8] = b"ockam.portal_invitation.digest";
+
+/// Attribute given to the members enrolled with an invitation, containing the invitation id
+pub const INVITATION_ID_ATTRIBUTE: &str = "ockam-invitation";
+
+/// Service and enrollment ticket of an invitation
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PortalInvitation {
+    pub id: String,
+    /// Name of the service, published in the service registry of the project
+    pub service: String,
+    /// Attributes given to the invitee, which are required by the policy of the service outlet
+    pub attributes: BTreeMap<String, String>,
+    pub ticket: EnrollmentTicket,
+    /// Seconds since the Unix epoch
+    pub expires_at: u64,
+}
+
+impl PortalInvitation {
+    pub fn new(
+        id: impl Into<String>,
+        service: impl Into<String>,
+        attributes: BTreeMap<String, String>,
+        ticket: EnrollmentTicket,
+        expires_in: Duration,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            service: service.into(),
+            attributes,
+            ticket,
+            expires_at: now().saturating_add(expires_in.as_secs()),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now()
+    }
+
+    /// SHA-256 digest of the JSON representation of the invitation
+    async fn digest(&self, identities: &Identities) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self)
+            .map_err(|e| ApiError::core(format!("the invitation can't be encoded: {e}")))?;
+        Ok(identities
+            .vault()
+            .verifying_vault
+            .sha256(&json)
+            .await?
+            .0
+            .to_vec())
+    }
+
+    /// Sign the invitation with the identity of the inviter
+    pub async fn sign(
+        self,
+        identities: &Identities,
+        inviter: &Identifier,
+    ) -> Result<SignedPortalInvitation> {
+        let attributes = AttributesBuilder::with_schema(PORTAL_INVITATION_SCHEMA)
+            .with_attribute(
+                PORTAL_INVITATION_DIGEST.to_vec(),
+                self.digest(identities).await?,
+            )
+            .build();
+        let validity = Duration::from_secs(self.expires_at.saturating_sub(now()));
+        let statement = identities
+            .credentials()
+            .credentials_creation()
+            .issue_credential(inviter, inviter, attributes, validity)
+            .await?;
+        Ok(SignedPortalInvitation {
+            invitation: self,
+            inviter: inviter.clone(),
+            inviter_identity: hex::encode(identities.export_identity(inviter).await?),
+            signature: hex::encode(minicbor::to_vec(&statement)?),
+        })
+    }
+}
+
+/// An invitation with the identity of the inviter and a statement attesting its digest
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SignedPortalInvitation {
+    pub invitation: PortalInvitation,
+    pub inviter: Identifier,
+    /// Change history of the inviter, hex encoded
+    pub inviter_identity: String,
+    /// Statement of the inviter, hex encoded
+    pub signature: String,
+}
+
+impl SignedPortalInvitation {
+    /// Encode the invitation as a hex string, like an enrollment ticket
+    pub fn hex_encoded(&self) -> Result<String> {
+        let serialized = serde_json::to_vec(self)
+            .map_err(|e| ApiError::core(format!("the invitation can't be encoded: {e}")))?;
+        Ok(hex::encode(serialized))
+    }
+
+    pub fn hex_decoded(encoded: &str) -> Result<Self> {
+        let decoded = hex::decode(encoded.trim())
+            .map_err(|e| ApiError::core(format!("the invitation can't be decoded: {e}")))?;
+        serde_json::from_slice(&decoded)
+            .map_err(|e| ApiError::core(format!("the invitation is invalid: {e}")))
+    }
+
+    /// Check that the invitation was signed by its inviter and has not expired, and return it
+    pub async fn verify(&self, identities: &Identities) -> Result<PortalInvitation> {
+        if self.invitation.is_expired() {
+            return Err(ApiError::core("the invitation has expired"));
+        }
+        let invalid = |e: hex::FromHexError| {
+            ApiError::core(format!("the signature of the invitation is invalid: {e}"))
+        };
+        let inviter_identity = hex::decode(&self.inviter_identity).map_err(invalid)?;
+        identities
+            .identities_creation()
+            .import(Some(&self.inviter), &inviter_identity)
+            .await?;
+        let statement: CredentialAndPurposeKey =
+            minicbor::decode(&hex::decode(&self.signature).map_err(invalid)?)?;
+        let data = identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(Some(&self.inviter), &[self.inviter.clone()], &statement)
+            .await?;
+        let attributes = data.credential_data.subject_attributes;
+        if attributes.schema != PORTAL_INVITATION_SCHEMA {
+            return Err(IdentityError::CredentialVerificationFailed.into());
+        }
+        let attested_digest = attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == PORTAL_INVITATION_DIGEST)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if attested_digest != Some(self.invitation.digest(identities).await?) {
+            return Err(ApiError::core(
+                "the invitation was modified after it was signed",
+            ));
+        }
+        Ok(self.invitation.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::{identities, OneTimeCode};
+
+    #[tokio::test]
+    async fn test_sign_and_verify() -> Result<()> {
+        let inviter_identities = identities();
+        let inviter = inviter_identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        let invitation = PortalInvitation::new(
+            "invitation",
+            "db",
+            BTreeMap::from([("role".to_string(), "reader".to_string())]),
+            EnrollmentTicket::new(OneTimeCode::new(), None, None),
+            Duration::from_secs(3600),
+        );
+        let encoded = invitation
+            .sign(&inviter_identities, &inviter)
+            .await?
+            .hex_encoded()?;
+
+        // the invitation is accepted on another machine
+        let signed = SignedPortalInvitation::hex_decoded(&encoded)?;
+        let accepted = signed.verify(&identities()).await?;
+        assert_eq!(accepted.service, "db");
+        assert_eq!(accepted.attributes["role"], "reader");
+
+        let mut modified = signed.clone();
+        modified.invitation.service = "admin".to_string();
+        assert!(modified.verify(&identities()).await.is_err());
+
+        let mut expired = signed;
+        expired.invitation.expires_at = now() - 1;
+        assert!(expired.verify(&identities()).await.is_err());
+        Ok(())
+    }
+}
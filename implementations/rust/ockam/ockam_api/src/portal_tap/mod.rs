@@ -0,0 +1,598 @@
+//! Taps recording the plaintext of the connections of a portal to a local `pcap` or `jsonl` file,
+//! for a bounded duration and only for the identities allowed by the `tap` policy.
+
+mod pcap;
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use ockam::identity::{Identifier, IdentitiesRepository, IdentitySecureChannelLocalInfo};
+use ockam_abac::expr::str;
+use ockam_abac::{AbacAccessControl, Env, Expr};
+use ockam_core::{async_trait, LocalMessage, Result};
+use ockam_node::Context;
+use ockam_transport_tcp::{PortalDirection, PortalInterceptor, PortalInterceptorFactory};
+
+use crate::cli_state::cached::{now, now_millis};
+use crate::error::ApiError;
+use crate::nodes::models::portal::PortalTapStatus;
+use pcap::TcpStream;
+
+/// Maximum duration of a tap
+pub const MAX_TAP_DURATION: Duration = Duration::from_secs(3600);
+
+/// Maximum number of bytes written by a tap
+pub const MAX_TAP_SIZE: u64 = 256 * 1024 * 1024;
+
+/// First port of the clients of the connections written in a `pcap` file
+const FIRST_CLIENT_PORT: u16 = 40_000;
+
+/// Side of a portal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PortalSide {
+    Inlet,
+    Outlet,
+}
+
+impl Display for PortalSide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortalSide::Inlet => f.write_str("inlet"),
+            PortalSide::Outlet => f.write_str("outlet"),
+        }
+    }
+}
+
+/// Format of the file written by a tap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapFormat {
+    #[default]
+    Pcap,
+    Jsonl,
+}
+
+impl FromStr for TapFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "pcap" => Ok(TapFormat::Pcap),
+            "jsonl" => Ok(TapFormat::Jsonl),
+            _ => Err(format!("unknown tap format {s}, expected pcap or jsonl")),
+        }
+    }
+}
+
+impl Display for TapFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TapFormat::Pcap => f.write_str("pcap"),
+            TapFormat::Jsonl => f.write_str("jsonl"),
+        }
+    }
+}
+
+/// Chunk of data of a connection, one line of a `jsonl` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TapRecord {
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Number of the connection in the node
+    pub connection: u64,
+    /// True if the data was sent by the client of the connection
+    pub from_client: bool,
+    /// Data in hexadecimal
+    pub data: String,
+}
+
+/// Taps of the portals of a node
+#[derive(Clone)]
+pub struct PortalTaps {
+    /// Directory of the files of the taps
+    dir: PathBuf,
+    state: Arc<Mutex<TapsState>>,
+}
+
+#[derive(Default)]
+struct TapsState {
+    taps: BTreeMap<(PortalSide, String), Arc<PortalTap>>,
+    next_tap: u64,
+    next_connection: u64,
+}
+
+impl Debug for PortalTaps {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("PortalTaps")
+    }
+}
+
+impl PortalTaps {
+    /// Create the taps of a node, writing their files in a directory
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            state: Default::default(),
+        }
+    }
+
+    /// Start recording the connections of a portal to a new file, replacing its previous tap.
+    /// The path of the file is relative to the directory of the taps
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn start(
+        &self,
+        side: PortalSide,
+        alias: &str,
+        file: &Path,
+        format: TapFormat,
+        duration: Duration,
+        policy: Expr,
+        server_port: u16,
+    ) -> Result<PortalTapStatus> {
+        if duration.is_zero() || duration > MAX_TAP_DURATION {
+            return Err(ApiError::core(format!(
+                "the duration of a tap must be between 1 second and {} seconds",
+                MAX_TAP_DURATION.as_secs()
+            )));
+        }
+        let path = self.file_path(file)?;
+        let mut file = create_tap_file(&path)
+            .map_err(|e| ApiError::core(format!("{} can't be created: {e}", path.display())))?;
+        let header = match format {
+            TapFormat::Pcap => pcap::file_header(),
+            TapFormat::Jsonl => vec![],
+        };
+        file.write_all(&header)
+            .map_err(|e| ApiError::core(e.to_string()))?;
+
+        let expires_at = now() + duration.as_secs();
+        let mut state = self.state.lock().unwrap();
+        state.next_tap += 1;
+        let tap = PortalTap {
+            id: state.next_tap,
+            path: path.clone(),
+            format,
+            policy,
+            server_port,
+            expires_at: Instant::now() + duration,
+            sink: Mutex::new(TapSink {
+                file: Some(file),
+                written: header.len() as u64,
+            }),
+        };
+        state.taps.insert((side, alias.to_string()), Arc::new(tap));
+        info!(%side, %alias, path = %path.display(), %format, "Started a tap");
+        Ok(PortalTapStatus {
+            alias: alias.to_string(),
+            path: path.to_string_lossy().to_string(),
+            format: format.to_string(),
+            expires_at,
+        })
+    }
+
+    /// Stop recording the connections of a portal. Return false if there was no tap
+    pub(crate) fn stop(&self, side: PortalSide, alias: &str) -> bool {
+        let stopped = self
+            .state
+            .lock()
+            .unwrap()
+            .taps
+            .remove(&(side, alias.to_string()))
+            .is_some();
+        if stopped {
+            info!(%side, %alias, "Stopped a tap");
+        }
+        stopped
+    }
+
+    /// Return an interceptor factory recording the connections of a portal when it is tapped,
+    /// then calling the interceptors of another factory if there is one
+    pub(crate) fn interceptor_factory(
+        &self,
+        side: PortalSide,
+        alias: &str,
+        local_identity: Identifier,
+        repository: Arc<dyn IdentitiesRepository>,
+        inner: Option<Arc<dyn PortalInterceptorFactory>>,
+    ) -> Arc<dyn PortalInterceptorFactory> {
+        Arc::new(TapInterceptorFactory {
+            taps: self.clone(),
+            side,
+            alias: alias.to_string(),
+            local_identity,
+            repository,
+            inner,
+        })
+    }
+
+    /// Return the tap of a portal if it is still recording
+    fn active(&self, side: PortalSide, alias: &str) -> Option<Arc<PortalTap>> {
+        let mut state = self.state.lock().unwrap();
+        let key = (side, alias.to_string());
+        let tap = state.taps.get(&key)?.clone();
+        if tap.is_finished() {
+            state.taps.remove(&key);
+            info!(%side, %alias, path = %tap.path.display(), "The tap is finished");
+            return None;
+        }
+        Some(tap)
+    }
+
+    /// Return the path of a tap file, which must be a relative path without parent components
+    fn file_path(&self, file: &Path) -> Result<PathBuf> {
+        let is_relative = file
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !is_relative || file.as_os_str().is_empty() {
+            return Err(ApiError::core(format!(
+                "the file of a tap must be a relative path in the {} directory, without '..': {}",
+                self.dir.display(),
+                file.display()
+            )));
+        }
+        Ok(self.dir.join(file))
+    }
+
+    fn next_connection(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_connection += 1;
+        state.next_connection
+    }
+}
+
+struct PortalTap {
+    id: u64,
+    path: PathBuf,
+    format: TapFormat,
+    policy: Expr,
+    /// Port of the servers of the connections, in a `pcap` file
+    server_port: u16,
+    expires_at: Instant,
+    sink: Mutex<TapSink>,
+}
+
+struct TapSink {
+    /// File of the tap, closed when it can't be written anymore
+    file: Option<File>,
+    written: u64,
+}
+
+impl PortalTap {
+    fn is_finished(&self) -> bool {
+        Instant::now() >= self.expires_at || self.sink.lock().unwrap().file.is_none()
+    }
+
+    fn write(&self, connection: u64, stream: &mut TcpStream, from_client: bool, data: &[u8]) {
+        let timestamp_ms = now_millis();
+        let bytes = match self.format {
+            TapFormat::Pcap => {
+                stream.records(Duration::from_millis(timestamp_ms), from_client, data)
+            }
+            TapFormat::Jsonl => {
+                let record = TapRecord {
+                    timestamp_ms,
+                    connection,
+                    from_client,
+                    data: hex::encode(data),
+                };
+                let mut line = serde_json::to_vec(&record).unwrap_or_default();
+                line.push(b'\n');
+                line
+            }
+        };
+
+        let mut sink = self.sink.lock().unwrap();
+        let written = sink.written + bytes.len() as u64;
+        let Some(file) = sink.file.as_mut() else {
+            return;
+        };
+        if written > MAX_TAP_SIZE {
+            warn!(path = %self.path.display(), "The tap reached its maximum size");
+            sink.file = None;
+        } else if let Err(e) = file.write_all(&bytes) {
+            warn!(path = %self.path.display(), %e, "The tap can't be written");
+            sink.file = None;
+        } else {
+            sink.written = written;
+        }
+    }
+}
+
+struct TapInterceptorFactory {
+    taps: PortalTaps,
+    side: PortalSide,
+    alias: String,
+    local_identity: Identifier,
+    repository: Arc<dyn IdentitiesRepository>,
+    inner: Option<Arc<dyn PortalInterceptorFactory>>,
+}
+
+impl Debug for TapInterceptorFactory {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TapInterceptorFactory")
+            .field("side", &self.side)
+            .field("alias", &self.alias)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl TapInterceptorFactory {
+    fn interceptor(
+        &self,
+        identity: Option<Identifier>,
+        inner: Option<Arc<dyn PortalInterceptor>>,
+    ) -> Arc<dyn PortalInterceptor> {
+        Arc::new(TapInterceptor {
+            taps: self.taps.clone(),
+            side: self.side,
+            alias: self.alias.clone(),
+            repository: self.repository.clone(),
+            identity,
+            connection: self.taps.next_connection(),
+            inner,
+            state: Mutex::new(ConnectionTap::default()),
+        })
+    }
+}
+
+impl PortalInterceptorFactory for TapInterceptorFactory {
+    fn create(&self) -> Arc<dyn PortalInterceptor> {
+        // the connections of an inlet are made on behalf of the node identity
+        let identity = match self.side {
+            PortalSide::Inlet => Some(self.local_identity.clone()),
+            PortalSide::Outlet => None,
+        };
+        self.interceptor(identity, self.inner.as_ref().map(|i| i.create()))
+    }
+
+    fn create_for_message(&self, message: &LocalMessage) -> Arc<dyn PortalInterceptor> {
+        let identity = IdentitySecureChannelLocalInfo::find_info(message)
+            .map(|info| info.their_identity_id())
+            .ok();
+        self.interceptor(
+            identity,
+            self.inner.as_ref().map(|i| i.create_for_message(message)),
+        )
+    }
+}
+
+struct TapInterceptor {
+    taps: PortalTaps,
+    side: PortalSide,
+    alias: String,
+    repository: Arc<dyn IdentitiesRepository>,
+    /// Identity checked by the policy of the taps
+    identity: Option<Identifier>,
+    connection: u64,
+    inner: Option<Arc<dyn PortalInterceptor>>,
+    state: Mutex<ConnectionTap>,
+}
+
+#[derive(Default)]
+struct ConnectionTap {
+    /// Decision of the policy of the current tap, with the id of that tap
+    decision: Option<(u64, bool)>,
+    /// Stream written in the `pcap` file of the current tap, with the id of that tap
+    stream: Option<(u64, TcpStream)>,
+}
+
+impl TapInterceptor {
+    /// Record the data seen by the TCP peer of the portal, if the portal is tapped
+    async fn record(&self, direction: PortalDirection, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let Some(tap) = self.taps.active(self.side, &self.alias) else {
+            return;
+        };
+        let decision = self
+            .state
+            .lock()
+            .unwrap()
+            .decision
+            .filter(|(id, _)| *id == tap.id);
+        let allowed = match decision {
+            Some((_, allowed)) => allowed,
+            None => {
+                let allowed = self.is_allowed(&tap).await;
+                self.state.lock().unwrap().decision = Some((tap.id, allowed));
+                allowed
+            }
+        };
+        if !allowed {
+            return;
+        }
+
+        let from_client = matches!(
+            (self.side, direction),
+            (PortalSide::Inlet, PortalDirection::FromPeer)
+                | (PortalSide::Outlet, PortalDirection::ToPeer)
+        );
+        let mut state = self.state.lock().unwrap();
+        if state.stream.as_ref().map(|(id, _)| *id) != Some(tap.id) {
+            let client_port = FIRST_CLIENT_PORT + (self.connection % 20_000) as u16;
+            state.stream = Some((tap.id, TcpStream::new(client_port, tap.server_port)));
+        }
+        if let Some((_, stream)) = state.stream.as_mut() {
+            tap.write(self.connection, stream, from_client, data);
+        }
+    }
+
+    async fn is_allowed(&self, tap: &PortalTap) -> bool {
+        let Some(identity) = &self.identity else {
+            return false;
+        };
+        let mut environment = Env::new();
+        environment.put("resource.id", str(self.alias.clone()));
+        environment.put("action.id", str(crate::actions::TAP.as_str()));
+        match AbacAccessControl::new(self.repository.clone(), tap.policy.clone(), environment)
+            .is_identity_authorized(identity.clone())
+            .await
+        {
+            Ok(allowed) => allowed,
+            Err(e) => {
+                warn!(%identity, %e, "The tap policy can't be evaluated");
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PortalInterceptor for TapInterceptor {
+    async fn intercept(
+        &self,
+        context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        // the data is recorded as it is read from, or written to, the TCP peer
+        if direction == PortalDirection::FromPeer {
+            self.record(direction, &chunk).await;
+        }
+        let chunk = match &self.inner {
+            Some(inner) => inner.intercept(context, direction, chunk).await?,
+            None => chunk,
+        };
+        if direction == PortalDirection::ToPeer {
+            self.record(direction, &chunk).await;
+        }
+        Ok(chunk)
+    }
+}
+
+/// Create a new tap file, only readable by the current user. An existing file is not replaced
+fn create_tap_file(path: &Path) -> std::io::Result<File> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_state::CliState;
+    use ockam::identity::utils::now;
+    use ockam::identity::{AttributesEntry, IdentitiesStorage};
+
+    #[tokio::test]
+    async fn test_record_allowed_connections() -> Result<()> {
+        let allowed: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let other: Identifier = "Ibb37445cacb3ca7a20040a9b36469e321a57d2cd".try_into()?;
+        let repository: Arc<dyn IdentitiesRepository> = IdentitiesStorage::create();
+        let attributes = BTreeMap::from([(b"role".to_vec(), b"debug".to_vec())]);
+        repository
+            .put_attributes(
+                &allowed,
+                AttributesEntry::new(attributes, now()?, None, None),
+            )
+            .await?;
+
+        let dir = CliState::test_dir().unwrap();
+        let taps = PortalTaps::new(&dir);
+        let factory = taps.interceptor_factory(
+            PortalSide::Outlet,
+            "db",
+            other.clone(),
+            repository.clone(),
+            None,
+        );
+        let interceptor = |identity: &Identifier| TapInterceptor {
+            taps: taps.clone(),
+            side: PortalSide::Outlet,
+            alias: "db".to_string(),
+            repository: repository.clone(),
+            identity: Some(identity.clone()),
+            connection: taps.next_connection(),
+            inner: None,
+            state: Mutex::new(ConnectionTap::default()),
+        };
+        let recorded = interceptor(&allowed);
+        let ignored = interceptor(&other);
+
+        // nothing is recorded before the tap is started
+        recorded.record(PortalDirection::ToPeer, b"before").await;
+        let policy = ockam_abac::parse(r#"(= subject.role "debug")"#)?.unwrap();
+        let status = taps.start(
+            PortalSide::Outlet,
+            "db",
+            Path::new("tap.jsonl"),
+            TapFormat::Jsonl,
+            Duration::from_secs(60),
+            policy.clone(),
+            5432,
+        )?;
+        let path = dir.join("tap.jsonl");
+        assert_eq!(status.path, path.to_string_lossy());
+        recorded.record(PortalDirection::ToPeer, b"select 1").await;
+        recorded.record(PortalDirection::FromPeer, b"1").await;
+        ignored.record(PortalDirection::ToPeer, b"secret").await;
+        assert!(taps.stop(PortalSide::Outlet, "db"));
+        recorded.record(PortalDirection::ToPeer, b"after").await;
+
+        let records: Vec<TapRecord> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let data: Vec<(bool, String)> = records
+            .into_iter()
+            .map(|r| (r.from_client, r.data))
+            .collect();
+        assert_eq!(
+            data,
+            vec![(true, hex::encode("select 1")), (false, hex::encode("1"))]
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // the duration of a tap is bounded
+        let start = |file: &str, duration: Duration| {
+            taps.start(
+                PortalSide::Outlet,
+                "db",
+                Path::new(file),
+                TapFormat::Pcap,
+                duration,
+                policy.clone(),
+                5432,
+            )
+        };
+        assert!(start("tap.pcap", MAX_TAP_DURATION + Duration::from_secs(1)).is_err());
+
+        // the files are only created in the directory of the taps, and are never replaced
+        let duration = Duration::from_secs(60);
+        assert!(start("tap.jsonl", duration).is_err());
+        assert!(start("../tap.pcap", duration).is_err());
+        assert!(start("captures/../../tap.pcap", duration).is_err());
+        assert!(start(&dir.join("other.pcap").to_string_lossy(), duration).is_err());
+        assert!(start("", duration).is_err());
+        assert!(!dir.parent().unwrap().join("tap.pcap").exists());
+        start("captures/tap.pcap", duration)?;
+        assert!(dir.join("captures").join("tap.pcap").exists());
+        assert!(format!("{factory:?}").contains("db"));
+        Ok(())
+    }
+}
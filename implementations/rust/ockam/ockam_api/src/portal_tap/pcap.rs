@@ -0,0 +1,174 @@
+//! Packet capture files of the recorded connections.
+
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+/// Address of the clients of the recorded connections
+pub(crate) const CLIENT_ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+
+/// Address of the servers of the recorded connections
+pub(crate) const SERVER_ADDRESS: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 2);
+
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+
+/// Link type of the packets starting with an IP header
+const LINKTYPE_RAW: u32 = 101;
+
+const SNAPLEN: u32 = 262_144;
+
+const IPV4_HEADER_LENGTH: usize = 20;
+const TCP_HEADER_LENGTH: usize = 20;
+
+/// Maximum size of the data of a segment, to fit in an IPv4 packet
+const MAX_SEGMENT_SIZE: usize = 65_000;
+
+const TCP_PROTOCOL: u8 = 6;
+const PSH_ACK: u8 = 0x18;
+
+/// Header of a capture file
+pub(crate) fn file_header() -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes.extend(PCAP_MAGIC.to_le_bytes());
+    bytes.extend(2u16.to_le_bytes());
+    bytes.extend(4u16.to_le_bytes());
+    // time zone offset and accuracy of the timestamps
+    bytes.extend(0i32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(SNAPLEN.to_le_bytes());
+    bytes.extend(LINKTYPE_RAW.to_le_bytes());
+    bytes
+}
+
+/// Endpoints and sequence numbers of a recorded connection
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TcpStream {
+    client_port: u16,
+    server_port: u16,
+    /// Next sequence number of the data sent by the client
+    client_seq: u32,
+    /// Next sequence number of the data sent by the server
+    server_seq: u32,
+}
+
+impl TcpStream {
+    pub(crate) fn new(client_port: u16, server_port: u16) -> Self {
+        Self {
+            client_port,
+            server_port,
+            client_seq: 1,
+            server_seq: 1,
+        }
+    }
+
+    /// Return the capture records of data sent by the client or by the server
+    pub(crate) fn records(
+        &mut self,
+        timestamp: Duration,
+        from_client: bool,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut bytes = vec![];
+        for segment in data.chunks(MAX_SEGMENT_SIZE) {
+            let packet = self.packet(from_client, segment);
+            bytes.extend((timestamp.as_secs() as u32).to_le_bytes());
+            bytes.extend(timestamp.subsec_micros().to_le_bytes());
+            bytes.extend((packet.len() as u32).to_le_bytes());
+            bytes.extend((packet.len() as u32).to_le_bytes());
+            bytes.extend(packet);
+        }
+        bytes
+    }
+
+    fn packet(&mut self, from_client: bool, segment: &[u8]) -> Vec<u8> {
+        let (source, destination, source_port, destination_port, seq, ack) = if from_client {
+            (
+                CLIENT_ADDRESS,
+                SERVER_ADDRESS,
+                self.client_port,
+                self.server_port,
+                &mut self.client_seq,
+                self.server_seq,
+            )
+        } else {
+            (
+                SERVER_ADDRESS,
+                CLIENT_ADDRESS,
+                self.server_port,
+                self.client_port,
+                &mut self.server_seq,
+                self.client_seq,
+            )
+        };
+
+        let total_length = IPV4_HEADER_LENGTH + TCP_HEADER_LENGTH + segment.len();
+        let mut packet = Vec::with_capacity(total_length);
+        // version 4, header of 5 words
+        packet.push(0x45);
+        packet.push(0);
+        packet.extend((total_length as u16).to_be_bytes());
+        // identification, flags and fragment offset
+        packet.extend([0, 0, 0x40, 0]);
+        // time to live and protocol
+        packet.extend([64, TCP_PROTOCOL]);
+        packet.extend([0, 0]);
+        packet.extend(source.octets());
+        packet.extend(destination.octets());
+        let checksum = ipv4_checksum(&packet);
+        packet[10..12].copy_from_slice(&checksum.to_be_bytes());
+
+        packet.extend(source_port.to_be_bytes());
+        packet.extend(destination_port.to_be_bytes());
+        packet.extend(seq.to_be_bytes());
+        packet.extend(ack.to_be_bytes());
+        // header of 5 words, the TCP checksum is not computed
+        packet.extend([0x50, PSH_ACK]);
+        packet.extend(u16::MAX.to_be_bytes());
+        packet.extend([0, 0, 0, 0]);
+        packet.extend(segment);
+
+        *seq = seq.wrapping_add(segment.len() as u32);
+        packet
+    }
+}
+
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let mut stream = TcpStream::new(40001, 5432);
+        let timestamp = Duration::from_millis(1_500);
+        let request = stream.records(timestamp, true, b"select 1");
+        let response = stream.records(timestamp, false, b"ok");
+
+        // record header, then IPv4 and TCP headers
+        let packet = &request[16..];
+        assert_eq!(&request[4..8], &500_000u32.to_le_bytes());
+        assert_eq!(packet.len(), 48);
+        assert_eq!(ipv4_checksum(&packet[..20]), 0);
+        assert_eq!(&packet[12..16], &CLIENT_ADDRESS.octets());
+        assert_eq!(&packet[20..22], &40001u16.to_be_bytes());
+        assert_eq!(&packet[40..], b"select 1");
+
+        // the acknowledgement of the server follows the data of the client
+        let packet = &response[16..];
+        assert_eq!(&packet[20..22], &5432u16.to_be_bytes());
+        assert_eq!(&packet[24..28], &1u32.to_be_bytes());
+        assert_eq!(&packet[28..32], &9u32.to_be_bytes());
+
+        let large = stream.records(timestamp, true, &[0; MAX_SEGMENT_SIZE + 1]);
+        assert_eq!(large.len(), 2 * (16 + 40) + MAX_SEGMENT_SIZE + 1);
+    }
+}
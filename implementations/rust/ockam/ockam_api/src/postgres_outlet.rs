@@ -0,0 +1,798 @@
+//! Outlets to a PostgreSQL server which authenticate their clients with credentials held by the
+//! outlet node.
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+use ockam::identity::{Identifier, IdentitySecureChannelLocalInfo};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{async_trait, Error, LocalMessage, Result};
+use ockam_node::Context;
+use ockam_transport_tcp::{PortalDirection, PortalInterceptor, PortalInterceptorFactory};
+
+use crate::error::ApiError;
+
+/// Version 3.0 of the Postgres protocol, sent in the startup message
+const PROTOCOL_VERSION: u32 = 196608;
+
+/// Codes sent in place of a protocol version by the requests preceding a startup message
+const SSL_REQUEST_CODE: u32 = 80877103;
+const GSSENC_REQUEST_CODE: u32 = 80877104;
+const CANCEL_REQUEST_CODE: u32 = 80877102;
+
+/// Maximum length of the messages sent before the authentication
+const MAX_STARTUP_MESSAGE_LENGTH: usize = 10_000;
+
+/// Prefix of the startup parameters added by the outlet. They are never sent to the server
+const PARAMETERS_PREFIX: &str = "ockam.";
+const IDENTITY_PARAMETER: &str = "ockam.identity";
+const KEY_PARAMETER: &str = "ockam.key";
+
+const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+/// Sub-codes of the authentication messages sent by the server
+const AUTHENTICATION_OK: u32 = 0;
+const AUTHENTICATION_SASL: u32 = 10;
+const AUTHENTICATION_SASL_CONTINUE: u32 = 11;
+const AUTHENTICATION_SASL_FINAL: u32 = 12;
+
+/// SQLSTATE returned to a client which can't be authenticated: `invalid_authorization_specification`
+const INVALID_AUTHORIZATION: &str = "28000";
+
+/// Credentials of a database user
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PostgresCredentials {
+    #[n(1)] pub user: String,
+    #[n(2)] pub password: String,
+}
+
+impl PostgresCredentials {
+    pub fn new(user: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+}
+
+impl Debug for PostgresCredentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PostgresCredentials")
+            .field("user", &self.user)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Database users of the identities connecting to a Postgres outlet, for example:
+///
+/// ```yaml
+/// identities:
+///   I6c20e814b56579306f55c64e8747e6c1b4a53d9a:
+///     user: reporting
+///     password: secret
+/// default:
+///   user: readonly
+///   password: other-secret
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, Decode, Encode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct PostgresUsers {
+    /// Credentials used for each identity
+    #[serde(default)]
+    #[n(1)] pub identities: BTreeMap<String, PostgresCredentials>,
+    /// Credentials used for the identities which are not listed, if any
+    #[serde(default)]
+    #[n(2)] pub default: Option<PostgresCredentials>,
+}
+
+impl PostgresUsers {
+    /// Parse the YAML description of the users of an outlet
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let users: PostgresUsers = serde_yaml::from_str(yaml)
+            .map_err(|e| ApiError::core(format!("the Postgres users can't be parsed: {e}")))?;
+        users.validate()?;
+        Ok(users)
+    }
+
+    pub fn with_identity(
+        mut self,
+        identifier: &Identifier,
+        credentials: PostgresCredentials,
+    ) -> Self {
+        self.identities.insert(identifier.to_string(), credentials);
+        self
+    }
+
+    pub fn with_default(mut self, credentials: PostgresCredentials) -> Self {
+        self.default = Some(credentials);
+        self
+    }
+
+    /// Check that the users are mapped to valid identifiers
+    pub fn validate(&self) -> Result<()> {
+        for identifier in self.identities.keys() {
+            Identifier::try_from(identifier.as_str())?;
+        }
+        if self.identities.is_empty() && self.default.is_none() {
+            return Err(ApiError::core("no database user is defined"));
+        }
+        Ok(())
+    }
+
+    /// Return the credentials used for an identity
+    pub fn credentials(&self, identifier: &Identifier) -> Option<&PostgresCredentials> {
+        self.identities
+            .get(&identifier.to_string())
+            .or(self.default.as_ref())
+    }
+}
+
+/// Local proxy authenticating the connections of a Postgres outlet
+#[derive(Clone, Debug)]
+pub struct PostgresAuthProxy {
+    address: SocketAddr,
+    key: Arc<String>,
+    task: Arc<JoinHandle<()>>,
+}
+
+impl PostgresAuthProxy {
+    /// Start a proxy listening on a free local port and connecting to the server at `upstream`,
+    /// which is a socket address or a `hostname:port`
+    pub async fn start(upstream: impl Into<String>, users: PostgresUsers) -> Result<Self> {
+        users.validate()?;
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let address = listener.local_addr().map_err(io_error)?;
+        let key = Arc::new(hex::encode(rand::random::<[u8; 16]>()));
+        let upstream = Arc::new(upstream.into());
+        let users = Arc::new(users);
+
+        let proxy_key = key.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let client = match listener.accept().await {
+                    Ok((client, _)) => client,
+                    Err(e) => {
+                        warn!(%address, %e, "The Postgres proxy can't accept connections");
+                        return;
+                    }
+                };
+                let (upstream, users, key) = (upstream.clone(), users.clone(), proxy_key.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(client, &upstream, &users, &key).await {
+                        debug!(%upstream, %e, "Closed a Postgres connection");
+                    }
+                });
+            }
+        });
+        debug!(%address, "Started a Postgres proxy");
+        Ok(Self {
+            address,
+            key,
+            task: Arc::new(task),
+        })
+    }
+
+    /// Local address of the proxy, which must be the target of the outlet
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Interceptor to set on the outlet, to send the identity of the inlets to the proxy
+    pub fn interceptor_factory(&self) -> Arc<dyn PortalInterceptorFactory> {
+        Arc::new(PostgresOutletInterceptorFactory {
+            key: self.key.clone(),
+        })
+    }
+
+    /// Stop accepting connections. The sessions already authenticated are not closed
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+/// Create an interceptor for each connection of a Postgres outlet
+#[derive(Debug)]
+struct PostgresOutletInterceptorFactory {
+    key: Arc<String>,
+}
+
+impl PortalInterceptorFactory for PostgresOutletInterceptorFactory {
+    fn create(&self) -> Arc<dyn PortalInterceptor> {
+        Arc::new(PostgresOutletInterceptor::new(None, &self.key))
+    }
+
+    fn create_for_message(&self, message: &LocalMessage) -> Arc<dyn PortalInterceptor> {
+        let identity = IdentitySecureChannelLocalInfo::find_info(message)
+            .map(|info| info.their_identity_id())
+            .ok();
+        Arc::new(PostgresOutletInterceptor::new(identity, &self.key))
+    }
+}
+
+/// Add the identity of the inlet to the startup message of a connection, then forward the
+/// rest of the connection unchanged
+struct PostgresOutletInterceptor {
+    identity: Option<Identifier>,
+    key: String,
+    /// Data received before the startup message is complete, `None` once it was forwarded
+    pending: Mutex<Option<Vec<u8>>>,
+}
+
+impl PostgresOutletInterceptor {
+    fn new(identity: Option<Identifier>, key: &str) -> Self {
+        Self {
+            identity,
+            key: key.to_string(),
+            pending: Mutex::new(Some(vec![])),
+        }
+    }
+
+    /// Return the data to send to the proxy, once the messages it contains are complete
+    fn process(&self, chunk: Vec<u8>) -> Result<Vec<u8>> {
+        let mut guard = self.pending.lock().unwrap();
+        let Some(pending) = guard.as_mut() else {
+            return Ok(chunk);
+        };
+        pending.extend(chunk);
+
+        let mut output = vec![];
+        while let Some(length) = startup_message_length(pending)? {
+            if pending.len() < length {
+                break;
+            }
+            let message: Vec<u8> = pending.drain(..length).collect();
+            match read_u32(&message[4..]) {
+                // the startup message follows the refused encryption requests
+                SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => output.extend(message),
+                PROTOCOL_VERSION => {
+                    output.extend(self.tag(&message[8..])?);
+                    output.append(pending);
+                    *guard = None;
+                    break;
+                }
+                // cancel requests and unsupported versions are handled by the proxy
+                _ => {
+                    output.extend(message);
+                    output.append(pending);
+                    *guard = None;
+                    break;
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /// Replace the parameters of the outlet in a startup message
+    fn tag(&self, parameters: &[u8]) -> Result<Vec<u8>> {
+        let mut parameters = parse_parameters(parameters)?;
+        parameters.retain(|(name, _)| !name.starts_with(PARAMETERS_PREFIX));
+        if let Some(identity) = &self.identity {
+            parameters.push((IDENTITY_PARAMETER.to_string(), identity.to_string()));
+        }
+        parameters.push((KEY_PARAMETER.to_string(), self.key.clone()));
+        Ok(startup_message(&parameters))
+    }
+}
+
+#[async_trait]
+impl PortalInterceptor for PostgresOutletInterceptor {
+    async fn intercept(
+        &self,
+        _context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        match direction {
+            PortalDirection::ToPeer => self.process(chunk),
+            PortalDirection::FromPeer => Ok(chunk),
+        }
+    }
+}
+
+/// Authenticate a client connection with the server, then copy the data of the session
+async fn handle_connection(
+    mut client: TcpStream,
+    upstream: &str,
+    users: &PostgresUsers,
+    key: &str,
+) -> Result<()> {
+    let mut parameters = loop {
+        let message = read_startup_message(&mut client).await?;
+        match read_u32(&message[4..]) {
+            SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => {
+                // the data of the portal is already encrypted by the secure channel
+                client.write_all(b"N").await.map_err(io_error)?;
+            }
+            CANCEL_REQUEST_CODE => {
+                let mut server = TcpStream::connect(upstream).await.map_err(io_error)?;
+                return server.write_all(&message).await.map_err(io_error);
+            }
+            PROTOCOL_VERSION => break parse_parameters(&message[8..])?,
+            version => {
+                let reason = format!("the protocol version {version} is not supported");
+                return reject(&mut client, "0A000", &reason).await;
+            }
+        }
+    };
+
+    let take = |parameters: &mut Vec<(String, String)>, name: &str| {
+        let value = parameters
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.clone());
+        parameters.retain(|(n, _)| n != name);
+        value
+    };
+    let proxy_key = take(&mut parameters, KEY_PARAMETER);
+    let identity = take(&mut parameters, IDENTITY_PARAMETER);
+    parameters.retain(|(name, _)| !name.starts_with(PARAMETERS_PREFIX));
+    if proxy_key.as_deref() != Some(key) {
+        return reject(
+            &mut client,
+            INVALID_AUTHORIZATION,
+            "the connection was not opened by the outlet",
+        )
+        .await;
+    }
+    let credentials = identity
+        .as_deref()
+        .and_then(|identity| Identifier::try_from(identity).ok())
+        .and_then(|identifier| users.credentials(&identifier));
+    let Some(credentials) = credentials else {
+        let reason = format!(
+            "no database user is mapped to the identity {}",
+            identity.as_deref().unwrap_or("of the inlet")
+        );
+        return reject(&mut client, INVALID_AUTHORIZATION, &reason).await;
+    };
+    take(&mut parameters, "user");
+    parameters.insert(0, ("user".to_string(), credentials.user.clone()));
+
+    let mut server = TcpStream::connect(upstream)
+        .await
+        .map_err(|e| ApiError::core(format!("the server {upstream} can't be reached: {e}")))?;
+    server
+        .write_all(&startup_message(&parameters))
+        .await
+        .map_err(io_error)?;
+    if let Some(error) = authenticate(&mut server, credentials).await? {
+        // the client receives the reason of the failure, for example an unknown database
+        return client.write_all(&error).await.map_err(io_error);
+    }
+    client
+        .write_all(&backend_message(b'R', &AUTHENTICATION_OK.to_be_bytes()))
+        .await
+        .map_err(io_error)?;
+    debug!(user = %credentials.user, identity = ?identity, "Authenticated a Postgres connection");
+
+    tokio::io::copy_bidirectional(&mut client, &mut server)
+        .await
+        .map_err(io_error)?;
+    Ok(())
+}
+
+/// Perform the authentication requested by the server. Return the error message sent by the
+/// server if the authentication failed
+async fn authenticate(
+    server: &mut TcpStream,
+    credentials: &PostgresCredentials,
+) -> Result<Option<Vec<u8>>> {
+    let mut scram = None;
+    loop {
+        let (tag, body) = read_backend_message(server).await?;
+        match tag {
+            b'E' => return Ok(Some(backend_message(tag, &body))),
+            b'R' if body.len() >= 4 => (),
+            _ => {
+                return Err(protocol_error(
+                    "unexpected message during the authentication",
+                ))
+            }
+        }
+        let data = &body[4..];
+        match read_u32(&body) {
+            AUTHENTICATION_OK => return Ok(None),
+            AUTHENTICATION_SASL => {
+                let mechanisms: Vec<&[u8]> = data.split(|b| *b == 0).collect();
+                if !mechanisms.contains(&SCRAM_SHA_256.as_bytes()) {
+                    return Err(protocol_error("the server doesn't support SCRAM-SHA-256"));
+                }
+                // the user name of the SCRAM exchange is ignored, the server uses the startup one
+                let client = ScramClient::new("", &credentials.password);
+                let first = client.client_first();
+                let mut message = format!("{SCRAM_SHA_256}\0").into_bytes();
+                message.extend((first.len() as u32).to_be_bytes());
+                message.extend(first.as_bytes());
+                write_frontend_message(server, b'p', &message).await?;
+                scram = Some(client);
+            }
+            AUTHENTICATION_SASL_CONTINUE => {
+                let client = scram
+                    .as_mut()
+                    .ok_or_else(|| protocol_error("no SASL exchange"))?;
+                let client_final = client.client_final(utf8(data)?)?;
+                write_frontend_message(server, b'p', client_final.as_bytes()).await?;
+            }
+            AUTHENTICATION_SASL_FINAL => {
+                let client = scram
+                    .as_ref()
+                    .ok_or_else(|| protocol_error("no SASL exchange"))?;
+                client.verify_server_final(utf8(data)?)?;
+            }
+            method => {
+                return Err(protocol_error(&format!(
+                    "the authentication method {method} is not supported, only {SCRAM_SHA_256} is"
+                )))
+            }
+        }
+    }
+}
+
+/// Client side of a `SCRAM-SHA-256` exchange, as described in RFC 5802 and RFC 7677
+struct ScramClient {
+    password: String,
+    nonce: String,
+    client_first_bare: String,
+    /// Salted password and authentication message, once the server first message is received
+    proof_material: Option<([u8; 32], String)>,
+}
+
+impl ScramClient {
+    fn new(user: &str, password: &str) -> Self {
+        Self::with_nonce(user, password, BASE64.encode(rand::random::<[u8; 18]>()))
+    }
+
+    fn with_nonce(user: &str, password: &str, nonce: String) -> Self {
+        let user = user.replace('=', "=3D").replace(',', "=2C");
+        Self {
+            password: password.to_string(),
+            client_first_bare: format!("n={user},r={nonce}"),
+            nonce,
+            proof_material: None,
+        }
+    }
+
+    fn client_first(&self) -> String {
+        format!("n,,{}", self.client_first_bare)
+    }
+
+    fn client_final(&mut self, server_first: &str) -> Result<String> {
+        let attributes = scram_attributes(server_first);
+        let (Some(nonce), Some(salt), Some(iterations)) = (
+            attributes.get(&'r'),
+            attributes.get(&'s'),
+            attributes.get(&'i'),
+        ) else {
+            return Err(protocol_error("invalid SCRAM server first message"));
+        };
+        if !nonce.starts_with(&self.nonce) || nonce.len() == self.nonce.len() {
+            return Err(protocol_error("invalid SCRAM server nonce"));
+        }
+        let salt = BASE64
+            .decode(salt)
+            .map_err(|_| protocol_error("invalid SCRAM salt"))?;
+        let iterations: u32 = iterations
+            .parse()
+            .map_err(|_| protocol_error("invalid SCRAM iteration count"))?;
+
+        let mut salted_password = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            self.password.as_bytes(),
+            &salt,
+            iterations,
+            &mut salted_password,
+        );
+        let client_key = hmac(&salted_password, b"Client Key");
+        let stored_key: [u8; 32] = Sha256::digest(client_key).into();
+        // "biws" is the base64 encoding of the gs2 header "n,,"
+        let client_final_without_proof = format!("c=biws,r={nonce}");
+        let auth_message = format!(
+            "{},{server_first},{client_final_without_proof}",
+            self.client_first_bare
+        );
+        let signature = hmac(&stored_key, auth_message.as_bytes());
+        let proof: Vec<u8> = client_key
+            .iter()
+            .zip(signature.iter())
+            .map(|(k, s)| k ^ s)
+            .collect();
+        self.proof_material = Some((salted_password, auth_message));
+        Ok(format!(
+            "{client_final_without_proof},p={}",
+            BASE64.encode(proof)
+        ))
+    }
+
+    /// Check that the server knows the password too
+    fn verify_server_final(&self, server_final: &str) -> Result<()> {
+        let (salted_password, auth_message) = self
+            .proof_material
+            .as_ref()
+            .ok_or_else(|| protocol_error("no SCRAM client final message"))?;
+        let attributes = scram_attributes(server_final);
+        if let Some(e) = attributes.get(&'e') {
+            return Err(protocol_error(&format!("SCRAM authentication failed: {e}")));
+        }
+        let server_key = hmac(salted_password, b"Server Key");
+        let expected = BASE64.encode(hmac(&server_key, auth_message.as_bytes()));
+        if attributes.get(&'v') != Some(&expected.as_str()) {
+            return Err(protocol_error("invalid SCRAM server signature"));
+        }
+        Ok(())
+    }
+}
+
+fn scram_attributes(message: &str) -> BTreeMap<char, &str> {
+    message
+        .split(',')
+        .filter_map(|attribute| {
+            let (name, value) = attribute.split_once('=')?;
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(name), None) => Some((name, value)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// Return the length of the first message of a startup sequence, if it was received
+fn startup_message_length(data: &[u8]) -> Result<Option<usize>> {
+    if data.len() < 4 {
+        return Ok(None);
+    }
+    let length = read_u32(data) as usize;
+    if !(8..=MAX_STARTUP_MESSAGE_LENGTH).contains(&length) {
+        return Err(protocol_error("invalid startup message length"));
+    }
+    Ok(Some(length))
+}
+
+async fn read_startup_message(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut message = vec![0; 4];
+    stream.read_exact(&mut message).await.map_err(io_error)?;
+    let length = startup_message_length(&message)?.unwrap_or_default();
+    message.resize(length, 0);
+    stream
+        .read_exact(&mut message[4..])
+        .await
+        .map_err(io_error)?;
+    Ok(message)
+}
+
+/// Parse the null-terminated names and values of a startup message
+fn parse_parameters(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let mut strings = data.split(|b| *b == 0);
+    let mut parameters = vec![];
+    loop {
+        match strings.next() {
+            Some([]) | None => return Ok(parameters),
+            Some(name) => {
+                let value = strings
+                    .next()
+                    .ok_or_else(|| protocol_error("invalid startup parameters"))?;
+                parameters.push((utf8(name)?.to_string(), utf8(value)?.to_string()));
+            }
+        }
+    }
+}
+
+fn startup_message(parameters: &[(String, String)]) -> Vec<u8> {
+    let mut body = PROTOCOL_VERSION.to_be_bytes().to_vec();
+    for (name, value) in parameters {
+        body.extend(name.as_bytes());
+        body.push(0);
+        body.extend(value.as_bytes());
+        body.push(0);
+    }
+    body.push(0);
+    let mut message = ((body.len() + 4) as u32).to_be_bytes().to_vec();
+    message.extend(body);
+    message
+}
+
+async fn read_backend_message(stream: &mut TcpStream) -> Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; 5];
+    stream.read_exact(&mut header).await.map_err(io_error)?;
+    let length = read_u32(&header[1..]) as usize;
+    if !(4..=MAX_STARTUP_MESSAGE_LENGTH).contains(&length) {
+        return Err(protocol_error("invalid message length"));
+    }
+    let mut body = vec![0; length - 4];
+    stream.read_exact(&mut body).await.map_err(io_error)?;
+    Ok((header[0], body))
+}
+
+fn backend_message(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut message = vec![tag];
+    message.extend(((body.len() + 4) as u32).to_be_bytes());
+    message.extend(body);
+    message
+}
+
+async fn write_frontend_message(stream: &mut TcpStream, tag: u8, body: &[u8]) -> Result<()> {
+    // frontend and backend messages have the same framing
+    stream
+        .write_all(&backend_message(tag, body))
+        .await
+        .map_err(io_error)
+}
+
+/// Send a fatal error to the client, which then closes the connection
+async fn reject(client: &mut TcpStream, code: &str, reason: &str) -> Result<()> {
+    debug!(%reason, "Rejected a Postgres connection");
+    let mut body = vec![];
+    for (field, value) in [
+        (b'S', "FATAL"),
+        (b'V', "FATAL"),
+        (b'C', code),
+        (b'M', reason),
+    ] {
+        body.push(field);
+        body.extend(value.as_bytes());
+        body.push(0);
+    }
+    body.push(0);
+    client
+        .write_all(&backend_message(b'E', &body))
+        .await
+        .map_err(io_error)
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+fn utf8(data: &[u8]) -> Result<&str> {
+    std::str::from_utf8(data).map_err(|_| protocol_error("invalid utf-8 string"))
+}
+
+fn protocol_error(message: &str) -> Error {
+    Error::new(Origin::Api, Kind::Protocol, message.to_string())
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(Origin::Api, Kind::Io, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scram_client() -> Result<()> {
+        // test vector of RFC 7677
+        let mut client = ScramClient::with_nonce("user", "pencil", "rOprNGfwEbeRWgbNEkqO".into());
+        assert_eq!(client.client_first(), "n,,n=user,r=rOprNGfwEbeRWgbNEkqO");
+        let client_final = client.client_final(
+            "r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096",
+        )?;
+        assert_eq!(
+            client_final,
+            "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="
+        );
+        client.verify_server_final("v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")?;
+        assert!(client
+            .verify_server_final("v=AAAATRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interceptor_tags_the_startup_message() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let interceptor = PostgresOutletInterceptor::new(Some(identity.clone()), "key");
+        let ssl_request = [8u32.to_be_bytes(), SSL_REQUEST_CODE.to_be_bytes()].concat();
+        assert_eq!(
+            interceptor.process(ssl_request[..3].to_vec())?,
+            Vec::<u8>::new()
+        );
+        assert_eq!(interceptor.process(ssl_request[3..].to_vec())?, ssl_request);
+
+        // the client can't choose the identity
+        let startup = startup_message(&[
+            ("user".into(), "postgres".into()),
+            (IDENTITY_PARAMETER.into(), "I0000".into()),
+        ]);
+        assert_eq!(
+            interceptor.process(startup[..10].to_vec())?,
+            Vec::<u8>::new()
+        );
+        let tagged = interceptor.process(startup[10..].to_vec())?;
+        assert_eq!(
+            parse_parameters(&tagged[8..])?,
+            vec![
+                ("user".to_string(), "postgres".to_string()),
+                (IDENTITY_PARAMETER.to_string(), identity.to_string()),
+                (KEY_PARAMETER.to_string(), "key".to_string()),
+            ]
+        );
+        // the rest of the session is forwarded unchanged
+        assert_eq!(interceptor.process(b"Q".to_vec())?, b"Q".to_vec());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_proxy() -> Result<()> {
+        let identity: Identifier = "Ie92f183eb4c324804ef4d62962dea94cf095a265".try_into()?;
+        let other: Identifier = "I6c20e814b56579306f55c64e8747e6c1b4a53d9a".try_into()?;
+        // a server trusting its clients, which echoes the data of the session
+        let server = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let server_address = server.local_addr().map_err(io_error)?;
+        let server_task = tokio::spawn(async move {
+            let (mut stream, _) = server.accept().await.unwrap();
+            let startup = read_startup_message(&mut stream).await.unwrap();
+            let parameters = parse_parameters(&startup[8..]).unwrap();
+            stream
+                .write_all(&backend_message(b'R', &AUTHENTICATION_OK.to_be_bytes()))
+                .await
+                .unwrap();
+            let mut data = [0u8; 4];
+            stream.read_exact(&mut data).await.unwrap();
+            stream.write_all(&data).await.unwrap();
+            parameters
+        });
+
+        let users = PostgresUsers::default()
+            .with_identity(&identity, PostgresCredentials::new("reporting", "secret"));
+        let proxy = PostgresAuthProxy::start(server_address.to_string(), users).await?;
+        let key = proxy.key.clone();
+        let connect = |identity: Option<Identifier>, key: &str| {
+            let interceptor = PostgresOutletInterceptor::new(identity, key);
+            let address = proxy.address();
+            async move {
+                let mut client = TcpStream::connect(address).await.map_err(io_error)?;
+                let startup = startup_message(&[("user".into(), "postgres".into())]);
+                client
+                    .write_all(&interceptor.process(startup)?)
+                    .await
+                    .map_err(io_error)?;
+                let (tag, body) = read_backend_message(&mut client).await?;
+                Ok::<(TcpStream, u8, Vec<u8>), Error>((client, tag, body))
+            }
+        };
+
+        // the connections which don't come from the outlet, or from an unknown identity, fail
+        let (_, tag, _) = connect(Some(identity.clone()), "other key").await?;
+        assert_eq!(tag, b'E');
+        let (_, tag, _) = connect(Some(other), &key).await?;
+        assert_eq!(tag, b'E');
+
+        let (mut client, tag, body) = connect(Some(identity), &key).await?;
+        assert_eq!((tag, read_u32(&body)), (b'R', AUTHENTICATION_OK));
+        client.write_all(b"ping").await.map_err(io_error)?;
+        let mut data = [0u8; 4];
+        client.read_exact(&mut data).await.map_err(io_error)?;
+        assert_eq!(&data, b"ping");
+
+        let parameters = server_task.await.unwrap();
+        assert_eq!(
+            parameters,
+            vec![("user".to_string(), "reporting".to_string())]
+        );
+        proxy.stop();
+        Ok(())
+    }
+}
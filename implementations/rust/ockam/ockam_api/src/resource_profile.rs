@@ -0,0 +1,163 @@
+//! Presets, [`ResourceProfile`]s, tuning the executor threads, the mailboxes capacity and the
+//! portal buffers of a node.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use ockam_core::errcode::{Kind, Origin};
+use ockam_node::channel_types::DEFAULT_MAILBOX_CAPACITY;
+use ockam_node::NodeBuilder;
+use ockam_transport_tcp::{TcpInletOptions, TcpOutletOptions, MAX_PAYLOAD_SIZE};
+
+/// Preset of resource settings for a node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResourceProfile {
+    /// Constrained devices: few threads, small mailboxes and small buffers
+    Edge,
+    /// Settings used when no profile is specified
+    #[default]
+    Default,
+    /// Nodes relaying a lot of portal traffic: large mailboxes and large buffers
+    HighThroughput,
+}
+
+impl ResourceProfile {
+    /// Return the resource settings of this profile
+    pub fn settings(&self) -> ResourceSettings {
+        match self {
+            ResourceProfile::Edge => ResourceSettings {
+                worker_threads: Some(2),
+                mailbox_capacity: 4,
+                portal_buffer_size: 4 * 1024,
+            },
+            ResourceProfile::Default => ResourceSettings {
+                worker_threads: None,
+                mailbox_capacity: DEFAULT_MAILBOX_CAPACITY as u32,
+                portal_buffer_size: MAX_PAYLOAD_SIZE as u32,
+            },
+            ResourceProfile::HighThroughput => ResourceSettings {
+                worker_threads: None,
+                mailbox_capacity: 256,
+                portal_buffer_size: 256 * 1024,
+            },
+        }
+    }
+
+    /// Return a builder for a node using the settings of this profile
+    pub fn node_builder(&self) -> NodeBuilder {
+        let settings = self.settings();
+        let builder = NodeBuilder::new().with_mailbox_capacity(settings.mailbox_capacity as usize);
+        match settings.worker_threads {
+            Some(threads) => builder.with_worker_threads(threads as usize),
+            None => builder,
+        }
+    }
+}
+
+impl Display for ResourceProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceProfile::Edge => write!(f, "edge"),
+            ResourceProfile::Default => write!(f, "default"),
+            ResourceProfile::HighThroughput => write!(f, "high-throughput"),
+        }
+    }
+}
+
+impl FromStr for ResourceProfile {
+    type Err = ockam_core::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "edge" => Ok(ResourceProfile::Edge),
+            "default" => Ok(ResourceProfile::Default),
+            "high-throughput" => Ok(ResourceProfile::HighThroughput),
+            _ => Err(ockam_core::Error::new(
+                Origin::Node,
+                Kind::Invalid,
+                format!("unknown resource profile {s}, expected edge, default or high-throughput"),
+            )),
+        }
+    }
+}
+
+/// Resource settings of a node
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ResourceSettings {
+    /// Number of threads of the node executor. The number of CPU cores is used when missing
+    #[n(1)] pub worker_threads: Option<u32>,
+    /// Number of messages which can be queued in the mailbox of a worker
+    #[n(2)] pub mailbox_capacity: u32,
+    /// Size of the buffer used to read the data of a portal connection
+    #[n(3)] pub portal_buffer_size: u32,
+}
+
+impl ResourceSettings {
+    /// Apply the buffer size of these settings to the options of an inlet
+    pub fn inlet_options(&self, options: TcpInletOptions) -> TcpInletOptions {
+        options.with_read_buffer_size(self.portal_buffer_size as usize)
+    }
+
+    /// Apply the buffer size of these settings to the options of an outlet
+    pub fn outlet_options(&self, options: TcpOutletOptions) -> TcpOutletOptions {
+        options.with_read_buffer_size(self.portal_buffer_size as usize)
+    }
+}
+
+/// Response body for the resources used by a node
+#[derive(Debug, Clone, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct NodeResources {
+    #[n(1)] pub profile: String,
+    #[n(2)] pub settings: ResourceSettings,
+}
+
+impl NodeResources {
+    pub fn new(profile: ResourceProfile) -> Self {
+        Self {
+            profile: profile.to_string(),
+            settings: profile.settings(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_profile_names() {
+        for profile in [
+            ResourceProfile::Edge,
+            ResourceProfile::Default,
+            ResourceProfile::HighThroughput,
+        ] {
+            assert_eq!(
+                ResourceProfile::from_str(&profile.to_string()).unwrap(),
+                profile
+            );
+            let json = serde_json::to_string(&profile).unwrap();
+            assert_eq!(json, format!("\"{profile}\""));
+        }
+        assert!(ResourceProfile::from_str("huge").is_err());
+    }
+
+    #[test]
+    fn test_resource_profile_settings() {
+        let edge = ResourceProfile::Edge.settings();
+        let default = ResourceProfile::Default.settings();
+        let high = ResourceProfile::HighThroughput.settings();
+        assert!(edge.mailbox_capacity < default.mailbox_capacity);
+        assert!(default.mailbox_capacity < high.mailbox_capacity);
+        assert!(edge.portal_buffer_size < default.portal_buffer_size);
+        assert!(default.portal_buffer_size < high.portal_buffer_size);
+        assert_eq!(edge.worker_threads, Some(2));
+    }
+}
@@ -0,0 +1,677 @@
+//! Progressive rollouts of a configuration update to the nodes of a fleet.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+use std::sync::Arc;
+use std::time::Duration;
+
+use minicbor::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::Storage;
+use ockam::identity::Identifier;
+use ockam_core::Result;
+
+use crate::cli_state::cached::now;
+use crate::config_updates::SignedConfigUpdate;
+use crate::error::ApiError;
+use crate::fleet::{FleetInventory, FleetMember};
+
+/// Storage namespace of the rollouts
+const ROLLOUTS_NAMESPACE: &str = "rollouts";
+
+/// Interval between two evaluations of the active rollout
+const EVALUATION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Nodes targeted by a rollout and conditions to move from one stage to the next
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct RolloutSpec {
+    #[n(1)] pub id: String,
+    /// Labels which must be reported by the nodes of the rollout
+    #[n(2)] pub selector: BTreeMap<String, String>,
+    /// Percentage of the selected nodes having the update at the end of each stage
+    #[n(3)] pub stages: Vec<u8>,
+    /// Duration during which the nodes of a stage must stay healthy
+    #[n(4)] pub bake_secs: u64,
+    /// Duration after which a node which didn't report the update has failed
+    #[n(5)] pub health_timeout_secs: u64,
+    /// Number of failed nodes tolerated before rolling back
+    #[n(6)] pub max_failures: u64,
+}
+
+impl RolloutSpec {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            selector: BTreeMap::new(),
+            stages: vec![10, 50, 100],
+            bake_secs: 300,
+            health_timeout_secs: 120,
+            max_failures: 0,
+        }
+    }
+
+    pub fn with_selector(mut self, selector: BTreeMap<String, String>) -> Self {
+        self.selector = selector;
+        self
+    }
+
+    pub fn with_stages(mut self, stages: Vec<u8>) -> Self {
+        self.stages = stages;
+        self
+    }
+
+    pub fn with_bake(mut self, bake: Duration) -> Self {
+        self.bake_secs = bake.as_secs();
+        self
+    }
+
+    pub fn with_health_timeout(mut self, health_timeout: Duration) -> Self {
+        self.health_timeout_secs = health_timeout.as_secs();
+        self
+    }
+
+    pub fn with_max_failures(mut self, max_failures: u64) -> Self {
+        self.max_failures = max_failures;
+        self
+    }
+
+    /// Check that the stages are increasing percentages ending with 100
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(ApiError::core("the rollout must have an id"));
+        }
+        if self.stages.last() != Some(&100) {
+            return Err(ApiError::core("the last stage of a rollout must be 100%"));
+        }
+        if self.stages[0] == 0 || self.stages.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(ApiError::core(
+                "the stages of a rollout must be increasing percentages",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Status of a rollout
+#[derive(Copy, Clone, Debug, Decode, Encode, Serialize, PartialEq, Eq)]
+#[rustfmt::skip]
+#[cbor(index_only)]
+#[serde(rename_all = "snake_case")]
+pub enum RolloutStatus {
+    #[n(0)] Running,
+    #[n(1)] RollingBack,
+    #[n(2)] Completed,
+    #[n(3)] RolledBack,
+}
+
+impl RolloutStatus {
+    /// Return true if the rollout still sends updates to the nodes
+    pub fn is_active(&self) -> bool {
+        matches!(self, Self::Running | Self::RollingBack)
+    }
+}
+
+impl Display for RolloutStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Running => "running",
+            Self::RollingBack => "rolling back",
+            Self::Completed => "completed",
+            Self::RolledBack => "rolled back",
+        })
+    }
+}
+
+/// State of a rollout, saved after each change
+#[derive(Clone, Debug, Encode, Decode, Serialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Rollout {
+    #[n(1)] pub spec: RolloutSpec,
+    #[n(2)] pub update: SignedConfigUpdate,
+    /// Update restoring the previous configuration, with a greater version than `update`
+    #[n(3)] pub rollback: SignedConfigUpdate,
+    #[n(4)] pub status: RolloutStatus,
+    /// Index of the current stage in the stages of the spec
+    #[n(5)] pub stage: u64,
+    /// Seconds since the Unix epoch when all the nodes of the current stage became healthy
+    #[n(6)] pub stage_healthy_at: Option<u64>,
+    /// Identifiers of the nodes which were assigned the update, with the time of the assignment
+    #[n(7)] pub targets: BTreeMap<String, u64>,
+    /// Identifiers of the nodes which didn't report the update before the health timeout
+    #[n(8)] pub failed: BTreeSet<String>,
+    #[n(9)] pub rollback_started_at: Option<u64>,
+    /// Reason of the last status change
+    #[n(10)] pub message: Option<String>,
+    #[n(11)] pub created_at: u64,
+    #[n(12)] pub updated_at: u64,
+}
+
+impl Rollout {
+    pub fn new(
+        spec: RolloutSpec,
+        update: SignedConfigUpdate,
+        rollback: SignedConfigUpdate,
+    ) -> Result<Self> {
+        spec.validate()?;
+        if rollback.update.version <= update.update.version {
+            return Err(ApiError::core(format!(
+                "the version {} of the rollback must be greater than the version {} of the update",
+                rollback.update.version, update.update.version
+            )));
+        }
+        let now = now();
+        Ok(Self {
+            spec,
+            update,
+            rollback,
+            status: RolloutStatus::Running,
+            stage: 0,
+            stage_healthy_at: None,
+            targets: BTreeMap::new(),
+            failed: BTreeSet::new(),
+            rollback_started_at: None,
+            message: None,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Percentage of the selected nodes targeted by the current stage
+    pub fn percentage(&self) -> u8 {
+        self.spec
+            .stages
+            .get(self.stage as usize)
+            .copied()
+            .unwrap_or(100)
+    }
+
+    /// Update to send to a node reporting its configuration version, if any
+    pub fn assigned_update(
+        &self,
+        identifier: &Identifier,
+        reported_version: Option<u64>,
+    ) -> Option<&SignedConfigUpdate> {
+        if !self.targets.contains_key(&identifier.to_string()) {
+            return None;
+        }
+        let update = match self.status {
+            RolloutStatus::Running | RolloutStatus::Completed => &self.update,
+            RolloutStatus::RollingBack | RolloutStatus::RolledBack => &self.rollback,
+        };
+        match reported_version {
+            Some(version) if version >= update.update.version => None,
+            _ => Some(update),
+        }
+    }
+
+    /// Roll back the nodes which received the update
+    pub fn abort(&mut self, reason: impl Into<String>, now: u64) -> Result<()> {
+        if self.status != RolloutStatus::Running {
+            return Err(ApiError::core(format!(
+                "the rollout {} is {}",
+                self.spec.id, self.status
+            )));
+        }
+        self.start_rollback(reason.into(), now);
+        Ok(())
+    }
+
+    /// Assign the update to the nodes of the current stage, check their health and move to the
+    /// next status. Return true if the rollout changed
+    pub fn evaluate(&mut self, members: &[FleetMember], now: u64) -> bool {
+        let changed = match self.status {
+            RolloutStatus::Running => self.evaluate_stage(members, now),
+            RolloutStatus::RollingBack => self.evaluate_rollback(members, now),
+            RolloutStatus::Completed | RolloutStatus::RolledBack => false,
+        };
+        if changed {
+            self.updated_at = now;
+        }
+        changed
+    }
+
+    fn evaluate_stage(&mut self, members: &[FleetMember], now: u64) -> bool {
+        let mut changed = false;
+
+        // the update is assigned to the selected nodes in the order of their identifiers, so
+        // that the same nodes are chosen after a restart
+        let mut eligible: Vec<&FleetMember> = members
+            .iter()
+            .filter(|m| m.matches(&self.spec.selector))
+            .collect();
+        if eligible.is_empty() && self.targets.is_empty() {
+            return self.set_message("no node matches the selector of the rollout".to_string());
+        }
+        eligible.sort_by_key(|m| m.identifier.to_string());
+        let wanted = (eligible.len() * self.percentage() as usize + 99) / 100;
+        for member in eligible {
+            if self.targets.len() >= wanted.max(1) {
+                break;
+            }
+            if self
+                .targets
+                .insert(member.identifier.to_string(), now)
+                .is_none()
+            {
+                self.stage_healthy_at = None;
+                changed = true;
+            }
+        }
+
+        let version = self.update.update.version;
+        let mut pending = 0;
+        for (identifier, assigned_at) in &self.targets {
+            if self.failed.contains(identifier) {
+                continue;
+            }
+            if self.is_healthy(members, identifier, version, now) {
+                continue;
+            }
+            if now.saturating_sub(*assigned_at) > self.spec.health_timeout_secs {
+                self.failed.insert(identifier.clone());
+                changed = true;
+            } else {
+                pending += 1;
+            }
+        }
+        if self.failed.len() as u64 > self.spec.max_failures {
+            let failed = self.failed.iter().cloned().collect::<Vec<_>>().join(", ");
+            let reason = format!("the update was not applied by the nodes {failed}");
+            self.start_rollback(reason, now);
+            return true;
+        }
+
+        if pending > 0 {
+            return self.stage_healthy_at.take().is_some() || changed;
+        }
+        match self.stage_healthy_at {
+            None => {
+                self.stage_healthy_at = Some(now);
+                true
+            }
+            Some(healthy_at) if now.saturating_sub(healthy_at) >= self.spec.bake_secs => {
+                if self.stage as usize + 1 < self.spec.stages.len() {
+                    self.stage += 1;
+                    self.stage_healthy_at = None;
+                    self.message = Some(format!("the stage {} is complete", self.stage));
+                } else {
+                    self.status = RolloutStatus::Completed;
+                    self.message = Some(format!(
+                        "the version {version} was applied by {} nodes",
+                        self.targets.len() - self.failed.len()
+                    ));
+                }
+                true
+            }
+            Some(_) => changed,
+        }
+    }
+
+    fn evaluate_rollback(&mut self, members: &[FleetMember], now: u64) -> bool {
+        let version = self.rollback.update.version;
+        let pending: Vec<String> = self
+            .targets
+            .keys()
+            .filter(|identifier| !self.is_healthy(members, identifier, version, now))
+            .cloned()
+            .collect();
+        let started_at = self.rollback_started_at.unwrap_or(now);
+        if pending.is_empty() {
+            self.status = RolloutStatus::RolledBack;
+            true
+        } else if now.saturating_sub(started_at) > self.spec.health_timeout_secs {
+            self.status = RolloutStatus::RolledBack;
+            self.message = Some(format!(
+                "the rollback was not confirmed by the nodes {}",
+                pending.join(", ")
+            ));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A node is healthy when it recently reported a version at least equal to `version`
+    fn is_healthy(
+        &self,
+        members: &[FleetMember],
+        identifier: &str,
+        version: u64,
+        now: u64,
+    ) -> bool {
+        members
+            .iter()
+            .find(|m| m.identifier.to_string() == identifier)
+            .map_or(false, |m| {
+                m.heartbeat.heartbeat.config_version >= Some(version)
+                    && now.saturating_sub(m.received_at) <= self.spec.health_timeout_secs
+            })
+    }
+
+    fn start_rollback(&mut self, reason: String, now: u64) {
+        warn!(rollout = %self.spec.id, %reason, "Rolling back");
+        self.status = RolloutStatus::RollingBack;
+        self.rollback_started_at = Some(now);
+        self.message = Some(reason);
+        self.updated_at = now;
+    }
+
+    fn set_message(&mut self, message: String) -> bool {
+        if self.message.as_ref() == Some(&message) {
+            return false;
+        }
+        self.message = Some(message);
+        true
+    }
+}
+
+/// Rollouts of a control node, kept in the storage of its fleet inventory
+#[derive(Clone)]
+pub struct Rollouts {
+    storage: Arc<dyn Storage>,
+    // serializes the changes made by the orchestrator and the node API
+    lock: Arc<Mutex<()>>,
+}
+
+impl Rollouts {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Save a new rollout, unless another rollout is active or has the same id
+    pub async fn create(&self, rollout: Rollout) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        if self.get(&rollout.spec.id).await?.is_some() {
+            return Err(ApiError::core(format!(
+                "the rollout {} already exists",
+                rollout.spec.id
+            )));
+        }
+        if let Some(active) = self.active().await? {
+            return Err(ApiError::core(format!(
+                "the rollout {} is {}",
+                active.spec.id, active.status
+            )));
+        }
+        self.save(&rollout).await
+    }
+
+    pub async fn get(&self, id: &str) -> Result<Option<Rollout>> {
+        match self.storage.get(id, ROLLOUTS_NAMESPACE).await? {
+            Some(bytes) => Ok(Some(minicbor::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Return all the rollouts, the most recent last
+    pub async fn list(&self) -> Result<Vec<Rollout>> {
+        let mut rollouts = vec![];
+        for key in self.storage.keys(ROLLOUTS_NAMESPACE).await? {
+            if let Some(rollout) = self.get(&key).await? {
+                rollouts.push(rollout);
+            }
+        }
+        rollouts.sort_by_key(|r| r.created_at);
+        Ok(rollouts)
+    }
+
+    /// Return the rollout which is running or rolling back, if any
+    pub async fn active(&self) -> Result<Option<Rollout>> {
+        Ok(self
+            .list()
+            .await?
+            .into_iter()
+            .find(|r| r.status.is_active()))
+    }
+
+    /// Change a rollout with `f`, and save it if `f` returns true
+    pub async fn update(
+        &self,
+        id: &str,
+        f: impl FnOnce(&mut Rollout) -> Result<bool>,
+    ) -> Result<Option<Rollout>> {
+        let _guard = self.lock.lock().await;
+        let mut rollout = match self.get(id).await? {
+            Some(rollout) => rollout,
+            None => return Ok(None),
+        };
+        if f(&mut rollout)? {
+            self.save(&rollout).await?;
+        }
+        Ok(Some(rollout))
+    }
+
+    /// Update to send to a node in the reply to its heartbeat, if any
+    pub async fn update_for(
+        &self,
+        identifier: &Identifier,
+        reported_version: Option<u64>,
+    ) -> Result<Option<SignedConfigUpdate>> {
+        Ok(self.active().await?.and_then(|rollout| {
+            rollout
+                .assigned_update(identifier, reported_version)
+                .cloned()
+        }))
+    }
+
+    async fn save(&self, rollout: &Rollout) -> Result<()> {
+        self.storage
+            .set(
+                &rollout.spec.id,
+                ROLLOUTS_NAMESPACE.to_string(),
+                minicbor::to_vec(rollout)?,
+            )
+            .await
+    }
+}
+
+/// Task evaluating the active rollout of a control node against its fleet inventory
+pub struct RolloutOrchestrator;
+
+impl RolloutOrchestrator {
+    pub fn start(inventory: FleetInventory) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVALUATION_INTERVAL);
+            loop {
+                interval.tick().await;
+                if let Err(e) = Self::evaluate(&inventory).await {
+                    warn!(%e, "The active rollout can't be evaluated");
+                }
+            }
+        })
+    }
+
+    async fn evaluate(inventory: &FleetInventory) -> Result<()> {
+        let rollouts = inventory.rollouts();
+        let active = match rollouts.active().await? {
+            Some(active) => active,
+            None => return Ok(()),
+        };
+        let members = inventory.members().await?;
+        let now = now();
+        rollouts
+            .update(&active.spec.id, |rollout| {
+                let status = rollout.status;
+                let changed = rollout.evaluate(&members, now);
+                if rollout.status != status {
+                    info!(rollout = %rollout.spec.id, status = %rollout.status, "Rollout status changed");
+                }
+                Ok(changed)
+            })
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config_updates::ConfigUpdate;
+    use crate::fleet::{Heartbeat, SignedHeartbeat};
+    use ockam::identity::identities;
+
+    const CONFIG: &str = r#"
+outlets:
+  - alias: web
+    to: 127.0.0.1:3000
+"#;
+
+    fn member(index: u8, region: &str, config_version: Option<u64>, now: u64) -> FleetMember {
+        let heartbeat = Heartbeat {
+            node_name: format!("n{index}"),
+            version: "0.1.0".to_string(),
+            uptime_secs: 60,
+            sent_at: now,
+            memory: BTreeMap::new(),
+            secure_channels: 1,
+            inlets: vec![],
+            outlets: vec![],
+            relays: vec![],
+            config_version,
+            labels: BTreeMap::from([("region".to_string(), region.to_string())]),
+        };
+        FleetMember {
+            identifier: format!("I{index:040x}").parse().unwrap(),
+            heartbeat: SignedHeartbeat {
+                heartbeat,
+                signature: String::new(),
+            },
+            received_at: now,
+        }
+    }
+
+    async fn rollout(spec: RolloutSpec) -> Result<Rollout> {
+        let identities = identities();
+        let control = identities
+            .identities_creation()
+            .create_identity()
+            .await?
+            .identifier()
+            .clone();
+        let update = ConfigUpdate::new(2, CONFIG)?
+            .sign(&identities, &control)
+            .await?;
+        let rollback = ConfigUpdate::new(3, "outlets: []")?
+            .sign(&identities, &control)
+            .await?;
+        Rollout::new(spec, update, rollback)
+    }
+
+    fn spec() -> RolloutSpec {
+        RolloutSpec::new("canary")
+            .with_selector(BTreeMap::from([("region".to_string(), "eu".to_string())]))
+            .with_stages(vec![25, 100])
+            .with_bake(Duration::from_secs(60))
+            .with_health_timeout(Duration::from_secs(120))
+    }
+
+    #[test]
+    fn test_validate_stages() {
+        assert!(spec().validate().is_ok());
+        assert!(spec().with_stages(vec![]).validate().is_err());
+        assert!(spec().with_stages(vec![50]).validate().is_err());
+        assert!(spec().with_stages(vec![50, 50, 100]).validate().is_err());
+        assert!(spec().with_stages(vec![0, 100]).validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rollout_stages() -> Result<()> {
+        let mut rollout = rollout(spec()).await?;
+        let mut now = 1000;
+        let mut members: Vec<FleetMember> = (1..=4).map(|i| member(i, "eu", None, now)).collect();
+        members.push(member(5, "us", None, now));
+
+        // the first stage assigns the update to 25% of the 4 selected nodes
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.targets.len(), 1);
+        let canary = members[0].identifier.clone();
+        assert!(rollout.assigned_update(&canary, None).is_some());
+        assert!(rollout
+            .assigned_update(&members[1].identifier, None)
+            .is_none());
+
+        // the canary applies the update, the stage bakes then the other nodes get the update
+        now += 30;
+        members[0] = member(1, "eu", Some(2), now);
+        assert!(rollout.evaluate(&members, now));
+        assert!(rollout.assigned_update(&canary, Some(2)).is_none());
+        now += 60;
+        members[0] = member(1, "eu", Some(2), now);
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.stage, 1);
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.targets.len(), 4);
+        assert!(!rollout
+            .targets
+            .contains_key(&members[4].identifier.to_string()));
+
+        now += 30;
+        members = (1..=4).map(|i| member(i, "eu", Some(2), now)).collect();
+        assert!(rollout.evaluate(&members, now));
+        now += 60;
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.status, RolloutStatus::Completed);
+        assert!(!rollout.evaluate(&members, now));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rollback_after_failure() -> Result<()> {
+        let mut rollout = rollout(spec()).await?;
+        let mut now = 1000;
+        let members: Vec<FleetMember> = (1..=4).map(|i| member(i, "eu", None, now)).collect();
+        rollout.evaluate(&members, now);
+
+        // the canary keeps reporting its previous configuration
+        now += 121;
+        let members: Vec<FleetMember> = (1..=4).map(|i| member(i, "eu", None, now)).collect();
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.status, RolloutStatus::RollingBack);
+        let canary = &members[0].identifier;
+        let sent = rollout.assigned_update(canary, Some(2)).unwrap();
+        assert_eq!(sent.update.version, 3);
+
+        now += 10;
+        let mut members = members;
+        members[0] = member(1, "eu", Some(3), now);
+        assert!(rollout.evaluate(&members, now));
+        assert_eq!(rollout.status, RolloutStatus::RolledBack);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_single_active_rollout() -> Result<()> {
+        let rollouts = Rollouts::new(ockam::identity::storage::InMemoryStorage::create());
+        rollouts.create(rollout(spec()).await?).await?;
+        assert!(rollouts.create(rollout(spec()).await?).await.is_err());
+
+        let other = rollout(RolloutSpec::new("other")).await?;
+        assert!(rollouts.create(other.clone()).await.is_err());
+        rollouts
+            .update("canary", |r| r.abort("stopped", now()).map(|_| true))
+            .await?;
+        assert!(rollouts.create(other).await.is_err());
+
+        rollouts
+            .update("canary", |r| Ok(r.evaluate(&[], now())))
+            .await?;
+        assert_eq!(
+            rollouts.get("canary").await?.unwrap().status,
+            RolloutStatus::RolledBack
+        );
+        rollouts
+            .create(rollout(RolloutSpec::new("other")).await?)
+            .await?;
+        assert_eq!(rollouts.list().await?.len(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,599 @@
+//! Discovery of the outlets of a project by service name.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use minicbor::{Decode, Decoder, Encode};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, trace, warn};
+
+use ockam::identity::{
+    secure_channel_required, Identifier, IdentityAttributesReader, IdentitySecureChannelLocalInfo,
+    SecureClient,
+};
+use ockam_core::api::{Method, Request, RequestHeader, Response};
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Address, DenyAll, Result, Routed, Worker};
+use ockam_node::Context;
+
+use crate::DefaultAddress;
+
+/// Time after which a publication expires if it is not refreshed
+pub const PUBLICATION_TTL: Duration = Duration::from_secs(60);
+
+/// Interval between two refreshes of a publication
+pub const PUBLICATION_REFRESH_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Maximum number of publications accepted by the registry
+const MAX_PUBLICATIONS: usize = 10_000;
+
+/// Value of the attribute `ockam.service.<name>` allowing a member to publish the service `<name>`
+pub const SERVICE_PUBLISHER: &str = "publisher";
+
+/// Return the attribute allowing a member to publish a service, when its value is
+/// [`SERVICE_PUBLISHER`]
+pub fn service_publisher_attribute(name: &str) -> String {
+    format!("ockam.service.{name}")
+}
+
+/// Publication of a service by the node hosting its outlet
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ServicePublication {
+    /// Logical name of the service, for example `payments-db`
+    #[n(1)] pub name: String,
+    /// Route to the outlet, starting with the project, for example
+    /// `/project/default/service/forward_to_db/secure/api/service/outlet`
+    #[n(2)] pub route: String,
+    #[n(3)] pub metadata: BTreeMap<String, String>,
+}
+
+impl ServicePublication {
+    pub fn new(name: impl Into<String>, route: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            route: route.into(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// A current publisher of a service
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode, Serialize, Deserialize)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct ServicePublisher {
+    #[n(1)] pub publisher: Identifier,
+    #[n(2)] pub route: String,
+    #[n(3)] pub metadata: BTreeMap<String, String>,
+    /// Number of seconds before the publication expires, unless it is refreshed
+    #[n(4)] pub expires_in_secs: u64,
+}
+
+/// Publications of the services of a project, by service name
+pub struct ServiceRegistry {
+    services: Mutex<BTreeMap<String, Vec<Publication>>>,
+    /// Attributes of the project members, used to check who can publish a service
+    attributes_reader: Arc<dyn IdentityAttributesReader>,
+}
+
+struct Publication {
+    publisher: Identifier,
+    route: String,
+    metadata: BTreeMap<String, String>,
+    expires_at: Instant,
+}
+
+impl ServiceRegistry {
+    pub fn new(attributes_reader: Arc<dyn IdentityAttributesReader>) -> Self {
+        Self {
+            services: Mutex::new(BTreeMap::new()),
+            attributes_reader,
+        }
+    }
+
+    /// Return true if the attributes of a member allow it to publish a service
+    pub async fn can_publish(&self, publisher: &Identifier, name: &str) -> Result<bool> {
+        let Some(entry) = self
+            .attributes_reader
+            .get_attributes_for(publisher, Some(DefaultAddress::SERVICE_REGISTRY))
+            .await?
+        else {
+            return Ok(false);
+        };
+        Ok(entry
+            .attrs()
+            .get(service_publisher_attribute(name).as_bytes())
+            .map(|value| value.as_slice() == SERVICE_PUBLISHER.as_bytes())
+            .unwrap_or(false))
+    }
+
+    /// Add or refresh the publication of a service route by a publisher
+    pub fn publish(
+        &self,
+        publisher: &Identifier,
+        publication: ServicePublication,
+        ttl: Duration,
+    ) -> Result<()> {
+        let mut services = self.services.lock().unwrap();
+        Self::remove_expired(&mut services);
+
+        let expires_at = Instant::now() + ttl;
+        if let Some(existing) = services
+            .get_mut(&publication.name)
+            .and_then(|publications| {
+                publications
+                    .iter_mut()
+                    .find(|p| &p.publisher == publisher && p.route == publication.route)
+            })
+        {
+            existing.metadata = publication.metadata;
+            existing.expires_at = expires_at;
+            return Ok(());
+        }
+
+        let count: usize = services.values().map(|p| p.len()).sum();
+        if count >= MAX_PUBLICATIONS {
+            return Err(ockam_core::Error::new(
+                Origin::Api,
+                Kind::ResourceExhausted,
+                "the service registry is full",
+            ));
+        }
+        services
+            .entry(publication.name)
+            .or_default()
+            .push(Publication {
+                publisher: publisher.clone(),
+                route: publication.route,
+                metadata: publication.metadata,
+                expires_at,
+            });
+        Ok(())
+    }
+
+    /// Remove the publications of a service made by a publisher
+    pub fn withdraw(&self, publisher: &Identifier, name: &str) {
+        let mut services = self.services.lock().unwrap();
+        if let Some(publications) = services.get_mut(name) {
+            publications.retain(|p| &p.publisher != publisher);
+            if publications.is_empty() {
+                services.remove(name);
+            }
+        }
+    }
+
+    /// Return the current publishers of a service
+    pub fn publishers(&self, name: &str) -> Vec<ServicePublisher> {
+        let mut services = self.services.lock().unwrap();
+        Self::remove_expired(&mut services);
+        let now = Instant::now();
+        services
+            .get(name)
+            .map(|publications| {
+                publications
+                    .iter()
+                    .map(|p| ServicePublisher {
+                        publisher: p.publisher.clone(),
+                        route: p.route.clone(),
+                        metadata: p.metadata.clone(),
+                        expires_in_secs: p.expires_at.saturating_duration_since(now).as_secs(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Return the names of the services having at least one publisher
+    pub fn services(&self) -> Vec<String> {
+        let mut services = self.services.lock().unwrap();
+        Self::remove_expired(&mut services);
+        services.keys().cloned().collect()
+    }
+
+    fn remove_expired(services: &mut BTreeMap<String, Vec<Publication>>) {
+        let now = Instant::now();
+        services.retain(|_, publications| {
+            publications.retain(|p| p.expires_at > now);
+            !publications.is_empty()
+        });
+    }
+}
+
+#[ockam_core::worker]
+impl Worker for ServiceRegistry {
+    type Context = Context;
+    type Message = Vec<u8>;
+
+    async fn handle_message(&mut self, c: &mut Context, m: Routed<Self::Message>) -> Result<()> {
+        if let Ok(i) = IdentitySecureChannelLocalInfo::find_info(m.local_message()) {
+            let from = i.their_identity_id();
+            let mut dec = Decoder::new(m.as_body());
+            let req: RequestHeader = dec.decode()?;
+            trace! {
+                target: "ockam_api::service_registry",
+                from   = %from,
+                id     = %req.id(),
+                method = ?req.method(),
+                path   = %req.path(),
+                body   = %req.has_body(),
+                "request"
+            }
+            let path_segments = req.path_segments::<5>();
+            let res = match (req.method(), path_segments.as_slice()) {
+                (Some(Method::Get), ["services"]) => {
+                    Response::ok(&req).body(self.services()).to_vec()?
+                }
+                (Some(Method::Put), ["services"]) => {
+                    let publication: ServicePublication = dec.decode()?;
+                    let name = publication.name.clone();
+                    if !self.can_publish(&from, &name).await? {
+                        warn!("{from} is not allowed to publish the service {name}");
+                        Response::forbidden(
+                            &req,
+                            &format!("the service {name} can't be published by {from}"),
+                        )
+                        .to_vec()?
+                    } else {
+                        match self.publish(&from, publication, PUBLICATION_TTL) {
+                            Ok(()) => {
+                                debug!("{from} published the service {name}");
+                                Response::ok(&req).to_vec()?
+                            }
+                            Err(e) => Response::bad_request(&req, &e.to_string()).to_vec()?,
+                        }
+                    }
+                }
+                (Some(Method::Get), ["services", name]) => {
+                    Response::ok(&req).body(self.publishers(name)).to_vec()?
+                }
+                (Some(Method::Delete), ["services", name]) => {
+                    self.withdraw(&from, name);
+                    Response::ok(&req).to_vec()?
+                }
+                _ => Response::unknown_path(&req).to_vec()?,
+            };
+            c.send(m.return_route(), res).await
+        } else {
+            secure_channel_required(c, m).await
+        }
+    }
+}
+
+/// Client of the service registry of a project
+pub struct ServiceRegistryClient(SecureClient);
+
+impl ServiceRegistryClient {
+    /// The client must connect to the authority node of the project
+    pub fn new(client: SecureClient) -> Self {
+        Self(client)
+    }
+
+    /// Publish or refresh a service route
+    pub async fn publish(&self, ctx: &Context, publication: &ServicePublication) -> Result<()> {
+        let request = Request::put("/services").body(publication.clone());
+        self.0
+            .tell(ctx, DefaultAddress::SERVICE_REGISTRY, request)
+            .await?
+            .success()
+    }
+
+    /// Remove the publications of a service made by this node
+    pub async fn withdraw(&self, ctx: &Context, name: &str) -> Result<()> {
+        let request = Request::delete(format!("/services/{name}"));
+        self.0
+            .tell(ctx, DefaultAddress::SERVICE_REGISTRY, request)
+            .await?
+            .success()
+    }
+
+    /// Return the current publishers of a service
+    pub async fn publishers(&self, ctx: &Context, name: &str) -> Result<Vec<ServicePublisher>> {
+        let request = Request::get(format!("/services/{name}"));
+        self.0
+            .ask(ctx, DefaultAddress::SERVICE_REGISTRY, request)
+            .await?
+            .success()
+    }
+
+    /// Refresh a publication at a regular interval, until the returned task is aborted.
+    /// Errors are logged, since the registry might be temporarily unavailable
+    pub async fn start_publishing(
+        self,
+        ctx: &Context,
+        publication: ServicePublication,
+    ) -> Result<JoinHandle<()>> {
+        let ctx = ctx
+            .new_detached(
+                Address::random_tagged("ServicePublisher.ctx"),
+                DenyAll,
+                DenyAll,
+            )
+            .await?;
+        Ok(tokio::spawn(async move {
+            loop {
+                match self.publish(&ctx, &publication).await {
+                    Ok(()) => debug!("published the service {}", publication.name),
+                    Err(e) => warn!(
+                        "the service {} could not be published: {e}",
+                        publication.name
+                    ),
+                }
+                tokio::time::sleep(PUBLICATION_REFRESH_INTERVAL).await;
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use ockam::identity::utils::now;
+    use ockam::identity::{identities, AttributesEntry};
+    use ockam_core::route;
+    use ockam_multiaddr::MultiAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::cli_state::StateDirTrait;
+    use crate::cloud::project::Project;
+    use crate::test_harness::{TestHarness, TestNode};
+
+    #[test]
+    fn test_service_registry() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000002")?;
+        let registry = ServiceRegistry::new(identities().repository().as_attributes_reader());
+
+        let db = ServicePublication::new(
+            "db",
+            "/project/default/service/forward_to_a/secure/api/service/outlet",
+        )
+        .with_metadata("region", "eu");
+        registry.publish(&alice, db.clone(), PUBLICATION_TTL)?;
+        registry.publish(&alice, db.clone(), PUBLICATION_TTL)?;
+        registry.publish(
+            &bob,
+            ServicePublication::new(
+                "db",
+                "/project/default/service/forward_to_b/secure/api/service/outlet",
+            ),
+            PUBLICATION_TTL,
+        )?;
+
+        let publishers = registry.publishers("db");
+        assert_eq!(publishers.len(), 2);
+        assert_eq!(publishers[0].publisher, alice);
+        assert_eq!(
+            publishers[0].metadata.get("region"),
+            Some(&"eu".to_string())
+        );
+        assert_eq!(registry.services(), vec!["db".to_string()]);
+
+        // expired publications are not returned
+        registry.publish(
+            &alice,
+            ServicePublication::new("cache", "/project/default/service/outlet"),
+            Duration::ZERO,
+        )?;
+        assert!(registry.publishers("cache").is_empty());
+
+        registry.withdraw(&alice, "db");
+        let publishers = registry.publishers("db");
+        assert_eq!(publishers.len(), 1);
+        assert_eq!(publishers[0].publisher, bob);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_publish_and_resolve_a_service() -> Result<()> {
+        // the node 0 hosts the project and its registry, the nodes 1 and 2 publish the
+        // service `db` and the node 3 connects to it
+        let mut harness = TestHarness::start(4).await?;
+        let project = harness.node(0);
+        let registry_members = identities().repository();
+        for publisher in [1, 2] {
+            let attributes = BTreeMap::from([(
+                service_publisher_attribute("db").into_bytes(),
+                SERVICE_PUBLISHER.as_bytes().to_vec(),
+            )]);
+            registry_members
+                .as_attributes_writer()
+                .put_attributes(
+                    harness.node(publisher).identifier(),
+                    AttributesEntry::new(attributes, now()?, None, None),
+                )
+                .await?;
+        }
+        let secure_channel_listener = project
+            .node()
+            .registry
+            .secure_channel_listeners
+            .get(&DefaultAddress::SECURE_CHANNEL_LISTENER.into())
+            .await
+            .unwrap();
+        let secure_channel_flow_control_id =
+            secure_channel_listener.listener().flow_control_id().clone();
+        project.context().flow_controls().add_consumer(
+            DefaultAddress::SERVICE_REGISTRY,
+            &secure_channel_flow_control_id,
+        );
+        project
+            .context()
+            .start_worker(
+                DefaultAddress::SERVICE_REGISTRY,
+                ServiceRegistry::new(registry_members.as_attributes_reader()),
+            )
+            .await?;
+        for node in &harness.nodes()[1..] {
+            add_project(node, project).await?;
+        }
+
+        // each publisher relays its outlet through the project node
+        for (publisher, tag) in [(1, b"one"), (2, b"two")] {
+            let node = harness.node(publisher);
+            let server = start_server(tag).await;
+            node.node()
+                .create_outlet(node.context(), server, "outlet".into(), None, true)
+                .await?;
+            let relay = format!("forward_to_node{publisher}");
+            node.node()
+                .create_relay(
+                    node.context(),
+                    &MultiAddr::from_str(&format!(
+                        "/ip4/127.0.0.1/tcp/{}",
+                        project.listener_address().port()
+                    ))?,
+                    Some(relay.clone()),
+                    true,
+                    None,
+                )
+                .await?;
+            project
+                .context()
+                .flow_controls()
+                .add_consumer(relay.clone(), &secure_channel_flow_control_id);
+            node.node()
+                .publish_service(
+                    node.context(),
+                    "outlet",
+                    ServicePublication::new(
+                        "db",
+                        format!("/project/test/service/{relay}/secure/api/service/outlet"),
+                    ),
+                )
+                .await?;
+        }
+
+        // a member without the attribute of the service can't publish it
+        let inlet_node = harness.node(3);
+        let registry_client = inlet_node.node().service_registry_client("test").await?;
+        assert!(registry_client
+            .publish(
+                inlet_node.context(),
+                &ServicePublication::new(
+                    "db",
+                    "/project/test/service/forward_to_mallory/secure/api/service/outlet"
+                ),
+            )
+            .await
+            .is_err());
+        let publishers = wait_for_publishers(inlet_node, &registry_client, 2).await?;
+        assert_eq!(publishers[0].publisher, *harness.node(1).identifier());
+
+        // the inlet connects to the first publisher
+        let inlet = inlet_node
+            .node()
+            .create_service_inlet(
+                inlet_node.context(),
+                "127.0.0.1:0".to_string(),
+                None,
+                None,
+                route![],
+                route![],
+                MultiAddr::from_str("/project/test")?,
+                "db".to_string(),
+                None,
+            )
+            .await?;
+        let inlet: SocketAddr = inlet.bind_addr.parse().unwrap();
+        assert_eq!(&request(inlet).await.unwrap(), b"one");
+
+        // when the first publisher can't be reached anymore, the service is resolved again
+        // and the inlet connects to the other publisher
+        harness.stop_node(1).await?;
+        let mut response = None;
+        for _ in 0..60 {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            if let Ok(Ok(r)) = tokio::time::timeout(Duration::from_secs(2), request(inlet)).await {
+                if r == b"two" {
+                    response = Some(r);
+                    break;
+                }
+            }
+        }
+        assert_eq!(response.as_deref(), Some(b"two".as_slice()));
+
+        harness.stop().await
+    }
+
+    /// Store the project hosted by the node `project` in the state of a node
+    async fn add_project(node: &TestNode, project: &TestNode) -> Result<()> {
+        let identity = project
+            .node()
+            .secure_channels
+            .identities()
+            .export_identity(project.identifier())
+            .await?;
+        let route = project.secure_channel_listener_multiaddr()?.to_string();
+        node.cli_state().projects.overwrite(
+            "test",
+            Project {
+                id: "test".to_string(),
+                name: "test".to_string(),
+                access_route: route.clone(),
+                identity: Some(project.identifier().clone()),
+                authority_access_route: Some(route),
+                authority_identity: Some(hex::encode(identity)),
+                ..Default::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    async fn wait_for_publishers(
+        node: &TestNode,
+        client: &ServiceRegistryClient,
+        count: usize,
+    ) -> Result<Vec<ServicePublisher>> {
+        // the publications are sent in the background
+        for _ in 0..50 {
+            let publishers = client.publishers(node.context(), "db").await?;
+            if publishers.len() == count {
+                return Ok(publishers);
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        Err(ockam_core::Error::new(
+            Origin::Api,
+            Kind::Timeout,
+            "the service was not published",
+        ))
+    }
+
+    /// Start a server answering each connection with a tag
+    async fn start_server(tag: &'static [u8; 3]) -> SocketAddr {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = server.accept().await {
+                tokio::spawn(async move {
+                    let mut buffer = [0u8; 1];
+                    if stream.read_exact(&mut buffer).await.is_ok() {
+                        let _ = stream.write_all(tag).await;
+                    }
+                });
+            }
+        });
+        address
+    }
+
+    async fn request(inlet: SocketAddr) -> std::io::Result<Vec<u8>> {
+        let mut stream = TcpStream::connect(inlet).await?;
+        stream.write_all(b"?").await?;
+        let mut response = vec![0u8; 3];
+        stream.read_exact(&mut response).await?;
+        Ok(response)
+    }
+}
@@ -0,0 +1,102 @@
+//! Bridge between the standard input and output of a process and a TCP inlet.
+
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+
+use crate::error::ApiError;
+
+/// Copy the standard input of the current process to the inlet listening at `addr`, and the
+/// bytes received from the inlet to the standard output, until the inlet closes the connection
+pub async fn bridge_stdio(addr: SocketAddr) -> Result<()> {
+    bridge(tokio::io::stdin(), tokio::io::stdout(), addr).await
+}
+
+/// Copy `input` to the inlet listening at `addr`, and the bytes received from the inlet to
+/// `output`, until the inlet closes the connection.
+///
+/// When `input` is closed first, the write side of the connection is shut down, and the bytes
+/// still sent by the outlet are copied until it closes the connection
+pub async fn bridge<R, W>(mut input: R, mut output: W, addr: SocketAddr) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| ApiError::core(format!("the inlet {addr} can't be reached: {e}")))?;
+    stream.set_nodelay(true).map_err(io_error)?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    let upstream = async {
+        let sent = tokio::io::copy(&mut input, &mut writer).await?;
+        writer.shutdown().await?;
+        Ok::<u64, std::io::Error>(sent)
+    };
+    let downstream = async {
+        let received = tokio::io::copy(&mut reader, &mut output).await?;
+        output.flush().await?;
+        Ok::<u64, std::io::Error>(received)
+    };
+    tokio::pin!(upstream);
+    tokio::pin!(downstream);
+
+    let mut input_closed = false;
+    loop {
+        tokio::select! {
+            sent = &mut upstream, if !input_closed => {
+                let sent = sent.map_err(io_error)?;
+                debug!(%addr, sent, "The input of the bridge is closed");
+                input_closed = true;
+            }
+            received = &mut downstream => {
+                let received = received.map_err(io_error)?;
+                debug!(%addr, received, "The inlet closed the connection of the bridge");
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(Origin::Api, Kind::Io, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_bridge() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await.map_err(io_error)?;
+        let addr = listener.local_addr().map_err(io_error)?;
+        // the server replies once the client is done, then closes the connection
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![];
+            stream.read_to_end(&mut request).await.unwrap();
+            request.reverse();
+            stream.write_all(&request).await.unwrap();
+        });
+
+        let mut output = vec![];
+        bridge(&b"hello"[..], &mut output, addr).await?;
+        server.await.unwrap();
+        assert_eq!(output, b"olleh");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_inlet() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        assert!(bridge(&b""[..], Vec::new(), addr).await.is_err());
+    }
+}
@@ -0,0 +1,117 @@
+//! Periodic maintenance of the local databases of a node.
+
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use ockam::identity::storage::MaintenanceReport;
+use ockam::LmdbStorage;
+
+/// Interval between two maintenances of the databases of a node
+pub const DEFAULT_STORAGE_MAINTENANCE_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+
+/// Number of rounds which can be postponed in a row because the node is busy
+pub const MAX_POSTPONED_ROUNDS: u32 = 3;
+
+/// Pause between the maintenance of two databases
+const MAINTENANCE_PAUSE: Duration = Duration::from_secs(1);
+
+/// Task maintaining the databases of a node
+#[derive(Clone)]
+pub struct StorageMaintenance {
+    databases: Vec<LmdbStorage>,
+    interval: Duration,
+}
+
+impl StorageMaintenance {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            databases: vec![],
+            interval,
+        }
+    }
+
+    pub fn with_database(mut self, database: LmdbStorage) -> Self {
+        self.databases.push(database);
+        self
+    }
+
+    /// Start the maintenance, the first round happens after one interval
+    pub fn start(self) -> JoinHandle<()> {
+        info!(
+            databases = self.databases.len(),
+            interval_secs = self.interval.as_secs(),
+            "Scheduling the maintenance of the databases"
+        );
+        tokio::spawn(async move {
+            let start = tokio::time::Instant::now() + self.interval;
+            let mut interval = tokio::time::interval_at(start, self.interval);
+            let mut slow_operations = LmdbStorage::statistics().slow_operations;
+            let mut postponed = 0;
+            loop {
+                interval.tick().await;
+                let current = LmdbStorage::statistics().slow_operations;
+                if current > slow_operations && postponed < MAX_POSTPONED_ROUNDS {
+                    debug!(postponed, "The node is busy, postponing the maintenance");
+                    slow_operations = current;
+                    postponed += 1;
+                    continue;
+                }
+                self.run().await;
+                slow_operations = LmdbStorage::statistics().slow_operations;
+                postponed = 0;
+            }
+        })
+    }
+
+    /// Maintain all the databases, one after the other
+    pub async fn run(&self) -> Vec<MaintenanceReport> {
+        let mut reports = vec![];
+        for (index, database) in self.databases.iter().enumerate() {
+            if index > 0 {
+                tokio::time::sleep(MAINTENANCE_PAUSE).await;
+            }
+            match database.maintain().await {
+                Ok(report) => {
+                    info!(
+                        database = %report.database,
+                        size = report.size_after,
+                        reclaimed_bytes = report.reclaimed_bytes(),
+                        free_bytes = report.free_bytes,
+                        duration_ms = report.duration.as_millis() as u64,
+                        "Maintained a database"
+                    );
+                    reports.push(report);
+                }
+                Err(e) => {
+                    warn!(%e, database = database.name(), "The database can't be maintained")
+                }
+            }
+        }
+        reports
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam::identity::storage::Storage;
+    use ockam_core::Result;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_maintain_all_databases() -> Result<()> {
+        let (first_file, second_file) =
+            (NamedTempFile::new().unwrap(), NamedTempFile::new().unwrap());
+        let first = LmdbStorage::new(first_file.path()).await?;
+        first.set("id", "key".to_string(), vec![1; 64]).await?;
+        let maintenance = StorageMaintenance::new(DEFAULT_STORAGE_MAINTENANCE_INTERVAL)
+            .with_database(first)
+            .with_database(LmdbStorage::new(second_file.path()).await?);
+
+        let reports = maintenance.run().await;
+        assert_eq!(reports.len(), 2);
+        assert!(reports.iter().all(|r| r.reclaimed_bytes() == 0));
+        Ok(())
+    }
+}
@@ -0,0 +1,461 @@
+//! Helpers to write end-to-end tests spanning several nodes running in the same process.
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::thread::JoinHandle;
+
+use ockam::identity::models::CredentialAndPurposeKey;
+use ockam::identity::utils::AttributesBuilder;
+use ockam::identity::{
+    secure_channels, Identifier, Identity, SecureChannel, SecureChannels, MAX_CREDENTIAL_VALIDITY,
+    PROJECT_MEMBER_SCHEMA, TRUST_CONTEXT_ID,
+};
+use ockam::{Context, Result, TcpListenerOptions, TcpTransport};
+use ockam_core::compat::sync::Arc;
+use ockam_core::{route, Address};
+use ockam_multiaddr::MultiAddr;
+use ockam_node::NodeBuilder;
+
+use crate::cli_state::{init_node_state, random_name, CliState, StateDirTrait, StateItemTrait};
+use crate::config::cli::{CredentialRetrieverConfig, TrustAuthorityConfig, TrustContextConfig};
+use crate::error::ApiError;
+use crate::nodes::service::{
+    NodeManagerGeneralOptions, NodeManagerTransportOptions, NodeManagerTrustOptions,
+};
+use crate::nodes::{InMemoryNode, NODEMANAGER_ADDR};
+use crate::DefaultAddress;
+
+/// A set of nodes started in the current process, possibly sharing a test authority
+pub struct TestHarness {
+    authority: Option<TestAuthority>,
+    nodes: Vec<TestNode>,
+}
+
+impl TestHarness {
+    /// Start `n` nodes without any trust context
+    pub async fn start(n: usize) -> Result<Self> {
+        let mut harness = Self {
+            authority: None,
+            nodes: vec![],
+        };
+        for _ in 0..n {
+            harness.add_node().await?;
+        }
+        Ok(harness)
+    }
+
+    /// Start a test authority and `n` nodes enrolled as members of that authority
+    pub async fn start_with_authority(n: usize) -> Result<Self> {
+        let mut harness = Self {
+            authority: Some(TestAuthority::create().await?),
+            nodes: vec![],
+        };
+        for _ in 0..n {
+            harness.add_member(BTreeMap::new()).await?;
+        }
+        Ok(harness)
+    }
+
+    /// Start a new node and return its index.
+    /// If the harness has an authority, the node trusts it but is not enrolled as a member
+    pub async fn add_node(&mut self) -> Result<usize> {
+        let authority = self.authority.as_ref();
+        let node = TestNode::start(|_| async move {
+            authority.map(|a| a.trust_context_config(None)).transpose()
+        })
+        .await?;
+        self.nodes.push(node);
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// Start a new node, enrolled as a member of the authority with some additional attributes,
+    /// and return its index
+    pub async fn add_member(&mut self, attributes: BTreeMap<String, String>) -> Result<usize> {
+        let authority = self
+            .authority
+            .as_ref()
+            .ok_or_else(|| ApiError::core("The test harness has no authority"))?;
+        let node = TestNode::start(|identity| async move {
+            let credential = authority
+                .issue_member_credential(&identity, attributes)
+                .await?;
+            Ok(Some(authority.trust_context_config(Some(&credential))?))
+        })
+        .await?;
+        self.nodes.push(node);
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// Return the node at the given index
+    pub fn node(&self, index: usize) -> &TestNode {
+        &self.nodes[index]
+    }
+
+    /// Return all the nodes of the harness
+    pub fn nodes(&self) -> &[TestNode] {
+        &self.nodes
+    }
+
+    /// Return the authority of the harness if one was created
+    pub fn authority(&self) -> Option<&TestAuthority> {
+        self.authority.as_ref()
+    }
+
+    /// Create a secure channel from the node `from` to the node `to`
+    pub async fn create_secure_channel(&self, from: usize, to: usize) -> Result<SecureChannel> {
+        let (from, to) = (self.node(from), self.node(to));
+        from.node
+            .create_secure_channel(
+                &from.ctx,
+                to.secure_channel_listener_multiaddr()?,
+                None,
+                Some(vec![to.identifier.clone()]),
+                None,
+                None,
+            )
+            .await
+    }
+
+    /// Create an outlet on the node `to`, forwarding traffic to `target`,
+    /// and an inlet on the node `from` connected to that outlet.
+    /// Return the address of the inlet
+    pub async fn create_portal(
+        &self,
+        from: usize,
+        to: usize,
+        target: SocketAddr,
+    ) -> Result<SocketAddr> {
+        let (from, to) = (self.node(from), self.node(to));
+        let outlet = to
+            .node
+            .create_outlet(
+                &to.ctx,
+                target,
+                Address::from_string(random_name()),
+                None,
+                true,
+            )
+            .await?;
+
+        // the inlet reaches the outlet through a secure channel with the node `to`
+        let outlet_addr = MultiAddr::from_str(&format!(
+            "/ip4/127.0.0.1/tcp/{}/secure/{}/service/{}",
+            to.listener_address.port(),
+            DefaultAddress::SECURE_CHANNEL_LISTENER,
+            outlet.worker_addr.address()
+        ))?;
+        let inlet = from
+            .node
+            .create_inlet(
+                &from.ctx,
+                "127.0.0.1:0".to_string(),
+                None,
+                None,
+                route![],
+                route![],
+                outlet_addr,
+                None,
+                Some(to.identifier.clone()),
+            )
+            .await?;
+        inlet
+            .bind_addr
+            .parse()
+            .map_err(|e| ApiError::core(format!("invalid inlet address: {e}")))
+    }
+
+    /// Stop a node, as if it went offline, and delete its state.
+    /// The nodes started after that node are shifted to the left
+    pub async fn stop_node(&mut self, index: usize) -> Result<()> {
+        self.nodes.remove(index).stop().await
+    }
+
+    /// Stop all the nodes of the harness and delete their state
+    pub async fn stop(self) -> Result<()> {
+        for node in self.nodes {
+            node.stop().await?;
+        }
+        Ok(())
+    }
+}
+
+/// An authority identity, kept in memory, issuing member credentials for a test trust context
+pub struct TestAuthority {
+    secure_channels: Arc<SecureChannels>,
+    identity: Identity,
+    trust_context_id: String,
+}
+
+impl TestAuthority {
+    /// Create a new authority identity with a random trust context id
+    pub async fn create() -> Result<Self> {
+        let secure_channels = secure_channels();
+        let identity = secure_channels
+            .identities()
+            .identities_creation()
+            .create_identity()
+            .await?;
+        Ok(Self {
+            secure_channels,
+            identity,
+            trust_context_id: random_name(),
+        })
+    }
+
+    /// Identifier of the authority
+    pub fn identifier(&self) -> &Identifier {
+        self.identity.identifier()
+    }
+
+    /// Id of the trust context of the authority
+    pub fn trust_context_id(&self) -> &str {
+        &self.trust_context_id
+    }
+
+    /// Issue a member credential with the trust context id and some additional attributes
+    pub async fn issue_member_credential(
+        &self,
+        subject: &Identity,
+        attributes: BTreeMap<String, String>,
+    ) -> Result<CredentialAndPurposeKey> {
+        // the subject of a credential must be known by the authority
+        self.secure_channels
+            .identities()
+            .repository()
+            .update_identity(subject.identifier(), subject.change_history())
+            .await?;
+        let mut builder = AttributesBuilder::with_schema(PROJECT_MEMBER_SCHEMA).with_attribute(
+            TRUST_CONTEXT_ID.to_vec(),
+            self.trust_context_id.as_bytes().to_vec(),
+        );
+        for (key, value) in attributes {
+            builder = builder.with_attribute(key.into_bytes(), value.into_bytes());
+        }
+        self.secure_channels
+            .identities()
+            .credentials()
+            .credentials_creation()
+            .issue_credential(
+                self.identifier(),
+                subject.identifier(),
+                builder.build(),
+                MAX_CREDENTIAL_VALIDITY,
+            )
+            .await
+    }
+
+    /// Trust context configuration for a node trusting this authority and,
+    /// optionally, presenting a credential issued by this authority
+    pub fn trust_context_config(
+        &self,
+        credential: Option<&CredentialAndPurposeKey>,
+    ) -> Result<TrustContextConfig> {
+        let own_credential = credential
+            .map(|c| {
+                Ok::<_, ockam_core::Error>(CredentialRetrieverConfig::FromMemory(minicbor::to_vec(
+                    c,
+                )?))
+            })
+            .transpose()?;
+        Ok(TrustContextConfig::new(
+            self.trust_context_id.clone(),
+            Some(TrustAuthorityConfig::new(
+                hex::encode(self.identity.export()?),
+                own_credential,
+            )),
+        ))
+    }
+}
+
+/// A node started by the test harness
+pub struct TestNode {
+    ctx: Context,
+    node: Arc<InMemoryNode>,
+    identifier: Identifier,
+    listener_address: SocketAddr,
+    router: Option<JoinHandle<()>>,
+    // declared last so that the state is deleted once the node has been dropped
+    cli_state: CliState,
+}
+
+impl TestNode {
+    /// Start a node on its own router. The trust context of the node is computed from the
+    /// identity of the node, once it has been created
+    async fn start<F, Fut>(trust_context: F) -> Result<Self>
+    where
+        F: FnOnce(Identity) -> Fut,
+        Fut: std::future::Future<Output = Result<Option<TrustContextConfig>>>,
+    {
+        let (ctx, router) = start_router()?;
+        let cli_state = CliState::ephemeral()?;
+        let node_name = random_name();
+        init_node_state(&cli_state, &node_name, None, None)
+            .await
+            .map_err(|e| ApiError::core(e.to_string()))?;
+        let node_config = cli_state.nodes.get(&node_name)?.config().clone();
+        let identifier = node_config.identifier()?;
+        let identity = cli_state
+            .get_identities(node_config.vault().await?)
+            .await?
+            .get_identity(&identifier)
+            .await?;
+
+        // the credentials issued by the authority are verified with its identity,
+        // which must be known by the node
+        let trust_context = trust_context(identity).await?;
+        if let Some(authority) = trust_context.as_ref().and_then(|tc| tc.authority().ok()) {
+            let authority = authority.identity().await?;
+            cli_state
+                .identities_repository()
+                .await?
+                .update_identity(authority.identifier(), authority.change_history())
+                .await?;
+        }
+
+        let tcp = TcpTransport::create(&ctx).await?;
+        let listener = tcp.listen("127.0.0.1:0", TcpListenerOptions::new()).await?;
+
+        let node = InMemoryNode::new(
+            &ctx,
+            NodeManagerGeneralOptions::new(cli_state.clone(), node_name, None, true, false),
+            NodeManagerTransportOptions::new(listener.flow_control_id().clone(), tcp),
+            NodeManagerTrustOptions::new(trust_context),
+        )
+        .await?;
+        ctx.flow_controls()
+            .add_consumer(NODEMANAGER_ADDR, listener.flow_control_id());
+
+        Ok(Self {
+            ctx,
+            node: Arc::new(node),
+            identifier,
+            listener_address: *listener.socket_address(),
+            router: Some(router),
+            cli_state,
+        })
+    }
+
+    /// Context of the node, to be used when calling the node functions
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    /// The node itself
+    pub fn node(&self) -> Arc<InMemoryNode> {
+        self.node.clone()
+    }
+
+    /// Identifier of the node identity
+    pub fn identifier(&self) -> &Identifier {
+        &self.identifier
+    }
+
+    /// CliState of the node
+    pub fn cli_state(&self) -> &CliState {
+        &self.cli_state
+    }
+
+    /// Address of the TCP listener of the node
+    pub fn listener_address(&self) -> SocketAddr {
+        self.listener_address
+    }
+
+    /// Address of the default secure channel listener of the node, reachable from the other nodes
+    pub fn secure_channel_listener_multiaddr(&self) -> Result<MultiAddr> {
+        MultiAddr::from_str(&format!(
+            "/ip4/127.0.0.1/tcp/{}/service/{}",
+            self.listener_address.port(),
+            DefaultAddress::SECURE_CHANNEL_LISTENER
+        ))
+        .map_err(ApiError::core)
+    }
+
+    /// Stop the node services and its router
+    pub async fn stop(mut self) -> Result<()> {
+        self.node.stop(&self.ctx).await?;
+        self.ctx.stop().await?;
+        if let Some(router) = self.router.take() {
+            tokio::task::spawn_blocking(move || router.join())
+                .await
+                .map_err(|e| ApiError::core(e.to_string()))?
+                .map_err(|_| ApiError::core("the router of the node panicked"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Start a new router, with its own runtime, on a dedicated thread and return its context
+fn start_router() -> Result<(Context, JoinHandle<()>)> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let router = std::thread::spawn(move || {
+        let (ctx, mut executor) = NodeBuilder::new().no_logging().build();
+        if sender.send(ctx).is_ok() {
+            // the router runs until the context is stopped
+            if let Err(e) = executor.execute(async {}) {
+                error!("the router of a test node stopped with an error: {e:?}");
+            }
+        }
+    });
+    let ctx = receiver
+        .recv()
+        .map_err(|_| ApiError::core("cannot start the router of a test node"))?;
+    Ok((ctx, router))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_portal_between_two_members() -> Result<()> {
+        let server = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_address = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = server.accept().await.unwrap();
+            let mut buffer = [0u8; 5];
+            stream.read_exact(&mut buffer).await.unwrap();
+            stream.write_all(&buffer).await.unwrap();
+        });
+
+        let harness = TestHarness::start_with_authority(2).await?;
+        let inlet = harness.create_portal(0, 1, server_address).await?;
+
+        let mut client = tokio::net::TcpStream::connect(inlet).await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+        let mut response = [0u8; 5];
+        client.read_exact(&mut response).await.unwrap();
+        assert_eq!(&response, b"hello");
+
+        harness.stop().await
+    }
+
+    #[tokio::test]
+    async fn test_secure_channel_between_nodes() -> Result<()> {
+        let harness = TestHarness::start(2).await?;
+        let channel = harness.create_secure_channel(0, 1).await?;
+        let response: String = harness
+            .node(0)
+            .context()
+            .send_and_receive(
+                route![
+                    channel.encryptor_address().clone(),
+                    DefaultAddress::ECHO_SERVICE
+                ],
+                "hello".to_string(),
+            )
+            .await?;
+        assert_eq!(response, "hello");
+
+        assert!(harness.node(0).cli_state().dir.exists());
+        harness.stop().await
+    }
+
+    #[tokio::test]
+    async fn test_a_member_needs_an_authority() -> Result<()> {
+        let mut harness = TestHarness::start(1).await?;
+        assert!(harness.add_member(BTreeMap::new()).await.is_err());
+        harness.stop().await
+    }
+}
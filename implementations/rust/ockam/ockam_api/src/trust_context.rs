@@ -1,4 +1,6 @@
-use crate::cli_state::{CliState, ProjectConfigCompact, StateDirTrait, StateItemTrait};
+use crate::cli_state::{
+    CliState, ProjectConfigCompact, Resolver, StateDirTrait, StateItemTrait, OCKAM_PROJECT,
+};
 use crate::cloud::project::Project;
 use crate::config::cli::TrustContextConfig;
 use miette::{IntoDiagnostic, WrapErr};
@@ -105,7 +107,9 @@ impl TrustContextConfigBuilder {
     }
 
     fn get_from_default_project(&self) -> Option<TrustContextConfig> {
-        let proj = self.cli_state.projects.default().ok()?;
+        let proj = Resolver::new(&self.cli_state.projects, OCKAM_PROJECT)
+            .get(None)
+            .ok()?;
         self.get_from_project_path(proj.path())
     }
 }
@@ -6,11 +6,14 @@ use ockam_core::errcode::{Kind, Origin};
 use ockam_core::flow_control::FlowControlId;
 use ockam_core::{Address, Error, Result, Route, TransportType, LOCAL};
 use ockam_multiaddr::proto::{
-    DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp, Worker,
+    Dns4, Dns6, DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp, Worker,
+};
+use ockam_multiaddr::{Code, MultiAddr, ProtoValue, Protocol};
+use ockam_transport_tcp::{
+    resolve_peers, AddressFamily, HttpProxy, TcpConnection, TcpConnectionOptions, TCP,
 };
-use ockam_multiaddr::{Code, MultiAddr, Protocol};
-use ockam_transport_tcp::{TcpConnection, TcpConnectionOptions, TCP};
 
+use crate::cli_state::{select_proxy, ProxyConfig};
 use crate::error::ApiError;
 
 /// Try to convert a multi-address to an Ockam route.
@@ -52,7 +55,7 @@ pub fn local_multiaddr_to_route(ma: &MultiAddr) -> Result<Route> {
                 ))
             }
 
-            code @ (Ip4::CODE | Ip6::CODE | DnsAddr::CODE) => {
+            code @ (Ip4::CODE | Ip6::CODE | DnsAddr::CODE | Dns4::CODE | Dns6::CODE) => {
                 return Err(Error::new(
                     Origin::Api,
                     Kind::Invalid,
@@ -84,6 +87,33 @@ pub async fn multiaddr_to_route(
     ma: &MultiAddr,
     tcp: &TcpTransport,
 ) -> Option<MultiAddrToRouteResult> {
+    connect_multiaddr(ma, tcp, |_| Ok(None)).await
+}
+
+/// Same as [`multiaddr_to_route`] but the TCP connection is established through an HTTP(S)
+/// forward proxy: the configured one, or the one defined by the environment variables
+pub async fn multiaddr_to_route_through_proxy(
+    ma: &MultiAddr,
+    tcp: &TcpTransport,
+    proxy_config: Option<&ProxyConfig>,
+) -> Option<MultiAddrToRouteResult> {
+    connect_multiaddr(ma, tcp, |peer| Ok(select_proxy(proxy_config, peer)?)).await
+}
+
+async fn connect_multiaddr(
+    ma: &MultiAddr,
+    tcp: &TcpTransport,
+    proxy_for: impl Fn(&str) -> Result<Option<HttpProxy>>,
+) -> Option<MultiAddrToRouteResult> {
+    let tcp_options = |peer: &str| match proxy_for(peer) {
+        Ok(Some(proxy)) => Some(TcpConnectionOptions::new().with_proxy(proxy)),
+        Ok(None) => Some(TcpConnectionOptions::new()),
+        Err(error) => {
+            error!(%error, %peer, "Couldn't select a proxy");
+            None
+        }
+    };
+
     let mut rb = Route::new();
     let mut it = ma.iter().peekable();
 
@@ -102,7 +132,7 @@ pub async fn multiaddr_to_route(
                 let port = it.next()?.cast::<Tcp>()?;
                 let socket_addr = SocketAddrV4::new(*ip4, *port);
 
-                let options = TcpConnectionOptions::new();
+                let options = tcp_options(&socket_addr.to_string())?;
                 flow_control_id = Some(options.flow_control_id().clone());
 
                 let connection = match tcp.connect(socket_addr.to_string(), options).await {
@@ -127,7 +157,7 @@ pub async fn multiaddr_to_route(
                 let port = it.next()?.cast::<Tcp>()?;
                 let socket_addr = SocketAddrV6::new(*ip6, *port, 0, 0);
 
-                let options = TcpConnectionOptions::new();
+                let options = tcp_options(&socket_addr.to_string())?;
                 flow_control_id = Some(options.flow_control_id().clone());
 
                 let connection = match tcp.connect(socket_addr.to_string(), options).await {
@@ -143,19 +173,19 @@ pub async fn multiaddr_to_route(
 
                 tcp_connection = Some(connection);
             }
-            DnsAddr::CODE => {
+            DnsAddr::CODE | Dns4::CODE | Dns6::CODE => {
                 if number_of_tcp_hops >= 1 {
                     return None; // Only 1 TCP hop is allowed
                 }
 
-                let host = p.cast::<DnsAddr>()?;
+                let (host, address_family) = dns_host(&p)?;
                 if let Some(p) = it.peek() {
                     if p.code() == Tcp::CODE {
                         let port = p.cast::<Tcp>()?;
 
-                        let options = TcpConnectionOptions::new();
+                        let peer = format!("{}:{}", host, *port);
+                        let options = tcp_options(&peer)?.with_address_family(address_family);
                         flow_control_id = Some(options.flow_control_id().clone());
-                        let peer = format!("{}:{}", &*host, *port);
 
                         let connection = match tcp.connect(&peer, options).await {
                             Ok(c) => c,
@@ -224,12 +254,17 @@ pub fn multiaddr_to_transport_route(ma: &MultiAddr) -> Option<Route> {
                 let socket_addr = SocketAddrV6::new(*ip6, *port, 0, 0);
                 route = route.append(Address::new(TransportType::new(1), socket_addr.to_string()))
             }
-            DnsAddr::CODE => {
-                let host = p.cast::<DnsAddr>()?;
+            DnsAddr::CODE | Dns4::CODE | Dns6::CODE => {
+                let (host, address_family) = dns_host(&p)?;
                 if let Some(p) = it.peek() {
                     if p.code() == Tcp::CODE {
                         let port = p.cast::<Tcp>()?;
-                        let addr = format!("{}:{}", &*host, *port);
+                        let mut addr = format!("{}:{}", host, *port);
+                        // The hostname is resolved later, unless it must be resolved to
+                        // an address of a given family
+                        if address_family != AddressFamily::Any {
+                            addr = resolve_peers(addr, address_family).ok()?[0].to_string();
+                        }
                         route = route.append(Address::new(TransportType::new(1), addr));
                         let _ = it.next();
                         continue;
@@ -257,6 +292,17 @@ pub fn multiaddr_to_transport_route(ma: &MultiAddr) -> Option<Route> {
     Some(route.into())
 }
 
+/// Return the hostname of a `/dnsaddr`, `/dns4` or `/dns6` value, and the family
+/// of the addresses it must be resolved to
+fn dns_host<'a>(p: &'a ProtoValue<'a>) -> Option<(String, AddressFamily)> {
+    match p.code() {
+        DnsAddr::CODE => Some((p.cast::<DnsAddr>()?.to_string(), AddressFamily::Any)),
+        Dns4::CODE => Some((p.cast::<Dns4>()?.to_string(), AddressFamily::Ipv4)),
+        Dns6::CODE => Some((p.cast::<Dns6>()?.to_string(), AddressFamily::Ipv6)),
+        _ => None,
+    }
+}
+
 /// Try to convert a multiaddr to an Ockam Address
 pub fn multiaddr_to_addr(ma: &MultiAddr) -> Option<Address> {
     let mut it = ma.iter().peekable();
@@ -322,12 +368,11 @@ pub fn is_local_node(ma: &MultiAddr) -> miette::Result<bool> {
             Node::CODE => {
                 at_rust_node = true;
             }
-            // A "/dnsaddr" will be local if it is "localhost"
-            DnsAddr::CODE => {
-                at_rust_node = p
-                    .cast::<DnsAddr>()
-                    .map(|dnsaddr| (*dnsaddr).eq("localhost"))
-                    .ok_or_else(|| miette!("Invalid \"dnsaddr\" value"))?;
+            // A "/dnsaddr", "/dns4" or "/dns6" will be local if it is "localhost"
+            DnsAddr::CODE | Dns4::CODE | Dns6::CODE => {
+                at_rust_node = dns_host(&p)
+                    .map(|(host, _)| host.eq("localhost"))
+                    .ok_or_else(|| miette!("Invalid DNS name value"))?;
             }
             // A "/ip4" will be local if it matches the loopback address
             Ip4::CODE => {
@@ -361,6 +406,8 @@ pub fn local_worker(code: &Code) -> Result<bool> {
         | Space::CODE
         | Project::CODE
         | DnsAddr::CODE
+        | Dns4::CODE
+        | Dns6::CODE
         | Ip4::CODE
         | Ip6::CODE
         | Tcp::CODE
@@ -379,7 +426,7 @@ pub mod test_utils {
     use ockam::identity::{SecureChannels, PROJECT_MEMBER_SCHEMA, TRUST_CONTEXT_ID};
     use ockam::Result;
     use ockam_core::compat::sync::Arc;
-    use ockam_core::flow_control::FlowControls;
+    use ockam_core::flow_control::{FlowControlId, FlowControls};
     use ockam_core::AsyncTryClone;
 
     use ockam_node::Context;
@@ -420,6 +467,21 @@ pub mod test_utils {
     /// things *will* break.
     // #[must_use] make sense to enable only on rust 1.67+
     pub async fn start_manager_for_tests(context: &mut Context) -> Result<NodeManagerHandle> {
+        start_manager_with_api_token_for_tests(
+            context,
+            FlowControls::generate_flow_control_id(), // FIXME
+            None,
+        )
+        .await
+    }
+
+    /// Starts a local node manager requiring an API token in the requests received
+    /// by the API transport listener having the given flow control id
+    pub async fn start_manager_with_api_token_for_tests(
+        context: &mut Context,
+        api_transport_flow_control_id: FlowControlId,
+        api_token: Option<String>,
+    ) -> Result<NodeManagerHandle> {
         let tcp = TcpTransport::create(context).await?;
         let cli_state = CliState::test()?;
 
@@ -439,7 +501,7 @@ pub mod test_utils {
         // has been re-created
         let secure_channels = SecureChannels::builder()
             .with_vault(vault)
-            .with_identities_repository(cli_state.identities.identities_repository().await?)
+            .with_identities_repository(cli_state.identities_repository().await?)
             .with_identities_storage(InMemoryStorage::create())
             .build();
 
@@ -475,9 +537,10 @@ pub mod test_utils {
 
         let node_manager = InMemoryNode::new(
             context,
-            NodeManagerGeneralOptions::new(cli_state.clone(), node_name, None, true, false),
+            NodeManagerGeneralOptions::new(cli_state.clone(), node_name, None, true, false)
+                .with_api_token(api_token),
             NodeManagerTransportOptions::new(
-                FlowControls::generate_flow_control_id(), // FIXME
+                api_transport_flow_control_id,
                 tcp.async_try_clone().await?,
             ),
             NodeManagerTrustOptions::new(Some(TrustContextConfig::new(
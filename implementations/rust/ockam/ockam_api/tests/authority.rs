@@ -65,6 +65,7 @@ async fn controlling_authority_by_member_times_out(ctx: &mut Context) -> Result<
         &admin.identifier,
         &MultiAddr::try_from("/secure/api")?,
         &member,
+        None,
     )
     .await?;
 
@@ -348,6 +349,8 @@ async fn default_configuration() -> Result<Configuration> {
         no_direct_authentication: true,
         no_token_enrollment: true,
         okta: None,
+        attributes_export: None,
+        storage_maintenance_interval: None,
     };
 
     // Hack to create Authority Identity using the same vault and storage
@@ -418,6 +421,7 @@ async fn setup(
             &configuration.identifier,
             &MultiAddr::try_from("/secure/api")?,
             &admin_id,
+            None,
         )
         .await?;
 
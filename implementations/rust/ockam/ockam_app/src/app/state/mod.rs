@@ -66,8 +66,8 @@ impl Default for AppState {
 impl AppState {
     /// Create a new AppState
     pub fn new() -> AppState {
-        let cli_state = CliState::initialize().unwrap_or_else(|_| {
-            CliState::backup_and_reset().expect(
+        let cli_state = CliState::initialize(None).unwrap_or_else(|_| {
+            CliState::backup_and_reset(None).expect(
                 "Failed to initialize CliState. Try to manually remove the '~/.ockam' directory",
             )
         });
@@ -65,7 +65,11 @@ async fn enroll_with_token<R: Runtime>(app: &AppHandle<R>, app_state: &AppState)
     system_tray_on_update_with_enroll_status(app, "Waiting for token...")?;
 
     // get an OIDC token
-    let oidc_service = OidcService::default();
+    let cli_state = app_state.state().await;
+    let oidc_service = OidcService::default_with_network_config(
+        cli_state.tls_config()?,
+        cli_state.proxy_config()?,
+    );
     let token = oidc_service.get_token_with_pkce().await?;
 
     // retrieve the user information
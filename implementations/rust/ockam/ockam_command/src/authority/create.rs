@@ -1,4 +1,5 @@
 use crate::node::util::run_ockam;
+use crate::util::duration::duration_parser;
 use crate::util::{embedded_node_that_is_not_stopped, exitcode};
 use crate::util::{local_cmd, node_rpc};
 use crate::{docs, identity, CommandGlobalOpts, Result};
@@ -7,6 +8,7 @@ use miette::Context as _;
 use miette::{miette, IntoDiagnostic};
 use ockam::identity::{AttributesEntry, Identifier};
 use ockam::Context;
+use ockam_api::attributes_export::AttributesExportConfig;
 use ockam_api::authority_node;
 use ockam_api::authority_node::{OktaConfiguration, TrustedIdentity};
 use ockam_api::bootstrapped_identities_store::PreTrustedIdentities;
@@ -19,6 +21,7 @@ use ockam_core::compat::fmt;
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::debug;
 
 const LONG_ABOUT: &str = include_str!("./static/create/long_about.txt");
@@ -88,10 +91,19 @@ pub struct CreateCommand {
     #[arg(long, value_name = "ATTRIBUTE_NAMES", default_value = None)]
     attributes: Option<Vec<String>>,
 
+    /// Export the changes made to the members attributes to a file, a webhook or a Kafka topic,
+    /// as configured in a JSON file
+    #[arg(long, value_name = "FILE")]
+    attributes_export: Option<PathBuf>,
+
     /// Run the node in foreground.
     #[arg(long, short, value_name = "BOOL", default_value_t = false)]
     foreground: bool,
 
+    /// Interval between two maintenances of the members database. Use `0` to disable it
+    #[arg(long, value_name = "DURATION", default_value = "6h", value_parser = duration_parser)]
+    storage_maintenance_interval: Duration,
+
     /// Vault that authority will use
     #[arg(long = "vault", value_name = "VAULT_NAME")]
     vault: Option<String>,
@@ -176,6 +188,14 @@ async fn spawn_background_node(
         });
     }
 
+    if let Some(attributes_export) = &cmd.attributes_export {
+        args.push("--attributes-export".to_string());
+        args.push(attributes_export.to_string_lossy().to_string());
+    }
+
+    args.push("--storage-maintenance-interval".to_string());
+    args.push(format!("{}s", cmd.storage_maintenance_interval.as_secs()));
+
     if let Some(vault) = &cmd.vault {
         args.push("--vault".to_string());
         args.push(vault.clone());
@@ -329,6 +349,12 @@ async fn start_authority_node(
     )?;
 
     let trusted_identities = cmd.trusted_identities(&identifier)?;
+    let attributes_export = cmd
+        .attributes_export
+        .as_ref()
+        .map(|path| AttributesExportConfig::read(path))
+        .transpose()
+        .into_diagnostic()?;
 
     let configuration = authority_node::Configuration {
         identifier,
@@ -342,6 +368,9 @@ async fn start_authority_node(
         no_direct_authentication: cmd.no_direct_authentication,
         no_token_enrollment: cmd.no_token_enrollment,
         okta: okta_configuration,
+        attributes_export,
+        storage_maintenance_interval: Some(cmd.storage_maintenance_interval)
+            .filter(|interval| !interval.is_zero()),
     };
     authority_node::start_node(&ctx, &configuration)
         .await
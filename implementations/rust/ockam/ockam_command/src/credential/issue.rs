@@ -29,6 +29,11 @@ pub struct IssueCommand {
     #[arg(short, long = "attribute", value_name = "ATTRIBUTE")]
     pub attributes: Vec<String>,
 
+    /// Names of the resources this credential can be presented for.
+    /// If not set, the credential can be presented for any resource
+    #[arg(long = "audience", value_name = "RESOURCE")]
+    pub audience: Vec<String>,
+
     /// Name of the Vault that will be used to issue the credential.
     #[arg(value_name = "VAULT_NAME")]
     pub vault: Option<String>,
@@ -58,6 +63,14 @@ impl IssueCommand {
     pub fn identity_identifier(&self) -> &Identifier {
         &self.identity_identifier
     }
+
+    fn audience(&self) -> Option<Vec<String>> {
+        if self.audience.is_empty() {
+            None
+        } else {
+            Some(self.audience.clone())
+        }
+    }
 }
 
 async fn run_impl(
@@ -89,11 +102,12 @@ async fn run_impl(
     let credential = identities
         .credentials()
         .credentials_creation()
-        .issue_credential(
+        .issue_credential_with_audience(
             &issuer,
             cmd.identity_identifier(),
             attributes_builder.build(),
             MAX_CREDENTIAL_VALIDITY,
+            cmd.audience(),
         )
         .await
         .into_diagnostic()?;
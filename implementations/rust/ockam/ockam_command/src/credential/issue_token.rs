@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::identity::Identifier;
+use ockam::Context;
+use ockam_api::nodes::models::credentials::{BearerTokenResponse, IssueBearerTokenRequest};
+use ockam_api::nodes::BackgroundNode;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::duration::duration_parser;
+use crate::util::parsers::identity_identifier_parser;
+use crate::util::{api, node_rpc};
+use crate::{fmt_log, fmt_ok, CommandGlobalOpts};
+
+/// Issue a short-lived bearer token for an identity, signed by a node.
+///
+/// The token attests the identity and the attributes of a client which can't establish a secure
+/// channel, for example a REST client. The client sends it in the `Authorization: Bearer <token>`
+/// header of its HTTP requests, and the services of the node check it with the same policies as
+/// the messages received over a secure channel. A token is valid for at most one hour
+#[derive(Clone, Debug, Args)]
+pub struct IssueTokenCommand {
+    #[command(flatten)]
+    pub node_opts: NodeOpts,
+
+    /// Identifier of the identity the token is issued for
+    #[arg(long = "for", value_name = "IDENTIFIER", value_parser = identity_identifier_parser)]
+    pub identity_identifier: Identifier,
+
+    /// Change history of the identity, hex encoded, if it is not known by the node yet
+    #[arg(long = "identity", value_name = "IDENTITY")]
+    pub identity: Option<String>,
+
+    /// Attributes in `key=value` format attested by the token
+    #[arg(short, long = "attribute", value_name = "ATTRIBUTE")]
+    pub attributes: Vec<String>,
+
+    /// Validity of the token
+    #[arg(long, value_name = "DURATION", default_value = "15m", value_parser = duration_parser)]
+    pub ttl: Duration,
+}
+
+impl IssueTokenCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self));
+    }
+
+    fn attributes(&self) -> miette::Result<BTreeMap<String, String>> {
+        let mut attributes = BTreeMap::new();
+        for attr in &self.attributes {
+            let (key, value) = attr
+                .split_once('=')
+                .ok_or(miette!("attributes must be in the `key=value` format"))?;
+            attributes.insert(key.to_string(), value.to_string());
+        }
+        Ok(attributes)
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, IssueTokenCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let request = IssueBearerTokenRequest::new(cmd.identity_identifier.clone(), cmd.attributes()?)
+        .with_subject_identity(cmd.identity.clone())
+        .with_ttl_secs(Some(cmd.ttl.as_secs()));
+    let response: BearerTokenResponse = node.ask(&ctx, api::issue_bearer_token(request)).await?;
+
+    let subject = response
+        .subject
+        .to_string()
+        .color(OckamColor::PrimaryResource.color());
+    opts.terminal
+        .stdout()
+        .plain(format!(
+            "{}\n{}\n{}",
+            fmt_ok!("Issued a bearer token for {subject}"),
+            fmt_log!("Send it in the `Authorization: Bearer <token>` header:"),
+            response.token
+        ))
+        .machine(&response.token)
+        .json(serde_json::to_value(&response).into_diagnostic()?)
+        .write_line()?;
+    Ok(())
+}
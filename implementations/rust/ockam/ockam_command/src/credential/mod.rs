@@ -1,5 +1,6 @@
 pub(crate) mod get;
 pub(crate) mod issue;
+pub(crate) mod issue_token;
 pub(crate) mod list;
 pub(crate) mod present;
 pub(crate) mod show;
@@ -9,6 +10,7 @@ pub(crate) mod verify;
 use colorful::Colorful;
 pub(crate) use get::GetCommand;
 pub(crate) use issue::IssueCommand;
+pub(crate) use issue_token::IssueTokenCommand;
 pub(crate) use list::ListCommand;
 use ockam::identity::{Identifier, Identities, Identity};
 use ockam_api::cli_state::{CredentialState, StateItemTrait};
@@ -38,6 +40,7 @@ pub enum CredentialSubcommand {
     #[command(display_order = 900)]
     Get(GetCommand),
     Issue(IssueCommand),
+    IssueToken(IssueTokenCommand),
     List(ListCommand),
     Present(PresentCommand),
     Show(ShowCommand),
@@ -50,6 +53,7 @@ impl CredentialCommand {
         match self.subcommand {
             CredentialSubcommand::Get(c) => c.run(options),
             CredentialSubcommand::Issue(c) => c.run(options),
+            CredentialSubcommand::IssueToken(c) => c.run(options),
             CredentialSubcommand::List(c) => c.run(options),
             CredentialSubcommand::Present(c) => c.run(options),
             CredentialSubcommand::Show(c) => c.run(options),
@@ -1,3 +1,4 @@
+use std::path::PathBuf;
 use std::process;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
@@ -18,6 +19,7 @@ use ockam_api::cloud::enroll::auth0::*;
 use ockam_api::cloud::project::{Project, Projects};
 use ockam_api::cloud::space::{Space, Spaces};
 use ockam_api::cloud::Controller;
+use ockam_api::enroll::dev_oidc_provider::{DevOidcConfig, DevOidcServer};
 use ockam_api::enroll::enrollment::{EnrollStatus, Enrollment};
 use ockam_api::enroll::oidc_service::OidcService;
 use ockam_api::nodes::InMemoryNode;
@@ -48,6 +50,17 @@ pub struct EnrollCommand {
     /// Use PKCE authorization flow
     #[arg(long)]
     pub authorization_code_flow: bool,
+
+    /// Authenticate with a local OIDC provider, which approves the authentication without
+    /// user interaction. The Orchestrator is the one set with the OCKAM_CONTROLLER_ADDR and
+    /// OCKAM_CONTROLLER_IDENTITY_ID environment variables. This is meant for development and tests
+    #[arg(long, hide = true)]
+    pub dev: bool,
+
+    /// Claims returned by the local OIDC provider in `--dev` mode, as a JSON file
+    /// with the fields `sub`, `nickname`, `name`, `picture`, `updated_at`, `email`, `email_verified`
+    #[arg(long, value_name = "FILE", requires = "dev", hide = true)]
+    pub dev_claims: Option<PathBuf>,
 }
 
 impl EnrollCommand {
@@ -55,6 +68,18 @@ impl EnrollCommand {
         initialize_identity_if_default(&opts, &self.identity);
         node_rpc(rpc, (opts, self));
     }
+
+    fn dev_oidc_config(&self) -> miette::Result<DevOidcConfig> {
+        let config = DevOidcConfig::default();
+        match &self.dev_claims {
+            Some(path) => {
+                let contents = std::fs::read_to_string(path).into_diagnostic()?;
+                let claims: UserInfo = serde_json::from_str(&contents).into_diagnostic()?;
+                Ok(config.with_claims(claims))
+            }
+            None => Ok(config),
+        }
+    }
 }
 
 async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, EnrollCommand)) -> miette::Result<()> {
@@ -96,7 +121,7 @@ fn ctrlc_handler(opts: CommandGlobalOpts) {
 async fn run_impl(
     ctx: &Context,
     opts: CommandGlobalOpts,
-    _cmd: EnrollCommand,
+    cmd: EnrollCommand,
 ) -> miette::Result<()> {
     opts.terminal.write_line(&fmt_log!(
         "Enrolling your default Ockam identity with Ockam Orchestrator...\n"
@@ -105,9 +130,23 @@ async fn run_impl(
     ctrlc_handler(opts.clone());
     display_parse_logs(&opts);
 
-    let oidc_service = OidcService::default();
-    let token = if _cmd.authorization_code_flow {
+    // the local OIDC server must be kept running until the user info has been retrieved
+    let dev_oidc_server = if cmd.dev {
+        Some(DevOidcServer::start(cmd.dev_oidc_config()?).into_diagnostic()?)
+    } else {
+        None
+    };
+    let oidc_service = match &dev_oidc_server {
+        Some(server) => OidcService::new(Arc::new(server.provider())),
+        None => OidcService::default_with_network_config(
+            opts.state.tls_config()?,
+            opts.state.proxy_config()?,
+        ),
+    };
+    let token = if cmd.authorization_code_flow {
         oidc_service.get_token_with_pkce().await.into_diagnostic()?
+    } else if cmd.dev {
+        oidc_service.get_token(&opts).await?
     } else {
         oidc_service.get_token_interactively(&opts).await?
     };
@@ -246,7 +285,7 @@ async fn default_space(
         for space in &available_spaces {
             opts.state
                 .spaces
-                .overwrite(&space.name, SpaceConfig::from(space))?;
+                .refresh(&space.name, SpaceConfig::from(space))?;
         }
 
         let space = available_spaces
@@ -265,7 +304,7 @@ async fn default_space(
     };
     opts.state
         .spaces
-        .overwrite(&default_space.name, SpaceConfig::from(&default_space))?;
+        .refresh(&default_space.name, SpaceConfig::from(&default_space))?;
     opts.terminal.write_line(&fmt_ok!(
         "Marked this space as your default space, on this machine.\n"
     ))?;
@@ -349,7 +388,7 @@ async fn default_project(
         for project in &available_projects {
             opts.state
                 .projects
-                .overwrite(&project.name, project.clone())?;
+                .refresh(&project.name, project.clone())?;
         }
         let p = match available_projects.iter().find(|ns| ns.name == "default") {
             None => available_projects
@@ -375,7 +414,7 @@ async fn default_project(
 
     opts.state
         .projects
-        .overwrite(&project.name, project.clone())?;
+        .refresh(&project.name, project.clone())?;
     opts.state
         .trust_contexts
         .overwrite(&project.name, project.clone().try_into()?)?;
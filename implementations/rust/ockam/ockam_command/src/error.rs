@@ -1,6 +1,9 @@
 use colorful::Colorful;
 use miette::miette;
 use miette::Diagnostic;
+use ockam_api::cli_state::CliStateError;
+use ockam_api::error::ApiError;
+use ockam_core::errcode::Category;
 use std::fmt::Debug;
 
 use crate::{exitcode, fmt_log, ExitCode, Version};
@@ -69,6 +72,11 @@ pub enum Error {
         resource_name: String,
     },
     // ==== End 5xx Errors ====
+
+    // Errors of the Ockam libraries, with their code in the errors catalogue
+    #[diagnostic(transparent)]
+    #[error(transparent)]
+    Catalogued(ApiError),
 }
 
 impl Error {
@@ -92,6 +100,33 @@ impl Error {
             Error::Conflict { .. } => exitcode::SOFTWARE,
             Error::InternalError { exit_code, .. } => *exit_code,
             Error::Unavailable { .. } => exitcode::UNAVAILABLE,
+            Error::Catalogued(e) => match e.catalogue_entry().category {
+                Category::Usage => exitcode::USAGE,
+                Category::Unauthorized => exitcode::NOPERM,
+                Category::Unavailable | Category::Timeout => exitcode::UNAVAILABLE,
+                Category::NotFound | Category::Conflict | Category::Internal => exitcode::SOFTWARE,
+            },
+        }
+    }
+}
+
+impl From<ockam::Error> for Error {
+    fn from(e: ockam::Error) -> Self {
+        Error::Catalogued(e.into())
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        Error::Catalogued(e)
+    }
+}
+
+impl From<CliStateError> for Error {
+    fn from(e: CliStateError) -> Self {
+        match e {
+            CliStateError::Ockam(e) => e.into(),
+            _ => Error::new(exitcode::SOFTWARE, miette!(e.to_string())),
         }
     }
 }
@@ -174,9 +209,6 @@ gen_from_impl!(serde_json::Error, DATAERR);
 gen_from_impl!(serde_yaml::Error, DATAERR);
 gen_from_impl!(minicbor::encode::Error<std::convert::Infallible>, DATAERR);
 gen_from_impl!(minicbor::decode::Error, DATAERR);
-gen_from_impl!(ockam::Error, SOFTWARE);
-gen_from_impl!(ockam_api::cli_state::CliStateError, SOFTWARE);
-gen_from_impl!(ockam_api::error::ApiError, SOFTWARE);
 gen_from_impl!(ockam_multiaddr::Error, SOFTWARE);
 gen_from_impl!(miette::ErrReport, SOFTWARE);
 gen_from_impl!(time::error::Parse, DATAERR);
@@ -3,12 +3,13 @@ use crate::util::node_rpc;
 use crate::{docs, fmt_log, fmt_ok, CommandGlobalOpts};
 use clap::Args;
 use colorful::Colorful;
-use miette::miette;
+use miette::{miette, IntoDiagnostic};
 use ockam::identity::Identifier;
 use ockam::Context;
 use ockam_api::cli_state::random_name;
 use ockam_api::cli_state::traits::{StateDirTrait, StateItemTrait};
-use ockam_vault::{HandleToSecret, SigningSecretKeyHandle};
+use ockam_vault::{HandleToSecret, SigningSecret, SigningSecretKeyHandle};
+use std::path::PathBuf;
 use tokio::sync::Mutex;
 use tokio::try_join;
 
@@ -32,6 +33,10 @@ pub struct CreateCommand {
     /// Key ID to use for the identity creation
     #[arg(short, long)]
     key_id: Option<String>,
+
+    /// PEM file containing an Ed25519 or a P-256 private key to use as the identity root key
+    #[arg(long, value_name = "PEM_FILE", conflicts_with = "key_id")]
+    key_file: Option<PathBuf>,
 }
 
 impl CreateCommand {
@@ -40,6 +45,7 @@ impl CreateCommand {
             name,
             vault,
             key_id,
+            key_file: None,
         }
     }
 
@@ -107,7 +113,20 @@ impl CreateCommand {
                             .await?)
                     }
                 }
-                None => Ok(identities_creation.create_identity().await?),
+                None => match &self.key_file {
+                    Some(key_file) => {
+                        let pem = std::fs::read_to_string(key_file).into_diagnostic()?;
+                        let secret = SigningSecret::from_pem(&pem).into_diagnostic()?;
+                        let handle = vault_state.import_signing_key(secret).await?;
+
+                        Ok(identities_creation
+                            .identity_builder()
+                            .with_existing_key(handle)
+                            .build()
+                            .await?)
+                    }
+                    None => Ok(identities_creation.create_identity().await?),
+                },
             }?;
 
             opts.state
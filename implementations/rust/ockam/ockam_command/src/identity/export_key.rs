@@ -0,0 +1,78 @@
+use crate::identity::{get_identity_name, initialize_identity_if_default};
+use crate::util::node_rpc;
+use crate::{docs, CommandGlobalOpts};
+use clap::Args;
+use miette::IntoDiagnostic;
+use ockam::identity::{Identity, Vault};
+use ockam_api::cli_state::traits::{StateDirTrait, StateItemTrait};
+use ockam_node::Context;
+use serde_json::json;
+
+const LONG_ABOUT: &str = include_str!("./static/export_key/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/export_key/after_long_help.txt");
+
+/// Export the root private key of an identity as a PKCS#8 PEM document
+#[derive(Clone, Debug, Args)]
+#[command(
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct ExportKeyCommand {
+    #[arg()]
+    name: Option<String>,
+
+    /// Vault name storing the identity key
+    #[arg(long, value_name = "VAULT_NAME")]
+    vault: Option<String>,
+}
+
+impl ExportKeyCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.name);
+        node_rpc(Self::run_impl, (opts, self))
+    }
+
+    async fn run_impl(
+        _ctx: Context,
+        (opts, cmd): (CommandGlobalOpts, ExportKeyCommand),
+    ) -> miette::Result<()> {
+        let name = get_identity_name(&opts.state, &cmd.name);
+        let identifier = opts.state.identities.get(&name)?.config().identifier();
+        let change_history = opts
+            .state
+            .identities
+            .identities_repository()
+            .await?
+            .get_identity(&identifier)
+            .await
+            .into_diagnostic()?;
+        let identity = Identity::import_from_change_history(
+            Some(&identifier),
+            change_history,
+            Vault::create_verifying_vault(),
+        )
+        .await
+        .into_diagnostic()?;
+        let public_key = identity.get_latest_public_key().into_diagnostic()?;
+
+        let vault_state = match &cmd.vault {
+            Some(vault) => opts.state.vaults.get(vault)?,
+            None => opts.state.vaults.default()?,
+        };
+        let pem = vault_state
+            .export_signing_key(&public_key)
+            .await?
+            .to_pkcs8_pem()
+            .into_diagnostic()?;
+
+        opts.terminal
+            .stdout()
+            .plain(pem.trim_end())
+            .machine(pem.trim_end())
+            .json(json!({ "identifier": &identifier, "pem": &pem }))
+            .write_line()?;
+        Ok(())
+    }
+}
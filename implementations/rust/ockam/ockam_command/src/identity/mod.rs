@@ -1,14 +1,18 @@
 mod create;
 mod default;
 mod delete;
+mod export_key;
 mod list;
 mod show;
+mod verify;
 
 use colorful::Colorful;
 pub use create::CreateCommand;
 pub(crate) use delete::DeleteCommand;
+pub(crate) use export_key::ExportKeyCommand;
 pub(crate) use list::ListCommand;
 pub(crate) use show::ShowCommand;
+pub(crate) use verify::VerifyCommand;
 
 use crate::identity::default::DefaultCommand;
 use crate::terminal::OckamColor;
@@ -38,6 +42,8 @@ pub enum IdentitySubcommand {
     List(ListCommand),
     Default(DefaultCommand),
     Delete(DeleteCommand),
+    ExportKey(ExportKeyCommand),
+    Verify(VerifyCommand),
 }
 
 impl IdentityCommand {
@@ -48,6 +54,8 @@ impl IdentityCommand {
             IdentitySubcommand::List(c) => c.run(options),
             IdentitySubcommand::Delete(c) => c.run(options),
             IdentitySubcommand::Default(c) => c.run(options),
+            IdentitySubcommand::ExportKey(c) => c.run(options),
+            IdentitySubcommand::Verify(c) => c.run(options),
         }
     }
 }
@@ -0,0 +1,172 @@
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::Context;
+use ockam_api::cli_state::traits::{StateDirTrait, StateItemTrait};
+use ockam_api::identity_verification::{initiate_verification, respond_to_verification};
+use ockam_api::DefaultAddress;
+use ockam_core::route;
+use ockam_transport_tcp::{TcpConnectionOptions, TcpListenerOptions, TcpTransport};
+
+use crate::identity::{get_identity_name, initialize_identity_if_default};
+use crate::terminal::{ConfirmResult, OckamColor};
+use crate::util::duration::duration_parser;
+use crate::util::node_rpc;
+use crate::vault::default_vault_name;
+use crate::{fmt_log, fmt_ok, CommandGlobalOpts};
+
+/// Verify the identity of a peer by comparing a 6-digit code out-of-band, and trust it.
+///
+/// One user waits for the verification with `--listen` and the other one connects with `--to`.
+/// Both see the same code if nobody tampered with the connection. The verified identity is
+/// added to the trusted peers, which can be given to `ockam node create --trusted-identities-file`
+#[derive(Clone, Debug, Args)]
+pub struct VerifyCommand {
+    /// Name of the local identity
+    #[arg(long = "identity", value_name = "IDENTITY_NAME")]
+    identity: Option<String>,
+
+    /// Address on which to wait for the peer, for example 0.0.0.0:4100
+    #[arg(
+        long,
+        value_name = "SOCKET_ADDRESS",
+        conflicts_with = "to",
+        required_unless_present = "to"
+    )]
+    listen: Option<String>,
+
+    /// Address of the peer waiting for the verification
+    #[arg(long, value_name = "SOCKET_ADDRESS")]
+    to: Option<String>,
+
+    /// Name given to the peer in the trusted peers
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Time to wait for the peer
+    #[arg(long, value_name = "DURATION", default_value = "2m", value_parser = duration_parser)]
+    timeout: Duration,
+}
+
+impl VerifyCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.identity);
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, VerifyCommand)) -> miette::Result<()> {
+    let identity_name = get_identity_name(&opts.state, &cmd.identity);
+    let identifier = opts
+        .state
+        .identities
+        .get(&identity_name)?
+        .config()
+        .identifier();
+    let vault = opts
+        .state
+        .vaults
+        .get(&default_vault_name(&opts.state))?
+        .get()
+        .await?;
+    let identities = opts.state.get_identities(vault).await?;
+    let identity = identities
+        .export_identity(&identifier)
+        .await
+        .into_diagnostic()?;
+
+    // the identities are exchanged over a plain TCP connection, the codes authenticate them
+    let tcp = TcpTransport::create(&ctx).await.into_diagnostic()?;
+    let peer = match (&cmd.listen, &cmd.to) {
+        (Some(listen), _) => {
+            let listener = tcp
+                .listen(listen, TcpListenerOptions::new())
+                .await
+                .into_diagnostic()?;
+            opts.terminal.write_line(&fmt_log!(
+                "Waiting for the peer to connect to {}...",
+                listener
+                    .socket_string()
+                    .color(OckamColor::PrimaryResource.color())
+            ))?;
+            respond_to_verification(
+                &ctx,
+                &identities,
+                identity,
+                listener.flow_control_id(),
+                cmd.timeout,
+            )
+            .await
+        }
+        (None, Some(to)) => {
+            let connection = tcp
+                .connect(to, TcpConnectionOptions::new())
+                .await
+                .into_diagnostic()?;
+            initiate_verification(
+                &ctx,
+                &identities,
+                identity,
+                route![
+                    connection.sender_address().clone(),
+                    DefaultAddress::IDENTITY_VERIFICATION
+                ],
+                cmd.timeout,
+            )
+            .await
+        }
+        (None, None) => return Err(miette!("Please use --listen or --to")),
+    }
+    .into_diagnostic()?;
+
+    opts.terminal.write_line(&fmt_log!(
+        "The identity of the peer is {}",
+        peer.identifier
+            .to_string()
+            .color(OckamColor::PrimaryResource.color())
+    ))?;
+    opts.terminal.write_line(&fmt_log!(
+        "Your verification code is {}\n",
+        peer.code
+            .to_string()
+            .color(OckamColor::PrimaryResource.color())
+    ))?;
+    match opts
+        .terminal
+        .confirm("Does your peer see the same code?")?
+    {
+        ConfirmResult::Yes => {}
+        ConfirmResult::No => {
+            return Err(miette!(
+                "The codes don't match, the connection might have been intercepted. The peer is not trusted"
+            ))
+        }
+        ConfirmResult::NonTTY => {
+            return Err(miette!(
+                "The verification codes must be compared interactively"
+            ))
+        }
+    }
+    opts.state
+        .add_trusted_peer(&peer.identifier, cmd.name.as_deref(), "sas")?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "The identity {} is now trusted",
+            peer.identifier
+                .to_string()
+                .color(OckamColor::PrimaryResource.color())
+        ))
+        .machine(peer.identifier.to_string())
+        .json(serde_json::json!({
+            "identifier": peer.identifier.to_string(),
+            "name": cmd.name,
+            "trusted_peers": opts.state.trusted_peers_path(),
+        }))
+        .write_line()?;
+    Ok(())
+}
@@ -240,12 +240,12 @@ pub struct CommandGlobalOpts {
 
 impl CommandGlobalOpts {
     pub fn new(global_args: GlobalArgs) -> Self {
-        let state = CliState::initialize().unwrap_or_else(|_| {
-            let state = CliState::backup_and_reset().expect(
+        let state = CliState::initialize(None).unwrap_or_else(|_| {
+            let state = CliState::backup_and_reset(None).expect(
                 "Failed to initialize CliState. Try to manually remove the '~/.ockam' directory",
             );
             let dir = &state.dir;
-            let backup_dir = CliState::backup_default_dir().unwrap();
+            let backup_dir = CliState::backup_profile_dir(&state.profile).unwrap();
             eprintln!(
                 "The {dir:?} directory has been reset and has been backed up to {backup_dir:?}"
             );
@@ -1,5 +1,6 @@
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::{path::PathBuf, process, str::FromStr};
+use std::{path::PathBuf, str::FromStr};
 
 use clap::Args;
 use colorful::Colorful;
@@ -10,14 +11,29 @@ use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 use tokio::try_join;
 
+use ockam::identity::{Identifier, QuotaLimits, Timeouts};
 use ockam::{Address, AsyncTryClone, TcpListenerOptions};
 use ockam::{Context, TcpTransport};
 use ockam_api::cli_state::traits::{StateDirTrait, StateItemTrait};
-use ockam_api::cli_state::{add_project_info_to_node_state, init_node_state, random_name};
+use ockam_api::cli_state::{
+    add_project_info_to_node_state, init_node_state, random_name, NodeProcessConfig, ProcessLimit,
+};
+use ockam_api::config_updates::ConfigUpdateService;
+use ockam_api::fleet::{HeartbeatConfig, HeartbeatReporter};
+use ockam_api::metrics_exporter::{MetricsExporter, MetricsExporterConfig};
+use ockam_api::node_hooks::{NodeHookContext, NodeHookStage, NodeHooks, NodeHooksConfig};
 use ockam_api::nodes::models::transport::CreateTransportJson;
+use ockam_api::nodes::resource_usage::DEFAULT_RESOURCE_USAGE_INTERVAL;
 use ockam_api::nodes::service::NodeManagerTrustOptions;
 use ockam_api::nodes::BackgroundNode;
 use ockam_api::nodes::InMemoryNode;
+use ockam_api::notifier::NotifierConfig;
+use ockam_api::outlet_resolver::OutletResolverConfig;
+use ockam_api::ping::{HealthCheckConfig, HealthCheckTarget, HealthChecker};
+use ockam_api::portal_dns::{DnsServiceName, PortalDns, PortalDnsRecord};
+use ockam_api::portal_events::PortalEventsSink;
+use ockam_api::resource_profile::ResourceProfile;
+use ockam_api::storage_maintenance::DEFAULT_STORAGE_MAINTENANCE_INTERVAL;
 use ockam_api::{
     bootstrapped_identities_store::PreTrustedIdentities,
     nodes::models::transport::{TransportMode, TransportType},
@@ -28,14 +44,21 @@ use ockam_api::{
 };
 use ockam_core::api::{Request, ResponseHeader, Status};
 use ockam_core::{route, LOCAL};
+use ockam_multiaddr::MultiAddr;
+use ockam_transport_tcp::OutletResolver;
 
-use crate::node::util::{spawn_node, NodeManagerDefaults};
+use crate::node::util::{spawn_node, NodeManagerDefaults, SpawnNodeOptions};
 use crate::secure_channel::listener::create as secure_channel_listener;
 use crate::service::config::Config;
 use crate::terminal::OckamColor;
 use crate::util::api::TrustContextOpts;
+use crate::util::duration::duration_parser;
+use crate::util::parsers::{
+    env_var_parser, identity_route_parser, label_parser, memory_limit_parser, socket_addr_parser,
+    timeout_parser,
+};
 use crate::util::{api, parse_node_name};
-use crate::util::{embedded_node_that_is_not_stopped, exitcode};
+use crate::util::{embedded_node_with_builder_that_is_not_stopped, exitcode};
 use crate::util::{local_cmd, node_rpc};
 use crate::{docs, shutdown, CommandGlobalOpts, Result};
 use crate::{fmt_log, fmt_ok};
@@ -99,7 +122,7 @@ pub struct CreateCommand {
 
     /// Name of the Identity that the node will use
     #[arg(long = "identity", value_name = "IDENTITY_NAME")]
-    identity: Option<String>,
+    pub(crate) identity: Option<String>,
 
     #[arg(long)]
     pub authority_identity: Option<String>,
@@ -109,6 +132,154 @@ pub struct CreateCommand {
 
     #[command(flatten)]
     pub trust_context_opts: TrustContextOpts,
+
+    /// Maximum number of secure channels that each identity can open concurrently on this node
+    #[arg(long, value_name = "COUNT")]
+    pub max_secure_channels_per_identity: Option<u32>,
+
+    /// Maximum number of portal connections that each identity can open concurrently on this node
+    #[arg(long, value_name = "COUNT")]
+    pub max_portal_connections_per_identity: Option<u32>,
+
+    /// Keep a local copy of the attributes of all the project members, synchronized from the
+    /// project authority, so that they are still available when the authority can't be reached.
+    /// The node must be enrolled with the attribute `ockam-replica=true`
+    #[arg(long)]
+    pub replicate_members: bool,
+
+    /// Write an event for each portal connection opened, closed or denied on this node, as JSON
+    /// lines. The destination is a file path, `file:<path>` or `unix:<socket path>`
+    #[arg(long, value_name = "SINK")]
+    pub portal_events: Option<PortalEventsSink>,
+
+    /// Answer the DNS queries sent to a UDP address, for example `127.0.0.1:5353`, with the
+    /// addresses of the inlets given a name with `ockam tcp-inlet create --dns-name`
+    #[arg(long, value_name = "SOCKET_ADDRESS", value_parser = socket_addr_parser)]
+    pub dns: Option<SocketAddr>,
+
+    /// Resolve a name to an inlet connected to a service published in the registry of the node
+    /// project, for example `payments.internal.ockam=payments-db:5432`.
+    /// The inlet is created the first time the name is resolved
+    #[arg(long, value_name = "NAME=SERVICE:PORT", requires = "dns")]
+    pub dns_service: Vec<DnsServiceName>,
+
+    /// Tune the number of threads, the mailbox capacities and the portal buffer sizes of the
+    /// node with a preset: `edge` for constrained devices, `default` or `high-throughput`
+    #[arg(long, value_name = "PROFILE")]
+    pub resource_profile: Option<ResourceProfile>,
+
+    /// Limit the memory used by a subsystem of the node, for example `mailboxes=64M` or
+    /// `portal_buffers=32M`. The allocations exceeding the limit fail instead of growing
+    /// the memory of the node
+    #[arg(long, value_name = "SUBSYSTEM=BYTES", value_parser = memory_limit_parser)]
+    pub memory_limit: Vec<(String, usize)>,
+
+    /// Keep the secure channels, relays, outlets and inlets created on this node, and create
+    /// them again when the node is restarted
+    #[arg(long)]
+    pub warm_start: bool,
+
+    /// Post the critical events of the node, like a credential expiring soon or a relay down
+    /// for some time, to the webhooks configured in a JSON file
+    #[arg(long, value_name = "FILE")]
+    pub notifications: Option<PathBuf>,
+
+    /// Push the metrics of the node, like the throughput of its outlets and its number of
+    /// secure channels, to InfluxDB or AWS Timestream as configured in a JSON file
+    #[arg(long, value_name = "FILE")]
+    pub metrics_exporter: Option<PathBuf>,
+
+    /// Append the requests handled by the node, and their responses, to a file which can be
+    /// replayed with `ockam node replay`. The requests can contain secrets, like enrollment tickets
+    #[arg(long, value_name = "FILE")]
+    pub record_api: Option<PathBuf>,
+
+    /// Replace a default timeout of the node, for example `handshake=5m` on a high-latency link.
+    /// The kinds of timeouts are `handshake`, `credential-exchange`, `message-send` and
+    /// `management-request`. A timeout given to a specific command still takes precedence
+    #[arg(long = "timeout", value_name = "KIND=DURATION", value_parser = timeout_parser)]
+    pub timeouts: Vec<(String, Duration)>,
+
+    /// Log the operations on the databases of the node taking longer than this duration, for
+    /// example `100ms`, with the database and the operation. The default is 500ms
+    #[arg(long, value_name = "DURATION", value_parser = duration_parser)]
+    pub slow_storage_threshold: Option<Duration>,
+
+    /// Interval between two maintenances of the local databases of the node. The maintenance
+    /// flushes the databases and reports their free space. Use `0` to disable it
+    #[arg(long, value_name = "DURATION", default_value = "6h", value_parser = duration_parser)]
+    pub storage_maintenance_interval: Duration,
+
+    /// Interval between two samples of the CPU, memory and open files used by the node process.
+    /// The samples are displayed by `ockam node show`. Use `0` to disable the sampling
+    #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = duration_parser)]
+    pub resource_usage_interval: Duration,
+
+    /// Keep the connections of the inlets open when their route to the outlet is lost for a
+    /// short time, and resume them once the route is re-established. The outlets of the node
+    /// also accept the resumption of their connections
+    #[arg(long)]
+    pub resume_portal_sessions: bool,
+
+    /// Resolve the outlet targets which are hostnames as configured in a JSON file: the bounds
+    /// of the time during which their addresses are cached, static addresses for some hosts,
+    /// and a DNS over HTTPS server
+    #[arg(long, value_name = "FILE")]
+    pub outlet_resolver: Option<PathBuf>,
+
+    /// Run the commands configured in a JSON file before the node starts, once it is started,
+    /// and before it stops, for example to register the node with a service discovery system
+    #[arg(long, value_name = "FILE")]
+    pub hooks: Option<PathBuf>,
+
+    /// Accept the configuration updates signed by this identity, pushed with
+    /// `ockam node push-config`. The outlets and policies of an update are applied to the node
+    #[arg(long, value_name = "IDENTIFIER")]
+    pub control_identity: Option<Identifier>,
+
+    /// Keep the heartbeats of the nodes reporting to this node with `--report-to`. They can be
+    /// listed with `ockam node fleet`
+    #[arg(long)]
+    pub fleet: bool,
+
+    /// Report the version, uptime, memory usage and portals of this node to a control node
+    /// created with `--fleet`, given by its identity and the route to its secure channel listener
+    #[arg(long, value_name = "IDENTIFIER@ROUTE", value_parser = identity_route_parser)]
+    pub report_to: Option<(Identifier, MultiAddr)>,
+
+    /// Interval between two heartbeats sent with `--report-to`
+    #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = duration_parser)]
+    pub heartbeat_interval: Duration,
+
+    /// Add a label to the heartbeats sent with `--report-to`. The labels select the nodes
+    /// receiving a configuration update with `ockam node rollout start`
+    #[arg(long = "label", value_name = "KEY=VALUE", value_parser = label_parser)]
+    pub labels: Vec<(String, String)>,
+
+    /// Probe another node on an interval, given by its identity and the route to its secure
+    /// channel listener. The results are listed with `ockam node ping --history`
+    #[arg(long = "health-check", value_name = "IDENTIFIER@ROUTE", value_parser = identity_route_parser)]
+    pub health_checks: Vec<(Identifier, MultiAddr)>,
+
+    /// Interval between two probes of the nodes given with `--health-check`
+    #[arg(long, value_name = "DURATION", default_value = "1m", value_parser = duration_parser)]
+    pub health_check_interval: Duration,
+
+    /// Add an environment variable to the environment of the background node process, for
+    /// example to configure its startup hooks. It is kept when the node is restarted
+    #[arg(long = "env", value_name = "NAME=VALUE", value_parser = env_var_parser)]
+    pub env: Vec<(String, String)>,
+
+    /// Working directory of the background node process. The relative paths given to the node
+    /// are resolved from this directory
+    #[arg(long, value_name = "DIR")]
+    pub working_dir: Option<PathBuf>,
+
+    /// Limit a resource of the background node process like `ulimit`, for example
+    /// `nofile=4096:8192`. The resources are `nofile`, `nproc`, `core`, `fsize`, `stack`
+    /// and `cpu`, and a value can be `unlimited`
+    #[arg(long = "ulimit", value_name = "RESOURCE=SOFT[:HARD]")]
+    pub ulimits: Vec<ProcessLimit>,
 }
 
 impl Default for CreateCommand {
@@ -129,6 +300,35 @@ impl Default for CreateCommand {
             authority_identity: None,
             credential: None,
             trust_context_opts: node_manager_defaults.trust_context_opts,
+            max_secure_channels_per_identity: None,
+            max_portal_connections_per_identity: None,
+            replicate_members: false,
+            portal_events: None,
+            dns: None,
+            dns_service: vec![],
+            resource_profile: None,
+            memory_limit: vec![],
+            warm_start: false,
+            notifications: None,
+            metrics_exporter: None,
+            record_api: None,
+            timeouts: vec![],
+            slow_storage_threshold: None,
+            storage_maintenance_interval: DEFAULT_STORAGE_MAINTENANCE_INTERVAL,
+            resource_usage_interval: DEFAULT_RESOURCE_USAGE_INTERVAL,
+            resume_portal_sessions: false,
+            outlet_resolver: None,
+            hooks: None,
+            control_identity: None,
+            fleet: false,
+            report_to: None,
+            heartbeat_interval: Duration::from_secs(30),
+            labels: vec![],
+            health_checks: vec![],
+            health_check_interval: Duration::from_secs(60),
+            env: vec![],
+            working_dir: None,
+            ulimits: vec![],
         }
     }
 }
@@ -153,6 +353,64 @@ impl CreateCommand {
         }
     }
 
+    /// Limits applied to each identity connecting to the node
+    pub fn quota_limits(&self) -> QuotaLimits {
+        QuotaLimits {
+            max_secure_channels: self.max_secure_channels_per_identity,
+            max_portal_connections: self.max_portal_connections_per_identity,
+        }
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+            .iter()
+            .fold(
+                Timeouts::default(),
+                |timeouts, (kind, duration)| match kind.as_str() {
+                    "handshake" => timeouts.with_handshake(*duration),
+                    "credential-exchange" => timeouts.with_credential_exchange(*duration),
+                    "message-send" => timeouts.with_message_send(*duration),
+                    "management-request" => timeouts.with_management_request(*duration),
+                    _ => timeouts,
+                },
+            )
+    }
+
+    /// Interval of the maintenance of the databases, if it is enabled
+    pub fn storage_maintenance_interval(&self) -> Option<Duration> {
+        Some(self.storage_maintenance_interval).filter(|interval| !interval.is_zero())
+    }
+
+    /// Interval of the sampling of the resource usage, if it is enabled
+    pub fn resource_usage_interval(&self) -> Option<Duration> {
+        Some(self.resource_usage_interval).filter(|interval| !interval.is_zero())
+    }
+
+    /// Control node to which the heartbeats of the node are sent
+    pub fn heartbeat_config(&self) -> Option<HeartbeatConfig> {
+        self.report_to.clone().map(|(control, route)| {
+            HeartbeatConfig::new(control, route)
+                .with_interval(self.heartbeat_interval)
+                .with_labels(self.labels.iter().cloned().collect())
+        })
+    }
+
+    /// Nodes probed by the node on an interval
+    pub fn health_check_config(&self) -> Option<HealthCheckConfig> {
+        if self.health_checks.is_empty() {
+            return None;
+        }
+        let targets = self
+            .health_checks
+            .iter()
+            .map(|(identifier, route)| HealthCheckTarget {
+                identifier: identifier.clone(),
+                route: route.clone(),
+            })
+            .collect();
+        Some(HealthCheckConfig::new(targets).with_interval(self.health_check_interval))
+    }
+
     pub fn logging_to_file(&self) -> bool {
         // Background nodes will spawn a foreground node in a child process.
         // In that case, the child process will log to files.
@@ -245,7 +503,11 @@ pub(crate) async fn background_mode(
 
 // Create a new node in the foreground (i.e. in this OS process)
 fn foreground_mode(opts: CommandGlobalOpts, cmd: CreateCommand) -> miette::Result<()> {
-    embedded_node_that_is_not_stopped(run_foreground_node, (opts, cmd))?;
+    let mut builder = cmd.resource_profile.unwrap_or_default().node_builder();
+    for (subsystem, limit) in &cmd.memory_limit {
+        builder = builder.with_memory_limit(subsystem, *limit);
+    }
+    embedded_node_with_builder_that_is_not_stopped(builder, run_foreground_node, (opts, cmd))?;
     Ok(())
 }
 
@@ -289,7 +551,7 @@ async fn run_foreground_node(
         .into_diagnostic()?;
 
     let node_state = opts.state.nodes.get(&node_name)?;
-    node_state.set_pid(process::id() as i32)?;
+    node_state.set_current_process()?;
     node_state.set_setup(
         &node_state
             .config()
@@ -302,10 +564,51 @@ async fn run_foreground_node(
                     &listener.socket_address().to_string(),
                 )
                 .into_diagnostic()?,
-            ),
+            )
+            .set_resource_profile(cmd.resource_profile)
+            .set_warm_start(cmd.warm_start)
+            .set_control_identity(cmd.control_identity.clone())
+            .set_fleet_inventory(cmd.fleet)
+            .set_heartbeats(cmd.heartbeat_config())
+            .set_health_checks(cmd.health_check_config())
+            .set_quota_limits(cmd.quota_limits())
+            .set_slow_storage_threshold(cmd.slow_storage_threshold),
     )?;
+    // only the local processes which can read the node directory can use the node API
+    let api_token = node_state.create_api_token()?;
 
     let pre_trusted_identities = load_pre_trusted_identities(&cmd)?;
+    let notifier_config = cmd
+        .notifications
+        .as_ref()
+        .map(|path| NotifierConfig::read(path))
+        .transpose()
+        .into_diagnostic()?;
+    let metrics_exporter_config = cmd
+        .metrics_exporter
+        .as_ref()
+        .map(|path| MetricsExporterConfig::read(path))
+        .transpose()
+        .into_diagnostic()?;
+    let hooks = match &cmd.hooks {
+        Some(path) => NodeHooksConfig::read(path).into_diagnostic()?.hooks(),
+        None => NodeHooks::default(),
+    };
+    let hook_context = NodeHookContext {
+        node_name: node_name.clone(),
+        node_address: listener.socket_address().to_string(),
+    };
+    hooks
+        .run(NodeHookStage::PreStart, &hook_context)
+        .await
+        .into_diagnostic()?;
+
+    let outlet_resolver = match &cmd.outlet_resolver {
+        Some(path) => OutletResolverConfig::read(path)
+            .and_then(|config| config.resolver())
+            .into_diagnostic()?,
+        None => OutletResolver::new(),
+    };
 
     let node_man = InMemoryNode::new(
         &ctx,
@@ -315,7 +618,21 @@ async fn run_foreground_node(
             pre_trusted_identities,
             cmd.launch_config.is_none(),
             true,
-        ),
+        )
+        .with_quota_limits(cmd.quota_limits())
+        .with_members_replication(cmd.replicate_members)
+        .with_portal_events_sink(cmd.portal_events.clone())
+        .with_resource_profile(cmd.resource_profile.unwrap_or_default())
+        .with_warm_start(cmd.warm_start)
+        .with_notifier_config(notifier_config)
+        .with_api_recording(cmd.record_api.clone())
+        .with_api_token(Some(api_token))
+        .with_timeouts(cmd.timeouts())
+        .with_portal_session_resumption(cmd.resume_portal_sessions)
+        .with_outlet_resolver(outlet_resolver)
+        .with_fleet_inventory(cmd.fleet)
+        .with_storage_maintenance(cmd.storage_maintenance_interval())
+        .with_resource_usage(cmd.resource_usage_interval()),
         NodeManagerTransportOptions::new(
             listener.flow_control_id().clone(),
             tcp.async_try_clone().await.into_diagnostic()?,
@@ -324,7 +641,43 @@ async fn run_foreground_node(
     )
     .await
     .into_diagnostic()?;
-    let node_manager_worker = NodeManagerWorker::new(Arc::new(node_man));
+    let node_man = Arc::new(node_man);
+    if let Some(config) = metrics_exporter_config {
+        MetricsExporter::start(Arc::downgrade(&**node_man), config);
+    }
+    if let Some(dns) = cmd.dns {
+        for service in &cmd.dns_service {
+            node_man
+                .add_dns_record(
+                    &service.name,
+                    PortalDnsRecord::Service {
+                        service: service.service.clone(),
+                        port: service.port,
+                    },
+                )
+                .await;
+        }
+        PortalDns::start(&ctx, node_man.clone(), dns)
+            .await
+            .into_diagnostic()?;
+    }
+    if let Some(control) = cmd.control_identity.clone() {
+        ConfigUpdateService::start(&ctx, (**node_man).clone(), control)
+            .await
+            .into_diagnostic()?;
+    }
+    if let Some(config) = cmd.heartbeat_config() {
+        HeartbeatReporter::start(&ctx, Arc::downgrade(&**node_man), config)
+            .await
+            .into_diagnostic()?;
+    }
+    if let Some(config) = cmd.health_check_config() {
+        HealthChecker::start(&ctx, Arc::downgrade(&**node_man), config)
+            .await
+            .into_diagnostic()?;
+    }
+    let runtime_state = node_man.runtime_state().cloned();
+    let node_manager_worker = NodeManagerWorker::new(node_man);
 
     ctx.flow_controls()
         .add_consumer(NODEMANAGER_ADDR, listener.flow_control_id());
@@ -332,6 +685,18 @@ async fn run_foreground_node(
         .await
         .into_diagnostic()?;
 
+    // Create again the channels, relays and portals of the node before it was stopped
+    if let Some(runtime_state) = runtime_state {
+        runtime_state.restore(&ctx).await.into_diagnostic()?;
+    }
+
+    // Only the default node takes the scheduled snapshots of the local state
+    let backup_scheduler = if opts.state.nodes.is_default(&node_name)? {
+        opts.state.start_backup_scheduler()?
+    } else {
+        None
+    };
+
     if let Some(config) = &cmd.launch_config {
         if start_services(&ctx, config).await.is_err() {
             //TODO: Process should terminate on any error during its setup phase,
@@ -348,6 +713,8 @@ async fn run_foreground_node(
         }
     }
 
+    let _ = hooks.run(NodeHookStage::PostStart, &hook_context).await;
+
     // Create a channel for communicating back to the main thread
     let (tx, mut rx) = tokio::sync::mpsc::channel(2);
     shutdown::wait(
@@ -359,6 +726,13 @@ async fn run_foreground_node(
     )
     .await?;
 
+    let _ = hooks.run(NodeHookStage::PreStop, &hook_context).await;
+
+    // No snapshot is taken while the node is stopping
+    if let Some(backup_scheduler) = backup_scheduler {
+        backup_scheduler.abort();
+    }
+
     // Try to stop node; it might have already been stopped or deleted (e.g. when running `node delete --all`)
     if let Ok(state) = opts.state.nodes.get(&node_name) {
         let _ = state.kill_process(false);
@@ -442,6 +816,15 @@ pub async fn spawn_background_node(
     cmd: CreateCommand,
 ) -> miette::Result<()> {
     let node_name = parse_node_name(&cmd.node_name)?;
+    // Resolve the working directory now, since the node process is spawned from another one
+    let working_dir = match &cmd.working_dir {
+        Some(dir) => Some(
+            std::fs::canonicalize(dir)
+                .into_diagnostic()
+                .wrap_err(format!("Invalid working directory {}", dir.display()))?,
+        ),
+        None => None,
+    };
     // Create node state, including the vault and identity if don't exist
     init_node_state(
         &opts.state,
@@ -451,6 +834,17 @@ pub async fn spawn_background_node(
     )
     .await?;
 
+    // Record the environment of the node process, applied each time the process is spawned
+    let node_state = opts.state.nodes.get(&node_name)?;
+    node_state.set_setup(
+        &node_state.config().setup_mut().set_process(
+            NodeProcessConfig::default()
+                .with_env(cmd.env.clone())
+                .with_working_dir(working_dir)
+                .with_limits(cmd.ulimits.clone()),
+        ),
+    )?;
+
     let trust_context_path = match cmd.trust_context_opts.trust_context.clone() {
         Some(tc) => {
             let config = opts.state.trust_contexts.read_config_from_path(&tc)?;
@@ -463,20 +857,45 @@ pub async fn spawn_background_node(
     // CLI in foreground mode to start the newly created node
     spawn_node(
         opts,
-        &node_name,
-        &cmd.tcp_listener_address,
-        cmd.trust_context_opts.project_path.as_ref(),
-        cmd.trusted_identities.as_ref(),
-        cmd.trusted_identities_file.as_ref(),
-        cmd.reload_from_trusted_identities_file.as_ref(),
-        cmd.launch_config
-            .as_ref()
-            .map(|config| serde_json::to_string(config).unwrap()),
-        cmd.authority_identity.as_ref(),
-        cmd.credential.as_ref(),
-        trust_context_path.as_ref(),
-        cmd.trust_context_opts.project.as_ref(),
-        cmd.logging_to_file(),
+        SpawnNodeOptions {
+            name: &node_name,
+            address: &cmd.tcp_listener_address,
+            project: cmd.trust_context_opts.project_path.as_ref(),
+            trusted_identities: cmd.trusted_identities.as_ref(),
+            trusted_identities_file: cmd.trusted_identities_file.as_ref(),
+            reload_from_trusted_identities_file: cmd.reload_from_trusted_identities_file.as_ref(),
+            launch_config: cmd
+                .launch_config
+                .as_ref()
+                .map(|config| serde_json::to_string(config).unwrap()),
+            authority_identity: cmd.authority_identity.as_ref(),
+            credential: cmd.credential.as_ref(),
+            trust_context: trust_context_path.as_ref(),
+            project_name: cmd.trust_context_opts.project.as_ref(),
+            quota_limits: cmd.quota_limits(),
+            replicate_members: cmd.replicate_members,
+            portal_events: cmd.portal_events.as_ref(),
+            dns: cmd.dns.as_ref(),
+            dns_services: &cmd.dns_service,
+            resource_profile: cmd.resource_profile.as_ref(),
+            memory_limits: &cmd.memory_limit,
+            warm_start: cmd.warm_start,
+            notifications: cmd.notifications.as_ref(),
+            metrics_exporter: cmd.metrics_exporter.as_ref(),
+            record_api: cmd.record_api.as_ref(),
+            timeouts: &cmd.timeouts,
+            slow_storage_threshold: cmd.slow_storage_threshold.as_ref(),
+            storage_maintenance_interval: cmd.storage_maintenance_interval(),
+            resource_usage_interval: cmd.resource_usage_interval(),
+            resume_portal_sessions: cmd.resume_portal_sessions,
+            outlet_resolver: cmd.outlet_resolver.as_ref(),
+            hooks: cmd.hooks.as_ref(),
+            control_identity: cmd.control_identity.as_ref(),
+            fleet_inventory: cmd.fleet,
+            heartbeats: cmd.heartbeat_config().as_ref(),
+            health_checks: cmd.health_check_config().as_ref(),
+            logging_to_file: cmd.logging_to_file(),
+        },
     )?;
 
     Ok(())
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam_api::nodes::models::drift::DriftReport;
+use ockam_api::nodes::service::drift::DriftRequest;
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::api::Request;
+use ockam_node::Context;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::node_rpc;
+use crate::{docs, fmt_ok, fmt_warn, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/drift/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/drift/after_long_help.txt");
+
+/// Compare a node with a declarative configuration
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct DriftCommand {
+    /// YAML file listing the inlets, outlets, relays and policies of the node
+    config: PathBuf,
+
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl DriftCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, DriftCommand),
+) -> miette::Result<()> {
+    let config = std::fs::read_to_string(&cmd.config).into_diagnostic()?;
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let report: DriftReport = node
+        .ask(
+            &ctx,
+            Request::post("/node/drift").body(DriftRequest { config }),
+        )
+        .await?;
+
+    let node_name = node_name.color(OckamColor::PrimaryResource.color());
+    let plain = if report.is_empty() {
+        fmt_ok!("The node {node_name} matches its configuration")
+    } else {
+        report
+            .drifts
+            .iter()
+            .map(|drift| fmt_warn!("{drift}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(serde_json::json!(&report))
+        .write_line()?;
+
+    if report.is_empty() {
+        Ok(())
+    } else {
+        Err(miette!(
+            "The node {node_name} drifted from its configuration: {} differences",
+            report.drifts.len()
+        ))
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+
+use ockam::identity::Identifier;
+use ockam_api::fleet::FleetMember;
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::api::Request;
+use ockam_node::Context;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::duration::duration_parser;
+use crate::util::node_rpc;
+use crate::util::parsers::identity_identifier_parser;
+use crate::{docs, fmt_log, fmt_ok, fmt_warn, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/fleet/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/fleet/after_long_help.txt");
+
+/// List the nodes reporting to a control node
+#[derive(Clone, Debug, Args)]
+#[command(
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct FleetCommand {
+    /// Only show the node with this identifier
+    #[arg(value_name = "IDENTIFIER", value_parser = identity_identifier_parser)]
+    identifier: Option<Identifier>,
+
+    /// Mark the nodes which didn't send a heartbeat for this duration as stale
+    #[arg(long, value_name = "DURATION", default_value = "2m", value_parser = duration_parser)]
+    stale_after: Duration,
+
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl FleetCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, FleetCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let members: Vec<FleetMember> = match &cmd.identifier {
+        Some(identifier) => vec![
+            node.ask(&ctx, Request::get(format!("/node/fleet/{identifier}")))
+                .await?,
+        ],
+        None => node.ask(&ctx, Request::get("/node/fleet")).await?,
+    };
+
+    let plain = if members.is_empty() {
+        fmt_log!("No node reported to the node {node_name}")
+    } else {
+        members
+            .iter()
+            .map(|member| {
+                let heartbeat = &member.heartbeat.heartbeat;
+                let name = heartbeat
+                    .node_name
+                    .as_str()
+                    .color(OckamColor::PrimaryResource.color());
+                let mut summary = format!(
+                    "{name} ({}) version {}, up for {}s, {} secure channels, inlets [{}], outlets [{}], relays [{}]",
+                    member.identifier,
+                    heartbeat.version,
+                    heartbeat.uptime_secs,
+                    heartbeat.secure_channels,
+                    heartbeat.inlets.join(", "),
+                    heartbeat.outlets.join(", "),
+                    heartbeat.relays.join(", "),
+                );
+                if let Some(version) = heartbeat.config_version {
+                    summary.push_str(&format!(", configuration version {version}"));
+                }
+                if member.is_stale(cmd.stale_after) {
+                    fmt_warn!("{summary}, stale")
+                } else {
+                    fmt_ok!("{summary}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(serde_json::json!(&members))
+        .write_line()?;
+    Ok(())
+}
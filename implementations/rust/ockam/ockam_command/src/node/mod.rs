@@ -4,26 +4,41 @@ use colorful::Colorful;
 pub use create::CreateCommand;
 use default::DefaultCommand;
 use delete::DeleteCommand;
+use drift::DriftCommand;
+use fleet::FleetCommand;
 use list::ListCommand;
 use logs::LogCommand;
-use ockam_api::cli_state::{CliState, StateDirTrait};
+use ockam_api::cli_state::{CliState, StateDirTrait, DEFAULT_NODE_NAME};
+use ping::PingCommand;
+use push_config::PushConfigCommand;
+use replay::ReplayCommand;
+use rollout::RolloutCommand;
 use show::ShowCommand;
 use start::StartCommand;
 use stop::StopCommand;
+use update_trust_context::UpdateTrustContextCommand;
 
 use crate::{docs, fmt_log, terminal::OckamColor, CommandGlobalOpts, PARSER_LOGS};
 
 mod create;
 mod default;
 mod delete;
+mod drift;
+mod fleet;
 mod list;
 mod logs;
 mod models;
+mod ping;
+mod push_config;
+mod replay;
+mod rollout;
 mod show;
 mod start;
 mod stop;
+mod update_trust_context;
 pub mod util;
 pub use create::*;
+pub(crate) use show::is_node_up;
 
 const LONG_ABOUT: &str = include_str!("./static/long_about.txt");
 const AFTER_LONG_HELP: &str = include_str!("./static/after_long_help.txt");
@@ -58,6 +73,20 @@ pub enum NodeSubcommand {
     Stop(StopCommand),
     #[command(display_order = 800)]
     Default(DefaultCommand),
+    #[command(display_order = 800)]
+    Replay(ReplayCommand),
+    #[command(display_order = 800)]
+    Drift(DriftCommand),
+    #[command(display_order = 800)]
+    PushConfig(PushConfigCommand),
+    #[command(display_order = 800)]
+    Fleet(FleetCommand),
+    #[command(display_order = 800)]
+    Ping(PingCommand),
+    #[command(display_order = 800)]
+    Rollout(RolloutCommand),
+    #[command(display_order = 800)]
+    UpdateTrustContext(UpdateTrustContextCommand),
 }
 
 impl NodeCommand {
@@ -71,6 +100,13 @@ impl NodeCommand {
             NodeSubcommand::Stop(c) => c.run(options),
             NodeSubcommand::Logs(c) => c.run(options),
             NodeSubcommand::Default(c) => c.run(options),
+            NodeSubcommand::Replay(c) => c.run(options),
+            NodeSubcommand::Drift(c) => c.run(options),
+            NodeSubcommand::PushConfig(c) => c.run(options),
+            NodeSubcommand::Fleet(c) => c.run(options),
+            NodeSubcommand::Ping(c) => c.run(options),
+            NodeSubcommand::Rollout(c) => c.run(options),
+            NodeSubcommand::UpdateTrustContext(c) => c.run(options),
         }
     }
 }
@@ -86,33 +122,33 @@ pub struct NodeOpts {
 /// then initialize it
 pub fn initialize_node_if_default(opts: &CommandGlobalOpts, node_name: &Option<String>) {
     let node_name = get_node_name(&opts.state, node_name);
-    if node_name == "default" && opts.state.nodes.default().is_err() {
+    if node_name == DEFAULT_NODE_NAME && opts.state.nodes.default().is_err() {
         spawn_default_node(opts)
     }
 }
 
 /// Return the node_name if Some otherwise return the default node name
 pub fn get_node_name<'a>(cli_state: &CliState, node_name: impl Into<&'a Option<String>>) -> String {
-    node_name
-        .into()
-        .clone()
-        .unwrap_or_else(|| get_default_node_name(cli_state))
+    let node_name = node_name.into().as_deref();
+    cli_state
+        .resolve_node(node_name)
+        .map(|resolved| {
+            tracing::debug!("using the node {}", resolved.explain());
+            resolved.name
+        })
+        .unwrap_or_else(|_| DEFAULT_NODE_NAME.to_string())
 }
 
 /// Return the default node name
 pub fn get_default_node_name(cli_state: &CliState) -> String {
-    cli_state
-        .nodes
-        .default()
-        .map(|n| n.name().to_string())
-        .unwrap_or_else(|_| "default".to_string())
+    get_node_name(cli_state, &None)
 }
 
 /// Start the default node
 fn spawn_default_node(opts: &CommandGlobalOpts) {
     let mut create_command = CreateCommand::default();
 
-    let default = "default";
+    let default = DEFAULT_NODE_NAME;
     create_command.node_name = default.into();
     create_command.run(opts.clone().set_quiet());
 
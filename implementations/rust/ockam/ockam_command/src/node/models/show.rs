@@ -2,6 +2,8 @@ use std::fmt::Display;
 
 use colorful::Colorful;
 
+use ockam_api::nodes::models::base::SubsystemMemoryUsage;
+use ockam_api::nodes::resource_usage::ResourceUsageSample;
 use ockam_multiaddr::{
     proto::{DnsAddr, Node, Tcp},
     MultiAddr,
@@ -26,6 +28,14 @@ pub struct ShowNodeResponse {
     pub route: RouteToNode,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub identity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_profile: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<SubsystemMemoryUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource_usage: Option<ResourceUsageSample>,
     pub transports: Vec<ShowTransportStatus>,
     pub secure_channel_listeners: Vec<ShowSecureChannelListener>,
     pub inlets: Vec<ShowInletStatus>,
@@ -66,6 +76,10 @@ impl ShowNodeResponse {
             is_up,
             route: RouteToNode { short, verbose },
             identity: None,
+            identity_name: None,
+            resource_profile: None,
+            memory: Default::default(),
+            resource_usage: None,
             transports: Default::default(),
             secure_channel_listeners: Default::default(),
             inlets: Default::default(),
@@ -103,7 +117,37 @@ impl Display for ShowNodeResponse {
         }
 
         if let Some(identity) = &self.identity {
-            writeln!(buffer, "  Identity: {}", identity)?;
+            match &self.identity_name {
+                Some(name) => writeln!(buffer, "  Identity: {name} ({identity})")?,
+                None => writeln!(buffer, "  Identity: {}", identity)?,
+            }
+        }
+
+        if let Some(resource_profile) = &self.resource_profile {
+            writeln!(buffer, "  Resource Profile: {}", resource_profile)?;
+        }
+
+        if !self.memory.is_empty() {
+            writeln!(buffer, "  Memory:")?;
+            for e in &self.memory {
+                match e.limit {
+                    Some(limit) => writeln!(
+                        buffer,
+                        "    {}: {} bytes (peak {}, limit {})",
+                        e.name, e.used, e.peak, limit
+                    )?,
+                    None => writeln!(buffer, "    {}: {} bytes (peak {})", e.name, e.used, e.peak)?,
+                }
+            }
+        }
+
+        if let Some(usage) = &self.resource_usage {
+            writeln!(buffer, "  Resource Usage:")?;
+            writeln!(buffer, "    CPU: {:.1}%", usage.cpu_usage)?;
+            writeln!(buffer, "    Memory: {} bytes", usage.memory)?;
+            if let Some(open_files) = usage.open_files {
+                writeln!(buffer, "    Open Files: {open_files}")?;
+            }
         }
 
         writeln!(buffer, "  Transports:")?;
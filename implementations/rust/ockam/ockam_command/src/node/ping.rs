@@ -0,0 +1,95 @@
+use clap::Args;
+use colorful::Colorful;
+
+use ockam::identity::Identifier;
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::ping::{HealthCheck, PingNode};
+use ockam_core::api::Request;
+use ockam_multiaddr::MultiAddr;
+use ockam_node::Context;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::node_rpc;
+use crate::util::parsers::identity_route_parser;
+use crate::{docs, fmt_err, fmt_log, fmt_ok, CommandGlobalOpts};
+
+const AFTER_LONG_HELP: &str = include_str!("./static/ping/after_long_help.txt");
+
+/// Probe another node through a secure channel.
+///
+/// The probed node replies with its name, version and uptime, once the secure channel checked
+/// its identity. The results of the health checks of a node created with `--health-check` are
+/// listed with `--history`.
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = docs::after_help(AFTER_LONG_HELP))]
+pub struct PingCommand {
+    /// Node to probe, given by its identity and the route to its secure channel listener
+    #[arg(value_name = "IDENTIFIER@ROUTE", value_parser = identity_route_parser, required_unless_present = "history")]
+    target: Option<(Identifier, MultiAddr)>,
+
+    /// List the results of the health checks of the node instead
+    #[arg(long, conflicts_with = "target")]
+    history: bool,
+
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl PingCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, PingCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let checks: Vec<HealthCheck> = match cmd.target {
+        Some((identifier, route)) => vec![
+            node.ask(
+                &ctx,
+                Request::post("/node/ping").body(PingNode::new(identifier, &route)),
+            )
+            .await?,
+        ],
+        None => node.ask(&ctx, Request::get("/node/health_checks")).await?,
+    };
+
+    let plain = if checks.is_empty() {
+        fmt_log!("The node {node_name} has no health checks")
+    } else {
+        checks.iter().map(display).collect::<Vec<_>>().join("\n")
+    };
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(serde_json::json!(&checks))
+        .write_line()?;
+    Ok(())
+}
+
+fn display(check: &HealthCheck) -> String {
+    let target = check
+        .target
+        .to_string()
+        .color(OckamColor::PrimaryResource.color());
+    match (&check.pong, &check.error) {
+        (Some(pong), _) => fmt_ok!(
+            "{} ({target}) replied in {}ms, version {}, up for {}s",
+            pong.node_name,
+            check.round_trip_ms.unwrap_or_default(),
+            pong.version,
+            pong.uptime_secs
+        ),
+        (None, error) => fmt_err!(
+            "{target} at {} didn't reply: {}",
+            check.route,
+            error.clone().unwrap_or_default()
+        ),
+    }
+}
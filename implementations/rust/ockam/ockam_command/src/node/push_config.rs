@@ -0,0 +1,124 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::identity::Identifier;
+use ockam::Context;
+use ockam_api::config_updates::ConfigUpdate;
+use ockam_api::nodes::InMemoryNode;
+use ockam_multiaddr::MultiAddr;
+
+use crate::identity::{get_identity_name, initialize_identity_if_default};
+use crate::terminal::OckamColor;
+use crate::util::node_rpc;
+use crate::util::parsers::identity_route_parser;
+use crate::{docs, fmt_err, fmt_log, fmt_ok, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/push_config/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/push_config/after_long_help.txt");
+
+/// Push a signed configuration to the nodes of a fleet
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct PushConfigCommand {
+    /// YAML file listing the outlets and policies of the nodes
+    config: PathBuf,
+
+    /// Version of the configuration, which must be greater than the version applied by the nodes
+    #[arg(long)]
+    version: u64,
+
+    /// Identity of a node and route to its secure channel listener, for example
+    /// `I2c3b0ef15c12fe43d405497fcfc46ab5c4c0b5d6@/dnsaddr/n1.example.com/tcp/4000/service/api`
+    #[arg(
+        long = "target",
+        value_name = "IDENTIFIER@ROUTE",
+        required = true,
+        value_parser = identity_route_parser
+    )]
+    targets: Vec<(Identifier, MultiAddr)>,
+
+    /// Name of the control identity signing the configuration
+    #[arg(long = "identity", value_name = "IDENTITY_NAME")]
+    identity: Option<String>,
+}
+
+impl PushConfigCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.identity);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, PushConfigCommand),
+) -> miette::Result<()> {
+    let config = std::fs::read_to_string(&cmd.config).into_diagnostic()?;
+    let update = ConfigUpdate::new(cmd.version, config).into_diagnostic()?;
+    let identity_name = get_identity_name(&opts.state, &cmd.identity);
+    let node_manager =
+        InMemoryNode::start_node(&ctx, &opts.state, None, Some(identity_name), None, None).await?;
+    let signed = node_manager
+        .sign_config_update(update)
+        .await
+        .into_diagnostic()?;
+
+    let mut lines = vec![];
+    let mut results = vec![];
+    let mut failures = 0;
+    for (identifier, route) in &cmd.targets {
+        let target = identifier
+            .to_string()
+            .color(OckamColor::PrimaryResource.color());
+        match node_manager
+            .push_config_update(&ctx, identifier, route, &signed)
+            .await
+        {
+            Ok(report) => {
+                lines.push(fmt_ok!(
+                    "The node {target} applied the version {} with {} changes",
+                    report.version,
+                    report.changes.len()
+                ));
+                for change in &report.changes {
+                    lines.push(fmt_log!("{change}"));
+                }
+                results.push(serde_json::json!({
+                    "identifier": identifier,
+                    "changes": report.changes,
+                }));
+            }
+            Err(e) => {
+                failures += 1;
+                lines.push(fmt_err!("The node {target} rejected the update: {e}"));
+                results.push(serde_json::json!({
+                    "identifier": identifier,
+                    "error": e.to_string(),
+                }));
+            }
+        }
+    }
+    opts.terminal
+        .stdout()
+        .plain(lines.join("\n"))
+        .json(serde_json::json!({ "version": cmd.version, "targets": results }))
+        .write_line()?;
+
+    if failures == 0 {
+        Ok(())
+    } else {
+        Err(miette!(
+            "The update was not applied by {failures} of {} nodes",
+            cmd.targets.len()
+        ))
+    }
+}
@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam_api::nodes::api_recording::{read_recording, AddressMapping};
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::api::Status;
+use ockam_node::Context;
+
+use crate::node::get_node_name;
+use crate::terminal::OckamColor;
+use crate::util::node_rpc;
+use crate::util::parsers::address_mapping_parser;
+use crate::{docs, fmt_ok, fmt_warn, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/replay/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/replay/after_long_help.txt");
+
+/// Send the requests recorded by a node to another node
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct ReplayCommand {
+    /// File created with `ockam node create --record-api`
+    recording: PathBuf,
+
+    /// Name of the node receiving the requests
+    #[arg(long, value_name = "NODE_NAME")]
+    to: Option<String>,
+
+    /// Replace a name or an address in the requests
+    #[arg(long = "map", value_name = "OLD=NEW", value_parser = address_mapping_parser)]
+    mappings: Vec<(String, String)>,
+
+    /// Also send the read requests and the requests which failed on the recorded node
+    #[arg(long)]
+    all: bool,
+
+    /// Continue with the next requests when a request fails
+    #[arg(long)]
+    keep_going: bool,
+}
+
+impl ReplayCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, ReplayCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.to);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let client = node.make_client().await?;
+    let mapping = AddressMapping::new(cmd.mappings.clone());
+
+    let requests = read_recording(&cmd.recording).into_diagnostic()?;
+    let mut replayed = 0;
+    for request in requests
+        .iter()
+        .filter(|r| cmd.all || r.is_successful_change())
+    {
+        let status = request
+            .replay(&ctx, &client, &mapping)
+            .await
+            .into_diagnostic()?;
+        if status == Some(Status::Ok) {
+            replayed += 1;
+            continue;
+        }
+        let status = status.map(|s| s.to_string()).unwrap_or_default();
+        opts.terminal.write_line(&fmt_warn!(
+            "The request {} {} failed on the node '{node_name}': {status}",
+            request.method,
+            request.path
+        ))?;
+        if !cmd.keep_going {
+            return Err(miette::miette!(
+                "The replay was interrupted after {replayed} requests"
+            ));
+        }
+    }
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "Replayed {replayed} requests on the node {}",
+            node_name.color(OckamColor::PrimaryResource.color())
+        ))
+        .write_line()?;
+    Ok(())
+}
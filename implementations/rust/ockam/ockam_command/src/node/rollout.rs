@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{Args, Subcommand};
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam_api::nodes::service::rollout::CreateRollout;
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::rollout::{Rollout, RolloutSpec, RolloutStatus};
+use ockam_core::api::Request;
+use ockam_node::Context;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::duration::duration_parser;
+use crate::util::node_rpc;
+use crate::util::parsers::label_parser;
+use crate::{docs, fmt_err, fmt_log, fmt_ok, fmt_warn, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/rollout/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/rollout/after_long_help.txt");
+
+/// Roll out a configuration to the nodes of a fleet, stage by stage
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    subcommand_required = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct RolloutCommand {
+    #[command(subcommand)]
+    subcommand: RolloutSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+enum RolloutSubcommand {
+    /// Start a rollout from a control node
+    Start(StartRollout),
+
+    /// Show the rollouts of a control node
+    Show {
+        /// Only show this rollout
+        id: Option<String>,
+
+        #[command(flatten)]
+        node_opts: NodeOpts,
+    },
+
+    /// Stop a running rollout and roll back the nodes which received the configuration
+    Abort {
+        id: String,
+
+        #[command(flatten)]
+        node_opts: NodeOpts,
+    },
+}
+
+#[derive(Clone, Debug, Args)]
+struct StartRollout {
+    /// YAML file listing the outlets and policies rolled out to the nodes
+    config: PathBuf,
+
+    /// Version of the configuration, which must be greater than the version applied by the nodes
+    #[arg(long)]
+    version: u64,
+
+    /// YAML file restoring the previous configuration of the nodes, applied with the next
+    /// version if the rollout is rolled back
+    #[arg(long, value_name = "FILE")]
+    rollback_config: PathBuf,
+
+    /// Name of the rollout. The version is used by default
+    #[arg(long)]
+    id: Option<String>,
+
+    /// Only update the nodes created with this label
+    #[arg(long = "selector", value_name = "KEY=VALUE", value_parser = label_parser)]
+    selector: Vec<(String, String)>,
+
+    /// Percentages of the selected nodes updated at the end of each stage
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "10,50,100",
+        value_parser = clap::value_parser!(u8).range(1..=100)
+    )]
+    stages: Vec<u8>,
+
+    /// Duration during which the nodes of a stage must stay healthy before the next stage
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = duration_parser)]
+    bake: Duration,
+
+    /// Duration after which a node which didn't report the configuration has failed
+    #[arg(long, value_name = "DURATION", default_value = "2m", value_parser = duration_parser)]
+    health_timeout: Duration,
+
+    /// Number of failed nodes tolerated before rolling back
+    #[arg(long, default_value = "0")]
+    max_failures: u64,
+
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl RolloutCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts().at_node);
+        node_rpc(run_impl, (opts, self))
+    }
+
+    fn node_opts(&self) -> &NodeOpts {
+        match &self.subcommand {
+            RolloutSubcommand::Start(cmd) => &cmd.node_opts,
+            RolloutSubcommand::Show { node_opts, .. } => node_opts,
+            RolloutSubcommand::Abort { node_opts, .. } => node_opts,
+        }
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, RolloutCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts().at_node);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let rollouts: Vec<Rollout> = match cmd.subcommand {
+        RolloutSubcommand::Start(start) => {
+            let spec = RolloutSpec::new(start.id.unwrap_or_else(|| start.version.to_string()))
+                .with_selector(start.selector.into_iter().collect())
+                .with_stages(start.stages)
+                .with_bake(start.bake)
+                .with_health_timeout(start.health_timeout)
+                .with_max_failures(start.max_failures);
+            let request = CreateRollout {
+                spec,
+                config: std::fs::read_to_string(&start.config).into_diagnostic()?,
+                version: start.version,
+                rollback_config: std::fs::read_to_string(&start.rollback_config)
+                    .into_diagnostic()?,
+            };
+            vec![
+                node.ask(&ctx, Request::post("/node/rollouts").body(request))
+                    .await?,
+            ]
+        }
+        RolloutSubcommand::Show { id: Some(id), .. } => {
+            vec![
+                node.ask(&ctx, Request::get(format!("/node/rollouts/{id}")))
+                    .await?,
+            ]
+        }
+        RolloutSubcommand::Show { id: None, .. } => {
+            node.ask(&ctx, Request::get("/node/rollouts")).await?
+        }
+        RolloutSubcommand::Abort { id, .. } => {
+            vec![
+                node.ask(&ctx, Request::post(format!("/node/rollouts/{id}/abort")))
+                    .await?,
+            ]
+        }
+    };
+
+    let plain = if rollouts.is_empty() {
+        fmt_log!("No rollout was started on the node {node_name}")
+    } else {
+        rollouts
+            .iter()
+            .map(|rollout| {
+                let id = rollout
+                    .spec
+                    .id
+                    .as_str()
+                    .color(OckamColor::PrimaryResource.color());
+                let mut summary = format!(
+                    "{id} version {}, stage {} of {} ({}%), {} nodes updated, {}",
+                    rollout.update.update.version,
+                    rollout.stage + 1,
+                    rollout.spec.stages.len(),
+                    rollout.percentage(),
+                    rollout.targets.len(),
+                    rollout.status
+                );
+                if let Some(message) = &rollout.message {
+                    summary.push_str(&format!(": {message}"));
+                }
+                match rollout.status {
+                    RolloutStatus::Running | RolloutStatus::Completed => fmt_ok!("{summary}"),
+                    RolloutStatus::RollingBack => fmt_warn!("{summary}"),
+                    RolloutStatus::RolledBack => fmt_err!("{summary}"),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(serde_json::json!(&rollouts))
+        .write_line()?;
+    Ok(())
+}
@@ -1,9 +1,12 @@
 use clap::Args;
 use miette::IntoDiagnostic;
+use ockam_api::nodes::models::base::NodeMemoryUsage;
 use ockam_api::nodes::models::secure_channel::SecureChannelListenersList;
 use ockam_api::nodes::models::services::ServiceList;
 use ockam_api::nodes::models::transport::TransportList;
+use ockam_api::nodes::resource_usage::NodeResourceUsage;
 use ockam_api::nodes::BackgroundNode;
+use ockam_api::resource_profile::NodeResources;
 use ockam_node::Context;
 use tokio_retry::strategy::FixedInterval;
 use tracing::{info, trace, warn};
@@ -95,12 +98,30 @@ pub async fn print_query_status(
 
             let mut node_info = ShowNodeResponse::new(is_default, node_name, true, node_port);
 
-            // Get short id for the node
+            // Get the identifier of the node, with its name if it is known
             node_info.identity = Some(match node_state.config().identity_config() {
-                Ok(resp) => resp.identifier().to_string(),
+                Ok(resp) => {
+                    node_info.identity_name = cli_state
+                        .display_names()?
+                        .name(&resp.identifier())
+                        .map(|name| name.to_string());
+                    resp.identifier().to_string()
+                }
                 Err(_) => String::from("None"),
             });
 
+            // Get the resource profile of the node
+            let resources: NodeResources = node.ask(ctx, api::query_resources()).await?;
+            node_info.resource_profile = Some(resources.profile);
+
+            // Get the memory used by the subsystems of the node
+            let memory: NodeMemoryUsage = node.ask(ctx, api::query_memory()).await?;
+            node_info.memory = memory.subsystems;
+
+            // Get the last sample of the resource usage of the node process
+            let usage: NodeResourceUsage = node.ask(ctx, api::query_resource_usage()).await?;
+            node_info.resource_usage = usage.last().cloned();
+
             // Get list of services for the node
             let services: ServiceList = node.ask(ctx, api::list_services()).await?;
             node_info.services = services
@@ -2,11 +2,13 @@ use clap::Args;
 use colorful::Colorful;
 
 use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_api::nodes::resource_usage::DEFAULT_RESOURCE_USAGE_INTERVAL;
 use ockam_api::nodes::BackgroundNode;
+use ockam_api::storage_maintenance::DEFAULT_STORAGE_MAINTENANCE_INTERVAL;
 use ockam_node::Context;
 
 use crate::node::show::print_query_status;
-use crate::node::util::{check_default, spawn_node};
+use crate::node::util::{check_default, spawn_node, SpawnNodeOptions};
 use crate::node::{get_node_name, initialize_node_if_default};
 use crate::util::node_rpc;
 use crate::{docs, fmt_err, CommandGlobalOpts};
@@ -63,18 +65,26 @@ async fn run_impl(
     // Restart node
     spawn_node(
         &opts,
-        &node_name,                                    // The selected node name
-        &node_setup.api_transport()?.addr.to_string(), // The selected node api address
-        None,                                          // No project information available
-        None,                                          // No trusted identities
-        None,                                          // "
-        None,                                          // "
-        None,                                          // Launch config
-        None,                                          // Authority Identity
-        None,                                          // Credential
-        None,                                          // Trust Context
-        None,                                          // Project Name
-        true,                                          // Restarted nodes will log to files
+        SpawnNodeOptions {
+            name: &node_name,
+            address: &node_setup.api_transport()?.addr.to_string(),
+            // Same quota limits and resource profile
+            quota_limits: node_setup.quota_limits(),
+            resource_profile: node_setup.resource_profile.as_ref(),
+            // Restore the channels and portals
+            warm_start: node_setup.warm_start.unwrap_or(false),
+            slow_storage_threshold: node_setup.slow_storage_threshold().as_ref(),
+            storage_maintenance_interval: Some(DEFAULT_STORAGE_MAINTENANCE_INTERVAL),
+            resource_usage_interval: Some(DEFAULT_RESOURCE_USAGE_INTERVAL),
+            // Same control node, fleet inventory and health checks
+            control_identity: node_setup.control_identity.as_ref(),
+            fleet_inventory: node_setup.fleet_inventory.unwrap_or(false),
+            heartbeats: node_setup.heartbeats.as_ref(),
+            health_checks: node_setup.health_checks.as_ref(),
+            // Restarted nodes will log to files
+            logging_to_file: true,
+            ..Default::default()
+        },
     )?;
 
     // Print node status
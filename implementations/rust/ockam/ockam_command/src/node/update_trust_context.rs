@@ -0,0 +1,94 @@
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam_api::nodes::models::trust_context::{
+    ChannelRevalidation, UpdateTrustContextRequest, UpdateTrustContextResponse,
+};
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::api::Request;
+use ockam_node::Context;
+
+use crate::node::{get_node_name, initialize_node_if_default};
+use crate::terminal::OckamColor;
+use crate::util::api::TrustContextOpts;
+use crate::util::node_rpc;
+use crate::{docs, fmt_log, fmt_ok, CommandGlobalOpts};
+
+const LONG_ABOUT: &str = include_str!("./static/update_trust_context/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/update_trust_context/after_long_help.txt");
+
+/// Replace the trust context of a running node
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP)
+)]
+pub struct UpdateTrustContextCommand {
+    /// Name of the node
+    node_name: Option<String>,
+
+    /// Identity of the new authority, when it is not part of the trust context
+    #[arg(long)]
+    authority_identity: Option<String>,
+
+    #[command(flatten)]
+    trust_context_opts: TrustContextOpts,
+
+    /// What to do with the existing secure channels of the node: keep, revalidate or close
+    #[arg(long, value_name = "POLICY", default_value = "revalidate")]
+    channels: ChannelRevalidation,
+}
+
+impl UpdateTrustContextCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_name);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, UpdateTrustContextCommand),
+) -> miette::Result<()> {
+    let trust_context = cmd
+        .trust_context_opts
+        .to_config(&opts.state)?
+        .with_authority_identity(cmd.authority_identity.as_ref())
+        .build()
+        .ok_or_else(|| {
+            miette!("A trust context, a project or an authority identity is required")
+        })?;
+    let node_name = get_node_name(&opts.state, &cmd.node_name);
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let response: UpdateTrustContextResponse = node
+        .ask(
+            &ctx,
+            Request::put("/node/trust_context").body(
+                UpdateTrustContextRequest::new(&trust_context, cmd.channels).into_diagnostic()?,
+            ),
+        )
+        .await?;
+
+    let node_name = node_name.color(OckamColor::PrimaryResource.color());
+    let trust_context_id = response
+        .trust_context_id
+        .as_str()
+        .color(OckamColor::PrimaryResource.color());
+    let mut plain = fmt_ok!("The node {node_name} now uses the trust context {trust_context_id}\n");
+    for authority in &response.authorities {
+        plain.push_str(&fmt_log!("Trusted authority: {authority}\n"));
+    }
+    for channel in &response.closed_channels {
+        plain.push_str(&fmt_log!("Closed the secure channel {channel}\n"));
+    }
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(serde_json::json!(&response))
+        .write_line()?;
+    Ok(())
+}
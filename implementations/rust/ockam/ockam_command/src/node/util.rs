@@ -1,13 +1,21 @@
 use std::env::current_exe;
 use std::fs::OpenOptions;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
+use std::time::Duration;
 
 use miette::Context as _;
 use miette::{miette, IntoDiagnostic};
 use rand::random;
 
-use ockam_api::cli_state::StateDirTrait;
+use ockam::identity::{Identifier, QuotaLimits};
+use ockam_api::cli_state::{NodeProcessConfig, ProcessLimit, StateDirTrait, StateItemTrait};
+use ockam_api::fleet::HeartbeatConfig;
+use ockam_api::ping::HealthCheckConfig;
+use ockam_api::portal_dns::DnsServiceName;
+use ockam_api::portal_events::PortalEventsSink;
+use ockam_api::resource_profile::ResourceProfile;
 use ockam_core::env::get_env_with_default;
 
 use crate::util::api::TrustContextOpts;
@@ -58,23 +66,84 @@ pub fn check_default(opts: &CommandGlobalOpts, name: &str) -> bool {
     false
 }
 
+/// The options of a node process spawned in foreground mode
+#[derive(Default)]
+pub struct SpawnNodeOptions<'a> {
+    pub name: &'a str,
+    pub address: &'a str,
+    pub project: Option<&'a PathBuf>,
+    pub trusted_identities: Option<&'a String>,
+    pub trusted_identities_file: Option<&'a PathBuf>,
+    pub reload_from_trusted_identities_file: Option<&'a PathBuf>,
+    pub launch_config: Option<String>,
+    pub authority_identity: Option<&'a String>,
+    pub credential: Option<&'a String>,
+    pub trust_context: Option<&'a PathBuf>,
+    pub project_name: Option<&'a String>,
+    pub quota_limits: QuotaLimits,
+    pub replicate_members: bool,
+    pub portal_events: Option<&'a PortalEventsSink>,
+    pub dns: Option<&'a SocketAddr>,
+    pub dns_services: &'a [DnsServiceName],
+    pub resource_profile: Option<&'a ResourceProfile>,
+    pub memory_limits: &'a [(String, usize)],
+    pub warm_start: bool,
+    pub notifications: Option<&'a PathBuf>,
+    pub metrics_exporter: Option<&'a PathBuf>,
+    pub record_api: Option<&'a PathBuf>,
+    pub timeouts: &'a [(String, Duration)],
+    pub slow_storage_threshold: Option<&'a Duration>,
+    pub storage_maintenance_interval: Option<Duration>,
+    pub resource_usage_interval: Option<Duration>,
+    pub resume_portal_sessions: bool,
+    pub outlet_resolver: Option<&'a PathBuf>,
+    pub hooks: Option<&'a PathBuf>,
+    pub control_identity: Option<&'a Identifier>,
+    pub fleet_inventory: bool,
+    pub heartbeats: Option<&'a HeartbeatConfig>,
+    pub health_checks: Option<&'a HealthCheckConfig>,
+    pub logging_to_file: bool,
+}
+
 /// A utility function to spawn a new node into foreground mode
-#[allow(clippy::too_many_arguments)]
-pub fn spawn_node(
-    opts: &CommandGlobalOpts,
-    name: &str,
-    address: &str,
-    project: Option<&PathBuf>,
-    trusted_identities: Option<&String>,
-    trusted_identities_file: Option<&PathBuf>,
-    reload_from_trusted_identities_file: Option<&PathBuf>,
-    launch_config: Option<String>,
-    authority_identity: Option<&String>,
-    credential: Option<&String>,
-    trust_context: Option<&PathBuf>,
-    project_name: Option<&String>,
-    logging_to_file: bool,
-) -> miette::Result<()> {
+pub fn spawn_node(opts: &CommandGlobalOpts, options: SpawnNodeOptions) -> miette::Result<()> {
+    let SpawnNodeOptions {
+        name,
+        address,
+        project,
+        trusted_identities,
+        trusted_identities_file,
+        reload_from_trusted_identities_file,
+        launch_config,
+        authority_identity,
+        credential,
+        trust_context,
+        project_name,
+        quota_limits,
+        replicate_members,
+        portal_events,
+        dns,
+        dns_services,
+        resource_profile,
+        memory_limits,
+        warm_start,
+        notifications,
+        metrics_exporter,
+        record_api,
+        timeouts,
+        slow_storage_threshold,
+        storage_maintenance_interval,
+        resource_usage_interval,
+        resume_portal_sessions,
+        outlet_resolver,
+        hooks,
+        control_identity,
+        fleet_inventory,
+        heartbeats,
+        health_checks,
+        logging_to_file,
+    } = options;
+
     let mut args = vec![
         match opts.global_args.verbose {
             0 => "-vv".to_string(),
@@ -149,6 +218,151 @@ pub fn spawn_node(
         args.push(project_name.to_string());
     }
 
+    if let Some(max) = quota_limits.max_secure_channels {
+        args.push("--max-secure-channels-per-identity".to_string());
+        args.push(max.to_string());
+    }
+
+    if let Some(max) = quota_limits.max_portal_connections {
+        args.push("--max-portal-connections-per-identity".to_string());
+        args.push(max.to_string());
+    }
+
+    if replicate_members {
+        args.push("--replicate-members".to_string());
+    }
+
+    if let Some(sink) = portal_events {
+        args.push("--portal-events".to_string());
+        args.push(sink.to_string());
+    }
+
+    if let Some(dns) = dns {
+        args.push("--dns".to_string());
+        args.push(dns.to_string());
+    }
+
+    for service in dns_services {
+        args.push("--dns-service".to_string());
+        args.push(service.to_string());
+    }
+
+    if let Some(resource_profile) = resource_profile {
+        args.push("--resource-profile".to_string());
+        args.push(resource_profile.to_string());
+    }
+
+    for (subsystem, limit) in memory_limits {
+        args.push("--memory-limit".to_string());
+        args.push(format!("{subsystem}={limit}"));
+    }
+
+    if warm_start {
+        args.push("--warm-start".to_string());
+    }
+
+    if let Some(path) = notifications {
+        args.push("--notifications".to_string());
+        args.push(
+            path.to_str()
+                .unwrap_or_else(|| panic!("unsupported path {path:?}"))
+                .to_string(),
+        );
+    }
+
+    if let Some(path) = metrics_exporter {
+        args.push("--metrics-exporter".to_string());
+        args.push(
+            path.to_str()
+                .unwrap_or_else(|| panic!("unsupported path {path:?}"))
+                .to_string(),
+        );
+    }
+
+    if let Some(path) = record_api {
+        args.push("--record-api".to_string());
+        args.push(
+            path.to_str()
+                .unwrap_or_else(|| panic!("unsupported path {path:?}"))
+                .to_string(),
+        );
+    }
+
+    for (kind, duration) in timeouts {
+        args.push("--timeout".to_string());
+        args.push(format!("{kind}={}ms", duration.as_millis()));
+    }
+
+    if let Some(threshold) = slow_storage_threshold {
+        args.push("--slow-storage-threshold".to_string());
+        args.push(format!("{}ms", threshold.as_millis()));
+    }
+
+    // the maintenance is enabled by default
+    args.push("--storage-maintenance-interval".to_string());
+    match storage_maintenance_interval {
+        Some(interval) => args.push(format!("{}s", interval.as_secs())),
+        None => args.push("0".to_string()),
+    }
+
+    // the sampling of the resource usage is enabled by default
+    args.push("--resource-usage-interval".to_string());
+    match resource_usage_interval {
+        Some(interval) => args.push(format!("{}s", interval.as_secs())),
+        None => args.push("0".to_string()),
+    }
+
+    if resume_portal_sessions {
+        args.push("--resume-portal-sessions".to_string());
+    }
+
+    if let Some(path) = outlet_resolver {
+        args.push("--outlet-resolver".to_string());
+        args.push(
+            path.to_str()
+                .unwrap_or_else(|| panic!("unsupported path {path:?}"))
+                .to_string(),
+        );
+    }
+
+    if let Some(path) = hooks {
+        args.push("--hooks".to_string());
+        args.push(
+            path.to_str()
+                .unwrap_or_else(|| panic!("unsupported path {path:?}"))
+                .to_string(),
+        );
+    }
+
+    if let Some(identifier) = control_identity {
+        args.push("--control-identity".to_string());
+        args.push(identifier.to_string());
+    }
+
+    if fleet_inventory {
+        args.push("--fleet".to_string());
+    }
+
+    if let Some(heartbeats) = heartbeats {
+        args.push("--report-to".to_string());
+        args.push(format!("{}@{}", heartbeats.control, heartbeats.route));
+        args.push("--heartbeat-interval".to_string());
+        args.push(format!("{}s", heartbeats.interval_secs));
+        for (key, value) in &heartbeats.labels {
+            args.push("--label".to_string());
+            args.push(format!("{key}={value}"));
+        }
+    }
+
+    if let Some(health_checks) = health_checks {
+        for target in &health_checks.targets {
+            args.push("--health-check".to_string());
+            args.push(format!("{}@{}", target.identifier, target.route));
+        }
+        args.push("--health-check-interval".to_string());
+        args.push(format!("{}s", health_checks.interval_secs));
+    }
+
     args.push(name.to_owned());
 
     run_ockam(opts, name, args, logging_to_file)
@@ -187,6 +401,19 @@ pub fn run_ockam(
         cmd.stdout(main_log_file).stderr(stderr_log_file);
     }
 
+    if let Some(process) = &node_state.config().setup().process {
+        apply_process_config(&mut cmd, process);
+    }
+
+    // The node must keep running when the console of the command is closed or interrupted
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW);
+    }
+
     let child = cmd
         .args(args)
         .stdin(Stdio::null())
@@ -198,3 +425,59 @@ pub fn run_ockam(
 
     Ok(())
 }
+
+/// Set the environment, the working directory and the resource limits of a node process
+fn apply_process_config(cmd: &mut Command, process: &NodeProcessConfig) {
+    cmd.envs(&process.env);
+    if let Some(working_dir) = &process.working_dir {
+        cmd.current_dir(working_dir);
+    }
+    if !process.limits.is_empty() {
+        apply_process_limits(cmd, process.limits.clone());
+    }
+}
+
+/// Set the resource limits of a process before it starts
+#[cfg(unix)]
+fn apply_process_limits(cmd: &mut Command, limits: Vec<ProcessLimit>) {
+    use nix::sys::resource::{getrlimit, rlim_t, setrlimit, Resource, RLIM_INFINITY};
+    use ockam_api::cli_state::ProcessResource;
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: getrlimit and setrlimit are async-signal-safe and don't allocate
+    unsafe {
+        cmd.pre_exec(move || {
+            for limit in &limits {
+                let resource = match limit.resource {
+                    ProcessResource::Nofile => Resource::RLIMIT_NOFILE,
+                    ProcessResource::Nproc => Resource::RLIMIT_NPROC,
+                    ProcessResource::Core => Resource::RLIMIT_CORE,
+                    ProcessResource::Fsize => Resource::RLIMIT_FSIZE,
+                    ProcessResource::Stack => Resource::RLIMIT_STACK,
+                    ProcessResource::Cpu => Resource::RLIMIT_CPU,
+                };
+                let value = |v: u64| {
+                    if v == u64::MAX {
+                        RLIM_INFINITY
+                    } else {
+                        v as rlim_t
+                    }
+                };
+                let hard = match limit.hard {
+                    Some(hard) => value(hard),
+                    None => getrlimit(resource)?.1,
+                };
+                setrlimit(resource, value(limit.soft), hard)?;
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Resource limits are only supported on Unix, the process is started without them
+#[cfg(windows)]
+fn apply_process_limits(_cmd: &mut Command, limits: Vec<ProcessLimit>) {
+    for limit in limits {
+        tracing::warn!(resource = %limit.resource, "resource limits are not supported on Windows");
+    }
+}
@@ -365,14 +365,7 @@ impl Output for VaultState {
     fn output(&self) -> Result<String> {
         let mut output = String::new();
         writeln!(output, "Name: {}", self.name())?;
-        writeln!(
-            output,
-            "Type: {}",
-            match self.config().is_aws() {
-                true => "AWS KMS",
-                false => "OCKAM",
-            }
-        )?;
+        writeln!(output, "Type: {}", self.config().kind())?;
         Ok(output)
     }
 }
@@ -511,6 +504,10 @@ impl fmt::Display for CredentialDisplay {
             human_readable_time(credential_data.expires_at)
         )?;
 
+        if let Some(audience) = &credential_data.audience {
+            writeln!(f, "Audience:                   {}", audience.join(", "))?;
+        }
+
         writeln!(f, "Attributes: ")?;
 
         write!(
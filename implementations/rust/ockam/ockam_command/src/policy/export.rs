@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam::Context;
+use ockam_api::cli_state::traits::{StateDirTrait, StateItemTrait};
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::policy_bundle::{PolicyBundle, DEFAULT_BUNDLE_SIGNATURE_VALIDITY};
+use ockam_core::api::Request;
+
+use crate::identity::{get_identity_name, initialize_identity_if_default};
+use crate::node::get_node_name;
+use crate::util::duration::duration_parser;
+use crate::util::{node_rpc, parse_node_name};
+use crate::vault::default_vault_name;
+use crate::{fmt_ok, CommandGlobalOpts};
+
+/// Export the policies of a node to a signed bundle file
+#[derive(Clone, Debug, Args)]
+pub struct ExportCommand {
+    #[arg(long, display_order = 900, id = "NODE_NAME")]
+    at: Option<String>,
+
+    /// Path of the bundle file to write
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
+
+    /// Name of the Identity signing the bundle
+    #[arg(long = "identity", value_name = "IDENTITY_NAME")]
+    identity: Option<String>,
+
+    /// Validity of the signature of the bundle, for example 30d
+    #[arg(long, value_name = "DURATION", value_parser = duration_parser)]
+    validity: Option<Duration>,
+}
+
+impl ExportCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.identity);
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, ExportCommand)) -> miette::Result<()> {
+    run_impl(&ctx, opts, cmd).await
+}
+
+async fn run_impl(
+    ctx: &Context,
+    opts: CommandGlobalOpts,
+    cmd: ExportCommand,
+) -> miette::Result<()> {
+    let at = get_node_name(&opts.state, &cmd.at);
+    let node_name = parse_node_name(&at)?;
+    let node = BackgroundNode::create(ctx, &opts.state, &node_name).await?;
+    let bundle: PolicyBundle = node.ask(ctx, Request::get("/policies")).await?;
+
+    let identity_name = get_identity_name(&opts.state, &cmd.identity);
+    let signer = opts
+        .state
+        .identities
+        .get(&identity_name)?
+        .config()
+        .identifier();
+    let vault = opts
+        .state
+        .vaults
+        .get(&default_vault_name(&opts.state))?
+        .get()
+        .await?;
+    let identities = opts.state.get_identities(vault).await?;
+    let count = bundle.policies.len();
+    let signed = bundle
+        .sign(
+            &identities,
+            &signer,
+            cmd.validity.unwrap_or(DEFAULT_BUNDLE_SIGNATURE_VALIDITY),
+        )
+        .await
+        .into_diagnostic()?;
+    signed.write(&cmd.output).into_diagnostic()?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "{count} policies of the node {node_name} have been exported to {}",
+            cmd.output.display()
+        ))
+        .machine(cmd.output.display())
+        .json(serde_json::json!({
+            "policies": count,
+            "at": &node_name,
+            "output": cmd.output,
+            "signer": signer.to_string(),
+        }))
+        .write_line()?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam::identity::{identities, Identifier};
+use ockam::Context;
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::policy_bundle::{
+    ImportPolicyBundle, MergeStrategy, PolicyImportReport, SignedPolicyBundle,
+};
+use ockam_core::api::Request;
+
+use crate::node::get_node_name;
+use crate::util::parsers::identity_identifier_parser;
+use crate::util::{node_rpc, parse_node_name};
+use crate::{fmt_ok, CommandGlobalOpts};
+
+/// Import a signed bundle of policies in a node
+#[derive(Clone, Debug, Args)]
+pub struct ImportCommand {
+    /// Path of the bundle file
+    #[arg(value_name = "FILE")]
+    file: PathBuf,
+
+    #[arg(long, display_order = 900, id = "NODE_NAME")]
+    at: Option<String>,
+
+    /// How the policies of the bundle are combined with the policies of the node:
+    /// merge, replace or keep-existing
+    #[arg(long, default_value = "merge")]
+    strategy: MergeStrategy,
+
+    /// Identifier of an Identity trusted to sign the bundle. The bundle is rejected if it is
+    /// not signed by one of these identities. If none is given, the signature of the bundle
+    /// is only checked against the identity embedded in the bundle
+    #[arg(long = "signed-by", value_name = "IDENTIFIER", value_parser = identity_identifier_parser)]
+    signed_by: Vec<Identifier>,
+}
+
+impl ImportCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, ImportCommand)) -> miette::Result<()> {
+    run_impl(&ctx, opts, cmd).await
+}
+
+async fn run_impl(
+    ctx: &Context,
+    opts: CommandGlobalOpts,
+    cmd: ImportCommand,
+) -> miette::Result<()> {
+    let at = get_node_name(&opts.state, &cmd.at);
+    let node_name = parse_node_name(&at)?;
+
+    // the signer identity is imported in a transient storage to verify the bundle
+    let signed = SignedPolicyBundle::read(&cmd.file).into_diagnostic()?;
+    let bundle = signed
+        .verify(&identities(), &cmd.signed_by)
+        .await
+        .into_diagnostic()?;
+
+    let node = BackgroundNode::create(ctx, &opts.state, &node_name).await?;
+    let req = Request::post("/policies").body(ImportPolicyBundle {
+        bundle,
+        strategy: cmd.strategy,
+    });
+    let report: PolicyImportReport = node.ask(ctx, req).await?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "The policies of {} have been imported in the node {node_name}: {} added, {} updated, {} removed, {} unchanged",
+            cmd.file.display(),
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len(),
+            report.unchanged
+        ))
+        .machine(format!(
+            "{} {} {} {}",
+            report.added.len(),
+            report.updated.len(),
+            report.removed.len(),
+            report.unchanged
+        ))
+        .json(serde_json::to_value(&report).into_diagnostic()?)
+        .write_line()?;
+    Ok(())
+}
@@ -10,12 +10,16 @@ use ockam_core::api::Request;
 
 use crate::policy::create::CreateCommand;
 use crate::policy::delete::DeleteCommand;
+use crate::policy::export::ExportCommand;
+use crate::policy::import::ImportCommand;
 use crate::policy::list::ListCommand;
 use crate::policy::show::ShowCommand;
 use crate::{CommandGlobalOpts, Result};
 
 mod create;
 mod delete;
+mod export;
+mod import;
 mod list;
 mod show;
 
@@ -32,6 +36,8 @@ pub enum PolicySubcommand {
     Show(ShowCommand),
     Delete(DeleteCommand),
     List(ListCommand),
+    Export(ExportCommand),
+    Import(ImportCommand),
 }
 
 impl PolicyCommand {
@@ -41,6 +47,8 @@ impl PolicyCommand {
             PolicySubcommand::Show(c) => c.run(opts),
             PolicySubcommand::Delete(c) => c.run(opts),
             PolicySubcommand::List(c) => c.run(opts),
+            PolicySubcommand::Export(c) => c.run(opts),
+            PolicySubcommand::Import(c) => c.run(opts),
         }
     }
 }
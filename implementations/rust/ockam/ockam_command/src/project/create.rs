@@ -62,7 +62,7 @@ async fn run_impl(
     let project = check_project_readiness(&opts, ctx, &node, project).await?;
     opts.state
         .projects
-        .overwrite(&project.name, project.clone())?;
+        .refresh(&project.name, project.clone())?;
     opts.state
         .trust_contexts
         .overwrite(&project.name, project.clone().try_into()?)?;
@@ -4,13 +4,13 @@ use tokio::sync::Mutex;
 use tokio::try_join;
 
 use ockam::Context;
-use ockam_api::cli_state::StateDirTrait;
+use ockam_api::cli_state::CACHE_MAX_AGE;
 use ockam_api::cloud::project::Projects;
 
 use ockam_api::nodes::InMemoryNode;
 
 use crate::util::api::CloudOpts;
-use crate::util::node_rpc;
+use crate::util::{node_rpc, warn_cached};
 use crate::{docs, CommandGlobalOpts};
 
 const LONG_ABOUT: &str = include_str!("./static/list/long_about.txt");
@@ -45,7 +45,7 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, _cmd: ListCommand) ->
     let is_finished: Mutex<bool> = Mutex::new(false);
 
     let get_projects = async {
-        let projects = controller.list_projects(ctx).await?;
+        let projects = controller.list_projects(ctx).await;
         *is_finished.lock().await = true;
         Ok(projects)
     };
@@ -57,18 +57,35 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, _cmd: ListCommand) ->
 
     let (projects, _) = try_join!(get_projects, progress_output)?;
 
+    // List the projects stored on this machine if the Orchestrator can't be reached
+    let projects = match projects {
+        Ok(projects) => {
+            for project in &projects {
+                opts.state
+                    .projects
+                    .refresh(&project.name, project.clone())?;
+            }
+            projects
+        }
+        Err(e) => {
+            let cached = opts.state.projects.list_cached()?;
+            if cached.is_empty() {
+                return Err(e);
+            }
+            let warning = cached
+                .iter()
+                .find_map(|p| p.staleness_warning(CACHE_MAX_AGE));
+            warn_cached(&opts, warning)?;
+            cached.into_iter().map(|p| p.value).collect()
+        }
+    };
+
     let plain =
         &opts
             .terminal
             .build_list(&projects, "Projects", "No projects found on this system.")?;
     let json = serde_json::to_string_pretty(&projects).into_diagnostic()?;
 
-    for project in &projects {
-        opts.state
-            .projects
-            .overwrite(&project.name, project.clone())?;
-    }
-
     opts.terminal
         .stdout()
         .plain(plain)
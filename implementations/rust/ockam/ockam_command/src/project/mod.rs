@@ -5,7 +5,7 @@ pub(crate) mod enroll;
 mod info;
 mod list;
 mod show;
-mod ticket;
+pub(crate) mod ticket;
 pub mod util;
 mod version;
 
@@ -2,7 +2,7 @@ use clap::Args;
 use miette::IntoDiagnostic;
 
 use ockam::Context;
-use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_api::cli_state::{StateDirTrait, StateItemTrait, CACHE_MAX_AGE};
 use ockam_api::cloud::project::Projects;
 
 use ockam_api::nodes::InMemoryNode;
@@ -10,7 +10,7 @@ use ockam_api::nodes::InMemoryNode;
 use crate::output::Output;
 use crate::project::util::refresh_projects;
 use crate::util::api::CloudOpts;
-use crate::util::node_rpc;
+use crate::util::{node_rpc, warn_cached};
 use crate::{docs, CommandGlobalOpts};
 
 const LONG_ABOUT: &str = include_str!("./static/show/long_about.txt");
@@ -56,16 +56,25 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, cmd: ShowCommand) -> m
         }
     };
 
-    // Send request
-    let project = controller.get_project(ctx, id).await?;
+    // Send request, or use the project stored on this machine if the Orchestrator can't be reached
+    let project = match controller.get_project(ctx, id).await {
+        Ok(project) => {
+            opts.state
+                .projects
+                .refresh(&project.name, project.clone())?;
+            project
+        }
+        Err(e) => {
+            let cached = opts.state.projects.get_cached(&cmd.name).map_err(|_| e)?;
+            warn_cached(&opts, cached.staleness_warning(CACHE_MAX_AGE))?;
+            cached.value
+        }
+    };
 
     opts.terminal
         .stdout()
         .plain(project.output()?)
         .json(serde_json::to_string_pretty(&project).into_diagnostic()?)
         .write_line()?;
-    opts.state
-        .projects
-        .overwrite(&project.name, project.clone())?;
     Ok(())
 }
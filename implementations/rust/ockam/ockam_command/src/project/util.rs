@@ -116,7 +116,7 @@ pub async fn check_project_readiness(
     // Persist project config prior to checking readiness which might take a while
     opts.state
         .projects
-        .overwrite(&project.name, project.clone())?;
+        .refresh(&project.name, project.clone())?;
 
     let spinner_option = opts.terminal.progress_spinner();
     let project = check_project_ready(
@@ -146,7 +146,7 @@ pub async fn check_project_readiness(
     // Persist project config with all its fields
     opts.state
         .projects
-        .overwrite(&project.name, project.clone())?;
+        .refresh(&project.name, project.clone())?;
     Ok(project)
 }
 
@@ -270,7 +270,7 @@ pub async fn refresh_projects(
     for project in projects {
         opts.state
             .projects
-            .overwrite(&project.name, project.clone())?;
+            .refresh(&project.name, project.clone())?;
     }
     Ok(())
 }
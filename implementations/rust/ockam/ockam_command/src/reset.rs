@@ -6,7 +6,7 @@ use colorful::Colorful;
 use miette::miette;
 use ockam_api::cli_state::CliState;
 
-/// Removes the local Ockam configuration including all Identities and Nodes
+/// Removes the local Ockam configuration of the current profile, including all Identities and Nodes
 #[derive(Clone, Debug, Args)]
 pub struct ResetCommand {
     /// Confirm the reset without prompting
@@ -35,7 +35,7 @@ fn run_impl(opts: CommandGlobalOpts, cmd: ResetCommand) -> miette::Result<()> {
             }
         }
     }
-    CliState::delete()?;
+    CliState::delete_at(&opts.state.dir)?;
     opts.terminal
         .stdout()
         .plain(fmt_ok!("Local Ockam configuration deleted"))
@@ -0,0 +1,67 @@
+use clap::Args;
+use colorful::Colorful;
+
+use ockam::Context;
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::Address;
+
+use crate::node::get_node_name;
+use crate::terminal::OckamColor;
+use crate::util::parse_node_name;
+use crate::{
+    docs, fmt_ok,
+    util::{api, node_rpc},
+    CommandGlobalOpts,
+};
+
+const LONG_ABOUT: &str = include_str!("./static/exchange_credentials/long_about.txt");
+const PREVIEW_TAG: &str = include_str!("../static/preview_tag.txt");
+const AFTER_LONG_HELP: &str = include_str!("./static/exchange_credentials/after_long_help.txt");
+
+/// Exchange credentials over an established Secure Channel
+#[derive(Clone, Debug, Args)]
+#[command(
+    arg_required_else_help = true,
+    long_about = docs::about(LONG_ABOUT),
+    before_help = docs::before_help(PREVIEW_TAG),
+    after_long_help = docs::after_help(AFTER_LONG_HELP),
+)]
+pub struct ExchangeCredentialsCommand {
+    /// Node at which the secure channel was initiated
+    #[arg(value_name = "NODE_NAME", long, display_order = 800)]
+    at: Option<String>,
+
+    /// Channel address
+    #[arg(display_order = 800)]
+    address: Address,
+}
+
+impl ExchangeCredentialsCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, ExchangeCredentialsCommand),
+) -> miette::Result<()> {
+    let at = get_node_name(&opts.state, &cmd.at);
+    let node_name = parse_node_name(&at)?;
+    let address = &cmd.address;
+
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    node.tell(&ctx, api::exchange_credentials(address)).await?;
+
+    let channel = address
+        .to_string()
+        .color(OckamColor::PrimaryResource.color());
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "Exchanged credentials over the secure channel {channel}"
+        ))
+        .json(serde_json::json!({ "channel": address.to_string() }))
+        .write_line()?;
+    Ok(())
+}
@@ -2,11 +2,13 @@ pub(crate) mod listener;
 
 mod create;
 mod delete;
+mod exchange_credentials;
 mod list;
 mod show;
 
 pub use create::CreateCommand;
 pub use delete::DeleteCommand;
+pub use exchange_credentials::ExchangeCredentialsCommand;
 pub use list::ListCommand;
 pub use show::ShowCommand;
 
@@ -36,6 +38,8 @@ enum SecureChannelSubcommand {
     #[command(display_order = 800)]
     Delete(DeleteCommand),
     #[command(display_order = 800)]
+    ExchangeCredentials(ExchangeCredentialsCommand),
+    #[command(display_order = 800)]
     List(ListCommand),
     #[command(display_order = 800)]
     Show(ShowCommand),
@@ -46,6 +50,7 @@ impl SecureChannelCommand {
         match self.subcommand {
             SecureChannelSubcommand::Create(c) => c.run(options),
             SecureChannelSubcommand::Delete(c) => c.run(options),
+            SecureChannelSubcommand::ExchangeCredentials(c) => c.run(options),
             SecureChannelSubcommand::List(c) => c.run(options),
             SecureChannelSubcommand::Show(c) => c.run(options),
         }
@@ -29,6 +29,13 @@ pub enum StartSubCommand {
         #[arg(long, default_value_t = hop_default_addr())]
         addr: String,
     },
+    /// Start a key-value store, only reachable through secure channels.
+    /// Each entry can be read and updated by its owner and by the identities
+    /// having the attributes listed in its access control list
+    KvStore {
+        #[arg(long, default_value_t = kv_store_default_addr())]
+        addr: String,
+    },
     Authenticated {
         #[arg(long, default_value_t = authenticated_default_addr())]
         addr: String,
@@ -56,6 +63,10 @@ fn hop_default_addr() -> String {
     DefaultAddress::HOP_SERVICE.to_string()
 }
 
+fn kv_store_default_addr() -> String {
+    DefaultAddress::KV_STORE.to_string()
+}
+
 fn authenticated_default_addr() -> String {
     DefaultAddress::AUTHENTICATED_SERVICE.to_string()
 }
@@ -89,6 +100,11 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, cmd: StartCommand) ->
             start_hop_service(ctx, &node, &addr).await?;
             addr
         }
+        StartSubCommand::KvStore { addr, .. } => {
+            let req = api::start_kv_store_service(&addr);
+            start_service_impl(ctx, &node, "Key-value store", req).await?;
+            addr
+        }
         StartSubCommand::Authenticated { addr, .. } => {
             let req = api::start_authenticated_service(&addr);
             start_service_impl(ctx, &node, "Authenticated", req).await?;
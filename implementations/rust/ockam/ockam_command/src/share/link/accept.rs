@@ -0,0 +1,137 @@
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::identity::identities;
+use ockam::Context;
+use ockam_api::cli_state::StateDirTrait;
+use ockam_api::nodes::models::portal::{CreateInlet, InletStatus};
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::portal_invitation::SignedPortalInvitation;
+use ockam_core::api::Request;
+use ockam_core::route;
+use ockam_multiaddr::MultiAddr;
+
+use crate::identity::initialize_identity_if_default;
+use crate::node::{get_node_name, is_node_up, spawn_background_node};
+use crate::project::enroll::{project_enroll, EnrollCommand};
+use crate::tcp::inlet::create::default_from_addr;
+use crate::terminal::OckamColor;
+use crate::util::api::{CloudOpts, TrustContextOpts};
+use crate::util::parsers::socket_addr_parser;
+use crate::util::{node_rpc, parse_node_name};
+use crate::{fmt_ok, CommandGlobalOpts};
+
+#[derive(Clone, Debug, Args)]
+pub struct AcceptCommand {
+    /// The invitation, or the path of a file containing it
+    #[arg(value_name = "INVITATION")]
+    invitation: String,
+
+    #[command(flatten)]
+    cloud_opts: CloudOpts,
+
+    /// Node on which the inlet is created. The node is created with the project of the
+    /// invitation if it doesn't exist
+    #[arg(long, display_order = 900, id = "NODE_NAME")]
+    at: Option<String>,
+
+    /// Address on which the inlet accepts tcp connections
+    #[arg(long, display_order = 900, id = "SOCKET_ADDRESS", hide_default_value = true, default_value_t = default_from_addr(), value_parser = socket_addr_parser)]
+    from: SocketAddr,
+}
+
+impl AcceptCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.cloud_opts.identity);
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, AcceptCommand)) -> miette::Result<()> {
+    run_impl(&ctx, opts, cmd).await
+}
+
+async fn run_impl(
+    ctx: &Context,
+    opts: CommandGlobalOpts,
+    cmd: AcceptCommand,
+) -> miette::Result<()> {
+    let encoded = std::fs::read_to_string(&cmd.invitation).unwrap_or(cmd.invitation.clone());
+    let signed = SignedPortalInvitation::hex_decoded(&encoded).into_diagnostic()?;
+    // the identity of the inviter is only imported to check the signature
+    let invitation = signed.verify(&identities()).await.into_diagnostic()?;
+
+    // enroll to the project with the ticket of the invitation
+    let project = project_enroll(
+        ctx,
+        &opts,
+        EnrollCommand {
+            okta: false,
+            enroll_ticket: Some(invitation.ticket.clone()),
+            cloud_opts: cmd.cloud_opts.clone(),
+            trust_opts: TrustContextOpts::default(),
+            new_trust_context_name: None,
+            force: false,
+        },
+    )
+    .await?;
+
+    let node_name = get_node_name(&opts.state, &cmd.at);
+    let node_name = parse_node_name(&node_name)?;
+    let mut node = BackgroundNode::create(ctx, &opts.state, &node_name).await?;
+    if !opts.state.nodes.exists(&node_name) {
+        let mut create = crate::node::CreateCommand::default();
+        create.node_name = node_name.clone();
+        create.identity = cmd.cloud_opts.identity.clone();
+        create.trust_context_opts.project = Some(project.clone());
+        spawn_background_node(&opts, create).await?;
+        is_node_up(ctx, &node_name, &mut node, opts.state.clone(), true).await?;
+    }
+
+    let mut payload = CreateInlet::via_service(
+        cmd.from.to_string(),
+        MultiAddr::from_str(&format!("/project/{project}")).into_diagnostic()?,
+        &invitation.service,
+        route![],
+        route![],
+    );
+    payload.set_wait_ms(Duration::from_secs(5).as_millis() as u64);
+    let inlet: InletStatus = node
+        .ask(ctx, Request::post("/node/inlet").body(payload))
+        .await
+        .map_err(|e| {
+            miette!(
+                "The inlet to the service {} can't be created: {e}",
+                invitation.service
+            )
+        })?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "Accepted the invitation to the service {}, which is available at {}",
+            invitation
+                .service
+                .clone()
+                .color(OckamColor::PrimaryResource.color()),
+            inlet
+                .bind_addr
+                .clone()
+                .color(OckamColor::PrimaryResource.color())
+        ))
+        .machine(&inlet.bind_addr)
+        .json(serde_json::json!({
+            "id": invitation.id,
+            "service": invitation.service,
+            "project": project,
+            "at": node_name,
+            "from": inlet.bind_addr,
+        }))
+        .write_line()?;
+    Ok(())
+}
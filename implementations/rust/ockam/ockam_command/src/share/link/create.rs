@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::Context;
+use ockam_api::authenticator::enrollment_tokens::TokenIssuer;
+use ockam_api::cli_state::{InvitationConfig, StateDirTrait, StateItemTrait};
+use ockam_api::identity::EnrollmentTicket;
+use ockam_api::portal_invitation::{PortalInvitation, INVITATION_ID_ATTRIBUTE};
+use ockam_core::compat::rand::random_string;
+use ockam_multiaddr::MultiAddr;
+
+use crate::identity::{get_identity_name, initialize_identity_if_default};
+use crate::share::link::project_authority;
+use crate::terminal::OckamColor;
+use crate::util::api::{CloudOpts, TrustContextOpts};
+use crate::util::duration::duration_parser;
+use crate::util::node_rpc;
+use crate::vault::default_vault_name;
+use crate::{fmt_ok, CommandGlobalOpts};
+
+#[derive(Clone, Debug, Args)]
+pub struct CreateCommand {
+    #[command(flatten)]
+    cloud_opts: CloudOpts,
+
+    #[command(flatten)]
+    trust_opts: TrustContextOpts,
+
+    /// Name of the service, published in the service registry of the project
+    #[arg(long, value_name = "SERVICE_NAME")]
+    service: String,
+
+    /// Project the invitee is enrolled to
+    #[arg(long, short, default_value = "/project/default")]
+    to: MultiAddr,
+
+    /// Attributes in `key=value` format given to the invitee, for example the attributes
+    /// required by the policy of the service outlet
+    #[arg(short, long = "attribute", value_name = "ATTRIBUTE")]
+    attributes: Vec<String>,
+
+    /// Time after which the invitation can't be accepted anymore
+    #[arg(long = "expires-in", value_name = "DURATION", default_value = "7d", value_parser = duration_parser)]
+    expires_in: Duration,
+}
+
+impl CreateCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.cloud_opts.identity);
+        node_rpc(rpc, (opts, self));
+    }
+
+    fn attributes(&self) -> miette::Result<BTreeMap<String, String>> {
+        let mut attributes = BTreeMap::new();
+        for attr in &self.attributes {
+            let (key, value) = attr
+                .split_once('=')
+                .ok_or(miette!("the attribute {attr} must have the form key=value"))?;
+            if key == INVITATION_ID_ATTRIBUTE {
+                return Err(miette!("the attribute {key} is reserved"));
+            }
+            attributes.insert(key.to_string(), value.to_string());
+        }
+        Ok(attributes)
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, CreateCommand)) -> miette::Result<()> {
+    run_impl(&ctx, opts, cmd).await
+}
+
+async fn run_impl(
+    ctx: &Context,
+    opts: CommandGlobalOpts,
+    cmd: CreateCommand,
+) -> miette::Result<()> {
+    opts.state.invitations.check_rate_limit()?;
+    let attributes = cmd.attributes()?;
+
+    let (_node, project, authority_node) =
+        project_authority(ctx, &opts, &cmd.cloud_opts, &cmd.trust_opts, &cmd.to).await?;
+
+    // the invitation id is given to the members enrolled with the invitation,
+    // so that they can be removed when the invitation is revoked
+    let id = random_string();
+    let mut token_attributes: HashMap<&str, &str> = attributes
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    token_attributes.insert(INVITATION_ID_ATTRIBUTE, &id);
+    let token = authority_node
+        .create_token(ctx, token_attributes, Some(cmd.expires_in))
+        .await?;
+    let ticket = EnrollmentTicket::new(token, Some(project.clone()), None);
+    let invitation = PortalInvitation::new(
+        &id,
+        &cmd.service,
+        attributes.clone(),
+        ticket,
+        cmd.expires_in,
+    );
+    let expires_at = invitation.expires_at;
+
+    let identity_name = get_identity_name(&opts.state, &cmd.cloud_opts.identity);
+    let inviter = opts
+        .state
+        .identities
+        .get(&identity_name)?
+        .config()
+        .identifier();
+    let vault = opts
+        .state
+        .vaults
+        .get(&default_vault_name(&opts.state))?
+        .get()
+        .await?;
+    let identities = opts.state.get_identities(vault).await?;
+    let encoded = invitation
+        .sign(&identities, &inviter)
+        .await
+        .into_diagnostic()?
+        .hex_encoded()
+        .into_diagnostic()?;
+
+    opts.state.invitations.create(
+        &id,
+        InvitationConfig {
+            id: id.clone(),
+            service: cmd.service.clone(),
+            attributes,
+            project: project.name.clone(),
+            created_at: expires_at.saturating_sub(cmd.expires_in.as_secs()),
+            expires_at,
+            revoked_at: None,
+        },
+    )?;
+
+    opts.terminal
+        .stdout()
+        .plain(
+            fmt_ok!(
+                "Created the invitation {} to the service {}\n\n",
+                id.clone().color(OckamColor::PrimaryResource.color()),
+                cmd.service
+            ) + &encoded,
+        )
+        .machine(&encoded)
+        .json(serde_json::json!({
+            "id": id,
+            "service": cmd.service,
+            "project": project.name,
+            "expires_at": expires_at,
+            "invitation": encoded,
+        }))
+        .write_line()?;
+    Ok(())
+}
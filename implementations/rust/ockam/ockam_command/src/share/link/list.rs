@@ -0,0 +1,39 @@
+use clap::Args;
+use miette::IntoDiagnostic;
+
+use ockam::Context;
+use ockam_api::cli_state::{InvitationConfig, StateDirTrait, StateItemTrait};
+
+use crate::util::node_rpc;
+use crate::CommandGlobalOpts;
+
+#[derive(Clone, Debug, Args)]
+pub struct ListCommand {}
+
+impl ListCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(_ctx: Context, (opts, _cmd): (CommandGlobalOpts, ListCommand)) -> miette::Result<()> {
+    let invitations: Vec<InvitationConfig> = opts
+        .state
+        .invitations
+        .list()?
+        .into_iter()
+        .map(|i| i.config().clone())
+        .collect();
+    let plain = opts.terminal.build_list(
+        &invitations,
+        "Invitations",
+        "No invitations were created on this machine.",
+    )?;
+    let json = serde_json::to_string_pretty(&invitations).into_diagnostic()?;
+    opts.terminal
+        .stdout()
+        .plain(plain)
+        .json(json)
+        .write_line()?;
+    Ok(())
+}
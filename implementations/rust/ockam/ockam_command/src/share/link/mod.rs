@@ -0,0 +1,87 @@
+use clap::{Args, Subcommand};
+use miette::miette;
+
+use ockam::Context;
+use ockam_api::cloud::AuthorityNode;
+use ockam_api::config::lookup::ProjectLookup;
+use ockam_api::nodes::InMemoryNode;
+use ockam_multiaddr::MultiAddr;
+
+use crate::identity::get_identity_name;
+use crate::project::ticket::get_project;
+use crate::util::api::{CloudOpts, TrustContextOpts};
+use crate::CommandGlobalOpts;
+
+mod accept;
+mod create;
+mod list;
+mod revoke;
+
+pub use accept::AcceptCommand;
+pub use create::CreateCommand;
+pub use list::ListCommand;
+pub use revoke::RevokeCommand;
+
+/// Manage signed invitations giving access to a service of a project
+#[derive(Clone, Debug, Args)]
+#[command(arg_required_else_help = true, subcommand_required = true)]
+pub struct LinkCommand {
+    #[command(subcommand)]
+    subcommand: LinkSubcommand,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum LinkSubcommand {
+    /// Create an invitation to access a service
+    Create(CreateCommand),
+    /// Accept an invitation: enroll to its project and create an inlet to its service
+    Accept(AcceptCommand),
+    /// List the invitations created on this machine
+    List(ListCommand),
+    /// Revoke an invitation and remove the identities enrolled with it from the project
+    Revoke(RevokeCommand),
+}
+
+impl LinkCommand {
+    pub fn run(self, options: CommandGlobalOpts) {
+        use LinkSubcommand::*;
+        match self.subcommand {
+            Create(c) => c.run(options),
+            Accept(c) => c.run(options),
+            List(c) => c.run(options),
+            Revoke(c) => c.run(options),
+        }
+    }
+}
+
+/// Start an in-memory node and create a client for the authority of a project.
+/// The node must be kept while the client is used
+async fn project_authority(
+    ctx: &Context,
+    opts: &CommandGlobalOpts,
+    cloud_opts: &CloudOpts,
+    trust_opts: &TrustContextOpts,
+    to: &MultiAddr,
+) -> miette::Result<(InMemoryNode, ProjectLookup, AuthorityNode)> {
+    let trust_context_config = trust_opts.to_config(&opts.state)?.build();
+    let node = InMemoryNode::start_with_trust_context(
+        ctx,
+        &opts.state,
+        trust_opts.project_path.as_ref(),
+        trust_context_config,
+    )
+    .await?;
+    let (project, authority) = match get_project(&opts.state, to).await? {
+        (Some(project), Some(authority)) => (project, authority),
+        _ => {
+            return Err(miette!(
+                "Please specify the route to a project, for example /project/default"
+            ))
+        }
+    };
+    let identity = get_identity_name(&opts.state, &cloud_opts.identity);
+    let authority_node = node
+        .create_authority_client(authority.identity_id(), authority.address(), Some(identity))
+        .await?;
+    Ok((node, project, authority_node))
+}
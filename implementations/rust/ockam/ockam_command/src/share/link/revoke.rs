@@ -0,0 +1,83 @@
+use std::str::FromStr;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam::Context;
+use ockam_api::authenticator::enrollment_tokens::Members;
+use ockam_api::cli_state::StateItemTrait;
+use ockam_api::portal_invitation::INVITATION_ID_ATTRIBUTE;
+use ockam_multiaddr::MultiAddr;
+
+use crate::identity::initialize_identity_if_default;
+use crate::share::link::project_authority;
+use crate::terminal::OckamColor;
+use crate::util::api::{CloudOpts, TrustContextOpts};
+use crate::util::node_rpc;
+use crate::{fmt_ok, CommandGlobalOpts};
+
+#[derive(Clone, Debug, Args)]
+pub struct RevokeCommand {
+    /// Id of the invitation
+    id: String,
+
+    #[command(flatten)]
+    cloud_opts: CloudOpts,
+
+    #[command(flatten)]
+    trust_opts: TrustContextOpts,
+}
+
+impl RevokeCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_identity_if_default(&opts, &self.cloud_opts.identity);
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, RevokeCommand)) -> miette::Result<()> {
+    run_impl(&ctx, opts, cmd).await
+}
+
+async fn run_impl(
+    ctx: &Context,
+    opts: CommandGlobalOpts,
+    cmd: RevokeCommand,
+) -> miette::Result<()> {
+    // the identities which accepted the invitation are removed from the project.
+    // The enrollment ticket of the invitation stays valid until the invitation expires
+    let invitation = opts.state.invitations.revoke(&cmd.id)?;
+    let project = &invitation.config().project;
+    let to = MultiAddr::from_str(&format!("/project/{project}")).into_diagnostic()?;
+    let (_node, _, authority_node) =
+        project_authority(ctx, &opts, &cmd.cloud_opts, &cmd.trust_opts, &to).await?;
+
+    let mut removed = vec![];
+    for (identifier, entry) in authority_node.list_members(ctx).await? {
+        let invitation_id = entry.attrs().get(INVITATION_ID_ATTRIBUTE.as_bytes());
+        if invitation_id.map(|id| id.as_slice()) == Some(cmd.id.as_bytes()) {
+            authority_node
+                .delete_member(ctx, identifier.clone())
+                .await?;
+            removed.push(identifier.to_string());
+        }
+    }
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "Revoked the invitation {}, {} enrolled identities were removed from the project {}",
+            cmd.id.clone().color(OckamColor::PrimaryResource.color()),
+            removed.len(),
+            project
+        ))
+        .machine(&cmd.id)
+        .json(serde_json::json!({
+            "id": cmd.id,
+            "project": project,
+            "removed": removed,
+        }))
+        .write_line()?;
+    Ok(())
+}
@@ -4,6 +4,7 @@ use crate::CommandGlobalOpts;
 
 mod accept;
 mod create;
+mod link;
 mod list;
 mod output;
 mod service;
@@ -11,6 +12,7 @@ mod show;
 
 pub use accept::AcceptCommand;
 pub use create::CreateCommand;
+pub use link::LinkCommand;
 pub use list::ListCommand;
 pub use service::ServiceCreateCommand;
 pub use show::ShowCommand;
@@ -29,6 +31,8 @@ pub enum ShareSubcommand {
     Accept(AcceptCommand),
     /// Create an invitation for another user to join a Space or Project
     Create(CreateCommand),
+    /// Manage signed invitation links giving access to a service, without the Orchestrator
+    Link(LinkCommand),
     /// List sharing invitations you've created or received
     List(ListCommand),
     /// Revoke a sharing invitation you've previously created
@@ -45,6 +49,7 @@ impl ShareCommand {
         match self.subcommand {
             Accept(c) => c.run(options),
             Create(c) => c.run(options),
+            Link(c) => c.run(options),
             List(c) => c.run(options),
             Revoke => todo!(),
             Service(c) => c.run(options),
@@ -1,3 +1,4 @@
+use ockam_api::cli_state::InvitationConfig;
 use ockam_api::cloud::share::{ReceivedInvitation, SentInvitation};
 
 use crate::error::Result;
@@ -20,3 +21,16 @@ impl Output for SentInvitation {
         ))
     }
 }
+
+impl Output for InvitationConfig {
+    fn output(&self) -> Result<String> {
+        Ok(format!(
+            "{}\n  service: {} project: {} (expires {}) status: {}",
+            self.id,
+            self.service,
+            self.project,
+            self.expires_at,
+            self.status()
+        ))
+    }
+}
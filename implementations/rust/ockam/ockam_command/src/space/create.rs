@@ -7,7 +7,7 @@ use crate::util::{is_enrolled_guard, node_rpc};
 use crate::{docs, CommandGlobalOpts};
 use colorful::Colorful;
 use ockam_api::cli_state::random_name;
-use ockam_api::cli_state::{SpaceConfig, StateDirTrait};
+use ockam_api::cli_state::SpaceConfig;
 use ockam_api::nodes::InMemoryNode;
 
 const LONG_ABOUT: &str = include_str!("./static/create/long_about.txt");
@@ -66,7 +66,7 @@ async fn run_impl(
     opts.println(&space)?;
     opts.state
         .spaces
-        .overwrite(&space.name, SpaceConfig::from(&space))?;
+        .refresh(&space.name, SpaceConfig::from(&space))?;
     Ok(())
 }
 
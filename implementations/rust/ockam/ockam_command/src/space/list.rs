@@ -4,13 +4,13 @@ use tokio::sync::Mutex;
 use tokio::try_join;
 
 use ockam::Context;
-use ockam_api::cli_state::{SpaceConfig, StateDirTrait};
-use ockam_api::cloud::space::Spaces;
+use ockam_api::cli_state::{SpaceConfig, CACHE_MAX_AGE};
+use ockam_api::cloud::space::{Space, Spaces};
 
 use ockam_api::nodes::InMemoryNode;
 
 use crate::util::api::CloudOpts;
-use crate::util::node_rpc;
+use crate::util::{node_rpc, warn_cached};
 use crate::{docs, CommandGlobalOpts};
 
 const LONG_ABOUT: &str = include_str!("./static/list/long_about.txt");
@@ -45,7 +45,7 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, _cmd: ListCommand) ->
     let controller = node.create_controller().await?;
 
     let get_spaces = async {
-        let spaces = controller.list_spaces(ctx).await?;
+        let spaces = controller.list_spaces(ctx).await;
         *is_finished.lock().await = true;
         Ok(spaces)
     };
@@ -58,6 +58,29 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, _cmd: ListCommand) ->
 
     let (spaces, _) = try_join!(get_spaces, progress_output)?;
 
+    // List the spaces stored on this machine if the Orchestrator can't be reached
+    let spaces: Vec<Space> = match spaces {
+        Ok(spaces) => {
+            for space in &spaces {
+                opts.state
+                    .spaces
+                    .refresh(&space.name, SpaceConfig::from(space))?;
+            }
+            spaces
+        }
+        Err(e) => {
+            let cached = opts.state.spaces.list_cached()?;
+            if cached.is_empty() {
+                return Err(e);
+            }
+            let warning = cached
+                .iter()
+                .find_map(|s| s.staleness_warning(CACHE_MAX_AGE));
+            warn_cached(&opts, warning)?;
+            cached.iter().map(|s| Space::from(&s.value)).collect()
+        }
+    };
+
     let plain = opts.terminal.build_list(
         &spaces,
         "Spaces",
@@ -65,12 +88,6 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, _cmd: ListCommand) ->
     )?;
     let json = serde_json::to_string_pretty(&spaces).into_diagnostic()?;
 
-    for space in spaces {
-        opts.state
-            .spaces
-            .overwrite(&space.name, SpaceConfig::from(&space))?;
-    }
-
     opts.terminal
         .stdout()
         .plain(plain)
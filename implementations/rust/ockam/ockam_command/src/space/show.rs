@@ -2,13 +2,13 @@ use clap::Args;
 use miette::IntoDiagnostic;
 
 use ockam::Context;
-use ockam_api::cli_state::{SpaceConfig, StateDirTrait, StateItemTrait};
+use ockam_api::cli_state::{SpaceConfig, StateDirTrait, StateItemTrait, CACHE_MAX_AGE};
 use ockam_api::cloud::space::{Space, Spaces};
 use ockam_api::nodes::InMemoryNode;
 
 use crate::output::Output;
 use crate::util::api::CloudOpts;
-use crate::util::node_rpc;
+use crate::util::{node_rpc, warn_cached};
 use crate::{docs, CommandGlobalOpts};
 
 const LONG_ABOUT: &str = include_str!("./static/show/long_about.txt");
@@ -48,14 +48,24 @@ async fn run_impl(ctx: &Context, opts: CommandGlobalOpts, cmd: ShowCommand) -> m
     // Send request
     let node = InMemoryNode::start(ctx, &opts.state).await?;
     let controller = node.create_controller().await?;
-    let space: Space = controller.get_space(ctx, id).await?;
+    // Use the space stored on this machine if the Orchestrator can't be reached
+    let space: Space = match controller.get_space(ctx, id).await {
+        Ok(space) => {
+            opts.state
+                .spaces
+                .refresh(&cmd.name, SpaceConfig::from(&space))?;
+            space
+        }
+        Err(e) => {
+            let cached = opts.state.spaces.get_cached(&cmd.name).map_err(|_| e)?;
+            warn_cached(&opts, cached.staleness_warning(CACHE_MAX_AGE))?;
+            Space::from(&cached.value)
+        }
+    };
     opts.terminal
         .stdout()
         .plain(space.output()?)
         .json(serde_json::to_string_pretty(&space).into_diagnostic()?)
         .write_line()?;
-    opts.state
-        .spaces
-        .overwrite(&cmd.name, SpaceConfig::from(&space))?;
     Ok(())
 }
@@ -1,5 +1,5 @@
 use ockam::Context;
-use ockam_api::cli_state::{SpaceConfig, StateDirTrait};
+use ockam_api::cli_state::SpaceConfig;
 use ockam_api::cloud::space::Spaces;
 use ockam_api::cloud::Controller;
 
@@ -15,7 +15,7 @@ async fn refresh_spaces(
     for space in spaces {
         opts.state
             .spaces
-            .overwrite(&space.name, SpaceConfig::from(&space))?;
+            .refresh(&space.name, SpaceConfig::from(&space))?;
     }
     Ok(())
 }
@@ -127,7 +127,7 @@ async fn get_orchestrator_version(
         }
         .get()
         .await?;
-        let identities_repository = opts.state.identities.identities_repository().await?;
+        let identities_repository = opts.state.identities_repository().await?;
         Node::builder()
             .with_vault(vault)
             .with_identities_repository(identities_repository)
@@ -1,11 +1,15 @@
 use core::fmt::Write;
+use std::time::Duration;
 
 use clap::builder::NonEmptyStringValueParser;
 use clap::{Args, Subcommand};
 use miette::{miette, IntoDiagnostic};
+use time::format_description::well_known::Iso8601;
+use time::OffsetDateTime;
 
 use ockam::Context;
-use ockam_api::cloud::subscription::{Subscription, Subscriptions};
+use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_api::cloud::subscription::{Subscription, SubscriptionUsage, Subscriptions};
 use ockam_api::cloud::Controller;
 
 use ockam_api::nodes::InMemoryNode;
@@ -15,6 +19,9 @@ use crate::util::api::CloudOpts;
 use crate::util::node_rpc;
 use crate::{docs, CommandGlobalOpts, Result};
 
+/// Time during which the usage of a subscription is not retrieved again
+const SUBSCRIPTION_USAGE_MAX_AGE: Duration = Duration::from_secs(60);
+
 #[derive(Clone, Debug, Args)]
 #[command(hide = docs::hide())]
 pub struct SubscriptionCommand {
@@ -45,6 +52,27 @@ pub enum SubscriptionSubcommand {
         )]
         space_id: Option<String>,
     },
+    /// Show the plan of a space, with the limits and the current usage of its resources.
+    /// The default space is used if no space ID is given.
+    Usage {
+        /// Space ID
+        #[arg(
+            id = "space",
+            value_name = "SPACE_ID",
+            long,
+            value_parser(NonEmptyStringValueParser::new())
+        )]
+        space_id: Option<String>,
+
+        /// Fail if COUNT more resources can't be created, for example `--check relays=2`.
+        /// COUNT is 1 by default
+        #[arg(long, value_name = "RESOURCE[=COUNT]")]
+        check: Option<String>,
+
+        /// Retrieve the usage from the Orchestrator even if it was recently retrieved
+        #[arg(long)]
+        refresh: bool,
+    },
 }
 
 impl SubscriptionCommand {
@@ -61,6 +89,39 @@ async fn run_impl(
     let controller = node.create_controller().await?;
 
     match cmd.subcommand {
+        SubscriptionSubcommand::Usage {
+            space_id,
+            check,
+            refresh,
+        } => {
+            let space_id = match space_id {
+                Some(space_id) => space_id,
+                None => opts.state.spaces.default()?.config().id.clone(),
+            };
+            let usage = get_subscription_usage(&opts, &controller, &ctx, space_id, refresh).await?;
+            opts.terminal
+                .stdout()
+                .plain(usage.output()?)
+                .json(serde_json::json!(&usage))
+                .write_line()?;
+            if let Some(check) = check {
+                let (resource, count) = match check.split_once('=') {
+                    Some((resource, count)) => (
+                        resource,
+                        count
+                            .parse::<u64>()
+                            .map_err(|_| miette!("invalid count in '{check}'"))?,
+                    ),
+                    None => (check.as_str(), 1),
+                };
+                if !usage.allows(resource, count) {
+                    return Err(miette!(
+                        "The plan of the space {} doesn't allow {count} more {resource}",
+                        usage.space_id
+                    ));
+                }
+            }
+        }
         SubscriptionSubcommand::Show {
             subscription_id,
             space_id,
@@ -72,7 +133,7 @@ async fn run_impl(
                 None => opts
                     .terminal
                     .write_line("Please specify either a space id or a subscription id")?,
-            }
+            };
         }
     };
     Ok(())
@@ -110,6 +171,54 @@ pub(crate) async fn get_subscription_by_id_or_space_id(
     }
 }
 
+/// Return the usage of the subscription of a space, from the cache if it was recently retrieved
+pub(crate) async fn get_subscription_usage(
+    opts: &CommandGlobalOpts,
+    controller: &Controller,
+    ctx: &Context,
+    space_id: String,
+    refresh: bool,
+) -> Result<SubscriptionUsage> {
+    if !refresh {
+        if let Some(usage) = opts
+            .state
+            .subscriptions
+            .get_fresh(&space_id, SUBSCRIPTION_USAGE_MAX_AGE)
+        {
+            return Ok(usage);
+        }
+    }
+    let usage = controller
+        .get_subscription_usage(ctx, space_id)
+        .await
+        .and_then(|s| s.success())
+        .into_diagnostic()?;
+    opts.state.subscriptions.cache(usage.clone())?;
+    Ok(usage)
+}
+
+impl Output for SubscriptionUsage {
+    fn output(&self) -> Result<String> {
+        let mut w = String::new();
+        write!(w, "Plan: {}", self.plan)?;
+        write!(w, "\n  Space id: {}", self.space_id)?;
+        if let Some(trial_ends_at) = self.trial_ends_at {
+            let trial_ends_at = OffsetDateTime::from_unix_timestamp(trial_ends_at as i64)
+                .into_diagnostic()?
+                .format(&Iso8601::DEFAULT)
+                .into_diagnostic()?;
+            write!(w, "\n  Trial ends at: {trial_ends_at}")?;
+        }
+        for r in &self.resources {
+            match r.limit {
+                Some(limit) => write!(w, "\n  {}: {} of {limit}", r.resource, r.used)?,
+                None => write!(w, "\n  {}: {} (unlimited)", r.resource, r.used)?,
+            }
+        }
+        Ok(w)
+    }
+}
+
 impl Output for Subscription {
     fn output(&self) -> Result<String> {
         let mut w = String::new();
@@ -1,4 +1,6 @@
+use std::fmt::{Display, Formatter};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
 use std::thread::sleep;
 use std::time::Duration;
@@ -14,14 +16,18 @@ use ockam::identity::Identifier;
 use ockam::Context;
 use ockam_abac::Resource;
 use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
-use ockam_api::nodes::models::portal::CreateInlet;
+use ockam_api::mqtt::MqttPortalConfig;
 use ockam_api::nodes::models::portal::InletStatus;
+use ockam_api::nodes::models::portal::{CreateInlet, TransparentInletMapping};
 use ockam_api::nodes::BackgroundNode;
+use ockam_api::port_range::PortRange;
 use ockam_core::api::{Reply, Request, Status};
+use ockam_core::compat::rand::random_string;
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{route, Error};
 use ockam_multiaddr::proto::Project;
 use ockam_multiaddr::{MultiAddr, Protocol as _};
+use ockam_transport_tcp::{DestinationRule, TransparentProxyMode};
 
 use crate::node::{get_node_name, initialize_node_if_default};
 use crate::policy::{add_default_project_policy, has_policy};
@@ -48,9 +54,10 @@ pub struct CreateCommand {
     #[arg(long, display_order = 900, id = "SOCKET_ADDRESS", hide_default_value = true, default_value_t = default_from_addr(), value_parser = socket_addr_parser)]
     from: SocketAddr,
 
-    /// Route to a tcp outlet.
+    /// Route to a tcp outlet, or `service:<name>` to connect to one of the outlets
+    /// publishing a service in the registry of the node project.
     #[arg(long, display_order = 900, id = "ROUTE", default_value_t = default_to_addr())]
-    to: MultiAddr,
+    to: InletTarget,
 
     /// Authorized identity for secure channel connection
     #[arg(long, name = "AUTHORIZED", display_order = 900)]
@@ -67,6 +74,45 @@ pub struct CreateCommand {
     /// Time to wait before retrying to connect to outlet.
     #[arg(long, display_order = 900, id = "RETRY", default_value = "20s", value_parser = duration_parser)]
     retry_wait: Duration,
+
+    /// Name resolving to the address of the inlet, for example `postgres.internal.ockam`,
+    /// if the node has been created with a DNS responder
+    #[arg(long, display_order = 900, id = "DNS_NAME")]
+    dns_name: Option<String>,
+
+    /// Only listen on a port of this range, for example `41000-41999`. With a port 0 in
+    /// `--from`, for example `127.0.0.1:0`, the inlet listens on a free port of the range
+    #[arg(long, display_order = 900, id = "PORT_RANGE")]
+    port_range: Option<PortRange>,
+
+    /// Accept the connections redirected to the inlet by iptables, with the `redirect`
+    /// (REDIRECT or DNAT targets) or `tproxy` (TPROXY target) mode. With the `capture` mode,
+    /// on Linux, the inlet installs the redirection of the mapped destinations itself with
+    /// nftables, which requires the `CAP_NET_ADMIN` capability, and removes it when deleted.
+    /// Each connection is sent to an outlet depending on its original destination, see `--map`
+    #[arg(long, display_order = 901, id = "MODE", requires = "MAPPING", value_parser = TransparentProxyMode::from_str)]
+    transparent: Option<TransparentProxyMode>,
+
+    /// Send the connections of a transparent inlet to an outlet, if their original destination
+    /// matches an IP address or a subnet, with an optional port.
+    /// For example `10.0.0.0/24:5432=/project/default/service/forward_to_db/secure/api/service/outlet`
+    #[arg(long, display_order = 901, id = "MAPPING", requires = "MODE", value_parser = parse_mapping)]
+    map: Vec<(DestinationRule, MultiAddr)>,
+
+    /// Path of a YAML file listing the MQTT topics whose payloads are encrypted with a key
+    /// shared by the inlets, so that the broker and the outlet only see encrypted payloads
+    #[arg(long, display_order = 902, value_name = "FILE")]
+    mqtt: Option<PathBuf>,
+}
+
+fn parse_mapping(input: &str) -> std::result::Result<(DestinationRule, MultiAddr), String> {
+    let (destination, route) = input
+        .split_once('=')
+        .ok_or("The mapping must have the form <destination>=<route>")?;
+    let destination = DestinationRule::from_str(destination)
+        .map_err(|_| format!("Invalid destination {destination}"))?;
+    let route = MultiAddr::from_str(route).map_err(|e| e.to_string())?;
+    Ok((destination, route))
 }
 
 pub(crate) fn default_from_addr() -> SocketAddr {
@@ -74,9 +120,45 @@ pub(crate) fn default_from_addr() -> SocketAddr {
     SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port)
 }
 
-fn default_to_addr() -> MultiAddr {
-    MultiAddr::from_str("/project/default/service/forward_to_default/secure/api/service/outlet")
-        .expect("Failed to parse default multiaddr")
+fn default_to_addr() -> InletTarget {
+    InletTarget::Route(
+        MultiAddr::from_str(
+            "/project/default/service/forward_to_default/secure/api/service/outlet",
+        )
+        .expect("Failed to parse default multiaddr"),
+    )
+}
+
+/// Destination of an inlet
+#[derive(Clone, Debug)]
+enum InletTarget {
+    /// Route to an outlet
+    Route(MultiAddr),
+    /// Name of a service published in the service registry of the project
+    Service(String),
+}
+
+impl FromStr for InletTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.strip_prefix("service:") {
+            Some("") => Err("the service name can't be empty".to_string()),
+            Some(name) => Ok(InletTarget::Service(name.to_string())),
+            None => MultiAddr::from_str(s)
+                .map(InletTarget::Route)
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl Display for InletTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InletTarget::Route(route) => route.fmt(f),
+            InletTarget::Service(name) => write!(f, "service:{name}"),
+        }
+    }
 }
 
 impl CreateCommand {
@@ -98,7 +180,35 @@ async fn rpc(
     ))?;
     display_parse_logs(&opts);
 
-    cmd.to = process_nodes_multiaddr(&cmd.to, &opts.state)?;
+    if let InletTarget::Route(to) = &cmd.to {
+        cmd.to = InletTarget::Route(process_nodes_multiaddr(to, &opts.state)?);
+    }
+    if cmd.transparent.is_some() && matches!(cmd.to, InletTarget::Service(_)) {
+        return Err(miette!("--transparent can not be used with a service"));
+    }
+    if let Some(port_range) = cmd.port_range {
+        let port = cmd.from.port();
+        if port != 0 && !(port_range.start()..=port_range.end()).contains(&port) {
+            return Err(miette!("The port {port} is not in the range {port_range}"));
+        }
+    }
+    let mappings = cmd
+        .map
+        .iter()
+        .map(|(destination, route)| {
+            Ok(TransparentInletMapping::new(
+                destination.to_string(),
+                process_nodes_multiaddr(route, &opts.state)?,
+            ))
+        })
+        .collect::<crate::Result<Vec<_>>>()?;
+    let mqtt = match &cmd.mqtt {
+        Some(path) => {
+            let config = std::fs::read_to_string(path).into_diagnostic()?;
+            Some(MqttPortalConfig::parse(&config).into_diagnostic()?)
+        }
+        None => None,
+    };
 
     let node_name = get_node_name(&opts.state, &cmd.at);
     let node_name = parse_node_name(&node_name)?;
@@ -118,45 +228,81 @@ async fn rpc(
             .project
             .to_owned();
         let resource = Resource::new("tcp-inlet");
-        if let Some(p) = project {
+        if let Some(p) = project.clone() {
             if !has_policy(&node_name, &ctx, &opts, &resource).await? {
                 add_default_project_policy(&node_name, &ctx, &opts, p, &resource).await?;
             }
         }
 
-        let via_project = if cmd.to.clone().matches(0, &[Project::CODE.into()]) {
-            if cmd.authorized.is_some() {
-                return Err(miette!("--authorized can not be used with project addresses").into());
-            }
-            true
-        } else {
-            false
+        let via_project = match &cmd.to {
+            _ if cmd.transparent.is_some() => mappings
+                .iter()
+                .any(|m| m.outlet_addr().matches(0, &[Project::CODE.into()])),
+            InletTarget::Route(to) if to.matches(0, &[Project::CODE.into()]) => true,
+            InletTarget::Route(_) => false,
+            InletTarget::Service(_) => true,
         };
+        if via_project && cmd.authorized.is_some() {
+            return Err(
+                miette!("--authorized can not be used with project addresses or services").into(),
+            );
+        }
 
+        // the same key is used for all the attempts, so that the inlet is only created once
+        let idempotency_key = random_string();
         let inlet = loop {
             let req = {
-                let mut payload = if via_project {
-                    CreateInlet::via_project(
+                let mut payload = match (&cmd.transparent, &cmd.to) {
+                    (Some(mode), _) => CreateInlet::transparent(
+                        cmd.from.to_string(),
+                        mode.to_string(),
+                        mappings.clone(),
+                        cmd.authorized.clone(),
+                    ),
+                    (None, InletTarget::Service(service)) => {
+                        let project = project.clone().ok_or_else(|| {
+                            miette!("The node {node_name} must belong to a project to connect to a service")
+                        })?;
+                        CreateInlet::via_service(
+                            cmd.from.to_string(),
+                            MultiAddr::from_str(&format!("/project/{}", project.name))
+                                .into_diagnostic()?,
+                            service,
+                            route![],
+                            route![],
+                        )
+                    }
+                    (None, InletTarget::Route(to)) if via_project => CreateInlet::via_project(
                         cmd.from.to_string(),
-                        cmd.to.clone(),
+                        to.clone(),
                         route![],
                         route![],
-                    )
-                } else {
-                    CreateInlet::to_node(
+                    ),
+                    (None, InletTarget::Route(to)) => CreateInlet::to_node(
                         cmd.from.to_string(),
-                        cmd.to.clone(),
+                        to.clone(),
                         route![],
                         route![],
                         cmd.authorized.clone(),
-                    )
+                    ),
                 };
                 if let Some(a) = cmd.alias.as_ref() {
                     payload.set_alias(a)
                 }
+                if let Some(name) = cmd.dns_name.as_ref() {
+                    payload.set_dns_name(name)
+                }
+                if let Some(port_range) = cmd.port_range {
+                    payload.set_port_range(port_range)
+                }
+                if let Some(config) = mqtt.clone() {
+                    payload.set_mqtt(config)
+                }
                 payload.set_wait_ms(cmd.connection_wait.as_millis() as u64);
 
-                Request::post("/node/inlet").body(payload)
+                Request::post("/node/inlet")
+                    .idempotency_key(&idempotency_key)
+                    .body(payload)
             };
 
             let result: Reply<InletStatus> = node.ask_and_get_reply(&ctx, req).await?;
@@ -235,7 +381,8 @@ async fn rpc(
         .plain(
             fmt_ok!(
                 "TCP Inlet {} on node {} is now sending traffic\n",
-                &cmd.from
+                &inlet
+                    .bind_addr
                     .to_string()
                     .color(OckamColor::PrimaryResource.color()),
                 &node_name
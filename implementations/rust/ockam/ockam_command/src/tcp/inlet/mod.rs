@@ -2,13 +2,17 @@ pub(crate) mod create;
 mod delete;
 mod list;
 mod show;
+mod stdio;
 
+use crate::tcp::tap::TapCommand;
 use crate::{docs, CommandGlobalOpts};
 use clap::{Args, Subcommand};
 use create::CreateCommand;
 use delete::DeleteCommand;
 pub(crate) use list::ListCommand;
+use ockam_api::portal_tap::PortalSide;
 pub(crate) use show::ShowCommand;
+use stdio::StdioCommand;
 
 const LONG_ABOUT: &str = include_str!("./static/long_about.txt");
 const AFTER_LONG_HELP: &str = include_str!("./static/after_long_help.txt");
@@ -32,6 +36,8 @@ pub enum TcpInletSubCommand {
     Delete(DeleteCommand),
     List(ListCommand),
     Show(ShowCommand),
+    Stdio(StdioCommand),
+    Tap(TapCommand),
 }
 
 impl TcpInletCommand {
@@ -41,6 +47,8 @@ impl TcpInletCommand {
             TcpInletSubCommand::Delete(c) => c.run(options),
             TcpInletSubCommand::List(c) => c.run(options),
             TcpInletSubCommand::Show(c) => c.run(options),
+            TcpInletSubCommand::Stdio(c) => c.run(options),
+            TcpInletSubCommand::Tap(c) => c.run(options, PortalSide::Inlet),
         }
     }
 }
@@ -0,0 +1,107 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use clap::Args;
+use miette::IntoDiagnostic;
+use tracing::{debug, warn};
+
+use ockam::identity::Identifier;
+use ockam::Context;
+use ockam_abac::Resource;
+use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_api::nodes::models::portal::{CreateInlet, InletStatus};
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::stdio_bridge::bridge_stdio;
+use ockam_core::api::Request;
+use ockam_core::compat::rand::random_string;
+use ockam_core::route;
+use ockam_multiaddr::proto::Project;
+use ockam_multiaddr::{MultiAddr, Protocol as _};
+
+use crate::node::{get_node_name, initialize_node_if_default};
+use crate::policy::{add_default_project_policy, has_policy};
+use crate::util::duration::duration_parser;
+use crate::util::{node_rpc, parse_node_name, process_nodes_multiaddr};
+use crate::{docs, CommandGlobalOpts};
+
+const AFTER_LONG_HELP: &str = include_str!("./static/stdio/after_long_help.txt");
+
+/// Connect the standard input and output to a TCP outlet, to be used as an OpenSSH ProxyCommand.
+///
+/// The node creates an inlet listening on a free local port for the duration of the
+/// connection, and deletes it when the outlet closes the connection. Only the bytes received
+/// from the outlet are written to the standard output
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = docs::after_help(AFTER_LONG_HELP))]
+pub struct StdioCommand {
+    /// Node on which to start the tcp inlet.
+    #[arg(long, display_order = 900, id = "NODE")]
+    at: Option<String>,
+
+    /// Route to a tcp outlet.
+    #[arg(long, display_order = 900, id = "ROUTE")]
+    to: MultiAddr,
+
+    /// Authorized identity for secure channel connection
+    #[arg(long, name = "AUTHORIZED", display_order = 900)]
+    authorized: Option<Identifier>,
+
+    /// Time to wait for the outlet to be available.
+    #[arg(long, display_order = 900, id = "WAIT", default_value = "5s", value_parser = duration_parser)]
+    connection_wait: Duration,
+}
+
+impl StdioCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.at);
+        node_rpc(rpc, (opts, self));
+    }
+}
+
+async fn rpc(ctx: Context, (opts, cmd): (CommandGlobalOpts, StdioCommand)) -> miette::Result<()> {
+    let to = process_nodes_multiaddr(&cmd.to, &opts.state)?;
+    let node_name = get_node_name(&opts.state, &cmd.at);
+    let node_name = parse_node_name(&node_name)?;
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+
+    let project = opts
+        .state
+        .nodes
+        .get(&node_name)?
+        .config()
+        .setup()
+        .project
+        .to_owned();
+    let resource = Resource::new("tcp-inlet");
+    if let Some(p) = project {
+        if !has_policy(&node_name, &ctx, &opts, &resource).await? {
+            add_default_project_policy(&node_name, &ctx, &opts, p, &resource).await?;
+        }
+    }
+
+    // the inlet only accepts local connections and is only used by this process
+    let from = "127.0.0.1:0".to_string();
+    let mut payload = if to.matches(0, &[Project::CODE.into()]) {
+        CreateInlet::via_project(from, to, route![], route![])
+    } else {
+        CreateInlet::to_node(from, to, route![], route![], cmd.authorized.clone())
+    };
+    let alias = format!("stdio-{}", random_string());
+    payload.set_alias(&alias);
+    payload.set_wait_ms(cmd.connection_wait.as_millis() as u64);
+    let inlet: InletStatus = node
+        .ask(&ctx, Request::post("/node/inlet").body(payload))
+        .await?;
+    let addr: SocketAddr = inlet.bind_addr.parse().into_diagnostic()?;
+    debug!(%addr, %alias, "bridging the standard input and output to the inlet");
+
+    let result = bridge_stdio(addr).await;
+    if let Err(e) = node
+        .tell(&ctx, Request::delete(format!("/node/inlet/{alias}")))
+        .await
+    {
+        warn!(%e, %alias, "the inlet used by the standard input and output can't be deleted");
+    }
+    result.into_diagnostic()?;
+    Ok(())
+}
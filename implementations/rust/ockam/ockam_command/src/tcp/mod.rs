@@ -2,4 +2,5 @@ pub mod connection;
 pub mod inlet;
 pub mod listener;
 pub mod outlet;
+mod tap;
 pub mod util;
@@ -1,8 +1,9 @@
 use std::net::SocketAddr;
+use std::path::PathBuf;
 
 use clap::Args;
 use colorful::Colorful;
-use miette::IntoDiagnostic;
+use miette::{miette, IntoDiagnostic};
 use tokio::sync::Mutex;
 use tokio::try_join;
 
@@ -10,18 +11,23 @@ use ockam::Context;
 use ockam_abac::Resource;
 use ockam_api::address::extract_address_value;
 use ockam_api::cli_state::{StateDirTrait, StateItemTrait};
+use ockam_api::grpc::GrpcPortalConfig;
+use ockam_api::mqtt::MqttPortalConfig;
 use ockam_api::nodes::models::portal::{CreateOutlet, OutletStatus};
 use ockam_api::nodes::BackgroundNode;
+use ockam_api::postgres_outlet::PostgresUsers;
+use ockam_api::service_registry::ServicePublication;
 use ockam_core::api::Request;
+use ockam_transport_tcp::{resolve_peers, AddressFamily};
 
 use crate::node::{get_node_name, initialize_node_if_default};
 use crate::policy::{add_default_project_policy, has_policy};
 use crate::tcp::util::alias_parser;
 use crate::terminal::OckamColor;
 use crate::util::node_rpc;
-use crate::util::parsers::socket_addr_parser;
+use crate::util::parsers::{address_family_parser, host_port_parser};
 use crate::{display_parse_logs, fmt_log};
-use crate::{docs, fmt_ok, CommandGlobalOpts};
+use crate::{docs, fmt_ok, CommandGlobalOpts, Result};
 
 const AFTER_LONG_HELP: &str = include_str!("./static/create/after_long_help.txt");
 
@@ -37,13 +43,74 @@ pub struct CreateCommand {
     #[arg(long, display_order = 901, id = "OUTLET_ADDRESS", default_value_t = default_from_addr())]
     from: String,
 
-    /// TCP address to send raw tcp traffic.
-    #[arg(long, display_order = 902, id = "SOCKET_ADDRESS", value_parser = socket_addr_parser)]
-    to: SocketAddr,
+    /// TCP address to send raw tcp traffic. If it is a hostname, the node connects to
+    /// all its addresses, IPv4 and IPv6 connection attempts being raced.
+    #[arg(long, display_order = 902, id = "SOCKET_ADDRESS", value_parser = host_port_parser)]
+    to: String,
+
+    /// Only connect to the addresses of this family when the target is a hostname: any, ipv4 or ipv6.
+    #[arg(long, display_order = 902, value_name = "FAMILY", default_value = "any", value_parser = address_family_parser)]
+    address_family: AddressFamily,
 
     /// Assign a name to this outlet.
     #[arg(long, display_order = 900, id = "ALIAS", value_parser = alias_parser)]
     alias: Option<String>,
+
+    /// Publish this outlet in the service registry of the node project, under a service name.
+    /// Inlets can then be created with `--to service:<name>`.
+    #[arg(long, display_order = 903, value_name = "SERVICE")]
+    publish: Option<String>,
+
+    /// Metadata of the published service, with the form `<key>=<value>`.
+    #[arg(long, display_order = 904, value_name = "KEY=VALUE", requires = "publish", value_parser = parse_metadata)]
+    metadata: Vec<(String, String)>,
+
+    /// Name of the relay forwarding the traffic of the project to this node.
+    #[arg(
+        long,
+        display_order = 905,
+        value_name = "RELAY",
+        default_value = "default",
+        requires = "publish"
+    )]
+    relay: String,
+
+    /// Path of a YAML file mapping the identities of the inlets to the users of a Postgres
+    /// server. The outlet authenticates the connections with these credentials, so that the
+    /// clients of the inlets connect without a password.
+    #[arg(long, display_order = 906, value_name = "FILE")]
+    postgres_users: Option<PathBuf>,
+
+    /// Path of a YAML file listing the MQTT topics each inlet identity can publish or subscribe
+    /// to. The connections of an inlet publishing or subscribing to another topic are closed.
+    #[arg(
+        long,
+        display_order = 906,
+        value_name = "FILE",
+        conflicts_with = "postgres_users"
+    )]
+    mqtt: Option<PathBuf>,
+
+    /// Path of a YAML file with the policies of the methods of a gRPC server. Each call is
+    /// authorized with the attributes of the inlet identity, a denied call receiving a
+    /// PERMISSION_DENIED status, and the server receives that identity in the call metadata.
+    #[arg(
+        long,
+        display_order = 906,
+        value_name = "FILE",
+        conflicts_with_all = ["postgres_users", "mqtt"]
+    )]
+    grpc: Option<PathBuf>,
+}
+
+fn parse_metadata(input: &str) -> Result<(String, String)> {
+    let (key, value) = input
+        .split_once('=')
+        .ok_or(miette!("The metadata must have the form <key>=<value>"))?;
+    if key.is_empty() {
+        return Err(miette!("The metadata key can't be empty").into());
+    }
+    Ok((key.to_string(), value.to_string()))
 }
 
 impl CreateCommand {
@@ -80,21 +147,83 @@ pub async fn run_impl(
         .project
         .to_owned();
     let resource = Resource::new("tcp-outlet");
-    if let Some(p) = project {
+    if let Some(p) = project.clone() {
         if !has_policy(&node_name, &ctx, &opts, &resource).await? {
             add_default_project_policy(&node_name, &ctx, &opts, p, &resource).await?;
         }
     }
 
+    let publication = match &cmd.publish {
+        Some(service) => {
+            let project = project.ok_or(miette!(
+                "The node {node_name} must belong to a project to publish a service"
+            ))?;
+            let route = format!(
+                "/project/{}/service/forward_to_{}/secure/api/service/{}",
+                project.name,
+                cmd.relay,
+                extract_address_value(&cmd.from)?
+            );
+            let publication = cmd
+                .metadata
+                .iter()
+                .fold(ServicePublication::new(service, route), |p, (k, v)| {
+                    p.with_metadata(k, v)
+                });
+            Some(publication)
+        }
+        None => None,
+    };
+
+    let postgres_users = match &cmd.postgres_users {
+        Some(path) => {
+            let users = std::fs::read_to_string(path).into_diagnostic()?;
+            Some(PostgresUsers::parse(&users).into_diagnostic()?)
+        }
+        None => None,
+    };
+    let mqtt = match &cmd.mqtt {
+        Some(path) => {
+            let config = std::fs::read_to_string(path).into_diagnostic()?;
+            Some(MqttPortalConfig::parse(&config).into_diagnostic()?)
+        }
+        None => None,
+    };
+    let grpc = match &cmd.grpc {
+        Some(path) => {
+            let config = std::fs::read_to_string(path).into_diagnostic()?;
+            Some(GrpcPortalConfig::parse(&config).into_diagnostic()?)
+        }
+        None => None,
+    };
+
     let is_finished: Mutex<bool> = Mutex::new(false);
 
+    let socket_addr = resolve_peers(cmd.to.clone(), cmd.address_family)
+        .map_err(|e| miette!("cannot resolve the address {}: {e}", cmd.to))?[0];
     let send_req = async {
-        let payload = CreateOutlet::new(
-            cmd.to,
+        let mut payload = CreateOutlet::new(
+            socket_addr,
             extract_address_value(&cmd.from)?.into(),
             cmd.alias,
             true,
         );
+        // a hostname is resolved again by the node, which can then try all its addresses
+        if cmd.to.parse::<SocketAddr>().is_err() {
+            payload = payload.with_hostname(cmd.to.clone(), cmd.address_family);
+        }
+        if let Some(publication) = publication {
+            payload = payload.with_publication(publication);
+        }
+        if let Some(users) = postgres_users {
+            payload = payload.with_postgres_users(users);
+        }
+        if let Some(config) = mqtt {
+            payload = payload.with_mqtt(config);
+        }
+        if let Some(config) = grpc {
+            payload = payload.with_grpc(config);
+        }
         let res = send_request(&ctx, &opts, payload, node_name.clone()).await;
         *is_finished.lock().await = true;
         res
@@ -2,13 +2,17 @@ pub mod create;
 mod delete;
 pub mod list;
 mod show;
+mod switch;
 
+use crate::tcp::tap::TapCommand;
 use crate::{docs, CommandGlobalOpts};
 use clap::{Args, Subcommand};
 use create::CreateCommand;
 use delete::DeleteCommand;
 use list::ListCommand;
+use ockam_api::portal_tap::PortalSide;
 use show::ShowCommand;
+use switch::SwitchCommand;
 
 const LONG_ABOUT: &str = include_str!("./static/long_about.txt");
 const AFTER_LONG_HELP: &str = include_str!("./static/after_long_help.txt");
@@ -32,6 +36,8 @@ pub enum TcpOutletSubCommand {
     Delete(DeleteCommand),
     List(ListCommand),
     Show(ShowCommand),
+    Switch(SwitchCommand),
+    Tap(TapCommand),
 }
 
 impl TcpOutletCommand {
@@ -41,6 +47,8 @@ impl TcpOutletCommand {
             TcpOutletSubCommand::Delete(c) => c.run(options),
             TcpOutletSubCommand::List(c) => c.run(options),
             TcpOutletSubCommand::Show(c) => c.run(options),
+            TcpOutletSubCommand::Switch(c) => c.run(options),
+            TcpOutletSubCommand::Tap(c) => c.run(options, PortalSide::Outlet),
         }
     }
 }
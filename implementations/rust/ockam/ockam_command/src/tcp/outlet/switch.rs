@@ -0,0 +1,98 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::IntoDiagnostic;
+
+use ockam::Context;
+use ockam_api::nodes::models::portal::{OutletStatus, SwitchServiceTarget};
+use ockam_api::nodes::BackgroundNode;
+use ockam_core::api::Request;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::duration::duration_parser;
+use crate::util::parsers::socket_addr_parser;
+use crate::util::{node_rpc, parse_node_name};
+use crate::{docs, fmt_log, fmt_ok, CommandGlobalOpts};
+
+const AFTER_LONG_HELP: &str = include_str!("./static/switch/after_long_help.txt");
+
+/// Switch the outlet publishing a service to a new target, without downtime.
+///
+/// The new target must accept TCP connections before the health check timeout. New connections
+/// are then made to the new target, while the established connections to the previous target
+/// are kept until they are closed. If the new target can't be reached the switch is rolled back.
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = docs::after_help(AFTER_LONG_HELP))]
+pub struct SwitchCommand {
+    /// Name of the service published by the outlet
+    #[arg(display_order = 900, required = true, id = "SERVICE")]
+    service: String,
+
+    /// New TCP address the outlet must send raw tcp traffic to
+    #[arg(long, display_order = 901, id = "SOCKET_ADDRESS", value_parser = socket_addr_parser)]
+    to: SocketAddr,
+
+    /// Maximum time to wait for the new target to accept connections
+    #[arg(long, display_order = 902, id = "DURATION", default_value = "30s", value_parser = duration_parser)]
+    health_check_timeout: Duration,
+
+    /// Node hosting the outlet. If none are provided, the default node will be used
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl SwitchCommand {
+    pub fn run(self, opts: CommandGlobalOpts) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self))
+    }
+}
+
+pub async fn run_impl(
+    ctx: Context,
+    (opts, cmd): (CommandGlobalOpts, SwitchCommand),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node_name = parse_node_name(&node_name)?;
+    let mut node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+
+    opts.terminal.write_line(&fmt_log!(
+        "Switching the service {} to {}...\n",
+        cmd.service
+            .clone()
+            .color(OckamColor::PrimaryResource.color()),
+        cmd.to
+            .to_string()
+            .color(OckamColor::PrimaryResource.color())
+    ))?;
+
+    // the request waits for the health check of the new target
+    node.set_timeout(cmd.health_check_timeout + Duration::from_secs(10));
+    let outlet_status: OutletStatus = node
+        .ask(
+            &ctx,
+            Request::put(format!("/node/services/{}/target", cmd.service))
+                .body(SwitchServiceTarget::new(cmd.to, cmd.health_check_timeout)),
+        )
+        .await?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "The service {} on node {} now sends new connections to {} ({})",
+            cmd.service.color(OckamColor::PrimaryResource.color()),
+            node_name.color(OckamColor::PrimaryResource.color()),
+            outlet_status
+                .socket_addr
+                .to_string()
+                .color(OckamColor::PrimaryResource.color()),
+            outlet_status.payload.clone().unwrap_or_default()
+        ))
+        .machine(outlet_status.socket_addr.to_string())
+        .json(serde_json::to_string_pretty(&outlet_status).into_diagnostic()?)
+        .write_line()?;
+    Ok(())
+}
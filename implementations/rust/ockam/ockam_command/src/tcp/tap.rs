@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::Args;
+use colorful::Colorful;
+use miette::{miette, IntoDiagnostic};
+
+use ockam::Context;
+use ockam_api::nodes::models::portal::{PortalTapStatus, StartPortalTap};
+use ockam_api::nodes::BackgroundNode;
+use ockam_api::portal_tap::{PortalSide, TapFormat};
+use ockam_core::api::Request;
+
+use crate::node::{get_node_name, initialize_node_if_default, NodeOpts};
+use crate::terminal::OckamColor;
+use crate::util::duration::duration_parser;
+use crate::util::{node_rpc, parse_node_name};
+use crate::{docs, fmt_log, fmt_ok, CommandGlobalOpts};
+
+const AFTER_LONG_HELP: &str = include_str!("./static/tap/after_long_help.txt");
+
+/// Record the connections of a portal to a local file, to debug an application.
+///
+/// The data of the connections is written in the clear, as a pcap file which can be opened with
+/// a packet analyzer, or as JSON lines. The file is created in the `taps` directory of the node,
+/// and is only readable by the user running the node. A policy must be set for the `tap` action on the alias
+/// of the portal, and only the connections of the identities satisfying that policy are recorded.
+/// The recording stops after its duration, at most one hour.
+#[derive(Clone, Debug, Args)]
+#[command(after_long_help = docs::after_help(AFTER_LONG_HELP))]
+pub struct TapCommand {
+    /// Alias of the portal
+    #[arg(display_order = 900, required = true, id = "ALIAS")]
+    alias: String,
+
+    /// New file the connections are written to, relative to the `taps` directory of the node
+    #[arg(
+        long,
+        display_order = 901,
+        value_name = "FILE",
+        required_unless_present = "stop"
+    )]
+    output: Option<PathBuf>,
+
+    /// Format of the file: pcap or jsonl
+    #[arg(
+        long,
+        display_order = 902,
+        value_name = "FORMAT",
+        default_value = "pcap"
+    )]
+    format: TapFormat,
+
+    /// Duration of the recording
+    #[arg(long, display_order = 903, id = "DURATION", default_value = "60s", value_parser = duration_parser)]
+    duration: Duration,
+
+    /// Stop recording the connections of the portal
+    #[arg(long, display_order = 904, conflicts_with = "output")]
+    stop: bool,
+
+    /// Node hosting the portal. If none are provided, the default node will be used
+    #[command(flatten)]
+    node_opts: NodeOpts,
+}
+
+impl TapCommand {
+    pub fn run(self, opts: CommandGlobalOpts, side: PortalSide) {
+        initialize_node_if_default(&opts, &self.node_opts.at_node);
+        node_rpc(run_impl, (opts, self, side))
+    }
+}
+
+async fn run_impl(
+    ctx: Context,
+    (opts, cmd, side): (CommandGlobalOpts, TapCommand, PortalSide),
+) -> miette::Result<()> {
+    let node_name = get_node_name(&opts.state, &cmd.node_opts.at_node);
+    let node_name = parse_node_name(&node_name)?;
+    let node = BackgroundNode::create(&ctx, &opts.state, &node_name).await?;
+    let path = format!("/node/{side}/{}/tap", cmd.alias);
+
+    if cmd.stop {
+        node.tell(&ctx, Request::delete(path)).await?;
+        opts.terminal
+            .stdout()
+            .plain(fmt_ok!(
+                "Stopped recording the connections of the {side} {}",
+                cmd.alias.color(OckamColor::PrimaryResource.color())
+            ))
+            .write_line()?;
+        return Ok(());
+    }
+
+    let output = cmd.output.ok_or(miette!("The output file is missing"))?;
+    opts.terminal.write_line(&fmt_log!(
+        "Recording the connections of the {side} {}...\n",
+        cmd.alias.clone().color(OckamColor::PrimaryResource.color())
+    ))?;
+
+    let status: PortalTapStatus = node
+        .ask(
+            &ctx,
+            Request::post(path).body(StartPortalTap::new(
+                output.to_string_lossy(),
+                cmd.format,
+                cmd.duration,
+            )),
+        )
+        .await?;
+
+    opts.terminal
+        .stdout()
+        .plain(fmt_ok!(
+            "The connections of the {side} {} are recorded to {} for {}s",
+            status
+                .alias
+                .clone()
+                .color(OckamColor::PrimaryResource.color()),
+            status
+                .path
+                .clone()
+                .color(OckamColor::PrimaryResource.color()),
+            cmd.duration.as_secs()
+        ))
+        .machine(status.path.clone())
+        .json(serde_json::to_string_pretty(&status).into_diagnostic()?)
+        .write_line()?;
+    Ok(())
+}
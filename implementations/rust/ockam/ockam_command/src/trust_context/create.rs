@@ -1,9 +1,11 @@
 use crate::util::local_cmd;
+use crate::Result;
 use crate::{docs, util::api::TrustContextOpts, CommandGlobalOpts};
 use clap::Args;
 use indoc::formatdoc;
 use miette::{miette, IntoDiagnostic};
 use ockam_api::cli_state::{random_name, StateDirTrait};
+use ockam_api::config::cli::FederatedAuthorityConfig;
 
 const LONG_ABOUT: &str = include_str!("./static/create/long_about.txt");
 const AFTER_LONG_HELP: &str = include_str!("./static/create/after_long_help.txt");
@@ -24,10 +26,31 @@ pub struct CreateCommand {
     #[arg(long)]
     credential: Option<String>,
 
+    /// Accept the credentials issued by the authority of another project.
+    /// The value has the form `<prefix>=<authority identity>`, where the attributes attested
+    /// by that authority are renamed `<prefix>.<attribute name>`
+    #[arg(long = "federated-authority", value_name = "PREFIX=IDENTITY", value_parser = parse_federated_authority)]
+    federated_authorities: Vec<FederatedAuthorityConfig>,
+
     #[command(flatten)]
     trust_context_opts: TrustContextOpts,
 }
 
+fn parse_federated_authority(input: &str) -> Result<FederatedAuthorityConfig> {
+    let (prefix, identity) = input.split_once('=').ok_or(miette!(
+        "A federated authority must have the form <prefix>=<identity>"
+    ))?;
+    if prefix.is_empty() {
+        return Err(
+            miette!("The attributes prefix of a federated authority can't be empty").into(),
+        );
+    }
+    Ok(FederatedAuthorityConfig::new(
+        identity.to_string(),
+        prefix.to_string(),
+    ))
+}
+
 impl CreateCommand {
     pub fn run(self, opts: CommandGlobalOpts) {
         local_cmd(run_impl(opts, self));
@@ -42,7 +65,10 @@ fn run_impl(opts: CommandGlobalOpts, cmd: CreateCommand) -> miette::Result<()> {
         .use_default_trust_context(false)
         .build();
 
-    if let Some(c) = config {
+    if let Some(mut c) = config {
+        for federated_authority in cmd.federated_authorities {
+            c = c.with_federated_authority(federated_authority);
+        }
         opts.state.trust_contexts.create(&cmd.name, c.clone())?;
 
         let auth = if let Ok(auth) = c.authority() {
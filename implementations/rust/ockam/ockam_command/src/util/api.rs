@@ -13,7 +13,7 @@ use ockam_api::cli_state::CliState;
 use ockam_api::nodes::models::flow_controls::AddConsumer;
 use ockam_api::nodes::models::services::{
     StartAuthenticatedServiceRequest, StartAuthenticatorRequest, StartCredentialsService,
-    StartHopServiceRequest, StartOktaIdentityProviderRequest,
+    StartHopServiceRequest, StartKvStoreServiceRequest, StartOktaIdentityProviderRequest,
 };
 use ockam_api::nodes::*;
 use ockam_api::trust_context::TrustContextConfigBuilder;
@@ -34,6 +34,21 @@ pub(crate) fn query_status() -> Request<()> {
     Request::get("/node")
 }
 
+/// Construct a request to query the resource settings of a node
+pub(crate) fn query_resources() -> Request<()> {
+    Request::get("/node/resources")
+}
+
+/// Construct a request to query the memory used by the subsystems of a node
+pub(crate) fn query_memory() -> Request<()> {
+    Request::get("/node/memory")
+}
+
+/// Construct a request to query the resource usage history of a node
+pub(crate) fn query_resource_usage() -> Request<()> {
+    Request::get("/node/usage")
+}
+
 /// Construct a request to query node tcp listeners
 pub(crate) fn list_tcp_listeners() -> Request<()> {
     Request::get("/node/tcp/listener")
@@ -87,6 +102,21 @@ pub(crate) fn show_secure_channel(
     Request::get("/node/show_secure_channel").body(payload)
 }
 
+/// Construct a request to exchange credentials over an established Secure Channel
+pub(crate) fn exchange_credentials(
+    addr: &Address,
+) -> Request<models::secure_channel::ExchangeCredentialsRequest> {
+    let payload = models::secure_channel::ExchangeCredentialsRequest::new(addr);
+    Request::post("/node/secure_channel/credentials").body(payload)
+}
+
+/// Construct a request to issue a bearer token for an identity
+pub(crate) fn issue_bearer_token(
+    request: models::credentials::IssueBearerTokenRequest,
+) -> Request<models::credentials::IssueBearerTokenRequest> {
+    Request::post("/node/credentials/actions/issue_token").body(request)
+}
+
 /// Construct a request to create Secure Channel Listeners
 pub(crate) fn create_secure_channel_listener(
     addr: &Address,
@@ -133,6 +163,12 @@ pub(crate) fn start_hop_service(addr: &str) -> Request<StartHopServiceRequest> {
     Request::post(node_service(DefaultAddress::HOP_SERVICE)).body(payload)
 }
 
+/// Construct a request to start a key-value store Service
+pub(crate) fn start_kv_store_service(addr: &str) -> Request<StartKvStoreServiceRequest> {
+    let payload = StartKvStoreServiceRequest::new(addr);
+    Request::post(node_service(DefaultAddress::KV_STORE)).body(payload)
+}
+
 /// Construct a request to start an Authenticated Service
 pub(crate) fn start_authenticated_service(addr: &str) -> Request<StartAuthenticatedServiceRequest> {
     let payload = StartAuthenticatedServiceRequest::new(addr);
@@ -18,7 +18,7 @@ use ockam_multiaddr::{
     MultiAddr, Protocol,
 };
 
-use crate::Result;
+use crate::{CommandGlobalOpts, Result};
 
 pub mod api;
 pub mod duration;
@@ -100,7 +100,22 @@ where
     Fut: core::future::Future<Output = miette::Result<T>> + Send + 'static,
     T: Send + 'static,
 {
-    let (ctx, mut executor) = NodeBuilder::new().no_logging().build();
+    embedded_node_with_builder_that_is_not_stopped(NodeBuilder::new(), f, a)
+}
+
+/// Same as [`embedded_node_that_is_not_stopped`] but with a node tuned by a [`NodeBuilder`]
+pub fn embedded_node_with_builder_that_is_not_stopped<A, F, Fut, T>(
+    builder: NodeBuilder,
+    f: F,
+    a: A,
+) -> miette::Result<T>
+where
+    A: Send + Sync + 'static,
+    F: FnOnce(Context, A) -> Fut + Send + Sync + 'static,
+    Fut: core::future::Future<Output = miette::Result<T>> + Send + 'static,
+    T: Send + 'static,
+{
+    let (ctx, mut executor) = builder.no_logging().build();
     executor
         .execute(async move {
             let child_ctx = ctx
@@ -269,6 +284,21 @@ pub fn is_enrolled_guard(cli_state: &CliState, identity_name: Option<&str>) -> m
     Ok(())
 }
 
+/// Warn that the Orchestrator could not be reached and that the data stored on this machine
+/// is used instead, with the staleness of that data, if any
+pub fn warn_cached(opts: &CommandGlobalOpts, staleness_warning: Option<String>) -> Result<()> {
+    use crate::fmt_warn;
+    use colorful::Colorful;
+    opts.terminal.write_line(&fmt_warn!(
+        "The Orchestrator could not be reached, using the data stored on this machine"
+    ))?;
+    if let Some(warning) = staleness_warning {
+        opts.terminal
+            .write_line(&fmt_warn!("Note that {warning}"))?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use ockam_api::address::extract_address_value;
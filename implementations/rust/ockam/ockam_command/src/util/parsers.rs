@@ -1,27 +1,45 @@
 use std::net::SocketAddr;
 use std::str::FromStr;
+use std::time::Duration;
 
 use miette::miette;
 
 use ockam::identity::Identifier;
-use ockam_transport_tcp::resolve_peer;
+use ockam_multiaddr::MultiAddr;
+use ockam_transport_tcp::{resolve_peer, AddressFamily};
 
+use crate::util::duration::duration_parser;
 use crate::Result;
 
 /// Helper function for parsing a socket from user input
 /// It is possible to just input a `port`. In that case the address will be assumed to be
 /// 127.0.0.1:<port>
 pub(crate) fn socket_addr_parser(input: &str) -> Result<SocketAddr> {
-    let addr: Vec<&str> = input.split(':').collect();
+    let address = host_port_parser(input)?;
+    Ok(resolve_peer(address.to_string())
+        .map_err(|e| miette!("cannot parse the address {address} as a socket address: {e}"))?)
+}
 
-    let address = match addr.len() {
+/// Helper function for parsing a host and a port from user input, without resolving the host.
+/// It is possible to just input a `port`. In that case the address will be assumed to be
+/// 127.0.0.1:<port>
+pub(crate) fn host_port_parser(input: &str) -> Result<String> {
+    let address = match input.rsplit_once(':') {
         // Only the port is available
-        1 => format!("127.0.0.1:{}", addr[0]),
-        // Both the ip and port are available
-        _ => input.to_string(),
+        None => format!("127.0.0.1:{input}"),
+        // Both the host and port are available
+        Some(_) => input.to_string(),
     };
-    Ok(resolve_peer(address.to_string())
-        .map_err(|e| miette!("cannot parse the address {address} as a socket address: {e}"))?)
+    match address.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(address),
+        _ => Err(miette!("cannot parse the address {address} as a host and a port").into()),
+    }
+}
+
+/// Helper fn for parsing an address family: `any`, `ipv4` or `ipv6`
+pub(crate) fn address_family_parser(input: &str) -> Result<AddressFamily> {
+    AddressFamily::from_str(input)
+        .map_err(|_| miette!("Invalid address family: {input}, expected any, ipv4 or ipv6").into())
 }
 
 /// Helper fn for parsing an identity from user input by using
@@ -30,6 +48,90 @@ pub(crate) fn identity_identifier_parser(input: &str) -> Result<Identifier> {
     Identifier::from_str(input).map_err(|_| miette!("Invalid identity identifier: {input}").into())
 }
 
+/// Helper fn for parsing the identity of a node and a route to it, `IDENTIFIER@ROUTE`
+pub(crate) fn identity_route_parser(input: &str) -> Result<(Identifier, MultiAddr)> {
+    let invalid = || miette!("Invalid node: {input}, expected IDENTIFIER@ROUTE");
+    let (identifier, route) = input.split_once('@').ok_or_else(invalid)?;
+    let identifier = identity_identifier_parser(identifier)?;
+    let route = MultiAddr::from_str(route).map_err(|e| miette!("Invalid route {route}: {e}"))?;
+    Ok((identifier, route))
+}
+
+/// Helper fn for parsing the memory limit of a node subsystem, for example `mailboxes=64M`.
+/// The number of bytes can be suffixed with `K`, `M` or `G` for multiples of 1024
+pub(crate) fn memory_limit_parser(input: &str) -> Result<(String, usize)> {
+    let invalid = || miette!("Invalid memory limit: {input}, expected SUBSYSTEM=BYTES");
+    let (subsystem, bytes) = input.split_once('=').ok_or_else(invalid)?;
+    if subsystem.is_empty() {
+        return Err(invalid().into());
+    }
+    let (number, multiplier) = match bytes.chars().last() {
+        Some('K') => (&bytes[..bytes.len() - 1], 1024),
+        Some('M') => (&bytes[..bytes.len() - 1], 1024 * 1024),
+        Some('G') => (&bytes[..bytes.len() - 1], 1024 * 1024 * 1024),
+        _ => (bytes, 1),
+    };
+    let number: usize = number.parse().map_err(|_| invalid())?;
+    let bytes = number.checked_mul(multiplier).ok_or_else(invalid)?;
+    Ok((subsystem.to_string(), bytes))
+}
+
+/// Kinds of timeouts which can be set with `timeout_parser`
+pub(crate) const TIMEOUT_KINDS: [&str; 4] = [
+    "handshake",
+    "credential-exchange",
+    "message-send",
+    "management-request",
+];
+
+/// Helper fn for parsing a timeout of a given kind, for example `handshake=5m`
+pub(crate) fn timeout_parser(input: &str) -> Result<(String, Duration)> {
+    let invalid = || {
+        miette!(
+            "Invalid timeout: {input}, expected KIND=DURATION where KIND is one of {}",
+            TIMEOUT_KINDS.join(", ")
+        )
+    };
+    let (kind, duration) = input.split_once('=').ok_or_else(invalid)?;
+    if !TIMEOUT_KINDS.contains(&kind) {
+        return Err(invalid().into());
+    }
+    let duration = duration_parser(duration).map_err(|_| invalid())?;
+    Ok((kind.to_string(), duration))
+}
+
+/// Parse an address mapping `OLD=NEW`
+pub(crate) fn address_mapping_parser(input: &str) -> Result<(String, String)> {
+    match input.split_once('=') {
+        Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+            Ok((old.to_string(), new.to_string()))
+        }
+        _ => Err(miette!("Invalid address mapping: {input}, expected OLD=NEW").into()),
+    }
+}
+
+/// Parse a label `KEY=VALUE`
+pub(crate) fn label_parser(input: &str) -> Result<(String, String)> {
+    match input.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !value.is_empty() => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(miette!("Invalid label: {input}, expected KEY=VALUE").into()),
+    }
+}
+
+/// Parse an environment variable `NAME=VALUE`. The value can be empty
+pub(crate) fn env_var_parser(input: &str) -> Result<(String, String)> {
+    match input.split_once('=') {
+        Some((name, value))
+            if !name.is_empty() && !name.contains('\0') && !value.contains('\0') =>
+        {
+            Ok((name.to_string(), value.to_string()))
+        }
+        _ => Err(miette!("Invalid environment variable: {input}, expected NAME=VALUE").into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::Ipv6Addr;
@@ -83,6 +185,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_host_and_port() {
+        assert_eq!(host_port_parser("9000").unwrap(), "127.0.0.1:9000");
+        assert_eq!(
+            host_port_parser("example.com:443").unwrap(),
+            "example.com:443"
+        );
+        assert_eq!(host_port_parser("[::1]:443").unwrap(), "[::1]:443");
+        assert!(host_port_parser("example.com").is_err());
+        assert!(host_port_parser(":443").is_err());
+    }
+
+    #[test]
+    fn test_address_family() {
+        assert_eq!(address_family_parser("any").unwrap(), AddressFamily::Any);
+        assert_eq!(address_family_parser("ipv6").unwrap(), AddressFamily::Ipv6);
+        assert!(address_family_parser("ipx").is_err());
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        assert_eq!(
+            memory_limit_parser("mailboxes=64M").unwrap(),
+            ("mailboxes".to_string(), 64 * 1024 * 1024)
+        );
+        assert_eq!(
+            memory_limit_parser("portal_buffers=1000").unwrap(),
+            ("portal_buffers".to_string(), 1000)
+        );
+        assert!(memory_limit_parser("mailboxes").is_err());
+        assert!(memory_limit_parser("=10").is_err());
+        assert!(memory_limit_parser("mailboxes=10T").is_err());
+    }
+
+    #[test]
+    fn test_timeout() {
+        assert_eq!(
+            timeout_parser("handshake=5m").unwrap(),
+            ("handshake".to_string(), Duration::from_secs(300))
+        );
+        assert_eq!(
+            timeout_parser("message-send=1500ms").unwrap(),
+            ("message-send".to_string(), Duration::from_millis(1500))
+        );
+        assert!(timeout_parser("handshake").is_err());
+        assert!(timeout_parser("connect=5s").is_err());
+        assert!(timeout_parser("handshake=soon").is_err());
+    }
+
     #[test]
     fn test_invalid_inputs() {
         // Test case 3: Any other format will throw an error
@@ -97,4 +248,46 @@ mod tests {
         let invalid_input = "192,166,0.1:9999";
         assert!(socket_addr_parser(invalid_input).is_err());
     }
+
+    #[test]
+    fn test_env_var() {
+        assert_eq!(
+            env_var_parser("HOOK_DIR=/opt/hooks").unwrap(),
+            ("HOOK_DIR".to_string(), "/opt/hooks".to_string())
+        );
+        assert_eq!(
+            env_var_parser("EMPTY=").unwrap(),
+            ("EMPTY".to_string(), "".to_string())
+        );
+        assert!(env_var_parser("HOOK_DIR").is_err());
+        assert!(env_var_parser("=value").is_err());
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(
+            label_parser("region=eu-west").unwrap(),
+            ("region".to_string(), "eu-west".to_string())
+        );
+        assert!(label_parser("region=").is_err());
+        assert!(label_parser("region").is_err());
+    }
+
+    #[test]
+    fn test_identity_route() {
+        let (identifier, route) = identity_route_parser(
+            "I0000000000000000000000000000000000000001@/dnsaddr/control.example.com/tcp/4000/service/api",
+        )
+        .unwrap();
+        assert_eq!(
+            identifier.to_string(),
+            "I0000000000000000000000000000000000000001"
+        );
+        assert_eq!(
+            route.to_string(),
+            "/dnsaddr/control.example.com/tcp/4000/service/api"
+        );
+        assert!(identity_route_parser("/dnsaddr/control.example.com/tcp/4000").is_err());
+        assert!(identity_route_parser("I01@/dnsaddr/control.example.com/tcp/4000").is_err());
+    }
 }
@@ -24,6 +24,10 @@ pub struct CreateCommand {
 
     #[arg(long, default_value = "false")]
     aws_kms: bool,
+
+    /// Keep the signing keys of the identities in the TPM 2.0 of this machine
+    #[arg(long, default_value = "false", conflicts_with = "aws_kms")]
+    tpm: bool,
 }
 
 impl CreateCommand {
@@ -41,8 +45,10 @@ async fn run_impl(
     opts: CommandGlobalOpts,
     cmd: CreateCommand,
 ) -> miette::Result<()> {
-    let CreateCommand { name, aws_kms, .. } = cmd;
-    let config = cli_state::VaultConfig::new(aws_kms)?;
+    let CreateCommand {
+        name, aws_kms, tpm, ..
+    } = cmd;
+    let config = cli_state::VaultConfig::new(aws_kms, tpm)?;
     if opts.state.vaults.is_empty()? {
         opts.terminal.write_line(&fmt_info!(
             "This is the first vault to be created in this environment. It will be set as the default vault"
@@ -63,12 +63,10 @@ impl Output for VaultListOutput {
         write!(
             output,
             "Type {}",
-            match self.config.is_aws() {
-                true => "AWS KMS",
-                false => "OCKAM",
-            }
-            .to_string()
-            .color(OckamColor::PrimaryResource.color())
+            self.config
+                .kind()
+                .to_string()
+                .color(OckamColor::PrimaryResource.color())
         )?;
         Ok(output)
     }
@@ -31,9 +31,7 @@ impl ShowCommand {
 }
 
 fn run_impl(opts: CommandGlobalOpts, cmd: ShowCommand) -> miette::Result<()> {
-    let name = cmd
-        .name
-        .unwrap_or(opts.state.vaults.default()?.name().to_string());
+    let name = opts.state.resolve_vault(cmd.name.as_deref())?.name;
     let state = opts.state.vaults.get(name)?;
 
     let json = serde_json::to_string_pretty(&state).into_diagnostic()?;
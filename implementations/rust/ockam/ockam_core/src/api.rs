@@ -34,6 +34,13 @@ pub struct RequestHeader {
     #[n(3)] method: Option<Method>,
     /// Indicator if a request body is expected after this header.
     #[n(4)] has_body: bool,
+    /// A key identifying the request when it is retried.
+    ///
+    /// A server receiving the same key again returns the original response
+    /// instead of handling the request a second time.
+    #[n(5)] idempotency_key: Option<String>,
+    /// A token authenticating the client of a local node.
+    #[n(6)] api_token: Option<String>,
 }
 
 impl RequestHeader {
@@ -43,6 +50,8 @@ impl RequestHeader {
             method: Some(method),
             path: path.into(),
             has_body,
+            idempotency_key: None,
+            api_token: None,
         }
     }
 }
@@ -278,6 +287,19 @@ impl RequestHeader {
     pub fn has_body(&self) -> bool {
         self.has_body
     }
+
+    pub fn idempotency_key(&self) -> Option<&str> {
+        self.idempotency_key.as_deref()
+    }
+
+    pub fn api_token(&self) -> Option<&str> {
+        self.api_token.as_deref()
+    }
+
+    /// Remove the API token, before the header is kept
+    pub fn clear_api_token(&mut self) {
+        self.api_token = None;
+    }
 }
 
 impl ResponseHeader {
@@ -459,6 +481,18 @@ impl<T> Request<T> {
         self
     }
 
+    /// Identify the request with a key, so that it can be retried without being handled twice
+    pub fn idempotency_key<K: Into<String>>(mut self, key: K) -> Self {
+        self.header.idempotency_key = Some(key.into());
+        self
+    }
+
+    /// Authenticate the request sent to a local node
+    pub fn api_token<K: Into<String>>(mut self, token: K) -> Self {
+        self.header.api_token = Some(token.into());
+        self
+    }
+
     pub fn header(&self) -> &RequestHeader {
         &self.header
     }
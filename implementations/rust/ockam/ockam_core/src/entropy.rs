@@ -0,0 +1,278 @@
+//! Source of the random bytes used to generate secrets, secret handles and nonces.
+
+use crate::compat::boxed::Box;
+use crate::compat::rand::{CryptoRng, RngCore};
+use crate::compat::sync::{Arc, Mutex};
+use crate::errcode::{Kind, Origin};
+use crate::{Error, Result};
+use core::fmt::{Debug, Formatter};
+use core::num::NonZeroU32;
+
+/// A source of random bytes suitable for cryptographic use
+pub trait EntropySource: Send + Sync + 'static {
+    /// Fill `dest` with random bytes, or return an error if the source is not healthy
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<()>;
+}
+
+/// Random number generator of the operating system
+///
+/// WARNING: on `no_std` targets this source does NOT generate true random values, a hardware
+/// source must be set with [`set_entropy_source`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsEntropySource;
+
+impl EntropySource for OsEntropySource {
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<()> {
+        crate::compat::rand::rngs::OsRng
+            .try_fill_bytes(dest)
+            .map_err(|e| Error::new(Origin::Core, Kind::Internal, e))
+    }
+}
+
+#[cfg(feature = "std")]
+static ENTROPY_SOURCE: std::sync::RwLock<Option<Arc<dyn EntropySource>>> =
+    std::sync::RwLock::new(None);
+
+#[cfg(not(feature = "std"))]
+static ENTROPY_SOURCE: spin::RwLock<Option<Arc<dyn EntropySource>>> = spin::RwLock::new(None);
+
+/// Use a source of random bytes for all the secrets and nonces generated by this process
+pub fn set_entropy_source(source: Arc<dyn EntropySource>) {
+    #[cfg(feature = "std")]
+    let mut current = ENTROPY_SOURCE.write().unwrap();
+    #[cfg(not(feature = "std"))]
+    let mut current = ENTROPY_SOURCE.write();
+    *current = Some(source);
+}
+
+/// Return the source of random bytes set with [`set_entropy_source`],
+/// or the random number generator of the operating system
+pub fn entropy_source() -> Arc<dyn EntropySource> {
+    #[cfg(feature = "std")]
+    let current = ENTROPY_SOURCE.read().unwrap();
+    #[cfg(not(feature = "std"))]
+    let current = ENTROPY_SOURCE.read();
+    match current.as_ref() {
+        Some(source) => source.clone(),
+        None => Arc::new(OsEntropySource),
+    }
+}
+
+/// Return a random number generator drawing its bytes from the current [`EntropySource`]
+pub fn rng() -> EntropyRng {
+    EntropyRng {
+        source: entropy_source(),
+    }
+}
+
+/// Random number generator backed by an [`EntropySource`], to use with the
+/// cryptographic libraries expecting a [`RngCore`]
+#[derive(Clone)]
+pub struct EntropyRng {
+    source: Arc<dyn EntropySource>,
+}
+
+impl Debug for EntropyRng {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.write_str("EntropyRng")
+    }
+}
+
+impl CryptoRng for EntropyRng {}
+
+impl RngCore for EntropyRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        // a secret must never be generated from a failing source
+        if let Err(e) = self.source.fill_bytes(dest) {
+            panic!("the entropy source failed: {e}");
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> core::result::Result<(), rand::Error> {
+        self.source.fill_bytes(dest).map_err(|e| {
+            error!("the entropy source failed: {e}");
+            rand::Error::from(NonZeroU32::new(rand::Error::CUSTOM_START).unwrap())
+        })
+    }
+}
+
+/// Parameters of the health tests of a [`HealthTestedSource`].
+///
+/// The default values assume a conservative min-entropy of 1 bit per byte, with a false
+/// positive probability of 2^-20 per test, as described in NIST SP 800-90B section 4.4
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthTestConfig {
+    /// Number of consecutive identical bytes after which the source is considered stuck
+    pub repetition_cutoff: u32,
+    /// Number of bytes of the window of the adaptive proportion test
+    pub proportion_window: u32,
+    /// Maximum number of occurrences of the first byte of a window in that window
+    pub proportion_cutoff: u32,
+}
+
+impl Default for HealthTestConfig {
+    fn default() -> Self {
+        Self {
+            repetition_cutoff: 21,
+            proportion_window: 512,
+            proportion_cutoff: 410,
+        }
+    }
+}
+
+/// An [`EntropySource`] checking the output of another source with the repetition count test
+/// and the adaptive proportion test of NIST SP 800-90B.
+/// Once a test fails, the source fails permanently
+pub struct HealthTestedSource {
+    inner: Box<dyn EntropySource>,
+    config: HealthTestConfig,
+    state: Mutex<HealthState>,
+}
+
+#[derive(Default)]
+struct HealthState {
+    failed: bool,
+    last: Option<u8>,
+    repetitions: u32,
+    window_first: Option<u8>,
+    window_size: u32,
+    window_count: u32,
+}
+
+impl HealthState {
+    /// Run the tests on a new byte, return false if one of them fails
+    fn check(&mut self, byte: u8, config: &HealthTestConfig) -> bool {
+        // repetition count test
+        if self.last == Some(byte) {
+            self.repetitions += 1;
+            if self.repetitions >= config.repetition_cutoff {
+                return false;
+            }
+        } else {
+            self.last = Some(byte);
+            self.repetitions = 1;
+        }
+
+        // adaptive proportion test
+        match self.window_first {
+            None => {
+                self.window_first = Some(byte);
+                self.window_size = 1;
+                self.window_count = 1;
+            }
+            Some(first) => {
+                self.window_size += 1;
+                if first == byte {
+                    self.window_count += 1;
+                    if self.window_count >= config.proportion_cutoff {
+                        return false;
+                    }
+                }
+                if self.window_size >= config.proportion_window {
+                    self.window_first = None;
+                }
+            }
+        }
+        true
+    }
+}
+
+impl HealthTestedSource {
+    /// Check a source with the default health tests
+    pub fn new(inner: impl EntropySource) -> Self {
+        Self::with_config(inner, HealthTestConfig::default())
+    }
+
+    /// Check a source with specific health tests parameters
+    pub fn with_config(inner: impl EntropySource, config: HealthTestConfig) -> Self {
+        Self {
+            inner: Box::new(inner),
+            config,
+            state: Mutex::new(HealthState::default()),
+        }
+    }
+
+    /// Return true if a health test failed
+    pub fn has_failed(&self) -> bool {
+        self.state.lock().unwrap().failed
+    }
+
+    fn failure() -> Error {
+        Error::new(
+            Origin::Core,
+            Kind::Internal,
+            "the entropy source failed its health tests",
+        )
+    }
+}
+
+impl EntropySource for HealthTestedSource {
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.failed {
+            return Err(Self::failure());
+        }
+        self.inner.fill_bytes(dest)?;
+        for byte in dest.iter() {
+            if !state.check(*byte, &self.config) {
+                state.failed = true;
+                dest.iter_mut().for_each(|b| *b = 0);
+                error!("the entropy source failed its health tests");
+                return Err(Self::failure());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StuckSource;
+
+    impl EntropySource for StuckSource {
+        fn fill_bytes(&self, dest: &mut [u8]) -> Result<()> {
+            dest.iter_mut().for_each(|b| *b = 7);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_health_tests() {
+        let source = HealthTestedSource::new(OsEntropySource);
+        let mut bytes = [0u8; 4096];
+        source.fill_bytes(&mut bytes).unwrap();
+        assert!(!source.has_failed());
+
+        let source = HealthTestedSource::new(StuckSource);
+        let mut bytes = [0u8; 32];
+        assert!(source.fill_bytes(&mut bytes).is_err());
+        assert!(source.has_failed());
+        assert_eq!(bytes, [0u8; 32]);
+
+        // the failure is permanent
+        let mut bytes = [0u8; 1];
+        assert!(source.fill_bytes(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_rng_uses_the_source() {
+        let mut rng = EntropyRng {
+            source: Arc::new(StuckSource),
+        };
+        assert_eq!(rng.next_u32(), 0x07070707);
+    }
+}
@@ -112,6 +112,13 @@ impl FromString for u64 {
     }
 }
 
+impl FromString for i64 {
+    fn from_string(s: &str) -> Result<Self> {
+        s.parse::<i64>()
+            .map_err(|_| error("i64 parsing error".to_string()))
+    }
+}
+
 #[cfg(feature = "std")]
 impl FromString for PathBuf {
     fn from_string(s: &str) -> Result<Self> {
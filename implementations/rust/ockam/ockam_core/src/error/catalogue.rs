@@ -0,0 +1,471 @@
+//! Catalogue of the stable codes given to the errors shown to users.
+use super::code::{ErrorCode, Kind};
+
+/// Category of an error, telling who can fix it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// The input or the configuration must be fixed
+    Usage,
+    /// A resource doesn't exist
+    NotFound,
+    /// A resource already exists or is being modified
+    Conflict,
+    /// The identity doesn't have the permission, or can't be trusted
+    Unauthorized,
+    /// A remote resource or the network is unavailable, retrying later may work
+    Unavailable,
+    /// An operation took too long, retrying later may work
+    Timeout,
+    /// The error is a bug and should be reported
+    Internal,
+}
+
+impl core::fmt::Display for Category {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let category = match self {
+            Category::Usage => "usage",
+            Category::NotFound => "not found",
+            Category::Conflict => "conflict",
+            Category::Unauthorized => "unauthorized",
+            Category::Unavailable => "unavailable",
+            Category::Timeout => "timeout",
+            Category::Internal => "internal",
+        };
+        f.write_str(category)
+    }
+}
+
+/// An entry of the errors catalogue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CatalogueEntry {
+    /// Number of the entry, stored in [`ErrorCode::extra`] for the specific entries
+    pub number: i32,
+    /// Stable code of the error, for example `OCK404`
+    pub code: &'static str,
+    /// Category of the error
+    pub category: Category,
+    /// Suggested remediation
+    pub help: &'static str,
+}
+
+impl CatalogueEntry {
+    const fn new(number: i32, code: &'static str, category: Category, help: &'static str) -> Self {
+        Self {
+            number,
+            code,
+            category,
+            help,
+        }
+    }
+
+    /// Return the entry having a given number, if there is one
+    pub fn find(number: i32) -> Option<&'static CatalogueEntry> {
+        CATALOGUE.iter().find(|e| e.number == number)
+    }
+
+    /// Return the generic entry of an error kind
+    pub fn for_kind(kind: Kind) -> &'static CatalogueEntry {
+        let number = match kind {
+            Kind::Invalid | Kind::Misuse | Kind::Serialization | Kind::Protocol => 400,
+            Kind::NotFound => 404,
+            Kind::AlreadyExists | Kind::Conflict => 409,
+            Kind::ResourceExhausted => 429,
+            Kind::Cancelled => 499,
+            Kind::Unsupported => 501,
+            Kind::Io => 502,
+            Kind::Shutdown => 503,
+            Kind::Timeout => 504,
+            Kind::Unknown | Kind::Internal | Kind::Other => 500,
+        };
+        Self::find(number).unwrap_or(&INTERNAL)
+    }
+}
+
+impl ErrorCode {
+    /// Return the catalogue entry of this error code
+    pub fn catalogue_entry(&self) -> &'static CatalogueEntry {
+        match self.extra {
+            0 => CatalogueEntry::for_kind(self.kind),
+            extra => {
+                CatalogueEntry::find(extra).unwrap_or_else(|| CatalogueEntry::for_kind(self.kind))
+            }
+        }
+    }
+
+    /// Return the stable code of this error code, for example `OCK404`
+    pub fn as_str(&self) -> &'static str {
+        self.catalogue_entry().code
+    }
+}
+
+const INTERNAL: CatalogueEntry = CatalogueEntry::new(
+    500,
+    "OCK500",
+    Category::Internal,
+    "Please report this issue, with a copy of your logs, to https://github.com/build-trust/ockam/issues",
+);
+
+/// All the entries of the catalogue. Entries can be added but never renumbered
+pub const CATALOGUE: &[CatalogueEntry] = &[
+    // ==== Error kinds ====
+    CatalogueEntry::new(
+        400,
+        "OCK400",
+        Category::Usage,
+        "Please check the arguments and the format of the values, and try again",
+    ),
+    CatalogueEntry::new(
+        404,
+        "OCK404",
+        Category::NotFound,
+        "Please check the spelling and try again",
+    ),
+    CatalogueEntry::new(
+        409,
+        "OCK409",
+        Category::Conflict,
+        "Please use another name, or delete the existing resource, and try again",
+    ),
+    CatalogueEntry::new(
+        429,
+        "OCK429",
+        Category::Unavailable,
+        "Please wait for some resources to be released and try again",
+    ),
+    CatalogueEntry::new(
+        499,
+        "OCK499",
+        Category::Unavailable,
+        "The operation was cancelled, please try again",
+    ),
+    INTERNAL,
+    CatalogueEntry::new(
+        501,
+        "OCK501",
+        Category::Usage,
+        "This operation is not supported, please check the documentation for alternatives",
+    ),
+    CatalogueEntry::new(
+        502,
+        "OCK502",
+        Category::Unavailable,
+        "Please check the network connection and the permissions of the files, and try again",
+    ),
+    CatalogueEntry::new(
+        503,
+        "OCK503",
+        Category::Unavailable,
+        "The node is shutting down, please restart it and try again",
+    ),
+    CatalogueEntry::new(
+        504,
+        "OCK504",
+        Category::Timeout,
+        "Please check that the remote node is running and reachable, and try again",
+    ),
+    // ==== Identity errors ====
+    CatalogueEntry::new(
+        1001,
+        "OCK1001",
+        Category::Usage,
+        "The key type is not supported, please use an Ed25519 or a P256 key",
+    ),
+    CatalogueEntry::new(
+        1002,
+        "OCK1002",
+        Category::Usage,
+        "The key data is invalid, please check that the key was exported correctly",
+    ),
+    CatalogueEntry::new(
+        1003,
+        "OCK1003",
+        Category::Usage,
+        "Please check the identifier, which starts with 'I' followed by 40 hexadecimal characters",
+    ),
+    CatalogueEntry::new(
+        1004,
+        "OCK1004",
+        Category::Usage,
+        "The identity has no change history, please create it again",
+    ),
+    CatalogueEntry::new(
+        1005,
+        "OCK1005",
+        Category::Unauthorized,
+        "The identity can't be verified, please check that it was not modified",
+    ),
+    CatalogueEntry::new(
+        1006,
+        "OCK1006",
+        Category::Unauthorized,
+        "The purpose key can't be verified, please check that it was attested by this identity",
+    ),
+    CatalogueEntry::new(
+        1007,
+        "OCK1007",
+        Category::Unauthorized,
+        "The credential can't be verified, please request a new credential from the authority",
+    ),
+    CatalogueEntry::new(
+        1008,
+        "OCK1008",
+        Category::Internal,
+        "Please check the system clock of this machine",
+    ),
+    CatalogueEntry::new(
+        1009,
+        "OCK1009",
+        Category::Unauthorized,
+        "The credential was issued by an unknown authority, please check the trust context",
+    ),
+    CatalogueEntry::new(
+        1010,
+        "OCK1010",
+        Category::Usage,
+        "The credential version is not supported, please upgrade Ockam",
+    ),
+    CatalogueEntry::new(
+        1011,
+        "OCK1011",
+        Category::Usage,
+        "The identity version is not supported, please upgrade Ockam",
+    ),
+    CatalogueEntry::new(
+        1012,
+        "OCK1012",
+        Category::Unauthorized,
+        "The credential was rejected, please check that it was issued by the trusted authority",
+    ),
+    CatalogueEntry::new(
+        1013,
+        "OCK1013",
+        Category::Usage,
+        "Please configure a trust context to check the credentials",
+    ),
+    CatalogueEntry::new(
+        1014,
+        "OCK1014",
+        Category::Unauthorized,
+        "The other party is not trusted, please check the trust policy of the secure channel",
+    ),
+    CatalogueEntry::new(
+        1015,
+        "OCK1015",
+        Category::Unauthorized,
+        "The other party was rejected by the admission filter of the secure channel listener",
+    ),
+    CatalogueEntry::new(
+        1016,
+        "OCK1016",
+        Category::Unavailable,
+        "The identity reached its quota of resources on this node, please release some resources",
+    ),
+    CatalogueEntry::new(
+        1017,
+        "OCK1017",
+        Category::Unauthorized,
+        "A message was replayed or corrupted, please create a new secure channel",
+    ),
+    CatalogueEntry::new(
+        1018,
+        "OCK1018",
+        Category::Unavailable,
+        "The secure channel sent too many messages, please create a new secure channel",
+    ),
+    CatalogueEntry::new(
+        1019,
+        "OCK1019",
+        Category::Usage,
+        "Please check the route of the messages sent through the secure channel",
+    ),
+    CatalogueEntry::new(1020, "OCK1020", Category::Internal, INTERNAL.help),
+    CatalogueEntry::new(
+        1021,
+        "OCK1021",
+        Category::Conflict,
+        "A secure channel already exists at this address, please use another address",
+    ),
+    CatalogueEntry::new(1022, "OCK1022", Category::Internal, INTERNAL.help),
+    CatalogueEntry::new(
+        1023,
+        "OCK1023",
+        Category::Usage,
+        "Please check that the value only contains hexadecimal characters",
+    ),
+    CatalogueEntry::new(
+        1024,
+        "OCK1024",
+        Category::Usage,
+        "The secret key doesn't belong to this identity, please check the vault",
+    ),
+    CatalogueEntry::new(
+        1025,
+        "OCK1025",
+        Category::Unauthorized,
+        "The authority transition can't be applied, please check that it was signed by the current authority",
+    ),
+    CatalogueEntry::new(
+        1026,
+        "OCK1026",
+        Category::Unauthorized,
+        "The stored data was modified, please restore it from a backup or create it again",
+    ),
+    CatalogueEntry::new(
+        1027,
+        "OCK1027",
+        Category::Unauthorized,
+        "The credential is not valid yet, please check the clocks of this machine and of the authority",
+    ),
+    CatalogueEntry::new(
+        1028,
+        "OCK1028",
+        Category::Unauthorized,
+        "The credential is expired, please request a new credential from the authority",
+    ),
+    CatalogueEntry::new(
+        1029,
+        "OCK1029",
+        Category::Unauthorized,
+        "The key of the other party is not attested by a trusted hardware, check its TPM, Secure Enclave or KMS configuration",
+    ),
+    CatalogueEntry::new(
+        1030,
+        "OCK1030",
+        Category::Usage,
+        "The metadata of a secure channel message must be smaller than 64KiB, and both parties must support message metadata",
+    ),
+    CatalogueEntry::new(
+        1031,
+        "OCK1031",
+        Category::Unauthorized,
+        "The identity was not delegated by its parent, please create it again from the parent identity",
+    ),
+    CatalogueEntry::new(
+        1032,
+        "OCK1032",
+        Category::Unauthorized,
+        "The identity is limited by its parent, please create a child identity allowing this purpose or attribute",
+    ),
+    CatalogueEntry::new(
+        1033,
+        "OCK1033",
+        Category::Usage,
+        "A secure channel message can't be decompressed, please check that both parties use the same compression dictionary",
+    ),
+    // ==== Transport errors ====
+    CatalogueEntry::new(
+        2001,
+        "OCK2001",
+        Category::Usage,
+        "The message can't be sent, please check its encoding",
+    ),
+    CatalogueEntry::new(
+        2002,
+        "OCK2002",
+        Category::Unavailable,
+        "A malformed message was received, please check that both nodes use the same Ockam version",
+    ),
+    CatalogueEntry::new(
+        2003,
+        "OCK2003",
+        Category::Conflict,
+        "The address can't be bound, please check that no other process listens on it",
+    ),
+    CatalogueEntry::new(
+        2004,
+        "OCK2004",
+        Category::Unavailable,
+        "The connection was dropped, please check that the remote node is running",
+    ),
+    CatalogueEntry::new(
+        2005,
+        "OCK2005",
+        Category::Conflict,
+        "A connection to this peer already exists, please reuse it",
+    ),
+    CatalogueEntry::new(
+        2006,
+        "OCK2006",
+        Category::Unavailable,
+        "The peer can't be reached, please check its address and that it is running",
+    ),
+    CatalogueEntry::new(
+        2007,
+        "OCK2007",
+        Category::Unavailable,
+        "The peer is busy, please try again later",
+    ),
+    CatalogueEntry::new(
+        2008,
+        "OCK2008",
+        Category::Usage,
+        "Please check the route of the message",
+    ),
+    CatalogueEntry::new(
+        2009,
+        "OCK2009",
+        Category::Usage,
+        "Please check the socket address, for example 127.0.0.1:4000",
+    ),
+    CatalogueEntry::new(
+        2010,
+        "OCK2010",
+        Category::Usage,
+        "The message is too big, please send smaller messages",
+    ),
+    CatalogueEntry::new(
+        2011,
+        "OCK2011",
+        Category::Usage,
+        "The message can't be encoded, please check its content",
+    ),
+    CatalogueEntry::new(
+        2012,
+        "OCK2012",
+        Category::Unavailable,
+        "The transport protocol was violated, please check that both nodes use the same Ockam version",
+    ),
+    CatalogueEntry::new(
+        2013,
+        "OCK2013",
+        Category::Unavailable,
+        "Please check the network connection and try again",
+    ),
+    CatalogueEntry::new(2014, "OCK2014", Category::Internal, INTERNAL.help),
+    CatalogueEntry::new(2015, "OCK2015", Category::Internal, INTERNAL.help),
+    CatalogueEntry::new(
+        2016,
+        "OCK2016",
+        Category::Unauthorized,
+        "A message with an excessive header length was rejected, please check the peer",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::collections::BTreeSet;
+    use crate::errcode::Origin;
+
+    #[test]
+    fn test_catalogue_codes_are_unique() {
+        let numbers: BTreeSet<i32> = CATALOGUE.iter().map(|e| e.number).collect();
+        let codes: BTreeSet<&str> = CATALOGUE.iter().map(|e| e.code).collect();
+        assert_eq!(numbers.len(), CATALOGUE.len());
+        assert_eq!(codes.len(), CATALOGUE.len());
+    }
+
+    #[test]
+    fn test_catalogue_entry() {
+        let code = ErrorCode::new(Origin::Node, Kind::NotFound);
+        assert_eq!(code.as_str(), "OCK404");
+        assert_eq!(code.catalogue_entry().category, Category::NotFound);
+
+        let code = ErrorCode::new_with_extra(Origin::Identity, Kind::Invalid, 1028);
+        assert_eq!(code.as_str(), "OCK1028");
+
+        // an unknown number falls back to the entry of the kind
+        let code = ErrorCode::new_with_extra(Origin::Transport, Kind::Timeout, 42);
+        assert_eq!(code.as_str(), "OCK504");
+    }
+}
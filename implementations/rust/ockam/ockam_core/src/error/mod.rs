@@ -7,11 +7,13 @@ use serde::{Deserialize, Serialize};
 
 use self::code::ErrorCode;
 
+mod catalogue;
 mod code;
 mod inner;
 
 /// A module to export the error code in a meaningful way
 pub mod errcode {
+    pub use super::catalogue::*;
     pub use super::code::*;
 }
 
@@ -89,6 +91,29 @@ impl Error {
         self.0.code
     }
 
+    /// Return the stable code of this error in the [errors catalogue](errcode::CATALOGUE),
+    /// for example `OCK404`
+    pub fn catalogue_code(&self) -> &'static str {
+        self.0.code.as_str()
+    }
+
+    /// Return the category of this error
+    pub fn category(&self) -> errcode::Category {
+        self.0.code.catalogue_entry().category
+    }
+
+    /// Return a suggested remediation for this error
+    pub fn help(&self) -> &'static str {
+        self.0.code.catalogue_entry().help
+    }
+
+    /// Identify this error with a specific entry of the [errors catalogue](errcode::CATALOGUE)
+    #[must_use]
+    pub fn with_catalogue_number(mut self, number: i32) -> Self {
+        self.0.code.extra = number;
+        self
+    }
+
     /// Return the source location for this error
     #[cfg(feature = "std")]
     pub(super) fn source_location(&self) -> Location {
@@ -67,6 +67,7 @@ pub mod compat;
 
 /// Debugger
 pub mod debugger;
+pub mod entropy;
 pub mod flow_control;
 
 /// Encoding
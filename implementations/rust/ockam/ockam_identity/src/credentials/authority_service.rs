@@ -1,14 +1,21 @@
+use crate::credentials::authority_transition::accepted_authorities;
 use crate::credentials::credentials_retriever::CredentialsRetriever;
 use crate::models::{CredentialAndPurposeKey, Identifier, TimestampInSeconds};
+use crate::storage::Storage;
 use crate::utils::{add_seconds, now};
-use crate::{Credentials, IdentityError};
+use crate::{AuthorityTransition, AuthorityTransitionData, Credentials, IdentityError};
 use tracing::debug;
 
+use ockam_core::compat::string::ToString;
 use ockam_core::compat::sync::Arc;
 use ockam_core::compat::sync::RwLock;
+use ockam_core::compat::vec::Vec;
 use ockam_core::Result;
 use ockam_node::Context;
 
+/// Storage key used to persist the authority transitions accepted for an authority
+const AUTHORITY_TRANSITIONS_KEY: &str = "AUTHORITY_TRANSITIONS";
+
 /// An AuthorityService represents an authority which issued credentials
 #[derive(Clone)]
 pub struct AuthorityService {
@@ -16,6 +23,8 @@ pub struct AuthorityService {
     identifier: Identifier,
     own_credential: Option<Arc<dyn CredentialsRetriever>>,
     inner_cache: Arc<RwLock<Option<CachedCredential>>>,
+    transitions: Arc<RwLock<Vec<AuthorityTransitionData>>>,
+    transitions_storage: Option<Arc<dyn Storage>>,
 }
 
 #[derive(Clone)]
@@ -36,7 +45,31 @@ impl AuthorityService {
             identifier,
             own_credential,
             inner_cache: Arc::new(RwLock::new(None)),
+            transitions: Arc::new(RwLock::new(vec![])),
+            transitions_storage: None,
+        }
+    }
+
+    /// Persist the accepted transitions in a storage, so that they are still applied after
+    /// a restart. The transitions previously stored for this authority are loaded
+    pub async fn with_transitions_storage(mut self, storage: Arc<dyn Storage>) -> Result<Self> {
+        if let Some(value) = storage
+            .get(&self.identifier.to_string(), AUTHORITY_TRANSITIONS_KEY)
+            .await?
+        {
+            let transitions: Vec<AuthorityTransitionData> = minicbor::decode(&value)?;
+            if let (Some(last), Some(retriever)) = (transitions.last(), &self.own_credential) {
+                retriever.issuer_changed(&last.next);
+            }
+            debug!(
+                "loaded {} authority transitions for {}",
+                transitions.len(),
+                self.identifier
+            );
+            self.transitions = Arc::new(RwLock::new(transitions));
         }
+        self.transitions_storage = Some(storage);
+        Ok(self)
     }
 
     /// Retrieve the credential for an identity within this authority
@@ -68,7 +101,11 @@ impl AuthorityService {
         let credential_data = self
             .credentials
             .credentials_verification()
-            .verify_credential(Some(subject), &[self.identifier.clone()], &credential)
+            .verify_credential(
+                Some(subject),
+                self.accepted_identifiers()?.as_slice(),
+                &credential,
+            )
             .await?;
 
         debug!("the retrieved credential is valid");
@@ -83,7 +120,76 @@ impl AuthorityService {
     }
 
     /// Issuer [`Identifier`]
+    /// This is the identifier the authority was initially configured with, see
+    /// [`AuthorityService::current_identifier`] for the identifier of the authority after
+    /// some authority transitions
     pub fn identifier(&self) -> &Identifier {
         &self.identifier
     }
+
+    /// Return the identifier of the authority after applying all the accepted transitions
+    pub fn current_identifier(&self) -> Identifier {
+        let guard = self.transitions.read().unwrap();
+        guard
+            .last()
+            .map(|t| t.next.clone())
+            .unwrap_or_else(|| self.identifier.clone())
+    }
+
+    /// Return the identifiers of the authorities whose credentials are currently accepted:
+    /// the current authority, and the previous ones if their grace window is still open
+    pub fn accepted_identifiers(&self) -> Result<Vec<Identifier>> {
+        let guard = self.transitions.read().unwrap();
+        accepted_authorities(&self.identifier, guard.as_slice())
+    }
+
+    /// Verify a transition statement signed by the current authority and, if it is valid,
+    /// start using the new authority. Credentials issued by the previous authority are still
+    /// accepted until the end of the grace window stated by the transition.
+    pub async fn accept_transition(
+        &self,
+        transition: &AuthorityTransition,
+    ) -> Result<AuthorityTransitionData> {
+        let current = self.current_identifier();
+        let data = transition.verify(&self.credentials, &current).await?;
+
+        let transitions = {
+            let mut guard = self.transitions.write().unwrap();
+            // the authority might have changed while we were verifying the statement
+            let still_current = guard
+                .last()
+                .map(|t| t.next == current)
+                .unwrap_or(self.identifier == current);
+            if !still_current {
+                return Err(IdentityError::InvalidAuthorityTransition.into());
+            }
+            guard.push(data.clone());
+            minicbor::to_vec(guard.as_slice())?
+        };
+        if let Some(retriever) = &self.own_credential {
+            retriever.issuer_changed(&data.next);
+        }
+        if let Some(storage) = &self.transitions_storage {
+            storage
+                .set(
+                    &self.identifier.to_string(),
+                    AUTHORITY_TRANSITIONS_KEY.to_string(),
+                    transitions,
+                )
+                .await?;
+        }
+
+        // our own credential was issued by the previous authority, a new one must be retrieved
+        *self.inner_cache.write().unwrap() = None;
+        debug!(
+            "accepted the authority transition from {} to {}",
+            data.previous, data.next
+        );
+        Ok(data)
+    }
+
+    /// Return the transitions accepted by this authority service
+    pub fn transitions(&self) -> Vec<AuthorityTransitionData> {
+        self.transitions.read().unwrap().clone()
+    }
 }
@@ -0,0 +1,225 @@
+use crate::models::{CredentialAndPurposeKey, CredentialSchemaIdentifier, Identifier};
+use crate::utils::{now, AttributesBuilder};
+use crate::{Credentials, IdentityError, TimestampInSeconds};
+
+use core::time::Duration;
+use minicbor::{Decode, Encode};
+use ockam_core::compat::string::ToString;
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+
+/// Identifier for the schema of an authority transition statement
+pub const AUTHORITY_TRANSITION_SCHEMA: CredentialSchemaIdentifier = CredentialSchemaIdentifier(2);
+
+/// Name of the attribute containing the identifier of the authority being replaced
+pub const AUTHORITY_TRANSITION_PREVIOUS: &[u8] = b"ockam.authority.previous";
+
+/// A signed statement, issued by the current authority of a trust context, stating that
+/// another identity becomes the new authority.
+///
+/// The statement is a [`CredentialAndPurposeKey`] where:
+///  - the issuer is the previous authority
+///  - the subject is the next authority
+///  - the expiration date is the end of the grace window, during which credentials issued
+///    by the previous authority are still accepted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuthorityTransition {
+    statement: CredentialAndPurposeKey,
+}
+
+/// Verified content of an [`AuthorityTransition`]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct AuthorityTransitionData {
+    /// Authority being replaced
+    #[n(1)] pub previous: Identifier,
+    /// Authority replacing the previous one
+    #[n(2)] pub next: Identifier,
+    /// Time at which the transition has been issued
+    #[n(3)] pub effective_at: TimestampInSeconds,
+    /// Credentials issued by the previous authority are accepted until that time
+    #[n(4)] pub grace_until: TimestampInSeconds,
+}
+
+impl AuthorityTransitionData {
+    /// Return true if credentials issued by the previous authority must still be accepted
+    pub fn is_in_grace_window(&self, now: TimestampInSeconds) -> bool {
+        now <= self.grace_until
+    }
+}
+
+impl AuthorityTransition {
+    /// Create an [`AuthorityTransition`] from a statement received from an authority
+    pub fn new(statement: CredentialAndPurposeKey) -> Self {
+        Self { statement }
+    }
+
+    /// Return the signed statement
+    pub fn statement(&self) -> &CredentialAndPurposeKey {
+        &self.statement
+    }
+
+    /// Issue a transition statement from `previous` to `next`.
+    /// The `previous` identity must be the current authority, and its keys must be available
+    /// in the vault used by `credentials`.
+    pub async fn issue(
+        credentials: &Credentials,
+        previous: &Identifier,
+        next: &Identifier,
+        grace_period: Duration,
+    ) -> Result<Self> {
+        if previous == next {
+            return Err(IdentityError::InvalidAuthorityTransition.into());
+        }
+        let attributes = AttributesBuilder::with_schema(AUTHORITY_TRANSITION_SCHEMA)
+            .with_attribute(
+                AUTHORITY_TRANSITION_PREVIOUS.to_vec(),
+                previous.to_string().as_bytes().to_vec(),
+            )
+            .build();
+        let statement = credentials
+            .credentials_creation()
+            .issue_credential(previous, next, attributes, grace_period)
+            .await?;
+        Ok(Self { statement })
+    }
+
+    /// Verify that the statement has been signed by `previous` and return its content
+    pub async fn verify(
+        &self,
+        credentials: &Credentials,
+        previous: &Identifier,
+    ) -> Result<AuthorityTransitionData> {
+        let data = credentials
+            .credentials_verification()
+            .verify_credential(None, &[previous.clone()], &self.statement)
+            .await?;
+        let credential_data = data.credential_data;
+
+        if credential_data.subject_attributes.schema != AUTHORITY_TRANSITION_SCHEMA {
+            return Err(IdentityError::InvalidAuthorityTransition.into());
+        }
+
+        let stated_previous = credential_data
+            .subject_attributes
+            .map
+            .iter()
+            .find(|(k, _)| Vec::<u8>::from((*k).clone()) == AUTHORITY_TRANSITION_PREVIOUS)
+            .map(|(_, v)| Vec::<u8>::from(v.clone()));
+        if stated_previous.as_deref() != Some(previous.to_string().as_bytes()) {
+            return Err(IdentityError::InvalidAuthorityTransition.into());
+        }
+
+        let next = credential_data
+            .subject
+            .ok_or(IdentityError::InvalidAuthorityTransition)?;
+
+        Ok(AuthorityTransitionData {
+            previous: previous.clone(),
+            next,
+            effective_at: credential_data.created_at,
+            grace_until: credential_data.expires_at,
+        })
+    }
+}
+
+/// Return the list of authorities which must be accepted at a given time, given an initial
+/// authority and the chain of transitions which have been accepted since then
+pub(crate) fn accepted_authorities(
+    initial: &Identifier,
+    transitions: &[AuthorityTransitionData],
+) -> Result<Vec<Identifier>> {
+    let now = now()?;
+    let mut authorities = vec![];
+    let mut current = initial;
+    for transition in transitions {
+        if transition.is_in_grace_window(now) {
+            authorities.push(current.clone());
+        }
+        current = &transition.next;
+    }
+    authorities.push(current.clone());
+    Ok(authorities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identities::identities;
+    use crate::storage::InMemoryStorage;
+    use crate::AuthorityService;
+
+    #[tokio::test]
+    async fn test_authority_transition() -> Result<()> {
+        let identities = identities();
+        let creation = identities.identities_creation();
+
+        let previous = creation.create_identity().await?;
+        let next = creation.create_identity().await?;
+        let other = creation.create_identity().await?;
+        let credentials = identities.credentials();
+
+        let transition = AuthorityTransition::issue(
+            &credentials,
+            previous.identifier(),
+            next.identifier(),
+            Duration::from_secs(60),
+        )
+        .await?;
+
+        let data = transition
+            .verify(&credentials, previous.identifier())
+            .await?;
+        assert_eq!(&data.next, next.identifier());
+        assert!(data.is_in_grace_window(now()?));
+
+        // the statement must be signed by the expected authority
+        assert!(transition
+            .verify(&credentials, other.identifier())
+            .await
+            .is_err());
+
+        // during the grace window both authorities are accepted
+        let authorities = accepted_authorities(previous.identifier(), &[data])?;
+        assert_eq!(
+            authorities,
+            vec![previous.identifier().clone(), next.identifier().clone()]
+        );
+
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_authority_transitions_are_persisted() -> Result<()> {
+        let identities = identities();
+        let creation = identities.identities_creation();
+        let previous = creation.create_identity().await?;
+        let next = creation.create_identity().await?;
+        let credentials = identities.credentials();
+        let storage = InMemoryStorage::create();
+
+        let authority =
+            AuthorityService::new(credentials.clone(), previous.identifier().clone(), None)
+                .with_transitions_storage(storage.clone())
+                .await?;
+        let transition = AuthorityTransition::issue(
+            &credentials,
+            previous.identifier(),
+            next.identifier(),
+            Duration::from_secs(60),
+        )
+        .await?;
+        authority.accept_transition(&transition).await?;
+
+        // a restarted node still accepts the credentials of both authorities
+        let authority = AuthorityService::new(credentials, previous.identifier().clone(), None)
+            .with_transitions_storage(storage)
+            .await?;
+        assert_eq!(&authority.current_identifier(), next.identifier());
+        assert_eq!(
+            authority.accepted_identifiers()?,
+            vec![previous.identifier().clone(), next.identifier().clone()]
+        );
+        Ok(())
+    }
+}
@@ -1,5 +1,8 @@
 use crate::models::{CredentialData, PurposeKeyAttestationData};
-use crate::{CredentialsCreation, CredentialsVerification, IdentitiesRepository, PurposeKeys};
+use crate::{
+    CredentialsClock, CredentialsCreation, CredentialsVerification, IdentitiesRepository,
+    PurposeKeys,
+};
 
 use ockam_core::compat::sync::Arc;
 use ockam_vault::{VaultForSigning, VaultForVerifyingSignatures};
@@ -20,6 +23,7 @@ pub struct Credentials {
     verifying_vault: Arc<dyn VaultForVerifyingSignatures>,
     purpose_keys: Arc<PurposeKeys>,
     identities_repository: Arc<dyn IdentitiesRepository>,
+    clock: CredentialsClock,
 }
 
 impl Credentials {
@@ -35,9 +39,21 @@ impl Credentials {
             verifying_vault,
             purpose_keys,
             identities_repository,
+            clock: CredentialsClock::default(),
         }
     }
 
+    /// Set the clock settings used to issue and verify credentials
+    pub fn with_clock(mut self, clock: CredentialsClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// [`CredentialsClock`]
+    pub fn clock(&self) -> CredentialsClock {
+        self.clock
+    }
+
     /// [`PurposeKeys`]
     pub fn purpose_keys(&self) -> Arc<PurposeKeys> {
         self.purpose_keys.clone()
@@ -50,21 +66,27 @@ impl Credentials {
 
     /// Return [`CredentialsCreation`]
     pub fn credentials_creation(&self) -> Arc<CredentialsCreation> {
-        Arc::new(CredentialsCreation::new(
-            self.purpose_keys.purpose_keys_creation(),
-            self.credential_vault.clone(),
-            self.verifying_vault.clone(),
-            self.identities_repository.clone(),
-        ))
+        Arc::new(
+            CredentialsCreation::new(
+                self.purpose_keys.purpose_keys_creation(),
+                self.credential_vault.clone(),
+                self.verifying_vault.clone(),
+                self.identities_repository.clone(),
+            )
+            .with_clock(self.clock),
+        )
     }
 
     /// Return [`CredentialsVerification`]
     pub fn credentials_verification(&self) -> Arc<CredentialsVerification> {
-        Arc::new(CredentialsVerification::new(
-            self.purpose_keys.purpose_keys_verification(),
-            self.verifying_vault.clone(),
-            self.identities_repository.clone(),
-        ))
+        Arc::new(
+            CredentialsVerification::new(
+                self.purpose_keys.purpose_keys_verification(),
+                self.verifying_vault.clone(),
+                self.identities_repository.clone(),
+            )
+            .with_clock(self.clock),
+        )
     }
 }
 
@@ -72,7 +94,8 @@ impl Credentials {
 mod tests {
     use crate::identities::identities;
     use crate::models::CredentialSchemaIdentifier;
-    use crate::Attributes;
+    use crate::utils::AttributesBuilder;
+    use crate::{Attributes, Credentials, CredentialsClock};
     use minicbor::bytes::ByteVec;
     use ockam_core::compat::collections::BTreeMap;
     use ockam_core::Result;
@@ -117,4 +140,90 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_issue_credential_with_clock_offset() -> Result<()> {
+        let identities = identities();
+        let creation = identities.identities_creation();
+
+        let issuer = creation.create_identity().await?;
+        let subject = creation.create_identity().await?;
+
+        // the issuer clock is one minute ahead, which is more than the default acceptance window
+        let issuer_clock = CredentialsClock::default().with_clock_offset(60);
+        let issuer_credentials = Credentials::new(
+            identities.vault().credential_vault,
+            identities.vault().verifying_vault,
+            identities.purpose_keys(),
+            identities.repository(),
+        )
+        .with_clock(issuer_clock);
+
+        let credential = issuer_credentials
+            .credentials_creation()
+            .issue_credential(
+                issuer.identifier(),
+                subject.identifier(),
+                AttributesBuilder::with_schema(CredentialSchemaIdentifier(1)).build(),
+                Duration::from_secs(120),
+            )
+            .await?;
+
+        let data = identities
+            .credentials()
+            .credentials_verification()
+            .verify_credential(
+                Some(subject.identifier()),
+                &[issuer.identifier().clone()],
+                &credential,
+            )
+            .await?;
+        assert_eq!(data.credential_data.issuer_clock_offset, Some(60));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_issue_credential_with_audience() -> Result<()> {
+        let identities = identities();
+        let creation = identities.identities_creation();
+
+        let issuer = creation.create_identity().await?;
+        let subject = creation.create_identity().await?;
+        let credentials = identities.credentials();
+
+        let credential = credentials
+            .credentials_creation()
+            .issue_credential_with_audience(
+                issuer.identifier(),
+                subject.identifier(),
+                AttributesBuilder::with_schema(CredentialSchemaIdentifier(1))
+                    .with_attribute("key", "value")
+                    .build(),
+                Duration::from_secs(60),
+                Some(vec!["outlet-1".to_string()]),
+            )
+            .await?;
+
+        credentials
+            .credentials_verification()
+            .receive_presented_credential(
+                subject.identifier(),
+                &[issuer.identifier().clone()],
+                &credential,
+            )
+            .await?;
+
+        let entry = identities
+            .repository()
+            .get_attributes(subject.identifier())
+            .await?
+            .unwrap();
+        assert_eq!(entry.audience(), Some(["outlet-1".to_string()].as_slice()));
+        assert!(entry.is_valid_for(Some("outlet-1")));
+        assert!(!entry.is_valid_for(Some("outlet-2")));
+        assert!(!entry.is_valid_for(None));
+
+        Ok(())
+    }
 }
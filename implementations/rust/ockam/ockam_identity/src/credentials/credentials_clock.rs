@@ -0,0 +1,120 @@
+use core::time::Duration;
+
+use crate::models::TimestampInSeconds;
+
+/// Default difference accepted between the creation time of a credential and the local time.
+/// Credentials can be created in the future related to this machine's time due to
+/// possible time dyssynchronization
+const DEFAULT_ACCEPTANCE_WINDOW: u64 = 5;
+
+/// Clock settings used to issue and verify [`super::super::models::Credential`]s.
+///
+/// An issuer embeds its clock offset in the credentials it signs, so that verifiers can compare
+/// the credential timestamps to their own time, corrected with their own offset.
+/// The remaining difference, which is unknown, must fit in the acceptance window of the verifier
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CredentialsClock {
+    clock_offset: i64,
+    acceptance_window: u64,
+}
+
+impl Default for CredentialsClock {
+    fn default() -> Self {
+        Self {
+            clock_offset: 0,
+            acceptance_window: DEFAULT_ACCEPTANCE_WINDOW,
+        }
+    }
+}
+
+impl CredentialsClock {
+    /// Set the offset of the local clock compared to a reference clock, in seconds.
+    /// The offset is positive when the local clock is ahead of the reference clock
+    pub fn with_clock_offset(mut self, clock_offset: i64) -> Self {
+        self.clock_offset = clock_offset;
+        self
+    }
+
+    /// Set the maximum difference accepted between the creation time of a credential
+    /// and the local time, once both are corrected with their clock offsets
+    pub fn with_acceptance_window(mut self, acceptance_window: Duration) -> Self {
+        self.acceptance_window = acceptance_window.as_secs();
+        self
+    }
+
+    /// Offset of the local clock, in seconds
+    pub fn clock_offset(&self) -> i64 {
+        self.clock_offset
+    }
+
+    /// Offset to embed in an issued credential, if the local clock has an offset
+    pub fn clock_offset_hint(&self) -> Option<i64> {
+        if self.clock_offset == 0 {
+            None
+        } else {
+            Some(self.clock_offset)
+        }
+    }
+
+    /// Acceptance window of the credentials creation time
+    pub fn acceptance_window(&self) -> Duration {
+        Duration::from_secs(self.acceptance_window)
+    }
+
+    /// Return true if a credential created at `created_at` by an issuer having the clock
+    /// offset `issuer_offset` can be accepted at the local time `now`
+    pub fn is_created(
+        &self,
+        created_at: TimestampInSeconds,
+        issuer_offset: Option<i64>,
+        now: TimestampInSeconds,
+    ) -> bool {
+        let created_at = Self::correct(created_at, issuer_offset.unwrap_or_default());
+        let now = Self::correct(now, self.clock_offset);
+        created_at <= now.saturating_add(self.acceptance_window as i128)
+    }
+
+    /// Return true if a credential expiring at `expires_at` according to an issuer having the
+    /// clock offset `issuer_offset` is expired at the local time `now`
+    pub fn is_expired(
+        &self,
+        expires_at: TimestampInSeconds,
+        issuer_offset: Option<i64>,
+        now: TimestampInSeconds,
+    ) -> bool {
+        let expires_at = Self::correct(expires_at, issuer_offset.unwrap_or_default());
+        let now = Self::correct(now, self.clock_offset);
+        expires_at < now
+    }
+
+    /// Return a timestamp expressed with the reference clock
+    fn correct(timestamp: TimestampInSeconds, clock_offset: i64) -> i128 {
+        timestamp.0 as i128 - clock_offset as i128
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acceptance_window() {
+        let now = TimestampInSeconds(1000);
+        let clock = CredentialsClock::default();
+        assert!(clock.is_created(TimestampInSeconds(1005), None, now));
+        assert!(!clock.is_created(TimestampInSeconds(1030), None, now));
+
+        // the issuer declares that its clock is 30 seconds ahead
+        assert!(clock.is_created(TimestampInSeconds(1030), Some(30), now));
+        assert!(!clock.is_expired(TimestampInSeconds(1020), Some(30), TimestampInSeconds(990)));
+        assert!(clock.is_expired(TimestampInSeconds(1020), Some(30), now));
+
+        // the verifier declares that its clock is 30 seconds late
+        let clock = clock.with_clock_offset(-30);
+        assert!(clock.is_created(TimestampInSeconds(1030), None, now));
+
+        let clock = CredentialsClock::default().with_acceptance_window(Duration::from_secs(60));
+        assert!(clock.is_created(TimestampInSeconds(1060), None, now));
+        assert!(!clock.is_created(TimestampInSeconds(1061), None, now));
+    }
+}
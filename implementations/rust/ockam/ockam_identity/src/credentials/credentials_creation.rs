@@ -2,10 +2,12 @@ use crate::models::{
     Attributes, Credential, CredentialAndPurposeKey, CredentialData, Identifier, VersionedData,
 };
 use crate::utils::{add_seconds, now};
-use crate::{IdentitiesRepository, Identity, PurposeKeyCreation};
+use crate::{CredentialsClock, IdentitiesRepository, Identity, PurposeKeyCreation};
 
 use core::time::Duration;
+use ockam_core::compat::string::String;
 use ockam_core::compat::sync::Arc;
+use ockam_core::compat::vec::Vec;
 use ockam_core::Result;
 use ockam_vault::{VaultForSigning, VaultForVerifyingSignatures};
 
@@ -15,6 +17,7 @@ pub struct CredentialsCreation {
     credential_vault: Arc<dyn VaultForSigning>,
     verifying_vault: Arc<dyn VaultForVerifyingSignatures>,
     identities_repository: Arc<dyn IdentitiesRepository>,
+    clock: CredentialsClock,
 }
 
 impl CredentialsCreation {
@@ -30,9 +33,16 @@ impl CredentialsCreation {
             verifying_vault,
             credential_vault,
             identities_repository,
+            clock: CredentialsClock::default(),
         }
     }
 
+    /// Set the clock settings used to issue credentials
+    pub fn with_clock(mut self, clock: CredentialsClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// [`IdentitiesRepository`]
     pub fn identities_repository(&self) -> Arc<dyn IdentitiesRepository> {
         self.identities_repository.clone()
@@ -47,6 +57,20 @@ impl CredentialsCreation {
         subject: &Identifier,
         subject_attributes: Attributes,
         ttl: Duration,
+    ) -> Result<CredentialAndPurposeKey> {
+        self.issue_credential_with_audience(issuer, subject, subject_attributes, ttl, None)
+            .await
+    }
+
+    /// Issue a [`Credential`] which can only be presented for the resources of the given audience.
+    /// A `None` audience makes the credential valid for any resource
+    pub async fn issue_credential_with_audience(
+        &self,
+        issuer: &Identifier,
+        subject: &Identifier,
+        subject_attributes: Attributes,
+        ttl: Duration,
+        audience: Option<Vec<String>>,
     ) -> Result<CredentialAndPurposeKey> {
         // TODO: Allow manual PurposeKey management
         let issuer_purpose_key = self
@@ -71,6 +95,8 @@ impl CredentialsCreation {
             subject_attributes,
             created_at,
             expires_at,
+            issuer_clock_offset: self.clock.clock_offset_hint(),
+            audience,
         };
         let credential_data = minicbor::to_vec(credential_data)?;
 
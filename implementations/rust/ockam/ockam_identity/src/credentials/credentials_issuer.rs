@@ -80,11 +80,12 @@ impl CredentialsIssuer {
         let credential = self
             .credentials
             .credentials_creation()
-            .issue_credential(
+            .issue_credential_with_audience(
                 &self.issuer,
                 subject,
                 subject_attributes,
                 MAX_CREDENTIAL_VALIDITY,
+                entry.audience().map(|a| a.to_vec()),
             )
             .await?;
 
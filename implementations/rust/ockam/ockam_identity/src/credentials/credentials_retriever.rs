@@ -4,7 +4,7 @@ use tracing::debug;
 use tracing::trace;
 
 use ockam_core::compat::boxed::Box;
-use ockam_core::compat::sync::Arc;
+use ockam_core::compat::sync::{Arc, RwLock};
 use ockam_core::{async_trait, Address, Result, Route};
 use ockam_node::{Context, DEFAULT_TIMEOUT};
 
@@ -20,6 +20,9 @@ pub trait CredentialsRetriever: Send + Sync + 'static {
         ctx: &Context,
         for_identity: &Identifier,
     ) -> Result<CredentialAndPurposeKey>;
+
+    /// Notify the retriever that the credentials issuer identity has been rotated
+    fn issuer_changed(&self, _issuer: &Identifier) {}
 }
 
 /// Credentials retriever that retrieves a credential from memory
@@ -51,7 +54,7 @@ impl CredentialsRetriever for CredentialsMemoryRetriever {
 /// Credentials retriever for credentials located on a different node
 pub struct RemoteCredentialsRetriever {
     secure_channels: Arc<SecureChannels>,
-    issuer: RemoteCredentialsRetrieverInfo,
+    issuer: RwLock<RemoteCredentialsRetrieverInfo>,
 }
 
 impl RemoteCredentialsRetriever {
@@ -62,18 +65,21 @@ impl RemoteCredentialsRetriever {
     ) -> Self {
         Self {
             secure_channels,
-            issuer,
+            issuer: RwLock::new(issuer),
         }
     }
 
+    fn issuer(&self) -> RemoteCredentialsRetrieverInfo {
+        self.issuer.read().unwrap().clone()
+    }
+
     async fn make_secure_client(
         &self,
         ctx: &Context,
         for_identity: &Identifier,
     ) -> Result<SecureClient> {
-        let resolved_route = ctx
-            .resolve_transport_route(self.issuer.route.clone())
-            .await?;
+        let issuer = self.issuer();
+        let resolved_route = ctx.resolve_transport_route(issuer.route.clone()).await?;
         trace!(
             "Getting credential from resolved route: {}",
             resolved_route.clone()
@@ -82,7 +88,7 @@ impl RemoteCredentialsRetriever {
         Ok(SecureClient::new(
             self.secure_channels.clone(),
             resolved_route,
-            &self.issuer.identifier,
+            &issuer.identifier,
             for_identity,
             DEFAULT_TIMEOUT,
         ))
@@ -96,7 +102,7 @@ impl CredentialsRetriever for RemoteCredentialsRetriever {
         ctx: &Context,
         for_identity: &Identifier,
     ) -> Result<CredentialAndPurposeKey> {
-        debug!("Getting credential from: {}", &self.issuer.route);
+        debug!("Getting credential from: {}", &self.issuer().route);
         let client = self.make_secure_client(ctx, for_identity).await?;
         let credential = client
             .ask(ctx, "credential_issuer", Request::post("/"))
@@ -104,6 +110,10 @@ impl CredentialsRetriever for RemoteCredentialsRetriever {
             .success()?;
         Ok(credential)
     }
+
+    fn issuer_changed(&self, issuer: &Identifier) {
+        self.issuer.write().unwrap().identifier = issuer.clone();
+    }
 }
 
 /// Information necessary to connect to a remote credential retriever
@@ -10,7 +10,7 @@ use ockam_node::{Context, WorkerBuilder};
 use crate::credentials::credentials_server_worker::CredentialsServerWorker;
 use crate::credentials::Credentials;
 use crate::models::{CredentialAndPurposeKey, Identifier};
-use crate::{IdentitySecureChannelLocalInfo, TrustContext};
+use crate::{AuthorityTransition, IdentitySecureChannelLocalInfo, TrustContext};
 
 /// This trait allows an identity to send its credential to another identity
 /// located at the end of a secure channel route
@@ -35,6 +35,15 @@ pub trait CredentialsServer: Send + Sync {
         credential: CredentialAndPurposeKey,
     ) -> Result<()>;
 
+    /// Push an authority transition to a member of the trust context, route shall use a
+    /// secure channel established with the identity of the current authority
+    async fn push_authority_transition(
+        &self,
+        ctx: &Context,
+        route: Route,
+        transition: &AuthorityTransition,
+    ) -> Result<()>;
+
     /// Start this service as a worker
     async fn start(
         &self,
@@ -94,6 +103,24 @@ impl CredentialsServer for CredentialsServerModule {
             .success()
     }
 
+    /// Push an authority transition to a member of the trust context
+    async fn push_authority_transition(
+        &self,
+        ctx: &Context,
+        route: Route,
+        transition: &AuthorityTransition,
+    ) -> Result<()> {
+        let client = Client::new(&route, None);
+        client
+            .tell(
+                ctx,
+                Request::post("actions/accept_authority_transition")
+                    .body(transition.statement().clone()),
+            )
+            .await?
+            .success()
+    }
+
     /// Start worker that will be available to receive others attributes and put them into storage,
     /// after successful verification
     async fn start(
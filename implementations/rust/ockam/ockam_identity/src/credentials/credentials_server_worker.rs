@@ -10,7 +10,7 @@ use ockam_node::Context;
 
 use crate::credentials::Credentials;
 use crate::models::{CredentialAndPurposeKey, Identifier};
-use crate::{IdentitySecureChannelLocalInfo, TrustContext};
+use crate::{AuthorityTransition, IdentitySecureChannelLocalInfo, TrustContext};
 
 const TARGET: &str = "ockam::credential_exchange_worker::service";
 
@@ -74,11 +74,10 @@ impl CredentialsServerWorker {
                 let credential_and_purpose_key: CredentialAndPurposeKey = dec.decode()?;
 
                 let res = self
-                    .credentials
-                    .credentials_verification()
+                    .trust_context
                     .receive_presented_credential(
+                        &self.credentials.credentials_verification(),
                         &sender,
-                        self.trust_context.authorities().await?.as_slice(),
                         &credential_and_purpose_key,
                     )
                     .await;
@@ -106,11 +105,10 @@ impl CredentialsServerWorker {
 
                 // FIXME info!("presented credential {}", credential);
                 let res = self
-                    .credentials
-                    .credentials_verification()
+                    .trust_context
                     .receive_presented_credential(
+                        &self.credentials.credentials_verification(),
                         &sender,
-                        self.trust_context.authorities().await?.as_slice(),
                         &credential_and_purpose_key,
                     )
                     .await;
@@ -143,6 +141,37 @@ impl CredentialsServerWorker {
                     }
                 }
             }
+            (Post, ["actions", "accept_authority_transition"]) => {
+                debug!("Received an authority transition from {}", sender);
+                let statement: CredentialAndPurposeKey = dec.decode()?;
+
+                // only the current authority can push a transition to its successor
+                if sender != self.trust_context.authority()?.current_identifier() {
+                    warn!(
+                        "Rejected an authority transition pushed by {}, which is not the current authority",
+                        sender
+                    );
+                    return Ok(Response::forbidden(req, "unauthorized authority").to_vec()?);
+                }
+
+                let res = self
+                    .trust_context
+                    .accept_authority_transition(&AuthorityTransition::new(statement))
+                    .await;
+                match res {
+                    Ok(transition) => {
+                        info!(
+                            "The authority {} has been replaced by {}",
+                            transition.previous, transition.next
+                        );
+                        Response::ok(req).to_vec()?
+                    }
+                    Err(err) => {
+                        debug!("Authority transition processing error: {}", err);
+                        Response::bad_request(req, &err.to_string()).to_vec()?
+                    }
+                }
+            }
 
             // ==*== Catch-all for Unimplemented APIs ==*==
             _ => {
@@ -1,9 +1,11 @@
 use crate::identities::AttributesEntry;
-use crate::models::{CredentialAndPurposeKey, CredentialData, Identifier, PurposePublicKey};
+use crate::models::{
+    Attributes, CredentialAndPurposeKey, CredentialData, Identifier, PurposePublicKey,
+};
 use crate::utils::now;
 use crate::{
-    CredentialAndPurposeKeyData, IdentitiesRepository, IdentityError, PurposeKeyVerification,
-    TimestampInSeconds,
+    CredentialAndPurposeKeyData, CredentialsClock, FederatedAuthority, IdentitiesRepository,
+    Identity, IdentityError, PurposeKeyVerification,
 };
 
 use ockam_core::compat::collections::BTreeMap;
@@ -12,15 +14,12 @@ use ockam_core::compat::vec::Vec;
 use ockam_core::Result;
 use ockam_vault::VaultForVerifyingSignatures;
 
-/// We allow Credentials to be created in the future related to this machine's time due to
-/// possible time dyssynchronization
-const MAX_ALLOWED_TIME_DRIFT: TimestampInSeconds = TimestampInSeconds(5);
-
 /// Service for managing [`Credential`]s
 pub struct CredentialsVerification {
     purpose_keys_verification: Arc<PurposeKeyVerification>,
     verifying_vault: Arc<dyn VaultForVerifyingSignatures>,
     identities_repository: Arc<dyn IdentitiesRepository>,
+    clock: CredentialsClock,
 }
 
 impl CredentialsVerification {
@@ -34,9 +33,16 @@ impl CredentialsVerification {
             purpose_keys_verification,
             verifying_vault,
             identities_repository,
+            clock: CredentialsClock::default(),
         }
     }
 
+    /// Set the clock settings used to verify credentials
+    pub fn with_clock(mut self, clock: CredentialsClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// [`IdentitiesRepository`]
     pub fn identities_repository(&self) -> Arc<dyn IdentitiesRepository> {
         self.identities_repository.clone()
@@ -126,18 +132,34 @@ impl CredentialsVerification {
             return Err(IdentityError::CredentialVerificationFailed.into());
         }
 
+        // A child identity can only issue, or be given, the attributes allowed by its parent
+        self.check_delegated_attributes(
+            &purpose_key_data.subject,
+            &credential_data.subject_attributes,
+        )
+        .await?;
+        if let Some(subject) = &credential_data.subject {
+            self.check_delegated_attributes(subject, &credential_data.subject_attributes)
+                .await?;
+        }
+
         let now = now()?;
 
-        if credential_data.created_at > now
-            && credential_data.created_at - now > MAX_ALLOWED_TIME_DRIFT
+        let issuer_clock_offset = credential_data.issuer_clock_offset;
+        if !self
+            .clock
+            .is_created(credential_data.created_at, issuer_clock_offset, now)
         {
             // Credential can't be created in the future
-            return Err(IdentityError::CredentialVerificationFailed.into());
+            return Err(IdentityError::CredentialNotYetValid.into());
         }
 
-        if credential_data.expires_at < now {
+        if self
+            .clock
+            .is_expired(credential_data.expires_at, issuer_clock_offset, now)
+        {
             // Credential expired
-            return Err(IdentityError::CredentialVerificationFailed.into());
+            return Err(IdentityError::CredentialExpired.into());
         }
 
         if let Some(_subject_latest_change_hash) = &credential_data.subject_latest_change_hash {
@@ -163,6 +185,40 @@ impl CredentialsVerification {
         })
     }
 
+    /// Return an error if the identity is a child identity which is not allowed to have
+    /// some of the attributes. Identities which are not stored are not checked: the subject of
+    /// a presented credential is stored when the secure channel is established
+    async fn check_delegated_attributes(
+        &self,
+        identifier: &Identifier,
+        attributes: &Attributes,
+    ) -> Result<()> {
+        let change_history = match self
+            .identities_repository
+            .retrieve_identity(identifier)
+            .await?
+        {
+            Some(change_history) => change_history,
+            None => return Ok(()),
+        };
+        let identity = Identity::import_from_change_history(
+            Some(identifier),
+            change_history,
+            self.verifying_vault.clone(),
+        )
+        .await?;
+        if let Some(delegation) = identity.delegation()? {
+            if !attributes
+                .map
+                .keys()
+                .all(|name| delegation.allows_attribute(name))
+            {
+                return Err(IdentityError::OutsideOfDelegation.into());
+            }
+        }
+        Ok(())
+    }
+
     /// Receive someone's [`Credential`]: verify and put attributes from it to the storage
     pub async fn receive_presented_credential(
         &self,
@@ -170,18 +226,57 @@ impl CredentialsVerification {
         authorities: &[Identifier],
         credential_and_purpose_key_attestation: &CredentialAndPurposeKey,
     ) -> Result<()> {
+        self.receive_presented_federated_credential(
+            subject,
+            authorities,
+            &[],
+            credential_and_purpose_key_attestation,
+        )
+        .await
+    }
+
+    /// Receive someone's [`Credential`], issued either by one of our authorities or by a
+    /// federated authority: verify and put attributes from it to the storage.
+    /// The names of the attributes attested by a federated authority are mapped with the
+    /// rules of that authority
+    pub async fn receive_presented_federated_credential(
+        &self,
+        subject: &Identifier,
+        authorities: &[Identifier],
+        federated_authorities: &[FederatedAuthority],
+        credential_and_purpose_key_attestation: &CredentialAndPurposeKey,
+    ) -> Result<()> {
+        let mut accepted_authorities = authorities.to_vec();
+        accepted_authorities.extend(federated_authorities.iter().map(|a| a.identifier().clone()));
+
         let credential_data = self
             .verify_credential(
                 Some(subject),
-                authorities,
+                accepted_authorities.as_slice(),
                 credential_and_purpose_key_attestation,
             )
             .await?;
 
+        let issuer = &credential_data.purpose_key_data.subject;
+        let federated_authority = if authorities.contains(issuer) {
+            None
+        } else {
+            federated_authorities
+                .iter()
+                .find(|a| a.identifier() == issuer)
+        };
+
         let map = credential_data.credential_data.subject_attributes.map;
         let map: BTreeMap<_, _> = map
             .into_iter()
-            .map(|(k, v)| (Vec::<u8>::from(k), Vec::<u8>::from(v)))
+            .map(|(k, v)| {
+                let k = Vec::<u8>::from(k);
+                let k = match federated_authority {
+                    Some(authority) => authority.map_attribute_name(&k),
+                    None => k,
+                };
+                (k, Vec::<u8>::from(v))
+            })
             .collect();
 
         self.identities_repository
@@ -192,7 +287,8 @@ impl CredentialsVerification {
                     now()?,
                     Some(credential_data.credential_data.expires_at),
                     Some(credential_data.purpose_key_data.subject),
-                ),
+                )
+                .with_audience(credential_data.credential_data.audience),
             )
             .await?;
 
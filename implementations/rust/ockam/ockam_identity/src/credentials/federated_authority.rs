@@ -0,0 +1,62 @@
+use crate::models::Identifier;
+
+use ockam_core::compat::string::String;
+use ockam_core::compat::vec::Vec;
+
+/// A FederatedAuthority is the authority of another trust context (for example the project
+/// of a partner organization) whose credentials are accepted in the current trust context.
+///
+/// The attributes attested by a federated authority are not trusted as if they were attested
+/// by our own authority. Instead their names are prefixed, so that a foreign `role` attribute
+/// becomes `partner.role` for example, and policies have to explicitly refer to them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FederatedAuthority {
+    identifier: Identifier,
+    attributes_prefix: String,
+}
+
+impl FederatedAuthority {
+    /// Create a new federated authority.
+    /// The prefix is prepended to each attribute name, followed by a `.`
+    pub fn new(identifier: Identifier, attributes_prefix: impl Into<String>) -> Self {
+        Self {
+            identifier,
+            attributes_prefix: attributes_prefix.into(),
+        }
+    }
+
+    /// Identifier of the federated authority
+    pub fn identifier(&self) -> &Identifier {
+        &self.identifier
+    }
+
+    /// Prefix used for the attributes attested by the federated authority
+    pub fn attributes_prefix(&self) -> &str {
+        &self.attributes_prefix
+    }
+
+    /// Return the name that an attribute attested by this authority has in our trust context
+    pub fn map_attribute_name(&self, name: &[u8]) -> Vec<u8> {
+        let mut mapped = Vec::with_capacity(self.attributes_prefix.len() + 1 + name.len());
+        mapped.extend_from_slice(self.attributes_prefix.as_bytes());
+        mapped.push(b'.');
+        mapped.extend_from_slice(name);
+        mapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    #[test]
+    fn test_map_attribute_name() {
+        let identifier = Identifier::from_str("I0000000000000000000000000000000000000000").unwrap();
+        let authority = FederatedAuthority::new(identifier, "partner");
+        assert_eq!(
+            authority.map_attribute_name(b"role"),
+            b"partner.role".to_vec()
+        );
+    }
+}
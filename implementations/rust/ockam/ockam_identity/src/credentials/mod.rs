@@ -1,21 +1,27 @@
 mod authority_service;
+mod authority_transition;
 #[allow(clippy::module_inception)]
 mod credentials;
+mod credentials_clock;
 mod credentials_creation;
 mod credentials_issuer;
 mod credentials_retriever;
 mod credentials_server;
 mod credentials_server_worker;
 mod credentials_verification;
+mod federated_authority;
 mod one_time_code;
 mod trust_context;
 
 pub use authority_service::*;
+pub use authority_transition::*;
 pub use credentials::*;
+pub use credentials_clock::*;
 pub use credentials_creation::*;
 pub use credentials_issuer::*;
 pub use credentials_retriever::*;
 pub use credentials_server::*;
 pub use credentials_verification::*;
+pub use federated_authority::*;
 pub use one_time_code::*;
 pub use trust_context::*;
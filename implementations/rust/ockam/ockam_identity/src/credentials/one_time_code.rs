@@ -1,9 +1,9 @@
 use core::str::FromStr;
 use minicbor::bytes::ByteArray;
 use minicbor::{Decode, Encode};
-use ockam_core::compat::rand;
 use ockam_core::compat::rand::RngCore;
 use ockam_core::compat::string::{String, ToString};
+use ockam_core::entropy;
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::Error;
 use ockam_core::Result;
@@ -24,7 +24,7 @@ impl OneTimeCode {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         let mut code = [0; 32];
-        rand::thread_rng().fill_bytes(&mut code);
+        entropy::rng().fill_bytes(&mut code);
         OneTimeCode::from(code)
     }
 
@@ -5,7 +5,10 @@ use ockam_node::Context;
 use tracing::{debug, error};
 
 use crate::models::{CredentialAndPurposeKey, Identifier};
-use crate::{AuthorityService, IdentityError};
+use crate::{
+    AuthorityService, AuthorityTransition, AuthorityTransitionData, CredentialsVerification,
+    FederatedAuthority, IdentityError,
+};
 
 /// A trust context defines which authorities are trusted to attest to which attributes, within a context.
 /// Our first implementation assumes that there is only one authority and it is trusted to attest to all attributes within this context.
@@ -15,12 +18,51 @@ pub struct TrustContext {
     id: String,
     /// Authority capable of retrieving credentials
     authority: Option<AuthorityService>,
+    /// Authorities of other trust contexts whose credentials are accepted in this trust context
+    federated_authorities: Vec<FederatedAuthority>,
 }
 
 impl TrustContext {
     /// Create a new Trust Context
     pub fn new(id: String, authority: Option<AuthorityService>) -> Self {
-        Self { id, authority }
+        Self {
+            id,
+            authority,
+            federated_authorities: vec![],
+        }
+    }
+
+    /// Accept the credentials issued by the authority of another trust context
+    pub fn with_federated_authority(mut self, federated_authority: FederatedAuthority) -> Self {
+        self.federated_authorities.push(federated_authority);
+        self
+    }
+
+    /// Return the federated authorities of this trust context
+    pub fn federated_authorities(&self) -> &[FederatedAuthority] {
+        &self.federated_authorities
+    }
+
+    /// Verify a credential presented by `subject`, issued either by our authority or
+    /// by a federated authority, and store its attributes
+    pub async fn receive_presented_credential(
+        &self,
+        credentials_verification: &CredentialsVerification,
+        subject: &Identifier,
+        credential: &CredentialAndPurposeKey,
+    ) -> Result<()> {
+        let authorities = match self.authority.as_ref() {
+            Some(authority) => authority.accepted_identifiers()?,
+            None => vec![],
+        };
+        credentials_verification
+            .receive_presented_federated_credential(
+                subject,
+                authorities.as_slice(),
+                self.federated_authorities.as_slice(),
+                credential,
+            )
+            .await
     }
 
     /// Return the ID of the Trust Context
@@ -36,8 +78,19 @@ impl TrustContext {
     }
 
     /// Return the authority identities attached to this trust context
+    /// If the authority has been rotated, the previous authority is still part of that list
+    /// until the end of the transition grace window
     pub async fn authorities(&self) -> Result<Vec<Identifier>> {
-        Ok(vec![self.authority()?.identifier().clone()])
+        self.authority()?.accepted_identifiers()
+    }
+
+    /// Rotate the authority of this trust context, after having checked that the transition
+    /// statement is signed by the current authority
+    pub async fn accept_authority_transition(
+        &self,
+        transition: &AuthorityTransition,
+    ) -> Result<AuthorityTransitionData> {
+        self.authority()?.accept_transition(transition).await
     }
 
     /// Return the credential for a given identity if an Authority has been defined
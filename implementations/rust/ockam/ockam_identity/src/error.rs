@@ -1,9 +1,12 @@
 use ockam_core::{
-    errcode::{Kind, Origin},
+    errcode::{CatalogueEntry, Kind, Origin},
     Error,
 };
 
 /// Identity crate error
+///
+/// Each variant has the `OCK1xxx` code of its position in the enum, so new variants must be
+/// added at the end
 #[derive(Clone, Debug)]
 pub enum IdentityError {
     /// Invalid key type
@@ -34,6 +37,10 @@ pub enum IdentityError {
     SecureChannelVerificationFailedMissingTrustContext,
     /// SecureChannelTrustCheckFailed
     SecureChannelTrustCheckFailed,
+    /// The other party was rejected by the admission filter of the secure channel listener
+    SecureChannelAdmissionRejected,
+    /// An identity reached its quota of resources on this node
+    QuotaExceeded,
     /// Invalid Nonce value
     InvalidNonce,
     /// Nonce overflow
@@ -50,6 +57,46 @@ pub enum IdentityError {
     InvalidHex,
     /// Secret Key doesn't correspond to the Identity
     WrongSecretKey,
+    /// An authority transition statement is invalid or doesn't apply to the current authority
+    InvalidAuthorityTransition,
+    /// A value read from the storage doesn't match its integrity tag
+    IntegrityCheckFailed,
+    /// A credential was created after the local time, beyond the accepted clock difference
+    CredentialNotYetValid,
+    /// A credential is expired
+    CredentialExpired,
+    /// The hardware attestation of a Purpose Key was rejected
+    HardwareAttestationVerificationFailed,
+    /// The metadata attached to a secure channel message is too large or can't be decoded
+    InvalidMessageMetadata,
+    /// The delegation of a child identity is not signed by its parent or doesn't apply to it
+    DelegationVerificationFailed,
+    /// A child identity used a purpose or an attribute which is not allowed by its delegation
+    OutsideOfDelegation,
+    /// A secure channel message compressed with a dictionary can't be decompressed
+    InvalidCompressedMessage,
+}
+
+impl IdentityError {
+    /// Return the entry of this error in the errors catalogue
+    pub fn catalogue_entry(&self) -> &'static CatalogueEntry {
+        CatalogueEntry::find(self.catalogue_number())
+            .unwrap_or_else(|| CatalogueEntry::for_kind(Kind::Unknown))
+    }
+
+    /// Return the stable code of this error, for example `OCK1028`
+    pub fn code(&self) -> &'static str {
+        self.catalogue_entry().code
+    }
+
+    /// Return a suggested remediation for this error
+    pub fn help(&self) -> &'static str {
+        self.catalogue_entry().help
+    }
+
+    fn catalogue_number(&self) -> i32 {
+        1000 + self.clone() as i32
+    }
 }
 
 impl ockam_core::compat::error::Error for IdentityError {}
@@ -64,6 +111,22 @@ impl From<IdentityError> for Error {
     fn from(err: IdentityError) -> Self {
         let kind = Kind::Unknown; // FIXME: fill these in with more
                                   // meaningful error kinds
-        Error::new(Origin::Identity, kind, err)
+        let number = err.catalogue_number();
+        Error::new(Origin::Identity, kind, err).with_catalogue_number(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogue_code() {
+        assert_eq!(IdentityError::InvalidKeyType.code(), "OCK1001");
+        assert_eq!(IdentityError::CredentialExpired.code(), "OCK1028");
+
+        let error: Error = IdentityError::CredentialExpired.into();
+        assert_eq!(error.catalogue_code(), "OCK1028");
+        assert_eq!(error.help(), IdentityError::CredentialExpired.help());
     }
 }
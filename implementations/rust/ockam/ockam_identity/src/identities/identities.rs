@@ -1,8 +1,9 @@
 use crate::identities::{IdentitiesKeys, IdentitiesRepository};
 use crate::purpose_keys::storage::{PurposeKeysRepository, PurposeKeysStorage};
 use crate::{
-    Credentials, CredentialsServer, CredentialsServerModule, Identifier, IdentitiesBuilder,
-    IdentitiesCreation, IdentitiesReader, IdentitiesStorage, Identity, PurposeKeys, Vault,
+    Credentials, CredentialsClock, CredentialsServer, CredentialsServerModule,
+    HardwareAttestationVerifier, Identifier, IdentitiesBuilder, IdentitiesCreation,
+    IdentitiesReader, IdentitiesStorage, Identity, PurposeKeys, Vault,
 };
 
 use ockam_core::compat::sync::Arc;
@@ -15,6 +16,8 @@ pub struct Identities {
     vault: Vault,
     identities_repository: Arc<dyn IdentitiesRepository>,
     purpose_keys_repository: Arc<dyn PurposeKeysRepository>,
+    credentials_clock: CredentialsClock,
+    hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
 }
 
 impl Identities {
@@ -33,6 +36,16 @@ impl Identities {
         self.purpose_keys_repository.clone()
     }
 
+    /// Return the clock settings used to issue and verify credentials
+    pub fn credentials_clock(&self) -> CredentialsClock {
+        self.credentials_clock
+    }
+
+    /// Return the verifier of the hardware attestations of the Purpose Keys, if there is one
+    pub fn hardware_attestation_verifier(&self) -> Option<Arc<dyn HardwareAttestationVerifier>> {
+        self.hardware_attestation_verifier.clone()
+    }
+
     /// Get an [`Identity`] from the repository
     pub async fn get_identity(&self, identifier: &Identifier) -> Result<Identity> {
         let change_history = self.identities_repository.get_identity(identifier).await?;
@@ -51,12 +64,15 @@ impl Identities {
 
     /// Return the [`PurposeKeys`] instance
     pub fn purpose_keys(&self) -> Arc<PurposeKeys> {
-        Arc::new(PurposeKeys::new(
-            self.vault.clone(),
-            self.identities_repository.as_identities_reader(),
-            self.identities_keys(),
-            self.purpose_keys_repository.clone(),
-        ))
+        Arc::new(
+            PurposeKeys::new(
+                self.vault.clone(),
+                self.identities_repository.as_identities_reader(),
+                self.identities_keys(),
+                self.purpose_keys_repository.clone(),
+            )
+            .with_hardware_attestation_verifier(self.hardware_attestation_verifier.clone()),
+        )
     }
 
     /// Return the identities keys management service
@@ -83,12 +99,15 @@ impl Identities {
 
     /// Return the identities credentials service
     pub fn credentials(&self) -> Arc<Credentials> {
-        Arc::new(Credentials::new(
-            self.vault.credential_vault.clone(),
-            self.vault.verifying_vault.clone(),
-            self.purpose_keys(),
-            self.identities_repository.clone(),
-        ))
+        Arc::new(
+            Credentials::new(
+                self.vault.credential_vault.clone(),
+                self.vault.verifying_vault.clone(),
+                self.purpose_keys(),
+                self.identities_repository.clone(),
+            )
+            .with_clock(self.credentials_clock),
+        )
     }
 
     /// Return the identities credentials server
@@ -103,11 +122,15 @@ impl Identities {
         vault: Vault,
         identities_repository: Arc<dyn IdentitiesRepository>,
         purpose_keys_repository: Arc<dyn PurposeKeysRepository>,
+        credentials_clock: CredentialsClock,
+        hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
     ) -> Identities {
         Identities {
             vault,
             identities_repository,
             purpose_keys_repository,
+            credentials_clock,
+            hardware_attestation_verifier,
         }
     }
 
@@ -117,6 +140,9 @@ impl Identities {
             vault: Vault::create(),
             repository: IdentitiesStorage::create(),
             purpose_keys_repository: PurposeKeysStorage::create(),
+            credentials_clock: CredentialsClock::default(),
+            hardware_attestation_verifier: None,
+            verification_cache: None,
         }
     }
 }
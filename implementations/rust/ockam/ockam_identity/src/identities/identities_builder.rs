@@ -1,7 +1,7 @@
-use crate::identities::{Identities, IdentitiesRepository, IdentitiesStorage};
+use crate::identities::{Identities, IdentitiesRepository, IdentitiesStorage, VerificationCache};
 use crate::purpose_keys::storage::{PurposeKeysRepository, PurposeKeysStorage};
 use crate::storage::Storage;
-use crate::{Vault, VaultStorage};
+use crate::{CredentialsClock, HardwareAttestationVerifier, Vault, VaultStorage};
 
 use ockam_core::compat::sync::Arc;
 
@@ -11,6 +11,9 @@ pub struct IdentitiesBuilder {
     pub(crate) vault: Vault,
     pub(crate) repository: Arc<dyn IdentitiesRepository>,
     pub(crate) purpose_keys_repository: Arc<dyn PurposeKeysRepository>,
+    pub(crate) credentials_clock: CredentialsClock,
+    pub(crate) hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
+    pub(crate) verification_cache: Option<Arc<dyn Storage>>,
 }
 
 /// Return a default identities
@@ -56,12 +59,42 @@ impl IdentitiesBuilder {
         self
     }
 
+    /// Set the clock settings used to issue and verify credentials
+    pub fn with_credentials_clock(mut self, credentials_clock: CredentialsClock) -> Self {
+        self.credentials_clock = credentials_clock;
+        self
+    }
+
+    /// Verify the hardware attestations of the Purpose Keys of the other identities
+    pub fn with_hardware_attestation_verifier(
+        mut self,
+        verifier: Arc<dyn HardwareAttestationVerifier>,
+    ) -> Self {
+        self.hardware_attestation_verifier = Some(verifier);
+        self
+    }
+
+    /// Remember the verified signatures in a storage, which can be shared by several nodes.
+    /// The storage must protect the integrity of the stored signatures, like an
+    /// [`crate::storage::IntegrityStorage`]
+    pub fn with_verification_cache(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.verification_cache = Some(storage);
+        self
+    }
+
     /// Build identities
     pub fn build(self) -> Arc<Identities> {
+        let mut vault = self.vault;
+        if let Some(storage) = self.verification_cache {
+            vault.verifying_vault =
+                Arc::new(VerificationCache::new(vault.verifying_vault, storage));
+        }
         Arc::new(Identities::new(
-            self.vault,
+            vault,
             self.repository,
             self.purpose_keys_repository,
+            self.credentials_clock,
+            self.hardware_attestation_verifier,
         ))
     }
 }
@@ -2,7 +2,7 @@ use ockam_core::compat::sync::Arc;
 use ockam_core::Result;
 use ockam_vault::{SigningKeyType, SigningSecretKeyHandle};
 
-use crate::models::TimestampInSeconds;
+use crate::models::{Delegation, TimestampInSeconds};
 use crate::utils::now;
 use crate::IdentitiesCreation;
 use crate::{Identity, IdentityOptions};
@@ -30,6 +30,7 @@ pub struct IdentityBuilder {
     revoke_all_purpose_keys: bool,
     key: Key,
     ttl: Ttl,
+    delegation: Option<Delegation>,
 }
 
 impl IdentityBuilder {
@@ -40,6 +41,7 @@ impl IdentityBuilder {
             revoke_all_purpose_keys: false,
             key: Key::Generate(SigningKeyType::EdDSACurve25519),
             ttl: Ttl::CreatedNowWithTtl(DEFAULT_IDENTITY_TTL),
+            delegation: None,
         }
     }
 
@@ -80,6 +82,13 @@ impl IdentityBuilder {
         self
     }
 
+    /// Limit the Identity with a [`Delegation`] signed by a parent Identity.
+    /// The delegation must be issued for the key of the Identity, see [`Self::with_existing_key`]
+    pub fn with_delegation(mut self, delegation: Delegation) -> Self {
+        self.delegation = Some(delegation);
+        self
+    }
+
     /// Create the corresponding [`IdentityOptions`] object
     pub async fn build_options(self) -> Result<IdentityOptions> {
         let key = match self.key {
@@ -105,8 +114,11 @@ impl IdentityBuilder {
             } => (created_at, expires_at),
         };
 
-        let options =
+        let mut options =
             IdentityOptions::new(key, self.revoke_all_purpose_keys, created_at, expires_at);
+        if let Some(delegation) = self.delegation {
+            options = options.with_delegation(delegation);
+        }
 
         Ok(options)
     }
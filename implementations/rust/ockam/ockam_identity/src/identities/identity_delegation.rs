@@ -0,0 +1,218 @@
+use core::time::Duration;
+use ockam_core::compat::string::String;
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+use ockam_vault::{SigningKeyType, VerifyingPublicKey};
+
+use crate::models::{Delegation, DelegationData, Identifier, VersionedData};
+use crate::utils::now;
+use crate::{IdentitiesCreation, Identity, IdentityError, Purpose, TimestampInSeconds};
+
+/// Limitation of a child Identity, see [`IdentitiesCreation::create_child_identity`]
+#[derive(Clone, Debug, Default)]
+pub struct DelegationScope {
+    allowed_attributes: Vec<String>,
+    allowed_purposes: Vec<Purpose>,
+}
+
+impl DelegationScope {
+    /// Allow the child to have an attribute in its credentials, or to issue credentials with it
+    pub fn with_attribute(mut self, name: impl Into<String>) -> Self {
+        self.allowed_attributes.push(name.into());
+        self
+    }
+
+    /// Allow the child to use Purpose Keys with that purpose
+    pub fn with_purpose(mut self, purpose: Purpose) -> Self {
+        if !self.allowed_purposes.contains(&purpose) {
+            self.allowed_purposes.push(purpose);
+        }
+        self
+    }
+}
+
+impl IdentitiesCreation {
+    /// Create a [`Delegation`] signed by `parent`, limiting the Identity whose first key is
+    /// `child_public_key`. The child Identity must be created with that key and the delegation,
+    /// and its keys can't expire after the delegation
+    pub async fn create_delegation(
+        &self,
+        parent: &Identifier,
+        child_public_key: VerifyingPublicKey,
+        scope: DelegationScope,
+        ttl: Duration,
+    ) -> Result<Delegation> {
+        let parent_change_history = self.repository.get_identity(parent).await?;
+        let parent_identity = Identity::import_from_change_history(
+            Some(parent),
+            parent_change_history.clone(),
+            self.verifying_vault.clone(),
+        )
+        .await?;
+        if parent_identity.delegation()?.is_some() {
+            // A child identity can't delegate
+            return Err(IdentityError::DelegationVerificationFailed.into());
+        }
+
+        let created_at = now()?;
+        let delegation_data = DelegationData {
+            parent: parent.clone(),
+            child_public_key: child_public_key.into(),
+            allowed_attributes: scope.allowed_attributes,
+            allowed_purposes: scope.allowed_purposes,
+            created_at,
+            expires_at: created_at + TimestampInSeconds(ttl.as_secs()),
+        };
+        let versioned_data = VersionedData {
+            version: 1,
+            data: minicbor::to_vec(&delegation_data)?,
+        };
+        let versioned_data = minicbor::to_vec(&versioned_data)?;
+
+        let hash = self.verifying_vault.sha256(&versioned_data).await?;
+        let secret_key = self
+            .identities_keys()
+            .get_secret_key(&parent_identity)
+            .await?;
+        let signature = self.identity_vault.sign(&secret_key, &hash.0).await?;
+
+        Ok(Delegation {
+            data: versioned_data,
+            signature: signature.into(),
+            parent_change_history,
+        })
+    }
+
+    /// Create and store a child Identity of `parent`, limited to the attributes and purposes
+    /// of the scope until the end of the `ttl`
+    pub async fn create_child_identity(
+        &self,
+        parent: &Identifier,
+        scope: DelegationScope,
+        ttl: Duration,
+    ) -> Result<Identity> {
+        let key = self
+            .identity_vault
+            .generate_signing_secret_key(SigningKeyType::EdDSACurve25519)
+            .await?;
+        let public_key = self.identity_vault.get_verifying_public_key(&key).await?;
+        let delegation = self
+            .create_delegation(parent, public_key, scope, ttl)
+            .await?;
+        let delegation_data = DelegationData::get_data(&delegation.get_versioned_data()?)?;
+
+        self.identity_builder()
+            .with_existing_key(key)
+            .with_timestamps(delegation_data.created_at, delegation_data.expires_at)
+            .with_delegation(delegation)
+            .build()
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CredentialSchemaIdentifier;
+    use crate::utils::AttributesBuilder;
+    use crate::{identities, Identities};
+    use ockam_core::compat::sync::Arc;
+
+    async fn child(identities: &Arc<Identities>) -> Result<(Identifier, Identity)> {
+        let creation = identities.identities_creation();
+        let parent = creation.create_identity().await?.identifier().clone();
+        let scope = DelegationScope::default()
+            .with_attribute("role")
+            .with_purpose(Purpose::SecureChannel);
+        let child = creation
+            .create_child_identity(&parent, scope, Duration::from_secs(3600))
+            .await?;
+        Ok((parent, child))
+    }
+
+    #[tokio::test]
+    async fn test_child_identity_is_verified() -> Result<()> {
+        let identities = identities();
+        let (parent, child) = child(&identities).await?;
+
+        // the child is verified on another machine
+        let imported = Identity::import(
+            Some(child.identifier()),
+            &child.export()?,
+            identities.vault().verifying_vault,
+        )
+        .await?;
+        let delegation = imported.delegation()?.unwrap();
+        assert_eq!(delegation.parent, parent);
+        assert!(delegation.allows_attribute(b"role"));
+        assert!(!delegation.allows_attribute(b"admin"));
+
+        // the delegation can't be reused for another key
+        let other = identities
+            .identities_creation()
+            .identity_builder()
+            .with_ttl(60u64)
+            .with_delegation(child.changes()[0].data().delegation.clone().unwrap())
+            .build()
+            .await;
+        assert!(other.is_err());
+
+        // a child can't delegate
+        let scope = DelegationScope::default().with_purpose(Purpose::SecureChannel);
+        assert!(identities
+            .identities_creation()
+            .create_child_identity(child.identifier(), scope, Duration::from_secs(60))
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_child_identity_limitation() -> Result<()> {
+        let identities = identities();
+        let (parent, child) = child(&identities).await?;
+        let purpose_keys = identities.purpose_keys();
+
+        // the secure channel purpose is allowed, not the credentials one
+        let key = purpose_keys
+            .purpose_keys_creation()
+            .create_secure_channel_purpose_key(child.identifier())
+            .await?;
+        purpose_keys
+            .purpose_keys_verification()
+            .verify_purpose_key_attestation(Some(child.identifier()), key.attestation())
+            .await?;
+        let key = purpose_keys
+            .purpose_keys_creation()
+            .create_credential_purpose_key(child.identifier())
+            .await?;
+        assert!(purpose_keys
+            .purpose_keys_verification()
+            .verify_purpose_key_attestation(Some(child.identifier()), key.attestation())
+            .await
+            .is_err());
+
+        // the credentials of the child can only contain the allowed attributes
+        let credentials = identities.credentials();
+        for (name, allowed) in [("role", true), ("admin", false)] {
+            let attributes = AttributesBuilder::with_schema(CredentialSchemaIdentifier(1))
+                .with_attribute(name, "value")
+                .build();
+            let credential = credentials
+                .credentials_creation()
+                .issue_credential(
+                    &parent,
+                    child.identifier(),
+                    attributes,
+                    Duration::from_secs(60),
+                )
+                .await?;
+            let verified = credentials
+                .credentials_verification()
+                .verify_credential(Some(child.identifier()), &[parent.clone()], &credential)
+                .await;
+            assert_eq!(verified.is_ok(), allowed);
+        }
+        Ok(())
+    }
+}
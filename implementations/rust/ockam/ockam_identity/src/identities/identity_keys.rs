@@ -114,6 +114,7 @@ impl IdentitiesKeys {
             revoke_all_purpose_keys: identity_options.revoke_all_purpose_keys,
             created_at: identity_options.created_at,
             expires_at: identity_options.expires_at,
+            delegation: identity_options.delegation,
         };
 
         let change_data = minicbor::to_vec(&change_data)?;
@@ -1,3 +1,4 @@
+use crate::models::Delegation;
 use crate::TimestampInSeconds;
 use ockam_vault::SigningSecretKeyHandle;
 
@@ -7,6 +8,7 @@ pub struct IdentityOptions {
     pub(super) revoke_all_purpose_keys: bool,
     pub(super) created_at: TimestampInSeconds,
     pub(super) expires_at: TimestampInSeconds,
+    pub(super) delegation: Option<Delegation>,
 }
 
 impl IdentityOptions {
@@ -22,9 +24,16 @@ impl IdentityOptions {
             revoke_all_purpose_keys,
             created_at,
             expires_at,
+            delegation: None,
         }
     }
 
+    /// Limit the new Identity with a [`Delegation`] signed by a parent Identity
+    pub fn with_delegation(mut self, delegation: Delegation) -> Self {
+        self.delegation = Some(delegation);
+        self
+    }
+
     /// New key
     pub fn signing_secret_key_handle(&self) -> &SigningSecretKeyHandle {
         &self.signing_secret_key_handle
@@ -44,4 +53,9 @@ impl IdentityOptions {
     pub fn expires_at(&self) -> TimestampInSeconds {
         self.expires_at
     }
+
+    /// Delegation from a parent Identity
+    pub fn delegation(&self) -> Option<&Delegation> {
+        self.delegation.as_ref()
+    }
 }
@@ -3,8 +3,10 @@ mod identities;
 mod identities_builder;
 mod identities_creation;
 mod identity_builder;
+mod identity_delegation;
 mod identity_keys;
 mod identity_options;
+mod verification_cache;
 
 /// Identities storage functions
 pub mod storage;
@@ -13,6 +15,8 @@ pub use identities::*;
 pub use identities_builder::*;
 pub use identities_creation::*;
 pub use identity_builder::*;
+pub use identity_delegation::*;
 pub use identity_keys::*;
 pub use identity_options::*;
 pub use storage::*;
+pub use verification_cache::*;
@@ -1,7 +1,7 @@
 use crate::models::{Identifier, TimestampInSeconds};
 use minicbor::{Decode, Encode};
 use ockam_core::compat::borrow::ToOwned;
-use ockam_core::compat::{collections::BTreeMap, vec::Vec};
+use ockam_core::compat::{collections::BTreeMap, string::String, vec::Vec};
 use serde::{Deserialize, Serialize};
 
 /// An entry on the AuthenticatedIdentities table.
@@ -14,6 +14,8 @@ pub struct AttributesEntry {
     #[n(2)] added: TimestampInSeconds,
     #[n(3)] expires: Option<TimestampInSeconds>,
     #[n(4)] attested_by: Option<Identifier>,
+    #[serde(default)]
+    #[n(5)] audience: Option<Vec<String>>,
 }
 
 impl AttributesEntry {
@@ -33,9 +35,16 @@ impl AttributesEntry {
             added,
             expires,
             attested_by,
+            audience: None,
         }
     }
 
+    /// Restrict this entry to the resources of the given audience
+    pub fn with_audience(mut self, audience: Option<Vec<String>>) -> Self {
+        self.audience = audience;
+        self
+    }
+
     /// The entry attributes
     pub fn attrs(&self) -> &BTreeMap<Vec<u8>, Vec<u8>> {
         &self.attrs
@@ -55,4 +64,19 @@ impl AttributesEntry {
     pub fn attested_by(&self) -> Option<Identifier> {
         self.attested_by.to_owned()
     }
+
+    /// Resources for which these attributes can be used. `None` if they can be used for any resource
+    pub fn audience(&self) -> Option<&[String]> {
+        self.audience.as_deref()
+    }
+
+    /// Return true if these attributes can be used for the given resource.
+    /// An entry restricted to an audience can not be used when the resource is unknown
+    pub fn is_valid_for(&self, resource: Option<&str>) -> bool {
+        match (&self.audience, resource) {
+            (None, _) => true,
+            (Some(audience), Some(resource)) => audience.iter().any(|r| r == resource),
+            (Some(_), None) => false,
+        }
+    }
 }
@@ -7,7 +7,7 @@ use ockam_core::compat::vec::Vec;
 use ockam_core::Result;
 
 use crate::identity::IdentityConstants;
-use crate::models::{ChangeHistory, Identifier};
+use crate::models::{ChangeHistory, HardwareAttestationKind, Identifier};
 use crate::storage::{InMemoryStorage, Storage};
 use crate::utils::now;
 use crate::{
@@ -149,6 +149,29 @@ impl IdentitiesWriter for IdentitiesStorage {
             )
             .await
     }
+
+    async fn set_hardware_key(
+        &self,
+        identifier: &Identifier,
+        hardware_key: Option<HardwareAttestationKind>,
+    ) -> Result<()> {
+        match hardware_key {
+            Some(kind) => {
+                self.storage
+                    .set(
+                        &identifier.to_string(),
+                        IdentityConstants::HARDWARE_KEY_KEY.to_string(),
+                        minicbor::to_vec(kind)?,
+                    )
+                    .await
+            }
+            None => {
+                self.storage
+                    .del(&identifier.to_string(), IdentityConstants::HARDWARE_KEY_KEY)
+                    .await
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -167,4 +190,18 @@ impl IdentitiesReader for IdentitiesStorage {
             Ok(None)
         }
     }
+
+    async fn get_hardware_key(
+        &self,
+        identifier: &Identifier,
+    ) -> Result<Option<HardwareAttestationKind>> {
+        match self
+            .storage
+            .get(&identifier.to_string(), IdentityConstants::HARDWARE_KEY_KEY)
+            .await?
+        {
+            Some(data) => Ok(Some(minicbor::decode(&data)?)),
+            None => Ok(None),
+        }
+    }
 }
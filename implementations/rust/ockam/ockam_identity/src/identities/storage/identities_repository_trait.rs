@@ -5,7 +5,7 @@ use ockam_core::errcode::{Kind, Origin};
 use ockam_core::Result;
 use ockam_core::{async_trait, Error};
 
-use crate::models::{ChangeHistory, Identifier};
+use crate::models::{ChangeHistory, HardwareAttestationKind, Identifier};
 use crate::AttributesEntry;
 
 /// Repository for data related to identities: key changes and attributes
@@ -32,6 +32,22 @@ pub trait IdentityAttributesReader: Send + Sync + 'static {
     /// Get the attributes associated with the given identity identifier
     async fn get_attributes(&self, identity: &Identifier) -> Result<Option<AttributesEntry>>;
 
+    /// Get the attributes associated with the given identity identifier when they can be used
+    /// to access the given resource: attributes restricted to an audience are only returned for
+    /// the resources of that audience.
+    ///
+    /// Access controls must use this method instead of `get_attributes`
+    async fn get_attributes_for(
+        &self,
+        identity: &Identifier,
+        resource: Option<&str>,
+    ) -> Result<Option<AttributesEntry>> {
+        Ok(self
+            .get_attributes(identity)
+            .await?
+            .filter(|entry| entry.is_valid_for(resource)))
+    }
+
     /// List all identities with their attributes
     async fn list(&self) -> Result<Vec<(Identifier, AttributesEntry)>>;
 }
@@ -64,6 +80,14 @@ pub trait IdentitiesWriter: Send + Sync + 'static {
         identifier: &Identifier,
         change_history: &ChangeHistory,
     ) -> Result<()>;
+
+    /// Store the kind of hardware keeping the secure channel Purpose Key of an identity,
+    /// or remove it if that Purpose Key is not attested by hardware
+    async fn set_hardware_key(
+        &self,
+        identifier: &Identifier,
+        hardware_key: Option<HardwareAttestationKind>,
+    ) -> Result<()>;
 }
 
 /// Trait implementing read access to identiets
@@ -72,6 +96,12 @@ pub trait IdentitiesReader: Send + Sync + 'static {
     /// Return a persisted identity
     async fn retrieve_identity(&self, identifier: &Identifier) -> Result<Option<ChangeHistory>>;
 
+    /// Return the kind of hardware keeping the secure channel Purpose Key of an identity, if any
+    async fn get_hardware_key(
+        &self,
+        identifier: &Identifier,
+    ) -> Result<Option<HardwareAttestationKind>>;
+
     /// Return a persisted identity that is expected to be present and return and Error if this is not the case
     async fn get_identity(&self, identifier: &Identifier) -> Result<ChangeHistory> {
         match self.retrieve_identity(identifier).await? {
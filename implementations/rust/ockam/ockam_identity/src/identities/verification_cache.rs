@@ -0,0 +1,224 @@
+use minicbor::bytes::ByteSlice;
+use ockam_core::compat::boxed::Box;
+use ockam_core::compat::collections::BTreeSet;
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::{async_trait, Result};
+use ockam_vault::{Sha256Output, Signature, VaultForVerifyingSignatures, VerifyingPublicKey};
+use tracing::{debug, warn};
+
+use crate::identity::IdentityConstants;
+use crate::storage::Storage;
+use crate::utils::now;
+
+/// Vault verifying signatures which remembers the signatures it has already verified.
+///
+/// An entry is addressed by the hash of the public key, the signed data and the signature. When
+/// the storage is shared by the nodes of a host, the change histories and credentials presented
+/// to several nodes are only verified by the first one. Only valid signatures are stored, and
+/// the storage must protect their integrity since a stored entry is trusted without verifying
+/// the signature again.
+pub struct VerificationCache {
+    vault: Arc<dyn VaultForVerifyingSignatures>,
+    storage: Arc<dyn Storage>,
+    verified: RwLock<BTreeSet<String>>,
+}
+
+impl VerificationCache {
+    /// Create a cache in front of a vault
+    pub fn new(vault: Arc<dyn VaultForVerifyingSignatures>, storage: Arc<dyn Storage>) -> Self {
+        Self {
+            vault,
+            storage,
+            verified: RwLock::new(BTreeSet::new()),
+        }
+    }
+
+    /// Remove the entries stored more than `max_age` seconds ago and return their number
+    pub async fn prune(&self, max_age: u64) -> Result<usize> {
+        let oldest = now()?.saturating_sub(max_age);
+        let namespace = IdentityConstants::VERIFIED_SIGNATURES_KEY;
+        let mut removed = 0;
+        for key in self.storage.keys(namespace).await? {
+            let stored_at = match self.storage.get(&key, namespace).await {
+                Ok(Some(value)) => value.try_into().map(u64::from_be_bytes).unwrap_or_default(),
+                _ => 0,
+            };
+            if stored_at < oldest {
+                self.storage.del(&key, namespace).await?;
+                removed += 1;
+            }
+        }
+        self.verified.write().unwrap().clear();
+        Ok(removed)
+    }
+
+    /// Return the content address of a signature
+    async fn key(
+        &self,
+        verifying_public_key: &VerifyingPublicKey,
+        data: &[u8],
+        signature: &Signature,
+    ) -> Result<String> {
+        let content =
+            minicbor::to_vec((verifying_public_key, <&ByteSlice>::from(data), signature))?;
+        Ok(hex::encode(self.vault.sha256(&content).await?.0))
+    }
+
+    async fn is_verified(&self, key: &str) -> bool {
+        if self.verified.read().unwrap().contains(key) {
+            return true;
+        }
+        match self
+            .storage
+            .get(key, IdentityConstants::VERIFIED_SIGNATURES_KEY)
+            .await
+        {
+            Ok(Some(_)) => {
+                self.verified.write().unwrap().insert(key.to_string());
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                // the signature is verified again if the entry can't be trusted
+                warn!("the verified signature {key} can't be read: {e}");
+                false
+            }
+        }
+    }
+
+    async fn set_verified(&self, key: String) {
+        let stored_at = now().map(|now| *now).unwrap_or_default();
+        if let Err(e) = self
+            .storage
+            .set(
+                &key,
+                IdentityConstants::VERIFIED_SIGNATURES_KEY.to_string(),
+                stored_at.to_be_bytes().to_vec(),
+            )
+            .await
+        {
+            debug!("the verified signature {key} can't be stored: {e}");
+        }
+        self.verified.write().unwrap().insert(key);
+    }
+}
+
+#[async_trait]
+impl VaultForVerifyingSignatures for VerificationCache {
+    async fn sha256(&self, data: &[u8]) -> Result<Sha256Output> {
+        self.vault.sha256(data).await
+    }
+
+    async fn verify_signature(
+        &self,
+        verifying_public_key: &VerifyingPublicKey,
+        data: &[u8],
+        signature: &Signature,
+    ) -> Result<bool> {
+        let key = self.key(verifying_public_key, data, signature).await?;
+        if self.is_verified(&key).await {
+            return Ok(true);
+        }
+
+        let valid = self
+            .vault
+            .verify_signature(verifying_public_key, data, signature)
+            .await?;
+        if valid {
+            self.set_verified(key).await;
+        }
+        Ok(valid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use ockam_vault::{
+        SigningKeyType, SoftwareVaultForSigning, SoftwareVaultForVerifyingSignatures,
+        VaultForSigning,
+    };
+
+    /// Vault counting the signatures it verifies
+    #[derive(Default)]
+    struct CountingVault {
+        verifications: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl VaultForVerifyingSignatures for CountingVault {
+        async fn sha256(&self, data: &[u8]) -> Result<Sha256Output> {
+            SoftwareVaultForVerifyingSignatures::create()
+                .sha256(data)
+                .await
+        }
+
+        async fn verify_signature(
+            &self,
+            verifying_public_key: &VerifyingPublicKey,
+            data: &[u8],
+            signature: &Signature,
+        ) -> Result<bool> {
+            self.verifications.fetch_add(1, Ordering::Relaxed);
+            SoftwareVaultForVerifyingSignatures::create()
+                .verify_signature(verifying_public_key, data, signature)
+                .await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signatures_are_verified_once_per_storage() -> Result<()> {
+        let signing_vault = SoftwareVaultForSigning::create();
+        let handle = signing_vault
+            .generate_signing_secret_key(SigningKeyType::EdDSACurve25519)
+            .await?;
+        let public_key = signing_vault.get_verifying_public_key(&handle).await?;
+        let signature = signing_vault.sign(&handle, b"change").await?;
+
+        let storage = InMemoryStorage::create();
+        let vault = Arc::new(CountingVault::default());
+        let first = VerificationCache::new(vault.clone(), storage.clone());
+        for _ in 0..2 {
+            assert!(
+                first
+                    .verify_signature(&public_key, b"change", &signature)
+                    .await?
+            );
+        }
+        assert_eq!(vault.verifications.load(Ordering::Relaxed), 1);
+
+        // another node sharing the storage doesn't verify the signature again
+        let second = VerificationCache::new(vault.clone(), storage.clone());
+        assert!(
+            second
+                .verify_signature(&public_key, b"change", &signature)
+                .await?
+        );
+        assert_eq!(vault.verifications.load(Ordering::Relaxed), 1);
+
+        // invalid signatures are never stored
+        for _ in 0..2 {
+            assert!(
+                !second
+                    .verify_signature(&public_key, b"other change", &signature)
+                    .await?
+            );
+        }
+        assert_eq!(vault.verifications.load(Ordering::Relaxed), 3);
+
+        // the entries are verified again once they are pruned
+        assert_eq!(second.prune(0).await?, 0);
+        tokio::time::sleep(core::time::Duration::from_millis(1100)).await;
+        assert_eq!(second.prune(0).await?, 1);
+        assert!(
+            second
+                .verify_signature(&public_key, b"change", &signature)
+                .await?
+        );
+        assert_eq!(vault.verifications.load(Ordering::Relaxed), 4);
+        Ok(())
+    }
+}
@@ -10,4 +10,8 @@ impl IdentityConstants {
     pub const CREDENTIALS_PURPOSE_KEY: &'static str = "C_PK";
     /// Attributes key for AttributesStorage
     pub const ATTRIBUTES_KEY: &'static str = "ATTRIBUTES";
+    /// Key used to persist the kind of hardware keeping the Secure Channel PurposeKey of an identity
+    pub const HARDWARE_KEY_KEY: &'static str = "HARDWARE_KEY";
+    /// Key used to persist the signatures verified by a [`crate::VerificationCache`]
+    pub const VERIFIED_SIGNATURES_KEY: &'static str = "VERIFIED_SIGNATURES";
 }
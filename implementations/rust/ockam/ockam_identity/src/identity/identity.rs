@@ -1,4 +1,4 @@
-use crate::models::{Change, ChangeHash, ChangeHistory, Identifier};
+use crate::models::{Change, ChangeHash, ChangeHistory, DelegationData, Identifier};
 use crate::verified_change::VerifiedChange;
 use crate::IdentityError;
 use crate::IdentityHistoryComparison;
@@ -134,6 +134,21 @@ impl Identity {
         }
     }
 
+    /// Return the limitation of this Identity if it was delegated by a parent Identity.
+    /// The delegation is verified when the Identity is imported
+    pub fn delegation(&self) -> Result<Option<DelegationData>> {
+        match self
+            .changes
+            .first()
+            .and_then(|change| change.data().delegation.as_ref())
+        {
+            Some(delegation) => Ok(Some(DelegationData::get_data(
+                &delegation.get_versioned_data()?,
+            )?)),
+            None => Ok(None),
+        }
+    }
+
     /// Add a new key change to the change history
     pub async fn add_change(
         self,
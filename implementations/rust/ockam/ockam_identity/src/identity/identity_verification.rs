@@ -1,8 +1,14 @@
-use crate::models::{Change, ChangeData, ChangeHash, ChangeSignature, CHANGE_HASH_LEN};
+use crate::models::{
+    Change, ChangeData, ChangeHash, ChangeHistory, ChangeSignature, Delegation, DelegationData,
+    Identifier, PrimaryPublicKey, TimestampInSeconds, CHANGE_HASH_LEN,
+};
 use crate::verified_change::VerifiedChange;
 use crate::{Identity, IdentityError};
 use arrayref::array_ref;
 
+use core::future::Future;
+use core::pin::Pin;
+use ockam_core::compat::boxed::Box;
 use ockam_core::compat::sync::Arc;
 use ockam_core::compat::vec::Vec;
 use ockam_core::Result;
@@ -61,6 +67,9 @@ impl Identity {
     ) -> Result<Vec<VerifiedChange>> {
         let mut to_be_verified_changes = Vec::with_capacity(new_changes.len());
 
+        // The keys of a child identity can't expire after its delegation
+        let mut delegation_expires_at: Option<TimestampInSeconds> = None;
+
         let mut previous_change_details = match last_known_change {
             Some(previous_change) => {
                 Some(Self::get_change_details(previous_change, vault.clone()).await?)
@@ -93,9 +102,27 @@ impl Identity {
                     // Corrupted changes sequence
                     return Err(IdentityError::IdentityVerificationFailed.into());
                 }
+
+                if change_details.change_data.delegation.is_some() {
+                    // Only the first Change can be delegated
+                    return Err(IdentityError::DelegationVerificationFailed.into());
+                }
             } else if change_details.change_data.previous_change.is_some() {
                 // Should be empty
                 return Err(IdentityError::IdentityVerificationFailed.into());
+            } else if let Some(delegation) = &change_details.change_data.delegation {
+                let delegation_data = DelegationData::get_data(&delegation.get_versioned_data()?)?;
+                if change_details.change_data.created_at < delegation_data.created_at {
+                    // The key can't be created before the delegation
+                    return Err(IdentityError::DelegationVerificationFailed.into());
+                }
+                delegation_expires_at = Some(delegation_data.expires_at);
+            }
+
+            if let Some(delegation_expires_at) = delegation_expires_at {
+                if change_details.change_data.expires_at > delegation_expires_at {
+                    return Err(IdentityError::DelegationVerificationFailed.into());
+                }
             }
 
             to_be_verified_changes.push(VerifiedChange::new(
@@ -169,6 +196,13 @@ impl Identity {
                 // Previous signature should be present if it's not the first change
                 return Err(IdentityError::IdentityVerificationFailed.into());
             }
+        } else if let Some(delegation) = &new_change_details.change_data.delegation {
+            Self::verify_delegation(
+                delegation,
+                &new_change_details.change_data.primary_public_key,
+                vault.clone(),
+            )
+            .await?;
         }
 
         if !Self::verify_change_signature(
@@ -188,4 +222,61 @@ impl Identity {
 
         Ok(())
     }
+
+    /// Verify that a [`Delegation`] is signed by the parent, and issued for the key of the
+    /// first Change of the child
+    async fn verify_delegation(
+        delegation: &Delegation,
+        child_public_key: &PrimaryPublicKey,
+        vault: Arc<dyn VaultForVerifyingSignatures>,
+    ) -> Result<()> {
+        let versioned_data = delegation.get_versioned_data()?;
+        if versioned_data.version != 1 {
+            return Err(IdentityError::UnknownIdentityVersion.into());
+        }
+        let delegation_data = DelegationData::get_data(&versioned_data)?;
+
+        if &delegation_data.child_public_key != child_public_key {
+            // The delegation was issued for another identity
+            return Err(IdentityError::DelegationVerificationFailed.into());
+        }
+
+        let parent = Self::import_parent(
+            delegation_data.parent,
+            delegation.parent_change_history.clone(),
+            vault.clone(),
+        )
+        .await?;
+
+        if parent.delegation()?.is_some() {
+            // A child identity can't delegate, otherwise it could escape its own limitation
+            return Err(IdentityError::DelegationVerificationFailed.into());
+        }
+
+        let hash = vault.sha256(&delegation.data).await?;
+        if !Self::verify_change_signature(
+            &parent.get_latest_public_key()?,
+            hash.0,
+            &delegation.signature,
+            vault,
+        )
+        .await?
+        {
+            return Err(IdentityError::DelegationVerificationFailed.into());
+        }
+
+        Ok(())
+    }
+
+    /// Import the parent of a child identity. The future is boxed since the import of an
+    /// identity verifies its delegation
+    fn import_parent(
+        parent: Identifier,
+        change_history: ChangeHistory,
+        vault: Arc<dyn VaultForVerifyingSignatures>,
+    ) -> Pin<Box<dyn Future<Output = Result<Identity>> + Send>> {
+        Box::pin(async move {
+            Self::import_from_change_history(Some(&parent), change_history, vault).await
+        })
+    }
 }
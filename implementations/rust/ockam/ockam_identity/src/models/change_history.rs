@@ -1,4 +1,4 @@
-use crate::models::{ChangeHash, TimestampInSeconds};
+use crate::models::{ChangeHash, Delegation, TimestampInSeconds};
 use minicbor::{Decode, Encode};
 use ockam_core::compat::vec::Vec;
 use ockam_vault::{
@@ -56,6 +56,9 @@ pub struct ChangeData {
     #[n(4)] pub created_at: TimestampInSeconds,
     /// Expiration [`TimestampInSeconds`] (UTC)
     #[n(5)] pub expires_at: TimestampInSeconds,
+    /// Limitation of the [`super::super::identity::Identity`] signed by its parent.
+    /// It can only be present in the first [`Change`] of a child identity
+    #[n(6)] pub delegation: Option<Delegation>,
 }
 
 /// [`Change`]'s public key
@@ -1,7 +1,7 @@
 use crate::models::{ChangeHash, Identifier, TimestampInSeconds};
 use minicbor::bytes::ByteVec;
 use minicbor::{Decode, Encode};
-use ockam_core::compat::{collections::BTreeMap, vec::Vec};
+use ockam_core::compat::{collections::BTreeMap, string::String, vec::Vec};
 use ockam_vault::{ECDSASHA256CurveP256Signature, EdDSACurve25519Signature};
 
 /// Credential
@@ -43,6 +43,12 @@ pub struct CredentialData {
     #[n(4)] pub created_at: TimestampInSeconds,
     /// Expiration [`TimestampInSeconds`] (UTC)
     #[n(5)] pub expires_at: TimestampInSeconds,
+    /// Offset in seconds of the Authority (issuer) clock compared to a reference clock,
+    /// positive when the issuer clock is ahead. Missing when the issuer has no known offset
+    #[n(6)] pub issuer_clock_offset: Option<i64>,
+    /// Resources this Credential can be presented for. Missing when the Credential is valid
+    /// for any resource
+    #[n(7)] pub audience: Option<Vec<String>>,
 }
 
 /// Number that determines which keys&values to expect in the [`Attributes`]
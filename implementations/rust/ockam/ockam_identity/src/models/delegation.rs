@@ -0,0 +1,45 @@
+use crate::models::{
+    ChangeHistory, ChangeSignature, Identifier, PrimaryPublicKey, TimestampInSeconds,
+};
+use crate::Purpose;
+use minicbor::{Decode, Encode};
+use ockam_core::compat::string::String;
+use ockam_core::compat::vec::Vec;
+
+/// Statement signed by a parent [`super::super::identity::Identity`] limiting what a child
+/// [`super::super::identity::Identity`] is allowed to do.
+/// It's part of the first [`super::Change`] of the child
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct Delegation {
+    /// CBOR serialized [`super::VersionedData`]
+    /// where VersionedData::data is CBOR serialized [`DelegationData`]
+    #[cbor(with = "minicbor::bytes")]
+    #[n(1)] pub data: Vec<u8>,
+    /// Signature over the data using the latest key of the parent
+    #[n(2)] pub signature: ChangeSignature,
+    /// [`ChangeHistory`] of the parent at the moment of signing
+    #[n(3)] pub parent_change_history: ChangeHistory,
+}
+
+/// Data inside a [`Delegation`]
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct DelegationData {
+    /// [`Identifier`] of the parent
+    #[n(1)] pub parent: Identifier,
+    /// Public Key of the first [`super::Change`] of the child.
+    /// The identifier of the child can't be used since it's computed from that [`super::Change`]
+    #[n(2)] pub child_public_key: PrimaryPublicKey,
+    /// Names of the attributes which can be attested in the credentials of the child,
+    /// or in the credentials issued by the child
+    #[n(3)] pub allowed_attributes: Vec<String>,
+    /// Purposes of the Purpose Keys which the child can use
+    #[n(4)] pub allowed_purposes: Vec<Purpose>,
+    /// Creation [`TimestampInSeconds`] (UTC)
+    #[n(5)] pub created_at: TimestampInSeconds,
+    /// Expiration [`TimestampInSeconds`] (UTC), the keys of the child can't expire after it
+    #[n(6)] pub expires_at: TimestampInSeconds,
+}
@@ -1,6 +1,7 @@
 mod change_history;
 mod credential;
 mod credential_and_purpose_key;
+mod delegation;
 mod identifiers;
 mod purpose_key_attestation;
 mod timestamp;
@@ -10,6 +11,7 @@ mod versioned_data;
 pub use change_history::*;
 pub use credential::*;
 pub use credential_and_purpose_key::*;
+pub use delegation::*;
 pub use identifiers::*;
 pub use purpose_key_attestation::*;
 pub use timestamp::*;
@@ -48,6 +48,45 @@ pub struct PurposeKeyAttestationData {
     #[n(4)] pub created_at: TimestampInSeconds,
     /// Expiration [`TimestampInSeconds`] (UTC)
     #[n(5)] pub expires_at: TimestampInSeconds,
+    /// Evidence that the secret of this Purpose Key was generated and is kept in hardware
+    #[n(6)] pub hardware_attestation: Option<HardwareAttestation>,
+}
+
+/// Evidence, produced by a TPM, a Secure Enclave or a KMS, that a key was generated
+/// and is kept in that hardware
+#[derive(Clone, Debug, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct HardwareAttestation {
+    /// Kind of hardware which produced the evidence
+    #[n(1)] pub kind: HardwareAttestationKind,
+    /// Evidence in the format of the hardware, for example a TPM2 quote or a certificate chain
+    #[cbor(with = "minicbor::bytes")]
+    #[n(2)] pub evidence: Vec<u8>,
+}
+
+/// Kind of hardware attesting a key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(index_only)]
+pub enum HardwareAttestationKind {
+    /// Trusted Platform Module 2.0
+    #[n(1)] Tpm2,
+    /// Apple Secure Enclave
+    #[n(2)] SecureEnclave,
+    /// AWS Key Management Service
+    #[n(3)] AwsKms,
+}
+
+impl HardwareAttestationKind {
+    /// Name of the kind of hardware, used as the value of the `ockam-hardware-key` attribute
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HardwareAttestationKind::Tpm2 => "tpm2",
+            HardwareAttestationKind::SecureEnclave => "secure-enclave",
+            HardwareAttestationKind::AwsKms => "aws-kms",
+        }
+    }
 }
 
 /// [`PurposeKeyAttestation`]'s public key
@@ -0,0 +1,29 @@
+use crate::models::utils::get_versioned_data;
+use crate::models::{Delegation, DelegationData, VersionedData};
+use crate::Purpose;
+
+use ockam_core::Result;
+
+impl Delegation {
+    /// Extract [`VersionedData`]
+    pub fn get_versioned_data(&self) -> Result<VersionedData> {
+        get_versioned_data(&self.data)
+    }
+}
+
+impl DelegationData {
+    /// Extract [`DelegationData`] from [`VersionedData`]
+    pub fn get_data(versioned_data: &VersionedData) -> Result<Self> {
+        Ok(minicbor::decode(&versioned_data.data)?)
+    }
+
+    /// Return true if the child can use Purpose Keys with that purpose
+    pub fn allows_purpose(&self, purpose: Purpose) -> bool {
+        self.allowed_purposes.contains(&purpose)
+    }
+
+    /// Return true if the attribute can be attested in the credentials of the child
+    pub fn allows_attribute(&self, name: &[u8]) -> bool {
+        self.allowed_attributes.iter().any(|a| a.as_bytes() == name)
+    }
+}
@@ -7,6 +7,7 @@ fn get_versioned_data<'a, T: Decode<'a, ()>>(data: &'a [u8]) -> Result<T> {
 
 mod change_history;
 mod credentials;
+mod delegation;
 mod identifiers;
 mod purpose_key_attestation;
 mod timestamp;
@@ -1,8 +1,11 @@
+use minicbor::{Decode, Encode};
+
 /// Purpose for a [`super::purpose_key::PurposeKey`]
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Encode, Decode)]
+#[rustfmt::skip]
 pub enum Purpose {
     /// Purpose Key dedicated for Secure Channel creation
-    SecureChannel,
+    #[n(1)] SecureChannel,
     /// Purpose Key dedicated for Credentials issuing
-    Credentials,
+    #[n(2)] Credentials,
 }
@@ -2,7 +2,7 @@ use ockam_core::compat::sync::Arc;
 use ockam_core::Result;
 use ockam_vault::X25519SecretKeyHandle;
 
-use crate::models::{PurposePublicKey, TimestampInSeconds};
+use crate::models::{HardwareAttestation, PurposePublicKey, TimestampInSeconds};
 use crate::purpose_keys::Ttl;
 use crate::{Identifier, Purpose, PurposeKeyCreation, SecureChannelPurposeKey};
 
@@ -22,6 +22,7 @@ pub struct SecureChannelPurposeKeyBuilder {
     identifier: Identifier,
     key: Key,
     ttl: Ttl,
+    hardware_attestation: Option<HardwareAttestation>,
 }
 
 impl SecureChannelPurposeKeyBuilder {
@@ -33,6 +34,7 @@ impl SecureChannelPurposeKeyBuilder {
             purpose_keys_creation,
             identifier,
             key,
+            hardware_attestation: None,
             ttl: Ttl::CreatedNowWithTtl(DEFAULT_SECURE_CHANNEL_PURPOSE_KEY_TTL),
         }
     }
@@ -50,6 +52,13 @@ impl SecureChannelPurposeKeyBuilder {
         self
     }
 
+    /// Attach the evidence that the key was generated and is kept in hardware,
+    /// for example a TPM2 quote obtained when the key was created
+    pub fn with_hardware_attestation(mut self, hardware_attestation: HardwareAttestation) -> Self {
+        self.hardware_attestation = Some(hardware_attestation);
+        self
+    }
+
     /// Set created_at and expires_at timestamps
     pub fn with_timestamps(
         mut self,
@@ -100,6 +109,7 @@ impl SecureChannelPurposeKeyBuilder {
                 PurposePublicKey::SecureChannelStatic(public_key.clone()),
                 created_at,
                 expires_at,
+                self.hardware_attestation,
             )
             .await?;
 
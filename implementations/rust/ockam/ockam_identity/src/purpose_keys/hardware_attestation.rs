@@ -0,0 +1,96 @@
+use ockam_core::compat::boxed::Box;
+use ockam_core::{async_trait, Result};
+
+use crate::models::{HardwareAttestation, PurposePublicKey};
+
+/// Attribute added to the attributes attested by an authority for an identity whose secure
+/// channel Purpose Key is attested by hardware. Its value is the kind of hardware, for example `tpm2`, so that a policy like
+/// `(= subject.ockam-hardware-key "tpm2")` only accepts channels from hardware-rooted keys
+pub const HARDWARE_KEY_ATTRIBUTE: &str = "ockam-hardware-key";
+
+/// Verifier of the hardware attestations carried by Purpose Keys.
+///
+/// Relying parties which don't set a verifier accept Purpose Keys with or without a hardware
+/// attestation, but don't consider them as hardware-rooted
+#[async_trait]
+pub trait HardwareAttestationVerifier: Send + Sync + 'static {
+    /// Return true if the evidence proves that the secret of `public_key`
+    /// was generated and is kept in hardware
+    async fn verify(
+        &self,
+        public_key: &PurposePublicKey,
+        attestation: &HardwareAttestation,
+    ) -> Result<bool>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::HardwareAttestationKind;
+    use crate::{identities, Identities};
+    use ockam_core::compat::sync::Arc;
+
+    struct EvidenceVerifier;
+
+    #[async_trait]
+    impl HardwareAttestationVerifier for EvidenceVerifier {
+        async fn verify(
+            &self,
+            _public_key: &PurposePublicKey,
+            attestation: &HardwareAttestation,
+        ) -> Result<bool> {
+            Ok(attestation.evidence == b"valid quote")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verify_hardware_attestation() -> Result<()> {
+        let identities = identities();
+        let identity = identities.identities_creation().create_identity().await?;
+        let attested = |evidence: &[u8]| HardwareAttestation {
+            kind: HardwareAttestationKind::Tpm2,
+            evidence: evidence.to_vec(),
+        };
+
+        let valid = identities
+            .purpose_keys()
+            .purpose_keys_creation()
+            .secure_channel_purpose_key_builder(identity.identifier())
+            .with_hardware_attestation(attested(b"valid quote"))
+            .build()
+            .await?;
+        let invalid = identities
+            .purpose_keys()
+            .purpose_keys_creation()
+            .secure_channel_purpose_key_builder(identity.identifier())
+            .with_hardware_attestation(attested(b"forged quote"))
+            .build()
+            .await?;
+
+        // without a verifier, the attestations are carried but not taken into account
+        let verification = identities.purpose_keys().purpose_keys_verification();
+        let data = verification
+            .verify_purpose_key_attestation(None, invalid.attestation())
+            .await?;
+        assert_eq!(verification.hardware_attested(&data), None);
+
+        let relying_party = Identities::builder()
+            .with_vault(identities.vault())
+            .with_identities_repository(identities.repository())
+            .with_hardware_attestation_verifier(Arc::new(EvidenceVerifier))
+            .build();
+        let verification = relying_party.purpose_keys().purpose_keys_verification();
+        let data = verification
+            .verify_purpose_key_attestation(None, valid.attestation())
+            .await?;
+        assert_eq!(
+            verification.hardware_attested(&data),
+            Some(HardwareAttestationKind::Tpm2)
+        );
+        assert!(verification
+            .verify_purpose_key_attestation(None, invalid.attestation())
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 mod builder;
+mod hardware_attestation;
 mod purpose_key_creation;
 mod purpose_key_verification;
 #[allow(clippy::module_inception)]
 mod purpose_keys;
 
 pub use builder::*;
+pub use hardware_attestation::*;
 pub use purpose_key_creation::*;
 pub use purpose_key_verification::*;
 pub use purpose_keys::*;
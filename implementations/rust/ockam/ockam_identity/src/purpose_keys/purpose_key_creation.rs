@@ -2,13 +2,14 @@ use ockam_core::compat::sync::Arc;
 use ockam_core::{Error, Result};
 
 use crate::models::{
-    Identifier, PurposeKeyAttestation, PurposeKeyAttestationData, PurposePublicKey, VersionedData,
+    HardwareAttestation, Identifier, PurposeKeyAttestation, PurposeKeyAttestationData,
+    PurposePublicKey, VersionedData,
 };
 use crate::purpose_keys::storage::PurposeKeysRepository;
 use crate::{
-    CredentialPurposeKey, CredentialPurposeKeyBuilder, IdentitiesKeys, IdentitiesReader, Identity,
-    IdentityError, Purpose, PurposeKeyVerification, SecureChannelPurposeKey,
-    SecureChannelPurposeKeyBuilder, TimestampInSeconds, Vault,
+    CredentialPurposeKey, CredentialPurposeKeyBuilder, HardwareAttestationVerifier, IdentitiesKeys,
+    IdentitiesReader, Identity, IdentityError, Purpose, PurposeKeyVerification,
+    SecureChannelPurposeKey, SecureChannelPurposeKeyBuilder, TimestampInSeconds, Vault,
 };
 
 /// This struct supports all the services related to identities
@@ -18,6 +19,7 @@ pub struct PurposeKeyCreation {
     identities_reader: Arc<dyn IdentitiesReader>,
     identity_keys: Arc<IdentitiesKeys>,
     repository: Arc<dyn PurposeKeysRepository>,
+    hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
 }
 
 impl PurposeKeyCreation {
@@ -33,9 +35,19 @@ impl PurposeKeyCreation {
             identities_reader,
             identity_keys,
             repository,
+            hardware_attestation_verifier: None,
         }
     }
 
+    /// Verify the hardware attestations of the Purpose Keys with a verifier
+    pub fn with_hardware_attestation_verifier(
+        mut self,
+        verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
+    ) -> Self {
+        self.hardware_attestation_verifier = verifier;
+        self
+    }
+
     /// Return [`PurposeKeysRepository`] instance
     pub fn repository(&self) -> Arc<dyn PurposeKeysRepository> {
         self.repository.clone()
@@ -43,10 +55,13 @@ impl PurposeKeyCreation {
 
     /// Create [`PurposeKeyVerification`]
     pub fn purpose_keys_verification(&self) -> Arc<PurposeKeyVerification> {
-        Arc::new(PurposeKeyVerification::new(
-            self.vault.verifying_vault.clone(),
-            self.identities_reader.clone(),
-        ))
+        Arc::new(
+            PurposeKeyVerification::new(
+                self.vault.verifying_vault.clone(),
+                self.identities_reader.clone(),
+            )
+            .with_hardware_attestation_verifier(self.hardware_attestation_verifier.clone()),
+        )
     }
 
     /// Get an instance of [`PurposeKeyBuilder`]
@@ -54,15 +69,7 @@ impl PurposeKeyCreation {
         &self,
         identifier: &Identifier,
     ) -> SecureChannelPurposeKeyBuilder {
-        SecureChannelPurposeKeyBuilder::new(
-            Arc::new(Self::new(
-                self.vault.clone(),
-                self.identities_reader.clone(),
-                self.identity_keys.clone(),
-                self.repository.clone(),
-            )),
-            identifier.clone(),
-        )
+        SecureChannelPurposeKeyBuilder::new(Arc::new(self.clone()), identifier.clone())
     }
 
     /// Get an instance of [`PurposeKeyBuilder`]
@@ -70,15 +77,7 @@ impl PurposeKeyCreation {
         &self,
         identifier: &Identifier,
     ) -> CredentialPurposeKeyBuilder {
-        CredentialPurposeKeyBuilder::new(
-            Arc::new(Self::new(
-                self.vault.clone(),
-                self.identities_reader.clone(),
-                self.identity_keys.clone(),
-                self.repository.clone(),
-            )),
-            identifier.clone(),
-        )
+        CredentialPurposeKeyBuilder::new(Arc::new(self.clone()), identifier.clone())
     }
 
     /// Return the [`Vault`]
@@ -106,13 +105,14 @@ impl PurposeKeyCreation {
         builder.build().await
     }
 
-    /// Attest a Purpose Key
+    /// Attest a Purpose Key, with the evidence that it was generated in hardware if it was
     pub async fn attest_purpose_key(
         &self,
         identifier: Identifier,
         public_key: PurposePublicKey,
         created_at: TimestampInSeconds,
         expires_at: TimestampInSeconds,
+        hardware_attestation: Option<HardwareAttestation>,
     ) -> Result<(PurposeKeyAttestation, PurposeKeyAttestationData)> {
         let identity_change_history = self.identities_reader.get_identity(&identifier).await?;
         let identity = Identity::import_from_change_history(
@@ -128,6 +128,7 @@ impl PurposeKeyCreation {
             public_key,
             created_at,
             expires_at,
+            hardware_attestation,
         };
 
         let attestation_data_binary = minicbor::to_vec(&attestation_data)?;
@@ -2,9 +2,15 @@ use ockam_core::compat::sync::Arc;
 use ockam_core::Result;
 use ockam_vault::VaultForVerifyingSignatures;
 
-use crate::models::{Identifier, PurposeKeyAttestation, PurposeKeyAttestationData};
+use crate::models::{
+    HardwareAttestationKind, Identifier, PurposeKeyAttestation, PurposeKeyAttestationData,
+    PurposePublicKey,
+};
 use crate::utils::now;
-use crate::{IdentitiesReader, Identity, IdentityError, TimestampInSeconds};
+use crate::{
+    HardwareAttestationVerifier, IdentitiesReader, Identity, IdentityError, Purpose,
+    TimestampInSeconds,
+};
 
 /// We allow purpose keys to be created in the future related to this machine's time due to
 /// possible time dyssynchronization
@@ -15,6 +21,7 @@ const MAX_ALLOWED_TIME_DRIFT: TimestampInSeconds = TimestampInSeconds(5);
 pub struct PurposeKeyVerification {
     verifying_vault: Arc<dyn VaultForVerifyingSignatures>,
     identities_reader: Arc<dyn IdentitiesReader>,
+    hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
 }
 
 impl PurposeKeyVerification {
@@ -26,8 +33,18 @@ impl PurposeKeyVerification {
         Self {
             verifying_vault,
             identities_reader,
+            hardware_attestation_verifier: None,
         }
     }
+
+    /// Verify the hardware attestations of the Purpose Keys with a verifier
+    pub fn with_hardware_attestation_verifier(
+        mut self,
+        verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
+    ) -> Self {
+        self.hardware_attestation_verifier = verifier;
+        self
+    }
 }
 
 impl PurposeKeyVerification {
@@ -65,6 +82,17 @@ impl PurposeKeyVerification {
         )
         .await?;
 
+        if let Some(delegation) = identity.delegation()? {
+            let purpose = match purpose_key_data.public_key {
+                PurposePublicKey::SecureChannelStatic(_) => Purpose::SecureChannel,
+                PurposePublicKey::CredentialSigning(_) => Purpose::Credentials,
+            };
+            if !delegation.allows_purpose(purpose) {
+                // The parent of that identity didn't allow it to use that kind of Purpose Key
+                return Err(IdentityError::OutsideOfDelegation.into());
+            }
+        }
+
         let latest_change = identity.get_latest_change()?;
 
         // TODO: We should inspect purpose_key_data.subject_latest_change_hash, the possibilities are:
@@ -118,6 +146,31 @@ impl PurposeKeyVerification {
             return Err(IdentityError::PurposeKeyAttestationVerificationFailed.into());
         }
 
+        if let (Some(verifier), Some(hardware_attestation)) = (
+            &self.hardware_attestation_verifier,
+            &purpose_key_data.hardware_attestation,
+        ) {
+            if !verifier
+                .verify(&purpose_key_data.public_key, hardware_attestation)
+                .await?
+            {
+                return Err(IdentityError::HardwareAttestationVerificationFailed.into());
+            }
+        }
+
         Ok(purpose_key_data)
     }
+
+    /// Return the kind of hardware keeping the secret of a verified Purpose Key.
+    /// A hardware attestation is only taken into account if a verifier is set
+    pub fn hardware_attested(
+        &self,
+        purpose_key_data: &PurposeKeyAttestationData,
+    ) -> Option<HardwareAttestationKind> {
+        self.hardware_attestation_verifier.as_ref()?;
+        purpose_key_data
+            .hardware_attestation
+            .as_ref()
+            .map(|attestation| attestation.kind)
+    }
 }
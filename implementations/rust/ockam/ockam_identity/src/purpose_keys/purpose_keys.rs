@@ -1,7 +1,10 @@
 use ockam_core::compat::sync::Arc;
 
 use crate::purpose_keys::storage::PurposeKeysRepository;
-use crate::{IdentitiesKeys, IdentitiesReader, PurposeKeyCreation, PurposeKeyVerification, Vault};
+use crate::{
+    HardwareAttestationVerifier, IdentitiesKeys, IdentitiesReader, PurposeKeyCreation,
+    PurposeKeyVerification, Vault,
+};
 
 /// This struct supports all the services related to identities
 #[derive(Clone)]
@@ -10,6 +13,7 @@ pub struct PurposeKeys {
     identities_reader: Arc<dyn IdentitiesReader>,
     identity_keys: Arc<IdentitiesKeys>,
     repository: Arc<dyn PurposeKeysRepository>,
+    hardware_attestation_verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
 }
 
 impl PurposeKeys {
@@ -25,9 +29,19 @@ impl PurposeKeys {
             identities_reader,
             identity_keys,
             repository,
+            hardware_attestation_verifier: None,
         }
     }
 
+    /// Verify the hardware attestations of the Purpose Keys with a verifier
+    pub fn with_hardware_attestation_verifier(
+        mut self,
+        verifier: Option<Arc<dyn HardwareAttestationVerifier>>,
+    ) -> Self {
+        self.hardware_attestation_verifier = verifier;
+        self
+    }
+
     /// Return [`PurposeKeysRepository`] instance
     pub fn repository(&self) -> Arc<dyn PurposeKeysRepository> {
         self.repository.clone()
@@ -35,20 +49,26 @@ impl PurposeKeys {
 
     /// Create [`PurposeKeyCreation`]
     pub fn purpose_keys_creation(&self) -> Arc<PurposeKeyCreation> {
-        Arc::new(PurposeKeyCreation::new(
-            self.vault.clone(),
-            self.identities_reader.clone(),
-            self.identity_keys.clone(),
-            self.repository.clone(),
-        ))
+        Arc::new(
+            PurposeKeyCreation::new(
+                self.vault.clone(),
+                self.identities_reader.clone(),
+                self.identity_keys.clone(),
+                self.repository.clone(),
+            )
+            .with_hardware_attestation_verifier(self.hardware_attestation_verifier.clone()),
+        )
     }
 
     /// Create [`PurposeKeyVerification`]
     pub fn purpose_keys_verification(&self) -> Arc<PurposeKeyVerification> {
-        Arc::new(PurposeKeyVerification::new(
-            self.vault.verifying_vault.clone(),
-            self.identities_reader.clone(),
-        ))
+        Arc::new(
+            PurposeKeyVerification::new(
+                self.vault.verifying_vault.clone(),
+                self.identities_reader.clone(),
+            )
+            .with_hardware_attestation_verifier(self.hardware_attestation_verifier.clone()),
+        )
     }
 }
 
@@ -1,6 +1,6 @@
 use core::fmt::{Debug, Formatter};
 use ockam_core::access_control::IncomingAccessControl;
-use ockam_core::compat::{boxed::Box, sync::Arc, vec::Vec};
+use ockam_core::compat::{boxed::Box, string::String, sync::Arc, vec::Vec};
 use ockam_core::Result;
 use ockam_core::{async_trait, RelayMessage};
 
@@ -11,6 +11,7 @@ use crate::secure_channel::local_info::IdentitySecureChannelLocalInfo;
 #[derive(Clone)]
 pub struct CredentialAccessControl {
     required_attributes: Vec<(Vec<u8>, Vec<u8>)>,
+    resource: Option<String>,
     storage: Arc<dyn IdentitiesRepository>,
 }
 
@@ -22,9 +23,17 @@ impl CredentialAccessControl {
     ) -> Self {
         Self {
             required_attributes: required_attributes.to_vec(),
+            resource: None,
             storage,
         }
     }
+
+    /// Only use the attributes which are valid for the given resource.
+    /// Without a resource, the attributes restricted to an audience are never used
+    pub fn with_resource(mut self, resource: &str) -> Self {
+        self.resource = Some(resource.into());
+        self
+    }
 }
 
 impl Debug for CredentialAccessControl {
@@ -33,6 +42,7 @@ impl Debug for CredentialAccessControl {
 
         f.debug_struct("Credential Access Control")
             .field("Required attributes", &attributes)
+            .field("Resource", &self.resource)
             .finish()
     }
 }
@@ -45,7 +55,10 @@ impl IncomingAccessControl for CredentialAccessControl {
         {
             let attributes = match self
                 .storage
-                .get_attributes(&msg_identity_id.their_identity_id())
+                .get_attributes_for(
+                    &msg_identity_id.their_identity_id(),
+                    self.resource.as_deref(),
+                )
                 .await?
             {
                 Some(a) => a,
@@ -0,0 +1,129 @@
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::string::String;
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+use tracing::info;
+
+use crate::models::Identifier;
+use crate::IdentityAttributesReader;
+
+/// Coarse filter applied by a secure channel listener to the identities initiating a handshake.
+///
+/// The filter is evaluated at the end of the handshake, once the credentials of the other party
+/// have been verified, and before the secure channel is handed over to any application worker.
+/// It complements the access controls of each resource by rejecting early the identities which
+/// should never be able to talk to the node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SecureChannelAdmission {
+    allowed_identifiers: Option<Vec<Identifier>>,
+    required_attributes: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl SecureChannelAdmission {
+    /// Only admit the given identifiers
+    pub fn with_allowed_identifiers(mut self, identifiers: Vec<Identifier>) -> Self {
+        self.allowed_identifiers
+            .get_or_insert_with(Vec::new)
+            .extend(identifiers);
+        self
+    }
+
+    /// Only admit identities having all the given attributes with the given values.
+    /// The attributes are the ones attested by the credentials presented during the handshake
+    pub fn with_required_attributes(mut self, attributes: BTreeMap<String, String>) -> Self {
+        self.required_attributes.extend(
+            attributes
+                .into_iter()
+                .map(|(k, v)| (k.into_bytes(), v.into_bytes())),
+        );
+        self
+    }
+
+    /// Return true if this filter admits everyone
+    pub fn is_open(&self) -> bool {
+        self.allowed_identifiers.is_none() && self.required_attributes.is_empty()
+    }
+
+    /// Check if an identity can be admitted
+    pub async fn check(
+        &self,
+        identifier: &Identifier,
+        attributes_reader: &dyn IdentityAttributesReader,
+    ) -> Result<bool> {
+        if let Some(allowed_identifiers) = &self.allowed_identifiers {
+            if !allowed_identifiers.contains(identifier) {
+                info!("{identifier} is not part of the identifiers allowed by the listener");
+                return Ok(false);
+            }
+        }
+
+        if self.required_attributes.is_empty() {
+            return Ok(true);
+        }
+
+        // attributes restricted to an audience of resources never admit an identity on a listener
+        let entry = match attributes_reader
+            .get_attributes_for(identifier, None)
+            .await?
+        {
+            Some(entry) => entry,
+            None => {
+                info!("{identifier} has no attributes, it can't be admitted by the listener");
+                return Ok(false);
+            }
+        };
+
+        let attributes = entry.attrs();
+        for (name, value) in &self.required_attributes {
+            if attributes.get(name) != Some(value) {
+                info!(
+                    "{identifier} doesn't have the attribute {} required by the listener",
+                    String::from_utf8_lossy(name)
+                );
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identities::{identities, AttributesEntry};
+    use crate::utils::now;
+
+    #[tokio::test]
+    async fn test_admission() -> Result<()> {
+        let identities = identities();
+        let alice = identities.identities_creation().create_identity().await?;
+        let bob = identities.identities_creation().create_identity().await?;
+        let repository = identities.repository();
+
+        let mut attributes = BTreeMap::new();
+        attributes.insert(b"role".to_vec(), b"admin".to_vec());
+        repository
+            .put_attributes(
+                alice.identifier(),
+                AttributesEntry::new(attributes, now()?, None, None),
+            )
+            .await?;
+        let reader = repository.as_attributes_reader();
+
+        let admission = SecureChannelAdmission::default();
+        assert!(admission.is_open());
+        assert!(admission.check(bob.identifier(), reader.as_ref()).await?);
+
+        let admission = SecureChannelAdmission::default()
+            .with_allowed_identifiers(vec![alice.identifier().clone()]);
+        assert!(admission.check(alice.identifier(), reader.as_ref()).await?);
+        assert!(!admission.check(bob.identifier(), reader.as_ref()).await?);
+
+        let admission = SecureChannelAdmission::default()
+            .with_required_attributes([("role".to_string(), "admin".to_string())].into());
+        assert!(admission.check(alice.identifier(), reader.as_ref()).await?);
+        assert!(!admission.check(bob.identifier(), reader.as_ref()).await?);
+
+        Ok(())
+    }
+}
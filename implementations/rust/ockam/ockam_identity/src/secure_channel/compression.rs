@@ -0,0 +1,237 @@
+use core::fmt::{Debug, Formatter};
+use ockam_core::compat::sync::Arc;
+use ockam_core::compat::vec::Vec;
+use ockam_core::{Address, Result, Route};
+use sha2::{Digest, Sha256};
+
+use crate::IdentityError;
+
+/// Largest message which is compressed. A dictionary mostly helps with small messages, the
+/// larger ones, like the data of a portal, are sent uncompressed
+#[cfg(feature = "compression")]
+const MAX_COMPRESSED_MESSAGE_SIZE: usize = 16 * 1024;
+
+/// Compression level of zstd, the dictionary does most of the work on small messages
+#[cfg(feature = "compression")]
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// First byte of a message sent as is on a channel using compression
+const UNCOMPRESSED: u8 = 0;
+/// First byte of a message compressed with the dictionary of the channel
+const COMPRESSED: u8 = 1;
+
+/// zstd dictionary shared out of band by both sides of a secure channel.
+///
+/// A dictionary trained on the traffic of a protocol, like the messages managing a node or
+/// the credentials presented to it, makes those small messages much smaller. This is
+/// useful on constrained links, like cellular or satellite links.
+/// The dictionary is only used if both sides of the channel have the same one
+#[derive(Clone, PartialEq, Eq)]
+pub struct CompressionDictionary {
+    id: u32,
+    content: Arc<Vec<u8>>,
+}
+
+impl CompressionDictionary {
+    /// Use a zstd dictionary, for example trained with `zstd --train`.
+    /// Its identifier, negotiated during the handshake, is derived from its content
+    pub fn new(content: Vec<u8>) -> Self {
+        let hash = Sha256::digest(&content);
+        Self {
+            id: u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]),
+            content: Arc::new(content),
+        }
+    }
+
+    /// Train a dictionary of at most `max_size` bytes on some samples of messages
+    #[cfg(feature = "compression")]
+    pub fn train(samples: &[Vec<u8>], max_size: usize) -> Result<Self> {
+        let content = zstd::dict::from_samples(samples, max_size)
+            .map_err(|_| IdentityError::InvalidCompressedMessage)?;
+        Ok(Self::new(content))
+    }
+
+    /// Identifier of the dictionary
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// Content of the dictionary
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
+
+impl Debug for CompressionDictionary {
+    // the content is never displayed
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "CompressionDictionary({:08x})", self.id)
+    }
+}
+
+/// Compress the messages of a channel with a dictionary.
+///
+/// Each message starts with a byte telling if it is compressed. That byte is encrypted along
+/// with the message, so it can't be changed by an attacker.
+///
+/// Only the messages sent to or by some services, like the management API of a node or its
+/// credentials service, are compressed. Compressing the data of a portal would let anyone
+/// injecting data next to a secret in the same stream recover that secret from the length of
+/// the encrypted messages.
+/// Without the `compression` feature the messages are never compressed
+pub(crate) struct MessageCompressor {
+    #[cfg(feature = "compression")]
+    compressor: zstd::bulk::Compressor<'static>,
+    services: Vec<Address>,
+}
+
+impl MessageCompressor {
+    /// Create a compressor for a dictionary and the services whose messages are compressed
+    #[cfg(feature = "compression")]
+    pub(crate) fn new(dictionary: &CompressionDictionary, services: Vec<Address>) -> Result<Self> {
+        let compressor =
+            zstd::bulk::Compressor::with_dictionary(COMPRESSION_LEVEL, dictionary.content())
+                .map_err(|_| IdentityError::InvalidCompressedMessage)?;
+        Ok(Self {
+            compressor,
+            services,
+        })
+    }
+
+    /// Create a compressor for a dictionary and the services whose messages are compressed
+    #[cfg(not(feature = "compression"))]
+    pub(crate) fn new(_dictionary: &CompressionDictionary, services: Vec<Address>) -> Result<Self> {
+        Ok(Self { services })
+    }
+
+    /// Return true if a message is sent to one of the compressed services, or if it is sent
+    /// by one of them, for example a response
+    pub(crate) fn compresses(&self, onward_route: &Route, return_route: &Route) -> bool {
+        [onward_route.next().ok(), return_route.next().ok()]
+            .into_iter()
+            .flatten()
+            .any(|address| self.services.contains(address))
+    }
+
+    /// Compress a message if it is small enough and if the result is actually smaller
+    pub(crate) fn compress(&mut self, message: &[u8]) -> Vec<u8> {
+        match self.compress_message(message) {
+            Some(compressed) if compressed.len() < message.len() => {
+                prefixed(COMPRESSED, &compressed)
+            }
+            _ => prefixed(UNCOMPRESSED, message),
+        }
+    }
+
+    /// Send a message as is
+    pub(crate) fn uncompressed(&self, message: &[u8]) -> Vec<u8> {
+        prefixed(UNCOMPRESSED, message)
+    }
+
+    #[cfg(feature = "compression")]
+    fn compress_message(&mut self, message: &[u8]) -> Option<Vec<u8>> {
+        if message.len() > MAX_COMPRESSED_MESSAGE_SIZE {
+            return None;
+        }
+        self.compressor.compress(message).ok()
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn compress_message(&mut self, _message: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// Decompress the messages sent by a [`MessageCompressor`]
+pub(crate) struct MessageDecompressor {
+    #[cfg(feature = "compression")]
+    decompressor: zstd::bulk::Decompressor<'static>,
+}
+
+impl MessageDecompressor {
+    /// Create a decompressor for a dictionary
+    #[cfg(feature = "compression")]
+    pub(crate) fn new(dictionary: &CompressionDictionary) -> Result<Self> {
+        let decompressor = zstd::bulk::Decompressor::with_dictionary(dictionary.content())
+            .map_err(|_| IdentityError::InvalidCompressedMessage)?;
+        Ok(Self { decompressor })
+    }
+
+    /// Create a decompressor for a dictionary
+    #[cfg(not(feature = "compression"))]
+    pub(crate) fn new(_dictionary: &CompressionDictionary) -> Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Return the original message
+    pub(crate) fn decompress(&mut self, mut message: Vec<u8>) -> Result<Vec<u8>> {
+        match message.first() {
+            Some(&UNCOMPRESSED) => {
+                message.remove(0);
+                Ok(message)
+            }
+            Some(&COMPRESSED) => self.decompress_message(&message[1..]),
+            _ => Err(IdentityError::InvalidCompressedMessage.into()),
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    fn decompress_message(&mut self, compressed: &[u8]) -> Result<Vec<u8>> {
+        // the size of the original message is bounded to protect against decompression bombs
+        self.decompressor
+            .decompress(compressed, MAX_COMPRESSED_MESSAGE_SIZE)
+            .map_err(|_| IdentityError::InvalidCompressedMessage.into())
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn decompress_message(&mut self, _compressed: &[u8]) -> Result<Vec<u8>> {
+        Err(IdentityError::InvalidCompressedMessage.into())
+    }
+}
+
+fn prefixed(prefix: u8, message: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(message.len() + 1);
+    result.push(prefix);
+    result.extend_from_slice(message);
+    result
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compress_with_dictionary() -> Result<()> {
+        let samples: Vec<Vec<u8>> = (0..1000u32)
+            .map(|i| {
+                format!(
+                    r#"{{"method":"GET","path":"/node/secure_channel/{i}","identity":"I{i:064x}"}}"#
+                )
+                .into_bytes()
+            })
+            .collect();
+        let dictionary = CompressionDictionary::train(&samples, 2 * 1024)?;
+        let mut compressor = MessageCompressor::new(&dictionary, vec![])?;
+        let mut decompressor = MessageDecompressor::new(&dictionary)?;
+
+        let message = br#"{"method":"GET","path":"/node/secure_channel/1000","identity":"I0"}"#;
+        let compressed = compressor.compress(message);
+        assert_eq!(compressed[0], COMPRESSED);
+        assert!(compressed.len() < message.len() / 2);
+        assert_eq!(decompressor.decompress(compressed)?, message.to_vec());
+
+        // a message which doesn't compress well is sent as is
+        let hash = Sha256::digest(message).to_vec();
+        let sent = compressor.compress(&hash);
+        assert_eq!(sent[0], UNCOMPRESSED);
+        assert_eq!(decompressor.decompress(sent)?, hash);
+
+        // the other side must use the same dictionary
+        let other = CompressionDictionary::new(vec![0; 1024]);
+        assert_ne!(other.id(), dictionary.id());
+        let mut other_decompressor = MessageDecompressor::new(&other)?;
+        let decompressed = other_decompressor.decompress(compressor.compress(message));
+        assert_ne!(decompressed.ok(), Some(message.to_vec()));
+        Ok(())
+    }
+}
@@ -5,13 +5,16 @@ use ockam_core::{Decodable, LocalMessage};
 use ockam_node::Context;
 
 use crate::models::Identifier;
-use crate::secure_channel::encryptor::{Encryptor, KEY_RENEWAL_INTERVAL};
+use crate::secure_channel::encryptor::{Encryptor, KEY_RENEWAL_INTERVAL, METADATA_FLAG};
 use crate::secure_channel::key_tracker::KeyTracker;
 use crate::secure_channel::nonce_tracker::NonceTracker;
-use crate::secure_channel::Addresses;
-use crate::{DecryptionRequest, DecryptionResponse, IdentityError, IdentitySecureChannelLocalInfo};
+use crate::secure_channel::{Addresses, MessageDecompressor};
+use crate::{
+    DecryptionRequest, DecryptionResponse, IdentityError, IdentitySecureChannelLocalInfo,
+    MessageMetadata,
+};
 
-use ockam_vault::{AeadSecretKeyHandle, VaultForSecureChannels};
+use ockam_vault::{AeadCipher, AeadSecretKeyHandle, VaultForSecureChannels};
 use tracing::{debug, warn};
 
 pub(crate) struct DecryptorHandler {
@@ -27,14 +30,18 @@ impl DecryptorHandler {
         role: &'static str,
         addresses: Addresses,
         key: AeadSecretKeyHandle,
+        cipher: AeadCipher,
         vault: Arc<dyn VaultForSecureChannels>,
         their_identity_id: Identifier,
+        replay_window: u64,
     ) -> Self {
         Self {
             role,
             addresses,
             their_identity_id,
-            decryptor: Decryptor::new(key, vault),
+            decryptor: Decryptor::new(key, vault)
+                .with_cipher(cipher)
+                .with_replay_window(replay_window),
         }
     }
 
@@ -82,7 +89,7 @@ impl DecryptorHandler {
         let payload = Vec::<u8>::decode(&msg.into_transport_message().payload)?;
 
         // Decrypt the binary
-        let decrypted_payload = self.decryptor.decrypt(&payload).await?;
+        let (decrypted_payload, metadata) = self.decryptor.decrypt_with_metadata(&payload).await?;
 
         // Encrypted data should be a TransportMessage
         let mut transport_message = TransportMessage::decode(&decrypted_payload)?;
@@ -95,9 +102,14 @@ impl DecryptorHandler {
 
         // Mark message LocalInfo with IdentitySecureChannelLocalInfo,
         // replacing any pre-existing entries
-        let local_info =
+        let mut local_info =
             IdentitySecureChannelLocalInfo::mark(vec![], self.their_identity_id.clone())?;
 
+        // The authenticated metadata is made available to the receiving worker
+        if let Some(metadata) = metadata {
+            local_info.push(metadata.to_local_info()?);
+        }
+
         let msg = LocalMessage::new(transport_message, local_info);
 
         match ctx
@@ -115,6 +127,12 @@ impl DecryptorHandler {
         }
     }
 
+    /// Decompress the messages once they are decrypted
+    pub(crate) fn with_decompressor(mut self, decompressor: MessageDecompressor) -> Self {
+        self.decryptor = self.decryptor.with_decompressor(decompressor);
+        self
+    }
+
     /// Remove the channel keys on shutdown
     pub(crate) async fn shutdown(&self) -> Result<()> {
         self.decryptor.shutdown().await
@@ -123,19 +141,41 @@ impl DecryptorHandler {
 
 pub(crate) struct Decryptor {
     vault: Arc<dyn VaultForSecureChannels>,
+    cipher: AeadCipher,
     key_tracker: KeyTracker,
     nonce_tracker: NonceTracker,
+    decompressor: Option<MessageDecompressor>,
 }
 
 impl Decryptor {
     pub fn new(key: AeadSecretKeyHandle, vault: Arc<dyn VaultForSecureChannels>) -> Self {
         Self {
             vault,
+            cipher: AeadCipher::default(),
             key_tracker: KeyTracker::new(key, KEY_RENEWAL_INTERVAL),
-            nonce_tracker: NonceTracker::new(),
+            nonce_tracker: NonceTracker::new(KEY_RENEWAL_INTERVAL),
+            decompressor: None,
         }
     }
 
+    /// Set the cipher used with the key, AES-GCM by default
+    pub fn with_cipher(mut self, cipher: AeadCipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Decompress the payloads once they are decrypted
+    pub(crate) fn with_decompressor(mut self, decompressor: MessageDecompressor) -> Self {
+        self.decompressor = Some(decompressor);
+        self
+    }
+
+    /// Only accept the messages arriving at most `replay_window` nonces after a more recent message
+    pub fn with_replay_window(mut self, replay_window: u64) -> Self {
+        self.nonce_tracker = NonceTracker::new(replay_window);
+        self
+    }
+
     /// Restore 12-byte nonce needed for AES GCM from 8 byte that we use for noise
     fn convert_nonce_from_small(b: &[u8]) -> Result<(u64, [u8; 12])> {
         let bytes: [u8; 8] = b.try_into().map_err(|_| IdentityError::InvalidNonce)?;
@@ -146,11 +186,37 @@ impl Decryptor {
     }
 
     pub async fn decrypt(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.decrypt_with_metadata(payload).await?.0)
+    }
+
+    /// Decrypt a payload and return the metadata which was bound to it, if any
+    pub async fn decrypt_with_metadata(
+        &mut self,
+        payload: &[u8],
+    ) -> Result<(Vec<u8>, Option<MessageMetadata>)> {
         if payload.len() < 8 {
             return Err(IdentityError::InvalidNonce.into());
         }
 
-        let (nonce, nonce_buffer) = Self::convert_nonce_from_small(&payload[..8])?;
+        let (nonce, _) = Self::convert_nonce_from_small(&payload[..8])?;
+        let has_metadata = nonce & METADATA_FLAG != 0;
+        let (nonce, associated_data, cipher_text) = if !has_metadata {
+            (nonce, &payload[..0], &payload[8..])
+        } else {
+            if payload.len() < 10 {
+                return Err(IdentityError::InvalidMessageMetadata.into());
+            }
+            let length = u16::from_be_bytes([payload[8], payload[9]]) as usize;
+            if payload.len() < 10 + length {
+                return Err(IdentityError::InvalidMessageMetadata.into());
+            }
+            (
+                nonce & !METADATA_FLAG,
+                &payload[10..10 + length],
+                &payload[10 + length..],
+            )
+        };
+        let nonce_buffer = Encryptor::convert_nonce_from_u64(nonce).1;
         let nonce_tracker = self.nonce_tracker.mark(nonce)?;
 
         // get the key corresponding to the current nonce and
@@ -158,14 +224,14 @@ impl Decryptor {
         let key = if let Some(key) = self.key_tracker.get_key(nonce)? {
             key
         } else {
-            Encryptor::rekey(&self.vault, &self.key_tracker.current_key).await?
+            Encryptor::rekey(&self.vault, &self.key_tracker.current_key, self.cipher).await?
         };
 
         // to improve protection against connection disruption attacks, we want to validate the
         // message with a decryption _before_ committing to the new state
         let result = self
             .vault
-            .aead_decrypt(&key, &payload[8..], &nonce_buffer, &[])
+            .aead_decrypt(&key, cipher_text, &nonce_buffer, associated_data)
             .await;
 
         if result.is_ok() {
@@ -174,7 +240,18 @@ impl Decryptor {
                 self.vault.delete_aead_secret_key(key_to_delete).await?;
             }
         }
-        result
+        let payload = match &mut self.decompressor {
+            Some(decompressor) => decompressor.decompress(result?)?,
+            None => result?,
+        };
+
+        // the metadata is only decoded once it is authenticated
+        let metadata = if has_metadata {
+            Some(MessageMetadata::from_associated_data(associated_data)?)
+        } else {
+            None
+        };
+        Ok((payload, metadata))
     }
 
     /// Remove the channel keys on shutdown
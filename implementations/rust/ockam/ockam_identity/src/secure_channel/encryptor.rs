@@ -1,15 +1,18 @@
 use ockam_core::compat::sync::Arc;
 use ockam_core::compat::vec::Vec;
 use ockam_core::errcode::{Kind, Origin};
-use ockam_core::{Error, Result};
-use ockam_vault::{AeadSecretKeyHandle, VaultForSecureChannels};
+use ockam_core::{Error, Result, Route};
+use ockam_vault::{AeadCipher, AeadSecretKeyHandle, VaultForSecureChannels};
 
-use crate::IdentityError;
+use crate::secure_channel::MessageCompressor;
+use crate::{IdentityError, MessageMetadata};
 
 pub(crate) struct Encryptor {
     key: AeadSecretKeyHandle,
     nonce: u64,
     vault: Arc<dyn VaultForSecureChannels>,
+    cipher: AeadCipher,
+    compressor: Option<MessageCompressor>,
 }
 
 // To simplify the implementation we use the same constant for the size of the message
@@ -17,6 +20,10 @@ pub(crate) struct Encryptor {
 // This means we only need to keep the current key and the previous one.
 pub(crate) const KEY_RENEWAL_INTERVAL: u64 = 32;
 
+/// Flag set on the nonce of the messages carrying authenticated metadata.
+/// Those messages are sent as: nonce (8 bytes) || metadata length (2 bytes) || metadata || ciphertext
+pub(crate) const METADATA_FLAG: u64 = 1 << 63;
+
 impl Encryptor {
     /// We use u64 nonce since it's convenient to work with it (e.g. increment)
     /// But we use 8-byte be format to send it over to the other side (according to noise spec)
@@ -30,9 +37,11 @@ impl Encryptor {
         (b, n)
     }
 
+    /// Derive the next key, which is used with the same cipher as the current key
     pub async fn rekey(
         vault: &Arc<dyn VaultForSecureChannels>,
         key: &AeadSecretKeyHandle,
+        cipher: AeadCipher,
     ) -> Result<AeadSecretKeyHandle> {
         let nonce_buffer = Self::convert_nonce_from_u64(u64::MAX).1;
         let zeroes = [0u8; 32];
@@ -43,32 +52,90 @@ impl Encryptor {
             .import_secret_buffer(new_key_buffer[0..32].to_vec())
             .await?;
 
-        vault.convert_secret_buffer_to_aead_key(buffer).await
+        vault
+            .convert_secret_buffer_to_aead_key_for_cipher(buffer, cipher)
+            .await
     }
 
     pub async fn encrypt(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_with_metadata(payload, None).await
+    }
+
+    /// Encrypt a payload and bind some metadata to it, sent unencrypted as associated data
+    pub async fn encrypt_with_metadata(
+        &mut self,
+        payload: &[u8],
+        metadata: Option<&MessageMetadata>,
+    ) -> Result<Vec<u8>> {
+        self.encrypt_message(payload, metadata, false).await
+    }
+
+    /// Encrypt a payload with some metadata, and compress it first if `compress` is true and
+    /// the channel uses compression
+    pub(crate) async fn encrypt_message(
+        &mut self,
+        payload: &[u8],
+        metadata: Option<&MessageMetadata>,
+        compress: bool,
+    ) -> Result<Vec<u8>> {
+        let associated_data = match metadata {
+            Some(metadata) => Some(metadata.to_associated_data()?),
+            None => None,
+        };
+
         let current_nonce = self.nonce;
-        if current_nonce == u64::MAX {
+        // the highest bit of the nonce is used to flag the messages with metadata
+        if current_nonce >= METADATA_FLAG {
             return Err(IdentityError::NonceOverflow.into());
         }
 
         self.nonce += 1;
 
         if current_nonce > 0 && current_nonce % KEY_RENEWAL_INTERVAL == 0 {
-            let new_key = Self::rekey(&self.vault, &self.key).await?;
+            let new_key = Self::rekey(&self.vault, &self.key, self.cipher).await?;
             let old_key = core::mem::replace(&mut self.key, new_key);
             self.vault.delete_aead_secret_key(old_key).await?;
         }
 
         let (small_nonce, nonce) = Self::convert_nonce_from_u64(current_nonce);
 
+        // once a channel uses compression every message starts with a compression flag
+        let compressed;
+        let payload = match &mut self.compressor {
+            Some(compressor) => {
+                compressed = if compress {
+                    compressor.compress(payload)
+                } else {
+                    compressor.uncompressed(payload)
+                };
+                &compressed
+            }
+            None => payload,
+        };
+
         let mut cipher_text = self
             .vault
-            .aead_encrypt(&self.key, payload, &nonce, &[])
+            .aead_encrypt(
+                &self.key,
+                payload,
+                &nonce,
+                associated_data.as_deref().unwrap_or(&[]),
+            )
             .await?;
 
-        let mut res = Vec::new();
-        res.extend_from_slice(&small_nonce);
+        let header_length = match &associated_data {
+            Some(associated_data) => 10 + associated_data.len(),
+            None => 8,
+        };
+        let mut res = Vec::with_capacity(header_length + cipher_text.len());
+        match associated_data {
+            Some(associated_data) => {
+                res.extend_from_slice(&(current_nonce | METADATA_FLAG).to_be_bytes());
+                res.extend_from_slice(&(associated_data.len() as u16).to_be_bytes());
+                res.extend_from_slice(&associated_data);
+            }
+            None => res.extend_from_slice(&small_nonce),
+        }
         res.append(&mut cipher_text);
 
         Ok(res)
@@ -79,7 +146,33 @@ impl Encryptor {
         nonce: u64,
         vault: Arc<dyn VaultForSecureChannels>,
     ) -> Self {
-        Self { key, nonce, vault }
+        Self {
+            key,
+            nonce,
+            vault,
+            cipher: AeadCipher::default(),
+            compressor: None,
+        }
+    }
+
+    /// Set the cipher used with the key, AES-GCM by default
+    pub fn with_cipher(mut self, cipher: AeadCipher) -> Self {
+        self.cipher = cipher;
+        self
+    }
+
+    /// Compress the payloads before encrypting them
+    pub(crate) fn with_compressor(mut self, compressor: MessageCompressor) -> Self {
+        self.compressor = Some(compressor);
+        self
+    }
+
+    /// Return true if a message with these routes is compressed
+    pub(crate) fn compresses(&self, onward_route: &Route, return_route: &Route) -> bool {
+        self.compressor
+            .as_ref()
+            .map(|c| c.compresses(onward_route, return_route))
+            .unwrap_or(false)
     }
 
     pub(crate) async fn shutdown(&self) -> Result<()> {
@@ -7,7 +7,7 @@ use tracing::debug;
 use crate::secure_channel::addresses::Addresses;
 use crate::secure_channel::api::{EncryptionRequest, EncryptionResponse};
 use crate::secure_channel::encryptor::Encryptor;
-use crate::IdentityError;
+use crate::{IdentityError, MessageMetadata};
 
 pub(crate) struct EncryptorWorker {
     //for debug purposes only
@@ -78,6 +78,12 @@ impl EncryptorWorker {
         // Remove our address
         let _ = onward_route.step();
 
+        // Metadata attached by the sender is bound to the encrypted message
+        let metadata = MessageMetadata::find_info(msg.local_message()).ok();
+
+        // only the messages of some services are compressed, never the data of a portal
+        let compress = self.encryptor.compresses(&onward_route, &return_route);
+
         let msg = TransportMessage::v1(
             onward_route,
             return_route,
@@ -85,7 +91,10 @@ impl EncryptorWorker {
         );
 
         // Encrypt the message
-        let encrypted_payload = self.encryptor.encrypt(&msg.encode()?).await?;
+        let encrypted_payload = self
+            .encryptor
+            .encrypt_message(&msg.encode()?, metadata.as_ref(), compress)
+            .await?;
 
         // Send the message to the decryptor on the other side
         ctx.send_from_address(
@@ -14,6 +14,12 @@ pub enum XXError {
     MessageLenMismatch,
     /// Invalid internal state.
     InvalidInternalState,
+    /// None of the cipher suites offered by the initiator is accepted.
+    NoCommonCipherSuite,
+    /// The cipher suite chosen by the responder was not offered.
+    UnexpectedCipherSuite,
+    /// The compression dictionary chosen by the responder was not offered.
+    UnexpectedCompressionDictionary,
 }
 
 impl StdError for XXError {}
@@ -24,6 +30,11 @@ impl fmt::Display for XXError {
             Self::InternalVaultError => write!(f, "internal vault error"),
             Self::MessageLenMismatch => write!(f, "message length mismatch"),
             Self::InvalidInternalState => write!(f, "invalid internal state"),
+            Self::NoCommonCipherSuite => write!(f, "no common cipher suite"),
+            Self::UnexpectedCipherSuite => write!(f, "unexpected cipher suite"),
+            Self::UnexpectedCompressionDictionary => {
+                write!(f, "unexpected compression dictionary")
+            }
         }
     }
 }
@@ -35,6 +46,9 @@ impl From<XXError> for Error {
             XXError::InternalVaultError => Kind::Internal,
             XXError::MessageLenMismatch => Kind::Misuse,
             XXError::InvalidInternalState => Kind::Internal,
+            XXError::NoCommonCipherSuite => Kind::Unsupported,
+            XXError::UnexpectedCipherSuite => Kind::Invalid,
+            XXError::UnexpectedCompressionDictionary => Kind::Invalid,
         };
 
         Error::new(Origin::KeyExchange, kind, err)
@@ -1,6 +1,7 @@
 use cfg_if::cfg_if;
+use minicbor::{Decode, Encode};
 use ockam_core::compat::sync::Arc;
-use ockam_core::compat::vec::Vec;
+use ockam_core::compat::vec::{vec, Vec};
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{Error, Result};
 use ockam_vault::{
@@ -12,7 +13,10 @@ use Status::*;
 
 use crate::secure_channel::handshake::error::XXError;
 use crate::secure_channel::handshake::handshake_state_machine::{HandshakeKeys, Status};
-use crate::secure_channel::Role;
+use crate::secure_channel::{
+    CipherSuite, CompressionDictionary, HandshakeConfig, MessageCompressor, MessageDecompressor,
+    Role,
+};
 
 /// The number of bytes in a SHA256 digest
 pub const SHA256_SIZE: usize = 32;
@@ -26,6 +30,18 @@ pub const AES_GCM_TAGSIZE: usize = 16;
 pub(super) struct Handshake {
     vault: Arc<dyn VaultForSecureChannels>,
     protocol_name: [u8; 32],
+    prologue: Vec<u8>,
+    psk: Option<[u8; 32]>,
+    /// cipher suites accepted for the channel, in order of preference
+    cipher_suites: Vec<CipherSuite>,
+    /// cipher suite used for the channel keys, known after message 1 for the responder
+    /// and after message 2 for the initiator
+    cipher_suite: CipherSuite,
+    /// dictionary which can be used to compress the messages
+    compression_dictionary: Option<CompressionDictionary>,
+    /// dictionary used to compress the payload of message 3 and the messages of the channel,
+    /// if both sides have the same one
+    compression: Option<CompressionDictionary>,
     pub(super) state: HandshakeState,
 }
 
@@ -40,7 +56,8 @@ impl Handshake {
         state.k = None;
         state.ck = Some(self.import_ck_secret(self.protocol_name().to_vec()).await?);
 
-        state.h = HandshakeState::sha256(&state.h);
+        // h = SHA256(h || prologue)
+        state.mix_hash(&self.prologue);
         self.state = state;
         Ok(())
     }
@@ -156,6 +173,9 @@ impl Handshake {
         let dh = self.dh(state.s()?, state.re()?).await?;
         self.hkdf(&mut state, dh).await?;
 
+        // ck, k = HKDF(ck, psk, 2)
+        self.mix_psk(&mut state).await?;
+
         // encrypt payload
         let c = self.encrypt_and_hash(&mut state, payload).await?;
         message3.extend(c);
@@ -181,6 +201,9 @@ impl Handshake {
         let dh = self.dh(state.e()?, state.rs()?).await?;
         self.hkdf(&mut state, dh).await?;
 
+        // ck, k = HKDF(ck, psk, 2)
+        self.mix_psk(&mut state).await?;
+
         // decrypt payload
         let c = Self::read_message3_payload(message)?;
         let payload = self.hash_and_decrypt(&mut state, c).await?;
@@ -202,6 +225,8 @@ impl Handshake {
         state.status = Ready(HandshakeKeys {
             encryption_key,
             decryption_key,
+            cipher: self.cipher_suite.aead_cipher(),
+            compression: self.compression.clone(),
         });
         // now remove the ephemeral keys which are not useful anymore
         self.state = state;
@@ -209,6 +234,104 @@ impl Handshake {
         Ok(())
     }
 
+    /// Payload of message 1, offering the cipher suites and the compression dictionary of
+    /// the initiator.
+    /// The payload stays empty if only the default suite is accepted and there is no
+    /// dictionary, like for the nodes which don't negotiate the channel parameters
+    pub(super) fn offer(&self) -> Result<Vec<u8>> {
+        let offer = HandshakeOffer {
+            cipher_suites: if self.cipher_suites == [CipherSuite::default()] {
+                vec![]
+            } else {
+                self.cipher_suites.iter().map(|s| s.code()).collect()
+            },
+            compression_dictionary: self.compression_dictionary.as_ref().map(|d| d.id()),
+        };
+        if offer.cipher_suites.is_empty() && offer.compression_dictionary.is_none() {
+            return Ok(vec![]);
+        }
+        Ok(minicbor::to_vec(offer)?)
+    }
+
+    /// Choose the cipher suite of the channel on the responder side: the first suite offered by
+    /// the initiator in message 1 which is also accepted by the responder.
+    /// Return None if the initiator didn't offer any suite, then the default suite is used
+    pub(super) fn choose_cipher_suite(
+        &mut self,
+        offer: &HandshakeOffer,
+    ) -> Result<Option<CipherSuite>> {
+        if offer.cipher_suites.is_empty() {
+            self.use_cipher_suite(CipherSuite::default())?;
+            return Ok(None);
+        }
+        let chosen = offer
+            .cipher_suites
+            .iter()
+            .copied()
+            .filter_map(CipherSuite::from_code)
+            .find(|suite| self.cipher_suites.contains(suite))
+            .ok_or(XXError::NoCommonCipherSuite)?;
+        self.use_cipher_suite(chosen)?;
+        Ok(Some(chosen))
+    }
+
+    /// Check the cipher suite chosen by the responder on the initiator side.
+    /// A responder which doesn't negotiate the cipher suite uses the default one
+    pub(super) fn accept_cipher_suite(&mut self, chosen: Option<u8>) -> Result<()> {
+        let suite = match chosen {
+            Some(code) => CipherSuite::from_code(code).ok_or(XXError::UnexpectedCipherSuite)?,
+            None => CipherSuite::default(),
+        };
+        self.use_cipher_suite(suite)
+    }
+
+    /// Use the compression dictionary offered by the initiator on the responder side, if the
+    /// responder has the same one. Return the identifier of the dictionary if it is used
+    pub(super) fn choose_compression(&mut self, offer: &HandshakeOffer) -> Option<u32> {
+        self.compression = self
+            .compression_dictionary
+            .clone()
+            .filter(|d| Some(d.id()) == offer.compression_dictionary);
+        self.compression.as_ref().map(|d| d.id())
+    }
+
+    /// Check the compression dictionary chosen by the responder on the initiator side.
+    /// A responder which doesn't have the same dictionary doesn't compress the messages
+    pub(super) fn accept_compression(&mut self, chosen: Option<u32>) -> Result<()> {
+        self.compression = match (chosen, &self.compression_dictionary) {
+            (None, _) => None,
+            (Some(id), Some(dictionary)) if id == dictionary.id() => Some(dictionary.clone()),
+            (Some(_), _) => return Err(XXError::UnexpectedCompressionDictionary.into()),
+        };
+        Ok(())
+    }
+
+    /// Compress the payload of message 3, with the identity and the credentials of the
+    /// initiator, if the channel uses compression
+    pub(super) fn compress_payload(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.compression {
+            Some(dictionary) => Ok(MessageCompressor::new(dictionary, vec![])?.compress(&payload)),
+            None => Ok(payload),
+        }
+    }
+
+    /// Decompress the payload of message 3 if the channel uses compression
+    pub(super) fn decompress_payload(&self, payload: Vec<u8>) -> Result<Vec<u8>> {
+        match &self.compression {
+            Some(dictionary) => MessageDecompressor::new(dictionary)?.decompress(payload),
+            None => Ok(payload),
+        }
+    }
+
+    /// Use a cipher suite for the channel if it is accepted
+    fn use_cipher_suite(&mut self, suite: CipherSuite) -> Result<()> {
+        if !self.cipher_suites.contains(&suite) {
+            return Err(XXError::UnexpectedCipherSuite.into());
+        }
+        self.cipher_suite = suite;
+        Ok(())
+    }
+
     /// Return the final results of the handshake if we reached the final state
     pub(super) fn get_handshake_keys(&self) -> Option<HandshakeKeys> {
         match &self.state.status {
@@ -223,15 +346,21 @@ impl Handshake {
     pub(super) async fn new(
         vault: Arc<dyn VaultForSecureChannels>,
         static_key: X25519SecretKeyHandle,
+        config: HandshakeConfig,
     ) -> Result<Handshake> {
         // 1. generate an ephemeral key pair for this handshake and set it to e
         let ephemeral_key = Self::generate_ephemeral_key(vault.clone()).await?;
 
         // 2. initialize the handshake
-        // We currently don't use any payload for message 1
         Ok(Handshake {
             vault,
-            protocol_name: *PROTOCOL_NAME,
+            protocol_name: config.pattern.protocol_name(),
+            psk: config.pattern.psk().cloned(),
+            prologue: config.prologue,
+            cipher_suites: config.cipher_suites,
+            cipher_suite: CipherSuite::default(),
+            compression_dictionary: config.compression_dictionary,
+            compression: None,
             state: HandshakeState::new(static_key, ephemeral_key),
         })
     }
@@ -288,6 +417,15 @@ impl Handshake {
         //_ => ,
     }
 
+    /// Mix the pre-shared key, if there is one, in the chaining key like a Diffie-Hellman key
+    async fn mix_psk(&self, state: &mut HandshakeState) -> Result<()> {
+        if let Some(psk) = &self.psk {
+            let psk = self.vault.import_secret_buffer(psk.to_vec()).await?;
+            self.hkdf(state, psk).await?;
+        }
+        Ok(())
+    }
+
     /// Compute the final encryption and decryption keys
     async fn compute_final_keys(
         &self,
@@ -304,8 +442,16 @@ impl Handshake {
             .try_into()
             .map_err(|_| XXError::InternalVaultError)?;
 
-        let k1 = self.vault.convert_secret_buffer_to_aead_key(k1).await?;
-        let k2 = self.vault.convert_secret_buffer_to_aead_key(k2).await?;
+        // the channel keys are used with the negotiated cipher
+        let cipher = self.cipher_suite.aead_cipher();
+        let k1 = self
+            .vault
+            .convert_secret_buffer_to_aead_key_for_cipher(k1, cipher)
+            .await?;
+        let k2 = self
+            .vault
+            .convert_secret_buffer_to_aead_key_for_cipher(k2, cipher)
+            .await?;
 
         self.vault.delete_secret_buffer(state.take_ck()?).await?;
         self.vault.delete_aead_secret_key(state.take_k()?).await?;
@@ -432,6 +578,27 @@ impl Handshake {
     }
 }
 
+/// Payload of message 1, with the parameters of the channel offered by the initiator
+#[derive(Debug, Clone, Default, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub(super) struct HandshakeOffer {
+    /// Codes of the cipher suites accepted by the initiator, in order of preference
+    #[n(1)] pub(super) cipher_suites: Vec<u8>,
+    /// Identifier of the dictionary which can be used to compress the messages
+    #[n(2)] pub(super) compression_dictionary: Option<u32>,
+}
+
+impl HandshakeOffer {
+    /// Decode the payload of message 1, which is empty if the initiator doesn't offer anything
+    pub(super) fn from_payload(message1_payload: &[u8]) -> Result<HandshakeOffer> {
+        if message1_payload.is_empty() {
+            return Ok(HandshakeOffer::default());
+        }
+        Ok(minicbor::decode(message1_payload)?)
+    }
+}
+
 /// The `HandshakeState` contains all the variables necessary to follow the Noise protocol
 #[derive(Debug, Clone)]
 pub(super) struct HandshakeState {
@@ -572,10 +739,12 @@ impl HandshakeState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::secure_channel::CipherSuite::*;
+    use crate::secure_channel::HandshakePattern;
     use hex::decode;
     use ockam_core::Result;
     use ockam_node::InMemoryKeyValueStorage;
-    use ockam_vault::{SoftwareVaultForSecureChannels, X25519SecretKey};
+    use ockam_vault::{AeadCipher, SoftwareVaultForSecureChannels, X25519SecretKey};
 
     #[tokio::test]
     async fn test_initialization() -> Result<()> {
@@ -644,10 +813,165 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_handshake_config() -> Result<()> {
+        let psk = HandshakePattern::XxPsk([7u8; 32]);
+        let config = HandshakeConfig::default()
+            .with_pattern(psk.clone())
+            .with_prologue(b"context".to_vec());
+
+        // the handshake succeeds with the same pattern and prologue
+        assert!(run_handshake(config.clone(), config.clone()).await.is_ok());
+        assert!(run_handshake(
+            HandshakeConfig::default().with_prologue(b"context".to_vec()),
+            HandshakeConfig::default().with_prologue(b"context".to_vec())
+        )
+        .await
+        .is_ok());
+
+        // and fails otherwise
+        assert!(run_handshake(
+            config.clone(),
+            config.clone().with_prologue(b"other context".to_vec())
+        )
+        .await
+        .is_err());
+        assert!(run_handshake(
+            config.clone(),
+            config
+                .clone()
+                .with_pattern(HandshakePattern::XxPsk([8u8; 32]))
+        )
+        .await
+        .is_err());
+        assert!(run_handshake(
+            config,
+            HandshakeConfig::default().with_prologue(b"context".to_vec())
+        )
+        .await
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cipher_suites_negotiation() -> Result<()> {
+        let aes = HandshakeConfig::default().with_cipher_suites(vec![Aes256GcmSha256]);
+        let chacha = HandshakeConfig::default().with_cipher_suites(vec![ChaCha20Poly1305Sha256]);
+        let both =
+            |first, second| HandshakeConfig::default().with_cipher_suites(vec![first, second]);
+
+        // the first suite offered by the initiator and accepted by the responder is used
+        assert_eq!(
+            run_handshake(
+                both(ChaCha20Poly1305Sha256, Aes256GcmSha256),
+                both(Aes256GcmSha256, ChaCha20Poly1305Sha256)
+            )
+            .await?
+            .cipher,
+            AeadCipher::ChaCha20Poly1305
+        );
+        assert_eq!(
+            run_handshake(
+                both(Aes256GcmSha256, ChaCha20Poly1305Sha256),
+                chacha.clone()
+            )
+            .await?
+            .cipher,
+            AeadCipher::ChaCha20Poly1305
+        );
+        assert_eq!(
+            run_handshake(chacha.clone(), chacha.clone()).await?.cipher,
+            AeadCipher::ChaCha20Poly1305
+        );
+
+        // nothing is offered when only AES-GCM is accepted, like with older initiators
+        assert_eq!(
+            run_handshake(aes.clone(), HandshakeConfig::default())
+                .await?
+                .cipher,
+            AeadCipher::Aes256Gcm
+        );
+
+        // the handshake fails if there is no common suite
+        assert!(run_handshake(aes.clone(), chacha.clone()).await.is_err());
+        assert!(run_handshake(chacha, aes).await.is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compression_negotiation() -> Result<()> {
+        let dictionary = CompressionDictionary::new(b"initiator responder".repeat(10));
+        let other = CompressionDictionary::new(b"other dictionary".repeat(10));
+        let with_dictionary = |dictionary: &CompressionDictionary| {
+            HandshakeConfig::default().with_compression_dictionary(dictionary.clone(), vec![])
+        };
+
+        // the messages are compressed if both sides have the same dictionary
+        let keys =
+            run_handshake(with_dictionary(&dictionary), with_dictionary(&dictionary)).await?;
+        assert_eq!(keys.compression, Some(dictionary.clone()));
+
+        // otherwise they are sent as is
+        let keys = run_handshake(with_dictionary(&dictionary), with_dictionary(&other)).await?;
+        assert_eq!(keys.compression, None);
+        let keys = run_handshake(with_dictionary(&dictionary), HandshakeConfig::default()).await?;
+        assert_eq!(keys.compression, None);
+        let keys = run_handshake(HandshakeConfig::default(), with_dictionary(&dictionary)).await?;
+        assert_eq!(keys.compression, None);
+        Ok(())
+    }
+
     // --------------------
     // TESTS IMPLEMENTATION
     // --------------------
 
+    /// Run a full handshake between an initiator and a responder
+    /// and return the keys of the initiator
+    async fn run_handshake(
+        initiator_config: HandshakeConfig,
+        responder_config: HandshakeConfig,
+    ) -> Result<HandshakeKeys> {
+        let vault = SoftwareVaultForSecureChannels::create();
+        let initiator_key = vault.generate_static_x25519_secret_key().await?;
+        let responder_key = vault.generate_static_x25519_secret_key().await?;
+        let mut initiator = Handshake::new(vault.clone(), initiator_key, initiator_config).await?;
+        let mut responder = Handshake::new(vault.clone(), responder_key, responder_config).await?;
+        initiator.initialize().await?;
+        responder.initialize().await?;
+
+        let offer = initiator.offer()?;
+        let message1 = initiator.encode_message1(&offer).await?;
+        let offer = HandshakeOffer::from_payload(&responder.decode_message1(&message1).await?)?;
+        let chosen = responder.choose_cipher_suite(&offer)?;
+        let compression = responder.choose_compression(&offer);
+        let message2 = responder.encode_message2(b"responder").await?;
+        initiator.decode_message2(&message2).await?;
+        initiator.accept_cipher_suite(chosen.map(|suite| suite.code()))?;
+        initiator.accept_compression(compression)?;
+        let payload = initiator.compress_payload(b"initiator".to_vec())?;
+        let message3 = initiator.encode_message3(&payload).await?;
+        let payload = responder.decode_message3(&message3).await?;
+        assert_eq!(responder.decompress_payload(payload)?, b"initiator");
+        initiator.set_final_state(Role::Initiator).await?;
+        responder.set_final_state(Role::Responder).await?;
+
+        // both sides use the same keys with the same cipher
+        let initiator_keys = initiator.get_handshake_keys().unwrap();
+        let responder_keys = responder.get_handshake_keys().unwrap();
+        assert_eq!(initiator_keys.cipher, responder_keys.cipher);
+        assert_eq!(initiator_keys.compression, responder_keys.compression);
+        let nonce = [0u8; 12];
+        let cipher_text = vault
+            .aead_encrypt(&initiator_keys.encryption_key, b"hello", &nonce, &[])
+            .await?;
+        let plain_text = vault
+            .aead_decrypt(&responder_keys.decryption_key, &cipher_text, &nonce, &[])
+            .await?;
+        assert_eq!(plain_text, b"hello");
+        Ok(initiator_keys)
+    }
+
     struct HandshakeMessages {
         initiator_static_key: X25519SecretKey,
         initiator_ephemeral_key: X25519SecretKey,
@@ -736,6 +1060,12 @@ mod tests {
             Ok(Handshake {
                 vault,
                 protocol_name,
+                prologue: vec![],
+                psk: None,
+                cipher_suites: vec![CipherSuite::default()],
+                cipher_suite: CipherSuite::default(),
+                compression_dictionary: None,
+                compression: None,
                 state: HandshakeState::new(static_key, ephemeral_key),
             })
         }
@@ -754,6 +1084,12 @@ mod tests {
             Ok(Handshake {
                 vault,
                 protocol_name,
+                prologue: vec![],
+                psk: None,
+                cipher_suites: vec![CipherSuite::default()],
+                cipher_suite: CipherSuite::default(),
+                compression_dictionary: None,
+                compression: None,
                 state: HandshakeState::new(static_key, ephemeral_key),
             })
         }
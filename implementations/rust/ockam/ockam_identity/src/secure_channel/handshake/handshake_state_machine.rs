@@ -3,14 +3,17 @@ use ockam_core::compat::string::ToString;
 use ockam_core::compat::sync::Arc;
 use ockam_core::compat::{boxed::Box, vec::Vec};
 use ockam_core::{async_trait, Result};
-use ockam_vault::{AeadSecretKeyHandle, X25519PublicKey};
+use ockam_vault::{AeadCipher, AeadSecretKeyHandle, X25519PublicKey};
 use tracing::{debug, warn};
 
 use crate::models::{
-    ChangeHistory, CredentialAndPurposeKey, Identifier, PurposeKeyAttestation, PurposePublicKey,
+    ChangeHistory, CredentialAndPurposeKey, HardwareAttestationKind, Identifier,
+    PurposeKeyAttestation, PurposePublicKey,
 };
 use crate::{
-    Identities, Identity, IdentityError, SecureChannelTrustInfo, TrustContext, TrustPolicy,
+    AttributesEntry, CompressionDictionary, Identities, Identity, IdentityError,
+    SecureChannelAdmission, SecureChannelTrustInfo, TrustContext, TrustPolicy,
+    HARDWARE_KEY_ATTRIBUTE,
 };
 
 /// Interface for a state machine in a key exchange protocol
@@ -45,11 +48,15 @@ pub(super) enum Status {
     Ready(HandshakeKeys),
 }
 
-/// At the end of a successful handshake a pair of encryption/decryption keys is available
+/// At the end of a successful handshake a pair of encryption/decryption keys is available,
+/// along with the negotiated cipher to use with them and the dictionary compressing the
+/// messages, if any
 #[derive(Debug, Clone)]
 pub(super) struct HandshakeKeys {
     pub(super) encryption_key: AeadSecretKeyHandle,
     pub(super) decryption_key: AeadSecretKeyHandle,
+    pub(super) cipher: AeadCipher,
+    pub(super) compression: Option<CompressionDictionary>,
 }
 
 /// The end result of a handshake with identity/credentials exchange is
@@ -68,6 +75,7 @@ pub(super) struct CommonStateMachine {
     pub(super) credentials: Vec<CredentialAndPurposeKey>,
     pub(super) trust_policy: Arc<dyn TrustPolicy>,
     pub(super) trust_context: Option<TrustContext>,
+    pub(super) admission: SecureChannelAdmission,
     their_identifier: Option<Identifier>,
 }
 
@@ -87,30 +95,32 @@ impl CommonStateMachine {
             credentials,
             trust_policy,
             trust_context,
+            admission: SecureChannelAdmission::default(),
             their_identifier: None,
         }
     }
 
-    /// Prepare a payload containing the identity of the current party and serialize it.
+    /// Prepare a payload containing the identity of the current party.
     /// That payload contains:
     ///
     ///  - the current Identity Change History
     ///  - the current Secure Channel Purpose Key Attestation
     ///  - the Identity Credentials and corresponding Credentials Purpose Key Attestations
     ///
-    pub(super) async fn make_identity_payload(&self) -> Result<Vec<u8>> {
+    pub(super) async fn make_identity_payload(&self) -> Result<IdentityAndCredentials> {
         // prepare the payload that will be sent either in message 2 or message 3
         let change_history = self
             .identities
             .repository()
             .get_identity(&self.identifier)
             .await?;
-        let payload = IdentityAndCredentials {
+        Ok(IdentityAndCredentials {
             change_history,
             purpose_key_attestation: self.purpose_key_attestation.clone(),
             credentials: self.credentials.clone(),
-        };
-        Ok(minicbor::to_vec(payload)?)
+            cipher_suite: None,
+            compression_dictionary: None,
+        })
     }
 
     /// Verify the identity sent by the other party: the Purpose Key and the credentials must be valid
@@ -133,15 +143,14 @@ impl CommonStateMachine {
             .update_identity(&identity)
             .await?;
 
-        let purpose_key = self
-            .identities
-            .purpose_keys()
-            .purpose_keys_verification()
+        let purpose_keys_verification = self.identities.purpose_keys().purpose_keys_verification();
+        let purpose_key = purpose_keys_verification
             .verify_purpose_key_attestation(
                 Some(identity.identifier()),
                 &peer.purpose_key_attestation,
             )
             .await?;
+        let hardware_key = purpose_keys_verification.hardware_attested(&purpose_key);
 
         match &purpose_key.public_key {
             PurposePublicKey::SecureChannelStatic(public_key) => {
@@ -154,18 +163,19 @@ impl CommonStateMachine {
             }
         }
 
-        self.verify_credentials(identity.identifier(), peer.credentials)
+        self.verify_credentials(identity.identifier(), peer.credentials, hardware_key)
             .await?;
         self.their_identifier = Some(identity.identifier().clone());
         Ok(())
     }
 
     /// Verify that the credentials sent by the other party are valid using a trust context
-    /// and store them
+    /// and store them, along with the kind of hardware keeping its Purpose Key
     async fn verify_credentials(
         &self,
         their_identifier: &Identifier,
         credentials: Vec<CredentialAndPurposeKey>,
+        hardware_key: Option<HardwareAttestationKind>,
     ) -> Result<()> {
         // check our TrustPolicy
         let trust_info = SecureChannelTrustInfo::new(their_identifier.clone());
@@ -185,13 +195,10 @@ impl CommonStateMachine {
                 credentials.len()
             );
             for credential in &credentials {
-                let result = self
-                    .identities
-                    .credentials()
-                    .credentials_verification()
+                let result = trust_context
                     .receive_presented_credential(
+                        &self.identities.credentials().credentials_verification(),
                         their_identifier,
-                        &[trust_context.authority()?.identifier().clone()],
                         credential,
                     )
                     .await;
@@ -210,9 +217,70 @@ impl CommonStateMachine {
             return Err(IdentityError::SecureChannelVerificationFailedMissingTrustContext.into());
         };
 
+        self.record_hardware_key(their_identifier, hardware_key)
+            .await?;
+
+        // check that the other party can be admitted, now that its credentials have been stored
+        if !self.admission.is_open() {
+            let attributes_reader = self.identities.repository().as_attributes_reader();
+            if !self
+                .admission
+                .check(their_identifier, attributes_reader.as_ref())
+                .await?
+            {
+                return Err(IdentityError::SecureChannelAdmissionRejected.into());
+            }
+        }
+
         Ok(())
     }
 
+    /// Record the kind of hardware keeping the Purpose Key of the other party, or remove it if
+    /// its Purpose Key is not attested by hardware.
+    ///
+    /// The hardware key attribute is only added to the attributes attested by an authority of
+    /// the trust context. No attributes are ever created for an identity which doesn't have some
+    async fn record_hardware_key(
+        &self,
+        their_identifier: &Identifier,
+        hardware_key: Option<HardwareAttestationKind>,
+    ) -> Result<()> {
+        let repository = self.identities.repository();
+        repository
+            .set_hardware_key(their_identifier, hardware_key)
+            .await?;
+        debug!(
+            "the hardware key of {} is {:?}",
+            their_identifier, hardware_key
+        );
+
+        let authorities = match &self.trust_context {
+            Some(trust_context) => trust_context.authorities().await.unwrap_or_default(),
+            None => return Ok(()),
+        };
+        let entry = match repository.get_attributes(their_identifier).await? {
+            Some(entry) => match entry.attested_by() {
+                Some(attested_by) if authorities.contains(&attested_by) => entry,
+                _ => return Ok(()),
+            },
+            None => return Ok(()),
+        };
+
+        let name = HARDWARE_KEY_ATTRIBUTE.as_bytes().to_vec();
+        let mut attrs = entry.attrs().clone();
+        match hardware_key {
+            Some(kind) => attrs.insert(name, kind.as_str().as_bytes().to_vec()),
+            None => attrs.remove(&name),
+        };
+        if &attrs == entry.attrs() {
+            return Ok(());
+        }
+        let annotated =
+            AttributesEntry::new(attrs, entry.added(), entry.expires(), entry.attested_by())
+                .with_audience(entry.audience().map(|audience| audience.to_vec()));
+        repository.put_attributes(their_identifier, annotated).await
+    }
+
     /// Return the results of the full handshake
     ///  - the other party identity
     ///  - the encryption and decryption keys to use on the next messages to exchange
@@ -244,4 +312,10 @@ pub(super) struct IdentityAndCredentials {
     /// Credentials associated to the identity along with corresponding Credentials Purpose Keys
     /// to verify those Credentials
     #[n(3)] pub(super) credentials: Vec<CredentialAndPurposeKey>,
+    /// Code of the cipher suite chosen by the responder, only sent in message 2 when
+    /// the initiator offered some cipher suites in message 1
+    #[n(4)] pub(super) cipher_suite: Option<u8>,
+    /// Identifier of the compression dictionary chosen by the responder, only sent in message 2
+    /// when the initiator offered the same dictionary in message 1
+    #[n(5)] pub(super) compression_dictionary: Option<u32>,
 }
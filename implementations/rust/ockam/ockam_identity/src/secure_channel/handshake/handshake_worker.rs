@@ -3,8 +3,8 @@ use core::time::Duration;
 use ockam_core::compat::{boxed::Box, vec::Vec};
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{
-    AllowAll, Any, Decodable, DenyAll, Error, Mailbox, Mailboxes, OutgoingAccessControl, Route,
-    Routed,
+    Address, AllowAll, Any, Decodable, DenyAll, Error, Mailbox, Mailboxes, OutgoingAccessControl,
+    Route, Routed,
 };
 use ockam_core::{AllowOnwardAddress, Result, Worker};
 use ockam_node::callback::CallbackSender;
@@ -25,9 +25,10 @@ use crate::secure_channel::handshake::handshake_state_machine::{
 use crate::secure_channel::handshake::initiator_state_machine::InitiatorStateMachine;
 use crate::secure_channel::handshake::responder_state_machine::ResponderStateMachine;
 use crate::secure_channel::{Addresses, Role};
+use crate::secure_channel::{MessageCompressor, MessageDecompressor};
 use crate::{
-    IdentityError, SecureChannelPurposeKey, SecureChannelRegistryEntry, SecureChannels,
-    TrustContext, TrustPolicy,
+    HandshakeConfig, IdentityError, IdentityQuotas, QuotaKind, QuotaPermit, SecureChannelAdmission,
+    SecureChannelPurposeKey, SecureChannelRegistryEntry, SecureChannels, TrustContext, TrustPolicy,
 };
 
 /// This struct implements a Worker receiving and sending messages
@@ -41,6 +42,10 @@ pub(crate) struct HandshakeWorker {
     role: Role,
     remote_route: Option<Route>,
     decryptor_handler: Option<DecryptorHandler>,
+    quotas: Option<IdentityQuotas>,
+    quota_permit: Option<QuotaPermit>,
+    replay_window: u64,
+    compressed_services: Vec<Address>,
 }
 
 #[ockam_core::worker]
@@ -122,6 +127,20 @@ impl Worker for HandshakeWorker {
 
         // if we reached the final state we can make a pair of encryptor/decryptor
         if let Some(final_state) = self.state_machine.get_handshake_results() {
+            // the permit is kept for as long as this worker is running
+            if let Some(quotas) = &self.quotas {
+                match quotas.try_acquire(&final_state.their_identifier, QuotaKind::SecureChannels) {
+                    Ok(permit) => self.quota_permit = Some(permit),
+                    Err(e) => {
+                        // the channel is rejected, stop this worker instead of waiting for
+                        // messages which will never be decrypted
+                        context
+                            .stop_worker(self.addresses.decryptor_remote.clone())
+                            .await?;
+                        return Err(e);
+                    }
+                }
+            }
             // start the encryptor worker and return the decryptor
             self.decryptor_handler = Some(self.finalize(context, final_state).await?);
             if let Some(callback_sender) = self.callback_sender.take() {
@@ -159,12 +178,17 @@ impl HandshakeWorker {
         decryptor_outgoing_access_control: Arc<dyn OutgoingAccessControl>,
         credentials: Vec<CredentialAndPurposeKey>,
         trust_context: Option<TrustContext>,
+        admission: SecureChannelAdmission,
+        quotas: Option<IdentityQuotas>,
+        replay_window: u64,
+        handshake: HandshakeConfig,
         remote_route: Option<Route>,
         timeout: Option<Duration>,
         role: Role,
     ) -> Result<()> {
         let vault = secure_channels.identities.vault().secure_channel_vault;
         let identities = secure_channels.identities();
+        let compressed_services = handshake.compressed_services().to_vec();
         let state_machine: Box<dyn StateMachine> = if role.is_initiator() {
             Box::new(
                 InitiatorStateMachine::new(
@@ -175,6 +199,7 @@ impl HandshakeWorker {
                     credentials,
                     trust_policy,
                     trust_context,
+                    handshake,
                 )
                 .await?,
             )
@@ -188,6 +213,8 @@ impl HandshakeWorker {
                     credentials,
                     trust_policy,
                     trust_context,
+                    admission,
+                    handshake,
                 )
                 .await?,
             )
@@ -209,6 +236,10 @@ impl HandshakeWorker {
             remote_route: remote_route.clone(),
             addresses: addresses.clone(),
             decryptor_handler: None,
+            quotas,
+            quota_permit: None,
+            replay_window,
+            compressed_services,
         };
 
         WorkerBuilder::new(worker)
@@ -289,25 +320,40 @@ impl HandshakeWorker {
         handshake_results: HandshakeResults,
     ) -> Result<DecryptorHandler> {
         // create a decryptor to delegate the processing of all messages after the handshake
-        let decryptor = DecryptorHandler::new(
+        let mut decryptor = DecryptorHandler::new(
             self.role.str(),
             self.addresses.clone(),
             handshake_results.handshake_keys.decryption_key,
+            handshake_results.handshake_keys.cipher,
             self.secure_channels.identities.vault().secure_channel_vault,
             handshake_results.their_identifier.clone(),
+            self.replay_window,
         );
 
+        let mut encryptor = Encryptor::new(
+            handshake_results.handshake_keys.encryption_key,
+            0,
+            self.secure_channels.identities.vault().secure_channel_vault,
+        )
+        .with_cipher(handshake_results.handshake_keys.cipher);
+
+        // both sides compress their messages if they agreed on a dictionary
+        if let Some(dictionary) = &handshake_results.handshake_keys.compression {
+            debug!("the messages are compressed with the dictionary {dictionary:?}");
+            encryptor = encryptor.with_compressor(MessageCompressor::new(
+                dictionary,
+                self.compressed_services.clone(),
+            )?);
+            decryptor = decryptor.with_decompressor(MessageDecompressor::new(dictionary)?);
+        }
+
         // create a separate encryptor worker which will be started independently
         {
             let encryptor = EncryptorWorker::new(
                 self.role.str(),
                 self.addresses.clone(),
                 self.remote_route()?,
-                Encryptor::new(
-                    handshake_results.handshake_keys.encryption_key,
-                    0,
-                    self.secure_channels.identities.vault().secure_channel_vault,
-                ),
+                encryptor,
             );
 
             let next_hop = self.remote_route()?.next()?.clone();
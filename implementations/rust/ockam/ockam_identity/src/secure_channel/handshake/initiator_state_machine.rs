@@ -17,7 +17,9 @@ use crate::secure_channel::handshake::handshake_state_machine::{
     Action, CommonStateMachine, Event, HandshakeKeys, HandshakeResults, IdentityAndCredentials,
     StateMachine, Status,
 };
-use crate::{Identities, Role, SecureChannelPurposeKey, TrustContext, TrustPolicy};
+use crate::{
+    HandshakeConfig, Identities, Role, SecureChannelPurposeKey, TrustContext, TrustPolicy,
+};
 
 /// Implementation of a state machine for the key exchange on the initiator side
 #[async_trait]
@@ -28,7 +30,8 @@ impl StateMachine for InitiatorStateMachine {
             // Initialize the handshake and send message 1
             (Initial, Initialize) => {
                 self.initialize_handshake().await?;
-                let message1 = self.encode_message1(&[]).await?;
+                let offer = self.offer()?;
+                let message1 = self.encode_message1(&offer).await?;
 
                 // Send message 1 and wait for message 2
                 self.handshake.state.status = WaitingForMessage2;
@@ -39,13 +42,18 @@ impl StateMachine for InitiatorStateMachine {
                 let message2_payload = self.decode_message2(&message).await?;
                 let their_identity_payload: IdentityAndCredentials =
                     minicbor::decode(&message2_payload)?;
+                self.accept_cipher_suite(their_identity_payload.cipher_suite)?;
+                self.accept_compression(their_identity_payload.compression_dictionary)?;
                 self.verify_identity(their_identity_payload, &self.handshake.state.rs()?.clone())
                     .await?;
                 let identity_payload = self
                     .identity_payload
                     .take()
                     .ok_or(XXError::InvalidInternalState)?;
-                let message3 = self.encode_message3(&identity_payload).await?;
+                // the identity and credentials are compressed if the responder accepted it
+                let message3_payload =
+                    self.compress_payload(minicbor::to_vec(identity_payload)?)?;
+                let message3 = self.encode_message3(&message3_payload).await?;
                 self.set_final_state(Initiator).await?;
                 Ok(SendMessage(message3))
             }
@@ -70,8 +78,8 @@ impl StateMachine for InitiatorStateMachine {
 pub(super) struct InitiatorStateMachine {
     pub(super) common: CommonStateMachine,
     pub(super) handshake: Handshake,
-    /// this serialized payload contains an identity, its credentials and a signature of its static key
-    pub(super) identity_payload: Option<Vec<u8>>,
+    /// this payload contains an identity, its credentials and a signature of its static key
+    pub(super) identity_payload: Option<IdentityAndCredentials>,
 }
 
 impl InitiatorStateMachine {
@@ -85,8 +93,12 @@ impl InitiatorStateMachine {
         to self.handshake {
             #[call(initialize)]
             async fn initialize_handshake(&mut self) -> Result<()>;
+            fn offer(&self) -> Result<Vec<u8>>;
             async fn encode_message1(&mut self, payload: &[u8]) -> Result<Vec<u8>>;
             async fn decode_message2(&mut self, message: &[u8]) -> Result<Vec<u8>>;
+            fn accept_cipher_suite(&mut self, chosen: Option<u8>) -> Result<()>;
+            fn accept_compression(&mut self, chosen: Option<u32>) -> Result<()>;
+            fn compress_payload(&self, payload: Vec<u8>) -> Result<Vec<u8>>;
             async fn encode_message3(&mut self, payload: &[u8]) -> Result<Vec<u8>>;
             async fn set_final_state(&mut self, role: Role) -> Result<()>;
             fn get_handshake_keys(&self) -> Option<HandshakeKeys>;
@@ -95,6 +107,7 @@ impl InitiatorStateMachine {
 }
 
 impl InitiatorStateMachine {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         vault: Arc<dyn VaultForSecureChannels>,
         identities: Arc<Identities>,
@@ -103,6 +116,7 @@ impl InitiatorStateMachine {
         credentials: Vec<CredentialAndPurposeKey>,
         trust_policy: Arc<dyn TrustPolicy>,
         trust_context: Option<TrustContext>,
+        handshake: HandshakeConfig,
     ) -> Result<InitiatorStateMachine> {
         let common = CommonStateMachine::new(
             identities,
@@ -116,7 +130,7 @@ impl InitiatorStateMachine {
 
         Ok(InitiatorStateMachine {
             common,
-            handshake: Handshake::new(vault, purpose_key.key().clone()).await?,
+            handshake: Handshake::new(vault, purpose_key.key().clone(), handshake).await?,
             identity_payload: Some(identity_payload),
         })
     }
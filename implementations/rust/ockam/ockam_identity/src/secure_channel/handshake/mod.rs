@@ -10,3 +10,5 @@ mod handshake_state_machine;
 pub(crate) mod handshake_worker;
 mod initiator_state_machine;
 mod responder_state_machine;
+
+pub(crate) use handshake::PROTOCOL_NAME;
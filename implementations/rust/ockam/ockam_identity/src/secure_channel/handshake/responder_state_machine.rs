@@ -12,12 +12,15 @@ use Status::*;
 
 use crate::models::{CredentialAndPurposeKey, Identifier};
 use crate::secure_channel::handshake::error::XXError;
-use crate::secure_channel::handshake::handshake::Handshake;
+use crate::secure_channel::handshake::handshake::{Handshake, HandshakeOffer};
 use crate::secure_channel::handshake::handshake_state_machine::{
     Action, CommonStateMachine, Event, HandshakeKeys, HandshakeResults, IdentityAndCredentials,
     StateMachine, Status,
 };
-use crate::{Identities, Role, SecureChannelPurposeKey, TrustContext, TrustPolicy};
+use crate::{
+    CipherSuite, HandshakeConfig, Identities, Role, SecureChannelAdmission,
+    SecureChannelPurposeKey, TrustContext, TrustPolicy,
+};
 
 /// Implementation of a state machine for the key exchange on the responder side
 #[async_trait]
@@ -33,12 +36,19 @@ impl StateMachine for ResponderStateMachine {
             }
             // Process message 1 and send message 2
             (WaitingForMessage1, ReceivedMessage(message)) => {
-                self.decode_message1(&message).await?;
-                let identity_payload = self
+                let message1_payload = self.decode_message1(&message).await?;
+                let offer = HandshakeOffer::from_payload(&message1_payload)?;
+                let cipher_suite = self.choose_cipher_suite(&offer)?;
+                let compression_dictionary = self.choose_compression(&offer);
+                let mut identity_payload = self
                     .identity_payload
                     .take()
                     .ok_or(XXError::InvalidInternalState)?;
-                let message2 = self.encode_message2(&identity_payload).await?;
+                identity_payload.cipher_suite = cipher_suite.map(|suite| suite.code());
+                identity_payload.compression_dictionary = compression_dictionary;
+                let message2 = self
+                    .encode_message2(&minicbor::to_vec(identity_payload)?)
+                    .await?;
 
                 self.handshake.state.status = WaitingForMessage3;
                 Ok(SendMessage(message2))
@@ -46,6 +56,7 @@ impl StateMachine for ResponderStateMachine {
             // Process message 3
             (WaitingForMessage3, ReceivedMessage(message)) => {
                 let message3_payload = self.decode_message3(&message).await?;
+                let message3_payload = self.decompress_payload(message3_payload)?;
                 let their_identity_payload: IdentityAndCredentials =
                     minicbor::decode(&message3_payload)?;
                 self.verify_identity(their_identity_payload, &self.handshake.state.rs()?.clone())
@@ -73,8 +84,8 @@ impl StateMachine for ResponderStateMachine {
 pub struct ResponderStateMachine {
     common: CommonStateMachine,
     handshake: Handshake,
-    /// this serialized payload contains an identity, its credentials and a signature of its static key
-    identity_payload: Option<Vec<u8>>,
+    /// this payload contains an identity, its credentials and a signature of its static key
+    identity_payload: Option<IdentityAndCredentials>,
 }
 
 impl ResponderStateMachine {
@@ -89,8 +100,11 @@ impl ResponderStateMachine {
             #[call(initialize)]
             async fn initialize_handshake(&mut self) -> Result<()>;
             async fn decode_message1(&mut self, message: &[u8]) -> Result<Vec<u8>>;
+            fn choose_cipher_suite(&mut self, offer: &HandshakeOffer) -> Result<Option<CipherSuite>>;
+            fn choose_compression(&mut self, offer: &HandshakeOffer) -> Option<u32>;
             async fn encode_message2(&mut self, payload: &[u8]) -> Result<Vec<u8>>;
             async fn decode_message3(&mut self, message: &[u8]) -> Result<Vec<u8>>;
+            fn decompress_payload(&self, payload: Vec<u8>) -> Result<Vec<u8>>;
             async fn set_final_state(&mut self, role: Role) -> Result<()>;
             fn get_handshake_keys(&self) -> Option<HandshakeKeys>;
         }
@@ -98,6 +112,7 @@ impl ResponderStateMachine {
 }
 
 impl ResponderStateMachine {
+    #[allow(clippy::too_many_arguments)]
     pub async fn new(
         vault: Arc<dyn VaultForSecureChannels>,
         identities: Arc<Identities>,
@@ -106,8 +121,10 @@ impl ResponderStateMachine {
         credentials: Vec<CredentialAndPurposeKey>,
         trust_policy: Arc<dyn TrustPolicy>,
         trust_context: Option<TrustContext>,
+        admission: SecureChannelAdmission,
+        handshake: HandshakeConfig,
     ) -> Result<ResponderStateMachine> {
-        let common = CommonStateMachine::new(
+        let mut common = CommonStateMachine::new(
             identities,
             identifier,
             purpose_key.attestation().clone(),
@@ -115,11 +132,12 @@ impl ResponderStateMachine {
             trust_policy,
             trust_context,
         );
+        common.admission = admission;
         let identity_payload = common.make_identity_payload().await?;
 
         Ok(ResponderStateMachine {
             common,
-            handshake: Handshake::new(vault, purpose_key.key().clone()).await?,
+            handshake: Handshake::new(vault, purpose_key.key().clone(), handshake).await?,
             identity_payload: Some(identity_payload),
         })
     }
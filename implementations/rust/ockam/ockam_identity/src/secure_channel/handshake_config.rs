@@ -0,0 +1,204 @@
+use core::fmt::{Debug, Formatter};
+use ockam_core::compat::vec::{vec, Vec};
+use ockam_core::Address;
+use ockam_vault::{has_aes_instructions, AeadCipher};
+
+use crate::secure_channel::handshake::PROTOCOL_NAME;
+use crate::CompressionDictionary;
+
+/// Protocol name of the XX pattern where a pre-shared key is mixed in the handshake
+const XX_PSK_PROTOCOL_NAME: &[u8; 32] = b"OCKAM_XX_PSK_25519_AESGCM_SHA256";
+
+/// Variant of the Noise XX pattern used to establish a secure channel.
+///
+/// Both sides of a secure channel must use the same variant, otherwise the handshake fails
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum HandshakePattern {
+    /// The `Noise_XX_25519_AESGCM_SHA256` pattern, where each side authenticates the other
+    /// with its static key
+    #[default]
+    Xx,
+    /// The XX pattern where a 32 bytes pre-shared key is also mixed in the chaining key before
+    /// the payload of the third message is encrypted, like the `psk3` Noise modifier.
+    /// Only the parties knowing the key can complete the handshake, even if their identities
+    /// are trusted
+    XxPsk([u8; 32]),
+}
+
+impl HandshakePattern {
+    /// Name of the protocol, used to initialize the handshake state
+    pub(crate) fn protocol_name(&self) -> [u8; 32] {
+        match self {
+            HandshakePattern::Xx => *PROTOCOL_NAME,
+            HandshakePattern::XxPsk(_) => *XX_PSK_PROTOCOL_NAME,
+        }
+    }
+
+    /// Pre-shared key of the pattern, if any
+    pub(crate) fn psk(&self) -> Option<&[u8; 32]> {
+        match self {
+            HandshakePattern::Xx => None,
+            HandshakePattern::XxPsk(psk) => Some(psk),
+        }
+    }
+}
+
+impl Debug for HandshakePattern {
+    // the pre-shared key is never displayed
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HandshakePattern::Xx => f.write_str("Xx"),
+            HandshakePattern::XxPsk(_) => f.write_str("XxPsk"),
+        }
+    }
+}
+
+/// Cipher suite used to encrypt the messages of a secure channel once its handshake is done.
+///
+/// The handshake itself always uses AES-GCM and SHA-256. The initiator offers its suites,
+/// in order of preference, and the responder picks the first one it also accepts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// AES-256-GCM with SHA-256, the suite used by the nodes which don't negotiate any suite
+    #[default]
+    Aes256GcmSha256,
+    /// ChaCha20-Poly1305 with SHA-256, faster than AES-GCM on CPUs without AES instructions
+    ChaCha20Poly1305Sha256,
+}
+
+impl CipherSuite {
+    /// Code of the suite in the handshake messages
+    pub(crate) fn code(&self) -> u8 {
+        match self {
+            CipherSuite::Aes256GcmSha256 => 0,
+            CipherSuite::ChaCha20Poly1305Sha256 => 1,
+        }
+    }
+
+    /// Suite corresponding to a code, None if the code is unknown, for example when it is
+    /// sent by a more recent node
+    pub(crate) fn from_code(code: u8) -> Option<CipherSuite> {
+        match code {
+            0 => Some(CipherSuite::Aes256GcmSha256),
+            1 => Some(CipherSuite::ChaCha20Poly1305Sha256),
+            _ => None,
+        }
+    }
+
+    /// AEAD cipher of the suite
+    pub(crate) fn aead_cipher(&self) -> AeadCipher {
+        match self {
+            CipherSuite::Aes256GcmSha256 => AeadCipher::Aes256Gcm,
+            CipherSuite::ChaCha20Poly1305Sha256 => AeadCipher::ChaCha20Poly1305,
+        }
+    }
+
+    /// Suites accepted by default, the fastest one for the current CPU first
+    pub fn preferred() -> Vec<CipherSuite> {
+        if has_aes_instructions() {
+            vec![
+                CipherSuite::Aes256GcmSha256,
+                CipherSuite::ChaCha20Poly1305Sha256,
+            ]
+        } else {
+            vec![
+                CipherSuite::ChaCha20Poly1305Sha256,
+                CipherSuite::Aes256GcmSha256,
+            ]
+        }
+    }
+}
+
+/// Configuration of the handshake of a secure channel: its pattern, its prologue, the
+/// cipher suites it accepts and the dictionary it can use to compress the messages.
+///
+/// The prologue is some data which is not sent but mixed in the handshake hash, for example
+/// a hash of some context shared out of band by a higher-level protocol. The handshake
+/// only succeeds if both sides use the same prologue, which binds the channel to that context
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandshakeConfig {
+    pub(crate) pattern: HandshakePattern,
+    pub(crate) prologue: Vec<u8>,
+    pub(crate) cipher_suites: Vec<CipherSuite>,
+    pub(crate) compression_dictionary: Option<CompressionDictionary>,
+    pub(crate) compressed_services: Vec<Address>,
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> Self {
+        Self {
+            pattern: HandshakePattern::default(),
+            prologue: Vec::new(),
+            cipher_suites: CipherSuite::preferred(),
+            compression_dictionary: None,
+            compressed_services: vec![],
+        }
+    }
+}
+
+impl HandshakeConfig {
+    /// Use a variant of the XX pattern
+    pub fn with_pattern(mut self, pattern: HandshakePattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// Mix a prologue in the handshake hash
+    pub fn with_prologue(mut self, prologue: impl Into<Vec<u8>>) -> Self {
+        self.prologue = prologue.into();
+        self
+    }
+
+    /// Accept only some cipher suites, in order of preference.
+    /// Use a single suite to enforce a policy, for example a FIPS-approved cipher.
+    /// An empty list is replaced by the default AES-GCM suite
+    pub fn with_cipher_suites(mut self, cipher_suites: Vec<CipherSuite>) -> Self {
+        self.cipher_suites = if cipher_suites.is_empty() {
+            vec![CipherSuite::default()]
+        } else {
+            cipher_suites
+        };
+        self
+    }
+
+    /// Compress the messages sent to or by some services, for example the management API of
+    /// a node or its credentials service, with a dictionary if the other side of the channel
+    /// uses the same dictionary. The identity and the credentials sent during the handshake
+    /// are compressed too.
+    /// The other messages, like the data of portals, are never compressed
+    #[cfg(feature = "compression")]
+    pub fn with_compression_dictionary(
+        mut self,
+        dictionary: CompressionDictionary,
+        services: Vec<Address>,
+    ) -> Self {
+        self.compression_dictionary = Some(dictionary);
+        self.compressed_services = services;
+        self
+    }
+
+    /// Pattern of the handshake
+    pub fn pattern(&self) -> &HandshakePattern {
+        &self.pattern
+    }
+
+    /// Prologue mixed in the handshake hash, empty by default
+    pub fn prologue(&self) -> &[u8] {
+        &self.prologue
+    }
+
+    /// Accepted cipher suites, in order of preference
+    pub fn cipher_suites(&self) -> &[CipherSuite] {
+        &self.cipher_suites
+    }
+
+    /// Dictionary used to compress the messages, if any
+    pub fn compression_dictionary(&self) -> Option<&CompressionDictionary> {
+        self.compression_dictionary.as_ref()
+    }
+
+    /// Services whose messages are compressed
+    pub fn compressed_services(&self) -> &[Address] {
+        &self.compressed_services
+    }
+}
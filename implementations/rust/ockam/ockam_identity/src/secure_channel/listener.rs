@@ -108,6 +108,10 @@ impl Worker for IdentityChannelListener {
             access_control.decryptor_outgoing_access_control,
             credentials,
             self.options.trust_context.clone(),
+            self.options.admission.clone(),
+            self.options.quotas.clone(),
+            self.options.replay_window,
+            self.options.handshake.clone(),
             None,
             None,
             Role::Responder,
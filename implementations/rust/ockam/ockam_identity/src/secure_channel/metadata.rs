@@ -0,0 +1,149 @@
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::string::String;
+use ockam_core::compat::vec::Vec;
+use ockam_core::{Decodable, Encodable, LocalInfo, LocalMessage, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::IdentityError;
+
+/// Message Metadata LocalInfo unique Identifier
+pub const SECURE_CHANNEL_MESSAGE_METADATA_IDENTIFIER: &str =
+    "SECURE_CHANNEL_MESSAGE_METADATA_IDENTIFIER";
+
+/// Key of the id of a message, used to detect the messages received several times
+pub const MESSAGE_ID_KEY: &str = "message-id";
+
+/// Maximum size of the encoded metadata of a message
+pub const MAX_MESSAGE_METADATA_SIZE: usize = u16::MAX as usize;
+
+/// Metadata attached to a message sent over a secure channel, for example a content type,
+/// a tenant id or routing hints.
+///
+/// The metadata is sent unencrypted but it is authenticated: it is bound to the encrypted
+/// payload as associated data, so that it can't be modified or moved to another message.
+///
+/// To attach metadata to a message, send it to the secure channel with
+/// [`MessageMetadata::to_local_info`], for example with `Context::send_with_local_info`.
+/// The receiving worker gets it back with [`MessageMetadata::find_info`].
+/// Workers receiving messages delivered at least once can use the
+/// [`MessageMetadata::message_id`] to drop duplicates.
+/// Both parties must support message metadata, older versions reject these messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageMetadata(BTreeMap<String, String>);
+
+impl MessageMetadata {
+    /// Create empty metadata
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entry to the metadata
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Add an entry to the metadata, replacing the previous value of that key
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.0.insert(key.into(), value.into());
+    }
+
+    /// Return the value of a key
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).map(|v| v.as_str())
+    }
+
+    /// Set the id of the message, so that the receiver can drop duplicates
+    pub fn with_message_id(self, message_id: impl Into<String>) -> Self {
+        self.with(MESSAGE_ID_KEY, message_id)
+    }
+
+    /// Return the id of the message, if it was set by the sender
+    pub fn message_id(&self) -> Option<&str> {
+        self.get(MESSAGE_ID_KEY)
+    }
+
+    /// Return true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Iterate over the entries, ordered by key
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.0.iter()
+    }
+
+    /// Encode the metadata as the associated data of a message
+    pub(crate) fn to_associated_data(&self) -> Result<Vec<u8>> {
+        let encoded = self.encode()?;
+        if encoded.len() > MAX_MESSAGE_METADATA_SIZE {
+            return Err(IdentityError::InvalidMessageMetadata.into());
+        }
+        Ok(encoded)
+    }
+
+    /// Decode the associated data of a message
+    pub(crate) fn from_associated_data(data: &[u8]) -> Result<Self> {
+        Self::decode(data).map_err(|_| IdentityError::InvalidMessageMetadata.into())
+    }
+}
+
+impl MessageMetadata {
+    /// Try to decode `MessageMetadata` from general `LocalInfo`
+    pub fn from_local_info(value: &LocalInfo) -> Result<Self> {
+        if value.type_identifier() != SECURE_CHANNEL_MESSAGE_METADATA_IDENTIFIER {
+            return Err(IdentityError::InvalidLocalInfoType.into());
+        }
+
+        if let Ok(info) = MessageMetadata::decode(value.data()) {
+            return Ok(info);
+        }
+
+        Err(IdentityError::InvalidLocalInfoType.into())
+    }
+
+    /// Encode `MessageMetadata` to general `LocalInfo`
+    pub fn to_local_info(&self) -> Result<LocalInfo> {
+        Ok(LocalInfo::new(
+            SECURE_CHANNEL_MESSAGE_METADATA_IDENTIFIER.into(),
+            self.encode()?,
+        ))
+    }
+
+    /// Find `MessageMetadata` in a list of general `LocalInfo` of that `LocalMessage`
+    pub fn find_info(local_msg: &LocalMessage) -> Result<Self> {
+        Self::find_info_from_list(local_msg.local_info())
+    }
+
+    /// Find `MessageMetadata` in a list of general `LocalInfo`
+    pub fn find_info_from_list(local_info: &[LocalInfo]) -> Result<Self> {
+        if let Some(local_info) = local_info
+            .iter()
+            .find(|x| x.type_identifier() == SECURE_CHANNEL_MESSAGE_METADATA_IDENTIFIER)
+        {
+            Self::from_local_info(local_info)
+        } else {
+            Err(IdentityError::InvalidLocalInfoType.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_info_roundtrip() -> Result<()> {
+        let metadata = MessageMetadata::new()
+            .with("content-type", "application/json")
+            .with("tenant", "acme");
+        let local_info = vec![metadata.to_local_info()?];
+        let found = MessageMetadata::find_info_from_list(&local_info)?;
+        assert_eq!(found, metadata);
+        assert_eq!(found.get("tenant"), Some("acme"));
+
+        let too_large = MessageMetadata::new().with("hint", "x".repeat(MAX_MESSAGE_METADATA_SIZE));
+        assert!(too_large.to_associated_data().is_err());
+        Ok(())
+    }
+}
@@ -1,16 +1,21 @@
 /// Access control data for workers
 pub mod access_control;
 mod addresses;
+mod admission;
 mod api;
+mod compression;
 mod decryptor;
 mod encryptor;
 mod encryptor_worker;
 mod handshake;
+mod handshake_config;
 mod key_tracker;
 mod listener;
 mod local_info;
+mod metadata;
 mod nonce_tracker;
 mod options;
+mod quotas;
 mod registry;
 mod role;
 /// List of trust policies to setup ABAC controls
@@ -18,11 +23,17 @@ pub mod trust_policy;
 
 pub use access_control::*;
 pub(crate) use addresses::*;
+pub use admission::*;
 pub use api::*;
+pub use compression::CompressionDictionary;
+pub(crate) use compression::{MessageCompressor, MessageDecompressor};
 pub(crate) use handshake::*;
+pub use handshake_config::*;
 pub(crate) use listener::*;
 pub use local_info::*;
+pub use metadata::*;
 pub use options::*;
+pub use quotas::*;
 pub use registry::*;
 pub(crate) use role::*;
 pub use trust_policy::*;
@@ -30,6 +41,7 @@ pub use trust_policy::*;
 #[cfg(test)]
 mod tests {
     use crate::secure_channel::{decryptor::Decryptor, encryptor::Encryptor};
+    use crate::MessageMetadata;
     use ockam_core::compat::rand::RngCore;
     use ockam_core::Result;
     use ockam_vault::{SoftwareVaultForSecureChannels, VaultForSecureChannels};
@@ -52,6 +64,37 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_encrypt_decrypt_with_metadata() {
+        let (mut encryptor, mut decryptor) = create_encryptor_decryptor().await.unwrap();
+        let metadata = MessageMetadata::new().with("content-type", "text/plain");
+
+        for n in 0..100 {
+            let msg = vec![n];
+            let ciphertext = if n % 2 == 0 {
+                encryptor
+                    .encrypt_with_metadata(&msg, Some(&metadata))
+                    .await
+                    .unwrap()
+            } else {
+                encryptor.encrypt(&msg).await.unwrap()
+            };
+            let (plaintext, received) = decryptor.decrypt_with_metadata(&ciphertext).await.unwrap();
+            assert_eq!(plaintext, msg);
+            assert_eq!(received, (n % 2 == 0).then(|| metadata.clone()));
+        }
+
+        // the metadata can't be modified
+        let mut ciphertext = encryptor
+            .encrypt_with_metadata(&[1], Some(&metadata))
+            .await
+            .unwrap();
+        let last_metadata_byte =
+            10 + u16::from_be_bytes([ciphertext[8], ciphertext[9]]) as usize - 1;
+        ciphertext[last_metadata_byte] ^= 1;
+        assert!(decryptor.decrypt_with_metadata(&ciphertext).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_encrypt_decrypt_with_message_lost() {
         let (mut encryptor, mut decryptor) = create_encryptor_decryptor().await.unwrap();
@@ -131,6 +174,41 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "compression")]
+    #[tokio::test]
+    async fn test_compress_only_the_messages_of_some_services() -> Result<()> {
+        use crate::secure_channel::{MessageCompressor, MessageDecompressor};
+        use crate::CompressionDictionary;
+        use ockam_core::route;
+
+        let samples: Vec<Vec<u8>> = (0..1000u32)
+            .map(|i| {
+                format!(r#"{{"method":"GET","path":"/node/secure_channel/{i}"}}"#).into_bytes()
+            })
+            .collect();
+        let dictionary = CompressionDictionary::train(&samples, 2 * 1024)?;
+        let (encryptor, decryptor) = create_encryptor_decryptor().await?;
+        let mut encryptor =
+            encryptor.with_compressor(MessageCompressor::new(&dictionary, vec!["api".into()])?);
+        let mut decryptor = decryptor.with_decompressor(MessageDecompressor::new(&dictionary)?);
+
+        // the requests to the node manager and its responses are compressed,
+        // the data of a portal is not
+        assert!(encryptor.compresses(&route!["api"], &route!["client"]));
+        assert!(encryptor.compresses(&route!["client"], &route!["api"]));
+        assert!(!encryptor.compresses(&route!["outlet"], &route!["inlet"]));
+
+        let message = br#"{"method":"GET","path":"/node/secure_channel/1000"}"#;
+        let compressed = encryptor.encrypt_message(message, None, true).await?;
+        let uncompressed = encryptor.encrypt_message(message, None, false).await?;
+        assert!(compressed.len() < uncompressed.len());
+        // nonce, compression flag, message and tag
+        assert_eq!(uncompressed.len(), 8 + 1 + message.len() + 16);
+        assert_eq!(decryptor.decrypt(&compressed).await?, message.to_vec());
+        assert_eq!(decryptor.decrypt(&uncompressed).await?, message.to_vec());
+        Ok(())
+    }
+
     async fn create_encryptor_decryptor() -> Result<(Encryptor, Decryptor)> {
         let vault1 = SoftwareVaultForSecureChannels::create();
         let vault2 = SoftwareVaultForSecureChannels::create();
@@ -12,13 +12,18 @@ type BitmapType = u64;
 pub(crate) struct NonceTracker {
     nonce_bitmap: BitmapType,
     current_nonce: u64,
+    /// Number of nonces before the current one which are still accepted
+    replay_window: u64,
 }
 
 impl NonceTracker {
-    pub(crate) fn new() -> Self {
+    /// The replay window can't be larger than [`KEY_RENEWAL_INTERVAL`]
+    /// since only the current key and the previous one are kept
+    pub(crate) fn new(replay_window: u64) -> Self {
         Self {
             nonce_bitmap: 0,
             current_nonce: 0,
+            replay_window: replay_window.min(KEY_RENEWAL_INTERVAL),
         }
     }
 
@@ -33,11 +38,12 @@ impl NonceTracker {
             NonceTracker {
                 nonce_bitmap: self.nonce_bitmap.overflowing_shl(relative_shift as u32).0 | 1,
                 current_nonce: nonce,
+                replay_window: self.replay_window,
             }
         } else {
             // first message or an out of order message
             let relative: u64 = self.current_nonce - nonce;
-            if relative > KEY_RENEWAL_INTERVAL {
+            if relative > self.replay_window {
                 return Err(IdentityError::InvalidNonce.into());
             }
 
@@ -50,6 +56,7 @@ impl NonceTracker {
             NonceTracker {
                 nonce_bitmap: self.nonce_bitmap | bit,
                 current_nonce: self.current_nonce,
+                replay_window: self.replay_window,
             }
         };
 
@@ -59,7 +66,7 @@ impl NonceTracker {
 
 #[test]
 pub fn check_nonce_tracker() {
-    let mut tracker = NonceTracker::new();
+    let mut tracker = NonceTracker::new(KEY_RENEWAL_INTERVAL);
     tracker = tracker.mark(0).unwrap();
     tracker = tracker.mark(1).unwrap();
     tracker.mark(0).unwrap_err();
@@ -81,3 +88,21 @@ pub fn check_nonce_tracker() {
         tracker = tracker.mark(n).unwrap();
     }
 }
+
+#[test]
+pub fn check_nonce_tracker_with_a_smaller_replay_window() {
+    let mut tracker = NonceTracker::new(2);
+    tracker = tracker.mark(0).unwrap();
+    tracker = tracker.mark(3).unwrap();
+    tracker = tracker.mark(1).unwrap();
+    tracker.mark(1).unwrap_err();
+    tracker = tracker.mark(10).unwrap();
+    tracker.mark(7).unwrap_err();
+    tracker.mark(8).unwrap();
+
+    // the window is limited by the key renewal interval
+    let tracker = NonceTracker::new(u64::MAX)
+        .mark(KEY_RENEWAL_INTERVAL)
+        .unwrap();
+    tracker.mark(0).unwrap();
+}
@@ -1,11 +1,19 @@
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::string::String;
 use ockam_core::compat::sync::Arc;
 use ockam_core::compat::vec::Vec;
 use ockam_core::flow_control::{FlowControlId, FlowControlOutgoingAccessControl, FlowControls};
 use ockam_core::{Address, OutgoingAccessControl, Result};
 
-use crate::models::CredentialAndPurposeKey;
+use crate::models::{CredentialAndPurposeKey, Identifier};
+use crate::secure_channel::encryptor::KEY_RENEWAL_INTERVAL;
 use crate::secure_channel::Addresses;
-use crate::{TrustContext, TrustEveryonePolicy, TrustPolicy};
+#[cfg(feature = "compression")]
+use crate::CompressionDictionary;
+use crate::{
+    CipherSuite, HandshakeConfig, HandshakePattern, IdentityQuotas, SecureChannelAdmission,
+    Timeouts, TrustContext, TrustEveryonePolicy, TrustPolicy,
+};
 
 use core::fmt;
 use core::fmt::Formatter;
@@ -14,6 +22,11 @@ use core::time::Duration;
 /// This is the default timeout for creating a secure channel
 pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
 
+/// This is the default, and maximum, replay protection window of a secure channel:
+/// a message is accepted if it arrives at most this number of messages after a more recent one,
+/// and if it was not already received
+pub const DEFAULT_REPLAY_WINDOW: u64 = KEY_RENEWAL_INTERVAL;
+
 /// Trust options for a Secure Channel
 pub struct SecureChannelOptions {
     pub(crate) flow_control_id: FlowControlId,
@@ -21,6 +34,8 @@ pub struct SecureChannelOptions {
     pub(crate) trust_context: Option<TrustContext>,
     pub(crate) credentials: Vec<CredentialAndPurposeKey>,
     pub(crate) timeout: Duration,
+    pub(crate) replay_window: u64,
+    pub(crate) handshake: HandshakeConfig,
 }
 
 impl fmt::Debug for SecureChannelOptions {
@@ -43,6 +58,8 @@ impl SecureChannelOptions {
             trust_context: None,
             credentials: vec![],
             timeout: DEFAULT_TIMEOUT,
+            replay_window: DEFAULT_REPLAY_WINDOW,
+            handshake: HandshakeConfig::default(),
         }
     }
 
@@ -52,6 +69,53 @@ impl SecureChannelOptions {
         self
     }
 
+    /// Sets the timeout of the handshake from a set of [`Timeouts`]
+    pub fn with_timeouts(self, timeouts: &Timeouts) -> Self {
+        self.with_timeout(timeouts.handshake())
+    }
+
+    /// Sets a replay protection window smaller than [`DEFAULT_REPLAY_WINDOW`],
+    /// to reject the messages arriving too late after more recent ones.
+    /// Larger values are capped to [`DEFAULT_REPLAY_WINDOW`]
+    pub fn with_replay_window(mut self, replay_window: u64) -> Self {
+        self.replay_window = replay_window.min(DEFAULT_REPLAY_WINDOW);
+        self
+    }
+
+    /// Use a variant of the XX handshake pattern, the listener must use the same variant
+    pub fn with_handshake_pattern(mut self, pattern: HandshakePattern) -> Self {
+        self.handshake = self.handshake.with_pattern(pattern);
+        self
+    }
+
+    /// Mix a prologue in the handshake, for example a hash of some context shared out of band.
+    /// The handshake fails if the listener doesn't use the same prologue
+    pub fn with_prologue(mut self, prologue: impl Into<Vec<u8>>) -> Self {
+        self.handshake = self.handshake.with_prologue(prologue);
+        self
+    }
+
+    /// Offer only some cipher suites to the listener, in order of preference.
+    /// By default the suite which is the fastest on this CPU is preferred
+    pub fn with_cipher_suites(mut self, cipher_suites: Vec<CipherSuite>) -> Self {
+        self.handshake = self.handshake.with_cipher_suites(cipher_suites);
+        self
+    }
+
+    /// Offer to compress the messages sent to or by some services of the channel with a
+    /// dictionary. The messages are only compressed if the listener uses the same dictionary
+    #[cfg(feature = "compression")]
+    pub fn with_compression_dictionary(
+        mut self,
+        dictionary: CompressionDictionary,
+        services: Vec<Address>,
+    ) -> Self {
+        self.handshake = self
+            .handshake
+            .with_compression_dictionary(dictionary, services);
+        self
+    }
+
     /// Adds provided credentials
     pub fn with_credentials(mut self, credentials: Vec<CredentialAndPurposeKey>) -> Self {
         self.credentials.extend(credentials);
@@ -130,6 +194,10 @@ pub struct SecureChannelListenerOptions {
     pub(crate) trust_policy: Arc<dyn TrustPolicy>,
     pub(crate) trust_context: Option<TrustContext>,
     pub(crate) credentials: Vec<CredentialAndPurposeKey>,
+    pub(crate) admission: SecureChannelAdmission,
+    pub(crate) quotas: Option<IdentityQuotas>,
+    pub(crate) replay_window: u64,
+    pub(crate) handshake: HandshakeConfig,
 }
 
 impl fmt::Debug for SecureChannelListenerOptions {
@@ -150,6 +218,10 @@ impl SecureChannelListenerOptions {
             trust_policy: Arc::new(TrustEveryonePolicy),
             trust_context: None,
             credentials: vec![],
+            admission: SecureChannelAdmission::default(),
+            quotas: None,
+            replay_window: DEFAULT_REPLAY_WINDOW,
+            handshake: HandshakeConfig::default(),
         }
     }
 
@@ -162,6 +234,48 @@ impl SecureChannelListenerOptions {
         self
     }
 
+    /// Sets a replay protection window smaller than [`DEFAULT_REPLAY_WINDOW`],
+    /// to reject the messages arriving too late after more recent ones.
+    /// Larger values are capped to [`DEFAULT_REPLAY_WINDOW`]
+    pub fn with_replay_window(mut self, replay_window: u64) -> Self {
+        self.replay_window = replay_window.min(DEFAULT_REPLAY_WINDOW);
+        self
+    }
+
+    /// Use a variant of the XX handshake pattern, the initiators must use the same variant
+    pub fn with_handshake_pattern(mut self, pattern: HandshakePattern) -> Self {
+        self.handshake = self.handshake.with_pattern(pattern);
+        self
+    }
+
+    /// Mix a prologue in the handshake, for example a hash of some context shared out of band.
+    /// The handshakes fail if the initiators don't use the same prologue
+    pub fn with_prologue(mut self, prologue: impl Into<Vec<u8>>) -> Self {
+        self.handshake = self.handshake.with_prologue(prologue);
+        self
+    }
+
+    /// Accept only some cipher suites. The first suite offered by an initiator which is
+    /// also in this list is used, and the handshake fails if there is none
+    pub fn with_cipher_suites(mut self, cipher_suites: Vec<CipherSuite>) -> Self {
+        self.handshake = self.handshake.with_cipher_suites(cipher_suites);
+        self
+    }
+
+    /// Compress the messages sent to or by some services of the channels with a dictionary,
+    /// when the initiators offer the same dictionary. The other channels are not compressed
+    #[cfg(feature = "compression")]
+    pub fn with_compression_dictionary(
+        mut self,
+        dictionary: CompressionDictionary,
+        services: Vec<Address>,
+    ) -> Self {
+        self.handshake = self
+            .handshake
+            .with_compression_dictionary(dictionary, services);
+        self
+    }
+
     /// Adds provided credentials
     pub fn with_credentials(mut self, credentials: Vec<CredentialAndPurposeKey>) -> Self {
         self.credentials.extend(credentials);
@@ -186,6 +300,25 @@ impl SecureChannelListenerOptions {
         self
     }
 
+    /// Only complete handshakes initiated by one of the given identifiers
+    pub fn with_allowed_identifiers(mut self, identifiers: Vec<Identifier>) -> Self {
+        self.admission = self.admission.with_allowed_identifiers(identifiers);
+        self
+    }
+
+    /// Only complete handshakes initiated by identities presenting credentials
+    /// with all the given attributes
+    pub fn with_required_attributes(mut self, attributes: BTreeMap<String, String>) -> Self {
+        self.admission = self.admission.with_required_attributes(attributes);
+        self
+    }
+
+    /// Limit the number of secure channels which can be opened concurrently by each identity
+    pub fn with_quotas(mut self, quotas: IdentityQuotas) -> Self {
+        self.quotas = Some(quotas);
+        self
+    }
+
     /// Freshly generated [`FlowControlId`]
     pub fn spawner_flow_control_id(&self) -> FlowControlId {
         self.flow_control_id.clone()
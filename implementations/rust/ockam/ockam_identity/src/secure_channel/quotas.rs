@@ -0,0 +1,268 @@
+use minicbor::{Decode, Encode};
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::string::ToString;
+use ockam_core::compat::sync::{Arc, Mutex, RwLock};
+use ockam_core::Result;
+use tracing::{debug, info};
+
+use crate::models::Identifier;
+use crate::storage::Storage;
+use crate::IdentityError;
+
+/// Storage namespace used to persist the per-identifier quota limits
+const QUOTAS_KEY: &str = "QUOTAS";
+
+/// Kind of resource which can be limited per identity
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaKind {
+    /// Secure channels accepted by a listener
+    SecureChannels,
+    /// Connections opened on a portal outlet
+    PortalConnections,
+}
+
+/// Maximum number of resources which can be used concurrently by an identity.
+/// `None` means that the resource is not limited
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Encode, Decode)]
+#[rustfmt::skip]
+#[cbor(map)]
+pub struct QuotaLimits {
+    /// Maximum number of secure channels established by an identity
+    #[n(1)] pub max_secure_channels: Option<u32>,
+    /// Maximum number of connections opened on the portal outlets by an identity
+    #[n(2)] pub max_portal_connections: Option<u32>,
+}
+
+impl QuotaLimits {
+    /// Limit the number of concurrent secure channels
+    pub fn with_max_secure_channels(mut self, max: u32) -> Self {
+        self.max_secure_channels = Some(max);
+        self
+    }
+
+    /// Limit the number of concurrent portal connections
+    pub fn with_max_portal_connections(mut self, max: u32) -> Self {
+        self.max_portal_connections = Some(max);
+        self
+    }
+
+    /// Return the limit for a given kind of resource
+    pub fn get(&self, kind: QuotaKind) -> Option<u32> {
+        match kind {
+            QuotaKind::SecureChannels => self.max_secure_channels,
+            QuotaKind::PortalConnections => self.max_portal_connections,
+        }
+    }
+}
+
+/// Per-identity quotas on the resources of a node.
+///
+/// Quotas protect a node shared by several members of a project: one noisy or compromised
+/// member can't open more than a fixed number of secure channels or portal connections.
+/// Usage is tracked in memory and released when the corresponding [`QuotaPermit`] is dropped.
+/// Limits specific to an identifier can be persisted in a [`Storage`].
+#[derive(Clone)]
+pub struct IdentityQuotas {
+    state: Arc<QuotasState>,
+}
+
+struct QuotasState {
+    default_limits: QuotaLimits,
+    limits: RwLock<BTreeMap<Identifier, QuotaLimits>>,
+    usage: Mutex<BTreeMap<(Identifier, QuotaKind), u32>>,
+    storage: Option<Arc<dyn Storage>>,
+}
+
+/// A resource used by an identity. The resource is released when the permit is dropped
+pub struct QuotaPermit {
+    state: Arc<QuotasState>,
+    identifier: Identifier,
+    kind: QuotaKind,
+}
+
+impl Drop for QuotaPermit {
+    fn drop(&mut self) {
+        let mut usage = self.state.usage.lock().unwrap();
+        let key = (self.identifier.clone(), self.kind);
+        if let Some(count) = usage.get_mut(&key) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                usage.remove(&key);
+            }
+        }
+    }
+}
+
+impl IdentityQuotas {
+    /// Create quotas applying the same limits to all identities
+    pub fn new(default_limits: QuotaLimits) -> Self {
+        Self {
+            state: Arc::new(QuotasState {
+                default_limits,
+                limits: RwLock::new(BTreeMap::new()),
+                usage: Mutex::new(BTreeMap::new()),
+                storage: None,
+            }),
+        }
+    }
+
+    /// Create quotas where the limits specific to an identifier are persisted in a storage.
+    /// The limits previously stored are loaded
+    pub async fn create_with_storage(
+        default_limits: QuotaLimits,
+        storage: Arc<dyn Storage>,
+    ) -> Result<Self> {
+        let mut limits = BTreeMap::new();
+        for id in storage.keys(QUOTAS_KEY).await? {
+            if let Some(value) = storage.get(&id, QUOTAS_KEY).await? {
+                let identifier = Identifier::try_from(id.as_str())?;
+                limits.insert(identifier, minicbor::decode(&value)?);
+            }
+        }
+        debug!("loaded quota limits for {} identities", limits.len());
+
+        Ok(Self {
+            state: Arc::new(QuotasState {
+                default_limits,
+                limits: RwLock::new(limits),
+                usage: Mutex::new(BTreeMap::new()),
+                storage: Some(storage),
+            }),
+        })
+    }
+
+    /// Set the limits of a specific identifier, overriding the default limits
+    pub async fn set_limits(&self, identifier: &Identifier, limits: QuotaLimits) -> Result<()> {
+        if let Some(storage) = &self.state.storage {
+            storage
+                .set(
+                    &identifier.to_string(),
+                    QUOTAS_KEY.to_string(),
+                    minicbor::to_vec(limits)?,
+                )
+                .await?;
+        }
+        self.state
+            .limits
+            .write()
+            .unwrap()
+            .insert(identifier.clone(), limits);
+        Ok(())
+    }
+
+    /// Remove the limits specific to an identifier. The default limits apply again
+    pub async fn remove_limits(&self, identifier: &Identifier) -> Result<()> {
+        if let Some(storage) = &self.state.storage {
+            storage.del(&identifier.to_string(), QUOTAS_KEY).await?;
+        }
+        self.state.limits.write().unwrap().remove(identifier);
+        Ok(())
+    }
+
+    /// Return the limits applying to an identifier
+    pub fn limits(&self, identifier: &Identifier) -> QuotaLimits {
+        self.state
+            .limits
+            .read()
+            .unwrap()
+            .get(identifier)
+            .copied()
+            .unwrap_or(self.state.default_limits)
+    }
+
+    /// Return the number of resources of a given kind currently used by an identifier
+    pub fn usage(&self, identifier: &Identifier, kind: QuotaKind) -> u32 {
+        self.state
+            .usage
+            .lock()
+            .unwrap()
+            .get(&(identifier.clone(), kind))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Use a resource on behalf of an identifier.
+    /// Return an error if the identifier already reached its limit
+    pub fn try_acquire(&self, identifier: &Identifier, kind: QuotaKind) -> Result<QuotaPermit> {
+        let limit = self.limits(identifier).get(kind);
+        let mut usage = self.state.usage.lock().unwrap();
+        let key = (identifier.clone(), kind);
+        // only the resources in use are tracked, so that the usage of the identities which
+        // were refused or are disconnected doesn't accumulate
+        let count = usage.get(&key).copied().unwrap_or(0);
+        if let Some(limit) = limit {
+            if count >= limit {
+                info!("{identifier} reached its quota of {limit} for {kind:?}");
+                return Err(IdentityError::QuotaExceeded.into());
+            }
+        }
+        usage.insert(key, count + 1);
+
+        Ok(QuotaPermit {
+            state: self.state.clone(),
+            identifier: identifier.clone(),
+            kind,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+    use core::str::FromStr;
+
+    #[tokio::test]
+    async fn test_quotas() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let bob = Identifier::from_str("I0000000000000000000000000000000000000002")?;
+        let quotas = IdentityQuotas::new(QuotaLimits::default().with_max_secure_channels(1));
+
+        let permit = quotas.try_acquire(&alice, QuotaKind::SecureChannels)?;
+        assert!(quotas
+            .try_acquire(&alice, QuotaKind::SecureChannels)
+            .is_err());
+        assert!(quotas.try_acquire(&bob, QuotaKind::SecureChannels).is_ok());
+        // portal connections are not limited
+        let _portal = quotas.try_acquire(&alice, QuotaKind::PortalConnections)?;
+        assert_eq!(quotas.usage(&alice, QuotaKind::SecureChannels), 1);
+
+        drop(permit);
+        assert_eq!(quotas.usage(&alice, QuotaKind::SecureChannels), 0);
+        assert!(quotas
+            .try_acquire(&alice, QuotaKind::SecureChannels)
+            .is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quotas_usage_is_removed() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let quotas = IdentityQuotas::new(QuotaLimits::default().with_max_secure_channels(0));
+
+        assert!(quotas
+            .try_acquire(&alice, QuotaKind::SecureChannels)
+            .is_err());
+        drop(quotas.try_acquire(&alice, QuotaKind::PortalConnections)?);
+        assert!(quotas.state.usage.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quotas_persistence() -> Result<()> {
+        let alice = Identifier::from_str("I0000000000000000000000000000000000000001")?;
+        let storage = InMemoryStorage::create();
+        let quotas =
+            IdentityQuotas::create_with_storage(QuotaLimits::default(), storage.clone()).await?;
+        quotas
+            .set_limits(
+                &alice,
+                QuotaLimits::default().with_max_portal_connections(2),
+            )
+            .await?;
+
+        let quotas = IdentityQuotas::create_with_storage(QuotaLimits::default(), storage).await?;
+        assert_eq!(quotas.limits(&alice).max_portal_connections, Some(2));
+        Ok(())
+    }
+}
@@ -1,7 +1,11 @@
 use core::fmt;
 use core::fmt::Formatter;
 use ockam_core::flow_control::FlowControlId;
-use ockam_core::Address;
+use ockam_core::{Address, Result};
+use ockam_node::Context;
+
+use crate::models::CredentialAndPurposeKey;
+use crate::{SecureChannels, TrustContext};
 
 /// Result of [`super::SecureChannels::create_secure_channel()`] call.
 #[derive(Debug, Clone)]
@@ -54,6 +58,27 @@ impl SecureChannel {
     pub fn encryptor_api_address(&self) -> &Address {
         &self.encryptor_api_address
     }
+
+    /// Exchange credentials again with the other party of this channel, without closing it.
+    /// See [`SecureChannels::exchange_credentials`]
+    pub async fn exchange_credentials(
+        &self,
+        ctx: &Context,
+        secure_channels: &SecureChannels,
+        credentials_service: impl Into<Address>,
+        trust_context: &TrustContext,
+        credential: CredentialAndPurposeKey,
+    ) -> Result<()> {
+        secure_channels
+            .exchange_credentials(
+                ctx,
+                &self.encryptor_address,
+                credentials_service,
+                trust_context,
+                credential,
+            )
+            .await
+    }
 }
 
 /// Result of [`super::SecureChannels::create_secure_channel_listener()`] call.
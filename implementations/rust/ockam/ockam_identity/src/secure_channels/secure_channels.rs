@@ -1,16 +1,22 @@
+use ockam_core::api::Request;
 use ockam_core::compat::sync::Arc;
-use ockam_core::Result;
-use ockam_core::{Address, Route};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{route, Address, Error, Result, Route};
+use ockam_node::api::Client;
 use ockam_node::Context;
+use tracing::debug;
 
 use crate::identities::Identities;
-use crate::models::Identifier;
+use crate::models::{CredentialAndPurposeKey, Identifier};
 use crate::secure_channel::handshake_worker::HandshakeWorker;
 use crate::secure_channel::{
-    Addresses, IdentityChannelListener, Role, SecureChannelListenerOptions, SecureChannelOptions,
-    SecureChannelRegistry,
+    Addresses, IdentityChannelListener, Role, SecureChannelAdmission, SecureChannelListenerOptions,
+    SecureChannelOptions, SecureChannelRegistry,
+};
+use crate::{
+    IdentityError, IdentitySecureChannelLocalInfo, SecureChannel, SecureChannelListener,
+    SecureChannelsBuilder, TrustContext, Vault,
 };
-use crate::{SecureChannel, SecureChannelListener, SecureChannelsBuilder, Vault};
 
 /// Identity implementation
 #[derive(Clone)]
@@ -115,6 +121,10 @@ impl SecureChannels {
             access_control.decryptor_outgoing_access_control,
             options.credentials,
             options.trust_context,
+            SecureChannelAdmission::default(),
+            None,
+            options.replay_window,
+            options.handshake,
             Some(route),
             Some(options.timeout),
             Role::Initiator,
@@ -132,4 +142,58 @@ impl SecureChannels {
     pub async fn stop_secure_channel(&self, ctx: &Context, channel: &Address) -> Result<()> {
         ctx.stop_worker(channel.clone()).await
     }
+
+    /// Exchange credentials again over an established secure channel, given its encryptor
+    /// address, for example when one of the parties obtained new attributes after the handshake.
+    ///
+    /// Our credential is presented to the credentials service of the other party, at
+    /// `credentials_service` on the other side of the channel, which presents its own credential
+    /// back. That credential is verified with the trust context and replaces the attributes
+    /// stored for the other party. The channel is kept open, even if the exchange fails
+    pub async fn exchange_credentials(
+        &self,
+        ctx: &Context,
+        channel: &Address,
+        credentials_service: impl Into<Address>,
+        trust_context: &TrustContext,
+        credential: CredentialAndPurposeKey,
+    ) -> Result<()> {
+        let entry = self
+            .secure_channel_registry
+            .get_channel_by_encryptor_address(channel)
+            .ok_or_else(|| {
+                Error::new(
+                    Origin::Identity,
+                    Kind::NotFound,
+                    format!("secure channel not found for encryptor address {channel}"),
+                )
+            })?;
+
+        let client = Client::new(&route![channel.clone(), credentials_service.into()], None);
+        let (reply, local_info) = client
+            .ask_with_local_info(
+                ctx,
+                Request::post("actions/present_mutual").body(credential),
+                None,
+            )
+            .await?;
+
+        // the credential must be presented back by the other party of that channel
+        let their_id =
+            IdentitySecureChannelLocalInfo::find_info_from_list(&local_info)?.their_identity_id();
+        if &their_id != entry.their_id() {
+            return Err(IdentityError::SecureChannelTrustCheckFailed.into());
+        }
+
+        let their_credential: CredentialAndPurposeKey = reply.success()?;
+        trust_context
+            .receive_presented_credential(
+                &self.identities.credentials().credentials_verification(),
+                &their_id,
+                &their_credential,
+            )
+            .await?;
+        debug!(%channel, %their_id, "Exchanged credentials over an established secure channel");
+        Ok(())
+    }
 }
@@ -4,7 +4,9 @@ use crate::identities::{Identities, IdentitiesRepository};
 use crate::secure_channel::SecureChannelRegistry;
 use crate::secure_channels::SecureChannels;
 use crate::storage::Storage;
-use crate::{IdentitiesBuilder, Vault, VaultStorage};
+use crate::{
+    CredentialsClock, HardwareAttestationVerifier, IdentitiesBuilder, Vault, VaultStorage,
+};
 
 /// This struct supports all the services related to secure channels
 #[derive(Clone)]
@@ -52,7 +54,35 @@ impl SecureChannelsBuilder {
             .identities_builder
             .with_identities_repository(identities.repository())
             .with_vault(identities.vault())
-            .with_purpose_keys_repository(identities.purpose_keys_repository());
+            .with_purpose_keys_repository(identities.purpose_keys_repository())
+            .with_credentials_clock(identities.credentials_clock());
+        self.identities_builder.hardware_attestation_verifier =
+            identities.hardware_attestation_verifier();
+        self
+    }
+
+    /// Set the clock settings used to issue and verify credentials
+    pub fn with_credentials_clock(mut self, credentials_clock: CredentialsClock) -> Self {
+        self.identities_builder = self
+            .identities_builder
+            .with_credentials_clock(credentials_clock);
+        self
+    }
+
+    /// Verify the hardware attestations of the Purpose Keys of the other identities
+    pub fn with_hardware_attestation_verifier(
+        mut self,
+        verifier: Arc<dyn HardwareAttestationVerifier>,
+    ) -> Self {
+        self.identities_builder = self
+            .identities_builder
+            .with_hardware_attestation_verifier(verifier);
+        self
+    }
+
+    /// Remember the verified signatures in a storage, which can be shared by several nodes
+    pub fn with_verification_cache(mut self, storage: Arc<dyn Storage>) -> Self {
+        self.identities_builder = self.identities_builder.with_verification_cache(storage);
         self
     }
 
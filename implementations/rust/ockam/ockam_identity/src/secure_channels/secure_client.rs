@@ -1,4 +1,4 @@
-use crate::{Identifier, SecureChannelOptions, TrustIdentifierPolicy};
+use crate::{Identifier, SecureChannelOptions, Timeouts, TrustIdentifierPolicy, DEFAULT_TIMEOUT};
 use minicbor::{Decode, Encode};
 
 use crate::{SecureChannel, SecureChannels};
@@ -36,6 +36,8 @@ pub struct SecureClient {
     client_identifier: Identifier,
     // default timeout to use for receiving a reply
     timeout: Duration,
+    // timeout of the secure channel handshake
+    handshake_timeout: Duration,
 }
 
 impl SecureClient {
@@ -54,8 +56,16 @@ impl SecureClient {
             server_identifier: server_identifier.clone(),
             client_identifier: client_identifier.clone(),
             timeout,
+            handshake_timeout: DEFAULT_TIMEOUT,
         }
     }
+
+    /// Use the handshake and management request timeouts of a set of [`Timeouts`]
+    pub fn with_timeouts(mut self, timeouts: &Timeouts) -> Self {
+        self.timeout = timeouts.management_request();
+        self.handshake_timeout = timeouts.handshake();
+        self
+    }
 }
 
 impl SecureClient {
@@ -160,7 +170,8 @@ impl SecureClient {
     /// Create a secure channel to the node
     pub async fn create_secure_channel(&self, ctx: &Context) -> Result<SecureChannel> {
         let options = SecureChannelOptions::new()
-            .with_trust_policy(TrustIdentifierPolicy::new(self.server_identifier.clone()));
+            .with_trust_policy(TrustIdentifierPolicy::new(self.server_identifier.clone()))
+            .with_timeout(self.handshake_timeout);
         self.secure_channels
             .create_secure_channel(
                 ctx,
@@ -0,0 +1,77 @@
+use core::time::Duration;
+
+use crate::DEFAULT_TIMEOUT;
+
+/// This is the default timeout for receiving the reply to a message
+pub const DEFAULT_MESSAGE_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeouts used when communicating with other nodes.
+///
+/// The default values are suitable for most networks but they can be increased
+/// for high-latency links, for example satellite links, where a handshake can take
+/// several round-trips of a few seconds each
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    handshake: Duration,
+    credential_exchange: Duration,
+    message_send: Duration,
+    management_request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            handshake: DEFAULT_TIMEOUT,
+            credential_exchange: DEFAULT_TIMEOUT,
+            message_send: DEFAULT_MESSAGE_SEND_TIMEOUT,
+            management_request: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl Timeouts {
+    /// Maximum duration of a secure channel handshake
+    pub fn handshake(&self) -> Duration {
+        self.handshake
+    }
+
+    /// Maximum duration for retrieving a credential from an authority
+    pub fn credential_exchange(&self) -> Duration {
+        self.credential_exchange
+    }
+
+    /// Maximum duration for receiving the reply to a message sent to another node
+    pub fn message_send(&self) -> Duration {
+        self.message_send
+    }
+
+    /// Maximum duration for receiving the response to a request sent to a node manager,
+    /// an authority or the Orchestrator
+    pub fn management_request(&self) -> Duration {
+        self.management_request
+    }
+
+    /// Set the maximum duration of a secure channel handshake
+    pub fn with_handshake(mut self, timeout: Duration) -> Self {
+        self.handshake = timeout;
+        self
+    }
+
+    /// Set the maximum duration for retrieving a credential from an authority
+    pub fn with_credential_exchange(mut self, timeout: Duration) -> Self {
+        self.credential_exchange = timeout;
+        self
+    }
+
+    /// Set the maximum duration for receiving the reply to a message sent to another node
+    pub fn with_message_send(mut self, timeout: Duration) -> Self {
+        self.message_send = timeout;
+        self
+    }
+
+    /// Set the maximum duration for receiving the response to a management request
+    pub fn with_management_request(mut self, timeout: Duration) -> Self {
+        self.management_request = timeout;
+        self
+    }
+}
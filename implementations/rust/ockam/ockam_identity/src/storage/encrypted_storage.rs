@@ -0,0 +1,382 @@
+use ockam_core::compat::rand::RngCore;
+use ockam_core::compat::{
+    boxed::Box,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use ockam_core::{async_trait, entropy, Result};
+use tracing::warn;
+
+use crate::identity::IdentityConstants;
+use crate::storage::storage_keys::{
+    entry_aad, invalid_data, StorageKey, StorageKeys, ENCRYPTION_MARKER_NAMESPACE,
+    INTEGRITY_MARKER_NAMESPACE, ROOT_KEY_NAMESPACE,
+};
+use crate::storage::{InMemoryStorage, IntegrityStorage, Storage, StorageEntry};
+
+/// Namespace for the data created during the enrollment of members
+pub const ENROLLMENT_KEY: &str = "ENROLLMENT";
+
+/// Namespaces whose values, stored in clear before the encryption was enabled, are
+/// encrypted when the storage is created for the first time
+pub(crate) const MIGRATED_NAMESPACES: [&str; 5] = [
+    IdentityConstants::SECURE_CHANNEL_PURPOSE_KEY,
+    IdentityConstants::CREDENTIALS_PURPOSE_KEY,
+    IdentityConstants::CHANGE_HISTORY_KEY,
+    IdentityConstants::ATTRIBUTES_KEY,
+    ENROLLMENT_KEY,
+];
+
+/// Prefix of encrypted values
+const ENCRYPTED_VALUE_MAGIC: &[u8] = b"OCKE\x01";
+const NONCE_LENGTH: usize = 12;
+
+/// Category of data stored at rest. Each keyspace is encrypted with its own key, so that
+/// an export of one category of data (for example an attributes dump) doesn't expose the others
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Keyspace {
+    /// Purpose keys attestations
+    PurposeKeys,
+    /// Identities change histories
+    ChangeHistory,
+    /// Attributes attested by an authority
+    Attributes,
+    /// Enrollment data
+    Enrollment,
+    /// Any other data
+    Other,
+}
+
+impl Keyspace {
+    /// Return the keyspace of a storage namespace
+    pub fn from_namespace(namespace: &str) -> Self {
+        match namespace {
+            IdentityConstants::SECURE_CHANNEL_PURPOSE_KEY
+            | IdentityConstants::CREDENTIALS_PURPOSE_KEY => Keyspace::PurposeKeys,
+            IdentityConstants::CHANGE_HISTORY_KEY => Keyspace::ChangeHistory,
+            IdentityConstants::ATTRIBUTES_KEY => Keyspace::Attributes,
+            ENROLLMENT_KEY => Keyspace::Enrollment,
+            _ => Keyspace::Other,
+        }
+    }
+
+    /// Label used to derive the key of this keyspace
+    fn label(&self) -> &'static [u8] {
+        match self {
+            Keyspace::PurposeKeys => b"ockam.storage.purpose_keys",
+            Keyspace::ChangeHistory => b"ockam.storage.change_history",
+            Keyspace::Attributes => b"ockam.storage.attributes",
+            Keyspace::Enrollment => b"ockam.storage.enrollment",
+            Keyspace::Other => b"ockam.storage.other",
+        }
+    }
+}
+
+/// Storage encrypting the values of another storage.
+///
+/// Each [`Keyspace`] is encrypted with its own key, derived from a [`StorageKey`].
+/// The identifier and namespace of each entry are used as
+/// associated data, so that an encrypted value can't be moved to another entry.
+/// The values are stored in an [`IntegrityStorage`], so the change histories and purpose keys
+/// are also integrity protected.
+pub struct EncryptedStorage {
+    storage: Arc<dyn Storage>,
+    keys: Arc<StorageKeys>,
+}
+
+impl EncryptedStorage {
+    /// Create an encrypted storage on top of an existing storage.
+    /// The values stored in clear before the encryption was enabled are encrypted when the
+    /// storage is created for the first time. The creation fails if the storage is protected
+    /// by another key, or if the markers of its protection were removed or forged
+    pub async fn create(storage: Arc<dyn Storage>, key: &StorageKey) -> Result<Arc<Self>> {
+        let integrity_storage = IntegrityStorage::create(storage, key).await?;
+        Ok(Arc::new(Self::with_keys(
+            integrity_storage.clone(),
+            integrity_storage.keys(),
+        )))
+    }
+
+    /// Create an encrypted storage with the keys of a protected storage
+    pub(crate) fn with_keys(storage: Arc<dyn Storage>, keys: Arc<StorageKeys>) -> Self {
+        Self { storage, keys }
+    }
+
+    /// Return true if a value was encrypted
+    pub(crate) fn is_encrypted(value: &[u8]) -> bool {
+        value.starts_with(ENCRYPTED_VALUE_MAGIC)
+    }
+
+    /// Protect a storage, encrypted and integrity protected with a key, with another key.
+    /// All the values are encrypted and tagged again with the new key, and written at once
+    /// with the value identifying the new key. Nothing is done if the storage is already
+    /// protected by the new key
+    pub async fn change_key(
+        storage: Arc<dyn Storage>,
+        key: &StorageKey,
+        new_key: &StorageKey,
+    ) -> Result<()> {
+        if new_key.protects(storage.as_ref()).await? {
+            return Ok(());
+        }
+        let encrypted_storage = Self::create(storage.clone(), key).await?;
+        let integrity_storage = IntegrityStorage::create(storage.clone(), key).await?;
+
+        // the values are protected with the new key in memory first
+        let protected = InMemoryStorage::create();
+        let new_encrypted_storage = Self::create(protected.clone(), new_key).await?;
+        let new_integrity_storage = IntegrityStorage::create(protected.clone(), new_key).await?;
+        for namespace in MIGRATED_NAMESPACES {
+            for id in storage.keys(namespace).await? {
+                if let Some(value) = encrypted_storage.get(&id, namespace).await? {
+                    new_encrypted_storage
+                        .set(&id, namespace.to_string(), value)
+                        .await?;
+                }
+            }
+        }
+        // the verified signatures are only integrity protected
+        let namespace = IdentityConstants::VERIFIED_SIGNATURES_KEY;
+        for id in storage.keys(namespace).await? {
+            if let Some(value) = integrity_storage.get(&id, namespace).await? {
+                new_integrity_storage
+                    .set(&id, namespace.to_string(), value)
+                    .await?;
+            }
+        }
+
+        let mut entries = vec![];
+        for namespace in MIGRATED_NAMESPACES.into_iter().chain([
+            IdentityConstants::VERIFIED_SIGNATURES_KEY,
+            ROOT_KEY_NAMESPACE,
+            INTEGRITY_MARKER_NAMESPACE,
+            ENCRYPTION_MARKER_NAMESPACE,
+        ]) {
+            for id in protected.keys(namespace).await? {
+                if let Some(value) = protected.get(&id, namespace).await? {
+                    entries.push(StorageEntry::new(id, namespace, value));
+                }
+            }
+        }
+        storage.set_all(entries).await
+    }
+
+    pub(crate) async fn encrypt(&self, id: &str, namespace: &str, value: &[u8]) -> Result<Vec<u8>> {
+        let key = self
+            .keys
+            .key(Keyspace::from_namespace(namespace).label())
+            .await?;
+        let mut nonce = [0u8; NONCE_LENGTH];
+        entropy::rng().fill_bytes(&mut nonce);
+        let cipher_text = self
+            .keys
+            .vault()
+            .aead_encrypt(&key, value, &nonce, &entry_aad(id, namespace))
+            .await?;
+
+        let mut encrypted =
+            Vec::with_capacity(ENCRYPTED_VALUE_MAGIC.len() + NONCE_LENGTH + cipher_text.len());
+        encrypted.extend_from_slice(ENCRYPTED_VALUE_MAGIC);
+        encrypted.extend_from_slice(&nonce);
+        encrypted.extend_from_slice(&cipher_text);
+        Ok(encrypted)
+    }
+
+    async fn decrypt(&self, id: &str, namespace: &str, value: Vec<u8>) -> Result<Vec<u8>> {
+        let encrypted = match value.strip_prefix(ENCRYPTED_VALUE_MAGIC) {
+            Some(encrypted) if encrypted.len() >= NONCE_LENGTH => encrypted,
+            // all the values stored in clear were encrypted when the storage was created
+            _ => {
+                warn!("the value of {id} in {namespace} is not encrypted");
+                return Err(invalid_data("a stored value is not encrypted"));
+            }
+        };
+        let (nonce, cipher_text) = encrypted.split_at(NONCE_LENGTH);
+        let key = self
+            .keys
+            .key(Keyspace::from_namespace(namespace).label())
+            .await?;
+        self.keys
+            .vault()
+            .aead_decrypt(&key, cipher_text, nonce, &entry_aad(id, namespace))
+            .await
+    }
+}
+
+#[async_trait]
+impl Storage for EncryptedStorage {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.storage.get(id, key).await? {
+            Some(value) => Ok(Some(self.decrypt(id, key, value).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let encrypted = self.encrypt(id, &key, &val).await?;
+        self.storage.set(id, key, encrypted).await
+    }
+
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()> {
+        let mut encrypted = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let value = self.encrypt(&entry.id, &entry.key, &entry.value).await?;
+            encrypted.push(StorageEntry::new(entry.id, entry.key, value));
+        }
+        self.storage.set_all(encrypted).await
+    }
+
+    async fn get_or_set(&self, id: &str, key: String, val: Vec<u8>) -> Result<Vec<u8>> {
+        let encrypted = self.encrypt(id, &key, &val).await?;
+        let value = self.storage.get_or_set(id, key.clone(), encrypted).await?;
+        self.decrypt(id, &key, value).await
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.storage.del(id, key).await
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        self.storage.keys(namespace).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage_keys::MARKER_ID;
+
+    #[tokio::test]
+    async fn test_encrypted_storage() -> Result<()> {
+        let key = StorageKey::generate();
+        let inner = InMemoryStorage::create();
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+
+        let attributes = b"attributes".to_vec();
+        storage
+            .set(
+                "id",
+                IdentityConstants::ATTRIBUTES_KEY.to_string(),
+                attributes.clone(),
+            )
+            .await?;
+
+        // the value is not stored in clear
+        let stored = inner
+            .get("id", IdentityConstants::ATTRIBUTES_KEY)
+            .await?
+            .unwrap();
+        assert_ne!(stored, attributes);
+        assert_eq!(
+            storage.get("id", IdentityConstants::ATTRIBUTES_KEY).await?,
+            Some(attributes.clone())
+        );
+
+        // a value can't be decrypted under another keyspace
+        inner
+            .set(
+                "id",
+                IdentityConstants::CHANGE_HISTORY_KEY.to_string(),
+                stored,
+            )
+            .await?;
+        assert!(storage
+            .get("id", IdentityConstants::CHANGE_HISTORY_KEY)
+            .await
+            .is_err());
+
+        // the storage can be opened again with the same key, but not with another one
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+        assert_eq!(
+            storage.get("id", IdentityConstants::ATTRIBUTES_KEY).await?,
+            Some(attributes)
+        );
+        assert!(EncryptedStorage::create(inner, &StorageKey::generate())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage_migration() -> Result<()> {
+        let key = StorageKey::generate();
+        let inner = InMemoryStorage::create();
+        let namespace = IdentityConstants::CHANGE_HISTORY_KEY;
+        let legacy = b"legacy change history".to_vec();
+        inner
+            .set("legacy", namespace.to_string(), legacy.clone())
+            .await?;
+
+        // the values stored in clear are encrypted when the encryption is enabled
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+        assert_ne!(inner.get("legacy", namespace).await?.unwrap(), legacy);
+        assert_eq!(
+            storage.get("legacy", namespace).await?,
+            Some(legacy.clone())
+        );
+
+        // then the values stored in clear are rejected
+        inner
+            .set("other", namespace.to_string(), legacy.clone())
+            .await?;
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+        assert!(storage.get("other", namespace).await.is_err());
+        assert_eq!(storage.get("legacy", namespace).await?, Some(legacy));
+
+        // and a storage without its marker is rejected instead of being encrypted again
+        inner.del(MARKER_ID, ENCRYPTION_MARKER_NAMESPACE).await?;
+        assert!(EncryptedStorage::create(inner, &key).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_storage_with_passphrase() -> Result<()> {
+        let inner = InMemoryStorage::create();
+        let key = StorageKey::from_passphrase(inner.as_ref(), "secret").await?;
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+
+        let attributes = b"attributes".to_vec();
+        let namespace = IdentityConstants::ATTRIBUTES_KEY;
+        storage
+            .set("id", namespace.to_string(), attributes.clone())
+            .await?;
+        assert_ne!(inner.get("id", namespace).await?.unwrap(), attributes);
+
+        // the same key is derived from the same passphrase
+        let key = StorageKey::from_passphrase(inner.as_ref(), "secret").await?;
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+        assert_eq!(storage.get("id", namespace).await?, Some(attributes));
+
+        // but not from another passphrase
+        let other_key = StorageKey::from_passphrase(inner.as_ref(), "other").await?;
+        assert!(EncryptedStorage::create(inner, &other_key).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_change_key() -> Result<()> {
+        let key = StorageKey::generate();
+        let inner = InMemoryStorage::create();
+        let storage = EncryptedStorage::create(inner.clone(), &key).await?;
+        let change_history = b"change history".to_vec();
+        let namespace = IdentityConstants::CHANGE_HISTORY_KEY;
+        storage
+            .set("id", namespace.to_string(), change_history.clone())
+            .await?;
+
+        // an existing storage can be protected by a passphrase
+        let new_key = StorageKey::from_passphrase(inner.as_ref(), "secret").await?;
+        EncryptedStorage::change_key(inner.clone(), &key, &new_key).await?;
+        assert!(new_key.protects(inner.as_ref()).await?);
+        assert!(IntegrityStorage::create(inner.clone(), &key).await.is_err());
+
+        let new_key = StorageKey::from_passphrase(inner.as_ref(), "secret").await?;
+        let storage = EncryptedStorage::create(inner.clone(), &new_key).await?;
+        assert_eq!(storage.get("id", namespace).await?, Some(change_history));
+
+        // changing the key again has no effect
+        EncryptedStorage::change_key(inner, &key, &new_key).await?;
+        Ok(())
+    }
+}
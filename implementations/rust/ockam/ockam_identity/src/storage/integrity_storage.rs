@@ -0,0 +1,325 @@
+use hmac::{Hmac, Mac};
+use ockam_core::compat::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use ockam_core::{async_trait, Result};
+use sha2::Sha256;
+use tracing::{info, warn};
+
+use crate::identity::IdentityConstants;
+use crate::storage::encrypted_storage::MIGRATED_NAMESPACES;
+use crate::storage::storage_keys::{entry_aad, invalid_data, StorageKey, StorageKeys};
+use crate::storage::{EncryptedStorage, Storage, StorageEntry};
+use crate::IdentityError;
+
+/// Label used to derive the key of the integrity tags
+const INTEGRITY_LABEL: &[u8] = b"ockam.storage.integrity";
+
+/// Length of a HMAC-SHA256 tag
+const TAG_LENGTH: usize = 32;
+
+/// Namespaces whose values are protected: tampering with them could make us trust another identity
+const PROTECTED_NAMESPACES: [&str; 4] = [
+    IdentityConstants::CHANGE_HISTORY_KEY,
+    IdentityConstants::SECURE_CHANNEL_PURPOSE_KEY,
+    IdentityConstants::CREDENTIALS_PURPOSE_KEY,
+    IdentityConstants::VERIFIED_SIGNATURES_KEY,
+];
+
+/// Storage protecting the integrity of the identities change histories and purpose keys
+/// stored in another storage.
+///
+/// Each protected value is stored with a HMAC-SHA256 tag computed over the identifier, the
+/// namespace and the value, with a key derived from a [`StorageKey`]. The tag is checked when
+/// the value is read, so that a change history swapped in the database file is rejected
+/// instead of being silently trusted.
+pub struct IntegrityStorage {
+    storage: Arc<dyn Storage>,
+    keys: Arc<StorageKeys>,
+}
+
+impl IntegrityStorage {
+    /// Create an integrity protected storage on top of an existing storage.
+    /// A storage which is not protected yet is protected with the given key: the values written
+    /// before are encrypted and tagged at once. The creation fails if the storage is protected
+    /// by another key, or if the markers of its protection were removed or forged
+    pub async fn create(storage: Arc<dyn Storage>, key: &StorageKey) -> Result<Arc<Self>> {
+        let keys = Arc::new(StorageKeys::create(storage.as_ref(), key).await?);
+        let integrity_storage = Self { storage, keys };
+
+        if !integrity_storage
+            .keys
+            .is_protecting(integrity_storage.storage.as_ref())
+            .await?
+        {
+            integrity_storage.protect_existing_values().await?;
+        }
+        Ok(Arc::new(integrity_storage))
+    }
+
+    /// Keys protecting the storage
+    pub(crate) fn keys(&self) -> Arc<StorageKeys> {
+        self.keys.clone()
+    }
+
+    /// Encrypt and tag the values written before the protection was enabled, and set the key
+    /// verifier and the markers, at once.
+    ///
+    /// A value which is already protected means that another process protected the storage at
+    /// the same time, or that the markers of a protected storage were removed. The storage is
+    /// only accepted in the first case, so that forged values are never protected
+    async fn protect_existing_values(&self) -> Result<()> {
+        let encrypted_storage = EncryptedStorage::with_keys(self.storage.clone(), self.keys());
+        let mut namespaces = PROTECTED_NAMESPACES.to_vec();
+        namespaces.extend(
+            MIGRATED_NAMESPACES
+                .into_iter()
+                .filter(|namespace| !Self::is_protected(namespace)),
+        );
+
+        let mut entries = vec![];
+        for namespace in namespaces {
+            let is_migrated = MIGRATED_NAMESPACES.contains(&namespace);
+            for id in self.storage.keys(namespace).await? {
+                let value = match self.storage.get(&id, namespace).await? {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let is_already_protected = if Self::is_protected(namespace) {
+                    self.check_tag(&id, namespace, &value).is_some()
+                } else {
+                    EncryptedStorage::is_encrypted(&value)
+                };
+                if is_already_protected {
+                    return self.check_protected_concurrently().await;
+                }
+
+                let value = if is_migrated {
+                    encrypted_storage.encrypt(&id, namespace, &value).await?
+                } else {
+                    value
+                };
+                let value = if Self::is_protected(namespace) {
+                    self.tag(&id, namespace, value)
+                } else {
+                    value
+                };
+                entries.push(StorageEntry::new(id, namespace, value));
+            }
+        }
+        let count = entries.len();
+        entries.extend(self.keys.protection_entries());
+        self.storage.set_all(entries).await?;
+
+        // another process might have protected the storage with another key at the same time
+        self.check_protected_concurrently().await?;
+        info!("enabled the protection of {count} stored values");
+        Ok(())
+    }
+
+    /// Check that the storage is protected by our key, after finding protected values in a
+    /// storage which was not protected yet
+    async fn check_protected_concurrently(&self) -> Result<()> {
+        if self.keys.is_protecting(self.storage.as_ref()).await? {
+            Ok(())
+        } else {
+            warn!("the storage contains protected values but its key verifier is missing");
+            Err(invalid_data("the storage protection was removed"))
+        }
+    }
+
+    fn is_protected(namespace: &str) -> bool {
+        PROTECTED_NAMESPACES.contains(&namespace)
+    }
+
+    fn mac(&self, id: &str, namespace: &str, value: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.keys.mac_key(INTEGRITY_LABEL))
+            .expect("HMAC accepts keys of any length");
+        mac.update(&entry_aad(id, namespace));
+        mac.update(&[0]);
+        mac.update(value);
+        mac
+    }
+
+    /// Append a tag to a value
+    fn tag(&self, id: &str, namespace: &str, mut value: Vec<u8>) -> Vec<u8> {
+        let tag = self.mac(id, namespace, &value).finalize().into_bytes();
+        value.extend_from_slice(&tag);
+        value
+    }
+
+    /// Return the length of the value without its tag if the value has a valid tag
+    fn check_tag(&self, id: &str, namespace: &str, value: &[u8]) -> Option<usize> {
+        let length = value.len().checked_sub(TAG_LENGTH)?;
+        let (value, tag) = value.split_at(length);
+        self.mac(id, namespace, value)
+            .verify_slice(tag)
+            .ok()
+            .map(|_| length)
+    }
+
+    /// Check the tag of a value and return the value without its tag
+    fn verify(&self, id: &str, namespace: &str, mut value: Vec<u8>) -> Result<Vec<u8>> {
+        match self.check_tag(id, namespace, &value) {
+            Some(length) => {
+                value.truncate(length);
+                Ok(value)
+            }
+            None => {
+                warn!("the value of {id} in {namespace} doesn't match its integrity tag");
+                Err(IdentityError::IntegrityCheckFailed.into())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Storage for IntegrityStorage {
+    async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.storage.get(id, key).await? {
+            Some(value) if Self::is_protected(key) => Ok(Some(self.verify(id, key, value)?)),
+            value => Ok(value),
+        }
+    }
+
+    async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
+        let val = if Self::is_protected(&key) {
+            self.tag(id, &key, val)
+        } else {
+            val
+        };
+        self.storage.set(id, key, val).await
+    }
+
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()> {
+        let entries = entries
+            .into_iter()
+            .map(|mut entry| {
+                if Self::is_protected(&entry.key) {
+                    entry.value = self.tag(&entry.id, &entry.key, entry.value);
+                }
+                entry
+            })
+            .collect();
+        self.storage.set_all(entries).await
+    }
+
+    async fn get_or_set(&self, id: &str, key: String, val: Vec<u8>) -> Result<Vec<u8>> {
+        if !Self::is_protected(&key) {
+            return self.storage.get_or_set(id, key, val).await;
+        }
+        let tagged = self.tag(id, &key, val);
+        let value = self.storage.get_or_set(id, key.clone(), tagged).await?;
+        self.verify(id, &key, value)
+    }
+
+    async fn del(&self, id: &str, key: &str) -> Result<()> {
+        self.storage.del(id, key).await
+    }
+
+    async fn keys(&self, namespace: &str) -> Result<Vec<String>> {
+        self.storage.keys(namespace).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::storage_keys::{
+        ENCRYPTION_MARKER_NAMESPACE, INTEGRITY_MARKER_NAMESPACE, MARKER_ID, ROOT_KEY_ID,
+        ROOT_KEY_NAMESPACE,
+    };
+    use crate::storage::InMemoryStorage;
+
+    #[tokio::test]
+    async fn test_integrity_storage() -> Result<()> {
+        let key = StorageKey::generate();
+        let inner = InMemoryStorage::create();
+        let namespace = IdentityConstants::VERIFIED_SIGNATURES_KEY;
+        let legacy = b"legacy signature".to_vec();
+        inner
+            .set("legacy", namespace.to_string(), legacy.clone())
+            .await?;
+
+        // existing values are tagged when the protection is enabled
+        let storage = IntegrityStorage::create(inner.clone(), &key).await?;
+        assert_eq!(storage.get("legacy", namespace).await?, Some(legacy));
+
+        let alice = b"alice signature".to_vec();
+        let bob = b"bob signature".to_vec();
+        for (id, value) in [("alice", &alice), ("bob", &bob)] {
+            storage
+                .set(id, namespace.to_string(), value.clone())
+                .await?;
+        }
+        assert_eq!(storage.get("alice", namespace).await?, Some(alice));
+
+        // swapping the value of bob for the one of alice is detected
+        let stored_bob = inner.get("bob", namespace).await?.unwrap();
+        inner
+            .set("alice", namespace.to_string(), stored_bob)
+            .await?;
+        let storage = IntegrityStorage::create(inner.clone(), &key).await?;
+        assert!(storage.get("alice", namespace).await.is_err());
+
+        // untagged values are rejected once the protection is enabled
+        inner.set("bob", namespace.to_string(), bob).await?;
+        assert!(storage.get("bob", namespace).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reject_removed_protection() -> Result<()> {
+        let key = StorageKey::generate();
+        let inner = InMemoryStorage::create();
+        let namespace = IdentityConstants::VERIFIED_SIGNATURES_KEY;
+        inner
+            .set(
+                "legacy",
+                namespace.to_string(),
+                b"legacy signature".to_vec(),
+            )
+            .await?;
+        IntegrityStorage::create(inner.clone(), &key).await?;
+
+        // only the first key protecting a storage is accepted
+        assert!(
+            IntegrityStorage::create(inner.clone(), &StorageKey::generate())
+                .await
+                .is_err()
+        );
+
+        // a storage without its marker is rejected instead of being tagged again
+        let marker = inner
+            .get(MARKER_ID, INTEGRITY_MARKER_NAMESPACE)
+            .await?
+            .unwrap();
+        inner.del(MARKER_ID, INTEGRITY_MARKER_NAMESPACE).await?;
+        assert!(IntegrityStorage::create(inner.clone(), &key).await.is_err());
+
+        // and so is a storage with a forged marker
+        inner
+            .set(MARKER_ID, INTEGRITY_MARKER_NAMESPACE.to_string(), vec![1])
+            .await?;
+        assert!(IntegrityStorage::create(inner.clone(), &key).await.is_err());
+
+        inner
+            .set(MARKER_ID, INTEGRITY_MARKER_NAMESPACE.to_string(), marker)
+            .await?;
+        IntegrityStorage::create(inner.clone(), &key).await?;
+
+        // a storage containing tagged values without its key verifier and markers is rejected
+        inner.del(ROOT_KEY_ID, ROOT_KEY_NAMESPACE).await?;
+        inner.del(MARKER_ID, INTEGRITY_MARKER_NAMESPACE).await?;
+        inner.del(MARKER_ID, ENCRYPTION_MARKER_NAMESPACE).await?;
+        inner
+            .set(
+                "forged",
+                namespace.to_string(),
+                b"forged signature".to_vec(),
+            )
+            .await?;
+        assert!(IntegrityStorage::create(inner.clone(), &key).await.is_err());
+        assert!(inner.get("forged", namespace).await?.is_some());
+        assert!(inner.get(ROOT_KEY_ID, ROOT_KEY_NAMESPACE).await?.is_none());
+        Ok(())
+    }
+}
@@ -7,15 +7,39 @@ use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{Error, Result};
 use ockam_node::tokio::task::{self, JoinError};
 
-use crate::storage::Storage;
+use crate::storage::{MaintenanceReport, Storage, StorageEntry};
 
 use core::str;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
 use lmdb::{Cursor, Database, Environment, Transaction};
 use std::fmt;
 use std::path::Path;
+use std::time::Instant;
 use tokio_retry::strategy::{jitter, FixedInterval};
 use tokio_retry::Retry;
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// Operations on a database taking longer than this duration are logged as slow operations
+pub const DEFAULT_SLOW_OPERATION_THRESHOLD: Duration = Duration::from_millis(500);
+
+static OPERATIONS: AtomicU64 = AtomicU64::new(0);
+static SLOW_OPERATIONS: AtomicU64 = AtomicU64::new(0);
+static TOTAL_DURATION_US: AtomicU64 = AtomicU64::new(0);
+static MAX_DURATION_US: AtomicU64 = AtomicU64::new(0);
+
+/// Counters of the operations made on all the LMDB databases of the current process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LmdbStatistics {
+    /// Number of operations
+    pub operations: u64,
+    /// Number of operations taking longer than the slow operation threshold
+    pub slow_operations: u64,
+    /// Total duration of the operations, in microseconds
+    pub total_duration_us: u64,
+    /// Duration of the longest operation, in microseconds
+    pub max_duration_us: u64,
+}
 
 /// Storage using the LMDB database
 #[derive(Clone)]
@@ -24,6 +48,10 @@ pub struct LmdbStorage {
     pub env: Arc<Environment>,
     /// lmdb database file
     pub map: Database,
+    /// name of the database file, used to tag the logged slow operations
+    tag: Arc<str>,
+    /// operations taking longer than this duration are logged as slow operations
+    slow_operation_threshold: Duration,
 }
 
 impl fmt::Debug for LmdbStorage {
@@ -46,6 +74,12 @@ impl LmdbStorage {
         Retry::spawn(retry_strategy, || async { Self::make(path).await }).await
     }
 
+    /// Open a database, or create it, without retrying while its files are held by another
+    /// process
+    pub async fn open<P: AsRef<Path>>(p: P) -> Result<Self> {
+        Self::make(p.as_ref()).await
+    }
+
     async fn make(p: &Path) -> Result<Self> {
         debug!("create the LMDB database");
         std::fs::create_dir_all(p.parent().unwrap())
@@ -59,15 +93,106 @@ impl LmdbStorage {
         let map = env
             .create_db(Some("map"), lmdb::DatabaseFlags::empty())
             .map_err(map_lmdb_err)?;
+        let tag = p
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
         Ok(LmdbStorage {
             env: Arc::new(env),
             map,
+            tag: tag.into(),
+            slow_operation_threshold: DEFAULT_SLOW_OPERATION_THRESHOLD,
         })
     }
 
+    /// Open an existing database in read-only mode.
+    /// All the writes fail, and the database file is never created
+    pub async fn open_read_only<P: AsRef<Path>>(p: P) -> Result<Self> {
+        let p = p.as_ref().to_path_buf();
+        debug!(path = %p.display(), "open the LMDB database in read-only mode");
+        let env = Environment::new()
+            .set_flags(
+                lmdb::EnvironmentFlags::NO_SUB_DIR
+                    | lmdb::EnvironmentFlags::NO_TLS
+                    | lmdb::EnvironmentFlags::READ_ONLY,
+            )
+            .set_max_dbs(1)
+            .open(p.as_ref())
+            .map_err(map_lmdb_err)?;
+        let map = env.open_db(Some("map")).map_err(map_lmdb_err)?;
+        let tag = p
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        Ok(LmdbStorage {
+            env: Arc::new(env),
+            map,
+            tag: tag.into(),
+            slow_operation_threshold: DEFAULT_SLOW_OPERATION_THRESHOLD,
+        })
+    }
+
+    /// Name of the database file
+    pub fn name(&self) -> &str {
+        &self.tag
+    }
+
+    /// Log the operations, and the transactions, on this database taking longer than
+    /// a given duration
+    pub fn with_slow_operation_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_operation_threshold = threshold;
+        self
+    }
+
+    /// Return the counters of the operations made on all the LMDB databases of the
+    /// current process
+    pub fn statistics() -> LmdbStatistics {
+        LmdbStatistics {
+            operations: OPERATIONS.load(Ordering::Relaxed),
+            slow_operations: SLOW_OPERATIONS.load(Ordering::Relaxed),
+            total_duration_us: TOTAL_DURATION_US.load(Ordering::Relaxed),
+            max_duration_us: MAX_DURATION_US.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Run a blocking operation on the database and record its duration.
+    /// The duration doesn't include the time spent waiting for a blocking thread, but it
+    /// includes the time spent waiting for the write lock of the database
+    async fn run<T, F>(&self, operation: &'static str, key: &str, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T> + Send + 'static,
+    {
+        let (result, duration) = task::spawn_blocking(move || {
+            let started = Instant::now();
+            let result = f();
+            (result, started.elapsed())
+        })
+        .await
+        .map_err(map_join_err)?;
+        let duration_us = duration.as_micros() as u64;
+
+        OPERATIONS.fetch_add(1, Ordering::Relaxed);
+        TOTAL_DURATION_US.fetch_add(duration_us, Ordering::Relaxed);
+        MAX_DURATION_US.fetch_max(duration_us, Ordering::Relaxed);
+        if duration > self.slow_operation_threshold {
+            SLOW_OPERATIONS.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                database = %self.tag,
+                operation,
+                duration_ms = duration.as_millis() as u64,
+                "slow LMDB operation"
+            );
+            // the keys can contain identifiers or names, they are only logged for debugging
+            debug!(database = %self.tag, operation, key, "key of the slow LMDB operation");
+        }
+        result
+    }
+
     /// Write a new binary value for a given key in the database
     pub async fn write(&self, k: String, v: Vec<u8>) -> Result<()> {
         let d = self.clone();
+        let key = k.clone();
         let t = move || {
             let mut w = d.env.begin_rw_txn().map_err(map_lmdb_err)?;
             w.put(d.map, &k, &v, lmdb::WriteFlags::empty())
@@ -75,12 +200,118 @@ impl LmdbStorage {
             w.commit().map_err(map_lmdb_err)?;
             Ok(())
         };
-        task::spawn_blocking(t).await.map_err(map_join_err)?
+        self.run("write", &key, t).await
+    }
+
+    /// Write several binary values in a single transaction
+    pub async fn write_all(&self, entries: Vec<(String, Vec<u8>)>) -> Result<()> {
+        let d = self.clone();
+        let t = move || {
+            let mut w = d.env.begin_rw_txn().map_err(map_lmdb_err)?;
+            for (k, v) in entries {
+                w.put(d.map, &k, &v, lmdb::WriteFlags::empty())
+                    .map_err(map_lmdb_err)?;
+            }
+            w.commit().map_err(map_lmdb_err)?;
+            Ok(())
+        };
+        self.run("write_all", "", t).await
+    }
+
+    /// Write a binary value for a given key if there is no value yet, and return the value
+    /// of the key
+    pub async fn read_or_write(&self, k: String, v: Vec<u8>) -> Result<Vec<u8>> {
+        let d = self.clone();
+        let key = k.clone();
+        let t = move || {
+            let mut w = d.env.begin_rw_txn().map_err(map_lmdb_err)?;
+            match w.put(d.map, &k, &v, lmdb::WriteFlags::NO_OVERWRITE) {
+                Ok(()) => {
+                    w.commit().map_err(map_lmdb_err)?;
+                    Ok(v)
+                }
+                Err(lmdb::Error::KeyExist) => {
+                    let existing = w.get(d.map, &k).map_err(map_lmdb_err)?.to_vec();
+                    w.abort();
+                    Ok(existing)
+                }
+                Err(e) => Err(map_lmdb_err(e)),
+            }
+        };
+        self.run("read_or_write", &key, t).await
+    }
+
+    /// Copy a consistent snapshot of the database to another file.
+    /// The snapshot is taken from a single read transaction, so this function can be called
+    /// while the database is being used by other processes
+    pub async fn backup_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| Error::new(Origin::Node, Kind::Io, e))?;
+        }
+        let backup = LmdbStorage::open(&path).await?;
+
+        let d = self.clone();
+        let t = move || {
+            let r = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
+            let mut w = backup.env.begin_rw_txn().map_err(map_lmdb_err)?;
+            let mut cursor = r.open_ro_cursor(d.map).map_err(map_lmdb_err)?;
+            for entry in cursor.iter() {
+                let (k, v) = entry.map_err(map_lmdb_err)?;
+                w.put(backup.map, &k, &v, lmdb::WriteFlags::empty())
+                    .map_err(map_lmdb_err)?;
+            }
+            w.commit().map_err(map_lmdb_err)?;
+            Ok(())
+        };
+        self.run("backup", "", t).await
+    }
+
+    /// Flush the database to disk and measure its free pages.
+    /// The free pages of an LMDB database are reused by the next writes, so its file never shrinks
+    pub async fn maintain(&self) -> Result<MaintenanceReport> {
+        let d = self.clone();
+        let started = Instant::now();
+        let t = move || {
+            d.env.sync(true).map_err(map_lmdb_err)?;
+            let page_size = d.env.stat().map_err(map_lmdb_err)?.page_size() as u64;
+            let used_pages = d.env.info().map_err(map_lmdb_err)?.last_pgno() as u64 + 1;
+            let free_pages = d.env.freelist().map_err(map_lmdb_err)? as u64;
+            Ok((used_pages * page_size, free_pages * page_size))
+        };
+        let (size, free_bytes) = self.run("maintain", "", t).await?;
+        let report = MaintenanceReport {
+            database: self.tag.to_string(),
+            size_before: size,
+            size_after: size,
+            free_bytes,
+            duration: started.elapsed(),
+        };
+        report.record();
+        Ok(report)
+    }
+
+    /// Read all the entries of the database and return their number.
+    /// An error is returned if a page of the database can't be read
+    pub async fn check_integrity(&self) -> Result<u64> {
+        let d = self.clone();
+        let t = move || {
+            let r = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
+            let mut cursor = r.open_ro_cursor(d.map).map_err(map_lmdb_err)?;
+            let mut entries = 0;
+            for entry in cursor.iter() {
+                entry.map_err(map_lmdb_err)?;
+                entries += 1;
+            }
+            Ok(entries)
+        };
+        self.run("check_integrity", "", t).await
     }
 
     /// Delete a database entry
     pub async fn delete(&self, k: String) -> Result<()> {
         let d = self.clone();
+        let key = k.clone();
         let t = move || {
             let mut w = d.env.begin_rw_txn().map_err(map_lmdb_err)?;
             match w.del(d.map, &k, None) {
@@ -90,7 +321,7 @@ impl LmdbStorage {
             w.commit().map_err(map_lmdb_err)?;
             Ok(())
         };
-        task::spawn_blocking(t).await.map_err(map_join_err)?
+        self.run("delete", &key, t).await
     }
 }
 
@@ -99,6 +330,7 @@ impl Storage for LmdbStorage {
     async fn get(&self, id: &str, key: &str) -> Result<Option<Vec<u8>>> {
         let d = self.clone();
         let k = format!("{id}:{key}");
+        let key = k.clone();
         let t = move || {
             let r = d.env.begin_ro_txn().map_err(map_lmdb_err)?;
             match r.get(d.map, &k) {
@@ -107,13 +339,27 @@ impl Storage for LmdbStorage {
                 Err(e) => Err(map_lmdb_err(e)),
             }
         };
-        task::spawn_blocking(t).await.map_err(map_join_err)?
+        self.run("get", &key, t).await
     }
 
     async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()> {
         self.write(format!("{id}:{key}"), val).await
     }
 
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()> {
+        self.write_all(
+            entries
+                .into_iter()
+                .map(|entry| (format!("{}:{}", entry.id, entry.key), entry.value))
+                .collect(),
+        )
+        .await
+    }
+
+    async fn get_or_set(&self, id: &str, key: String, val: Vec<u8>) -> Result<Vec<u8>> {
+        self.read_or_write(format!("{id}:{key}"), val).await
+    }
+
     async fn del(&self, id: &str, key: &str) -> Result<()> {
         self.delete(format!("{id}:{key}")).await
     }
@@ -133,7 +379,7 @@ impl Storage for LmdbStorage {
                 })
                 .collect())
         };
-        task::spawn_blocking(t).await.map_err(map_join_err)?
+        self.run("keys", namespace, t).await
     }
 }
 
@@ -144,3 +390,140 @@ fn map_join_err(err: JoinError) -> Error {
 fn map_lmdb_err(err: lmdb::Error) -> Error {
     Error::new(Origin::Application, Kind::Io, err)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_operations_are_counted() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        let before = LmdbStorage::statistics();
+
+        storage.set("id", "key".to_string(), vec![1, 2, 3]).await?;
+        assert_eq!(storage.get("id", "key").await?, Some(vec![1, 2, 3]));
+
+        let after = LmdbStorage::statistics();
+        assert!(after.operations >= before.operations + 2);
+        assert!(after.max_duration_us >= before.max_duration_us);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_set_all_and_get_or_set() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        storage
+            .set_all(vec![
+                StorageEntry::new("id1", "key", vec![1]),
+                StorageEntry::new("id2", "key", vec![2]),
+            ])
+            .await?;
+        assert_eq!(storage.get("id1", "key").await?, Some(vec![1]));
+        assert_eq!(storage.get("id2", "key").await?, Some(vec![2]));
+
+        assert_eq!(
+            storage
+                .get_or_set("id1", "key".to_string(), vec![3])
+                .await?,
+            vec![1]
+        );
+        assert_eq!(
+            storage
+                .get_or_set("id3", "key".to_string(), vec![3])
+                .await?,
+            vec![3]
+        );
+        assert_eq!(storage.get("id3", "key").await?, Some(vec![3]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        storage.set("id1", "key".to_string(), vec![1]).await?;
+        storage.set("id2", "key".to_string(), vec![2]).await?;
+
+        let backup_dir = tempfile::tempdir().unwrap();
+        let backup_path = backup_dir.path().join("backup.lmdb");
+        storage.backup_to(&backup_path).await?;
+        // the changes made after the backup are not part of it
+        storage.set("id3", "key".to_string(), vec![3]).await?;
+
+        let restored_path = backup_dir.path().join("restored.lmdb");
+        LmdbStorage::new(&backup_path)
+            .await?
+            .backup_to(&restored_path)
+            .await?;
+        let restored = LmdbStorage::new(&restored_path).await?;
+        assert_eq!(restored.get("id1", "key").await?, Some(vec![1]));
+        assert_eq!(restored.get("id2", "key").await?, Some(vec![2]));
+        assert_eq!(restored.get("id3", "key").await?, None);
+        assert_eq!(restored.check_integrity().await?, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_open_read_only() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        storage.set("id", "key".to_string(), vec![1]).await?;
+
+        let read_only = LmdbStorage::open_read_only(file.path()).await?;
+        assert_eq!(read_only.get("id", "key").await?, Some(vec![1]));
+        assert!(read_only
+            .set("id", "key".to_string(), vec![2])
+            .await
+            .is_err());
+        assert_eq!(storage.get("id", "key").await?, Some(vec![1]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_integrity() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        assert_eq!(storage.check_integrity().await?, 0);
+        storage.set("id", "key1".to_string(), vec![1]).await?;
+        storage.set("id", "key2".to_string(), vec![2]).await?;
+        assert_eq!(storage.check_integrity().await?, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_operations_are_counted() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path())
+            .await?
+            .with_slow_operation_threshold(Duration::ZERO);
+
+        let before = LmdbStorage::statistics();
+        storage.set("id", "key".to_string(), vec![1]).await?;
+        let after = LmdbStorage::statistics();
+
+        assert!(after.slow_operations > before.slow_operations);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_maintain() -> Result<()> {
+        let file = NamedTempFile::new().unwrap();
+        let storage = LmdbStorage::new(file.path()).await?;
+        for i in 0..100 {
+            storage
+                .set(&i.to_string(), "key".to_string(), vec![0; 1024])
+                .await?;
+        }
+        for i in 0..100 {
+            storage.del(&i.to_string(), "key").await?;
+        }
+
+        let report = storage.maintain().await?;
+        assert!(report.size_after > 0);
+        assert_eq!(report.reclaimed_bytes(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use ockam_core::compat::string::String;
+
+static RUNS: AtomicU64 = AtomicU64::new(0);
+static RECLAIMED_BYTES: AtomicU64 = AtomicU64::new(0);
+static FREE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Result of the maintenance of a database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Name of the database file
+    pub database: String,
+    /// Size of the database before the maintenance, in bytes
+    pub size_before: u64,
+    /// Size of the database after the maintenance, in bytes
+    pub size_after: u64,
+    /// Size of the pages which are not used and will be reused by the next writes, in bytes
+    pub free_bytes: u64,
+    /// Duration of the maintenance
+    pub duration: Duration,
+}
+
+impl MaintenanceReport {
+    /// Number of bytes given back to the file system
+    pub fn reclaimed_bytes(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+
+    /// Add this report to the maintenance counters of the current process
+    pub(crate) fn record(&self) {
+        RUNS.fetch_add(1, Ordering::Relaxed);
+        RECLAIMED_BYTES.fetch_add(self.reclaimed_bytes(), Ordering::Relaxed);
+        FREE_BYTES.store(self.free_bytes, Ordering::Relaxed);
+    }
+}
+
+/// Counters of the maintenance of all the databases of the current process
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaintenanceStatistics {
+    /// Number of databases maintained
+    pub runs: u64,
+    /// Total number of bytes given back to the file system
+    pub reclaimed_bytes: u64,
+    /// Size of the free pages of the last maintained database, in bytes
+    pub free_bytes: u64,
+}
+
+impl MaintenanceStatistics {
+    /// Return the counters of the current process
+    pub fn current() -> Self {
+        Self {
+            runs: RUNS.load(Ordering::Relaxed),
+            reclaimed_bytes: RECLAIMED_BYTES.load(Ordering::Relaxed),
+            free_bytes: FREE_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -8,7 +8,7 @@ use ockam_core::compat::{
 };
 use ockam_core::Result;
 
-use crate::storage::Storage;
+use crate::storage::{Storage, StorageEntry};
 
 /// Non-persistent table stored in RAM
 #[derive(Clone, Default)]
@@ -53,6 +53,25 @@ impl Storage for InMemoryStorage {
         Ok(())
     }
 
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()> {
+        let mut m = self.map.write().unwrap();
+        for entry in entries {
+            m.entry(entry.key)
+                .or_default()
+                .insert(entry.id, entry.value);
+        }
+        Ok(())
+    }
+
+    async fn get_or_set(&self, id: &str, namespace: String, val: Vec<u8>) -> Result<Vec<u8>> {
+        let mut m = self.map.write().unwrap();
+        Ok(m.entry(namespace)
+            .or_default()
+            .entry(id.to_string())
+            .or_insert(val)
+            .clone())
+    }
+
     async fn del(&self, id: &str, namespace: &str) -> Result<()> {
         let mut m = self.map.write().unwrap();
         if let Some(a) = m.get_mut(namespace) {
@@ -1,20 +1,36 @@
 #[allow(clippy::module_inception)]
 mod storage;
 
+mod encrypted_storage;
+mod integrity_storage;
 mod memory;
+mod storage_keys;
 
 /// LMDB implementation of the Storage trait
 #[cfg(feature = "std")]
 pub mod lmdb_storage;
+/// Reports of the maintenance of the databases
+#[cfg(feature = "std")]
+pub mod maintenance;
+/// Postgres implementation of the Storage trait
+#[cfg(feature = "postgres")]
+pub mod postgres_storage;
 /// Sqlite implementation of the Storage trait
 #[cfg(feature = "sqlite")]
 pub mod sqlite_storage;
 
+pub use encrypted_storage::*;
+pub use integrity_storage::*;
 pub use memory::*;
 pub use storage::*;
+pub use storage_keys::{StorageKey, STORAGE_KEY_LENGTH};
 
 #[cfg(feature = "std")]
 pub use lmdb_storage::*;
+#[cfg(feature = "std")]
+pub use maintenance::*;
 
+#[cfg(feature = "postgres")]
+pub use postgres_storage::*;
 #[cfg(feature = "sqlite")]
 pub use sqlite_storage::*;
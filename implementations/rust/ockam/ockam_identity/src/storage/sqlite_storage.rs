@@ -8,11 +8,12 @@ use ockam_node::tokio::task::{self, JoinError};
 use rusqlite::{params, Connection};
 use std::fmt;
 use std::path::Path;
+use std::time::Instant;
 use tokio_retry::strategy::{jitter, FixedInterval};
 use tokio_retry::Retry;
 use tracing::debug;
 
-use Storage;
+use crate::storage::{MaintenanceReport, Storage, StorageEntry};
 
 /// Storage using the Sqlite database
 #[derive(Clone)]
@@ -64,9 +65,11 @@ impl SqliteStorage {
         let p = p.to_path_buf();
         // Creates database file if it doesn't exist
         let conn = Connection::open(p).map_err(map_sqlite_err)?;
+        // the free pages of the databases created with an incremental auto vacuum can be given
+        // back to the file system by `maintain`. This has no effect on an existing database
         let _ = conn
             .execute_batch(
-                &("PRAGMA encoding = 'UTF-8';".to_owned()
+                &("PRAGMA auto_vacuum = INCREMENTAL; PRAGMA encoding = 'UTF-8';".to_owned()
                     + SqliteStorage::CREATE_IDENTITY_TABLE_SQL
                     + SqliteStorage::CREATE_IDENTITY_INDEX_SQL
                     + SqliteStorage::CREATE_POLICY_TABLE_SQL
@@ -82,6 +85,38 @@ impl SqliteStorage {
     pub fn conn(&self) -> Arc<Mutex<Connection>> {
         Arc::clone(&self.conn)
     }
+
+    /// Give the free pages back to the file system, update the statistics used by the query
+    /// planner, and move the content of the write-ahead log to the database
+    pub async fn maintain(&self) -> Result<MaintenanceReport> {
+        let conn = self.conn();
+        let started = Instant::now();
+        let t = move || {
+            let conn = conn.lock().unwrap();
+            let pragma = |name: &str| {
+                conn.pragma_query_value(None, name, |row| row.get::<_, i64>(0))
+                    .map(|value| value as u64)
+                    .map_err(map_sqlite_err)
+            };
+            let page_size = pragma("page_size")?;
+            let size_before = pragma("page_count")? * page_size;
+            conn.execute_batch("PRAGMA incremental_vacuum; ANALYZE;")
+                .map_err(map_sqlite_err)?;
+            // this is a no-op when the database doesn't use a write-ahead log
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE);", [], |_| Ok(()))
+                .map_err(map_sqlite_err)?;
+            let report = MaintenanceReport {
+                database: conn.path().unwrap_or_default().to_string(),
+                size_before,
+                size_after: pragma("page_count")? * page_size,
+                free_bytes: pragma("freelist_count")? * page_size,
+                duration: started.elapsed(),
+            };
+            report.record();
+            Ok(report)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)?
+    }
 }
 
 #[async_trait]
@@ -120,6 +155,44 @@ impl Storage for SqliteStorage {
         task::spawn_blocking(t).await.map_err(map_join_err)?
     }
 
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()> {
+        let conn = self.conn();
+        let t = move || {
+            let mut conn = conn.lock().unwrap();
+            let transaction = conn.transaction().map_err(map_sqlite_err)?;
+            for entry in entries {
+                transaction
+                    .execute(
+                        "INSERT OR REPLACE INTO identity (identity_id, key, value) VALUES (?1, ?2, ?3)",
+                        params![entry.id, entry.key, entry.value],
+                    )
+                    .map_err(map_sqlite_err)?;
+            }
+            transaction.commit().map_err(map_sqlite_err)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)?
+    }
+
+    async fn get_or_set(&self, id: &str, key: String, val: Vec<u8>) -> Result<Vec<u8>> {
+        let conn = self.conn();
+        let id = String::from(id);
+        let t = move || {
+            let conn = conn.lock().unwrap();
+            conn.execute(
+                "INSERT OR IGNORE INTO identity (identity_id, key, value) VALUES (?1, ?2, ?3)",
+                params![id, key, val],
+            )
+            .map_err(map_sqlite_err)?;
+            conn.query_row::<Vec<u8>, _, _>(
+                "SELECT value FROM identity WHERE identity_id = ?1 AND key = ?2;",
+                params![id, key],
+                |row| row.get(0),
+            )
+            .map_err(map_sqlite_err)
+        };
+        task::spawn_blocking(t).await.map_err(map_join_err)?
+    }
+
     async fn del(&self, id: &str, key: &str) -> Result<()> {
         let conn = self.conn();
         let id = String::from(id);
@@ -189,4 +262,22 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_maintain_reclaims_free_pages() -> Result<()> {
+        let temp_path = NamedTempFile::new().unwrap().into_temp_path();
+        let db = SqliteStorage::new(temp_path.to_path_buf()).await?;
+        for i in 0..100 {
+            db.set(&i.to_string(), String::from("key"), vec![0; 4096])
+                .await?;
+        }
+        for i in 0..100 {
+            db.del(&i.to_string(), "key").await?;
+        }
+
+        let report = db.maintain().await?;
+        assert!(report.reclaimed_bytes() > 0);
+        assert_eq!(report.free_bytes, 0);
+        Ok(())
+    }
 }
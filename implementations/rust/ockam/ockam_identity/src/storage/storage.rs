@@ -11,6 +11,13 @@ pub trait Storage: Send + Sync + 'static {
     /// Set entry
     async fn set(&self, id: &str, key: String, val: Vec<u8>) -> Result<()>;
 
+    /// Set several entries at once: either all the entries are set or none of them is
+    async fn set_all(&self, entries: Vec<StorageEntry>) -> Result<()>;
+
+    /// Set an entry if it doesn't exist yet, and return the value of the entry.
+    /// This is atomic, so that only one of the processes sharing the storage sets the entry
+    async fn get_or_set(&self, id: &str, key: String, val: Vec<u8>) -> Result<Vec<u8>>;
+
     /// Delete entry
     async fn del(&self, id: &str, key: &str) -> Result<()>;
 
@@ -18,3 +25,25 @@ pub trait Storage: Send + Sync + 'static {
     /// store.
     async fn keys(&self, namespace: &str) -> Result<Vec<String>>;
 }
+
+/// Entry set with [`Storage::set_all`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageEntry {
+    /// Identifier of the entry
+    pub id: String,
+    /// Key, or namespace, of the entry
+    pub key: String,
+    /// Value of the entry
+    pub value: Vec<u8>,
+}
+
+impl StorageEntry {
+    /// Create an entry
+    pub fn new(id: impl Into<String>, key: impl Into<String>, value: Vec<u8>) -> Self {
+        Self {
+            id: id.into(),
+            key: key.into(),
+            value,
+        }
+    }
+}
@@ -0,0 +1,520 @@
+use core::fmt;
+use ockam_core::compat::rand::RngCore;
+use ockam_core::compat::{
+    collections::BTreeMap,
+    string::ToString,
+    sync::{Arc, RwLock},
+    vec::Vec,
+};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{entropy, Error, Result};
+use ockam_vault::{
+    AeadSecretKeyHandle, HKDFNumberOfOutputs, SecretBufferHandle, SoftwareVaultForSecureChannels,
+    VaultForSecureChannels, X25519PublicKey, X25519SecretKeyHandle, X25519_PUBLIC_KEY_LENGTH,
+};
+use tracing::warn;
+
+use crate::storage::{Storage, StorageEntry};
+use crate::IdentityError;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// Namespace containing the value identifying the key used to derive the storage keys.
+/// This namespace is neither encrypted nor integrity protected
+pub(crate) const ROOT_KEY_NAMESPACE: &str = "STORAGE_ENCRYPTION_ROOT";
+pub(crate) const ROOT_KEY_ID: &str = "root";
+/// Entry containing the parameters used to derive the storage key from a passphrase
+const PASSPHRASE_ID: &str = "passphrase";
+/// Entry containing the storage key wrapped by a vault
+const WRAPPED_KEY_ID: &str = "wrapped";
+
+/// Namespace containing the marker indicating that all protected values are tagged
+pub(crate) const INTEGRITY_MARKER_NAMESPACE: &str = "STORAGE_INTEGRITY";
+/// Namespace containing the marker indicating that all the values stored before the
+/// encryption was enabled are encrypted
+pub(crate) const ENCRYPTION_MARKER_NAMESPACE: &str = "STORAGE_ENCRYPTION";
+pub(crate) const MARKER_ID: &str = "enabled";
+
+/// Label used to derive the key authenticating the markers
+const MARKER_LABEL: &[u8] = b"ockam.storage.marker";
+/// Label used to derive the key wrapping a storage key in a vault
+const WRAPPING_LABEL: &[u8] = b"ockam.storage.key_wrapping";
+const WRAPPING_NONCE_LENGTH: usize = 12;
+
+/// Number of PBKDF2-HMAC-SHA256 iterations used to derive a storage key from a passphrase.
+/// The number of iterations is stored with the salt, so that it can be increased later
+const PASSPHRASE_ITERATIONS: u32 = if cfg!(test) { 1_000 } else { 600_000 };
+const PASSPHRASE_SALT_LENGTH: usize = 16;
+
+/// Length of a [`StorageKey`]
+pub const STORAGE_KEY_LENGTH: usize = 32;
+
+/// Secret from which the keys protecting a storage at rest are derived.
+///
+/// The key is either generated randomly and kept in the storage, wrapped by a vault, or derived
+/// from a passphrase. A storage protected by a passphrase doesn't depend on any vault, so it can
+/// be shared by several hosts.
+#[derive(Clone, PartialEq, Eq)]
+pub struct StorageKey([u8; STORAGE_KEY_LENGTH]);
+
+impl StorageKey {
+    /// Generate a random key
+    pub fn generate() -> Self {
+        let mut key = [0u8; STORAGE_KEY_LENGTH];
+        entropy::rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    /// Read the key kept in a file, or create that file with a new key.
+    /// The file is only readable by its owner, and it is created atomically so that several
+    /// processes opening the same storage at once use the same key
+    #[cfg(feature = "std")]
+    pub fn read_or_create(path: &std::path::Path) -> Result<Self> {
+        use std::io::{ErrorKind, Write};
+
+        let io_err = |e| Error::new(Origin::Identity, Kind::Io, e);
+        match std::fs::read(path) {
+            Ok(bytes) => return Self::try_from(bytes.as_slice()),
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(io_err(e)),
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
+        }
+
+        // the key is written to a temporary file first, then linked to its final path, which
+        // fails if another process created the key in the meantime
+        let key = Self::generate();
+        let tmp_path = path.with_extension(format!("{}.tmp", hex::encode(Self::generate().0)));
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let written = options.open(&tmp_path).and_then(|mut file| {
+            file.write_all(key.as_bytes())?;
+            file.sync_all()
+        });
+        let linked = written.and_then(|_| std::fs::hard_link(&tmp_path, path));
+        let _ = std::fs::remove_file(&tmp_path);
+        match linked {
+            Ok(()) => Ok(key),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                Self::try_from(std::fs::read(path).map_err(io_err)?.as_slice())
+            }
+            Err(e) => Err(io_err(e)),
+        }
+    }
+
+    /// Derive the key of a storage from a passphrase, with PBKDF2-HMAC-SHA256 and a random salt
+    /// created if the storage is not protected by a passphrase yet. When several processes
+    /// create the salt at once, they all use the first salt stored.
+    /// The derivation is slow on purpose, so it runs on a blocking thread
+    pub async fn from_passphrase(storage: &dyn Storage, passphrase: &str) -> Result<Self> {
+        let mut salt = [0u8; PASSPHRASE_SALT_LENGTH];
+        entropy::rng().fill_bytes(&mut salt);
+        let mut parameters = PASSPHRASE_ITERATIONS.to_be_bytes().to_vec();
+        parameters.extend_from_slice(&salt);
+        let parameters = storage
+            .get_or_set(PASSPHRASE_ID, ROOT_KEY_NAMESPACE.to_string(), parameters)
+            .await?;
+
+        if parameters.len() != 4 + PASSPHRASE_SALT_LENGTH {
+            return Err(invalid_data(
+                "the storage passphrase parameters are invalid",
+            ));
+        }
+        let (iterations, salt) = parameters.split_at(4);
+        let iterations = u32::from_be_bytes(
+            iterations
+                .try_into()
+                .map_err(|_| invalid_data("the storage passphrase parameters are invalid"))?,
+        );
+        let salt = salt.to_vec();
+        let passphrase = passphrase.to_string();
+        let derive = move || {
+            let mut key = [0u8; STORAGE_KEY_LENGTH];
+            pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), &salt, iterations, &mut key);
+            Self(key)
+        };
+        #[cfg(feature = "std")]
+        let key = ockam_node::tokio::task::spawn_blocking(derive)
+            .await
+            .map_err(|e| Error::new(Origin::Identity, Kind::Internal, e))?;
+        #[cfg(not(feature = "std"))]
+        let key = derive();
+        Ok(key)
+    }
+
+    /// Return the key kept in a storage, wrapped by a vault, or generate a new key and keep it in
+    /// the storage. The key is wrapped with a key derived from a static X25519 key of the vault,
+    /// so the storage can only be read with that vault
+    pub async fn from_vault(
+        storage: &dyn Storage,
+        vault: &dyn VaultForSecureChannels,
+    ) -> Result<Self> {
+        match storage.get(WRAPPED_KEY_ID, ROOT_KEY_NAMESPACE).await? {
+            Some(wrapped) => Self::unwrap(&wrapped, vault).await,
+            None => Self::generate().store_in_vault(storage, vault).await,
+        }
+    }
+
+    /// Keep this key in a storage, wrapped by a vault, and return the key kept in the storage.
+    /// When several processes store a key at once, they all use the first key stored
+    pub async fn store_in_vault(
+        &self,
+        storage: &dyn Storage,
+        vault: &dyn VaultForSecureChannels,
+    ) -> Result<Self> {
+        let (wrapped, secret_key) = self.wrap(vault).await?;
+        let stored = storage
+            .get_or_set(
+                WRAPPED_KEY_ID,
+                ROOT_KEY_NAMESPACE.to_string(),
+                wrapped.clone(),
+            )
+            .await?;
+        if stored != wrapped {
+            vault.delete_static_x25519_secret_key(secret_key).await?;
+            return Self::unwrap(&stored, vault).await;
+        }
+        Ok(self.clone())
+    }
+
+    /// Remove the key wrapped by a vault from a storage, once the storage is protected by a
+    /// passphrase
+    pub async fn delete_from_vault(
+        storage: &dyn Storage,
+        vault: &dyn VaultForSecureChannels,
+    ) -> Result<()> {
+        if let Some(wrapped) = storage.get(WRAPPED_KEY_ID, ROOT_KEY_NAMESPACE).await? {
+            if let Ok(public_key) = wrapping_public_key(&wrapped) {
+                if let Ok(secret_key) = vault.get_x25519_secret_key_handle(&public_key).await {
+                    vault.delete_static_x25519_secret_key(secret_key).await?;
+                }
+            }
+            storage.del(WRAPPED_KEY_ID, ROOT_KEY_NAMESPACE).await?;
+        }
+        Ok(())
+    }
+
+    /// Return true if a storage keeps its key wrapped by a vault
+    pub async fn is_in_vault(storage: &dyn Storage) -> Result<bool> {
+        Ok(storage
+            .get(WRAPPED_KEY_ID, ROOT_KEY_NAMESPACE)
+            .await?
+            .is_some())
+    }
+
+    /// Return true if a storage is protected by this key
+    pub async fn protects(&self, storage: &dyn Storage) -> Result<bool> {
+        Ok(storage.get(ROOT_KEY_ID, ROOT_KEY_NAMESPACE).await? == Some(self.verifier().to_vec()))
+    }
+
+    /// Bytes of the key
+    pub fn as_bytes(&self) -> &[u8; STORAGE_KEY_LENGTH] {
+        &self.0
+    }
+
+    /// Value stored to check that a storage is opened with the right key, without revealing it
+    fn verifier(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"ockam.storage.key_verifier");
+        hasher.update(self.0);
+        hasher.finalize().into()
+    }
+
+    /// Encrypt this key with a key derived from a new static X25519 key of a vault.
+    /// The wrapped key starts with the public key identifying the static key in the vault
+    async fn wrap(
+        &self,
+        vault: &dyn VaultForSecureChannels,
+    ) -> Result<(Vec<u8>, X25519SecretKeyHandle)> {
+        let secret_key = vault.generate_static_x25519_secret_key().await?;
+        let public_key = vault.get_x25519_public_key(&secret_key).await?;
+        let wrapping_key = wrapping_key(vault, &secret_key, &public_key).await?;
+        let mut nonce = [0u8; WRAPPING_NONCE_LENGTH];
+        entropy::rng().fill_bytes(&mut nonce);
+        let cipher_text = vault
+            .aead_encrypt(&wrapping_key, &self.0, &nonce, WRAPPING_LABEL)
+            .await;
+        vault.delete_aead_secret_key(wrapping_key).await?;
+
+        let mut wrapped = public_key.0.to_vec();
+        wrapped.extend_from_slice(&nonce);
+        wrapped.extend_from_slice(&cipher_text?);
+        Ok((wrapped, secret_key))
+    }
+
+    /// Decrypt a key wrapped by a vault
+    async fn unwrap(wrapped: &[u8], vault: &dyn VaultForSecureChannels) -> Result<Self> {
+        let public_key = wrapping_public_key(wrapped)?;
+        let (nonce, cipher_text) =
+            wrapped[X25519_PUBLIC_KEY_LENGTH..].split_at(WRAPPING_NONCE_LENGTH);
+        let secret_key = vault
+            .get_x25519_secret_key_handle(&public_key)
+            .await
+            .map_err(|_| invalid_data("the storage key is wrapped by another vault"))?;
+        let wrapping_key = wrapping_key(vault, &secret_key, &public_key).await?;
+        let key = vault
+            .aead_decrypt(&wrapping_key, cipher_text, nonce, WRAPPING_LABEL)
+            .await;
+        vault.delete_aead_secret_key(wrapping_key).await?;
+        Self::try_from(key?.as_slice())
+    }
+}
+
+/// Return the public key identifying the static key of the vault wrapping a storage key
+fn wrapping_public_key(wrapped: &[u8]) -> Result<X25519PublicKey> {
+    if wrapped.len() < X25519_PUBLIC_KEY_LENGTH + WRAPPING_NONCE_LENGTH {
+        return Err(invalid_data("the wrapped storage key is invalid"));
+    }
+    Ok(X25519PublicKey(
+        wrapped[..X25519_PUBLIC_KEY_LENGTH]
+            .try_into()
+            .map_err(|_| invalid_data("the wrapped storage key is invalid"))?,
+    ))
+}
+
+/// Derive the key wrapping a storage key from a static X25519 key of a vault
+async fn wrapping_key(
+    vault: &dyn VaultForSecureChannels,
+    secret_key: &X25519SecretKeyHandle,
+    public_key: &X25519PublicKey,
+) -> Result<AeadSecretKeyHandle> {
+    let shared_secret = vault.x25519_ecdh(secret_key, public_key).await?;
+    let salt = vault.import_secret_buffer(WRAPPING_LABEL.to_vec()).await?;
+    let hkdf_output = vault
+        .hkdf(&salt, Some(&shared_secret), HKDFNumberOfOutputs::Two)
+        .await?;
+    vault.delete_secret_buffer(salt).await?;
+    vault.delete_secret_buffer(shared_secret).await?;
+
+    let [secret, unused]: [SecretBufferHandle; 2] = hkdf_output
+        .0
+         .0
+        .try_into()
+        .map_err(|_| invalid_data("unexpected HKDF output"))?;
+    vault.delete_secret_buffer(unused).await?;
+    vault.convert_secret_buffer_to_aead_key(secret).await
+}
+
+impl TryFrom<&[u8]> for StorageKey {
+    type Error = Error;
+
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Ok(Self(bytes.try_into().map_err(|_| {
+            invalid_data("a storage key must be 32 bytes long")
+        })?))
+    }
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StorageKey")
+    }
+}
+
+/// Keys protecting the data of a storage at rest.
+///
+/// Each key is derived with HKDF, for a given label, from a [`StorageKey`]. Only a value
+/// identifying the storage key is kept in the underlying storage, so that a storage opened
+/// with another key is rejected instead of being read as garbage. The markers indicating that
+/// the storage is protected are authenticated with the storage key, so that they can't be
+/// removed or forged to have values tagged and encrypted again.
+pub(crate) struct StorageKeys {
+    storage_key: StorageKey,
+    vault: Arc<dyn VaultForSecureChannels>,
+    root_secret: SecretBufferHandle,
+    keys: RwLock<BTreeMap<&'static [u8], AeadSecretKeyHandle>>,
+}
+
+impl StorageKeys {
+    /// Create the keys derived from a storage key. The creation fails if the storage is
+    /// already protected by another key
+    pub(crate) async fn create(storage: &dyn Storage, key: &StorageKey) -> Result<Self> {
+        if let Some(stored) = storage.get(ROOT_KEY_ID, ROOT_KEY_NAMESPACE).await? {
+            if stored != key.verifier() {
+                return Err(invalid_data(
+                    "the storage is protected by another key or passphrase",
+                ));
+            }
+        }
+
+        // the keys are only used by this storage, so they are kept in a vault of their own
+        let vault: Arc<dyn VaultForSecureChannels> = SoftwareVaultForSecureChannels::create();
+        let root_secret = vault.import_secret_buffer(key.as_bytes().to_vec()).await?;
+        Ok(Self {
+            storage_key: key.clone(),
+            vault,
+            root_secret,
+            keys: RwLock::new(BTreeMap::new()),
+        })
+    }
+
+    /// Return true if a storage is protected by these keys, or false if it is not protected yet.
+    /// A storage is protected once its key verifier and its markers are set. A storage missing
+    /// some of them, or with a marker which was not set with this key, was tampered with and is
+    /// rejected
+    pub(crate) async fn is_protecting(&self, storage: &dyn Storage) -> Result<bool> {
+        let verifier = storage.get(ROOT_KEY_ID, ROOT_KEY_NAMESPACE).await?;
+        let mut markers = vec![];
+        for namespace in [INTEGRITY_MARKER_NAMESPACE, ENCRYPTION_MARKER_NAMESPACE] {
+            markers.push((namespace, storage.get(MARKER_ID, namespace).await?));
+        }
+
+        match verifier {
+            None if markers.iter().all(|(_, marker)| marker.is_none()) => Ok(false),
+            Some(verifier) if verifier != self.storage_key.verifier() => Err(invalid_data(
+                "the storage is protected by another key or passphrase",
+            )),
+            Some(_)
+                if markers.iter().all(|(namespace, marker)| {
+                    marker.as_deref().map_or(false, |marker| {
+                        self.marker(namespace).verify_slice(marker).is_ok()
+                    })
+                }) =>
+            {
+                Ok(true)
+            }
+            _ => {
+                warn!("the markers of the storage protection are missing or invalid");
+                Err(IdentityError::IntegrityCheckFailed.into())
+            }
+        }
+    }
+
+    /// Return the key verifier and the markers to write, with the protected values, when a
+    /// storage starts being protected
+    pub(crate) fn protection_entries(&self) -> Vec<StorageEntry> {
+        let mut entries = vec![StorageEntry::new(
+            ROOT_KEY_ID,
+            ROOT_KEY_NAMESPACE,
+            self.storage_key.verifier().to_vec(),
+        )];
+        for namespace in [INTEGRITY_MARKER_NAMESPACE, ENCRYPTION_MARKER_NAMESPACE] {
+            let marker = self.marker(namespace).finalize().into_bytes().to_vec();
+            entries.push(StorageEntry::new(MARKER_ID, namespace, marker));
+        }
+        entries
+    }
+
+    fn marker(&self, namespace: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.mac_key(MARKER_LABEL))
+            .expect("HMAC accepts keys of any length");
+        mac.update(namespace.as_bytes());
+        mac
+    }
+
+    /// Vault containing the keys
+    pub(crate) fn vault(&self) -> &Arc<dyn VaultForSecureChannels> {
+        &self.vault
+    }
+
+    /// Return the HMAC-SHA256 key derived for a given label
+    pub(crate) fn mac_key(&self, label: &[u8]) -> [u8; 32] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.storage_key.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(label);
+        mac.finalize().into_bytes().into()
+    }
+
+    /// Return the key derived for a given label
+    pub(crate) async fn key(&self, label: &'static [u8]) -> Result<AeadSecretKeyHandle> {
+        if let Some(key) = self.keys.read().unwrap().get(label) {
+            return Ok(key.clone());
+        }
+
+        let salt = self.vault.import_secret_buffer(label.to_vec()).await?;
+        let hkdf_output = self
+            .vault
+            .hkdf(&salt, Some(&self.root_secret), HKDFNumberOfOutputs::Two)
+            .await?;
+        self.vault.delete_secret_buffer(salt).await?;
+
+        let [secret, unused]: [SecretBufferHandle; 2] = hkdf_output
+            .0
+             .0
+            .try_into()
+            .map_err(|_| invalid_data("unexpected HKDF output"))?;
+        self.vault.delete_secret_buffer(unused).await?;
+        let key = self.vault.convert_secret_buffer_to_aead_key(secret).await?;
+
+        self.keys.write().unwrap().insert(label, key.clone());
+        Ok(key)
+    }
+}
+
+/// Associated data binding a value to its entry in a storage
+pub(crate) fn entry_aad(id: &str, namespace: &str) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(id.len() + namespace.len() + 1);
+    aad.extend_from_slice(namespace.as_bytes());
+    aad.push(0);
+    aad.extend_from_slice(id.as_bytes());
+    aad
+}
+
+pub(crate) fn invalid_data(message: &str) -> Error {
+    Error::new(Origin::Identity, Kind::Invalid, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+
+    #[test]
+    fn test_read_or_create_storage_key() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data").join("storage.key");
+        let key = StorageKey::read_or_create(&path)?;
+        assert_eq!(StorageKey::read_or_create(&path)?, key);
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        std::fs::write(&path, b"too short").unwrap();
+        assert!(StorageKey::read_or_create(&path).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_storage_key_wrapped_by_vault() -> Result<()> {
+        let storage = InMemoryStorage::create();
+        let vault = SoftwareVaultForSecureChannels::create();
+        let key = StorageKey::from_vault(storage.as_ref(), vault.as_ref()).await?;
+        assert_eq!(
+            StorageKey::from_vault(storage.as_ref(), vault.as_ref()).await?,
+            key
+        );
+
+        // the key is not stored in clear, and can't be unwrapped by another vault
+        let wrapped = storage
+            .get(WRAPPED_KEY_ID, ROOT_KEY_NAMESPACE)
+            .await?
+            .unwrap();
+        assert!(!wrapped
+            .windows(STORAGE_KEY_LENGTH)
+            .any(|bytes| bytes == key.as_bytes()));
+        let other_vault = SoftwareVaultForSecureChannels::create();
+        assert!(
+            StorageKey::from_vault(storage.as_ref(), other_vault.as_ref())
+                .await
+                .is_err()
+        );
+
+        StorageKey::delete_from_vault(storage.as_ref(), vault.as_ref()).await?;
+        assert!(!StorageKey::is_in_vault(storage.as_ref()).await?);
+        Ok(())
+    }
+}
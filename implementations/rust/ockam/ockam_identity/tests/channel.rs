@@ -5,10 +5,10 @@ use ockam_identity::models::{CredentialSchemaIdentifier, Identifier};
 use ockam_identity::secure_channels::secure_channels;
 use ockam_identity::utils::AttributesBuilder;
 use ockam_identity::{
-    AuthorityService, DecryptionResponse, EncryptionRequest, EncryptionResponse,
-    IdentityAccessControlBuilder, IdentitySecureChannelLocalInfo, SecureChannelListenerOptions,
-    SecureChannelOptions, SecureChannels, TrustContext, TrustEveryonePolicy, TrustIdentifierPolicy,
-    Vault,
+    AuthorityService, DecryptionResponse, EncryptionRequest, EncryptionResponse, HandshakePattern,
+    IdentityAccessControlBuilder, IdentityQuotas, IdentitySecureChannelLocalInfo, QuotaLimits,
+    SecureChannel, SecureChannelListenerOptions, SecureChannelOptions, SecureChannels,
+    TrustContext, TrustEveryonePolicy, TrustIdentifierPolicy, Vault,
 };
 use ockam_node::{Context, MessageReceiveOptions, WorkerBuilder};
 use ockam_vault::{
@@ -282,6 +282,177 @@ async fn test_channel_rejected_trust_policy(ctx: &mut Context) -> Result<()> {
     ctx.stop().await
 }
 
+#[ockam_macros::test]
+async fn test_channel_rejected_by_listener_allowed_identifiers(ctx: &mut Context) -> Result<()> {
+    let secure_channels = secure_channels();
+    let identities_creation = secure_channels.identities().identities_creation();
+
+    let alice = identities_creation.create_identity().await?;
+    let bob = identities_creation.create_identity().await?;
+    let charlie = identities_creation.create_identity().await?;
+
+    let bob_listener = secure_channels
+        .create_secure_channel_listener(
+            ctx,
+            bob.identifier(),
+            "bob_listener",
+            SecureChannelListenerOptions::new()
+                .with_allowed_identifiers(vec![charlie.identifier().clone()]),
+        )
+        .await?;
+
+    let alice_channel = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new().with_timeout(Duration::from_millis(500)),
+        )
+        .await?;
+
+    let mut child_ctx = ctx
+        .new_detached_with_mailboxes(Mailboxes::main(
+            "child",
+            Arc::new(AllowAll),
+            Arc::new(AllowAll),
+        ))
+        .await?;
+
+    ctx.flow_controls()
+        .add_consumer("child", bob_listener.flow_control_id());
+
+    child_ctx
+        .send(
+            route![alice_channel, child_ctx.address()],
+            "Hello, Bob!".to_string(),
+        )
+        .await?;
+
+    let result = child_ctx
+        .receive_extended::<String>(
+            MessageReceiveOptions::new().with_timeout(Duration::from_millis(50)),
+        )
+        .await;
+
+    assert!(result.is_err());
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn test_channel_rejected_by_listener_quotas(ctx: &mut Context) -> Result<()> {
+    let secure_channels = secure_channels();
+    let identities_creation = secure_channels.identities().identities_creation();
+
+    let alice = identities_creation.create_identity().await?;
+    let bob = identities_creation.create_identity().await?;
+
+    let quotas = IdentityQuotas::new(QuotaLimits::default().with_max_secure_channels(1));
+    secure_channels
+        .create_secure_channel_listener(
+            ctx,
+            bob.identifier(),
+            "bob_listener",
+            SecureChannelListenerOptions::new().with_quotas(quotas),
+        )
+        .await?;
+
+    let accepted = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new(),
+        )
+        .await?;
+    // the initiator doesn't wait for the responder once it sent the last handshake message
+    let rejected = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new(),
+        )
+        .await?;
+    ctx.sleep(Duration::from_millis(100)).await;
+
+    let responder = |channel: &SecureChannel| {
+        secure_channels
+            .secure_channel_registry()
+            .get_channel_by_encryptor_address(channel.encryptor_address())
+            .unwrap()
+            .their_decryptor_address()
+    };
+    let workers = ctx.list_workers().await?;
+    assert!(workers.contains(&responder(&accepted)));
+    // the worker of the rejected channel doesn't wait for more messages
+    assert!(!workers.contains(&responder(&rejected)));
+
+    ctx.stop().await
+}
+
+#[ockam_macros::test]
+async fn test_channel_handshake_prologue_and_pattern(ctx: &mut Context) -> Result<()> {
+    let secure_channels = secure_channels();
+    let identities_creation = secure_channels.identities().identities_creation();
+
+    let alice = identities_creation.create_identity().await?;
+    let bob = identities_creation.create_identity().await?;
+
+    let psk = HandshakePattern::XxPsk([42u8; 32]);
+    secure_channels
+        .create_secure_channel_listener(
+            ctx,
+            bob.identifier(),
+            "bob_listener",
+            SecureChannelListenerOptions::new()
+                .with_handshake_pattern(psk.clone())
+                .with_prologue(b"session 1".to_vec()),
+        )
+        .await?;
+
+    // the channel is created with the same pattern and prologue
+    let alice_channel = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new()
+                .with_handshake_pattern(psk.clone())
+                .with_prologue(b"session 1".to_vec()),
+        )
+        .await;
+    assert!(alice_channel.is_ok());
+
+    // but not with another prologue, or without the pre-shared key
+    let alice_channel = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new()
+                .with_handshake_pattern(psk)
+                .with_prologue(b"session 2".to_vec())
+                .with_timeout(Duration::from_millis(500)),
+        )
+        .await;
+    assert!(alice_channel.is_err());
+
+    let alice_channel = secure_channels
+        .create_secure_channel(
+            ctx,
+            alice.identifier(),
+            route!["bob_listener"],
+            SecureChannelOptions::new()
+                .with_prologue(b"session 1".to_vec())
+                .with_timeout(Duration::from_millis(500)),
+        )
+        .await;
+    assert!(alice_channel.is_err());
+
+    ctx.stop().await
+}
+
 #[ockam_macros::test]
 async fn test_channel_send_multiple_messages_both_directions(ctx: &mut Context) -> Result<()> {
     let secure_channels = secure_channels();
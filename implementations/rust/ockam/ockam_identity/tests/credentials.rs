@@ -1,15 +1,21 @@
 use std::sync::atomic::{AtomicI8, Ordering};
 use std::time::Duration;
 
+use ockam_core::api::{Request, ResponseHeader, Status};
+use ockam_core::compat::collections::BTreeMap;
 use ockam_core::compat::sync::Arc;
-use ockam_core::{async_trait, Any, DenyAll};
-use ockam_core::{route, Result, Routed, Worker};
-use ockam_identity::models::CredentialSchemaIdentifier;
+use ockam_core::{async_trait, Any, DenyAll, IncomingAccessControl, LocalMessage, RelayMessage};
+use ockam_core::{route, Result, Routed, TransportMessage, Worker};
+use ockam_identity::models::{
+    CredentialSchemaIdentifier, HardwareAttestation, HardwareAttestationKind, PurposePublicKey,
+};
 use ockam_identity::secure_channels::secure_channels;
-use ockam_identity::utils::AttributesBuilder;
+use ockam_identity::utils::{now, AttributesBuilder};
 use ockam_identity::{
-    AuthorityService, CredentialAccessControl, CredentialsMemoryRetriever,
-    SecureChannelListenerOptions, SecureChannelOptions, TrustContext, TrustIdentifierPolicy,
+    AttributesEntry, AuthorityService, CredentialAccessControl, CredentialsIssuer,
+    CredentialsMemoryRetriever, HardwareAttestationVerifier, IdentitySecureChannelLocalInfo,
+    SecureChannelListenerOptions, SecureChannelOptions, SecureChannels, TrustContext,
+    TrustIdentifierPolicy, HARDWARE_KEY_ATTRIBUTE,
 };
 use ockam_node::{Context, WorkerBuilder};
 
@@ -205,6 +211,133 @@ async fn full_flow_twoway(ctx: &mut Context) -> Result<()> {
     ctx.stop().await
 }
 
+#[ockam_macros::test]
+async fn exchange_credentials_over_existing_channel(ctx: &mut Context) -> Result<()> {
+    let secure_channels = secure_channels();
+    let identities = secure_channels.identities();
+    let identities_creation = identities.identities_creation();
+    let identities_repository = identities.repository();
+    let credentials = identities.credentials();
+    let credentials_service = identities.credentials_server();
+
+    let authority = identities_creation.create_identity().await?;
+    let server = identities_creation.create_identity().await?;
+    let client = identities_creation.create_identity().await?;
+
+    let server_credential = credentials
+        .credentials_creation()
+        .issue_credential(
+            authority.identifier(),
+            server.identifier(),
+            AttributesBuilder::with_schema(CredentialSchemaIdentifier(0))
+                .with_attribute("is_server", "true")
+                .build(),
+            Duration::from_secs(60),
+        )
+        .await?;
+
+    let listener = secure_channels
+        .create_secure_channel_listener(
+            ctx,
+            server.identifier(),
+            "listener",
+            SecureChannelListenerOptions::new(),
+        )
+        .await?;
+    let trust_context = TrustContext::new(
+        "test_trust_context_id".to_string(),
+        Some(AuthorityService::new(
+            credentials.clone(),
+            authority.identifier().clone(),
+            Some(Arc::new(CredentialsMemoryRetriever::new(server_credential))),
+        )),
+    );
+    ctx.flow_controls()
+        .add_consumer("credential_exchange", listener.flow_control_id());
+
+    credentials_service
+        .start(
+            ctx,
+            trust_context.clone(),
+            server.identifier().clone(),
+            "credential_exchange".into(),
+            true,
+        )
+        .await?;
+
+    // the channel is established without any credential
+    let channel = secure_channels
+        .create_secure_channel(
+            ctx,
+            client.identifier(),
+            route!["listener"],
+            SecureChannelOptions::new(),
+        )
+        .await?;
+    assert!(identities_repository
+        .get_attributes(client.identifier())
+        .await?
+        .is_none());
+
+    // the client obtains new attributes mid-session and exchanges them over the same channel
+    for role in ["user", "admin"] {
+        let credential = credentials
+            .credentials_creation()
+            .issue_credential(
+                authority.identifier(),
+                client.identifier(),
+                AttributesBuilder::with_schema(CredentialSchemaIdentifier(0))
+                    .with_attribute("role", role)
+                    .build(),
+                Duration::from_secs(60),
+            )
+            .await?;
+        channel
+            .exchange_credentials(
+                ctx,
+                &secure_channels,
+                "credential_exchange",
+                &trust_context,
+                credential,
+            )
+            .await?;
+
+        let client_attributes = identities_repository
+            .get_attributes(client.identifier())
+            .await?
+            .unwrap();
+        assert_eq!(
+            client_attributes
+                .attrs()
+                .get("role".as_bytes())
+                .unwrap()
+                .as_slice(),
+            role.as_bytes()
+        );
+    }
+
+    let server_attributes = identities_repository
+        .get_attributes(server.identifier())
+        .await?
+        .unwrap();
+    assert_eq!(
+        server_attributes
+            .attrs()
+            .get("is_server".as_bytes())
+            .unwrap()
+            .as_slice(),
+        b"true"
+    );
+
+    // the channel is still usable
+    assert!(secure_channels
+        .secure_channel_registry()
+        .get_channel_by_encryptor_address(channel.encryptor_address())
+        .is_some());
+
+    ctx.stop().await
+}
+
 #[ockam_macros::test]
 async fn access_control(ctx: &mut Context) -> Result<()> {
     let secure_channels = secure_channels();
@@ -310,6 +443,185 @@ async fn access_control(ctx: &mut Context) -> Result<()> {
     ctx.stop().await
 }
 
+#[tokio::test]
+async fn access_control_with_audience() -> Result<()> {
+    let secure_channels = secure_channels();
+    let identities = secure_channels.identities();
+    let identities_repository = identities.repository();
+    let client = identities.identities_creation().create_identity().await?;
+
+    identities_repository
+        .put_attributes(
+            client.identifier(),
+            AttributesEntry::new(
+                BTreeMap::from([(b"is_superuser".to_vec(), b"true".to_vec())]),
+                now()?,
+                None,
+                None,
+            )
+            .with_audience(Some(vec!["outlet-1".to_string()])),
+        )
+        .await?;
+
+    let message = RelayMessage::new(
+        "sender".into(),
+        "counter".into(),
+        LocalMessage::new(
+            TransportMessage::v1(route!["counter"], route!["sender"], vec![]),
+            IdentitySecureChannelLocalInfo::mark(vec![], client.identifier().clone())?,
+        ),
+    );
+
+    // the attributes can only be used for the resources of their audience
+    let required_attributes = vec![(b"is_superuser".to_vec(), b"true".to_vec())];
+    let access_control =
+        CredentialAccessControl::new(&required_attributes, identities_repository.clone());
+    assert!(!access_control.is_authorized(&message).await?);
+    assert!(
+        !access_control
+            .clone()
+            .with_resource("outlet-2")
+            .is_authorized(&message)
+            .await?
+    );
+    assert!(
+        access_control
+            .with_resource("outlet-1")
+            .is_authorized(&message)
+            .await?
+    );
+
+    Ok(())
+}
+
+struct AcceptAllAttestations;
+
+#[async_trait]
+impl HardwareAttestationVerifier for AcceptAllAttestations {
+    async fn verify(
+        &self,
+        _public_key: &PurposePublicKey,
+        _attestation: &HardwareAttestation,
+    ) -> Result<bool> {
+        Ok(true)
+    }
+}
+
+#[ockam_macros::test]
+async fn hardware_key_without_membership(ctx: &mut Context) -> Result<()> {
+    let secure_channels = SecureChannels::builder()
+        .with_hardware_attestation_verifier(Arc::new(AcceptAllAttestations))
+        .build();
+    let identities = secure_channels.identities();
+    let identities_creation = identities.identities_creation();
+    let identities_repository = identities.repository();
+
+    let authority = identities_creation.create_identity().await?;
+    let member = identities_creation.create_identity().await?;
+    let non_member = identities_creation.create_identity().await?;
+    for client in [&member, &non_member] {
+        identities
+            .purpose_keys()
+            .purpose_keys_creation()
+            .secure_channel_purpose_key_builder(client.identifier())
+            .with_hardware_attestation(HardwareAttestation {
+                kind: HardwareAttestationKind::Tpm2,
+                evidence: b"quote".to_vec(),
+            })
+            .build()
+            .await?;
+    }
+    identities_repository
+        .put_attributes(
+            member.identifier(),
+            AttributesEntry::new(
+                BTreeMap::from([(b"role".to_vec(), b"member".to_vec())]),
+                now()?,
+                None,
+                Some(authority.identifier().clone()),
+            ),
+        )
+        .await?;
+
+    let trust_context = TrustContext::new(
+        "test_trust_context_id".to_string(),
+        Some(AuthorityService::new(
+            identities.credentials(),
+            authority.identifier().clone(),
+            None,
+        )),
+    );
+    let listener = secure_channels
+        .create_secure_channel_listener(
+            ctx,
+            authority.identifier(),
+            "listener",
+            SecureChannelListenerOptions::new().with_trust_context(trust_context),
+        )
+        .await?;
+    ctx.flow_controls()
+        .add_consumer("issuer", listener.flow_control_id());
+    let issuer = CredentialsIssuer::new(
+        identities_repository.clone(),
+        identities.credentials(),
+        authority.identifier(),
+        "test_trust_context_id".to_string(),
+    );
+    WorkerBuilder::new(issuer)
+        .with_address("issuer")
+        .start(ctx)
+        .await?;
+
+    let mut statuses = vec![];
+    for client in [&member, &non_member] {
+        let channel = secure_channels
+            .create_secure_channel(
+                ctx,
+                client.identifier(),
+                route!["listener"],
+                SecureChannelOptions::new(),
+            )
+            .await?;
+        let response: Vec<u8> = ctx
+            .send_and_receive(
+                route![channel, "issuer"],
+                Request::post("/credential").to_vec()?,
+            )
+            .await?;
+        let header: ResponseHeader = minicbor::decode(&response)?;
+        statuses.push(header.status());
+    }
+
+    // the hardware key of a member is added to the attributes attested by the authority
+    assert_eq!(statuses[0], Some(Status::Ok));
+    let attributes = identities_repository
+        .get_attributes(member.identifier())
+        .await?
+        .unwrap();
+    assert_eq!(
+        attributes
+            .attrs()
+            .get(HARDWARE_KEY_ATTRIBUTE.as_bytes())
+            .map(|v| v.as_slice()),
+        Some(b"tpm2".as_slice())
+    );
+
+    // a hardware key alone doesn't make an identity a member
+    assert_eq!(statuses[1], Some(Status::Forbidden));
+    assert!(identities_repository
+        .get_attributes(non_member.identifier())
+        .await?
+        .is_none());
+    assert_eq!(
+        identities_repository
+            .get_hardware_key(non_member.identifier())
+            .await?,
+        Some(HardwareAttestationKind::Tpm2)
+    );
+
+    ctx.stop().await
+}
+
 struct CountingWorker {
     msgs_count: Arc<AtomicI8>,
 }
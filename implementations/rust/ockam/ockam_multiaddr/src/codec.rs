@@ -1,5 +1,5 @@
 use super::{Buffer, Checked, Code, Codec, Protocol};
-use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp, Worker};
+use crate::proto::{Dns4, Dns6, DnsAddr, Node, Project, Secure, Service, Space, Tcp, Worker};
 use crate::{Error, ProtoValue};
 use core::fmt;
 use unsigned_varint::decode;
@@ -51,6 +51,8 @@ impl Codec for StdCodec {
             }
             c @ Worker::CODE
             | c @ DnsAddr::CODE
+            | c @ Dns4::CODE
+            | c @ Dns6::CODE
             | c @ Service::CODE
             | c @ Node::CODE
             | c @ Project::CODE
@@ -76,6 +78,8 @@ impl Codec for StdCodec {
             crate::proto::Ip6::CODE => crate::proto::Ip6::read_bytes(input).is_ok(),
             Tcp::CODE => Tcp::read_bytes(input).is_ok(),
             DnsAddr::CODE => DnsAddr::read_bytes(input).is_ok(),
+            Dns4::CODE => Dns4::read_bytes(input).is_ok(),
+            Dns6::CODE => Dns6::read_bytes(input).is_ok(),
             Service::CODE => Service::read_bytes(input).is_ok(),
             Node::CODE => Node::read_bytes(input).is_ok(),
             Project::CODE => Project::read_bytes(input).is_ok(),
@@ -94,6 +98,8 @@ impl Codec for StdCodec {
             crate::proto::Ip6::CODE => crate::proto::Ip6::read_bytes(val.data())?.write_bytes(buf),
             Tcp::CODE => Tcp::read_bytes(val.data())?.write_bytes(buf),
             DnsAddr::CODE => DnsAddr::read_bytes(val.data())?.write_bytes(buf),
+            Dns4::CODE => Dns4::read_bytes(val.data())?.write_bytes(buf),
+            Dns6::CODE => Dns6::read_bytes(val.data())?.write_bytes(buf),
             Service::CODE => Service::read_bytes(val.data())?.write_bytes(buf),
             Node::CODE => Node::read_bytes(val.data())?.write_bytes(buf),
             Project::CODE => Project::read_bytes(val.data())?.write_bytes(buf),
@@ -133,6 +139,14 @@ impl Codec for StdCodec {
                 DnsAddr::read_str(value)?.write_bytes(buf);
                 Ok(())
             }
+            Dns4::PREFIX => {
+                Dns4::read_str(value)?.write_bytes(buf);
+                Ok(())
+            }
+            Dns6::PREFIX => {
+                Dns6::read_str(value)?.write_bytes(buf);
+                Ok(())
+            }
             Service::PREFIX => {
                 Service::read_str(value)?.write_bytes(buf);
                 Ok(())
@@ -186,6 +200,14 @@ impl Codec for StdCodec {
                 DnsAddr::read_bytes(value)?.write_str(f)?;
                 Ok(())
             }
+            Dns4::CODE => {
+                Dns4::read_bytes(value)?.write_str(f)?;
+                Ok(())
+            }
+            Dns6::CODE => {
+                Dns6::read_bytes(value)?.write_str(f)?;
+                Ok(())
+            }
             Service::CODE => {
                 Service::read_bytes(value)?.write_str(f)?;
                 Ok(())
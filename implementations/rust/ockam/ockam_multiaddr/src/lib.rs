@@ -27,7 +27,7 @@ use once_cell::race::OnceBox;
 use std::net::{SocketAddrV4, SocketAddrV6};
 use tinyvec::{Array, ArrayVec, TinyVec};
 
-use crate::proto::{DnsAddr, Ip4, Ip6, Tcp};
+use crate::proto::{Dns4, Dns6, DnsAddr, Ip4, Ip6, Tcp};
 pub use error::Error;
 use ockam_core::env::FromString;
 pub use registry::{Registry, RegistryBuilder};
@@ -609,12 +609,12 @@ impl MultiAddr {
                     let port = it.next().unwrap().cast::<Tcp>().unwrap();
                     return Ok(SocketAddrV6::new(*ip6, *port, 0, 0).to_string());
                 }
-                DnsAddr::CODE => {
-                    let host = p.cast::<DnsAddr>().unwrap();
+                DnsAddr::CODE | Dns4::CODE | Dns6::CODE => {
+                    let host = core::str::from_utf8(p.data().0).map_err(Error::message)?;
                     if let Some(p) = it.peek() {
                         if p.code() == Tcp::CODE {
                             let port = p.cast::<Tcp>().unwrap();
-                            return Ok(format!("{}:{}", &*host, *port));
+                            return Ok(format!("{}:{}", host, *port));
                         }
                     }
                 }
@@ -815,4 +815,16 @@ mod tests {
         assert_eq!(a, b);
         assert_eq!(v, t);
     }
+
+    #[test]
+    fn dns_to_socket_addr() {
+        for addr in [
+            "/dnsaddr/localhost/tcp/4000",
+            "/dns4/localhost/tcp/4000",
+            "/dns6/localhost/tcp/4000/service/api",
+        ] {
+            let addr: super::MultiAddr = addr.parse().unwrap();
+            assert_eq!(addr.to_socket_addr().unwrap(), "localhost:4000");
+        }
+    }
 }
@@ -205,6 +205,8 @@ macro_rules! gen_str_proto {
 
 gen_str_proto!(Worker, 102526, "worker");
 gen_str_proto!(DnsAddr, 56, "dnsaddr");
+gen_str_proto!(Dns4, 54, "dns4");
+gen_str_proto!(Dns6, 55, "dns6");
 gen_str_proto!(Service, 62526, "service");
 gen_str_proto!(Node, 72526, "node");
 gen_str_proto!(Project, 82526, "project");
@@ -1,6 +1,6 @@
 use super::{Code, Codec, Protocol};
 use crate::codec::StdCodec;
-use crate::proto::{DnsAddr, Node, Project, Secure, Service, Space, Tcp, Worker};
+use crate::proto::{Dns4, Dns6, DnsAddr, Node, Project, Secure, Service, Space, Tcp, Worker};
 use alloc::collections::btree_map::BTreeMap;
 use alloc::sync::Arc;
 use core::fmt;
@@ -28,6 +28,8 @@ impl Default for Registry {
         r.register(Worker::CODE, Worker::PREFIX, std_codec.clone());
         r.register(Tcp::CODE, Tcp::PREFIX, std_codec.clone());
         r.register(DnsAddr::CODE, DnsAddr::PREFIX, std_codec.clone());
+        r.register(Dns4::CODE, Dns4::PREFIX, std_codec.clone());
+        r.register(Dns6::CODE, Dns6::PREFIX, std_codec.clone());
         #[allow(clippy::redundant_clone)]
         r.register(Service::CODE, Service::PREFIX, std_codec.clone());
         #[allow(clippy::redundant_clone)]
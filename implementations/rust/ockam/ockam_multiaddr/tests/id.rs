@@ -1,5 +1,7 @@
 use core::fmt;
-use ockam_multiaddr::proto::{DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp};
+use ockam_multiaddr::proto::{
+    Dns4, Dns6, DnsAddr, Ip4, Ip6, Node, Project, Secure, Service, Space, Tcp,
+};
 use ockam_multiaddr::{Code, Match, MultiAddr, Protocol};
 use quickcheck::{quickcheck, Arbitrary, Gen};
 use rand::distributions::{Alphanumeric, DistString};
@@ -104,6 +106,14 @@ quickcheck! {
                         addr.push_back(DnsAddr::new("localhost")).unwrap();
                         prot.push_back(DnsAddr::CODE);
                     }
+                    Dns4::CODE => {
+                        addr.push_back(Dns4::new("localhost")).unwrap();
+                        prot.push_back(Dns4::CODE);
+                    }
+                    Dns6::CODE => {
+                        addr.push_back(Dns6::new("localhost")).unwrap();
+                        prot.push_back(Dns6::CODE);
+                    }
                     Ip4::CODE => {
                         addr.push_back(Ip4::new([172,0,0,2])).unwrap();
                         prot.push_back(Ip4::CODE)
@@ -146,6 +156,8 @@ quickcheck! {
 const PROTOS: &[Code] = &[
     Tcp::CODE,
     DnsAddr::CODE,
+    Dns4::CODE,
+    Dns6::CODE,
     Ip4::CODE,
     Ip6::CODE,
     Secure::CODE,
@@ -162,6 +174,8 @@ impl Arbitrary for Addr {
             match *g.choose(PROTOS).unwrap() {
                 Tcp::CODE => a.push_back(Tcp::new(u16::arbitrary(g))).unwrap(),
                 DnsAddr::CODE => a.push_back(DnsAddr::new(gen_hostname())).unwrap(),
+                Dns4::CODE => a.push_back(Dns4::new(gen_hostname())).unwrap(),
+                Dns6::CODE => a.push_back(Dns6::new(gen_hostname())).unwrap(),
                 Ip4::CODE => a.push_back(Ip4::new(Ipv4Addr::arbitrary(g))).unwrap(),
                 Ip6::CODE => a.push_back(Ip6::new(Ipv6Addr::arbitrary(g))).unwrap(),
                 Secure::CODE => a.push_back(Secure::new(gen_string())).unwrap(),
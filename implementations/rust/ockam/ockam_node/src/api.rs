@@ -4,6 +4,7 @@ use minicbor::{Decode, Encode};
 
 use ockam_core::api::Reply::Successful;
 use ockam_core::api::{Error, Reply, Request, Response};
+use ockam_core::compat::string::String;
 use ockam_core::compat::time::Duration;
 use ockam_core::compat::vec::Vec;
 use ockam_core::{LocalInfo, Result, Route};
@@ -15,6 +16,7 @@ use crate::{Context, MessageSendReceiveOptions};
 pub struct Client {
     route: Route,
     timeout: Option<Duration>,
+    api_token: Option<String>,
 }
 
 impl Client {
@@ -24,9 +26,16 @@ impl Client {
         Self {
             route: route.clone(),
             timeout,
+            api_token: None,
         }
     }
 
+    /// Authenticate the requests with a token, which is required by the API of local nodes
+    pub fn with_api_token(mut self, api_token: Option<String>) -> Self {
+        self.api_token = api_token;
+        self
+    }
+
     /// Send a request of type T and receive a reply of type R
     ///
     /// The result is a `Result<Reply<R>>` where `Reply<R>` can contain a value of type `R` but
@@ -122,6 +131,10 @@ impl Client {
     where
         T: Encode<()>,
     {
+        let req = match &self.api_token {
+            Some(api_token) => req.api_token(api_token.clone()),
+            None => req,
+        };
         let mut buf = Vec::new();
         req.encode(&mut buf)?;
         trace! {
@@ -3,9 +3,17 @@ pub type MessageSender<T> = crate::tokio::sync::mpsc::Sender<T>;
 /// Receiver used to receive payload messages
 pub type MessageReceiver<T> = crate::tokio::sync::mpsc::Receiver<T>;
 
+/// Default number of messages which can be waiting in the mailbox of a worker
+pub const DEFAULT_MAILBOX_CAPACITY: usize = 16;
+
 /// Create message channel
 pub fn message_channel<T>() -> (MessageSender<T>, MessageReceiver<T>) {
-    crate::tokio::sync::mpsc::channel(16)
+    message_channel_with_capacity(DEFAULT_MAILBOX_CAPACITY)
+}
+
+/// Create message channel holding up to `capacity` messages
+pub fn message_channel_with_capacity<T>(capacity: usize) -> (MessageSender<T>, MessageReceiver<T>) {
+    crate::tokio::sync::mpsc::channel(capacity)
 }
 
 /// Router sender
@@ -1,4 +1,5 @@
 use crate::channel_types::{SmallReceiver, SmallSender};
+use crate::memory::NodeMemory;
 use crate::tokio::runtime::Handle;
 use crate::{error::*, AsyncDropSender, NodeMessage};
 use core::sync::atomic::AtomicUsize;
@@ -27,6 +28,10 @@ pub struct Context {
     /// List of transports used to resolve external addresses to local workers in routes
     pub(super) transports: Arc<RwLock<HashMap<TransportType, Arc<dyn Transport>>>>,
     pub(super) flow_controls: FlowControls,
+    /// Capacity of the mailboxes of this context and of the contexts created from it
+    pub(super) mailbox_capacity: usize,
+    /// Memory used by the subsystems of the node
+    pub(super) memory: NodeMemory,
 }
 
 /// This trait can be used to integrate transports into a node
@@ -82,6 +87,11 @@ impl Context {
     pub fn flow_controls(&self) -> &FlowControls {
         &self.flow_controls
     }
+
+    /// Return the memory trackers of the subsystems of the node
+    pub fn memory(&self) -> &NodeMemory {
+        &self.memory
+    }
 }
 
 impl Context {
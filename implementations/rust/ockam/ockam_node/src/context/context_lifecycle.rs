@@ -11,7 +11,10 @@ use ockam_core::{
 use ockam_transport_core::Transport;
 
 use crate::async_drop::AsyncDrop;
-use crate::channel_types::{message_channel, small_channel, SmallReceiver, SmallSender};
+use crate::channel_types::{
+    message_channel_with_capacity, small_channel, SmallReceiver, SmallSender,
+};
+use crate::memory::NodeMemory;
 use crate::tokio::{self, runtime::Handle};
 use crate::{debugger, Context};
 use crate::{error::*, relay::CtrlSignal, router::SenderPair, NodeMessage};
@@ -31,6 +34,14 @@ impl Drop for Context {
                 warn!("Encountered error while dropping detached context: {}", e);
             }
         }
+
+        // The messages which were never received are not using memory anymore
+        #[cfg(feature = "std")]
+        let mailboxes = self.memory.mailboxes();
+        #[cfg(feature = "std")]
+        while let Ok(msg) = self.receiver.try_recv() {
+            mailboxes.release(msg.local_message().transport().payload.len());
+        }
     }
 }
 
@@ -55,6 +66,7 @@ impl Context {
     ///
     /// `async_drop_sender` must be provided when creating a detached
     /// Context type (i.e. not backed by a worker relay).
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         rt: Handle,
         sender: SmallSender<NodeMessage>,
@@ -62,8 +74,10 @@ impl Context {
         async_drop_sender: Option<AsyncDropSender>,
         transports: Arc<RwLock<HashMap<TransportType, Arc<dyn Transport>>>>,
         flow_controls: &FlowControls,
+        mailbox_capacity: usize,
+        memory: &NodeMemory,
     ) -> (Self, SenderPair, SmallReceiver<CtrlSignal>) {
-        let (mailbox_tx, receiver) = message_channel();
+        let (mailbox_tx, receiver) = message_channel_with_capacity(mailbox_capacity);
         let (ctrl_tx, ctrl_rx) = small_channel();
         (
             Self {
@@ -75,6 +89,8 @@ impl Context {
                 mailbox_count: Arc::new(0.into()),
                 transports,
                 flow_controls: flow_controls.clone(),
+                mailbox_capacity,
+                memory: memory.clone(),
             },
             SenderPair {
                 msgs: mailbox_tx,
@@ -95,6 +111,8 @@ impl Context {
             None,
             self.transports.clone(),
             &self.flow_controls,
+            self.mailbox_capacity,
+            &self.memory,
         )
     }
 
@@ -110,6 +128,8 @@ impl Context {
             Some(drop_sender),
             self.transports.clone(),
             &self.flow_controls,
+            self.mailbox_capacity,
+            &self.memory,
         )
     }
 
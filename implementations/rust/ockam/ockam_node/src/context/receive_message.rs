@@ -65,6 +65,9 @@ impl Context {
 
                 // First we update the mailbox fill metrics
                 self.mailbox_count.fetch_sub(1, Ordering::Acquire);
+                self.memory
+                    .mailboxes()
+                    .release(msg.local_message().transport().payload.len());
 
                 msg
             }) {
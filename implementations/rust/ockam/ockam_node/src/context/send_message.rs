@@ -1,4 +1,4 @@
-use crate::channel_types::small_channel;
+use crate::channel_types::{small_channel, MessageSender};
 use crate::context::MessageWait;
 use crate::{debugger, Context, MessageReceiveOptions, DEFAULT_TIMEOUT};
 use crate::{error::*, NodeMessage};
@@ -272,12 +272,7 @@ impl Context {
         }
 
         // Send the packed user message with associated route
-        sender
-            .send(relay_msg)
-            .await
-            .map_err(NodeError::from_send_err)?;
-
-        Ok(())
+        self.send_to_mailbox(&sender, relay_msg).await
     }
 
     /// Forward a transport message to its next routing destination
@@ -357,10 +352,24 @@ impl Context {
         }
 
         // Forward the message
-        sender
-            .send(relay_msg)
-            .await
-            .map_err(NodeError::from_send_err)?;
+        self.send_to_mailbox(&sender, relay_msg).await
+    }
+
+    /// Send a message to the mailbox of a worker, accounting for its payload in the memory
+    /// used by the mailboxes until the message is received
+    async fn send_to_mailbox(
+        &self,
+        sender: &MessageSender<RelayMessage>,
+        relay_msg: RelayMessage,
+    ) -> Result<()> {
+        let bytes = relay_msg.local_message().transport().payload.len();
+        let mailboxes = self.memory.mailboxes();
+        mailboxes.try_acquire(bytes)?;
+
+        if let Err(e) = sender.send(relay_msg).await {
+            mailboxes.release(bytes);
+            return Err(NodeError::from_send_err(e));
+        }
 
         Ok(())
     }
@@ -0,0 +1,122 @@
+use core::time::Duration;
+use ockam_core::compat::collections::{HashMap, VecDeque};
+use ockam_core::compat::sync::Mutex;
+use ockam_core::compat::vec::Vec;
+use std::time::Instant;
+
+/// Default time during which a message id is remembered
+pub const DEFAULT_DEDUPLICATION_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Default maximum number of message ids remembered
+pub const DEFAULT_DEDUPLICATION_CAPACITY: usize = 100_000;
+
+/// Cache of the ids of the messages already processed by a worker.
+///
+/// Messages delivered at least once, for example when they are re-sent after a timeout,
+/// can be received several times. A worker checks the id of each message, chosen by the
+/// sender, and drops the duplicates. An id is remembered for a limited time: the TTL must be
+/// longer than the period during which a sender can retry a message.
+/// When the capacity is reached the oldest ids are forgotten first.
+#[derive(Debug)]
+pub struct MessageDeduplicator {
+    ttl: Duration,
+    capacity: usize,
+    seen: Mutex<SeenIds>,
+}
+
+#[derive(Debug, Default)]
+struct SeenIds {
+    ids: HashMap<Vec<u8>, Instant>,
+    /// Ids ordered by reception time, to evict the oldest ones first
+    order: VecDeque<(Instant, Vec<u8>)>,
+}
+
+impl Default for MessageDeduplicator {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUPLICATION_TTL)
+    }
+}
+
+impl MessageDeduplicator {
+    /// Remember message ids during `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            capacity: DEFAULT_DEDUPLICATION_CAPACITY,
+            seen: Mutex::new(SeenIds::default()),
+        }
+    }
+
+    /// Remember at most `capacity` message ids
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Return true if a message with this id was already received during the TTL,
+    /// otherwise remember the id and return false
+    pub fn is_duplicate(&self, message_id: &[u8]) -> bool {
+        self.is_duplicate_at(message_id, Instant::now())
+    }
+
+    /// Number of message ids currently remembered
+    pub fn len(&self) -> usize {
+        self.seen.lock().unwrap().ids.len()
+    }
+
+    /// Return true if no message id is remembered
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn is_duplicate_at(&self, message_id: &[u8], now: Instant) -> bool {
+        let mut seen = self.seen.lock().unwrap();
+        seen.evict(now, self.ttl, self.capacity);
+
+        if seen.ids.contains_key(message_id) {
+            return true;
+        }
+
+        seen.ids.insert(message_id.to_vec(), now);
+        seen.order.push_back((now, message_id.to_vec()));
+        false
+    }
+}
+
+impl SeenIds {
+    fn evict(&mut self, now: Instant, ttl: Duration, capacity: usize) {
+        while let Some((received_at, _)) = self.order.front() {
+            let expired = now.saturating_duration_since(*received_at) >= ttl;
+            if !expired && self.order.len() < capacity {
+                break;
+            }
+            if let Some((_, id)) = self.order.pop_front() {
+                self.ids.remove(&id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicates_are_detected_during_the_ttl() {
+        let deduplicator = MessageDeduplicator::new(Duration::from_secs(60)).with_capacity(2);
+        let start = Instant::now();
+
+        assert!(!deduplicator.is_duplicate_at(b"1", start));
+        assert!(deduplicator.is_duplicate_at(b"1", start + Duration::from_secs(30)));
+        assert!(!deduplicator.is_duplicate_at(b"2", start + Duration::from_secs(30)));
+
+        // the first id expired
+        assert!(!deduplicator.is_duplicate_at(b"1", start + Duration::from_secs(61)));
+        assert_eq!(deduplicator.len(), 2);
+
+        // the capacity is reached, the oldest id is forgotten
+        assert!(!deduplicator.is_duplicate_at(b"3", start + Duration::from_secs(62)));
+        assert!(!deduplicator.is_duplicate_at(b"2", start + Duration::from_secs(62)));
+        assert_eq!(deduplicator.len(), 2);
+    }
+}
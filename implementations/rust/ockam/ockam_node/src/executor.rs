@@ -42,7 +42,11 @@ pub struct Executor {
 impl Executor {
     /// Create a new Ockam node [`Executor`] instance
     pub fn new(flow_controls: &FlowControls) -> Self {
-        let rt = Runtime::new().unwrap();
+        Self::with_runtime(Runtime::new().unwrap(), flow_controls)
+    }
+
+    /// Create a new Ockam node [`Executor`] instance using a specific runtime
+    pub(crate) fn with_runtime(rt: Runtime, flow_controls: &FlowControls) -> Self {
         let router = Router::new(flow_controls);
         #[cfg(feature = "metrics")]
         let metrics = Metrics::new(&rt, router.get_metrics_readout());
@@ -0,0 +1,257 @@
+//! Fault injection, to test the resilience of applications to degraded links.
+
+use crate::tokio::time::sleep;
+use crate::{Context, WorkerBuilder};
+use core::time::Duration;
+use ockam_core::compat::boxed::Box;
+use ockam_core::compat::vec::Vec;
+use ockam_core::flow_control::FlowControlId;
+use ockam_core::{async_trait, Address, Any, LocalMessage, Result, Routed, Worker, LOCAL};
+use tracing::{debug, info};
+
+/// Faults applied to the messages going through a [`FaultyHop`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaultInjection {
+    seed: u64,
+    drop_probability: f64,
+    latency: Duration,
+    reorder_probability: f64,
+    disconnect_after_bytes: Option<usize>,
+    consumer: Vec<FlowControlId>,
+}
+
+impl FaultInjection {
+    /// Faults drawn from a pseudo-random generator initialized with `seed`.
+    /// No fault is applied until some are configured
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            drop_probability: 0.0,
+            latency: Duration::ZERO,
+            reorder_probability: 0.0,
+            disconnect_after_bytes: None,
+            consumer: vec![],
+        }
+    }
+
+    /// Drop each message with a probability between 0 and 1
+    pub fn with_drop_probability(mut self, drop_probability: f64) -> Self {
+        self.drop_probability = drop_probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay each message
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Hold each message with a probability between 0 and 1, and forward it after the next one
+    pub fn with_reorder_probability(mut self, reorder_probability: f64) -> Self {
+        self.reorder_probability = reorder_probability.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Cut the link once the payloads forwarded in both directions exceed `bytes`
+    pub fn with_disconnect_after_bytes(mut self, bytes: usize) -> Self {
+        self.disconnect_after_bytes = Some(bytes);
+        self
+    }
+
+    /// Accept the messages coming from a producer, for example a TCP connection or a secure
+    /// channel, so that the replies can go through the hop
+    pub fn as_consumer(mut self, id: &FlowControlId) -> Self {
+        self.consumer.push(id.clone());
+        self
+    }
+}
+
+/// What to do with a message
+#[derive(Debug, PartialEq, Eq)]
+pub enum FaultOutcome<T> {
+    /// Forward these messages, in this order, which can be none
+    Forward(Vec<T>),
+    /// Cut the link, the message is not forwarded
+    Disconnect,
+}
+
+/// Deterministic application of a [`FaultInjection`] to a sequence of messages
+#[derive(Debug)]
+pub struct FaultInjector<T> {
+    faults: FaultInjection,
+    random: SplitMix64,
+    forwarded_bytes: usize,
+    held: Option<T>,
+}
+
+impl<T> FaultInjector<T> {
+    /// Create an injector for some faults
+    pub fn new(faults: FaultInjection) -> Self {
+        Self {
+            random: SplitMix64(faults.seed),
+            faults,
+            forwarded_bytes: 0,
+            held: None,
+        }
+    }
+
+    /// Latency to add before forwarding a message
+    pub fn latency(&self) -> Duration {
+        self.faults.latency
+    }
+
+    /// Decide what to do with a message having a payload of `len` bytes
+    pub fn inject(&mut self, message: T, len: usize) -> FaultOutcome<T> {
+        // draw all the values for each message, so that each decision doesn't depend on the others
+        let drop = self.random.next_probability() < self.faults.drop_probability;
+        let reorder = self.random.next_probability() < self.faults.reorder_probability;
+
+        if drop {
+            return FaultOutcome::Forward(vec![]);
+        }
+        self.forwarded_bytes = self.forwarded_bytes.saturating_add(len);
+        if let Some(limit) = self.faults.disconnect_after_bytes {
+            if self.forwarded_bytes > limit {
+                return FaultOutcome::Disconnect;
+            }
+        }
+        match self.held.take() {
+            Some(held) => FaultOutcome::Forward(vec![message, held]),
+            None if reorder => {
+                self.held = Some(message);
+                FaultOutcome::Forward(vec![])
+            }
+            None => FaultOutcome::Forward(vec![message]),
+        }
+    }
+}
+
+/// SplitMix64 generator, which gives the same sequence on all the platforms for a given seed
+#[derive(Debug)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Return a number in [0, 1)
+    fn next_probability(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Worker forwarding messages to the next hop of their onward route, with some faults
+pub struct FaultyHop {
+    injector: FaultInjector<LocalMessage>,
+}
+
+impl FaultyHop {
+    /// Start a faulty hop at a random address, and return that address
+    pub async fn create(ctx: &Context, faults: FaultInjection) -> Result<Address> {
+        let address = Address::random_tagged("FaultyHop");
+        for id in &faults.consumer {
+            ctx.flow_controls().add_consumer(address.clone(), id);
+        }
+        info!("Starting a faulty hop at {address}: {faults:?}");
+        WorkerBuilder::new(FaultyHop {
+            injector: FaultInjector::new(faults),
+        })
+        .with_address(address.clone())
+        .start(ctx)
+        .await?;
+        Ok(address)
+    }
+}
+
+#[async_trait]
+impl Worker for FaultyHop {
+    type Context = Context;
+    type Message = Any;
+
+    async fn handle_message(&mut self, ctx: &mut Context, msg: Routed<Any>) -> Result<()> {
+        let mut message = msg.into_local_message();
+        let transport_message = message.transport_mut();
+        transport_message.onward_route.step()?;
+        transport_message
+            .return_route
+            .modify()
+            .prepend(ctx.address());
+        let next = transport_message.onward_route.next().ok().cloned();
+        let len = transport_message.payload.len();
+
+        let latency = self.injector.latency();
+        if !latency.is_zero() {
+            sleep(latency).await;
+        }
+        match self.injector.inject(message, len) {
+            FaultOutcome::Forward(messages) => {
+                for message in messages {
+                    ctx.forward(message).await?;
+                }
+            }
+            FaultOutcome::Disconnect => {
+                info!("Cutting the link at the faulty hop {}", ctx.address());
+                if let Some(next) = next.filter(|next| next.transport_type() == LOCAL) {
+                    if let Err(e) = ctx.stop_worker(next.clone()).await {
+                        debug!("The next hop {next} can't be stopped: {e}");
+                    }
+                }
+                ctx.stop_worker(ctx.address()).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcomes(faults: FaultInjection, count: u8) -> Vec<FaultOutcome<u8>> {
+        let mut injector = FaultInjector::new(faults);
+        (0..count).map(|i| injector.inject(i, 10)).collect()
+    }
+
+    #[test]
+    fn test_deterministic_faults() {
+        let faults = FaultInjection::new(42)
+            .with_drop_probability(0.3)
+            .with_reorder_probability(0.3);
+        assert_eq!(outcomes(faults.clone(), 100), outcomes(faults.clone(), 100));
+
+        let forwarded: Vec<u8> = outcomes(faults, 100)
+            .into_iter()
+            .flat_map(|o| match o {
+                FaultOutcome::Forward(messages) => messages,
+                FaultOutcome::Disconnect => vec![],
+            })
+            .collect();
+        assert!(forwarded.len() < 100);
+        assert!(forwarded.windows(2).any(|w| w[0] > w[1]));
+    }
+
+    #[test]
+    fn test_no_faults() {
+        let expected: Vec<FaultOutcome<u8>> =
+            (0..10).map(|i| FaultOutcome::Forward(vec![i])).collect();
+        assert_eq!(outcomes(FaultInjection::new(1), 10), expected);
+    }
+
+    #[test]
+    fn test_disconnect_after_bytes() {
+        let outcomes = outcomes(FaultInjection::new(1).with_disconnect_after_bytes(25), 3);
+        assert_eq!(
+            outcomes,
+            vec![
+                FaultOutcome::Forward(vec![0]),
+                FaultOutcome::Forward(vec![1]),
+                FaultOutcome::Disconnect
+            ]
+        );
+    }
+}
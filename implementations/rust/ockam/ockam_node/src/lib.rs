@@ -49,6 +49,17 @@ pub mod debugger;
 /// Callback utility
 pub mod callback;
 
+/// Memory accounting per subsystem
+pub mod memory;
+
+/// Detection of the messages received several times
+#[cfg(feature = "std")]
+pub mod deduplication;
+
+/// Fault injection on links, for tests
+#[cfg(feature = "fault_injection")]
+pub mod fault_injection;
+
 mod async_drop;
 mod context;
 mod delayed;
@@ -0,0 +1,202 @@
+use core::fmt::{Debug, Formatter};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use ockam_core::compat::collections::BTreeMap;
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::compat::vec::Vec;
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Error, Result};
+
+/// Name of the subsystem accounting for the messages waiting in the workers mailboxes
+pub const MAILBOXES_SUBSYSTEM: &str = "mailboxes";
+
+/// Number of bytes used by a subsystem of a node, with an optional limit.
+///
+/// The accounting is done by the subsystem itself when it allocates or frees its buffers,
+/// so the bytes reported are an estimate of the memory used by the subsystem data,
+/// without the overhead of the data structures holding them.
+#[derive(Default)]
+pub struct MemoryTracker {
+    used: AtomicUsize,
+    peak: AtomicUsize,
+    /// 0 when there is no limit
+    limit: AtomicUsize,
+}
+
+impl Debug for MemoryTracker {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("MemoryTracker")
+            .field("used", &self.used())
+            .field("peak", &self.peak())
+            .field("limit", &self.limit())
+            .finish()
+    }
+}
+
+impl MemoryTracker {
+    /// Account for `bytes` more bytes, unless that would exceed the limit
+    pub fn try_acquire(&self, bytes: usize) -> Result<()> {
+        let limit = self.limit.load(Ordering::Relaxed);
+        let result = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                let new_used = used.saturating_add(bytes);
+                if limit != 0 && new_used > limit {
+                    None
+                } else {
+                    Some(new_used)
+                }
+            });
+        match result {
+            Ok(used) => {
+                self.peak.fetch_max(used + bytes, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(used) => Err(Error::new(
+                Origin::Node,
+                Kind::ResourceExhausted,
+                format!("the memory limit of {limit} bytes would be exceeded: {used} bytes are already used, {bytes} bytes are requested"),
+            )),
+        }
+    }
+
+    /// Stop accounting for `bytes` bytes
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Account for `bytes` more bytes, until the returned reservation is dropped
+    pub fn try_reserve(self: &Arc<Self>, bytes: usize) -> Result<MemoryReservation> {
+        self.try_acquire(bytes)?;
+        Ok(MemoryReservation {
+            tracker: self.clone(),
+            bytes,
+        })
+    }
+
+    /// Number of bytes currently used
+    pub fn used(&self) -> usize {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Maximum number of bytes used since the node started
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Maximum number of bytes which can be used, if there is a limit
+    pub fn limit(&self) -> Option<usize> {
+        match self.limit.load(Ordering::Relaxed) {
+            0 => None,
+            limit => Some(limit),
+        }
+    }
+
+    /// Set or remove the maximum number of bytes which can be used.
+    /// Lowering the limit below the current usage only fails the next allocations
+    pub fn set_limit(&self, limit: Option<usize>) {
+        self.limit
+            .store(limit.unwrap_or_default(), Ordering::Relaxed);
+    }
+}
+
+/// Bytes accounted by a [`MemoryTracker`] until this reservation is dropped
+#[derive(Debug)]
+pub struct MemoryReservation {
+    tracker: Arc<MemoryTracker>,
+    bytes: usize,
+}
+
+impl MemoryReservation {
+    /// Number of bytes reserved
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.tracker.release(self.bytes)
+    }
+}
+
+/// Memory trackers of the subsystems of a node, by subsystem name.
+///
+/// The trackers are shared by all the contexts of a node, see [`Context::memory`](crate::Context::memory)
+#[derive(Clone, Debug, Default)]
+pub struct NodeMemory {
+    trackers: Arc<RwLock<BTreeMap<String, Arc<MemoryTracker>>>>,
+}
+
+impl NodeMemory {
+    /// Create an empty set of trackers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the tracker of a subsystem, which is created if it doesn't exist yet
+    pub fn tracker(&self, subsystem: &str) -> Arc<MemoryTracker> {
+        if let Some(tracker) = self.trackers.read().unwrap().get(subsystem) {
+            return tracker.clone();
+        }
+        self.trackers
+            .write()
+            .unwrap()
+            .entry(subsystem.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Return the tracker of the messages waiting in the workers mailboxes
+    pub fn mailboxes(&self) -> Arc<MemoryTracker> {
+        self.tracker(MAILBOXES_SUBSYSTEM)
+    }
+
+    /// Set or remove the limit of a subsystem
+    pub fn set_limit(&self, subsystem: &str, limit: Option<usize>) {
+        self.tracker(subsystem).set_limit(limit)
+    }
+
+    /// Return all the trackers, by subsystem name
+    pub fn trackers(&self) -> Vec<(String, Arc<MemoryTracker>)> {
+        self.trackers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(name, tracker)| (name.clone(), tracker.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_tracker_limit() {
+        let memory = NodeMemory::new();
+        let tracker = memory.tracker("buffers");
+        memory.set_limit("buffers", Some(100));
+
+        let reservation = tracker.try_reserve(60).unwrap();
+        assert_eq!(tracker.used(), 60);
+        assert!(tracker.try_reserve(50).is_err());
+        assert_eq!(tracker.used(), 60);
+
+        drop(reservation);
+        assert_eq!(tracker.used(), 0);
+        assert_eq!(tracker.peak(), 60);
+
+        tracker.try_acquire(100).unwrap();
+        tracker.release(100);
+        assert_eq!(tracker.peak(), 100);
+
+        tracker.set_limit(None);
+        tracker.try_acquire(1000).unwrap();
+        assert_eq!(memory.trackers().len(), 1);
+    }
+}
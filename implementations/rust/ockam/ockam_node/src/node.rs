@@ -2,6 +2,8 @@ use ockam_core::compat::sync::Arc;
 use ockam_core::flow_control::FlowControls;
 use ockam_core::{Address, AllowAll, Mailbox, Mailboxes};
 
+use crate::channel_types::DEFAULT_MAILBOX_CAPACITY;
+use crate::memory::NodeMemory;
 use crate::{debugger, Context, Executor};
 
 /// A minimal worker implementation that does nothing
@@ -19,6 +21,9 @@ impl ockam_core::Worker for NullWorker {
 /// builder API to customise the underlying node that is created.
 pub struct NodeBuilder {
     logging: bool,
+    worker_threads: Option<usize>,
+    mailbox_capacity: usize,
+    memory: NodeMemory,
 }
 
 impl Default for NodeBuilder {
@@ -30,12 +35,45 @@ impl Default for NodeBuilder {
 impl NodeBuilder {
     /// Create a node
     pub fn new() -> Self {
-        Self { logging: true }
+        Self {
+            logging: true,
+            worker_threads: None,
+            mailbox_capacity: DEFAULT_MAILBOX_CAPACITY,
+            memory: NodeMemory::new(),
+        }
     }
 
     /// Disable logging on this node
     pub fn no_logging(self) -> Self {
-        Self { logging: false }
+        Self {
+            logging: false,
+            ..self
+        }
+    }
+
+    /// Set the number of threads executing the workers of this node.
+    /// By default there is one thread per CPU core
+    pub fn with_worker_threads(self, worker_threads: usize) -> Self {
+        Self {
+            worker_threads: Some(worker_threads.max(1)),
+            ..self
+        }
+    }
+
+    /// Set the number of messages which can be waiting in the mailbox of each worker,
+    /// before their senders have to wait
+    pub fn with_mailbox_capacity(self, mailbox_capacity: usize) -> Self {
+        Self {
+            mailbox_capacity: mailbox_capacity.max(1),
+            ..self
+        }
+    }
+
+    /// Limit the number of bytes which can be used by a subsystem of the node,
+    /// for example [`MAILBOXES_SUBSYSTEM`](crate::memory::MAILBOXES_SUBSYSTEM)
+    pub fn with_memory_limit(self, subsystem: &str, limit: usize) -> Self {
+        self.memory.set_limit(subsystem, Some(limit));
+        self
     }
 
     /// Consume this builder and yield a new Ockam Node
@@ -50,7 +88,18 @@ impl NodeBuilder {
         // Shared instance of FlowControls
         let flow_controls = FlowControls::new();
 
-        let mut exe = Executor::new(&flow_controls);
+        let mut exe = match self.worker_threads {
+            #[cfg(feature = "std")]
+            Some(worker_threads) => Executor::with_runtime(
+                crate::tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(worker_threads)
+                    .enable_all()
+                    .build()
+                    .unwrap(),
+                &flow_controls,
+            ),
+            _ => Executor::new(&flow_controls),
+        };
         let addr: Address = "app".into();
 
         // The root application worker needs a mailbox and relay to accept
@@ -65,6 +114,8 @@ impl NodeBuilder {
             None,
             Default::default(),
             &flow_controls,
+            self.mailbox_capacity,
+            &self.memory,
         );
 
         debugger::log_inherit_context("NODE", &ctx, &ctx);
@@ -1,10 +1,13 @@
 use ockam_core::{
     compat::io,
-    errcode::{Kind, Origin},
+    errcode::{CatalogueEntry, Kind, Origin},
     Error,
 };
 
 /// A Transport worker specific error type
+///
+/// Each variant has the `OCK2xxx` code of its position in the enum, so new variants must be
+/// added at the end
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum TransportError {
     /// Failed to send a malformed message
@@ -43,6 +46,28 @@ pub enum TransportError {
     AttackAttmept,
 }
 
+impl TransportError {
+    /// Return the entry of this error in the errors catalogue
+    pub fn catalogue_entry(&self) -> &'static CatalogueEntry {
+        CatalogueEntry::find(self.catalogue_number())
+            .unwrap_or_else(|| CatalogueEntry::for_kind(Kind::Unknown))
+    }
+
+    /// Return the stable code of this error, for example `OCK2004`
+    pub fn code(&self) -> &'static str {
+        self.catalogue_entry().code
+    }
+
+    /// Return a suggested remediation for this error
+    pub fn help(&self) -> &'static str {
+        self.catalogue_entry().help
+    }
+
+    fn catalogue_number(&self) -> i32 {
+        2000 + *self as i32
+    }
+}
+
 impl ockam_core::compat::error::Error for TransportError {}
 impl core::fmt::Display for TransportError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -90,7 +115,7 @@ impl From<TransportError> for Error {
             AttackAttmept => Kind::Misuse,
         };
 
-        Error::new(Origin::Transport, kind, err)
+        Error::new(Origin::Transport, kind, err).with_catalogue_number(err.catalogue_number())
     }
 }
 
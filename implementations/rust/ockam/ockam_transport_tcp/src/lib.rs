@@ -20,12 +20,20 @@ extern crate alloc;
 
 mod options;
 mod portal;
+mod proxy;
 mod registry;
 mod transport;
 
 use ockam_core::TransportType;
 pub use options::{TcpConnectionOptions, TcpListenerOptions};
-pub use portal::{PortalInternalMessage, PortalMessage, MAX_PAYLOAD_SIZE};
+pub use portal::{
+    DestinationRule, HostLookup, OutletResolver, PortalDirection, PortalInterceptor,
+    PortalInterceptorFactory, PortalInternalMessage, PortalMessage, PortalSessionResumption,
+    ResolvedHost, SystemLookup, TransparentProxy, TransparentProxyMode, DEFAULT_RESOLVER_TTL,
+    DEFAULT_SESSION_BUFFER_SIZE, DEFAULT_SESSION_RESUME_TIMEOUT, MAX_PAYLOAD_SIZE,
+    PORTAL_BUFFERS_SUBSYSTEM,
+};
+pub use proxy::HttpProxy;
 pub use registry::*;
 pub use transport::common::*;
 pub use transport::*;
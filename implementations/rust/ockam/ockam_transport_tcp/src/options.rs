@@ -1,4 +1,5 @@
 use crate::workers::Addresses;
+use crate::{AddressFamily, HttpProxy};
 use ockam_core::compat::sync::Arc;
 use ockam_core::flow_control::{FlowControlId, FlowControlOutgoingAccessControl, FlowControls};
 use ockam_core::{Address, AllowAll, IncomingAccessControl, OutgoingAccessControl};
@@ -13,6 +14,8 @@ pub(crate) struct TcpConnectionAccessControl {
 pub struct TcpConnectionOptions {
     pub(super) consumer: Vec<FlowControlId>,
     pub(crate) flow_control_id: FlowControlId,
+    pub(crate) proxy: Option<HttpProxy>,
+    pub(crate) address_family: AddressFamily,
 }
 
 impl TcpConnectionOptions {
@@ -22,9 +25,23 @@ impl TcpConnectionOptions {
         Self {
             consumer: vec![],
             flow_control_id: FlowControls::generate_flow_control_id(),
+            proxy: None,
+            address_family: AddressFamily::Any,
         }
     }
 
+    /// Only connect to the addresses of this family when the peer is a hostname
+    pub fn with_address_family(mut self, address_family: AddressFamily) -> Self {
+        self.address_family = address_family;
+        self
+    }
+
+    /// Establish the connection through an HTTP(S) forward proxy
+    pub fn with_proxy(mut self, proxy: HttpProxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
     /// Mark that this Connection is a Consumer for to the given [`FlowControlId`]
     pub fn as_consumer(mut self, id: &FlowControlId) -> Self {
         self.consumer.push(id.clone());
@@ -0,0 +1,241 @@
+use crate::DestinationRule;
+use core::fmt::Write;
+use ockam_core::compat::net::{IpAddr, SocketAddr};
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::Result;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use tracing::{debug, warn};
+
+/// Base of the firewall marks and of the routing tables used to capture the traffic,
+/// the port of the Inlet is added to it so that several Inlets can capture traffic
+const CAPTURE_MARK_BASE: u32 = 0x4f43_0000;
+
+/// Redirection installed by a capturing Inlet: the TCP connections to the destinations of
+/// its rules, opened by local processes or routed through the host, are delivered to
+/// the transparent socket of the Inlet.
+///
+/// The packets are matched by an nftables table dedicated to the Inlet. The packets of local
+/// processes are marked, then routed back to the host by a policy routing rule, so that the
+/// `TPROXY` statement of the prerouting chain can deliver them to the Inlet.
+/// Installing the redirection requires the `CAP_NET_ADMIN` capability and the `nft` and `ip`
+/// commands
+#[derive(Debug)]
+pub(super) struct PacketCapture {
+    listener: SocketAddr,
+    installed: bool,
+}
+
+impl PacketCapture {
+    pub(super) fn new(listener: SocketAddr) -> Self {
+        Self {
+            listener,
+            installed: false,
+        }
+    }
+
+    /// Install the redirection of the destinations of `rules`, replacing the redirection
+    /// which was previously installed
+    pub(super) fn install(&mut self, rules: &[DestinationRule]) -> Result<()> {
+        let ruleset = self.ruleset(rules)?;
+        if self.installed {
+            self.uninstall();
+        }
+        run("nft", &["-f", "-"], Some(&ruleset))?;
+        self.installed = true;
+        let mark = self.mark().to_string();
+        for family in ["-4", "-6"] {
+            run(
+                "ip",
+                &[family, "rule", "add", "fwmark", &mark, "lookup", &mark],
+                None,
+            )?;
+            run(
+                "ip",
+                &[
+                    family, "route", "add", "local", "default", "dev", "lo", "table", &mark,
+                ],
+                None,
+            )?;
+        }
+        debug!(listener = %self.listener, table = %self.table(), "installed the capture of the inlet destinations");
+        Ok(())
+    }
+
+    /// Remove the redirection. The errors are only logged since a part of the redirection
+    /// might not have been installed
+    pub(super) fn uninstall(&mut self) {
+        if !self.installed {
+            return;
+        }
+        let mark = self.mark().to_string();
+        let table = self.table();
+        let commands: [(&str, &[&str]); 5] = [
+            ("nft", &["delete", "table", "inet", &table]),
+            (
+                "ip",
+                &["-4", "rule", "del", "fwmark", &mark, "lookup", &mark],
+            ),
+            (
+                "ip",
+                &["-6", "rule", "del", "fwmark", &mark, "lookup", &mark],
+            ),
+            ("ip", &["-4", "route", "flush", "table", &mark]),
+            ("ip", &["-6", "route", "flush", "table", &mark]),
+        ];
+        for (program, args) in commands {
+            if let Err(err) = run(program, args, None) {
+                warn!(%err, "could not remove the capture of the inlet destinations");
+            }
+        }
+        self.installed = false;
+    }
+
+    fn mark(&self) -> u32 {
+        CAPTURE_MARK_BASE + self.listener.port() as u32
+    }
+
+    fn table(&self) -> String {
+        format!("ockam_inlet_{}", self.listener.port())
+    }
+
+    /// nftables table marking the packets to the destinations of `rules` and delivering
+    /// them to the listener of the Inlet
+    fn ruleset(&self, rules: &[DestinationRule]) -> Result<String> {
+        let mark = self.mark();
+        let mut prerouting = String::new();
+        let mut output = String::new();
+        for rule in rules {
+            let (family, target) = match (rule.network(), self.listener.ip()) {
+                (IpAddr::V4(_), IpAddr::V4(ip)) if ip.is_unspecified() => ("ip", String::new()),
+                (IpAddr::V4(_), IpAddr::V4(ip)) => ("ip", ip.to_string()),
+                (IpAddr::V6(_), IpAddr::V6(ip)) if ip.is_unspecified() => ("ip6", String::new()),
+                (IpAddr::V6(_), IpAddr::V6(ip)) => ("ip6", format!("[{ip}]")),
+                (IpAddr::V4(_), IpAddr::V6(ip)) if ip.is_unspecified() => ("ip", String::new()),
+                _ => return Err(unsupported_destination(rule)),
+            };
+            let port = match rule.port() {
+                Some(port) => format!(" tcp dport {port}"),
+                None => String::new(),
+            };
+            let matcher = format!(
+                "meta l4proto tcp {family} daddr {}/{}{port}",
+                rule.network(),
+                rule.prefix_len()
+            );
+            let _ = writeln!(
+                prerouting,
+                "    {matcher} tproxy {family} to {target}:{} meta mark set {mark} accept",
+                self.listener.port()
+            );
+            let _ = writeln!(output, "    {matcher} meta mark set {mark}");
+        }
+
+        let table = self.table();
+        let mut ruleset = String::new();
+        // declaring the table before deleting it replaces the table if it already exists
+        let _ = writeln!(ruleset, "table inet {table}");
+        let _ = writeln!(ruleset, "delete table inet {table}");
+        let _ = writeln!(ruleset, "table inet {table} {{");
+        let _ = writeln!(ruleset, "  chain prerouting {{");
+        let _ = writeln!(
+            ruleset,
+            "    type filter hook prerouting priority mangle; policy accept;"
+        );
+        let _ = writeln!(
+            ruleset,
+            "    meta l4proto tcp socket transparent 1 meta mark set {mark} accept"
+        );
+        ruleset.push_str(&prerouting);
+        let _ = writeln!(ruleset, "  }}");
+        let _ = writeln!(ruleset, "  chain output {{");
+        let _ = writeln!(
+            ruleset,
+            "    type route hook output priority mangle; policy accept;"
+        );
+        ruleset.push_str(&output);
+        let _ = writeln!(ruleset, "  }}");
+        let _ = writeln!(ruleset, "}}");
+        Ok(ruleset)
+    }
+}
+
+impl Drop for PacketCapture {
+    fn drop(&mut self) {
+        self.uninstall()
+    }
+}
+
+fn run(program: &str, args: &[&str], stdin: Option<&str>) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| capture_error(format!("could not run {program}: {e}")))?;
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        child_stdin
+            .write_all(input.as_bytes())
+            .map_err(|e| capture_error(format!("could not run {program}: {e}")))?;
+    }
+    let output = child
+        .wait_with_output()
+        .map_err(|e| capture_error(format!("could not run {program}: {e}")))?;
+    if !output.status.success() {
+        return Err(capture_error(format!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+fn capture_error(message: String) -> ockam_core::Error {
+    ockam_core::Error::new(Origin::Transport, Kind::Io, message)
+}
+
+fn unsupported_destination(rule: &DestinationRule) -> ockam_core::Error {
+    ockam_core::Error::new(
+        Origin::Transport,
+        Kind::Invalid,
+        format!("the destinations {rule} can't be captured by an inlet bound to an address of another family"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::str::FromStr;
+
+    #[test]
+    fn test_capture_ruleset() -> Result<()> {
+        let capture = PacketCapture::new("127.0.0.1:4000".parse().unwrap());
+        let ruleset = capture.ruleset(&[
+            DestinationRule::from_str("10.0.0.0/24:5432")?,
+            DestinationRule::from_str("192.168.1.10")?,
+        ])?;
+        assert!(ruleset
+            .starts_with("table inet ockam_inlet_4000\ndelete table inet ockam_inlet_4000\n"));
+        assert!(ruleset.contains(
+            "meta l4proto tcp ip daddr 10.0.0.0/24 tcp dport 5432 tproxy ip to 127.0.0.1:4000 meta mark set 1329794976 accept"
+        ));
+        assert!(ruleset
+            .contains("meta l4proto tcp ip daddr 192.168.1.10/32 meta mark set 1329794976\n"));
+
+        // IPv6 destinations require an IPv6 listener
+        assert!(capture
+            .ruleset(&[DestinationRule::from_str("[fd00::/64]:443")?])
+            .is_err());
+        let capture = PacketCapture::new("[::]:4000".parse().unwrap());
+        let ruleset = capture.ruleset(&[
+            DestinationRule::from_str("[fd00::/64]:443")?,
+            DestinationRule::from_str("10.0.0.0/8")?,
+        ])?;
+        assert!(ruleset.contains("ip6 daddr fd00::/64 tcp dport 443 tproxy ip6 to :4000"));
+        assert!(ruleset.contains("ip daddr 10.0.0.0/8 tproxy ip to :4000"));
+        Ok(())
+    }
+}
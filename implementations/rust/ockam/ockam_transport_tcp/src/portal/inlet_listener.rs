@@ -1,12 +1,15 @@
 use crate::portal::addresses::{Addresses, PortalType};
+use crate::portal::PortalSession;
+use crate::transport::common::bind_listener;
 use crate::{portal::TcpPortalWorker, TcpInletOptions, TcpRegistry};
 use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::sync::Arc;
 use ockam_core::{async_trait, compat::boxed::Box};
 use ockam_core::{Address, Processor, Result, Route};
 use ockam_node::Context;
 use ockam_transport_core::TransportError;
 use tokio::net::TcpListener;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 /// A TCP Portal Inlet listen processor
 ///
@@ -46,11 +49,11 @@ impl TcpInletListenProcessor {
         let processor_address = Address::random_tagged("TcpInletListenProcessor");
 
         debug!("Binding TcpPortalListenerWorker to {}", addr);
-        let inner = match TcpListener::bind(addr).await {
+        let inner = match Self::bind(addr, &options).await {
             Ok(addr) => addr,
             Err(err) => {
                 error!(%addr, %err, "could not bind to address");
-                return Err(TransportError::from(err).into());
+                return Err(err);
             }
         };
         let socket_addr = inner.local_addr().map_err(TransportError::from)?;
@@ -61,6 +64,42 @@ impl TcpInletListenProcessor {
 
         Ok((socket_addr, processor_address))
     }
+
+    /// Bind to `addr` or, if its port is 0 and the ports are restricted to a range,
+    /// to the first free port of the range, starting from a random one
+    async fn bind(addr: SocketAddr, options: &TcpInletOptions) -> Result<TcpListener> {
+        let port_range = match &options.port_range {
+            Some(port_range) if addr.port() == 0 => port_range,
+            Some(port_range) if !port_range.contains(&addr.port()) => {
+                warn!(%addr, ?port_range, "the port is not in the allowed range");
+                return Err(TransportError::InvalidAddress.into());
+            }
+            _ => return Self::bind_port(addr, options).await,
+        };
+        if port_range.is_empty() {
+            return Err(TransportError::BindFailed.into());
+        }
+
+        let start = *port_range.start() as u32;
+        let count = *port_range.end() as u32 - start + 1;
+        let offset = rand::random::<u32>() % count;
+        for index in 0..count {
+            let port = start + (offset + index) % count;
+            let addr = SocketAddr::new(addr.ip(), port as u16);
+            match Self::bind_port(addr, options).await {
+                Ok(listener) => return Ok(listener),
+                Err(err) => debug!(%addr, %err, "the port is not available"),
+            }
+        }
+        Err(TransportError::BindFailed.into())
+    }
+
+    async fn bind_port(addr: SocketAddr, options: &TcpInletOptions) -> Result<TcpListener> {
+        match &options.transparent_proxy {
+            Some(transparent_proxy) => transparent_proxy.bind(addr).await,
+            None => bind_listener(addr),
+        }
+    }
 }
 
 #[async_trait]
@@ -76,21 +115,65 @@ impl Processor for TcpInletListenProcessor {
     async fn shutdown(&mut self, ctx: &mut Self::Context) -> Result<()> {
         self.registry
             .remove_inlet_listener_processor(&ctx.address());
+        if let Some(transparent_proxy) = &self.options.transparent_proxy {
+            transparent_proxy.release();
+        }
 
         Ok(())
     }
 
     async fn process(&mut self, ctx: &mut Self::Context) -> Result<bool> {
         let addresses = Addresses::generate(PortalType::Inlet);
-        let outlet_listener_route = self.outlet_listener_route.clone();
 
-        self.options.setup_flow_control(
+        let (stream, peer) = self.inner.accept().await.map_err(TransportError::from)?;
+        let outlet_listener_route = match &self.options.transparent_proxy {
+            Some(transparent_proxy) => {
+                let destination = match transparent_proxy.original_destination(&stream) {
+                    Ok(destination) => destination,
+                    Err(err) => {
+                        warn!(%peer, %err, "could not retrieve the original destination of a connection");
+                        return Ok(true);
+                    }
+                };
+                match transparent_proxy.route_for(&destination) {
+                    Some(route) => {
+                        debug!(%peer, %destination, %route, "transparent inlet connection");
+                        route
+                    }
+                    None => {
+                        warn!(%peer, %destination, "no outlet is configured for the original destination of a connection");
+                        return Ok(true);
+                    }
+                }
+            }
+            None => match &self.options.session_resumption {
+                Some(session_resumption) => session_resumption.route(),
+                None => self.outlet_listener_route.clone(),
+            },
+        };
+
+        TcpInletOptions::setup_flow_control(
             ctx.flow_controls(),
-            &addresses,
+            &addresses.remote,
             outlet_listener_route.next()?,
         );
 
-        let (stream, peer) = self.inner.accept().await.map_err(TransportError::from)?;
+        // The sessions of transparent inlets are not resumed since their routes depend
+        // on the destination of each connection
+        let session_resumption = match &self.options.transparent_proxy {
+            Some(_) => None,
+            None => self.options.session_resumption.clone(),
+        };
+        let buffer_size = session_resumption
+            .as_ref()
+            .map(|r| r.buffer_size())
+            .unwrap_or_default();
+        let session = Arc::new(PortalSession::new(
+            addresses.remote.clone(),
+            vec![],
+            buffer_size,
+        ));
+
         TcpPortalWorker::start_new_inlet(
             ctx,
             self.registry.clone(),
@@ -99,6 +182,10 @@ impl Processor for TcpInletListenProcessor {
             outlet_listener_route,
             addresses,
             self.options.incoming_access_control.clone(),
+            self.options.interceptor.as_ref().map(|i| i.create()),
+            self.options.read_buffer_size,
+            session,
+            session_resumption,
         )
         .await?;
 
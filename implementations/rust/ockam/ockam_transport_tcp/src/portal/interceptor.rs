@@ -0,0 +1,45 @@
+use core::fmt::Debug;
+use ockam_core::compat::{boxed::Box, sync::Arc, vec::Vec};
+use ockam_core::{async_trait, LocalMessage, Result};
+use ockam_node::Context;
+
+/// Direction of the data intercepted by a [`PortalInterceptor`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortalDirection {
+    /// Data read from the TCP peer of the portal, about to be sent to the other side of the portal
+    FromPeer,
+    /// Data received from the other side of the portal, about to be written to the TCP peer
+    ToPeer,
+}
+
+/// Inspect, validate or transform the data of a portal connection.
+///
+/// An interceptor is invoked on each chunk of data going through an inlet or an outlet,
+/// for example to scan the data for leaks or to validate a protocol.
+/// The connection doesn't make progress in that direction until the interceptor returns,
+/// so a slow interceptor slows down the connection instead of buffering its data.
+#[async_trait]
+pub trait PortalInterceptor: Send + Sync + 'static {
+    /// Return the data to forward in place of `chunk`, which can be empty to drop it.
+    /// Return an error to close the connection
+    async fn intercept(
+        &self,
+        context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>>;
+}
+
+/// Create a [`PortalInterceptor`] for each connection of a portal,
+/// so that an interceptor can keep a state specific to a connection
+pub trait PortalInterceptorFactory: Debug + Send + Sync + 'static {
+    /// Create the interceptor of a new connection
+    fn create(&self) -> Arc<dyn PortalInterceptor>;
+
+    /// Create the interceptor of a new outlet connection, opened by `message`.
+    /// The local info of the message identifies the inlet, for example with the identity
+    /// of the secure channel it was received from
+    fn create_for_message(&self, _message: &LocalMessage) -> Arc<dyn PortalInterceptor> {
+        self.create()
+    }
+}
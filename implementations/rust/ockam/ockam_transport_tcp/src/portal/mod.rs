@@ -1,13 +1,27 @@
 mod addresses;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod capture;
 mod inlet_listener;
+mod interceptor;
 pub mod options;
 mod outlet_listener;
 mod portal_message;
 mod portal_receiver;
 mod portal_worker;
+mod resolver;
+mod session;
+mod transparent_proxy;
 
 pub(crate) use inlet_listener::*;
+pub use interceptor::*;
 pub(crate) use outlet_listener::*;
 pub use portal_message::*;
 pub(crate) use portal_receiver::*;
 pub(crate) use portal_worker::*;
+pub use resolver::{HostLookup, OutletResolver, ResolvedHost, SystemLookup, DEFAULT_RESOLVER_TTL};
+pub(crate) use resolver::{OutletTarget, OutletTargetResolution};
+use session::{AllowSessionRoute, PortalSession};
+pub use session::{
+    PortalSessionResumption, DEFAULT_SESSION_BUFFER_SIZE, DEFAULT_SESSION_RESUME_TIMEOUT,
+};
+pub use transparent_proxy::*;
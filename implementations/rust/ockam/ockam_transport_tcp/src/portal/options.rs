@@ -1,12 +1,78 @@
-use crate::portal::addresses::Addresses;
+use crate::{
+    AddressFamily, OutletResolver, PortalInterceptorFactory, PortalSessionResumption,
+    TransparentProxy, MAX_PAYLOAD_SIZE,
+};
+use core::fmt::Debug;
+use core::ops::RangeInclusive;
+use core::sync::atomic::{AtomicU64, Ordering};
+use ockam_core::compat::boxed::Box;
+use ockam_core::compat::net::SocketAddr;
 use ockam_core::compat::sync::Arc;
 use ockam_core::flow_control::{FlowControlId, FlowControls};
-use ockam_core::{Address, AllowAll, IncomingAccessControl};
+use ockam_core::{Address, AllowAll, IncomingAccessControl, LocalMessage, Result};
+
+/// Guard kept by a portal connection for as long as it is open
+pub type OutletConnectionPermit = Box<dyn Send + Sync>;
+
+/// Limit the number of connections which can be opened on an Outlet
+pub trait OutletConnectionLimiter: Debug + Send + Sync + 'static {
+    /// Called when a new connection is requested by the sender of `message`.
+    /// Return an error to refuse the connection. Otherwise the returned permit is dropped
+    /// when the connection is closed
+    fn acquire(&self, message: &LocalMessage) -> Result<OutletConnectionPermit>;
+}
+
+/// Observe the connections opened on an Outlet, for example to audit them
+pub trait OutletConnectionObserver: Debug + Send + Sync + 'static {
+    /// Called when a new connection to `peer` has been accepted for the sender of `message`.
+    /// The returned guard is dropped when the connection is closed, `stats` then contains
+    /// the number of bytes transferred by the connection
+    fn connection_opened(
+        &self,
+        message: &LocalMessage,
+        peer: SocketAddr,
+        stats: PortalConnectionStats,
+    ) -> OutletConnectionPermit;
+}
+
+/// Number of bytes transferred by a portal connection
+#[derive(Debug, Clone, Default)]
+pub struct PortalConnectionStats {
+    bytes_to_peer: Arc<AtomicU64>,
+    bytes_from_peer: Arc<AtomicU64>,
+}
+
+impl PortalConnectionStats {
+    /// Number of bytes written to the TCP peer of the portal
+    pub fn bytes_to_peer(&self) -> u64 {
+        self.bytes_to_peer.load(Ordering::Relaxed)
+    }
+
+    /// Number of bytes read from the TCP peer of the portal
+    pub fn bytes_from_peer(&self) -> u64 {
+        self.bytes_from_peer.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn add_bytes_to_peer(&self, count: usize) {
+        self.bytes_to_peer
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub(super) fn add_bytes_from_peer(&self, count: usize) {
+        self.bytes_from_peer
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+}
 
 /// Trust Options for an Inlet
 #[derive(Debug)]
 pub struct TcpInletOptions {
     pub(super) incoming_access_control: Arc<dyn IncomingAccessControl>,
+    pub(super) interceptor: Option<Arc<dyn PortalInterceptorFactory>>,
+    pub(super) transparent_proxy: Option<TransparentProxy>,
+    pub(super) read_buffer_size: usize,
+    pub(super) session_resumption: Option<PortalSessionResumption>,
+    pub(super) port_range: Option<RangeInclusive<u16>>,
 }
 
 impl TcpInletOptions {
@@ -14,9 +80,50 @@ impl TcpInletOptions {
     pub fn new() -> Self {
         Self {
             incoming_access_control: Arc::new(AllowAll),
+            interceptor: None,
+            transparent_proxy: None,
+            read_buffer_size: MAX_PAYLOAD_SIZE,
+            session_resumption: None,
+            port_range: None,
         }
     }
 
+    /// Restrict the ports the Inlet can listen on. If the port of the bind address is 0,
+    /// the Inlet listens on a free port of the range instead of any port assigned by the OS.
+    /// Otherwise the port of the bind address must belong to the range
+    pub fn with_port_range(mut self, port_range: RangeInclusive<u16>) -> Self {
+        self.port_range = Some(port_range);
+        self
+    }
+
+    /// Set the size of the buffer used to read the data of each TCP connection
+    pub fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size.max(1);
+        self
+    }
+
+    /// Accept the connections redirected to the Inlet by the firewall, and send each of them
+    /// to an Outlet depending on its original destination. The route given when creating
+    /// the Inlet is then not used
+    pub fn with_transparent_proxy(mut self, transparent_proxy: TransparentProxy) -> Self {
+        self.transparent_proxy = Some(transparent_proxy);
+        self
+    }
+
+    /// Resume the sessions of the connections after the route to the Outlet has been replaced.
+    /// The route of the session resumption is then used instead of the route given when
+    /// creating the Inlet
+    pub fn with_session_resumption(mut self, session_resumption: PortalSessionResumption) -> Self {
+        self.session_resumption = Some(session_resumption);
+        self
+    }
+
+    /// Intercept the data of each connection of the Inlet
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn PortalInterceptorFactory>) -> Self {
+        self.interceptor = Some(interceptor);
+        self
+    }
+
     /// Set Incoming Access Control
     pub fn with_incoming_access_control_impl(
         mut self,
@@ -36,9 +143,8 @@ impl TcpInletOptions {
     }
 
     pub(super) fn setup_flow_control(
-        &self,
         flow_controls: &FlowControls,
-        addresses: &Addresses,
+        remote_address: &Address,
         next: &Address,
     ) {
         if let Some(flow_control_id) = flow_controls
@@ -46,7 +152,7 @@ impl TcpInletOptions {
             .map(|x| x.flow_control_id().clone())
         {
             // Allow a sender with corresponding flow_control_id send messages to this address
-            flow_controls.add_consumer(addresses.remote.clone(), &flow_control_id);
+            flow_controls.add_consumer(remote_address.clone(), &flow_control_id);
         }
     }
 }
@@ -62,6 +168,13 @@ impl Default for TcpInletOptions {
 pub struct TcpOutletOptions {
     pub(super) consumer: Vec<FlowControlId>,
     pub(super) incoming_access_control: Arc<dyn IncomingAccessControl>,
+    pub(super) connection_limiter: Option<Arc<dyn OutletConnectionLimiter>>,
+    pub(super) connection_observer: Option<Arc<dyn OutletConnectionObserver>>,
+    pub(super) interceptor: Option<Arc<dyn PortalInterceptorFactory>>,
+    pub(super) read_buffer_size: usize,
+    pub(super) session_buffer_size: usize,
+    pub(crate) address_family: AddressFamily,
+    pub(crate) resolver: Option<OutletResolver>,
 }
 
 impl TcpOutletOptions {
@@ -70,9 +183,64 @@ impl TcpOutletOptions {
         Self {
             consumer: vec![],
             incoming_access_control: Arc::new(AllowAll),
+            connection_limiter: None,
+            connection_observer: None,
+            interceptor: None,
+            read_buffer_size: MAX_PAYLOAD_SIZE,
+            session_buffer_size: 0,
+            address_family: AddressFamily::Any,
+            resolver: None,
         }
     }
 
+    /// Set the size of the buffer used to read the data of each TCP connection
+    pub fn with_read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.read_buffer_size = read_buffer_size.max(1);
+        self
+    }
+
+    /// Only connect to the addresses of this family when the peer of the Outlet is a hostname.
+    /// Otherwise each connection is attempted on all the addresses of the peer, IPv4 and IPv6
+    /// attempts being raced
+    pub fn with_address_family(mut self, address_family: AddressFamily) -> Self {
+        self.address_family = address_family;
+        self
+    }
+
+    /// Resolve the hostname of the peer with a resolver caching its addresses. The Outlet then
+    /// follows the changes of the addresses of its peer, see [`OutletResolver`].
+    /// Otherwise the hostname is only resolved when the Outlet is created
+    pub fn with_resolver(mut self, resolver: OutletResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Accept the resumption of the sessions of the connections by their Inlet, see
+    /// [`PortalSessionResumption`]. Each connection retains the last `buffer_size` bytes
+    /// sent to its Inlet
+    pub fn with_session_resumption(mut self, buffer_size: usize) -> Self {
+        self.session_buffer_size = buffer_size;
+        self
+    }
+
+    /// Intercept the data of each connection of the Outlet
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn PortalInterceptorFactory>) -> Self {
+        self.interceptor = Some(interceptor);
+        self
+    }
+
+    /// Set a limiter checking each new connection
+    pub fn with_connection_limiter(mut self, limiter: Arc<dyn OutletConnectionLimiter>) -> Self {
+        self.connection_limiter = Some(limiter);
+        self
+    }
+
+    /// Set an observer notified of each accepted connection
+    pub fn with_connection_observer(mut self, observer: Arc<dyn OutletConnectionObserver>) -> Self {
+        self.connection_observer = Some(observer);
+        self
+    }
+
     /// Set Incoming Access Control
     pub fn with_incoming_access_control_impl(
         mut self,
@@ -111,9 +279,8 @@ impl TcpOutletOptions {
     }
 
     pub(super) fn setup_flow_control_for_outlet(
-        &self,
         flow_controls: &FlowControls,
-        addresses: &Addresses,
+        remote_address: &Address,
         src_addr: &Address,
     ) {
         // Check if the Worker that send us this message is a Producer
@@ -123,7 +290,7 @@ impl TcpOutletOptions {
             .get_flow_control_with_producer(src_addr)
             .map(|x| x.flow_control_id().clone())
         {
-            flow_controls.add_consumer(addresses.remote.clone(), &producer_flow_control_id);
+            flow_controls.add_consumer(remote_address.clone(), &producer_flow_control_id);
         }
     }
 }
@@ -1,10 +1,16 @@
 use crate::portal::addresses::{Addresses, PortalType};
-use crate::{portal::TcpPortalWorker, PortalMessage, TcpOutletOptions, TcpRegistry};
-use ockam_core::{async_trait, Address, DenyAll, Result, Routed, Worker};
+use crate::portal::{OutletTarget, OutletTargetResolution, PortalSession};
+use crate::{
+    portal::TcpPortalWorker, PortalConnectionStats, PortalMessage, TcpOutletOptions, TcpRegistry,
+};
+use ockam_core::compat::collections::HashMap;
+use ockam_core::compat::net::SocketAddr;
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::{async_trait, Address, DenyAll, Result, Route, Routed, Worker};
 use ockam_node::{Context, WorkerBuilder};
 use ockam_transport_core::TransportError;
-use std::net::SocketAddr;
-use tracing::debug;
+use std::sync::Weak;
+use tracing::{debug, warn};
 
 /// A TCP Portal Outlet listen worker
 ///
@@ -13,17 +19,23 @@ use tracing::debug;
 /// [`TcpTransport::create_outlet`](crate::TcpTransport::create_outlet).
 pub(crate) struct TcpOutletListenWorker {
     registry: TcpRegistry,
-    peer: SocketAddr,
+    /// Target of the outlet. Its addresses are tried in turn when connecting to it.
+    /// The target can be changed while the outlet is running,
+    /// see [`TcpTransport::retarget_outlet`](crate::TcpTransport::retarget_outlet)
+    target: Arc<RwLock<OutletTarget>>,
     options: TcpOutletOptions,
+    /// Sessions which can be resumed, indexed by the remote address of their worker
+    sessions: HashMap<Address, Weak<PortalSession>>,
 }
 
 impl TcpOutletListenWorker {
     /// Create a new `TcpOutletListenWorker`
-    fn new(registry: TcpRegistry, peer: SocketAddr, options: TcpOutletOptions) -> Self {
+    fn new(registry: TcpRegistry, target: OutletTarget, options: TcpOutletOptions) -> Self {
         Self {
             registry,
-            peer,
+            target: Arc::new(RwLock::new(target)),
             options,
+            sessions: HashMap::new(),
         }
     }
 
@@ -31,14 +43,14 @@ impl TcpOutletListenWorker {
         ctx: &Context,
         registry: TcpRegistry,
         address: Address,
-        peer: SocketAddr,
+        target: OutletTarget,
         options: TcpOutletOptions,
     ) -> Result<()> {
         let access_control = options.incoming_access_control.clone();
 
         options.setup_flow_control_for_outlet_listener(ctx.flow_controls(), &address);
 
-        let worker = Self::new(registry, peer, options);
+        let worker = Self::new(registry, target, options);
         WorkerBuilder::new(worker)
             .with_address(address)
             .with_incoming_access_control_arc(access_control)
@@ -56,7 +68,8 @@ impl Worker for TcpOutletListenWorker {
     type Message = PortalMessage;
 
     async fn initialize(&mut self, ctx: &mut Self::Context) -> Result<()> {
-        self.registry.add_outlet_listener_worker(&ctx.address());
+        self.registry
+            .add_outlet_listener_worker(&ctx.address(), self.target.clone());
 
         Ok(())
     }
@@ -75,23 +88,65 @@ impl Worker for TcpOutletListenWorker {
         let return_route = msg.return_route();
         let src_addr = msg.src_addr();
 
-        if let PortalMessage::Ping = msg.body() {
-        } else {
-            return Err(TransportError::Protocol.into());
+        match msg.as_body() {
+            PortalMessage::Ping => (),
+            PortalMessage::Resume { session, received } => {
+                return self.resume_session(ctx, &msg, session, *received, return_route);
+            }
+            _ => return Err(TransportError::Protocol.into()),
+        }
+
+        let mut connection_permits = vec![];
+        if let Some(limiter) = &self.options.connection_limiter {
+            connection_permits.push(limiter.acquire(msg.local_message())?);
+        }
+
+        let (peers, resolution) = self.resolve_target().await?;
+        let stats = PortalConnectionStats::default();
+        if let Some(observer) = &self.options.connection_observer {
+            connection_permits.push(observer.connection_opened(
+                msg.local_message(),
+                peers[0],
+                stats.clone(),
+            ));
         }
 
         let addresses = Addresses::generate(PortalType::Outlet);
 
-        self.options
-            .setup_flow_control_for_outlet(ctx.flow_controls(), &addresses, &src_addr);
+        TcpOutletOptions::setup_flow_control_for_outlet(
+            ctx.flow_controls(),
+            &addresses.remote,
+            &src_addr,
+        );
+
+        let session = Arc::new(PortalSession::new(
+            addresses.remote.clone(),
+            msg.local_message().local_info().to_vec(),
+            self.options.session_buffer_size,
+        ));
+        if session.is_resumable() {
+            self.sessions
+                .retain(|_, session| session.strong_count() > 0);
+            self.sessions
+                .insert(addresses.remote.clone(), Arc::downgrade(&session));
+        }
 
         TcpPortalWorker::start_new_outlet(
             ctx,
             self.registry.clone(),
-            self.peer,
+            peers,
             return_route.clone(),
             addresses.clone(),
             self.options.incoming_access_control.clone(),
+            self.options
+                .interceptor
+                .as_ref()
+                .map(|i| i.create_for_message(msg.local_message())),
+            self.options.read_buffer_size,
+            stats,
+            connection_permits,
+            session,
+            resolution,
         )
         .await?;
 
@@ -100,3 +155,80 @@ impl Worker for TcpOutletListenWorker {
         Ok(())
     }
 }
+
+impl TcpOutletListenWorker {
+    /// Return the addresses of the target for a new connection. The hostname of the target,
+    /// if any, is resolved with the resolver of the outlet, which caches its addresses
+    async fn resolve_target(&self) -> Result<(Vec<SocketAddr>, Option<OutletTargetResolution>)> {
+        let target = self
+            .target
+            .read()
+            .map_err(|_| TransportError::PortalInvalidState)?
+            .clone();
+        let (hostname, resolver) = match (target.hostname, &self.options.resolver) {
+            (Some(hostname), Some(resolver)) => (hostname, resolver),
+            _ => return Ok((target.peers, None)),
+        };
+
+        let address_family = self.options.address_family;
+        let peers = match resolver.resolve(&hostname, address_family).await {
+            Ok(peers) => {
+                // keep the last addresses, unless the outlet was retargeted in the meantime
+                let mut current = self
+                    .target
+                    .write()
+                    .map_err(|_| TransportError::PortalInvalidState)?;
+                if current.hostname.as_ref() == Some(&hostname) {
+                    current.peers = peers.clone();
+                }
+                peers
+            }
+            Err(err) => {
+                warn!(%hostname, %err, "The outlet target can't be resolved, using its last addresses");
+                target.peers
+            }
+        };
+        let resolution = OutletTargetResolution {
+            resolver: resolver.clone(),
+            hostname,
+            address_family,
+        };
+        Ok((peers, Some(resolution)))
+    }
+
+    /// Resume a session over the route of the resumption request.
+    /// The session is then resumed by the receiver of the Outlet worker, which sends
+    /// the data missed by the Inlet again
+    fn resume_session(
+        &mut self,
+        ctx: &Context,
+        msg: &Routed<PortalMessage>,
+        session_address: &Address,
+        received: u64,
+        return_route: Route,
+    ) -> Result<()> {
+        let session = match self.sessions.get(session_address).and_then(Weak::upgrade) {
+            Some(session) => session,
+            None => {
+                warn!(session = %session_address, "cannot resume an unknown portal session");
+                return Ok(());
+            }
+        };
+
+        // Only the identity which created the session can resume it
+        if session.owner() != msg.local_message().local_info() {
+            warn!(session = %session_address, "a portal session can only be resumed by its owner");
+            return Ok(());
+        }
+
+        TcpOutletOptions::setup_flow_control_for_outlet(
+            ctx.flow_controls(),
+            session.remote_address(),
+            &msg.src_addr(),
+        );
+        session.resume(return_route, received);
+
+        debug!(session = %session_address, "resumed a portal session");
+        Ok(())
+    }
+}
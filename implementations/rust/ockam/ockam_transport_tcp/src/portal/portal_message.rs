@@ -1,4 +1,4 @@
-use ockam_core::Message;
+use ockam_core::{Address, Message};
 use serde::{Deserialize, Serialize};
 
 /// A command message type for a Portal
@@ -13,6 +13,21 @@ pub enum PortalMessage {
     Disconnect,
     /// Message with binary payload
     Payload(Vec<u8>),
+    /// Message that an Inlet sends to the Outlet listener once the route to the Outlet
+    /// has changed, to resume the session handled by the Outlet worker at `session`.
+    /// `received` is the number of bytes received by the Inlet so far
+    Resume {
+        /// Address of the Outlet worker
+        session: Address,
+        /// Number of bytes received by the Inlet
+        received: u64,
+    },
+    /// Response of the Outlet to a [`PortalMessage::Resume`] message, with the number
+    /// of bytes received by the Outlet so far
+    Resumed {
+        /// Number of bytes received by the Outlet
+        received: u64,
+    },
 }
 
 /// An internal message type for a Portal
@@ -24,3 +39,6 @@ pub enum PortalInternalMessage {
 
 ///Maximum allowed size for a payload
 pub const MAX_PAYLOAD_SIZE: usize = 48 * 1024;
+
+/// Name of the node memory subsystem accounting for the read buffers of the portal connections
+pub const PORTAL_BUFFERS_SUBSYSTEM: &str = "portal_buffers";
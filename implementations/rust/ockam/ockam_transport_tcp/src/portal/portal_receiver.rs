@@ -1,11 +1,21 @@
+use crate::portal::addresses::PortalType;
 use crate::portal::portal_message::MAX_PAYLOAD_SIZE;
-use crate::{PortalInternalMessage, PortalMessage, TcpRegistry};
-use ockam_core::compat::vec::Vec;
+use crate::portal::PortalSession;
+use crate::{
+    PortalConnectionStats, PortalDirection, PortalInterceptor, PortalInternalMessage,
+    PortalMessage, PortalSessionResumption, TcpInletOptions, TcpRegistry,
+    DEFAULT_SESSION_RESUME_TIMEOUT,
+};
+use core::time::Duration;
+use ockam_core::compat::{sync::Arc, vec::Vec};
 use ockam_core::{async_trait, Encodable, LocalMessage, Route, TransportMessage};
 use ockam_core::{route, Address, Processor, Result};
+use ockam_node::memory::MemoryReservation;
 use ockam_node::Context;
+use std::time::Instant;
+use tokio::sync::watch;
 use tokio::{io::AsyncReadExt, net::tcp::OwnedReadHalf};
-use tracing::{error, warn};
+use tracing::{debug, error, warn};
 
 /// A TCP Portal receiving message processor
 ///
@@ -15,25 +25,209 @@ use tracing::{error, warn};
 pub(crate) struct TcpPortalRecvProcessor {
     registry: TcpRegistry,
     buf: Vec<u8>,
+    /// Accounts for the capacity of `buf` in the memory used by the portal buffers
+    _buf_reservation: MemoryReservation,
     read_half: OwnedReadHalf,
     sender_address: Address,
-    onward_route: Route,
+    interceptor: Option<Arc<dyn PortalInterceptor>>,
+    stats: PortalConnectionStats,
+    portal_type: PortalType,
+    /// The onward route is kept in the session since it changes when the session is resumed
+    session: Arc<PortalSession>,
+    /// Routes to the Outlet, for an Inlet resuming its sessions
+    outlet_routes: Option<watch::Receiver<Route>>,
+    resume_timeout: Duration,
+    /// Set while the route to the other side is down: the TCP connection is then not read
+    /// until the session is resumed
+    suspended_since: Option<Instant>,
 }
 
 impl TcpPortalRecvProcessor {
     /// Create a new `TcpPortalRecvProcessor`
-    pub fn new(
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
         registry: TcpRegistry,
         read_half: OwnedReadHalf,
         sender_address: Address,
-        onward_route: Route,
+        interceptor: Option<Arc<dyn PortalInterceptor>>,
+        buf_reservation: MemoryReservation,
+        stats: PortalConnectionStats,
+        portal_type: PortalType,
+        session: Arc<PortalSession>,
+        session_resumption: Option<PortalSessionResumption>,
     ) -> Self {
         Self {
             registry,
-            buf: Vec::with_capacity(MAX_PAYLOAD_SIZE),
+            buf: Vec::with_capacity(buf_reservation.bytes()),
+            _buf_reservation: buf_reservation,
             read_half,
             sender_address,
-            onward_route,
+            interceptor,
+            stats,
+            portal_type,
+            session,
+            outlet_routes: session_resumption.as_ref().map(|r| r.subscribe()),
+            resume_timeout: session_resumption
+                .map(|r| r.timeout())
+                .unwrap_or(DEFAULT_SESSION_RESUME_TIMEOUT),
+            suspended_since: None,
+        }
+    }
+}
+
+impl TcpPortalRecvProcessor {
+    /// Notify the Sender and the other side of the portal that the connection was closed
+    async fn notify_disconnection(&self, ctx: &Context) -> Result<()> {
+        if let Err(err) = ctx
+            .send(
+                route![self.sender_address.clone()],
+                PortalInternalMessage::Disconnect,
+            )
+            .await
+        {
+            warn!(
+                "Error notifying Tcp Portal Sender about dropped connection {}",
+                err
+            );
+        }
+
+        let msg = TransportMessage::v1(
+            self.session.onward_route(),
+            self.sender_address.clone(),
+            PortalMessage::Disconnect.encode()?,
+        );
+        ctx.forward(LocalMessage::new(msg, vec![])).await
+    }
+
+    /// Send data to the other side, retaining it if the session can be resumed
+    async fn send(&mut self, ctx: &Context, data: &[u8]) -> Result<()> {
+        self.session.add_sent(data);
+        self.forward_payload(ctx, data).await
+    }
+
+    async fn forward_payload(&mut self, ctx: &Context, data: &[u8]) -> Result<()> {
+        let msg = TransportMessage::v1(
+            self.session.onward_route(),
+            self.sender_address.clone(),
+            PortalMessage::Payload(data.to_vec()).encode()?,
+        );
+        match ctx.forward(LocalMessage::new(msg, vec![])).await {
+            Ok(()) => Ok(()),
+            Err(err) if self.session.is_resumable() => {
+                // The data is retained and will be sent again once the session is resumed
+                if self.suspended_since.is_none() {
+                    warn!(%err, "the route of a portal session is down, waiting for its resumption");
+                    self.suspended_since = Some(Instant::now());
+                }
+                Ok(())
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Return the next route to the Outlet, or never return if the sessions are not resumed
+    async fn next_outlet_route(outlet_routes: &mut Option<watch::Receiver<Route>>) -> Route {
+        if let Some(routes) = outlet_routes {
+            if routes.changed().await.is_ok() {
+                return routes.borrow_and_update().clone();
+            }
+        }
+        core::future::pending().await
+    }
+
+    /// Ask the Outlet listener at `outlet_route` to resume the session
+    async fn request_resumption(&mut self, ctx: &Context, outlet_route: Route) -> Result<()> {
+        // The session is not established yet
+        let peer_address = match self.session.peer_address() {
+            Some(peer_address) => peer_address,
+            None => return Ok(()),
+        };
+        TcpInletOptions::setup_flow_control(
+            ctx.flow_controls(),
+            self.session.remote_address(),
+            outlet_route.next()?,
+        );
+        self.session.set_resume_route(outlet_route.clone());
+        self.suspended_since.get_or_insert(Instant::now());
+
+        let msg = TransportMessage::v1(
+            outlet_route,
+            self.session.remote_address().clone(),
+            PortalMessage::Resume {
+                session: peer_address,
+                received: self.session.received(),
+            }
+            .encode()?,
+        );
+        if let Err(err) = ctx.forward(LocalMessage::new(msg, vec![])).await {
+            warn!(%err, "cannot request the resumption of a portal session");
+        }
+        Ok(())
+    }
+
+    /// The session was resumed: send again the data which was missed by the other side.
+    /// Return false if that data is not retained anymore
+    async fn resend(&mut self, ctx: &Context) -> Result<bool> {
+        self.suspended_since = None;
+        let data = match self.session.take_data_to_resend() {
+            Some(data) => data,
+            None => {
+                warn!("a portal session cannot be resumed since too much data was lost");
+                self.notify_disconnection(ctx).await?;
+                return Ok(false);
+            }
+        };
+
+        // The Outlet acknowledges the resumption requested by the Inlet
+        if let PortalType::Outlet = self.portal_type {
+            let msg = TransportMessage::v1(
+                self.session.onward_route(),
+                self.session.remote_address().clone(),
+                PortalMessage::Resumed {
+                    received: self.session.received(),
+                }
+                .encode()?,
+            );
+            ctx.forward(LocalMessage::new(msg, vec![])).await?;
+        }
+
+        for chunk in data.chunks(MAX_PAYLOAD_SIZE) {
+            self.forward_payload(ctx, chunk).await?;
+        }
+        debug!(resent = data.len(), "resumed a portal session");
+        Ok(true)
+    }
+
+    /// Wait until the session is resumed, for at most the resumption timeout
+    async fn wait_for_resumption(&mut self, ctx: &Context, since: Instant) -> Result<bool> {
+        let remaining = self.resume_timeout.saturating_sub(since.elapsed());
+        tokio::select! {
+            _ = self.session.resumed() => self.resend(ctx).await,
+            route = Self::next_outlet_route(&mut self.outlet_routes) => {
+                self.request_resumption(ctx, route).await?;
+                Ok(true)
+            }
+            _ = tokio::time::sleep(remaining) => {
+                warn!("a portal session was not resumed in time");
+                self.notify_disconnection(ctx).await?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Append the data which is already available on the connection to the buffer, without
+/// waiting, until the buffer is full.
+/// When a peer sends many small packets this batches them in a single portal message,
+/// which is encrypted at once by the secure channel instead of paying the cost of a message
+/// and an AEAD operation for each packet
+fn read_available(read_half: &OwnedReadHalf, buf: &mut Vec<u8>) {
+    while buf.len() < buf.capacity() {
+        match read_half.try_read_buf(buf) {
+            Ok(0) => break,
+            Ok(_) => continue,
+            // the connection errors and its closing are reported by the next read
+            Err(_) => break,
         }
     }
 }
@@ -56,9 +250,21 @@ impl Processor for TcpPortalRecvProcessor {
     }
 
     async fn process(&mut self, ctx: &mut Context) -> Result<bool> {
+        if let Some(since) = self.suspended_since {
+            return self.wait_for_resumption(ctx, since).await;
+        }
+
         self.buf.clear();
 
-        let _len = match self.read_half.read_buf(&mut self.buf).await {
+        let read = tokio::select! {
+            read = self.read_half.read_buf(&mut self.buf) => read,
+            route = Self::next_outlet_route(&mut self.outlet_routes) => {
+                self.request_resumption(ctx, route).await?;
+                return Ok(true);
+            }
+            _ = self.session.resumed() => return self.resend(ctx).await,
+        };
+        let _len = match read {
             Ok(len) => len,
             Err(err) => {
                 error!("Tcp Portal connection read failed with error: {}", err);
@@ -67,40 +273,96 @@ impl Processor for TcpPortalRecvProcessor {
         };
 
         if self.buf.is_empty() {
-            // Notify Sender that connection was closed
-            if let Err(err) = ctx
-                .send(
-                    route![self.sender_address.clone()],
-                    PortalInternalMessage::Disconnect,
-                )
+            self.notify_disconnection(ctx).await?;
+            return Ok(false);
+        }
+        read_available(&self.read_half, &mut self.buf);
+
+        self.stats.add_bytes_from_peer(self.buf.len());
+
+        let intercepted = match self.interceptor.clone() {
+            Some(interceptor) => match interceptor
+                .intercept(ctx, PortalDirection::FromPeer, self.buf.to_vec())
                 .await
             {
-                warn!(
-                    "Error notifying Tcp Portal Sender about dropped connection {}",
-                    err
-                );
-            }
+                Ok(data) => Some(data),
+                Err(err) => {
+                    warn!("Tcp Portal connection closed by its interceptor: {}", err);
+                    self.notify_disconnection(ctx).await?;
+                    return Ok(false);
+                }
+            },
+            None => None,
+        };
+        let buf = core::mem::take(&mut self.buf);
+        let data = intercepted.as_deref().unwrap_or(&buf);
 
-            let msg = TransportMessage::v1(
-                self.onward_route.clone(),
-                self.sender_address.clone(),
-                PortalMessage::Disconnect.encode()?,
-            );
-            ctx.forward(LocalMessage::new(msg, vec![])).await?;
+        // Loop since the buffer or the intercepted data can be larger than the maximum payload
+        for chunk in data.chunks(MAX_PAYLOAD_SIZE) {
+            self.send(ctx, chunk).await?;
+        }
+        self.buf = buf;
 
-            return Ok(false);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// Return a client connection and the read half of the accepted connection
+    async fn connect() -> (TcpStream, OwnedReadHalf) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (accepted, _) = listener.accept().await.unwrap();
+        let (read_half, _) = accepted.into_split();
+        (client, read_half)
+    }
+
+    /// Read a batch of data like the processor does
+    async fn read_batch(read_half: &mut OwnedReadHalf, buf: &mut Vec<u8>) {
+        buf.clear();
+        read_half.read_buf(buf).await.unwrap();
+        read_available(read_half, buf);
+    }
+
+    #[tokio::test]
+    async fn small_writes_are_sent_in_one_payload() {
+        let (mut client, mut read_half) = connect().await;
+        for i in 0..10u8 {
+            client.write_all(&[i; 100]).await.unwrap();
         }
+        client.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
 
-        // Loop just in case buf was extended (should not happen though)
-        for chunk in self.buf.chunks(MAX_PAYLOAD_SIZE) {
-            let msg = TransportMessage::v1(
-                self.onward_route.clone(),
-                self.sender_address.clone(),
-                PortalMessage::Payload(chunk.to_vec()).encode()?,
-            );
-            ctx.forward(LocalMessage::new(msg, vec![])).await?;
+        let mut buf = Vec::with_capacity(MAX_PAYLOAD_SIZE);
+        read_batch(&mut read_half, &mut buf).await;
+        assert_eq!(buf.len(), 1000);
+        assert_eq!(buf.chunks(MAX_PAYLOAD_SIZE).count(), 1);
+        assert_eq!(&buf[900..], &[9; 100]);
+    }
+
+    #[tokio::test]
+    async fn batches_are_capped_at_the_max_payload_size() {
+        let (mut client, mut read_half) = connect().await;
+        let writes = 2 * MAX_PAYLOAD_SIZE / 1024;
+        for _ in 0..writes {
+            client.write_all(&[1; 1024]).await.unwrap();
         }
+        client.flush().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
 
-        Ok(true)
+        let mut buf = Vec::with_capacity(MAX_PAYLOAD_SIZE);
+        read_batch(&mut read_half, &mut buf).await;
+        assert_eq!(buf.len(), MAX_PAYLOAD_SIZE);
+        read_batch(&mut read_half, &mut buf).await;
+        assert_eq!(buf.len(), MAX_PAYLOAD_SIZE);
+        // the buffer doesn't grow past its capacity
+        assert_eq!(buf.capacity(), MAX_PAYLOAD_SIZE);
     }
 }
@@ -1,10 +1,16 @@
 use crate::portal::addresses::{Addresses, PortalType};
-use crate::{portal::TcpPortalRecvProcessor, PortalInternalMessage, PortalMessage, TcpRegistry};
+use crate::portal::{AllowSessionRoute, OutletTargetResolution, PortalSession};
+use crate::transport::common::connect_to_any;
+use crate::{
+    portal::TcpPortalRecvProcessor, OutletConnectionPermit, PortalConnectionStats, PortalDirection,
+    PortalInterceptor, PortalInternalMessage, PortalMessage, PortalSessionResumption, TcpRegistry,
+    PORTAL_BUFFERS_SUBSYSTEM,
+};
 use core::time::Duration;
-use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc};
+use ockam_core::compat::{boxed::Box, net::SocketAddr, sync::Arc, vec::Vec};
 use ockam_core::{
-    async_trait, AllowAll, AllowOnwardAddresses, AllowSourceAddress, Decodable, DenyAll,
-    IncomingAccessControl, Mailbox, Mailboxes,
+    async_trait, AllowAll, AllowSourceAddress, Decodable, DenyAll, IncomingAccessControl, Mailbox,
+    Mailboxes,
 };
 use ockam_core::{Any, Result, Route, Routed, Worker};
 use ockam_node::{Context, ProcessorBuilder, WorkerBuilder};
@@ -39,15 +45,27 @@ pub(crate) struct TcpPortalWorker {
     state: State,
     write_half: Option<OwnedWriteHalf>,
     read_half: Option<OwnedReadHalf>,
-    peer: SocketAddr,
+    /// Address of the TCP peer. An Outlet can have several addresses for its peer before
+    /// it is connected, the first one accepting the connection is kept
+    peers: Vec<SocketAddr>,
+    /// Resolution of the hostname of an Outlet target, used when none of its addresses
+    /// accepts a connection
+    resolution: Option<OutletTargetResolution>,
     addresses: Addresses,
     remote_route: Option<Route>,
     is_disconnecting: bool,
     portal_type: PortalType,
+    interceptor: Option<Arc<dyn PortalInterceptor>>,
+    read_buffer_size: usize,
+    stats: PortalConnectionStats,
+    session: Arc<PortalSession>,
+    session_resumption: Option<PortalSessionResumption>,
+    _connection_permits: Vec<OutletConnectionPermit>,
 }
 
 impl TcpPortalWorker {
     /// Start a new `TcpPortalWorker` of type [`TypeName::Inlet`]
+    #[allow(clippy::too_many_arguments)]
     pub(super) async fn start_new_inlet(
         ctx: &Context,
         registry: TcpRegistry,
@@ -56,38 +74,63 @@ impl TcpPortalWorker {
         ping_route: Route,
         addresses: Addresses,
         access_control: Arc<dyn IncomingAccessControl>,
+        interceptor: Option<Arc<dyn PortalInterceptor>>,
+        read_buffer_size: usize,
+        session: Arc<PortalSession>,
+        session_resumption: Option<PortalSessionResumption>,
     ) -> Result<()> {
         Self::start(
             ctx,
             registry,
-            peer,
+            vec![peer],
+            None,
             State::SendPing { ping_route },
             Some(stream),
             addresses,
             PortalType::Inlet,
             access_control,
+            interceptor,
+            read_buffer_size,
+            PortalConnectionStats::default(),
+            vec![],
+            session,
+            session_resumption,
         )
         .await
     }
 
     /// Start a new `TcpPortalWorker` of type [`TypeName::Outlet`]
+    #[allow(clippy::too_many_arguments)]
     pub(super) async fn start_new_outlet(
         ctx: &Context,
         registry: TcpRegistry,
-        peer: SocketAddr,
+        peers: Vec<SocketAddr>,
         pong_route: Route,
         addresses: Addresses,
         access_control: Arc<dyn IncomingAccessControl>,
+        interceptor: Option<Arc<dyn PortalInterceptor>>,
+        read_buffer_size: usize,
+        stats: PortalConnectionStats,
+        connection_permits: Vec<OutletConnectionPermit>,
+        session: Arc<PortalSession>,
+        resolution: Option<OutletTargetResolution>,
     ) -> Result<()> {
         Self::start(
             ctx,
             registry,
-            peer,
+            peers,
+            resolution,
             State::SendPong { pong_route },
             None,
             addresses,
             PortalType::Outlet,
             access_control,
+            interceptor,
+            read_buffer_size,
+            stats,
+            connection_permits,
+            session,
+            None,
         )
         .await
     }
@@ -97,12 +140,19 @@ impl TcpPortalWorker {
     async fn start(
         ctx: &Context,
         registry: TcpRegistry,
-        peer: SocketAddr,
+        peers: Vec<SocketAddr>,
+        resolution: Option<OutletTargetResolution>,
         state: State,
         stream: Option<TcpStream>,
         addresses: Addresses,
         portal_type: PortalType,
         access_control: Arc<dyn IncomingAccessControl>,
+        interceptor: Option<Arc<dyn PortalInterceptor>>,
+        read_buffer_size: usize,
+        stats: PortalConnectionStats,
+        connection_permits: Vec<OutletConnectionPermit>,
+        session: Arc<PortalSession>,
+        session_resumption: Option<PortalSessionResumption>,
     ) -> Result<()> {
         info!(
             "Creating new {:?} at internal: {}, remote: {}",
@@ -124,11 +174,18 @@ impl TcpPortalWorker {
             state,
             write_half: tx,
             read_half: rx,
-            peer,
+            peers,
+            resolution,
             addresses: addresses.clone(),
             remote_route: None,
             is_disconnecting: false,
             portal_type,
+            interceptor,
+            read_buffer_size,
+            stats,
+            session,
+            session_resumption,
+            _connection_permits: connection_permits,
         };
 
         let internal_mailbox = Mailbox::new(
@@ -157,6 +214,7 @@ enum DisconnectionReason {
     FailedTx,
     FailedRx,
     Remote,
+    Intercepted,
 }
 
 impl TcpPortalWorker {
@@ -167,20 +225,29 @@ impl TcpPortalWorker {
     /// Start a `TcpPortalRecvProcessor`
     async fn start_receiver(&mut self, ctx: &Context, onward_route: Route) -> Result<()> {
         if let Some(rx) = self.read_half.take() {
-            let next_hop = onward_route.next()?.clone();
+            self.session.set_onward_route(onward_route);
+            let buf_reservation = ctx
+                .memory()
+                .tracker(PORTAL_BUFFERS_SUBSYSTEM)
+                .try_reserve(self.read_buffer_size)?;
             let receiver = TcpPortalRecvProcessor::new(
                 self.registry.clone(),
                 rx,
                 self.addresses.internal.clone(),
-                onward_route,
+                self.interceptor.clone(),
+                buf_reservation,
+                self.stats.clone(),
+                self.portal_type.clone(),
+                self.session.clone(),
+                self.session_resumption.clone(),
             );
 
             ProcessorBuilder::new(receiver)
                 .with_address(self.addresses.receiver.clone())
-                .with_outgoing_access_control(AllowOnwardAddresses(vec![
-                    next_hop,
+                .with_outgoing_access_control(AllowSessionRoute::new(
+                    self.session.clone(),
                     self.addresses.internal.clone(),
-                ])) // Only sends messages to `onward_route` and Sender
+                )) // Only sends messages to the session route and Sender
                 .start(ctx)
                 .await?;
 
@@ -192,9 +259,10 @@ impl TcpPortalWorker {
 
     async fn notify_remote_about_disconnection(&mut self, ctx: &Context) -> Result<()> {
         // Notify the other end
-        if let Some(remote_route) = self.remote_route.take() {
+        if self.remote_route.take().is_some() {
+            // the route may have changed if the session was resumed
             ctx.send_from_address(
-                remote_route,
+                self.session.onward_route(),
                 PortalMessage::Disconnect,
                 self.addresses.remote.clone(),
             )
@@ -252,7 +320,7 @@ impl TcpPortalWorker {
             DisconnectionReason::FailedTx => {
                 self.notify_remote_about_disconnection(ctx).await?;
             }
-            DisconnectionReason::FailedRx => {
+            DisconnectionReason::FailedRx | DisconnectionReason::Intercepted => {
                 self.notify_remote_about_disconnection(ctx).await?;
                 self.stop_receiver(ctx).await?;
             }
@@ -296,9 +364,21 @@ impl TcpPortalWorker {
         .await?;
 
         if self.write_half.is_none() {
-            let stream = TcpStream::connect(self.peer)
-                .await
-                .map_err(TransportError::from)?;
+            let (peer, stream) = match (connect_to_any(&self.peers).await, &self.resolution) {
+                (Ok(connected), _) => connected,
+                // the addresses of the target may have changed
+                (Err(err), Some(resolution)) => {
+                    debug!(hostname = %resolution.hostname, %err, "Resolving the outlet target again");
+                    let peers = resolution.resolve_again().await?;
+                    if peers == self.peers {
+                        return Err(err);
+                    }
+                    self.peers = peers;
+                    connect_to_any(&self.peers).await?
+                }
+                (Err(err), None) => return Err(err),
+            };
+            self.peers = vec![peer];
             let (rx, tx) = stream.into_split();
             self.write_half = Some(tx);
             self.read_half = Some(rx);
@@ -422,13 +502,37 @@ impl Worker for TcpPortalWorker {
 
                     match msg {
                         PortalMessage::Payload(payload) => {
+                            self.session.add_received(payload.len());
+                            let payload = match self.interceptor.clone() {
+                                Some(interceptor) => match interceptor
+                                    .intercept(ctx, PortalDirection::ToPeer, payload)
+                                    .await
+                                {
+                                    Ok(payload) => payload,
+                                    Err(err) => {
+                                        warn!(
+                                            "{:?} at: {} closed by its interceptor: {}",
+                                            self.portal_type.str(),
+                                            self.addresses.internal,
+                                            err
+                                        );
+                                        self.start_disconnection(
+                                            ctx,
+                                            DisconnectionReason::Intercepted,
+                                        )
+                                        .await?;
+                                        return Ok(());
+                                    }
+                                },
+                                None => payload,
+                            };
                             if let Some(tx) = &mut self.write_half {
                                 match tx.write_all(&payload).await {
-                                    Ok(()) => {}
+                                    Ok(()) => self.stats.add_bytes_to_peer(payload.len()),
                                     Err(err) => {
                                         warn!(
                                             "Failed to send message to peer {} with error: {}",
-                                            self.peer, err
+                                            self.peers[0], err
                                         );
                                         self.start_disconnection(
                                             ctx,
@@ -445,7 +549,16 @@ impl Worker for TcpPortalWorker {
                             self.start_disconnection(ctx, DisconnectionReason::Remote)
                                 .await?;
                         }
-                        PortalMessage::Ping | PortalMessage::Pong => {
+                        PortalMessage::Resumed { received } => match self.portal_type {
+                            PortalType::Inlet => {
+                                debug!("Inlet at: {} resumed its session", self.addresses.internal);
+                                self.session.resume(return_route, received);
+                            }
+                            PortalType::Outlet => return Err(TransportError::Protocol.into()),
+                        },
+                        PortalMessage::Ping
+                        | PortalMessage::Pong
+                        | PortalMessage::Resume { .. } => {
                             return Err(TransportError::Protocol.into());
                         }
                     }
@@ -0,0 +1,353 @@
+use crate::transport::common::order_addresses;
+use crate::AddressFamily;
+use core::fmt::Debug;
+use core::time::Duration;
+use ockam_core::compat::boxed::Box;
+use ockam_core::compat::collections::HashMap;
+use ockam_core::compat::net::{IpAddr, SocketAddr};
+use ockam_core::compat::string::{String, ToString};
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::compat::vec::Vec;
+use ockam_core::{async_trait, Result};
+use ockam_transport_core::TransportError;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// Default time during which the addresses of a host are cached, when the lookup
+/// doesn't return the TTL of the records
+pub const DEFAULT_RESOLVER_TTL: Duration = Duration::from_secs(60);
+
+/// Addresses of a host, with the TTL of their records if it is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedHost {
+    /// Addresses of the host
+    pub addresses: Vec<IpAddr>,
+    /// Time during which the addresses can be cached
+    pub ttl: Option<Duration>,
+}
+
+/// Look up the addresses of a host, for example with the resolver of the operating system
+/// or with DNS over HTTPS
+#[async_trait]
+pub trait HostLookup: Debug + Send + Sync + 'static {
+    /// Return the addresses of `host`
+    async fn lookup(&self, host: &str) -> Result<ResolvedHost>;
+}
+
+/// Look up the addresses of a host with the resolver of the operating system.
+/// The TTL of the records is not known
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemLookup;
+
+#[async_trait]
+impl HostLookup for SystemLookup {
+    async fn lookup(&self, host: &str) -> Result<ResolvedHost> {
+        let addresses = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(TransportError::from)?
+            .map(|addr| addr.ip())
+            .collect();
+        Ok(ResolvedHost {
+            addresses,
+            ttl: None,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    addresses: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+/// Resolve the targets of the Outlets which are hostnames.
+///
+/// The addresses of each host are cached for the TTL of their records, bounded by a minimum
+/// and a maximum TTL, so that an Outlet doesn't send a query for each of its connections
+/// but still follows the changes of a dynamic DNS name:
+///
+///  - a host can be given static addresses, which are never looked up
+///  - when the addresses of a host can't be looked up anymore, the expired addresses are used
+///  - when no address of a host accepts a connection, the host is resolved again
+///
+/// The cache is shared by the clones of a resolver
+#[derive(Debug, Clone)]
+pub struct OutletResolver {
+    lookup: Arc<dyn HostLookup>,
+    overrides: HashMap<String, Vec<IpAddr>>,
+    default_ttl: Duration,
+    min_ttl: Duration,
+    max_ttl: Duration,
+    cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
+}
+
+impl Default for OutletResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutletResolver {
+    /// Create a resolver using the resolver of the operating system
+    pub fn new() -> Self {
+        Self {
+            lookup: Arc::new(SystemLookup),
+            overrides: HashMap::new(),
+            default_ttl: DEFAULT_RESOLVER_TTL,
+            min_ttl: Duration::from_secs(5),
+            max_ttl: Duration::from_secs(3600),
+            cache: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Look up the addresses of the hosts with another method, for example DNS over HTTPS
+    pub fn with_lookup(mut self, lookup: Arc<dyn HostLookup>) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    /// Always resolve `host` to the given addresses
+    pub fn with_override(mut self, host: impl Into<String>, addresses: Vec<IpAddr>) -> Self {
+        self.overrides
+            .insert(host.into().to_ascii_lowercase(), addresses);
+        self
+    }
+
+    /// Set the time during which the addresses are cached when their TTL is not known
+    pub fn with_default_ttl(mut self, ttl: Duration) -> Self {
+        self.default_ttl = ttl;
+        self
+    }
+
+    /// Bound the time during which the addresses are cached, whatever the TTL of their records
+    pub fn with_ttl_bounds(mut self, min_ttl: Duration, max_ttl: Duration) -> Self {
+        self.min_ttl = min_ttl;
+        self.max_ttl = max_ttl.max(min_ttl);
+        self
+    }
+
+    /// Resolve a peer, `<host>:<port>`, to its addresses of a family.
+    /// The IPv4 and IPv6 addresses are interleaved, see [`resolve_peers`](crate::resolve_peers)
+    pub async fn resolve(&self, peer: &str, family: AddressFamily) -> Result<Vec<SocketAddr>> {
+        if let Ok(addr) = peer.parse::<SocketAddr>() {
+            return Ok(vec![addr]);
+        }
+        let (host, port) = split_host_port(peer)?;
+        let ips = self.lookup_host(&host).await?;
+        let addrs = order_addresses(ips.into_iter().map(|ip| SocketAddr::new(ip, port)), family);
+        if addrs.is_empty() {
+            return Err(TransportError::InvalidAddress.into());
+        }
+        Ok(addrs)
+    }
+
+    /// Forget the cached addresses of a peer, so that it is resolved again
+    pub fn invalidate(&self, peer: &str) {
+        if let Ok((host, _)) = split_host_port(peer) {
+            self.cache.lock().unwrap().remove(&host);
+        }
+    }
+
+    async fn lookup_host(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if let Some(addresses) = self.overrides.get(host) {
+            return Ok(addresses.clone());
+        }
+
+        let stale = match self.cache.lock().unwrap().get(host) {
+            Some(entry) if entry.expires_at > Instant::now() => return Ok(entry.addresses.clone()),
+            entry => entry.cloned(),
+        };
+
+        match self.lookup.lookup(host).await {
+            Ok(resolved) if !resolved.addresses.is_empty() => {
+                let ttl = resolved
+                    .ttl
+                    .unwrap_or(self.default_ttl)
+                    .clamp(self.min_ttl, self.max_ttl);
+                debug!(%host, addresses = ?resolved.addresses, ?ttl, "Resolved an outlet target");
+                self.cache.lock().unwrap().insert(
+                    host.to_string(),
+                    CacheEntry {
+                        addresses: resolved.addresses.clone(),
+                        expires_at: Instant::now() + ttl,
+                    },
+                );
+                Ok(resolved.addresses)
+            }
+            result => match stale {
+                Some(entry) => {
+                    warn!(%host, "The outlet target can't be resolved, using its expired addresses");
+                    Ok(entry.addresses)
+                }
+                None => match result {
+                    Ok(_) => Err(TransportError::InvalidAddress.into()),
+                    Err(err) => Err(err),
+                },
+            },
+        }
+    }
+}
+
+/// Target of an Outlet, shared with the registry so that it can be changed while
+/// the Outlet is running
+#[derive(Debug, Clone)]
+pub(crate) struct OutletTarget {
+    /// Last known addresses of the target
+    pub(crate) peers: Vec<SocketAddr>,
+    /// Hostname of the target, resolved again when its addresses expire
+    pub(crate) hostname: Option<String>,
+}
+
+impl OutletTarget {
+    pub(crate) fn new(peers: Vec<SocketAddr>) -> Self {
+        Self {
+            peers,
+            hostname: None,
+        }
+    }
+}
+
+/// Resolution of the hostname of an Outlet target, used by a portal worker
+/// when none of the addresses of the target accepts a connection
+#[derive(Debug, Clone)]
+pub(crate) struct OutletTargetResolution {
+    pub(crate) resolver: OutletResolver,
+    pub(crate) hostname: String,
+    pub(crate) address_family: AddressFamily,
+}
+
+impl OutletTargetResolution {
+    /// Resolve the hostname again, without using the cached addresses
+    pub(crate) async fn resolve_again(&self) -> Result<Vec<SocketAddr>> {
+        self.resolver.invalidate(&self.hostname);
+        self.resolver
+            .resolve(&self.hostname, self.address_family)
+            .await
+    }
+}
+
+/// Split `<host>:<port>` in a lowercase host, without the brackets of an IPv6 address, and a port
+fn split_host_port(peer: &str) -> Result<(String, u16)> {
+    let (host, port) = peer
+        .rsplit_once(':')
+        .ok_or(TransportError::InvalidAddress)?;
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| TransportError::InvalidAddress)?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() {
+        return Err(TransportError::InvalidAddress.into());
+    }
+    Ok((host.to_ascii_lowercase(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// Return 10.0.0.<n> for the n-th lookup, or an error once `failing` is set
+    #[derive(Debug, Default)]
+    struct CountingLookup {
+        count: AtomicUsize,
+        failing: AtomicBool,
+    }
+
+    #[async_trait]
+    impl HostLookup for CountingLookup {
+        async fn lookup(&self, _host: &str) -> Result<ResolvedHost> {
+            if self.failing.load(Ordering::Relaxed) {
+                return Err(TransportError::InvalidAddress.into());
+            }
+            let n = self.count.fetch_add(1, Ordering::Relaxed) + 1;
+            Ok(ResolvedHost {
+                addresses: vec![IpAddr::from([10, 0, 0, n as u8])],
+                ttl: Some(Duration::from_secs(30)),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_respects_ttl() -> Result<()> {
+        let ttl = Duration::from_millis(100);
+        let lookup = Arc::new(CountingLookup::default());
+        let resolver = OutletResolver::new()
+            .with_lookup(lookup.clone())
+            .with_ttl_bounds(ttl, ttl);
+
+        let first = resolver
+            .resolve("db.example.com:5432", AddressFamily::Any)
+            .await?;
+        assert_eq!(first, vec!["10.0.0.1:5432".parse().unwrap()]);
+
+        // the addresses are cached for the TTL of the records
+        assert_eq!(
+            resolver
+                .resolve("DB.example.com:5432", AddressFamily::Any)
+                .await?,
+            first
+        );
+        assert_eq!(lookup.count.load(Ordering::Relaxed), 1);
+
+        // then the host is resolved again
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(
+            resolver
+                .resolve("db.example.com:5432", AddressFamily::Any)
+                .await?,
+            vec!["10.0.0.2:5432".parse().unwrap()]
+        );
+
+        // the expired addresses are used when the host can't be resolved
+        lookup.failing.store(true, Ordering::Relaxed);
+        tokio::time::sleep(ttl * 2).await;
+        assert_eq!(
+            resolver
+                .resolve("db.example.com:5432", AddressFamily::Any)
+                .await?,
+            vec!["10.0.0.2:5432".parse().unwrap()]
+        );
+
+        // but not once they are invalidated
+        resolver.invalidate("db.example.com:5432");
+        assert!(resolver
+            .resolve("db.example.com:5432", AddressFamily::Any)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_families() -> Result<()> {
+        let lookup = Arc::new(CountingLookup::default());
+        let resolver = OutletResolver::new()
+            .with_lookup(lookup.clone())
+            .with_override(
+                "db.internal",
+                vec![IpAddr::from([192, 168, 1, 1]), "fd00::1".parse().unwrap()],
+            );
+
+        assert_eq!(
+            resolver
+                .resolve("db.internal:80", AddressFamily::Ipv6)
+                .await?,
+            vec!["[fd00::1]:80".parse().unwrap()]
+        );
+        assert_eq!(
+            resolver.resolve("127.0.0.1:80", AddressFamily::Any).await?,
+            vec!["127.0.0.1:80".parse().unwrap()]
+        );
+        assert_eq!(lookup.count.load(Ordering::Relaxed), 0);
+
+        // the lookup only returns IPv4 addresses
+        assert!(resolver
+            .resolve("db.example.com:80", AddressFamily::Ipv6)
+            .await
+            .is_err());
+        assert!(resolver
+            .resolve("db.example.com", AddressFamily::Any)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
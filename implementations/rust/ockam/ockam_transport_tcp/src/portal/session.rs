@@ -0,0 +1,298 @@
+use crate::MAX_PAYLOAD_SIZE;
+use core::fmt::{Debug, Formatter};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+use ockam_core::compat::collections::VecDeque;
+use ockam_core::compat::sync::{Arc, Mutex};
+use ockam_core::compat::vec::Vec;
+use ockam_core::{async_trait, Address, LocalInfo, OutgoingAccessControl, RelayMessage, Result};
+use tokio::sync::{watch, Notify};
+
+/// Default maximum duration of an outage of the route between an Inlet and an Outlet
+pub const DEFAULT_SESSION_RESUME_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of bytes retained by each side of a portal connection,
+/// in order to send them again once the connection is resumed
+pub const DEFAULT_SESSION_BUFFER_SIZE: usize = 4 * MAX_PAYLOAD_SIZE;
+
+/// Allow the connections of an Inlet to survive a brief outage of the route to their Outlet.
+///
+/// Each connection of the Inlet is a session identified by the address of its Outlet worker.
+/// When the route to the Outlet is replaced with [`PortalSessionResumption::set_route`], for
+/// example after a secure channel has been re-created, each connection resumes its session
+/// over the new route instead of being reset:
+///
+///  - the data sent during the outage is sent again, provided that it is still retained.
+///    Each side retains the last `buffer_size` bytes it sent
+///  - while the route is down the Inlet stops reading from its TCP connections
+///  - if the session can not be resumed within the timeout, the connection is closed
+///
+/// The Outlet must accept resumptions, see [`TcpOutletOptions::with_session_resumption`](crate::TcpOutletOptions::with_session_resumption)
+#[derive(Clone)]
+pub struct PortalSessionResumption {
+    route: Arc<watch::Sender<ockam_core::Route>>,
+    buffer_size: usize,
+    timeout: Duration,
+}
+
+impl Debug for PortalSessionResumption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PortalSessionResumption")
+            .field("route", &*self.route.borrow())
+            .field("buffer_size", &self.buffer_size)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl PortalSessionResumption {
+    /// Create a session resumption for an Inlet connected to the Outlet at `outlet_route`
+    pub fn new(outlet_route: impl Into<ockam_core::Route>) -> Self {
+        let (route, _) = watch::channel(outlet_route.into());
+        Self {
+            route: Arc::new(route),
+            buffer_size: DEFAULT_SESSION_BUFFER_SIZE,
+            timeout: DEFAULT_SESSION_RESUME_TIMEOUT,
+        }
+    }
+
+    /// Set the number of bytes retained by each connection
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    /// Set the maximum duration of an outage
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Replace the route to the Outlet. The open connections resume their sessions over this route
+    pub fn set_route(&self, outlet_route: impl Into<ockam_core::Route>) {
+        self.route.send_replace(outlet_route.into());
+    }
+
+    /// Current route to the Outlet
+    pub fn route(&self) -> ockam_core::Route {
+        self.route.borrow().clone()
+    }
+
+    /// Number of bytes retained by each connection
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+
+    /// Maximum duration of an outage
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub(super) fn subscribe(&self) -> watch::Receiver<ockam_core::Route> {
+        self.route.subscribe()
+    }
+}
+
+/// State of a portal connection shared by its worker, its receiver and, for an Outlet,
+/// the Outlet listener which handles the resumption requests
+pub(super) struct PortalSession {
+    /// Address of the worker receiving the messages of the other side
+    remote_address: Address,
+    /// Local information of the message which created the session. A session can only be
+    /// resumed by a message with the same local information, i.e. from the same identity
+    owner: Vec<LocalInfo>,
+    buffer_size: usize,
+    received: AtomicU64,
+    state: Mutex<SessionState>,
+    resumed: Notify,
+}
+
+struct SessionState {
+    onward_route: ockam_core::Route,
+    /// Address of the worker of the other side, known once the connection is established
+    peer_address: Option<Address>,
+    /// Route used to send a resumption request, allowed as a destination for the receiver
+    resume_route: Option<ockam_core::Route>,
+    sent: u64,
+    retained: VecDeque<u8>,
+    /// Number of bytes received by the other side, set when the session was resumed
+    peer_received: Option<u64>,
+}
+
+impl PortalSession {
+    pub(super) fn new(remote_address: Address, owner: Vec<LocalInfo>, buffer_size: usize) -> Self {
+        Self {
+            remote_address,
+            owner,
+            buffer_size,
+            received: AtomicU64::new(0),
+            state: Mutex::new(SessionState {
+                onward_route: ockam_core::Route::new().into(),
+                peer_address: None,
+                resume_route: None,
+                sent: 0,
+                retained: VecDeque::new(),
+                peer_received: None,
+            }),
+            resumed: Notify::new(),
+        }
+    }
+
+    pub(super) fn remote_address(&self) -> &Address {
+        &self.remote_address
+    }
+
+    pub(super) fn owner(&self) -> &[LocalInfo] {
+        &self.owner
+    }
+
+    /// Return true if the data sent by this side is retained
+    pub(super) fn is_resumable(&self) -> bool {
+        self.buffer_size > 0
+    }
+
+    pub(super) fn onward_route(&self) -> ockam_core::Route {
+        self.state.lock().unwrap().onward_route.clone()
+    }
+
+    pub(super) fn set_onward_route(&self, route: ockam_core::Route) {
+        let mut state = self.state.lock().unwrap();
+        state.peer_address = route.recipient().ok();
+        state.onward_route = route;
+    }
+
+    pub(super) fn peer_address(&self) -> Option<Address> {
+        self.state.lock().unwrap().peer_address.clone()
+    }
+
+    pub(super) fn set_resume_route(&self, route: ockam_core::Route) {
+        self.state.lock().unwrap().resume_route = Some(route);
+    }
+
+    pub(super) fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    pub(super) fn add_received(&self, count: usize) {
+        self.received.fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    /// Account for data sent to the other side, and retain it if the session is resumable
+    pub(super) fn add_sent(&self, data: &[u8]) {
+        let mut state = self.state.lock().unwrap();
+        state.sent += data.len() as u64;
+        if self.buffer_size == 0 {
+            return;
+        }
+        let data = &data[data.len().saturating_sub(self.buffer_size)..];
+        let overflow = (state.retained.len() + data.len()).saturating_sub(self.buffer_size);
+        state.retained.drain(..overflow);
+        state.retained.extend(data);
+    }
+
+    /// The session was resumed over `route`, the other side having received `peer_received` bytes
+    pub(super) fn resume(&self, route: ockam_core::Route, peer_received: u64) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.onward_route = route;
+            state.resume_route = None;
+            state.peer_received = Some(peer_received);
+        }
+        self.resumed.notify_one();
+    }
+
+    /// Wait until the session is resumed
+    pub(super) async fn resumed(&self) {
+        self.resumed.notified().await
+    }
+
+    /// Return the data which must be sent again after a resumption, or `None` if
+    /// the data missed by the other side is not retained anymore
+    pub(super) fn take_data_to_resend(&self) -> Option<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let peer_received = state.peer_received.take().unwrap_or(state.sent);
+        let missing = state.sent.checked_sub(peer_received)? as usize;
+        if missing > state.retained.len() {
+            return None;
+        }
+        let start = state.retained.len() - missing;
+        Some(state.retained.range(start..).copied().collect())
+    }
+
+    fn allowed_next_hops(&self) -> Vec<Address> {
+        let state = self.state.lock().unwrap();
+        [Some(&state.onward_route), state.resume_route.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter_map(|route| route.next().ok().cloned())
+            .collect()
+    }
+}
+
+/// Outgoing access control of a portal receiver: messages can only be sent to the
+/// current route of the session, which changes when the session is resumed, or to the
+/// portal worker
+#[derive(Clone)]
+pub(super) struct AllowSessionRoute {
+    session: Arc<PortalSession>,
+    worker_address: Address,
+}
+
+impl AllowSessionRoute {
+    pub(super) fn new(session: Arc<PortalSession>, worker_address: Address) -> Self {
+        Self {
+            session,
+            worker_address,
+        }
+    }
+}
+
+impl Debug for AllowSessionRoute {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AllowSessionRoute")
+            .field("worker_address", &self.worker_address)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl OutgoingAccessControl for AllowSessionRoute {
+    async fn is_authorized(&self, relay_msg: &RelayMessage) -> Result<bool> {
+        let destination = relay_msg.destination();
+        Ok(destination == &self.worker_address
+            || self.session.allowed_next_hops().contains(destination))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retained_data() {
+        let session = PortalSession::new(Address::random_local(), vec![], 8);
+        session.add_sent(b"hello");
+        session.add_sent(b"world");
+
+        // the other side missed the last 3 bytes
+        session.resume(ockam_core::route!["channel"], 7);
+        assert_eq!(session.take_data_to_resend(), Some(b"rld".to_vec()));
+
+        // the other side missed more bytes than the retained ones
+        session.resume(ockam_core::route!["channel"], 1);
+        assert_eq!(session.take_data_to_resend(), None);
+
+        // nothing was missed
+        session.resume(ockam_core::route!["channel"], 10);
+        assert_eq!(session.take_data_to_resend(), Some(vec![]));
+    }
+
+    #[test]
+    fn test_not_resumable() {
+        let session = PortalSession::new(Address::random_local(), vec![], 0);
+        assert!(!session.is_resumable());
+        session.add_sent(b"hello");
+        session.resume(ockam_core::route!["channel"], 0);
+        assert_eq!(session.take_data_to_resend(), None);
+    }
+}
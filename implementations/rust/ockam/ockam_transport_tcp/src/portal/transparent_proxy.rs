@@ -0,0 +1,440 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::portal::capture::PacketCapture;
+use core::fmt::{Display, Formatter};
+use core::str::FromStr;
+use ockam_core::compat::net::{IpAddr, SocketAddr};
+use ockam_core::compat::string::String;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use ockam_core::compat::sync::Mutex;
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::compat::vec::Vec;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use ockam_core::errcode::{Kind, Origin};
+use ockam_core::{Result, Route};
+use ockam_transport_core::TransportError;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::warn;
+
+/// How the connections are redirected to a transparent Inlet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransparentProxyMode {
+    /// The connections are redirected with the iptables `REDIRECT` or `DNAT` targets.
+    /// Their original destination is retrieved with the `SO_ORIGINAL_DST` socket option
+    Redirect,
+    /// The connections are redirected with the iptables `TPROXY` target.
+    /// Their original destination is the local address of the accepted socket.
+    /// Binding the Inlet requires the `CAP_NET_ADMIN` capability
+    TProxy,
+    /// The Inlet installs the redirection itself, for the destinations of its rules, and
+    /// removes it when it is stopped. The applications keep connecting to the original
+    /// destinations, from the host or from the machines routed through it, and don't need
+    /// to know the port of the Inlet, which can be any free port. The connections of the node
+    /// itself to these destinations are captured too.
+    /// This mode requires the `CAP_NET_ADMIN` capability and the `nft` and `ip` commands
+    Capture,
+}
+
+impl FromStr for TransparentProxyMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s {
+            "redirect" => Ok(TransparentProxyMode::Redirect),
+            "tproxy" => Ok(TransparentProxyMode::TProxy),
+            "capture" => Ok(TransparentProxyMode::Capture),
+            _ => Err(format!(
+                "invalid transparent proxy mode {s}, expected 'redirect', 'tproxy' or 'capture'"
+            )),
+        }
+    }
+}
+
+impl Display for TransparentProxyMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TransparentProxyMode::Redirect => f.write_str("redirect"),
+            TransparentProxyMode::TProxy => f.write_str("tproxy"),
+            TransparentProxyMode::Capture => f.write_str("capture"),
+        }
+    }
+}
+
+/// Original destinations matched by a rule of a [`TransparentProxy`]:
+/// an IP address or a subnet, with an optional port, for example `10.0.0.12:5432`,
+/// `10.0.0.0/24`, `10.0.0.0/24:5432` or `[fd00::/64]:443`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestinationRule {
+    network: IpAddr,
+    prefix_len: u8,
+    port: Option<u16>,
+}
+
+impl DestinationRule {
+    /// Match the destinations of a subnet, on any port if `port` is not specified
+    pub fn new(network: IpAddr, prefix_len: u8, port: Option<u16>) -> Result<Self> {
+        let max_prefix_len = match network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_prefix_len {
+            return Err(TransportError::InvalidAddress.into());
+        }
+        Ok(Self {
+            network,
+            prefix_len,
+            port,
+        })
+    }
+
+    /// Network of the matched destinations
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    /// Length of the prefix of the network
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Port of the matched destinations, any port if it is not specified
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Return true if a destination is matched by this rule
+    pub fn matches(&self, destination: &SocketAddr) -> bool {
+        if matches!(self.port, Some(port) if port != destination.port()) {
+            return false;
+        }
+        match (self.network, destination.ip()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                prefix_matches(&network.octets(), &ip.octets(), self.prefix_len)
+            }
+            (IpAddr::V4(network), IpAddr::V6(ip)) => match ip.to_ipv4_mapped() {
+                Some(ip) => prefix_matches(&network.octets(), &ip.octets(), self.prefix_len),
+                None => false,
+            },
+            (IpAddr::V6(_), IpAddr::V4(_)) => false,
+        }
+    }
+
+    /// Rules matching fewer destinations are more specific
+    fn specificity(&self) -> (bool, u8) {
+        let prefix_len = match self.network {
+            IpAddr::V4(_) => self.prefix_len,
+            IpAddr::V6(_) => self.prefix_len.saturating_sub(96),
+        };
+        (self.port.is_some(), prefix_len)
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix_len: u8) -> bool {
+    let full_bytes = (prefix_len / 8) as usize;
+    let remaining_bits = prefix_len % 8;
+    if network[..full_bytes] != ip[..full_bytes] {
+        return false;
+    }
+    if remaining_bits == 0 {
+        return true;
+    }
+    let mask = 0xffu8 << (8 - remaining_bits);
+    network[full_bytes] & mask == ip[full_bytes] & mask
+}
+
+impl FromStr for DestinationRule {
+    type Err = TransportError;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        // Split the optional port, IPv6 subnets must be enclosed in brackets to have a port
+        let (network, port) = match s.strip_prefix('[') {
+            Some(rest) => match rest.split_once(']') {
+                Some((network, "")) => (network, None),
+                Some((network, port)) => match port.strip_prefix(':') {
+                    Some(port) => (network, Some(port)),
+                    None => return Err(TransportError::InvalidAddress),
+                },
+                None => return Err(TransportError::InvalidAddress),
+            },
+            None => match s.rsplit_once(':') {
+                Some((network, port)) if !network.contains(':') => (network, Some(port)),
+                _ => (s, None),
+            },
+        };
+        let port = match port {
+            Some(port) => Some(
+                port.parse::<u16>()
+                    .map_err(|_| TransportError::InvalidAddress)?,
+            ),
+            None => None,
+        };
+        let (ip, prefix_len) = match network.split_once('/') {
+            Some((ip, prefix_len)) => (
+                ip,
+                Some(
+                    prefix_len
+                        .parse::<u8>()
+                        .map_err(|_| TransportError::InvalidAddress)?,
+                ),
+            ),
+            None => (network, None),
+        };
+        let ip = IpAddr::from_str(ip).map_err(|_| TransportError::InvalidAddress)?;
+        let prefix_len = prefix_len.unwrap_or(if ip.is_ipv4() { 32 } else { 128 });
+        Self::new(ip, prefix_len, port).map_err(|_| TransportError::InvalidAddress)
+    }
+}
+
+impl Display for DestinationRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let network = format!("{}/{}", self.network, self.prefix_len);
+        match (self.port, self.network) {
+            (None, _) => f.write_str(&network),
+            (Some(port), IpAddr::V4(_)) => write!(f, "{network}:{port}"),
+            (Some(port), IpAddr::V6(_)) => write!(f, "[{network}]:{port}"),
+        }
+    }
+}
+
+/// Routes used by a transparent Inlet, depending on the original destination of each
+/// accepted connection.
+///
+/// The most specific rule matching the original destination is used: a rule with a port
+/// before a rule without port, then the rule with the longest prefix.
+/// The connections whose destination doesn't match any rule are closed
+#[derive(Debug, Clone)]
+pub struct TransparentProxy {
+    mode: TransparentProxyMode,
+    routes: Arc<RwLock<Vec<(DestinationRule, Route)>>>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    capture: Arc<Mutex<Option<PacketCapture>>>,
+}
+
+impl TransparentProxy {
+    /// Create a transparent proxy without routes
+    pub fn new(mode: TransparentProxyMode) -> Self {
+        Self {
+            mode,
+            routes: Default::default(),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            capture: Default::default(),
+        }
+    }
+
+    /// Send the connections to the destinations matched by `rule` to the Outlet at `route`
+    pub fn with_route(self, rule: DestinationRule, route: impl Into<Route>) -> Self {
+        self.set_route(rule, route);
+        self
+    }
+
+    /// Set the route used for a rule, for example when the connection to its Outlet
+    /// has been re-established. The connections which are already open are not affected.
+    /// When the Inlet captures its destinations, the destinations of a new rule are captured
+    pub fn set_route(&self, rule: DestinationRule, route: impl Into<Route>) {
+        let route = route.into();
+        let rules = {
+            let mut routes = self.routes.write().unwrap();
+            match routes.iter_mut().find(|(r, _)| r == &rule) {
+                Some((_, existing)) => {
+                    *existing = route;
+                    return;
+                }
+                None => routes.push((rule, route)),
+            }
+            routes.iter().map(|(r, _)| r.clone()).collect::<Vec<_>>()
+        };
+        if let Err(err) = self.recapture(&rules) {
+            warn!(%err, "could not capture the destinations of a new rule");
+        }
+    }
+
+    /// Redirection mode
+    pub fn mode(&self) -> TransparentProxyMode {
+        self.mode
+    }
+
+    /// Rules and their routes
+    pub fn routes(&self) -> Vec<(DestinationRule, Route)> {
+        self.routes.read().unwrap().clone()
+    }
+
+    /// Return the route to use for a connection to an original destination
+    pub fn route_for(&self, destination: &SocketAddr) -> Option<Route> {
+        self.routes
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(rule, _)| rule.matches(destination))
+            .max_by_key(|(rule, _)| rule.specificity())
+            .map(|(_, route)| route.clone())
+    }
+
+    /// Bind a listener accepting the redirected connections
+    pub(super) async fn bind(&self, addr: SocketAddr) -> Result<TcpListener> {
+        match self.mode {
+            TransparentProxyMode::Redirect => Ok(TcpListener::bind(addr)
+                .await
+                .map_err(TransportError::from)?),
+            TransparentProxyMode::TProxy => bind_transparent(addr),
+            TransparentProxyMode::Capture => {
+                let listener = bind_transparent(addr)?;
+                self.capture(listener.local_addr().map_err(TransportError::from)?)?;
+                Ok(listener)
+            }
+        }
+    }
+
+    /// Capture the destinations of the rules, once the Inlet is bound to `listener`
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn capture(&self, listener: SocketAddr) -> Result<()> {
+        let rules: Vec<_> = self.routes().into_iter().map(|(r, _)| r).collect();
+        let mut capture = PacketCapture::new(listener);
+        capture.install(&rules)?;
+        *self.capture.lock().unwrap() = Some(capture);
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn capture(&self, _listener: SocketAddr) -> Result<()> {
+        Err(unsupported())
+    }
+
+    /// Capture the destinations of the rules again, after a rule has been added
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn recapture(&self, rules: &[DestinationRule]) -> Result<()> {
+        match self.capture.lock().unwrap().as_mut() {
+            Some(capture) => capture.install(rules),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn recapture(&self, _rules: &[DestinationRule]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Remove the redirection installed by a capturing Inlet, when it is stopped
+    pub(super) fn release(&self) {
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(mut capture) = self.capture.lock().unwrap().take() {
+            capture.uninstall()
+        }
+    }
+
+    /// Return the destination of a redirected connection, before it was redirected
+    pub(super) fn original_destination(&self, stream: &TcpStream) -> Result<SocketAddr> {
+        match self.mode {
+            TransparentProxyMode::Redirect => original_dst(stream),
+            TransparentProxyMode::TProxy | TransparentProxyMode::Capture => {
+                Ok(stream.local_addr().map_err(TransportError::from)?)
+            }
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn bind_transparent(addr: SocketAddr) -> Result<TcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let socket =
+        Socket::new(Domain::for_address(addr), Type::STREAM, None).map_err(TransportError::from)?;
+    socket
+        .set_ip_transparent(true)
+        .map_err(TransportError::from)?;
+    socket
+        .set_reuse_address(true)
+        .map_err(TransportError::from)?;
+    socket.set_nonblocking(true).map_err(TransportError::from)?;
+    socket.bind(&addr.into()).map_err(TransportError::from)?;
+    socket.listen(1024).map_err(TransportError::from)?;
+    Ok(TcpListener::from_std(socket.into()).map_err(TransportError::from)?)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn bind_transparent(_addr: SocketAddr) -> Result<TcpListener> {
+    Err(unsupported())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn original_dst(stream: &TcpStream) -> Result<SocketAddr> {
+    let socket = socket2::SockRef::from(stream);
+    let local_addr = stream.local_addr().map_err(TransportError::from)?;
+    let original_dst = if local_addr.is_ipv4() {
+        socket.original_dst()
+    } else {
+        socket.original_dst_ipv6()
+    };
+    original_dst
+        .map_err(TransportError::from)?
+        .as_socket()
+        .ok_or_else(|| TransportError::InvalidAddress.into())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn original_dst(_stream: &TcpStream) -> Result<SocketAddr> {
+    Err(unsupported())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn unsupported() -> ockam_core::Error {
+    ockam_core::Error::new(
+        Origin::Transport,
+        Kind::Unsupported,
+        "transparent proxying is only supported on Linux",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ockam_core::route;
+
+    #[test]
+    fn test_destination_rule() -> Result<()> {
+        let rule = DestinationRule::from_str("10.0.0.0/24:5432")?;
+        assert!(rule.matches(&"10.0.0.12:5432".parse().unwrap()));
+        assert!(!rule.matches(&"10.0.0.12:5433".parse().unwrap()));
+        assert!(!rule.matches(&"10.0.1.12:5432".parse().unwrap()));
+        assert!(rule.matches(&"[::ffff:10.0.0.12]:5432".parse().unwrap()));
+        assert_eq!(rule.to_string(), "10.0.0.0/24:5432");
+
+        let rule = DestinationRule::from_str("10.0.0.12")?;
+        assert!(rule.matches(&"10.0.0.12:80".parse().unwrap()));
+        assert!(!rule.matches(&"10.0.0.13:80".parse().unwrap()));
+
+        let rule = DestinationRule::from_str("[fd00::/64]:443")?;
+        assert!(rule.matches(&"[fd00::1]:443".parse().unwrap()));
+        assert!(!rule.matches(&"[fd01::1]:443".parse().unwrap()));
+        assert_eq!(rule.to_string(), "[fd00::/64]:443");
+
+        let rule = DestinationRule::from_str("10.0.0.0/12")?;
+        assert!(rule.matches(&"10.15.0.1:80".parse().unwrap()));
+        assert!(!rule.matches(&"10.16.0.1:80".parse().unwrap()));
+
+        assert!(DestinationRule::from_str("10.0.0.0/33").is_err());
+        assert!(DestinationRule::from_str("10.0.0.0:port").is_err());
+        assert!(DestinationRule::from_str("db.internal:5432").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transparent_proxy_routes() -> Result<()> {
+        let proxy = TransparentProxy::new(TransparentProxyMode::Redirect)
+            .with_route(DestinationRule::from_str("10.0.0.0/8")?, route!["subnet"])
+            .with_route(DestinationRule::from_str("10.0.0.0/24")?, route!["small"])
+            .with_route(DestinationRule::from_str("10.0.0.0/8:5432")?, route!["db"]);
+
+        let route = |destination: &str| proxy.route_for(&destination.parse().unwrap());
+        assert_eq!(route("10.1.0.1:80"), Some(route!["subnet"]));
+        assert_eq!(route("10.0.0.1:80"), Some(route!["small"]));
+        assert_eq!(route("10.0.0.1:5432"), Some(route!["db"]));
+        assert_eq!(route("192.168.0.1:5432"), None);
+
+        proxy.set_route(DestinationRule::from_str("10.0.0.0/8")?, route!["other"]);
+        assert_eq!(route("10.1.0.1:80"), Some(route!["other"]));
+        assert_eq!(proxy.routes().len(), 3);
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
+use crate::portal::OutletTarget;
 use crate::{TcpListenerInfo, TcpReceiverInfo, TcpRegistry, TcpSenderInfo};
+use ockam_core::compat::sync::{Arc, RwLock};
 use ockam_core::Address;
 
 impl TcpRegistry {
@@ -32,11 +34,24 @@ impl TcpRegistry {
             lock.remove_inlet_listener_processor(addr);
         }
     }
-    pub(crate) fn add_outlet_listener_worker(&self, addr: &Address) {
+    pub(crate) fn add_outlet_listener_worker(
+        &self,
+        addr: &Address,
+        target: Arc<RwLock<OutletTarget>>,
+    ) {
         if let Ok(mut lock) = self.registry.write() {
-            lock.add_outlet_listener_worker(addr);
+            lock.add_outlet_listener_worker(addr, target);
         }
     }
+    pub(crate) fn outlet_listener_target(
+        &self,
+        addr: &Address,
+    ) -> Option<Arc<RwLock<OutletTarget>>> {
+        self.registry
+            .read()
+            .ok()
+            .and_then(|lock| lock.outlet_listener_target(addr))
+    }
     pub(crate) fn remove_outlet_listener_worker(&self, addr: &Address) {
         if let Ok(mut lock) = self.registry.write() {
             lock.remove_outlet_listener_worker(addr);
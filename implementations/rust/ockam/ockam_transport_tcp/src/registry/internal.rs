@@ -1,4 +1,6 @@
+use crate::portal::OutletTarget;
 use crate::{TcpListenerInfo, TcpReceiverInfo, TcpSenderInfo};
+use ockam_core::compat::sync::{Arc, RwLock};
 use ockam_core::Address;
 
 #[derive(Default)]
@@ -6,7 +8,7 @@ pub(super) struct InternalRegistry {
     pub(super) portal_workers: Vec<Address>,
     pub(super) portal_receiver_processors: Vec<Address>,
     pub(super) inlet_listener_processors: Vec<Address>,
-    pub(super) outlet_listener_workers: Vec<Address>,
+    pub(super) outlet_listener_workers: Vec<(Address, Arc<RwLock<OutletTarget>>)>,
     pub(super) listener_processors: Vec<TcpListenerInfo>,
     pub(super) sender_workers: Vec<TcpSenderInfo>,
     pub(super) receiver_processors: Vec<TcpReceiverInfo>,
@@ -31,11 +33,24 @@ impl InternalRegistry {
     pub(super) fn remove_inlet_listener_processor(&mut self, addr: &Address) {
         self.inlet_listener_processors.retain(|x| x != addr);
     }
-    pub(super) fn add_outlet_listener_worker(&mut self, addr: &Address) {
-        self.outlet_listener_workers.push(addr.clone())
+    pub(super) fn add_outlet_listener_worker(
+        &mut self,
+        addr: &Address,
+        target: Arc<RwLock<OutletTarget>>,
+    ) {
+        self.outlet_listener_workers.push((addr.clone(), target))
     }
     pub(super) fn remove_outlet_listener_worker(&mut self, addr: &Address) {
-        self.outlet_listener_workers.retain(|x| x != addr);
+        self.outlet_listener_workers.retain(|(x, _)| x != addr);
+    }
+    pub(super) fn outlet_listener_target(
+        &self,
+        addr: &Address,
+    ) -> Option<Arc<RwLock<OutletTarget>>> {
+        self.outlet_listener_workers
+            .iter()
+            .find(|(x, _)| x == addr)
+            .map(|(_, target)| target.clone())
     }
     pub(super) fn add_listener_processor(&mut self, info: TcpListenerInfo) {
         self.listener_processors.push(info)
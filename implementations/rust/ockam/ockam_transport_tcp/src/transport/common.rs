@@ -1,10 +1,16 @@
 use crate::TcpConnectionMode;
 use core::fmt;
 use core::fmt::Formatter;
+use core::str::FromStr;
+use core::time::Duration;
 use ockam_core::compat::net::{SocketAddr, ToSocketAddrs};
+use ockam_core::compat::vec::Vec;
 use ockam_core::flow_control::FlowControlId;
 use ockam_core::{Address, Result};
 use ockam_transport_core::TransportError;
+use tokio::net::TcpStream;
+use tokio::task::JoinSet;
+use tracing::debug;
 
 /// Result of [`TcpTransport::connect`] call.
 #[derive(Clone, Debug)]
@@ -121,35 +127,186 @@ impl TcpListener {
     }
 }
 
+/// Family of the IP addresses used to reach a peer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AddressFamily {
+    /// IPv4 and IPv6 addresses
+    #[default]
+    Any,
+    /// Only IPv4 addresses
+    Ipv4,
+    /// Only IPv6 addresses
+    Ipv6,
+}
+
+impl AddressFamily {
+    /// Return true if the address belongs to this family
+    pub fn matches(&self, addr: &SocketAddr) -> bool {
+        match self {
+            AddressFamily::Any => true,
+            AddressFamily::Ipv4 => addr.is_ipv4(),
+            AddressFamily::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressFamily::Any => write!(f, "any"),
+            AddressFamily::Ipv4 => write!(f, "ipv4"),
+            AddressFamily::Ipv6 => write!(f, "ipv6"),
+        }
+    }
+}
+
+impl FromStr for AddressFamily {
+    type Err = TransportError;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(AddressFamily::Any),
+            "ipv4" | "ip4" => Ok(AddressFamily::Ipv4),
+            "ipv6" | "ip6" => Ok(AddressFamily::Ipv6),
+            _ => Err(TransportError::InvalidAddress),
+        }
+    }
+}
+
 /// Resolve the given peer to a [`SocketAddr`](std::net::SocketAddr)
 pub fn resolve_peer(peer: String) -> Result<SocketAddr> {
-    // Try to parse as SocketAddr
-    if let Ok(p) = parse_socket_addr(&peer) {
-        return Ok(p);
+    Ok(resolve_peers(peer, AddressFamily::Any)?[0])
+}
+
+/// Resolve the given peer to all its addresses of an address family.
+///
+/// The IPv4 and IPv6 addresses are interleaved, starting with an IPv4 address, so that
+/// the addresses of both families are tried early when connecting to them in turn
+pub fn resolve_peers(peer: String, family: AddressFamily) -> Result<Vec<SocketAddr>> {
+    // Try to parse as SocketAddr, then to resolve hostname
+    let resolved: Vec<SocketAddr> = match parse_socket_addr(&peer) {
+        Ok(p) => vec![p],
+        Err(_) => peer
+            .to_socket_addrs()
+            .map(|iter| iter.collect())
+            .unwrap_or_default(),
+    };
+
+    let addrs = order_addresses(resolved, family);
+
+    // Nothing worked, return an error
+    if addrs.is_empty() {
+        return Err(TransportError::InvalidAddress.into());
     }
+    Ok(addrs)
+}
 
-    // Try to resolve hostname
-    if let Ok(mut iter) = peer.to_socket_addrs() {
-        // Prefer ip4
-        if let Some(p) = iter.find(|x| x.is_ipv4()) {
-            return Ok(p);
+/// Keep the addresses of a family, without duplicates, and interleave the IPv4 and IPv6
+/// addresses starting with an IPv4 address
+pub(crate) fn order_addresses(
+    addrs: impl IntoIterator<Item = SocketAddr>,
+    family: AddressFamily,
+) -> Vec<SocketAddr> {
+    let mut ipv4 = vec![];
+    let mut ipv6 = vec![];
+    for addr in addrs {
+        if !family.matches(&addr) || ipv4.contains(&addr) || ipv6.contains(&addr) {
+            continue;
         }
-        if let Some(p) = iter.find(|x| x.is_ipv6()) {
-            return Ok(p);
+        if addr.is_ipv4() {
+            ipv4.push(addr)
+        } else {
+            ipv6.push(addr)
         }
     }
 
-    // Nothing worked, return an error
-    Err(TransportError::InvalidAddress.into())
+    let mut addrs = Vec::with_capacity(ipv4.len() + ipv6.len());
+    let (mut ipv4, mut ipv6) = (ipv4.into_iter(), ipv6.into_iter());
+    loop {
+        match (ipv4.next(), ipv6.next()) {
+            (None, None) => break,
+            (v4, v6) => addrs.extend(v4.into_iter().chain(v6)),
+        }
+    }
+    addrs
 }
 
 pub(super) fn parse_socket_addr(s: &str) -> Result<SocketAddr> {
     Ok(s.parse().map_err(|_| TransportError::InvalidAddress)?)
 }
 
+/// Bind a TCP listener. A listener bound to the unspecified IPv6 address `[::]` is dual-stack:
+/// it also accepts IPv4 connections, whatever the default of the operating system is
+pub(crate) fn bind_listener(addr: SocketAddr) -> Result<tokio::net::TcpListener> {
+    use socket2::{Domain, Socket, Type};
+
+    let socket =
+        Socket::new(Domain::for_address(addr), Type::STREAM, None).map_err(TransportError::from)?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false).map_err(TransportError::from)?;
+    }
+    #[cfg(unix)]
+    socket
+        .set_reuse_address(true)
+        .map_err(TransportError::from)?;
+    socket.set_nonblocking(true).map_err(TransportError::from)?;
+    socket.bind(&addr.into()).map_err(TransportError::from)?;
+    socket.listen(1024).map_err(TransportError::from)?;
+    Ok(tokio::net::TcpListener::from_std(socket.into()).map_err(TransportError::from)?)
+}
+
+/// Delay before trying the next address of a peer while a connection attempt is in progress,
+/// as recommended by the Happy Eyeballs algorithm (RFC 8305)
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Connect to the first peer address which accepts a connection.
+///
+/// The connection attempts are raced: the next address is tried when the previous attempt
+/// fails or has not succeeded after [`CONNECTION_ATTEMPT_DELAY`], without cancelling the
+/// previous attempts. The first established connection is returned
+pub(crate) async fn connect_to_any(peers: &[SocketAddr]) -> Result<(SocketAddr, TcpStream)> {
+    let mut attempts = JoinSet::new();
+    let mut next_peers = peers.iter().copied();
+    let mut remaining = peers.len();
+    let mut last_error = None;
+    loop {
+        if let Some(peer) = next_peers.next() {
+            remaining -= 1;
+            debug!(addr = %peer, "Connecting");
+            attempts.spawn(async move { (peer, TcpStream::connect(peer).await) });
+        }
+        if attempts.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            attempt = attempts.join_next() => match attempt {
+                Some(Ok((peer, Ok(stream)))) => {
+                    debug!(addr = %peer, "Connected");
+                    return Ok((peer, stream));
+                }
+                Some(Ok((peer, Err(err)))) => {
+                    debug!(addr = %peer, %err, "Failed to connect");
+                    last_error = Some(err);
+                }
+                Some(Err(_)) | None => (),
+            },
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if remaining > 0 => (),
+        }
+    }
+
+    Err(match last_error {
+        Some(err) => TransportError::from(err).into(),
+        None => TransportError::PeerNotFound.into(),
+    })
+}
+
 #[cfg(test)]
 mod test {
-    use crate::transport::common::parse_socket_addr;
+    use crate::transport::common::{
+        bind_listener, connect_to_any, parse_socket_addr, resolve_peers,
+    };
+    use crate::AddressFamily;
     use core::fmt::Debug;
     use ockam_core::{Error, Result};
     use ockam_transport_core::TransportError;
@@ -201,4 +358,58 @@ mod test {
         let result = parse_socket_addr("127.0.0.1:8080");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_resolve_peers() {
+        let result = resolve_peers("127.0.0.1:80".to_string(), AddressFamily::Any);
+        assert_eq!(result.unwrap(), vec!["127.0.0.1:80".parse().unwrap()]);
+
+        let result = resolve_peers("[::1]:80".to_string(), AddressFamily::Ipv6);
+        assert_eq!(result.unwrap(), vec!["[::1]:80".parse().unwrap()]);
+
+        let result = resolve_peers("127.0.0.1:80".to_string(), AddressFamily::Ipv6);
+        assert_transport_error(result, TransportError::InvalidAddress);
+
+        let result = resolve_peers("[::1]:80".to_string(), AddressFamily::Ipv4);
+        assert_transport_error(result, TransportError::InvalidAddress);
+    }
+
+    #[test]
+    fn test_parse_address_family() {
+        assert_eq!("any".parse::<AddressFamily>(), Ok(AddressFamily::Any));
+        assert_eq!("ipv4".parse::<AddressFamily>(), Ok(AddressFamily::Ipv4));
+        assert_eq!("ip6".parse::<AddressFamily>(), Ok(AddressFamily::Ipv6));
+        assert!("ipv5".parse::<AddressFamily>().is_err());
+        assert_eq!(AddressFamily::Ipv6.to_string(), "ipv6");
+    }
+
+    #[tokio::test]
+    async fn test_connect_to_any() -> Result<()> {
+        // a port which was just released doesn't accept connections
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let closed_addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener_addr = listener.local_addr().unwrap();
+
+        let (peer, _stream) = connect_to_any(&[closed_addr, listener_addr]).await?;
+        assert_eq!(peer, listener_addr);
+
+        assert!(connect_to_any(&[closed_addr]).await.is_err());
+        assert!(connect_to_any(&[]).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_listener() -> Result<()> {
+        let listener = bind_listener("[::]:0".parse().unwrap())?;
+        let port = listener.local_addr().unwrap().port();
+
+        for addr in [format!("127.0.0.1:{port}"), format!("[::1]:{port}")] {
+            let (peer, _stream) = connect_to_any(&[addr.parse().unwrap()]).await?;
+            assert_eq!(peer.port(), port);
+        }
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use crate::transport::common::{resolve_peer, TcpConnection};
+use crate::transport::common::{resolve_peers, TcpConnection};
 use crate::workers::{Addresses, TcpRecvProcessor, TcpSendWorker};
 use crate::{TcpConnectionMode, TcpConnectionOptions, TcpTransport};
 use ockam_core::{Address, Result};
@@ -21,10 +21,15 @@ impl TcpTransport {
         peer: impl Into<String>,
         options: TcpConnectionOptions,
     ) -> Result<TcpConnection> {
-        // Resolve peer address
-        let socket = resolve_peer(peer.into())?;
-
-        let (read_half, write_half) = TcpSendWorker::connect(socket).await?;
+        let peer = peer.into();
+        let (socket, (read_half, write_half)) = match &options.proxy {
+            Some(proxy) => TcpSendWorker::connect_through_proxy(proxy, &peer).await?,
+            None => {
+                // Resolve peer addresses, and race the connections to them
+                let peers = resolve_peers(peer, options.address_family)?;
+                TcpSendWorker::connect(&peers).await?
+            }
+        };
 
         let mode = TcpConnectionMode::Outgoing;
         let addresses = Addresses::generate(mode);
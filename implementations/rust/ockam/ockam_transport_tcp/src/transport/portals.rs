@@ -1,8 +1,10 @@
-use crate::portal::TcpInletListenProcessor;
-use crate::transport::common::{parse_socket_addr, resolve_peer};
+use crate::portal::{OutletTarget, TcpInletListenProcessor};
+use crate::transport::common::{parse_socket_addr, resolve_peers};
 use crate::{portal::TcpOutletListenWorker, TcpInletOptions, TcpOutletOptions, TcpTransport};
 use ockam_core::compat::net::SocketAddr;
 use ockam_core::{Address, Result, Route};
+use ockam_transport_core::TransportError;
+use tracing::debug;
 
 impl TcpTransport {
     /// Create Tcp Inlet that listens on bind_addr, transforms Tcp stream into Ockam Routable
@@ -82,13 +84,21 @@ impl TcpTransport {
         peer: impl Into<String>,
         options: TcpOutletOptions,
     ) -> Result<()> {
-        // Resolve peer address
-        let peer_addr = resolve_peer(peer.into())?;
+        // Resolve peer addresses, the connections to them are raced
+        let peer = peer.into();
+        let target = match &options.resolver {
+            // the hostname is resolved again when its addresses expire
+            Some(resolver) if peer.parse::<SocketAddr>().is_err() => OutletTarget {
+                peers: resolver.resolve(&peer, options.address_family).await?,
+                hostname: Some(peer),
+            },
+            _ => OutletTarget::new(resolve_peers(peer, options.address_family)?),
+        };
         TcpOutletListenWorker::start(
             &self.ctx,
             self.registry.clone(),
             address.into(),
-            peer_addr,
+            target,
             options,
         )
         .await?;
@@ -103,12 +113,52 @@ impl TcpTransport {
         peer: SocketAddr,
         options: TcpOutletOptions,
     ) -> Result<()> {
-        TcpOutletListenWorker::start(&self.ctx, self.registry.clone(), address, peer, options)
-            .await?;
+        TcpOutletListenWorker::start(
+            &self.ctx,
+            self.registry.clone(),
+            address,
+            OutletTarget::new(vec![peer]),
+            options,
+        )
+        .await?;
 
         Ok(())
     }
 
+    /// Change the peer of a running outlet and return the previous one.
+    ///
+    /// The connections opened after this call are made to the new peer, while the
+    /// connections already established with the previous peer are kept until they are closed
+    /// ```rust
+    /// use ockam_transport_tcp::{TcpOutletOptions, TcpTransport};
+    /// # use ockam_node::Context;
+    /// # use ockam_core::{AllowAll, Result};
+    /// # async fn test(ctx: Context) -> Result<()> {
+    /// let tcp = TcpTransport::create(&ctx).await?;
+    /// tcp.create_outlet("outlet", "127.0.0.1:5000", TcpOutletOptions::new()).await?;
+    /// tcp.retarget_outlet("outlet", "127.0.0.1:5001".parse().unwrap())?;
+    /// # tcp.stop_outlet("outlet").await?;
+    /// # Ok(()) }
+    /// ```
+    pub fn retarget_outlet(
+        &self,
+        addr: impl Into<Address>,
+        peer: SocketAddr,
+    ) -> Result<SocketAddr> {
+        let addr = addr.into();
+        let current = self
+            .registry
+            .outlet_listener_target(&addr)
+            .ok_or(TransportError::UnknownRoute)?;
+        let mut current = current
+            .write()
+            .map_err(|_| TransportError::PortalInvalidState)?;
+        let previous = current.peers[0];
+        *current = OutletTarget::new(vec![peer]);
+        debug!(%addr, %previous, %peer, "Outlet retargeted");
+        Ok(previous)
+    }
+
     /// Stop outlet at addr
     /// ```rust
     /// use ockam_transport_tcp::{TcpOutletOptions, TcpTransport};
@@ -1,5 +1,6 @@
+use crate::transport::common::connect_to_any;
 use crate::workers::Addresses;
-use crate::{TcpConnectionMode, TcpRegistry, TcpSenderInfo};
+use crate::{HttpProxy, TcpConnectionMode, TcpRegistry, TcpSenderInfo};
 use cfg_if::cfg_if;
 use core::time::Duration;
 use ockam_core::flow_control::FlowControlId;
@@ -19,7 +20,7 @@ use socket2::{SockRef, TcpKeepalive};
 use tokio::io::AsyncWriteExt;
 use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::TcpStream;
-use tracing::{debug, info, trace, warn};
+use tracing::{info, trace, warn};
 
 #[derive(Serialize, Deserialize, Message, Clone)]
 pub(crate) enum TcpSendWorkerMsg {
@@ -119,21 +120,25 @@ impl TcpSendWorker {
         Ok(())
     }
 
+    /// Connect to the first address of the peer accepting a connection
     pub(crate) async fn connect(
-        socket_address: SocketAddr,
-    ) -> Result<(OwnedReadHalf, OwnedWriteHalf)> {
-        debug!(addr = %socket_address, "Connecting");
-        let connection = match TcpStream::connect(socket_address).await {
-            Ok(c) => {
-                debug!(addr = %socket_address, "Connected");
-                c
-            }
-            Err(e) => {
-                debug!(addr = %socket_address, err = %e, "Failed to connect");
-                return Err(TransportError::from(e).into());
-            }
-        };
+        peers: &[SocketAddr],
+    ) -> Result<(SocketAddr, (OwnedReadHalf, OwnedWriteHalf))> {
+        let (socket_address, connection) = connect_to_any(peers).await?;
+        Ok((socket_address, Self::split(connection)))
+    }
+
+    /// Connect to a peer through an HTTP(S) forward proxy
+    pub(crate) async fn connect_through_proxy(
+        proxy: &HttpProxy,
+        peer: &str,
+    ) -> Result<(SocketAddr, (OwnedReadHalf, OwnedWriteHalf))> {
+        let connection = proxy.connect(peer).await?;
+        let socket_address = connection.peer_addr().map_err(TransportError::from)?;
+        Ok((socket_address, Self::split(connection)))
+    }
 
+    fn split(connection: TcpStream) -> (OwnedReadHalf, OwnedWriteHalf) {
         let mut keepalive = TcpKeepalive::new()
             .with_time(Duration::from_secs(300))
             .with_interval(Duration::from_secs(75));
@@ -147,7 +152,7 @@ impl TcpSendWorker {
         let socket = SockRef::from(&connection);
         socket.set_tcp_keepalive(&keepalive).unwrap();
 
-        Ok(connection.into_split())
+        connection.into_split()
     }
 }
 
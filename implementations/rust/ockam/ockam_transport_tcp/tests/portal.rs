@@ -1,13 +1,17 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 
 use ockam_core::compat::rand::random;
-use ockam_core::{route, Result};
+use ockam_core::compat::sync::Arc;
+use ockam_core::{async_trait, route, Result};
 use ockam_node::Context;
 use ockam_transport_tcp::{
-    TcpConnectionOptions, TcpInletOptions, TcpListenerOptions, TcpOutletOptions, TcpTransport,
+    HostLookup, OutletResolver, PortalDirection, PortalInterceptor, PortalInterceptorFactory,
+    PortalSessionResumption, ResolvedHost, TcpConnectionOptions, TcpInletOptions,
+    TcpListenerOptions, TcpOutletOptions, TcpTransport, DEFAULT_SESSION_BUFFER_SIZE,
 };
 
 const LENGTH: usize = 32;
@@ -118,6 +122,121 @@ async fn portal__reverse_flow__should_succeed(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+/// Uppercase the data sent to the TCP peer
+#[derive(Debug)]
+struct Uppercase;
+
+#[async_trait]
+impl PortalInterceptor for Uppercase {
+    async fn intercept(
+        &self,
+        _context: &mut Context,
+        direction: PortalDirection,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        match direction {
+            PortalDirection::ToPeer => Ok(chunk.to_ascii_uppercase()),
+            PortalDirection::FromPeer => Ok(chunk),
+        }
+    }
+}
+
+impl PortalInterceptorFactory for Uppercase {
+    fn create(&self) -> Arc<dyn PortalInterceptor> {
+        Arc::new(Uppercase)
+    }
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_interceptor__should_transform_data(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    tcp.create_tcp_outlet(
+        "outlet".into(),
+        listener.local_addr().unwrap(),
+        TcpOutletOptions::new().with_interceptor(Arc::new(Uppercase)),
+    )
+    .await?;
+    let (inlet_addr, _) = tcp
+        .create_inlet("127.0.0.1:0", route!["outlet"], TcpInletOptions::new())
+        .await?;
+
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut payload = [0u8; 5];
+        stream.read_exact(&mut payload).await.unwrap();
+        assert_eq!(&payload, b"HELLO");
+        stream.write_all(b"world").await.unwrap();
+    });
+
+    let mut stream = TcpStream::connect(inlet_addr).await.unwrap();
+    stream.write_all(b"hello").await.unwrap();
+    let mut payload = [0u8; 5];
+    stream.read_exact(&mut payload).await.unwrap();
+    // only the data sent to the TCP peer of the outlet is transformed
+    assert_eq!(&payload, b"world");
+
+    assert!(handle.await.is_ok());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__retarget_outlet__should_keep_established_connections(
+    ctx: &mut Context,
+) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+    let blue = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let green = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let blue_addr = blue.local_addr().unwrap();
+    let green_addr = green.local_addr().unwrap();
+    tcp.create_tcp_outlet("outlet".into(), blue_addr, TcpOutletOptions::new())
+        .await?;
+    let (inlet_addr, _) = tcp
+        .create_inlet("127.0.0.1:0", route!["outlet"], TcpInletOptions::new())
+        .await?;
+
+    let blue_handle = tokio::spawn(async move {
+        let (mut stream, _) = blue.accept().await.unwrap();
+        read_assert_binary(&mut stream, payload1).await;
+        read_assert_binary(&mut stream, payload1).await;
+    });
+    let green_handle = tokio::spawn(async move {
+        let (mut stream, _) = green.accept().await.unwrap();
+        read_assert_binary(&mut stream, payload2).await;
+    });
+
+    let mut blue_stream = TcpStream::connect(inlet_addr).await.unwrap();
+    write_binary(&mut blue_stream, payload1).await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+
+    assert_eq!(tcp.retarget_outlet("outlet", green_addr)?, blue_addr);
+    assert!(tcp.retarget_outlet("unknown", green_addr).is_err());
+
+    // new connections go to the new peer, the established one is kept
+    let mut green_stream = TcpStream::connect(inlet_addr).await.unwrap();
+    write_binary(&mut green_stream, payload2).await;
+    write_binary(&mut blue_stream, payload1).await;
+
+    assert!(blue_handle.await.is_ok());
+    assert!(green_handle.await.is_ok());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
 #[allow(non_snake_case)]
 #[ockam_macros::test(timeout = 15000)]
 async fn portal__tcp_connection__should_succeed(ctx: &mut Context) -> Result<()> {
@@ -238,3 +357,188 @@ async fn portal__tcp_connection_with_invalid_message_flow__should_not_succeed(
 
     Ok(())
 }
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 15000)]
+async fn portal__resume_session__should_keep_the_connection(ctx: &mut Context) -> Result<()> {
+    let payload1 = generate_binary();
+    let payload2 = generate_binary();
+    let payload3 = generate_binary();
+
+    let options = TcpListenerOptions::new();
+    let outlet_flow_control_id = options.spawner_flow_control_id();
+
+    let tcp = TcpTransport::create(ctx).await?;
+    let tcp_listener = tcp.listen("127.0.0.1:0", options).await?;
+    let tcp_connection = tcp
+        .connect(
+            tcp_listener.socket_address().to_string(),
+            TcpConnectionOptions::new(),
+        )
+        .await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bind_address = listener.local_addr().unwrap().to_string();
+    tcp.create_outlet(
+        "outlet",
+        bind_address.clone(),
+        TcpOutletOptions::new()
+            .as_consumer(&outlet_flow_control_id)
+            .with_session_resumption(DEFAULT_SESSION_BUFFER_SIZE),
+    )
+    .await?;
+
+    let session_resumption = PortalSessionResumption::new(route![tcp_connection.clone(), "outlet"]);
+    let (inlet_socket_addr, _) = tcp
+        .create_inlet(
+            "127.0.0.1:0",
+            route![],
+            TcpInletOptions::new().with_session_resumption(session_resumption.clone()),
+        )
+        .await?;
+
+    let handle = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        read_assert_binary(&mut stream, payload1).await;
+        read_assert_binary(&mut stream, payload2).await;
+        write_binary(&mut stream, payload3).await;
+    });
+
+    let mut stream = TcpStream::connect(inlet_socket_addr).await.unwrap();
+    write_binary(&mut stream, payload1).await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+
+    // the route to the outlet is down while some data is sent
+    tcp.disconnect(tcp_connection.sender_address().clone())
+        .await?;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    write_binary(&mut stream, payload2).await;
+    tokio::time::sleep(Duration::from_millis(250)).await;
+
+    // the session is resumed over a new route
+    let tcp_connection = tcp
+        .connect(
+            tcp_listener.socket_address().to_string(),
+            TcpConnectionOptions::new(),
+        )
+        .await?;
+    session_resumption.set_route(route![tcp_connection, "outlet"]);
+
+    read_assert_binary(&mut stream, payload3).await;
+
+    let res = handle.await;
+    assert!(res.is_ok());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__port_range__should_bind_in_range(ctx: &mut Context) -> Result<()> {
+    let tcp = TcpTransport::create(ctx).await?;
+    let port_range = 41000..=41009;
+
+    // a free port of the range is chosen
+    let (first, _) = tcp
+        .create_inlet(
+            "127.0.0.1:0",
+            route!["outlet"],
+            TcpInletOptions::new().with_port_range(port_range.clone()),
+        )
+        .await?;
+    assert!(port_range.contains(&first.port()));
+
+    let (second, _) = tcp
+        .create_inlet(
+            "127.0.0.1:0",
+            route!["outlet"],
+            TcpInletOptions::new().with_port_range(port_range.clone()),
+        )
+        .await?;
+    assert!(port_range.contains(&second.port()));
+    assert_ne!(first.port(), second.port());
+
+    // a port outside of the range is refused
+    let res = tcp
+        .create_inlet(
+            "127.0.0.1:42000",
+            route!["outlet"],
+            TcpInletOptions::new().with_port_range(port_range),
+        )
+        .await;
+    assert!(res.is_err());
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
+
+/// Resolve any host to the loopback address, counting the lookups
+#[derive(Debug, Default)]
+struct LoopbackLookup {
+    lookups: AtomicUsize,
+}
+
+#[async_trait]
+impl HostLookup for LoopbackLookup {
+    async fn lookup(&self, _host: &str) -> Result<ResolvedHost> {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        Ok(ResolvedHost {
+            addresses: vec!["127.0.0.1".parse().unwrap()],
+            ttl: Some(Duration::from_secs(60)),
+        })
+    }
+}
+
+#[allow(non_snake_case)]
+#[ockam_macros::test(timeout = 5000)]
+async fn portal__outlet_resolver__should_cache_the_target_addresses(
+    ctx: &mut Context,
+) -> Result<()> {
+    let payload = generate_binary();
+
+    let tcp = TcpTransport::create(ctx).await?;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let lookup = Arc::new(LoopbackLookup::default());
+    let resolver = OutletResolver::new().with_lookup(lookup.clone());
+    tcp.create_outlet(
+        "outlet",
+        format!("db.example.com:{port}"),
+        TcpOutletOptions::new().with_resolver(resolver),
+    )
+    .await?;
+    let (inlet_addr, _) = tcp
+        .create_inlet("127.0.0.1:0", route!["outlet"], TcpInletOptions::new())
+        .await?;
+
+    let handle = tokio::spawn(async move {
+        for _ in 0..2 {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_assert_binary(&mut stream, payload).await;
+        }
+    });
+
+    for _ in 0..2 {
+        let mut stream = TcpStream::connect(inlet_addr).await.unwrap();
+        write_binary(&mut stream, payload).await;
+    }
+    assert!(handle.await.is_ok());
+
+    // the target was only resolved when the outlet was created
+    assert_eq!(lookup.lookups.load(Ordering::Relaxed), 1);
+
+    if let Err(e) = ctx.stop().await {
+        println!("Unclean stop: {}", e)
+    }
+
+    Ok(())
+}
@@ -8,11 +8,11 @@ use ockam_core::{
 };
 use ockam_node::{Context, WorkerBuilder};
 use ockam_transport_core::TransportError;
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::sync::Arc;
 use tokio::net::UdpSocket;
 use tokio_util::udp::UdpFramed;
-use tracing::{debug, error, trace};
+use tracing::{debug, trace};
 
 /// The router for the UDP transport
 ///
@@ -31,7 +31,9 @@ use tracing::{debug, error, trace};
 /// sender. 'server' messages bypass the router as listeners inject the
 /// sender's address into the return route of received messages.
 ///
-/// This transport only supports IPv4.
+/// The 'client' socket is bound to the unspecified IPv6 address when IPv6 is available,
+/// in order to send messages to both IPv4 and IPv6 peers, and to the unspecified IPv4
+/// address otherwise.
 pub(crate) struct UdpRouter {
     ctx: Context,
     main_addr: Address,
@@ -59,11 +61,22 @@ impl UdpRouter {
         let handle = UdpRouterHandle::try_new(&child_ctx, &api_addr).await?;
 
         // Create sender, listener pair for 'client' messages
-        let client_sender = Self::create_sender_listener(
+        let client_sender = match Self::create_sender_listener(
             &child_ctx,
-            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0),
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 0),
         )
-        .await?;
+        .await
+        {
+            Ok(client_sender) => client_sender,
+            Err(err) => {
+                debug!(%err, "IPv6 is not available, falling back to IPv4");
+                Self::create_sender_listener(
+                    &child_ctx,
+                    SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0),
+                )
+                .await?
+            }
+        };
 
         let router = Self {
             ctx: child_ctx,
@@ -105,16 +118,13 @@ impl UdpRouter {
     ///
     /// Returns the address of the created sender.
     async fn create_sender_listener(ctx: &Context, local_addr: SocketAddr) -> Result<Address> {
-        // This transport only supports IPv4
-        if !local_addr.is_ipv4() {
-            error!(local_addr = %local_addr, "This transport only supprts IPv4");
-            return Err(TransportError::InvalidAddress.into());
-        }
-
         // Bind new socket
         let socket = UdpSocket::bind(local_addr)
             .await
             .map_err(|_| TransportError::InvalidAddress)?;
+        let local_addr = socket
+            .local_addr()
+            .map_err(|_| TransportError::InvalidAddress)?;
 
         // Split socket into sink and stream
         let (sink, stream) = UdpFramed::new(socket, TransportMessageCodec).split();
@@ -123,7 +133,7 @@ impl UdpRouter {
 
         // Create sender
         let sender_addr = Address::random_tagged("UdpSendWorker");
-        let sender = UdpSendWorker::new(sink);
+        let sender = UdpSendWorker::new(sink, local_addr);
         // FIXME: @ac
         ctx.start_worker(sender_addr.clone(), sender).await?;
 
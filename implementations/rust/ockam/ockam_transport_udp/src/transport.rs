@@ -7,7 +7,7 @@ use ockam_transport_core::TransportError;
 ///
 /// A node will have, at most, one UDP transport running.
 ///
-/// This transport only supports IPv4.
+/// This transport supports IPv4 and IPv6 peers.
 pub struct UdpTransport {
     router_handle: UdpRouterHandle,
 }
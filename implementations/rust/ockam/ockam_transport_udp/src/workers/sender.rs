@@ -4,7 +4,7 @@ use futures_util::{stream::SplitSink, SinkExt};
 use ockam_core::{async_trait, Any, Result, Routed, TransportMessage, Worker};
 use ockam_node::Context;
 use ockam_transport_core::TransportError;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{SocketAddr, SocketAddrV6, ToSocketAddrs};
 use tokio_util::udp::UdpFramed;
 use tracing::{error, trace, warn};
 
@@ -15,14 +15,35 @@ use tracing::{error, trace, warn};
 pub(crate) struct UdpSendWorker {
     /// The read half of the udnerlying UDP socket.
     sink: SplitSink<UdpFramed<TransportMessageCodec>, (TransportMessage, SocketAddr)>,
+    /// Local address of the socket, its family determines the addresses which can be reached
+    local_addr: SocketAddr,
 }
 
 impl UdpSendWorker {
     /// Create a new `UdpSendWorker`
     pub(crate) fn new(
         sink: SplitSink<UdpFramed<TransportMessageCodec>, (TransportMessage, SocketAddr)>,
+        local_addr: SocketAddr,
     ) -> Self {
-        Self { sink }
+        Self { sink, local_addr }
+    }
+
+    /// Select the first peer address which can be reached from the local socket.
+    /// An IPv4 socket can only reach IPv4 addresses. An IPv6 socket reaches IPv4 addresses
+    /// via their IPv4-mapped IPv6 address
+    fn select_peer_addr(
+        &self,
+        mut peer_addrs: impl Iterator<Item = SocketAddr>,
+    ) -> Option<SocketAddr> {
+        if self.local_addr.is_ipv4() {
+            return peer_addrs.find(SocketAddr::is_ipv4);
+        }
+        peer_addrs.next().map(|addr| match addr {
+            SocketAddr::V4(v4) => {
+                SocketAddr::V6(SocketAddrV6::new(v4.ip().to_ipv6_mapped(), v4.port(), 0, 0))
+            }
+            v6 => v6,
+        })
     }
 }
 
@@ -42,7 +63,7 @@ impl Worker for UdpSendWorker {
 
         trace!("Sending message to {:?}", msg.onward_route);
 
-        // Resolve peer address to IPv4 SocketAddr(s).
+        // Resolve peer address to SocketAddr(s) reachable from the local socket.
         let peer_addr = msg.onward_route.step()?;
 
         if peer_addr.transport_type() != UDP {
@@ -55,13 +76,12 @@ impl Worker for UdpSendWorker {
         let peer_addrs = peer_addr
             .to_socket_addrs()
             .map_err(|_| TransportError::InvalidAddress)?;
-        let peer_addrs: Vec<_> = peer_addrs.filter(SocketAddr::is_ipv4).collect();
 
         // Try to send to first SocketAddr
-        let addr = match peer_addrs.first() {
-            Some(a) => *a,
+        let addr = match self.select_peer_addr(peer_addrs) {
+            Some(a) => a,
             None => {
-                warn!("No IPv4 address resolved for peer {:?}", peer_addr);
+                warn!(local_addr = %self.local_addr, "No reachable address resolved for peer {:?}", peer_addr);
                 return Err(TransportError::UnknownRoute.into());
             }
         };
@@ -192,6 +192,40 @@ async fn send_receive(ctx: &mut Context) -> Result<()> {
     Ok(())
 }
 
+/// The transport should send messages to IPv6 peers
+#[ockam_macros::test]
+async fn send_receive_ipv6(ctx: &mut Context) -> Result<()> {
+    // Find an available port
+    let bind_addr = {
+        let socket = tokio::net::UdpSocket::bind("[::1]:0").await.unwrap();
+        socket.local_addr().unwrap().to_string()
+    };
+    debug!("bind_addr = {:?}", bind_addr);
+
+    // Transport
+    let transport = UdpTransport::create(ctx).await?;
+
+    // Listener
+    ctx.start_worker("echoer", Echoer::new()).await?;
+    transport.listen(bind_addr.clone()).await?;
+
+    // Sender
+    let msg = String::from("Hola");
+    let r = route![(UDP, bind_addr), "echoer"];
+    let reply = ctx
+        .send_and_receive_extended::<String>(
+            r,
+            msg.clone(),
+            MessageSendReceiveOptions::new().with_timeout(TIMEOUT),
+        )
+        .await?
+        .body();
+    assert_eq!(reply, msg, "Should receive the same message");
+
+    ctx.stop().await?;
+    Ok(())
+}
+
 pub struct Echoer {
     prev_src_addr: Option<String>,
 }
@@ -33,6 +33,14 @@ pub enum VaultError {
     InvalidSha256Len,
     /// Invalid Signature Size
     InvalidSignatureSize,
+    /// Invalid private key format
+    InvalidPrivateKeyFormat,
+    /// ChaCha20-Poly1305 encryption failed
+    AeadChaChaPolyEncrypt,
+    /// ChaCha20-Poly1305 decryption failed
+    AeadChaChaPolyDecrypt,
+    /// AEAD cipher is not supported by this vault
+    UnsupportedAeadCipher,
 }
 
 impl ockam_core::compat::error::Error for VaultError {}
@@ -50,10 +58,19 @@ impl core::fmt::Display for VaultError {
             Self::InvalidHkdfOutputType => write!(f, "invalid HKDF output type"),
             Self::AeadAesGcmEncrypt => write!(f, "aes encryption failed"),
             Self::AeadAesGcmDecrypt => write!(f, "aes decryption failed"),
+            Self::AeadChaChaPolyEncrypt => write!(f, "chacha20-poly1305 encryption failed"),
+            Self::AeadChaChaPolyDecrypt => write!(f, "chacha20-poly1305 decryption failed"),
+            Self::UnsupportedAeadCipher => write!(f, "aead cipher not supported by this vault"),
             Self::HkdfExpandError => write!(f, "hkdf key expansion failed"),
             Self::KeyNotFound => write!(f, "key not found"),
             Self::InvalidSha256Len => write!(f, "invalid sha256 len"),
             Self::InvalidSignatureSize => write!(f, "invalid signature len"),
+            Self::InvalidPrivateKeyFormat => {
+                write!(
+                    f,
+                    "the private key is not a valid Ed25519 or P-256 PEM/PKCS#8 key"
+                )
+            }
         }
     }
 }
@@ -65,6 +82,7 @@ impl From<VaultError> for Error {
         let kind = match err {
             InvalidPublicKey | InvalidKeyType | InvalidHkdfOutputType => Kind::Misuse,
             UnknownEcdhKeyType => Kind::NotFound,
+            UnsupportedAeadCipher => Kind::Unsupported,
             _ => Kind::Invalid,
         };
 
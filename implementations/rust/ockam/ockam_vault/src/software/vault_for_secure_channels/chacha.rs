@@ -0,0 +1,75 @@
+use crate::{AeadSecret, VaultError, AES_NONCE_LENGTH};
+
+use ockam_core::compat::vec::Vec;
+use ockam_core::Result;
+
+use chacha20poly1305::aead::{Aead, NewAead, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key};
+
+/// ChaCha20-Poly1305 uses the same nonce length as AES-GCM
+const CHACHA_NONCE_LENGTH: usize = AES_NONCE_LENGTH;
+
+/// ChaCha20-Poly1305 encrypting / decrypting algorithm
+pub struct ChaChaGen(ChaCha20Poly1305);
+
+impl ChaChaGen {
+    pub fn encrypt_message(&self, msg: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != CHACHA_NONCE_LENGTH {
+            return Err(VaultError::AeadChaChaPolyEncrypt.into());
+        }
+
+        self.0
+            .encrypt(nonce.into(), Payload { aad, msg })
+            .map_err(|_| VaultError::AeadChaChaPolyEncrypt.into())
+    }
+
+    pub fn decrypt_message(&self, msg: &[u8], nonce: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if nonce.len() != CHACHA_NONCE_LENGTH {
+            return Err(VaultError::AeadChaChaPolyDecrypt.into());
+        }
+
+        self.0
+            .decrypt(nonce.into(), Payload { aad, msg })
+            .map_err(|_| VaultError::AeadChaChaPolyDecrypt.into())
+    }
+}
+
+/// Make a ChaCha20-Poly1305 encrypting / decrypting algorithm from a 256 bits secret
+pub(super) fn make_chacha(secret: &AeadSecret) -> ChaChaGen {
+    ChaChaGen(ChaCha20Poly1305::new(Key::from_slice(&secret.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AeadCipher, SoftwareVaultForSecureChannels, VaultForSecureChannels};
+    use ockam_core::Result;
+
+    #[tokio::test]
+    async fn test_aead_key_is_bound_to_its_cipher() -> Result<()> {
+        let vault = SoftwareVaultForSecureChannels::create();
+        let nonce = [0u8; 12];
+
+        let chacha = vault.import_secret_buffer(vec![1u8; 32]).await?;
+        let chacha = vault
+            .convert_secret_buffer_to_aead_key_for_cipher(chacha, AeadCipher::ChaCha20Poly1305)
+            .await?;
+        let aes = vault.import_secret_buffer(vec![1u8; 32]).await?;
+        let aes = vault.convert_secret_buffer_to_aead_key(aes).await?;
+
+        let cipher_text = vault
+            .aead_encrypt(&chacha, b"hello", &nonce, b"aad")
+            .await?;
+        let plain_text = vault
+            .aead_decrypt(&chacha, &cipher_text, &nonce, b"aad")
+            .await?;
+        assert_eq!(plain_text, b"hello");
+
+        // The same secret used with AES-GCM can't decrypt the ChaCha20-Poly1305 cipher text
+        assert!(vault
+            .aead_decrypt(&aes, &cipher_text, &nonce, b"aad")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -4,6 +4,11 @@
     not(feature = "disable_default_noise_protocol")
 ))]
 pub(crate) mod aes;
+#[cfg(any(
+    feature = "OCKAM_XX_25519_AES256_GCM_SHA256",
+    not(feature = "disable_default_noise_protocol")
+))]
+pub(crate) mod chacha;
 
 mod types;
 #[allow(clippy::module_inception)]
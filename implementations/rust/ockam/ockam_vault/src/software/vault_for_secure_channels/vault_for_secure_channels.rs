@@ -1,16 +1,18 @@
 use super::aes::make_aes;
+use super::chacha::make_chacha;
 
 use crate::{
-    AeadSecret, AeadSecretKeyHandle, BufferSecret, HKDFNumberOfOutputs, HandleToSecret, HashOutput,
-    HkdfOutput, SecretBufferHandle, SoftwareVaultForVerifyingSignatures, VaultError,
+    AeadCipher, AeadSecret, AeadSecretKeyHandle, BufferSecret, HKDFNumberOfOutputs, HandleToSecret,
+    HashOutput, HkdfOutput, SecretBufferHandle, SoftwareVaultForVerifyingSignatures, VaultError,
     VaultForSecureChannels, X25519PublicKey, X25519SecretKey, X25519SecretKeyHandle,
     AEAD_SECRET_LENGTH,
 };
 
 use ockam_core::compat::collections::BTreeMap;
-use ockam_core::compat::rand::{thread_rng, RngCore};
+use ockam_core::compat::rand::RngCore;
 use ockam_core::compat::sync::{Arc, RwLock};
 use ockam_core::compat::vec::{vec, Vec};
+use ockam_core::entropy;
 use ockam_core::{async_trait, compat::boxed::Box, Result};
 use ockam_node::{InMemoryKeyValueStorage, KeyValueStorage};
 
@@ -20,7 +22,7 @@ use sha2::{Digest, Sha256};
 /// [`SecureChannelVault`] implementation using software
 pub struct SoftwareVaultForSecureChannels {
     ephemeral_buffer_secrets: Arc<RwLock<BTreeMap<SecretBufferHandle, BufferSecret>>>,
-    ephemeral_aead_secrets: Arc<RwLock<BTreeMap<AeadSecretKeyHandle, AeadSecret>>>,
+    ephemeral_aead_secrets: Arc<RwLock<BTreeMap<AeadSecretKeyHandle, (AeadCipher, AeadSecret)>>>,
     ephemeral_x25519_secrets: Arc<RwLock<BTreeMap<X25519SecretKeyHandle, X25519SecretKey>>>,
     // Use String as a key for backwards compatibility
     static_x25519_secrets: Arc<dyn KeyValueStorage<KeyId, StoredSecret>>,
@@ -128,7 +130,7 @@ impl SoftwareVaultForSecureChannels {
         // that every time we import the same secret - it gets different Handle value.
         // However, if we decide to have persistent Buffer or Aes secrets, that should be
         // changed (probably to hash value of the secret)
-        let mut rng = thread_rng();
+        let mut rng = entropy::rng();
         let mut rand = vec![0u8; 8];
         rng.fill_bytes(&mut rand);
         HandleToSecret::new(rand)
@@ -156,7 +158,7 @@ impl SoftwareVaultForSecureChannels {
 
     fn generate_x25519_secret() -> X25519SecretKey {
         // Just random 32 bytes
-        let secret = x25519_dalek::StaticSecret::random_from_rng(thread_rng());
+        let secret = x25519_dalek::StaticSecret::random_from_rng(entropy::rng());
         X25519SecretKey::new(secret.to_bytes())
     }
 
@@ -194,7 +196,10 @@ impl SoftwareVaultForSecureChannels {
         }
     }
 
-    async fn get_aead_secret(&self, handle: &AeadSecretKeyHandle) -> Result<AeadSecret> {
+    async fn get_aead_secret(
+        &self,
+        handle: &AeadSecretKeyHandle,
+    ) -> Result<(AeadCipher, AeadSecret)> {
         match self.ephemeral_aead_secrets.read().unwrap().get(handle) {
             Some(secret) => Ok(secret.clone()),
             None => Err(VaultError::KeyNotFound.into()),
@@ -277,9 +282,14 @@ impl VaultForSecureChannels for SoftwareVaultForSecureChannels {
         nonce: &[u8],
         aad: &[u8],
     ) -> Result<Vec<u8>> {
-        let secret = self.get_aead_secret(secret_key_handle).await?;
-        let aes = make_aes(&secret);
-        aes.encrypt_message(plain_text, nonce, aad)
+        match self.get_aead_secret(secret_key_handle).await? {
+            (AeadCipher::Aes256Gcm, secret) => {
+                make_aes(&secret).encrypt_message(plain_text, nonce, aad)
+            }
+            (AeadCipher::ChaCha20Poly1305, secret) => {
+                make_chacha(&secret).encrypt_message(plain_text, nonce, aad)
+            }
+        }
     }
 
     async fn aead_decrypt(
@@ -289,9 +299,14 @@ impl VaultForSecureChannels for SoftwareVaultForSecureChannels {
         nonce: &[u8],
         aad: &[u8],
     ) -> Result<Vec<u8>> {
-        let secret = self.get_aead_secret(secret_key_handle).await?;
-        let aes = make_aes(&secret);
-        aes.decrypt_message(cipher_text, nonce, aad)
+        match self.get_aead_secret(secret_key_handle).await? {
+            (AeadCipher::Aes256Gcm, secret) => {
+                make_aes(&secret).decrypt_message(cipher_text, nonce, aad)
+            }
+            (AeadCipher::ChaCha20Poly1305, secret) => {
+                make_chacha(&secret).decrypt_message(cipher_text, nonce, aad)
+            }
+        }
     }
 
     async fn generate_static_x25519_secret_key(&self) -> Result<X25519SecretKeyHandle> {
@@ -361,6 +376,18 @@ impl VaultForSecureChannels for SoftwareVaultForSecureChannels {
     async fn convert_secret_buffer_to_aead_key(
         &self,
         secret_buffer_handle: SecretBufferHandle,
+    ) -> Result<AeadSecretKeyHandle> {
+        self.convert_secret_buffer_to_aead_key_for_cipher(
+            secret_buffer_handle,
+            AeadCipher::Aes256Gcm,
+        )
+        .await
+    }
+
+    async fn convert_secret_buffer_to_aead_key_for_cipher(
+        &self,
+        secret_buffer_handle: SecretBufferHandle,
+        cipher: AeadCipher,
     ) -> Result<AeadSecretKeyHandle> {
         let buffer = match self
             .ephemeral_buffer_secrets
@@ -386,7 +413,7 @@ impl VaultForSecureChannels for SoftwareVaultForSecureChannels {
         self.ephemeral_aead_secrets
             .write()
             .unwrap()
-            .insert(handle.clone(), secret);
+            .insert(handle.clone(), (cipher, secret));
 
         Ok(handle)
     }
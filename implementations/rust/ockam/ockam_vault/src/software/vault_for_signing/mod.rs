@@ -1,3 +1,4 @@
+mod pkcs8;
 mod types;
 #[allow(clippy::module_inception)]
 mod vault_for_signing;
@@ -9,8 +9,8 @@ use crate::{
     EDDSA_CURVE25519_SECRET_KEY_LENGTH,
 };
 
-use ockam_core::compat::rand::thread_rng;
 use ockam_core::compat::sync::Arc;
+use ockam_core::entropy;
 use ockam_core::errcode::{Kind, Origin};
 use ockam_core::{async_trait, compat::boxed::Box, Error, Result};
 use ockam_node::{InMemoryKeyValueStorage, KeyValueStorage};
@@ -50,6 +50,11 @@ impl SoftwareVaultForSigning {
         Ok(handle)
     }
 
+    /// Export a stored secret key, for example to convert it to PKCS#8
+    pub async fn export_key(&self, handle: &SigningSecretKeyHandle) -> Result<SigningSecret> {
+        self.get_stored_secret(handle).await
+    }
+
     /// Return the total number of keys
     pub async fn number_of_keys(&self) -> Result<usize> {
         Ok(self.secrets.keys().await?.len())
@@ -97,7 +102,7 @@ impl VaultForSigning for SoftwareVaultForSigning {
         let key = match signing_key_type {
             SigningKeyType::EdDSACurve25519 => {
                 // Just random 32 bytes
-                let signing_key = ed25519_dalek::SigningKey::generate(&mut thread_rng());
+                let signing_key = ed25519_dalek::SigningKey::generate(&mut entropy::rng());
                 let signing_key = signing_key.to_bytes();
                 let signing_key = EdDSACurve25519SecretKey::new(signing_key);
 
@@ -105,7 +110,7 @@ impl VaultForSigning for SoftwareVaultForSigning {
             }
             SigningKeyType::ECDSASHA256CurveP256 => {
                 // Somewhat special random 32 bytes
-                let signing_key = p256::ecdsa::SigningKey::random(&mut thread_rng());
+                let signing_key = p256::ecdsa::SigningKey::random(&mut entropy::rng());
                 let signing_key = signing_key.to_bytes();
                 let signing_key = ECDSASHA256CurveP256SecretKey::new(signing_key.into());
 
@@ -1,8 +1,9 @@
 use crate::{
-    AeadSecretKeyHandle, HashOutput, HkdfOutput, SecretBufferHandle, X25519PublicKey,
+    AeadCipher, AeadSecretKeyHandle, HashOutput, HkdfOutput, SecretBufferHandle, X25519PublicKey,
     X25519SecretKeyHandle,
 };
 
+use crate::VaultError;
 use ockam_core::compat::vec::Vec;
 use ockam_core::{async_trait, compat::boxed::Box, Result};
 
@@ -100,6 +101,25 @@ pub trait VaultForSecureChannels: Send + Sync + 'static {
         secret_buffer_handle: SecretBufferHandle,
     ) -> Result<AeadSecretKeyHandle>;
 
+    /// Convert a Secret Buffer to a Key for the given AEAD cipher.
+    /// [`VaultForSecureChannels::aead_encrypt`] and [`VaultForSecureChannels::aead_decrypt`]
+    /// then use that cipher with the returned Key.
+    ///
+    /// Vaults which only support AES-GCM can rely on this default implementation.
+    async fn convert_secret_buffer_to_aead_key_for_cipher(
+        &self,
+        secret_buffer_handle: SecretBufferHandle,
+        cipher: AeadCipher,
+    ) -> Result<AeadSecretKeyHandle> {
+        match cipher {
+            AeadCipher::Aes256Gcm => {
+                self.convert_secret_buffer_to_aead_key(secret_buffer_handle)
+                    .await
+            }
+            AeadCipher::ChaCha20Poly1305 => Err(VaultError::UnsupportedAeadCipher.into()),
+        }
+    }
+
     /// Delete AEAD Key.
     async fn delete_aead_secret_key(&self, secret_key_handle: AeadSecretKeyHandle) -> Result<bool>;
 }
@@ -0,0 +1,30 @@
+/// AEAD ciphers which can be used to encrypt the messages of a secure channel.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Default)]
+pub enum AeadCipher {
+    /// AES-256 in Galois/Counter Mode, fast on CPUs with AES instructions.
+    #[default]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305, fast on CPUs without AES instructions.
+    ChaCha20Poly1305,
+}
+
+/// Return true if the CPU running this code has AES instructions.
+/// In that case AES-GCM is usually faster than ChaCha20-Poly1305.
+#[cfg(all(feature = "std", target_arch = "x86_64"))]
+pub fn has_aes_instructions() -> bool {
+    std::is_x86_feature_detected!("aes")
+}
+
+/// Return true if the CPU running this code has AES instructions.
+/// In that case AES-GCM is usually faster than ChaCha20-Poly1305.
+#[cfg(all(feature = "std", target_arch = "aarch64"))]
+pub fn has_aes_instructions() -> bool {
+    std::arch::is_aarch64_feature_detected!("aes")
+}
+
+/// Return true if the CPU running this code has AES instructions.
+/// In that case AES-GCM is usually faster than ChaCha20-Poly1305.
+#[cfg(not(all(feature = "std", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+pub fn has_aes_instructions() -> bool {
+    false
+}
@@ -1,8 +1,10 @@
+mod ciphers;
 mod hashes;
 mod public_keys;
 mod secrets;
 mod signatures;
 
+pub use ciphers::*;
 pub use hashes::*;
 pub use public_keys::*;
 pub use secrets::*;
@@ -0,0 +1,267 @@
+use crate::error::Error;
+use crate::key_store::{
+    public_key_from_der, signature_from_der, KeyOptions, PlatformKeyStore, UserPresence,
+};
+use jni::objects::{JByteArray, JObject, JValue};
+use jni::{JNIEnv, JavaVM};
+use ockam_core::Result;
+use ockam_vault::{Signature, VerifyingPublicKey};
+use tracing::debug;
+
+const ANDROID_KEY_STORE: &str = "AndroidKeyStore";
+const SPEC_BUILDER: &str = "android/security/keystore/KeyGenParameterSpec$Builder";
+const SPEC_BUILDER_RETURN: &str = "Landroid/security/keystore/KeyGenParameterSpec$Builder;";
+
+/// KeyProperties.PURPOSE_SIGN
+const PURPOSE_SIGN: i32 = 4;
+/// KeyProperties.AUTH_DEVICE_CREDENTIAL
+const AUTH_DEVICE_CREDENTIAL: i32 = 1;
+/// KeyProperties.AUTH_BIOMETRIC_STRONG
+const AUTH_BIOMETRIC_STRONG: i32 = 2;
+
+/// Keys of the Android Keystore, backed by the TEE or the StrongBox of the device.
+///
+/// The Java VM of the application is obtained from the `ndk-context` crate
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AndroidKeyStore;
+
+impl AndroidKeyStore {
+    /// Execute some JNI calls on the current thread, attached to the Java VM
+    fn with_env<R>(f: impl FnOnce(&mut JNIEnv) -> jni::errors::Result<R>) -> Result<R> {
+        let vm = java_vm()?;
+        let mut env = vm
+            .attach_current_thread()
+            .map_err(|e| Error::KeyStore(e.to_string()))?;
+        match f(&mut env) {
+            Ok(r) => Ok(r),
+            Err(jni::errors::Error::JavaException) => {
+                let message = Self::take_exception(&mut env);
+                if message.contains("UserNotAuthenticatedException") {
+                    Err(Error::UserNotAuthenticated(message).into())
+                } else {
+                    Err(Error::KeyStore(message).into())
+                }
+            }
+            Err(e) => Err(Error::KeyStore(e.to_string()).into()),
+        }
+    }
+
+    /// Clear the pending Java exception and return its description
+    fn take_exception(env: &mut JNIEnv) -> String {
+        let exception = match env.exception_occurred() {
+            Ok(exception) => exception,
+            Err(e) => return e.to_string(),
+        };
+        let _ = env.exception_clear();
+        env.call_method(&exception, "toString", "()Ljava/lang/String;", &[])
+            .and_then(|s| s.l())
+            .and_then(|s| env.get_string(&s.into()).map(String::from))
+            .unwrap_or_else(|_| "unknown Java exception".to_string())
+    }
+
+    fn load_key_store<'a>(env: &mut JNIEnv<'a>) -> jni::errors::Result<JObject<'a>> {
+        let provider = env.new_string(ANDROID_KEY_STORE)?;
+        let key_store = env
+            .call_static_method(
+                "java/security/KeyStore",
+                "getInstance",
+                "(Ljava/lang/String;)Ljava/security/KeyStore;",
+                &[JValue::Object(&provider)],
+            )?
+            .l()?;
+        env.call_method(
+            &key_store,
+            "load",
+            "(Ljava/security/KeyStore$LoadStoreParameter;)V",
+            &[JValue::Object(&JObject::null())],
+        )?;
+        Ok(key_store)
+    }
+
+    fn key_spec<'a>(
+        env: &mut JNIEnv<'a>,
+        label: &str,
+        options: &KeyOptions,
+    ) -> jni::errors::Result<JObject<'a>> {
+        let alias = env.new_string(label)?;
+        let builder = env.new_object(
+            SPEC_BUILDER,
+            "(Ljava/lang/String;I)V",
+            &[JValue::Object(&alias), JValue::Int(PURPOSE_SIGN)],
+        )?;
+
+        let curve = env.new_string("secp256r1")?;
+        let curve = env.new_object(
+            "java/security/spec/ECGenParameterSpec",
+            "(Ljava/lang/String;)V",
+            &[JValue::Object(&curve)],
+        )?;
+        env.call_method(
+            &builder,
+            "setAlgorithmParameterSpec",
+            format!("(Ljava/security/spec/AlgorithmParameterSpec;){SPEC_BUILDER_RETURN}"),
+            &[JValue::Object(&curve)],
+        )?;
+
+        let sha256 = env.new_string("SHA-256")?;
+        let digests = env.new_object_array(1, "java/lang/String", &sha256)?;
+        env.call_method(
+            &builder,
+            "setDigests",
+            format!("([Ljava/lang/String;){SPEC_BUILDER_RETURN}"),
+            &[JValue::Object(&digests)],
+        )?;
+
+        let authenticators = match options.user_presence() {
+            UserPresence::None => None,
+            UserPresence::Biometry => Some(AUTH_BIOMETRIC_STRONG),
+            UserPresence::BiometryOrPasscode => {
+                Some(AUTH_BIOMETRIC_STRONG | AUTH_DEVICE_CREDENTIAL)
+            }
+        };
+        if let Some(authenticators) = authenticators {
+            env.call_method(
+                &builder,
+                "setUserAuthenticationRequired",
+                format!("(Z){SPEC_BUILDER_RETURN}"),
+                &[JValue::Bool(1)],
+            )?;
+            let validity = options
+                .authentication_validity()
+                .as_secs()
+                .min(i32::MAX as u64);
+            env.call_method(
+                &builder,
+                "setUserAuthenticationParameters",
+                format!("(II){SPEC_BUILDER_RETURN}"),
+                &[JValue::Int(validity as i32), JValue::Int(authenticators)],
+            )?;
+        }
+
+        env.call_method(
+            &builder,
+            "build",
+            "()Landroid/security/keystore/KeyGenParameterSpec;",
+            &[],
+        )?
+        .l()
+    }
+}
+
+#[allow(unsafe_code)]
+fn java_vm() -> Result<JavaVM> {
+    let context = ndk_context::android_context();
+    // SAFETY: the pointer is set by the Android runtime when the application is started
+    unsafe { JavaVM::from_raw(context.vm().cast()) }
+        .map_err(|e| Error::KeyStore(e.to_string()).into())
+}
+
+impl PlatformKeyStore for AndroidKeyStore {
+    fn create_key(&self, label: &str, options: &KeyOptions) -> Result<VerifyingPublicKey> {
+        let public_key = Self::with_env(|env| {
+            let spec = Self::key_spec(env, label, options)?;
+            let algorithm = env.new_string("EC")?;
+            let provider = env.new_string(ANDROID_KEY_STORE)?;
+            let generator = env
+                .call_static_method(
+                    "java/security/KeyPairGenerator",
+                    "getInstance",
+                    "(Ljava/lang/String;Ljava/lang/String;)Ljava/security/KeyPairGenerator;",
+                    &[JValue::Object(&algorithm), JValue::Object(&provider)],
+                )?
+                .l()?;
+            env.call_method(
+                &generator,
+                "initialize",
+                "(Ljava/security/spec/AlgorithmParameterSpec;)V",
+                &[JValue::Object(&spec)],
+            )?;
+            let key_pair = env
+                .call_method(
+                    &generator,
+                    "generateKeyPair",
+                    "()Ljava/security/KeyPair;",
+                    &[],
+                )?
+                .l()?;
+            let public_key = env
+                .call_method(&key_pair, "getPublic", "()Ljava/security/PublicKey;", &[])?
+                .l()?;
+            let encoded = env
+                .call_method(&public_key, "getEncoded", "()[B", &[])?
+                .l()?;
+            env.convert_byte_array(JByteArray::from(encoded))
+        })?;
+        debug!(%label, "created an Android Keystore key");
+        public_key_from_der(&public_key)
+    }
+
+    fn sign(&self, label: &str, data: &[u8]) -> Result<Signature> {
+        let signature = Self::with_env(|env| {
+            let key_store = Self::load_key_store(env)?;
+            let alias = env.new_string(label)?;
+            let key = env
+                .call_method(
+                    &key_store,
+                    "getKey",
+                    "(Ljava/lang/String;[C)Ljava/security/Key;",
+                    &[JValue::Object(&alias), JValue::Object(&JObject::null())],
+                )?
+                .l()?;
+            if key.is_null() {
+                return Ok(None);
+            }
+            let algorithm = env.new_string("SHA256withECDSA")?;
+            let signer = env
+                .call_static_method(
+                    "java/security/Signature",
+                    "getInstance",
+                    "(Ljava/lang/String;)Ljava/security/Signature;",
+                    &[JValue::Object(&algorithm)],
+                )?
+                .l()?;
+            // fails with a UserNotAuthenticatedException if the user must authenticate first
+            env.call_method(
+                &signer,
+                "initSign",
+                "(Ljava/security/PrivateKey;)V",
+                &[JValue::Object(&key)],
+            )?;
+            let data = env.byte_array_from_slice(data)?;
+            env.call_method(&signer, "update", "([B)V", &[JValue::Object(&data)])?;
+            let signature = env.call_method(&signer, "sign", "()[B", &[])?.l()?;
+            env.convert_byte_array(JByteArray::from(signature))
+                .map(Some)
+        })?
+        .ok_or(Error::KeyNotFound)?;
+        signature_from_der(&signature)
+    }
+
+    fn delete_key(&self, label: &str) -> Result<bool> {
+        let deleted = Self::with_env(|env| {
+            let key_store = Self::load_key_store(env)?;
+            let alias = env.new_string(label)?;
+            let exists = env
+                .call_method(
+                    &key_store,
+                    "containsAlias",
+                    "(Ljava/lang/String;)Z",
+                    &[JValue::Object(&alias)],
+                )?
+                .z()?;
+            if exists {
+                env.call_method(
+                    &key_store,
+                    "deleteEntry",
+                    "(Ljava/lang/String;)V",
+                    &[JValue::Object(&alias)],
+                )?;
+            }
+            Ok(exists)
+        })?;
+        if deleted {
+            debug!(%label, "deleted an Android Keystore key");
+        }
+        Ok(deleted)
+    }
+}
@@ -0,0 +1,33 @@
+use ockam_core::errcode::{Kind, Origin};
+use thiserror::Error;
+
+#[allow(missing_docs)]
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("no key store is available on this platform")]
+    UnsupportedPlatform,
+    #[error("key store error: {0}")]
+    KeyStore(String),
+    #[error("the user did not authorize the use of the key: {0}")]
+    UserNotAuthenticated(String),
+    #[error("invalid public key")]
+    InvalidPublicKey,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("key was not found")]
+    KeyNotFound,
+    #[error("invalid handle")]
+    InvalidHandle,
+}
+
+impl From<Error> for ockam_core::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::UnsupportedPlatform => Kind::Unsupported,
+            Error::UserNotAuthenticated(_) => Kind::Cancelled,
+            Error::KeyNotFound => Kind::NotFound,
+            _ => Kind::Io,
+        };
+        ockam_core::Error::new(Origin::Vault, kind, e)
+    }
+}
@@ -0,0 +1,134 @@
+use crate::error::Error;
+use core::time::Duration;
+use ockam_core::Result;
+use ockam_vault::{
+    ECDSASHA256CurveP256PublicKey, ECDSASHA256CurveP256Signature, Signature, VerifyingPublicKey,
+};
+use p256::pkcs8::DecodePublicKey;
+use serde::{Deserialize, Serialize};
+
+/// Presence of the user required to sign with a key
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserPresence {
+    /// The key can be used as long as the device is unlocked
+    #[default]
+    None,
+    /// The user must authenticate with the biometry enrolled when the key was created
+    Biometry,
+    /// The user must authenticate with biometry or with the passcode of the device
+    BiometryOrPasscode,
+}
+
+/// Options of the keys created in a key store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyOptions {
+    user_presence: UserPresence,
+    authentication_validity: Duration,
+}
+
+impl Default for KeyOptions {
+    fn default() -> Self {
+        Self {
+            user_presence: UserPresence::None,
+            authentication_validity: Duration::from_secs(30),
+        }
+    }
+}
+
+impl KeyOptions {
+    /// Require the presence of the user to sign
+    pub fn with_user_presence(mut self, user_presence: UserPresence) -> Self {
+        self.user_presence = user_presence;
+        self
+    }
+
+    /// Time during which a key can be used after the user authenticated.
+    /// This is only used by the Android Keystore, the Secure Enclave asks for each signature
+    pub fn with_authentication_validity(mut self, validity: Duration) -> Self {
+        self.authentication_validity = validity;
+        self
+    }
+
+    /// Presence of the user required to sign
+    pub fn user_presence(&self) -> UserPresence {
+        self.user_presence
+    }
+
+    /// Time during which a key can be used after the user authenticated
+    pub fn authentication_validity(&self) -> Duration {
+        self.authentication_validity
+    }
+}
+
+/// Store of non-exportable ECDSA P-256 keys provided by the platform.
+///
+/// The operations can block, for example while the user is asked to authenticate,
+/// so they are executed on a blocking thread by the vault
+pub trait PlatformKeyStore: Send + Sync + 'static {
+    /// Create a key with a unique label and return its public key
+    fn create_key(&self, label: &str, options: &KeyOptions) -> Result<VerifyingPublicKey>;
+
+    /// Sign the SHA-256 digest of some data
+    fn sign(&self, label: &str, data: &[u8]) -> Result<Signature>;
+
+    /// Delete a key, return false if it doesn't exist
+    fn delete_key(&self, label: &str) -> Result<bool>;
+}
+
+/// Convert an uncompressed SEC1 (X9.63) public key
+pub fn public_key_from_sec1(bytes: &[u8]) -> Result<VerifyingPublicKey> {
+    let public_key = bytes.try_into().map_err(|_| Error::InvalidPublicKey)?;
+    Ok(VerifyingPublicKey::ECDSASHA256CurveP256(
+        ECDSASHA256CurveP256PublicKey(public_key),
+    ))
+}
+
+/// Convert a DER encoded SubjectPublicKeyInfo public key
+pub fn public_key_from_der(bytes: &[u8]) -> Result<VerifyingPublicKey> {
+    let public_key = p256::ecdsa::VerifyingKey::from_public_key_der(bytes)
+        .map_err(|_| Error::InvalidPublicKey)?;
+    public_key_from_sec1(&public_key.to_encoded_point(false).to_bytes())
+}
+
+/// Convert a DER encoded ECDSA signature
+pub fn signature_from_der(bytes: &[u8]) -> Result<Signature> {
+    let signature = p256::ecdsa::Signature::from_der(bytes).map_err(|_| Error::InvalidSignature)?;
+    let signature = signature
+        .to_vec()
+        .try_into()
+        .map_err(|_| Error::InvalidSignature)?;
+    Ok(Signature::ECDSASHA256CurveP256(
+        ECDSASHA256CurveP256Signature(signature),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+    use p256::pkcs8::EncodePublicKey;
+
+    #[test]
+    fn test_convert_platform_encodings() {
+        let secret = SigningKey::random(&mut rand::thread_rng());
+        let public = secret.verifying_key();
+
+        let der = public.to_public_key_der().unwrap();
+        let sec1 = public.to_encoded_point(false);
+        assert_eq!(
+            public_key_from_der(der.as_bytes()).unwrap(),
+            public_key_from_sec1(sec1.as_bytes()).unwrap()
+        );
+        assert!(public_key_from_sec1(&[4; 33]).is_err());
+
+        let signature: p256::ecdsa::Signature = secret.sign(b"data");
+        match signature_from_der(signature.to_der().as_bytes()).unwrap() {
+            Signature::ECDSASHA256CurveP256(s) => {
+                assert_eq!(s.0.to_vec(), signature.to_bytes().to_vec())
+            }
+            _ => panic!("unexpected signature type"),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Implementations of the ockam_vault::VaultForSigning trait keeping the keys
+//! in the Apple Secure Enclave or in the Android Keystore
+//!
+#![deny(unsafe_code)]
+#![warn(
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+mod error;
+mod key_store;
+mod platform_signing_vault;
+
+#[cfg(all(
+    feature = "secure-enclave",
+    any(target_os = "macos", target_os = "ios")
+))]
+mod secure_enclave;
+
+#[cfg(all(feature = "android-keystore", target_os = "android"))]
+mod android_keystore;
+
+pub use error::*;
+pub use key_store::*;
+pub use platform_signing_vault::*;
+
+#[cfg(all(
+    feature = "secure-enclave",
+    any(target_os = "macos", target_os = "ios")
+))]
+pub use secure_enclave::*;
+
+#[cfg(all(feature = "android-keystore", target_os = "android"))]
+pub use android_keystore::*;
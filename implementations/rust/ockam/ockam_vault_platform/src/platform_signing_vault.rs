@@ -0,0 +1,343 @@
+use crate::error::Error;
+use crate::key_store::{public_key_from_sec1, KeyOptions, PlatformKeyStore};
+use ockam_core::compat::rand::RngCore;
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::{async_trait, entropy, Result};
+use ockam_node::storage::{FileValueStorage, ValueStorage};
+use ockam_node::tokio::task;
+use ockam_vault::{
+    HandleToSecret, Signature, SigningKeyType, SigningSecretKeyHandle, VaultError, VaultForSigning,
+    VerifyingPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tracing::error;
+
+/// Prefix of the labels of the keys created by Ockam in a key store
+pub const KEY_LABEL_PREFIX: &str = "ockam-";
+
+struct PlatformKeyPair {
+    key: SigningSecretKeyHandle,
+    public_key: VerifyingPublicKey,
+}
+
+/// Key created in the key store, as stored in the keys file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct PlatformKeyEntry {
+    label: String,
+    /// Uncompressed SEC1 public key
+    #[serde(with = "hex")]
+    public_key: Vec<u8>,
+}
+
+impl PlatformKeyEntry {
+    fn key_pair(&self) -> Result<PlatformKeyPair> {
+        Ok(PlatformKeyPair {
+            key: handle(&self.label),
+            public_key: public_key_from_sec1(&self.public_key)?,
+        })
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PlatformKeys {
+    keys: Vec<PlatformKeyEntry>,
+}
+
+/// Security module implementation using the key store of the platform:
+/// the Apple Secure Enclave or the Android Keystore
+///
+/// The key stores can't list their keys efficiently, so the labels and public keys
+/// of the keys created by this vault are stored in a file
+pub struct PlatformSigningVault {
+    key_store: Arc<dyn PlatformKeyStore>,
+    options: KeyOptions,
+    storage: FileValueStorage<PlatformKeys>,
+    // Keys loaded from the keys file at the Vault initialization,
+    // and updated locally during add/delete operations
+    keys: Arc<RwLock<Vec<PlatformKeyPair>>>,
+}
+
+impl PlatformSigningVault {
+    /// Create a security module using the key store of the current platform
+    pub async fn create(options: KeyOptions, keys_path: &Path) -> Result<Self> {
+        Self::create_with_key_store(default_key_store()?, options, keys_path).await
+    }
+
+    /// Create a security module using a specific key store
+    pub async fn create_with_key_store(
+        key_store: Arc<dyn PlatformKeyStore>,
+        options: KeyOptions,
+        keys_path: &Path,
+    ) -> Result<Self> {
+        let storage = FileValueStorage::<PlatformKeys>::create(keys_path).await?;
+
+        let mut key_pairs = vec![];
+        for entry in storage.read_value(|keys| Ok(keys.keys)).await? {
+            match entry.key_pair() {
+                Ok(key_pair) => key_pairs.push(key_pair),
+                Err(err) => error!("Error reading the key {}: {err}", entry.label),
+            }
+        }
+
+        Ok(Self {
+            key_store,
+            options,
+            storage,
+            keys: Arc::new(RwLock::new(key_pairs)),
+        })
+    }
+
+    /// Return list of all keys
+    pub fn keys(&self) -> Vec<SigningSecretKeyHandle> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .map(|x| x.key.clone())
+            .collect()
+    }
+
+    /// Return number of keys
+    pub async fn number_of_keys(&self) -> Result<usize> {
+        Ok(self.keys.read().unwrap().len())
+    }
+
+    fn label(&self, key: &SigningSecretKeyHandle) -> Result<String> {
+        let label = match key {
+            SigningSecretKeyHandle::ECDSASHA256CurveP256(handle) => {
+                String::from_utf8(handle.value().clone()).map_err(|_| Error::InvalidHandle)?
+            }
+            SigningSecretKeyHandle::EdDSACurve25519(_) => return Err(Error::InvalidHandle.into()),
+        };
+        if self.keys.read().unwrap().iter().any(|x| &x.key == key) {
+            Ok(label)
+        } else {
+            Err(Error::KeyNotFound.into())
+        }
+    }
+}
+
+/// Return the key store of the platform the code is compiled for
+pub fn default_key_store() -> Result<Arc<dyn PlatformKeyStore>> {
+    #[cfg(all(
+        feature = "secure-enclave",
+        any(target_os = "macos", target_os = "ios")
+    ))]
+    return Ok(Arc::new(crate::SecureEnclaveKeyStore));
+
+    #[cfg(all(feature = "android-keystore", target_os = "android"))]
+    return Ok(Arc::new(crate::AndroidKeyStore));
+
+    #[allow(unreachable_code)]
+    Err(Error::UnsupportedPlatform.into())
+}
+
+fn handle(label: &str) -> SigningSecretKeyHandle {
+    SigningSecretKeyHandle::ECDSASHA256CurveP256(HandleToSecret::new(label.as_bytes().to_vec()))
+}
+
+fn new_label() -> String {
+    let mut bytes = [0u8; 16];
+    entropy::rng().fill_bytes(&mut bytes);
+    format!("{KEY_LABEL_PREFIX}{}", hex::encode(bytes))
+}
+
+/// Run a key store operation, which can block, on a blocking thread
+async fn blocking<R: Send + 'static>(f: impl FnOnce() -> Result<R> + Send + 'static) -> Result<R> {
+    task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::KeyStore(e.to_string()))?
+}
+
+#[async_trait]
+impl VaultForSigning for PlatformSigningVault {
+    async fn sign(
+        &self,
+        signing_secret_key_handle: &SigningSecretKeyHandle,
+        data: &[u8],
+    ) -> Result<Signature> {
+        let label = self.label(signing_secret_key_handle)?;
+        let key_store = self.key_store.clone();
+        let data = data.to_vec();
+        blocking(move || key_store.sign(&label, &data)).await
+    }
+
+    async fn generate_signing_secret_key(
+        &self,
+        signing_key_type: SigningKeyType,
+    ) -> Result<SigningSecretKeyHandle> {
+        if signing_key_type != SigningKeyType::ECDSASHA256CurveP256 {
+            return Err(VaultError::InvalidKeyType.into());
+        }
+
+        let label = new_label();
+        let key_store = self.key_store.clone();
+        let options = self.options.clone();
+        let public_key = {
+            let label = label.clone();
+            blocking(move || key_store.create_key(&label, &options)).await?
+        };
+        let entry = PlatformKeyEntry {
+            label: label.clone(),
+            public_key: match &public_key {
+                VerifyingPublicKey::ECDSASHA256CurveP256(k) => k.0.to_vec(),
+                VerifyingPublicKey::EdDSACurve25519(_) => {
+                    return Err(Error::InvalidPublicKey.into())
+                }
+            },
+        };
+
+        self.storage
+            .update_value(move |mut keys: PlatformKeys| {
+                keys.keys.push(entry.clone());
+                Ok(keys)
+            })
+            .await?;
+
+        let key = handle(&label);
+        self.keys.write().unwrap().push(PlatformKeyPair {
+            key: key.clone(),
+            public_key,
+        });
+
+        Ok(key)
+    }
+
+    async fn get_verifying_public_key(
+        &self,
+        signing_secret_key_handle: &SigningSecretKeyHandle,
+    ) -> Result<VerifyingPublicKey> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|x| {
+                if &x.key == signing_secret_key_handle {
+                    Some(x.public_key.clone())
+                } else {
+                    None
+                }
+            })
+            .ok_or(Error::KeyNotFound.into())
+    }
+
+    async fn get_secret_key_handle(
+        &self,
+        verifying_public_key: &VerifyingPublicKey,
+    ) -> Result<SigningSecretKeyHandle> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|x| {
+                if &x.public_key == verifying_public_key {
+                    Some(x.key.clone())
+                } else {
+                    None
+                }
+            })
+            .ok_or(Error::KeyNotFound.into())
+    }
+
+    async fn delete_signing_secret_key(
+        &self,
+        signing_secret_key_handle: SigningSecretKeyHandle,
+    ) -> Result<bool> {
+        let label = match self.label(&signing_secret_key_handle) {
+            Ok(label) => label,
+            Err(_) => return Ok(false),
+        };
+
+        let key_store = self.key_store.clone();
+        let deleted = {
+            let label = label.clone();
+            blocking(move || key_store.delete_key(&label)).await?
+        };
+
+        self.storage
+            .update_value(move |mut keys: PlatformKeys| {
+                keys.keys.retain(|k| k.label != label);
+                Ok(keys)
+            })
+            .await?;
+        self.keys
+            .write()
+            .unwrap()
+            .retain(|x| x.key != signing_secret_key_handle);
+
+        Ok(deleted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::key_store::signature_from_der;
+    use ockam_core::compat::collections::BTreeMap;
+    use ockam_core::compat::sync::Mutex;
+    use ockam_vault::{SoftwareVaultForVerifyingSignatures, VaultForVerifyingSignatures};
+    use p256::ecdsa::signature::Signer;
+    use p256::ecdsa::SigningKey;
+
+    /// Key store keeping its keys in memory
+    #[derive(Default)]
+    struct InMemoryKeyStore {
+        keys: Mutex<BTreeMap<String, SigningKey>>,
+    }
+
+    impl PlatformKeyStore for InMemoryKeyStore {
+        fn create_key(&self, label: &str, _options: &KeyOptions) -> Result<VerifyingPublicKey> {
+            let key = SigningKey::random(&mut rand::thread_rng());
+            let public_key =
+                public_key_from_sec1(key.verifying_key().to_encoded_point(false).as_bytes())?;
+            self.keys.lock().unwrap().insert(label.to_string(), key);
+            Ok(public_key)
+        }
+
+        fn sign(&self, label: &str, data: &[u8]) -> Result<Signature> {
+            let keys = self.keys.lock().unwrap();
+            let key = keys.get(label).ok_or(Error::KeyNotFound)?;
+            let signature: p256::ecdsa::Signature = key.sign(data);
+            signature_from_der(signature.to_der().as_bytes())
+        }
+
+        fn delete_key(&self, label: &str) -> Result<bool> {
+            Ok(self.keys.lock().unwrap().remove(label).is_some())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_reloaded() -> Result<()> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys.json");
+        let key_store = Arc::new(InMemoryKeyStore::default());
+
+        let vault = PlatformSigningVault::create_with_key_store(
+            key_store.clone(),
+            KeyOptions::default(),
+            &path,
+        )
+        .await?;
+        let key = vault
+            .generate_signing_secret_key(SigningKeyType::ECDSASHA256CurveP256)
+            .await?;
+        let public_key = vault.get_verifying_public_key(&key).await?;
+        let signature = vault.sign(&key, b"data").await?;
+        let verifier = SoftwareVaultForVerifyingSignatures::new();
+        assert!(
+            verifier
+                .verify_signature(&public_key, b"data", &signature)
+                .await?
+        );
+
+        let vault =
+            PlatformSigningVault::create_with_key_store(key_store, KeyOptions::default(), &path)
+                .await?;
+        assert_eq!(vault.get_secret_key_handle(&public_key).await?, key);
+        assert!(vault.delete_signing_secret_key(key.clone()).await?);
+        assert_eq!(vault.number_of_keys().await?, 0);
+        assert!(vault.sign(&key, b"data").await.is_err());
+        Ok(())
+    }
+}
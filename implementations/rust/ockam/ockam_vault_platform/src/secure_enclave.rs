@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::key_store::{
+    public_key_from_sec1, signature_from_der, KeyOptions, PlatformKeyStore, UserPresence,
+};
+use ockam_core::Result;
+use ockam_vault::{Signature, VerifyingPublicKey};
+use security_framework::access_control::SecAccessControl;
+use security_framework::item::{ItemClass, ItemSearchOptions, Reference, SearchResult};
+use security_framework::key::{Algorithm, GenerateKeyOptions, KeyType, SecKey, Token};
+use security_framework_sys::access_control::{
+    kSecAccessControlBiometryCurrentSet, kSecAccessControlPrivateKeyUsage,
+    kSecAccessControlUserPresence,
+};
+use tracing::debug;
+
+/// errSecItemNotFound
+const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+/// errSecUserCanceled
+const ERR_SEC_USER_CANCELED: i32 = -128;
+/// errSecAuthFailed
+const ERR_SEC_AUTH_FAILED: i32 = -25293;
+
+/// Keys of the Apple Secure Enclave.
+///
+/// The keys are stored in the data protection keychain and can only be used on this device
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SecureEnclaveKeyStore;
+
+impl SecureEnclaveKeyStore {
+    fn find(label: &str) -> Result<Option<SecKey>> {
+        let mut search = ItemSearchOptions::new();
+        search
+            .class(ItemClass::key())
+            .label(label)
+            .load_refs(true)
+            .limit(1);
+        let results = match search.search() {
+            Ok(results) => results,
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => return Ok(None),
+            Err(e) => return Err(Error::KeyStore(e.to_string()).into()),
+        };
+        Ok(results.into_iter().find_map(|result| match result {
+            SearchResult::Ref(Reference::Key(key)) => Some(key),
+            _ => None,
+        }))
+    }
+
+    fn access_control(user_presence: UserPresence) -> Result<SecAccessControl> {
+        let flags = match user_presence {
+            UserPresence::None => kSecAccessControlPrivateKeyUsage,
+            UserPresence::Biometry => {
+                kSecAccessControlPrivateKeyUsage | kSecAccessControlBiometryCurrentSet
+            }
+            UserPresence::BiometryOrPasscode => {
+                kSecAccessControlPrivateKeyUsage | kSecAccessControlUserPresence
+            }
+        };
+        SecAccessControl::create_with_flags(flags)
+            .map_err(|e| Error::KeyStore(e.to_string()).into())
+    }
+}
+
+impl PlatformKeyStore for SecureEnclaveKeyStore {
+    fn create_key(&self, label: &str, options: &KeyOptions) -> Result<VerifyingPublicKey> {
+        let mut key_options = GenerateKeyOptions::default();
+        key_options
+            .set_key_type(KeyType::ec())
+            .set_size_in_bits(256)
+            .set_label(label)
+            .set_token(Token::SecureEnclave)
+            .set_access_control(Self::access_control(options.user_presence())?);
+        #[cfg(target_os = "macos")]
+        key_options.set_location(security_framework::item::Location::DataProtectionKeychain);
+
+        let key = SecKey::generate(key_options.to_dictionary())
+            .map_err(|e| Error::KeyStore(e.to_string()))?;
+        debug!(%label, "created a Secure Enclave key");
+        let public_key = key
+            .public_key()
+            .and_then(|k| k.external_representation())
+            .ok_or(Error::InvalidPublicKey)?;
+        public_key_from_sec1(&public_key.to_vec())
+    }
+
+    fn sign(&self, label: &str, data: &[u8]) -> Result<Signature> {
+        let key = Self::find(label)?.ok_or(Error::KeyNotFound)?;
+        // the user is asked to authenticate here if the key requires it
+        let signature = key
+            .create_signature(Algorithm::ECDSASignatureMessageX962SHA256, data)
+            .map_err(|e| match e.code() as i32 {
+                ERR_SEC_USER_CANCELED | ERR_SEC_AUTH_FAILED => {
+                    Error::UserNotAuthenticated(e.to_string())
+                }
+                _ => Error::KeyStore(e.to_string()),
+            })?;
+        signature_from_der(&signature)
+    }
+
+    fn delete_key(&self, label: &str) -> Result<bool> {
+        match Self::find(label)? {
+            Some(key) => {
+                key.delete().map_err(|e| Error::KeyStore(e.to_string()))?;
+                debug!(%label, "deleted a Secure Enclave key");
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use ockam_core::errcode::{Kind, Origin};
+use thiserror::Error;
+
+#[allow(missing_docs)]
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("the TPM can't be opened: {0}")]
+    Connect(String),
+    #[error("the TPM is not available anymore")]
+    Disconnected,
+    #[error("tpm error: {0}")]
+    Tss(String),
+    #[error("the TPM did not return an ECC public key")]
+    InvalidPublicKey,
+    #[error("the TPM did not return an ECDSA signature")]
+    InvalidSignature,
+    #[error("the data to seal is too large")]
+    SealedDataTooLarge,
+    #[error("key was not found")]
+    KeyNotFound,
+    #[error("invalid handle")]
+    InvalidHandle,
+}
+
+impl From<tss_esapi::Error> for Error {
+    fn from(e: tss_esapi::Error) -> Self {
+        Error::Tss(e.to_string())
+    }
+}
+
+impl From<Error> for ockam_core::Error {
+    fn from(e: Error) -> Self {
+        ockam_core::Error::new(Origin::Vault, Kind::Io, e)
+    }
+}
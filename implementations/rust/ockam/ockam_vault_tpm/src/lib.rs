@@ -0,0 +1,18 @@
+//! TPM 2.0 implementation of the ockam_vault::VaultForSigning trait
+//!
+#![deny(unsafe_code)]
+#![warn(
+    missing_docs,
+    trivial_casts,
+    trivial_numeric_casts,
+    unused_import_braces,
+    unused_qualifications
+)]
+
+mod error;
+mod tpm_client;
+mod tpm_signing_vault;
+
+pub use error::*;
+pub use tpm_client::*;
+pub use tpm_signing_vault::*;
@@ -0,0 +1,325 @@
+use crate::error::Error;
+use ockam_core::compat::sync::Mutex;
+use ockam_core::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+use tss_esapi::attributes::ObjectAttributesBuilder;
+use tss_esapi::constants::tss::{TPM2_RH_NULL, TPM2_ST_HASHCHECK};
+use tss_esapi::handles::KeyHandle;
+use tss_esapi::interface_types::algorithm::{HashingAlgorithm, PublicAlgorithm};
+use tss_esapi::interface_types::ecc::EccCurve;
+use tss_esapi::interface_types::key_bits::RsaKeyBits;
+use tss_esapi::interface_types::resource_handles::Hierarchy;
+use tss_esapi::structures::{
+    Digest, EccScheme, HashScheme, HashcheckTicket, KeyedHashScheme, Private, Public,
+    PublicBuilder, PublicKeyedHashParameters, RsaExponent, SensitiveData,
+    Signature as TpmSignature, SignatureScheme, SymmetricDefinitionObject,
+};
+use tss_esapi::traits::{Marshall, UnMarshall};
+use tss_esapi::tss2_esys::TPMT_TK_HASHCHECK;
+use tss_esapi::utils::{
+    create_restricted_decryption_rsa_public, create_unrestricted_signing_ecc_public,
+};
+use tss_esapi::{Context, TctiNameConf};
+
+/// Configuration of the access to a TPM
+#[derive(Debug, Clone, Default)]
+pub struct TpmConfig {
+    tcti: Option<String>,
+}
+
+impl TpmConfig {
+    /// Use a specific TCTI, for example `device:/dev/tpmrm0` or `mssim:host=localhost,port=2321`.
+    /// By default the TCTI is taken from the `TPM2TOOLS_TCTI`, `TCTI` or `TEST_TCTI`
+    /// environment variables
+    pub fn with_tcti(mut self, tcti: impl Into<String>) -> Self {
+        self.tcti = Some(tcti.into());
+        self
+    }
+
+    fn tcti(&self) -> Result<TctiNameConf> {
+        match &self.tcti {
+            Some(tcti) => TctiNameConf::from_str(tcti).map_err(|e| Error::Connect(e.to_string())),
+            None => {
+                TctiNameConf::from_environment_variable().map_err(|e| Error::Connect(e.to_string()))
+            }
+        }
+        .map_err(|e| e.into())
+    }
+}
+
+/// Key created by the TPM, wrapped by its storage primary key.
+/// It can only be loaded by the TPM which created it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TpmKeyBlob {
+    /// Marshalled TPM2B_PRIVATE structure
+    #[serde(with = "hex")]
+    pub private: Vec<u8>,
+    /// Marshalled TPMT_PUBLIC structure
+    #[serde(with = "hex")]
+    pub public: Vec<u8>,
+}
+
+type Job = Box<dyn FnOnce(&mut TpmState) + Send>;
+
+/// The ESAPI context is not thread-safe, so it is owned by a dedicated thread
+/// which executes the operations sent to the client in sequence
+struct TpmState {
+    context: Context,
+    storage_key: KeyHandle,
+}
+
+/// Client executing operations on a TPM 2.0
+pub struct TpmClient {
+    jobs: Mutex<mpsc::Sender<Job>>,
+}
+
+impl TpmClient {
+    /// Open the TPM and create its storage primary key
+    pub async fn new(config: TpmConfig) -> Result<Self> {
+        let tcti = config.tcti()?;
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let (ready, started) = oneshot::channel::<Result<()>>();
+
+        thread::Builder::new()
+            .name("ockam_vault_tpm".to_string())
+            .spawn(move || {
+                let mut state = match TpmState::open(tcti) {
+                    Ok(state) => {
+                        let _ = ready.send(Ok(()));
+                        state
+                    }
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                while let Ok(job) = receiver.recv() {
+                    job(&mut state);
+                }
+                if let Err(e) = state.context.flush_context(state.storage_key.into()) {
+                    error!("the TPM storage key could not be flushed: {e}");
+                }
+            })
+            .map_err(|e| Error::Connect(e.to_string()))?;
+
+        started.await.map_err(|_| Error::Disconnected)??;
+        Ok(Self {
+            jobs: Mutex::new(jobs),
+        })
+    }
+
+    /// Execute an operation on the TPM thread
+    async fn execute<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut TpmState) -> Result<R> + Send + 'static,
+    ) -> Result<R> {
+        let (sender, receiver) = oneshot::channel();
+        let job: Job = Box::new(move |state| {
+            let _ = sender.send(f(state));
+        });
+        self.jobs
+            .lock()
+            .unwrap()
+            .send(job)
+            .map_err(|_| Error::Disconnected)?;
+        receiver.await.map_err(|_| Error::Disconnected)?
+    }
+
+    /// Create an ECDSA P-256 signing key
+    pub async fn create_signing_key(&self) -> Result<TpmKeyBlob> {
+        self.execute(|state| {
+            let public = create_unrestricted_signing_ecc_public(
+                EccScheme::EcDsa(HashScheme::new(HashingAlgorithm::Sha256)),
+                EccCurve::NistP256,
+            )
+            .map_err(Error::from)?;
+            state.create(public, None)
+        })
+        .await
+    }
+
+    /// Sign the SHA-256 digest of some data and return the r || s signature
+    pub async fn sign(&self, key: &TpmKeyBlob, data: &[u8]) -> Result<[u8; 64]> {
+        let key = key.clone();
+        let digest = Sha256::digest(data).to_vec();
+        self.execute(move |state| {
+            let handle = state.load(&key)?;
+            let signature = state.sign(handle, digest);
+            state.flush(handle);
+            signature_to_bytes(signature?)
+        })
+        .await
+    }
+
+    /// Seal some data (at most 128 bytes) so that it can only be unsealed by this TPM
+    pub async fn seal(&self, data: &[u8]) -> Result<TpmKeyBlob> {
+        let data = SensitiveData::try_from(data.to_vec()).map_err(|_| Error::SealedDataTooLarge)?;
+        self.execute(move |state| state.create(sealed_object_public()?, Some(data)))
+            .await
+    }
+
+    /// Unseal some data sealed with [`TpmClient::seal`]
+    pub async fn unseal(&self, sealed: &TpmKeyBlob) -> Result<Vec<u8>> {
+        let sealed = sealed.clone();
+        self.execute(move |state| {
+            let handle = state.load(&sealed)?;
+            let data = state
+                .context
+                .execute_with_nullauth_session(|context| context.unseal(handle.into()));
+            state.flush(handle);
+            Ok(data.map_err(Error::from)?.value().to_vec())
+        })
+        .await
+    }
+}
+
+impl TpmState {
+    fn open(tcti: TctiNameConf) -> Result<Self> {
+        let mut context = Context::new(tcti).map_err(|e| Error::Connect(e.to_string()))?;
+        let public = create_restricted_decryption_rsa_public(
+            SymmetricDefinitionObject::AES_128_CFB,
+            RsaKeyBits::Rsa2048,
+            RsaExponent::default(),
+        )
+        .map_err(Error::from)?;
+        let storage_key = context
+            .execute_with_nullauth_session(|context| {
+                context.create_primary(Hierarchy::Owner, public, None, None, None, None)
+            })
+            .map_err(Error::from)?
+            .key_handle;
+        debug!("created the TPM storage primary key");
+        Ok(Self {
+            context,
+            storage_key,
+        })
+    }
+
+    fn create(&mut self, public: Public, data: Option<SensitiveData>) -> Result<TpmKeyBlob> {
+        let storage_key = self.storage_key;
+        let created = self
+            .context
+            .execute_with_nullauth_session(|context| {
+                context.create(storage_key, public, None, data, None, None)
+            })
+            .map_err(Error::from)?;
+        Ok(TpmKeyBlob {
+            private: created.out_private.value().to_vec(),
+            public: created.out_public.marshall().map_err(Error::from)?,
+        })
+    }
+
+    fn load(&mut self, key: &TpmKeyBlob) -> Result<KeyHandle> {
+        let storage_key = self.storage_key;
+        let private = Private::try_from(key.private.clone()).map_err(|_| Error::InvalidHandle)?;
+        let public = Public::unmarshall(&key.public).map_err(|_| Error::InvalidHandle)?;
+        Ok(self
+            .context
+            .execute_with_nullauth_session(|context| context.load(storage_key, private, public))
+            .map_err(Error::from)?)
+    }
+
+    fn sign(&mut self, handle: KeyHandle, digest: Vec<u8>) -> Result<TpmSignature> {
+        let digest = Digest::try_from(digest).map_err(Error::from)?;
+        // the digest was not computed by the TPM, so there is no hashcheck ticket
+        let validation = HashcheckTicket::try_from(TPMT_TK_HASHCHECK {
+            tag: TPM2_ST_HASHCHECK,
+            hierarchy: TPM2_RH_NULL,
+            digest: Default::default(),
+        })
+        .map_err(Error::from)?;
+        Ok(self
+            .context
+            .execute_with_nullauth_session(|context| {
+                context.sign(handle, digest, SignatureScheme::Null, validation)
+            })
+            .map_err(Error::from)?)
+    }
+
+    fn flush(&mut self, handle: KeyHandle) {
+        if let Err(e) = self.context.flush_context(handle.into()) {
+            error!("a TPM key could not be flushed: {e}");
+        }
+    }
+}
+
+fn sealed_object_public() -> Result<Public> {
+    let attributes = ObjectAttributesBuilder::new()
+        .with_fixed_tpm(true)
+        .with_fixed_parent(true)
+        .with_user_with_auth(true)
+        .with_no_da(true)
+        .build()
+        .map_err(Error::from)?;
+    Ok(PublicBuilder::new()
+        .with_public_algorithm(PublicAlgorithm::KeyedHash)
+        .with_name_hashing_algorithm(HashingAlgorithm::Sha256)
+        .with_object_attributes(attributes)
+        .with_keyed_hash_parameters(PublicKeyedHashParameters::new(KeyedHashScheme::Null))
+        .with_keyed_hash_unique_identifier(Digest::default())
+        .build()
+        .map_err(Error::from)?)
+}
+
+/// Return the uncompressed SEC1 encoding of the public key of a TPM key
+pub(crate) fn public_key_bytes(key: &TpmKeyBlob) -> Result<[u8; 65]> {
+    match Public::unmarshall(&key.public).map_err(|_| Error::InvalidPublicKey)? {
+        Public::Ecc { unique, .. } => {
+            uncompressed_point(unique.x().value(), unique.y().value()).map_err(|e| e.into())
+        }
+        _ => Err(Error::InvalidPublicKey.into()),
+    }
+}
+
+fn signature_to_bytes(signature: TpmSignature) -> Result<[u8; 64]> {
+    match signature {
+        TpmSignature::EcDsa(signature) => concat_scalars(
+            signature.signature_r().value(),
+            signature.signature_s().value(),
+        )
+        .map_err(|_| Error::InvalidSignature.into()),
+        _ => Err(Error::InvalidSignature.into()),
+    }
+}
+
+/// The TPM strips the leading zeros of the coordinates
+fn uncompressed_point(x: &[u8], y: &[u8]) -> core::result::Result<[u8; 65], Error> {
+    let coordinates = concat_scalars(x, y).map_err(|_| Error::InvalidPublicKey)?;
+    let mut point = [0u8; 65];
+    point[0] = 0x04;
+    point[1..].copy_from_slice(&coordinates);
+    Ok(point)
+}
+
+fn concat_scalars(a: &[u8], b: &[u8]) -> core::result::Result<[u8; 64], ()> {
+    if a.len() > 32 || b.len() > 32 {
+        return Err(());
+    }
+    let mut bytes = [0u8; 64];
+    bytes[32 - a.len()..32].copy_from_slice(a);
+    bytes[64 - b.len()..].copy_from_slice(b);
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scalars_are_padded() {
+        let point = uncompressed_point(&[1; 31], &[2; 32]).unwrap();
+        assert_eq!(point[0], 0x04);
+        assert_eq!(point[1], 0);
+        assert_eq!(&point[2..33], &[1; 31]);
+        assert_eq!(&point[33..], &[2; 32]);
+
+        assert!(uncompressed_point(&[1; 33], &[2; 32]).is_err());
+        assert_eq!(concat_scalars(&[], &[3]).unwrap()[63], 3);
+    }
+}
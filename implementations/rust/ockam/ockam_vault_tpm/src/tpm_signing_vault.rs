@@ -0,0 +1,214 @@
+use crate::error::Error;
+use crate::tpm_client::{public_key_bytes, TpmClient, TpmConfig, TpmKeyBlob};
+use ockam_core::compat::sync::{Arc, RwLock};
+use ockam_core::{async_trait, Result};
+use ockam_node::storage::{FileValueStorage, ValueStorage};
+use ockam_vault::{
+    ECDSASHA256CurveP256PublicKey, ECDSASHA256CurveP256Signature, HandleToSecret, Signature,
+    SigningKeyType, SigningSecretKeyHandle, VaultError, VaultForSigning, VerifyingPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use tracing::error;
+
+struct TpmKeyPair {
+    key: SigningSecretKeyHandle,
+    public_key: VerifyingPublicKey,
+    blob: TpmKeyBlob,
+}
+
+impl TpmKeyPair {
+    fn new(blob: TpmKeyBlob) -> Result<Self> {
+        let public_key = public_key_bytes(&blob)?;
+        // the handle doesn't need to be secret, the key can only be used by the TPM
+        let key = SigningSecretKeyHandle::ECDSASHA256CurveP256(HandleToSecret::new(
+            Sha256::digest(public_key).to_vec(),
+        ));
+        Ok(Self {
+            key,
+            public_key: VerifyingPublicKey::ECDSASHA256CurveP256(ECDSASHA256CurveP256PublicKey(
+                public_key,
+            )),
+            blob,
+        })
+    }
+}
+
+/// Keys wrapped by the TPM, as stored in the keys file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TpmKeys {
+    keys: Vec<TpmKeyBlob>,
+}
+
+/// Security module implementation using a TPM 2.0
+///
+/// The signing keys never leave the TPM unencrypted: they are wrapped by the storage primary key
+/// of the TPM and the wrapped keys are stored in a file
+pub struct TpmSigningVault {
+    client: Arc<TpmClient>,
+    storage: FileValueStorage<TpmKeys>,
+    // Keys loaded from the keys file at the Vault initialization,
+    // and updated locally during add/delete operations
+    keys: Arc<RwLock<Vec<TpmKeyPair>>>,
+}
+
+impl TpmSigningVault {
+    /// Create a TPM security module using the TPM configured with environment variables
+    pub async fn create(keys_path: &Path) -> Result<Self> {
+        Self::create_with_config(TpmConfig::default(), keys_path).await
+    }
+
+    /// Create a TPM security module storing its wrapped keys in a file
+    pub async fn create_with_config(config: TpmConfig, keys_path: &Path) -> Result<Self> {
+        let client = TpmClient::new(config).await?;
+        let storage = FileValueStorage::<TpmKeys>::create(keys_path).await?;
+
+        let mut key_pairs = vec![];
+        for blob in storage.read_value(|keys| Ok(keys.keys)).await? {
+            match TpmKeyPair::new(blob) {
+                Ok(key_pair) => key_pairs.push(key_pair),
+                Err(err) => error!("Error reading a TPM key: {err}"),
+            }
+        }
+
+        Ok(Self {
+            client: Arc::new(client),
+            storage,
+            keys: Arc::new(RwLock::new(key_pairs)),
+        })
+    }
+
+    /// Return list of all keys
+    pub fn keys(&self) -> Vec<SigningSecretKeyHandle> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .map(|x| x.key.clone())
+            .collect()
+    }
+
+    /// Return number of keys
+    pub async fn number_of_keys(&self) -> Result<usize> {
+        Ok(self.keys.read().unwrap().len())
+    }
+
+    /// Seal a secret, at most 128 bytes, so that it can only be unsealed by this TPM
+    pub async fn seal(&self, secret: &[u8]) -> Result<TpmKeyBlob> {
+        self.client.seal(secret).await
+    }
+
+    /// Unseal a secret sealed with [`TpmSigningVault::seal`]
+    pub async fn unseal(&self, sealed: &TpmKeyBlob) -> Result<Vec<u8>> {
+        self.client.unseal(sealed).await
+    }
+
+    fn blob(&self, key: &SigningSecretKeyHandle) -> Result<TpmKeyBlob> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find(|x| &x.key == key)
+            .map(|x| x.blob.clone())
+            .ok_or(Error::KeyNotFound.into())
+    }
+}
+
+#[async_trait]
+impl VaultForSigning for TpmSigningVault {
+    async fn sign(
+        &self,
+        signing_secret_key_handle: &SigningSecretKeyHandle,
+        data: &[u8],
+    ) -> Result<Signature> {
+        let blob = self.blob(signing_secret_key_handle)?;
+        let signature = self.client.sign(&blob, data).await?;
+        Ok(Signature::ECDSASHA256CurveP256(
+            ECDSASHA256CurveP256Signature(signature),
+        ))
+    }
+
+    async fn generate_signing_secret_key(
+        &self,
+        signing_key_type: SigningKeyType,
+    ) -> Result<SigningSecretKeyHandle> {
+        if signing_key_type != SigningKeyType::ECDSASHA256CurveP256 {
+            return Err(VaultError::InvalidKeyType.into());
+        }
+
+        let blob = self.client.create_signing_key().await?;
+        let key_pair = TpmKeyPair::new(blob.clone())?;
+        let key = key_pair.key.clone();
+
+        self.storage
+            .update_value(move |mut keys: TpmKeys| {
+                keys.keys.push(blob.clone());
+                Ok(keys)
+            })
+            .await?;
+        self.keys.write().unwrap().push(key_pair);
+
+        Ok(key)
+    }
+
+    async fn get_verifying_public_key(
+        &self,
+        signing_secret_key_handle: &SigningSecretKeyHandle,
+    ) -> Result<VerifyingPublicKey> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|x| {
+                if &x.key == signing_secret_key_handle {
+                    Some(x.public_key.clone())
+                } else {
+                    None
+                }
+            })
+            .ok_or(Error::KeyNotFound.into())
+    }
+
+    async fn get_secret_key_handle(
+        &self,
+        verifying_public_key: &VerifyingPublicKey,
+    ) -> Result<SigningSecretKeyHandle> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .find_map(|x| {
+                if &x.public_key == verifying_public_key {
+                    Some(x.key.clone())
+                } else {
+                    None
+                }
+            })
+            .ok_or(Error::KeyNotFound.into())
+    }
+
+    async fn delete_signing_secret_key(
+        &self,
+        signing_secret_key_handle: SigningSecretKeyHandle,
+    ) -> Result<bool> {
+        let blob = match self.blob(&signing_secret_key_handle) {
+            Ok(blob) => blob,
+            Err(_) => return Ok(false),
+        };
+
+        // the wrapped key is useless once it is removed from the keys file
+        self.storage
+            .update_value(move |mut keys: TpmKeys| {
+                keys.keys.retain(|k| k != &blob);
+                Ok(keys)
+            })
+            .await?;
+        self.keys
+            .write()
+            .unwrap()
+            .retain(|x| x.key != signing_secret_key_handle);
+
+        Ok(true)
+    }
+}